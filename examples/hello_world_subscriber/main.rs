@@ -2,10 +2,10 @@
 
 use rustdds::{
   policy::Reliability, with_key::Sample, DataReaderStatus, DomainParticipantBuilder, Keyed,
-  QosPolicyBuilder, TopicKind,
+  QosPolicyBuilder, ShutdownToken, TopicKind,
 };
 use serde::{Deserialize, Serialize};
-use futures::{FutureExt, StreamExt};
+use futures::StreamExt;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct HelloWorldData {
@@ -25,12 +25,9 @@ impl Keyed for HelloWorldData {
 
 fn main() {
   // Set Ctrl-C handler
-  let (stop_sender, stop_receiver) = smol::channel::bounded(1);
-  ctrlc::set_handler(move || {
-    stop_sender.send_blocking(()).unwrap_or(());
-    // ignore errors, as we are quitting anyway
-  })
-  .expect("Error setting Ctrl-C handler");
+  let shutdown = ShutdownToken::new();
+  let ctrlc_shutdown = shutdown.clone();
+  ctrlc::set_handler(move || ctrlc_shutdown.trigger()).expect("Error setting Ctrl-C handler");
   println!("Press Ctrl-C to quit.");
 
   let domain_participant = DomainParticipantBuilder::new(0)
@@ -60,15 +57,13 @@ fn main() {
 
   // set up async executor to run concurrent tasks
   smol::block_on(async {
-    let mut sample_stream = data_reader.async_sample_stream();
+    // Both streams end on Ctrl-C
+    let mut sample_stream = data_reader.async_sample_stream().until_shutdown(&shutdown);
     let mut event_stream = sample_stream.async_event_stream();
 
     println!("Waiting for hello messages.");
     loop {
       futures::select! {
-        _ = stop_receiver.recv().fuse() =>
-          break,
-
         result = sample_stream.select_next_some() => {
           match result {
             Ok(s) => match s.into_value() {
@@ -95,6 +90,8 @@ fn main() {
               println!("DataReader event: {e:?}"),
           }
         }
+
+        complete => break,
       } // select!
     } // loop
 
@@ -0,0 +1,30 @@
+//! Prints Rust types for the types declared in an IDL file.
+//!
+//! Usage: `cargo run --example rustdds_gen -- shapes.idl > shapes.rs`
+use std::{env, fs, process::ExitCode};
+
+use rustdds::serialization::idl::generate_rust;
+
+fn main() -> ExitCode {
+  let Some(path) = env::args().nth(1) else {
+    eprintln!("Usage: rustdds_gen <file.idl>");
+    return ExitCode::FAILURE;
+  };
+  let idl = match fs::read_to_string(&path) {
+    Ok(idl) => idl,
+    Err(e) => {
+      eprintln!("Cannot read {path}: {e}");
+      return ExitCode::FAILURE;
+    }
+  };
+  match generate_rust(&idl) {
+    Ok(rust) => {
+      print!("{rust}");
+      ExitCode::SUCCESS
+    }
+    Err(e) => {
+      eprintln!("{path}: {e}");
+      ExitCode::FAILURE
+    }
+  }
+}
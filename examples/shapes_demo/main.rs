@@ -20,8 +20,9 @@ use rustdds::{
   dds::statusevents, with_key::Sample, DomainParticipantBuilder, Keyed, QosPolicyBuilder,
   StatusEvented, TopicDescription, TopicKind,
 };
-use rustdds::policy::{Deadline, Durability, History, Reliability}; /* import all QoS
-                                                                     * policies directly */
+use rustdds::policy::{
+  Deadline, Durability, History, Liveliness, Ownership, Partition, Reliability, TimeBasedFilter,
+}; // import all QoS policies directly
 use serde::{Deserialize, Serialize};
 use clap::{Arg, ArgMatches, Command}; // command line argument processing
 use mio_06::{Events, Poll, PollOpt, Ready, Token}; // polling
@@ -68,6 +69,8 @@ fn main() {
     .get_one::<String>("color")
     .cloned()
     .unwrap_or("BLUE".to_owned());
+  // Subscriber filters by color only if color was explicitly given.
+  let color_filter = matches.get_one::<String>("color").cloned();
   // Change some logging to make automatic tests work
   let is_auto_test = std::env::var("auto_test").is_ok();
 
@@ -140,24 +143,62 @@ fn main() {
     qos_b = qos_b.deadline(dl);
   }
 
-  assert!(
-    !matches.contains_id("partition"),
-    "QoS policy Partition is not yet implemented."
-  );
+  if let Some(partitions) = matches.get_many::<String>("partition") {
+    qos_b = qos_b.partition(Partition {
+      name: partitions.cloned().collect(),
+    });
+  }
 
-  assert!(
-    !matches.contains_id("interval"),
-    "QoS policy Time Based Filter is not yet implemented."
-  );
+  if let Some(interval) = matches.get_one::<f64>("interval") {
+    qos_b = qos_b.time_based_filter(TimeBasedFilter {
+      minimum_separation: rustdds::Duration::from_frac_seconds(*interval),
+    });
+  }
 
-  assert!(
-    !matches.contains_id("ownership_strength"),
-    "QoS policy Ownership Strength is not yet implemented."
-  );
+  if let Some(strength) = matches.get_one::<i32>("ownership_strength") {
+    qos_b = qos_b.ownership(if *strength < 0 {
+      Ownership::Shared
+    } else {
+      Ownership::Exclusive {
+        strength: *strength,
+      }
+    });
+  }
+
+  let liveliness_lease = matches
+    .get_one::<f64>("lease_duration")
+    .map_or(rustdds::Duration::INFINITE, |l| {
+      rustdds::Duration::from_frac_seconds(*l)
+    });
+  let liveliness_policy = matches
+    .get_one::<String>("liveliness")
+    .map(|kind| match kind.as_str() {
+      "p" => Liveliness::ManualByParticipant {
+        lease_duration: liveliness_lease,
+      },
+      "t" => Liveliness::ManualByTopic {
+        lease_duration: liveliness_lease,
+      },
+      _ => Liveliness::Automatic {
+        lease_duration: liveliness_lease,
+      },
+    });
+  if let Some(lv) = liveliness_policy {
+    qos_b = qos_b.liveliness(lv);
+  }
+  // How often manual liveliness is asserted, if at all.
+  let assert_liveliness_period: Option<Duration> = matches
+    .get_one::<f64>("assert_liveliness")
+    .map(|p| Duration::from_secs_f64(*p));
 
   assert!(
-    !matches.contains_id("representation"),
-    "QoS policy Representation is not yet implemented."
+    !matches!(
+      matches
+        .get_one::<String>("representation")
+        .map(String::as_str),
+      Some("2")
+    ),
+    "Data representation XCDR2 is not yet implemented."
   );
 
   let qos = qos_b.build();
@@ -274,6 +315,7 @@ fn main() {
   };
 
   let mut last_write = Instant::now();
+  let mut last_liveliness_assert = Instant::now();
 
   loop {
     poll.poll(&mut events, Some(loop_delay)).unwrap();
@@ -292,6 +334,11 @@ fn main() {
                 trace!("DataReader triggered");
                 match reader.take_next_sample() {
                   Ok(Some(sample)) => match sample.into_value() {
+                    Sample::Value(sample)
+                      if color_filter.as_ref().is_some_and(|c| *c != sample.color) =>
+                    {
+                      trace!("Filtered out color {}", sample.color);
+                    }
                     Sample::Value(sample) => println!(
                       "{:10.10} {:10.10} {:3.3} {:3.3} [{}]",
                       topic.name(),
@@ -360,6 +407,8 @@ fn main() {
                   statusevents::DataWriterStatus::PublicationMatched { .. } => {
                     println!("on_publication_matched()");
                   }
+                  statusevents::DataWriterStatus::UnacknowledgedBelowThreshold { .. }
+                  | statusevents::DataWriterStatus::ReaderPresenceChanged { .. } => {}
                 }
               } else {
                 println!("DataWriter status: {status:?}");
@@ -392,6 +441,20 @@ fn main() {
             .unwrap_or_else(|e| error!("DataWriter write failed: {:?}", e));
           last_write = now;
         }
+        if let Some(period) = assert_liveliness_period {
+          if last_liveliness_assert + period < now {
+            match liveliness_policy {
+              Some(Liveliness::ManualByParticipant { .. }) => domain_participant
+                .assert_liveliness()
+                .unwrap_or_else(|e| error!("Participant assert_liveliness failed: {e:?}")),
+              Some(Liveliness::ManualByTopic { .. }) => writer
+                .assert_liveliness()
+                .unwrap_or_else(|e| error!("DataWriter assert_liveliness failed: {e:?}")),
+              _ => (), // Automatic liveliness needs no assertion
+            }
+            last_liveliness_assert = now;
+          }
+        }
       }
       None => {
         if is_publisher {
@@ -453,8 +516,7 @@ fn get_matches() -> ArgMatches {
       Arg::new("color")
         .short('c')
         .value_name("color")
-        .default_value("BLUE")
-        .help("Color to publish (or filter)"),
+        .help("Color to publish (default BLUE) or to filter (default none)"),
     )
     .arg(
       Arg::new("durability")
@@ -503,26 +565,54 @@ fn get_matches() -> ArgMatches {
       Arg::new("deadline")
         .help("Set a 'deadline' with interval (seconds)")
         .short('f')
+        .value_parser(clap::value_parser!(f64))
         .value_name("interval"),
     )
     .arg(
       Arg::new("partition")
-        .help("Set a 'partition' string")
+        .help("Set a 'partition' string. May be given several times.")
         .short('p')
+        .action(clap::ArgAction::Append)
         .value_name("partition"),
     )
     .arg(
       Arg::new("interval")
         .help("Apply 'time based filter' with interval (seconds)")
         .short('i')
+        .value_parser(clap::value_parser!(f64))
         .value_name("interval"),
     )
     .arg(
       Arg::new("ownership_strength")
         .help("Set ownership strength [-1: SHARED]")
         .short('s')
+        .value_parser(clap::value_parser!(i32))
+        .allow_negative_numbers(true)
         .value_name("strength"),
     )
+    .arg(
+      Arg::new("liveliness")
+        .help("Set liveliness kind [a: AUTOMATIC, p: MANUAL_BY_PARTICIPANT, t: MANUAL_BY_TOPIC]")
+        .short('l')
+        .value_parser(["a", "p", "t"])
+        .value_name("kind"),
+    )
+    .arg(
+      Arg::new("lease_duration")
+        .help("Set liveliness lease duration (seconds)")
+        .long("lease")
+        .value_parser(clap::value_parser!(f64))
+        .requires("liveliness")
+        .value_name("seconds"),
+    )
+    .arg(
+      Arg::new("assert_liveliness")
+        .help("Assert manual liveliness with this period (seconds)")
+        .long("assert-period")
+        .value_parser(clap::value_parser!(f64))
+        .requires("liveliness")
+        .value_name("seconds"),
+    )
     .arg(
       Arg::new("security")
         .help(
@@ -10,6 +10,7 @@ pub use participant::DomainParticipant;
 pub(crate) mod ddsdata;
 pub(crate) mod pubsub;
 pub(crate) mod readcondition;
+pub(crate) mod shutdown;
 pub(crate) mod topic;
 pub(crate) mod typedesc;
 
@@ -40,3 +41,68 @@ pub mod with_key;
 
 /// Serializer/deserializer adapters to connect serialization to RTPS.
 pub mod adapters;
+
+/// Application hooks on the write and read paths of DataWriters and
+/// DataReaders.
+pub mod interceptor;
+
+/// Strict or permissive handling of out-of-spec messages.
+pub mod compliance;
+
+/// Hierarchical topic names and wildcard subscriptions.
+pub mod topic_namespace;
+
+/// Resource usage presets for constrained and large systems.
+pub mod resource_profile;
+
+/// Multicast and initial peer settings of discovery.
+pub mod discovery_network;
+
+/// Type descriptions and XTypes assignability checks.
+pub mod xtypes;
+
+/// Reading data of types known only at run time.
+pub mod dynamic;
+
+/// Process-wide creation and lookup of DomainParticipants.
+pub mod participant_factory;
+
+/// Writing and reading very large samples in chunks.
+pub mod sliced;
+
+/// Reading samples without copying their data.
+pub mod loaned;
+
+/// Content filters implemented as Rust closures.
+pub mod content_filter;
+
+/// Discovery peers looked up from DNS, DNS-SD or other name services.
+pub mod peer_resolution;
+
+/// RTPS protocol version and vendor id advertised by a DomainParticipant.
+pub mod protocol_identity;
+
+/// RTPS over TCP, for networks that block UDP.
+pub mod tcp_transport;
+
+/// Separate sockets for discovery and user traffic.
+pub mod traffic_isolation;
+
+/// Delivering samples within a DomainParticipant without serializing them.
+pub mod intra_process;
+
+/// Bandwidth limits and priorities for the DataWriters of a participant.
+pub mod flow_control;
+
+/// Damping of remote endpoint changes, and protection against discovery
+/// storms.
+pub mod discovery_damping;
+
+/// Polling of communication statuses, as in the DDS specification.
+pub mod communication_status;
+
+/// WaitSets that block until Conditions are triggered
+pub mod waitset;
+
+/// Callbacks for communication status changes
+pub mod listener;
@@ -81,7 +81,7 @@
 pub mod no_key {
   use bytes::Bytes;
 
-  use crate::RepresentationIdentifier;
+  use crate::{content_filter::DdsSqlFilter, RepresentationIdentifier};
 
   /// trait for connecting a Deserializer implementation and DataReader
   /// together - no_key version.
@@ -200,6 +200,16 @@ pub mod no_key {
     fn output_encoding() -> RepresentationIdentifier;
 
     fn to_bytes(value: &D) -> Result<Bytes, Self::Error>;
+
+    /// Whether the value passes the DDS SQL content filter, or `None`, if the
+    /// adapter cannot tell. DataWriters apply the "DDSSQL" filters of matched
+    /// DataReaders with this.
+    ///
+    /// The default cannot tell. Adapters of types that implement
+    /// `serde::Serialize` should return `Some(filter.matches(value))`.
+    fn matches_sql_filter(_value: &D, _filter: &DdsSqlFilter) -> Option<bool> {
+      None
+    }
   }
 }
 
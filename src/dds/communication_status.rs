@@ -0,0 +1,646 @@
+//! Polling of communication statuses.
+//!
+//! RustDDS delivers changes of the communication statuses of DataWriters and
+//! DataReaders as events, [`DataWriterStatus`] and [`DataReaderStatus`],
+//! through [`StatusEvented`](crate::StatusEvented). Portable DDS applications
+//! may rather poll them, as specified in Section 2.2.4.1 "Communication
+//! Status" of the DDS specification v1.4:
+//! [`Entity::get_status_changes`] tells which statuses have changed, and the
+//! status getters of the entity, e.g.
+//! [`get_liveliness_changed_status`](crate::with_key::DataReader::get_liveliness_changed_status),
+//! return the current status. Getting a status resets its `*_change` counts
+//! and removes it from the status changes.
+//!
+//! Polling does not consume the status events, so both can be used together.
+//!
+//! ```
+//! use rustdds::{communication_status::*, *};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug)]
+//! struct SomeType {
+//!   a: i32,
+//! }
+//!
+//! let participant = DomainParticipant::new(0).unwrap();
+//! let qos = QosPolicyBuilder::new().build();
+//! let topic = participant
+//!   .create_topic(
+//!     "some_topic".to_string(),
+//!     "SomeType".to_string(),
+//!     &qos,
+//!     TopicKind::NoKey,
+//!   )
+//!   .unwrap();
+//! let publisher = participant.create_publisher(&qos).unwrap();
+//! let writer = publisher
+//!   .create_datawriter_no_key_cdr::<SomeType>(&topic, None)
+//!   .unwrap();
+//!
+//! if writer
+//!   .get_status_changes()
+//!   .contains(StatusKind::PublicationMatched)
+//! {
+//!   let status = writer.get_publication_matched_status();
+//!   println!("{} matched readers", status.current_count);
+//! }
+//! ```
+
+use std::{sync::Mutex, task::Waker};
+
+use enumflags2::{bitflags, BitFlags};
+
+use crate::{
+  dds::{
+    listener::{DataReaderListener, DataWriterListener, ListenerSlot},
+    qos::QosPolicyId,
+    statusevents::{DataReaderStatus, DataWriterStatus, SampleRejectedStatusKind},
+    waitset::{ConditionWakers, StatusCondition, StatusRecord},
+  },
+  structure::guid::GUID,
+};
+
+/// Communication statuses. DDS specification v1.4 Section 2.3.3 defines the
+/// values.
+#[bitflags]
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusKind {
+  /// Not reported. Inconsistent topics are reported by the DomainParticipant
+  /// as [`DomainParticipantStatusEvent::InconsistentTopic`](crate::DomainParticipantStatusEvent::InconsistentTopic).
+  InconsistentTopic = 0x0001,
+  OfferedDeadlineMissed = 0x0002,
+  RequestedDeadlineMissed = 0x0004,
+  OfferedIncompatibleQos = 0x0020,
+  RequestedIncompatibleQos = 0x0040,
+  SampleLost = 0x0080,
+  SampleRejected = 0x0100,
+  /// Not reported. Received data is signaled by the DataReaders themselves.
+  DataOnReaders = 0x0200,
+  /// Set when the DataReader receives samples, and reset when it reads or
+  /// takes. There is no status getter.
+  DataAvailable = 0x0400,
+  LivelinessLost = 0x0800,
+  LivelinessChanged = 0x1000,
+  PublicationMatched = 0x2000,
+  SubscriptionMatched = 0x4000,
+}
+
+/// A set of [`StatusKind`]s
+pub type StatusMask = BitFlags<StatusKind>;
+
+/// The polling interface of the Entity class of the DDS specification.
+pub trait Entity {
+  /// The communication statuses that have changed since their getter was
+  /// last called.
+  fn get_status_changes(&self) -> StatusMask;
+
+  /// A condition for a [`WaitSet`](crate::waitset::WaitSet), triggered by the
+  /// status changes of this entity.
+  fn get_statuscondition(&self) -> StatusCondition;
+}
+
+/// Liveliness of the DataWriter, as asserted to its matched DataReaders.
+///
+/// Not reported yet: the count stays at zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LivelinessLostStatus {
+  pub total_count: i32,
+  pub total_count_change: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OfferedDeadlineMissedStatus {
+  pub total_count: i32,
+  pub total_count_change: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OfferedIncompatibleQosStatus {
+  pub total_count: i32,
+  pub total_count_change: i32,
+  pub last_policy_id: Option<QosPolicyId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PublicationMatchedStatus {
+  pub total_count: i32,
+  pub total_count_change: i32,
+  pub current_count: i32,
+  pub current_count_change: i32,
+  /// The reader that was last matched or lost
+  pub last_subscription_handle: Option<GUID>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SampleRejectedStatus {
+  pub total_count: i32,
+  pub total_count_change: i32,
+  pub last_reason: SampleRejectedStatusKind,
+}
+
+impl Default for SampleRejectedStatus {
+  fn default() -> Self {
+    Self {
+      total_count: 0,
+      total_count_change: 0,
+      last_reason: SampleRejectedStatusKind::NotRejected,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LivelinessChangedStatus {
+  pub alive_count: i32,
+  pub not_alive_count: i32,
+  pub alive_count_change: i32,
+  pub not_alive_count_change: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RequestedDeadlineMissedStatus {
+  pub total_count: i32,
+  pub total_count_change: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RequestedIncompatibleQosStatus {
+  pub total_count: i32,
+  pub total_count_change: i32,
+  pub last_policy_id: Option<QosPolicyId>,
+}
+
+/// Not reported yet: the count stays at zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SampleLostStatus {
+  pub total_count: i32,
+  pub total_count_change: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SubscriptionMatchedStatus {
+  pub total_count: i32,
+  pub total_count_change: i32,
+  pub current_count: i32,
+  pub current_count_change: i32,
+  /// The writer that was last matched or lost
+  pub last_publication_handle: Option<GUID>,
+}
+
+// Implemented by the statuses, whose getters reset the change counts.
+trait CountChanges: Copy {
+  fn reset_changes(&mut self);
+}
+
+macro_rules! count_changes {
+  ($status:ty, $( $change:ident ),+) => {
+    impl CountChanges for $status {
+      fn reset_changes(&mut self) {
+        $( self.$change = 0; )+
+      }
+    }
+  };
+}
+
+count_changes!(LivelinessLostStatus, total_count_change);
+count_changes!(OfferedDeadlineMissedStatus, total_count_change);
+count_changes!(OfferedIncompatibleQosStatus, total_count_change);
+count_changes!(
+  PublicationMatchedStatus,
+  total_count_change,
+  current_count_change
+);
+count_changes!(SampleRejectedStatus, total_count_change);
+count_changes!(
+  LivelinessChangedStatus,
+  alive_count_change,
+  not_alive_count_change
+);
+count_changes!(RequestedDeadlineMissedStatus, total_count_change);
+count_changes!(RequestedIncompatibleQosStatus, total_count_change);
+count_changes!(SampleLostStatus, total_count_change);
+count_changes!(
+  SubscriptionMatchedStatus,
+  total_count_change,
+  current_count_change
+);
+
+// Returns the status, and resets its change counts and changed flag.
+fn take<S: CountChanges>(changes: &mut StatusMask, kind: StatusKind, status: &mut S) -> S {
+  let current = *status;
+  status.reset_changes();
+  changes.remove(kind);
+  current
+}
+
+#[derive(Debug, Default)]
+struct WriterStatuses {
+  changes: StatusMask,
+  liveliness_lost: LivelinessLostStatus,
+  offered_deadline_missed: OfferedDeadlineMissedStatus,
+  offered_incompatible_qos: OfferedIncompatibleQosStatus,
+  publication_matched: PublicationMatchedStatus,
+}
+
+// The communication statuses of a DataWriter. Updated by the RTPS Writer as it
+// sends status events, and polled through the DataWriter, or given to its
+// listener.
+#[derive(Debug, Default)]
+pub(crate) struct WriterStatusRecord {
+  statuses: Mutex<WriterStatuses>,
+  wakers: ConditionWakers,
+  pub listener: ListenerSlot<dyn DataWriterListener>,
+}
+
+impl WriterStatusRecord {
+  pub fn record(&self, event: &DataWriterStatus) {
+    let mut s = self.statuses.lock().unwrap();
+    let kind = match event {
+      DataWriterStatus::LivelinessLost { count } => {
+        s.liveliness_lost.total_count = count.count();
+        s.liveliness_lost.total_count_change += count.count_change();
+        StatusKind::LivelinessLost
+      }
+      DataWriterStatus::OfferedDeadlineMissed { count } => {
+        s.offered_deadline_missed.total_count = count.count();
+        s.offered_deadline_missed.total_count_change += count.count_change();
+        StatusKind::OfferedDeadlineMissed
+      }
+      DataWriterStatus::OfferedIncompatibleQos {
+        count,
+        last_policy_id,
+        ..
+      } => {
+        s.offered_incompatible_qos.total_count = count.count();
+        s.offered_incompatible_qos.total_count_change += count.count_change();
+        s.offered_incompatible_qos.last_policy_id = Some(*last_policy_id);
+        StatusKind::OfferedIncompatibleQos
+      }
+      DataWriterStatus::PublicationMatched {
+        total,
+        current,
+        reader,
+      } => {
+        let status = &mut s.publication_matched;
+        status.total_count = total.count();
+        status.total_count_change += total.count_change();
+        status.current_count = current.count();
+        status.current_count_change += current.count_change();
+        status.last_subscription_handle = Some(*reader);
+        StatusKind::PublicationMatched
+      }
+      // Not a DDS communication status
+      DataWriterStatus::UnacknowledgedBelowThreshold { .. }
+      | DataWriterStatus::ReaderPresenceChanged { .. } => return,
+    };
+    s.changes.insert(kind);
+    drop(s);
+    self.wakers.wake_all();
+    self.notify_listener(kind);
+  }
+
+  // The listener, if set, takes the changed status.
+  fn notify_listener(&self, kind: StatusKind) {
+    if !self.listener.is_set() {
+      return;
+    }
+    match kind {
+      StatusKind::LivelinessLost => {
+        let status = self.take_liveliness_lost();
+        self.listener.call(move |l| l.on_liveliness_lost(status));
+      }
+      StatusKind::OfferedDeadlineMissed => {
+        let status = self.take_offered_deadline_missed();
+        self
+          .listener
+          .call(move |l| l.on_offered_deadline_missed(status));
+      }
+      StatusKind::OfferedIncompatibleQos => {
+        let status = self.take_offered_incompatible_qos();
+        self
+          .listener
+          .call(move |l| l.on_offered_incompatible_qos(status));
+      }
+      StatusKind::PublicationMatched => {
+        let status = self.take_publication_matched();
+        self
+          .listener
+          .call(move |l| l.on_publication_matched(status));
+      }
+      _ => (),
+    }
+  }
+
+  pub fn changes(&self) -> StatusMask {
+    self.statuses.lock().unwrap().changes
+  }
+
+  pub fn take_liveliness_lost(&self) -> LivelinessLostStatus {
+    let s = &mut *self.statuses.lock().unwrap();
+    take(
+      &mut s.changes,
+      StatusKind::LivelinessLost,
+      &mut s.liveliness_lost,
+    )
+  }
+
+  pub fn take_offered_deadline_missed(&self) -> OfferedDeadlineMissedStatus {
+    let s = &mut *self.statuses.lock().unwrap();
+    take(
+      &mut s.changes,
+      StatusKind::OfferedDeadlineMissed,
+      &mut s.offered_deadline_missed,
+    )
+  }
+
+  pub fn take_offered_incompatible_qos(&self) -> OfferedIncompatibleQosStatus {
+    let s = &mut *self.statuses.lock().unwrap();
+    take(
+      &mut s.changes,
+      StatusKind::OfferedIncompatibleQos,
+      &mut s.offered_incompatible_qos,
+    )
+  }
+
+  pub fn take_publication_matched(&self) -> PublicationMatchedStatus {
+    let s = &mut *self.statuses.lock().unwrap();
+    take(
+      &mut s.changes,
+      StatusKind::PublicationMatched,
+      &mut s.publication_matched,
+    )
+  }
+}
+
+#[derive(Debug, Default)]
+struct ReaderStatuses {
+  changes: StatusMask,
+  sample_rejected: SampleRejectedStatus,
+  liveliness_changed: LivelinessChangedStatus,
+  requested_deadline_missed: RequestedDeadlineMissedStatus,
+  requested_incompatible_qos: RequestedIncompatibleQosStatus,
+  sample_lost: SampleLostStatus,
+  subscription_matched: SubscriptionMatchedStatus,
+}
+
+// The communication statuses of a DataReader. Updated by the RTPS Reader as it
+// sends status events, and polled through the DataReader, or given to its
+// listener.
+#[derive(Debug, Default)]
+pub(crate) struct ReaderStatusRecord {
+  statuses: Mutex<ReaderStatuses>,
+  wakers: ConditionWakers,
+  pub listener: ListenerSlot<dyn DataReaderListener>,
+}
+
+impl ReaderStatusRecord {
+  pub fn record(&self, event: &DataReaderStatus) {
+    let mut s = self.statuses.lock().unwrap();
+    let kind = match event {
+      DataReaderStatus::SampleRejected { count, last_reason } => {
+        s.sample_rejected.total_count = count.count();
+        s.sample_rejected.total_count_change += count.count_change();
+        s.sample_rejected.last_reason = *last_reason;
+        StatusKind::SampleRejected
+      }
+      DataReaderStatus::LivelinessChanged {
+        alive_total,
+        not_alive_total,
+      } => {
+        let status = &mut s.liveliness_changed;
+        status.alive_count = alive_total.count();
+        status.alive_count_change += alive_total.count_change();
+        status.not_alive_count = not_alive_total.count();
+        status.not_alive_count_change += not_alive_total.count_change();
+        StatusKind::LivelinessChanged
+      }
+      DataReaderStatus::RequestedDeadlineMissed { count } => {
+        s.requested_deadline_missed.total_count = count.count();
+        s.requested_deadline_missed.total_count_change += count.count_change();
+        StatusKind::RequestedDeadlineMissed
+      }
+      DataReaderStatus::RequestedIncompatibleQos {
+        count,
+        last_policy_id,
+        ..
+      } => {
+        s.requested_incompatible_qos.total_count = count.count();
+        s.requested_incompatible_qos.total_count_change += count.count_change();
+        s.requested_incompatible_qos.last_policy_id = Some(*last_policy_id);
+        StatusKind::RequestedIncompatibleQos
+      }
+      DataReaderStatus::SampleLost { count } => {
+        s.sample_lost.total_count = count.count();
+        s.sample_lost.total_count_change += count.count_change();
+        StatusKind::SampleLost
+      }
+      DataReaderStatus::SubscriptionMatched {
+        total,
+        current,
+        writer,
+      } => {
+        let status = &mut s.subscription_matched;
+        status.total_count = total.count();
+        status.total_count_change += total.count_change();
+        status.current_count = current.count();
+        status.current_count_change += current.count_change();
+        status.last_publication_handle = Some(*writer);
+        StatusKind::SubscriptionMatched
+      }
+      // RustDDS extensions, not DDS communication statuses
+      DataReaderStatus::WriterRestarted { .. } | DataReaderStatus::WriterQuarantined { .. } => {
+        return
+      }
+    };
+    s.changes.insert(kind);
+    drop(s);
+    self.wakers.wake_all();
+    self.notify_listener(kind);
+  }
+
+  // The listener, if set, takes the changed status.
+  fn notify_listener(&self, kind: StatusKind) {
+    if !self.listener.is_set() {
+      return;
+    }
+    match kind {
+      StatusKind::SampleRejected => {
+        let status = self.take_sample_rejected();
+        self.listener.call(move |l| l.on_sample_rejected(status));
+      }
+      StatusKind::LivelinessChanged => {
+        let status = self.take_liveliness_changed();
+        self.listener.call(move |l| l.on_liveliness_changed(status));
+      }
+      StatusKind::RequestedDeadlineMissed => {
+        let status = self.take_requested_deadline_missed();
+        self
+          .listener
+          .call(move |l| l.on_requested_deadline_missed(status));
+      }
+      StatusKind::RequestedIncompatibleQos => {
+        let status = self.take_requested_incompatible_qos();
+        self
+          .listener
+          .call(move |l| l.on_requested_incompatible_qos(status));
+      }
+      StatusKind::SampleLost => {
+        let status = self.take_sample_lost();
+        self.listener.call(move |l| l.on_sample_lost(status));
+      }
+      StatusKind::SubscriptionMatched => {
+        let status = self.take_subscription_matched();
+        self
+          .listener
+          .call(move |l| l.on_subscription_matched(status));
+      }
+      _ => (),
+    }
+  }
+
+  // The Reader received samples. The listener is called only when they are
+  // the first since the DataReader last read.
+  pub fn data_available(&self) {
+    let mut s = self.statuses.lock().unwrap();
+    let newly_available = !s.changes.contains(StatusKind::DataAvailable);
+    s.changes.insert(StatusKind::DataAvailable);
+    drop(s);
+    self.wakers.wake_all();
+    if newly_available {
+      self.listener.call(|l| l.on_data_available());
+    }
+  }
+
+  // The DataReader is reading them
+  pub fn data_read(&self) {
+    self
+      .statuses
+      .lock()
+      .unwrap()
+      .changes
+      .remove(StatusKind::DataAvailable);
+  }
+
+  pub fn changes(&self) -> StatusMask {
+    self.statuses.lock().unwrap().changes
+  }
+
+  pub fn take_sample_rejected(&self) -> SampleRejectedStatus {
+    let s = &mut *self.statuses.lock().unwrap();
+    take(
+      &mut s.changes,
+      StatusKind::SampleRejected,
+      &mut s.sample_rejected,
+    )
+  }
+
+  pub fn take_liveliness_changed(&self) -> LivelinessChangedStatus {
+    let s = &mut *self.statuses.lock().unwrap();
+    take(
+      &mut s.changes,
+      StatusKind::LivelinessChanged,
+      &mut s.liveliness_changed,
+    )
+  }
+
+  pub fn take_requested_deadline_missed(&self) -> RequestedDeadlineMissedStatus {
+    let s = &mut *self.statuses.lock().unwrap();
+    take(
+      &mut s.changes,
+      StatusKind::RequestedDeadlineMissed,
+      &mut s.requested_deadline_missed,
+    )
+  }
+
+  pub fn take_requested_incompatible_qos(&self) -> RequestedIncompatibleQosStatus {
+    let s = &mut *self.statuses.lock().unwrap();
+    take(
+      &mut s.changes,
+      StatusKind::RequestedIncompatibleQos,
+      &mut s.requested_incompatible_qos,
+    )
+  }
+
+  pub fn take_sample_lost(&self) -> SampleLostStatus {
+    let s = &mut *self.statuses.lock().unwrap();
+    take(&mut s.changes, StatusKind::SampleLost, &mut s.sample_lost)
+  }
+
+  pub fn take_subscription_matched(&self) -> SubscriptionMatchedStatus {
+    let s = &mut *self.statuses.lock().unwrap();
+    take(
+      &mut s.changes,
+      StatusKind::SubscriptionMatched,
+      &mut s.subscription_matched,
+    )
+  }
+}
+
+impl StatusRecord for WriterStatusRecord {
+  fn changes(&self) -> StatusMask {
+    self.changes()
+  }
+
+  fn register_waker(&self, waker: &Waker) {
+    self.wakers.register(waker);
+  }
+}
+
+impl StatusRecord for ReaderStatusRecord {
+  fn changes(&self) -> StatusMask {
+    self.changes()
+  }
+
+  fn register_waker(&self, waker: &Waker) {
+    self.wakers.register(waker);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{dds::statusevents::CountWithChange, structure::guid::EntityKind};
+
+  #[test]
+  fn getters_reset_changes() {
+    let record = ReaderStatusRecord::default();
+    let writer = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    assert!(record.changes().is_empty());
+
+    for matched in 1..=2 {
+      record.record(&DataReaderStatus::SubscriptionMatched {
+        total: CountWithChange::new(matched, 1),
+        current: CountWithChange::new(matched, 1),
+        writer,
+      });
+    }
+    record.record(&DataReaderStatus::SubscriptionMatched {
+      total: CountWithChange::new(2, 0),
+      current: CountWithChange::new(1, -1),
+      writer,
+    });
+    assert_eq!(record.changes(), StatusKind::SubscriptionMatched);
+
+    assert_eq!(
+      record.take_subscription_matched(),
+      SubscriptionMatchedStatus {
+        total_count: 2,
+        total_count_change: 2,
+        current_count: 1,
+        current_count_change: 1,
+        last_publication_handle: Some(writer),
+      }
+    );
+    assert!(record.changes().is_empty());
+    let again = record.take_subscription_matched();
+    assert_eq!((again.total_count, again.total_count_change), (2, 0));
+
+    // Extensions are not communication statuses
+    record.record(&DataReaderStatus::WriterRestarted {
+      count: CountWithChange::new(1, 1),
+      writer,
+      policy: crate::policy::WriterRestart::Reject,
+    });
+    assert!(record.changes().is_empty());
+  }
+}
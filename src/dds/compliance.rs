@@ -0,0 +1,110 @@
+//! Participant-level choice between strict RTPS specification compliance and
+//! interoperability with known deviating implementations.
+//!
+//! [`ComplianceMode::Permissive`] is the default. It accepts the following
+//! deviations from the RTPS specification v2.5, which have been seen from
+//! other DDS implementations:
+//!
+//! * A CDR string with length zero, i.e. without the terminating NUL
+//!   character, is read as an empty string. A string with other missing
+//!   terminator is read as is.
+//! * A parameter in a ParameterList, whose length is not a multiple of 4.
+//! * A PID_SENTINEL parameter with non-zero length.
+//! * A submessage, whose length is not a multiple of 4, followed by another
+//!   submessage. The next submessage is read from where the length says.
+//! * A repeated sequence number from a remote SPDP writer is processed again
+//!   instead of being dropped as a duplicate. At least some versions of
+//!   eProsima FastDDS do not increment the sequence number of their
+//!   participant announcements.
+//!
+//! [`ComplianceMode::Strict`] rejects all of the above, and is intended for
+//! certification and conformance testing. The whole RTPS message or
+//! discovery sample containing a deviation is dropped.
+//!
+//! Deviations are logged in both modes.
+//!
+//! The mode applies to what a DomainParticipant receives from the network.
+//! RTPS messages are parsed in its event loop thread and discovery samples in
+//! its Discovery thread, and the mode is in effect in those two threads only.
+//! Anything parsed in other threads, such as samples that a DataReader
+//! deserializes when the application reads them, is treated as in
+//! [`ComplianceMode::Permissive`].
+
+use std::cell::Cell;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+/// How to treat received messages that do not conform to the RTPS
+/// specification. See the [module documentation](self) for details.
+///
+/// Set with
+/// [`DomainParticipantBuilder::compliance_mode`](crate::DomainParticipantBuilder::compliance_mode).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ComplianceMode {
+  /// Reject out-of-spec input.
+  Strict,
+  /// Accept known deviations of other implementations.
+  #[default]
+  Permissive,
+}
+
+// Incoming messages are parsed in the background threads of a
+// DomainParticipant (event loop and Discovery), and deep inside
+// deserialization code, which has no access to the participant. Therefore each
+// of those threads enters the mode of its participant when it starts, and other
+// threads have no mode.
+thread_local! {
+  static CURRENT_MODE: Cell<Option<ComplianceMode>> = const { Cell::new(None) };
+}
+
+impl ComplianceMode {
+  pub fn is_strict(self) -> bool {
+    self == Self::Strict
+  }
+
+  // Mode of the DomainParticipant that runs the current thread. Permissive
+  // in application threads.
+  pub(crate) fn current() -> Self {
+    CURRENT_MODE.with(Cell::get).unwrap_or_default()
+  }
+
+  // Puts the mode in effect in the current thread, until the returned scope is
+  // dropped. Scopes do not nest: a thread runs the work of one participant.
+  #[must_use]
+  pub(crate) fn enter(self) -> ComplianceScope {
+    let previous = CURRENT_MODE.with(|m| m.replace(Some(self)));
+    debug_assert!(
+      previous.is_none(),
+      "Compliance mode entered twice in the same thread"
+    );
+    ComplianceScope { previous }
+  }
+}
+
+// Keeps a ComplianceMode in effect in the current thread. See
+// `ComplianceMode::enter`.
+pub(crate) struct ComplianceScope {
+  previous: Option<ComplianceMode>,
+}
+
+impl Drop for ComplianceScope {
+  fn drop(&mut self) {
+    CURRENT_MODE.with(|m| m.set(self.previous));
+  }
+}
+
+// Called when input deviates from the specification. Returns true, if the
+// current mode accepts the deviation, false if the input must be rejected.
+pub(crate) fn tolerate(deviation: &str) -> bool {
+  match ComplianceMode::current() {
+    ComplianceMode::Strict => {
+      warn!("Strict compliance mode: rejecting input. {deviation}");
+      false
+    }
+    ComplianceMode::Permissive => {
+      debug!("Accepting out-of-spec input: {deviation}");
+      true
+    }
+  }
+}
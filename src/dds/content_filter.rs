@@ -0,0 +1,538 @@
+//! Content filters implemented as Rust closures.
+//!
+//! A DataReader with a content filter receives only the samples that pass the
+//! filter. DDS specifies filters as SQL-like expressions, but between Rust
+//! applications a filter can be any closure. A [`ContentFilterFactory`]
+//! creates the closure from a filter expression and parameters, and is
+//! identified by its filter class name.
+//!
+//! The DataReader advertises the class name, expression and parameters of its
+//! filter in Discovery. A DataWriter that has a factory of the same class
+//! registered creates the same filter for the DataReader, and sends it only
+//! the samples that pass. Other DataWriters send everything, and the
+//! DataReader drops the samples that do not pass. Either way the application
+//! sees the same samples, so factories of the same class must create filters
+//! that behave the same on both sides.
+//!
+//! ```
+//! use rustdds::{content_filter::ContentFilterFactory, *};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Clone, Debug)]
+//! struct Reading {
+//!   sensor: u32,
+//!   value: f64,
+//! }
+//! impl Keyed for Reading {
+//!   type K = u32;
+//!   fn key(&self) -> u32 {
+//!     self.sensor
+//!   }
+//! }
+//!
+//! // Both the writing and the reading application have this.
+//! fn above_threshold() -> ContentFilterFactory<Reading> {
+//!   ContentFilterFactory::new("AboveThreshold", |_expression, parameters| {
+//!     let threshold: f64 = parameters
+//!       .first()
+//!       .and_then(|p| p.parse().ok())
+//!       .ok_or("a numeric threshold is needed")?;
+//!     Ok(move |reading: &Reading| reading.value > threshold)
+//!   })
+//! }
+//!
+//! let participant = DomainParticipant::new(0).unwrap();
+//! let qos = QosPolicyBuilder::new().build();
+//! let topic = participant
+//!   .create_topic(
+//!     "readings".to_string(),
+//!     "Reading".to_string(),
+//!     &qos,
+//!     TopicKind::WithKey,
+//!   )
+//!   .unwrap();
+//!
+//! let publisher = participant.create_publisher(&qos).unwrap();
+//! let writer = publisher
+//!   .create_datawriter_cdr::<Reading>(&topic, None)
+//!   .unwrap();
+//! writer.register_content_filter_factory(above_threshold());
+//!
+//! let subscriber = participant.create_subscriber(&qos).unwrap();
+//! let reader = subscriber
+//!   .create_datareader_cdr::<Reading>(&topic, None)
+//!   .unwrap();
+//! reader
+//!   .set_content_filter(&above_threshold(), "", vec!["20.5".to_string()])
+//!   .unwrap();
+//! ```
+//!
+//! Filters of the "DDSSQL" class are the SQL-like filter expressions of the
+//! DDS specification, which other DDS implementations also understand. The
+//! factory [`ContentFilterFactory::ddssql`] creates them for any type that
+//! implements `Serialize`. A [`ContentFilteredTopic`] names such a filter, so
+//! that it can be given to
+//! [`Subscriber::create_filtered_datareader`](crate::Subscriber::create_filtered_datareader).
+//! DataWriters apply "DDSSQL" filters without a registered factory, if their
+//! serializer adapter can inspect the samples. See
+//! [`SerializerAdapter::matches_sql_filter`](crate::no_key::SerializerAdapter::matches_sql_filter).
+//!
+//!
+//! ```
+//! # use rustdds::*;
+//! # use serde::{Deserialize, Serialize};
+//! # #[derive(Serialize, Deserialize, Clone, Debug)]
+//! # struct Reading {
+//! #   sensor: u32,
+//! #   value: f64,
+//! # }
+//! # impl Keyed for Reading {
+//! #   type K = u32;
+//! #   fn key(&self) -> u32 {
+//! #     self.sensor
+//! #   }
+//! # }
+//! # let participant = DomainParticipant::new(0).unwrap();
+//! # let qos = QosPolicyBuilder::new().build();
+//! # let topic = participant
+//! #   .create_topic("readings".to_string(), "Reading".to_string(), &qos, TopicKind::WithKey)
+//! #   .unwrap();
+//! let filtered_topic = participant
+//!   .create_contentfilteredtopic(
+//!     "high_readings",
+//!     &topic,
+//!     "value > %0 AND sensor BETWEEN 1 AND 9",
+//!     vec!["20.5".to_string()],
+//!   )
+//!   .unwrap();
+//! let subscriber = participant.create_subscriber(&qos).unwrap();
+//! let reader = subscriber
+//!   .create_filtered_datareader_cdr::<Reading>(&filtered_topic, None)
+//!   .unwrap();
+//! ```
+
+use std::{
+  collections::BTreeMap,
+  sync::{Arc, Mutex, RwLock},
+};
+
+use serde::Serialize;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  create_error_bad_parameter,
+  dds::{
+    participant::DomainParticipant,
+    result::{CreateError, CreateResult},
+    topic::{Topic, TopicDescription},
+    typedesc::TypeDesc,
+  },
+  discovery::content_filter_property::ContentFilterProperty,
+  structure::guid::GUID,
+};
+
+pub(crate) mod sql;
+
+use sql::SqlFilter;
+
+/// Filter class name of the SQL filters of the DDS specification.
+pub const DDSSQL_FILTER_CLASS_NAME: &str = "DDSSQL";
+
+/// A filter created by a [`ContentFilterFactory`]. Returns true for the
+/// samples that pass.
+pub type SampleFilter<D> = Arc<dyn Fn(&D) -> bool + Send + Sync>;
+
+type CreateFilter<D> = dyn Fn(&str, &[String]) -> Result<SampleFilter<D>, String> + Send + Sync;
+
+/// Creates content filters of one filter class. See the
+/// [module documentation](self).
+pub struct ContentFilterFactory<D> {
+  class_name: String,
+  create: Arc<CreateFilter<D>>,
+}
+
+impl<D> Clone for ContentFilterFactory<D> {
+  fn clone(&self) -> Self {
+    Self {
+      class_name: self.class_name.clone(),
+      create: Arc::clone(&self.create),
+    }
+  }
+}
+
+impl<D: 'static> ContentFilterFactory<D> {
+  /// `create` makes a filter from a filter expression and its parameters, or
+  /// tells why it cannot.
+  ///
+  /// The class name "DDSSQL" is reserved for the SQL filters of the DDS
+  /// specification, which other implementations may apply. Use
+  /// [`ddssql`](Self::ddssql) for them.
+  pub fn new<C, F>(class_name: impl Into<String>, create: C) -> Self
+  where
+    C: Fn(&str, &[String]) -> Result<F, String> + Send + Sync + 'static,
+    F: Fn(&D) -> bool + Send + Sync + 'static,
+  {
+    Self {
+      class_name: class_name.into(),
+      create: Arc::new(move |expression: &str, parameters: &[String]| {
+        create(expression, parameters).map(|filter| Arc::new(filter) as SampleFilter<D>)
+      }),
+    }
+  }
+}
+
+impl<D: Serialize + 'static> ContentFilterFactory<D> {
+  /// Creates filters from DDS SQL filter expressions, such as
+  /// `"value > %0 AND name LIKE %1"`.
+  ///
+  /// The expression compares fields of the sample to literals and to the
+  /// parameters `%0`, `%1`, etc. with `=`, `<>`, `<`, `<=`, `>`, `>=`, `LIKE`
+  /// and `[NOT] BETWEEN`, and combines comparisons with `AND`, `OR`, `NOT`
+  /// and parentheses. Fields are named as in the Rust type, nested fields
+  /// separated by dots, and enum fields compare to their variant names.
+  /// String literals and parameters are in single quotes. The sample is
+  /// inspected through its `Serialize` implementation.
+  pub fn ddssql() -> Self {
+    Self::new(DDSSQL_FILTER_CLASS_NAME, |expression, parameters| {
+      let filter = SqlFilter::parse(expression, parameters)?;
+      Ok(move |sample: &D| filter.matches(sample))
+    })
+  }
+}
+
+/// A parsed DDS SQL filter expression with its parameters. See
+/// [`ContentFilterFactory::ddssql`] for the syntax.
+#[derive(Clone, Debug)]
+pub struct DdsSqlFilter(SqlFilter);
+
+impl DdsSqlFilter {
+  /// Whether the sample passes. The sample is inspected through its
+  /// `Serialize` implementation.
+  pub fn matches<D: Serialize + ?Sized>(&self, sample: &D) -> bool {
+    self.0.matches(sample)
+  }
+}
+
+impl<D> ContentFilterFactory<D> {
+  pub fn class_name(&self) -> &str {
+    &self.class_name
+  }
+
+  pub fn create_filter(
+    &self,
+    filter_expression: &str,
+    expression_parameters: &[String],
+  ) -> Result<SampleFilter<D>, String> {
+    (self.create)(filter_expression, expression_parameters)
+  }
+}
+
+/// A Topic together with a DDS SQL filter expression. DataReaders created
+/// for it receive only the samples of the related Topic that pass the
+/// filter. See [`ContentFilterFactory::ddssql`] for the syntax.
+///
+/// Create with
+/// [`DomainParticipant::create_contentfilteredtopic`](crate::DomainParticipant::create_contentfilteredtopic).
+#[derive(Clone, Debug)]
+pub struct ContentFilteredTopic {
+  name: String,
+  related_topic: Topic,
+  filter_expression: String,
+  expression_parameters: Vec<String>,
+}
+
+impl ContentFilteredTopic {
+  pub(crate) fn new(
+    name: &str,
+    related_topic: &Topic,
+    filter_expression: &str,
+    expression_parameters: Vec<String>,
+  ) -> CreateResult<Self> {
+    if name.is_empty() || filter_expression.is_empty() {
+      return create_error_bad_parameter!(
+        "A ContentFilteredTopic must have a name and a filter expression."
+      );
+    }
+    if let Err(e) = SqlFilter::parse(filter_expression, &expression_parameters) {
+      return create_error_bad_parameter!("Invalid filter expression {filter_expression:?}: {e}");
+    }
+    Ok(Self {
+      name: name.to_string(),
+      related_topic: related_topic.clone(),
+      filter_expression: filter_expression.to_string(),
+      expression_parameters,
+    })
+  }
+
+  pub fn related_topic(&self) -> &Topic {
+    &self.related_topic
+  }
+
+  pub fn filter_expression(&self) -> &str {
+    &self.filter_expression
+  }
+
+  pub fn expression_parameters(&self) -> &[String] {
+    &self.expression_parameters
+  }
+}
+
+impl TopicDescription for ContentFilteredTopic {
+  fn participant(&self) -> Option<DomainParticipant> {
+    self.related_topic.participant()
+  }
+
+  fn get_type(&self) -> TypeDesc {
+    self.related_topic.get_type()
+  }
+
+  fn name(&self) -> String {
+    self.name.clone()
+  }
+}
+
+// Content filters of the readers matched to a Writer, as advertised in
+// Discovery. Updated by the RTPS Writer, as readers are matched and lost, and
+// read by the DataWriter, which applies them.
+#[derive(Debug, Default)]
+pub(crate) struct MatchedReaderFilters {
+  filters: RwLock<BTreeMap<GUID, ContentFilterProperty>>,
+}
+
+impl MatchedReaderFilters {
+  pub fn update(&self, reader: GUID, filter: Option<&ContentFilterProperty>) {
+    let mut filters = self.filters.write().unwrap();
+    match filter {
+      Some(filter) => filters.insert(reader, filter.clone()),
+      None => filters.remove(&reader),
+    };
+  }
+
+  pub fn remove(&self, reader: GUID) {
+    self.filters.write().unwrap().remove(&reader);
+  }
+}
+
+// A filter created for a matched reader
+enum WriterFilter<D> {
+  Factory(SampleFilter<D>),
+  // Of the "DDSSQL" class, when no factory of the class is registered
+  Sql(DdsSqlFilter),
+}
+
+// A filter created for a matched reader, with the property it was created
+// from. None, if the filter could not be created.
+type CreatedFilter<D> = (ContentFilterProperty, Option<WriterFilter<D>>);
+
+// Tells whether a sample passes a DDS SQL filter, if it can
+pub(crate) type SqlMatcher<D> = fn(&D, &DdsSqlFilter) -> Option<bool>;
+
+// The filters that a DataWriter applies on behalf of matched readers.
+pub(crate) struct WriterContentFilters<D> {
+  factories: RwLock<BTreeMap<String, ContentFilterFactory<D>>>,
+  sql_matcher: SqlMatcher<D>,
+  matched: Arc<MatchedReaderFilters>,
+  created: Mutex<BTreeMap<GUID, CreatedFilter<D>>>,
+}
+
+impl<D> WriterContentFilters<D> {
+  pub fn new(matched: Arc<MatchedReaderFilters>, sql_matcher: SqlMatcher<D>) -> Self {
+    Self {
+      factories: RwLock::new(BTreeMap::new()),
+      sql_matcher,
+      matched,
+      created: Mutex::new(BTreeMap::new()),
+    }
+  }
+
+  // Replaces any factory of the same class
+  pub fn register(&self, factory: ContentFilterFactory<D>) {
+    self
+      .factories
+      .write()
+      .unwrap()
+      .insert(factory.class_name.clone(), factory);
+    // Filters are created again with the new factories
+    self.created.lock().unwrap().clear();
+  }
+
+  // The readers that filter out the sample
+  pub fn readers_filtering_out(&self, sample: &D) -> Vec<GUID> {
+    let factories = self.factories.read().unwrap();
+    let matched = self.matched.filters.read().unwrap();
+    let mut created = self.created.lock().unwrap();
+    created.retain(|reader, _| matched.contains_key(reader));
+
+    let mut filtering_out = Vec::new();
+    for (reader, property) in matched.iter() {
+      let up_to_date = created
+        .get(reader)
+        .is_some_and(|(created_from, _)| created_from == property);
+      if !up_to_date {
+        created.insert(
+          *reader,
+          (
+            property.clone(),
+            Self::create(&factories, *reader, property),
+          ),
+        );
+      }
+      let passes = match created.get(reader) {
+        Some((_, Some(WriterFilter::Factory(filter)))) => filter(sample),
+        Some((_, Some(WriterFilter::Sql(filter)))) => {
+          (self.sql_matcher)(sample, filter).unwrap_or(true)
+        }
+        _ => true,
+      };
+      if !passes {
+        filtering_out.push(*reader);
+      }
+    }
+    filtering_out
+  }
+
+  fn create(
+    factories: &BTreeMap<String, ContentFilterFactory<D>>,
+    reader: GUID,
+    property: &ContentFilterProperty,
+  ) -> Option<WriterFilter<D>> {
+    let created = match factories.get(&property.filter_class_name) {
+      Some(factory) => factory
+        .create_filter(&property.filter_expression, &property.expression_parameters)
+        .map(WriterFilter::Factory),
+      None if property.filter_class_name == DDSSQL_FILTER_CLASS_NAME => {
+        SqlFilter::parse(&property.filter_expression, &property.expression_parameters)
+          .map(|filter| WriterFilter::Sql(DdsSqlFilter(filter)))
+      }
+      // Filters of other classes are applied by the reader only
+      None => return None,
+    };
+    created
+      .map_err(|e| {
+        warn!(
+          "Cannot create content filter {:?} of reader {reader:?}: {e}. Sending it all samples.",
+          property.filter_class_name
+        );
+      })
+      .ok()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::structure::guid::EntityKind;
+
+  fn threshold_factory() -> ContentFilterFactory<i32> {
+    ContentFilterFactory::new("Threshold", |_, parameters: &[String]| {
+      let threshold: i32 = parameters
+        .first()
+        .and_then(|p| p.parse().ok())
+        .ok_or("no threshold")?;
+      Ok(move |x: &i32| *x > threshold)
+    })
+  }
+
+  fn property(class_name: &str, parameters: &[&str]) -> ContentFilterProperty {
+    ContentFilterProperty {
+      content_filtered_topic_name: "numbers_filtered".to_string(),
+      related_topic_name: "numbers".to_string(),
+      filter_class_name: class_name.to_string(),
+      filter_expression: String::new(),
+      expression_parameters: parameters.iter().map(|p| p.to_string()).collect(),
+    }
+  }
+
+  #[test]
+  fn writer_applies_filters_of_matched_readers() {
+    let matched = Arc::new(MatchedReaderFilters::default());
+    let filters = WriterContentFilters::new(Arc::clone(&matched), |_, _| None);
+    let low = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let high = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let other_class = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_BUILT_IN);
+
+    matched.update(low, Some(&property("Threshold", &["10"])));
+    matched.update(high, Some(&property("Threshold", &["100"])));
+    matched.update(other_class, Some(&property("DDSSQL", &["1"])));
+    // Nothing is filtered without factories
+    assert!(filters.readers_filtering_out(&5).is_empty());
+
+    filters.register(threshold_factory());
+    assert_eq!(filters.readers_filtering_out(&50), vec![high]);
+    let mut both = filters.readers_filtering_out(&5);
+    both.sort();
+    let mut expected = vec![low, high];
+    expected.sort();
+    assert_eq!(both, expected);
+
+    // A changed filter is created again, and a lost reader is forgotten
+    matched.update(high, Some(&property("Threshold", &["1"])));
+    matched.remove(low);
+    assert!(filters.readers_filtering_out(&5).is_empty());
+
+    // A filter that cannot be created filters out nothing
+    matched.update(high, Some(&property("Threshold", &["many"])));
+    assert!(filters.readers_filtering_out(&0).is_empty());
+  }
+
+  #[test]
+  fn writer_applies_ddssql_filters() {
+    #[derive(Serialize)]
+    struct Reading {
+      value: i32,
+    }
+
+    let matched = Arc::new(MatchedReaderFilters::default());
+    // As with a serializer adapter of Serialize types
+    let filters = WriterContentFilters::new(Arc::clone(&matched), |sample: &Reading, filter| {
+      Some(filter.matches(sample))
+    });
+    let reader = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let broken = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+
+    let mut sql = property(DDSSQL_FILTER_CLASS_NAME, &["10"]);
+    sql.filter_expression = "value > %0".to_string();
+    matched.update(reader, Some(&sql));
+    // An expression that does not parse filters out nothing
+    let mut unparsable = property(DDSSQL_FILTER_CLASS_NAME, &[]);
+    unparsable.filter_expression = "value >".to_string();
+    matched.update(broken, Some(&unparsable));
+
+    assert!(filters
+      .readers_filtering_out(&Reading { value: 11 })
+      .is_empty());
+    assert_eq!(
+      filters.readers_filtering_out(&Reading { value: 10 }),
+      vec![reader]
+    );
+  }
+
+  #[test]
+  fn content_filtered_topic_validates_expression() {
+    let dp = DomainParticipant::new(0).unwrap();
+    let topic = dp
+      .create_topic(
+        "numbers".to_string(),
+        "i32".to_string(),
+        &crate::QosPolicies::qos_none(),
+        crate::TopicKind::WithKey,
+      )
+      .unwrap();
+
+    let filtered = dp
+      .create_contentfilteredtopic("numbers_filtered", &topic, "a > %0", vec!["1".to_string()])
+      .unwrap();
+    assert_eq!(filtered.name(), "numbers_filtered");
+    assert_eq!(filtered.get_type().name(), "i32");
+    assert_eq!(filtered.related_topic().name(), "numbers");
+
+    // Missing parameter, and a syntax error
+    assert!(dp
+      .create_contentfilteredtopic("f", &topic, "a > %1", vec!["1".to_string()])
+      .is_err());
+    assert!(dp
+      .create_contentfilteredtopic("f", &topic, "a > AND", vec![])
+      .is_err());
+  }
+}
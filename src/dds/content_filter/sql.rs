@@ -0,0 +1,804 @@
+// The DDSSQL filter class: a subset of the SQL filter expressions of DDS Spec
+// v1.4 Annex B "Syntax for Queries and Filters".
+//
+// Supported are comparisons (=, <>, !=, <, <=, >, >=, LIKE) between fields
+// and literals or parameters (%0 - %99), [NOT] BETWEEN, and AND, OR, NOT and
+// parentheses. Fields are named by their Rust field names, and nested fields
+// with dots, e.g. "position.x". Enum fields compare equal to their variant
+// names.
+//
+// Samples are inspected through their serde Serialize implementation, so
+// filters work for any type that can be serialized, without generated code.
+
+use std::{borrow::Cow, cmp::Ordering, fmt};
+
+use serde::{ser, Serialize};
+
+// A sample, or part of it, as captured through serde
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+  Null,
+  Bool(bool),
+  Int(i128),
+  Float(f64),
+  Str(String),
+  Struct(Vec<(String, Value)>),
+  Seq(Vec<Value>),
+}
+
+impl Value {
+  fn field(&self, path: &[String]) -> Option<&Value> {
+    match path.split_first() {
+      None => Some(self),
+      Some((name, rest)) => match self {
+        Value::Struct(fields) => fields
+          .iter()
+          .find(|(field_name, _)| field_name == name)
+          .and_then(|(_, value)| value.field(rest)),
+        _ => None,
+      },
+    }
+  }
+
+  fn compare(&self, other: &Value) -> Option<Ordering> {
+    match (self, other) {
+      (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+      (Value::Int(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+      (Value::Float(a), Value::Int(b)) => a.partial_cmp(&(*b as f64)),
+      (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+      (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+      (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+      // A char field is captured as a string, but may be compared to a number
+      (Value::Str(a), b @ (Value::Int(_) | Value::Float(_))) => Self::number_literal(a)?.compare(b),
+      (a @ (Value::Int(_) | Value::Float(_)), Value::Str(b)) => {
+        a.compare(&Self::number_literal(b)?)
+      }
+      _ => None,
+    }
+  }
+
+  fn number_literal(text: &str) -> Option<Value> {
+    if let Ok(i) = text.parse::<i128>() {
+      return Some(Value::Int(i));
+    }
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+      return i128::from_str_radix(hex, 16).ok().map(Value::Int);
+    }
+    text.parse::<f64>().ok().map(Value::Float)
+  }
+
+  // An expression parameter, which is given as text
+  fn parameter(text: &str) -> Value {
+    let text = text.trim();
+    if let Some(quoted) = text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+      Value::Str(quoted.replace("''", "'"))
+    } else if text.eq_ignore_ascii_case("TRUE") {
+      Value::Bool(true)
+    } else if text.eq_ignore_ascii_case("FALSE") {
+      Value::Bool(false)
+    } else {
+      Self::number_literal(text).unwrap_or_else(|| Value::Str(text.to_string()))
+    }
+  }
+}
+
+// SQL LIKE: "%" matches any sequence of characters and "_" any one character.
+fn like(text: &str, pattern: &str) -> bool {
+  let text: Vec<char> = text.chars().collect();
+  let pattern: Vec<char> = pattern.chars().collect();
+  // matches[j]: does text[..i] match pattern[..j]
+  let mut matches = vec![false; pattern.len() + 1];
+  matches[0] = true;
+  for (j, p) in pattern.iter().enumerate() {
+    matches[j + 1] = matches[j] && *p == '%';
+  }
+  for c in text {
+    let mut previous_diagonal = matches[0];
+    matches[0] = false;
+    for (j, p) in pattern.iter().enumerate() {
+      let above = matches[j + 1];
+      matches[j + 1] = match p {
+        '%' => matches[j] || above,
+        '_' => previous_diagonal,
+        p => previous_diagonal && *p == c,
+      };
+      previous_diagonal = above;
+    }
+  }
+  matches[pattern.len()]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RelOp {
+  Eq,
+  Ne,
+  Lt,
+  Le,
+  Gt,
+  Ge,
+  Like,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+  Field(Vec<String>),
+  Literal(Value),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+  And(Box<Expr>, Box<Expr>),
+  Or(Box<Expr>, Box<Expr>),
+  Not(Box<Expr>),
+  Compare(Operand, RelOp, Operand),
+  Between(Operand, Operand, Operand),
+}
+
+impl Operand {
+  fn evaluate<'a>(&'a self, sample: &'a Value) -> Option<Cow<'a, Value>> {
+    match self {
+      Operand::Literal(value) => Some(Cow::Borrowed(value)),
+      Operand::Field(path) => match sample.field(path) {
+        Some(value) => Some(Cow::Borrowed(value)),
+        // An identifier that is not a field is an enumeration value
+        None if path.len() == 1 => Some(Cow::Owned(Value::Str(path[0].clone()))),
+        None => None,
+      },
+    }
+  }
+}
+
+impl Expr {
+  fn evaluate(&self, sample: &Value) -> bool {
+    match self {
+      Expr::And(a, b) => a.evaluate(sample) && b.evaluate(sample),
+      Expr::Or(a, b) => a.evaluate(sample) || b.evaluate(sample),
+      Expr::Not(a) => !a.evaluate(sample),
+      Expr::Compare(left, op, right) => {
+        let (Some(left), Some(right)) = (left.evaluate(sample), right.evaluate(sample)) else {
+          return false;
+        };
+        if *op == RelOp::Like {
+          return match (left.as_ref(), right.as_ref()) {
+            (Value::Str(text), Value::Str(pattern)) => like(text, pattern),
+            _ => false,
+          };
+        }
+        match left.compare(&right) {
+          Some(ordering) => match op {
+            RelOp::Eq => ordering == Ordering::Equal,
+            RelOp::Ne => ordering != Ordering::Equal,
+            RelOp::Lt => ordering == Ordering::Less,
+            RelOp::Le => ordering != Ordering::Greater,
+            RelOp::Gt => ordering == Ordering::Greater,
+            RelOp::Ge => ordering != Ordering::Less,
+            RelOp::Like => unreachable!(),
+          },
+          None => false,
+        }
+      }
+      Expr::Between(value, low, high) => {
+        let (Some(value), Some(low), Some(high)) = (
+          value.evaluate(sample),
+          low.evaluate(sample),
+          high.evaluate(sample),
+        ) else {
+          return false;
+        };
+        value.compare(&low).is_some_and(Ordering::is_ge)
+          && value.compare(&high).is_some_and(Ordering::is_le)
+      }
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Identifier(String),
+  Literal(Value),
+  Parameter(usize),
+  Op(RelOp),
+  And,
+  Or,
+  Not,
+  Between,
+  LeftParen,
+  RightParen,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+  let mut tokens = Vec::new();
+  let mut chars = expression.chars().peekable();
+  while let Some(&c) = chars.peek() {
+    if c.is_whitespace() {
+      chars.next();
+    } else if c == '(' || c == ')' {
+      chars.next();
+      tokens.push(if c == '(' {
+        Token::LeftParen
+      } else {
+        Token::RightParen
+      });
+    } else if c == '\'' || c == '`' {
+      // String literal. The closing quote is always "'", and a doubled "'"
+      // stands for one.
+      chars.next();
+      let mut string = String::new();
+      loop {
+        match chars.next() {
+          Some('\'') if chars.peek() == Some(&'\'') => {
+            chars.next();
+            string.push('\'');
+          }
+          Some('\'') => break,
+          Some(c) => string.push(c),
+          None => return Err("unterminated string literal".to_string()),
+        }
+      }
+      tokens.push(Token::Literal(Value::Str(string)));
+    } else if c == '%' {
+      chars.next();
+      let mut digits = String::new();
+      while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+        digits.push(*d);
+        chars.next();
+      }
+      let index = digits
+        .parse()
+        .map_err(|_| "a parameter must be % followed by its index".to_string())?;
+      tokens.push(Token::Parameter(index));
+    } else if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' {
+      let mut number = String::new();
+      while let Some(d) = chars
+        .peek()
+        .filter(|d| d.is_ascii_alphanumeric() || matches!(d, '.' | '-' | '+'))
+      {
+        // A sign is part of the number only at the start, or in an exponent
+        if matches!(d, '-' | '+')
+          && !(number.is_empty() || number.ends_with('e') || number.ends_with('E'))
+        {
+          break;
+        }
+        number.push(*d);
+        chars.next();
+      }
+      let value =
+        Value::number_literal(&number).ok_or_else(|| format!("invalid number {number:?}"))?;
+      tokens.push(Token::Literal(value));
+    } else if c.is_alphabetic() || c == '_' {
+      let mut word = String::new();
+      while let Some(d) = chars
+        .peek()
+        .filter(|d| d.is_alphanumeric() || matches!(d, '_' | '.'))
+      {
+        word.push(*d);
+        chars.next();
+      }
+      tokens.push(match word.to_ascii_uppercase().as_str() {
+        "AND" => Token::And,
+        "OR" => Token::Or,
+        "NOT" => Token::Not,
+        "BETWEEN" => Token::Between,
+        "LIKE" => Token::Op(RelOp::Like),
+        "TRUE" => Token::Literal(Value::Bool(true)),
+        "FALSE" => Token::Literal(Value::Bool(false)),
+        _ => Token::Identifier(word),
+      });
+    } else {
+      chars.next();
+      let op = match (c, chars.peek()) {
+        ('=', _) => RelOp::Eq,
+        ('<', Some('>')) | ('!', Some('=')) => {
+          chars.next();
+          RelOp::Ne
+        }
+        ('<', Some('=')) => {
+          chars.next();
+          RelOp::Le
+        }
+        ('>', Some('=')) => {
+          chars.next();
+          RelOp::Ge
+        }
+        ('<', _) => RelOp::Lt,
+        ('>', _) => RelOp::Gt,
+        _ => return Err(format!("unexpected character {c:?}")),
+      };
+      tokens.push(Token::Op(op));
+    }
+  }
+  Ok(tokens)
+}
+
+// Recursive descent parser. Precedence from lowest: OR, AND, NOT.
+struct Parser<'a> {
+  tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+  parameters: &'a [String],
+}
+
+impl<'a> Parser<'a> {
+  fn or(&mut self) -> Result<Expr, String> {
+    let mut expr = self.and()?;
+    while self.tokens.next_if_eq(&Token::Or).is_some() {
+      expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+    }
+    Ok(expr)
+  }
+
+  fn and(&mut self) -> Result<Expr, String> {
+    let mut expr = self.not()?;
+    while self.tokens.next_if_eq(&Token::And).is_some() {
+      expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+    }
+    Ok(expr)
+  }
+
+  fn not(&mut self) -> Result<Expr, String> {
+    if self.tokens.next_if_eq(&Token::Not).is_some() {
+      Ok(Expr::Not(Box::new(self.not()?)))
+    } else if self.tokens.next_if_eq(&Token::LeftParen).is_some() {
+      let expr = self.or()?;
+      match self.tokens.next() {
+        Some(Token::RightParen) => Ok(expr),
+        _ => Err("missing )".to_string()),
+      }
+    } else {
+      self.predicate()
+    }
+  }
+
+  fn predicate(&mut self) -> Result<Expr, String> {
+    let left = self.operand()?;
+    let negated = self.tokens.next_if_eq(&Token::Not).is_some();
+    let expr = match self.tokens.next() {
+      Some(Token::Between) => {
+        let low = self.operand()?;
+        if self.tokens.next() != Some(Token::And) {
+          return Err("BETWEEN needs AND".to_string());
+        }
+        Expr::Between(left, low, self.operand()?)
+      }
+      Some(Token::Op(op)) if !negated || op == RelOp::Like => {
+        Expr::Compare(left, op, self.operand()?)
+      }
+      other => return Err(format!("expected a comparison, found {other:?}")),
+    };
+    Ok(if negated {
+      Expr::Not(Box::new(expr))
+    } else {
+      expr
+    })
+  }
+
+  fn operand(&mut self) -> Result<Operand, String> {
+    match self.tokens.next() {
+      Some(Token::Identifier(name)) => Ok(Operand::Field(
+        name.split('.').map(str::to_string).collect(),
+      )),
+      Some(Token::Literal(value)) => Ok(Operand::Literal(value)),
+      Some(Token::Parameter(index)) => self
+        .parameters
+        .get(index)
+        .map(|p| Operand::Literal(Value::parameter(p)))
+        .ok_or_else(|| format!("parameter %{index} is not given")),
+      other => Err(format!(
+        "expected a field, literal or parameter, found {other:?}"
+      )),
+    }
+  }
+}
+
+/// A parsed DDSSQL filter expression with its parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SqlFilter {
+  expr: Expr,
+}
+
+impl SqlFilter {
+  pub fn parse(expression: &str, parameters: &[String]) -> Result<Self, String> {
+    let mut parser = Parser {
+      tokens: tokenize(expression)?.into_iter().peekable(),
+      parameters,
+    };
+    let expr = parser.or()?;
+    match parser.tokens.next() {
+      None => Ok(Self { expr }),
+      Some(token) => Err(format!("unexpected {token:?} at the end of the expression")),
+    }
+  }
+
+  pub fn matches<D: Serialize + ?Sized>(&self, sample: &D) -> bool {
+    match sample.serialize(ValueSerializer) {
+      Ok(value) => self.expr.evaluate(&value),
+      Err(_) => false,
+    }
+  }
+}
+
+// Capturing samples through serde
+
+#[derive(Debug)]
+struct CaptureError(String);
+
+impl fmt::Display for CaptureError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.0)
+  }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl ser::Error for CaptureError {
+  fn custom<T: fmt::Display>(msg: T) -> Self {
+    CaptureError(msg.to_string())
+  }
+}
+
+struct ValueSerializer;
+
+struct SeqCapture(Vec<Value>);
+
+struct StructCapture(Vec<(String, Value)>);
+
+struct MapCapture {
+  fields: Vec<(String, Value)>,
+  key: Option<String>,
+}
+
+impl ser::Serializer for ValueSerializer {
+  type Ok = Value;
+  type Error = CaptureError;
+  type SerializeSeq = SeqCapture;
+  type SerializeTuple = SeqCapture;
+  type SerializeTupleStruct = SeqCapture;
+  type SerializeTupleVariant = SeqCapture;
+  type SerializeMap = MapCapture;
+  type SerializeStruct = StructCapture;
+  type SerializeStructVariant = StructCapture;
+
+  fn serialize_bool(self, v: bool) -> Result<Value, CaptureError> {
+    Ok(Value::Bool(v))
+  }
+  fn serialize_i8(self, v: i8) -> Result<Value, CaptureError> {
+    Ok(Value::Int(v.into()))
+  }
+  fn serialize_i16(self, v: i16) -> Result<Value, CaptureError> {
+    Ok(Value::Int(v.into()))
+  }
+  fn serialize_i32(self, v: i32) -> Result<Value, CaptureError> {
+    Ok(Value::Int(v.into()))
+  }
+  fn serialize_i64(self, v: i64) -> Result<Value, CaptureError> {
+    Ok(Value::Int(v.into()))
+  }
+  fn serialize_i128(self, v: i128) -> Result<Value, CaptureError> {
+    Ok(Value::Int(v))
+  }
+  fn serialize_u8(self, v: u8) -> Result<Value, CaptureError> {
+    Ok(Value::Int(v.into()))
+  }
+  fn serialize_u16(self, v: u16) -> Result<Value, CaptureError> {
+    Ok(Value::Int(v.into()))
+  }
+  fn serialize_u32(self, v: u32) -> Result<Value, CaptureError> {
+    Ok(Value::Int(v.into()))
+  }
+  fn serialize_u64(self, v: u64) -> Result<Value, CaptureError> {
+    Ok(Value::Int(v.into()))
+  }
+  fn serialize_f32(self, v: f32) -> Result<Value, CaptureError> {
+    Ok(Value::Float(v.into()))
+  }
+  fn serialize_f64(self, v: f64) -> Result<Value, CaptureError> {
+    Ok(Value::Float(v))
+  }
+  fn serialize_char(self, v: char) -> Result<Value, CaptureError> {
+    Ok(Value::Str(v.to_string()))
+  }
+  fn serialize_str(self, v: &str) -> Result<Value, CaptureError> {
+    Ok(Value::Str(v.to_string()))
+  }
+  fn serialize_bytes(self, v: &[u8]) -> Result<Value, CaptureError> {
+    Ok(Value::Seq(
+      v.iter().map(|b| Value::Int((*b).into())).collect(),
+    ))
+  }
+  fn serialize_none(self) -> Result<Value, CaptureError> {
+    Ok(Value::Null)
+  }
+  fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, CaptureError> {
+    value.serialize(self)
+  }
+  fn serialize_unit(self) -> Result<Value, CaptureError> {
+    Ok(Value::Null)
+  }
+  fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, CaptureError> {
+    Ok(Value::Null)
+  }
+  fn serialize_unit_variant(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    variant: &'static str,
+  ) -> Result<Value, CaptureError> {
+    Ok(Value::Str(variant.to_string()))
+  }
+  fn serialize_newtype_struct<T: Serialize + ?Sized>(
+    self,
+    _name: &'static str,
+    value: &T,
+  ) -> Result<Value, CaptureError> {
+    value.serialize(self)
+  }
+  fn serialize_newtype_variant<T: Serialize + ?Sized>(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    _variant: &'static str,
+    value: &T,
+  ) -> Result<Value, CaptureError> {
+    value.serialize(self)
+  }
+  fn serialize_seq(self, len: Option<usize>) -> Result<SeqCapture, CaptureError> {
+    Ok(SeqCapture(Vec::with_capacity(len.unwrap_or(0))))
+  }
+  fn serialize_tuple(self, len: usize) -> Result<SeqCapture, CaptureError> {
+    Ok(SeqCapture(Vec::with_capacity(len)))
+  }
+  fn serialize_tuple_struct(
+    self,
+    _name: &'static str,
+    len: usize,
+  ) -> Result<SeqCapture, CaptureError> {
+    Ok(SeqCapture(Vec::with_capacity(len)))
+  }
+  fn serialize_tuple_variant(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    _variant: &'static str,
+    len: usize,
+  ) -> Result<SeqCapture, CaptureError> {
+    Ok(SeqCapture(Vec::with_capacity(len)))
+  }
+  fn serialize_map(self, _len: Option<usize>) -> Result<MapCapture, CaptureError> {
+    Ok(MapCapture {
+      fields: Vec::new(),
+      key: None,
+    })
+  }
+  fn serialize_struct(
+    self,
+    _name: &'static str,
+    len: usize,
+  ) -> Result<StructCapture, CaptureError> {
+    Ok(StructCapture(Vec::with_capacity(len)))
+  }
+  fn serialize_struct_variant(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    _variant: &'static str,
+    len: usize,
+  ) -> Result<StructCapture, CaptureError> {
+    Ok(StructCapture(Vec::with_capacity(len)))
+  }
+}
+
+impl SeqCapture {
+  fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CaptureError> {
+    self.0.push(value.serialize(ValueSerializer)?);
+    Ok(())
+  }
+}
+
+impl ser::SerializeSeq for SeqCapture {
+  type Ok = Value;
+  type Error = CaptureError;
+  fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CaptureError> {
+    self.push(value)
+  }
+  fn end(self) -> Result<Value, CaptureError> {
+    Ok(Value::Seq(self.0))
+  }
+}
+
+impl ser::SerializeTuple for SeqCapture {
+  type Ok = Value;
+  type Error = CaptureError;
+  fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CaptureError> {
+    self.push(value)
+  }
+  fn end(self) -> Result<Value, CaptureError> {
+    Ok(Value::Seq(self.0))
+  }
+}
+
+impl ser::SerializeTupleStruct for SeqCapture {
+  type Ok = Value;
+  type Error = CaptureError;
+  fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CaptureError> {
+    self.push(value)
+  }
+  fn end(self) -> Result<Value, CaptureError> {
+    Ok(Value::Seq(self.0))
+  }
+}
+
+impl ser::SerializeTupleVariant for SeqCapture {
+  type Ok = Value;
+  type Error = CaptureError;
+  fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CaptureError> {
+    self.push(value)
+  }
+  fn end(self) -> Result<Value, CaptureError> {
+    Ok(Value::Seq(self.0))
+  }
+}
+
+impl ser::SerializeStruct for StructCapture {
+  type Ok = Value;
+  type Error = CaptureError;
+  fn serialize_field<T: Serialize + ?Sized>(
+    &mut self,
+    key: &'static str,
+    value: &T,
+  ) -> Result<(), CaptureError> {
+    self
+      .0
+      .push((key.to_string(), value.serialize(ValueSerializer)?));
+    Ok(())
+  }
+  fn end(self) -> Result<Value, CaptureError> {
+    Ok(Value::Struct(self.0))
+  }
+}
+
+impl ser::SerializeStructVariant for StructCapture {
+  type Ok = Value;
+  type Error = CaptureError;
+  fn serialize_field<T: Serialize + ?Sized>(
+    &mut self,
+    key: &'static str,
+    value: &T,
+  ) -> Result<(), CaptureError> {
+    ser::SerializeStruct::serialize_field(self, key, value)
+  }
+  fn end(self) -> Result<Value, CaptureError> {
+    Ok(Value::Struct(self.0))
+  }
+}
+
+// Maps are captured like structs, so that map entries can be named as fields
+impl ser::SerializeMap for MapCapture {
+  type Ok = Value;
+  type Error = CaptureError;
+  fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), CaptureError> {
+    self.key = Some(match key.serialize(ValueSerializer)? {
+      Value::Str(s) => s,
+      Value::Int(i) => i.to_string(),
+      Value::Bool(b) => b.to_string(),
+      other => format!("{other:?}"),
+    });
+    Ok(())
+  }
+  fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CaptureError> {
+    let key = self
+      .key
+      .take()
+      .ok_or_else(|| CaptureError("map value without key".to_string()))?;
+    self.fields.push((key, value.serialize(ValueSerializer)?));
+    Ok(())
+  }
+  fn end(self) -> Result<Value, CaptureError> {
+    Ok(Value::Struct(self.fields))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde::Serialize;
+
+  use super::*;
+
+  #[derive(Serialize)]
+  enum Color {
+    Red,
+    Blue,
+  }
+
+  #[derive(Serialize)]
+  struct Position {
+    x: i32,
+    y: f64,
+  }
+
+  #[derive(Serialize)]
+  struct Shape {
+    name: String,
+    value: u32,
+    color: Color,
+    position: Position,
+    visible: bool,
+  }
+
+  fn shape(name: &str, value: u32, color: Color) -> Shape {
+    Shape {
+      name: name.to_string(),
+      value,
+      color,
+      position: Position { x: -3, y: 2.5 },
+      visible: true,
+    }
+  }
+
+  fn matches(expression: &str, parameters: &[&str], sample: &Shape) -> bool {
+    let parameters: Vec<String> = parameters.iter().map(|p| p.to_string()).collect();
+    SqlFilter::parse(expression, &parameters)
+      .unwrap()
+      .matches(sample)
+  }
+
+  #[test]
+  fn comparisons_and_parameters() {
+    let square = shape("Square", 10, Color::Red);
+    assert!(matches(
+      "value > %0 AND name LIKE %1",
+      &["5", "'Sq%'"],
+      &square
+    ));
+    assert!(!matches(
+      "value > %0 AND name LIKE %1",
+      &["10", "'Sq%'"],
+      &square
+    ));
+    assert!(matches("value >= 10 AND value <= 10", &[], &square));
+    assert!(matches("value <> 11 and value != 12", &[], &square));
+    assert!(matches("name = 'Square' OR value < 0", &[], &square));
+    assert!(matches("name LIKE 'S_uare'", &[], &square));
+    assert!(!matches("name NOT LIKE 'S%'", &[], &square));
+    assert!(matches("NOT (value < 5 OR name = 'Circle')", &[], &square));
+    assert!(matches("value BETWEEN 5 AND %0", &["20"], &square));
+    assert!(matches("value NOT BETWEEN 11 AND 20", &[], &square));
+  }
+
+  #[test]
+  fn nested_fields_enums_and_booleans() {
+    let circle = shape("Circle", 1, Color::Blue);
+    assert!(matches("position.x < 0 AND position.y > 2", &[], &circle));
+    assert!(matches("color = Blue", &[], &circle));
+    assert!(matches("color = %0", &["Blue"], &circle));
+    assert!(!matches("color = 'Red'", &[], &circle));
+    assert!(matches("visible = TRUE", &[], &circle));
+    // Missing nested fields never match
+    assert!(!matches("position.z = 0", &[], &circle));
+  }
+
+  #[test]
+  fn like_wildcards() {
+    assert!(like("", "%"));
+    assert!(like("abc", "a%"));
+    assert!(like("abc", "%c"));
+    assert!(like("abc", "a_c"));
+    assert!(!like("abc", "a_"));
+    assert!(like("a%c", "a%%c"));
+  }
+
+  #[test]
+  fn syntax_errors() {
+    for expression in [
+      "value >",
+      "value > 1 AND",
+      "(value > 1",
+      "value > %3",
+      "value BETWEEN 1 OR 2",
+      "name = 'unterminated",
+      "value > 1 value",
+    ] {
+      assert!(
+        SqlFilter::parse(expression, &[]).is_err(),
+        "{expression} was accepted"
+      );
+    }
+  }
+}
@@ -3,7 +3,8 @@ use std::cmp::min;
 use bytes::Bytes;
 
 use crate::{
-  dds::key::KeyHash, messages::submessages::elements::serialized_payload::SerializedPayload,
+  dds::{intra_process::LocalSample, key::KeyHash},
+  messages::submessages::elements::serialized_payload::SerializedPayload,
   structure::cache_change::ChangeKind,
 };
 
@@ -12,7 +13,8 @@ use crate::{
 #[derive(Debug, PartialEq, Eq, Clone)]
 // Contents of a DATA submessage or several DATAFRAG submessages. This is either
 // a new sample, or key, or a key hash. The latter two are used to indicate
-// dispose or unregister.
+// dispose or unregister. A sample written for intra-process delivery is kept
+// unserialized, until it must be sent out.
 pub enum DDSData {
   Data {
     serialized_payload: SerializedPayload,
@@ -25,6 +27,11 @@ pub enum DDSData {
     change_kind: ChangeKind,
     key_hash: KeyHash,
   },
+  // DDSData is not reachable from outside the crate, though it is `pub`.
+  #[allow(private_interfaces)]
+  Local {
+    sample: LocalSample,
+  },
 }
 
 impl DDSData {
@@ -44,7 +51,7 @@ impl DDSData {
 
   pub fn change_kind(&self) -> ChangeKind {
     match self {
-      DDSData::Data {..} /*| DDSData::DataFrags {..}*/ => ChangeKind::Alive,
+      DDSData::Data {..} | DDSData::Local {..} /*| DDSData::DataFrags {..}*/ => ChangeKind::Alive,
       DDSData::DisposeByKey { change_kind, ..} | DDSData::DisposeByKeyHash { change_kind, .. }  => *change_kind,
     }
   }
@@ -57,6 +64,17 @@ impl DDSData {
       DDSData::DisposeByKeyHash { .. } => 16,
       // This is a fundamental constant of the RTPS
       // specification v2.5 Section 9.6.4.8 KeyHash (PID_KEY_HASH)
+      DDSData::Local { sample } => sample.serialized_size(),
+    }
+  }
+
+  // Replaces a local sample with its serialized form, so that it can be sent
+  // out. If serialization fails, the sample is kept as it is.
+  pub(crate) fn serialize_local(&mut self) {
+    if let DDSData::Local { sample } = self {
+      if let Some(serialized_payload) = sample.serialized_payload() {
+        *self = DDSData::Data { serialized_payload };
+      }
     }
   }
 
@@ -64,7 +82,7 @@ impl DDSData {
     match self {
       DDSData::Data { serialized_payload } => serialized_payload.reallocate(),
       DDSData::DisposeByKey { key, .. } => key.reallocate(),
-      DDSData::DisposeByKeyHash { .. } => {}
+      DDSData::DisposeByKeyHash { .. } | DDSData::Local { .. } => {}
     }
   }
 
@@ -79,6 +97,9 @@ impl DDSData {
       DDSData::Data { serialized_payload } => serialized_payload.value.clone(),
       DDSData::DisposeByKey { key, .. } => key.value.clone(),
       DDSData::DisposeByKeyHash { key_hash, .. } => Bytes::from(key_hash.to_vec()),
+      DDSData::Local { sample } => sample
+        .serialized_payload()
+        .map_or_else(Bytes::new, |p| p.value),
     }
   }
 
@@ -97,6 +118,9 @@ impl DDSData {
         let start = min(from, end);
         Bytes::from(hash_vec).slice(start..end)
       }
+      DDSData::Local { sample } => sample
+        .serialized_payload()
+        .map_or_else(Bytes::new, |p| p.bytes_slice(from, to)),
     }
   }
 }
@@ -0,0 +1,80 @@
+//! Damping of remote endpoint changes, and protection against discovery
+//! storms.
+//!
+//! Each change of a remote endpoint received in SEDP discovery, i.e. a new,
+//! updated or disposed DataReader or DataWriter, makes the participant match
+//! the endpoint again with its local endpoints. A remote endpoint that flaps,
+//! e.g. updates its QoS over and over, or is disposed and created again in a
+//! loop, and a remote participant that floods SEDP with changes, keep
+//! discovery and the event loop of the participant busy with rematching.
+//!
+//! With
+//! [`DomainParticipantBuilder::discovery_damping`](crate::DomainParticipantBuilder::discovery_damping):
+//! * A change of an endpoint that has changed within the
+//!   [`settle_time`](DiscoveryDampingSettings::settle_time) is held, until
+//!   the endpoint has stayed unchanged for the settle time. Only the latest
+//!   held change of the endpoint is then applied, so that all the changes
+//!   made meanwhile cost a single rematching. A change of an endpoint that has
+//!   been quiet is applied at once, so that normal discovery is not delayed.
+//! * Changes from each remote participant are applied at most at the rate of
+//!   [`max_changes_per_second`](DiscoveryDampingSettings::max_changes_per_second),
+//!   after a burst of
+//!   [`max_burst`](DiscoveryDampingSettings::max_burst) changes. Changes
+//!   beyond that are held, and applied in the order they arrived as the rate
+//!   allows. Held changes of the same endpoint are coalesced as above.
+//!
+//! Changes are delayed and coalesced, but not dropped, so that the
+//! participant ends up with the latest state of every remote endpoint. Held
+//! changes are discarded when their participant is lost. Changes received in
+//! secure discovery (DDS Security) come from authenticated participants, and
+//! are applied as they arrive.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use rustdds::{DiscoveryDampingSettings, DomainParticipantBuilder};
+//!
+//! let damping = DiscoveryDampingSettings {
+//!   settle_time: Duration::from_secs(1),
+//!   max_changes_per_second: Some(50),
+//!   ..DiscoveryDampingSettings::default()
+//! };
+//! let builder = DomainParticipantBuilder::new(0).discovery_damping(damping);
+//! ```
+
+use std::time::Duration;
+
+/// How a DomainParticipant damps the changes of remote endpoints. See the
+/// [module documentation](self).
+///
+/// Set with
+/// [`DomainParticipantBuilder::discovery_damping`](crate::DomainParticipantBuilder::discovery_damping).
+/// The default applies every change as it arrives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiscoveryDampingSettings {
+  /// How long a remote endpoint must stay unchanged before a further change
+  /// of it is applied. Zero applies changes at once.
+  pub settle_time: Duration,
+  /// Most endpoint changes per second applied from a single remote
+  /// participant. `None` is unlimited.
+  pub max_changes_per_second: Option<u32>,
+  /// Changes from a single remote participant that are applied at once,
+  /// before the rate limit takes effect. A newly discovered participant
+  /// announces all its endpoints at once, so this should cover the endpoints
+  /// of a typical participant.
+  pub max_burst: u32,
+}
+
+impl DiscoveryDampingSettings {
+  pub const DEFAULT_MAX_BURST: u32 = 256;
+}
+
+impl Default for DiscoveryDampingSettings {
+  fn default() -> Self {
+    Self {
+      settle_time: Duration::ZERO,
+      max_changes_per_second: None,
+      max_burst: Self::DEFAULT_MAX_BURST,
+    }
+  }
+}
@@ -0,0 +1,419 @@
+//! Network settings of participant discovery.
+//!
+//! By default, participants find each other by sending SPDP announcements to
+//! the multicast group 239.255.0.1, as specified by RTPS. Some networks need
+//! something else:
+//!
+//! * Containers and virtual networks may not forward multicast at all. Then
+//!   multicast can be disabled, and announcements are sent by unicast to a list
+//!   of initial peers instead.
+//! * Routed networks need a multicast TTL greater than 1 for announcements to
+//!   cross routers.
+//! * Some switches handle only specific multicast groups.
+//! * In a large system, a participant may be interested in only a few of the
+//!   topics. A [`DiscoveryTopicFilter`] restricts endpoint discovery to them.
+//!
+//! Participants use IPv4 by default. With [`IpStack::V6`] they use IPv6
+//! instead, and with [`IpStack::DualStack`] both: each listening socket has
+//! an IPv6 twin, and locators of both versions are advertised. SPDP
+//! announcements go to
+//! [`spdp_multicast_address_v6`](DiscoveryNetworkSettings::spdp_multicast_address_v6)
+//! over IPv6, which by default is the link-local group FF02::FFFF:EFFF:1,
+//! i.e. 239.255.0.1 in the last 32 bits. A remote participant that
+//! advertises unicast locators of both versions is sent to by one version
+//! only, chosen with `prefer_v6`. Link-local IPv6 addresses are not
+//! advertised, as locators cannot tell their interface. The TCP transport
+//! uses IPv4 only.
+//!
+//! By default, all local interfaces are used. An
+//! [`interface_allowlist`](DiscoveryNetworkSettings::interface_allowlist)
+//! restricts the participant to some of them, given by name, such as
+//! `"eth0"`, or by network, such as `"10.0.0.0/8"`. Only addresses of the
+//! selected interfaces are advertised as locators, and multicast is sent and
+//! received on the selected interfaces only. The listening sockets are still
+//! bound to the unspecified address. The list is applied again whenever
+//! local addresses change, so an interface that appears later is taken into
+//! use, if it is selected.
+//!
+//! Hosts that become known only at run time can be probed with
+//! [`DomainParticipant::send_discovery_probe`](crate::DomainParticipant::send_discovery_probe),
+//! which adds them to the initial peers and announces the participant to them
+//! at once. Peers can also be looked up periodically from a name service,
+//! such as DNS or DNS-SD. See [`peer_resolution`](crate::peer_resolution).
+//!
+//! ```
+//! use std::net::Ipv4Addr;
+//!
+//! use rustdds::{DiscoveryNetworkSettings, DomainParticipantBuilder};
+//!
+//! let settings = DiscoveryNetworkSettings::unicast_only(vec![Ipv4Addr::new(10, 0, 0, 2).into()]);
+//! let builder = DomainParticipantBuilder::new(0).discovery_network(settings);
+//! ```
+//!
+//! ```
+//! use rustdds::{DiscoveryNetworkSettings, DomainParticipantBuilder};
+//!
+//! let settings = DiscoveryNetworkSettings {
+//!   interface_allowlist: vec!["eth0".parse().unwrap(), "10.0.0.0/8".parse().unwrap()],
+//!   ..DiscoveryNetworkSettings::default()
+//! };
+//! let builder = DomainParticipantBuilder::new(0).discovery_network(settings);
+//! ```
+
+use std::{
+  net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+  str::FromStr,
+};
+
+use crate::{
+  dds::topic_namespace::DiscoveryTopicFilter, network::constant::spdp_well_known_unicast_port,
+  structure::locator::Locator,
+};
+
+/// How a DomainParticipant sends and receives discovery traffic. See the
+/// [module documentation](self).
+///
+/// Set with
+/// [`DomainParticipantBuilder::discovery_network`](crate::DomainParticipantBuilder::discovery_network).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveryNetworkSettings {
+  /// Whether multicast is used at all. If false, no multicast locators are
+  /// advertised or listened to, and nothing is sent to multicast locators of
+  /// remote participants.
+  pub multicast_enabled: bool,
+  /// IP versions used for discovery and user traffic
+  pub ip_stack: IpStack,
+  /// Local interfaces used for discovery and user traffic. An address is used
+  /// if any of the selectors matches it. If empty, all interfaces are used.
+  /// [`TrafficIsolationSettings`](crate::TrafficIsolationSettings) may
+  /// replace this for either class of traffic.
+  pub interface_allowlist: Vec<InterfaceSelector>,
+  /// Multicast group of SPDP announcements.
+  pub spdp_multicast_address: Ipv4Addr,
+  /// Multicast group of SPDP announcements over IPv6
+  pub spdp_multicast_address_v6: Ipv6Addr,
+  /// Time-to-live of sent multicast datagrams, i.e. how many routers they may
+  /// cross.
+  pub multicast_ttl: u32,
+  /// Whether sent multicast datagrams are looped back to this host. This must
+  /// be on for participants on the same host to discover each other by
+  /// multicast.
+  pub multicast_loopback: bool,
+  /// Hosts that SPDP announcements are sent to by unicast, in addition to
+  /// multicast.
+  pub initial_peers: Vec<IpAddr>,
+  /// Announcements are sent to the well-known unicast ports of participant ids
+  /// from 0 up to and including this one on each initial peer.
+  pub initial_peer_max_participant_id: u16,
+  /// Whether a received RTPS PING makes this participant send its SPDP
+  /// announcement at once, rather than at the next periodic announcement.
+  /// Some implementations, e.g. RTI Connext, send these probes to the hosts
+  /// they are configured to discover.
+  pub answer_discovery_probes: bool,
+  /// If set, SEDP announcements of remote readers, writers and topics are
+  /// processed only for the topics that the filter allows, and the others are
+  /// dropped on arrival. Local readers and writers of other topics are not
+  /// announced, so they do not match any remote ones.
+  pub topic_filter: Option<DiscoveryTopicFilter>,
+}
+
+impl DiscoveryNetworkSettings {
+  pub const DEFAULT_SPDP_MULTICAST_ADDRESS: Ipv4Addr = Ipv4Addr::new(239, 255, 0, 1);
+  /// Link-local scope, with the IPv4 default group in the last 32 bits. This
+  /// is also the IPv6 group of user traffic.
+  pub const DEFAULT_SPDP_MULTICAST_ADDRESS_V6: Ipv6Addr =
+    Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0xffff, 0xefff, 0x0001);
+  pub const DEFAULT_INITIAL_PEER_MAX_PARTICIPANT_ID: u16 = 4;
+
+  /// Discovery by unicast to the given hosts only.
+  pub fn unicast_only(initial_peers: Vec<IpAddr>) -> Self {
+    Self {
+      multicast_enabled: false,
+      initial_peers,
+      ..Self::default()
+    }
+  }
+
+  // Locators of the SPDP readers that participants on the initial peers
+  // would have.
+  pub(crate) fn initial_peer_locators(&self, domain_id: u16) -> Vec<Locator> {
+    spdp_peer_locators(
+      &self.initial_peers,
+      self.initial_peer_max_participant_id,
+      domain_id,
+    )
+  }
+}
+
+// Locators of the SPDP readers that participants with ids up to and including
+// `max_participant_id` would have on the given hosts.
+pub(crate) fn spdp_peer_locators(
+  peers: &[IpAddr],
+  max_participant_id: u16,
+  domain_id: u16,
+) -> Vec<Locator> {
+  peers
+    .iter()
+    .flat_map(|ip| {
+      (0..=max_participant_id).map(move |participant_id| {
+        Locator::from(SocketAddr::new(
+          *ip,
+          spdp_well_known_unicast_port(domain_id, participant_id),
+        ))
+      })
+    })
+    .collect()
+}
+
+impl Default for DiscoveryNetworkSettings {
+  fn default() -> Self {
+    Self {
+      multicast_enabled: true,
+      ip_stack: IpStack::default(),
+      interface_allowlist: Vec::new(),
+      spdp_multicast_address: Self::DEFAULT_SPDP_MULTICAST_ADDRESS,
+      spdp_multicast_address_v6: Self::DEFAULT_SPDP_MULTICAST_ADDRESS_V6,
+      multicast_ttl: 1,
+      multicast_loopback: true,
+      initial_peers: Vec::new(),
+      initial_peer_max_participant_id: Self::DEFAULT_INITIAL_PEER_MAX_PARTICIPANT_ID,
+      answer_discovery_probes: true,
+      topic_filter: None,
+    }
+  }
+}
+
+/// IP versions that a DomainParticipant uses for UDP. See the
+/// [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IpStack {
+  #[default]
+  V4,
+  V6,
+  /// Both versions. A remote participant that advertises unicast locators of
+  /// both is sent to by IPv6, if `prefer_v6` is set, and by IPv4 otherwise.
+  DualStack {
+    prefer_v6: bool,
+  },
+}
+
+impl IpStack {
+  pub fn uses_v4(self) -> bool {
+    matches!(self, Self::V4 | Self::DualStack { .. })
+  }
+
+  pub fn uses_v6(self) -> bool {
+    matches!(self, Self::V6 | Self::DualStack { .. })
+  }
+
+  // Drops the UDP locators of a remote participant or endpoint that this
+  // stack cannot send to. Of unicast locators of both versions, only those of
+  // the preferred version are kept, so that the peer does not get everything
+  // twice. Multicast locators of both versions are kept, as they reach
+  // different sets of participants.
+  pub(crate) fn select_locators(self, unicast: &mut Vec<Locator>, multicast: &mut Vec<Locator>) {
+    let usable = |locator: &Locator| match locator {
+      Locator::UdpV4(_) => self.uses_v4(),
+      Locator::UdpV6(_) => self.uses_v6(),
+      _ => true,
+    };
+    unicast.retain(usable);
+    multicast.retain(usable);
+
+    if let Self::DualStack { prefer_v6 } = self {
+      let has_v4 = unicast.iter().any(|l| matches!(l, Locator::UdpV4(_)));
+      let has_v6 = unicast.iter().any(|l| matches!(l, Locator::UdpV6(_)));
+      if has_v4 && has_v6 {
+        unicast.retain(|l| match l {
+          Locator::UdpV4(_) => !prefer_v6,
+          Locator::UdpV6(_) => prefer_v6,
+          _ => true,
+        });
+      }
+    }
+  }
+}
+
+/// Local network interfaces that a DomainParticipant may use. See
+/// [`DiscoveryNetworkSettings::interface_allowlist`].
+///
+/// Parsed with [`str::parse`] from an address with a prefix length, such as
+/// `"10.0.0.0/8"` or `"fd00::/8"`, or a single address, such as
+/// `"192.168.1.7"`. Any other string is an interface name, such as `"eth0"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterfaceSelector {
+  /// All addresses of the interface of this name
+  Name(String),
+  /// Local addresses in this network
+  Network { address: IpAddr, prefix_len: u8 },
+}
+
+impl InterfaceSelector {
+  // Whether the address `ip` of the interface `interface_name` is selected
+  pub(crate) fn matches(&self, interface_name: &str, ip: IpAddr) -> bool {
+    match self {
+      Self::Name(name) => name == interface_name,
+      Self::Network {
+        address,
+        prefix_len,
+      } => {
+        let (network, ip, bits) = match (address, ip) {
+          (IpAddr::V4(network), IpAddr::V4(ip)) => (
+            u128::from(u32::from(*network)),
+            u128::from(u32::from(ip)),
+            32,
+          ),
+          (IpAddr::V6(network), IpAddr::V6(ip)) => (u128::from(*network), u128::from(ip), 128),
+          _ => return false,
+        };
+        let host_bits = bits - u32::from(*prefix_len).min(bits);
+        (network ^ ip).checked_shr(host_bits).unwrap_or(0) == 0
+      }
+    }
+  }
+}
+
+// Whether an allowlist lets the address `ip` of the interface
+// `interface_name` be used
+pub(crate) fn interface_allowed(
+  allowlist: &[InterfaceSelector],
+  interface_name: &str,
+  ip: IpAddr,
+) -> bool {
+  allowlist.is_empty()
+    || allowlist
+      .iter()
+      .any(|selector| selector.matches(interface_name, ip))
+}
+
+impl FromStr for InterfaceSelector {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, String> {
+    let (address, prefix_len) = match s.split_once('/') {
+      Some((address, prefix_len)) => (address, Some(prefix_len)),
+      None => (s, None),
+    };
+    let address = match (address.parse::<IpAddr>(), prefix_len) {
+      (Ok(address), _) => address,
+      (Err(_), Some(_)) => return Err(format!("Invalid network address in {s:?}")),
+      (Err(_), None) if s.is_empty() => return Err("Empty interface name".to_string()),
+      (Err(_), None) => return Ok(Self::Name(s.to_string())),
+    };
+    let max_prefix_len = if address.is_ipv4() { 32 } else { 128 };
+    let prefix_len = match prefix_len {
+      None => max_prefix_len,
+      Some(prefix_len) => prefix_len
+        .parse::<u8>()
+        .ok()
+        .filter(|len| *len <= max_prefix_len)
+        .ok_or_else(|| format!("Invalid prefix length in {s:?}"))?,
+    };
+    Ok(Self::Network {
+      address,
+      prefix_len,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn initial_peer_locators_cover_participant_ids() {
+    let settings = DiscoveryNetworkSettings {
+      initial_peer_max_participant_id: 1,
+      ..DiscoveryNetworkSettings::unicast_only(vec![
+        Ipv4Addr::new(10, 0, 0, 2).into(),
+        Ipv4Addr::new(10, 0, 0, 3).into(),
+      ])
+    };
+    assert!(!settings.multicast_enabled);
+
+    let locators = settings.initial_peer_locators(1);
+    let expected: Vec<Locator> = [
+      ([10, 0, 0, 2], 7660),
+      ([10, 0, 0, 2], 7662),
+      ([10, 0, 0, 3], 7660),
+      ([10, 0, 0, 3], 7662),
+    ]
+    .into_iter()
+    .map(|(ip, port)| Locator::from(SocketAddr::new(Ipv4Addr::from(ip).into(), port)))
+    .collect();
+    assert_eq!(locators, expected);
+  }
+
+  #[test]
+  fn locators_are_selected_per_peer() {
+    let v4 = Locator::from(SocketAddr::new(Ipv4Addr::new(10, 0, 0, 2).into(), 7411));
+    let v6 = Locator::from(SocketAddr::new(
+      Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 2).into(),
+      7411,
+    ));
+    let v4_group = Locator::from(SocketAddr::new(
+      DiscoveryNetworkSettings::DEFAULT_SPDP_MULTICAST_ADDRESS.into(),
+      7401,
+    ));
+    let v6_group = Locator::from(SocketAddr::new(
+      DiscoveryNetworkSettings::DEFAULT_SPDP_MULTICAST_ADDRESS_V6.into(),
+      7401,
+    ));
+    let tcp = Locator::tcp(SocketAddr::new(Ipv4Addr::new(10, 0, 0, 2).into(), 7411));
+
+    let select = |stack: IpStack, unicast: &[Locator]| {
+      let mut unicast = unicast.to_vec();
+      let mut multicast = vec![v4_group, v6_group];
+      stack.select_locators(&mut unicast, &mut multicast);
+      (unicast, multicast)
+    };
+
+    assert_eq!(
+      select(IpStack::V4, &[v4, v6, tcp]),
+      (vec![v4, tcp], vec![v4_group])
+    );
+    assert_eq!(select(IpStack::V6, &[v4, v6]), (vec![v6], vec![v6_group]));
+    let dual = IpStack::DualStack { prefer_v6: true };
+    assert_eq!(
+      select(dual, &[v4, v6]),
+      (vec![v6], vec![v4_group, v6_group])
+    );
+    // A peer with IPv4 locators only is reached by IPv4
+    assert_eq!(select(dual, &[v4]).0, vec![v4]);
+    let dual = IpStack::DualStack { prefer_v6: false };
+    assert_eq!(select(dual, &[v4, v6]).0, vec![v4]);
+  }
+
+  #[test]
+  fn interface_selectors_parse_and_match() {
+    let parse = |s: &str| s.parse::<InterfaceSelector>();
+    let ten = IpAddr::from(Ipv4Addr::new(10, 1, 2, 3));
+    let other = IpAddr::from(Ipv4Addr::new(192, 168, 1, 7));
+    let ula = IpAddr::from(Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 2));
+
+    let eth0 = parse("eth0").unwrap();
+    assert_eq!(eth0, InterfaceSelector::Name("eth0".to_string()));
+    assert!(eth0.matches("eth0", other));
+    assert!(!eth0.matches("eth1", ten));
+
+    let network = parse("10.0.0.0/8").unwrap();
+    assert!(network.matches("eth1", ten));
+    assert!(!network.matches("eth0", other));
+    assert!(!network.matches("eth1", ula));
+    assert!(parse("fd00::/8").unwrap().matches("eth1", ula));
+    assert!(parse("0.0.0.0/0").unwrap().matches("eth1", other));
+    assert!(parse("::/0").unwrap().matches("eth1", ula));
+
+    let single = parse("192.168.1.7").unwrap();
+    assert!(single.matches("eth0", other));
+    assert!(!single.matches("eth0", IpAddr::from(Ipv4Addr::new(192, 168, 1, 8))));
+
+    assert!(parse("10.0.0.0/33").is_err());
+    assert!(parse("eth0/8").is_err());
+    assert!(parse("").is_err());
+
+    assert!(interface_allowed(&[], "eth1", ten));
+    assert!(interface_allowed(&[eth0.clone(), network], "eth1", ten));
+    assert!(!interface_allowed(&[eth0], "eth1", ten));
+  }
+}
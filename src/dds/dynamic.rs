@@ -0,0 +1,1015 @@
+//! Reading data of types that are known only at run time.
+//!
+//! A [`DynamicType`] is made of a [`TypeObject`]: one received in Discovery,
+//! see [`DomainParticipant::discovered_type`], or one written by hand. It
+//! decodes serialized samples into [`DynamicData`], a tree of values that
+//! follows the type. This way a tool can subscribe to any topic without a
+//! Rust type for its data.
+//!
+//! Give the `DynamicType` to a [`SimpleDataReader`] with a
+//! [`DynamicDeserializerAdapter`], which has no default decoder:
+//!
+//! ```
+//! use rustdds::{dynamic::*, *};
+//!
+//! let participant = DomainParticipant::new(0).unwrap();
+//! let qos = QosPolicyBuilder::new().build();
+//! let topic = participant
+//!   .create_topic("pose".to_string(), "Pose".to_string(), &qos, TopicKind::NoKey)
+//!   .unwrap();
+//! let subscriber = participant.create_subscriber(&qos).unwrap();
+//! let reader = subscriber
+//!   .create_simple_datareader_no_key::<DynamicData, DynamicDeserializerAdapter>(&topic, None)
+//!   .unwrap();
+//!
+//! if let Some(type_object) = participant.discovered_type("pose") {
+//!   let dynamic_type = DynamicType::new(type_object);
+//!   while let Ok(Some(sample)) = reader.try_take_one_with(dynamic_type.clone()) {
+//!     println!("{}", sample.into_value());
+//!   }
+//! }
+//! ```
+//!
+//! Classic CDR and XCDR2 data are decoded. Structs are decoded as the
+//! extensibility in the type says, so the type must be the one the writer
+//! uses, or a type assignable from it. The types `float128`, `char16` and
+//! `wstring` are not supported.
+//!
+//! [`DomainParticipant::discovered_type`]: crate::DomainParticipant::discovered_type
+//! [`SimpleDataReader`]: crate::no_key::SimpleDataReader
+
+use std::{fmt, sync::Arc};
+
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use speedy::Endianness;
+
+use crate::{
+  dds::adapters::no_key,
+  serialization::{deserialize_from_cdr_with_decoder_and_endianness, Error, Result},
+  xtypes::{EnumType, Extensibility, StructMember, StructType, TypeObject, UnionType},
+  RepresentationIdentifier,
+};
+
+/// A value of a type known only at run time. See the [module
+/// documentation](self).
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynamicData {
+  Bool(bool),
+  Byte(u8),
+  Int8(i8),
+  UInt8(u8),
+  Int16(i16),
+  UInt16(u16),
+  Int32(i32),
+  UInt32(u32),
+  Int64(i64),
+  UInt64(u64),
+  Float32(f32),
+  Float64(f64),
+  Char(char),
+  String(String),
+  Sequence(Vec<DynamicData>),
+  /// The elements of a multidimensional array are in row-major order.
+  Array(Vec<DynamicData>),
+  Map(Vec<(DynamicData, DynamicData)>),
+  /// An enumerator, and the name of its literal, if the type has one with
+  /// the value.
+  Enum {
+    value: i32,
+    literal: Option<String>,
+  },
+  /// The members of a struct by name, in the order of the type. Absent
+  /// optional members are left out.
+  Struct(Vec<(String, DynamicData)>),
+  /// The discriminator, and the selected case, if any.
+  Union {
+    discriminator: Box<DynamicData>,
+    case: Option<(String, Box<DynamicData>)>,
+  },
+}
+
+impl DynamicData {
+  /// The member `name` of a struct, or the selected case of a union, if it
+  /// has that name.
+  pub fn member(&self, name: &str) -> Option<&DynamicData> {
+    match self {
+      Self::Struct(members) => members
+        .iter()
+        .find(|(member_name, _)| member_name == name)
+        .map(|(_, value)| value),
+      Self::Union {
+        case: Some((case_name, value)),
+        ..
+      } if case_name == name => Some(value),
+      _ => None,
+    }
+  }
+
+  /// The value of an integer or enum, if it fits in `i64`.
+  pub fn as_i64(&self) -> Option<i64> {
+    match *self {
+      Self::Byte(v) | Self::UInt8(v) => Some(v.into()),
+      Self::Int8(v) => Some(v.into()),
+      Self::Int16(v) => Some(v.into()),
+      Self::UInt16(v) => Some(v.into()),
+      Self::Int32(v) | Self::Enum { value: v, .. } => Some(v.into()),
+      Self::UInt32(v) => Some(v.into()),
+      Self::Int64(v) => Some(v),
+      Self::UInt64(v) => i64::try_from(v).ok(),
+      _ => None,
+    }
+  }
+
+  /// The value of a number, possibly rounded.
+  pub fn as_f64(&self) -> Option<f64> {
+    match *self {
+      Self::Float32(v) => Some(v.into()),
+      Self::Float64(v) => Some(v),
+      Self::UInt64(v) => Some(v as f64),
+      _ => self.as_i64().map(|v| v as f64),
+    }
+  }
+
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Self::String(s) => Some(s),
+      _ => None,
+    }
+  }
+}
+
+impl fmt::Display for DynamicData {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn list<'a, T: 'a>(
+      f: &mut fmt::Formatter<'_>,
+      items: impl IntoIterator<Item = &'a T>,
+      item: impl Fn(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+    ) -> fmt::Result {
+      for (i, t) in items.into_iter().enumerate() {
+        if i > 0 {
+          f.write_str(", ")?;
+        }
+        item(f, t)?;
+      }
+      Ok(())
+    }
+
+    match self {
+      Self::Bool(v) => write!(f, "{v}"),
+      Self::Byte(v) | Self::UInt8(v) => write!(f, "{v}"),
+      Self::Int8(v) => write!(f, "{v}"),
+      Self::Int16(v) => write!(f, "{v}"),
+      Self::UInt16(v) => write!(f, "{v}"),
+      Self::Int32(v) => write!(f, "{v}"),
+      Self::UInt32(v) => write!(f, "{v}"),
+      Self::Int64(v) => write!(f, "{v}"),
+      Self::UInt64(v) => write!(f, "{v}"),
+      Self::Float32(v) => write!(f, "{v}"),
+      Self::Float64(v) => write!(f, "{v}"),
+      Self::Char(v) => write!(f, "{v:?}"),
+      Self::String(v) => write!(f, "{v:?}"),
+      Self::Sequence(elements) | Self::Array(elements) => {
+        f.write_str("[")?;
+        list(f, elements, |f, e| write!(f, "{e}"))?;
+        f.write_str("]")
+      }
+      Self::Map(entries) => {
+        f.write_str("{")?;
+        list(f, entries, |f, (k, v)| write!(f, "{k}: {v}"))?;
+        f.write_str("}")
+      }
+      Self::Enum {
+        literal: Some(literal),
+        ..
+      } => f.write_str(literal),
+      Self::Enum { value, .. } => write!(f, "{value}"),
+      Self::Struct(members) => {
+        f.write_str("{")?;
+        list(f, members, |f, (name, v)| write!(f, "{name}: {v}"))?;
+        f.write_str("}")
+      }
+      Self::Union {
+        case: Some((name, value)),
+        ..
+      } => write!(f, "{{{name}: {value}}}"),
+      Self::Union { discriminator, .. } => write!(f, "{{discriminator: {discriminator}}}"),
+    }
+  }
+}
+
+/// The type of [`DynamicData`]. Cloning is cheap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamicType {
+  type_object: Arc<TypeObject>,
+}
+
+impl DynamicType {
+  pub fn new(type_object: TypeObject) -> Self {
+    Self {
+      type_object: Arc::new(type_object),
+    }
+  }
+
+  pub fn type_object(&self) -> &TypeObject {
+    &self.type_object
+  }
+
+  /// Decodes a serialized sample, without the encapsulation header.
+  pub fn decode(
+    &self,
+    input_bytes: &[u8],
+    encoding: RepresentationIdentifier,
+  ) -> Result<DynamicData> {
+    // The encoding of structs is given by their extensibility in the type.
+    if let Some((_, endianness)) = encoding.xcdr2_encoding() {
+      return Xcdr2Reader::new(input_bytes, endianness).read(&self.type_object);
+    }
+    let endianness = match encoding {
+      RepresentationIdentifier::CDR_LE => Endianness::LittleEndian,
+      RepresentationIdentifier::CDR_BE => Endianness::BigEndian,
+      _ => {
+        return Err(message(format!(
+          "DynamicData cannot be decoded from {encoding:?}"
+        )))
+      }
+    };
+    deserialize_from_cdr_with_decoder_and_endianness(
+      input_bytes,
+      endianness,
+      TypeSeed(&self.type_object),
+    )
+    .map(|(data, _)| data)
+  }
+}
+
+impl From<TypeObject> for DynamicType {
+  fn from(type_object: TypeObject) -> Self {
+    Self::new(type_object)
+  }
+}
+
+impl no_key::Decode<DynamicData> for DynamicType {
+  type Error = Error;
+
+  fn decode_bytes(
+    self,
+    input_bytes: &[u8],
+    encoding: RepresentationIdentifier,
+  ) -> Result<DynamicData> {
+    self.decode(input_bytes, encoding)
+  }
+}
+
+/// DeserializerAdapter for DataReaders of [`DynamicData`]. The decoder is the
+/// [`DynamicType`], so read with e.g.
+/// [`SimpleDataReader::try_take_one_with`](crate::no_key::SimpleDataReader::try_take_one_with).
+pub struct DynamicDeserializerAdapter;
+
+const ENCODINGS: [RepresentationIdentifier; 14] = [
+  RepresentationIdentifier::CDR_BE,
+  RepresentationIdentifier::CDR_LE,
+  RepresentationIdentifier::XCDR2_BE,
+  RepresentationIdentifier::XCDR2_LE,
+  RepresentationIdentifier::D_CDR2_BE,
+  RepresentationIdentifier::D_CDR2_LE,
+  RepresentationIdentifier::PL_XCDR2_BE,
+  RepresentationIdentifier::PL_XCDR2_LE,
+  RepresentationIdentifier::CDR2_BE,
+  RepresentationIdentifier::CDR2_LE,
+  RepresentationIdentifier::D_CDR_BE,
+  RepresentationIdentifier::D_CDR_LE,
+  RepresentationIdentifier::PL_CDR2_BE,
+  RepresentationIdentifier::PL_CDR2_LE,
+];
+
+impl no_key::DeserializerAdapter<DynamicData> for DynamicDeserializerAdapter {
+  type Error = Error;
+  type Decoded = DynamicData;
+
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    &ENCODINGS
+  }
+
+  fn transform_decoded(decoded: Self::Decoded) -> DynamicData {
+    decoded
+  }
+}
+
+fn unsupported(type_object: &TypeObject) -> String {
+  format!("DynamicData does not support {type_object:?}")
+}
+
+fn array_length(dimensions: &[u32]) -> usize {
+  dimensions.iter().map(|d| *d as usize).product()
+}
+
+fn enum_data(enum_type: &EnumType, value: i32) -> DynamicData {
+  DynamicData::Enum {
+    value,
+    literal: enum_type
+      .literals
+      .iter()
+      .find(|literal| literal.value == value)
+      .map(|literal| literal.name.clone()),
+  }
+}
+
+fn union_case(union_type: &UnionType, discriminator: &DynamicData) -> Option<usize> {
+  let label = discriminator.as_i64();
+  union_type
+    .cases
+    .iter()
+    .position(|case| case.labels.iter().any(|l| Some(i64::from(*l)) == label))
+    .or_else(|| union_type.cases.iter().position(|case| case.is_default))
+}
+
+// The XTypes default value of a type: zeros, empty strings and collections,
+// and the first enumerator. Given to members missing from the data.
+fn default_data(type_object: &TypeObject) -> DynamicData {
+  match type_object {
+    TypeObject::Boolean => DynamicData::Bool(false),
+    TypeObject::Byte => DynamicData::Byte(0),
+    TypeObject::Int8 => DynamicData::Int8(0),
+    TypeObject::UInt8 => DynamicData::UInt8(0),
+    TypeObject::Int16 => DynamicData::Int16(0),
+    TypeObject::UInt16 => DynamicData::UInt16(0),
+    TypeObject::Int32 => DynamicData::Int32(0),
+    TypeObject::UInt32 => DynamicData::UInt32(0),
+    TypeObject::Int64 => DynamicData::Int64(0),
+    TypeObject::UInt64 => DynamicData::UInt64(0),
+    TypeObject::Float32 => DynamicData::Float32(0.0),
+    TypeObject::Float64 | TypeObject::Float128 => DynamicData::Float64(0.0),
+    TypeObject::Char8 | TypeObject::Char16 => DynamicData::Char('\0'),
+    TypeObject::String { .. } | TypeObject::WString { .. } => DynamicData::String(String::new()),
+    TypeObject::Sequence { .. } => DynamicData::Sequence(Vec::new()),
+    TypeObject::Array {
+      element,
+      dimensions,
+    } => DynamicData::Array(vec![default_data(element); array_length(dimensions)]),
+    TypeObject::Map { .. } => DynamicData::Map(Vec::new()),
+    TypeObject::Alias { base, .. } => default_data(base),
+    TypeObject::Enum(enum_type) => enum_data(
+      enum_type,
+      enum_type
+        .literals
+        .first()
+        .map_or(0, |literal| literal.value),
+    ),
+    TypeObject::Struct(struct_type) => DynamicData::Struct(
+      struct_type
+        .members
+        .iter()
+        .filter(|member| !member.optional)
+        .map(|member| (member.name.clone(), default_data(&member.member_type)))
+        .collect(),
+    ),
+    TypeObject::Union(union_type) => {
+      let discriminator = default_data(&union_type.discriminator);
+      let case = union_case(union_type, &discriminator).map(|i| {
+        let case = &union_type.cases[i];
+        (case.name.clone(), Box::new(default_data(&case.member_type)))
+      });
+      DynamicData::Union {
+        discriminator: Box::new(discriminator),
+        case,
+      }
+    }
+  }
+}
+
+// Classic CDR is decoded with its serde Deserializer, guided by the type.
+// Structs are plain tuples in classic CDR, so member names are not needed,
+// and they need not be 'static.
+struct TypeSeed<'a>(&'a TypeObject);
+
+impl<'de, 'a> DeserializeSeed<'de> for TypeSeed<'a> {
+  type Value = DynamicData;
+
+  fn deserialize<D>(self, deserializer: D) -> std::result::Result<DynamicData, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let visitor = DataVisitor(self.0);
+    match self.0 {
+      TypeObject::Boolean => deserializer.deserialize_bool(visitor),
+      TypeObject::Byte | TypeObject::UInt8 => deserializer.deserialize_u8(visitor),
+      TypeObject::Int8 => deserializer.deserialize_i8(visitor),
+      TypeObject::Int16 => deserializer.deserialize_i16(visitor),
+      TypeObject::UInt16 => deserializer.deserialize_u16(visitor),
+      TypeObject::Int32 => deserializer.deserialize_i32(visitor),
+      TypeObject::UInt32 => deserializer.deserialize_u32(visitor),
+      TypeObject::Int64 => deserializer.deserialize_i64(visitor),
+      TypeObject::UInt64 => deserializer.deserialize_u64(visitor),
+      TypeObject::Float32 => deserializer.deserialize_f32(visitor),
+      TypeObject::Float64 => deserializer.deserialize_f64(visitor),
+      TypeObject::Char8 => deserializer.deserialize_char(visitor),
+      TypeObject::String { .. } => deserializer.deserialize_string(visitor),
+      TypeObject::Sequence { .. } => deserializer.deserialize_seq(visitor),
+      TypeObject::Array { dimensions, .. } => {
+        deserializer.deserialize_tuple(array_length(dimensions), visitor)
+      }
+      TypeObject::Map { .. } => deserializer.deserialize_map(visitor),
+      TypeObject::Alias { base, .. } => TypeSeed(base).deserialize(deserializer),
+      // Enumerators are 32 bits
+      TypeObject::Enum(_) => deserializer.deserialize_i32(visitor),
+      TypeObject::Struct(struct_type) => {
+        deserializer.deserialize_tuple(struct_type.members.len(), visitor)
+      }
+      // The discriminator and the case
+      TypeObject::Union(_) => deserializer.deserialize_tuple(2, visitor),
+      TypeObject::Float128 | TypeObject::Char16 | TypeObject::WString { .. } => {
+        Err(de::Error::custom(unsupported(self.0)))
+      }
+    }
+  }
+}
+
+// An optional struct member
+struct OptionalSeed<'a>(&'a TypeObject);
+
+impl<'de, 'a> DeserializeSeed<'de> for OptionalSeed<'a> {
+  type Value = Option<DynamicData>;
+
+  fn deserialize<D>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    deserializer.deserialize_option(self)
+  }
+}
+
+impl<'de, 'a> Visitor<'de> for OptionalSeed<'a> {
+  type Value = Option<DynamicData>;
+
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    write!(formatter, "optional {:?}", self.0)
+  }
+
+  fn visit_none<E: de::Error>(self) -> std::result::Result<Self::Value, E> {
+    Ok(None)
+  }
+
+  fn visit_some<D>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    TypeSeed(self.0).deserialize(deserializer).map(Some)
+  }
+}
+
+struct DataVisitor<'a>(&'a TypeObject);
+
+macro_rules! visit_primitive {
+  ($method:ident, $type:ty, $variant:ident) => {
+    fn $method<E: de::Error>(self, v: $type) -> std::result::Result<DynamicData, E> {
+      Ok(DynamicData::$variant(v))
+    }
+  };
+}
+
+impl<'de, 'a> Visitor<'de> for DataVisitor<'a> {
+  type Value = DynamicData;
+
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    write!(formatter, "{:?}", self.0)
+  }
+
+  visit_primitive!(visit_bool, bool, Bool);
+  visit_primitive!(visit_i8, i8, Int8);
+  visit_primitive!(visit_i16, i16, Int16);
+  visit_primitive!(visit_i64, i64, Int64);
+  visit_primitive!(visit_u16, u16, UInt16);
+  visit_primitive!(visit_u32, u32, UInt32);
+  visit_primitive!(visit_u64, u64, UInt64);
+  visit_primitive!(visit_f32, f32, Float32);
+  visit_primitive!(visit_f64, f64, Float64);
+  visit_primitive!(visit_char, char, Char);
+
+  fn visit_u8<E: de::Error>(self, v: u8) -> std::result::Result<DynamicData, E> {
+    match self.0 {
+      TypeObject::Byte => Ok(DynamicData::Byte(v)),
+      _ => Ok(DynamicData::UInt8(v)),
+    }
+  }
+
+  fn visit_i32<E: de::Error>(self, v: i32) -> std::result::Result<DynamicData, E> {
+    match self.0 {
+      TypeObject::Enum(enum_type) => Ok(enum_data(enum_type, v)),
+      _ => Ok(DynamicData::Int32(v)),
+    }
+  }
+
+  fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<DynamicData, E> {
+    Ok(DynamicData::String(v.to_string()))
+  }
+
+  fn visit_string<E: de::Error>(self, v: String) -> std::result::Result<DynamicData, E> {
+    Ok(DynamicData::String(v))
+  }
+
+  fn visit_seq<A>(self, mut seq: A) -> std::result::Result<DynamicData, A::Error>
+  where
+    A: SeqAccess<'de>,
+  {
+    let missing = || de::Error::custom("CDR data ended unexpectedly");
+    match self.0 {
+      TypeObject::Sequence { element, .. } => {
+        let mut elements = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(e) = seq.next_element_seed(TypeSeed(element))? {
+          elements.push(e);
+        }
+        Ok(DynamicData::Sequence(elements))
+      }
+      TypeObject::Array {
+        element,
+        dimensions,
+      } => {
+        let length = array_length(dimensions);
+        let mut elements = Vec::with_capacity(length);
+        for _ in 0..length {
+          elements.push(
+            seq
+              .next_element_seed(TypeSeed(element))?
+              .ok_or_else(missing)?,
+          );
+        }
+        Ok(DynamicData::Array(elements))
+      }
+      TypeObject::Struct(struct_type) => {
+        let mut members = Vec::with_capacity(struct_type.members.len());
+        for member in &struct_type.members {
+          let value = if member.optional {
+            seq
+              .next_element_seed(OptionalSeed(&member.member_type))?
+              .ok_or_else(missing)?
+          } else {
+            Some(
+              seq
+                .next_element_seed(TypeSeed(&member.member_type))?
+                .ok_or_else(missing)?,
+            )
+          };
+          if let Some(value) = value {
+            members.push((member.name.clone(), value));
+          }
+        }
+        Ok(DynamicData::Struct(members))
+      }
+      TypeObject::Union(union_type) => {
+        let discriminator = seq
+          .next_element_seed(TypeSeed(&union_type.discriminator))?
+          .ok_or_else(missing)?;
+        let case = match union_case(union_type, &discriminator) {
+          Some(i) => {
+            let case = &union_type.cases[i];
+            let value = seq
+              .next_element_seed(TypeSeed(&case.member_type))?
+              .ok_or_else(missing)?;
+            Some((case.name.clone(), Box::new(value)))
+          }
+          None => None,
+        };
+        Ok(DynamicData::Union {
+          discriminator: Box::new(discriminator),
+          case,
+        })
+      }
+      other => Err(de::Error::custom(format!(
+        "Expected {other:?}, found a sequence"
+      ))),
+    }
+  }
+
+  fn visit_map<A>(self, mut map: A) -> std::result::Result<DynamicData, A::Error>
+  where
+    A: MapAccess<'de>,
+  {
+    match self.0 {
+      TypeObject::Map { key, value, .. } => {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(k) = map.next_key_seed(TypeSeed(key))? {
+          entries.push((k, map.next_value_seed(TypeSeed(value))?));
+        }
+        Ok(DynamicData::Map(entries))
+      }
+      other => Err(de::Error::custom(format!(
+        "Expected {other:?}, found a map"
+      ))),
+    }
+  }
+}
+
+// XCDR2 is decoded directly, because the DHEADERs and EMHEADERs of
+// appendable and mutable structs need the positions in the data. The
+// encoding is that of the xcdr2 module: no DHEADERs before collections, and
+// unions without DHEADERs.
+struct Xcdr2Reader<'i> {
+  input: &'i [u8],
+  position: usize,
+  endianness: Endianness,
+}
+
+// See the xcdr2 module
+const MUST_UNDERSTAND: u32 = 0x8000_0000;
+const MEMBER_ID_MASK: u32 = 0x0fff_ffff;
+const LENGTH_CODE_NEXTINT: u32 = 4;
+
+fn message(msg: impl Into<String>) -> Error {
+  Error::Message(msg.into())
+}
+
+impl<'i> Xcdr2Reader<'i> {
+  fn new(input: &'i [u8], endianness: Endianness) -> Self {
+    Self {
+      input,
+      position: 0,
+      endianness,
+    }
+  }
+
+  fn take(&mut self, count: usize) -> Result<&'i [u8]> {
+    let end = self
+      .position
+      .checked_add(count)
+      .filter(|end| *end <= self.input.len())
+      .ok_or_else(|| message("XCDR2 data ended unexpectedly"))?;
+    let bytes = &self.input[self.position..end];
+    self.position = end;
+    Ok(bytes)
+  }
+
+  // Reads a primitive, and returns it in little-endian byte order.
+  fn get<const N: usize>(&mut self) -> Result<[u8; N]> {
+    let alignment = N.min(4);
+    self.take((alignment - self.position % alignment) % alignment)?;
+    let mut bytes = [0; N];
+    bytes.copy_from_slice(self.take(N)?);
+    if self.endianness == Endianness::BigEndian {
+      bytes.reverse();
+    }
+    Ok(bytes)
+  }
+
+  fn get_u32(&mut self) -> Result<u32> {
+    self.get().map(u32::from_le_bytes)
+  }
+
+  fn get_length(&mut self) -> Result<usize> {
+    let length = self.get_u32()? as usize;
+    if length > self.input.len() - self.position {
+      return Err(message(format!("XCDR2 length {length} exceeds the data")));
+    }
+    Ok(length)
+  }
+
+  fn read(&mut self, type_object: &TypeObject) -> Result<DynamicData> {
+    Ok(match type_object {
+      TypeObject::Boolean => match self.get::<1>()? {
+        [0] => DynamicData::Bool(false),
+        [1] => DynamicData::Bool(true),
+        [b] => return Err(message(format!("Invalid XCDR2 boolean {b}"))),
+      },
+      TypeObject::Byte => DynamicData::Byte(u8::from_le_bytes(self.get()?)),
+      TypeObject::Int8 => DynamicData::Int8(i8::from_le_bytes(self.get()?)),
+      TypeObject::UInt8 => DynamicData::UInt8(u8::from_le_bytes(self.get()?)),
+      TypeObject::Int16 => DynamicData::Int16(i16::from_le_bytes(self.get()?)),
+      TypeObject::UInt16 => DynamicData::UInt16(u16::from_le_bytes(self.get()?)),
+      TypeObject::Int32 => DynamicData::Int32(i32::from_le_bytes(self.get()?)),
+      TypeObject::UInt32 => DynamicData::UInt32(u32::from_le_bytes(self.get()?)),
+      TypeObject::Int64 => DynamicData::Int64(i64::from_le_bytes(self.get()?)),
+      TypeObject::UInt64 => DynamicData::UInt64(u64::from_le_bytes(self.get()?)),
+      TypeObject::Float32 => DynamicData::Float32(f32::from_le_bytes(self.get()?)),
+      TypeObject::Float64 => DynamicData::Float64(f64::from_le_bytes(self.get()?)),
+      TypeObject::Char8 => DynamicData::Char(char::from(self.get::<1>()?[0])),
+      TypeObject::String { .. } => {
+        let length = self.get_length()?;
+        let bytes = match self.take(length)? {
+          [string @ .., 0] => string,
+          _ => return Err(message("XCDR2 string is not NUL-terminated")),
+        };
+        let string =
+          std::str::from_utf8(bytes).map_err(|e| message(format!("Invalid XCDR2 string: {e}")))?;
+        DynamicData::String(string.to_string())
+      }
+      TypeObject::Sequence { element, .. } => {
+        let count = self.get_length()?;
+        let elements = (0..count)
+          .map(|_| self.read(element))
+          .collect::<Result<_>>()?;
+        DynamicData::Sequence(elements)
+      }
+      TypeObject::Array {
+        element,
+        dimensions,
+      } => DynamicData::Array(
+        (0..array_length(dimensions))
+          .map(|_| self.read(element))
+          .collect::<Result<_>>()?,
+      ),
+      TypeObject::Map { key, value, .. } => {
+        let count = self.get_length()?;
+        let entries = (0..count)
+          .map(|_| Ok((self.read(key)?, self.read(value)?)))
+          .collect::<Result<_>>()?;
+        DynamicData::Map(entries)
+      }
+      TypeObject::Alias { base, .. } => self.read(base)?,
+      TypeObject::Enum(enum_type) => enum_data(enum_type, i32::from_le_bytes(self.get()?)),
+      TypeObject::Struct(struct_type) => self.read_struct(struct_type)?,
+      TypeObject::Union(union_type) => {
+        let discriminator = self.read(&union_type.discriminator)?;
+        let case = match union_case(union_type, &discriminator) {
+          Some(i) => {
+            let case = &union_type.cases[i];
+            Some((case.name.clone(), Box::new(self.read(&case.member_type)?)))
+          }
+          None => None,
+        };
+        DynamicData::Union {
+          discriminator: Box::new(discriminator),
+          case,
+        }
+      }
+      TypeObject::Float128 | TypeObject::Char16 | TypeObject::WString { .. } => {
+        return Err(message(unsupported(type_object)))
+      }
+    })
+  }
+
+  // A member of a final or appendable struct
+  fn read_member(&mut self, member: &StructMember) -> Result<Option<DynamicData>> {
+    if member.optional {
+      match self.get::<1>()? {
+        [0] => return Ok(None),
+        [1] => {}
+        [b] => return Err(message(format!("Invalid XCDR2 optional flag {b}"))),
+      }
+    }
+    self.read(&member.member_type).map(Some)
+  }
+
+  fn read_struct(&mut self, struct_type: &StructType) -> Result<DynamicData> {
+    let mut members = Vec::with_capacity(struct_type.members.len());
+    match struct_type.extensibility {
+      Extensibility::Final => {
+        for member in &struct_type.members {
+          if let Some(value) = self.read_member(member)? {
+            members.push((member.name.clone(), value));
+          }
+        }
+      }
+      Extensibility::Appendable => {
+        let end = self.get_length()? + self.position;
+        for member in &struct_type.members {
+          if self.position >= end {
+            // Written with an older version of the type
+            if !member.optional {
+              members.push((member.name.clone(), default_data(&member.member_type)));
+            }
+          } else if let Some(value) = self.read_member(member)? {
+            members.push((member.name.clone(), value));
+          }
+        }
+        // Skip members appended in a newer version of the type.
+        self.position = end;
+      }
+      Extensibility::Mutable => {
+        let end = self.get_length()? + self.position;
+        let mut values = vec![None; struct_type.members.len()];
+        loop {
+          // Members begin at 4-byte boundaries, but the struct may end
+          // before one.
+          let aligned = self.position + (4 - self.position % 4) % 4;
+          if aligned >= end {
+            break;
+          }
+          self.position = aligned;
+          let emheader = self.get_u32()?;
+          let member_id = emheader & MEMBER_ID_MASK;
+          let length = match (emheader >> 28) & 0x7 {
+            length_code @ 0..=3 => 1 << length_code,
+            LENGTH_CODE_NEXTINT => self.get_length()?,
+            // NEXTINT is also the first four bytes of the data.
+            length_code => {
+              let next_int = u32::from_le_bytes(self.get()?) as usize;
+              self.position -= 4;
+              match length_code {
+                5 => Some(next_int),
+                6 => next_int.checked_mul(4),
+                _ => next_int.checked_mul(8),
+              }
+              .and_then(|l| l.checked_add(4))
+              .ok_or_else(|| message("XCDR2 member length overflows"))?
+            }
+          };
+          let data_end = self
+            .position
+            .checked_add(length)
+            .filter(|e| *e <= end)
+            .ok_or_else(|| message("XCDR2 member exceeds its struct"))?;
+
+          match struct_type.members.iter().position(|m| m.id == member_id) {
+            // An optional member is present without a flag.
+            Some(i) => values[i] = Some(self.read(&struct_type.members[i].member_type)?),
+            None if emheader & MUST_UNDERSTAND != 0 => {
+              return Err(message(format!(
+                "Unknown XCDR2 member {member_id} must be understood"
+              )));
+            }
+            None => {}
+          }
+          self.position = data_end;
+        }
+        for (member, value) in struct_type.members.iter().zip(values) {
+          match value {
+            Some(value) => members.push((member.name.clone(), value)),
+            None if !member.optional => {
+              members.push((member.name.clone(), default_data(&member.member_type)));
+            }
+            None => {}
+          }
+        }
+        self.position = end;
+      }
+    }
+    Ok(DynamicData::Struct(members))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde::{Deserialize, Serialize};
+
+  use super::*;
+  use crate::{
+    dds::adapters::no_key::SerializerAdapter,
+    dds_type,
+    serialization::{
+      to_vec_with_endianness, xcdr2::to_vec_xcdr2, Extensible, ExtensibleSerializerAdapter,
+      Xcdr2Encoding,
+    },
+    xtypes::{EnumLiteral, UnionCase},
+  };
+
+  #[derive(Serialize, Deserialize, Clone, Copy)]
+  enum Shape {
+    Circle,
+    Square,
+  }
+
+  impl Extensible for Shape {
+    fn type_object() -> Option<TypeObject> {
+      Some(TypeObject::Enum(EnumType {
+        name: "Shape".to_string(),
+        extensibility: Extensibility::Final,
+        literals: vec![
+          EnumLiteral {
+            name: "CIRCLE".to_string(),
+            value: 0,
+          },
+          EnumLiteral {
+            name: "SQUARE".to_string(),
+            value: 1,
+          },
+        ],
+      }))
+    }
+  }
+
+  dds_type! {
+    #[dds(extensibility = "mutable")]
+    #[derive(Serialize, Deserialize)]
+    struct Drawing {
+      #[dds(id = 3)]
+      name: String,
+      shape: Shape,
+      points: Vec<i16>,
+      size: Option<f64>,
+      hidden: Option<bool>,
+    }
+  }
+
+  dds_type! {
+    #[derive(Serialize, Deserialize)]
+    struct Point {
+      x: i32,
+      label: char,
+    }
+  }
+
+  fn point() -> (Point, TypeObject) {
+    (Point { x: -5, label: 'p' }, Point::type_object().unwrap())
+  }
+
+  fn expected_point() -> DynamicData {
+    DynamicData::Struct(vec![
+      ("x".to_string(), DynamicData::Int32(-5)),
+      ("label".to_string(), DynamicData::Char('p')),
+    ])
+  }
+
+  #[test]
+  fn dynamic_data_from_cdr() {
+    let (point, type_object) = point();
+    let dynamic_type = DynamicType::new(type_object);
+    for (endianness, encoding) in [
+      (Endianness::LittleEndian, RepresentationIdentifier::CDR_LE),
+      (Endianness::BigEndian, RepresentationIdentifier::CDR_BE),
+    ] {
+      let bytes = to_vec_with_endianness(&point, endianness).unwrap();
+      assert_eq!(
+        dynamic_type.decode(&bytes, encoding).unwrap(),
+        expected_point()
+      );
+    }
+    assert!(dynamic_type
+      .decode(&[], RepresentationIdentifier::PL_CDR_LE)
+      .is_err());
+  }
+
+  #[test]
+  fn dynamic_data_from_xcdr2() {
+    let (point, type_object) = point();
+    let dynamic_type = DynamicType::new(type_object);
+    // Point is appendable.
+    let bytes = to_vec_xcdr2(&point, Xcdr2Encoding::Delimited, Endianness::BigEndian).unwrap();
+    let data = dynamic_type
+      .decode(&bytes, RepresentationIdentifier::D_CDR2_BE)
+      .unwrap();
+    assert_eq!(data, expected_point());
+    assert_eq!(data.to_string(), "{x: -5, label: 'p'}");
+
+    let drawing = Drawing {
+      name: "d".to_string(),
+      shape: Shape::Square,
+      points: vec![1, 2],
+      size: Some(0.5),
+      hidden: None,
+    };
+    let type_object = Drawing::type_object().unwrap();
+    let bytes = ExtensibleSerializerAdapter::<Drawing>::to_bytes(&drawing).unwrap();
+    let encoding = ExtensibleSerializerAdapter::<Drawing>::output_encoding();
+    assert_eq!(encoding, RepresentationIdentifier::PL_XCDR2_LE);
+    let data = DynamicType::new(type_object)
+      .decode(&bytes, encoding)
+      .unwrap();
+    assert_eq!(data.member("name").and_then(DynamicData::as_str), Some("d"));
+    assert_eq!(data.member("size").and_then(DynamicData::as_f64), Some(0.5));
+    assert_eq!(data.member("hidden"), None);
+    assert_eq!(
+      data.to_string(),
+      "{name: \"d\", shape: SQUARE, points: [1, 2], size: 0.5}"
+    );
+  }
+
+  #[test]
+  fn dynamic_data_defaults_and_unions() {
+    // A newer version of Point has a member that the writer does not know.
+    let (point, type_object) = point();
+    let TypeObject::Struct(mut struct_type) = type_object else {
+      panic!("Point is a struct");
+    };
+    struct_type
+      .members
+      .push(StructMember::new(2, "z", TypeObject::Float32));
+    let bytes = to_vec_xcdr2(&point, Xcdr2Encoding::Delimited, Endianness::LittleEndian).unwrap();
+    let data = DynamicType::new(TypeObject::Struct(struct_type))
+      .decode(&bytes, RepresentationIdentifier::D_CDR2_LE)
+      .unwrap();
+    assert_eq!(data.member("z"), Some(&DynamicData::Float32(0.0)));
+
+    #[derive(Serialize)]
+    enum Value {
+      #[allow(dead_code)]
+      Int(i32),
+      Text(String),
+    }
+    let union_type = TypeObject::Union(UnionType {
+      name: "Value".to_string(),
+      extensibility: Extensibility::Final,
+      discriminator: Box::new(TypeObject::Int32),
+      cases: vec![
+        UnionCase {
+          id: 0,
+          name: "int".to_string(),
+          member_type: TypeObject::Int32,
+          labels: vec![0],
+          is_default: false,
+        },
+        UnionCase {
+          id: 1,
+          name: "text".to_string(),
+          member_type: TypeObject::String { bound: None },
+          labels: vec![1],
+          is_default: false,
+        },
+      ],
+    });
+    let bytes =
+      to_vec_with_endianness(&Value::Text("t".to_string()), Endianness::LittleEndian).unwrap();
+    let data = DynamicType::new(union_type)
+      .decode(&bytes, RepresentationIdentifier::CDR_LE)
+      .unwrap();
+    assert_eq!(data.member("text").and_then(DynamicData::as_str), Some("t"));
+  }
+}
@@ -0,0 +1,93 @@
+//! Bandwidth limits and priorities for the DataWriters of a participant.
+//!
+//! A DataWriter sends its samples as fast as the application writes them,
+//! and repairs them as fast as DataReaders ask for them. When a DataWriter of
+//! a bulk topic, e.g. a file transfer, writes large samples in a row, their
+//! DATA_FRAG submessages fill the send buffers and the network link, and the
+//! small samples of control topics in the same participant wait behind them.
+//!
+//! The flow controller of the participant schedules the messages that carry
+//! samples of user DataWriters. Each DataWriter can have, with the
+//! [`FlowControl`](crate::policy::FlowControl) QoS policy:
+//! * a bandwidth limit of its own, so that it never sends faster than that,
+//!   and
+//! * a priority, which decides whose messages go first when the bandwidth of
+//!   the whole participant, limited with
+//!   [`DomainParticipantBuilder::flow_control`](crate::DomainParticipantBuilder::flow_control),
+//!   is used up. DataWriters of equal priority are served in the order their
+//!   messages are waiting.
+//!
+//! Messages that do not have to wait are sent immediately. The others are
+//! queued in the participant, and sent in the background when bandwidth
+//! becomes available. Once a DataWriter has waiting messages, its other
+//! messages, such as HEARTBEATs and GAPs, wait behind them, so that their
+//! order does not change. A DataWriter with more than
+//! [`max_queued_bytes`](FlowControllerSettings::max_queued_bytes) waiting
+//! drops the messages that do not fit. Reliable DataReaders then ask for them
+//! again, as they would after a network loss.
+//!
+//! Built-in DataWriters, i.e. discovery and liveliness, are not limited.
+//! Without a participant limit, DataWriters that have no FlowControl policy
+//! are not limited either.
+//!
+//! ```
+//! use rustdds::{policy, DomainParticipantBuilder, FlowControllerSettings, QosPolicyBuilder};
+//!
+//! // The whole participant sends at most 10 MB/s.
+//! let builder = DomainParticipantBuilder::new(0).flow_control(FlowControllerSettings {
+//!   max_bytes_per_second: Some(10_000_000),
+//!   ..FlowControllerSettings::default()
+//! });
+//!
+//! // The file transfer gets at most 8 MB/s of it, and gives way to others.
+//! let bulk_qos = QosPolicyBuilder::new()
+//!   .flow_control(policy::FlowControl {
+//!     priority: -1,
+//!     max_bytes_per_second: Some(8_000_000),
+//!   })
+//!   .build();
+//!
+//! // Control messages go first.
+//! let control_qos = QosPolicyBuilder::new()
+//!   .flow_control(policy::FlowControl {
+//!     priority: 10,
+//!     max_bytes_per_second: None,
+//!   })
+//!   .build();
+//! ```
+
+use std::time::Duration;
+
+/// The flow controller of a DomainParticipant. See the
+/// [module documentation](self).
+///
+/// Set with
+/// [`DomainParticipantBuilder::flow_control`](crate::DomainParticipantBuilder::flow_control).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowControllerSettings {
+  /// Largest rate, in bytes per second, at which the user DataWriters of the
+  /// participant send together. `None` leaves the total unlimited, so that
+  /// only the limits of the DataWriters apply.
+  pub max_bytes_per_second: Option<u64>,
+  /// How long a burst may be sent at full speed after an idle period. A
+  /// bandwidth limit allows bursts of this much of its rate. Shorter bursts
+  /// are gentler on the network, and longer ones cost less scheduling.
+  pub burst_period: Duration,
+  /// Most bytes that the messages waiting of a single DataWriter may take.
+  pub max_queued_bytes: usize,
+}
+
+impl FlowControllerSettings {
+  pub const DEFAULT_BURST_PERIOD: Duration = Duration::from_millis(10);
+  pub const DEFAULT_MAX_QUEUED_BYTES: usize = 16 * 1024 * 1024;
+}
+
+impl Default for FlowControllerSettings {
+  fn default() -> Self {
+    Self {
+      max_bytes_per_second: None,
+      burst_period: Self::DEFAULT_BURST_PERIOD,
+      max_queued_bytes: Self::DEFAULT_MAX_QUEUED_BYTES,
+    }
+  }
+}
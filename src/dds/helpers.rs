@@ -1,8 +1,23 @@
-use std::thread;
+use std::{
+  collections::BTreeMap,
+  future::Future,
+  pin::Pin,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Condvar, Mutex, OnceLock,
+  },
+  task::{Context, Poll, Wake, Waker},
+  thread::{self, Thread},
+  time::Instant,
+};
 
 use mio_extras::channel::{SyncSender, TrySendError};
+use futures::{
+  future::{select, Either},
+  stream::{Stream, StreamExt},
+};
 
-use crate::structure::duration::Duration;
+use crate::{dds::result::ReadResult, structure::duration::Duration};
 
 const TIMEOUT_EPSILON_NS: i64 = 1000; // 1µs
 
@@ -39,3 +54,140 @@ pub fn try_send_timeout<T>(
     Err(other) => Err(other),
   }
 }
+
+// A Waker that unparks the thread that created it, for blocking on the
+// wakeups of async machinery.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+  fn wake(self: Arc<Self>) {
+    self.0.unpark();
+  }
+}
+
+pub fn current_thread_waker() -> Waker {
+  Waker::from(Arc::new(ThreadWaker(thread::current())))
+}
+
+type TimerKey = (Instant, u64);
+
+// Wakes up async tasks at their deadlines, so that async timeouts work with
+// any runtime. One thread, started on first use, serves all of them.
+struct AsyncTimer {
+  wakers: Mutex<BTreeMap<TimerKey, Waker>>,
+  changed: Condvar,
+  next_id: AtomicU64,
+}
+
+impl AsyncTimer {
+  fn get() -> &'static Self {
+    static TIMER: OnceLock<AsyncTimer> = OnceLock::new();
+    TIMER.get_or_init(|| {
+      thread::Builder::new()
+        .name("RustDDS async timer".to_string())
+        .spawn(|| Self::get().run())
+        .expect("Cannot start the async timer thread");
+      AsyncTimer {
+        wakers: Mutex::new(BTreeMap::new()),
+        changed: Condvar::new(),
+        next_id: AtomicU64::new(0),
+      }
+    })
+  }
+
+  // Sets the waker woken at the deadline. Replaces the waker of `key`, if
+  // that has not been woken yet.
+  fn set(&self, key: Option<TimerKey>, deadline: Instant, waker: &Waker) -> TimerKey {
+    let mut wakers = self.wakers.lock().unwrap();
+    let key = key
+      .filter(|key| wakers.contains_key(key))
+      .unwrap_or_else(|| (deadline, self.next_id.fetch_add(1, Ordering::Relaxed)));
+    let earliest = !wakers.keys().next().is_some_and(|first| *first < key);
+    wakers.insert(key, waker.clone());
+    if earliest {
+      self.changed.notify_one();
+    }
+    key
+  }
+
+  fn remove(&self, key: TimerKey) {
+    self.wakers.lock().unwrap().remove(&key);
+  }
+
+  fn run(&self) {
+    let mut wakers = self.wakers.lock().unwrap();
+    loop {
+      let now = Instant::now();
+      while let Some(entry) = wakers.first_entry() {
+        if entry.key().0 > now {
+          break;
+        }
+        entry.remove().wake();
+      }
+      wakers = match wakers.keys().next() {
+        Some((deadline, _)) => {
+          let wait = deadline.saturating_duration_since(now);
+          self.changed.wait_timeout(wakers, wait).unwrap().0
+        }
+        None => self.changed.wait(wakers).unwrap(),
+      };
+    }
+  }
+}
+
+// A future that completes at a deadline. This does not depend on the timer
+// of any async runtime.
+pub struct Sleep {
+  deadline: Instant,
+  key: Option<TimerKey>,
+}
+
+impl Sleep {
+  pub fn until(deadline: Instant) -> Self {
+    Self {
+      deadline,
+      key: None,
+    }
+  }
+
+  pub fn new(duration: std::time::Duration) -> Self {
+    Self::until(Instant::now() + duration)
+  }
+}
+
+impl Future for Sleep {
+  type Output = ();
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+    if Instant::now() >= self.deadline {
+      return Poll::Ready(());
+    }
+    let key = AsyncTimer::get().set(self.key, self.deadline, cx.waker());
+    self.key = Some(key);
+    Poll::Pending
+  }
+}
+
+impl Drop for Sleep {
+  fn drop(&mut self) {
+    if let Some(key) = self.key {
+      AsyncTimer::get().remove(key);
+    }
+  }
+}
+
+// The next item of a sample stream, or None if there is none within the
+// timeout. The streams take samples only when returning them, so a timeout
+// loses nothing.
+pub async fn next_timeout<S, T>(
+  stream: &mut S,
+  timeout: std::time::Duration,
+) -> ReadResult<Option<T>>
+where
+  S: Stream<Item = ReadResult<T>> + Unpin,
+{
+  match select(stream.next(), Sleep::new(timeout)).await {
+    Either::Left((item, _)) => item.transpose(),
+    Either::Right(_) => Ok(None),
+  }
+}
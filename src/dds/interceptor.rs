@@ -0,0 +1,293 @@
+//! Interceptors are application hooks that see every sample written by a
+//! [`DataWriter`](crate::with_key::DataWriter) before serialization, or every
+//! sample received by a [`DataReader`](crate::with_key::DataReader) after
+//! deserialization.
+//!
+//! An interceptor may inspect the sample (e.g. audit logging), modify it
+//! (e.g. fill in defaults), or veto it (e.g. schema validation). Several
+//! interceptors can be registered to one entity. They are called in the order
+//! of registration, and the chain stops at the first veto.
+//!
+//! Plain closures can be used as interceptors:
+//!
+//! ```
+//! use rustdds::interceptor::{InterceptorAction, WriteInterceptor};
+//! use rustdds::WriteOptions;
+//!
+//! let validator = |sample: &mut i32, _: &WriteOptions| {
+//!   if *sample >= 0 {
+//!     InterceptorAction::Pass
+//!   } else {
+//!     InterceptorAction::veto("negative values are not allowed")
+//!   }
+//! };
+//! let mut x = -1;
+//! assert!(validator.on_write(&mut x, &WriteOptions::default()).is_veto());
+//! ```
+
+use std::sync::{Arc, RwLock};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  dds::{
+    result::{WriteError, WriteResult},
+    with_key::{datasample::Sample, datawriter::WriteOptions},
+  },
+  structure::guid::GUID,
+  Keyed,
+};
+
+/// Decision of an interceptor about a sample.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterceptorAction {
+  /// Let the (possibly modified) sample continue.
+  Pass,
+  /// Drop the sample. The reason is reported in
+  /// [`WriteError::Vetoed`] on the write path, and logged on the read path.
+  Veto { reason: String },
+}
+
+impl InterceptorAction {
+  pub fn veto(reason: impl Into<String>) -> Self {
+    Self::Veto {
+      reason: reason.into(),
+    }
+  }
+
+  pub fn is_veto(&self) -> bool {
+    matches!(self, Self::Veto { .. })
+  }
+}
+
+/// Hook on the write path of a DataWriter. Called before the sample is
+/// serialized.
+pub trait WriteInterceptor<D>: Send + Sync {
+  fn on_write(&self, sample: &mut D, write_options: &WriteOptions) -> InterceptorAction;
+}
+
+impl<D, F> WriteInterceptor<D> for F
+where
+  F: Fn(&mut D, &WriteOptions) -> InterceptorAction + Send + Sync,
+{
+  fn on_write(&self, sample: &mut D, write_options: &WriteOptions) -> InterceptorAction {
+    self(sample, write_options)
+  }
+}
+
+/// Hook on the read path of a DataReader. Called after the sample has been
+/// deserialized, but before it is stored to the DataReader cache.
+///
+/// `writer` is the GUID of the DataWriter that published the sample.
+/// Vetoed samples are never seen by the application.
+pub trait ReadInterceptor<D: Keyed>: Send + Sync {
+  fn on_read(
+    &self,
+    sample: &mut Sample<D, D::K>,
+    writer: GUID,
+    write_options: &WriteOptions,
+  ) -> InterceptorAction;
+}
+
+impl<D, F> ReadInterceptor<D> for F
+where
+  D: Keyed,
+  F: Fn(&mut Sample<D, D::K>, GUID, &WriteOptions) -> InterceptorAction + Send + Sync,
+{
+  fn on_read(
+    &self,
+    sample: &mut Sample<D, D::K>,
+    writer: GUID,
+    write_options: &WriteOptions,
+  ) -> InterceptorAction {
+    self(sample, writer, write_options)
+  }
+}
+
+// Interceptors registered to a DataWriter. Registration is possible through a
+// shared reference, because DataWriter is used via &self.
+pub(crate) struct WriteInterceptorChain<D> {
+  interceptors: RwLock<Vec<Arc<dyn WriteInterceptor<D>>>>,
+}
+
+impl<D> WriteInterceptorChain<D> {
+  pub fn new() -> Self {
+    Self {
+      interceptors: RwLock::new(Vec::new()),
+    }
+  }
+
+  pub fn add(&self, interceptor: Arc<dyn WriteInterceptor<D>>) {
+    self.interceptors.write().unwrap().push(interceptor);
+  }
+
+  pub fn clear(&self) {
+    self.interceptors.write().unwrap().clear();
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.interceptors.read().unwrap().is_empty()
+  }
+
+  // Returns the sample to be written, or the vetoed sample inside the error.
+  pub fn intercept(&self, mut sample: D, write_options: &WriteOptions) -> WriteResult<D, D> {
+    for interceptor in self.interceptors.read().unwrap().iter() {
+      if let InterceptorAction::Veto { reason } = interceptor.on_write(&mut sample, write_options) {
+        debug!("Write vetoed by interceptor: {reason}");
+        return Err(WriteError::Vetoed {
+          reason,
+          data: sample,
+        });
+      }
+    }
+    Ok(sample)
+  }
+}
+
+// Interceptors registered to a DataReader.
+pub(crate) struct ReadInterceptorChain<D: Keyed> {
+  interceptors: RwLock<Vec<Arc<dyn ReadInterceptor<D>>>>,
+}
+
+impl<D: Keyed> ReadInterceptorChain<D> {
+  pub fn new() -> Self {
+    Self {
+      interceptors: RwLock::new(Vec::new()),
+    }
+  }
+
+  pub fn add(&self, interceptor: Arc<dyn ReadInterceptor<D>>) {
+    self.interceptors.write().unwrap().push(interceptor);
+  }
+
+  pub fn clear(&self) {
+    self.interceptors.write().unwrap().clear();
+  }
+
+  // Returns false, if the sample was vetoed.
+  pub fn intercept(
+    &self,
+    sample: &mut Sample<D, D::K>,
+    writer: GUID,
+    write_options: &WriteOptions,
+  ) -> bool {
+    for interceptor in self.interceptors.read().unwrap().iter() {
+      if let InterceptorAction::Veto { reason } = interceptor.on_read(sample, writer, write_options)
+      {
+        info!("Received sample from {writer:?} vetoed by interceptor: {reason}");
+        return false;
+      }
+    }
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::structure::guid::EntityKind;
+
+  #[derive(Debug, PartialEq, Clone)]
+  struct Shape {
+    color: String,
+    x: i32,
+  }
+
+  impl Keyed for Shape {
+    type K = String;
+    fn key(&self) -> String {
+      self.color.clone()
+    }
+  }
+
+  #[test]
+  fn write_chain_modifies_and_vetoes() {
+    let chain = WriteInterceptorChain::<Shape>::new();
+    chain.add(Arc::new(|s: &mut Shape, _: &WriteOptions| {
+      s.x = s.x.clamp(0, 100);
+      InterceptorAction::Pass
+    }));
+    chain.add(Arc::new(|s: &mut Shape, _: &WriteOptions| {
+      if s.color.is_empty() {
+        InterceptorAction::veto("color missing")
+      } else {
+        InterceptorAction::Pass
+      }
+    }));
+
+    let written = chain
+      .intercept(
+        Shape {
+          color: "RED".to_string(),
+          x: 500,
+        },
+        &WriteOptions::default(),
+      )
+      .unwrap();
+    assert_eq!(written.x, 100);
+
+    match chain.intercept(
+      Shape {
+        color: String::new(),
+        x: -5,
+      },
+      &WriteOptions::default(),
+    ) {
+      Err(WriteError::Vetoed { reason, data }) => {
+        assert_eq!(reason, "color missing");
+        // Earlier interceptors have already modified the sample
+        assert_eq!(data.x, 0);
+      }
+      other => panic!("Expected veto, got {other:?}"),
+    }
+
+    chain.clear();
+    assert!(chain
+      .intercept(
+        Shape {
+          color: String::new(),
+          x: -5
+        },
+        &WriteOptions::default()
+      )
+      .is_ok());
+  }
+
+  #[test]
+  fn read_chain_stops_at_first_veto() {
+    let chain = ReadInterceptorChain::<Shape>::new();
+    let writer = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    chain.add(Arc::new(
+      |s: &mut Sample<Shape, String>, _: GUID, _: &WriteOptions| match s {
+        Sample::Value(shape) if shape.color == "BLUE" => InterceptorAction::veto("no blue"),
+        _ => InterceptorAction::Pass,
+      },
+    ));
+    chain.add(Arc::new(
+      |s: &mut Sample<Shape, String>, _: GUID, _: &WriteOptions| {
+        if let Sample::Value(shape) = s {
+          shape.x += 1;
+        }
+        InterceptorAction::Pass
+      },
+    ));
+
+    let mut red = Sample::Value(Shape {
+      color: "RED".to_string(),
+      x: 1,
+    });
+    assert!(chain.intercept(&mut red, writer, &WriteOptions::default()));
+    assert_eq!(red.value().unwrap().x, 2);
+
+    let mut blue = Sample::Value(Shape {
+      color: "BLUE".to_string(),
+      x: 1,
+    });
+    assert!(!chain.intercept(&mut blue, writer, &WriteOptions::default()));
+    assert_eq!(blue.value().unwrap().x, 1);
+
+    let mut dispose = Sample::<Shape, String>::Dispose("BLUE".to_string());
+    assert!(chain.intercept(&mut dispose, writer, &WriteOptions::default()));
+  }
+}
@@ -0,0 +1,238 @@
+//! Delivering samples to DataReaders of the same DomainParticipant without
+//! serializing them.
+//!
+//! Normally a sample written to a DataWriter is serialized and sent over the
+//! network even to DataReaders in the same DomainParticipant, which then
+//! deserialize it again. With
+//! [`DomainParticipantBuilder::intra_process`](crate::DomainParticipantBuilder::intra_process),
+//! a sample written with
+//! [`DataWriter::write_shared`](crate::with_key::DataWriter::write_shared) is
+//! given to the matched DataReaders of the same participant as an `Arc` of
+//! the written value. Each DataReader gets a clone of the value, so the sample
+//! is never serialized for them, nor does it go through the sockets.
+//!
+//! Local delivery does not change the semantics of QoS policies:
+//! * The sample goes through the same processing in the DataReader as one
+//!   received from the network, so History, Deadline, Lifespan, Ownership,
+//!   TimeBasedFilter, content filters and instance states apply as usual.
+//! * Only DataReaders that the DataWriter has matched through Discovery get
+//!   the sample, as they would over the network. A sample is not delivered to
+//!   a DataReader whose content filter it does not pass, nor to a DataReader
+//!   other than the one it is written to with
+//!   [`WriteOptionsBuilder::to_single_reader`](crate::WriteOptionsBuilder::to_single_reader).
+//! * Remote DataReaders get the sample serialized by the DataWriter's
+//!   serializer adapter, as if it was written with `write`. If remote
+//!   DataReaders are matched when the sample is written, it is serialized
+//!   once, in the background thread, and not in the calling one. A remote
+//!   DataReader, or a late-joining local one, matched later gets the samples
+//!   that Durability or LastValueCache keep for it serialized as well.
+//! * A local DataReader of a different data type than the DataWriter gets
+//!   the serialized sample and deserializes it as usual.
+//!
+//! If the participant is not built for intra-process delivery, or the
+//! DataWriter has write interceptors, `write_shared` writes a clone of the
+//! value with `write`.
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use rustdds::*;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, Clone, Serialize, Deserialize)]
+//! struct Frame {
+//!   camera: u32,
+//!   pixels: Vec<u8>,
+//! }
+//! impl Keyed for Frame {
+//!   type K = u32;
+//!   fn key(&self) -> u32 {
+//!     self.camera
+//!   }
+//! }
+//!
+//! let participant = DomainParticipantBuilder::new(0)
+//!   .intra_process(true)
+//!   .build()
+//!   .unwrap();
+//! let qos = QosPolicyBuilder::new().build();
+//! let topic = participant
+//!   .create_topic("frames".to_string(), "Frame".to_string(), &qos, TopicKind::WithKey)
+//!   .unwrap();
+//! let publisher = participant.create_publisher(&qos).unwrap();
+//! let writer = publisher
+//!   .create_datawriter::<Frame, CDRSerializerAdapter<_>>(&topic, None)
+//!   .unwrap();
+//!
+//! let frame = Arc::new(Frame {
+//!   camera: 1,
+//!   pixels: vec![0; 1 << 20],
+//! });
+//! writer.write_shared(frame, None).unwrap();
+//! ```
+
+use std::{
+  any::Any,
+  fmt,
+  sync::{Arc, OnceLock},
+};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  dds::{
+    adapters::with_key::SerializerAdapter,
+    key::{Key, KeyHash, Keyed},
+  },
+  messages::submessages::elements::serialized_payload::SerializedPayload,
+};
+
+type SharedValue = Arc<dyn Any + Send + Sync>;
+
+// A sample written with DataWriter::write_shared. The value is shared by all
+// local DataReaders, and serialized only if it must be sent out.
+#[derive(Clone)]
+pub(crate) struct LocalSample {
+  value: SharedValue,
+  key_hash: KeyHash,
+  // Monomorphized over the data type and serializer of the DataWriter, so
+  // that the receiving side need not know them.
+  clone_value: fn(&SharedValue) -> Box<dyn Any>,
+  serialize: fn(&SharedValue) -> Option<SerializedPayload>,
+  serialized: Arc<OnceLock<Option<SerializedPayload>>>,
+}
+
+impl LocalSample {
+  pub fn new<D, SA>(value: Arc<D>) -> Self
+  where
+    D: Keyed + Clone + Send + Sync + 'static,
+    SA: SerializerAdapter<D>,
+  {
+    Self {
+      key_hash: value.key().hash_key(false),
+      value,
+      clone_value: clone_value::<D>,
+      serialize: serialize::<D, SA>,
+      serialized: Arc::new(OnceLock::new()),
+    }
+  }
+
+  pub fn key_hash(&self) -> KeyHash {
+    self.key_hash
+  }
+
+  // A clone of the value, if it is of type D.
+  pub fn value<D: 'static>(&self) -> Option<D> {
+    (self.clone_value)(&self.value)
+      .downcast::<D>()
+      .ok()
+      .map(|d| *d)
+  }
+
+  // The sample serialized by the DataWriter's serializer adapter. It is
+  // serialized at the first call only. None, if serialization fails.
+  pub fn serialized_payload(&self) -> Option<SerializedPayload> {
+    self
+      .serialized
+      .get_or_init(|| (self.serialize)(&self.value))
+      .clone()
+  }
+
+  // Size of the serialized sample, or zero if it has not been serialized.
+  pub fn serialized_size(&self) -> usize {
+    self
+      .serialized
+      .get()
+      .and_then(Option::as_ref)
+      .map_or(0, SerializedPayload::len_serialized)
+  }
+}
+
+fn clone_value<D: Clone + 'static>(value: &SharedValue) -> Box<dyn Any> {
+  // The value was created from an Arc<D>, so the downcast succeeds.
+  let value = value.downcast_ref::<D>().cloned();
+  Box::new(value.expect("LocalSample of unexpected type"))
+}
+
+fn serialize<D, SA>(value: &SharedValue) -> Option<SerializedPayload>
+where
+  D: Keyed + 'static,
+  SA: SerializerAdapter<D>,
+{
+  let value = value.downcast_ref::<D>()?;
+  match SA::to_bytes(value) {
+    Ok(bytes) => Some(SerializedPayload::new_from_bytes(
+      SA::output_encoding(),
+      bytes,
+    )),
+    Err(e) => {
+      error!("Cannot serialize a sample written with write_shared: {e}");
+      None
+    }
+  }
+}
+
+impl fmt::Debug for LocalSample {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("LocalSample")
+      .field("key_hash", &self.key_hash)
+      .field("serialized", &self.serialized.get().is_some())
+      .finish_non_exhaustive()
+  }
+}
+
+// Local samples are the same if they share the value.
+impl PartialEq for LocalSample {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.value, &other.value)
+  }
+}
+
+impl Eq for LocalSample {}
+
+#[cfg(test)]
+mod tests {
+  use serde::{Deserialize, Serialize};
+
+  use super::*;
+  use crate::{
+    dds::adapters::no_key::DeserializerAdapter, CDRDeserializerAdapter, CDRSerializerAdapter,
+  };
+
+  #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+  struct Reading {
+    sensor: u32,
+    value: f64,
+  }
+
+  impl Keyed for Reading {
+    type K = u32;
+    fn key(&self) -> u32 {
+      self.sensor
+    }
+  }
+
+  #[test]
+  fn value_is_shared_and_serialized_on_demand() {
+    let reading = Arc::new(Reading {
+      sensor: 7,
+      value: 1.5,
+    });
+    let sample = LocalSample::new::<Reading, CDRSerializerAdapter<Reading>>(Arc::clone(&reading));
+    assert_eq!(sample.key_hash(), 7u32.hash_key(false));
+    assert_eq!(sample.value::<Reading>().as_ref(), Some(&*reading));
+    assert_eq!(sample.value::<String>(), None);
+    assert_eq!(sample.clone(), sample);
+
+    assert_eq!(sample.serialized_size(), 0);
+    let payload = sample.serialized_payload().unwrap();
+    assert_eq!(sample.serialized_size(), payload.len_serialized());
+    let decoded = CDRDeserializerAdapter::<Reading>::from_bytes(
+      &payload.value,
+      payload.representation_identifier,
+    )
+    .unwrap();
+    assert_eq!(decoded, *reading);
+  }
+}
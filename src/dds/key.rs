@@ -2,15 +2,15 @@
 // See e.g. Figure 2.3 in "2.2.1.2.2 Overall Conceptual Model"
 use std::hash::Hash;
 
-use byteorder::BigEndian;
 use rand::Rng;
 use log::error;
 use serde::{Deserialize, Serialize};
+use speedy::Endianness;
 pub use cdr_encoding_size::*;
 
 use crate::serialization::{
   pl_cdr_adapters::{PlCdrDeserializeError, PlCdrSerializeError},
-  to_vec,
+  to_vec_with_endianness,
 };
 
 /// Data sample must implement [`Keyed`] to be used in a WITH_KEY topic.
@@ -159,7 +159,7 @@ pub trait Key:
       maximum-size of the serialized key.
     */
 
-    let mut cdr_bytes = to_vec::<Self, BigEndian>(self).unwrap_or_else(|e| {
+    let mut cdr_bytes = to_vec_with_endianness(self, Endianness::BigEndian).unwrap_or_else(|e| {
       error!("Hashing key {:?} failed!", e);
       // This would cause a lot of hash collisions, but wht else we could do
       // if the key cannot be serialized? Are there any realistic conditions
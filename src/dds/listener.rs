@@ -0,0 +1,306 @@
+//! Listeners, as in Section 2.2.4.3 "Listener" of the DDS specification v1.4.
+//!
+//! A listener set on a DataReader, DataWriter or DomainParticipant is called
+//! back as the communication statuses of the entity change. This is an
+//! alternative to polling the statuses, or waiting for them with a
+//! [`WaitSet`](crate::waitset::WaitSet) or async streams.
+//!
+//! The callbacks of all listeners run one at a time, in an internal thread,
+//! never in the threads that send and receive data. A callback that blocks
+//! delays the other callbacks, but not communication. Calling the methods of
+//! entities from a callback is allowed.
+//!
+//! A status given to a callback is taken as by its getter, e.g.
+//! [`get_subscription_matched_status`](crate::with_key::DataReader::get_subscription_matched_status):
+//! its change counts are reset and it is removed from
+//! [`Entity::get_status_changes`](crate::communication_status::Entity::get_status_changes).
+//!
+//! ```
+//! use std::sync::mpsc;
+//!
+//! use rustdds::{communication_status::*, listener::DataReaderListener, *};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug)]
+//! struct SomeType {
+//!   a: i32,
+//! }
+//!
+//! struct Matches(mpsc::Sender<i32>);
+//!
+//! impl DataReaderListener for Matches {
+//!   fn on_subscription_matched(&mut self, status: SubscriptionMatchedStatus) {
+//!     let _ = self.0.send(status.current_count);
+//!   }
+//! }
+//!
+//! let participant = DomainParticipant::new(0).unwrap();
+//! let qos = QosPolicyBuilder::new().build();
+//! let topic = participant
+//!   .create_topic(
+//!     "some_topic".to_string(),
+//!     "SomeType".to_string(),
+//!     &qos,
+//!     TopicKind::NoKey,
+//!   )
+//!   .unwrap();
+//! let subscriber = participant.create_subscriber(&qos).unwrap();
+//! let reader = subscriber
+//!   .create_datareader_no_key_cdr::<SomeType>(&topic, None)
+//!   .unwrap();
+//!
+//! let (sender, _matched_writers) = mpsc::channel();
+//! reader.set_listener(Some(Box::new(Matches(sender))));
+//! ```
+
+use std::{
+  fmt,
+  panic::{self, AssertUnwindSafe},
+  sync::{mpsc, Arc, Mutex, OnceLock},
+  thread,
+};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::dds::{
+  communication_status::{
+    LivelinessChangedStatus, LivelinessLostStatus, OfferedDeadlineMissedStatus,
+    OfferedIncompatibleQosStatus, PublicationMatchedStatus, RequestedDeadlineMissedStatus,
+    RequestedIncompatibleQosStatus, SampleLostStatus, SampleRejectedStatus,
+    SubscriptionMatchedStatus,
+  },
+  statusevents::DomainParticipantStatusEvent,
+};
+
+/// Callbacks for the statuses of a DataReader. All methods do nothing by
+/// default, so implement only those of interest.
+///
+/// Set with
+/// [`DataReader::set_listener`](crate::with_key::DataReader::set_listener).
+pub trait DataReaderListener: Send {
+  /// New samples have been received since the DataReader last read or took
+  /// samples. This is called once until the DataReader reads again, not for
+  /// every sample.
+  fn on_data_available(&mut self) {}
+  fn on_sample_rejected(&mut self, _status: SampleRejectedStatus) {}
+  fn on_liveliness_changed(&mut self, _status: LivelinessChangedStatus) {}
+  fn on_requested_deadline_missed(&mut self, _status: RequestedDeadlineMissedStatus) {}
+  fn on_requested_incompatible_qos(&mut self, _status: RequestedIncompatibleQosStatus) {}
+  fn on_sample_lost(&mut self, _status: SampleLostStatus) {}
+  fn on_subscription_matched(&mut self, _status: SubscriptionMatchedStatus) {}
+}
+
+/// Callbacks for the statuses of a DataWriter. All methods do nothing by
+/// default, so implement only those of interest.
+///
+/// Set with
+/// [`DataWriter::set_listener`](crate::with_key::DataWriter::set_listener).
+pub trait DataWriterListener: Send {
+  fn on_liveliness_lost(&mut self, _status: LivelinessLostStatus) {}
+  fn on_offered_deadline_missed(&mut self, _status: OfferedDeadlineMissedStatus) {}
+  fn on_offered_incompatible_qos(&mut self, _status: OfferedIncompatibleQosStatus) {}
+  fn on_publication_matched(&mut self, _status: PublicationMatchedStatus) {}
+}
+
+/// Callback for the status events of a DomainParticipant, as also delivered
+/// by
+/// [`DomainParticipant::status_listener`](crate::DomainParticipant::status_listener).
+///
+/// Set with
+/// [`DomainParticipant::set_listener`](crate::DomainParticipant::set_listener).
+pub trait DomainParticipantListener: Send {
+  fn on_participant_status(&mut self, event: DomainParticipantStatusEvent);
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// Runs the callbacks of all listeners in order, in one thread, which is
+// started on first use.
+fn execute(job: Job) {
+  static EXECUTOR: OnceLock<mpsc::Sender<Job>> = OnceLock::new();
+  let executor = EXECUTOR.get_or_init(|| {
+    let (sender, receiver) = mpsc::channel::<Job>();
+    thread::Builder::new()
+      .name("RustDDS listener executor".to_string())
+      .spawn(move || {
+        for job in receiver {
+          if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+            error!("A listener callback panicked. The listener is not called again.");
+          }
+        }
+      })
+      .expect("Cannot start the listener executor thread");
+    sender
+  });
+  // The receiver is never dropped.
+  let _ = executor.send(job);
+}
+
+// The listener of an entity, if one is set
+pub(crate) struct ListenerSlot<L: ?Sized> {
+  listener: Mutex<Option<Arc<Mutex<Box<L>>>>>,
+}
+
+impl<L: ?Sized + Send + 'static> ListenerSlot<L> {
+  pub fn set(&self, listener: Option<Box<L>>) {
+    *self.listener.lock().unwrap() = listener.map(|l| Arc::new(Mutex::new(l)));
+  }
+
+  pub fn is_set(&self) -> bool {
+    self.listener.lock().unwrap().is_some()
+  }
+
+  // Calls the listener in the executor thread, if one is set. A listener that
+  // has panicked is poisoned, and not called.
+  pub fn call(&self, callback: impl FnOnce(&mut L) + Send + 'static) {
+    let Some(listener) = self.listener.lock().unwrap().clone() else {
+      return;
+    };
+    execute(Box::new(move || {
+      if let Ok(mut listener) = listener.lock() {
+        callback(&mut **listener);
+      }
+    }));
+  }
+}
+
+impl<L: ?Sized> Default for ListenerSlot<L> {
+  fn default() -> Self {
+    Self {
+      listener: Mutex::new(None),
+    }
+  }
+}
+
+impl<L: ?Sized> fmt::Debug for ListenerSlot<L> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let is_set = self.listener.lock().is_ok_and(|l| l.is_some());
+    f.debug_struct("ListenerSlot")
+      .field("is_set", &is_set)
+      .finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use serde::{Deserialize, Serialize};
+
+  use super::*;
+  use crate::dds::{
+    participant::DomainParticipant,
+    qos::{policy::Reliability, QosPolicyBuilder},
+    topic::TopicKind,
+  };
+
+  #[test]
+  fn callbacks_run_in_order_and_survive_panics() {
+    struct Forward(mpsc::Sender<i32>);
+
+    impl DataWriterListener for Forward {
+      fn on_publication_matched(&mut self, status: PublicationMatchedStatus) {
+        if status.current_count < 0 {
+          panic!("test panic");
+        }
+        self.0.send(status.current_count).unwrap();
+      }
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let slot: ListenerSlot<dyn DataWriterListener> = ListenerSlot::default();
+    slot.call(|_| unreachable!("no listener is set"));
+    slot.set(Some(Box::new(Forward(sender))));
+    for current_count in [1, 2, -1, 3] {
+      let status = PublicationMatchedStatus {
+        current_count,
+        ..PublicationMatchedStatus::default()
+      };
+      slot.call(move |l| l.on_publication_matched(status));
+    }
+    let timeout = Duration::from_secs(5);
+    assert_eq!(receiver.recv_timeout(timeout), Ok(1));
+    assert_eq!(receiver.recv_timeout(timeout), Ok(2));
+    // The panicking listener is not called again, but other listeners are.
+    assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+
+    let (sender, receiver) = mpsc::channel();
+    slot.set(Some(Box::new(Forward(sender))));
+    slot.call(|l| l.on_publication_matched(PublicationMatchedStatus::default()));
+    assert_eq!(receiver.recv_timeout(timeout), Ok(0));
+  }
+
+  #[test]
+  fn reader_listener_is_called() {
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Sample {
+      a: i32,
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+      Matched(i32),
+      DataAvailable,
+    }
+
+    struct Events(mpsc::Sender<Event>);
+
+    impl DataReaderListener for Events {
+      fn on_data_available(&mut self) {
+        let _ = self.0.send(Event::DataAvailable);
+      }
+
+      fn on_subscription_matched(&mut self, status: SubscriptionMatchedStatus) {
+        let _ = self.0.send(Event::Matched(status.current_count));
+      }
+    }
+
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::BestEffort)
+      .build();
+    let topic = dp
+      .create_topic(
+        "listener_test".to_string(),
+        "Sample".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let subscriber = dp.create_subscriber(&qos).unwrap();
+    let mut reader = subscriber
+      .create_datareader_no_key_cdr::<Sample>(&topic, None)
+      .unwrap();
+    let (sender, events) = mpsc::channel();
+    reader.set_listener(Some(Box::new(Events(sender))));
+
+    let publisher = dp.create_publisher(&qos).unwrap();
+    let writer = publisher
+      .create_datawriter_no_key_cdr::<Sample>(&topic, None)
+      .unwrap();
+    let timeout = Duration::from_secs(5);
+    assert_eq!(events.recv_timeout(timeout), Ok(Event::Matched(1)));
+    // The listener took the status
+    assert_eq!(
+      reader
+        .get_subscription_matched_status()
+        .current_count_change,
+      0
+    );
+
+    // The writer sends only to the readers it has matched, too
+    while writer.get_publication_matched_status().current_count < 1 {
+      thread::sleep(Duration::from_millis(10));
+    }
+    writer.write(Sample { a: 1 }, None).unwrap();
+    // Local readers can be notified before the sample is readable, so wait
+    // again, if there was nothing to take.
+    loop {
+      assert_eq!(events.recv_timeout(timeout), Ok(Event::DataAvailable));
+      if reader.take_next_sample().unwrap().is_some() {
+        break;
+      }
+    }
+  }
+}
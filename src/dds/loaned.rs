@@ -0,0 +1,119 @@
+//! Reading samples without copying their data.
+//!
+//! [`DataReader::take`](crate::with_key::DataReader::take) deserializes each
+//! sample into an owned value, which allocates for every string and sequence
+//! in it. [`read_loaned`](crate::with_key::DataReader::read_loaned) instead
+//! gives the received samples as [`LoanedSample`]s, which share the
+//! serialized data with the DataReader's cache. [`LoanedSample::value`]
+//! deserializes a type that borrows its `&str` and `&[u8]` fields from the
+//! loan, so that a high-rate subscriber need not allocate per sample.
+//!
+//! Borrowing works for samples encoded in XCDR2, e.g. by an
+//! [`Xcdr2SerializerAdapter`](crate::serialization::Xcdr2SerializerAdapter)
+//! or by other DDS implementations using XCDR2. Classic CDR is decoded by
+//! copying, so from it only types with owned fields can be read.
+//!
+//! ```
+//! use rustdds::*;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Frame<'a> {
+//!   camera: &'a str,
+//!   pixels: &'a [u8],
+//! }
+//!
+//! let participant = DomainParticipant::new(0).unwrap();
+//! let qos = QosPolicyBuilder::new().build();
+//! let subscriber = participant.create_subscriber(&qos).unwrap();
+//! let topic = participant
+//!   .create_topic("frames".to_string(), "Frame".to_string(), &qos, TopicKind::NoKey)
+//!   .unwrap();
+//! let mut reader = subscriber
+//!   .create_datareader_no_key::<Vec<u8>, CDRDeserializerAdapter<_>>(&topic, None)
+//!   .unwrap();
+//!
+//! for loan in reader.read_loaned(16).unwrap() {
+//!   let frame: Frame = loan.value().unwrap();
+//!   println!("{}: {} bytes", frame.camera, frame.pixels.len());
+//! }
+//! ```
+
+use std::ops::Deref;
+
+use serde::Deserialize;
+
+use crate::{
+  dds::{result::ReadResult, sliced::SampleSlices},
+  serialization::{
+    deserialize_from_cdr_with_rep_id, xcdr2::Xcdr2Deserializer, Extensible, StructLayout,
+  },
+};
+
+/// A received sample, lent in serialized form. See the
+/// [module documentation](self).
+///
+/// The sample bytes stay shared with the DataReader's cache while the loan is
+/// alive. Values deserialized by [`value`](Self::value) may borrow from the
+/// loan, so they cannot outlive it. The writer, timestamps and encoding of the
+/// sample are available through [`SampleSlices`].
+#[derive(Debug, Clone)]
+pub struct LoanedSample {
+  slices: SampleSlices,
+}
+
+impl LoanedSample {
+  pub(crate) fn new(slices: SampleSlices) -> Self {
+    Self { slices }
+  }
+
+  /// Deserializes the sample. `&str` and `&[u8]` in `T` borrow from this
+  /// loan, if the sample is encoded in XCDR2.
+  ///
+  /// All structs are decoded with the encoding of the sample. For types with
+  /// several extensibilities, use [`extensible_value`](Self::extensible_value).
+  pub fn value<'a, T>(&'a self) -> ReadResult<T>
+  where
+    T: Deserialize<'a>,
+  {
+    self.decode(Vec::new())
+  }
+
+  /// Deserializes the sample like [`value`](Self::value), decoding each
+  /// struct as its [`Extensible`] layout says.
+  pub fn extensible_value<'a, T>(&'a self) -> ReadResult<T>
+  where
+    T: Deserialize<'a> + Extensible,
+  {
+    self.decode(T::layouts())
+  }
+
+  fn decode<'a, T>(&'a self, layouts: Vec<StructLayout>) -> ReadResult<T>
+  where
+    T: Deserialize<'a>,
+  {
+    let bytes = self.slices.as_bytes();
+    let encoding = self.slices.representation_identifier();
+    match encoding.xcdr2_encoding() {
+      Some((xcdr2_encoding, endianness)) => {
+        let mut deserializer = Xcdr2Deserializer::new(bytes, xcdr2_encoding, endianness)
+          .with_layouts(layouts)
+          .borrowing();
+        Ok(T::deserialize(&mut deserializer)?)
+      }
+      None => Ok(deserialize_from_cdr_with_rep_id(bytes, encoding)?.0),
+    }
+  }
+
+  pub fn into_slices(self) -> SampleSlices {
+    self.slices
+  }
+}
+
+impl Deref for LoanedSample {
+  type Target = SampleSlices;
+
+  fn deref(&self) -> &SampleSlices {
+    &self.slices
+  }
+}
@@ -2,18 +2,30 @@ use std::{
   io,
   pin::Pin,
   task::{Context, Poll},
+  time::Duration as StdDuration,
 };
 
+use serde::Serialize;
 use futures::stream::{FusedStream, Stream};
 
 use crate::{
   dds::{
     adapters::no_key::{DefaultDecoder, DeserializerAdapter},
+    communication_status::{
+      Entity, LivelinessChangedStatus, RequestedDeadlineMissedStatus,
+      RequestedIncompatibleQosStatus, SampleLostStatus, SampleRejectedStatus, StatusMask,
+      SubscriptionMatchedStatus,
+    },
+    helpers::next_timeout,
+    listener::DataReaderListener,
+    loaned::LoanedSample,
     no_key::datasample::DataSample,
     qos::{HasQoSPolicy, QosPolicies},
-    readcondition::ReadCondition,
+    readcondition::{QueryCondition, ReadCondition},
     result::ReadResult,
+    shutdown::ShutdownToken,
     statusevents::DataReaderStatus,
+    waitset::StatusCondition,
     with_key::{
       datareader as datareader_with_key,
       datasample::{DataSample as WithKeyDataSample, Sample},
@@ -23,7 +35,7 @@ use crate::{
     },
   },
   serialization::CDRDeserializerAdapter,
-  structure::entity::RTPSEntity,
+  structure::{entity::RTPSEntity, rpc::SampleIdentity},
   StatusEvented, GUID,
 };
 use super::wrappers::{DAWrapper, NoKeyWrapper};
@@ -166,6 +178,77 @@ where
     Ok(result)
   }
 
+  /// Like [`take`](Self::take), but appends the samples to the given buffer
+  /// instead of returning a new `Vec`. Returns the number of samples appended.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// # let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # // NoKey is important
+  /// # let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType {}
+  /// #
+  /// let mut data_reader = subscriber.create_datareader_no_key::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  /// let mut buffer = Vec::with_capacity(64);
+  /// let count = data_reader.take_into(&mut buffer, 64, ReadCondition::not_read());
+  /// ```
+  pub fn take_into(
+    &mut self,
+    buffer: &mut Vec<DataSample<D>>,
+    max_samples: usize,
+    read_condition: ReadCondition,
+  ) -> ReadResult<usize> {
+    let before = buffer.len();
+    self
+      .keyed_datareader
+      .take_with(max_samples, read_condition, |ks| {
+        if let Some(s) = DataSample::<D>::from_with_key(ks) {
+          buffer.push(s);
+        }
+      })?;
+    Ok(buffer.len() - before)
+  }
+
+  /// See
+  /// [`with_key::DataReader::read_loaned`](crate::with_key::DataReader::read_loaned)
+  pub fn read_loaned(&mut self, max_samples: usize) -> ReadResult<Vec<LoanedSample>> {
+    self.keyed_datareader.read_loaned(max_samples)
+  }
+
+  /// Like [`read`](Self::read), but passes each sample to `f` by reference
+  /// instead of collecting them to a new `Vec`. Returns the number of samples
+  /// visited.
+  pub fn read_with<F>(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+    mut f: F,
+  ) -> ReadResult<usize>
+  where
+    F: FnMut(DataSample<&D>),
+  {
+    let mut count = 0;
+    self
+      .keyed_datareader
+      .read_with(max_samples, read_condition, |ks| {
+        if let Some(s) = DataSample::<D>::from_with_key_ref(ks) {
+          count += 1;
+          f(s);
+        }
+      })?;
+    Ok(count)
+  }
+
   /// Reads next unread sample
   ///
   /// # Examples
@@ -387,8 +470,25 @@ where
         .map(|ds| ds.value),
     )
   }
-  /*
-  /// Gets latest RequestedDeadlineMissed status
+  /// See
+  /// [`with_key::DataReader::acknowledge`](crate::with_key::DataReader::acknowledge).
+  pub fn acknowledge(&self, samples: impl IntoIterator<Item = SampleIdentity>) -> ReadResult<()> {
+    self.keyed_datareader.acknowledge(samples)
+  }
+
+  /// The SAMPLE_REJECTED communication status. Resets its change count. See
+  /// [`communication_status`](crate::communication_status).
+  pub fn get_sample_rejected_status(&self) -> SampleRejectedStatus {
+    self.keyed_datareader.get_sample_rejected_status()
+  }
+
+  /// The LIVELINESS_CHANGED communication status. Resets its change counts.
+  pub fn get_liveliness_changed_status(&self) -> LivelinessChangedStatus {
+    self.keyed_datareader.get_liveliness_changed_status()
+  }
+
+  /// The REQUESTED_DEADLINE_MISSED communication status. Resets its change
+  /// count.
   ///
   /// # Examples
   ///
@@ -408,19 +508,38 @@ where
   /// # #[derive(Serialize, Deserialize)]
   /// # struct SomeType {}
   /// #
-  /// let mut data_reader = subscriber.create_datareader_no_key::<SomeType, CDRDeserializerAdapter<_>>(topic, None).unwrap();
-  /// if let Ok(Some(status)) = data_reader.get_requested_deadline_missed_status() {
-  ///   // Do something
-  /// }
+  /// let data_reader = subscriber.create_datareader_no_key::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  /// let status = data_reader.get_requested_deadline_missed_status();
+  /// assert_eq!(status.total_count, 0);
   /// ```
+  pub fn get_requested_deadline_missed_status(&self) -> RequestedDeadlineMissedStatus {
+    self.keyed_datareader.get_requested_deadline_missed_status()
+  }
+
+  /// The REQUESTED_INCOMPATIBLE_QOS communication status. Resets its change
+  /// count.
+  pub fn get_requested_incompatible_qos_status(&self) -> RequestedIncompatibleQosStatus {
+    self
+      .keyed_datareader
+      .get_requested_incompatible_qos_status()
+  }
 
+  /// The SAMPLE_LOST communication status. Resets its change count.
+  pub fn get_sample_lost_status(&self) -> SampleLostStatus {
+    self.keyed_datareader.get_sample_lost_status()
+  }
 
-  pub fn get_requested_deadline_missed_status(
-    &mut self,
-  ) -> ReadResult<Option<RequestedDeadlineMissedStatus>> {
-    self.keyed_datareader.get_requested_deadline_missed_status()
+  /// The SUBSCRIPTION_MATCHED communication status. Resets its change counts.
+  pub fn get_subscription_matched_status(&self) -> SubscriptionMatchedStatus {
+    self.keyed_datareader.get_subscription_matched_status()
+  }
+
+  /// Sets the listener that is called back as the communication statuses of
+  /// this DataReader change, replacing any previous one. `None` removes it.
+  /// See [`listener`](crate::listener).
+  pub fn set_listener(&self, listener: Option<Box<dyn DataReaderListener>>) {
+    self.keyed_datareader.set_listener(listener);
   }
-  */
 
   /// An async stream for reading the (bare) data samples
   pub fn async_bare_sample_stream(self) -> BareDataReaderStream<D, DA> {
@@ -435,6 +554,79 @@ where
       keyed_stream: self.keyed_datareader.async_sample_stream(),
     }
   }
+
+  /// Blocks until there are samples that `read_condition` selects, or
+  /// `timeout` has passed. Returns whether there are such samples. See
+  /// [`with_key::DataReader::wait_for_samples`](crate::with_key::DataReader::wait_for_samples).
+  pub fn wait_for_samples(
+    &mut self,
+    read_condition: ReadCondition,
+    timeout: StdDuration,
+  ) -> ReadResult<bool> {
+    self
+      .keyed_datareader
+      .wait_for_samples(read_condition, timeout)
+  }
+}
+
+impl<D: 'static, DA> DataReader<D, DA>
+where
+  D: Serialize,
+  DA: DefaultDecoder<D>,
+{
+  /// Reads at most `max_samples` samples that the [`QueryCondition`]
+  /// selects. Samples that do not pass the query are not marked read.
+  pub fn read_query(
+    &mut self,
+    max_samples: usize,
+    query_condition: &QueryCondition,
+  ) -> ReadResult<Vec<DataSample<&D>>> {
+    let values = self.keyed_datareader.read_matching(
+      max_samples,
+      query_condition.read_condition(),
+      |w: &NoKeyWrapper<D>| query_condition.matches(&w.d),
+    )?;
+    Ok(
+      values
+        .into_iter()
+        .filter_map(DataSample::<D>::from_with_key_ref)
+        .collect(),
+    )
+  }
+
+  /// Takes at most `max_samples` samples that the [`QueryCondition`]
+  /// selects.
+  pub fn take_query(
+    &mut self,
+    max_samples: usize,
+    query_condition: &QueryCondition,
+  ) -> ReadResult<Vec<DataSample<D>>> {
+    let values = self.keyed_datareader.take_matching(
+      max_samples,
+      query_condition.read_condition(),
+      |w: &NoKeyWrapper<D>| query_condition.matches(&w.d),
+    )?;
+    Ok(
+      values
+        .into_iter()
+        .filter_map(DataSample::<D>::from_with_key)
+        .collect(),
+    )
+  }
+
+  /// Like [`wait_for_samples`](Self::wait_for_samples), but waits for samples
+  /// that the [`QueryCondition`] selects.
+  pub fn wait_for_query(
+    &mut self,
+    query_condition: &QueryCondition,
+    timeout: StdDuration,
+  ) -> ReadResult<bool> {
+    self.keyed_datareader.wait_for_samples_matching(
+      query_condition.read_condition(),
+      |w: &NoKeyWrapper<D>| query_condition.matches(&w.d),
+      timeout,
+    )
+  }
 }
 
 /// WARNING! UNTESTED
@@ -565,6 +757,20 @@ where
   }
 }
 
+impl<D, DA> Entity for DataReader<D, DA>
+where
+  D: 'static,
+  DA: DeserializerAdapter<D>,
+{
+  fn get_status_changes(&self) -> StatusMask {
+    self.keyed_datareader.get_status_changes()
+  }
+
+  fn get_statuscondition(&self) -> StatusCondition {
+    self.keyed_datareader.get_statuscondition()
+  }
+}
+
 // ----------------------------------------------
 // ----------------------------------------------
 
@@ -590,6 +796,14 @@ where
       keyed_stream: self.keyed_stream.async_event_stream(),
     }
   }
+
+  /// See
+  /// [`with_key::DataReaderStream::until_shutdown`](crate::with_key::DataReaderStream::until_shutdown)
+  pub fn until_shutdown(self, shutdown: &ShutdownToken) -> Self {
+    Self {
+      keyed_stream: self.keyed_stream.until_shutdown(shutdown),
+    }
+  }
 }
 
 // https://users.rust-lang.org/t/take-in-impl-future-cannot-borrow-data-in-a-dereference-of-pin/52042
@@ -618,20 +832,32 @@ where
         // has not left a waker behind to wake us up. Therefore, we need to loop
         // and try again until we get a returnable result or Pending.
         Poll::Ready(Some(Ok(Sample::Dispose(_)))) => (), // continue looping
-        Poll::Ready(None) => break Poll::Ready(None),    // This should never happen
+        Poll::Ready(None) => break Poll::Ready(None),    // shut down
         Poll::Pending => break Poll::Pending,
       }
     } // loop
   }
 }
 
+impl<D, DA> BareDataReaderStream<D, DA>
+where
+  D: 'static,
+  DA: DefaultDecoder<D>,
+{
+  /// See
+  /// [`with_key::BareDataReaderStream::take_next_timeout`](crate::with_key::BareDataReaderStream::take_next_timeout)
+  pub async fn take_next_timeout(&mut self, timeout: StdDuration) -> ReadResult<Option<D>> {
+    next_timeout(self, timeout).await
+  }
+}
+
 impl<D, DA> FusedStream for BareDataReaderStream<D, DA>
 where
   D: 'static,
   DA: DefaultDecoder<D>,
 {
   fn is_terminated(&self) -> bool {
-    false // Never terminate. This means it is always valid to call poll_next().
+    self.keyed_stream.is_terminated()
   }
 }
 
@@ -657,6 +883,14 @@ where
       keyed_stream: self.keyed_stream.async_event_stream(),
     }
   }
+
+  /// See
+  /// [`with_key::DataReaderStream::until_shutdown`](crate::with_key::DataReaderStream::until_shutdown)
+  pub fn until_shutdown(self, shutdown: &ShutdownToken) -> Self {
+    Self {
+      keyed_stream: self.keyed_stream.until_shutdown(shutdown),
+    }
+  }
 }
 
 // https://users.rust-lang.org/t/take-in-impl-future-cannot-borrow-data-in-a-dereference-of-pin/52042
@@ -690,20 +924,35 @@ where
           // and try again until we get a returnable result or Pending.
           Sample::Dispose(_) => (),
         },
-        Poll::Ready(None) => break Poll::Ready(None), // This should never happen
+        Poll::Ready(None) => break Poll::Ready(None), // shut down
         Poll::Pending => break Poll::Pending,
       }
     } // loop
   }
 }
 
+impl<D, DA> DataReaderStream<D, DA>
+where
+  D: 'static,
+  DA: DefaultDecoder<D>,
+{
+  /// See
+  /// [`with_key::DataReaderStream::take_next_timeout`](crate::with_key::DataReaderStream::take_next_timeout)
+  pub async fn take_next_timeout(
+    &mut self,
+    timeout: StdDuration,
+  ) -> ReadResult<Option<DataSample<D>>> {
+    next_timeout(self, timeout).await
+  }
+}
+
 impl<D, DA> FusedStream for DataReaderStream<D, DA>
 where
   D: 'static,
   DA: DefaultDecoder<D>,
 {
   fn is_terminated(&self) -> bool {
-    false // Never terminate. This means it is always valid to call poll_next().
+    self.keyed_stream.is_terminated()
   }
 }
 
@@ -736,6 +985,6 @@ where
   DA: DeserializerAdapter<D>,
 {
   fn is_terminated(&self) -> bool {
-    false // Never terminate. This means it is always valid to call poll_next().
+    self.keyed_stream.is_terminated()
   }
 }
@@ -1,20 +1,29 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, time::Duration};
 
 use mio_06::Evented;
 
 use crate::{
   dds::{
     adapters::no_key::SerializerAdapter,
+    communication_status::{
+      Entity, LivelinessLostStatus, OfferedDeadlineMissedStatus, OfferedIncompatibleQosStatus,
+      PublicationMatchedStatus, StatusMask,
+    },
+    listener::DataWriterListener,
     pubsub::Publisher,
     qos::{HasQoSPolicy, QosPolicies},
     result::{unwrap_no_key_write_error, WriteResult},
+    sliced::SlicedSample,
     statusevents::{DataWriterStatus, StatusReceiverStream},
     topic::Topic,
+    waitset::StatusCondition,
     with_key::datawriter as datawriter_with_key,
   },
   discovery::sedp_messages::SubscriptionBuiltinTopicData,
   serialization::CDRSerializerAdapter,
-  structure::{entity::RTPSEntity, rpc::SampleIdentity, time::Timestamp},
+  structure::{
+    entity::RTPSEntity, rpc::SampleIdentity, sequence_number::SequenceNumber, time::Timestamp,
+  },
   StatusEvented, GUID,
 };
 use super::wrappers::{NoKeyWrapper, SAWrapper};
@@ -101,6 +110,41 @@ where
       .map_err(unwrap_no_key_write_error)
   }
 
+  /// See
+  /// [`with_key::DataWriter::set_sample_reuse`](crate::with_key::DataWriter::set_sample_reuse)
+  pub fn set_sample_reuse(&self, enabled: bool) {
+    self.keyed_datawriter.set_sample_reuse(enabled);
+  }
+
+  /// See [`with_key::DataWriter::rewrite`](crate::with_key::DataWriter::rewrite)
+  pub fn rewrite(
+    &self,
+    write_options: datawriter_with_key::WriteOptions,
+  ) -> WriteResult<SampleIdentity, ()> {
+    self.keyed_datawriter.rewrite(write_options)
+  }
+
+  /// Begins a sample, which is written in chunks. The chunks must add up to
+  /// `total_len` bytes of serialized data. See [`SlicedSample`].
+  pub fn begin_sample(&self, total_len: usize) -> WriteResult<SlicedSample<'_>, ()> {
+    self.keyed_datawriter.begin_sample(&(), total_len)
+  }
+
+  /// See
+  /// [`with_key::DataWriter::write_modified`](crate::with_key::DataWriter::write_modified)
+  pub fn write_modified<F>(
+    &self,
+    modify: F,
+    write_options: datawriter_with_key::WriteOptions,
+  ) -> WriteResult<SampleIdentity, ()>
+  where
+    F: FnOnce(&mut D) -> bool,
+  {
+    self
+      .keyed_datawriter
+      .write_modified(|w| modify(&mut w.d), write_options)
+  }
+
   /// Waits for all acknowledgements to finish
   ///
   /// # Examples
@@ -128,128 +172,36 @@ where
   pub fn wait_for_acknowledgments(&self, max_wait: Duration) -> WriteResult<bool, ()> {
     self.keyed_datawriter.wait_for_acknowledgments(max_wait)
   }
-  /*
-  // status queries
-  /// Unimplemented. <b>Do not use</b>.
-  ///
-  /// # Examples
-  ///
-  // TODO: enable run when implemented
-  /// ```no_run
-  /// # use serde::{Serialize, Deserialize};
-  /// # use rustdds::*;
-  /// # use rustdds::no_key::DataWriter;
-  /// # use rustdds::serialization::CDRSerializerAdapter;
-  /// #
-  /// let domain_participant = DomainParticipant::new(0).unwrap();
-  /// let qos = QosPolicyBuilder::new().build();
-  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
-  ///
-  /// # #[derive(Serialize, Deserialize)]
-  /// # struct SomeType {}
-  /// #
-  /// // NoKey is important
-  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
-  /// let data_writer = publisher.create_datawriter_no_key::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
-  ///
-  /// if let Ok(status) = data_writer.get_liveliness_lost_status() {
-  ///   // Do something
-  /// }
-  /// ```
-  pub fn get_liveliness_lost_status(&self) -> Result<LivelinessLostStatus> {
+  /// The LIVELINESS_LOST communication status. Resets its change count. See
+  /// [`communication_status`](crate::communication_status).
+  pub fn get_liveliness_lost_status(&self) -> LivelinessLostStatus {
     self.keyed_datawriter.get_liveliness_lost_status()
   }
 
-  /// Should get latest offered deadline missed status. <b>Do not use yet</b> use `get_status_lister` instead for the moment.
-  ///
-  /// # Examples
-  ///
-  /// ```
-  /// # use serde::{Serialize, Deserialize};
-  /// # use std::time::Duration;
-  /// # use rustdds::*;
-  /// # use rustdds::no_key::DataWriter;
-  /// # use rustdds::serialization::CDRSerializerAdapter;
-  /// #
-  /// let domain_participant = DomainParticipant::new(0).unwrap();
-  /// let qos = QosPolicyBuilder::new().build();
-  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
-  ///
-  /// # #[derive(Serialize, Deserialize)]
-  /// # struct SomeType {}
-  /// #
-  /// // NoKey is important
-  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
-  /// let data_writer = publisher.create_datawriter_no_key::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
-  ///
-  /// if let Ok(odl_status) = data_writer.get_offered_deadline_missed_status() {
-  ///   // Do something
-  /// }
-  /// ```
-  pub fn get_offered_deadline_missed_status(&self) -> Result<OfferedDeadlineMissedStatus> {
+  /// The OFFERED_DEADLINE_MISSED communication status. Resets its change
+  /// count.
+  pub fn get_offered_deadline_missed_status(&self) -> OfferedDeadlineMissedStatus {
     self.keyed_datawriter.get_offered_deadline_missed_status()
   }
 
-  /// Unimplemented. <b>Do not use</b>.
-  ///
-  /// # Examples
-  ///
-  // TODO: enable run when implemented
-  /// ```no_run
-  /// # use serde::{Serialize, Deserialize};
-  /// # use rustdds::*;
-  /// # use rustdds::no_key::DataWriter;
-  /// # use rustdds::serialization::CDRSerializerAdapter;
-  /// #
-  /// let domain_participant = DomainParticipant::new(0).unwrap();
-  /// let qos = QosPolicyBuilder::new().build();
-  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
-  ///
-  /// # #[derive(Serialize, Deserialize)]
-  /// # struct SomeType {}
-  /// #
-  /// // NoKey is important
-  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
-  /// let data_writer = publisher.create_datawriter_no_key::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
-  ///
-  /// if let Ok(status) = data_writer.get_offered_incompatible_qos_status() {
-  ///   // Do something
-  /// }
-  /// ```
-  pub fn get_offered_incompatible_qos_status(&self) -> Result<OfferedIncompatibleQosStatus> {
+  /// The OFFERED_INCOMPATIBLE_QOS communication status. Resets its change
+  /// count.
+  pub fn get_offered_incompatible_qos_status(&self) -> OfferedIncompatibleQosStatus {
     self.keyed_datawriter.get_offered_incompatible_qos_status()
   }
 
-  /// Unimplemented. <b>Do not use</b>.
-  ///
-  /// # Examples
-  ///
-  // TODO: enable run when implemented
-  /// ```no_run
-  /// # use serde::{Serialize, Deserialize};
-  /// # use rustdds::*;
-  /// # use rustdds::no_key::DataWriter;
-  /// # use rustdds::serialization::CDRSerializerAdapter;
-  /// #
-  /// let domain_participant = DomainParticipant::new(0).unwrap();
-  /// let qos = QosPolicyBuilder::new().build();
-  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
-  ///
-  /// # #[derive(Serialize, Deserialize)]
-  /// # struct SomeType {}
-  /// #
-  /// // NoKey is important
-  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
-  /// let data_writer = publisher.create_datawriter_no_key::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
-  ///
-  /// if let Ok(status) = data_writer.get_publication_matched_status() {
-  ///   // Do something
-  /// }
-  /// ```
-  pub fn get_publication_matched_status(&self) -> Result<PublicationMatchedStatus> {
+  /// The PUBLICATION_MATCHED communication status. Resets its change counts.
+  pub fn get_publication_matched_status(&self) -> PublicationMatchedStatus {
     self.keyed_datawriter.get_publication_matched_status()
   }
-  */
+
+  /// Sets the listener that is called back as the communication statuses of
+  /// this DataWriter change, replacing any previous one. `None` removes it.
+  /// See [`listener`](crate::listener).
+  pub fn set_listener(&self, listener: Option<Box<dyn DataWriterListener>>) {
+    self.keyed_datawriter.set_listener(listener);
+  }
+
   /// Topic this DataWriter is connected to.
   ///
   /// # Examples
@@ -277,6 +229,59 @@ where
     self.keyed_datawriter.topic()
   }
 
+  /// Number of samples that were not sent, because a newer sample superseded
+  /// them. A NO_KEY topic has only one instance. Always zero, unless the
+  /// [`Conflation`](crate::policy::Conflation) policy is enabled.
+  pub fn conflated_sample_count(&self) -> u64 {
+    self.keyed_datawriter.conflated_sample_count()
+  }
+
+  /// See
+  /// [`with_key::DataWriter::sequence_number_watermarks`](crate::with_key::DataWriter::sequence_number_watermarks).
+  pub fn sequence_number_watermarks(&self) -> datawriter_with_key::SequenceNumberWatermarks {
+    self.keyed_datawriter.sequence_number_watermarks()
+  }
+
+  /// See
+  /// [`with_key::DataWriter::set_unacknowledged_threshold`](crate::with_key::DataWriter::set_unacknowledged_threshold).
+  pub fn set_unacknowledged_threshold(&self, threshold: Option<u64>) {
+    self
+      .keyed_datawriter
+      .set_unacknowledged_threshold(threshold);
+  }
+
+  /// See
+  /// [`with_key::DataWriter::history_status`](crate::with_key::DataWriter::history_status).
+  pub fn history_status(&self) -> WriteResult<datawriter_with_key::WriterHistoryStatus, ()> {
+    self.keyed_datawriter.history_status()
+  }
+
+  /// See
+  /// [`with_key::DataWriter::purge_acknowledged`](crate::with_key::DataWriter::purge_acknowledged).
+  pub fn purge_acknowledged(&self) -> WriteResult<usize, ()> {
+    self.keyed_datawriter.purge_acknowledged()
+  }
+
+  /// See
+  /// [`with_key::DataWriter::clear_history`](crate::with_key::DataWriter::clear_history).
+  pub fn clear_history(&self) -> WriteResult<usize, ()> {
+    self.keyed_datawriter.clear_history()
+  }
+
+  /// See
+  /// [`with_key::DataWriter::unacknowledged_samples`](crate::with_key::DataWriter::unacknowledged_samples).
+  pub fn unacknowledged_samples(&self) -> WriteResult<BTreeMap<GUID, Vec<SequenceNumber>>, ()> {
+    self.keyed_datawriter.unacknowledged_samples()
+  }
+
+  /// See
+  /// [`with_key::DataWriter::redeliver_unacknowledged`](crate::with_key::DataWriter::redeliver_unacknowledged).
+  pub fn redeliver_unacknowledged(&self, reader: GUID, to_reader: GUID) -> WriteResult<usize, ()> {
+    self
+      .keyed_datawriter
+      .redeliver_unacknowledged(reader, to_reader)
+  }
+
   /// Publisher this DataWriter is connected to.
   ///
   /// # Examples
@@ -422,6 +427,16 @@ impl<D, SA: SerializerAdapter<D>> RTPSEntity for DataWriter<D, SA> {
   }
 }
 
+impl<D, SA: SerializerAdapter<D>> Entity for DataWriter<D, SA> {
+  fn get_status_changes(&self) -> StatusMask {
+    self.keyed_datawriter.get_status_changes()
+  }
+
+  fn get_statuscondition(&self) -> StatusCondition {
+    self.keyed_datawriter.get_statuscondition()
+  }
+}
+
 impl<D, SA: SerializerAdapter<D>> HasQoSPolicy for DataWriter<D, SA> {
   fn qos(&self) -> QosPolicies {
     self.keyed_datawriter.qos()
@@ -463,6 +478,16 @@ where
   pub async fn async_wait_for_acknowledgments(&self) -> WriteResult<bool, ()> {
     self.keyed_datawriter.async_wait_for_acknowledgments().await
   } // fn
+
+  pub async fn async_wait_for_acknowledgments_with_timeout(
+    &self,
+    max_wait: Duration,
+  ) -> WriteResult<bool, ()> {
+    self
+      .keyed_datawriter
+      .async_wait_for_acknowledgments_with_timeout(max_wait)
+      .await
+  }
 } // impl
 
 #[cfg(test)]
@@ -14,6 +14,7 @@ use crate::{
     no_key::{datasample::DeserializedCacheChange, wrappers::DecodeWrapper},
     qos::*,
     result::ReadResult,
+    sliced::SampleSlices,
     statusevents::*,
     with_key,
   },
@@ -79,6 +80,12 @@ where
     }
   }
 
+  /// See
+  /// [`with_key::SimpleDataReader::try_take_one_sliced`](crate::with_key::SimpleDataReader::try_take_one_sliced)
+  pub fn try_take_one_sliced(&self) -> ReadResult<Option<SampleSlices>> {
+    self.keyed_simpledatareader.try_take_one_sliced()
+  }
+
   pub fn qos(&self) -> &QosPolicies {
     self.keyed_simpledatareader.qos()
   }
@@ -3,7 +3,9 @@ use std::{marker::PhantomData, ops::Deref};
 use bytes::Bytes;
 
 use crate::{
-  dds::adapters::*, messages::submessages::submessages::RepresentationIdentifier, Keyed,
+  dds::{adapters::*, content_filter::DdsSqlFilter},
+  messages::submessages::submessages::RepresentationIdentifier,
+  Keyed,
 };
 
 // This wrapper is used to convert NO_KEY types to WITH_KEY
@@ -55,6 +57,10 @@ where
   fn to_bytes(value: &NoKeyWrapper<D>) -> Result<Bytes, SA::Error> {
     SA::to_bytes(&value.d)
   }
+
+  fn matches_sql_filter(value: &NoKeyWrapper<D>, filter: &DdsSqlFilter) -> Option<bool> {
+    SA::matches_sql_filter(&value.d, filter)
+  }
 }
 
 // This is the point of wrapping. Implement dummy key serialization
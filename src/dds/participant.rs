@@ -1,11 +1,11 @@
 // use mio::Token;
 use std::{
-  collections::HashMap,
+  collections::{BTreeMap, HashMap},
   io,
   io::ErrorKind,
-  net::Ipv4Addr,
+  net::{Ipv4Addr, SocketAddr},
   pin::Pin,
-  sync::{atomic, Arc, Mutex, RwLock, Weak},
+  sync::{atomic, Arc, Mutex, PoisonError, RwLock, Weak},
   task::{Context, Poll},
   thread,
   thread::JoinHandle,
@@ -20,30 +20,53 @@ use futures::stream::{FusedStream, Stream};
 use log::{debug, error, info, trace, warn};
 
 use crate::{
-  create_error_out_of_resources, create_error_poisoned,
+  create_error_bad_parameter, create_error_out_of_resources, create_error_poisoned,
   dds::{
+    compliance::ComplianceMode,
+    content_filter::ContentFilteredTopic,
+    discovery_damping::DiscoveryDampingSettings,
+    discovery_network::{DiscoveryNetworkSettings, InterfaceSelector},
+    listener::{DomainParticipantListener, ListenerSlot},
+    peer_resolution::{PeerResolution, PeerResolutionThread, PeerResolver},
+    protocol_identity::ProtocolIdentity,
     pubsub::*,
     qos::*,
+    resource_profile::{ResourceProfile, ResourceSettings, SocketStatistics},
     result::*,
     statusevents::{
       sync_status_channel, DomainParticipantStatusEvent, StatusChannelReceiver, StatusChannelSender,
     },
+    flow_control::FlowControllerSettings,
+    tcp_transport::TcpTransportSettings,
     topic::*,
+    traffic_isolation::{TrafficClassSettings, TrafficIsolationSettings},
     typedesc::TypeDesc,
+    xtypes::TypeObject,
   },
   discovery::{
     discovery::{Discovery, DiscoveryCommand},
-    discovery_db::DiscoveryDB,
+    discovery_db::{discovery_db_read, DiscoveryDB},
+    lease_assertion::SpdpTransmissions,
     sedp_messages::DiscoveredTopicData,
+    spdp_participant_data::SpdpDiscoveredParticipantData,
+  },
+  network::{
+    constant::*,
+    socket_buffers::{SocketBufferSizes, SocketCounters},
+    tcp::{tcp_locators, TcpTransport},
+    udp_listener::UDPListener,
+    udp_sender::{MulticastOptions, SenderSocketSettings},
+    util::{get_allowed_unicast_ip_addrs, get_local_unicast_locators},
   },
-  network::{constant::*, udp_listener::UDPListener},
   rtps::{
     constant::*,
     dp_event_loop::{DPEventLoop, DomainInfo, EventLoopCommand},
     reader::*,
     writer::WriterIngredients,
   },
-  structure::{dds_cache::DDSCache, entity::RTPSEntity, guid::*, locator::Locator},
+  structure::{
+    dds_cache::DDSCache, entity::RTPSEntity, guid::*, locator::Locator, time::Timestamp,
+  },
   StatusEvented,
 };
 #[cfg(feature = "security")]
@@ -51,8 +74,16 @@ use crate::{
   create_error_internal, create_error_not_allowed_by_security,
   security::{
     self,
-    config::DomainParticipantSecurityConfigFiles,
+    audit::HandshakeAudit,
+    config::{rekey_grace_period_property, CryptoKeySize, DomainParticipantSecurityConfigFiles},
+    cryptographic::SessionKeyCacheStatistics,
+    decode_limits::{DecodeLimits, DecodeRejectionCounts},
+    error_counts::SecurityErrorCounts,
     security_plugins::{SecurityPlugins, SecurityPluginsHandle},
+    startup::{
+      SecurityStartupCallback, SecurityStartupProgress, SecurityStartupReporter,
+      SecurityStartupStage,
+    },
     AccessControl, Authentication, Cryptographic,
   },
 };
@@ -61,6 +92,7 @@ use crate::no_security::SecurityPluginsHandle;
 
 pub struct DomainParticipantBuilder {
   domain_id: u16,
+  participant_id: Option<u16>,
 
   #[allow(dead_code)] /* only_networks is a placeholder for a feature to limit
   which interfaces the DomainParticipant will talk to. */
@@ -70,20 +102,285 @@ pub struct DomainParticipantBuilder {
   security_plugins: Option<SecurityPlugins>,
   #[cfg(feature = "security")]
   sec_properties: Option<policy::Property>, // Properties for configuring security plugins
+  #[cfg(feature = "security")]
+  handshake_audit: Option<HandshakeAudit>,
+  #[cfg(feature = "security")]
+  decode_limits: Option<DecodeLimits>,
+  #[cfg(feature = "security")]
+  crypto_key_size: Option<CryptoKeySize>,
+  #[cfg(feature = "security")]
+  crypto_rekey_grace_period: Option<Duration>,
+  #[cfg(feature = "security")]
+  security_startup_progress: Option<SecurityStartupCallback>,
+
+  compliance_mode: ComplianceMode,
+  protocol_identity: ProtocolIdentity,
+  resource_settings: ResourceSettings,
+  discovery_network: DiscoveryNetworkSettings,
+  peer_resolution: Option<PeerResolution>,
+  tcp_transport: Option<TcpTransportSettings>,
+  traffic_isolation: Option<TrafficIsolationSettings>,
+  flow_control: FlowControllerSettings,
+  discovery_damping: DiscoveryDampingSettings,
+  self_health_period: Option<Duration>,
+  intra_process: bool,
+  share_in_process: bool,
+}
+
+// Participants built with DomainParticipantBuilder::share_in_process, by
+// domain, with the settings they were built with.
+static SHARED_PARTICIPANTS: Mutex<BTreeMap<u16, (SharedSettings, DomainParticipantWeak)>> =
+  Mutex::new(BTreeMap::new());
+
+// Settings that participants sharing one in process must agree on
+#[derive(PartialEq)]
+struct SharedSettings {
+  participant_id: Option<u16>,
+  compliance_mode: ComplianceMode,
+  protocol_identity: ProtocolIdentity,
+  resource_settings: ResourceSettings,
+  discovery_network: DiscoveryNetworkSettings,
+  tcp_transport: Option<TcpTransportSettings>,
+  traffic_isolation: Option<TrafficIsolationSettings>,
+  flow_control: FlowControllerSettings,
+  discovery_damping: DiscoveryDampingSettings,
+  self_health_period: Option<Duration>,
+  intra_process: bool,
 }
 
 impl DomainParticipantBuilder {
   pub fn new(domain_id: u16) -> DomainParticipantBuilder {
     DomainParticipantBuilder {
       domain_id,
+      participant_id: None,
       only_networks: None,
       #[cfg(feature = "security")]
       security_plugins: None,
       #[cfg(feature = "security")]
       sec_properties: None,
+      #[cfg(feature = "security")]
+      handshake_audit: None,
+      #[cfg(feature = "security")]
+      decode_limits: None,
+      #[cfg(feature = "security")]
+      crypto_key_size: None,
+      #[cfg(feature = "security")]
+      crypto_rekey_grace_period: None,
+      #[cfg(feature = "security")]
+      security_startup_progress: None,
+      compliance_mode: ComplianceMode::default(),
+      protocol_identity: ProtocolIdentity::default(),
+      resource_settings: ResourceSettings::default(),
+      discovery_network: DiscoveryNetworkSettings::default(),
+      peer_resolution: None,
+      tcp_transport: None,
+      traffic_isolation: None,
+      flow_control: FlowControllerSettings::default(),
+      discovery_damping: DiscoveryDampingSettings::default(),
+      self_health_period: None,
+      intra_process: false,
+      share_in_process: false,
     }
   }
 
+  /// Use the given participant id, instead of the lowest one whose ports are
+  /// free on this host. The id determines the unicast port numbers of the
+  /// participant. Building fails, if the ports are already in use.
+  pub fn participant_id(mut self, participant_id: u16) -> Self {
+    self.participant_id = Some(participant_id);
+    self
+  }
+
+  pub(crate) fn domain_id(&self) -> u16 {
+    self.domain_id
+  }
+
+  pub(crate) fn requested_participant_id(&self) -> Option<u16> {
+    self.participant_id
+  }
+
+  /// Choose between strict RTPS specification compliance and interoperability
+  /// workarounds for received messages. The default is
+  /// [`ComplianceMode::Permissive`].
+  pub fn compliance_mode(mut self, mode: ComplianceMode) -> Self {
+    self.compliance_mode = mode;
+    self
+  }
+
+  /// Advertise another RTPS protocol version or vendor id than those of
+  /// RustDDS, for testing and emulation. See
+  /// [`protocol_identity`](crate::protocol_identity). The default is
+  /// [`ProtocolIdentity::THIS_IMPLEMENTATION`].
+  pub fn protocol_identity(mut self, identity: ProtocolIdentity) -> Self {
+    self.protocol_identity = identity;
+    self
+  }
+
+  /// Use the resource settings of a preset profile. The default is
+  /// [`ResourceProfile::Default`].
+  pub fn resource_profile(mut self, profile: ResourceProfile) -> Self {
+    self.resource_settings = profile.settings();
+    self
+  }
+
+  /// Set resource usage in detail. Overrides any earlier
+  /// [`resource_profile`](Self::resource_profile).
+  pub fn resource_settings(mut self, settings: ResourceSettings) -> Self {
+    self.resource_settings = settings;
+    self
+  }
+
+  /// Multicast and initial peer settings of discovery. The default is
+  /// multicast discovery as specified by RTPS.
+  pub fn discovery_network(mut self, settings: DiscoveryNetworkSettings) -> Self {
+    self.discovery_network = settings;
+    self
+  }
+
+  /// Look up hosts to send SPDP announcements to with `resolver`, every
+  /// `period`, in addition to the initial peers. See
+  /// [`peer_resolution`](crate::peer_resolution). The default is not to look
+  /// up peers.
+  ///
+  /// With [`share_in_process`](Self::share_in_process), only the build that
+  /// creates the participant uses its resolver.
+  pub fn peer_resolver(mut self, resolver: impl PeerResolver + 'static, period: Duration) -> Self {
+    self.peer_resolution = Some(PeerResolution {
+      resolver: Box::new(resolver),
+      period,
+    });
+    self
+  }
+
+  /// Send unicast traffic over TCP, for networks that block UDP. See
+  /// [`tcp_transport`](crate::tcp_transport). The default is to use UDP only.
+  pub fn tcp_transport(mut self, settings: TcpTransportSettings) -> Self {
+    self.tcp_transport = Some(settings);
+    self
+  }
+
+  /// Send metatraffic from sockets of its own, with their own interfaces,
+  /// DSCP marking and buffer sizes. See
+  /// [`traffic_isolation`](crate::traffic_isolation). The default is to send
+  /// all traffic from the same sockets.
+  pub fn traffic_isolation(mut self, settings: TrafficIsolationSettings) -> Self {
+    self.traffic_isolation = Some(settings);
+    self
+  }
+
+  /// Limit the bandwidth of the user DataWriters of the participant
+  /// together. See [`flow_control`](crate::flow_control). The default is no
+  /// limit.
+  pub fn flow_control(mut self, settings: FlowControllerSettings) -> Self {
+    self.flow_control = settings;
+    self
+  }
+
+  /// Damp the changes of remote endpoints that flap, and limit the rate of
+  /// changes from each remote participant. See
+  /// [`discovery_damping`](crate::discovery_damping). The default applies
+  /// every change as it arrives.
+  pub fn discovery_damping(mut self, settings: DiscoveryDampingSettings) -> Self {
+    self.discovery_damping = settings;
+    self
+  }
+
+  /// Publish the internal health of the participant every `period` on the
+  /// [`SELF_HEALTH_TOPIC_NAME`](crate::SELF_HEALTH_TOPIC_NAME) topic, as
+  /// [`ParticipantHealth`](crate::ParticipantHealth). The default is not to
+  /// publish.
+  pub fn self_health(mut self, period: Duration) -> Self {
+    self.self_health_period = Some(period);
+    self
+  }
+
+  /// Deliver samples written with
+  /// [`write_shared`](crate::with_key::DataWriter::write_shared) to the
+  /// DataReaders of this participant without serializing them. See
+  /// [`intra_process`](crate::intra_process). The default is false.
+  pub fn intra_process(mut self, enabled: bool) -> Self {
+    self.intra_process = enabled;
+    self
+  }
+
+  /// Share one participant with the others that are built with this option in
+  /// the same process and domain. The default is false.
+  ///
+  /// Applications where each component creates its own DomainParticipant
+  /// would otherwise run a discovery thread, a set of sockets and a
+  /// discovery database for each, and remote participants would discover,
+  /// and exchange SPDP and SEDP traffic with, each of them. Shared
+  /// participants are one RTPS participant: they have the same GUID prefix,
+  /// and remote participants see the endpoints of all components in it.
+  ///
+  /// The first build creates the participant, and later builds return views
+  /// of it, for as long as any of them is alive. Each view lists and deletes
+  /// only the Publishers and Subscribers created through it, with
+  /// [`get_publishers`](DomainParticipant::get_publishers),
+  /// [`get_subscribers`](DomainParticipant::get_subscribers) and
+  /// [`delete_contained_entities`](DomainParticipant::delete_contained_entities).
+  /// Everything else is common to the views: they compare equal, and they
+  /// have the same discovered data, QoS and status events.
+  ///
+  /// The later builds must have the same participant id, compliance mode,
+  /// protocol identity, resource, discovery network, TCP transport, traffic
+  /// isolation, flow control, discovery damping, self-health and
+  /// intra-process settings, and fail with `BadParameter` otherwise. Shared
+  /// participants cannot be secured, since each secure participant has its
+  /// own identity.
+  pub fn share_in_process(mut self, share: bool) -> Self {
+    self.share_in_process = share;
+    self
+  }
+
+  fn build_shared(mut self) -> CreateResult<DomainParticipant> {
+    #[cfg(feature = "security")]
+    if self.security_plugins.is_some() {
+      return create_error_bad_parameter!(
+        "A DomainParticipant with security cannot be shared in process."
+      );
+    }
+    let settings = SharedSettings {
+      participant_id: self.participant_id,
+      compliance_mode: self.compliance_mode,
+      protocol_identity: self.protocol_identity,
+      resource_settings: self.resource_settings,
+      discovery_network: self.discovery_network.clone(),
+      tcp_transport: self.tcp_transport.clone(),
+      traffic_isolation: self.traffic_isolation.clone(),
+      flow_control: self.flow_control,
+      discovery_damping: self.discovery_damping,
+      self_health_period: self.self_health_period,
+      intra_process: self.intra_process,
+    };
+    // The lock is held while building, so that concurrent builds in the same
+    // domain do not both create a participant.
+    let mut shared = SHARED_PARTICIPANTS
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner);
+    if let Some((shared_settings, participant)) = shared.get(&self.domain_id) {
+      if let Some(participant) = participant.clone().upgrade() {
+        if *shared_settings != settings {
+          return create_error_bad_parameter!(
+            "The DomainParticipant shared in domain {} was built with different settings.",
+            self.domain_id
+          );
+        }
+        // A view of its own, with no Publishers or Subscribers yet
+        return Ok(DomainParticipant {
+          dpi: participant.dpi,
+          contained: Arc::default(),
+        });
+      }
+    }
+
+    let domain_id = self.domain_id;
+    self.share_in_process = false;
+    let participant = self.build()?;
+    shared.insert(domain_id, (settings, participant.weak_clone()));
+    Ok(participant)
+  }
+
   #[cfg(feature = "security")]
   /// Low-level security configuration, which allows supplying custom plugins.
   pub fn security(
@@ -98,17 +395,201 @@ impl DomainParticipantBuilder {
     self
   }
 
+  #[cfg(feature = "security")]
+  /// Configure security with the given plugins. These may be custom
+  /// implementations of the plugin traits in
+  /// [`security_plugins`](crate::security_plugins), e.g. ones that keep keys in
+  /// a hardware security module, or the builtin plugins, in any combination.
+  ///
+  /// `sec_properties` are given to the plugins as the properties of the
+  /// participant QoS. The builtin plugins read their configuration, such as
+  /// the locations of certificates and keys, from them.
+  pub fn with_security_plugins(
+    mut self,
+    auth: Box<impl Authentication + 'static>,
+    access: Box<impl AccessControl + 'static>,
+    crypto: Box<impl Cryptographic + 'static>,
+    sec_properties: policy::Property,
+  ) -> Self {
+    self.security(auth, access, crypto, sec_properties);
+    self
+  }
+
   #[cfg(feature = "security")]
   /// Easier way to configure security.
-  pub fn builtin_security(mut self, configs: DomainParticipantSecurityConfigFiles) -> Self {
+  pub fn builtin_security(self, configs: DomainParticipantSecurityConfigFiles) -> Self {
     let auth = Box::new(security::AuthenticationBuiltin::new());
     let access = Box::new(security::AccessControlBuiltin::new());
     let crypto = Box::new(security::CryptographicBuiltin::new());
-    self.security(auth, access, crypto, configs.into_property_policy());
+    self.with_security_plugins(auth, access, crypto, configs.into_property_policy())
+  }
+
+  #[cfg(feature = "security")]
+  /// Record authentication handshakes and remote permissions validations to
+  /// the given audit store. Has no effect unless security is configured.
+  pub fn handshake_audit(mut self, audit: HandshakeAudit) -> Self {
+    self.handshake_audit = Some(audit);
+    self
+  }
+
+  #[cfg(feature = "security")]
+  /// Limits for secured data received from remote participants. Input
+  /// exceeding them is dropped before decoding. The default is
+  /// [`DecodeLimits::default()`]. Has no effect unless security is configured.
+  pub fn security_decode_limits(mut self, limits: DecodeLimits) -> Self {
+    self.decode_limits = Some(limits);
     self
   }
 
+  #[cfg(feature = "security")]
+  /// Length of the AES keys generated by the builtin cryptographic plugin.
+  /// This overrides a `dds.sec.crypto.keysize` property given with
+  /// [`security`](Self::security). Endpoints use the key size of the
+  /// participant, unless their own QoS properties specify one. Has no effect
+  /// unless security is configured.
+  pub fn crypto_key_size(mut self, key_size: CryptoKeySize) -> Self {
+    self.crypto_key_size = Some(key_size);
+    self
+  }
+
+  #[cfg(feature = "security")]
+  /// How long the builtin cryptographic plugin keeps the old keys of a remote
+  /// DataWriter or DataReader after receiving new ones, so that data encoded
+  /// with the old keys can still be decoded. The default is 10 seconds. Has
+  /// no effect unless security is configured.
+  ///
+  /// See [`DomainParticipant::rekey_local_endpoint`].
+  pub fn crypto_rekey_grace_period(mut self, grace_period: Duration) -> Self {
+    self.crypto_rekey_grace_period = Some(grace_period);
+    self
+  }
+
+  #[cfg(feature = "security")]
+  /// Call `progress` as each step of secure construction begins. The
+  /// callback runs on the thread that builds the participant. Has no effect
+  /// unless security is configured.
+  pub fn security_startup_progress(
+    mut self,
+    progress: impl FnMut(SecurityStartupProgress) + Send + 'static,
+  ) -> Self {
+    self.security_startup_progress = Some(Box::new(progress));
+    self
+  }
+
+  /// Like [`build`](Self::build), but the participant is built in a
+  /// background thread, so that e.g. validating security configuration does
+  /// not block the calling async task. Works with any async runtime.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipantBuilder;
+  /// let participant =
+  ///   futures::executor::block_on(DomainParticipantBuilder::new(0).build_async()).unwrap();
+  /// ```
+  pub async fn build_async(self) -> CreateResult<DomainParticipant> {
+    let (result_sender, result_receiver) = futures::channel::oneshot::channel();
+    thread::Builder::new()
+      .name("RustDDS participant builder".to_string())
+      .spawn(move || {
+        // Failing to send means that the future was dropped, and the
+        // participant is dropped here.
+        let _ = result_sender.send(self.build());
+      })?;
+    result_receiver
+      .await
+      .unwrap_or_else(|_| create_error_poisoned!("Participant builder thread panicked."))
+  }
+
   pub fn build(#[allow(unused_mut)] mut self) -> CreateResult<DomainParticipant> {
+    if self.share_in_process {
+      return self.build_shared();
+    }
+    if let Some(participant_id) = self.participant_id {
+      if participant_id >= PARTICIPANT_ID_LIMIT {
+        return create_error_bad_parameter!(
+          "ParticipantId {participant_id} is too large. Must be less than {PARTICIPANT_ID_LIMIT}."
+        );
+      }
+    }
+    if !self.discovery_network.spdp_multicast_address.is_multicast() {
+      return create_error_bad_parameter!(
+        "SPDP multicast address {} is not a multicast address.",
+        self.discovery_network.spdp_multicast_address
+      );
+    }
+    if !self
+      .discovery_network
+      .spdp_multicast_address_v6
+      .is_multicast()
+    {
+      return create_error_bad_parameter!(
+        "SPDP multicast address {} is not a multicast address.",
+        self.discovery_network.spdp_multicast_address_v6
+      );
+    }
+    if let Some(isolation) = &self.traffic_isolation {
+      for class in [&isolation.metatraffic, &isolation.user_traffic] {
+        if let Some(dscp) = class
+          .dscp
+          .filter(|dscp| *dscp > TrafficClassSettings::MAX_DSCP)
+        {
+          return create_error_bad_parameter!(
+            "DSCP {dscp} is too large. Must be at most {}.",
+            TrafficClassSettings::MAX_DSCP
+          );
+        }
+      }
+    }
+    if self.flow_control.max_bytes_per_second == Some(0) {
+      return create_error_bad_parameter!(
+        "The bandwidth limit of the participant must be positive."
+      );
+    }
+    if self.discovery_damping.max_changes_per_second == Some(0) {
+      return create_error_bad_parameter!(
+        "The discovery change rate limit of the participant must be positive."
+      );
+    }
+    let interface_allowlist = &self.discovery_network.interface_allowlist;
+    if !interface_allowlist.is_empty()
+      && get_allowed_unicast_ip_addrs(interface_allowlist).is_empty()
+    {
+      warn!(
+        "The interface allowlist {interface_allowlist:?} selects no local address. The \
+         participant is not reachable until a selected interface appears."
+      );
+    }
+    if !self.discovery_network.multicast_enabled
+      && self.discovery_network.initial_peers.is_empty()
+      && self.peer_resolution.is_none()
+      && self.tcp_transport.as_ref().map_or(0, |tcp| tcp.peers.len()) == 0
+    {
+      warn!(
+        "Multicast is disabled and there are no initial peers. Only participants that have this \
+         host as an initial peer can be discovered."
+      );
+    }
+
+    #[cfg(feature = "security")]
+    if let Some(properties) = self.sec_properties.as_mut() {
+      let crypto_properties = self
+        .crypto_key_size
+        .map(CryptoKeySize::to_property)
+        .into_iter()
+        .chain(
+          self
+            .crypto_rekey_grace_period
+            .map(rekey_grace_period_property),
+        );
+      for crypto_property in crypto_properties {
+        properties
+          .value
+          .retain(|property| property.name != crypto_property.name);
+        properties.value.push(crypto_property);
+      }
+    }
+
     // QosPolicies with possible security properties, otherwise default
     let participant_qos = QosPolicies {
       #[cfg(feature = "security")]
@@ -119,15 +600,24 @@ impl DomainParticipantBuilder {
     let candidate_participant_guid = GUID::new_participant_guid();
     #[cfg(not(feature = "security"))]
     let participant_guid = candidate_participant_guid;
+    #[cfg(feature = "security")]
+    let mut startup_reporter = SecurityStartupReporter::new(self.security_startup_progress.take());
     // If security plugins are present, security is enabled
     #[cfg(feature = "security")]
     let participant_guid = if let Some(ref mut security_plugins) = self.security_plugins.as_mut() {
       trace!("DomainParticipant security construction start");
+      if let Some(audit) = self.handshake_audit.take() {
+        security_plugins.set_handshake_audit(audit);
+      }
+      if let Some(limits) = self.decode_limits.take() {
+        security_plugins.set_decode_limits(limits);
+      }
       // Do the security checks according to DDS Security spec v1.1
       // Section "8.8.1 Authentication and AccessControl behavior with local
       // DomainParticipant". The other steps related to Discovery
       // (generating tokens etc.) are done when initializing Discovery.
 
+      startup_reporter.report(SecurityStartupStage::ValidatingIdentity);
       let sec_guid = match security_plugins.validate_local_identity(
         self.domain_id,
         &participant_qos,
@@ -142,6 +632,7 @@ impl DomainParticipantBuilder {
         }
       };
 
+      startup_reporter.report(SecurityStartupStage::ValidatingPermissions);
       if let Err(e) = security_plugins.validate_local_permissions(
         self.domain_id,
         sec_guid.prefix,
@@ -153,6 +644,7 @@ impl DomainParticipantBuilder {
         );
       }
 
+      startup_reporter.report(SecurityStartupStage::CheckingParticipantAccess);
       match security_plugins.check_create_participant(
         self.domain_id,
         sec_guid.prefix,
@@ -174,6 +666,7 @@ impl DomainParticipantBuilder {
       }
 
       // Register participant with the crypto plugin
+      startup_reporter.report(SecurityStartupStage::RegisteringCryptography);
       if let Err(e) = security_plugins
         .get_participant_sec_attributes(sec_guid.prefix)
         .and_then(|sec_attr| {
@@ -205,6 +698,10 @@ impl DomainParticipantBuilder {
     // wire.
     let (spdp_liveness_sender, spdp_liveness_receiver) = mio_channel::sync_channel(8);
 
+    // Our SPDP messages as sent by the event loop, for Discovery to repeat if
+    // the event loop falls behind.
+    let spdp_transmissions = SpdpTransmissions::new();
+
     // Discovery thread receives and decodes updates from the wire.
     // It updates data to DiscoveryDB, and sends notifications to dp_event_loop,
     // which owns the Readers and Writers and notifies them also.
@@ -231,30 +728,55 @@ impl DomainParticipantBuilder {
     // intermediate DP wrapper
     let dp = DomainParticipantDisc::new(
       self.domain_id,
+      self.participant_id,
       participant_guid,
       participant_qos,
       djh_receiver,
       discovery_update_notification_receiver,
       discovery_command_sender,
       spdp_liveness_sender,
+      spdp_transmissions.clone(),
       status_sender.clone(),
       status_receiver,
       security_plugins_handle.clone(),
+      self.compliance_mode,
+      self.protocol_identity,
+      self.resource_settings,
+      self.discovery_network,
+      self.peer_resolution,
+      self.tcp_transport,
+      self.traffic_isolation,
+      self.flow_control,
+      self.intra_process,
     )?;
 
     // outer DP wrapper
     let dp = DomainParticipant {
       dpi: Arc::new(Mutex::new(dp)),
+      contained: Arc::default(),
     };
 
     let (discovery_started_sender, discovery_started_receiver) = std::sync::mpsc::channel();
 
+    #[cfg(feature = "security")]
+    let security_ready_sender = security_plugins_handle.as_ref().map(|_| {
+      startup_reporter.report(SecurityStartupStage::StartingSecureDiscovery);
+      status_sender.clone()
+    });
+
     // Construct and start background thread
     let dp_clone = dp.weak_clone();
     let disc_db_clone = dp.discovery_db();
+    let compliance_mode = self.compliance_mode;
+    let protocol_identity = self.protocol_identity;
+    let resource_settings = self.resource_settings;
+    let self_health_period = self.self_health_period;
+    let discovery_damping = self.discovery_damping;
     let discovery_handle = thread::Builder::new()
       .name("RustDDS discovery thread".to_string())
       .spawn(move || {
+        let _compliance = compliance_mode.enter();
+        protocol_identity.set_for_current_thread();
         if let Ok(mut discovery) = Discovery::new(
           dp_clone,
           disc_db_clone,
@@ -262,8 +784,12 @@ impl DomainParticipantBuilder {
           discovery_updated_sender,
           discovery_command_receiver,
           spdp_liveness_receiver,
+          spdp_transmissions,
           status_sender,
           security_plugins_handle,
+          resource_settings,
+          self_health_period,
+          discovery_damping,
         ) {
           discovery.discovery_event_loop(); // run the event loop
         }
@@ -276,6 +802,13 @@ impl DomainParticipantBuilder {
       Ok(Ok(())) => {
         // normal case
         info!("Discovery started. Participant constructed.");
+        #[cfg(feature = "security")]
+        if let Some(status_sender) = security_ready_sender {
+          startup_reporter.report(SecurityStartupStage::Ready);
+          let _ = status_sender.try_send(DomainParticipantStatusEvent::SecurityReady {
+            startup_time: startup_reporter.elapsed().into(),
+          });
+        }
         Ok(dp)
       }
       Ok(Err(e)) => {
@@ -291,7 +824,9 @@ impl DomainParticipantBuilder {
 ///
 /// It is recommended that only one DomainParticipant per OS process is created,
 /// as it allocates network sockets, creates background threads, and allocates
-/// some memory for object caches.
+/// some memory for object caches. Components that each need a participant of
+/// their own can share one with
+/// [`DomainParticipantBuilder::share_in_process`].
 ///
 /// If you need to communicate to many DDS domains,
 /// then you must create a separate DomainParticipant for each of them.
@@ -304,6 +839,15 @@ impl DomainParticipantBuilder {
 // This is a smart pointer for DomainParticipant for easier manipulation.
 pub struct DomainParticipant {
   dpi: Arc<Mutex<DomainParticipantDisc>>,
+  contained: Arc<Mutex<ContainedEntities>>,
+}
+
+// Publishers and Subscribers created by the application. Those of Discovery
+// are not listed. Each DomainParticipant shared in process has its own.
+#[derive(Default)]
+struct ContainedEntities {
+  publishers: Vec<PublisherWeak>,
+  subscribers: Vec<SubscriberWeak>,
 }
 
 impl DomainParticipant {
@@ -336,7 +880,9 @@ impl DomainParticipant {
   /// ```
   pub fn create_publisher(&self, qos: &QosPolicies) -> CreateResult<Publisher> {
     let w = self.weak_clone(); // this must be done first to avoid deadlock
-    self.dpi.lock()?.create_publisher(&w, qos)
+    let publisher = self.dpi.lock()?.create_publisher(&w, qos)?;
+    self.contained.lock()?.publishers.push(publisher.downgrade());
+    Ok(publisher)
   }
 
   /// Creates DDS Subscriber
@@ -358,7 +904,77 @@ impl DomainParticipant {
   pub fn create_subscriber(&self, qos: &QosPolicies) -> CreateResult<Subscriber> {
     // println!("DP(outer): create_subscriber");
     let w = self.weak_clone(); // do this first, avoid deadlock
-    self.dpi.lock()?.create_subscriber(&w, qos)
+    let subscriber = self.dpi.lock()?.create_subscriber(&w, qos)?;
+    self
+      .contained
+      .lock()?
+      .subscribers
+      .push(subscriber.downgrade());
+    Ok(subscriber)
+  }
+
+  /// Lists the Publishers created by this DomainParticipant, which the
+  /// application has not dropped.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::{DomainParticipant, QosPolicyBuilder};
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  /// assert_eq!(domain_participant.get_publishers().len(), 1);
+  ///
+  /// drop(publisher);
+  /// assert!(domain_participant.get_publishers().is_empty());
+  /// ```
+  pub fn get_publishers(&self) -> Vec<Publisher> {
+    let mut contained = self.contained.lock().unwrap();
+    contained.publishers.retain(|p| p.upgrade().is_some());
+    contained
+      .publishers
+      .iter()
+      .filter_map(PublisherWeak::upgrade)
+      .collect()
+  }
+
+  /// Lists the Subscribers created by this DomainParticipant, which the
+  /// application has not dropped.
+  pub fn get_subscribers(&self) -> Vec<Subscriber> {
+    let mut contained = self.contained.lock().unwrap();
+    contained.subscribers.retain(|s| s.upgrade().is_some());
+    contained
+      .subscribers
+      .iter()
+      .filter_map(SubscriberWeak::upgrade)
+      .collect()
+  }
+
+  /// Deletes the DataWriters and DataReaders of all the Publishers and
+  /// Subscribers of this DomainParticipant. See
+  /// [`Publisher::delete_contained_entities`] and
+  /// [`Subscriber::delete_contained_entities`].
+  ///
+  /// The Publishers and Subscribers are no longer listed by this
+  /// DomainParticipant afterwards. The built-in entities of Discovery are not
+  /// affected.
+  pub fn delete_contained_entities(&self) {
+    // Take the lists first, so that the participant is not locked while
+    // deleting.
+    let (publishers, subscribers) = {
+      let mut contained = self.contained.lock().unwrap();
+      (
+        std::mem::take(&mut contained.publishers),
+        std::mem::take(&mut contained.subscribers),
+      )
+    };
+    for publisher in publishers.iter().filter_map(PublisherWeak::upgrade) {
+      publisher.delete_contained_entities();
+    }
+    for subscriber in subscribers.iter().filter_map(SubscriberWeak::upgrade) {
+      subscriber.delete_contained_entities();
+    }
   }
 
   /// Create DDS Topic
@@ -388,6 +1004,54 @@ impl DomainParticipant {
   ) -> CreateResult<Topic> {
     // println!("Create topic outer");
     let w = self.weak_clone();
+    self
+      .dpi
+      .lock()?
+      .create_topic(&w, name, TypeDesc::new(type_desc), qos, topic_kind)
+  }
+
+  /// Create DDS Topic, whose type is also described by a [`TypeObject`].
+  ///
+  /// The readers and writers of the topic announce the type in Discovery,
+  /// and are matched only with remote endpoints of assignable types. See
+  /// [`xtypes`](crate::xtypes). The [`dds_type!`](crate::dds_type) macro
+  /// generates the TypeObject.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::{
+  /// #   dds_type, serialization::Extensible, DomainParticipant, QosPolicyBuilder, TopicKind,
+  /// # };
+  /// # use serde::{Deserialize, Serialize};
+  /// dds_type! {
+  ///   #[derive(Serialize, Deserialize)]
+  ///   pub struct Pose {
+  ///     pub x: f64,
+  ///     pub y: f64,
+  ///   }
+  /// }
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let topic = domain_participant.create_topic_with_type_object(
+  ///   "pose".to_string(),
+  ///   "Pose".to_string(),
+  ///   Pose::type_object().unwrap(),
+  ///   &qos,
+  ///   TopicKind::NoKey,
+  /// );
+  /// ```
+  pub fn create_topic_with_type_object(
+    &self,
+    name: String,
+    type_name: String,
+    type_object: TypeObject,
+    qos: &QosPolicies,
+    topic_kind: TopicKind,
+  ) -> CreateResult<Topic> {
+    let w = self.weak_clone();
+    let type_desc = TypeDesc::with_type_object(type_name, type_object);
     self
       .dpi
       .lock()?
@@ -399,6 +1063,35 @@ impl DomainParticipant {
     self.dpi.lock()?.find_topic(&w, name, timeout)
   }
 
+  /// Create a ContentFilteredTopic, which selects samples of `related_topic`
+  /// with a DDS SQL filter expression.
+  ///
+  /// # Arguments
+  ///
+  /// * `name` - Name of the filtered topic. It is announced in Discovery, so
+  ///   that remote DataWriters can filter on their side.
+  /// * `related_topic` - Topic whose samples are filtered.
+  /// * `filter_expression` - E.g. `"value > %0 AND name LIKE %1"`. See
+  ///   [`ContentFilterFactory::ddssql`](crate::dds::content_filter::ContentFilterFactory::ddssql).
+  /// * `expression_parameters` - Values of `%0`, `%1`, etc.
+  ///
+  /// Returns `BadParameter` if the expression cannot be parsed, or refers to
+  /// parameters that are not given.
+  pub fn create_contentfilteredtopic(
+    &self,
+    name: &str,
+    related_topic: &Topic,
+    filter_expression: &str,
+    expression_parameters: Vec<String>,
+  ) -> CreateResult<ContentFilteredTopic> {
+    ContentFilteredTopic::new(
+      name,
+      related_topic,
+      filter_expression,
+      expression_parameters,
+    )
+  }
+
   /// # Examples
   ///
   /// ```
@@ -423,6 +1116,48 @@ impl DomainParticipant {
     self.dpi.lock().unwrap().participant_id()
   }
 
+  /// Gets the current time, as used in the source timestamps of samples
+  /// written by DataWriters of this DomainParticipant.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipant;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let now = domain_participant.current_time();
+  /// ```
+  pub fn current_time(&self) -> Timestamp {
+    Timestamp::now()
+  }
+
+  /// Tells if `handle` is the GUID of this DomainParticipant, or of a
+  /// DataWriter or DataReader that it contains and that has not been dropped.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::*;
+  /// # use serde::Serialize;
+  /// # #[derive(Serialize)]
+  /// # struct SomeType {}
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  /// let topic = domain_participant
+  ///   .create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey)
+  ///   .unwrap();
+  /// let data_writer = publisher
+  ///   .create_datawriter_no_key_cdr::<SomeType>(&topic, None)
+  ///   .unwrap();
+  ///
+  /// assert!(domain_participant.contains_entity(data_writer.guid()));
+  /// assert!(domain_participant.contains_entity(domain_participant.guid()));
+  /// ```
+  pub fn contains_entity(&self, handle: GUID) -> bool {
+    self.dpi.lock().unwrap().contains_entity(handle)
+  }
+
   /// Gets all DiscoveredTopics from DDS network
   ///
   /// # Examples
@@ -440,6 +1175,76 @@ impl DomainParticipant {
     self.dpi.lock().unwrap().discovered_topics()
   }
 
+  /// Gets a discovered topic by name, if any, like
+  /// [`discovered_topics`](Self::discovered_topics). If several participants
+  /// have announced the topic, one of their announcements is returned.
+  pub fn discovered_topic(&self, topic_name: &str) -> Option<DiscoveredTopicData> {
+    self.dpi.lock().unwrap().discovered_topic(topic_name)
+  }
+
+  /// Gets the type of a topic, as described by remote DataWriters or
+  /// DataReaders of it. The type is known only if they tell it, and after it
+  /// has been looked up from them, so it may be `None` for a while after the
+  /// topic has been discovered.
+  ///
+  /// The type can be used to read the topic as
+  /// [`DynamicData`](crate::dynamic::DynamicData).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipant;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// for dtopic in domain_participant.discovered_topics() {
+  ///   let type_object = domain_participant.discovered_type(dtopic.topic_name());
+  /// }
+  /// ```
+  pub fn discovered_type(&self, topic_name: &str) -> Option<TypeObject> {
+    self.dpi.lock().unwrap().discovered_type(topic_name)
+  }
+
+  /// Gets the SPDP data of all discovered remote DomainParticipants. Besides
+  /// locators and lease durations, it tells which DDS implementation each
+  /// participant runs, e.g.
+  /// [`product_name`](SpdpDiscoveredParticipantData::product_name),
+  /// [`product_version`](SpdpDiscoveredParticipantData::product_version) and
+  /// [`host_name`](SpdpDiscoveredParticipantData::host_name), if the
+  /// implementation advertises them.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::DomainParticipant;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// for participant in domain_participant.discovered_participants() {
+  ///   println!(
+  ///     "{:?} {:?} on host {:?}",
+  ///     participant.product_name(),
+  ///     participant.product_version(),
+  ///     participant.host_name()
+  ///   );
+  /// }
+  /// ```
+  pub fn discovered_participants(&self) -> Vec<SpdpDiscoveredParticipantData> {
+    self.dpi.lock().unwrap().discovered_participants()
+  }
+
+  /// Gets the SPDP data of a discovered remote DomainParticipant, like
+  /// [`discovered_participants`](Self::discovered_participants), by its
+  /// GUID.
+  pub fn discovered_participant(
+    &self,
+    participant_guid: GUID,
+  ) -> Option<SpdpDiscoveredParticipantData> {
+    self
+      .dpi
+      .lock()
+      .unwrap()
+      .discovered_participant(participant_guid)
+  }
+
   /// Manually asserts liveliness, affecting all writers with
   /// LIVELINESS QoS of MANUAL_BY_PARTICIPANT created by
   /// this particular participant.
@@ -456,6 +1261,28 @@ impl DomainParticipant {
     self.dpi.lock()?.assert_liveliness()
   }
 
+  /// Probes a host for DomainParticipants, to get discovery going without
+  /// waiting for periodic announcements.
+  ///
+  /// An RTPS PING is sent by unicast to `address`, which should be the SPDP
+  /// unicast port of a participant on the host. The address is added to the
+  /// destinations of our SPDP announcements, and an announcement is sent at
+  /// once. Remote participants can then discover us, and announce themselves
+  /// in return.
+  ///
+  /// See also
+  /// [`DiscoveryNetworkSettings::answer_discovery_probes`](crate::DiscoveryNetworkSettings::answer_discovery_probes).
+  pub fn send_discovery_probe(&self, address: SocketAddr) -> WriteResult<(), ()> {
+    self
+      .send_event_loop_command(EventLoopCommand::SendDiscoveryProbe {
+        locator: Locator::from(address),
+      })
+      .map_err(|e| WriteError::Poisoned {
+        reason: format!("Cannot send discovery probe: {e:?}"),
+        data: (),
+      })
+  }
+
   /// Get a `DomainDomainParticipantStatusListener` that can be used
   /// to get `DomainParticipantStatusEvent`s for this DomainParticipant.
   pub fn status_listener(&self) -> DomainParticipantStatusListener {
@@ -464,6 +1291,102 @@ impl DomainParticipant {
     }
   }
 
+  /// Sets the listener that is called back with the
+  /// `DomainParticipantStatusEvent`s of this DomainParticipant, replacing any
+  /// previous one. `None` removes it. The events are still delivered through
+  /// [`status_listener`](Self::status_listener) as well. See
+  /// [`listener`](crate::listener).
+  pub fn set_listener(&self, listener: Option<Box<dyn DomainParticipantListener>>) {
+    let tap = listener.map(|listener| {
+      let slot = ListenerSlot::default();
+      slot.set(Some(listener));
+      Box::new(move |event: &DomainParticipantStatusEvent| {
+        let event = event.clone();
+        slot.call(move |l| l.on_participant_status(event));
+      }) as Box<dyn Fn(&DomainParticipantStatusEvent) + Send>
+    });
+    self
+      .dpi
+      .lock()
+      .unwrap()
+      .status_channel_receiver()
+      .set_listener(tap);
+  }
+
+  #[cfg(feature = "security")]
+  /// Counts of secured input rejected for exceeding the
+  /// [`DecodeLimits`](DomainParticipantBuilder::security_decode_limits), by
+  /// reason. `None` if security is not configured.
+  pub fn security_decode_rejections(&self) -> Option<DecodeRejectionCounts> {
+    self
+      .dpi
+      .lock()
+      .unwrap()
+      .dpi
+      .security_plugins_handle
+      .as_ref()
+      .map(|handle| handle.read_plugins().decode_rejection_counts())
+  }
+
+  #[cfg(feature = "security")]
+  /// Counts of errors returned by the security plugins, by
+  /// [category](crate::SecurityErrorCategory). `None` if security is not
+  /// configured.
+  pub fn security_error_counts(&self) -> Option<SecurityErrorCounts> {
+    self
+      .dpi
+      .lock()
+      .unwrap()
+      .dpi
+      .security_plugins_handle
+      .as_ref()
+      .map(|handle| handle.read_plugins().error_counts())
+  }
+
+  #[cfg(feature = "security")]
+  /// Statistics of the session key cache of the cryptographic plugin. `None`
+  /// if security is not configured, or the plugin has no such cache.
+  pub fn security_session_key_cache_statistics(&self) -> Option<SessionKeyCacheStatistics> {
+    self
+      .dpi
+      .lock()
+      .unwrap()
+      .dpi
+      .security_plugins_handle
+      .as_ref()
+      .and_then(|handle| handle.read_plugins().session_key_cache_statistics())
+  }
+
+  #[cfg(feature = "security")]
+  /// Generates new keys for a local DataWriter or DataReader of this
+  /// participant, and sends them to the matched remote endpoints in crypto
+  /// tokens. Use this to rotate the keys of long-lived endpoints.
+  ///
+  /// The re-keying is done by Discovery in the background. Remote endpoints
+  /// keep the old keys for a grace period, so that data sent before they
+  /// received the new keys can still be decoded. See
+  /// [`DomainParticipantBuilder::crypto_rekey_grace_period`].
+  ///
+  /// An `Err` result means that the request could not be given to Discovery.
+  pub fn rekey_local_endpoint(&self, endpoint_guid: GUID) -> WriteResult<(), ()> {
+    self.dpi.lock()?.rekey_local_endpoint(endpoint_guid)
+  }
+
+  /// How much the UDP sockets of this participant have received and dropped,
+  /// and the sizes of their receive buffers. See
+  /// [`ResourceSettings::socket_receive_buffer_size`].
+  pub fn socket_statistics(&self) -> Vec<SocketStatistics> {
+    self
+      .dpi
+      .lock()
+      .unwrap()
+      .dpi
+      .socket_counters
+      .iter()
+      .map(|counters| counters.statistics())
+      .collect()
+  }
+
   pub(crate) fn weak_clone(&self) -> DomainParticipantWeak {
     DomainParticipantWeak::new(self)
   }
@@ -481,6 +1404,22 @@ impl DomainParticipant {
     self.dpi.lock().unwrap().dpi.discovery_db.clone()
   }
 
+  pub(crate) fn intra_process(&self) -> bool {
+    self.dpi.lock().unwrap().dpi.intra_process
+  }
+
+  // Is the thread that runs the Readers and Writers still running
+  pub(crate) fn event_loop_running(&self) -> bool {
+    self
+      .dpi
+      .lock()
+      .unwrap()
+      .dpi
+      .ev_loop_handle
+      .as_ref()
+      .is_some_and(|handle| !handle.is_finished())
+  }
+
   pub(crate) fn new_entity_id(&self, entity_kind: EntityKind) -> EntityId {
     self.dpi.lock().unwrap().new_entity_id(entity_kind)
   }
@@ -488,6 +1427,17 @@ impl DomainParticipant {
   pub(crate) fn self_locators(&self) -> HashMap<mio_06::Token, Vec<Locator>> {
     self.dpi.lock().unwrap().self_locators()
   }
+
+  pub(crate) fn network_changed(&self) {
+    self.dpi.lock().unwrap().network_changed();
+  }
+
+  pub(crate) fn send_event_loop_command(
+    &self,
+    command: EventLoopCommand,
+  ) -> Result<(), mio_channel::SendError<EventLoopCommand>> {
+    self.dpi.lock().unwrap().dpi.stop_poll_sender.send(command)
+  }
 } // end impl DomainParticipant
 
 // --------------------------------------------------------------------------
@@ -657,6 +1607,7 @@ impl PartialEq for DomainParticipant {
 #[derive(Clone)]
 pub struct DomainParticipantWeak {
   dpi: Weak<Mutex<DomainParticipantDisc>>,
+  contained: Arc<Mutex<ContainedEntities>>,
   // This struct caches some items to avoid construction deadlocks
   #[cfg(feature = "security")] // just to avoid warning
   domain_id: u16,
@@ -669,6 +1620,7 @@ impl DomainParticipantWeak {
   pub fn new(dp: &DomainParticipant) -> Self {
     Self {
       dpi: Arc::downgrade(&dp.dpi),
+      contained: Arc::clone(&dp.contained),
       #[cfg(feature="security")] // just to avoid warning
       domain_id: dp.domain_id(),
       guid: dp.guid(),
@@ -723,12 +1675,22 @@ impl DomainParticipantWeak {
       .and_then(|dpi| {
         dpi
           .lock()?
-          .create_topic(self, name, type_desc, qos, topic_kind)
+          .create_topic(self, name, TypeDesc::new(type_desc), qos, topic_kind)
       })
   }
 
+  // Whether this was made from the given handle or one of its clones.
+  // Participants shared in process have the same GUID, but are different
+  // handles.
+  pub(crate) fn is_handle_of(&self, participant: &DomainParticipant) -> bool {
+    Arc::ptr_eq(&self.contained, &participant.contained)
+  }
+
   pub fn upgrade(self) -> Option<DomainParticipant> {
-    self.dpi.upgrade().map(|d| DomainParticipant { dpi: d })
+    self.dpi.upgrade().map(|dpi| DomainParticipant {
+      dpi,
+      contained: self.contained,
+    })
   }
 } // end impl
 
@@ -753,26 +1715,48 @@ impl DomainParticipantDisc {
   #[allow(clippy::too_many_arguments)]
   pub fn new(
     domain_id: u16,
+    requested_participant_id: Option<u16>,
     participant_guid: GUID,
     qos_policies: QosPolicies,
     discovery_join_handle: mio_channel::Receiver<JoinHandle<()>>,
     discovery_update_notification_receiver: mio_channel::Receiver<DiscoveryNotificationType>,
     discovery_command_sender: mio_channel::SyncSender<DiscoveryCommand>,
     spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
+    spdp_transmissions: SpdpTransmissions,
     status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
     status_receiver: StatusChannelReceiver<DomainParticipantStatusEvent>,
     security_plugins_handle: Option<SecurityPluginsHandle>,
+    compliance_mode: ComplianceMode,
+    protocol_identity: ProtocolIdentity,
+    resource_settings: ResourceSettings,
+    discovery_network: DiscoveryNetworkSettings,
+    peer_resolution: Option<PeerResolution>,
+    tcp_transport: Option<TcpTransportSettings>,
+    traffic_isolation: Option<TrafficIsolationSettings>,
+    flow_control: FlowControllerSettings,
+    intra_process: bool,
   ) -> CreateResult<Self> {
     let dpi = DomainParticipantInner::new(
       domain_id,
+      requested_participant_id,
       participant_guid,
       qos_policies,
       discovery_update_notification_receiver,
       discovery_command_sender.clone(),
       spdp_liveness_sender,
+      spdp_transmissions,
       status_sender,
       status_receiver,
       security_plugins_handle,
+      compliance_mode,
+      protocol_identity,
+      resource_settings,
+      discovery_network,
+      peer_resolution,
+      tcp_transport,
+      traffic_isolation,
+      flow_control,
+      intra_process,
     )?;
 
     Ok(Self {
@@ -817,7 +1801,7 @@ impl DomainParticipantDisc {
     &self,
     dp: &DomainParticipantWeak,
     name: String,
-    type_desc: String,
+    type_desc: TypeDesc,
     qos: &QosPolicies,
     topic_kind: TopicKind,
   ) -> CreateResult<Topic> {
@@ -842,10 +1826,33 @@ impl DomainParticipantDisc {
     self.dpi.participant_id()
   }
 
+  pub fn contains_entity(&self, handle: GUID) -> bool {
+    self.dpi.contains_entity(handle)
+  }
+
   pub fn discovered_topics(&self) -> Vec<DiscoveredTopicData> {
     self.dpi.discovered_topics()
   }
 
+  pub fn discovered_topic(&self, topic_name: &str) -> Option<DiscoveredTopicData> {
+    self.dpi.discovered_topic(topic_name)
+  }
+
+  pub fn discovered_type(&self, topic_name: &str) -> Option<TypeObject> {
+    self.dpi.discovered_type(topic_name)
+  }
+
+  pub fn discovered_participants(&self) -> Vec<SpdpDiscoveredParticipantData> {
+    self.dpi.discovered_participants()
+  }
+
+  pub fn discovered_participant(
+    &self,
+    participant_guid: GUID,
+  ) -> Option<SpdpDiscoveredParticipantData> {
+    self.dpi.discovered_participant(participant_guid)
+  }
+
   pub(crate) fn dds_cache(&self) -> Arc<RwLock<DDSCache>> {
     self.dpi.dds_cache()
   }
@@ -870,10 +1877,24 @@ impl DomainParticipantDisc {
       .map_err(|_e| WriteError::WouldBlock { data: () })
   }
 
+  #[cfg(feature = "security")]
+  pub(crate) fn rekey_local_endpoint(&self, local_endpoint_guid: GUID) -> WriteResult<(), ()> {
+    self
+      .discovery_command_sender
+      .send(DiscoveryCommand::RekeyLocalEndpoint {
+        local_endpoint_guid,
+      })
+      .map_err(|_e| WriteError::WouldBlock { data: () })
+  }
+
   pub(crate) fn self_locators(&self) -> HashMap<mio_06::Token, Vec<Locator>> {
     self.dpi.self_locators.clone()
   }
 
+  pub(crate) fn network_changed(&mut self) {
+    self.dpi.network_changed();
+  }
+
   pub(crate) fn status_channel_receiver(
     &self,
   ) -> &StatusChannelReceiver<DomainParticipantStatusEvent> {
@@ -947,8 +1968,27 @@ pub(crate) struct DomainParticipantInner {
 
   // RTPS locators describing how to reach this DP
   self_locators: HashMap<mio_06::Token, Vec<Locator>>,
+  // Bound addresses of the unicast listeners, for recomputing self_locators
+  // when local IP addresses change. Multicast locators do not depend on the
+  // addresses.
+  unicast_listener_addresses: HashMap<mio_06::Token, Vec<SocketAddr>>,
+  // Local interfaces that the participant may use for each class of traffic.
+  // Applied again, when local IP addresses change.
+  metatraffic_allowlist: Vec<InterfaceSelector>,
+  user_traffic_allowlist: Vec<InterfaceSelector>,
+  // Port of the TCP server, whose locators replace those of the unicast
+  // listeners
+  tcp_port: Option<u16>,
+  // DataWriters deliver samples written with write_shared to local
+  // DataReaders without serializing them
+  intra_process: bool,
+  // Receive counters of the UDP listeners, which are in the event loop
+  socket_counters: Vec<Arc<SocketCounters>>,
 
   security_plugins_handle: Option<SecurityPluginsHandle>,
+
+  // Stops the peer resolver thread when dropped
+  _peer_resolution: Option<PeerResolutionThread>,
 }
 
 impl Drop for DomainParticipantInner {
@@ -975,83 +2015,215 @@ impl Drop for DomainParticipantInner {
   }
 }
 
+// The locators of the IPv6 listeners are advertised together with those of
+// the IPv4 listeners of the same kind.
+fn locator_token(listener_token: mio_06::Token) -> mio_06::Token {
+  match listener_token {
+    DISCOVERY_LISTENER_V6_TOKEN => DISCOVERY_LISTENER_TOKEN,
+    DISCOVERY_MUL_LISTENER_V6_TOKEN => DISCOVERY_MUL_LISTENER_TOKEN,
+    USER_TRAFFIC_LISTENER_V6_TOKEN => USER_TRAFFIC_LISTENER_TOKEN,
+    USER_TRAFFIC_MUL_LISTENER_V6_TOKEN => USER_TRAFFIC_MUL_LISTENER_TOKEN,
+    other => other,
+  }
+}
+
+// Whether the listener receives metatraffic, rather than user traffic
+fn is_metatraffic(listener_token: mio_06::Token) -> bool {
+  matches!(
+    locator_token(listener_token),
+    DISCOVERY_LISTENER_TOKEN | DISCOVERY_MUL_LISTENER_TOKEN
+  )
+}
+
 impl DomainParticipantInner {
-  #[allow(clippy::too_many_arguments)]
+  #[allow(clippy::too_many_arguments, clippy::needless_pass_by_value)]
   fn new(
     domain_id: u16,
+    requested_participant_id: Option<u16>,
     participant_guid: GUID,
     _qos_policies: QosPolicies,
     discovery_update_notification_receiver: mio_channel::Receiver<DiscoveryNotificationType>,
     discovery_command_sender: mio_channel::SyncSender<DiscoveryCommand>,
     spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
+    spdp_transmissions: SpdpTransmissions,
     status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
     status_receiver: StatusChannelReceiver<DomainParticipantStatusEvent>,
     security_plugins_handle: Option<SecurityPluginsHandle>,
+    compliance_mode: ComplianceMode,
+    protocol_identity: ProtocolIdentity,
+    resource_settings: ResourceSettings,
+    discovery_network: DiscoveryNetworkSettings,
+    peer_resolution: Option<PeerResolution>,
+    tcp_settings: Option<TcpTransportSettings>,
+    traffic_isolation: Option<TrafficIsolationSettings>,
+    flow_control: FlowControllerSettings,
+    intra_process: bool,
   ) -> CreateResult<Self> {
     #[cfg(not(feature = "security"))]
     let _dummy = _qos_policies; // to make clippy happy
 
     let mut listeners = HashMap::new();
+    let ip_stack = discovery_network.ip_stack;
+    // The participant id is chosen with the IPv4 listeners, unless the
+    // participant is IPv6-only.
+    let (unicast_host, discovery_token, user_traffic_token) = if ip_stack.uses_v4() {
+      (
+        "0.0.0.0",
+        DISCOVERY_LISTENER_TOKEN,
+        USER_TRAFFIC_LISTENER_TOKEN,
+      )
+    } else {
+      (
+        "::",
+        DISCOVERY_LISTENER_V6_TOKEN,
+        USER_TRAFFIC_LISTENER_V6_TOKEN,
+      )
+    };
+    let dual_stack = ip_stack.uses_v4() && ip_stack.uses_v6();
+
+    // Metatraffic and user traffic may have interfaces and sockets of their
+    // own.
+    let unisolated = TrafficIsolationSettings::default();
+    let isolation = traffic_isolation.as_ref().unwrap_or(&unisolated);
+    let metatraffic_allowlist = isolation
+      .metatraffic
+      .interface_allowlist(&discovery_network.interface_allowlist)
+      .to_vec();
+    let user_traffic_allowlist = isolation
+      .user_traffic
+      .interface_allowlist(&discovery_network.interface_allowlist)
+      .to_vec();
+    let allowlist_for = |token| {
+      if is_metatraffic(token) {
+        &metatraffic_allowlist
+      } else {
+        &user_traffic_allowlist
+      }
+    };
 
-    match UDPListener::new_multicast(
-      "0.0.0.0",
-      spdp_well_known_multicast_port(domain_id),
-      Ipv4Addr::new(239, 255, 0, 1),
-    ) {
-      Ok(l) => {
-        listeners.insert(DISCOVERY_MUL_LISTENER_TOKEN, l);
+    if discovery_network.multicast_enabled && ip_stack.uses_v4() {
+      match UDPListener::new_multicast(
+        "0.0.0.0",
+        spdp_well_known_multicast_port(domain_id),
+        discovery_network.spdp_multicast_address.into(),
+        &metatraffic_allowlist,
+      ) {
+        Ok(l) => {
+          listeners.insert(DISCOVERY_MUL_LISTENER_TOKEN, l);
+        }
+        Err(e) => warn!("Cannot get multicast discovery listener: {e:?}"),
       }
-      Err(e) => warn!("Cannot get multicast discovery listener: {e:?}"),
     }
+    if discovery_network.multicast_enabled && ip_stack.uses_v6() {
+      match UDPListener::new_multicast(
+        "::",
+        spdp_well_known_multicast_port(domain_id),
+        discovery_network.spdp_multicast_address_v6.into(),
+        &metatraffic_allowlist,
+      ) {
+        Ok(l) => {
+          listeners.insert(DISCOVERY_MUL_LISTENER_V6_TOKEN, l);
+        }
+        Err(e) => warn!("Cannot get IPv6 multicast discovery listener: {e:?}"),
+      }
+    }
+
+    // The participant id is free, if we can bind to its SPDP unicast port.
+    // Ports are released when participants are dropped, so their ids are
+    // reused.
+    let (participant_id, discovery_listener) = match requested_participant_id {
+      Some(participant_id) => {
+        match UDPListener::new_unicast(
+          unicast_host,
+          spdp_well_known_unicast_port(domain_id, participant_id),
+        ) {
+          Ok(dl) => (participant_id, dl),
+          Err(e) => {
+            return create_error_out_of_resources!(
+              "ParticipantId {participant_id} is not available in domain {domain_id}: {e:?}"
+            )
+          }
+        }
+      }
+      None => {
+        let mut participant_id = 0;
+        let mut discovery_listener = None;
+        while discovery_listener.is_none() && participant_id < PARTICIPANT_ID_LIMIT {
+          discovery_listener = UDPListener::new_unicast(
+            unicast_host,
+            spdp_well_known_unicast_port(domain_id, participant_id),
+          )
+          .ok();
+          if discovery_listener.is_none() {
+            debug!("ParticipantId {participant_id} is in use on this host.");
+            participant_id += 1;
+          }
+        }
+        match discovery_listener {
+          Some(dl) => (participant_id, dl),
+          None => return create_error_out_of_resources!("Could not find free ParticipantId"),
+        }
+      }
+    };
 
-    let mut participant_id = 0;
+    info!("ParticipantId {} selected.", participant_id);
 
-    let mut discovery_listener = None;
+    listeners.insert(discovery_token, discovery_listener);
 
-    // Magic value 120 below is from RTPS spec 2.5 Section "9.6.2.3 Default Port
-    // Numbers"
-    while discovery_listener.is_none() && participant_id < 120 {
-      discovery_listener = UDPListener::new_unicast(
-        "0.0.0.0",
+    // A dual-stack participant listens to the same port on IPv6, if it is
+    // free there.
+    if dual_stack {
+      match UDPListener::new_unicast(
+        "::",
         spdp_well_known_unicast_port(domain_id, participant_id),
       )
-      .ok();
-      if discovery_listener.is_none() {
-        participant_id += 1;
+      .or_else(|_| UDPListener::new_unicast("::", 0))
+      {
+        Ok(l) => {
+          listeners.insert(DISCOVERY_LISTENER_V6_TOKEN, l);
+        }
+        Err(e) => warn!("Cannot get IPv6 unicast discovery listener: {e:?}"),
       }
     }
 
-    info!("ParticipantId {} selected.", participant_id);
-
-    // here discovery_listener is redefined (shadowed)
-    let discovery_listener = match discovery_listener {
-      Some(dl) => dl,
-      None => return create_error_out_of_resources!("Could not find free ParticipantId"),
-    };
-    listeners.insert(DISCOVERY_LISTENER_TOKEN, discovery_listener);
-
     // Now the user traffic listeners
 
-    match UDPListener::new_multicast(
-      "0.0.0.0",
-      user_traffic_multicast_port(domain_id),
-      Ipv4Addr::new(239, 255, 0, 1),
-    ) {
-      Ok(l) => {
-        listeners.insert(USER_TRAFFIC_MUL_LISTENER_TOKEN, l);
+    if discovery_network.multicast_enabled && ip_stack.uses_v4() {
+      match UDPListener::new_multicast(
+        "0.0.0.0",
+        user_traffic_multicast_port(domain_id),
+        Ipv4Addr::new(239, 255, 0, 1).into(),
+        &user_traffic_allowlist,
+      ) {
+        Ok(l) => {
+          listeners.insert(USER_TRAFFIC_MUL_LISTENER_TOKEN, l);
+        }
+        Err(e) => warn!("Cannot get multicast user traffic listener: {e:?}"),
+      }
+    }
+    if discovery_network.multicast_enabled && ip_stack.uses_v6() {
+      match UDPListener::new_multicast(
+        "::",
+        user_traffic_multicast_port(domain_id),
+        DiscoveryNetworkSettings::DEFAULT_SPDP_MULTICAST_ADDRESS_V6.into(),
+        &user_traffic_allowlist,
+      ) {
+        Ok(l) => {
+          listeners.insert(USER_TRAFFIC_MUL_LISTENER_V6_TOKEN, l);
+        }
+        Err(e) => warn!("Cannot get IPv6 multicast user traffic listener: {e:?}"),
       }
-      Err(e) => warn!("Cannot get multicast user traffic listener: {e:?}"),
     }
 
     let user_traffic_listener = UDPListener::new_unicast(
-      "0.0.0.0",
+      unicast_host,
       user_traffic_unicast_port(domain_id, participant_id),
     )
     .or_else(|e| {
       if matches!(e.kind(), ErrorKind::AddrInUse) {
         // If we do not get the preferred listening port,
         // try again, with "any" port number.
-        UDPListener::new_unicast("0.0.0.0", 0).or_else(|e| {
+        UDPListener::new_unicast(unicast_host, 0).or_else(|e| {
           create_error_out_of_resources!(
             "Could not open unicast user traffic listener, any port number: {:?}",
             e
@@ -1062,19 +2234,79 @@ impl DomainParticipantInner {
       }
     })?;
 
-    listeners.insert(USER_TRAFFIC_LISTENER_TOKEN, user_traffic_listener);
+    // By default, the TCP server listens to the same port number as the
+    // user traffic listener.
+    let tcp_transport = tcp_settings
+      .as_ref()
+      .map(|settings| {
+        let result = match settings.listen_port {
+          Some(port) => TcpTransport::start(port, settings),
+          None => user_traffic_listener
+            .local_port()
+            .and_then(|port| TcpTransport::start(port, settings))
+            .or_else(|_| TcpTransport::start(0, settings)),
+        };
+        result.or_else(|e| create_error_out_of_resources!("Could not open TCP server: {e:?}"))
+      })
+      .transpose()?;
+    let tcp_port = tcp_transport.as_ref().map(TcpTransport::local_port);
+
+    if dual_stack {
+      match UDPListener::new_unicast("::", user_traffic_unicast_port(domain_id, participant_id))
+        .or_else(|_| UDPListener::new_unicast("::", 0))
+      {
+        Ok(l) => {
+          listeners.insert(USER_TRAFFIC_LISTENER_V6_TOKEN, l);
+        }
+        Err(e) => warn!("Cannot get IPv6 unicast user traffic listener: {e:?}"),
+      }
+    }
+    listeners.insert(user_traffic_token, user_traffic_listener);
+
+    let metatraffic_buffer_sizes = SocketBufferSizes::new(
+      &isolation.metatraffic.resource_settings(&resource_settings),
+      status_sender.clone(),
+    );
+    let user_traffic_buffer_sizes = SocketBufferSizes::new(
+      &isolation.user_traffic.resource_settings(&resource_settings),
+      status_sender.clone(),
+    );
+    for (token, listener) in &mut listeners {
+      listener.set_receive_buffer_size(resource_settings.receive_buffer_size);
+      listener.set_socket_buffer_sizes(if is_metatraffic(*token) {
+        &metatraffic_buffer_sizes
+      } else {
+        &user_traffic_buffer_sizes
+      });
+    }
+    let socket_counters = listeners.values().map(UDPListener::counters).collect();
 
     // construct our own Locators
-    let self_locators: HashMap<mio_06::Token, Vec<Locator>> = listeners
-      .iter()
-      .map(|(t, l)| match l.to_locator_address() {
-        Ok(locs) => (*t, locs),
-        Err(e) => {
-          error!("No local network address for token {:?}: {:?}", t, e);
-          (*t, vec![])
+    let mut self_locators: HashMap<mio_06::Token, Vec<Locator>> = HashMap::new();
+    let mut unicast_listener_addresses: HashMap<mio_06::Token, Vec<SocketAddr>> = HashMap::new();
+    for (t, l) in &listeners {
+      let locators = l.to_locator_address(allowlist_for(*t)).unwrap_or_else(|e| {
+        error!("No local network address for token {:?}: {:?}", t, e);
+        vec![]
+      });
+      self_locators
+        .entry(locator_token(*t))
+        .or_default()
+        .extend(locators);
+      if !l.is_multicast() {
+        if let Ok(address) = l.local_address() {
+          unicast_listener_addresses
+            .entry(locator_token(*t))
+            .or_default()
+            .push(address);
         }
-      })
-      .collect();
+      }
+    }
+    if let Some(tcp_transport) = &tcp_transport {
+      for token in unicast_listener_addresses.keys() {
+        self_locators.insert(*token, tcp_transport.locators(allowlist_for(*token)));
+      }
+    }
 
     // Adding readers
     let (sender_add_reader, receiver_add_reader) =
@@ -1093,27 +2325,69 @@ impl DomainParticipantInner {
     };
     let domain_info_clone = domain_info.clone();
 
-    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    let dds_cache = Arc::new(RwLock::new(DDSCache::with_default_max_samples(
+      resource_settings.default_cache_max_samples,
+    )));
     let dds_cache_clone = Arc::clone(&dds_cache);
 
     let (discovery_db_event_sender, discovery_db_event_receiver) =
       mio_channel::sync_channel::<()>(1);
 
     // Discovert DB creation
-    let discovery_db = Arc::new(RwLock::new(DiscoveryDB::new(
+    let mut discovery_db = DiscoveryDB::new(
       participant_guid,
       discovery_db_event_sender,
       status_sender.clone(),
-    )));
+    );
+    discovery_db.set_limits(
+      resource_settings.max_discovered_participants,
+      resource_settings.max_discovered_endpoints,
+    );
+    discovery_db.set_topic_filter(discovery_network.topic_filter.clone());
+    let discovery_db = Arc::new(RwLock::new(discovery_db));
 
     let (stop_poll_sender, stop_poll_receiver) = mio_channel::channel();
 
+    let multicast_options = MulticastOptions {
+      enabled: discovery_network.multicast_enabled,
+      ttl: discovery_network.multicast_ttl,
+      loopback: discovery_network.multicast_loopback,
+    };
+    // Without isolation, all traffic goes through the user traffic sockets.
+    let user_traffic_sockets = SenderSocketSettings {
+      interface_allowlist: user_traffic_allowlist.clone(),
+      buffer_sizes: user_traffic_buffer_sizes,
+      dscp: isolation.user_traffic.dscp,
+    };
+    let metatraffic_sockets = traffic_isolation.as_ref().map(|_| SenderSocketSettings {
+      interface_allowlist: metatraffic_allowlist.clone(),
+      buffer_sizes: metatraffic_buffer_sizes,
+      dscp: isolation.metatraffic.dscp,
+    });
+    let mut initial_peer_locators = discovery_network.initial_peer_locators(domain_id);
+    if let Some(settings) = &tcp_settings {
+      initial_peer_locators.extend(settings.peers.iter().map(|peer| Locator::tcp(*peer)));
+    }
+    let answer_discovery_probes = discovery_network.answer_discovery_probes;
+    let peer_resolution = peer_resolution
+      .map(|resolution| {
+        PeerResolutionThread::start(
+          resolution,
+          domain_id,
+          discovery_network.initial_peer_max_participant_id,
+          stop_poll_sender.clone(),
+        )
+      })
+      .transpose()?;
+
     // Launch the background thread for DomainParticipant
     let disc_db_clone = discovery_db.clone();
     let security_plugins_clone = security_plugins_handle.clone();
     let ev_loop_handle = thread::Builder::new()
       .name(format!("RustDDS Participant {} event loop", participant_id))
       .spawn(move || {
+        let _compliance = compliance_mode.enter();
+        protocol_identity.set_for_current_thread();
         let dp_event_loop = DPEventLoop::new(
           domain_info_clone,
           dds_cache_clone,
@@ -1140,8 +2414,17 @@ impl DomainParticipantInner {
           discovery_update_notification_receiver,
           discovery_command_sender,
           spdp_liveness_sender,
+          spdp_transmissions,
           status_sender,
           security_plugins_clone,
+          multicast_options,
+          ip_stack,
+          user_traffic_sockets,
+          metatraffic_sockets,
+          initial_peer_locators,
+          answer_discovery_probes,
+          tcp_transport,
+          flow_control,
         );
         dp_event_loop.event_loop();
       })?;
@@ -1172,10 +2455,43 @@ impl DomainParticipantInner {
       discovery_db_event_receiver,
       status_receiver,
       self_locators,
+      unicast_listener_addresses,
+      metatraffic_allowlist,
+      user_traffic_allowlist,
+      tcp_port,
+      intra_process,
+      socket_counters,
       security_plugins_handle,
+      _peer_resolution: peer_resolution,
     })
   }
 
+  // Called from Discovery, when local IP addresses have changed. The
+  // listeners are bound to the unspecified address, so they keep working, but
+  // the advertised locators and multicast memberships must be updated.
+  pub(crate) fn network_changed(&mut self) {
+    for (token, addresses) in &self.unicast_listener_addresses {
+      let interface_allowlist = if is_metatraffic(*token) {
+        &self.metatraffic_allowlist
+      } else {
+        &self.user_traffic_allowlist
+      };
+      let locators = match self.tcp_port {
+        Some(tcp_port) => tcp_locators(tcp_port, interface_allowlist),
+        None => addresses
+          .iter()
+          .flat_map(|address| get_local_unicast_locators(*address, interface_allowlist))
+          .collect(),
+      };
+      info!("Local locators for {token:?} are now {locators:?}");
+      self.self_locators.insert(*token, locators);
+    }
+    self
+      .stop_poll_sender
+      .send(EventLoopCommand::NetworkChanged)
+      .unwrap_or_else(|e| error!("Cannot notify dp_event_loop of network change: {e:?}"));
+  }
+
   pub fn dds_cache(&self) -> Arc<RwLock<DDSCache>> {
     self.dds_cache.clone()
   }
@@ -1235,7 +2551,7 @@ impl DomainParticipantInner {
     &self,
     domain_participant_weak: &DomainParticipantWeak,
     name: String,
-    type_desc: String,
+    topic_type_desc: TypeDesc,
     qos: &QosPolicies,
     topic_kind: TopicKind,
   ) -> CreateResult<Topic> {
@@ -1268,7 +2584,6 @@ impl DomainParticipantInner {
       };
     }
 
-    let topic_type_desc = TypeDesc::new(type_desc);
     let topic = Topic::new(
       domain_participant_weak,
       name.clone(),
@@ -1346,7 +2661,7 @@ impl DomainParticipantInner {
         None => TopicKind::NoKey,
       };
       let name = d.topic_name().clone();
-      let type_desc = d.topic_data.type_name.clone();
+      let type_desc = TypeDesc::new(d.topic_data.type_name.clone());
       self.create_topic(domain_participant_weak, name, type_desc, &qos, topic_kind)
     };
 
@@ -1399,6 +2714,45 @@ impl DomainParticipantInner {
 
     db.all_user_topics().cloned().collect()
   }
+
+  pub fn contains_entity(&self, handle: GUID) -> bool {
+    if handle == self.guid() {
+      return true;
+    }
+    let db = discovery_db_read(&self.discovery_db);
+    handle.prefix == self.guid().prefix
+      && (db.get_local_topic_writer(handle).is_some()
+        || db.get_local_topic_reader(handle).is_some())
+  }
+
+  pub fn discovered_topic(&self, topic_name: &str) -> Option<DiscoveredTopicData> {
+    discovery_db_read(&self.discovery_db)
+      .get_topic(topic_name)
+      .cloned()
+  }
+
+  pub fn discovered_type(&self, topic_name: &str) -> Option<TypeObject> {
+    discovery_db_read(&self.discovery_db).type_object_of_topic(topic_name)
+  }
+
+  pub fn discovered_participants(&self) -> Vec<SpdpDiscoveredParticipantData> {
+    discovery_db_read(&self.discovery_db)
+      .remote_participants()
+      .cloned()
+      .collect()
+  }
+
+  pub fn discovered_participant(
+    &self,
+    participant_guid: GUID,
+  ) -> Option<SpdpDiscoveredParticipantData> {
+    if participant_guid.prefix == self.guid().prefix {
+      return None;
+    }
+    discovery_db_read(&self.discovery_db)
+      .find_participant_proxy(participant_guid.prefix)
+      .cloned()
+  }
   pub(crate) fn status_channel_receiver(
     &self,
   ) -> &StatusChannelReceiver<DomainParticipantStatusEvent> {
@@ -1450,22 +2804,119 @@ mod tests {
   use byteorder::LittleEndian;
 
   use crate::{
-    dds::{qos::QosPolicies, topic::TopicKind},
+    dds::{compliance::ComplianceMode, qos::QosPolicies, topic::TopicKind},
     messages::{
       header::Header, protocol_id::ProtocolId, protocol_version::ProtocolVersion,
       submessages::submessages::*, vendor_id::VendorId,
     },
     network::{constant::user_traffic_unicast_port, udp_sender::UDPSender},
     rtps::{submessage::*, Message},
-    serialization::CDRSerializerAdapter,
+    serialization::{CDRDeserializerAdapter, CDRSerializerAdapter},
     structure::{
-      guid::{EntityId, GUID},
+      entity::RTPSEntity,
+      guid::{EntityId, EntityKind, GUID},
       locator::Locator,
       sequence_number::{SequenceNumber, SequenceNumberSet},
     },
     test::random_data::RandomData,
   };
-  use super::DomainParticipant;
+  use super::{DomainParticipant, DomainParticipantBuilder};
+
+  #[test]
+  fn shared_participants_in_process() {
+    // A domain of its own, so that other tests do not share
+    let domain_id = 41;
+    let first = DomainParticipantBuilder::new(domain_id)
+      .share_in_process(true)
+      .build()
+      .unwrap();
+    let second = DomainParticipantBuilder::new(domain_id)
+      .share_in_process(true)
+      .build()
+      .unwrap();
+    assert_eq!(first.guid(), second.guid());
+
+    // Each lists and deletes only its own entities
+    let qos = QosPolicies::qos_none();
+    let first_publisher = first.create_publisher(&qos).unwrap();
+    let _second_publisher = second.create_publisher(&qos).unwrap();
+    assert_eq!(first.get_publishers(), vec![first_publisher.clone()]);
+    second.delete_contained_entities();
+    assert!(second.get_publishers().is_empty());
+    assert_eq!(first.get_publishers().len(), 1);
+
+    // Not shared
+    let separate = DomainParticipant::new(domain_id).unwrap();
+    assert_ne!(first.guid(), separate.guid());
+    // Different settings
+    assert!(DomainParticipantBuilder::new(domain_id)
+      .share_in_process(true)
+      .compliance_mode(ComplianceMode::Strict)
+      .build()
+      .is_err());
+
+    // The participant is shared only while alive
+    let guid = first.guid();
+    drop(first);
+    drop(second);
+    let third = DomainParticipantBuilder::new(domain_id)
+      .share_in_process(true)
+      .build()
+      .unwrap();
+    assert_ne!(third.guid(), guid);
+  }
+
+  #[test]
+  fn enumerate_and_delete_contained_entities() {
+    let domain_participant = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicies::qos_none();
+    let publisher = domain_participant.create_publisher(&qos).unwrap();
+    let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+    // The Publisher and Subscriber of Discovery are not listed
+    assert_eq!(domain_participant.get_publishers().len(), 1);
+    assert_eq!(domain_participant.get_subscribers().len(), 1);
+
+    let topic = domain_participant
+      .create_topic(
+        "contained".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let data_writer = publisher
+      .create_datawriter::<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>>(&topic, None)
+      .unwrap();
+    let data_reader = subscriber
+      .create_datareader::<RandomData, CDRDeserializerAdapter<RandomData>>(&topic, None)
+      .unwrap();
+    assert_eq!(publisher.get_datawriters(), vec![data_writer.guid()]);
+    assert_eq!(
+      publisher.lookup_datawriter("contained"),
+      Some(data_writer.guid())
+    );
+    assert_eq!(
+      subscriber.lookup_datareader("contained"),
+      Some(data_reader.guid())
+    );
+    assert_eq!(subscriber.lookup_datareader("other"), None);
+    assert!(domain_participant.contains_entity(data_writer.guid()));
+    assert!(domain_participant.contains_entity(data_reader.guid()));
+    assert!(!domain_participant.contains_entity(GUID::dummy_test_guid(
+      EntityKind::WRITER_WITH_KEY_USER_DEFINED
+    )));
+
+    domain_participant.delete_contained_entities();
+    assert!(publisher.get_datawriters().is_empty());
+    assert!(subscriber.get_datareaders().is_empty());
+    assert_eq!(publisher.lookup_datawriter("contained"), None);
+    assert!(domain_participant.get_publishers().is_empty());
+    assert!(domain_participant.get_subscribers().is_empty());
+
+    // Dropping the deleted entities does not remove them again
+    drop(data_writer);
+    drop(data_reader);
+  }
 
   // TODO: improve basic test when more or the structure is known
   #[test]
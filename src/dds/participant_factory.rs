@@ -0,0 +1,210 @@
+//! Process-wide creation and lookup of DomainParticipants.
+//!
+//! The DDS specification has a DomainParticipantFactory singleton, which
+//! creates DomainParticipants and finds the existing ones. In RustDDS
+//! participants can also be created directly with
+//! [`DomainParticipant::new`] or [`DomainParticipantBuilder::build`], but only
+//! participants created through the [`DomainParticipantFactory`] can be looked
+//! up.
+//!
+//! Each participant of a domain on a host needs its own participant id,
+//! because the id determines its unicast port numbers. By default the lowest
+//! id whose ports are free is used, so ids of dropped participants are reused.
+//! An id can also be chosen with
+//! [`DomainParticipantBuilder::participant_id`]. The factory then checks that
+//! no participant of this process has it, before the ports are tried.
+//!
+//! ```
+//! use rustdds::{DomainParticipantBuilder, DomainParticipantFactory};
+//!
+//! let factory = DomainParticipantFactory::instance();
+//! let participant = factory
+//!   .create_participant(DomainParticipantBuilder::new(0))
+//!   .unwrap();
+//! let found = factory.lookup_participant(0).unwrap();
+//! assert_eq!(found, participant);
+//! ```
+
+use std::sync::{Mutex, OnceLock};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  create_error_bad_parameter,
+  dds::{
+    participant::{DomainParticipant, DomainParticipantBuilder, DomainParticipantWeak},
+    result::{CreateError, CreateResult},
+  },
+  structure::entity::RTPSEntity,
+};
+
+struct RegisteredParticipant {
+  domain_id: u16,
+  participant_id: u16,
+  participant: DomainParticipantWeak,
+}
+
+impl RegisteredParticipant {
+  fn upgrade(&self) -> Option<DomainParticipant> {
+    self.participant.clone().upgrade()
+  }
+}
+
+/// Creates DomainParticipants and keeps track of them. See the
+/// [module documentation](self).
+///
+/// The factory does not keep participants alive. A participant is dropped
+/// as usual, when the application drops its last handle to it.
+pub struct DomainParticipantFactory {
+  participants: Mutex<Vec<RegisteredParticipant>>,
+}
+
+impl DomainParticipantFactory {
+  /// The factory of this process.
+  pub fn instance() -> &'static Self {
+    static INSTANCE: OnceLock<DomainParticipantFactory> = OnceLock::new();
+    INSTANCE.get_or_init(|| Self {
+      participants: Mutex::new(Vec::new()),
+    })
+  }
+
+  /// Builds a participant and registers it for lookup.
+  ///
+  /// Fails with `BadParameter`, if the builder requests a participant id that
+  /// a participant of this process already has in the domain.
+  pub fn create_participant(
+    &self,
+    builder: DomainParticipantBuilder,
+  ) -> CreateResult<DomainParticipant> {
+    let domain_id = builder.domain_id();
+    // Keep the list locked until the new participant is registered, so that
+    // concurrent callers cannot both get the same participant id.
+    let mut participants = self.participants.lock().unwrap();
+    participants.retain(|p| p.upgrade().is_some());
+    if let Some(participant_id) = builder.requested_participant_id() {
+      if let Some(existing) = participants
+        .iter()
+        .filter(|p| p.domain_id == domain_id && p.participant_id == participant_id)
+        .find_map(RegisteredParticipant::upgrade)
+      {
+        return create_error_bad_parameter!(
+          "ParticipantId {participant_id} in domain {domain_id} is already used by participant \
+           {:?}",
+          existing.guid()
+        );
+      }
+    }
+
+    let participant = builder.build()?;
+    debug!(
+      "DomainParticipantFactory: created participant {} in domain {domain_id}",
+      participant.participant_id()
+    );
+    participants.push(RegisteredParticipant {
+      domain_id,
+      participant_id: participant.participant_id(),
+      participant: participant.weak_clone(),
+    });
+    Ok(participant)
+  }
+
+  /// A participant of the domain, if there is any. If there are several,
+  /// the one with the lowest participant id is returned.
+  pub fn lookup_participant(&self, domain_id: u16) -> Option<DomainParticipant> {
+    self.participants(domain_id).into_iter().next()
+  }
+
+  /// The participant of the domain that has the given participant id.
+  pub fn lookup_participant_by_id(
+    &self,
+    domain_id: u16,
+    participant_id: u16,
+  ) -> Option<DomainParticipant> {
+    self
+      .participants
+      .lock()
+      .unwrap()
+      .iter()
+      .filter(|p| p.domain_id == domain_id && p.participant_id == participant_id)
+      .find_map(RegisteredParticipant::upgrade)
+  }
+
+  /// All participants of the domain, ordered by participant id.
+  pub fn participants(&self, domain_id: u16) -> Vec<DomainParticipant> {
+    let mut participants: Vec<(u16, DomainParticipant)> = self
+      .participants
+      .lock()
+      .unwrap()
+      .iter()
+      .filter(|p| p.domain_id == domain_id)
+      .filter_map(|p| p.upgrade().map(|dp| (p.participant_id, dp)))
+      .collect();
+    participants.sort_by_key(|(participant_id, _)| *participant_id);
+    participants.into_iter().map(|(_, dp)| dp).collect()
+  }
+
+  /// Removes the participant from the factory, so that it can no longer be
+  /// looked up, and drops the given handle. Returns false, if the factory
+  /// did not have the participant.
+  ///
+  /// The participant is dropped once the application has no other handles
+  /// to it.
+  pub fn delete_participant(&self, participant: DomainParticipant) -> bool {
+    let removed = {
+      let mut participants = self.participants.lock().unwrap();
+      participants
+        .iter()
+        .position(|p| p.participant.is_handle_of(&participant))
+        .map(|index| participants.remove(index))
+    };
+    drop(participant);
+    removed.is_some()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn participants_are_registered_by_id() {
+    // Use a domain of its own, so that other tests do not interfere.
+    let domain_id = 23;
+    let factory = DomainParticipantFactory::instance();
+    let first = factory
+      .create_participant(DomainParticipantBuilder::new(domain_id))
+      .unwrap();
+    let second = factory
+      .create_participant(DomainParticipantBuilder::new(domain_id))
+      .unwrap();
+    assert_ne!(first.participant_id(), second.participant_id());
+    assert_eq!(factory.participants(domain_id).len(), 2);
+    assert_eq!(
+      factory.lookup_participant_by_id(domain_id, second.participant_id()),
+      Some(second.clone())
+    );
+
+    let taken = DomainParticipantBuilder::new(domain_id).participant_id(first.participant_id());
+    assert!(matches!(
+      factory.create_participant(taken),
+      Err(CreateError::BadParameter { .. })
+    ));
+
+    let first_id = first.participant_id();
+    assert!(factory.delete_participant(first));
+    assert!(factory
+      .lookup_participant_by_id(domain_id, first_id)
+      .is_none());
+    assert_eq!(factory.lookup_participant(domain_id), Some(second));
+
+    // A participant that was not created by the factory is not deleted, even
+    // if the factory has dropped participants to forget.
+    let dropped = factory
+      .create_participant(DomainParticipantBuilder::new(domain_id))
+      .unwrap();
+    drop(dropped);
+    let unregistered = DomainParticipant::new(domain_id).unwrap();
+    assert!(!factory.delete_participant(unregistered));
+  }
+}
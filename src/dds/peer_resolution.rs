@@ -0,0 +1,444 @@
+//! Discovery peers looked up from a name service.
+//!
+//! Where multicast is not available, e.g. in Kubernetes, participants must
+//! send their SPDP announcements by unicast to the hosts of the other
+//! participants. Their addresses are often not known in advance, and change
+//! as pods are restarted, but a name service knows them. A [`PeerResolver`]
+//! looks them up, and is called periodically, so that the participant
+//! announces itself to the hosts that are currently resolved.
+//!
+//! Two resolvers are included:
+//!
+//! * [`DnsPeerResolver`] resolves a list of DNS names, e.g. the name of a
+//!   Kubernetes headless service, which resolves to the addresses of all its
+//!   pods.
+//! * [`DnsSdPeerResolver`] browses a DNS-SD service type by multicast DNS,
+//!   and resolves the hosts of the service instances found.
+//!
+//! Other name services can be used by implementing [`PeerResolver`].
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use rustdds::{
+//!   peer_resolution::DnsPeerResolver, DiscoveryNetworkSettings, DomainParticipantBuilder,
+//! };
+//!
+//! let builder = DomainParticipantBuilder::new(0)
+//!   .discovery_network(DiscoveryNetworkSettings::unicast_only(vec![]))
+//!   .peer_resolver(
+//!     DnsPeerResolver::new(["dds-peers.default.svc.cluster.local"]),
+//!     Duration::from_secs(30),
+//!   );
+//! ```
+
+use std::{
+  collections::BTreeSet,
+  io,
+  net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  thread::{self, JoinHandle},
+  time::{Duration, Instant},
+};
+
+use mio_extras::channel as mio_channel;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{dds::discovery_network::spdp_peer_locators, rtps::dp_event_loop::EventLoopCommand};
+
+/// Looks up the hosts that SPDP announcements are sent to.
+///
+/// Set with
+/// [`DomainParticipantBuilder::peer_resolver`](crate::DomainParticipantBuilder::peer_resolver).
+/// The announcements are sent to the well-known unicast ports of the
+/// participant ids up to
+/// [`DiscoveryNetworkSettings::initial_peer_max_participant_id`](crate::DiscoveryNetworkSettings::initial_peer_max_participant_id)
+/// on each host, as for the initial peers.
+pub trait PeerResolver: Send {
+  /// Returns the current addresses of the peers. This is called from a
+  /// separate thread, so it may block.
+  ///
+  /// On error, the previously resolved peers are kept.
+  fn resolve(&mut self) -> io::Result<Vec<IpAddr>>;
+}
+
+/// Resolves a list of DNS names with the resolver of the operating system.
+/// Each name may resolve to several addresses, and all of them are peers.
+#[derive(Debug, Clone)]
+pub struct DnsPeerResolver {
+  names: Vec<String>,
+}
+
+impl DnsPeerResolver {
+  pub fn new<S: Into<String>>(names: impl IntoIterator<Item = S>) -> Self {
+    Self {
+      names: names.into_iter().map(Into::into).collect(),
+    }
+  }
+}
+
+impl PeerResolver for DnsPeerResolver {
+  // Names that cannot be resolved are skipped, unless none can.
+  fn resolve(&mut self) -> io::Result<Vec<IpAddr>> {
+    let mut peers = BTreeSet::new();
+    let mut last_error = None;
+    for name in &self.names {
+      match (name.as_str(), 0).to_socket_addrs() {
+        Ok(addresses) => peers.extend(addresses.map(|a| a.ip())),
+        Err(e) => {
+          debug!("Cannot resolve peer name {name:?}: {e}");
+          last_error = Some(e);
+        }
+      }
+    }
+    match last_error {
+      Some(e) if peers.is_empty() => Err(e),
+      _ => Ok(peers.into_iter().collect()),
+    }
+  }
+}
+
+/// Browses a DNS-SD service type, such as `"_rtps._udp.local"`, with
+/// multicast DNS (RFC 6762, RFC 6763). The hosts of the service instances
+/// that answer are the peers. The ports of the instances are not used.
+///
+/// The query is sent as a one-shot query from an ephemeral port, so the
+/// answers come back by unicast, and port 5353 need not be free.
+#[derive(Debug, Clone)]
+pub struct DnsSdPeerResolver {
+  service: String,
+  timeout: Duration,
+}
+
+impl DnsSdPeerResolver {
+  /// How long answers to a query are collected, unless set with
+  /// [`timeout`](Self::timeout).
+  pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+  const MDNS_ADDRESS: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+  const MDNS_PORT: u16 = 5353;
+
+  pub fn new(service_type: &str) -> Self {
+    Self {
+      service: normalize_name(service_type),
+      timeout: Self::DEFAULT_TIMEOUT,
+    }
+  }
+
+  pub fn timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = timeout;
+    self
+  }
+}
+
+impl PeerResolver for DnsSdPeerResolver {
+  fn resolve(&mut self) -> io::Result<Vec<IpAddr>> {
+    let socket = UdpSocket::bind(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0))?;
+    socket.send_to(
+      &ptr_query(&self.service),
+      SocketAddr::new(Self::MDNS_ADDRESS.into(), Self::MDNS_PORT),
+    )?;
+
+    let deadline = Instant::now() + self.timeout;
+    let mut records = Vec::new();
+    let mut buf = [0; 9000];
+    loop {
+      let remaining = deadline.saturating_duration_since(Instant::now());
+      if remaining.is_zero() {
+        break;
+      }
+      socket.set_read_timeout(Some(remaining))?;
+      match socket.recv_from(&mut buf) {
+        Ok((len, from)) => match parse_records(&buf[..len]) {
+          Some(received) => records.extend(received),
+          None => debug!("Malformed mDNS response from {from}"),
+        },
+        Err(e)
+          if matches!(
+            e.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+          ) =>
+        {
+          break
+        }
+        Err(e) => return Err(e),
+      }
+    }
+    Ok(service_hosts(&self.service, &records))
+  }
+}
+
+// Names are compared in lowercase, without the trailing dot.
+fn normalize_name(name: &str) -> String {
+  name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_AAAA: u16 = 28;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+
+// A DNS query message for the PTR records of the name
+fn ptr_query(name: &str) -> Vec<u8> {
+  // Header: id, flags, one question, no records
+  let mut message = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+  for label in name.split('.').filter(|label| !label.is_empty()) {
+    message.push(label.len().min(63) as u8);
+    message.extend_from_slice(&label.as_bytes()[..label.len().min(63)]);
+  }
+  message.push(0);
+  message.extend_from_slice(&TYPE_PTR.to_be_bytes());
+  message.extend_from_slice(&CLASS_IN.to_be_bytes());
+  message
+}
+
+#[derive(Debug, PartialEq)]
+enum RecordData {
+  Ptr(String),
+  Srv(String),
+  Address(IpAddr),
+  Other,
+}
+
+#[derive(Debug, PartialEq)]
+struct Record {
+  name: String,
+  data: RecordData,
+}
+
+// All the resource records of a DNS message. None if it is malformed.
+fn parse_records(message: &[u8]) -> Option<Vec<Record>> {
+  let count = |at: usize| -> Option<usize> {
+    Some(u16::from_be_bytes(message.get(at..at + 2)?.try_into().ok()?) as usize)
+  };
+  let questions = count(4)?;
+  let records = count(6)? + count(8)? + count(10)?;
+
+  let mut pos = 12;
+  for _ in 0..questions {
+    pos = read_name(message, pos)?.1 + 4; // type and class
+  }
+  let mut parsed = Vec::with_capacity(records);
+  for _ in 0..records {
+    let (name, after_name) = read_name(message, pos)?;
+    let fixed = message.get(after_name..after_name + 10)?;
+    let rtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+    let data_len = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+    let data_start = after_name + 10;
+    let data = message.get(data_start..data_start + data_len)?;
+    let data = match rtype {
+      TYPE_PTR => RecordData::Ptr(read_name(message, data_start)?.0),
+      // Priority, weight and port precede the target
+      TYPE_SRV => RecordData::Srv(read_name(message, data_start + 6)?.0),
+      TYPE_A => RecordData::Address(Ipv4Addr::from(<[u8; 4]>::try_from(data).ok()?).into()),
+      TYPE_AAAA => RecordData::Address(Ipv6Addr::from(<[u8; 16]>::try_from(data).ok()?).into()),
+      _ => RecordData::Other,
+    };
+    parsed.push(Record { name, data });
+    pos = data_start + data_len;
+  }
+  Some(parsed)
+}
+
+// Reads a possibly compressed name. Returns it normalized, and the position
+// after it.
+fn read_name(message: &[u8], start: usize) -> Option<(String, usize)> {
+  const MAX_JUMPS: usize = 16;
+  let mut labels: Vec<String> = Vec::new();
+  let mut pos = start;
+  let mut end = None;
+  let mut jumps = 0;
+  loop {
+    let len = *message.get(pos)? as usize;
+    if len == 0 {
+      let end = end.unwrap_or(pos + 1);
+      return Some((labels.join(".").to_ascii_lowercase(), end));
+    } else if len & 0xC0 == 0xC0 {
+      // Compression pointer to an earlier name
+      let offset = ((len & 0x3F) << 8) | *message.get(pos + 1)? as usize;
+      end.get_or_insert(pos + 2);
+      jumps += 1;
+      if jumps > MAX_JUMPS {
+        return None;
+      }
+      pos = offset;
+    } else {
+      let label = message.get(pos + 1..pos + 1 + len)?;
+      labels.push(String::from_utf8_lossy(label).into_owned());
+      pos += 1 + len;
+    }
+  }
+}
+
+// Addresses of the hosts of the instances of the service
+fn service_hosts(service: &str, records: &[Record]) -> Vec<IpAddr> {
+  let instances: BTreeSet<&str> = records
+    .iter()
+    .filter(|r| r.name == service)
+    .filter_map(|r| match &r.data {
+      RecordData::Ptr(instance) => Some(instance.as_str()),
+      _ => None,
+    })
+    .collect();
+  let hosts: BTreeSet<&str> = records
+    .iter()
+    .filter(|r| instances.contains(r.name.as_str()))
+    .filter_map(|r| match &r.data {
+      RecordData::Srv(host) => Some(host.as_str()),
+      _ => None,
+    })
+    .collect();
+  records
+    .iter()
+    .filter(|r| hosts.contains(r.name.as_str()))
+    .filter_map(|r| match r.data {
+      RecordData::Address(ip) => Some(ip),
+      _ => None,
+    })
+    .collect::<BTreeSet<_>>()
+    .into_iter()
+    .collect()
+}
+
+// A PeerResolver with the period it is called at
+pub(crate) struct PeerResolution {
+  pub resolver: Box<dyn PeerResolver>,
+  pub period: Duration,
+}
+
+// Calls the PeerResolver periodically in its own thread, and gives the
+// resolved peers to the event loop. Name services may block for seconds, so
+// the thread is not joined on drop, but stops at its next wakeup.
+pub(crate) struct PeerResolutionThread {
+  stop: Arc<AtomicBool>,
+  handle: JoinHandle<()>,
+}
+
+impl PeerResolutionThread {
+  pub fn start(
+    resolution: PeerResolution,
+    domain_id: u16,
+    max_participant_id: u16,
+    event_loop_commands: mio_channel::Sender<EventLoopCommand>,
+  ) -> io::Result<Self> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = thread::Builder::new()
+      .name("RustDDS peer resolver".to_string())
+      .spawn({
+        let stop = Arc::clone(&stop);
+        move || {
+          let PeerResolution {
+            mut resolver,
+            period,
+          } = resolution;
+          let mut previous = None;
+          while !stop.load(Ordering::Acquire) {
+            match resolver.resolve() {
+              Ok(peers) => {
+                let locators = spdp_peer_locators(&peers, max_participant_id, domain_id);
+                if previous.as_ref() != Some(&locators) {
+                  debug!("Resolved discovery peers {peers:?}");
+                  let command = EventLoopCommand::SetResolvedPeers {
+                    locators: locators.clone(),
+                  };
+                  if event_loop_commands.send(command).is_err() {
+                    return; // the event loop has stopped
+                  }
+                  previous = Some(locators);
+                }
+              }
+              Err(e) => warn!("Cannot resolve discovery peers: {e}"),
+            }
+            thread::park_timeout(period);
+          }
+        }
+      })?;
+    Ok(Self { stop, handle })
+  }
+}
+
+impl Drop for PeerResolutionThread {
+  fn drop(&mut self) {
+    self.stop.store(true, Ordering::Release);
+    self.handle.thread().unpark();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn dns_resolves_localhost() {
+    let mut resolver = DnsPeerResolver::new(["localhost", "no-such-host.invalid"]);
+    let peers = resolver.resolve().unwrap();
+    assert!(peers.iter().all(IpAddr::is_loopback));
+    assert!(!peers.is_empty());
+
+    assert!(DnsPeerResolver::new(["no-such-host.invalid"])
+      .resolve()
+      .is_err());
+  }
+
+  #[test]
+  fn service_hosts_are_found_in_mdns_response() {
+    let mut message = vec![0, 0, 0x84, 0, 0, 0, 0, 3, 0, 0, 0, 2];
+    // PTR _rtps._udp.local -> node1._rtps._udp.local
+    let service_at = message.len();
+    message.extend_from_slice(b"\x05_rtps\x04_udp\x05local\x00");
+    message.extend_from_slice(&[0, 12, 0, 1, 0, 0, 0, 120, 0, 8]);
+    let instance_at = message.len();
+    message.extend_from_slice(b"\x05node1");
+    message.extend_from_slice(&[0xC0, service_at as u8]);
+    // SRV node1._rtps._udp.local -> host1.local, port 7400
+    message.extend_from_slice(&[0xC0, instance_at as u8]);
+    message.extend_from_slice(&[0, 33, 0, 1, 0, 0, 0, 120, 0, 14]);
+    message.extend_from_slice(&[0, 0, 0, 0, 0x1C, 0xE8]);
+    let host_at = message.len();
+    message.extend_from_slice(b"\x05Host1");
+    message.extend_from_slice(&[0xC0, (service_at + 11) as u8]);
+    // An unrelated PTR
+    message.extend_from_slice(b"\x04_ftp\xC0");
+    message.push((service_at + 6) as u8);
+    message.extend_from_slice(&[0, 12, 0, 1, 0, 0, 0, 120, 0, 2]);
+    message.extend_from_slice(&[0xC0, service_at as u8]);
+    // Additional A and AAAA of host1.local
+    message.extend_from_slice(&[0xC0, host_at as u8]);
+    message.extend_from_slice(&[0, 1, 0, 1, 0, 0, 0, 120, 0, 4, 10, 0, 0, 7]);
+    message.extend_from_slice(&[0xC0, host_at as u8]);
+    message.extend_from_slice(&[0, 28, 0, 1, 0, 0, 0, 120, 0, 16]);
+    message.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+
+    let records = parse_records(&message).unwrap();
+    assert_eq!(records.len(), 5);
+    assert_eq!(records[1].data, RecordData::Srv("host1.local".to_string()));
+    let hosts = service_hosts(&normalize_name("_RTPS._udp.local."), &records);
+    assert_eq!(
+      hosts,
+      vec![
+        IpAddr::from(Ipv4Addr::new(10, 0, 0, 7)),
+        IpAddr::from(Ipv6Addr::LOCALHOST)
+      ]
+    );
+
+    // Truncated messages and pointer loops are rejected
+    assert!(parse_records(&message[..message.len() - 1]).is_none());
+    assert!(read_name(&[0xC0, 0], 0).is_none());
+  }
+
+  #[test]
+  fn query_asks_for_ptr_records() {
+    let query = ptr_query("_rtps._udp.local");
+    assert_eq!(&query[4..6], &[0, 1]);
+    assert_eq!(
+      read_name(&query, 12),
+      Some(("_rtps._udp.local".to_string(), 30))
+    );
+    assert_eq!(&query[30..], &[0, 12, 0, 1]);
+  }
+}
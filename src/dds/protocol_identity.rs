@@ -0,0 +1,125 @@
+//! The RTPS protocol version and vendor id that a DomainParticipant
+//! advertises.
+//!
+//! By default, a DomainParticipant tells the version of RTPS that RustDDS
+//! implements and the vendor id of RustDDS, in the header of each RTPS message
+//! and in its SPDP participant announcements. Forward compatibility tests, and
+//! bridges that emulate another implementation, can advertise something else
+//! with
+//! [`DomainParticipantBuilder::protocol_identity`](crate::DomainParticipantBuilder::protocol_identity).
+//! This changes only what remote participants see: messages are still encoded
+//! as RustDDS does. Remote participants may apply their vendor-specific
+//! behavior based on the vendor id, and other RustDDS participants do not
+//! recognize a participant with another vendor id as RustDDS.
+//!
+//! Received messages of a newer minor version of RTPS are processed, as the
+//! specification requires, skipping submessages and parameters that RustDDS
+//! does not know. Messages of a newer major version are dropped, unless
+//! [`ProtocolIdentity::accept_newer_major_versions`] is set. The first message
+//! of each newer version is logged.
+
+use std::{cell::Cell, sync::Mutex};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::messages::{protocol_version::ProtocolVersion, vendor_id::VendorId};
+
+/// What a DomainParticipant advertises of itself in RTPS. See the
+/// [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolIdentity {
+  /// Version in the RTPS message headers and SPDP announcements
+  pub protocol_version: ProtocolVersion,
+  /// Vendor id in the RTPS message headers and SPDP announcements
+  pub vendor_id: VendorId,
+  /// Process received messages of a newer major version of RTPS as if they
+  /// were of this version, instead of dropping them. For testing only.
+  pub accept_newer_major_versions: bool,
+}
+
+impl ProtocolIdentity {
+  pub const THIS_IMPLEMENTATION: Self = Self {
+    protocol_version: ProtocolVersion::THIS_IMPLEMENTATION,
+    vendor_id: VendorId::THIS_IMPLEMENTATION,
+    accept_newer_major_versions: false,
+  };
+
+  // Identity of the DomainParticipant that runs the current thread. The
+  // default in application threads.
+  pub(crate) fn current() -> Self {
+    CURRENT_IDENTITY.with(Cell::get)
+  }
+
+  pub(crate) fn set_for_current_thread(self) {
+    CURRENT_IDENTITY.with(|i| i.set(self));
+  }
+}
+
+impl Default for ProtocolIdentity {
+  fn default() -> Self {
+    Self::THIS_IMPLEMENTATION
+  }
+}
+
+// Messages are built and parsed in the background threads of a
+// DomainParticipant, which set the identity when they start, as with
+// ComplianceMode.
+thread_local! {
+  static CURRENT_IDENTITY: Cell<ProtocolIdentity> =
+    const { Cell::new(ProtocolIdentity::THIS_IMPLEMENTATION) };
+}
+
+// Newer versions that have been logged already
+static LOGGED_VERSIONS: Mutex<Vec<ProtocolVersion>> = Mutex::new(Vec::new());
+
+// Called for the version of each received RTPS message. Returns false, if
+// the message must be dropped.
+pub(crate) fn accepts_version(version: ProtocolVersion) -> bool {
+  let implemented = ProtocolVersion::THIS_IMPLEMENTATION;
+  if version <= implemented {
+    return true;
+  }
+  let accept =
+    version.major == implemented.major || ProtocolIdentity::current().accept_newer_major_versions;
+
+  let mut logged = LOGGED_VERSIONS.lock().unwrap_or_else(|e| e.into_inner());
+  if !logged.contains(&version) {
+    logged.push(version);
+    if accept {
+      info!("Received RTPS {version:?} messages. Processing them as RTPS {implemented:?}.");
+    } else {
+      warn!("Received RTPS {version:?} messages. Dropping them, as the major version is newer.");
+    }
+  }
+  accept
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn newer_versions() {
+    let implemented = ProtocolVersion::THIS_IMPLEMENTATION;
+    let newer_minor = ProtocolVersion {
+      major: implemented.major,
+      minor: implemented.minor + 1,
+    };
+    let newer_major = ProtocolVersion {
+      major: implemented.major + 1,
+      minor: 0,
+    };
+    assert!(accepts_version(ProtocolVersion::PROTOCOLVERSION_2_1));
+    assert!(accepts_version(newer_minor));
+    assert!(!accepts_version(newer_major));
+
+    ProtocolIdentity {
+      accept_newer_major_versions: true,
+      ..ProtocolIdentity::default()
+    }
+    .set_for_current_thread();
+    assert!(accepts_version(newer_major));
+    ProtocolIdentity::default().set_for_current_thread();
+  }
+}
@@ -1,6 +1,7 @@
 use std::{
+  collections::{BTreeMap, BTreeSet},
   fmt::Debug,
-  sync::{Arc, Mutex, MutexGuard, RwLock},
+  sync::{atomic::AtomicU64, mpsc::RecvTimeoutError, Arc, Mutex, MutexGuard, RwLock, Weak},
   time::Duration,
 };
 
@@ -11,9 +12,11 @@ use byteorder::LittleEndian;
 use log::{debug, error, info, trace, warn};
 
 use crate::{
-  create_error_dropped, create_error_internal, create_error_poisoned,
+  create_error_bad_parameter, create_error_dropped, create_error_internal, create_error_poisoned,
   dds::{
     adapters,
+    communication_status::{ReaderStatusRecord, WriterStatusRecord},
+    content_filter::{ContentFilterFactory, ContentFilteredTopic, MatchedReaderFilters},
     key::Keyed,
     no_key,
     no_key::{
@@ -21,7 +24,7 @@ use crate::{
     },
     participant::*,
     qos::*,
-    result::{CreateError, CreateResult, WaitResult},
+    result::{CreateError, CreateResult, WaitError, WaitResult, WriteError, WriteResult},
     statusevents::{sync_status_channel, DataReaderStatus},
     topic::*,
     with_key,
@@ -30,12 +33,14 @@ use crate::{
     },
   },
   discovery::{
-    discovery::DiscoveryCommand, discovery_db::DiscoveryDB, sedp_messages::DiscoveredWriterData,
+    content_filter_property::ContentFilterProperty, discovery::DiscoveryCommand,
+    discovery_db::DiscoveryDB, sedp_messages::DiscoveredWriterData,
   },
   mio_source,
   rtps::{
+    dp_event_loop::EventLoopCommand,
     reader::ReaderIngredients,
-    writer::{WriterCommand, WriterIngredients},
+    writer::{WatermarkTracker, WriterCommand, WriterIngredients},
   },
   serialization::{CDRDeserializerAdapter, CDRSerializerAdapter},
   structure::{
@@ -117,6 +122,12 @@ impl Publisher {
     }
   }
 
+  pub(crate) fn downgrade(&self) -> PublisherWeak {
+    PublisherWeak {
+      inner: Arc::downgrade(&self.inner),
+    }
+  }
+
   fn inner_lock(&self) -> MutexGuard<'_, InnerPublisher> {
     self
       .inner
@@ -253,7 +264,6 @@ impl Publisher {
       .create_datawriter(self, Some(entity_id), topic, qos, writer_like_stateless)
   }
 
-  #[cfg(feature = "security")] // to avoid "never used" warning
   pub(crate) fn create_datawriter_with_entity_id_no_key<D, SA>(
     &self,
     entity_id: EntityId,
@@ -276,8 +286,39 @@ impl Publisher {
   // delete_datawriter should not be needed. The DataWriter object itself should
   // be deleted to accomplish this.
 
-  // lookup datawriter: maybe not necessary? App should remember datawriters it
-  // has created.
+  /// Finds a DataWriter of this Publisher by the name of its Topic. If there
+  /// are several, any one of them is returned.
+  ///
+  /// The DataWriter is identified by its GUID, since the DataWriter object
+  /// belongs to the application.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::*;
+  /// # use serde::Serialize;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize)]
+  /// # struct SomeType { a: i32 }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
+  /// let data_writer = publisher.create_datawriter_no_key_cdr::<SomeType>(&topic, None).unwrap();
+  /// assert_eq!(publisher.lookup_datawriter("some_topic"), Some(data_writer.guid()));
+  /// assert_eq!(publisher.lookup_datawriter("other_topic"), None);
+  /// ```
+  pub fn lookup_datawriter(&self, topic_name: &str) -> Option<GUID> {
+    self.inner_lock().lookup_datawriter(topic_name)
+  }
+
+  /// Lists the DataWriters of this Publisher, i.e. those created by it and
+  /// not yet dropped or deleted.
+  pub fn get_datawriters(&self) -> Vec<GUID> {
+    self.inner_lock().writers.iter().copied().collect()
+  }
 
   // Suspend and resume publications are performance optimization methods.
   // The minimal correct implementation is to do nothing. See DDS spec 2.2.2.4.1.8
@@ -335,8 +376,14 @@ impl Publisher {
     self.inner_lock().domain_participant.clone().upgrade()
   }
 
-  // delete_contained_entities: We should not need this. Contained DataWriters
-  // should dispose themselves and notify publisher.
+  /// Deletes all the DataWriters of this Publisher. Their RTPS Writers are
+  /// removed, and remote participants are told that they are gone.
+  ///
+  /// The DataWriter objects still held by the application can no longer
+  /// write. They should be dropped.
+  pub fn delete_contained_entities(&self) {
+    self.inner_lock().delete_contained_entities();
+  }
 
   /// Returns default DataWriter qos.
   ///
@@ -377,12 +424,26 @@ impl Publisher {
     self.inner_lock().set_default_datawriter_qos(q);
   }
 
-  // This is used on DataWriter .drop()
-  pub(crate) fn remove_writer(&self, guid: GUID) {
-    self.inner_lock().remove_writer(guid);
+  // This is used on DataWriter .drop(). Returns false, if the DataWriter has
+  // already been deleted.
+  pub(crate) fn remove_writer(&self, guid: GUID) -> bool {
+    self.inner_lock().remove_writer(guid)
   }
 } // impl
 
+// Reference to a Publisher that does not keep it alive. DomainParticipant
+// lists its Publishers with these.
+#[derive(Clone)]
+pub(crate) struct PublisherWeak {
+  inner: Weak<Mutex<InnerPublisher>>,
+}
+
+impl PublisherWeak {
+  pub fn upgrade(&self) -> Option<Publisher> {
+    self.inner.upgrade().map(|inner| Publisher { inner })
+  }
+}
+
 impl PartialEq for Publisher {
   fn eq(&self, other: &Self) -> bool {
     let id_self = { self.inner_lock().identity() };
@@ -397,6 +458,306 @@ impl Debug for Publisher {
   }
 }
 
+/// Sends the data of several DataWriters of one [`Publisher`] in a single
+/// burst.
+///
+/// Data written to the added DataWriters is not sent right away, but kept
+/// until [`flush`](Self::flush) is called. Then the data of all the writers is
+/// sent together, and RTPS messages to the same destination are combined into
+/// as few UDP datagrams as possible. This reduces the jitter between
+/// logically related topics, such as pose and velocity published on each
+/// control cycle.
+///
+/// Adding and removing writers takes effect asynchronously in the background
+/// thread. Data written immediately after [`add_writer`](Self::add_writer)
+/// may still be sent without waiting for a flush.
+///
+/// Deferral ends when a writer is removed or the controller is dropped, and
+/// any data still pending is then sent.
+///
+/// # Example
+///
+/// ```
+/// # use serde::{Deserialize, Serialize};
+/// # use rustdds::*;
+/// # #[derive(Serialize, Deserialize, Debug)]
+/// # struct Pose { x: f64, y: f64 }
+/// let domain_participant = DomainParticipant::new(0).unwrap();
+/// let qos = QosPolicyBuilder::new().build();
+/// let publisher = domain_participant.create_publisher(&qos).unwrap();
+/// let topic = domain_participant
+///   .create_topic("pose".to_string(), "Pose".to_string(), &qos, TopicKind::NoKey)
+///   .unwrap();
+/// let writer = publisher.create_datawriter_no_key_cdr::<Pose>(&topic, None).unwrap();
+///
+/// let mut controller = PublisherFlushController::new(&publisher);
+/// controller.add_writer_no_key(&writer).unwrap();
+/// writer.write(Pose { x: 1.0, y: 2.0 }, None).unwrap();
+/// controller.flush().unwrap();
+/// ```
+pub struct PublisherFlushController {
+  publisher: Publisher,
+  writers: Vec<EntityId>,
+}
+
+impl PublisherFlushController {
+  pub fn new(publisher: &Publisher) -> Self {
+    Self {
+      publisher: publisher.clone(),
+      writers: Vec::new(),
+    }
+  }
+
+  /// Starts deferring the data of a WITH_KEY DataWriter. Fails with
+  /// `BadParameter`, if the writer belongs to another Publisher.
+  pub fn add_writer<D, SA>(&mut self, writer: &WithKeyDataWriter<D, SA>) -> CreateResult<()>
+  where
+    D: Keyed,
+    SA: adapters::with_key::SerializerAdapter<D>,
+  {
+    self.add(writer.publisher(), writer.guid())
+  }
+
+  /// Starts deferring the data of a NO_KEY DataWriter. Fails with
+  /// `BadParameter`, if the writer belongs to another Publisher.
+  pub fn add_writer_no_key<D, SA>(&mut self, writer: &NoKeyDataWriter<D, SA>) -> CreateResult<()>
+  where
+    SA: adapters::no_key::SerializerAdapter<D>,
+  {
+    self.add(writer.publisher(), writer.guid())
+  }
+
+  fn add(&mut self, publisher: &Publisher, writer: GUID) -> CreateResult<()> {
+    if *publisher != self.publisher {
+      return create_error_bad_parameter!(
+        "DataWriter {writer:?} does not belong to the Publisher of the flush controller."
+      );
+    }
+    if self.writers.contains(&writer.entity_id) {
+      return Ok(());
+    }
+    let participant = match self.publisher.participant() {
+      Some(dp) => dp,
+      None => return create_error_dropped!("DomainParticipant doesn't exist anymore."),
+    };
+    match participant.send_event_loop_command(EventLoopCommand::DeferWriterData {
+      writer: writer.entity_id,
+      defer: true,
+    }) {
+      Ok(()) => {
+        self.writers.push(writer.entity_id);
+        Ok(())
+      }
+      Err(e) => create_error_poisoned!("Cannot defer data of writer {writer:?}: {e:?}"),
+    }
+  }
+
+  /// Stops deferring the data of a DataWriter, and sends any data still
+  /// pending. Returns false, if the writer was not added.
+  pub fn remove_writer(&mut self, writer: GUID) -> bool {
+    let len = self.writers.len();
+    self.writers.retain(|w| *w != writer.entity_id);
+    let removed = self.writers.len() < len;
+    if removed {
+      self.undefer(writer.entity_id);
+    }
+    removed
+  }
+
+  fn undefer(&self, writer: EntityId) {
+    match self.publisher.participant() {
+      Some(dp) => dp
+        .send_event_loop_command(EventLoopCommand::DeferWriterData {
+          writer,
+          defer: false,
+        })
+        .unwrap_or_else(|e| error!("Cannot end deferral of writer {writer:?}: {e:?}")),
+      // The writers are gone with the participant.
+      None => debug!("PublisherFlushController: DomainParticipant is already gone."),
+    }
+  }
+
+  /// Sends the data written to the added DataWriters since the previous
+  /// flush.
+  pub fn flush(&self) -> WriteResult<(), ()> {
+    let participant = match self.publisher.participant() {
+      Some(dp) => dp,
+      None => {
+        return Err(WriteError::Poisoned {
+          reason: "DomainParticipant doesn't exist anymore.".to_string(),
+          data: (),
+        })
+      }
+    };
+    participant
+      .send_event_loop_command(EventLoopCommand::FlushWriters {
+        writers: self.writers.clone(),
+      })
+      .map_err(|e| WriteError::Poisoned {
+        reason: format!("Cannot flush writers: {e:?}"),
+        data: (),
+      })
+  }
+}
+
+impl Drop for PublisherFlushController {
+  fn drop(&mut self) {
+    for writer in &self.writers {
+      self.undefer(*writer);
+    }
+  }
+}
+
+/// Switches a group of Publishers and Subscribers to a new
+/// [`Partition`](policy::Partition) at once.
+///
+/// The DataWriters and DataReaders of the group are switched together in the
+/// background thread, so that none of them sends or receives data according
+/// to the old partitions while others already use the new ones. This allows
+/// blue/green style cutovers, where a group of endpoints is moved from one
+/// data flow to another at runtime. DataWriters and DataReaders created later
+/// in the group use the new partitions, too.
+///
+/// The new partitions are announced in discovery, and remote participants
+/// match their endpoints again, when they receive the announcement.
+///
+/// The QoS policies of the switched DataWriters and DataReaders, as returned
+/// by their `qos` methods, are not updated.
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// # use rustdds::*;
+/// let domain_participant = DomainParticipant::new(0).unwrap();
+/// let qos = QosPolicyBuilder::new()
+///   .partition(policy::Partition::new(&["blue"]))
+///   .build();
+/// let publisher = domain_participant.create_publisher(&qos).unwrap();
+/// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+///
+/// let mut switch = PartitionSwitch::new();
+/// switch.add_publisher(&publisher);
+/// switch.add_subscriber(&subscriber);
+/// let cutover = switch.switch_to(policy::Partition::new(&["green"])).unwrap();
+/// let report = cutover.wait(Duration::from_secs(1)).unwrap();
+/// ```
+#[derive(Default)]
+pub struct PartitionSwitch {
+  publishers: Vec<Publisher>,
+  subscribers: Vec<Subscriber>,
+}
+
+impl PartitionSwitch {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn add_publisher(&mut self, publisher: &Publisher) {
+    if !self.publishers.contains(publisher) {
+      self.publishers.push(publisher.clone());
+    }
+  }
+
+  pub fn add_subscriber(&mut self, subscriber: &Subscriber) {
+    if !self
+      .subscribers
+      .iter()
+      .any(|s| Arc::ptr_eq(&s.inner, &subscriber.inner))
+    {
+      self.subscribers.push(subscriber.clone());
+    }
+  }
+
+  /// Replaces the partitions of all the Publishers and Subscribers of the
+  /// group, and of their DataWriters and DataReaders, with `partition`.
+  ///
+  /// Fails with `BadParameter`, if the group is empty or belongs to several
+  /// DomainParticipants.
+  pub fn switch_to(&self, partition: policy::Partition) -> CreateResult<PartitionCutover> {
+    let mut participants = self
+      .publishers
+      .iter()
+      .map(Publisher::participant)
+      .chain(self.subscribers.iter().map(Subscriber::participant));
+    let participant = match participants.next() {
+      Some(Some(dp)) => dp,
+      Some(None) => return create_error_dropped!("DomainParticipant doesn't exist anymore."),
+      None => {
+        return create_error_bad_parameter!("PartitionSwitch has no Publishers or Subscribers.")
+      }
+    };
+    if participants.any(|dp| dp.as_ref() != Some(&participant)) {
+      return create_error_bad_parameter!(
+        "PartitionSwitch members belong to different DomainParticipants."
+      );
+    }
+
+    let mut writers = Vec::new();
+    for publisher in &self.publishers {
+      writers.extend(publisher.inner_lock().switch_partition(&partition)?);
+    }
+    let mut readers = Vec::new();
+    for subscriber in &self.subscribers {
+      readers.extend(subscriber.inner.switch_partition(&partition)?);
+    }
+
+    let (done_sender, done_receiver) = std::sync::mpsc::channel();
+    participant
+      .send_event_loop_command(EventLoopCommand::SwitchPartition {
+        writers: writers.iter().map(|w| w.entity_id).collect(),
+        readers: readers.iter().map(|r| r.entity_id).collect(),
+        partition,
+        done: done_sender,
+      })
+      .or_else(|e| create_error_poisoned!("Cannot switch partitions: {e:?}"))?;
+
+    // Announce the new partitions to remote participants
+    for publisher in &self.publishers {
+      publisher.inner_lock().announce_writers();
+    }
+    for subscriber in &self.subscribers {
+      subscriber.inner.announce_readers();
+    }
+
+    Ok(PartitionCutover {
+      done: done_receiver,
+    })
+  }
+}
+
+/// A partition switch started by [`PartitionSwitch::switch_to`].
+pub struct PartitionCutover {
+  done: std::sync::mpsc::Receiver<PartitionSwitchReport>,
+}
+
+impl PartitionCutover {
+  /// Waits until all the switched DataWriters and DataReaders have been
+  /// matched again with the endpoints discovered so far. Remote participants
+  /// update their side of the matches, as they receive the new partitions in
+  /// discovery.
+  pub fn wait(&self, max_wait: Duration) -> WaitResult<PartitionSwitchReport> {
+    match self.done.recv_timeout(max_wait) {
+      Ok(report) => Ok(report),
+      Err(RecvTimeoutError::Timeout) => Err(WaitError::Timeout),
+      Err(RecvTimeoutError::Disconnected) => {
+        // The event loop has stopped, so the switch will not complete.
+        warn!("PartitionCutover: DomainParticipant event loop is gone.");
+        Err(WaitError::Timeout)
+      }
+    }
+  }
+}
+
+/// Matches of the switched endpoints after a partition switch.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PartitionSwitchReport {
+  /// Number of DataReaders matched to each switched DataWriter
+  pub matched_readers: BTreeMap<GUID, usize>,
+  /// Number of DataWriters matched to each switched DataReader
+  pub matched_writers: BTreeMap<GUID, usize>,
+}
+
 // "Inner" struct
 
 #[derive(Clone)]
@@ -410,6 +771,7 @@ struct InnerPublisher {
   remove_writer_sender: mio_channel::SyncSender<GUID>,
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   security_plugins_handle: Option<SecurityPluginsHandle>,
+  writers: BTreeSet<GUID>, // DataWriters created by this Publisher and not yet dropped
 }
 
 // public interface for Publisher
@@ -440,11 +802,12 @@ impl InnerPublisher {
       remove_writer_sender,
       discovery_command,
       security_plugins_handle,
+      writers: BTreeSet::new(),
     }
   }
 
   pub fn create_datawriter<D, SA>(
-    &self,
+    &mut self,
     outer: &Publisher,
     entity_id_opt: Option<EntityId>,
     topic: &Topic,
@@ -460,6 +823,14 @@ impl InnerPublisher {
     let writer_waker = Arc::new(Mutex::new(None));
     // Status reports back from Writer to DataWriter.
     let (status_sender, status_receiver) = sync_status_channel(4)?;
+    // Samples skipped by the Conflation policy, counted by Writer.
+    let conflated_samples = Arc::new(AtomicU64::new(0));
+    // Acknowledgement progress, updated by Writer.
+    let watermarks = Arc::new(WatermarkTracker::new());
+    // Content filters of matched readers, updated by Writer.
+    let matched_reader_filters = Arc::new(MatchedReaderFilters::default());
+    // Communication statuses, updated by Writer.
+    let status_record = Arc::new(WriterStatusRecord::default());
 
     // DDS Spec 2.2.2.4.1.5 create_datawriter:
     // If no QoS is specified, we should take the Publisher default
@@ -473,6 +844,19 @@ impl InnerPublisher {
       .modify_by(&topic.qos())
       .modify_by(&optional_qos.unwrap_or_else(QosPolicies::qos_none));
 
+    // A pinned Data Representation must be what the serializer writes.
+    if let Some(data_representation) = writer_qos.data_representation() {
+      let pinned = data_representation.preferred();
+      if policy::DataRepresentationId::of(SA::output_encoding()) != Some(pinned) {
+        return create_error_bad_parameter!(
+          "DataRepresentation of topic {} is {:?}, but the serializer writes {:?}",
+          topic.name(),
+          pinned,
+          SA::output_encoding()
+        );
+      }
+    }
+
     let entity_id =
       self.unwrap_or_new_entity_id(entity_id_opt, EntityKind::WRITER_WITH_KEY_USER_DEFINED);
     let dp = self
@@ -539,6 +923,10 @@ impl InnerPublisher {
       like_stateless: writer_like_stateless,
       qos_policies: writer_qos.clone(),
       status_sender,
+      conflated_samples: Arc::clone(&conflated_samples),
+      watermarks: Arc::clone(&watermarks),
+      matched_reader_filters: Arc::clone(&matched_reader_filters),
+      status_record: Arc::clone(&status_record),
       security_plugins: self.security_plugins_handle.clone(),
     };
 
@@ -558,7 +946,13 @@ impl InnerPublisher {
       writer_waker,
       self.discovery_command.clone(),
       status_receiver,
+      conflated_samples,
+      watermarks,
+      matched_reader_filters,
+      status_record,
+      dp.intra_process(),
     )?;
+    self.writers.insert(guid);
 
     // notify Discovery DB
     let mut db = self
@@ -629,7 +1023,7 @@ impl InnerPublisher {
   }
 
   pub fn create_datawriter_no_key<D, SA>(
-    &self,
+    &mut self,
     outer: &Publisher,
     entity_id_opt: Option<EntityId>,
     topic: &Topic,
@@ -673,9 +1067,57 @@ impl InnerPublisher {
     entity_id_opt.unwrap_or_else(|| self.participant().unwrap().new_entity_id(entity_kind))
   }
 
-  pub(crate) fn remove_writer(&self, guid: GUID) {
+  // Moves this Publisher and its DataWriters to `partition`. Returns the
+  // DataWriters.
+  fn switch_partition(&mut self, partition: &policy::Partition) -> CreateResult<Vec<GUID>> {
+    self.my_qos_policies.partition = Some(partition.clone());
+    self.default_datawriter_qos.partition = Some(partition.clone());
+    let mut db = self
+      .discovery_db
+      .write()
+      .or_else(|e| create_error_poisoned!("Cannot lock discovery_db. {}", e))?;
+    for writer in &self.writers {
+      db.update_local_endpoint_partition(*writer, partition);
+    }
+    Ok(self.writers.iter().copied().collect())
+  }
+
+  // Publishing a local writer again in discovery announces its changed QoS.
+  fn announce_writers(&self) {
+    for writer in &self.writers {
+      self
+        .discovery_command
+        .try_send(DiscoveryCommand::AddLocalWriter { guid: *writer })
+        .unwrap_or_else(|e| error!("Cannot announce writer {writer:?}: {e}"));
+    }
+  }
+
+  pub(crate) fn remove_writer(&mut self, guid: GUID) -> bool {
+    if !self.writers.remove(&guid) {
+      return false;
+    }
     try_send_timeout(&self.remove_writer_sender, guid, None)
       .unwrap_or_else(|e| error!("Cannot remove Writer {:?} : {:?}", guid, e));
+    true
+  }
+
+  fn lookup_datawriter(&self, topic_name: &str) -> Option<GUID> {
+    let db = self.discovery_db.read().ok()?;
+    self.writers.iter().copied().find(|writer| {
+      db.get_local_topic_writer(*writer)
+        .is_some_and(|w| w.publication_topic_data.topic_name == topic_name)
+    })
+  }
+
+  // Removes the DataWriters like dropping them would.
+  fn delete_contained_entities(&mut self) {
+    for writer in self.writers.clone() {
+      self.remove_writer(writer);
+      self
+        .discovery_command
+        .try_send(DiscoveryCommand::RemoveLocalWriter { guid: writer })
+        .unwrap_or_else(|e| error!("Cannot remove writer {writer:?} from Discovery: {e}"));
+    }
   }
 
   pub(crate) fn identity(&self) -> EntityId {
@@ -807,6 +1249,44 @@ impl Subscriber {
     self.create_datareader::<D, CDRDeserializerAdapter<D>>(topic, qos)
   }
 
+  /// Create DDS DataReader for a
+  /// [`ContentFilteredTopic`](crate::dds::content_filter::ContentFilteredTopic).
+  ///
+  /// The reader reads the related Topic, but samples that do not pass the
+  /// filter expression are dropped before the application sees them. The
+  /// filter is announced in Discovery, so that remote DataWriters which
+  /// support it can leave out the non-matching samples already.
+  pub fn create_filtered_datareader<D, SA>(
+    &self,
+    topic: &ContentFilteredTopic,
+    qos: Option<QosPolicies>,
+  ) -> CreateResult<WithKeyDataReader<D, SA>>
+  where
+    D: 'static + Keyed + Serialize,
+    SA: adapters::with_key::DeserializerAdapter<D>,
+  {
+    let reader = self.create_datareader::<D, SA>(topic.related_topic(), qos)?;
+    reader.set_named_content_filter(
+      topic.name(),
+      &ContentFilterFactory::ddssql(),
+      topic.filter_expression(),
+      topic.expression_parameters().to_vec(),
+    )?;
+    Ok(reader)
+  }
+
+  pub fn create_filtered_datareader_cdr<D>(
+    &self,
+    topic: &ContentFilteredTopic,
+    qos: Option<QosPolicies>,
+  ) -> CreateResult<WithKeyDataReader<D, CDRDeserializerAdapter<D>>>
+  where
+    D: 'static + serde::de::DeserializeOwned + Keyed + Serialize,
+    for<'de> <D as Keyed>::K: Deserialize<'de>,
+  {
+    self.create_filtered_datareader::<D, CDRDeserializerAdapter<D>>(topic, qos)
+  }
+
   /// Create DDS DataReader for non keyed Topics
   ///
   /// # Arguments
@@ -893,7 +1373,6 @@ impl Subscriber {
       .create_datareader(self, topic, Some(entity_id), qos, reader_like_stateless)
   }
 
-  #[cfg(feature = "security")] // to avoid "never used" warning
   pub(crate) fn create_datareader_with_entity_id_no_key<D, SA>(
     &self,
     topic: &Topic,
@@ -910,23 +1389,53 @@ impl Subscriber {
       .create_datareader_no_key(self, topic, Some(entity_id), qos, reader_like_stateless)
   }
 
-  // Retrieves a previously created DataReader belonging to the Subscriber.
-  // TODO: Is this even possible. Would probably need to return reference and
-  // store references on creation
-  /*
-  pub(crate) fn lookup_datareader<D, SA>(
-    &self,
-    _topic_name: &str,
-  ) -> Option<WithKeyDataReader<D, SA>>
-  where
-    D: Keyed + DeserializeOwned,
-    SA: DeserializerAdapter<D>,
-  {
-    todo!()
-  // TO think: Is this really necessary? Because the caller would have to know
-    // types D and SA. Should we just trust whoever creates DataReaders to also remember them?
+  /// Finds a DataReader of this Subscriber by the name of its Topic. If there
+  /// are several, any one of them is returned.
+  ///
+  /// The DataReader is identified by its GUID, since the DataReader object
+  /// belongs to the application.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use rustdds::*;
+  /// # use serde::Deserialize;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Deserialize)]
+  /// # struct SomeType { a: i32 }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::NoKey).unwrap();
+  /// let data_reader = subscriber.create_datareader_no_key_cdr::<SomeType>(&topic, None).unwrap();
+  /// assert_eq!(subscriber.lookup_datareader("some_topic"), Some(data_reader.guid()));
+  /// ```
+  pub fn lookup_datareader(&self, topic_name: &str) -> Option<GUID> {
+    self.inner.lookup_datareader(topic_name)
+  }
+
+  /// Lists the DataReaders of this Subscriber, i.e. those created by it and
+  /// not yet dropped or deleted.
+  pub fn get_datareaders(&self) -> Vec<GUID> {
+    self.inner.readers.lock().unwrap().iter().copied().collect()
+  }
+
+  /// Deletes all the DataReaders of this Subscriber. Their RTPS Readers are
+  /// removed, and remote participants are told that they are gone.
+  ///
+  /// The DataReader objects still held by the application receive no more
+  /// data. They should be dropped.
+  pub fn delete_contained_entities(&self) {
+    self.inner.delete_contained_entities();
+  }
+
+  pub(crate) fn downgrade(&self) -> SubscriberWeak {
+    SubscriberWeak {
+      inner: Arc::downgrade(&self.inner),
+    }
   }
-  */
 
   /// Returns [DomainParticipant](struct.DomainParticipant.html) if it is sill
   /// alive.
@@ -946,20 +1455,43 @@ impl Subscriber {
     self.inner.participant()
   }
 
-  pub(crate) fn remove_reader(&self, guid: GUID) {
-    self.inner.remove_reader(guid);
+  // This is used on DataReader .drop(). Returns false, if the DataReader has
+  // already been deleted.
+  pub(crate) fn remove_reader(&self, guid: GUID) -> bool {
+    self.inner.remove_reader(guid)
+  }
+
+  pub(crate) fn set_reader_content_filter(
+    &self,
+    guid: GUID,
+    content_filter: Option<ContentFilterProperty>,
+  ) -> CreateResult<()> {
+    self.inner.set_reader_content_filter(guid, content_filter)
   }
 }
 
+// Reference to a Subscriber that does not keep it alive. DomainParticipant
+// lists its Subscribers with these.
 #[derive(Clone)]
+pub(crate) struct SubscriberWeak {
+  inner: Weak<InnerSubscriber>,
+}
+
+impl SubscriberWeak {
+  pub fn upgrade(&self) -> Option<Subscriber> {
+    self.inner.upgrade().map(|inner| Subscriber { inner })
+  }
+}
+
 pub struct InnerSubscriber {
   domain_participant: DomainParticipantWeak,
   discovery_db: Arc<RwLock<DiscoveryDB>>,
-  qos: QosPolicies,
+  qos: RwLock<QosPolicies>, // Partition may be switched
   sender_add_reader: mio_channel::SyncSender<ReaderIngredients>,
   sender_remove_reader: mio_channel::SyncSender<GUID>,
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   security_plugins_handle: Option<SecurityPluginsHandle>,
+  readers: Mutex<BTreeSet<GUID>>, // DataReaders created by this Subscriber and not yet dropped
 }
 
 impl InnerSubscriber {
@@ -975,11 +1507,12 @@ impl InnerSubscriber {
     Self {
       domain_participant,
       discovery_db,
-      qos,
+      qos: RwLock::new(qos),
       sender_add_reader,
       sender_remove_reader,
       discovery_command,
       security_plugins_handle,
+      readers: Mutex::new(BTreeSet::new()),
     }
   }
 
@@ -1036,9 +1569,27 @@ impl InnerSubscriber {
     // specified QoS.
     let qos = self
       .qos
+      .read()
+      .unwrap()
       .modify_by(&topic.qos())
       .modify_by(&optional_qos.unwrap_or_else(QosPolicies::qos_none));
 
+    // The deserializer must be able to read one of the pinned Data
+    // Representations.
+    if let Some(data_representation) = qos.data_representation() {
+      let readable = SA::supported_encodings().iter().any(|encoding| {
+        policy::DataRepresentationId::of(*encoding)
+          .is_some_and(|representation| data_representation.accepts(representation))
+      });
+      if !readable {
+        return create_error_bad_parameter!(
+          "The deserializer cannot read any DataRepresentation {:?} of topic {}",
+          data_representation.value,
+          topic.name()
+        );
+      }
+    }
+
     let entity_id =
       self.unwrap_or_new_entity_id(entity_id_opt, EntityKind::READER_WITH_KEY_USER_DEFINED);
 
@@ -1116,6 +1667,9 @@ impl InnerSubscriber {
 
     let (poll_event_source, poll_event_sender) = mio_source::make_poll_channel()?;
 
+    // Communication statuses, updated by Reader.
+    let status_record = Arc::new(ReaderStatusRecord::default());
+
     let new_reader = ReaderIngredients {
       guid: reader_guid,
       notification_sender: send,
@@ -1127,6 +1681,7 @@ impl InnerSubscriber {
       data_reader_command_receiver: reader_command_receiver,
       data_reader_waker: data_reader_waker.clone(),
       poll_event_sender,
+      status_record: Arc::clone(&status_record),
       security_plugins: self.security_plugins_handle.clone(),
     };
 
@@ -1191,7 +1746,9 @@ impl InnerSubscriber {
       reader_command_sender,
       data_reader_waker,
       poll_event_source,
+      status_record,
     )?;
+    self.readers.lock().unwrap().insert(datareader.guid());
 
     // Send reader ingredients to DP event loop, where the actual reader will be
     // constructed
@@ -1295,9 +1852,78 @@ impl InnerSubscriber {
     self.domain_participant.clone().upgrade()
   }
 
-  pub(crate) fn remove_reader(&self, guid: GUID) {
+  // Moves this Subscriber and its DataReaders to `partition`. Returns the
+  // DataReaders.
+  fn switch_partition(&self, partition: &policy::Partition) -> CreateResult<Vec<GUID>> {
+    self.qos.write().unwrap().partition = Some(partition.clone());
+    let readers = self.readers.lock().unwrap().clone();
+    let mut db = self
+      .discovery_db
+      .write()
+      .or_else(|e| create_error_poisoned!("Cannot lock discovery_db. {}", e))?;
+    for reader in &readers {
+      db.update_local_endpoint_partition(*reader, partition);
+    }
+    Ok(readers.into_iter().collect())
+  }
+
+  // Publishing a local reader again in discovery announces its changed QoS.
+  fn announce_readers(&self) {
+    for reader in self.readers.lock().unwrap().iter() {
+      self
+        .discovery_command
+        .try_send(DiscoveryCommand::AddLocalReader { guid: *reader })
+        .unwrap_or_else(|e| error!("Cannot announce reader {reader:?}: {e}"));
+    }
+  }
+
+  // Announces the changed content filter of a local reader in discovery.
+  fn set_reader_content_filter(
+    &self,
+    guid: GUID,
+    content_filter: Option<ContentFilterProperty>,
+  ) -> CreateResult<()> {
+    self
+      .discovery_db
+      .write()
+      .or_else(|e| create_error_poisoned!("Cannot lock discovery_db. {}", e))?
+      .update_local_reader_content_filter(guid, content_filter);
+    self
+      .discovery_command
+      .try_send(DiscoveryCommand::AddLocalReader { guid })
+      .or_else(|e| {
+        create_error_internal!("Cannot announce the content filter of reader {guid:?}: {e}")
+      })
+  }
+
+  pub(crate) fn remove_reader(&self, guid: GUID) -> bool {
+    if !self.readers.lock().unwrap().remove(&guid) {
+      return false;
+    }
     try_send_timeout(&self.sender_remove_reader, guid, None)
       .unwrap_or_else(|e| error!("Cannot remove Reader {:?} : {:?}", guid, e));
+    true
+  }
+
+  fn lookup_datareader(&self, topic_name: &str) -> Option<GUID> {
+    let readers = self.readers.lock().unwrap();
+    let db = self.discovery_db.read().ok()?;
+    readers.iter().copied().find(|reader| {
+      db.get_local_topic_reader(*reader)
+        .is_some_and(|r| r.subscription_topic_data.topic_name() == topic_name)
+    })
+  }
+
+  // Removes the DataReaders like dropping them would.
+  fn delete_contained_entities(&self) {
+    let readers = self.readers.lock().unwrap().clone();
+    for reader in readers {
+      self.remove_reader(reader);
+      self
+        .discovery_command
+        .try_send(DiscoveryCommand::RemoveLocalReader { guid: reader })
+        .unwrap_or_else(|e| error!("Cannot remove reader {reader:?} from Discovery: {e}"));
+    }
   }
 
   fn unwrap_or_new_entity_id(
@@ -44,11 +44,10 @@ pub enum QosPolicyId {
   // OwnershipStrength, // 7
   Liveliness,
   TimeBasedFilter, // 9
-  // Partition,
-
-  // Note: If "Partition" is ever implemented, observe also DDS Security spec v1.1
+  Partition,
+  // Note: Regarding "Partition", observe also DDS Security spec v1.1
   // Section "7.3.5 Immutability of Publisher Partition Qos in combination with non-volatile
-  // Durability kind" when implementing.
+  // Durability kind".
   Reliability, // 11
   DestinationOrder,
   History, // 13
@@ -61,7 +60,8 @@ pub enum QosPolicyId {
   // TransportPriority, // 20
   Lifespan,
   // DurabilityService, // 22
-  Property, // No Id in the security spec (But this is from older DDS/RTPs spec.)
+  DataRepresentation, // 23, from DDS-XTypes
+  Property,           // No Id in the security spec (But this is from older DDS/RTPs spec.)
 }
 
 /// Utility for building [QosPolicies]
@@ -74,11 +74,26 @@ pub struct QosPolicyBuilder {
   ownership: Option<policy::Ownership>,
   liveliness: Option<policy::Liveliness>,
   time_based_filter: Option<policy::TimeBasedFilter>,
+  partition: Option<policy::Partition>,
   reliability: Option<policy::Reliability>,
   destination_order: Option<policy::DestinationOrder>,
   history: Option<policy::History>,
   resource_limits: Option<policy::ResourceLimits>,
   lifespan: Option<policy::Lifespan>,
+  data_representation: Option<policy::DataRepresentation>,
+  writer_restart: Option<policy::WriterRestart>,
+  cache_watermarks: Option<policy::CacheWatermarks>,
+  delivery_order: Option<policy::DeliveryOrder>,
+  last_value_cache: Option<policy::LastValueCache>,
+  conflation: Option<policy::Conflation>,
+  payload_checksum: Option<policy::PayloadChecksum>,
+  writer_quarantine: Option<policy::WriterQuarantine>,
+  forward_error_correction: Option<policy::ForwardErrorCorrection>,
+  repair_scheduling: Option<policy::RepairScheduling>,
+  writer_initialization: Option<policy::WriterInitialization>,
+  batching: Option<policy::Batching>,
+  acknowledgment: Option<policy::Acknowledgment>,
+  flow_control: Option<policy::FlowControl>,
   #[cfg(feature = "security")]
   property: Option<policy::Property>,
 }
@@ -130,6 +145,12 @@ impl QosPolicyBuilder {
     self
   }
 
+  #[must_use]
+  pub fn partition(mut self, partition: policy::Partition) -> Self {
+    self.partition = Some(partition);
+    self
+  }
+
   #[must_use]
   pub const fn reliability(mut self, reliability: policy::Reliability) -> Self {
     self.reliability = Some(reliability);
@@ -172,6 +193,96 @@ impl QosPolicyBuilder {
     self
   }
 
+  #[must_use]
+  pub fn data_representation(mut self, data_representation: policy::DataRepresentation) -> Self {
+    self.data_representation = Some(data_representation);
+    self
+  }
+
+  #[must_use]
+  pub const fn writer_restart(mut self, writer_restart: policy::WriterRestart) -> Self {
+    self.writer_restart = Some(writer_restart);
+    self
+  }
+
+  #[must_use]
+  pub const fn cache_watermarks(mut self, cache_watermarks: policy::CacheWatermarks) -> Self {
+    self.cache_watermarks = Some(cache_watermarks);
+    self
+  }
+
+  #[must_use]
+  pub const fn delivery_order(mut self, delivery_order: policy::DeliveryOrder) -> Self {
+    self.delivery_order = Some(delivery_order);
+    self
+  }
+
+  #[must_use]
+  pub const fn last_value_cache(mut self, last_value_cache: policy::LastValueCache) -> Self {
+    self.last_value_cache = Some(last_value_cache);
+    self
+  }
+
+  #[must_use]
+  pub const fn conflation(mut self, conflation: policy::Conflation) -> Self {
+    self.conflation = Some(conflation);
+    self
+  }
+
+  #[must_use]
+  pub const fn payload_checksum(mut self, payload_checksum: policy::PayloadChecksum) -> Self {
+    self.payload_checksum = Some(payload_checksum);
+    self
+  }
+
+  #[must_use]
+  pub const fn writer_quarantine(mut self, writer_quarantine: policy::WriterQuarantine) -> Self {
+    self.writer_quarantine = Some(writer_quarantine);
+    self
+  }
+
+  #[must_use]
+  pub const fn forward_error_correction(
+    mut self,
+    forward_error_correction: policy::ForwardErrorCorrection,
+  ) -> Self {
+    self.forward_error_correction = Some(forward_error_correction);
+    self
+  }
+
+  #[must_use]
+  pub const fn repair_scheduling(mut self, repair_scheduling: policy::RepairScheduling) -> Self {
+    self.repair_scheduling = Some(repair_scheduling);
+    self
+  }
+
+  #[must_use]
+  pub const fn writer_initialization(
+    mut self,
+    writer_initialization: policy::WriterInitialization,
+  ) -> Self {
+    self.writer_initialization = Some(writer_initialization);
+    self
+  }
+
+  #[must_use]
+  pub const fn batching(mut self, batching: policy::Batching) -> Self {
+    self.batching = Some(batching);
+    self
+  }
+
+  #[must_use]
+  pub const fn acknowledgment(mut self, acknowledgment: policy::Acknowledgment) -> Self {
+    self.acknowledgment = Some(acknowledgment);
+    self
+  }
+
+  #[must_use]
+  pub const fn flow_control(mut self, flow_control: policy::FlowControl) -> Self {
+    self.flow_control = Some(flow_control);
+    self
+  }
+
   #[cfg(feature = "security")]
   #[must_use]
   pub fn property(mut self, property: policy::Property) -> Self {
@@ -188,11 +299,26 @@ impl QosPolicyBuilder {
       ownership: self.ownership,
       liveliness: self.liveliness,
       time_based_filter: self.time_based_filter,
+      partition: self.partition,
       reliability: self.reliability,
       destination_order: self.destination_order,
       history: self.history,
       resource_limits: self.resource_limits,
       lifespan: self.lifespan,
+      data_representation: self.data_representation,
+      writer_restart: self.writer_restart,
+      cache_watermarks: self.cache_watermarks,
+      delivery_order: self.delivery_order,
+      last_value_cache: self.last_value_cache,
+      conflation: self.conflation,
+      payload_checksum: self.payload_checksum,
+      writer_quarantine: self.writer_quarantine,
+      forward_error_correction: self.forward_error_correction,
+      repair_scheduling: self.repair_scheduling,
+      writer_initialization: self.writer_initialization,
+      batching: self.batching,
+      acknowledgment: self.acknowledgment,
+      flow_control: self.flow_control,
       #[cfg(feature = "security")]
       property: self.property,
     }
@@ -212,11 +338,27 @@ pub struct QosPolicies {
   pub(crate) ownership: Option<policy::Ownership>,
   pub(crate) liveliness: Option<policy::Liveliness>,
   pub(crate) time_based_filter: Option<policy::TimeBasedFilter>,
+  pub(crate) partition: Option<policy::Partition>,
   pub(crate) reliability: Option<policy::Reliability>,
   pub(crate) destination_order: Option<policy::DestinationOrder>,
   pub(crate) history: Option<policy::History>,
   pub(crate) resource_limits: Option<policy::ResourceLimits>,
   pub(crate) lifespan: Option<policy::Lifespan>,
+  pub(crate) data_representation: Option<policy::DataRepresentation>,
+  // RustDDS extension, local only. Not sent in Discovery.
+  pub(crate) writer_restart: Option<policy::WriterRestart>,
+  pub(crate) cache_watermarks: Option<policy::CacheWatermarks>,
+  pub(crate) delivery_order: Option<policy::DeliveryOrder>,
+  pub(crate) last_value_cache: Option<policy::LastValueCache>,
+  pub(crate) conflation: Option<policy::Conflation>,
+  pub(crate) payload_checksum: Option<policy::PayloadChecksum>,
+  pub(crate) writer_quarantine: Option<policy::WriterQuarantine>,
+  pub(crate) forward_error_correction: Option<policy::ForwardErrorCorrection>,
+  pub(crate) repair_scheduling: Option<policy::RepairScheduling>,
+  pub(crate) writer_initialization: Option<policy::WriterInitialization>,
+  pub(crate) batching: Option<policy::Batching>,
+  pub(crate) acknowledgment: Option<policy::Acknowledgment>,
+  pub(crate) flow_control: Option<policy::FlowControl>,
   #[cfg(feature = "security")]
   pub(crate) property: Option<policy::Property>,
 }
@@ -264,6 +406,10 @@ impl QosPolicies {
     self.time_based_filter
   }
 
+  pub fn partition(&self) -> Option<policy::Partition> {
+    self.partition.clone()
+  }
+
   pub const fn reliability(&self) -> Option<policy::Reliability> {
     self.reliability
   }
@@ -296,6 +442,62 @@ impl QosPolicies {
     self.lifespan
   }
 
+  pub fn data_representation(&self) -> Option<policy::DataRepresentation> {
+    self.data_representation.clone()
+  }
+
+  pub const fn writer_restart(&self) -> Option<policy::WriterRestart> {
+    self.writer_restart
+  }
+
+  pub const fn cache_watermarks(&self) -> Option<policy::CacheWatermarks> {
+    self.cache_watermarks
+  }
+
+  pub const fn delivery_order(&self) -> Option<policy::DeliveryOrder> {
+    self.delivery_order
+  }
+
+  pub const fn last_value_cache(&self) -> Option<policy::LastValueCache> {
+    self.last_value_cache
+  }
+
+  pub const fn conflation(&self) -> Option<policy::Conflation> {
+    self.conflation
+  }
+
+  pub const fn payload_checksum(&self) -> Option<policy::PayloadChecksum> {
+    self.payload_checksum
+  }
+
+  pub const fn writer_quarantine(&self) -> Option<policy::WriterQuarantine> {
+    self.writer_quarantine
+  }
+
+  pub const fn forward_error_correction(&self) -> Option<policy::ForwardErrorCorrection> {
+    self.forward_error_correction
+  }
+
+  pub const fn repair_scheduling(&self) -> Option<policy::RepairScheduling> {
+    self.repair_scheduling
+  }
+
+  pub const fn writer_initialization(&self) -> Option<policy::WriterInitialization> {
+    self.writer_initialization
+  }
+
+  pub const fn batching(&self) -> Option<policy::Batching> {
+    self.batching
+  }
+
+  pub const fn acknowledgment(&self) -> Option<policy::Acknowledgment> {
+    self.acknowledgment
+  }
+
+  pub const fn flow_control(&self) -> Option<policy::FlowControl> {
+    self.flow_control
+  }
+
   #[cfg(feature = "security")]
   pub fn property(&self) -> Option<policy::Property> {
     self.property.clone()
@@ -315,11 +517,31 @@ impl QosPolicies {
       ownership: other.ownership.or(self.ownership),
       liveliness: other.liveliness.or(self.liveliness),
       time_based_filter: other.time_based_filter.or(self.time_based_filter),
+      partition: other.partition.clone().or(self.partition.clone()),
       reliability: other.reliability.or(self.reliability),
       destination_order: other.destination_order.or(self.destination_order),
       history: other.history.or(self.history),
       resource_limits: other.resource_limits.or(self.resource_limits),
       lifespan: other.lifespan.or(self.lifespan),
+      data_representation: other
+        .data_representation
+        .clone()
+        .or(self.data_representation.clone()),
+      writer_restart: other.writer_restart.or(self.writer_restart),
+      cache_watermarks: other.cache_watermarks.or(self.cache_watermarks),
+      delivery_order: other.delivery_order.or(self.delivery_order),
+      last_value_cache: other.last_value_cache.or(self.last_value_cache),
+      conflation: other.conflation.or(self.conflation),
+      payload_checksum: other.payload_checksum.or(self.payload_checksum),
+      writer_quarantine: other.writer_quarantine.or(self.writer_quarantine),
+      forward_error_correction: other
+        .forward_error_correction
+        .or(self.forward_error_correction),
+      repair_scheduling: other.repair_scheduling.or(self.repair_scheduling),
+      writer_initialization: other.writer_initialization.or(self.writer_initialization),
+      batching: other.batching.or(self.batching),
+      acknowledgment: other.acknowledgment.or(self.acknowledgment),
+      flow_control: other.flow_control.or(self.flow_control),
       #[cfg(feature = "security")]
       property: other.property.clone().or(self.property.clone()),
     }
@@ -389,8 +611,9 @@ impl QosPolicies {
 
     // check Ownership:
     // offered kind == requested kind
+    // Ownership strength is not part of the compatibility check.
     if let (Some(off), Some(req)) = (self.ownership, other.ownership) {
-      if off != req {
+      if off.kind_num() != req.kind_num() {
         return Some(QosPolicyId::Ownership);
       }
     }
@@ -419,16 +642,65 @@ impl QosPolicies {
     // check Destination Order
     // offered kind >= requested kind
     // kind ranking: BY_RECEPTION_TIMESTAMP < BY_SOURCE_TIMESTAMP
-    if let (Some(off), Some(req)) = (self.destination_order, other.destination_order) {
+    // A writer that does not state the policy offers the default,
+    // BY_RECEPTION_TIMESTAMP.
+    if let Some(req) = other.destination_order {
+      let off = self
+        .destination_order
+        .unwrap_or(policy::DestinationOrder::ByReceptionTimestamp);
       if off < req {
         return Some(QosPolicyId::DestinationOrder);
       }
     }
 
+    // check Data Representation (DDS-XTypes v1.3)
+    // The representation offered by the writer must be one of those requested
+    // by the reader.
+    if let (Some(off), Some(req)) = (&self.data_representation, &other.data_representation) {
+      if !req.accepts(off.preferred()) {
+        return Some(QosPolicyId::DataRepresentation);
+      }
+    }
+
     // default value. no incompatibility detected.
     None
   }
 
+  /// Check if a compatible writer (`self`) and reader (`other`) still differ
+  /// in their Data Representation, i.e. the writer does not use the
+  /// representation that the reader prefers.
+  ///
+  /// This is not an incompatibility, but typical of a rolling upgrade, where
+  /// only some participants state the policy. A side that does not state it
+  /// is taken to use the default, XCDR.
+  pub fn data_representation_differs(&self, other: &Self) -> bool {
+    let offered = self
+      .data_representation
+      .as_ref()
+      .map_or(policy::DataRepresentationId::Xcdr, |d| d.preferred());
+    let preferred = other
+      .data_representation
+      .as_ref()
+      .map_or(policy::DataRepresentationId::Xcdr, |d| d.preferred());
+    offered != preferred
+  }
+
+  /// Check if Partitions of a writer (`self`) and a reader (`other`) have
+  /// a partition in common.
+  ///
+  /// Partition mismatch is not a QoS incompatibility, so this is not part of
+  /// [`Self::compliance_failure_wrt`]. Mismatching Partitions simply prevent
+  /// matching without any incompatibility notification. See DDS spec v1.4
+  /// Section "2.2.3.13 PARTITION".
+  pub fn partitions_match(&self, other: &Self) -> bool {
+    let default_partition = policy::Partition::default();
+    self
+      .partition
+      .as_ref()
+      .unwrap_or(&default_partition)
+      .matches(other.partition.as_ref().unwrap_or(&default_partition))
+  }
+
   // serialization
   pub fn to_parameter_list(
     &self,
@@ -445,11 +717,26 @@ impl QosPolicies {
       ownership,
       liveliness,
       time_based_filter,
+      partition,
       reliability,
       destination_order,
       history,
       resource_limits,
       lifespan,
+      data_representation,
+      writer_restart: _,           // local setting, not sent
+      cache_watermarks: _,         // local setting, not sent
+      delivery_order: _,           // local setting, not sent
+      last_value_cache: _,         // local setting, not sent
+      conflation: _,               // local setting, not sent
+      payload_checksum: _,         // local setting, not sent
+      writer_quarantine: _,        // local setting, not sent
+      forward_error_correction: _, // local setting, not sent
+      repair_scheduling: _,        // local setting, not sent
+      writer_initialization: _,    // local setting, not sent
+      batching: _,                 // local setting, not sent
+      acknowledgment: _,           // local setting, not sent
+      flow_control: _,             // local setting, not sent
       #[cfg(feature = "security")]
         property: _, // TODO: properties to parameter list?
     } = self;
@@ -495,6 +782,7 @@ impl QosPolicies {
       time_based_filter,
       policy::TimeBasedFilter
     );
+    emit_option!(PID_PARTITION, partition, policy::Partition);
 
     if let Some(rel) = reliability.as_ref() {
       let reliability_ser = match rel {
@@ -531,6 +819,11 @@ impl QosPolicies {
     }
     emit_option!(PID_RESOURCE_LIMITS, resource_limits, policy::ResourceLimits);
     emit_option!(PID_LIFESPAN, lifespan, policy::Lifespan);
+    emit_option!(
+      PID_DATA_REPRESENTATION,
+      data_representation,
+      policy::DataRepresentation
+    );
 
     Ok(pl)
   }
@@ -592,9 +885,12 @@ impl QosPolicies {
 
     let liveliness: Option<policy::Liveliness> = get_option!(PID_LIVELINESS);
     let time_based_filter: Option<policy::TimeBasedFilter> = get_option!(PID_TIME_BASED_FILTER);
+    let partition: Option<policy::Partition> = get_option!(PID_PARTITION);
 
     let resource_limits: Option<policy::ResourceLimits> = get_option!(PID_RESOURCE_LIMITS);
     let lifespan: Option<policy::Lifespan> = get_option!(PID_LIFESPAN);
+    let data_representation: Option<policy::DataRepresentation> =
+      get_option!(PID_DATA_REPRESENTATION);
 
     #[cfg(feature = "security")]
     let property: Option<policy::Property> = None; // TODO: Should also properties be read?
@@ -609,11 +905,26 @@ impl QosPolicies {
       ownership,
       liveliness,
       time_based_filter,
+      partition,
       reliability,
       destination_order,
       history,
       resource_limits,
       lifespan,
+      data_representation,
+      writer_restart: None,
+      cache_watermarks: None,
+      delivery_order: None,
+      last_value_cache: None,
+      conflation: None,
+      payload_checksum: None,
+      writer_quarantine: None,
+      forward_error_correction: None,
+      repair_scheduling: None,
+      writer_initialization: None,
+      batching: None,
+      acknowledgment: None,
+      flow_control: None,
       #[cfg(feature = "security")]
       property,
     })
@@ -655,19 +966,24 @@ pub const LENGTH_UNLIMITED: i32 = -1;
 // put these into a submodule to avoid repeating the word "policy" or
 // "qospolicy"
 /// Contains all available QoSPolicies
+///
+/// The policies documented as RustDDS extensions are not part of the DDS
+/// specification. They are local to the DataWriter or DataReader that has
+/// them: they are not sent in Discovery and do not affect QoS compatibility.
 pub mod policy {
   use std::cmp::Ordering;
 
-  use speedy::{Readable, Writable};
+  use speedy::{Context, Readable, Reader, Writable, Writer};
   use serde::{Deserialize, Serialize};
   #[allow(unused_imports)]
   use log::{debug, error, info, trace, warn};
   #[cfg(feature = "security")]
-  use speedy::{Context, IsEof, Reader, Writer};
+  use speedy::IsEof;
 
-  use crate::structure::duration::Duration;
-  #[cfg(feature = "security")]
-  use crate::serialization::speedy_pl_cdr_helpers::*;
+  use crate::{
+    serialization::{speedy_pl_cdr_helpers::*, RepresentationIdentifier},
+    structure::duration::Duration,
+  };
 
   /*
   pub struct UserData {
@@ -693,6 +1009,143 @@ pub mod policy {
     pub duration: Duration,
   }
 
+  /// DDS-XTypes v1.3 DATA_REPRESENTATION
+  ///
+  /// Pins the serialization format of a topic, independent of what the
+  /// serializer adapters of a RustDDS version default to. This lets a fleet
+  /// upgrade RustDDS one node at a time, and switch the format only when all
+  /// nodes are ready.
+  ///
+  /// A DataWriter uses the representation listed first. Creating it fails, if
+  /// its serializer adapter does not write that representation. A DataReader
+  /// accepts all the listed representations. Creating it fails, if its
+  /// deserializer adapter can read none of them. An empty list means XCDR.
+  ///
+  /// The representation includes the encodings of extensible types: with XCDR
+  /// mutable types are written as PL_CDR, and with XCDR2 appendable and
+  /// mutable types are written as D_CDR2 and PL_CDR2.
+  ///
+  /// A writer and a reader that both state the policy match only if the
+  /// reader accepts the representation of the writer. If they match, but the
+  /// writer does not use the representation that the reader prefers, e.g.
+  /// because only one of them states the policy, a warning is logged.
+  ///
+  /// ```
+  /// use rustdds::{
+  ///   policy::{DataRepresentation, DataRepresentationId},
+  ///   QosPolicyBuilder,
+  /// };
+  ///
+  /// // Keep writing XCDR until all readers understand XCDR2.
+  /// let writer_qos = QosPolicyBuilder::new()
+  ///   .data_representation(DataRepresentation::new(&[DataRepresentationId::Xcdr]))
+  ///   .build();
+  /// // Upgraded readers accept both.
+  /// let reader_qos = QosPolicyBuilder::new()
+  ///   .data_representation(DataRepresentation::new(&[
+  ///     DataRepresentationId::Xcdr2,
+  ///     DataRepresentationId::Xcdr,
+  ///   ]))
+  ///   .build();
+  /// assert_eq!(writer_qos.compliance_failure_wrt(&reader_qos), None);
+  /// ```
+  #[derive(Clone, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+  pub struct DataRepresentation {
+    pub value: Vec<DataRepresentationId>,
+  }
+
+  impl DataRepresentation {
+    pub fn new(value: &[DataRepresentationId]) -> Self {
+      Self {
+        value: value.to_vec(),
+      }
+    }
+
+    /// The representation listed first: the one that a DataWriter uses, and
+    /// that a DataReader prefers.
+    pub fn preferred(&self) -> DataRepresentationId {
+      self
+        .value
+        .first()
+        .copied()
+        .unwrap_or(DataRepresentationId::Xcdr)
+    }
+
+    /// Check if a DataReader with this policy accepts the representation.
+    pub fn accepts(&self, representation: DataRepresentationId) -> bool {
+      if self.value.is_empty() {
+        representation == DataRepresentationId::Xcdr
+      } else {
+        self.value.contains(&representation)
+      }
+    }
+  }
+
+  // Serialized as sequence<short>.
+  impl<'a, C: Context> Readable<'a, C> for DataRepresentation {
+    fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
+      let count = reader.read_u32()?;
+      // Do not trust count for preallocation, it comes from the network.
+      let mut value = Vec::new();
+      for _ in 0..count {
+        let id = reader.read_i16()?;
+        // Representations that we do not know cannot be used with us anyway.
+        match DataRepresentationId::from_i16(id) {
+          Some(id) => value.push(id),
+          None => debug!("Ignoring unknown data representation id {id}"),
+        }
+      }
+      Ok(DataRepresentation { value })
+    }
+  }
+
+  impl<C: Context> Writable<C> for DataRepresentation {
+    fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
+      writer.write_u32(self.value.len() as u32)?;
+      for id in &self.value {
+        writer.write_i16(*id as i16)?;
+      }
+      Ok(())
+    }
+  }
+
+  /// Representation in [`DataRepresentation`]. Values are from the IDL of
+  /// DDS-XTypes v1.3.
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+  pub enum DataRepresentationId {
+    /// XCDR version 1, i.e. CDR and PL_CDR
+    Xcdr = 0,
+    /// XML. RustDDS does not have serializer adapters for it.
+    Xml = 1,
+    /// XCDR version 2
+    Xcdr2 = 2,
+  }
+
+  impl DataRepresentationId {
+    fn from_i16(id: i16) -> Option<Self> {
+      match id {
+        0 => Some(Self::Xcdr),
+        1 => Some(Self::Xml),
+        2 => Some(Self::Xcdr2),
+        _ => None,
+      }
+    }
+
+    /// The representation that serialized data with the given
+    /// `RepresentationIdentifier` belongs to.
+    pub fn of(representation_identifier: RepresentationIdentifier) -> Option<Self> {
+      if representation_identifier.cdr_endianness().is_some() {
+        Some(Self::Xcdr)
+      } else if representation_identifier.xcdr2_encoding().is_some() {
+        Some(Self::Xcdr2)
+      } else if representation_identifier == RepresentationIdentifier::XML {
+        Some(Self::Xml)
+      } else {
+        None
+      }
+    }
+  }
+
   /// DDS 2.2.3.4 DURABILITY
   ///
   /// DDS Spec 1.4:
@@ -771,6 +1224,9 @@ pub mod policy {
   pub struct Deadline(pub Duration);
 
   /// DDS 2.2.3.8 LATENCY_BUDGET
+  ///
+  /// A DataWriter may hold written samples for up to the budget, and sends
+  /// the samples written within it together.
   #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Readable, Writable, Serialize, Deserialize)]
   pub struct LatencyBudget {
     pub duration: Duration,
@@ -783,6 +1239,15 @@ pub mod policy {
     Exclusive { strength: i32 }, // This also implements OwnershipStrength
   }
 
+  impl Ownership {
+    pub(crate) fn kind_num(&self) -> i32 {
+      match self {
+        Self::Shared => 0,
+        Self::Exclusive { .. } => 1,
+      }
+    }
+  }
+
   /// DDS 2.2.3.11 LIVELINESS
   #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Readable, Writable, Serialize, Deserialize)]
   pub enum Liveliness {
@@ -832,11 +1297,118 @@ pub mod policy {
     pub minimum_separation: Duration,
   }
 
-  /*
+  /// DDS 2.2.3.13 PARTITION
+  ///
+  /// A Partition is a list of partition names. A name may contain wildcards
+  /// `*` (any sequence of characters) and `?` (any single character).
+  ///
+  /// An empty list means the default partition, which is the same as a list
+  /// containing only the empty string.
+  #[derive(Clone, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
   pub struct Partition {
-    pub name: Vec<Vec<u8>>,
+    pub name: Vec<String>,
+  }
+
+  impl Partition {
+    pub fn new(names: &[&str]) -> Self {
+      Self {
+        name: names.iter().map(|n| (*n).to_string()).collect(),
+      }
+    }
+
+    // Empty list of names is the same as the default partition "".
+    pub(crate) fn names(&self) -> Vec<&str> {
+      if self.name.is_empty() {
+        vec![""]
+      } else {
+        self.name.iter().map(String::as_str).collect()
+      }
+    }
+
+    /// Check if two Partitions have at least one partition name in common.
+    ///
+    /// Wildcards are allowed on either side, but two names that both contain
+    /// wildcards are never considered matching.
+    pub fn matches(&self, other: &Self) -> bool {
+      let other_names = other.names();
+      self.names().iter().any(|my_name| {
+        other_names
+          .iter()
+          .any(|other_name| Self::name_matches(my_name, other_name))
+      })
+    }
+
+    fn name_matches(a: &str, b: &str) -> bool {
+      match (Self::has_wildcards(a), Self::has_wildcards(b)) {
+        (false, false) => a == b,
+        (true, false) => Self::wildcard_match(a.as_bytes(), b.as_bytes()),
+        (false, true) => Self::wildcard_match(b.as_bytes(), a.as_bytes()),
+        (true, true) => false,
+      }
+    }
+
+    fn has_wildcards(name: &str) -> bool {
+      name.contains(['*', '?'])
+    }
+
+    // Simple backtracking matcher for "*" and "?"
+    fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+      let (mut p, mut n) = (0, 0);
+      let mut star: Option<(usize, usize)> = None; // (pattern pos, name pos)
+
+      while n < name.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == name[n]) {
+          p += 1;
+          n += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+          star = Some((p, n));
+          p += 1;
+        } else if let Some((star_p, star_n)) = star {
+          // backtrack: let the last star consume one more character
+          p = star_p + 1;
+          n = star_n + 1;
+          star = Some((star_p, star_n + 1));
+        } else {
+          return false;
+        }
+      }
+      pattern[p..].iter().all(|c| *c == b'*')
+    }
+  }
+
+  // Partition is serialized as sequence<string>.
+  // Alignment comes before each string length.
+  impl<'a, C: Context> Readable<'a, C> for Partition {
+    fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
+      let count = reader.read_u32()?;
+      // Do not trust count for preallocation, it comes from the network.
+      let mut name = Vec::new();
+
+      let mut prev_len = 0;
+      for _ in 0..count {
+        read_pad(reader, prev_len, 4)?;
+        let s: StringWithNul = reader.read_value()?;
+        prev_len = s.len() + 4; // string length field + characters + NUL
+        name.push(s.into());
+      }
+      Ok(Partition { name })
+    }
+  }
+
+  impl<C: Context> Writable<C> for Partition {
+    fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
+      writer.write_u32(self.name.len() as u32)?;
+
+      let mut prev_len = 0;
+      for n in &self.name {
+        write_pad(writer, prev_len, 4)?;
+        let s = StringWithNul::from(n);
+        writer.write_value(&s)?;
+        prev_len = s.len() + 4;
+      }
+      Ok(())
+    }
   }
-  */
 
   /// DDS 2.2.3.14 RELIABILITY
   #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -869,6 +1441,10 @@ pub mod policy {
   }
 
   /// DDS 2.2.3.17 DESTINATION_ORDER
+  ///
+  /// With `BySourceTimeStamp`, a DataReader orders writes to an instance by
+  /// their source timestamp, and ties by writer GUID. A sample older than the
+  /// newest one already received for the same instance is dropped.
   #[derive(
     Copy,
     Clone,
@@ -915,6 +1491,320 @@ pub mod policy {
     pub max_samples_per_instance: i32,
   }
 
+  /// RustDDS extension: What a DataReader should do, when a matched
+  /// DataWriter restarts its sequence numbering.
+  ///
+  /// This happens when the process hosting the DataWriter is restarted, and
+  /// the new DataWriter has the same GUID as the old one. The restart is
+  /// detected, when the Writer announces sequence numbers lower than what
+  /// has already been received from it.
+  ///
+  /// In either case the DataReader reports
+  /// [`DataReaderStatus::WriterRestarted`](crate::DataReaderStatus::WriterRestarted).
+  #[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+  )]
+  pub enum WriterRestart {
+    /// Forget the reliability state of the old Writer and receive the
+    /// restarted one as if it were a new Writer. Samples of the old Writer
+    /// that are not yet taken may be lost.
+    #[default]
+    TreatAsNewWriter,
+    /// Ignore all further messages from the Writer, until it is rediscovered.
+    Reject,
+  }
+
+  /// RustDDS extension: Background eviction of samples received for a
+  /// DataReader.
+  ///
+  /// When the number of received samples of the Topic reaches `high`,
+  /// samples are evicted until `low` remains. Samples already read by all
+  /// local DataReaders are evicted first, oldest first. If that is not enough,
+  /// the oldest unread samples are evicted, too. These are lost to the
+  /// application.
+  ///
+  /// Eviction is done periodically by the DomainParticipant event loop, and
+  /// its statistics are available from
+  /// [`DataReader::cache_statistics`](crate::with_key::DataReader::cache_statistics).
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+  pub struct CacheWatermarks {
+    pub high: i32,
+    pub low: i32,
+  }
+
+  /// RustDDS extension: Order in which a Reliable DataReader delivers the
+  /// samples of each Writer to the application.
+  ///
+  /// With `AsReceived`, a sample is available for reading as soon as it is
+  /// received, even if samples with smaller sequence numbers are still
+  /// missing. The missing samples are repaired as usual, and delivered when
+  /// they arrive. This avoids head-of-line blocking, which is useful e.g. for
+  /// video or telemetry, where the newest data matters most. Each sample is
+  /// still delivered only once.
+  ///
+  /// Best-effort DataReaders always deliver samples as received.
+  #[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+  )]
+  pub enum DeliveryOrder {
+    /// Deliver the samples of each Writer in sequence number order.
+    #[default]
+    InOrder,
+    /// Deliver samples as soon as they are received.
+    AsReceived,
+  }
+
+  /// RustDDS extension: Whether a DataWriter keeps the latest sample of each
+  /// instance for Readers that match later.
+  ///
+  /// With `Enabled`, the latest sample of each instance is kept regardless of
+  /// the History and Durability policies, and sent to each newly matched
+  /// Reader right after matching. This way late joiners get the current
+  /// state of all instances even from a `Volatile` DataWriter, without
+  /// keeping the whole history. Disposing an instance removes it from the
+  /// cache. Samples written to a single Reader only are not cached.
+  #[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+  )]
+  pub enum LastValueCache {
+    #[default]
+    Disabled,
+    Enabled,
+  }
+
+  /// RustDDS extension: Whether a best-effort DataWriter may skip samples
+  /// that have been superseded before they were sent.
+  ///
+  /// With `LatestPerInstance`, if several samples of the same instance are
+  /// waiting to be sent at once, i.e. the application writes faster than the
+  /// samples can be transmitted, only the newest of them is sent. The skipped
+  /// samples still get sequence numbers and are kept in the writer history.
+  /// The number of skipped samples can be read with
+  /// [`DataWriter::conflated_sample_count`](crate::with_key::DataWriter::conflated_sample_count).
+  ///
+  /// This is useful for data where only the current value matters, such as
+  /// market prices or sensor readings, on links slower than the write rate.
+  /// The policy has no effect on `Reliable` DataWriters, which must deliver
+  /// every sample.
+  #[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+  )]
+  pub enum Conflation {
+    /// Send every sample.
+    #[default]
+    Disabled,
+    /// Send only the newest pending sample of each instance.
+    LatestPerInstance,
+  }
+
+  /// RustDDS extension: Whether a DataWriter sends a checksum of each sample
+  /// for end-to-end integrity checking.
+  ///
+  /// With `Md5`, an MD5 digest of the serialized sample is sent in inline QoS
+  /// with the DATA or each DATAFRAG. A RustDDS DataReader verifies it after
+  /// the sample has been reassembled and decrypted, and rejects a sample that
+  /// does not match with [`ByPayloadChecksum`]. A `Reliable` DataReader then
+  /// requests the sample again. This catches corruption introduced e.g. by
+  /// bridges or custom transports, which the UDP checksum does not cover. It
+  /// is not protection against tampering, for which DDS Security should be
+  /// used.
+  ///
+  /// Other DDS implementations ignore the checksum.
+  ///
+  /// [`ByPayloadChecksum`]: crate::dds::statusevents::SampleRejectedStatusKind::ByPayloadChecksum
+  #[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+  )]
+  pub enum PayloadChecksum {
+    /// Send no checksum.
+    #[default]
+    Disabled,
+    /// Send an MD5 digest of the serialized payload.
+    Md5,
+  }
+
+  /// RustDDS extension: Quarantine of matched DataWriters that violate the
+  /// RTPS protocol.
+  ///
+  /// A DataReader always ignores invalid submessages from a Writer, such as a
+  /// HEARTBEAT that announces a negative or impossibly large range of
+  /// sequence numbers, or moves its first available sequence number
+  /// backwards. With this policy, a Writer that has sent `max_violations`
+  /// such submessages within `duration` is also quarantined for `duration`:
+  /// everything from it is ignored, so that a buggy or malicious Writer cannot
+  /// keep disturbing the reliability state of the DataReader. After the
+  /// quarantine the Writer is served again.
+  ///
+  /// Each quarantine is reported with
+  /// [`DataReaderStatus::WriterQuarantined`](crate::DataReaderStatus::WriterQuarantined).
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+  pub struct WriterQuarantine {
+    pub max_violations: i32,
+    pub duration: Duration,
+  }
+
+  /// RustDDS extension: Forward error correction for best-effort DataWriters.
+  ///
+  /// With `Xor`, the DataWriter sends a parity datagram after every
+  /// `group_size` samples it has sent to all matched Readers. The parity is
+  /// the XOR of the serialized samples of the group. A RustDDS DataReader that
+  /// has lost exactly one sample of a group reconstructs it from the parity
+  /// and the other samples. This reduces loss on links where ACKNACK round
+  /// trips are impossible or too slow, such as unidirectional or satellite
+  /// links, at the cost of one extra datagram per group. Smaller groups
+  /// recover more losses but cost more bandwidth.
+  ///
+  /// Only samples sent as a single DATA are protected. Fragmented samples,
+  /// disposals and samples sent to some Readers only are not. The policy has
+  /// no effect on `Reliable` DataWriters, which repair losses by
+  /// retransmission, nor when DDS Security is enabled, since the parity would
+  /// reveal encrypted samples.
+  ///
+  /// The parity is sent in a vendor-specific submessage, which other DDS
+  /// implementations ignore.
+  #[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+  )]
+  pub enum ForwardErrorCorrection {
+    /// Send no parity.
+    #[default]
+    Disabled,
+    /// Send the XOR parity of every `group_size` samples. Recovers one lost
+    /// sample per group.
+    Xor { group_size: u16 },
+  }
+
+  /// RustDDS extension: How a `Reliable` DataWriter orders retransmissions
+  /// (repairs) requested by Readers and new samples, when both are pending.
+  ///
+  /// Control topics often prefer fresh data, so that a lossy Reader does not
+  /// delay the newest command behind old ones. Topics such as logs prefer
+  /// completeness, so that Readers catch up before receiving more.
+  ///
+  /// Regardless of this policy, pending repairs are also sent on the repair
+  /// timer, one sample at a time per Reader.
+  #[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+  )]
+  pub enum RepairScheduling {
+    /// New samples are sent as soon as they are written. Repairs follow on
+    /// the repair timer.
+    #[default]
+    NewestFirst,
+    /// All pending repairs are sent before a new sample.
+    RepairFirst,
+    /// Up to `repairs_per_sample` pending repairs per Reader are sent before
+    /// each new sample.
+    Weighted { repairs_per_sample: u16 },
+  }
+
+  /// RustDDS extension: When a DataWriter sets up the resources that are
+  /// needed only for sending to DataReaders.
+  ///
+  /// With `OnFirstMatch`, the DataWriter starts its periodic HEARTBEAT and
+  /// history cleaning timers only when the first DataReader matches. Until
+  /// then, a `Volatile` DataWriter also does not keep the samples written, as
+  /// no DataReader could receive them: they only consume sequence numbers.
+  /// Samples of a DataWriter with a stronger Durability, or with the
+  /// LastValueCache policy, are kept as usual for late-joining DataReaders.
+  /// Once set up, the resources are kept, also when DataReaders leave. With
+  /// DDS Security, the receiver-specific keys of each DataReader are
+  /// registered when it matches in any case.
+  ///
+  /// Regardless of this policy, a DataWriter reports
+  /// [`DataWriterStatus::ReaderPresenceChanged`] when it gets its first
+  /// matched DataReader and when it loses its last one, so that an
+  /// application can pause producing data that no one reads.
+  ///
+  /// [`DataWriterStatus::ReaderPresenceChanged`]: crate::DataWriterStatus::ReaderPresenceChanged
+  #[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+  )]
+  pub enum WriterInitialization {
+    /// Set up everything when the DataWriter is created.
+    #[default]
+    Eager,
+    /// Set up sending when the first DataReader matches.
+    OnFirstMatch,
+  }
+
+  /// RustDDS extension: Whether a DataWriter packs samples written in a row
+  /// into the same RTPS messages.
+  ///
+  /// Normally each sample is sent in its own UDP datagram, which costs a
+  /// system call and message headers per sample. With batching, the samples
+  /// that are waiting to be sent are sent together, and their DATA
+  /// submessages, or DATA_FRAG submessages of large samples, going to the
+  /// same destination are packed into as few RTPS messages of at most
+  /// `max_bytes` as possible. The samples are sent, when their payloads add
+  /// up to `max_bytes`, or when the oldest of them has waited for
+  /// `max_delay`. This replaces the delay given by the LATENCY_BUDGET policy.
+  ///
+  /// This is useful on topics with high rates of small samples, where it
+  /// cuts the overhead considerably at the cost of some latency. Messages are
+  /// packed at most to the size of a UDP datagram, and messages protected as
+  /// a whole by DDS Security are not packed.
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+  pub struct Batching {
+    /// Largest RTPS message that samples are packed into, in bytes
+    pub max_bytes: u32,
+    /// Longest time that a sample waits for others to be sent with
+    pub max_delay: Duration,
+  }
+
+  /// RustDDS extension: Whether DataReaders confirm the samples they have
+  /// processed to the DataWriter.
+  ///
+  /// The acknowledgments of the RELIABILITY policy only tell that a
+  /// DataReader has received a sample. With `ApplicationExplicit`, the
+  /// application of the DataReader also acknowledges each sample with
+  /// [`DataReader::acknowledge`](crate::with_key::DataReader::acknowledge)
+  /// once it has processed it, and the DataReader tells the DataWriter. The
+  /// DataWriter lists the samples that each DataReader has not acknowledged
+  /// with
+  /// [`DataWriter::unacknowledged_samples`](crate::with_key::DataWriter::unacknowledged_samples),
+  /// and can deliver them again, to the same or to another DataReader, with
+  /// [`DataWriter::redeliver_unacknowledged`](crate::with_key::DataWriter::redeliver_unacknowledged).
+  /// This is how work queues are built on DDS: a sample that a worker did not
+  /// finish is handed to another one.
+  ///
+  /// Both the DataWriter and its DataReaders need this policy, and the
+  /// RELIABILITY policy should be Reliable, so that lost acknowledgments are
+  /// sent again. Only RustDDS DataReaders send the acknowledgments. The
+  /// DataWriter tracks the samples written while a DataReader is matched, not
+  /// the historical ones that a late-joining DataReader gets.
+  #[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+  )]
+  pub enum Acknowledgment {
+    /// Samples are acknowledged by the RELIABILITY protocol only.
+    #[default]
+    Protocol,
+    /// The application also acknowledges the samples it has processed.
+    ApplicationExplicit,
+  }
+
+  /// RustDDS extension: How fast a DataWriter sends, and whether its messages
+  /// go before those of other DataWriters.
+  ///
+  /// The messages of the DataWriter are scheduled by the flow controller of
+  /// the participant. The DataWriter never sends faster than
+  /// `max_bytes_per_second`, and when the bandwidth of the participant is
+  /// used up, the waiting messages of the DataWriters with the highest
+  /// `priority` are sent first. See [`flow_control`](crate::flow_control).
+  ///
+  /// This is useful when bulk topics share a participant with small, urgent
+  /// ones: a bulk DataWriter with a bandwidth limit and a low priority does
+  /// not hold back the others.
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+  pub struct FlowControl {
+    /// Priority of the DataWriter. Higher values go first.
+    pub priority: i32,
+    /// Largest rate at which the DataWriter sends, in bytes per second. `None`
+    /// is unlimited.
+    pub max_bytes_per_second: Option<u64>,
+  }
+
   #[cfg(feature = "security")]
   use crate::security;
   // DDS Security spec v1.1
@@ -1063,3 +1953,163 @@ pub mod policy {
     }
   }
 } // mod policy
+
+#[cfg(test)]
+mod tests {
+  use speedy::{Endianness, Readable, Writable};
+
+  use super::{policy::*, *};
+
+  #[test]
+  fn partition_matching() {
+    let default = Partition::default();
+    assert!(default.matches(&Partition::new(&[""])));
+    assert!(!default.matches(&Partition::new(&["A"])));
+
+    let ab = Partition::new(&["A", "B"]);
+    assert!(ab.matches(&Partition::new(&["B", "C"])));
+    assert!(!ab.matches(&Partition::new(&["C"])));
+
+    // wildcards
+    assert!(Partition::new(&["Sen*"]).matches(&Partition::new(&["Sensors"])));
+    assert!(Partition::new(&["Sensors"]).matches(&Partition::new(&["S?n*rs"])));
+    assert!(Partition::new(&["*"]).matches(&Partition::new(&["anything"])));
+    assert!(!Partition::new(&["Sen*x"]).matches(&Partition::new(&["Sensors"])));
+    // two wildcard names never match each other
+    assert!(!Partition::new(&["S*"]).matches(&Partition::new(&["S*"])));
+  }
+
+  #[test]
+  fn partitions_match_with_missing_policy() {
+    let none = QosPolicies::qos_none();
+    let with_default = QosPolicyBuilder::new()
+      .partition(Partition::default())
+      .build();
+    let with_a = QosPolicyBuilder::new()
+      .partition(Partition::new(&["A"]))
+      .build();
+
+    assert!(none.partitions_match(&with_default));
+    assert!(!none.partitions_match(&with_a));
+    assert!(with_a.partitions_match(&with_a));
+  }
+
+  #[test]
+  fn partition_ser_deser() {
+    let partition = Partition::new(&["a", "bcd", "", "efghi"]);
+    for endianness in [Endianness::LittleEndian, Endianness::BigEndian] {
+      let bytes = partition.write_to_vec_with_ctx(endianness).unwrap();
+      let partition2 = Partition::read_from_buffer_with_ctx(endianness, &bytes).unwrap();
+      assert_eq!(partition, partition2);
+    }
+
+    // Padding goes before string length
+    let bytes = Partition::new(&["a", "bc"])
+      .write_to_vec_with_ctx(Endianness::LittleEndian)
+      .unwrap();
+    assert_eq!(
+      bytes,
+      vec![2, 0, 0, 0, 2, 0, 0, 0, b'a', 0, 0, 0, 3, 0, 0, 0, b'b', b'c', 0]
+    );
+  }
+
+  #[test]
+  fn destination_order_compatibility() {
+    let by_source = QosPolicyBuilder::new()
+      .destination_order(DestinationOrder::BySourceTimeStamp)
+      .build();
+    let by_reception = QosPolicyBuilder::new()
+      .destination_order(DestinationOrder::ByReceptionTimestamp)
+      .build();
+    let unspecified = QosPolicies::qos_none();
+
+    // offered (self) vs. requested (other)
+    assert_eq!(by_source.compliance_failure_wrt(&by_reception), None);
+    assert_eq!(by_source.compliance_failure_wrt(&by_source), None);
+    assert_eq!(
+      by_reception.compliance_failure_wrt(&by_source),
+      Some(QosPolicyId::DestinationOrder)
+    );
+    assert_eq!(
+      unspecified.compliance_failure_wrt(&by_source),
+      Some(QosPolicyId::DestinationOrder)
+    );
+    assert_eq!(by_reception.compliance_failure_wrt(&unspecified), None);
+  }
+
+  #[test]
+  fn ownership_strength_does_not_affect_compatibility() {
+    let offered = QosPolicyBuilder::new()
+      .ownership(Ownership::Exclusive { strength: 5 })
+      .build();
+    let requested = QosPolicyBuilder::new()
+      .ownership(Ownership::Exclusive { strength: 0 })
+      .build();
+    let shared = QosPolicyBuilder::new().ownership(Ownership::Shared).build();
+
+    assert_eq!(offered.compliance_failure_wrt(&requested), None);
+    assert_eq!(
+      offered.compliance_failure_wrt(&shared),
+      Some(QosPolicyId::Ownership)
+    );
+  }
+
+  #[test]
+  fn data_representation_compatibility() {
+    use DataRepresentationId::{Xcdr, Xcdr2};
+
+    let qos = |ids: &[DataRepresentationId]| {
+      QosPolicyBuilder::new()
+        .data_representation(DataRepresentation::new(ids))
+        .build()
+    };
+    let unspecified = QosPolicies::qos_none();
+
+    // offered (self) vs. requested (other)
+    assert_eq!(
+      qos(&[Xcdr]).compliance_failure_wrt(&qos(&[Xcdr2, Xcdr])),
+      None
+    );
+    assert_eq!(
+      qos(&[Xcdr2]).compliance_failure_wrt(&qos(&[Xcdr])),
+      Some(QosPolicyId::DataRepresentation)
+    );
+    // Empty list means XCDR
+    assert_eq!(
+      qos(&[Xcdr2]).compliance_failure_wrt(&qos(&[])),
+      Some(QosPolicyId::DataRepresentation)
+    );
+    assert_eq!(qos(&[Xcdr2]).compliance_failure_wrt(&unspecified), None);
+
+    // Compatible, but not what the reader prefers
+    assert!(qos(&[Xcdr]).data_representation_differs(&qos(&[Xcdr2, Xcdr])));
+    assert!(qos(&[Xcdr2]).data_representation_differs(&unspecified));
+    assert!(!qos(&[Xcdr]).data_representation_differs(&unspecified));
+    assert!(!unspecified.data_representation_differs(&unspecified));
+  }
+
+  #[test]
+  fn data_representation_ser_deser() {
+    let qos = QosPolicyBuilder::new()
+      .data_representation(DataRepresentation::new(&[
+        DataRepresentationId::Xcdr2,
+        DataRepresentationId::Xcdr,
+      ]))
+      .build();
+    let pl = qos.to_parameter_list(Endianness::LittleEndian).unwrap();
+    assert_eq!(pl.len(), 1);
+    assert_eq!(pl[0].parameter_id, ParameterId::PID_DATA_REPRESENTATION);
+    assert_eq!(pl[0].value, vec![2, 0, 0, 0, 2, 0, 0, 0]);
+
+    let pl_map = BTreeMap::from([(ParameterId::PID_DATA_REPRESENTATION, vec![&pl[0]])]);
+    let qos2 = QosPolicies::from_parameter_list(Endianness::LittleEndian, &pl_map).unwrap();
+    assert_eq!(qos2, qos);
+
+    // Unknown representations are skipped
+    let bytes = [2, 0, 0, 0, 7, 0, 1, 0];
+    assert_eq!(
+      DataRepresentation::read_from_buffer_with_ctx(Endianness::LittleEndian, &bytes).unwrap(),
+      DataRepresentation::new(&[DataRepresentationId::Xml])
+    );
+  }
+}
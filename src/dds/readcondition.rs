@@ -1,6 +1,14 @@
 use enumflags2::BitFlags;
+use serde::Serialize;
 
-use crate::dds::sampleinfo::*;
+use crate::{
+  create_error_bad_parameter,
+  dds::{
+    content_filter::sql::SqlFilter,
+    result::{CreateError, CreateResult},
+    sampleinfo::*,
+  },
+};
 
 /// This is used to specify which samples are to be read or taken from
 /// a [`Datareader`](crate::with_key::DataReader)
@@ -13,11 +21,23 @@ pub struct ReadCondition {
   sample_state_mask: BitFlags<SampleState>,
   view_state_mask: BitFlags<ViewState>,
   instance_state_mask: BitFlags<InstanceState>,
-  // Extension idea: Add a query string and a list of query parameters to upgrade this
-  // to QueryCondition. But that would be a lot of work, especially in DataReader.
 }
 
 impl ReadCondition {
+  /// Condition selects samples whose sample, view and instance states are
+  /// included in the respective masks
+  pub fn new(
+    sample_state_mask: BitFlags<SampleState>,
+    view_state_mask: BitFlags<ViewState>,
+    instance_state_mask: BitFlags<InstanceState>,
+  ) -> Self {
+    Self {
+      sample_state_mask,
+      view_state_mask,
+      instance_state_mask,
+    }
+  }
+
   /// Condition reads all available samples
   pub fn any() -> Self {
     Self {
@@ -48,3 +68,96 @@ impl ReadCondition {
     &self.instance_state_mask
   }
 }
+
+/// A [`ReadCondition`] that also selects on the data of the samples with a
+/// DDS SQL query expression, such as `"value > %0 AND name LIKE %1"`. See
+/// [`ContentFilterFactory::ddssql`](crate::content_filter::ContentFilterFactory::ddssql)
+/// for the syntax.
+///
+/// Use with e.g.
+/// [`DataReader::read_query`](crate::with_key::DataReader::read_query) and
+/// [`DataReader::wait_for_query`](crate::with_key::DataReader::wait_for_query).
+/// Samples of disposed or unregistered instances have no data to query, so
+/// they are selected by their states only.
+///
+/// See DDS Specification 1.4 Section "2.2.2.5.9 QueryCondition"
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryCondition {
+  read_condition: ReadCondition,
+  query_expression: String,
+  query_parameters: Vec<String>,
+  query: SqlFilter,
+}
+
+impl QueryCondition {
+  /// Returns `BadParameter` if the expression cannot be parsed, or refers to
+  /// parameters that are not given.
+  pub fn new(
+    read_condition: ReadCondition,
+    query_expression: &str,
+    query_parameters: Vec<String>,
+  ) -> CreateResult<Self> {
+    let query = Self::parse(query_expression, &query_parameters)?;
+    Ok(Self {
+      read_condition,
+      query_expression: query_expression.to_string(),
+      query_parameters,
+      query,
+    })
+  }
+
+  fn parse(query_expression: &str, query_parameters: &[String]) -> CreateResult<SqlFilter> {
+    SqlFilter::parse(query_expression, query_parameters).or_else(|e| {
+      create_error_bad_parameter!("Invalid query expression {query_expression:?}: {e}")
+    })
+  }
+
+  pub fn read_condition(&self) -> ReadCondition {
+    self.read_condition
+  }
+
+  pub fn query_expression(&self) -> &str {
+    &self.query_expression
+  }
+
+  pub fn query_parameters(&self) -> &[String] {
+    &self.query_parameters
+  }
+
+  /// Changes the parameters of the query. On error, the condition is not
+  /// changed.
+  pub fn set_query_parameters(&mut self, query_parameters: Vec<String>) -> CreateResult<()> {
+    self.query = Self::parse(&self.query_expression, &query_parameters)?;
+    self.query_parameters = query_parameters;
+    Ok(())
+  }
+
+  pub(crate) fn matches<D: Serialize + ?Sized>(&self, data: &D) -> bool {
+    self.query.matches(data)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Serialize)]
+  struct Reading {
+    value: i32,
+  }
+
+  #[test]
+  fn query_parameters_can_be_changed() {
+    let mut query =
+      QueryCondition::new(ReadCondition::any(), "value > %0", vec!["5".to_string()]).unwrap();
+    assert!(query.matches(&Reading { value: 6 }));
+
+    query.set_query_parameters(vec!["10".to_string()]).unwrap();
+    assert!(!query.matches(&Reading { value: 6 }));
+
+    // Too few parameters is an error, which leaves the query as it was
+    assert!(query.set_query_parameters(vec![]).is_err());
+    assert_eq!(query.query_parameters(), ["10".to_string()]);
+    assert!(query.matches(&Reading { value: 11 }));
+  }
+}
@@ -0,0 +1,164 @@
+//! Presets for the memory and thread usage of a DomainParticipant.
+//!
+//! A [`ResourceProfile`] selects a complete set of [`ResourceSettings`] with
+//! one knob:
+//!
+//! * [`Small`](ResourceProfile::Small) is for constrained devices that take
+//!   part in small systems. Receive buffers and default caches are small, the
+//!   number of remote participants and endpoints is capped, and the lease
+//!   watchdog thread is not started.
+//! * [`Default`](ResourceProfile::Default) is what a DomainParticipant uses,
+//!   if nothing else is configured.
+//! * [`Large`](ResourceProfile::Large) is for hosts in large systems with
+//!   plenty of memory. Buffers and default caches are larger, so that bursts
+//!   of data are less likely to be lost.
+//!
+//! Individual settings can be adjusted after choosing a profile:
+//!
+//! ```
+//! use rustdds::{DomainParticipantBuilder, ResourceProfile, ResourceSettings};
+//!
+//! let settings = ResourceSettings {
+//!   max_discovered_participants: Some(4),
+//!   ..ResourceProfile::Small.settings()
+//! };
+//! let builder = DomainParticipantBuilder::new(0).resource_settings(settings);
+//! ```
+//!
+//! The OS buffers of the UDP sockets are left at their system default size,
+//! unless [`ResourceSettings::socket_receive_buffer_size`] or
+//! [`ResourceSettings::socket_send_buffer_size`] is set, as the `Large` profile
+//! does. High-throughput subscribers need a large receive buffer, or the OS
+//! drops datagrams that arrive while the participant is busy. The OS may
+//! give a socket less than requested. On Linux, the limits are the
+//! `net.core.rmem_max` and `net.core.wmem_max` sysctls. A smaller buffer is
+//! logged as a warning and reported as
+//! [`SocketBufferClamped`](crate::DomainParticipantStatusEvent::SocketBufferClamped).
+//! How much each socket has received and dropped is in
+//! [`DomainParticipant::socket_statistics`](crate::DomainParticipant::socket_statistics).
+//!
+//! The code size can be reduced at compile time by leaving out optional
+//! subsystems with Cargo features:
+//!
+//! * `security`: DDS Security. Not enabled by default.
+//! * `ros2`: The deprecated ROS2 module. Enabled by default, so it is left out
+//!   by building with `default-features = false`.
+//!
+//! RustDDS has no discovery server, so there is nothing to leave out for it.
+
+use std::net::{IpAddr, SocketAddr};
+
+/// Named presets for [`ResourceSettings`]. See the
+/// [module documentation](self).
+///
+/// Set with
+/// [`DomainParticipantBuilder::resource_profile`](crate::DomainParticipantBuilder::resource_profile).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResourceProfile {
+  Small,
+  #[default]
+  Default,
+  Large,
+}
+
+impl ResourceProfile {
+  pub fn settings(self) -> ResourceSettings {
+    match self {
+      Self::Small => ResourceSettings {
+        receive_buffer_size: 128 * 1024,
+        default_cache_max_samples: 16,
+        max_discovered_participants: Some(16),
+        max_discovered_endpoints: Some(256),
+        lease_watchdog_thread: false,
+        socket_receive_buffer_size: None,
+        socket_send_buffer_size: None,
+      },
+      Self::Default => ResourceSettings {
+        receive_buffer_size: 256 * 1024,
+        default_cache_max_samples: 64,
+        max_discovered_participants: None,
+        max_discovered_endpoints: None,
+        lease_watchdog_thread: true,
+        socket_receive_buffer_size: None,
+        socket_send_buffer_size: None,
+      },
+      Self::Large => ResourceSettings {
+        receive_buffer_size: 1024 * 1024,
+        default_cache_max_samples: 1024,
+        max_discovered_participants: None,
+        max_discovered_endpoints: None,
+        lease_watchdog_thread: true,
+        socket_receive_buffer_size: Some(4 * 1024 * 1024),
+        socket_send_buffer_size: Some(1024 * 1024),
+      },
+    }
+  }
+}
+
+/// Resource usage of a DomainParticipant.
+///
+/// Set with
+/// [`DomainParticipantBuilder::resource_settings`](crate::DomainParticipantBuilder::resource_settings).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceSettings {
+  /// Size in bytes of the buffers that UDP messages are received into. Each
+  /// socket has one, and a new one is allocated when the previous one is
+  /// full. Values smaller than the maximum UDP message size (64 KiB) are
+  /// rounded up to it.
+  pub receive_buffer_size: usize,
+  /// Maximum number of samples in the cache of a Topic, whose QoS does not
+  /// specify [`ResourceLimits`](crate::policy::ResourceLimits).
+  pub default_cache_max_samples: i32,
+  /// Maximum number of participants in discovery, including the local one.
+  /// Announcements from further participants are ignored. `None` means no
+  /// limit.
+  pub max_discovered_participants: Option<usize>,
+  /// Maximum number of remote DataReaders and DataWriters in discovery.
+  /// Announcements of further endpoints are ignored. `None` means no limit.
+  pub max_discovered_endpoints: Option<usize>,
+  /// Whether to start a separate thread to detect late participant lease
+  /// assertions due to local overload. Without it, late assertions are not
+  /// reported as
+  /// [`LeaseAssertionLate`](crate::DomainParticipantStatusEvent::LeaseAssertionLate).
+  pub lease_watchdog_thread: bool,
+  /// Size in bytes of the OS receive buffer (`SO_RCVBUF`) of each UDP
+  /// socket. `None` keeps the system default. TCP connections are left to
+  /// the buffer auto-tuning of the OS.
+  pub socket_receive_buffer_size: Option<usize>,
+  /// Size in bytes of the OS send buffer (`SO_SNDBUF`) of each UDP socket.
+  /// `None` keeps the system default.
+  pub socket_send_buffer_size: Option<usize>,
+}
+
+impl Default for ResourceSettings {
+  fn default() -> Self {
+    ResourceProfile::default().settings()
+  }
+}
+
+/// The OS buffers of a socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketBuffer {
+  /// `SO_RCVBUF`
+  Receive,
+  /// `SO_SNDBUF`
+  Send,
+}
+
+/// Receive statistics of a UDP socket that a DomainParticipant listens to,
+/// from
+/// [`DomainParticipant::socket_statistics`](crate::DomainParticipant::socket_statistics).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocketStatistics {
+  /// Address that the socket is bound to
+  pub local_address: SocketAddr,
+  /// Multicast group that the socket has joined, if any
+  pub multicast_group: Option<IpAddr>,
+  /// Size of the OS receive buffer, as the OS reports it
+  pub receive_buffer_size: usize,
+  /// Datagrams received from the socket
+  pub received_datagrams: u64,
+  /// Datagrams that the OS has dropped, because the receive buffer was full.
+  /// Counted on Linux only, and `None` elsewhere.
+  pub dropped_datagrams: Option<u64>,
+}
@@ -122,6 +122,11 @@ pub enum WriteError<D> {
   #[error("Write operation timed out while blocking")]
   WouldBlock { data: D },
 
+  /// A [`WriteInterceptor`](crate::interceptor::WriteInterceptor) vetoed the
+  /// sample. It was not sent.
+  #[error("Write vetoed by interceptor: {reason}")]
+  Vetoed { reason: String, data: D },
+
   /// Something that should not go wrong went wrong anyway.
   /// This is usually a bug in RustDDS
   #[error("Internal error: {reason}")]
@@ -146,6 +151,20 @@ impl From<serialization::Error> for WriteError<()> {
   }
 }
 
+impl WriteError<()> {
+  // Inverse of forget_data: gives the data back to the caller.
+  pub(crate) fn with_data<D>(self, data: D) -> WriteError<D> {
+    match self {
+      WriteError::Serialization { reason, data: () } => WriteError::Serialization { reason, data },
+      WriteError::Poisoned { reason, data: () } => WriteError::Poisoned { reason, data },
+      WriteError::Io(e) => WriteError::Io(e),
+      WriteError::WouldBlock { data: () } => WriteError::WouldBlock { data },
+      WriteError::Vetoed { reason, data: () } => WriteError::Vetoed { reason, data },
+      WriteError::Internal { reason } => WriteError::Internal { reason },
+    }
+  }
+}
+
 impl<D> WriteError<D> {
   /// Forgets the data of WriteError, which can be useful in cases where it is
   /// not needed.
@@ -157,6 +176,7 @@ impl<D> WriteError<D> {
       WriteError::Poisoned { reason, data: _ } => WriteError::Poisoned { reason, data: () },
       WriteError::Io(e) => WriteError::Io(e),
       WriteError::WouldBlock { data: _ } => WriteError::WouldBlock { data: () },
+      WriteError::Vetoed { reason, data: _ } => WriteError::Vetoed { reason, data: () },
       WriteError::Internal { reason } => WriteError::Internal { reason },
     }
   }
@@ -178,6 +198,10 @@ pub(crate) fn unwrap_no_key_write_error<D>(
       data: data.d,
     },
     WriteError::WouldBlock { data } => WriteError::WouldBlock { data: data.d },
+    WriteError::Vetoed { reason, data } => WriteError::Vetoed {
+      reason,
+      data: data.d,
+    },
     WriteError::Internal { reason } => WriteError::Internal { reason },
     WriteError::Io(io) => WriteError::Io(io),
   }
@@ -229,7 +229,10 @@ pub struct SampleInfo {
   /// publication_handle identifies the DataWriter that modified
   /// the instance (i.e. wrote this sample)
   pub(crate) publication_handle: GUID,
+  /// RTPS sequence number assigned to this sample by the DataWriter.
   pub(crate) sequence_number: SequenceNumber,
+  /// Local time when this sample was received.
+  pub(crate) reception_timestamp: Timestamp,
 }
 
 impl SampleInfo {
@@ -293,6 +296,23 @@ impl SampleInfo {
     self.publication_handle
   }
 
+  /// RTPS sequence number of this sample, as assigned by the writing
+  /// DataWriter. Together with [`writer_guid`](Self::writer_guid) it
+  /// uniquely identifies the sample, e.g. for duplicate detection.
+  ///
+  /// Sequence numbers from one DataWriter are increasing, but not necessarily
+  /// consecutive at the reader, e.g. due to filtering or best-effort loss.
+  pub fn sequence_number(&self) -> SequenceNumber {
+    self.sequence_number
+  }
+
+  /// Local time when this DataReader received the sample. Unlike
+  /// [`source_timestamp`](Self::source_timestamp), this is always present,
+  /// and is comparable across Topics read in this process.
+  pub fn reception_timestamp(&self) -> Timestamp {
+    self.reception_timestamp
+  }
+
   pub fn related_sample_identity(&self) -> Option<SampleIdentity> {
     self.write_options.related_sample_identity()
   }
@@ -0,0 +1,327 @@
+//! A [`ShutdownToken`] lets the application wake up everyone waiting for DDS
+//! data, when it is time to quit.
+//!
+//! Clones of a token share the same trigger. Each clone can be registered to
+//! a mio-0.6 or mio-0.8 `Poll`, awaited as a `Future`, or attached to an async
+//! sample stream with e.g.
+//! [`DataReaderStream::until_shutdown`](crate::with_key::DataReaderStream::until_shutdown).
+//! Once triggered, the token stays triggered.
+//!
+//! ```
+//! use rustdds::ShutdownToken;
+//!
+//! let shutdown = ShutdownToken::new();
+//! let worker_shutdown = shutdown.clone();
+//! let worker = std::thread::spawn(move || {
+//!   // A real worker would poll DataReaders together with the token.
+//!   futures::executor::block_on(worker_shutdown);
+//! });
+//! shutdown.trigger();
+//! worker.join().unwrap();
+//! ```
+
+use std::{
+  collections::BTreeMap,
+  fmt,
+  future::Future,
+  io,
+  pin::Pin,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+  },
+  task::{Context, Poll, Waker},
+};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::mio_source::{make_poll_channel, PollEventSender, PollEventSource};
+
+// Everyone who must be notified on trigger. Keyed by token id, so that a
+// dropped token can remove its own entries.
+struct Listeners {
+  next_id: u64,
+  set_readiness: BTreeMap<u64, mio_06::SetReadiness>,
+  poll_event_senders: BTreeMap<u64, PollEventSender>,
+  wakers: BTreeMap<u64, Waker>,
+}
+
+struct Shared {
+  // Written only while holding the listeners lock, so that a listener added
+  // concurrently with trigger() is either notified or sees the flag.
+  triggered: AtomicBool,
+  listeners: Mutex<Listeners>,
+}
+
+/// Shared, one-shot shutdown signal, similar to a DDS GuardCondition.
+///
+/// Event sources in mio can be registered to only one `Poll` at a time, so
+/// clone the token for each `Poll` or task that needs to wake up. After
+/// receiving an event from the token, check [`is_triggered`](Self::is_triggered).
+pub struct ShutdownToken {
+  id: u64,
+  shared: Arc<Shared>,
+  registration: mio_06::Registration,
+  // Created on first registration to mio-0.8, because that may fail.
+  poll_event_source: Option<PollEventSource>,
+}
+
+impl ShutdownToken {
+  pub fn new() -> Self {
+    Self::attach(Arc::new(Shared {
+      triggered: AtomicBool::new(false),
+      listeners: Mutex::new(Listeners {
+        next_id: 0,
+        set_readiness: BTreeMap::new(),
+        poll_event_senders: BTreeMap::new(),
+        wakers: BTreeMap::new(),
+      }),
+    }))
+  }
+
+  fn attach(shared: Arc<Shared>) -> Self {
+    let (registration, set_readiness) = mio_06::Registration::new2();
+    let id = {
+      let mut listeners = shared.listeners.lock().unwrap();
+      let id = listeners.next_id;
+      listeners.next_id += 1;
+      if shared.triggered.load(Ordering::Acquire) {
+        set_ready(&set_readiness);
+      }
+      listeners.set_readiness.insert(id, set_readiness);
+      id
+    };
+    Self {
+      id,
+      shared,
+      registration,
+      poll_event_source: None,
+    }
+  }
+
+  /// Signal shutdown to all clones of this token. Triggering again has no
+  /// effect.
+  pub fn trigger(&self) {
+    let mut listeners = self.shared.listeners.lock().unwrap();
+    if self.shared.triggered.swap(true, Ordering::AcqRel) {
+      return; // already done
+    }
+    debug!("Shutdown triggered");
+    for set_readiness in listeners.set_readiness.values() {
+      set_ready(set_readiness);
+    }
+    for sender in listeners.poll_event_senders.values() {
+      sender.send();
+    }
+    for waker in std::mem::take(&mut listeners.wakers).into_values() {
+      waker.wake();
+    }
+  }
+
+  pub fn is_triggered(&self) -> bool {
+    self.shared.triggered.load(Ordering::Acquire)
+  }
+
+  // Like Future::poll, but usable through a shared reference. Leaves the waker
+  // behind, if not yet triggered.
+  pub(crate) fn poll_triggered(&self, cx: &mut Context<'_>) -> Poll<()> {
+    if self.is_triggered() {
+      return Poll::Ready(());
+    }
+    let mut listeners = self.shared.listeners.lock().unwrap();
+    // Check again, now that trigger() cannot run concurrently.
+    if self.is_triggered() {
+      Poll::Ready(())
+    } else {
+      listeners.wakers.insert(self.id, cx.waker().clone());
+      Poll::Pending
+    }
+  }
+}
+
+fn set_ready(set_readiness: &mio_06::SetReadiness) {
+  if let Err(e) = set_readiness.set_readiness(mio_06::Ready::readable()) {
+    debug!("ShutdownToken: cannot set readiness: {e}");
+  }
+}
+
+impl Default for ShutdownToken {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Clone for ShutdownToken {
+  fn clone(&self) -> Self {
+    Self::attach(Arc::clone(&self.shared))
+  }
+}
+
+impl fmt::Debug for ShutdownToken {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("ShutdownToken")
+      .field("triggered", &self.is_triggered())
+      .finish()
+  }
+}
+
+impl Drop for ShutdownToken {
+  fn drop(&mut self) {
+    if let Ok(mut listeners) = self.shared.listeners.lock() {
+      listeners.set_readiness.remove(&self.id);
+      listeners.poll_event_senders.remove(&self.id);
+      listeners.wakers.remove(&self.id);
+    }
+  }
+}
+
+impl Future for ShutdownToken {
+  type Output = ();
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+    self.poll_triggered(cx)
+  }
+}
+
+impl mio_06::Evented for ShutdownToken {
+  fn register(
+    &self,
+    poll: &mio_06::Poll,
+    token: mio_06::Token,
+    interest: mio_06::Ready,
+    opts: mio_06::PollOpt,
+  ) -> io::Result<()> {
+    self.registration.register(poll, token, interest, opts)
+  }
+
+  fn reregister(
+    &self,
+    poll: &mio_06::Poll,
+    token: mio_06::Token,
+    interest: mio_06::Ready,
+    opts: mio_06::PollOpt,
+  ) -> io::Result<()> {
+    self.registration.reregister(poll, token, interest, opts)
+  }
+
+  fn deregister(&self, poll: &mio_06::Poll) -> io::Result<()> {
+    poll.deregister(&self.registration)
+  }
+}
+
+impl mio_08::event::Source for ShutdownToken {
+  fn register(
+    &mut self,
+    registry: &mio_08::Registry,
+    token: mio_08::Token,
+    interests: mio_08::Interest,
+  ) -> io::Result<()> {
+    if self.poll_event_source.is_none() {
+      let (source, sender) = make_poll_channel()?;
+      let mut listeners = self.shared.listeners.lock().unwrap();
+      if self.is_triggered() {
+        sender.send();
+      }
+      listeners.poll_event_senders.insert(self.id, sender);
+      self.poll_event_source = Some(source);
+    }
+    match self.poll_event_source.as_mut() {
+      Some(source) => source.register(registry, token, interests),
+      None => Err(io::Error::new(
+        io::ErrorKind::Other,
+        "ShutdownToken has no event source",
+      )),
+    }
+  }
+
+  fn reregister(
+    &mut self,
+    registry: &mio_08::Registry,
+    token: mio_08::Token,
+    interests: mio_08::Interest,
+  ) -> io::Result<()> {
+    match self.poll_event_source.as_mut() {
+      Some(source) => source.reregister(registry, token, interests),
+      None => self.register(registry, token, interests),
+    }
+  }
+
+  fn deregister(&mut self, registry: &mio_08::Registry) -> io::Result<()> {
+    match self.poll_event_source.as_mut() {
+      Some(source) => source.deregister(registry),
+      None => Ok(()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::*;
+
+  #[test]
+  fn trigger_wakes_all_clones() {
+    let shutdown = ShutdownToken::new();
+
+    let poll_06 = mio_06::Poll::new().unwrap();
+    let token_06 = shutdown.clone();
+    poll_06
+      .register(
+        &token_06,
+        mio_06::Token(1),
+        mio_06::Ready::readable(),
+        mio_06::PollOpt::edge(),
+      )
+      .unwrap();
+
+    let mut poll_08 = mio_08::Poll::new().unwrap();
+    let mut token_08 = shutdown.clone();
+    poll_08
+      .registry()
+      .register(&mut token_08, mio_08::Token(2), mio_08::Interest::READABLE)
+      .unwrap();
+
+    let awaiting = std::thread::spawn({
+      let token = shutdown.clone();
+      move || futures::executor::block_on(token)
+    });
+
+    let mut events_06 = mio_06::Events::with_capacity(4);
+    let mut events_08 = mio_08::Events::with_capacity(4);
+    poll_06
+      .poll(&mut events_06, Some(Duration::from_millis(10)))
+      .unwrap();
+    poll_08
+      .poll(&mut events_08, Some(Duration::from_millis(10)))
+      .unwrap();
+    assert!(events_06.is_empty());
+    assert!(events_08.is_empty());
+    assert!(!token_06.is_triggered());
+
+    shutdown.trigger();
+
+    poll_06
+      .poll(&mut events_06, Some(Duration::from_secs(1)))
+      .unwrap();
+    poll_08
+      .poll(&mut events_08, Some(Duration::from_secs(1)))
+      .unwrap();
+    assert_eq!(
+      events_06.iter().map(|e| e.token()).collect::<Vec<_>>(),
+      vec![mio_06::Token(1)]
+    );
+    assert_eq!(
+      events_08.iter().map(|e| e.token()).collect::<Vec<_>>(),
+      vec![mio_08::Token(2)]
+    );
+    assert!(token_08.is_triggered());
+    awaiting.join().unwrap();
+
+    // Tokens cloned after the trigger are triggered already
+    let late = shutdown.clone();
+    assert!(late.is_triggered());
+    futures::executor::block_on(late);
+  }
+}
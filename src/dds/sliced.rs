@@ -0,0 +1,304 @@
+//! Publishing and receiving very large samples in slices.
+//!
+//! Samples such as files or maps may be too large to have conveniently in
+//! memory as one object. A [`SlicedSample`] is written chunk by chunk with
+//! [`write_chunk`](SlicedSample::write_chunk), and published with
+//! [`finish`](SlicedSample::finish). The chunks are appended directly to the
+//! serialized sample, which the RTPS Writer sends in DATA_FRAG submessages as
+//! usual, if it is larger than the fragment size. The Writer keeps the sample
+//! in its history for repairs, but the application never needs a contiguous
+//! copy of its own.
+//!
+//! The chunks must together form the serialized representation of one sample
+//! in the encoding of the DataWriter's
+//! [`SerializerAdapter`](crate::no_key::SerializerAdapter). Write interceptors
+//! are not applied, since there is no sample object to give them.
+//!
+//! On the receiving side,
+//! [`SimpleDataReader::try_take_one_sliced`](crate::with_key::SimpleDataReader::try_take_one_sliced)
+//! gives the received serialized sample as [`SampleSlices`], which can be
+//! processed in chunks or copied into a buffer of the application, without
+//! deserializing it.
+//!
+//! ```
+//! use rustdds::{no_key::DataWriter, *};
+//!
+//! let participant = DomainParticipant::new(0).unwrap();
+//! let qos = QosPolicyBuilder::new().build();
+//! let publisher = participant.create_publisher(&qos).unwrap();
+//! let topic = participant
+//!   .create_topic(
+//!     "file_transfer".to_string(),
+//!     "Blob".to_string(),
+//!     &qos,
+//!     TopicKind::NoKey,
+//!   )
+//!   .unwrap();
+//! let writer: DataWriter<Vec<u8>> = publisher.create_datawriter_no_key(&topic, None).unwrap();
+//!
+//! // A CDR sequence<octet> is its length followed by the octets.
+//! let content = [7_u8; 100_000];
+//! let mut sample = writer.begin_sample(4 + content.len()).unwrap();
+//! sample
+//!   .write_chunk(&(content.len() as u32).to_le_bytes())
+//!   .unwrap();
+//! for chunk in content.chunks(8192) {
+//!   sample.write_chunk(chunk).unwrap();
+//! }
+//! sample.finish().unwrap();
+//! ```
+
+use bytes::{Buf, Bytes, BytesMut};
+
+use crate::{
+  dds::{
+    ddsdata::DDSData,
+    result::{WriteError, WriteResult},
+    with_key::datawriter::WriteOptions,
+  },
+  messages::submessages::elements::serialized_payload::SerializedPayload,
+  structure::{
+    cache_change::CacheChange, guid::GUID, rpc::SampleIdentity, sequence_number::SequenceNumber,
+    time::Timestamp,
+  },
+  RepresentationIdentifier,
+};
+
+type SendSerialized<'a> =
+  Box<dyn FnOnce(Bytes, WriteOptions) -> WriteResult<SampleIdentity, ()> + 'a>;
+
+/// A sample that is being written in chunks. See the
+/// [module documentation](self).
+///
+/// Created by `begin_sample` of a [`with_key::DataWriter`](crate::with_key::DataWriter)
+/// or a [`no_key::DataWriter`](crate::no_key::DataWriter). Nothing is
+/// published, if the `SlicedSample` is dropped without calling
+/// [`finish`](Self::finish).
+pub struct SlicedSample<'a> {
+  buffer: BytesMut,
+  total_len: usize,
+  send: SendSerialized<'a>,
+}
+
+impl<'a> SlicedSample<'a> {
+  pub(crate) fn new(total_len: usize, send: SendSerialized<'a>) -> WriteResult<Self, ()> {
+    // DATA_FRAG gives the sample size as 32 bits
+    if u32::try_from(total_len).is_err() {
+      return Err(WriteError::Serialization {
+        reason: format!("Sliced sample of {total_len} bytes is too large for RTPS."),
+        data: (),
+      });
+    }
+    Ok(Self {
+      buffer: BytesMut::with_capacity(total_len),
+      total_len,
+      send,
+    })
+  }
+
+  /// Length of the sample, as given when it was begun
+  pub fn total_len(&self) -> usize {
+    self.total_len
+  }
+
+  /// Number of bytes still to be written
+  pub fn remaining_len(&self) -> usize {
+    self.total_len - self.buffer.len()
+  }
+
+  /// Appends a chunk to the sample. Fails, and appends nothing, if the chunk
+  /// does not fit in the remaining length.
+  pub fn write_chunk(&mut self, chunk: &[u8]) -> WriteResult<(), ()> {
+    if chunk.len() > self.remaining_len() {
+      return Err(WriteError::Serialization {
+        reason: format!(
+          "Chunk of {} bytes does not fit in sliced sample, {} bytes remain.",
+          chunk.len(),
+          self.remaining_len()
+        ),
+        data: (),
+      });
+    }
+    self.buffer.extend_from_slice(chunk);
+    Ok(())
+  }
+
+  /// Publishes the sample. All of the total length must have been written.
+  pub fn finish(self) -> WriteResult<SampleIdentity, ()> {
+    self.finish_with_options(WriteOptions::default())
+  }
+
+  pub fn finish_with_options(self, write_options: WriteOptions) -> WriteResult<SampleIdentity, ()> {
+    if self.remaining_len() > 0 {
+      return Err(WriteError::Serialization {
+        reason: format!(
+          "Sliced sample is incomplete: {} of {} bytes written.",
+          self.buffer.len(),
+          self.total_len
+        ),
+        data: (),
+      });
+    }
+    (self.send)(self.buffer.freeze(), write_options)
+  }
+}
+
+/// A received sample in serialized form. See the
+/// [module documentation](self).
+///
+/// The bytes are shared with the DataReader's cache, not copied.
+#[derive(Debug, Clone)]
+pub struct SampleSlices {
+  writer_guid: GUID,
+  sequence_number: SequenceNumber,
+  receive_instant: Timestamp,
+  source_timestamp: Option<Timestamp>,
+  representation_identifier: RepresentationIdentifier,
+  value: Bytes,
+}
+
+impl SampleSlices {
+  pub(crate) fn from_cache_change(receive_instant: Timestamp, cc: &CacheChange) -> Option<Self> {
+    match &cc.data_value {
+      DDSData::Data {
+        serialized_payload:
+          SerializedPayload {
+            representation_identifier,
+            value,
+            ..
+          },
+      } => Some(Self {
+        writer_guid: cc.writer_guid,
+        sequence_number: cc.sequence_number,
+        receive_instant,
+        source_timestamp: cc.write_options.source_timestamp(),
+        representation_identifier: *representation_identifier,
+        value: value.clone(),
+      }),
+      // Samples delivered in process are serialized for slicing.
+      DDSData::Local { sample } => sample.serialized_payload().map(|payload| Self {
+        writer_guid: cc.writer_guid,
+        sequence_number: cc.sequence_number,
+        receive_instant,
+        source_timestamp: cc.write_options.source_timestamp(),
+        representation_identifier: payload.representation_identifier,
+        value: payload.value,
+      }),
+      // Disposals have no sample
+      DDSData::DisposeByKey { .. } | DDSData::DisposeByKeyHash { .. } => None,
+    }
+  }
+
+  pub fn writer_guid(&self) -> GUID {
+    self.writer_guid
+  }
+
+  pub fn sequence_number(&self) -> SequenceNumber {
+    self.sequence_number
+  }
+
+  pub fn receive_instant(&self) -> Timestamp {
+    self.receive_instant
+  }
+
+  pub fn source_timestamp(&self) -> Option<Timestamp> {
+    self.source_timestamp
+  }
+
+  /// Encoding of the serialized sample
+  pub fn representation_identifier(&self) -> RepresentationIdentifier {
+    self.representation_identifier
+  }
+
+  pub fn len(&self) -> usize {
+    self.value.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.value.is_empty()
+  }
+
+  /// The whole sample
+  pub fn as_bytes(&self) -> &[u8] {
+    &self.value
+  }
+
+  /// The sample in chunks of `chunk_size` bytes. The last chunk may be
+  /// shorter.
+  ///
+  /// # Panics
+  /// If `chunk_size` is 0.
+  pub fn chunks(&self, chunk_size: usize) -> impl Iterator<Item = &[u8]> {
+    self.value.chunks(chunk_size)
+  }
+
+  /// Copies the sample, starting at `offset`, into `buffer`. Returns the
+  /// number of bytes copied, which is less than the buffer length only at the
+  /// end of the sample.
+  pub fn copy_to(&self, offset: usize, buffer: &mut [u8]) -> usize {
+    let source = self.value.get(offset..).unwrap_or_default();
+    let len = source.len().min(buffer.len());
+    buffer[..len].copy_from_slice(&source[..len]);
+    len
+  }
+
+  /// The sample as an [`std::io::Read`]
+  pub fn reader(&self) -> impl std::io::Read {
+    self.value.clone().reader()
+  }
+
+  pub fn into_bytes(self) -> Bytes {
+    self.value
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Read;
+
+  use super::*;
+
+  #[test]
+  fn sliced_sample_checks_length() {
+    let sent = std::cell::RefCell::new(None);
+    let send: SendSerialized = Box::new(|bytes, _| {
+      *sent.borrow_mut() = Some(bytes);
+      Ok(SampleIdentity::default())
+    });
+    let mut sample = SlicedSample::new(6, send).unwrap();
+    sample.write_chunk(b"abcd").unwrap();
+    assert!(sample.write_chunk(b"efg").is_err());
+    assert_eq!(sample.remaining_len(), 2);
+    sample.write_chunk(b"ef").unwrap();
+    sample.finish().unwrap();
+    assert_eq!(sent.borrow().as_deref(), Some(&b"abcdef"[..]));
+
+    let incomplete = SlicedSample::new(6, Box::new(|_, _| Ok(SampleIdentity::default()))).unwrap();
+    assert!(incomplete.finish().is_err());
+  }
+
+  #[test]
+  fn sample_slices_copy_and_read() {
+    let slices = SampleSlices {
+      writer_guid: GUID::GUID_UNKNOWN,
+      sequence_number: SequenceNumber::new(1),
+      receive_instant: Timestamp::ZERO,
+      source_timestamp: None,
+      representation_identifier: RepresentationIdentifier::CDR_LE,
+      value: Bytes::from_static(b"0123456789"),
+    };
+    assert_eq!(
+      slices.chunks(4).collect::<Vec<_>>(),
+      [&b"0123"[..], b"4567", b"89"]
+    );
+
+    let mut buffer = [0; 4];
+    assert_eq!(slices.copy_to(8, &mut buffer), 2);
+    assert_eq!(&buffer[..2], b"89");
+    assert_eq!(slices.copy_to(20, &mut buffer), 0);
+
+    let mut read = String::new();
+    slices.reader().read_to_string(&mut read).unwrap();
+    assert_eq!(read, "0123456789");
+  }
+}
@@ -7,6 +7,7 @@
 // 2.2.4.1 in DDS Specification v1.4
 use std::{
   io,
+  net::{IpAddr, SocketAddr},
   pin::Pin,
   sync::{Arc, Mutex},
   task::{Context, Poll, Waker},
@@ -21,7 +22,12 @@ use mio_08::{event, Interest, Registry, Token};
 use chrono::Utc;
 
 use crate::{
-  dds::{qos::QosPolicyId, topic::TopicData},
+  dds::{
+    qos::{policy::WriterRestart, QosPolicyId},
+    resource_profile::SocketBuffer,
+    topic::TopicData,
+    xtypes::AssignabilityResult,
+  },
   discovery::SpdpDiscoveredParticipantData,
   messages::{protocol_version::ProtocolVersion, vendor_id::VendorId},
   mio_source::*,
@@ -63,37 +69,48 @@ pub(crate) fn sync_status_channel<T>(
   let (signal_receiver, signal_sender) = make_poll_channel()?;
   let (actual_sender, actual_receiver) = mio_channel::sync_channel(capacity);
   let waker = Arc::new(Mutex::new(None));
+  let listener = Arc::new(Mutex::new(None));
   Ok((
     StatusChannelSender {
       actual_sender,
       signal_sender,
       waker: Arc::clone(&waker),
+      listener: Arc::clone(&listener),
     },
     StatusChannelReceiver {
       actual_receiver: Mutex::new(actual_receiver),
       signal_receiver,
       waker,
+      listener,
     },
   ))
 }
 
+// Sees every status sent, in addition to the receiver
+pub(crate) type StatusTap<T> = Box<dyn Fn(&T) + Send>;
+
 // TODO: try to make this (and the Receiver) private types
 #[derive(Clone)]
 pub struct StatusChannelSender<T> {
   actual_sender: mio_channel::SyncSender<T>,
   signal_sender: PollEventSender,
   waker: Arc<Mutex<Option<Waker>>>,
+  listener: Arc<Mutex<Option<StatusTap<T>>>>,
 }
 
 pub struct StatusChannelReceiver<T> {
   actual_receiver: Mutex<mio_channel::Receiver<T>>,
   signal_receiver: PollEventSource,
   waker: Arc<Mutex<Option<Waker>>>,
+  listener: Arc<Mutex<Option<StatusTap<T>>>>,
 }
 
 impl<T> StatusChannelSender<T> {
   /// Best-effort send. If there is no receiver, this will fail silently.
   pub fn try_send(&self, t: T) -> Result<(), mio_channel::TrySendError<T>> {
+    if let Some(listener) = self.listener.lock().unwrap().as_ref() {
+      listener(&t);
+    }
     let mut w = self.waker.lock().unwrap(); // lock already at the beginning
     match self.actual_sender.try_send(t) {
       Ok(()) => {
@@ -129,6 +146,11 @@ impl<T> StatusChannelReceiver<T> {
   pub(crate) fn get_waker_update_lock(&self) -> std::sync::MutexGuard<'_, Option<Waker>> {
     self.waker.lock().unwrap()
   }
+
+  // Sets the function that sees the statuses as they are sent
+  pub(crate) fn set_listener(&self, listener: Option<StatusTap<T>>) {
+    *self.listener.lock().unwrap() = listener;
+  }
 }
 
 impl<'a, E> StatusEvented<'a, E, StatusReceiverStream<'a, E>> for StatusChannelReceiver<E> {
@@ -316,6 +338,56 @@ pub enum DomainParticipantStatusEvent {
     requested_qos: Box<QosPolicies>,
     offered_qos: Box<QosPolicies>,
   },
+  /// The remote Reader cannot read the type of the local Writer, so they
+  /// were not matched. See [`xtypes`](crate::xtypes).
+  RemoteReaderTypeIncompatible {
+    local_writer: GUID,
+    remote_reader: GUID,
+    assignability: AssignabilityResult,
+  },
+  /// The local Reader cannot read the type of the remote Writer.
+  RemoteWriterTypeIncompatible {
+    local_reader: GUID,
+    remote_writer: GUID,
+    assignability: AssignabilityResult,
+  },
+  /// This participant did not send its own Discovery announcement on time,
+  /// because it is overloaded locally. Remote participants will consider us
+  /// lost, if this goes on for longer than our lease duration.
+  LeaseAssertionLate {
+    late_by: Duration,
+    lease_duration: Duration,
+  },
+  /// A remote RustDDS participant reports that its Discovery announcements
+  /// have been late due to its own overload, rather than lost in the network.
+  /// `late_assertions` is the total reported so far.
+  RemoteLeaseAssertionLate {
+    participant: GuidPrefix,
+    late_assertions: u32,
+  },
+  /// Local IP addresses have changed, e.g. due to WiFi roaming or DHCP
+  /// renewal. The participant has updated its locators and announced them
+  /// to remote participants.
+  NetworkChanged {
+    added: Vec<IpAddr>,
+    removed: Vec<IpAddr>,
+  },
+  /// The OS gave a socket a smaller buffer than requested in
+  /// [`ResourceSettings`](crate::ResourceSettings). Sizes are in bytes.
+  SocketBufferClamped {
+    local_address: SocketAddr,
+    buffer: SocketBuffer,
+    requested: usize,
+    effective: usize,
+  },
+  /// Secure construction of this participant is complete, and it has started
+  /// to authenticate remote participants. `startup_time` is how long the
+  /// construction took. See also
+  /// [`DomainParticipantBuilder::security_startup_progress`](crate::DomainParticipantBuilder::security_startup_progress).
+  #[cfg(feature = "security")]
+  SecurityReady {
+    startup_time: Duration,
+  },
   #[cfg(feature = "security")]
   Authentication {
     participant: GuidPrefix,
@@ -399,7 +471,8 @@ pub struct EndpointDescription {
 
 #[derive(Debug, Clone)]
 pub enum DataReaderStatus {
-  /// Sample was rejected, because resource limits would have been exceeded.
+  /// Sample was rejected, because resource limits would have been exceeded,
+  /// or its payload checksum did not match.
   SampleRejected {
     count: CountWithChange,
     last_reason: SampleRejectedStatusKind,
@@ -449,6 +522,29 @@ pub enum DataReaderStatus {
     writer: GUID,
     // last_publication_key:
   },
+
+  /// RustDDS extension: A matched DataWriter has restarted its sequence
+  /// numbering, e.g. because its process was restarted and it reappeared
+  /// with the same GUID. `policy` tells how the restart was handled, as
+  /// configured by the [`WriterRestart`](crate::policy::WriterRestart) QoS
+  /// policy of this DataReader.
+  WriterRestarted {
+    count: CountWithChange,
+    writer: GUID,
+    policy: WriterRestart,
+  },
+
+  /// RustDDS extension: A matched DataWriter has violated the RTPS protocol
+  /// too often, and is ignored for `duration`, as configured by the
+  /// [`WriterQuarantine`](crate::policy::WriterQuarantine) QoS policy of this
+  /// DataReader. `last_misbehavior` is the violation that started the
+  /// quarantine.
+  WriterQuarantined {
+    count: CountWithChange,
+    writer: GUID,
+    last_misbehavior: WriterMisbehavior,
+    duration: Duration,
+  },
 }
 
 #[derive(Debug, Clone)]
@@ -474,6 +570,22 @@ pub enum DataWriterStatus {
     reader: GUID,
     // last_subscription_key:
   },
+
+  /// RustDDS extension: The number of samples not yet acknowledged by all
+  /// matched reliable DataReaders dropped below the threshold set with
+  /// [`DataWriter::set_unacknowledged_threshold`](crate::with_key::DataWriter::set_unacknowledged_threshold).
+  UnacknowledgedBelowThreshold {
+    unacknowledged: u64,
+    threshold: u64,
+  },
+
+  /// RustDDS extension: The DataWriter got its first matched DataReader, or
+  /// lost its last one. Applications can use this to pause producing data
+  /// while there are no DataReaders. See also
+  /// [`WriterInitialization`](crate::policy::WriterInitialization).
+  ReaderPresenceChanged {
+    has_readers: bool,
+  },
 }
 
 /// Helper to contain same count actions across statuses
@@ -523,6 +635,27 @@ pub enum SampleRejectedStatusKind {
   ByInstancesLimit,
   BySamplesLimit,
   BySamplesPerInstanceLimit,
+  /// The sample did not match the checksum that the DataWriter sent. See
+  /// [`PayloadChecksum`](crate::policy::PayloadChecksum).
+  ByPayloadChecksum,
+}
+
+/// Protocol violations of a DataWriter, as detected by a DataReader. See
+/// [`WriterQuarantine`](crate::policy::WriterQuarantine).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriterMisbehavior {
+  /// HEARTBEAT with a first sequence number that is zero or negative, or
+  /// greater than the last sequence number plus one
+  InvalidHeartbeatRange,
+  /// HEARTBEAT announcing more sequence numbers than any writer history can
+  /// hold
+  ExcessiveHeartbeatRange,
+  /// HEARTBEAT moving the first available sequence number backwards
+  SequenceNumberRegression,
+  /// GAP with a start or set base that is zero or negative
+  InvalidGap,
+  /// DATA or DATAFRAG with a sequence number that is zero or negative
+  InvalidSequenceNumber,
 }
 
 /* commented out for now, as it is not used.
@@ -0,0 +1,74 @@
+//! RTPS over TCP, for networks where UDP does not get through.
+//!
+//! Firewalls often block UDP multicast, and the ephemeral UDP ports that
+//! RTPS uses, but let TCP connections to a few known ports through. With
+//! [`DomainParticipantBuilder::tcp_transport`](crate::DomainParticipantBuilder::tcp_transport),
+//! a DomainParticipant listens for TCP connections on one port, and
+//! advertises TCP locators instead of its UDP unicast locators. Remote
+//! RustDDS participants then send all unicast traffic to it over TCP,
+//! including SEDP discovery. SPDP announcements are sent over TCP to the
+//! configured [`peers`](TcpTransportSettings::peers), in addition to
+//! multicast, unless multicast is disabled with
+//! [`DiscoveryNetworkSettings`](crate::DiscoveryNetworkSettings).
+//!
+//! Connections are opened when there is something to send, and kept open. A
+//! lost connection is opened again at the next send, but not more often than
+//! [`reconnect_interval`](TcpTransportSettings::reconnect_interval). Messages
+//! sent in the meantime are dropped, and reliable Writers repair the loss as
+//! they would over UDP.
+//!
+//! Each RTPS message is sent as a frame that begins with its length, as a
+//! 32-bit big-endian integer. Other DDS implementations frame RTPS over TCP
+//! differently, so this transport connects only RustDDS participants.
+//!
+//! ```
+//! use std::net::{Ipv4Addr, SocketAddr};
+//!
+//! use rustdds::{DiscoveryNetworkSettings, DomainParticipantBuilder, TcpTransportSettings};
+//!
+//! let tcp = TcpTransportSettings {
+//!   peers: vec![SocketAddr::new(Ipv4Addr::new(10, 0, 0, 2).into(), 7411)],
+//!   ..TcpTransportSettings::default()
+//! };
+//! let builder = DomainParticipantBuilder::new(0)
+//!   .discovery_network(DiscoveryNetworkSettings::unicast_only(vec![]))
+//!   .tcp_transport(tcp);
+//! ```
+
+use std::{net::SocketAddr, time::Duration};
+
+/// How a DomainParticipant uses TCP. See the [module documentation](self).
+///
+/// Set with
+/// [`DomainParticipantBuilder::tcp_transport`](crate::DomainParticipantBuilder::tcp_transport).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TcpTransportSettings {
+  /// Port of the TCP server. If `None`, the server listens to the same port
+  /// number as the participant's UDP user traffic unicast port, i.e. 7411 for
+  /// participant id 0 in domain 0. This is the port that remote participants
+  /// list in their [`peers`](Self::peers).
+  pub listen_port: Option<u16>,
+  /// TCP servers of remote participants that SPDP announcements are sent to.
+  pub peers: Vec<SocketAddr>,
+  /// Minimum time between attempts to connect to the same server.
+  pub reconnect_interval: Duration,
+  /// Largest RTPS message that is accepted from a connection. A connection
+  /// that sends a larger one is closed.
+  pub max_message_size: usize,
+}
+
+impl TcpTransportSettings {
+  pub const DEFAULT_RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+  pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+}
+
+impl Default for TcpTransportSettings {
+  fn default() -> Self {
+    Self {
+      listen_port: None,
+      peers: Vec::new(),
+      reconnect_interval: Self::DEFAULT_RECONNECT_INTERVAL,
+      max_message_size: Self::DEFAULT_MAX_MESSAGE_SIZE,
+    }
+  }
+}
@@ -0,0 +1,518 @@
+//! Hierarchical topic names and wildcard subscriptions.
+//!
+//! DDS topic names are flat, but a common convention is to use `/` as a level
+//! separator, e.g. `vehicle/17/status`. A [`TopicPattern`] selects topics by
+//! such names. Each level of the pattern is one of
+//!
+//! * a literal, which must match the level exactly,
+//! * `+`, which matches any single level, or
+//! * `#`, which matches all the remaining levels, including none. It may only
+//!   be the last level.
+//!
+//! `+` and `#` are not valid characters in DDS topic names, so they cannot be
+//! confused with literals.
+//!
+//! A [`WildcardSubscriber`] creates a DataReader for each discovered topic
+//! that matches its pattern, and drops the reader when the last remote
+//! writer of the topic is lost. Samples from all the readers are taken as one
+//! stream, each tagged with the name of its topic.
+//!
+//! A [`DiscoveryTopicFilter`] limits discovery to the topics that a
+//! participant is interested in. In a large system this saves the memory and
+//! processing of tracking the many remote endpoints of other topics.
+//!
+//! ```
+//! use rustdds::topic_namespace::{topic_path, TopicPattern};
+//!
+//! let pattern = TopicPattern::new("vehicle/+/status").unwrap();
+//! assert!(pattern.matches(&topic_path(["vehicle", "17", "status"])));
+//! assert!(!pattern.matches("vehicle/17/position"));
+//! assert!(TopicPattern::new("vehicle/#").unwrap().matches("vehicle"));
+//! ```
+
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fmt,
+};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  create_error_bad_parameter, create_error_dropped,
+  dds::{
+    adapters::with_key::{DefaultDecoder, DeserializerAdapter},
+    qos::QosPolicies,
+    readcondition::ReadCondition,
+    result::{CreateError, CreateResult, ReadResult},
+    statusevents::DomainParticipantStatusEvent,
+    topic::TopicKind,
+    with_key::{DataReader, DataSample},
+  },
+  structure::guid::GUID,
+  DomainParticipant, Keyed, Subscriber,
+};
+
+/// Separator between the levels of a hierarchical topic name.
+pub const LEVEL_SEPARATOR: char = '/';
+
+/// Joins levels into a hierarchical topic name.
+pub fn topic_path<I, S>(levels: I) -> String
+where
+  I: IntoIterator<Item = S>,
+  S: AsRef<str>,
+{
+  levels
+    .into_iter()
+    .map(|l| l.as_ref().to_string())
+    .collect::<Vec<_>>()
+    .join(&LEVEL_SEPARATOR.to_string())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Level {
+  Literal(String),
+  AnyOne,
+  AnyRest,
+}
+
+/// A topic name pattern with `+` and `#` wildcards. See the
+/// [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicPattern {
+  pattern: String,
+  levels: Vec<Level>,
+}
+
+impl TopicPattern {
+  /// Fails with `BadParameter`, if the pattern is empty, or a wildcard is not
+  /// a whole level, or `#` is not the last level.
+  pub fn new(pattern: &str) -> CreateResult<Self> {
+    if pattern.is_empty() {
+      return create_error_bad_parameter!("Empty topic pattern");
+    }
+    let mut levels = Vec::new();
+    let mut split = pattern.split(LEVEL_SEPARATOR).peekable();
+    while let Some(level) = split.next() {
+      let level = match level {
+        "+" => Level::AnyOne,
+        "#" if split.peek().is_none() => Level::AnyRest,
+        "#" => {
+          return create_error_bad_parameter!(
+            "Topic pattern {pattern:?}: '#' must be the last level"
+          )
+        }
+        l if l.contains(['+', '#']) => {
+          return create_error_bad_parameter!(
+            "Topic pattern {pattern:?}: a wildcard must be a whole level"
+          )
+        }
+        l => Level::Literal(l.to_string()),
+      };
+      levels.push(level);
+    }
+    Ok(Self {
+      pattern: pattern.to_string(),
+      levels,
+    })
+  }
+
+  pub fn matches(&self, topic_name: &str) -> bool {
+    let mut names = topic_name.split(LEVEL_SEPARATOR);
+    for level in &self.levels {
+      match (level, names.next()) {
+        (Level::AnyRest, _) => return true,
+        (Level::AnyOne, Some(_)) => {}
+        (Level::Literal(l), Some(name)) if l == name => {}
+        _ => return false,
+      }
+    }
+    names.next().is_none()
+  }
+
+  pub fn as_str(&self) -> &str {
+    &self.pattern
+  }
+}
+
+/// The topics whose endpoints are discovered. See the
+/// [module documentation](self).
+///
+/// A topic is allowed, if its name is one of the allowed names, starts with
+/// one of the allowed prefixes, or matches one of the allowed patterns.
+/// Set with
+/// [`DiscoveryNetworkSettings::topic_filter`](crate::DiscoveryNetworkSettings::topic_filter).
+///
+/// ```
+/// use rustdds::topic_namespace::{DiscoveryTopicFilter, TopicPattern};
+///
+/// let filter = DiscoveryTopicFilter::new()
+///   .allow_topic("rt/chatter")
+///   .allow_prefix("rt/robot1/")
+///   .allow_pattern(TopicPattern::new("rq/+/get_parameters").unwrap());
+/// assert!(filter.allows("rt/robot1/odom"));
+/// assert!(!filter.allows("rt/robot2/odom"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiscoveryTopicFilter {
+  topic_names: BTreeSet<String>,
+  prefixes: Vec<String>,
+  patterns: Vec<TopicPattern>,
+}
+
+impl DiscoveryTopicFilter {
+  /// A filter that allows no topics
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  #[must_use]
+  pub fn allow_topic(mut self, topic_name: impl Into<String>) -> Self {
+    self.topic_names.insert(topic_name.into());
+    self
+  }
+
+  #[must_use]
+  pub fn allow_prefix(mut self, prefix: impl Into<String>) -> Self {
+    self.prefixes.push(prefix.into());
+    self
+  }
+
+  #[must_use]
+  pub fn allow_pattern(mut self, pattern: TopicPattern) -> Self {
+    self.patterns.push(pattern);
+    self
+  }
+
+  pub fn allows(&self, topic_name: &str) -> bool {
+    self.topic_names.contains(topic_name)
+      || self.prefixes.iter().any(|p| topic_name.starts_with(p))
+      || self.patterns.iter().any(|p| p.matches(topic_name))
+  }
+}
+
+/// A sample taken by a [`WildcardSubscriber`], together with the name of the
+/// topic it was received from.
+pub struct TopicSample<D: Keyed> {
+  topic_name: String,
+  sample: DataSample<D>,
+}
+
+impl<D: Keyed> TopicSample<D> {
+  pub fn topic_name(&self) -> &str {
+    &self.topic_name
+  }
+
+  pub fn sample(&self) -> &DataSample<D> {
+    &self.sample
+  }
+
+  pub fn into_sample(self) -> DataSample<D> {
+    self.sample
+  }
+}
+
+impl<D> fmt::Debug for TopicSample<D>
+where
+  D: Keyed + fmt::Debug,
+  D::K: fmt::Debug,
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("TopicSample")
+      .field("topic_name", &self.topic_name)
+      .field("sample", &self.sample)
+      .finish()
+  }
+}
+
+struct MatchedTopic<D: Keyed + 'static, DA: DeserializerAdapter<D>> {
+  reader: DataReader<D, DA>,
+  // Remote writers known from status events. The reader is dropped, when
+  // this becomes empty.
+  writers: BTreeSet<GUID>,
+}
+
+/// Subscribes to all WITH_KEY topics whose name matches a [`TopicPattern`]
+/// and whose type name is the given one.
+///
+/// The subscriber learns about topics from
+/// [`DomainParticipantStatusEvent`]s, which the application passes to
+/// [`handle_status_event`](Self::handle_status_event), and from
+/// [`refresh`](Self::refresh), which looks at all topics discovered so far.
+pub struct WildcardSubscriber<D: Keyed + 'static, DA: DeserializerAdapter<D>> {
+  subscriber: Subscriber,
+  pattern: TopicPattern,
+  type_name: String,
+  qos: QosPolicies,
+  topics: BTreeMap<String, MatchedTopic<D, DA>>,
+}
+
+impl<D, DA> WildcardSubscriber<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+  /// The QoS policies are used for both the topics and the readers. No
+  /// readers are created before the first call to `refresh` or
+  /// `handle_status_event`.
+  pub fn new(
+    subscriber: &Subscriber,
+    pattern: TopicPattern,
+    type_name: &str,
+    qos: QosPolicies,
+  ) -> Self {
+    Self {
+      subscriber: subscriber.clone(),
+      pattern,
+      type_name: type_name.to_string(),
+      qos,
+      topics: BTreeMap::new(),
+    }
+  }
+
+  pub fn pattern(&self) -> &TopicPattern {
+    &self.pattern
+  }
+
+  /// Names of the topics that currently have a reader.
+  pub fn topic_names(&self) -> impl Iterator<Item = &str> {
+    self.topics.keys().map(String::as_str)
+  }
+
+  /// Creates readers for all matching topics discovered so far. Useful after
+  /// creating the subscriber, because earlier status events are not
+  /// repeated.
+  pub fn refresh(&mut self) -> CreateResult<()> {
+    for topic in self.participant()?.discovered_topics() {
+      self.topic_detected(topic.topic_name(), topic.type_name())?;
+    }
+    Ok(())
+  }
+
+  /// Updates the set of readers according to a participant status event.
+  /// Events not about matching topics are ignored.
+  pub fn handle_status_event(&mut self, event: &DomainParticipantStatusEvent) -> CreateResult<()> {
+    match event {
+      DomainParticipantStatusEvent::TopicDetected { name, type_name } => {
+        self.topic_detected(name, type_name)?;
+      }
+      DomainParticipantStatusEvent::WriterDetected { writer } => {
+        self.topic_detected(&writer.topic_name, &writer.type_name)?;
+        if let Some(topic) = self.topics.get_mut(&writer.topic_name) {
+          topic.writers.insert(writer.guid);
+        }
+      }
+      DomainParticipantStatusEvent::WriterLost { guid, .. } => {
+        let emptied = self.topics.iter_mut().find_map(|(name, topic)| {
+          (topic.writers.remove(guid) && topic.writers.is_empty()).then(|| name.clone())
+        });
+        if let Some(name) = emptied {
+          self.remove_topic(&name);
+        }
+      }
+      DomainParticipantStatusEvent::TopicLost { name } => {
+        self.remove_topic(name);
+      }
+      _ => {}
+    }
+    Ok(())
+  }
+
+  fn topic_detected(&mut self, name: &str, type_name: &str) -> CreateResult<()> {
+    if self.topics.contains_key(name) || !self.pattern.matches(name) {
+      return Ok(());
+    }
+    if type_name != self.type_name {
+      debug!(
+        "Topic {name:?} matches {:?}, but has type {type_name:?}. Expected {:?}.",
+        self.pattern.as_str(),
+        self.type_name
+      );
+      return Ok(());
+    }
+    let topic = self.participant()?.create_topic(
+      name.to_string(),
+      self.type_name.clone(),
+      &self.qos,
+      TopicKind::WithKey,
+    )?;
+    let reader = self
+      .subscriber
+      .create_datareader::<D, DA>(&topic, Some(self.qos.clone()))?;
+    info!(
+      "Wildcard subscription {:?}: reading topic {name:?}",
+      self.pattern.as_str()
+    );
+    self.topics.insert(
+      name.to_string(),
+      MatchedTopic {
+        reader,
+        writers: BTreeSet::new(),
+      },
+    );
+    Ok(())
+  }
+
+  fn participant(&self) -> CreateResult<DomainParticipant> {
+    match self.subscriber.participant() {
+      Some(dp) => Ok(dp),
+      None => create_error_dropped!("DomainParticipant doesn't exist anymore."),
+    }
+  }
+
+  // Dropping the reader deletes it from the Subscriber.
+  fn remove_topic(&mut self, name: &str) {
+    if self.topics.remove(name).is_some() {
+      info!(
+        "Wildcard subscription {:?}: stopped reading topic {name:?}",
+        self.pattern.as_str()
+      );
+    }
+  }
+}
+
+impl<D, DA> WildcardSubscriber<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
+{
+  /// Takes up to `max_samples` samples from all the readers. Topics are
+  /// visited in name order.
+  pub fn take(&mut self, max_samples: usize) -> ReadResult<Vec<TopicSample<D>>> {
+    let mut result = Vec::new();
+    for (name, topic) in self.topics.iter_mut() {
+      let remaining = max_samples - result.len();
+      if remaining == 0 {
+        break;
+      }
+      result.extend(
+        topic
+          .reader
+          .take(remaining, ReadCondition::not_read())?
+          .into_iter()
+          .map(|sample| TopicSample {
+            topic_name: name.clone(),
+            sample,
+          }),
+      );
+    }
+    Ok(result)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::Utc;
+
+  use super::*;
+  use crate::{
+    dds::statusevents::{EndpointDescription, LostReason},
+    structure::guid::EntityKind,
+    test::random_data::RandomData,
+    CDRDeserializerAdapter,
+  };
+
+  #[test]
+  fn topic_pattern_matching() {
+    let p = TopicPattern::new("vehicle/+/status").unwrap();
+    assert!(p.matches("vehicle/17/status"));
+    assert!(p.matches("vehicle//status"));
+    assert!(!p.matches("vehicle/17/status/extra"));
+    assert!(!p.matches("vehicle/status"));
+    assert!(!p.matches("car/17/status"));
+
+    let p = TopicPattern::new("vehicle/#").unwrap();
+    assert!(p.matches("vehicle"));
+    assert!(p.matches("vehicle/17"));
+    assert!(p.matches("vehicle/17/status"));
+    assert!(!p.matches("vehicles/17"));
+
+    let p = TopicPattern::new("#").unwrap();
+    assert!(p.matches("anything/at/all"));
+
+    let p = TopicPattern::new("plain").unwrap();
+    assert!(p.matches("plain"));
+    assert!(!p.matches("plain/more"));
+
+    assert_eq!(topic_path(["a", "b", "c"]), "a/b/c");
+  }
+
+  #[test]
+  fn malformed_topic_patterns() {
+    for bad in ["", "a/#/b", "a/b+", "#a", "a/+b/c"] {
+      assert!(
+        matches!(
+          TopicPattern::new(bad),
+          Err(CreateError::BadParameter { .. })
+        ),
+        "{bad:?} accepted"
+      );
+    }
+  }
+
+  #[test]
+  fn wildcard_subscriber_follows_status_events() {
+    let participant = DomainParticipant::new(0).expect("Failed to create participant");
+    let subscriber = participant
+      .create_subscriber(&QosPolicies::qos_none())
+      .unwrap();
+    let mut wildcard = WildcardSubscriber::<RandomData, CDRDeserializerAdapter<RandomData>>::new(
+      &subscriber,
+      TopicPattern::new("vehicle/+/status").unwrap(),
+      "RandomData",
+      QosPolicies::qos_none(),
+    );
+    let detected = |name: &str, type_name: &str| DomainParticipantStatusEvent::TopicDetected {
+      name: name.to_string(),
+      type_name: type_name.to_string(),
+    };
+
+    // Non-matching name or type: no reader
+    wildcard
+      .handle_status_event(&detected("vehicle/1/position", "RandomData"))
+      .unwrap();
+    wildcard
+      .handle_status_event(&detected("vehicle/1/status", "OtherData"))
+      .unwrap();
+    assert_eq!(wildcard.topic_names().count(), 0);
+
+    wildcard
+      .handle_status_event(&detected("vehicle/1/status", "RandomData"))
+      .unwrap();
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    wildcard
+      .handle_status_event(&DomainParticipantStatusEvent::WriterDetected {
+        writer: EndpointDescription {
+          updated_time: Utc::now(),
+          guid: writer_guid,
+          topic_name: "vehicle/2/status".to_string(),
+          type_name: "RandomData".to_string(),
+          qos: QosPolicies::qos_none(),
+        },
+      })
+      .unwrap();
+    assert_eq!(
+      wildcard.topic_names().collect::<Vec<_>>(),
+      ["vehicle/1/status", "vehicle/2/status"]
+    );
+
+    // Losing the only writer drops the reader of its topic
+    wildcard
+      .handle_status_event(&DomainParticipantStatusEvent::WriterLost {
+        guid: writer_guid,
+        reason: LostReason::Disposed,
+      })
+      .unwrap();
+    assert_eq!(
+      wildcard.topic_names().collect::<Vec<_>>(),
+      ["vehicle/1/status"]
+    );
+
+    wildcard
+      .handle_status_event(&DomainParticipantStatusEvent::TopicLost {
+        name: "vehicle/1/status".to_string(),
+      })
+      .unwrap();
+    assert_eq!(wildcard.topic_names().count(), 0);
+  }
+}
@@ -0,0 +1,154 @@
+//! Separate sockets for discovery and user traffic.
+//!
+//! A DomainParticipant sends its metatraffic, i.e. SPDP and SEDP discovery,
+//! the participant messages of the Liveliness policy and the other built-in
+//! topics, from the same sockets as the samples of its DataWriters. On a
+//! saturated link, heavy user traffic then fills the same OS send buffers and
+//! network queues, and discovery and liveliness messages are delayed or
+//! dropped, so that remote participants lose their leases.
+//!
+//! With
+//! [`DomainParticipantBuilder::traffic_isolation`](crate::DomainParticipantBuilder::traffic_isolation),
+//! metatraffic is sent from sockets of its own, and each class of traffic can
+//! have:
+//! * its own local interfaces, which replace the
+//!   [`interface_allowlist`](crate::DiscoveryNetworkSettings::interface_allowlist)
+//!   of the participant for that class,
+//! * its own DSCP marking, so that routers and switches can prioritize
+//!   metatraffic over user traffic, and
+//! * its own OS buffer sizes, which replace those of the
+//!   [`ResourceSettings`](crate::ResourceSettings).
+//!
+//! Metatraffic and user traffic are received on separate sockets in any case,
+//! as they have different ports. Settings that are not given fall back to
+//! those of the participant. The TCP transport, if in use, carries both
+//! classes over the same connections, and ignores these settings.
+//!
+//! ```
+//! use rustdds::{DomainParticipantBuilder, TrafficClassSettings, TrafficIsolationSettings};
+//!
+//! let isolation = TrafficIsolationSettings {
+//!   metatraffic: TrafficClassSettings {
+//!     // Expedited Forwarding
+//!     dscp: Some(46),
+//!     ..TrafficClassSettings::default()
+//!   },
+//!   user_traffic: TrafficClassSettings {
+//!     interface_allowlist: Some(vec!["10.1.0.0/16".parse().unwrap()]),
+//!     socket_send_buffer_size: Some(4 * 1024 * 1024),
+//!     ..TrafficClassSettings::default()
+//!   },
+//! };
+//! let builder = DomainParticipantBuilder::new(0).traffic_isolation(isolation);
+//! ```
+
+use crate::dds::{discovery_network::InterfaceSelector, resource_profile::ResourceSettings};
+
+/// How a DomainParticipant separates metatraffic from user traffic. See the
+/// [module documentation](self).
+///
+/// Set with
+/// [`DomainParticipantBuilder::traffic_isolation`](crate::DomainParticipantBuilder::traffic_isolation).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrafficIsolationSettings {
+  /// Discovery, liveliness and the other built-in topics
+  pub metatraffic: TrafficClassSettings,
+  /// Samples of the DataWriters and DataReaders of the application
+  pub user_traffic: TrafficClassSettings,
+}
+
+/// Sockets of one class of traffic. `None` falls back to the setting of the
+/// participant.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrafficClassSettings {
+  /// Local interfaces used for this class. Only their addresses are
+  /// advertised as locators of the class, and multicast is sent and received
+  /// on them only.
+  pub interface_allowlist: Option<Vec<InterfaceSelector>>,
+  /// Differentiated Services code point (0 to 63) of the sent datagrams, in
+  /// the IPv4 TOS or IPv6 Traffic Class field. `None` leaves the system
+  /// default, which is usually 0, i.e. best effort.
+  pub dscp: Option<u8>,
+  /// Size in bytes of the OS receive buffer (`SO_RCVBUF`) of each listening
+  /// socket of this class
+  pub socket_receive_buffer_size: Option<usize>,
+  /// Size in bytes of the OS send buffer (`SO_SNDBUF`) of each sending socket
+  /// of this class
+  pub socket_send_buffer_size: Option<usize>,
+}
+
+impl TrafficClassSettings {
+  pub const MAX_DSCP: u8 = 63;
+
+  // The allowlist of this class, or the one of the participant
+  pub(crate) fn interface_allowlist<'a>(
+    &'a self,
+    participant_allowlist: &'a [InterfaceSelector],
+  ) -> &'a [InterfaceSelector] {
+    self
+      .interface_allowlist
+      .as_deref()
+      .unwrap_or(participant_allowlist)
+  }
+
+  // The resource settings of the participant, with the socket buffer sizes of
+  // this class
+  pub(crate) fn resource_settings(
+    &self,
+    participant_settings: &ResourceSettings,
+  ) -> ResourceSettings {
+    ResourceSettings {
+      socket_receive_buffer_size: self
+        .socket_receive_buffer_size
+        .or(participant_settings.socket_receive_buffer_size),
+      socket_send_buffer_size: self
+        .socket_send_buffer_size
+        .or(participant_settings.socket_send_buffer_size),
+      ..*participant_settings
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn class_settings_fall_back_to_participant() {
+    let participant_allowlist: Vec<InterfaceSelector> = vec!["eth0".parse().unwrap()];
+    let participant_settings = ResourceSettings {
+      socket_receive_buffer_size: Some(1024),
+      socket_send_buffer_size: Some(2048),
+      ..ResourceSettings::default()
+    };
+
+    let unset = TrafficClassSettings::default();
+    assert_eq!(
+      unset.interface_allowlist(&participant_allowlist),
+      participant_allowlist.as_slice()
+    );
+    assert_eq!(
+      unset.resource_settings(&participant_settings),
+      participant_settings
+    );
+
+    let class_allowlist: Vec<InterfaceSelector> = vec!["10.0.0.0/8".parse().unwrap()];
+    let set = TrafficClassSettings {
+      interface_allowlist: Some(class_allowlist.clone()),
+      dscp: Some(46),
+      socket_receive_buffer_size: None,
+      socket_send_buffer_size: Some(8192),
+    };
+    assert_eq!(
+      set.interface_allowlist(&participant_allowlist),
+      class_allowlist.as_slice()
+    );
+    let settings = set.resource_settings(&participant_settings);
+    assert_eq!(settings.socket_receive_buffer_size, Some(1024));
+    assert_eq!(settings.socket_send_buffer_size, Some(8192));
+    assert_eq!(
+      settings.receive_buffer_size,
+      participant_settings.receive_buffer_size
+    );
+  }
+}
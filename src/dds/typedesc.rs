@@ -1,14 +1,38 @@
+use crate::xtypes::{TypeInformation, TypeObject};
+
 /// Description of the type of a [Topic](../struct.Topic.html)
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct TypeDesc {
   my_name: String, // this is a rather minimal implementation
-} // placeholders
+  type_object: Option<TypeObject>,
+}
 
 impl TypeDesc {
   pub fn new(my_name: String) -> Self {
-    Self { my_name }
+    Self {
+      my_name,
+      type_object: None,
+    }
   }
+
+  /// A type described also by a [`TypeObject`], which Discovery uses to
+  /// match readers and writers by their types.
+  pub fn with_type_object(my_name: String, type_object: TypeObject) -> Self {
+    Self {
+      my_name,
+      type_object: Some(type_object),
+    }
+  }
+
   pub fn name(&self) -> &str {
     &self.my_name
   }
+
+  pub fn type_object(&self) -> Option<&TypeObject> {
+    self.type_object.as_ref()
+  }
+
+  pub fn type_information(&self) -> Option<TypeInformation> {
+    self.type_object.as_ref().map(TypeInformation::of)
+  }
 }
@@ -0,0 +1,405 @@
+//! WaitSets and Conditions, as in Section 2.2.2.1.6 "WaitSet Class" of the
+//! DDS specification v1.4.
+//!
+//! A [`WaitSet`] blocks the calling thread until one of the [`Condition`]s
+//! attached to it is triggered, or a timeout expires. This is the portable
+//! alternative to polling the DataReaders and DataWriters with mio or async.
+//!
+//! * A [`GuardCondition`] is triggered by the application, e.g. to wake up a
+//!   waiting thread from another thread.
+//! * A [`StatusCondition`] of a DataReader or DataWriter is triggered while
+//!   some of its enabled communication statuses have changed. Get it with
+//!   [`Entity::get_statuscondition`](crate::communication_status::Entity::get_statuscondition).
+//!   The [`DataAvailable`](StatusKind::DataAvailable) status of a DataReader
+//!   is set when it receives samples, and reset when it reads or takes.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use rustdds::{communication_status::*, waitset::*, *};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug)]
+//! struct SomeType {
+//!   a: i32,
+//! }
+//!
+//! let participant = DomainParticipant::new(0).unwrap();
+//! let qos = QosPolicyBuilder::new().build();
+//! let topic = participant
+//!   .create_topic(
+//!     "some_topic".to_string(),
+//!     "SomeType".to_string(),
+//!     &qos,
+//!     TopicKind::NoKey,
+//!   )
+//!   .unwrap();
+//! let subscriber = participant.create_subscriber(&qos).unwrap();
+//! let reader = subscriber
+//!   .create_datareader_no_key_cdr::<SomeType>(&topic, None)
+//!   .unwrap();
+//!
+//! let data_available = reader.get_statuscondition();
+//! data_available.set_enabled_statuses(StatusKind::DataAvailable.into());
+//! let stop = GuardCondition::new();
+//!
+//! let mut waitset = WaitSet::new();
+//! let data_id = waitset.attach_condition(data_available);
+//! let stop_id = waitset.attach_condition(stop.clone());
+//!
+//! stop.set_trigger_value(true);
+//! let triggered = waitset.wait(Duration::from_secs(1)).unwrap();
+//! assert_eq!(triggered, vec![stop_id]);
+//!
+//! waitset.detach_condition(data_id);
+//! ```
+
+use std::{
+  collections::BTreeMap,
+  fmt,
+  sync::{
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    Arc, Mutex,
+  },
+  task::Waker,
+  thread::{self, ThreadId},
+  time::{Duration, Instant},
+};
+
+use crate::dds::{
+  communication_status::{StatusKind, StatusMask},
+  helpers::current_thread_waker,
+  result::{WaitError, WaitResult},
+};
+
+/// Something a [`WaitSet`] can wait for.
+pub trait Condition: Send + Sync {
+  /// Is the condition triggered now.
+  fn get_trigger_value(&self) -> bool;
+
+  /// Wakes the `waker` once, when the trigger value may have become true.
+  /// Spurious wakeups are allowed.
+  fn register_waker(&self, waker: &Waker);
+}
+
+/// Identifies a [`Condition`] attached to a [`WaitSet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConditionId(u64);
+
+/// Blocks until some of the attached [`Condition`]s is triggered.
+///
+/// See the [module documentation](crate::waitset).
+pub struct WaitSet {
+  conditions: BTreeMap<ConditionId, Box<dyn Condition>>,
+  next_id: u64,
+  // The waker of the waiting thread is registered to the conditions on each
+  // wait, so it is reused to avoid registering duplicates.
+  waker: Option<(ThreadId, Waker)>,
+}
+
+impl WaitSet {
+  pub fn new() -> Self {
+    Self {
+      conditions: BTreeMap::new(),
+      next_id: 0,
+      waker: None,
+    }
+  }
+
+  /// Attaches a condition. The returned id identifies it in the results of
+  /// [`wait`](Self::wait).
+  pub fn attach_condition(&mut self, condition: impl Condition + 'static) -> ConditionId {
+    let id = ConditionId(self.next_id);
+    self.next_id += 1;
+    self.conditions.insert(id, Box::new(condition));
+    id
+  }
+
+  /// Detaches a condition. Returns false if it was not attached.
+  pub fn detach_condition(&mut self, id: ConditionId) -> bool {
+    self.conditions.remove(&id).is_some()
+  }
+
+  /// Ids of the attached conditions
+  pub fn conditions(&self) -> Vec<ConditionId> {
+    self.conditions.keys().copied().collect()
+  }
+
+  /// Blocks until some of the attached conditions is triggered, and returns
+  /// the ids of the triggered conditions. Returns immediately if some
+  /// already is. Returns [`WaitError::Timeout`] if none is triggered within
+  /// `timeout`.
+  pub fn wait(&mut self, timeout: Duration) -> WaitResult<Vec<ConditionId>> {
+    let deadline = Instant::now() + timeout;
+    let waker = self.thread_waker();
+    loop {
+      // Register before checking, so that triggers in between wake us up.
+      for condition in self.conditions.values() {
+        condition.register_waker(&waker);
+      }
+      let triggered: Vec<ConditionId> = self
+        .conditions
+        .iter()
+        .filter(|(_, condition)| condition.get_trigger_value())
+        .map(|(id, _)| *id)
+        .collect();
+      if !triggered.is_empty() {
+        return Ok(triggered);
+      }
+      let remaining = deadline.saturating_duration_since(Instant::now());
+      if remaining.is_zero() {
+        return Err(WaitError::Timeout);
+      }
+      thread::park_timeout(remaining);
+    }
+  }
+
+  fn thread_waker(&mut self) -> Waker {
+    let current = thread::current().id();
+    match &self.waker {
+      Some((thread_id, waker)) if *thread_id == current => waker.clone(),
+      _ => {
+        let waker = current_thread_waker();
+        self.waker = Some((current, waker.clone()));
+        waker
+      }
+    }
+  }
+}
+
+impl Default for WaitSet {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl fmt::Debug for WaitSet {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("WaitSet")
+      .field("conditions", &self.conditions.keys())
+      .finish()
+  }
+}
+
+// Wakers of the WaitSets waiting for a condition. Each is woken once.
+#[derive(Debug, Default)]
+pub(crate) struct ConditionWakers {
+  wakers: Mutex<Vec<Waker>>,
+}
+
+impl ConditionWakers {
+  pub fn register(&self, waker: &Waker) {
+    let mut wakers = self.wakers.lock().unwrap();
+    if !wakers.iter().any(|w| w.will_wake(waker)) {
+      wakers.push(waker.clone());
+    }
+  }
+
+  pub fn wake_all(&self) {
+    let wakers = std::mem::take(&mut *self.wakers.lock().unwrap());
+    for waker in wakers {
+      waker.wake();
+    }
+  }
+}
+
+#[derive(Debug, Default)]
+struct GuardConditionInner {
+  trigger_value: AtomicBool,
+  wakers: ConditionWakers,
+}
+
+/// A condition triggered by the application. Clones share the trigger value.
+#[derive(Debug, Clone, Default)]
+pub struct GuardCondition {
+  inner: Arc<GuardConditionInner>,
+}
+
+impl GuardCondition {
+  /// A new condition, not triggered
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the trigger value. Setting it to true wakes up the WaitSets this
+  /// condition is attached to.
+  pub fn set_trigger_value(&self, value: bool) {
+    self.inner.trigger_value.store(value, Ordering::Release);
+    if value {
+      self.inner.wakers.wake_all();
+    }
+  }
+}
+
+impl Condition for GuardCondition {
+  fn get_trigger_value(&self) -> bool {
+    self.inner.trigger_value.load(Ordering::Acquire)
+  }
+
+  fn register_waker(&self, waker: &Waker) {
+    self.inner.wakers.register(waker);
+  }
+}
+
+// The communication statuses of an entity, as seen by its StatusConditions
+pub(crate) trait StatusRecord: Send + Sync {
+  fn changes(&self) -> StatusMask;
+  fn register_waker(&self, waker: &Waker);
+}
+
+/// A condition triggered while some of the enabled communication statuses of
+/// an entity have changed, i.e. are included in
+/// [`Entity::get_status_changes`](crate::communication_status::Entity::get_status_changes).
+/// Getting a status resets its change, and so
+/// the trigger.
+///
+/// All statuses are enabled by default. Clones share the enabled statuses.
+#[derive(Clone)]
+pub struct StatusCondition {
+  record: Arc<dyn StatusRecord>,
+  enabled_statuses: Arc<AtomicU32>,
+}
+
+impl StatusCondition {
+  pub(crate) fn new(record: Arc<dyn StatusRecord>) -> Self {
+    Self {
+      record,
+      enabled_statuses: Arc::new(AtomicU32::new(StatusMask::all().bits())),
+    }
+  }
+
+  pub fn get_enabled_statuses(&self) -> StatusMask {
+    StatusMask::from_bits_truncate(self.enabled_statuses.load(Ordering::Acquire))
+  }
+
+  pub fn set_enabled_statuses(&self, mask: StatusMask) {
+    self.enabled_statuses.store(mask.bits(), Ordering::Release);
+  }
+
+  /// Is the status changed and enabled
+  pub fn is_triggered_by(&self, kind: StatusKind) -> bool {
+    self.get_enabled_statuses().contains(kind) && self.record.changes().contains(kind)
+  }
+}
+
+impl Condition for StatusCondition {
+  fn get_trigger_value(&self) -> bool {
+    self
+      .record
+      .changes()
+      .intersects(self.get_enabled_statuses())
+  }
+
+  fn register_waker(&self, waker: &Waker) {
+    self.record.register_waker(waker);
+  }
+}
+
+impl fmt::Debug for StatusCondition {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("StatusCondition")
+      .field("enabled_statuses", &self.get_enabled_statuses())
+      .field("changes", &self.record.changes())
+      .finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde::{Deserialize, Serialize};
+
+  use super::*;
+  use crate::dds::{
+    communication_status::Entity,
+    participant::DomainParticipant,
+    qos::{policy::Reliability, QosPolicyBuilder},
+    topic::TopicKind,
+  };
+
+  #[test]
+  fn guard_condition_wakes_up_wait() {
+    let guard = GuardCondition::new();
+    let mut waitset = WaitSet::new();
+    let id = waitset.attach_condition(guard.clone());
+    assert!(matches!(
+      waitset.wait(Duration::from_millis(10)),
+      Err(WaitError::Timeout)
+    ));
+
+    let trigger = thread::spawn({
+      let guard = guard.clone();
+      move || {
+        thread::sleep(Duration::from_millis(50));
+        guard.set_trigger_value(true);
+      }
+    });
+    assert_eq!(waitset.wait(Duration::from_secs(5)).unwrap(), vec![id]);
+    trigger.join().unwrap();
+
+    guard.set_trigger_value(false);
+    assert!(waitset.detach_condition(id));
+    assert!(waitset.conditions().is_empty());
+  }
+
+  #[test]
+  fn status_condition_signals_data_available() {
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Sample {
+      a: i32,
+    }
+
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::BestEffort)
+      .build();
+    let topic = dp
+      .create_topic(
+        "waitset_test".to_string(),
+        "Sample".to_string(),
+        &qos,
+        TopicKind::NoKey,
+      )
+      .unwrap();
+    let publisher = dp.create_publisher(&qos).unwrap();
+    let subscriber = dp.create_subscriber(&qos).unwrap();
+    let writer = publisher
+      .create_datawriter_no_key_cdr::<Sample>(&topic, None)
+      .unwrap();
+    let mut reader = subscriber
+      .create_datareader_no_key_cdr::<Sample>(&topic, None)
+      .unwrap();
+
+    let condition = reader.get_statuscondition();
+    let mut waitset = WaitSet::new();
+    let id = waitset.attach_condition(condition.clone());
+
+    // BestEffort samples written before matching would be lost
+    condition.set_enabled_statuses(StatusKind::SubscriptionMatched.into());
+    assert_eq!(waitset.wait(Duration::from_secs(5)).unwrap(), vec![id]);
+    assert_eq!(reader.get_subscription_matched_status().current_count, 1);
+    // The writer sends only to the readers it has matched, too
+    let writer_condition = writer.get_statuscondition();
+    writer_condition.set_enabled_statuses(StatusKind::PublicationMatched.into());
+    let mut writer_waitset = WaitSet::new();
+    let writer_id = writer_waitset.attach_condition(writer_condition);
+    assert_eq!(
+      writer_waitset.wait(Duration::from_secs(5)).unwrap(),
+      vec![writer_id]
+    );
+
+    condition.set_enabled_statuses(StatusKind::DataAvailable.into());
+    writer.write(Sample { a: 1 }, None).unwrap();
+    // Local readers can be notified before the sample is readable, so wait
+    // again, if there was nothing to take.
+    loop {
+      assert_eq!(waitset.wait(Duration::from_secs(5)).unwrap(), vec![id]);
+      assert!(condition.is_triggered_by(StatusKind::DataAvailable));
+      // Taking resets the status
+      let sample = reader.take_next_sample().unwrap();
+      assert!(!condition.get_trigger_value());
+      if sample.is_some() {
+        break;
+      }
+    }
+  }
+}
@@ -3,8 +3,11 @@ use std::{
   pin::Pin,
   sync::{Arc, Mutex, MutexGuard},
   task::{Context, Poll},
+  thread,
+  time::{Duration as StdDuration, Instant},
 };
 
+use serde::Serialize;
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 use futures::stream::{FusedStream, Stream};
@@ -13,17 +16,32 @@ use super::datasample_cache::DataSampleCache;
 use crate::{
   dds::{
     adapters::with_key::{DefaultDecoder, *},
+    communication_status::{
+      Entity, LivelinessChangedStatus, RequestedDeadlineMissedStatus,
+      RequestedIncompatibleQosStatus, SampleLostStatus, SampleRejectedStatus, StatusMask,
+      SubscriptionMatchedStatus,
+    },
+    content_filter::ContentFilterFactory,
+    helpers::{current_thread_waker, next_timeout},
+    interceptor::ReadInterceptor,
     key::*,
+    listener::DataReaderListener,
+    loaned::LoanedSample,
     qos::*,
     readcondition::*,
-    result::ReadResult,
+    result::{CreateResult, ReadResult},
+    shutdown::ShutdownToken,
     statusevents::*,
-    with_key::{datasample::*, simpledatareader::*},
+    waitset::StatusCondition,
+    with_key::{datasample::*, instance_statistics::InstanceStatistics, simpledatareader::*},
     ReadError,
   },
   discovery::sedp_messages::PublicationBuiltinTopicData,
   serialization::CDRDeserializerAdapter,
-  structure::{duration::Duration, entity::RTPSEntity, guid::GUID, time::Timestamp},
+  structure::{
+    dds_cache::CacheStatistics, duration::Duration, entity::RTPSEntity, guid::GUID,
+    rpc::SampleIdentity, time::Timestamp,
+  },
 };
 
 /// Simplified type for CDR encoding
@@ -72,6 +90,10 @@ pub enum SelectByKey {
 pub struct DataReader<D: Keyed, DA: DeserializerAdapter<D> = CDRDeserializerAdapter<D>> {
   simple_data_reader: SimpleDataReader<D, DA>,
   datasample_cache: DataSampleCache<D>, // DataReader-local cache of deserialized samples
+  // Reused by take_into and read_with, so that they do not allocate per call.
+  selected_keys: Vec<(Timestamp, D::K)>,
+  // The instance last served by take_round_robin
+  round_robin_cursor: Option<D::K>,
 }
 
 impl<D: 'static, DA> DataReader<D, DA>
@@ -85,8 +107,127 @@ where
     Self {
       simple_data_reader,
       datasample_cache: dsc,
+      selected_keys: Vec::new(),
+      round_robin_cursor: None,
     }
   }
+
+  /// Registers an interceptor that is called for each received sample after
+  /// deserialization, before it is stored to the DataReader cache. Vetoed
+  /// samples are dropped, and are never seen by `read` or `take`.
+  ///
+  /// Interceptors are called in the order they were added. If one of them
+  /// vetoes the sample, the remaining interceptors are not called.
+  pub fn add_read_interceptor<I>(&self, interceptor: I)
+  where
+    I: ReadInterceptor<D> + 'static,
+  {
+    self.simple_data_reader.add_read_interceptor(interceptor);
+  }
+
+  /// Removes all interceptors added with
+  /// [`add_read_interceptor`](Self::add_read_interceptor).
+  pub fn clear_read_interceptors(&self) {
+    self.simple_data_reader.clear_read_interceptors();
+  }
+
+  /// Sets a content filter. Samples that do not pass are dropped before they
+  /// are stored to the DataReader cache. See
+  /// [`SimpleDataReader::set_content_filter`].
+  pub fn set_content_filter(
+    &self,
+    factory: &ContentFilterFactory<D>,
+    filter_expression: &str,
+    expression_parameters: Vec<String>,
+  ) -> CreateResult<()> {
+    self
+      .simple_data_reader
+      .set_content_filter(factory, filter_expression, expression_parameters)
+  }
+
+  pub(crate) fn set_named_content_filter(
+    &self,
+    content_filtered_topic_name: String,
+    factory: &ContentFilterFactory<D>,
+    filter_expression: &str,
+    expression_parameters: Vec<String>,
+  ) -> CreateResult<()> {
+    self.simple_data_reader.set_named_content_filter(
+      content_filtered_topic_name,
+      factory,
+      filter_expression,
+      expression_parameters,
+    )
+  }
+
+  /// Removes the filter set with [`set_content_filter`](Self::set_content_filter).
+  pub fn clear_content_filter(&self) -> CreateResult<()> {
+    self.simple_data_reader.clear_content_filter()
+  }
+
+  /// Statistics of evicting received samples from the cache, as configured by
+  /// the [`CacheWatermarks`](crate::policy::CacheWatermarks) QoS policy.
+  pub fn cache_statistics(&self) -> CacheStatistics {
+    self.simple_data_reader.cache_statistics()
+  }
+
+  /// Acknowledges samples that the application has processed, given by their
+  /// [`SampleInfo::sample_identity`](crate::SampleInfo::sample_identity).
+  /// The DataWriters of the samples learn that they need not deliver them
+  /// again. This does nothing, unless the DataReader has the
+  /// [`Acknowledgment`](crate::policy::Acknowledgment) QoS policy
+  /// `ApplicationExplicit`.
+  ///
+  /// Samples can be acknowledged in any order. Acknowledging a sample that
+  /// is already acknowledged, or that this DataReader has not received, has
+  /// no effect.
+  pub fn acknowledge(&self, samples: impl IntoIterator<Item = SampleIdentity>) -> ReadResult<()> {
+    self.simple_data_reader.acknowledge(samples)
+  }
+
+  /// The SAMPLE_REJECTED communication status. Resets its change count. See
+  /// [`communication_status`](crate::communication_status).
+  pub fn get_sample_rejected_status(&self) -> SampleRejectedStatus {
+    self.simple_data_reader.get_sample_rejected_status()
+  }
+
+  /// The LIVELINESS_CHANGED communication status. Resets its change counts.
+  pub fn get_liveliness_changed_status(&self) -> LivelinessChangedStatus {
+    self.simple_data_reader.get_liveliness_changed_status()
+  }
+
+  /// The REQUESTED_DEADLINE_MISSED communication status. Resets its change
+  /// count.
+  pub fn get_requested_deadline_missed_status(&self) -> RequestedDeadlineMissedStatus {
+    self
+      .simple_data_reader
+      .get_requested_deadline_missed_status()
+  }
+
+  /// The REQUESTED_INCOMPATIBLE_QOS communication status. Resets its change
+  /// count.
+  pub fn get_requested_incompatible_qos_status(&self) -> RequestedIncompatibleQosStatus {
+    self
+      .simple_data_reader
+      .get_requested_incompatible_qos_status()
+  }
+
+  /// The SAMPLE_LOST communication status. Resets its change count.
+  pub fn get_sample_lost_status(&self) -> SampleLostStatus {
+    self.simple_data_reader.get_sample_lost_status()
+  }
+
+  /// The SUBSCRIPTION_MATCHED communication status. Resets its change counts.
+  pub fn get_subscription_matched_status(&self) -> SubscriptionMatchedStatus {
+    self.simple_data_reader.get_subscription_matched_status()
+  }
+
+  /// Sets the listener that is called back as the communication statuses of
+  /// this DataReader change, replacing any previous one. `None` removes it.
+  /// See [`listener`](crate::listener).
+  pub fn set_listener(&self, listener: Option<Box<dyn DataReaderListener>>) {
+    self.simple_data_reader.set_listener(listener);
+  }
 }
 
 impl<D: 'static, DA> DataReader<D, DA>
@@ -103,6 +244,11 @@ where
         .datasample_cache
         .fill_from_deserialized_cache_change(dcc);
     }
+    // Samples of lost Writers were taken above, so that the instance state
+    // changes come after them.
+    for writer in self.simple_data_reader.take_lost_writers() {
+      self.datasample_cache.writer_lost(writer);
+    }
     Ok(())
   }
 
@@ -251,6 +397,186 @@ where
     Ok(result)
   }
 
+  /// Like [`take`](Self::take), but appends the samples to the given buffer
+  /// instead of returning a new `Vec`.
+  ///
+  /// Intended for high-rate consumers, which can keep one buffer and clear it
+  /// between calls. At most `max_samples` samples are appended. Returns the
+  /// number of samples appended.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let mut buffer = Vec::with_capacity(64);
+  /// // Wait for data to arrive...
+  ///
+  /// buffer.clear();
+  /// if let Ok(count) = data_reader.take_into(&mut buffer, 64, ReadCondition::not_read()) {
+  ///   for data in buffer.iter() {
+  ///     // do something
+  ///   }
+  /// }
+  /// ```
+  pub fn take_into(
+    &mut self,
+    buffer: &mut Vec<DataSample<D>>,
+    max_samples: usize,
+    read_condition: ReadCondition,
+  ) -> ReadResult<usize> {
+    self.take_with(max_samples, read_condition, |ds| buffer.push(ds))
+  }
+
+  // Common part of take_into for both with_key and no_key DataReaders.
+  pub(crate) fn take_with<F>(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+    f: F,
+  ) -> ReadResult<usize>
+  where
+    F: FnMut(DataSample<D>),
+  {
+    // Clear notification buffer. This must be done first to avoid race conditions.
+    self.drain_read_notifications();
+    self.fill_and_lock_local_datasample_cache()?;
+
+    let mut selected = std::mem::take(&mut self.selected_keys);
+    self
+      .datasample_cache
+      .select_keys_for_access_into(read_condition, &mut selected);
+    selected.truncate(max_samples);
+
+    self.datasample_cache.take_by_keys_with(&selected, f);
+
+    let count = selected.len();
+    selected.clear();
+    self.selected_keys = selected;
+    Ok(count)
+  }
+
+  /// Lends at most `max_samples` received samples in serialized form, to be
+  /// deserialized without copying their strings and byte sequences. See
+  /// [`LoanedSample`].
+  ///
+  /// The samples are consumed from the DataReader, as by `take`, but they do
+  /// not pass through its sample cache: samples already deserialized there
+  /// by an earlier `read` are not lent, and lent samples are not given again
+  /// by `read` or `take`. Disposals are skipped, and read interceptors and
+  /// the content filter are not applied.
+  pub fn read_loaned(&mut self, max_samples: usize) -> ReadResult<Vec<LoanedSample>> {
+    self.drain_read_notifications();
+
+    let mut loans = Vec::new();
+    while loans.len() < max_samples {
+      match self.simple_data_reader.try_take_one_sliced()? {
+        Some(slices) => loans.push(LoanedSample::new(slices)),
+        None => break,
+      }
+    }
+    Ok(loans)
+  }
+
+  /// Counts the instances that this DataReader knows by their state, and
+  /// lists the `most_recent` instances that last received a sample.
+  ///
+  /// Received samples are moved to the sample cache, but nothing is read or
+  /// taken.
+  pub fn instance_statistics(
+    &mut self,
+    most_recent: usize,
+  ) -> ReadResult<InstanceStatistics<D::K>> {
+    self.fill_and_lock_local_datasample_cache()?;
+    Ok(self.datasample_cache.instance_statistics(most_recent))
+  }
+
+  /// Like [`read`](Self::read), but passes each sample to `f` by reference
+  /// instead of collecting them to a new `Vec`.
+  ///
+  /// The samples are marked read, as in `read`. At most `max_samples` samples
+  /// are visited. Returns the number of samples visited.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::{DataReader, Sample};
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// // Wait for data to arrive...
+  ///
+  /// let mut sum = 0;
+  /// data_reader.read_with(10, ReadCondition::not_read(), |data| {
+  ///   if let Sample::Value(v) = data.value() {
+  ///     sum += v.a;
+  ///   }
+  /// });
+  /// ```
+  pub fn read_with<F>(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+    f: F,
+  ) -> ReadResult<usize>
+  where
+    F: FnMut(DataSample<&D>),
+  {
+    self.drain_read_notifications();
+    self.fill_and_lock_local_datasample_cache()?;
+
+    let mut selected = std::mem::take(&mut self.selected_keys);
+    self
+      .datasample_cache
+      .select_keys_for_access_into(read_condition, &mut selected);
+    selected.truncate(max_samples);
+
+    self.datasample_cache.read_by_keys_with(&selected, f);
+
+    let count = selected.len();
+    selected.clear();
+    self.selected_keys = selected;
+    Ok(count)
+  }
+
   /// Reads next unread sample
   ///
   /// # Examples
@@ -706,6 +1032,79 @@ where
     Ok(result)
   }
 
+  /// Takes samples of all instances fairly: up to `max_samples_per_instance`
+  /// samples of each instance, at most `max_samples` in total.
+  ///
+  /// The instances are served in key order, but each call continues from the
+  /// instance following the one served last by the previous call. This way a
+  /// single instance that receives samples at a high rate cannot starve the
+  /// processing of the others, even if `max_samples` is too small to serve all
+  /// instances in one call. This is like repeated calls to `take_instance`
+  /// with [`SelectByKey::Next`], but without the need to keep track of keys.
+  ///
+  /// The samples are returned grouped by instance, and in sequence number
+  /// order within each instance.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct RobotCommand { robot_id: i32 }
+  /// # impl Keyed for RobotCommand {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.robot_id
+  /// #   }
+  /// # }
+  ///
+  /// let topic = domain_participant.create_topic("robot_commands".to_string(), "RobotCommand".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<RobotCommand, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// // Wait for data to arrive...
+  ///
+  /// // At most 2 commands per robot, 100 in total
+  /// if let Ok(commands) = data_reader.take_round_robin(100, 2, ReadCondition::not_read()) {
+  ///   for command in commands.iter() {
+  ///     // do something
+  ///   }
+  /// }
+  /// ```
+  pub fn take_round_robin(
+    &mut self,
+    max_samples: usize,
+    max_samples_per_instance: usize,
+    read_condition: ReadCondition,
+  ) -> ReadResult<Vec<DataSample<D>>> {
+    // Clear notification buffer. This must be done first to avoid race conditions.
+    self.drain_read_notifications();
+
+    self.fill_and_lock_local_datasample_cache()?;
+
+    let (selected, last_served) = self.datasample_cache.select_round_robin_keys_for_access(
+      read_condition,
+      self.round_robin_cursor.as_ref(),
+      max_samples_per_instance,
+      max_samples,
+    );
+    if last_served.is_some() {
+      self.round_robin_cursor = last_served;
+    }
+
+    let result = self.take_by_keys(&selected);
+
+    Ok(result)
+  }
+
   /// Return values:
   /// true - got all historical data
   /// false - timeout before all historical data was received
@@ -731,18 +1130,251 @@ where
   pub fn async_bare_sample_stream(self) -> BareDataReaderStream<D, DA> {
     BareDataReaderStream {
       datareader: Arc::new(Mutex::new(self)),
+      shutdown: None,
     }
   }
 
   /// An async stream for reading the data samples.
   /// The resulting Stream can be used to get another stream of status events.
+  ///
+  /// Each item is a [`DataSample`], i.e. the deserialized sample together
+  /// with its [`SampleInfo`](crate::SampleInfo). Samples are
+  /// taken from the DataReader, so each of them is yielded only once.
+  ///
+  /// # Examples
+  ///
+  /// ```no_run
+  /// # use serde::{Serialize, Deserialize};
+  /// # use futures::StreamExt;
+  /// # use rustdds::*;
+  /// # use rustdds::with_key::{DataReader, Sample};
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  /// #
+  /// # async fn example(data_reader: DataReader<SomeType>) {
+  /// let mut sample_stream = data_reader.async_sample_stream();
+  /// while let Some(result) = sample_stream.next().await {
+  ///   match result {
+  ///     Ok(sample) => {
+  ///       let info = sample.sample_info();
+  ///       match sample.value() {
+  ///         Sample::Value(value) => println!("{} from {:?}", value.a, info.publication_handle()),
+  ///         Sample::Dispose(key) => println!("{key} disposed"),
+  ///       }
+  ///     }
+  ///     Err(e) => eprintln!("Read failed: {e:?}"),
+  ///   }
+  /// }
+  /// # }
+  /// ```
   pub fn async_sample_stream(self) -> DataReaderStream<D, DA> {
     DataReaderStream {
       datareader: Arc::new(Mutex::new(self)),
+      shutdown: None,
     }
   }
+
+  /// Blocks until there are samples that `read_condition` selects, or
+  /// `timeout` has passed. Returns whether there are such samples. The
+  /// samples are not read or taken.
+  ///
+  /// This lets a thread wait for data without mio or an async runtime, as
+  /// with a ReadCondition attached to a WaitSet in the DDS specification.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use std::time::Duration;
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// # let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType { a: i32 }
+  /// # impl Keyed for SomeType {
+  /// #   type K = i32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.a
+  /// #   }
+  /// # }
+  /// #
+  /// # let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<SomeType, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// if data_reader.wait_for_samples(ReadCondition::not_read(), Duration::from_millis(10)).unwrap() {
+  ///   let data = data_reader.take(10, ReadCondition::not_read());
+  /// }
+  /// ```
+  pub fn wait_for_samples(
+    &mut self,
+    read_condition: ReadCondition,
+    timeout: StdDuration,
+  ) -> ReadResult<bool> {
+    self.wait_for_samples_matching(read_condition, |_| true, timeout)
+  }
+
+  // Common part of the waits of with_key and no_key DataReaders. Samples with
+  // data must also pass the query.
+  pub(crate) fn wait_for_samples_matching<Q>(
+    &mut self,
+    read_condition: ReadCondition,
+    query: Q,
+    timeout: StdDuration,
+  ) -> ReadResult<bool>
+  where
+    Q: Fn(&D) -> bool,
+  {
+    let deadline = Instant::now() + timeout;
+    let waker = current_thread_waker();
+    let result = loop {
+      // The waker is set before looking at the cache, so that samples arriving
+      // in between wake us up. It is taken on each wakeup.
+      self.simple_data_reader.set_waker(Some(waker.clone()));
+      if let Err(e) = self.fill_and_lock_local_datasample_cache() {
+        break Err(e);
+      }
+      if self
+        .datasample_cache
+        .has_samples_for_query(read_condition, &query)
+      {
+        break Ok(true);
+      }
+      let remaining = deadline.saturating_duration_since(Instant::now());
+      if remaining.is_zero() {
+        break Ok(false);
+      }
+      thread::park_timeout(remaining);
+    };
+    self.simple_data_reader.set_waker(None);
+    result
+  }
+
+  // Common part of read_query for both with_key and no_key DataReaders.
+  pub(crate) fn read_matching<Q>(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+    query: Q,
+  ) -> ReadResult<Vec<DataSample<&D>>>
+  where
+    Q: Fn(&D) -> bool,
+  {
+    self.drain_read_notifications();
+    self.fill_and_lock_local_datasample_cache()?;
+
+    let mut selected = self
+      .datasample_cache
+      .select_keys_for_query(read_condition, query);
+    selected.truncate(max_samples);
+    Ok(self.datasample_cache.read_by_keys(&selected))
+  }
+
+  // Common part of take_query for both with_key and no_key DataReaders.
+  pub(crate) fn take_matching<Q>(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+    query: Q,
+  ) -> ReadResult<Vec<DataSample<D>>>
+  where
+    Q: Fn(&D) -> bool,
+  {
+    self.drain_read_notifications();
+    self.fill_and_lock_local_datasample_cache()?;
+
+    let mut selected = self
+      .datasample_cache
+      .select_keys_for_query(read_condition, query);
+    selected.truncate(max_samples);
+    Ok(self.take_by_keys(&selected))
+  }
 } // impl
 
+impl<D: 'static, DA> DataReader<D, DA>
+where
+  D: Keyed + Serialize,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
+{
+  /// Reads at most `max_samples` samples that the [`QueryCondition`]
+  /// selects. Samples that do not pass the query are not marked read.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::*;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// # let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// #[derive(Serialize, Deserialize)]
+  /// struct Reading { sensor: u32, value: f64 }
+  /// # impl Keyed for Reading {
+  /// #   type K = u32;
+  /// #
+  /// #   fn key(&self) -> Self::K {
+  /// #     self.sensor
+  /// #   }
+  /// # }
+  /// #
+  /// # let topic = domain_participant.create_topic("readings".to_string(), "Reading".to_string(), &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<Reading, CDRDeserializerAdapter<_>>(&topic, None).unwrap();
+  ///
+  /// let high = QueryCondition::new(ReadCondition::not_read(), "value > %0", vec!["100".to_string()]).unwrap();
+  /// let data = data_reader.read_query(10, &high);
+  /// ```
+  pub fn read_query(
+    &mut self,
+    max_samples: usize,
+    query_condition: &QueryCondition,
+  ) -> ReadResult<Vec<DataSample<&D>>> {
+    self.read_matching(max_samples, query_condition.read_condition(), |d| {
+      query_condition.matches(d)
+    })
+  }
+
+  /// Takes at most `max_samples` samples that the [`QueryCondition`]
+  /// selects.
+  pub fn take_query(
+    &mut self,
+    max_samples: usize,
+    query_condition: &QueryCondition,
+  ) -> ReadResult<Vec<DataSample<D>>> {
+    self.take_matching(max_samples, query_condition.read_condition(), |d| {
+      query_condition.matches(d)
+    })
+  }
+
+  /// Like [`wait_for_samples`](Self::wait_for_samples), but waits for samples
+  /// that the [`QueryCondition`] selects.
+  pub fn wait_for_query(
+    &mut self,
+    query_condition: &QueryCondition,
+    timeout: StdDuration,
+  ) -> ReadResult<bool> {
+    self.wait_for_samples_matching(
+      query_condition.read_condition(),
+      |d| query_condition.matches(d),
+      timeout,
+    )
+  }
+}
+
 // -------------------
 
 impl<D, DA> mio_06::Evented for DataReader<D, DA>
@@ -867,6 +1499,20 @@ where
   }
 }
 
+impl<D, DA> Entity for DataReader<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D>,
+{
+  fn get_status_changes(&self) -> StatusMask {
+    self.simple_data_reader.get_status_changes()
+  }
+
+  fn get_statuscondition(&self) -> StatusCondition {
+    self.simple_data_reader.get_statuscondition()
+  }
+}
+
 // ----------------------------------------------
 // ----------------------------------------------
 
@@ -877,6 +1523,7 @@ pub struct BareDataReaderStream<
   DA: DeserializerAdapter<D> + 'static = CDRDeserializerAdapter<D>,
 > {
   datareader: Arc<Mutex<DataReader<D, DA>>>,
+  shutdown: Option<ShutdownToken>,
 }
 
 impl<D, DA> BareDataReaderStream<D, DA>
@@ -888,8 +1535,25 @@ where
   pub fn async_event_stream(&self) -> DataReaderEventStream<D, DA> {
     DataReaderEventStream {
       datareader: Arc::clone(&self.datareader),
+      shutdown: self.shutdown.clone(),
     }
   }
+
+  /// End the stream, and the event streams made from it afterwards, when
+  /// `shutdown` is triggered. A pending `next()` then returns `None`
+  /// immediately.
+  pub fn until_shutdown(mut self, shutdown: &ShutdownToken) -> Self {
+    self.shutdown = Some(shutdown.clone());
+    self
+  }
+
+  fn is_shut_down(&self, cx: &mut Context<'_>) -> bool {
+    self
+      .shutdown
+      .as_ref()
+      .is_some_and(|shutdown| shutdown.poll_triggered(cx).is_ready())
+  }
+
   fn lock_datareader(&self) -> ReadResult<MutexGuard<DataReader<D, DA>>> {
     self.datareader.lock().map_err(|e| ReadError::Poisoned {
       reason: format!("BareDataReaderStream could not lock datareader: {e:?}"),
@@ -914,6 +1578,9 @@ where
 
   fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
     debug!("poll_next");
+    if self.is_shut_down(cx) {
+      return Poll::Ready(None);
+    }
     let mut datareader = match self.lock_datareader() {
       Ok(g) => g,
       Err(e) => return Poll::Ready(Some(Err(e))),
@@ -952,13 +1619,37 @@ where
   }
 }
 
+impl<D, DA> BareDataReaderStream<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
+{
+  /// Takes the next sample, as `next()` does, but waits at most `timeout`.
+  /// Returns `Ok(None)` if no sample arrives in time, or the stream has
+  /// ended. This needs no timer from the async runtime.
+  ///
+  /// Like `next()`, this is cancellation-safe: a sample is taken from the
+  /// DataReader only when the future completes, so dropping the future, e.g.
+  /// in `select!`, loses no samples.
+  pub async fn take_next_timeout(
+    &mut self,
+    timeout: StdDuration,
+  ) -> ReadResult<Option<Sample<D, D::K>>> {
+    next_timeout(self, timeout).await
+  }
+}
+
 impl<D, DA> FusedStream for BareDataReaderStream<D, DA>
 where
   D: Keyed + 'static,
   DA: DeserializerAdapter<D> + DefaultDecoder<D>,
 {
   fn is_terminated(&self) -> bool {
-    false // Never terminate. This means it is always valid to call poll_next().
+    // Until shutdown, it is always valid to call poll_next().
+    self
+      .shutdown
+      .as_ref()
+      .is_some_and(ShutdownToken::is_triggered)
   }
 }
 
@@ -969,6 +1660,7 @@ pub struct DataReaderStream<
   DA: DeserializerAdapter<D> + 'static = CDRDeserializerAdapter<D>,
 > {
   datareader: Arc<Mutex<DataReader<D, DA>>>,
+  shutdown: Option<ShutdownToken>,
 }
 
 impl<D, DA> DataReaderStream<D, DA>
@@ -980,8 +1672,25 @@ where
   pub fn async_event_stream(&self) -> DataReaderEventStream<D, DA> {
     DataReaderEventStream {
       datareader: Arc::clone(&self.datareader),
+      shutdown: self.shutdown.clone(),
     }
   }
+
+  /// End the stream, and the event streams made from it afterwards, when
+  /// `shutdown` is triggered. A pending `next()` then returns `None`
+  /// immediately.
+  pub fn until_shutdown(mut self, shutdown: &ShutdownToken) -> Self {
+    self.shutdown = Some(shutdown.clone());
+    self
+  }
+
+  fn is_shut_down(&self, cx: &mut Context<'_>) -> bool {
+    self
+      .shutdown
+      .as_ref()
+      .is_some_and(|shutdown| shutdown.poll_triggered(cx).is_ready())
+  }
+
   fn lock_datareader(&self) -> ReadResult<MutexGuard<DataReader<D, DA>>> {
     self.datareader.lock().map_err(|e| ReadError::Poisoned {
       reason: format!("DataReaderStream could not lock datareader: {e:?}"),
@@ -1006,6 +1715,9 @@ where
 
   fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
     debug!("poll_next");
+    if self.is_shut_down(cx) {
+      return Poll::Ready(None);
+    }
     let mut datareader = match self.lock_datareader() {
       Ok(g) => g,
       Err(e) => return Poll::Ready(Some(Err(e))),
@@ -1044,13 +1756,37 @@ where
   }
 }
 
+impl<D, DA> DataReaderStream<D, DA>
+where
+  D: Keyed + 'static,
+  DA: DeserializerAdapter<D> + DefaultDecoder<D>,
+{
+  /// Takes the next sample, as `next()` does, but waits at most `timeout`.
+  /// Returns `Ok(None)` if no sample arrives in time, or the stream has
+  /// ended. This needs no timer from the async runtime.
+  ///
+  /// Like `next()`, this is cancellation-safe: a sample is taken from the
+  /// DataReader only when the future completes, so dropping the future, e.g.
+  /// in `select!`, loses no samples.
+  pub async fn take_next_timeout(
+    &mut self,
+    timeout: StdDuration,
+  ) -> ReadResult<Option<DataSample<D>>> {
+    next_timeout(self, timeout).await
+  }
+}
+
 impl<D, DA> FusedStream for DataReaderStream<D, DA>
 where
   D: Keyed + 'static,
   DA: DeserializerAdapter<D> + DefaultDecoder<D>,
 {
   fn is_terminated(&self) -> bool {
-    false // Never terminate. This means it is always valid to call poll_next().
+    // Until shutdown, it is always valid to call poll_next().
+    self
+      .shutdown
+      .as_ref()
+      .is_some_and(ShutdownToken::is_triggered)
   }
 }
 
@@ -1062,6 +1798,7 @@ pub struct DataReaderEventStream<
   DA: DeserializerAdapter<D> + 'static = CDRDeserializerAdapter<D>,
 > {
   datareader: Arc<Mutex<DataReader<D, DA>>>,
+  shutdown: Option<ShutdownToken>,
 }
 
 impl<D, DA> DataReaderEventStream<D, DA>
@@ -1069,6 +1806,13 @@ where
   D: Keyed + 'static,
   DA: DeserializerAdapter<D>,
 {
+  fn is_shut_down(&self, cx: &mut Context<'_>) -> bool {
+    self
+      .shutdown
+      .as_ref()
+      .is_some_and(|shutdown| shutdown.poll_triggered(cx).is_ready())
+  }
+
   fn lock_datareader(&self) -> ReadResult<MutexGuard<DataReader<D, DA>>> {
     self.datareader.lock().map_err(|e| ReadError::Poisoned {
       reason: format!("DataReaderEventStream could not lock datareader: {e:?}"),
@@ -1084,6 +1828,9 @@ where
   type Item = DataReaderStatus;
 
   fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    if self.is_shut_down(cx) {
+      return Poll::Ready(None);
+    }
     let datareader = match self.lock_datareader() {
       Ok(g) => g,
       Err(_e) => return Poll::Ready(None),
@@ -1102,7 +1849,11 @@ where
   DA: DeserializerAdapter<D>,
 {
   fn is_terminated(&self) -> bool {
-    false // Never terminate. This means it is always valid to call poll_next().
+    // Until shutdown, it is always valid to call poll_next().
+    self
+      .shutdown
+      .as_ref()
+      .is_some_and(ShutdownToken::is_triggered)
   }
 }
 
@@ -1118,9 +1869,10 @@ mod tests {
   use std::rc::Rc;
 
   use bytes::Bytes;
+  use futures::StreamExt;
   use mio_extras::channel as mio_channel;
   use log::info;
-  use byteorder::LittleEndian;
+  use speedy::Endianness;
 
   use super::*;
   use crate::{
@@ -1137,7 +1889,7 @@ mod tests {
       message_receiver::*,
       reader::{Reader, ReaderIngredients},
     },
-    serialization::to_vec,
+    serialization::to_vec_with_endianness,
     structure::{
       guid::{EntityId, EntityKind, GuidPrefix},
       sequence_number::SequenceNumber,
@@ -1199,6 +1951,7 @@ mod tests {
       data_reader_waker,
       poll_event_sender: notification_event_sender,
       security_plugins: None,
+      status_record: Arc::default(),
     };
 
     let mut reader = Reader::new(
@@ -1250,7 +2003,7 @@ mod tests {
         SerializedPayload {
           representation_identifier: RepresentationIdentifier::CDR_LE,
           representation_options: [0, 0],
-          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&test_data).unwrap()),
+          value: Bytes::from(to_vec_with_endianness(&test_data, Endianness::LittleEndian).unwrap()),
         }
         .into(),
       ),
@@ -1265,7 +2018,9 @@ mod tests {
         SerializedPayload {
           representation_identifier: RepresentationIdentifier::CDR_LE,
           representation_options: [0, 0],
-          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&test_data2).unwrap()),
+          value: Bytes::from(
+            to_vec_with_endianness(&test_data2, Endianness::LittleEndian).unwrap(),
+          ),
         }
         .into(),
       ),
@@ -1309,6 +2064,43 @@ mod tests {
     let result_vec2 = datareader.take(100, ReadCondition::any());
     assert!(result_vec2.is_ok());
     assert_eq!(result_vec2.unwrap().len(), 0);
+
+    // Test that the async stream takes the next sample, with its SampleInfo
+    let test_data3 = RandomData {
+      a: 12,
+      b: ":(((".to_string(),
+    };
+    let data_msg3 = Data {
+      reader_id: reader.entity_id(),
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::from(3),
+      serialized_payload: Some(
+        SerializedPayload {
+          representation_identifier: RepresentationIdentifier::CDR_LE,
+          representation_options: [0, 0],
+          value: Bytes::from(
+            to_vec_with_endianness(&test_data3, Endianness::LittleEndian).unwrap(),
+          ),
+        }
+        .into(),
+      ),
+      ..Data::default()
+    };
+    reader.handle_data_msg(data_msg3, data_flags, &mr_state);
+
+    let mut sample_stream = datareader.async_sample_stream();
+    let datasample3 = futures::executor::block_on(sample_stream.next())
+      .unwrap()
+      .unwrap();
+    assert_eq!(datasample3.sample_info().publication_handle(), writer_guid);
+    assert_eq!(datasample3.into_value().unwrap(), test_data3);
+
+    // Nothing more to take, so the timeout variant returns None
+    let started = Instant::now();
+    let next =
+      futures::executor::block_on(sample_stream.take_next_timeout(StdDuration::from_millis(100)));
+    assert!(matches!(next, Ok(None)));
+    assert!(started.elapsed() >= StdDuration::from_millis(100));
   }
 
   #[test]
@@ -1364,6 +2156,7 @@ mod tests {
       data_reader_waker,
       poll_event_sender: notification_event_sender,
       security_plugins: None,
+      status_record: Arc::default(),
     };
 
     let mut reader = Reader::new(
@@ -1431,7 +2224,7 @@ mod tests {
         SerializedPayload {
           representation_identifier: RepresentationIdentifier::CDR_LE,
           representation_options: [0, 0],
-          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&data_key1).unwrap()),
+          value: Bytes::from(to_vec_with_endianness(&data_key1, Endianness::LittleEndian).unwrap()),
         }
         .into(),
       ),
@@ -1445,7 +2238,9 @@ mod tests {
         SerializedPayload {
           representation_identifier: RepresentationIdentifier::CDR_LE,
           representation_options: [0, 0],
-          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&data_key2_1).unwrap()),
+          value: Bytes::from(
+            to_vec_with_endianness(&data_key2_1, Endianness::LittleEndian).unwrap(),
+          ),
         }
         .into(),
       ),
@@ -1459,7 +2254,9 @@ mod tests {
         SerializedPayload {
           representation_identifier: RepresentationIdentifier::CDR_LE,
           representation_options: [0, 0],
-          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&data_key2_2).unwrap()),
+          value: Bytes::from(
+            to_vec_with_endianness(&data_key2_2, Endianness::LittleEndian).unwrap(),
+          ),
         }
         .into(),
       ),
@@ -1473,7 +2270,9 @@ mod tests {
         SerializedPayload {
           representation_identifier: RepresentationIdentifier::CDR_LE,
           representation_options: [0, 0],
-          value: Bytes::from(to_vec::<RandomData, LittleEndian>(&data_key2_3).unwrap()),
+          value: Bytes::from(
+            to_vec_with_endianness(&data_key2_3, Endianness::LittleEndian).unwrap(),
+          ),
         }
         .into(),
       ),
@@ -1531,4 +2330,58 @@ mod tests {
     assert!(results.is_ok());
     assert!(results.unwrap().is_empty());
   }
+
+  #[test]
+  fn query_condition_selects_by_data() {
+    let dp = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicies::builder()
+      .reliability(policy::Reliability::Reliable {
+        max_blocking_time: crate::Duration::ZERO,
+      })
+      .history(policy::History::KeepAll)
+      .build();
+    let topic = dp
+      .create_topic(
+        "query_condition".to_string(),
+        "RandomData".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let publisher = dp.create_publisher(&qos).unwrap();
+    let writer = publisher
+      .create_datawriter_cdr::<RandomData>(&topic, None)
+      .unwrap();
+    let mut reader = dp
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_datareader_cdr::<RandomData>(&topic, None)
+      .unwrap();
+
+    let large =
+      QueryCondition::new(ReadCondition::not_read(), "a > %0", vec!["4".to_string()]).unwrap();
+    assert!(!reader
+      .wait_for_query(&large, StdDuration::from_millis(10))
+      .unwrap());
+
+    for a in [1, 5, 9] {
+      let data = RandomData {
+        a,
+        b: String::new(),
+      };
+      writer.write(data, None).unwrap();
+    }
+    // Samples of one Writer arrive in order, so all have arrived with the last
+    let last = QueryCondition::new(ReadCondition::any(), "a = 9", vec![]).unwrap();
+    assert!(reader
+      .wait_for_query(&last, StdDuration::from_secs(5))
+      .unwrap());
+    assert_eq!(reader.read_query(10, &large).unwrap().len(), 2);
+
+    // The large ones are read now, the small one is not
+    assert!(reader.read_query(10, &large).unwrap().is_empty());
+    let mut not_read = reader.take(10, ReadCondition::not_read()).unwrap();
+    assert_eq!(not_read.len(), 1);
+    assert_eq!(not_read.pop().unwrap().into_value().unwrap().a, 1);
+  }
 }
@@ -12,7 +12,10 @@ use crate::{
     qos::{policy, QosPolicies},
     readcondition::ReadCondition,
     sampleinfo::*,
-    with_key::datasample::{DataSample, DeserializedCacheChange, Sample},
+    with_key::{
+      datasample::{DataSample, DeserializedCacheChange, Sample},
+      instance_statistics::InstanceStatistics,
+    },
   },
   structure::{guid::GUID, sequence_number::SequenceNumber, time::Timestamp},
   with_key::WriteOptions,
@@ -39,8 +42,19 @@ pub(crate) struct InstanceMetaData {
   instance_state: InstanceState,         // latest known alive/not_alive state for this instance
   latest_generation_available: NotAliveGenerationCounts, // in this instance
   last_generation_accessed: NotAliveGenerationCounts, // in this instance
+  // Newest accepted write as (source timestamp, writer). Used only with
+  // DestinationOrder BY_SOURCE_TIMESTAMP.
+  latest_source_order: Option<(Timestamp, GUID)>,
+  // Writers of this instance that are still matched. When the last one is
+  // lost, the instance becomes NOT_ALIVE_NO_WRITERS.
+  writers: BTreeSet<GUID>,
+  // Receive time of the newest sample, for instance statistics
+  last_update: Timestamp,
 }
 
+// Samples selected from several instances, and the last instance served
+type RoundRobinSelection<K> = (Vec<(Timestamp, K)>, Option<K>);
+
 struct SampleWithMetaData<D: Keyed> {
   // a snapshot of the instance-wide counts
   // at the time this sample was received.
@@ -100,16 +114,88 @@ where
     sequence_number: SequenceNumber,
     receive_timestamp: Timestamp,
     write_options: WriteOptions,
+  ) {
+    let new_instance_state = match new_sample {
+      Sample::Value(_) => InstanceState::Alive,
+      Sample::Dispose(_) => InstanceState::NotAliveDisposed,
+    };
+    self.insert_sample(
+      new_sample,
+      new_instance_state,
+      writer_guid,
+      sequence_number,
+      receive_timestamp,
+      write_options,
+    );
+  }
+
+  // A remote Writer is no longer matched. Instances that have no other
+  // matched Writers become NOT_ALIVE_NO_WRITERS. Each of these gets a sample
+  // without data, so that the application notices the state change.
+  // Disposed instances stay disposed.
+  pub(crate) fn writer_lost(&mut self, writer_guid: GUID) {
+    let mut orphaned = Vec::new();
+    for (key, imd) in self.instance_map.iter_mut() {
+      if imd.writers.remove(&writer_guid)
+        && imd.writers.is_empty()
+        && imd.instance_state == InstanceState::Alive
+      {
+        orphaned.push(key.clone());
+      }
+    }
+
+    for key in orphaned {
+      // Use the sequence number of the Writer's last sample of the instance, so
+      // that the state change sorts after it.
+      let sequence_number = self.instance_map[&key]
+        .instance_samples
+        .iter()
+        .filter_map(|ts| self.datasamples.get(ts))
+        .filter(|s| s.writer_guid == writer_guid)
+        .map(|s| s.sequence_number)
+        .max()
+        .unwrap_or_else(SequenceNumber::zero);
+      debug!("An instance has no writers after losing {writer_guid:?}");
+      let receive_timestamp = self.next_local_timestamp();
+      self.insert_sample(
+        Sample::Dispose(key),
+        InstanceState::NotAliveNoWriters,
+        writer_guid,
+        sequence_number,
+        receive_timestamp,
+        WriteOptions::default(),
+      );
+    }
+  }
+
+  // Unique key for a locally generated sample, ordered after all the samples
+  // received so far.
+  fn next_local_timestamp(&self) -> Timestamp {
+    let now = Timestamp::now();
+    match self.datasamples.keys().next_back() {
+      Some(last) if *last >= now => Timestamp::from_ticks(last.to_ticks() + 1),
+      _ => now,
+    }
+  }
+
+  fn insert_sample(
+    &mut self,
+    new_sample: Sample<D, D::K>,
+    new_instance_state: InstanceState,
+    writer_guid: GUID,
+    sequence_number: SequenceNumber,
+    receive_timestamp: Timestamp,
+    write_options: WriteOptions,
   ) {
     let instance_key = match &new_sample {
       Sample::Value(d) => d.key(),
       Sample::Dispose(k) => k.clone(),
     };
 
-    let new_instance_state = match new_sample {
-      Sample::Value(_) => InstanceState::Alive,
-      Sample::Dispose(_) => InstanceState::NotAliveDisposed,
-    };
+    // A lost Writer must not be counted as a writer of the instance again.
+    let writer_is_alive = new_instance_state != InstanceState::NotAliveNoWriters;
+    let by_source_timestamp = writer_is_alive
+      && self.qos.destination_order() == Some(policy::DestinationOrder::BySourceTimeStamp);
 
     // find or create metadata record
     let instance_metadata = if let Some(imd) = self.instance_map.get_mut(&instance_key) {
@@ -122,6 +208,9 @@ where
         latest_generation_available: NotAliveGenerationCounts::zero(), /* this is new instance,
                                                                         * so start from zero */
         last_generation_accessed: NotAliveGenerationCounts::sub_zero(), // never accessed
+        latest_source_order: None,
+        writers: BTreeSet::new(),
+        last_update: receive_timestamp,
       };
       self.instance_map.insert(instance_key.clone(), imd);
       self
@@ -131,8 +220,36 @@ where
         .unwrap()
     };
 
+    if writer_is_alive {
+      instance_metadata.writers.insert(writer_guid);
+    }
+
+    if by_source_timestamp {
+      // Conflicting writes to an instance are resolved by source timestamp, and
+      // ties by writer GUID, so that all readers end up with the same final
+      // value regardless of reception order. A write older than the newest one
+      // already accepted is stale, and is dropped.
+      let source_order = (
+        write_options
+          .source_timestamp()
+          .unwrap_or(receive_timestamp),
+        writer_guid,
+      );
+      match instance_metadata.latest_source_order {
+        Some(latest) if source_order < latest => {
+          debug!(
+            "Dropping stale sample {sequence_number:?} from {writer_guid:?}: source order \
+             {source_order:?} is older than {latest:?}"
+          );
+          return;
+        }
+        _ => instance_metadata.latest_source_order = Some(source_order),
+      }
+    }
+
     // update instance metadata
     instance_metadata.instance_samples.insert(receive_timestamp);
+    instance_metadata.last_update = receive_timestamp;
 
     match (instance_metadata.instance_state, new_instance_state) {
       (InstanceState::Alive, _) => (), // was Alive, does not change counts
@@ -250,24 +367,69 @@ where
   // Samples are marked read or viewed only when "read" or "take" methods (below)
   // are called.
   pub fn select_keys_for_access(&self, rc: ReadCondition) -> Vec<(Timestamp, D::K)> {
+    let mut keys = Vec::new();
+    self.select_keys_for_access_into(rc, &mut keys);
+    keys
+  }
+
+  // Same as above, but reuses the given Vec. Previous contents are discarded.
+  pub fn select_keys_for_access_into(&self, rc: ReadCondition, keys: &mut Vec<(Timestamp, D::K)>) {
+    keys.clear();
+    keys.extend(self.datasamples.iter().filter_map(|(ts, dsm)| {
+      let key = dsm.key();
+      // Instance meta wouldn't be cleaned with samples belongs to it.
+      let instance_meta = self.instance_map.get(&key).unwrap();
+      if self.sample_selector(&rc, instance_meta, dsm) {
+        Some((*ts, key))
+      } else {
+        None
+      }
+    }));
+    self.sort_by_sequence_number(keys);
+  }
+
+  // Like select_keys_for_access, but samples with data are selected only if
+  // the data also passes the query. Samples without data pass it.
+  pub fn select_keys_for_query<Q>(&self, rc: ReadCondition, query: Q) -> Vec<(Timestamp, D::K)>
+  where
+    Q: Fn(&D) -> bool,
+  {
     let mut keys: Vec<(Timestamp, D::K)> = self
       .datasamples
       .iter()
+      .filter(|(_, dsm)| match &dsm.sample {
+        Sample::Value(d) => query(d),
+        Sample::Dispose(_) => true,
+      })
       .filter_map(|(ts, dsm)| {
         let key = dsm.key();
-        // Instance meta wouldn't be cleaned with samples belongs to it.
-        let instance_meta = self.instance_map.get(&key).unwrap();
-        if self.sample_selector(&rc, instance_meta, dsm) {
-          Some((*ts, key))
-        } else {
-          None
-        }
+        let instance_meta = self.instance_map.get(&key)?;
+        self
+          .sample_selector(&rc, instance_meta, dsm)
+          .then_some((*ts, key))
       })
       .collect();
     self.sort_by_sequence_number(&mut keys);
     keys
   }
 
+  // Is there any sample that select_keys_for_query would select
+  pub fn has_samples_for_query<Q>(&self, rc: ReadCondition, query: Q) -> bool
+  where
+    Q: Fn(&D) -> bool,
+  {
+    self.datasamples.values().any(|dsm| {
+      self
+        .instance_map
+        .get(&dsm.key())
+        .is_some_and(|imd| self.sample_selector(&rc, imd, dsm))
+        && match &dsm.sample {
+          Sample::Value(d) => query(d),
+          Sample::Dispose(_) => true,
+        }
+    })
+  }
+
   pub fn select_instance_keys_for_access(
     &self,
     instance: &D::K,
@@ -297,6 +459,41 @@ where
     }
   }
 
+  // Selects up to `per_instance` samples of each instance, visiting the
+  // instances in key order, starting after the instance `after` and wrapping
+  // around. At most `max_samples` samples are selected in total. Returns the
+  // selected samples grouped by instance, and the last instance that had any,
+  // so that the next selection can continue from there.
+  pub fn select_round_robin_keys_for_access(
+    &self,
+    rc: ReadCondition,
+    after: Option<&D::K>,
+    per_instance: usize,
+    max_samples: usize,
+  ) -> RoundRobinSelection<D::K> {
+    let mut instances: Vec<&D::K> = self.instance_map.keys().collect();
+    if let Some(after) = after {
+      let start = instances.partition_point(|k| *k <= after);
+      instances.rotate_left(start);
+    }
+
+    let mut keys = Vec::new();
+    let mut last_served = None;
+    for instance in instances {
+      let remaining = max_samples - keys.len();
+      if remaining == 0 {
+        break;
+      }
+      let mut instance_keys = self.select_instance_keys_for_access(instance, rc);
+      instance_keys.truncate(per_instance.min(remaining));
+      if !instance_keys.is_empty() {
+        keys.append(&mut instance_keys);
+        last_served = Some(instance.clone());
+      }
+    }
+    (keys, last_served)
+  }
+
   // select helper
   fn sample_selector(
     &self,
@@ -328,6 +525,7 @@ where
   }
 
   fn make_sample_info(
+    reception_timestamp: Timestamp,
     dswm: &SampleWithMetaData<D>,
     imd: &InstanceMetaData,
     sample_rank: usize,
@@ -353,6 +551,7 @@ where
       write_options: dswm.write_options.clone(),
       publication_handle: dswm.writer_guid,
       sequence_number: dswm.sequence_number,
+      reception_timestamp,
     }
   }
 
@@ -428,7 +627,8 @@ where
       let dswm = self.datasamples.get_mut(ts).unwrap();
       let imd = self.instance_map.get(key).unwrap();
 
-      let sample_info = Self::make_sample_info(dswm, imd, len - index - 1, mrs_total, mrsic_total);
+      let sample_info =
+        Self::make_sample_info(*ts, dswm, imd, len - index - 1, mrs_total, mrsic_total);
       dswm.sample_has_been_read = true; // mark as read
       Self::record_instance_generation_viewed(
         &mut instance_generations,
@@ -461,6 +661,61 @@ where
     result
   }
 
+  // Like read_by_keys, but hands the samples to `sink` one by one. The
+  // references are valid only during the call, so the samples need not be
+  // collected before the next one is marked read.
+  //
+  // Panics: Same conditions as read_by_keys.
+  pub(in crate::dds::with_key) fn read_by_keys_with<F>(
+    &mut self,
+    keys: &[(Timestamp, D::K)],
+    mut sink: F,
+  ) where
+    F: FnMut(DataSample<&D>),
+  {
+    let len = keys.len();
+
+    if len == 0 {
+      return;
+    }
+
+    let mut instance_generations: HashMap<D::K, NotAliveGenerationCounts> = HashMap::new();
+    let mrsic_total = self
+      .instance_map
+      .get(&keys.last().unwrap().1)
+      .unwrap()
+      .latest_generation_available
+      .total();
+    let mrs_total = self
+      .datasamples
+      .iter()
+      .next_back()
+      .unwrap()
+      .1
+      .generation_counts
+      .total();
+    for (index, (ts, key)) in keys.iter().enumerate() {
+      let dswm = self.datasamples.get_mut(ts).unwrap();
+      let imd = self.instance_map.get(key).unwrap();
+      let sample_info =
+        Self::make_sample_info(*ts, dswm, imd, len - index - 1, mrs_total, mrsic_total);
+      dswm.sample_has_been_read = true; // mark as read
+      Self::record_instance_generation_viewed(
+        &mut instance_generations,
+        dswm.generation_counts,
+        key,
+      );
+      sink(DataSample::new(
+        sample_info,
+        result_ok_as_ref_err_clone(&dswm.sample),
+      ));
+    }
+
+    // Instances are marked viewed only after all SampleInfos are made, as in
+    // read_by_keys.
+    self.mark_instances_viewed(&instance_generations);
+  }
+
   // Panics: `keys` must only contain (Timestamp,Key)-pairs that were immediately
   // before this call obtained by select_*_for_access functions. This function
   // will blindly assume that the given keys and timestamps are present in the
@@ -469,11 +724,26 @@ where
     &mut self,
     keys: &[(Timestamp, D::K)],
   ) -> Vec<DataSample<D>> {
+    let mut result = Vec::with_capacity(keys.len());
+    self.take_by_keys_with(keys, |ds| result.push(ds));
+    result
+  }
+
+  // Like take_by_keys, but hands the samples to `sink` one by one instead of
+  // collecting them.
+  //
+  // Panics: Same conditions as take_by_keys.
+  pub(in crate::dds::with_key) fn take_by_keys_with<F>(
+    &mut self,
+    keys: &[(Timestamp, D::K)],
+    mut sink: F,
+  ) where
+    F: FnMut(DataSample<D>),
+  {
     let len = keys.len();
-    let mut result = Vec::with_capacity(len);
 
     if len == 0 {
-      return result;
+      return;
     }
 
     let mut instance_generations: HashMap<D::K, NotAliveGenerationCounts> = HashMap::new();
@@ -495,7 +765,8 @@ where
     for (index, (ts, key)) in keys.iter().enumerate() {
       let dswm = self.datasamples.remove(ts).unwrap();
       let imd = self.instance_map.get(key).unwrap();
-      let sample_info = Self::make_sample_info(&dswm, imd, len - index - 1, mrs_total, mrsic_total);
+      let sample_info =
+        Self::make_sample_info(*ts, &dswm, imd, len - index - 1, mrs_total, mrsic_total);
       // dwsm.sample_has_been_read = true; // no need to mark read, as the dswm is
       // about to be destroyed
       Self::record_instance_generation_viewed(
@@ -503,11 +774,10 @@ where
         dswm.generation_counts,
         key,
       );
-      result.push(DataSample::new(sample_info, dswm.sample));
+      sink(DataSample::new(sample_info, dswm.sample));
     }
 
     self.mark_instances_viewed(&instance_generations);
-    result
   }
 
   // Panics: `keys` must only contain (Timestamp,Key)-pairs that were immediately
@@ -593,6 +863,19 @@ where
       .map(|(k, _)| k.clone())
       .next()
   }
+
+  pub(in crate::dds::with_key) fn instance_statistics(
+    &self,
+    most_recent: usize,
+  ) -> InstanceStatistics<D::K> {
+    InstanceStatistics::collect(
+      self
+        .instance_map
+        .iter()
+        .map(|(key, imd)| (key, imd.instance_state, imd.last_update)),
+      most_recent,
+    )
+  }
 }
 
 // helper function
@@ -606,13 +889,254 @@ pub(crate) fn result_ok_as_ref_err_clone<T, E: Clone>(r: &Sample<T, E>) -> Sampl
 
 #[cfg(test)]
 mod tests {
-  // use super::*;
-  // use crate::{
-  //   structure::{time::Timestamp},
-  // };
+  use super::*;
+  use crate::{
+    dds::qos::QosPolicyBuilder,
+    structure::guid::{EntityKind, GuidPrefix},
+    test::random_data::RandomData,
+    Duration,
+  };
   // use crate::dds::ddsdata::DDSData;
   // use crate::dds::traits::key::Keyed;
-  // use crate::test::random_data::*;
+
+  #[test]
+  fn dsc_by_source_timestamp_drops_stale_writes() {
+    let qos = QosPolicyBuilder::new()
+      .destination_order(policy::DestinationOrder::BySourceTimeStamp)
+      .history(policy::History::KeepAll)
+      .build();
+    let mut datasample_cache = DataSampleCache::<RandomData>::new(qos);
+
+    let writer_1 = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let mut writer_2 = writer_1;
+    writer_2.prefix = GuidPrefix::new(&[0xff; 12]);
+    let source_time = Timestamp::ZERO + Duration::from_secs(100);
+    let received = Timestamp::ZERO + Duration::from_secs(200);
+    let sample = |b: &str| {
+      Sample::Value(RandomData {
+        a: 1,
+        b: b.to_string(),
+      })
+    };
+
+    // (source timestamp, writer, payload), in reception order
+    let writes = [
+      (source_time, writer_1, "first"),
+      (source_time + Duration::from_secs(2), writer_1, "newest"),
+      (source_time + Duration::from_secs(1), writer_2, "late"), // stale, dropped
+      (source_time + Duration::from_secs(2), writer_2, "tie"),  // larger GUID wins the tie
+      (source_time + Duration::from_secs(2), writer_1, "lost"), // smaller GUID, dropped
+    ];
+    for (i, (source_timestamp, writer, b)) in writes.into_iter().enumerate() {
+      datasample_cache.add_sample(
+        sample(b),
+        writer,
+        SequenceNumber::from(i as i64 + 1),
+        received + Duration::from_millis(i as i64),
+        WriteOptions::from(Some(source_timestamp)),
+      );
+    }
+
+    let keys = datasample_cache.select_keys_for_access(ReadCondition::any());
+    let values: Vec<String> = datasample_cache
+      .read_by_keys(&keys)
+      .into_iter()
+      .map(|ds| ds.into_value().value().unwrap().b.clone())
+      .collect();
+    assert_eq!(values, vec!["first", "newest", "tie"]);
+  }
+
+  #[test]
+  fn dsc_instance_has_no_writers_after_last_writer_lost() {
+    let qos = QosPolicyBuilder::new()
+      .history(policy::History::KeepAll)
+      .build();
+    let mut datasample_cache = DataSampleCache::<RandomData>::new(qos);
+
+    let writer_1 = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let mut writer_2 = writer_1;
+    writer_2.prefix = GuidPrefix::new(&[0xff; 12]);
+    let received = Timestamp::ZERO + Duration::from_secs(200);
+    let write = |dsc: &mut DataSampleCache<RandomData>, n: i64, a: i64, writer: GUID| {
+      dsc.add_sample(
+        Sample::Value(RandomData {
+          a,
+          b: String::new(),
+        }),
+        writer,
+        SequenceNumber::from(n),
+        received + Duration::from_millis(n),
+        WriteOptions::default(),
+      );
+    };
+    // Instance 1 has two writers, instance 2 only one.
+    write(&mut datasample_cache, 1, 1, writer_1);
+    write(&mut datasample_cache, 2, 1, writer_2);
+    write(&mut datasample_cache, 3, 2, writer_1);
+
+    let instance_states = |dsc: &mut DataSampleCache<RandomData>| {
+      let keys = dsc.select_keys_for_access(ReadCondition::not_read());
+      dsc
+        .read_by_keys(&keys)
+        .into_iter()
+        .map(|ds| {
+          let is_value = matches!(ds.value(), Sample::Value(_));
+          (ds.key(), is_value, ds.sample_info().instance_state())
+        })
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(instance_states(&mut datasample_cache).len(), 3);
+
+    // Instance 1 is still written by writer 2.
+    datasample_cache.writer_lost(writer_1);
+    assert_eq!(
+      instance_states(&mut datasample_cache),
+      vec![(2, false, InstanceState::NotAliveNoWriters)]
+    );
+
+    datasample_cache.writer_lost(writer_2);
+    assert_eq!(
+      instance_states(&mut datasample_cache),
+      vec![(1, false, InstanceState::NotAliveNoWriters)]
+    );
+    // Losing a writer again changes nothing.
+    datasample_cache.writer_lost(writer_2);
+    assert!(instance_states(&mut datasample_cache).is_empty());
+
+    // A new write brings the instance back to life.
+    write(&mut datasample_cache, 4, 2, writer_2);
+    let keys = datasample_cache.select_keys_for_access(ReadCondition::not_read());
+    let samples = datasample_cache.read_by_keys(&keys);
+    assert_eq!(samples.len(), 1);
+    let info = samples[0].sample_info();
+    assert_eq!(info.instance_state(), InstanceState::Alive);
+    assert_eq!(info.no_writers_generation_count(), 1);
+
+    let statistics = datasample_cache.instance_statistics(1);
+    assert_eq!((statistics.alive, statistics.no_writers), (1, 1));
+    // The instance state change of instance 1 is newer than any received sample.
+    let (key, state, _) = statistics.recently_updated[0];
+    assert_eq!((key, state), (1, InstanceState::NotAliveNoWriters));
+  }
+
+  #[test]
+  fn dsc_read_and_take_with_sink() {
+    let qos = QosPolicyBuilder::new()
+      .history(policy::History::KeepAll)
+      .build();
+    let mut datasample_cache = DataSampleCache::<RandomData>::new(qos);
+    let writer = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    for a in 1..=3 {
+      datasample_cache.add_sample(
+        Sample::Value(RandomData {
+          a,
+          b: a.to_string(),
+        }),
+        writer,
+        SequenceNumber::from(a),
+        Timestamp::ZERO + Duration::from_secs(a as i32),
+        WriteOptions::default(),
+      );
+    }
+
+    // Previous contents of the key buffer are discarded
+    let mut keys = datasample_cache.select_keys_for_access(ReadCondition::any());
+    datasample_cache.select_keys_for_access_into(ReadCondition::not_read(), &mut keys);
+    assert_eq!(keys.len(), 3);
+    keys.truncate(2);
+
+    let mut read = Vec::new();
+    datasample_cache.read_by_keys_with(&keys, |ds| {
+      read.push((
+        ds.value().clone().unwrap().a,
+        ds.sample_info().sample_state(),
+      ));
+    });
+    assert_eq!(
+      read,
+      vec![(1, SampleState::NotRead), (2, SampleState::NotRead)]
+    );
+
+    datasample_cache.select_keys_for_access_into(ReadCondition::not_read(), &mut keys);
+    assert_eq!(keys.len(), 1);
+
+    let mut taken = Vec::with_capacity(3);
+    datasample_cache.select_keys_for_access_into(ReadCondition::any(), &mut keys);
+    datasample_cache.take_by_keys_with(&keys, |ds| taken.push(ds));
+    assert_eq!(taken.len(), 3);
+    assert_eq!(taken[0].sample_info().sample_state(), SampleState::Read);
+    assert_eq!(taken[2].sample_info().sample_state(), SampleState::NotRead);
+    assert!(datasample_cache
+      .select_keys_for_access(ReadCondition::any())
+      .is_empty());
+  }
+
+  #[test]
+  fn dsc_round_robin_selection_serves_all_instances() {
+    let qos = QosPolicyBuilder::new()
+      .history(policy::History::KeepAll)
+      .build();
+    let mut datasample_cache = DataSampleCache::<RandomData>::new(qos);
+    let writer = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    // Instance 1 is chatty, instances 2 and 3 have one sample each.
+    for (sn, a) in [1, 1, 1, 1, 2, 3].into_iter().enumerate() {
+      let sn = sn as i32 + 1;
+      datasample_cache.add_sample(
+        Sample::Value(RandomData {
+          a,
+          b: sn.to_string(),
+        }),
+        writer,
+        SequenceNumber::from(i64::from(sn)),
+        Timestamp::ZERO + Duration::from_secs(sn),
+        WriteOptions::default(),
+      );
+    }
+    let instances = |keys: &[(Timestamp, i64)]| keys.iter().map(|(_, k)| *k).collect::<Vec<_>>();
+
+    let (keys, last) =
+      datasample_cache.select_round_robin_keys_for_access(ReadCondition::any(), None, 2, 3);
+    assert_eq!(instances(&keys), vec![1, 1, 2]);
+    assert_eq!(last, Some(2));
+    datasample_cache.take_by_keys(&keys);
+
+    // The next selection continues after instance 2, and wraps around.
+    let (keys, last) =
+      datasample_cache.select_round_robin_keys_for_access(ReadCondition::any(), Some(&2), 2, 3);
+    assert_eq!(instances(&keys), vec![3, 1, 1]);
+    assert_eq!(last, Some(1));
+    datasample_cache.take_by_keys(&keys);
+
+    let (keys, last) =
+      datasample_cache.select_round_robin_keys_for_access(ReadCondition::any(), Some(&1), 2, 3);
+    assert!(keys.is_empty());
+    assert_eq!(last, None);
+  }
+
+  #[test]
+  fn dsc_sample_info_identifies_write() {
+    let mut datasample_cache = DataSampleCache::<RandomData>::new(QosPolicyBuilder::new().build());
+    let writer = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let received = Timestamp::ZERO + Duration::from_secs(10);
+    datasample_cache.add_sample(
+      Sample::Value(RandomData {
+        a: 1,
+        b: "one".to_string(),
+      }),
+      writer,
+      SequenceNumber::from(42),
+      received,
+      WriteOptions::default(),
+    );
+
+    let keys = datasample_cache.select_keys_for_access(ReadCondition::any());
+    let samples = datasample_cache.take_by_keys(&keys);
+    let sample_info = samples[0].sample_info();
+    assert_eq!(sample_info.writer_guid(), writer);
+    assert_eq!(sample_info.sequence_number(), SequenceNumber::from(42));
+    assert_eq!(sample_info.reception_timestamp(), received);
+    assert_eq!(sample_info.source_timestamp(), None);
+  }
 
   #[test]
   fn dsc_empty_qos() {
@@ -1,14 +1,16 @@
 use std::{
+  collections::BTreeMap,
   marker::PhantomData,
   pin::Pin,
   sync::{
-    atomic::{AtomicI64, Ordering},
+    atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering},
     Arc, Mutex,
   },
   task::{Context, Poll, Waker},
-  time::{Duration, Instant},
+  time::Duration,
 };
 
+use bytes::Bytes;
 use futures::{Future, Stream};
 use mio_06::{Events, PollOpt, Ready, Token};
 use mio_extras::channel::{self as mio_channel, SendError, TrySendError};
@@ -18,20 +20,33 @@ use log::{debug, error, info, trace, warn};
 use crate::{
   dds::{
     adapters::with_key::SerializerAdapter,
+    communication_status::{
+      Entity, LivelinessLostStatus, OfferedDeadlineMissedStatus, OfferedIncompatibleQosStatus,
+      PublicationMatchedStatus, StatusMask, WriterStatusRecord,
+    },
+    content_filter::{ContentFilterFactory, MatchedReaderFilters, WriterContentFilters},
     ddsdata::DDSData,
     helpers::*,
+    interceptor::{WriteInterceptor, WriteInterceptorChain},
+    intra_process::LocalSample,
+    listener::DataWriterListener,
     pubsub::Publisher,
+    key::{Key, KeyHash},
     qos::{
-      policy::{Liveliness, Reliability},
+      policy::{Conflation, LastValueCache, Liveliness, Reliability},
       HasQoSPolicy, QosPolicies,
     },
     result::{CreateResult, WriteError, WriteResult},
+    sampleinfo::InstanceState,
+    sliced::SlicedSample,
     statusevents::*,
     topic::Topic,
+    waitset::StatusCondition,
+    with_key::instance_statistics::InstanceStatistics,
   },
   discovery::{discovery::DiscoveryCommand, sedp_messages::SubscriptionBuiltinTopicData},
   messages::submessages::elements::serialized_payload::SerializedPayload,
-  rtps::writer::WriterCommand,
+  rtps::writer::{WatermarkTracker, WriterCommand},
   serialization::CDRSerializerAdapter,
   structure::{
     cache_change::ChangeKind, duration, entity::RTPSEntity, guid::GUID, rpc::SampleIdentity,
@@ -40,6 +55,45 @@ use crate::{
   Keyed, TopicDescription,
 };
 
+/// Sequence numbers that tell how far the matched reliable DataReaders have
+/// acknowledged the samples of a DataWriter. See
+/// [`DataWriter::sequence_number_watermarks`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceNumberWatermarks {
+  /// Lowest sequence number that some matched reliable DataReader has not
+  /// acknowledged. One past `highest_written`, if everything is acknowledged.
+  pub lowest_unacknowledged: SequenceNumber,
+  /// Sequence number of the latest sample passed to the network. Zero before
+  /// anything is written.
+  pub highest_written: SequenceNumber,
+}
+
+impl SequenceNumberWatermarks {
+  /// Number of samples waiting for acknowledgement.
+  pub fn unacknowledged(&self) -> u64 {
+    let window = i64::from(self.highest_written) - i64::from(self.lowest_unacknowledged) + 1;
+    window.max(0) as u64
+  }
+}
+
+/// State of the history that a DataWriter keeps for repairs and
+/// late-joining DataReaders. See [`DataWriter::history_status`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriterHistoryStatus {
+  /// Lowest sequence number in the history. One past
+  /// `last_sequence_number`, if the history is empty.
+  pub first_sequence_number: SequenceNumber,
+  /// Sequence number of the latest sample passed to the network
+  pub last_sequence_number: SequenceNumber,
+  /// Number of samples in the history
+  pub samples: usize,
+  /// Serialized size of the samples in the history
+  pub bytes: usize,
+  /// Number of samples not yet acknowledged by each matched reliable
+  /// DataReader
+  pub unacknowledged: BTreeMap<GUID, u64>,
+}
+
 // TODO: Move the write options and the builder type to some lower-level module
 // to avoid circular dependencies.
 #[derive(Debug, Default)]
@@ -59,6 +113,7 @@ impl WriteOptionsBuilder {
       related_sample_identity: self.related_sample_identity,
       source_timestamp: self.source_timestamp,
       to_single_reader: self.to_single_reader,
+      excluded_readers: Vec::new(),
     }
   }
 
@@ -98,6 +153,9 @@ pub struct WriteOptions {
   source_timestamp: Option<Timestamp>,             // from DDS spec
   to_single_reader: Option<GUID>,                  /* try to send to one Reader only
                                                     * future extension room fo other fields. */
+  // Readers whose content filters the sample does not pass. Set by the
+  // DataWriter.
+  excluded_readers: Vec<GUID>,
 }
 
 impl WriteOptions {
@@ -112,6 +170,10 @@ impl WriteOptions {
   pub fn to_single_reader(&self) -> Option<GUID> {
     self.to_single_reader
   }
+
+  pub(crate) fn excluded_readers(&self) -> &[GUID] {
+    &self.excluded_readers
+  }
 }
 
 impl From<Option<Timestamp>> for WriteOptions {
@@ -120,6 +182,7 @@ impl From<Option<Timestamp>> for WriteOptions {
       related_sample_identity: None,
       source_timestamp,
       to_single_reader: None,
+      excluded_readers: Vec::new(),
     }
   }
 }
@@ -167,6 +230,24 @@ pub struct DataWriter<D: Keyed, SA: SerializerAdapter<D> = CDRSerializerAdapter<
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   status_receiver: StatusChannelReceiver<DataWriterStatus>,
   available_sequence_number: AtomicI64,
+  write_interceptors: WriteInterceptorChain<D>,
+  sample_reuse: AtomicBool,
+  last_sample: Mutex<Option<ReusableSample<D>>>,
+  conflated_samples: Arc<AtomicU64>,
+  watermarks: Arc<WatermarkTracker>,
+  content_filters: WriterContentFilters<D>,
+  status_record: Arc<WriterStatusRecord>,
+  // Samples written with write_shared are delivered in process
+  intra_process: bool,
+  // State and time of the latest write of each instance
+  instances: Mutex<BTreeMap<D::K, (InstanceState, Timestamp)>>,
+}
+
+// Most recently written sample, kept together with its serialized form when
+// sample reuse is enabled.
+struct ReusableSample<D> {
+  data: D,
+  serialized: Bytes,
 }
 
 impl<D, SA> Drop for DataWriter<D, SA>
@@ -175,8 +256,11 @@ where
   SA: SerializerAdapter<D>,
 {
   fn drop(&mut self) {
-    // Tell Publisher to drop the corresponding RTPS Writer
-    self.my_publisher.remove_writer(self.my_guid);
+    // Tell Publisher to drop the corresponding RTPS Writer. If the Publisher
+    // has deleted this DataWriter already, Discovery has been told also.
+    if !self.my_publisher.remove_writer(self.my_guid) {
+      return;
+    }
 
     // Notify Discovery that we are no longer
     match self
@@ -213,6 +297,11 @@ where
     cc_upload_waker: Arc<Mutex<Option<Waker>>>,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
     status_receiver: StatusChannelReceiver<DataWriterStatus>,
+    conflated_samples: Arc<AtomicU64>,
+    watermarks: Arc<WatermarkTracker>,
+    matched_reader_filters: Arc<MatchedReaderFilters>,
+    status_record: Arc<WriterStatusRecord>,
+    intra_process: bool,
   ) -> CreateResult<Self> {
     if let Some(lv) = qos.liveliness {
       match lv {
@@ -236,9 +325,162 @@ where
       discovery_command,
       status_receiver,
       available_sequence_number: AtomicI64::new(1), // valid numbering starts from 1
+      write_interceptors: WriteInterceptorChain::new(),
+      sample_reuse: AtomicBool::new(false),
+      last_sample: Mutex::new(None),
+      conflated_samples,
+      watermarks,
+      content_filters: WriterContentFilters::new(matched_reader_filters, SA::matches_sql_filter),
+      status_record,
+      intra_process,
+      instances: Mutex::new(BTreeMap::new()),
     })
   }
 
+  /// Registers an interceptor that is called for each sample passed to
+  /// `write` or `write_with_options`, before serialization.
+  ///
+  /// Interceptors are called in the order they were added. If one of them
+  /// vetoes the sample, the write returns [`WriteError::Vetoed`] and the
+  /// remaining interceptors are not called.
+  ///
+  /// Note that `dispose` is not intercepted, as it does not carry a sample.
+  pub fn add_write_interceptor<I>(&self, interceptor: I)
+  where
+    I: WriteInterceptor<D> + 'static,
+  {
+    self.write_interceptors.add(Arc::new(interceptor));
+  }
+
+  /// Removes all interceptors added with
+  /// [`add_write_interceptor`](Self::add_write_interceptor).
+  pub fn clear_write_interceptors(&self) {
+    self.write_interceptors.clear();
+  }
+
+  /// Registers a factory of content filters, replacing any earlier factory
+  /// of the same class. Matched DataReaders that advertise a filter of the
+  /// class are sent only the samples that pass it. See
+  /// [`content_filter`](crate::content_filter).
+  ///
+  /// Samples written with [`begin_sample`](Self::begin_sample) are not
+  /// filtered, since there is no sample object to filter.
+  ///
+  /// Filters of the "DDSSQL" class are applied without a factory, if the
+  /// serializer adapter can inspect the samples. A factory of the class
+  /// replaces that.
+  pub fn register_content_filter_factory(&self, factory: ContentFilterFactory<D>) {
+    self.content_filters.register(factory);
+  }
+
+  // Readers whose content filters the sample does not pass are excluded from
+  // the write.
+  fn apply_content_filters(&self, data: &D, mut write_options: WriteOptions) -> WriteOptions {
+    write_options.excluded_readers = self.content_filters.readers_filtering_out(data);
+    write_options
+  }
+
+  /// Enables or disables sample reuse. When enabled, the DataWriter keeps the
+  /// most recent sample written with `write` or `write_with_options`, together
+  /// with its serialized form. It can then be published again with
+  /// [`rewrite`](Self::rewrite) without serializing, or modified in place with
+  /// [`write_modified`](Self::write_modified).
+  ///
+  /// This is intended for topics that publish large, nearly identical samples
+  /// at a high rate. Disabling drops the kept sample.
+  pub fn set_sample_reuse(&self, enabled: bool) {
+    self.sample_reuse.store(enabled, Ordering::Relaxed);
+    if !enabled {
+      *self.last_sample.lock().unwrap() = None;
+    }
+  }
+
+  /// Publishes the previously written sample again, reusing its serialized
+  /// form. Interceptors are not called again, because the sample is the same.
+  ///
+  /// Fails with [`WriteError::Internal`], if sample reuse is not enabled or
+  /// nothing has been written since enabling it.
+  pub fn rewrite(&self, write_options: WriteOptions) -> WriteResult<SampleIdentity, ()> {
+    let (serialized, key, write_options) = match self.last_sample.lock()?.as_ref() {
+      Some(last) => (
+        last.serialized.clone(), // cheap: Bytes is reference-counted
+        last.data.key(),
+        self.apply_content_filters(&last.data, write_options),
+      ),
+      None => {
+        return Err(WriteError::Internal {
+          reason: "No sample to rewrite. Is sample reuse enabled?".to_string(),
+        })
+      }
+    };
+    self.send_serialized(serialized, &key, write_options)
+  }
+
+  /// Modifies the previously written sample with `modify` and publishes it.
+  /// This avoids constructing a new sample for each write.
+  ///
+  /// `modify` returns `false` if it did not change anything. Then the previous
+  /// serialized form is reused, as in [`rewrite`](Self::rewrite). Otherwise the
+  /// sample passes through interceptors and is serialized again.
+  ///
+  /// Fails like `rewrite` if there is no previous sample. If the modified
+  /// sample cannot be written, it is dropped and there is nothing to reuse
+  /// until the next successful write.
+  pub fn write_modified<F>(
+    &self,
+    modify: F,
+    write_options: WriteOptions,
+  ) -> WriteResult<SampleIdentity, ()>
+  where
+    F: FnOnce(&mut D) -> bool,
+  {
+    let mut last_sample = self.last_sample.lock()?;
+    let Some(ReusableSample {
+      mut data,
+      serialized,
+    }) = last_sample.take()
+    else {
+      return Err(WriteError::Internal {
+        reason: "No sample to modify. Is sample reuse enabled?".to_string(),
+      });
+    };
+
+    if !modify(&mut data) {
+      let write_options = self.apply_content_filters(&data, write_options);
+      let result = self.send_serialized(serialized.clone(), &data.key(), write_options);
+      *last_sample = Some(ReusableSample { data, serialized });
+      return result;
+    }
+
+    let data = self
+      .write_interceptors
+      .intercept(data, &write_options)
+      .map_err(WriteError::forget_data)?;
+    let serialized = SA::to_bytes(&data).map_err(|e| WriteError::Serialization {
+      reason: format!("{e}"),
+      data: (),
+    })?;
+    let write_options = self.apply_content_filters(&data, write_options);
+    let result = self.send_serialized(serialized.clone(), &data.key(), write_options);
+    if result.is_ok() {
+      *last_sample = Some(ReusableSample { data, serialized });
+    }
+    result
+  }
+
+  /// Begins a sample of instance `key`, which is written in chunks. The chunks
+  /// must add up to `total_len` bytes of serialized data. See
+  /// [`SlicedSample`].
+  pub fn begin_sample(&self, key: &D::K, total_len: usize) -> WriteResult<SlicedSample<'_>, ()> {
+    let key = key.clone();
+    SlicedSample::new(
+      total_len,
+      Box::new(move |serialized, write_options| {
+        self.send_serialized(serialized, &key, write_options)
+      }),
+    )
+  }
+
   fn next_sequence_number(&self) -> SequenceNumber {
     SequenceNumber::from(
       self
@@ -247,10 +489,28 @@ where
     )
   }
 
-  fn undo_sequence_number(&self) {
-    self
-      .available_sequence_number
-      .fetch_sub(1, Ordering::Relaxed);
+  // Takes `count` consecutive sequence numbers, and returns the first one.
+  fn next_sequence_numbers(&self, count: usize) -> SequenceNumber {
+    SequenceNumber::from(
+      self
+        .available_sequence_number
+        .fetch_add(count as i64, Ordering::Relaxed),
+    )
+  }
+
+  // Returns an unsent sequence number, unless a later one has been taken
+  // already. The Writer then tells the Readers that it does not exist.
+  fn undo_sequence_number(&self, sequence_number: SequenceNumber) {
+    self.undo_sequence_numbers(sequence_number, 1);
+  }
+
+  fn undo_sequence_numbers(&self, first: SequenceNumber, count: usize) {
+    let _ = self.available_sequence_number.compare_exchange(
+      i64::from(first) + count as i64,
+      i64::from(first),
+      Ordering::Relaxed,
+      Ordering::Relaxed,
+    );
   }
 
   /// Manually refreshes liveliness
@@ -341,6 +601,8 @@ where
     data: D,
     write_options: WriteOptions,
   ) -> WriteResult<SampleIdentity, D> {
+    let data = self.write_interceptors.intercept(data, &write_options)?;
+
     // serialize
     let send_buffer = match SA::to_bytes(&data) {
       Ok(b) => b,
@@ -352,15 +614,108 @@ where
       }
     };
 
+    let write_options = self.apply_content_filters(&data, write_options);
+    match self.send_serialized(send_buffer.clone(), &data.key(), write_options) {
+      Ok(sample_identity) => {
+        if self.sample_reuse.load(Ordering::Relaxed) {
+          *self.last_sample.lock().unwrap() = Some(ReusableSample {
+            data,
+            serialized: send_buffer,
+          });
+        }
+        Ok(sample_identity)
+      }
+      Err(e) => Err(e.with_data(data)),
+    }
+  }
+
+  /// Writes a sample that the caller shares with the DataWriter. If the
+  /// DomainParticipant is built with
+  /// [`intra_process`](crate::DomainParticipantBuilder::intra_process),
+  /// DataReaders of the same participant get clones of the value, without it
+  /// being serialized. See [`intra_process`](crate::intra_process).
+  ///
+  /// Otherwise, or if write interceptors are registered, a clone of the value
+  /// is written like with [`write`](Self::write). A shared sample is not kept
+  /// for [`rewrite`](Self::rewrite) or
+  /// [`write_modified`](Self::write_modified).
+  pub fn write_shared(
+    &self,
+    data: Arc<D>,
+    source_timestamp: Option<Timestamp>,
+  ) -> WriteResult<(), Arc<D>>
+  where
+    D: Clone + Send + Sync + 'static,
+  {
+    let write_options = WriteOptions::from(source_timestamp);
+    if !self.intra_process || !self.write_interceptors.is_empty() {
+      return self
+        .write_with_options(D::clone(&data), write_options)
+        .map(|_| ())
+        .map_err(|e| e.forget_data().with_data(data));
+    }
+
+    if self.sample_reuse.load(Ordering::Relaxed) {
+      *self.last_sample.lock().unwrap() = None;
+    }
+    let write_options = self.apply_content_filters(&data, write_options);
+    let ddsdata = DDSData::Local {
+      sample: LocalSample::new::<D, SA>(Arc::clone(&data)),
+    };
+    match self.send_ddsdata(ddsdata, &data.key(), write_options) {
+      Ok(_sample_identity) => Ok(()),
+      Err(e) => Err(e.with_data(data)),
+    }
+  }
+
+  // Instance of a sample for the RTPS Writer, if the LastValueCache or
+  // Conflation policy is enabled. Otherwise the key hash is not needed.
+  fn instance_for_writer(&self, key: &D::K) -> Option<KeyHash> {
+    let last_value_cache = self.qos_policy.last_value_cache() == Some(LastValueCache::Enabled);
+    let conflation = self.qos_policy.conflation() == Some(Conflation::LatestPerInstance);
+    (last_value_cache || conflation).then(|| key.hash_key(false))
+  }
+
+  // Records a successful write or dispose for instance statistics.
+  fn record_instance_update(&self, key: &D::K, state: InstanceState) {
+    let update = (state, Timestamp::now());
+    let mut instances = self.instances.lock().unwrap_or_else(|e| e.into_inner());
+    match instances.get_mut(key) {
+      Some(instance) => *instance = update,
+      None => {
+        instances.insert(key.clone(), update);
+      }
+    }
+  }
+
+  // Hands an already serialized sample of instance `key` over to the RTPS
+  // Writer.
+  fn send_serialized(
+    &self,
+    send_buffer: Bytes,
+    key: &D::K,
+    write_options: WriteOptions,
+  ) -> WriteResult<SampleIdentity, ()> {
     let ddsdata = DDSData::new(SerializedPayload::new_from_bytes(
       SA::output_encoding(),
       send_buffer,
     ));
+    self.send_ddsdata(ddsdata, key, write_options)
+  }
+
+  // Hands a sample of instance `key` over to the RTPS Writer.
+  fn send_ddsdata(
+    &self,
+    ddsdata: DDSData,
+    key: &D::K,
+    write_options: WriteOptions,
+  ) -> WriteResult<SampleIdentity, ()> {
     let sequence_number = self.next_sequence_number();
     let writer_command = WriterCommand::DDSData {
       ddsdata,
       write_options,
       sequence_number,
+      instance: self.instance_for_writer(key),
     };
 
     let timeout = self.qos().reliable_max_blocking_time();
@@ -368,6 +723,7 @@ where
     match try_send_timeout(&self.cc_upload, writer_command, timeout) {
       Ok(_) => {
         self.refresh_manual_liveliness();
+        self.record_instance_update(key, InstanceState::Alive);
         Ok(SampleIdentity {
           writer_guid: self.my_guid,
           sequence_number,
@@ -379,18 +735,18 @@ where
           self.my_topic.name(),
           timeout,
         );
-        self.undo_sequence_number();
-        Err(WriteError::WouldBlock { data })
+        self.undo_sequence_number(sequence_number);
+        Err(WriteError::WouldBlock { data: () })
       }
       Err(TrySendError::Disconnected(_)) => {
-        self.undo_sequence_number();
+        self.undo_sequence_number(sequence_number);
         Err(WriteError::Poisoned {
           reason: "Cannot send to Writer".to_string(),
-          data,
+          data: (),
         })
       }
       Err(TrySendError::Io(e)) => {
-        self.undo_sequence_number();
+        self.undo_sequence_number(sequence_number);
         Err(e.into())
       }
     }
@@ -443,7 +799,7 @@ where
     match &self.qos_policy.reliability {
       None | Some(Reliability::BestEffort) => Ok(true),
       Some(Reliability::Reliable { .. }) => {
-        let (acked_sender, mut acked_receiver) = sync_status_channel::<()>(1)?;
+        let (acked_sender, mut acked_receiver) = sync_status_channel::<bool>(1)?;
         let poll = mio_06::Poll::new()?;
         poll.register(
           acked_receiver.as_status_evented(),
@@ -455,6 +811,7 @@ where
           .cc_upload
           .try_send(WriterCommand::WaitForAcknowledgments {
             all_acked: acked_sender,
+            max_wait: Some(max_wait),
           })
           .unwrap_or_else(|e| {
             warn!("wait_for_acknowledgments: cannot initiate waiting. This will timeout. {e}");
@@ -464,7 +821,7 @@ where
         poll.poll(&mut events, Some(max_wait))?;
         if let Some(_event) = events.iter().next() {
           match acked_receiver.try_recv() {
-            Ok(_) => Ok(true), // got token
+            Ok(all_acked) => Ok(all_acked), // got token
             Err(e) => {
               warn!("wait_for_acknowledgments - Spurious poll event? - {e}");
               Ok(false) // TODO: We could also loop here
@@ -478,148 +835,29 @@ where
     } // match
   }
 
-  /*
-
-  /// Unimplemented. <b>Do not use</b>.
-  ///
-  /// # Examples
-  ///
-  /// ```no_run
-  // TODO: enable when functional
-  /// # use serde::{Serialize, Deserialize};
-  /// # use rustdds::*;
-  /// # use rustdds::with_key::DataWriter;
-  /// # use rustdds::serialization::CDRSerializerAdapter;
-  /// #
-  /// let domain_participant = DomainParticipant::new(0).unwrap();
-  /// let qos = QosPolicyBuilder::new().build();
-  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
-  ///
-  /// #[derive(Serialize, Deserialize, Debug)]
-  /// struct SomeType { a: i32 }
-  /// impl Keyed for SomeType {
-  ///   type K = i32;
-  ///
-  ///   fn key(&self) -> Self::K {
-  ///     self.a
-  ///   }
-  /// }
-  ///
-  /// // WithKey is important
-  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
-  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
-  ///
-  /// // Liveliness lost status has changed
-  ///
-  /// if let Ok(lls) = data_writer.get_liveliness_lost_status() {
-  ///   // do something
-  /// }
-  /// ```
-  pub fn get_liveliness_lost_status(&self) -> Result<LivelinessLostStatus> {
-    todo!()
+  /// The LIVELINESS_LOST communication status. Resets its change count. See
+  /// [`communication_status`](crate::communication_status).
+  pub fn get_liveliness_lost_status(&self) -> LivelinessLostStatus {
+    self.status_record.take_liveliness_lost()
   }
 
-  /// Should get latest offered deadline missed status. <b>Do not use yet</b> use `get_status_lister` instead for the moment.
-  ///
-  /// # Examples
-  ///
-  /// ```
-  /// # use serde::{Serialize, Deserialize};
-  /// # use rustdds::*;
-  /// # use rustdds::with_key::DataWriter;
-  /// # use rustdds::serialization::CDRSerializerAdapter;
-  /// #
-  /// let domain_participant = DomainParticipant::new(0).unwrap();
-  /// let qos = QosPolicyBuilder::new().build();
-  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
-  ///
-  /// #[derive(Serialize, Deserialize, Debug)]
-  /// struct SomeType { a: i32 }
-  /// impl Keyed for SomeType {
-  ///   type K = i32;
-  ///
-  ///   fn key(&self) -> Self::K {
-  ///     self.a
-  ///   }
-  /// }
-  ///
-  /// // WithKey is important
-  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
-  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
-  ///
-  /// // Deadline missed status has changed
-  ///
-  /// if let Ok(odms) = data_writer.get_offered_deadline_missed_status() {
-  ///   // do something
-  /// }
-  /// ```
-  pub fn get_offered_deadline_missed_status(&self) -> Result<OfferedDeadlineMissedStatus> {
-    let mut fstatus = OfferedDeadlineMissedStatus::new();
-    while let Ok(status) = self.status_receiver.try_recv() {
-      match status {
-        StatusChange::OfferedDeadlineMissedStatus(status) => fstatus = status,
-  // TODO: possibly save old statuses
-        _ => (),
-      }
-    }
-
-    match self
-      .cc_upload
-      .try_send(WriterCommand::ResetOfferedDeadlineMissedStatus {
-        writer_guid: self.guid(),
-      }) {
-      Ok(_) => (),
-      Err(e) => error!("Unable to send ResetOfferedDeadlineMissedStatus. {e:?}"),
-    };
-
-    Ok(fstatus)
+  /// The OFFERED_DEADLINE_MISSED communication status. Resets its change
+  /// count.
+  pub fn get_offered_deadline_missed_status(&self) -> OfferedDeadlineMissedStatus {
+    self.status_record.take_offered_deadline_missed()
   }
 
-  /// Unimplemented. <b>Do not use</b>.
-  ///
-  /// # Examples
-  ///
-  /// ```no_run
-  // TODO: enable when functional
-  /// # use serde::{Serialize, Deserialize};
-  /// # use rustdds::*;
-  /// # use rustdds::with_key::DataWriter;
-  /// # use rustdds::serialization::CDRSerializerAdapter;
-  /// #
-  /// let domain_participant = DomainParticipant::new(0).unwrap();
-  /// let qos = QosPolicyBuilder::new().build();
-  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
-  ///
-  /// #[derive(Serialize, Deserialize, Debug)]
-  /// struct SomeType { a: i32 }
-  /// impl Keyed for SomeType {
-  ///   type K = i32;
-  ///
-  ///   fn key(&self) -> Self::K {
-  ///     self.a
-  ///   }
-  /// }
-  ///
-  /// // WithKey is important
-  /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
-  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
-  ///
-  /// // Liveliness lost status has changed
-  ///
-  /// if let Ok(oiqs) = data_writer.get_offered_incompatible_qos_status() {
-  ///   // do something
-  /// }
-  /// ```
-  pub fn get_offered_incompatible_qos_status(&self) -> Result<OfferedIncompatibleQosStatus> {
-    todo!()
+  /// The OFFERED_INCOMPATIBLE_QOS communication status. Resets its change
+  /// count.
+  pub fn get_offered_incompatible_qos_status(&self) -> OfferedIncompatibleQosStatus {
+    self.status_record.take_offered_incompatible_qos()
   }
 
-  /// Unimplemented. <b>Do not use</b>.
+  /// The PUBLICATION_MATCHED communication status. Resets its change counts.
   ///
   /// # Examples
   ///
-  /// ```no_run
-  // TODO: enable when functional
+  /// ```
   /// # use serde::{Serialize, Deserialize};
   /// # use rustdds::*;
   /// # use rustdds::with_key::DataWriter;
@@ -639,21 +877,22 @@ where
   ///   }
   /// }
   ///
-  /// // WithKey is important
   /// let topic = domain_participant.create_topic("some_topic".to_string(), "SomeType".to_string(), &qos, TopicKind::WithKey).unwrap();
-  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(topic, None).unwrap();
-  ///
-  /// // Liveliness lost status has changed
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(&topic, None).unwrap();
   ///
-  /// if let Ok(pms) = data_writer.get_publication_matched_status() {
-  ///   // do something
-  /// }
+  /// let status = data_writer.get_publication_matched_status();
+  /// assert_eq!(status.current_count_change, 0);
   /// ```
-  pub fn get_publication_matched_status(&self) -> Result<PublicationMatchedStatus> {
-    todo!()
+  pub fn get_publication_matched_status(&self) -> PublicationMatchedStatus {
+    self.status_record.take_publication_matched()
   }
 
-  */
+  /// Sets the listener that is called back as the communication statuses of
+  /// this DataWriter change, replacing any previous one. `None` removes it.
+  /// See [`listener`](crate::listener).
+  pub fn set_listener(&self, listener: Option<Box<dyn DataWriterListener>>) {
+    self.status_record.listener.set(listener);
+  }
 
   /// Topic assigned to this DataWriter
   ///
@@ -689,6 +928,140 @@ where
     &self.my_topic
   }
 
+  /// Number of samples that were not sent, because a newer sample of the same
+  /// instance superseded them. Always zero, unless the
+  /// [`Conflation`](crate::policy::Conflation) policy is enabled.
+  pub fn conflated_sample_count(&self) -> u64 {
+    self.conflated_samples.load(Ordering::Relaxed)
+  }
+
+  /// Lowest unacknowledged and highest written sequence numbers of this
+  /// DataWriter. Applications can use these to pace their writing, so that
+  /// slow reliable DataReaders do not fall too far behind. The values are
+  /// updated as the samples are sent and acknowledged, so they lag behind
+  /// `write` calls slightly.
+  ///
+  /// Only reliable DataReaders acknowledge samples, so with no reliable
+  /// DataReaders matched, all samples count as acknowledged.
+  pub fn sequence_number_watermarks(&self) -> SequenceNumberWatermarks {
+    self.watermarks.watermarks()
+  }
+
+  /// Requests a
+  /// [`DataWriterStatus::UnacknowledgedBelowThreshold`]
+  /// status each time the number of unacknowledged samples drops from
+  /// `threshold` or more to below it. `None` or `Some(0)` turns the
+  /// notifications off, which is the default.
+  pub fn set_unacknowledged_threshold(&self, threshold: Option<u64>) {
+    self.watermarks.set_threshold(threshold);
+  }
+
+  /// Current state of the history of this DataWriter: the range of
+  /// sequence numbers and the amount of data held, and how far each
+  /// matched reliable DataReader is from having acknowledged all of it.
+  pub fn history_status(&self) -> WriteResult<WriterHistoryStatus, ()> {
+    self.history_request(|reply| WriterCommand::HistoryStatus { reply })
+  }
+
+  /// Removes the samples that all matched reliable DataReaders have
+  /// acknowledged from the history. Returns the number of samples removed.
+  ///
+  /// The History QoS policy removes old samples periodically. This removes
+  /// them right away, e.g. to bound the memory of a TRANSIENT_LOCAL
+  /// DataWriter that keeps all samples. Late-joining DataReaders do not
+  /// receive removed samples.
+  pub fn purge_acknowledged(&self) -> WriteResult<usize, ()> {
+    self.history_request(|reply| WriterCommand::PurgeHistory {
+      acknowledged_only: true,
+      reply,
+    })
+  }
+
+  /// Removes all samples from the history, whether acknowledged or not.
+  /// Returns the number of samples removed. DataReaders that have not
+  /// received them yet are told that they are no longer available.
+  pub fn clear_history(&self) -> WriteResult<usize, ()> {
+    self.history_request(|reply| WriterCommand::PurgeHistory {
+      acknowledged_only: false,
+      reply,
+    })
+  }
+
+  /// Sequence numbers of the samples that each matched DataReader has not
+  /// acknowledged as processed, in ascending order. Empty, unless this
+  /// DataWriter has the [`Acknowledgment`](crate::policy::Acknowledgment)
+  /// policy `ApplicationExplicit`.
+  ///
+  /// A sample stays unacknowledged, until the DataReader acknowledges it or
+  /// the sample is redelivered with
+  /// [`redeliver_unacknowledged`](Self::redeliver_unacknowledged).
+  pub fn unacknowledged_samples(&self) -> WriteResult<BTreeMap<GUID, Vec<SequenceNumber>>, ()> {
+    self.history_request(|reply| WriterCommand::UnacknowledgedSamples { reply })
+  }
+
+  /// Writes the samples that `reader` has not acknowledged as processed again
+  /// with new sequence numbers, to `to_reader` only. `to_reader` may be
+  /// `reader` itself, to have it process them again, or another matched
+  /// DataReader, e.g. when `reader` has stopped. Returns the number of
+  /// samples redelivered.
+  ///
+  /// The redelivered samples keep their source timestamps and related sample
+  /// identities. They are then tracked as samples of `to_reader`, and no
+  /// longer as samples of `reader`. Samples that are no longer in the history
+  /// cannot be redelivered, and are still listed by
+  /// [`unacknowledged_samples`](Self::unacknowledged_samples).
+  pub fn redeliver_unacknowledged(&self, reader: GUID, to_reader: GUID) -> WriteResult<usize, ()> {
+    let count = self
+      .unacknowledged_samples()?
+      .get(&reader)
+      .map_or(0, Vec::len);
+    if count == 0 {
+      return Ok(0);
+    }
+    let first_sequence_number = self.next_sequence_numbers(count);
+    let redelivered = self.history_request(|reply| WriterCommand::RedeliverUnacknowledged {
+      reader,
+      to_reader,
+      first_sequence_number,
+      count,
+      reply,
+    })?;
+    // If some samples have left the history in the meantime, not all the
+    // sequence numbers were used.
+    self.undo_sequence_numbers(
+      first_sequence_number + SequenceNumber::from(redelivered as i64),
+      count - redelivered,
+    );
+    Ok(redelivered)
+  }
+
+  // Sends a command to the Writer, and waits for its reply.
+  fn history_request<T>(
+    &self,
+    command: impl FnOnce(std::sync::mpsc::Sender<T>) -> WriterCommand,
+  ) -> WriteResult<T, ()> {
+    const REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+    let (reply_sender, reply_receiver) = std::sync::mpsc::channel();
+    let timeout = self.qos().reliable_max_blocking_time();
+    match try_send_timeout(&self.cc_upload, command(reply_sender), timeout) {
+      Ok(()) => {}
+      Err(TrySendError::Full(_)) => return Err(WriteError::WouldBlock { data: () }),
+      Err(TrySendError::Io(e)) => return Err(e.into()),
+      Err(TrySendError::Disconnected(_)) => {
+        return Err(WriteError::Poisoned {
+          reason: "Cannot send to Writer".to_string(),
+          data: (),
+        })
+      }
+    }
+    reply_receiver
+      .recv_timeout(REPLY_TIMEOUT)
+      .map_err(|e| WriteError::Poisoned {
+        reason: format!("No reply from Writer: {e}"),
+        data: (),
+      })
+  }
+
   /// Publisher assigned to this DataWriter
   ///
   /// # Examples
@@ -874,15 +1247,17 @@ where
       ChangeKind::NotAliveDisposed,
       SerializedPayload::new_from_bytes(SA::output_encoding(), send_buffer),
     );
+    let sequence_number = self.next_sequence_number();
     self
       .cc_upload
       .send(WriterCommand::DDSData {
         ddsdata,
         write_options: WriteOptions::from(source_timestamp),
-        sequence_number: self.next_sequence_number(),
+        sequence_number,
+        instance: self.instance_for_writer(key),
       })
       .map_err(|e| {
-        self.undo_sequence_number();
+        self.undo_sequence_number(sequence_number);
         WriteError::Serialization {
           reason: format!("{e}"),
           data: (),
@@ -890,8 +1265,127 @@ where
       })?;
 
     self.refresh_manual_liveliness();
+    self.record_instance_update(key, InstanceState::NotAliveDisposed);
     Ok(())
   }
+
+  /// Disposes all the instances that this DataWriter has written and not
+  /// disposed yet, as [`dispose`](Self::dispose) does for one instance.
+  /// Returns the number of instances disposed.
+  ///
+  /// The dispose messages are handed over together, and sent at once, with
+  /// the messages to the same destination combined into as few datagrams as
+  /// possible. This is meant for a controlled shutdown of a component that
+  /// owns many instances.
+  pub fn dispose_all(&self, source_timestamp: Option<Timestamp>) -> WriteResult<usize, ()> {
+    let keys = self.instance_keys(|state| state == InstanceState::Alive);
+    self.send_key_only_batch(&keys, ChangeKind::NotAliveDisposed, source_timestamp)?;
+
+    let now = Timestamp::now();
+    let mut instances = self.instances.lock().unwrap_or_else(|e| e.into_inner());
+    for key in &keys {
+      instances.insert(key.clone(), (InstanceState::NotAliveDisposed, now));
+    }
+    Ok(keys.len())
+  }
+
+  /// Unregisters all the instances that this DataWriter has written or
+  /// disposed. Readers see an instance as not alive when no DataWriter has it
+  /// registered anymore. Returns the number of instances unregistered.
+  ///
+  /// The messages are sent as with [`dispose_all`](Self::dispose_all).
+  /// Unregistered instances are no longer counted in
+  /// [`instance_statistics`](Self::instance_statistics), until written again.
+  pub fn unregister_all(&self, source_timestamp: Option<Timestamp>) -> WriteResult<usize, ()> {
+    let keys = self.instance_keys(|_| true);
+    self.send_key_only_batch(&keys, ChangeKind::NotAliveUnregistered, source_timestamp)?;
+
+    let mut instances = self.instances.lock().unwrap_or_else(|e| e.into_inner());
+    for key in &keys {
+      instances.remove(key);
+    }
+    Ok(keys.len())
+  }
+
+  // Keys of the known instances in the given states
+  fn instance_keys(&self, state_filter: impl Fn(InstanceState) -> bool) -> Vec<D::K> {
+    let instances = self.instances.lock().unwrap_or_else(|e| e.into_inner());
+    instances
+      .iter()
+      .filter(|(_, (state, _))| state_filter(*state))
+      .map(|(key, _)| key.clone())
+      .collect()
+  }
+
+  // Hands a key-only change of each instance over to the RTPS Writer in a
+  // single command.
+  fn send_key_only_batch(
+    &self,
+    keys: &[D::K],
+    change_kind: ChangeKind,
+    source_timestamp: Option<Timestamp>,
+  ) -> WriteResult<(), ()> {
+    if keys.is_empty() {
+      return Ok(());
+    }
+    let payloads = keys
+      .iter()
+      .map(|key| {
+        SA::key_to_bytes(key).map_err(|e| WriteError::Serialization {
+          reason: format!("{e}"),
+          data: (),
+        })
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    let first_sequence_number = self.next_sequence_numbers(keys.len());
+    let changes = keys
+      .iter()
+      .zip(payloads)
+      .enumerate()
+      .map(|(i, (key, send_buffer))| {
+        let ddsdata = DDSData::new_disposed_by_key(
+          change_kind,
+          SerializedPayload::new_from_bytes(SA::output_encoding(), send_buffer),
+        );
+        let sequence_number = SequenceNumber::from(i64::from(first_sequence_number) + i as i64);
+        (ddsdata, sequence_number, self.instance_for_writer(key))
+      })
+      .collect();
+    self
+      .cc_upload
+      .send(WriterCommand::DDSDataBatch {
+        changes,
+        write_options: WriteOptions::from(source_timestamp),
+      })
+      .map_err(|e| {
+        self.undo_sequence_numbers(first_sequence_number, keys.len());
+        WriteError::Serialization {
+          reason: format!("{e}"),
+          data: (),
+        }
+      })?;
+
+    self.refresh_manual_liveliness();
+    Ok(())
+  }
+
+  /// Counts the instances that this DataWriter has written or disposed by
+  /// their state, and lists the `most_recent` instances written or disposed
+  /// last.
+  ///
+  /// Instances are counted from their first successful write until the
+  /// DataWriter is dropped, or the instance is unregistered with
+  /// [`unregister_all`](Self::unregister_all).
+  pub fn instance_statistics(&self, most_recent: usize) -> InstanceStatistics<D::K> {
+    let instances = self.instances.lock().unwrap_or_else(|e| e.into_inner());
+    InstanceStatistics::collect(
+      instances
+        .iter()
+        .map(|(key, (state, updated))| (key, *state, *updated)),
+      most_recent,
+    )
+  }
 }
 
 impl<'a, D, SA> StatusEvented<'a, DataWriterStatus, StatusReceiverStream<'a, DataWriterStatus>>
@@ -927,6 +1421,20 @@ where
   }
 }
 
+impl<D, SA> Entity for DataWriter<D, SA>
+where
+  D: Keyed,
+  SA: SerializerAdapter<D>,
+{
+  fn get_status_changes(&self) -> StatusMask {
+    self.status_record.changes()
+  }
+
+  fn get_statuscondition(&self) -> StatusCondition {
+    StatusCondition::new(self.status_record.clone())
+  }
+}
+
 impl<D, SA> HasQoSPolicy for DataWriter<D, SA>
 where
   D: Keyed,
@@ -941,7 +1449,10 @@ where
 // async writing implementation
 //
 
-// A future for an asynchronous write operation
+// A future for an asynchronous write operation.
+//
+// The sequence number is taken only when the data is passed to the Writer,
+// so dropping a pending write leaves no hole in the numbering.
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct AsyncWrite<'a, D, SA>
 where
@@ -949,10 +1460,9 @@ where
   SA: SerializerAdapter<D>,
 {
   writer: &'a DataWriter<D, SA>,
-  writer_command: Option<WriterCommand>,
-  sequence_number: SequenceNumber,
+  data: Option<(DDSData, WriteOptions, Option<KeyHash>)>,
   timeout: Option<duration::Duration>,
-  timeout_instant: Instant,
+  timeout_sleep: Sleep,
   sample: Option<D>,
 }
 
@@ -973,21 +1483,34 @@ where
   type Output = WriteResult<SampleIdentity, D>;
 
   fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-    match self.writer_command.take() {
-      Some(wc) => {
+    match self.data.take() {
+      Some((ddsdata, write_options, instance)) => {
+        let sequence_number = self.writer.next_sequence_number();
+        let wc = WriterCommand::DDSData {
+          ddsdata,
+          write_options,
+          sequence_number,
+          instance,
+        };
         match self.writer.cc_upload.try_send(wc) {
           Ok(()) => {
             self.writer.refresh_manual_liveliness();
             Poll::Ready(Ok(SampleIdentity {
               writer_guid: self.writer.my_guid,
-              sequence_number: self.sequence_number,
+              sequence_number,
             }))
           }
-          Err(TrySendError::Full(wc)) => {
+          Err(TrySendError::Full(WriterCommand::DDSData {
+            ddsdata,
+            write_options,
+            instance,
+            ..
+          })) => {
+            self.writer.undo_sequence_number(sequence_number);
             *self.writer.cc_upload_waker.lock().unwrap() = Some(cx.waker().clone());
-            if Instant::now() < self.timeout_instant {
-              // Put our command back
-              self.writer_command = Some(wc);
+            if Pin::new(&mut self.timeout_sleep).poll(cx).is_pending() {
+              // Keep our data for the next try
+              self.data = Some((ddsdata, write_options, instance));
               Poll::Pending
             } else {
               // TODO: unwrap
@@ -996,6 +1519,7 @@ where
               }))
             }
           }
+          Err(TrySendError::Full(_)) => unreachable!("try_send returned another command"),
           Err(other_err) => {
             warn!(
               "Failed to write new data: topic={:?}  reason={:?}  timeout={:?}",
@@ -1003,9 +1527,7 @@ where
               other_err,
               self.timeout
             );
-            // TODO: Is this (undo) the right thing to do, if there are
-            // several futures in progress? (Can this result in confused numbering?)
-            self.writer.undo_sequence_number();
+            self.writer.undo_sequence_number(sequence_number);
             Poll::Ready(Err(WriteError::Poisoned {
               reason: format!("{other_err}"),
               data: self.sample.take().unwrap(),
@@ -1034,13 +1556,14 @@ where
   SA: SerializerAdapter<D>,
 {
   Waiting {
-    ack_wait_receiver: StatusChannelReceiver<()>,
+    ack_wait_receiver: StatusChannelReceiver<bool>,
   },
   Done,
   WaitingSendCommand {
     writer: &'a DataWriter<D, SA>,
-    ack_wait_receiver: StatusChannelReceiver<()>,
-    ack_wait_sender: StatusChannelSender<()>,
+    ack_wait_receiver: StatusChannelReceiver<bool>,
+    ack_wait_sender: StatusChannelSender<bool>,
+    max_wait: Option<Duration>,
   },
   Fail(WriteError<()>),
 }
@@ -1076,20 +1599,20 @@ where
           //   // RecvError means the sending side has disconnected.
           //   // We assume this would only be because the event loop thread is dead.
           //   => Poll::Ready(Err(WriteError::Poisoned{ reason: "RecvError".to_string(), data:()})),
-          Poll::Ready(Some(())) => Poll::Ready(Ok(true)),
-          // There is no timeout support here, so we never really
-          // return Ok(false)
+          // false, if the Writer ended the wait at timeout
+          Poll::Ready(Some(all_acked)) => Poll::Ready(Ok(all_acked)),
         }
       }
       AsyncWaitForAcknowledgments::WaitingSendCommand { .. } => {
         let mut dummy = AsyncWaitForAcknowledgments::Done;
         core::mem::swap(&mut dummy, &mut self);
-        let (writer, ack_wait_receiver, ack_wait_sender) = match dummy {
+        let (writer, ack_wait_receiver, ack_wait_sender, max_wait) = match dummy {
           AsyncWaitForAcknowledgments::WaitingSendCommand {
             writer,
             ack_wait_receiver,
             ack_wait_sender,
-          } => (writer, ack_wait_receiver, ack_wait_sender),
+            max_wait,
+          } => (writer, ack_wait_receiver, ack_wait_sender, max_wait),
           _ => unreachable!(),
         };
 
@@ -1097,19 +1620,26 @@ where
           .cc_upload
           .try_send(WriterCommand::WaitForAcknowledgments {
             all_acked: ack_wait_sender,
+            max_wait,
           }) {
           Ok(()) => {
             *self = AsyncWaitForAcknowledgments::Waiting { ack_wait_receiver };
+            // Poll again right away, so that the receiver stores our waker.
+            cx.waker().wake_by_ref();
             Poll::Pending
           }
 
           Err(TrySendError::Full(WriterCommand::WaitForAcknowledgments {
             all_acked: ack_wait_sender,
+            max_wait,
           })) => {
+            // Wake up, when the Writer has made room in the channel.
+            *writer.cc_upload_waker.lock().unwrap() = Some(cx.waker().clone());
             *self = AsyncWaitForAcknowledgments::WaitingSendCommand {
               writer,
               ack_wait_receiver,
               ack_wait_sender,
+              max_wait,
             };
             Poll::Pending
           }
@@ -1155,6 +1685,8 @@ where
   ) -> WriteResult<SampleIdentity, D> {
     // Construct a future for an async write operation and await for its completion
 
+    let data = self.write_interceptors.intercept(data, &write_options)?;
+
     let send_buffer = match SA::to_bytes(&data) {
       Ok(s) => s,
       Err(e) => {
@@ -1169,32 +1701,50 @@ where
       SA::output_encoding(),
       send_buffer,
     ));
-    let sequence_number = self.next_sequence_number();
-    let writer_command = WriterCommand::DDSData {
-      ddsdata: dds_data,
-      write_options,
-      sequence_number,
-    };
+    let key = data.key();
+    let instance = self.instance_for_writer(&key);
+    let write_options = self.apply_content_filters(&data, write_options);
 
     let timeout = self.qos().reliable_max_blocking_time();
 
     let write_future = AsyncWrite {
       writer: self,
-      writer_command: Some(writer_command),
-      sequence_number,
+      data: Some((dds_data, write_options, instance)),
       timeout,
-      timeout_instant: std::time::Instant::now()
-        + timeout
-          .map(|t| t.to_std())
-          .unwrap_or(crate::dds::helpers::TIMEOUT_FALLBACK.to_std()),
+      timeout_sleep: Sleep::new(timeout.unwrap_or(TIMEOUT_FALLBACK).to_std()),
       sample: Some(data),
     };
-    write_future.await
+    let sample_identity = write_future.await?;
+    self.record_instance_update(&key, InstanceState::Alive);
+    Ok(sample_identity)
   }
 
   /// Like the synchronous version.
-  /// But there is no timeout. Use asyncs to bring your own timeout.
+  /// But there is no timeout. Use asyncs to bring your own timeout, or
+  /// [`Self::async_wait_for_acknowledgments_with_timeout`].
   pub async fn async_wait_for_acknowledgments(&self) -> WriteResult<bool, ()> {
+    self.async_wait_for_acknowledgments_opt(None).await
+  }
+
+  /// Like the synchronous version: waits until all matched reliable
+  /// DataReaders have acknowledged the data written so far, or until
+  /// `max_wait` has passed. Returns `Ok(false)` on timeout.
+  ///
+  /// The timeout is kept by the DomainParticipant event loop, so this works
+  /// with any async runtime.
+  pub async fn async_wait_for_acknowledgments_with_timeout(
+    &self,
+    max_wait: Duration,
+  ) -> WriteResult<bool, ()> {
+    self
+      .async_wait_for_acknowledgments_opt(Some(max_wait))
+      .await
+  }
+
+  async fn async_wait_for_acknowledgments_opt(
+    &self,
+    max_wait: Option<Duration>,
+  ) -> WriteResult<bool, ()> {
     match &self.qos_policy.reliability {
       None | Some(Reliability::BestEffort) => Ok(true),
       Some(Reliability::Reliable { .. }) => {
@@ -1202,12 +1752,13 @@ where
         // WaitForAcknowledgments command and then wait for the
         // acknowledgements. Await for this future to complete.
 
-        let (ack_wait_sender, ack_wait_receiver) = sync_status_channel::<()>(1).unwrap(); // TODO: remove unwrap
+        let (ack_wait_sender, ack_wait_receiver) = sync_status_channel::<bool>(1).unwrap(); // TODO: remove unwrap
 
         let async_ack_wait = AsyncWaitForAcknowledgments::WaitingSendCommand {
           writer: self,
           ack_wait_receiver,
           ack_wait_sender,
+          max_wait,
         };
         async_ack_wait.await
       }
@@ -1270,6 +1821,63 @@ mod tests {
     // TODO: write also with timestamp
   }
 
+  #[test]
+  fn dw_sample_reuse_test() {
+    let domain_participant = DomainParticipant::new(0).expect("Publisher creation failed!");
+    let qos = QosPolicies::qos_none();
+    let publisher = domain_participant
+      .create_publisher(&qos)
+      .expect("Failed to create publisher");
+    let topic = domain_participant
+      .create_topic(
+        "Reuse".to_string(),
+        "Huh?".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .expect("Failed to create topic");
+
+    let data_writer: DataWriter<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>> =
+      publisher
+        .create_datawriter(&topic, None)
+        .expect("Failed to create datawriter");
+
+    // Nothing to reuse yet
+    data_writer.set_sample_reuse(true);
+    assert!(data_writer.rewrite(WriteOptions::default()).is_err());
+
+    let data = RandomData {
+      a: 4,
+      b: "Fobar".to_string(),
+    };
+    let first = data_writer
+      .write_with_options(data, WriteOptions::default())
+      .expect("Unable to write data");
+    let second = data_writer
+      .rewrite(WriteOptions::default())
+      .expect("Unable to rewrite");
+    let third = data_writer
+      .write_modified(
+        |d| {
+          d.a += 1;
+          true
+        },
+        WriteOptions::default(),
+      )
+      .expect("Unable to write modified");
+    let fourth = data_writer
+      .write_modified(|_| false, WriteOptions::default())
+      .expect("Unable to write unmodified");
+    // Each one is a new sample
+    assert_eq!(
+      [first, second, third, fourth].map(|si| i64::from(si.sequence_number)),
+      [1, 2, 3, 4]
+    );
+
+    data_writer.set_sample_reuse(false);
+    assert!(data_writer.rewrite(WriteOptions::default()).is_err());
+  }
+
   #[test]
   fn dw_dispose_test() {
     let domain_participant = DomainParticipant::new(0).expect("Publisher creation failed!");
@@ -1302,15 +1910,64 @@ mod tests {
     data_writer
       .write(data.clone(), None)
       .expect("Unable to write data");
+    assert_eq!(data_writer.instance_statistics(0).alive, 1);
 
     thread::sleep(Duration::from_millis(100));
     data_writer
       .dispose(&data.key(), None)
       .expect("Unable to dispose data");
 
+    let statistics = data_writer.instance_statistics(1);
+    assert_eq!((statistics.instances, statistics.disposed), (1, 1));
+    assert_eq!(statistics.recently_updated[0].0, data.key());
+
     // TODO: verify that dispose is sent correctly
   }
 
+  #[test]
+  fn dw_dispose_all_test() {
+    let domain_participant = DomainParticipant::new(0).expect("Publisher creation failed!");
+    let qos = QosPolicies::qos_none();
+    let publisher = domain_participant
+      .create_publisher(&qos)
+      .expect("Failed to create publisher");
+    let topic = domain_participant
+      .create_topic(
+        "DisposeAll".to_string(),
+        "Huh?".to_string(),
+        &qos,
+        TopicKind::WithKey,
+      )
+      .expect("Failed to create topic");
+
+    let data_writer: DataWriter<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>> =
+      publisher
+        .create_datawriter(&topic, None)
+        .expect("Failed to create datawriter");
+
+    for a in 1..=3 {
+      let data = RandomData {
+        a,
+        b: "Fobar".to_string(),
+      };
+      data_writer.write(data, None).expect("Unable to write data");
+    }
+    data_writer.dispose(&2, None).expect("Unable to dispose");
+
+    // Only the alive instances are disposed
+    assert_eq!(data_writer.dispose_all(None).unwrap(), 2);
+    let statistics = data_writer.instance_statistics(0);
+    assert_eq!((statistics.instances, statistics.disposed), (3, 3));
+    assert_eq!(data_writer.dispose_all(None).unwrap(), 0);
+
+    assert_eq!(data_writer.unregister_all(None).unwrap(), 3);
+    assert_eq!(data_writer.instance_statistics(0).instances, 0);
+    assert_eq!(data_writer.unregister_all(None).unwrap(), 0);
+
+    // Each instance took one sequence number
+    assert_eq!(data_writer.next_sequence_number(), SequenceNumber::from(10));
+  }
+
   #[test]
   fn dw_wait_for_ack_test() {
     let domain_participant = DomainParticipant::new(0).expect("Participant creation failed!");
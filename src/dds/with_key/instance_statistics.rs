@@ -0,0 +1,113 @@
+use crate::{dds::sampleinfo::InstanceState, structure::time::Timestamp};
+
+/// Instance population of a keyed DataReader or DataWriter, from
+/// [`DataReader::instance_statistics`](crate::with_key::DataReader::instance_statistics)
+/// or
+/// [`DataWriter::instance_statistics`](crate::with_key::DataWriter::instance_statistics).
+///
+/// Instances are counted as long as the entity knows them, i.e. also after
+/// they have been disposed or all their samples have been taken.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstanceStatistics<K> {
+  /// All instances known to the entity
+  pub instances: usize,
+  /// Instances in state [`InstanceState::Alive`]
+  pub alive: usize,
+  /// Instances in state [`InstanceState::NotAliveDisposed`]
+  pub disposed: usize,
+  /// Instances in state [`InstanceState::NotAliveNoWriters`]. Always zero for
+  /// a DataWriter.
+  pub no_writers: usize,
+  /// The most recently updated instances, newest first, with their state and
+  /// the time of their latest update. At most as many as requested.
+  pub recently_updated: Vec<(K, InstanceState, Timestamp)>,
+}
+
+impl<K: Clone> InstanceStatistics<K> {
+  // Counts the instances given as (key, state, latest update), and picks the
+  // `most_recent` latest updated of them.
+  pub(crate) fn collect<'a>(
+    instances: impl Iterator<Item = (&'a K, InstanceState, Timestamp)>,
+    most_recent: usize,
+  ) -> Self
+  where
+    K: 'a,
+  {
+    let mut statistics = Self {
+      instances: 0,
+      alive: 0,
+      disposed: 0,
+      no_writers: 0,
+      recently_updated: Vec::new(),
+    };
+    let mut updates = Vec::new();
+    for (key, state, updated) in instances {
+      statistics.instances += 1;
+      match state {
+        InstanceState::Alive => statistics.alive += 1,
+        InstanceState::NotAliveDisposed => statistics.disposed += 1,
+        InstanceState::NotAliveNoWriters => statistics.no_writers += 1,
+      }
+      if most_recent > 0 {
+        updates.push((updated, key, state));
+      }
+    }
+
+    // Sort only the selected ones, as there may be many instances.
+    let newest_first =
+      |a: &(Timestamp, &K, InstanceState), b: &(Timestamp, &K, InstanceState)| b.0.cmp(&a.0);
+    if updates.len() > most_recent {
+      updates.select_nth_unstable_by(most_recent, newest_first);
+      updates.truncate(most_recent);
+    }
+    updates.sort_unstable_by(newest_first);
+    statistics.recently_updated = updates
+      .into_iter()
+      .map(|(updated, key, state)| (key.clone(), state, updated))
+      .collect();
+    statistics
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Duration;
+
+  #[test]
+  fn collect_counts_and_picks_most_recent() {
+    let at = |secs: i32| Timestamp::ZERO + Duration::from_secs(secs);
+    let instances = [
+      (1, InstanceState::Alive, at(3)),
+      (2, InstanceState::NotAliveDisposed, at(5)),
+      (3, InstanceState::Alive, at(1)),
+      (4, InstanceState::NotAliveNoWriters, at(4)),
+    ];
+    let iter = || instances.iter().map(|(k, s, t)| (k, *s, *t));
+
+    let statistics = InstanceStatistics::collect(iter(), 2);
+    assert_eq!(
+      statistics,
+      InstanceStatistics {
+        instances: 4,
+        alive: 2,
+        disposed: 1,
+        no_writers: 1,
+        recently_updated: vec![
+          (2, InstanceState::NotAliveDisposed, at(5)),
+          (4, InstanceState::NotAliveNoWriters, at(4)),
+        ],
+      }
+    );
+
+    assert!(InstanceStatistics::collect(iter(), 0)
+      .recently_updated
+      .is_empty());
+    assert_eq!(
+      InstanceStatistics::collect(iter(), 10)
+        .recently_updated
+        .len(),
+      4
+    );
+  }
+}
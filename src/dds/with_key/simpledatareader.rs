@@ -1,10 +1,10 @@
 use std::{
-  cmp::max,
-  collections::BTreeMap,
+  cmp::{max, min},
+  collections::{BTreeMap, BTreeSet},
   io,
   marker::PhantomData,
   pin::Pin,
-  sync::{Arc, Mutex, MutexGuard},
+  sync::{Arc, Mutex, MutexGuard, RwLock},
   task::{Context, Poll, Waker},
 };
 
@@ -15,34 +15,61 @@ use mio_extras::channel as mio_channel;
 use log::{debug, error, info, trace, warn};
 
 use crate::{
+  create_error_bad_parameter,
   dds::{
     adapters::with_key::{Decode, DefaultDecoder, DeserializerAdapter},
+    communication_status::{
+      Entity, LivelinessChangedStatus, ReaderStatusRecord, RequestedDeadlineMissedStatus,
+      RequestedIncompatibleQosStatus, SampleLostStatus, SampleRejectedStatus, StatusMask,
+      SubscriptionMatchedStatus,
+    },
+    content_filter::{ContentFilterFactory, SampleFilter},
     ddsdata::*,
+    interceptor::{ReadInterceptor, ReadInterceptorChain},
     key::*,
+    listener::DataReaderListener,
     pubsub::Subscriber,
     qos::*,
     result::*,
+    sliced::SampleSlices,
     statusevents::*,
     topic::{Topic, TopicDescription},
+    waitset::StatusCondition,
     with_key::datasample::{DeserializedCacheChange, Sample},
   },
-  discovery::discovery::DiscoveryCommand,
+  discovery::{content_filter_property::ContentFilterProperty, discovery::DiscoveryCommand},
+  messages::submessages::elements::serialized_payload::SerializedPayload,
   mio_source::PollEventSource,
   serialization::CDRDeserializerAdapter,
   structure::{
     cache_change::CacheChange,
-    dds_cache::TopicCache,
+    dds_cache::{CacheStatistics, TopicCache},
     entity::RTPSEntity,
     guid::{EntityId, GUID},
+    rpc::SampleIdentity,
     sequence_number::SequenceNumber,
     time::Timestamp,
   },
 };
 
+// What to do with a change taken from the topic cache
+enum Taken<T> {
+  // Leave the change unread and look at the next one
+  Ignore,
+  // Mark the change read and look at the next one
+  Skip,
+  // Mark the change read and return this
+  Result(ReadResult<T>),
+}
+
 #[derive(Clone, Debug)]
 pub(crate) enum ReaderCommand {
   #[allow(dead_code)] // TODO: Implement this (resetting) feature
   ResetRequestedDeadlineStatus,
+  // Samples that the application has processed, by Writer
+  AcknowledgeSamples {
+    samples: BTreeMap<GUID, Vec<SequenceNumber>>,
+  },
 }
 
 // This is helper struct.
@@ -51,10 +78,17 @@ pub(crate) struct ReadState<K: Key> {
   latest_instant: Timestamp, /* This is used as a read pointer from dds_cache for BEST_EFFORT
                               * reading */
   last_read_sn: BTreeMap<GUID, SequenceNumber>, // collection of read pointers for RELIABLE reading
+  // With DeliveryOrder::AsReceived, sequence numbers above last_read_sn, which
+  // have already been read out of order.
+  read_ahead_sn: BTreeMap<GUID, BTreeSet<SequenceNumber>>,
   /// hash_to_key_map is used for decoding received key hashes back to original
   /// key values. This is needed when we receive a dispose message via hash
   /// only.
   hash_to_key_map: BTreeMap<KeyHash, K>, // TODO: garbage collect this somehow
+  // Writer restart counts from TopicCache, as of our last read.
+  writer_restarts: BTreeMap<GUID, u32>,
+  // Writer loss counts from TopicCache, as of our last check.
+  writer_losses: BTreeMap<GUID, u32>,
 }
 
 impl<K: Key> ReadState<K> {
@@ -62,25 +96,86 @@ impl<K: Key> ReadState<K> {
     ReadState {
       latest_instant: Timestamp::ZERO,
       last_read_sn: BTreeMap::new(),
+      read_ahead_sn: BTreeMap::new(),
       hash_to_key_map: BTreeMap::<KeyHash, K>::new(),
+      writer_restarts: BTreeMap::new(),
+      writer_losses: BTreeMap::new(),
+    }
+  }
+
+  // A restarted Writer reuses sequence numbers from the beginning, so the read
+  // pointer must be reset. Otherwise the new samples look like they have
+  // already been read.
+  fn follow_writer_restarts(&mut self, topic_cache_restarts: &BTreeMap<GUID, u32>) {
+    for (writer, restarts) in topic_cache_restarts {
+      if self.writer_restarts.get(writer) != Some(restarts) {
+        self.writer_restarts.insert(*writer, *restarts);
+        self.last_read_sn.remove(writer);
+        self.read_ahead_sn.remove(writer);
+      }
+    }
+  }
+
+  // Returns the Writers that have been lost since the previous call.
+  fn follow_writer_losses(&mut self, topic_cache_losses: &BTreeMap<GUID, u32>) -> Vec<GUID> {
+    let mut lost = Vec::new();
+    for (writer, losses) in topic_cache_losses {
+      if self.writer_losses.get(writer) != Some(losses) {
+        self.writer_losses.insert(*writer, *losses);
+        lost.push(*writer);
+      }
+    }
+    lost
+  }
+
+  // With DeliveryOrder::AsReceived, last_read_sn is advanced only up to the
+  // point, before which all samples have either been read, or will never be
+  // available. Samples read beyond it are remembered in read_ahead_sn.
+  fn mark_read_as_received(&mut self, writer: GUID, sn: SequenceNumber, topic_cache: &TopicCache) {
+    let read_ahead = self.read_ahead_sn.entry(writer).or_default();
+    read_ahead.insert(sn);
+    let last_read = self
+      .last_read_sn
+      .entry(writer)
+      .or_insert(SequenceNumber::zero());
+    let reliable_before = topic_cache.reliable_before(writer);
+    loop {
+      let next = last_read.plus_1();
+      if read_ahead.remove(&next) {
+        *last_read = next;
+      } else if next < reliable_before {
+        // Missing samples before reliable_before are not coming anymore.
+        match topic_cache.first_cached_sn_from(writer, next) {
+          Some(cached) if cached == next => break, // not read yet
+          Some(cached) => *last_read = min(cached, reliable_before) - SequenceNumber::new(1),
+          None => *last_read = reliable_before - SequenceNumber::new(1),
+        }
+      } else {
+        break;
+      }
     }
+    // Forget read-ahead samples that were skipped over, e.g. evicted
+    *read_ahead = read_ahead.split_off(&last_read.plus_1());
   }
 
   // This is a helper function so that borrow checker understands
   // that we are splitting one mutable borrow into two _disjoint_ mutable
   // borrows.
-  fn get_sn_map_and_hash_map(
+  #[allow(clippy::type_complexity)]
+  fn get_sn_maps_and_hash_map(
     &mut self,
   ) -> (
     &mut BTreeMap<GUID, SequenceNumber>,
+    &BTreeMap<GUID, BTreeSet<SequenceNumber>>,
     &mut BTreeMap<KeyHash, K>,
   ) {
     let ReadState {
       last_read_sn,
+      read_ahead_sn,
       hash_to_key_map,
       ..
     } = self;
-    (last_read_sn, hash_to_key_map)
+    (last_read_sn, read_ahead_sn, hash_to_key_map)
   }
 }
 
@@ -105,6 +200,7 @@ pub struct SimpleDataReader<D: Keyed, DA: DeserializerAdapter<D> = CDRDeserializ
 
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   status_receiver: StatusChannelReceiver<DataReaderStatus>,
+  status_record: Arc<ReaderStatusRecord>,
 
   #[allow(dead_code)] // TODO: This is currently unused, because we do not implement
   // resetting deadline missed status. Remove attribute when it is supported.
@@ -112,6 +208,10 @@ pub struct SimpleDataReader<D: Keyed, DA: DeserializerAdapter<D> = CDRDeserializ
   data_reader_waker: Arc<Mutex<Option<Waker>>>,
 
   event_source: PollEventSource,
+
+  read_interceptors: ReadInterceptorChain<D>,
+  // Samples that do not pass are dropped
+  content_filter: RwLock<Option<SampleFilter<D>>>,
 }
 
 impl<D, DA> Drop for SimpleDataReader<D, DA>
@@ -120,8 +220,18 @@ where
   DA: DeserializerAdapter<D>,
 {
   fn drop(&mut self) {
-    // Tell dp_event_loop
-    self.my_subscriber.remove_reader(self.my_guid);
+    // Tell dp_event_loop. If the Subscriber has deleted this DataReader
+    // already, Discovery has been told also.
+    let removed = self.my_subscriber.remove_reader(self.my_guid);
+
+    // Our read progress no longer holds back cache eviction
+    if let Ok(mut tc) = self.topic_cache.lock() {
+      tc.unregister_reader(self.my_guid);
+    }
+
+    if !removed {
+      return;
+    }
 
     // Tell discovery
     match self
@@ -159,6 +269,7 @@ where
     reader_command: mio_channel::SyncSender<ReaderCommand>,
     data_reader_waker: Arc<Mutex<Option<Waker>>>,
     event_source: PollEventSource,
+    status_record: Arc<ReaderStatusRecord>,
   ) -> CreateResult<Self> {
     let dp = match subscriber.participant() {
       Some(dp) => dp,
@@ -182,6 +293,7 @@ where
         ),
       });
     }
+    topic_cache.lock().unwrap().register_reader(my_guid);
 
     Ok(Self {
       my_subscriber: subscriber,
@@ -194,11 +306,102 @@ where
       deserializer_type: PhantomData,
       discovery_command,
       status_receiver,
+      status_record,
       reader_command,
       data_reader_waker,
       event_source,
+      read_interceptors: ReadInterceptorChain::new(),
+      content_filter: RwLock::new(None),
     })
   }
+
+  /// Registers an interceptor that is called for each received sample after
+  /// deserialization. Vetoed samples are dropped.
+  ///
+  /// Interceptors are called in the order they were added. If one of them
+  /// vetoes the sample, the remaining interceptors are not called.
+  pub fn add_read_interceptor<I>(&self, interceptor: I)
+  where
+    I: ReadInterceptor<D> + 'static,
+  {
+    self.read_interceptors.add(Arc::new(interceptor));
+  }
+
+  /// Removes all interceptors added with
+  /// [`add_read_interceptor`](Self::add_read_interceptor).
+  pub fn clear_read_interceptors(&self) {
+    self.read_interceptors.clear();
+  }
+
+  /// Sets a content filter created by `factory`. Only the samples that pass
+  /// the filter are received. The filter is advertised in Discovery, so that
+  /// DataWriters with a factory of the same class registered filter the
+  /// samples before sending. See [`content_filter`](crate::content_filter).
+  ///
+  /// Disposals always pass. Replaces any filter set earlier.
+  pub fn set_content_filter(
+    &self,
+    factory: &ContentFilterFactory<D>,
+    filter_expression: &str,
+    expression_parameters: Vec<String>,
+  ) -> CreateResult<()> {
+    let content_filtered_topic_name = format!("{}_filtered", self.my_topic.name());
+    self.set_named_content_filter(
+      content_filtered_topic_name,
+      factory,
+      filter_expression,
+      expression_parameters,
+    )
+  }
+
+  // Like set_content_filter, but the name of the content-filtered topic that
+  // is advertised in Discovery is given.
+  pub(crate) fn set_named_content_filter(
+    &self,
+    content_filtered_topic_name: String,
+    factory: &ContentFilterFactory<D>,
+    filter_expression: &str,
+    expression_parameters: Vec<String>,
+  ) -> CreateResult<()> {
+    let filter = match factory.create_filter(filter_expression, &expression_parameters) {
+      Ok(filter) => filter,
+      Err(e) => {
+        return create_error_bad_parameter!(
+          "Cannot create content filter {:?}: {}",
+          factory.class_name(),
+          e
+        )
+      }
+    };
+    *self.content_filter.write().unwrap() = Some(filter);
+    let topic_name = self.my_topic.name();
+    self.my_subscriber.set_reader_content_filter(
+      self.my_guid,
+      Some(ContentFilterProperty {
+        content_filtered_topic_name,
+        related_topic_name: topic_name,
+        filter_class_name: factory.class_name().to_string(),
+        filter_expression: filter_expression.to_string(),
+        expression_parameters,
+      }),
+    )
+  }
+
+  /// Removes the filter set with [`set_content_filter`](Self::set_content_filter).
+  pub fn clear_content_filter(&self) -> CreateResult<()> {
+    *self.content_filter.write().unwrap() = None;
+    self
+      .my_subscriber
+      .set_reader_content_filter(self.my_guid, None)
+  }
+
+  fn passes_content_filter(&self, sample: &Sample<D, D::K>) -> bool {
+    match (sample, self.content_filter.read().unwrap().as_ref()) {
+      (Sample::Value(value), Some(filter)) => filter(value),
+      _ => true,
+    }
+  }
+
   pub(crate) fn set_waker(&self, w: Option<Waker>) {
     *self.data_reader_waker.lock().unwrap() = w;
   }
@@ -207,15 +410,31 @@ where
     let rec = self.notification_receiver.lock().unwrap();
     while rec.try_recv().is_ok() {}
     self.event_source.drain();
+    self.status_record.data_read();
+  }
+
+  // Writers that have been lost since the previous call. The DataReader
+  // updates the states of their instances.
+  pub(crate) fn take_lost_writers(&self) -> Vec<GUID> {
+    let topic_cache = self.acquire_the_topic_cache_guard();
+    self
+      .read_state
+      .lock()
+      .unwrap()
+      .follow_writer_losses(topic_cache.writer_losses())
   }
 
   fn try_take_undecoded<'a>(
     is_reliable: bool,
+    as_received: bool,
     topic_cache: &'a TopicCache,
     latest_instant: Timestamp,
     last_read_sn: &'a BTreeMap<GUID, SequenceNumber>,
+    read_ahead_sn: &'a BTreeMap<GUID, BTreeSet<SequenceNumber>>,
   ) -> Box<dyn Iterator<Item = (Timestamp, &'a CacheChange)> + 'a> {
-    if is_reliable {
+    if is_reliable && as_received {
+      topic_cache.get_changes_as_received(last_read_sn, read_ahead_sn)
+    } else if is_reliable {
       topic_cache.get_changes_in_range_reliable(last_read_sn)
     } else {
       topic_cache.get_changes_in_range_best_effort(latest_instant, Timestamp::now())
@@ -246,46 +465,29 @@ where
     match cc.data_value {
       DDSData::Data {
         ref serialized_payload,
-      } => {
-        // what is our data serialization format (representation identifier) ?
-        if let Some(recognized_rep_id) = DA::supported_encodings()
-          .iter()
-          .find(|r| **r == serialized_payload.representation_identifier)
-        {
-          match DA::from_bytes_with(&serialized_payload.value, *recognized_rep_id, decoder) {
-            // Data update, decoded ok
-            Ok(payload) => {
-              let p = Sample::Value(payload);
-              Self::update_hash_to_key_map(hash_to_key_map, &p);
-              Ok(DeserializedCacheChange::new(timestamp, cc, p))
-            }
-            Err(e) => Err(ReadError::Deserialization {
-              reason: format!(
-                "Failed to deserialize sample bytes: {}, , Topic = {}, Type = {:?}",
-                e,
-                self.my_topic.name(),
-                self.my_topic.get_type()
-              ),
-            }),
+      } => self.deserialize_payload(timestamp, cc, serialized_payload, hash_to_key_map, decoder),
+
+      // Delivered in process. A clone of the written value, if the DataWriter
+      // has the same data type. Otherwise it is deserialized as usual.
+      DDSData::Local { ref sample } => match sample.value::<D>() {
+        Some(value) => {
+          let p = Sample::Value(value);
+          Self::update_hash_to_key_map(hash_to_key_map, &p);
+          Ok(DeserializedCacheChange::new(timestamp, cc, p))
+        }
+        None => match sample.serialized_payload() {
+          Some(serialized_payload) => {
+            self.deserialize_payload(timestamp, cc, &serialized_payload, hash_to_key_map, decoder)
           }
-        } else {
-          info!(
-            "Unknown representation id: {:?} , Topic = {}, Type = {:?} data = {:02x?}",
-            serialized_payload.representation_identifier,
-            self.my_topic.name(),
-            self.my_topic.get_type(),
-            serialized_payload.value,
-          );
-          Err(ReadError::Deserialization {
+          None => Err(ReadError::Deserialization {
             reason: format!(
-              "Unknown representation id {:?} , Topic = {}, Type = {:?}",
-              serialized_payload.representation_identifier,
+              "Cannot serialize local sample, Topic = {}, Type = {:?}",
               self.my_topic.name(),
               self.my_topic.get_type()
             ),
-          })
-        }
-      }
+          }),
+        },
+      },
 
       DDSData::DisposeByKey {
         key: ref serialized_key,
@@ -335,6 +537,57 @@ where
     } // match
   }
 
+  fn deserialize_payload<S>(
+    &self,
+    timestamp: Timestamp,
+    cc: &CacheChange,
+    serialized_payload: &SerializedPayload,
+    hash_to_key_map: &mut BTreeMap<KeyHash, D::K>,
+    decoder: S,
+  ) -> ReadResult<DeserializedCacheChange<D>>
+  where
+    S: Decode<DA::Decoded, DA::DecodedKey>,
+  {
+    // what is our data serialization format (representation identifier) ?
+    if let Some(recognized_rep_id) = DA::supported_encodings()
+      .iter()
+      .find(|r| **r == serialized_payload.representation_identifier)
+    {
+      match DA::from_bytes_with(&serialized_payload.value, *recognized_rep_id, decoder) {
+        // Data update, decoded ok
+        Ok(payload) => {
+          let p = Sample::Value(payload);
+          Self::update_hash_to_key_map(hash_to_key_map, &p);
+          Ok(DeserializedCacheChange::new(timestamp, cc, p))
+        }
+        Err(e) => Err(ReadError::Deserialization {
+          reason: format!(
+            "Failed to deserialize sample bytes: {}, , Topic = {}, Type = {:?}",
+            e,
+            self.my_topic.name(),
+            self.my_topic.get_type()
+          ),
+        }),
+      }
+    } else {
+      info!(
+        "Unknown representation id: {:?} , Topic = {}, Type = {:?} data = {:02x?}",
+        serialized_payload.representation_identifier,
+        self.my_topic.name(),
+        self.my_topic.get_type(),
+        serialized_payload.value,
+      );
+      Err(ReadError::Deserialization {
+        reason: format!(
+          "Unknown representation id {:?} , Topic = {}, Type = {:?}",
+          serialized_payload.representation_identifier,
+          self.my_topic.name(),
+          self.my_topic.get_type()
+        ),
+      })
+    }
+  }
+
   /// Note: Always remember to call .drain_read_notifications() just before
   /// calling this one. Otherwise, new notifications may not appear.
   pub fn try_take_one(&self) -> ReadResult<Option<DeserializedCacheChange<D>>>
@@ -351,55 +604,186 @@ where
   where
     S: Decode<DA::Decoded, DA::DecodedKey> + Clone,
   {
+    self.try_take_next(|timestamp, cc, hash_to_key_map| {
+      let mut result = self.deserialize_with(timestamp, cc, hash_to_key_map, decoder.clone());
+      let vetoed = match result {
+        Ok(ref mut dcc) => {
+          !self.passes_content_filter(&dcc.sample)
+            || !self.read_interceptors.intercept(
+              &mut dcc.sample,
+              dcc.writer_guid,
+              &dcc.write_options,
+            )
+        }
+        Err(_) => false,
+      };
+
+      if let Err(ReadError::UnknownKey { .. }) = result {
+        // ignore unknown key hash, continue looping
+        Taken::Ignore
+      } else if vetoed {
+        Taken::Skip
+      } else {
+        Taken::Result(result)
+      }
+    })
+  }
+
+  /// Takes the next sample in serialized form, without deserializing it. See
+  /// [`SampleSlices`]. Disposals are skipped, and read interceptors and the
+  /// content filter are not applied.
+  ///
+  /// Note: Always remember to call .drain_read_notifications() just before
+  /// calling this one. Otherwise, new notifications may not appear.
+  pub fn try_take_one_sliced(&self) -> ReadResult<Option<SampleSlices>> {
+    self.try_take_next(
+      |timestamp, cc, _| match SampleSlices::from_cache_change(timestamp, cc) {
+        Some(slices) => Taken::Result(Ok(slices)),
+        None => Taken::Skip,
+      },
+    )
+  }
+
+  // Takes the next unread change from the topic cache and gives it to `take`,
+  // which decides what becomes of it.
+  fn try_take_next<T>(
+    &self,
+    mut take: impl FnMut(Timestamp, &CacheChange, &mut BTreeMap<KeyHash, D::K>) -> Taken<T>,
+  ) -> ReadResult<Option<T>> {
     let is_reliable = matches!(
       self.qos_policy.reliability(),
       Some(policy::Reliability::Reliable { .. })
     );
+    let as_received = self.qos_policy.delivery_order() == Some(policy::DeliveryOrder::AsReceived);
 
-    let topic_cache = self.acquire_the_topic_cache_guard();
+    let mut topic_cache = self.acquire_the_topic_cache_guard();
 
     let mut read_state_ref = self.read_state.lock().unwrap();
-    let latest_instant = read_state_ref.latest_instant;
-    let (last_read_sn, hash_to_key_map) = read_state_ref.get_sn_map_and_hash_map();
+    read_state_ref.follow_writer_restarts(topic_cache.writer_restarts());
 
     // loop in case we get a sample that should be ignored, so we try next.
     loop {
-      let (timestamp, cc) =
-        match Self::try_take_undecoded(is_reliable, &topic_cache, latest_instant, last_read_sn)
-          .next()
-        {
-          None => return Ok(None), // no more data available right now
-          Some((ts, cc)) => (ts, cc),
-        };
-
-      let result = self.deserialize_with(timestamp, cc, hash_to_key_map, decoder.clone());
-
-      if let Err(ReadError::UnknownKey { .. }) = result {
-        // ignore unknown key hash, continue looping
+      let latest_instant = read_state_ref.latest_instant;
+      let (last_read_sn, read_ahead_sn, hash_to_key_map) =
+        read_state_ref.get_sn_maps_and_hash_map();
+      let (timestamp, cc) = match Self::try_take_undecoded(
+        is_reliable,
+        as_received,
+        &topic_cache,
+        latest_instant,
+        last_read_sn,
+        read_ahead_sn,
+      )
+      .next()
+      {
+        None => return Ok(None), // no more data available right now
+        Some((ts, cc)) => (ts, cc),
+      };
+
+      let result = match take(timestamp, cc, hash_to_key_map) {
+        Taken::Ignore => continue,
+        Taken::Skip => None,
+        Taken::Result(result) => Some(result),
+      };
+      // make copies of guid and SN to calm down borrow checker.
+      let writer_guid = cc.writer_guid;
+      let sequence_number = cc.sequence_number;
+      // Advance read pointer, error or not, because otherwise
+      // the SimpleDatareader is stuck.
+      read_state_ref.latest_instant = max(latest_instant, timestamp);
+      let read_up_to = if is_reliable && as_received {
+        read_state_ref.mark_read_as_received(writer_guid, sequence_number, &topic_cache);
+        read_state_ref.last_read_sn[&writer_guid]
       } else {
-        // return with this result
-        // make copies of guid and SN to calm down borrow checker.
-        let writer_guid = cc.writer_guid;
-        let sequence_number = cc.sequence_number;
-        // Advance read pointer, error or not, because otherwise
-        // the SimpleDatareader is stuck.
-        read_state_ref.latest_instant = max(latest_instant, timestamp);
         read_state_ref
           .last_read_sn
           .insert(writer_guid, sequence_number);
-
-        // // Debug sanity check:
-        // use crate::Duration;
-        // if Timestamp::now().duration_since(timestamp) > Duration::from_secs(1) {
-        //   error!("Sample delayed by {:?} , Topic = {} {:?}",
-        //     Timestamp::now().duration_since(timestamp), self.topic().name(),
-        //     sequence_number,
-        //      );
-        // }
-
+        sequence_number
+      };
+      topic_cache.mark_read(self.my_guid, writer_guid, read_up_to);
+
+      // // Debug sanity check:
+      // use crate::Duration;
+      // if Timestamp::now().duration_since(timestamp) > Duration::from_secs(1) {
+      //   error!("Sample delayed by {:?} , Topic = {} {:?}",
+      //     Timestamp::now().duration_since(timestamp), self.topic().name(),
+      //     sequence_number,
+      //      );
+      // }
+
+      if let Some(result) = result {
         return result.map(Some);
       }
+      // else try next sample
+    }
+  }
+
+  /// Statistics of evicting received samples, as configured by the
+  /// [`CacheWatermarks`](crate::policy::CacheWatermarks) QoS policy.
+  pub fn cache_statistics(&self) -> CacheStatistics {
+    self.acquire_the_topic_cache_guard().statistics()
+  }
+
+  /// Tells the DataWriters of the samples that the application has processed
+  /// them. See the [`Acknowledgment`](crate::policy::Acknowledgment) QoS
+  /// policy, without which this does nothing.
+  pub fn acknowledge(&self, samples: impl IntoIterator<Item = SampleIdentity>) -> ReadResult<()> {
+    let mut by_writer: BTreeMap<GUID, Vec<SequenceNumber>> = BTreeMap::new();
+    for sample in samples {
+      by_writer
+        .entry(sample.writer_guid)
+        .or_default()
+        .push(sample.sequence_number);
     }
+    if by_writer.is_empty() {
+      return Ok(());
+    }
+    self
+      .reader_command
+      .send(ReaderCommand::AcknowledgeSamples { samples: by_writer })
+      .map_err(|e| ReadError::Poisoned {
+        reason: format!("Cannot send to Reader: {e:?}"),
+      })
+  }
+
+  /// The SAMPLE_REJECTED communication status. Resets its change count. See
+  /// [`communication_status`](crate::communication_status).
+  pub fn get_sample_rejected_status(&self) -> SampleRejectedStatus {
+    self.status_record.take_sample_rejected()
+  }
+
+  /// The LIVELINESS_CHANGED communication status. Resets its change counts.
+  pub fn get_liveliness_changed_status(&self) -> LivelinessChangedStatus {
+    self.status_record.take_liveliness_changed()
+  }
+
+  /// The REQUESTED_DEADLINE_MISSED communication status. Resets its change
+  /// count.
+  pub fn get_requested_deadline_missed_status(&self) -> RequestedDeadlineMissedStatus {
+    self.status_record.take_requested_deadline_missed()
+  }
+
+  /// The REQUESTED_INCOMPATIBLE_QOS communication status. Resets its change
+  /// count.
+  pub fn get_requested_incompatible_qos_status(&self) -> RequestedIncompatibleQosStatus {
+    self.status_record.take_requested_incompatible_qos()
+  }
+
+  /// The SAMPLE_LOST communication status. Resets its change count.
+  pub fn get_sample_lost_status(&self) -> SampleLostStatus {
+    self.status_record.take_sample_lost()
+  }
+
+  /// The SUBSCRIPTION_MATCHED communication status. Resets its change counts.
+  pub fn get_subscription_matched_status(&self) -> SubscriptionMatchedStatus {
+    self.status_record.take_subscription_matched()
+  }
+
+  /// Sets the listener that is called back as the communication statuses of
+  /// this DataReader change, replacing any previous one. `None` removes it.
+  /// See [`listener`](crate::listener).
+  pub fn set_listener(&self, listener: Option<Box<dyn DataReaderListener>>) {
+    self.status_record.listener.set(listener);
   }
 
   pub fn qos(&self) -> &QosPolicies {
@@ -549,6 +933,20 @@ where
   }
 }
 
+impl<D, DA> Entity for SimpleDataReader<D, DA>
+where
+  D: Keyed,
+  DA: DeserializerAdapter<D>,
+{
+  fn get_status_changes(&self) -> StatusMask {
+    self.status_record.changes()
+  }
+
+  fn get_statuscondition(&self) -> StatusCondition {
+    StatusCondition::new(self.status_record.clone())
+  }
+}
+
 // ----------------------------------------------
 // ----------------------------------------------
 
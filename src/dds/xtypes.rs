@@ -0,0 +1,1229 @@
+//! Type descriptions and the XTypes assignability rules.
+//!
+//! DDS-XTypes defines when data written with one version of a type can be
+//! read with another. In XTypes terms, the reader type (the *target*) is
+//! assignable from the writer type (the *source*), if every sample of the
+//! source type can be read as a sample of the target type. Which changes are
+//! allowed depends on the [`Extensibility`] of the types:
+//!
+//! * [`Final`](Extensibility::Final) types cannot change at all.
+//! * [`Appendable`](Extensibility::Appendable) types may gain or lose members
+//!   at the end.
+//! * [`Mutable`](Extensibility::Mutable) types may gain or lose members
+//!   anywhere. Members are matched by their member ids.
+//!
+//! Key members can never be added or removed, and member types must be
+//! assignable in turn. Primitive types must be identical.
+//!
+//! [`is_assignable`] compares two [`TypeObject`]s and lists all the
+//! differences, so that e.g. a CI pipeline can check changes of message
+//! definitions before they are deployed. [`check_type_evolution`] checks both
+//! directions, which is what a rolling upgrade needs.
+//!
+//! ```
+//! use rustdds::xtypes::*;
+//!
+//! let old = TypeObject::Struct(StructType::new(
+//!   "Pose",
+//!   Extensibility::Appendable,
+//!   vec![
+//!     StructMember::new(0, "x", TypeObject::Float64),
+//!     StructMember::new(1, "y", TypeObject::Float64),
+//!   ],
+//! ));
+//! let mut new = old.clone();
+//! if let TypeObject::Struct(s) = &mut new {
+//!   s.members.push(StructMember::new(2, "z", TypeObject::Float64));
+//! }
+//!
+//! let evolution = check_type_evolution(&old, &new);
+//! assert!(evolution.is_fully_compatible());
+//! for difference in evolution.new_reads_old.differences() {
+//!   println!("{difference}");
+//! }
+//! ```
+//!
+//! The type model is a simplified form of the XTypes TypeObject. Struct
+//! inheritance is not modeled: the members of base types are listed in the
+//! derived type. Bitmasks and bitsets are not supported.
+//!
+//! Discovery uses the types for matching. A topic created with
+//! [`create_topic_with_type_object`] announces the [`TypeInformation`] of its
+//! type with its readers and writers. Remote endpoints with a different type
+//! are matched only if the types are assignable. The type of a remote
+//! endpoint is fetched from its participant with the TypeLookup service, when
+//! needed. Endpoints without type information are matched by topic name
+//! alone, as before.
+//!
+//! [`create_topic_with_type_object`]: crate::DomainParticipant::create_topic_with_type_object
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use speedy::{Context, Endianness, Readable, Reader, Writable, Writer};
+
+use crate::serialization::xcdr2::{to_vec_xcdr2, Xcdr2Encoding};
+
+/// Extensibility kind of a constructed type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Extensibility {
+  Final,
+  Appendable,
+  Mutable,
+}
+
+/// Description of a data type.
+///
+/// `None` as a bound means unbounded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TypeObject {
+  Boolean,
+  Byte,
+  Int8,
+  UInt8,
+  Int16,
+  UInt16,
+  Int32,
+  UInt32,
+  Int64,
+  UInt64,
+  Float32,
+  Float64,
+  Float128,
+  Char8,
+  Char16,
+  String {
+    bound: Option<u32>,
+  },
+  WString {
+    bound: Option<u32>,
+  },
+  Sequence {
+    element: Box<TypeObject>,
+    bound: Option<u32>,
+  },
+  Array {
+    element: Box<TypeObject>,
+    dimensions: Vec<u32>,
+  },
+  Map {
+    key: Box<TypeObject>,
+    value: Box<TypeObject>,
+    bound: Option<u32>,
+  },
+  /// Another name for a type. Aliases are transparent to assignability.
+  Alias {
+    name: String,
+    base: Box<TypeObject>,
+  },
+  Enum(EnumType),
+  Struct(StructType),
+  Union(UnionType),
+}
+
+impl TypeObject {
+  // The type behind any aliases
+  fn resolved(&self) -> &Self {
+    let mut t = self;
+    while let Self::Alias { base, .. } = t {
+      t = base;
+    }
+    t
+  }
+
+  /// The minimal form of this type, as in the XTypes minimal TypeObject.
+  ///
+  /// Type names are left out, and the names of members, enum literals and
+  /// union cases are replaced by hashes of the names. Aliases are resolved.
+  /// The assignability of minimal types is the same as that of the complete
+  /// types, but the differences are not as readable.
+  pub fn minimal(&self) -> Self {
+    let boxed = |t: &Self| Box::new(t.minimal());
+    match self.resolved() {
+      Self::Sequence { element, bound } => Self::Sequence {
+        element: boxed(element),
+        bound: *bound,
+      },
+      Self::Array {
+        element,
+        dimensions,
+      } => Self::Array {
+        element: boxed(element),
+        dimensions: dimensions.clone(),
+      },
+      Self::Map { key, value, bound } => Self::Map {
+        key: boxed(key),
+        value: boxed(value),
+        bound: *bound,
+      },
+      Self::Enum(e) => Self::Enum(EnumType {
+        name: String::new(),
+        extensibility: e.extensibility,
+        literals: e
+          .literals
+          .iter()
+          .map(|l| EnumLiteral {
+            name: name_hash(&l.name),
+            value: l.value,
+          })
+          .collect(),
+      }),
+      Self::Struct(s) => Self::Struct(StructType {
+        name: String::new(),
+        extensibility: s.extensibility,
+        members: s
+          .members
+          .iter()
+          .map(|m| StructMember {
+            id: m.id,
+            name: name_hash(&m.name),
+            member_type: m.member_type.minimal(),
+            key: m.key,
+            optional: m.optional,
+          })
+          .collect(),
+      }),
+      Self::Union(u) => Self::Union(UnionType {
+        name: String::new(),
+        extensibility: u.extensibility,
+        discriminator: boxed(&u.discriminator),
+        cases: u
+          .cases
+          .iter()
+          .map(|c| UnionCase {
+            id: c.id,
+            name: name_hash(&c.name),
+            member_type: c.member_type.minimal(),
+            labels: c.labels.clone(),
+            is_default: c.is_default,
+          })
+          .collect(),
+      }),
+      other => other.clone(),
+    }
+  }
+}
+
+// The NameHash of XTypes: the first 4 bytes of the MD5 of the name, here in
+// hex, so that it can stand in for the name.
+fn name_hash(name: &str) -> String {
+  let digest = md5::compute(name.as_bytes());
+  digest.0[..4].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn fmt_bound(f: &mut fmt::Formatter<'_>, bound: Option<u32>) -> fmt::Result {
+  match bound {
+    Some(b) => write!(f, ", {b}>"),
+    None => write!(f, ">"),
+  }
+}
+
+/// IDL-like type name, e.g. `sequence<int32, 10>` or the name of a struct.
+impl fmt::Display for TypeObject {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Boolean => write!(f, "boolean"),
+      Self::Byte => write!(f, "octet"),
+      Self::Int8 => write!(f, "int8"),
+      Self::UInt8 => write!(f, "uint8"),
+      Self::Int16 => write!(f, "int16"),
+      Self::UInt16 => write!(f, "uint16"),
+      Self::Int32 => write!(f, "int32"),
+      Self::UInt32 => write!(f, "uint32"),
+      Self::Int64 => write!(f, "int64"),
+      Self::UInt64 => write!(f, "uint64"),
+      Self::Float32 => write!(f, "float"),
+      Self::Float64 => write!(f, "double"),
+      Self::Float128 => write!(f, "long double"),
+      Self::Char8 => write!(f, "char"),
+      Self::Char16 => write!(f, "wchar"),
+      Self::String { bound: None } => write!(f, "string"),
+      Self::String { bound: Some(b) } => write!(f, "string<{b}>"),
+      Self::WString { bound: None } => write!(f, "wstring"),
+      Self::WString { bound: Some(b) } => write!(f, "wstring<{b}>"),
+      Self::Sequence { element, bound } => {
+        write!(f, "sequence<{element}")?;
+        fmt_bound(f, *bound)
+      }
+      Self::Array {
+        element,
+        dimensions,
+      } => {
+        write!(f, "{element}")?;
+        for d in dimensions {
+          write!(f, "[{d}]")?;
+        }
+        Ok(())
+      }
+      Self::Map { key, value, bound } => {
+        write!(f, "map<{key}, {value}")?;
+        fmt_bound(f, *bound)
+      }
+      Self::Alias { name, .. } => write!(f, "{name}"),
+      Self::Enum(e) => write!(f, "{}", e.name),
+      Self::Struct(s) => write!(f, "{}", s.name),
+      Self::Union(u) => write!(f, "{}", u.name),
+    }
+  }
+}
+
+/// Identifies a [`TypeObject`] by a hash of it, like the XTypes `EK_MINIMAL`
+/// and `EK_COMPLETE` type identifiers.
+///
+/// The hash is computed over the XCDR2 serialization of the TypeObject of
+/// RustDDS, not that of the XTypes specification, so identifiers are
+/// comparable only between RustDDS participants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TypeIdentifier {
+  kind: u8,
+  hash: [u8; 14],
+}
+
+impl TypeIdentifier {
+  const EK_MINIMAL: u8 = 0xF1;
+  const EK_COMPLETE: u8 = 0xF2;
+
+  /// Identifier of the [minimal](TypeObject::minimal) form of the type
+  pub fn minimal(type_object: &TypeObject) -> Self {
+    Self::of(Self::EK_MINIMAL, &type_object.minimal())
+  }
+
+  /// Identifier of the type with all its names
+  pub fn complete(type_object: &TypeObject) -> Self {
+    Self::of(Self::EK_COMPLETE, type_object)
+  }
+
+  pub fn is_minimal(&self) -> bool {
+    self.kind == Self::EK_MINIMAL
+  }
+
+  fn of(kind: u8, type_object: &TypeObject) -> Self {
+    // Always little-endian, so that the hash is the same everywhere
+    let bytes = to_vec_xcdr2(type_object, Xcdr2Encoding::Plain, Endianness::LittleEndian)
+      .expect("A TypeObject is always serializable");
+    let mut hash = [0; 14];
+    hash.copy_from_slice(&md5::compute(bytes).0[..14]);
+    Self { kind, hash }
+  }
+}
+
+impl fmt::Display for TypeIdentifier {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let kind = if self.is_minimal() {
+      "minimal"
+    } else {
+      "complete"
+    };
+    write!(f, "{kind}:")?;
+    for b in self.hash {
+      write!(f, "{b:02x}")?;
+    }
+    Ok(())
+  }
+}
+
+/// The identifiers of a type, which are announced with the readers and
+/// writers of a topic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeInformation {
+  pub minimal: TypeIdentifier,
+  pub complete: TypeIdentifier,
+}
+
+impl TypeInformation {
+  pub fn of(type_object: &TypeObject) -> Self {
+    Self {
+      minimal: TypeIdentifier::minimal(type_object),
+      complete: TypeIdentifier::complete(type_object),
+    }
+  }
+}
+
+// Wire format in Discovery: the kind and hash of the minimal identifier, and
+// then those of the complete one. Identifiers of an unknown kind are rejected,
+// since another vendor may use the same parameter id for something else.
+impl<'a, C: Context> Readable<'a, C> for TypeInformation {
+  fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
+    let mut read_identifier = |expected_kind: u8| -> Result<TypeIdentifier, C::Error> {
+      let kind = reader.read_u8()?;
+      let mut hash = [0; 14];
+      reader.read_bytes(&mut hash)?;
+      if kind == expected_kind {
+        Ok(TypeIdentifier { kind, hash })
+      } else {
+        Err(speedy::Error::custom(format!("Unknown TypeIdentifier kind {kind:#x}")).into())
+      }
+    };
+    let minimal = read_identifier(TypeIdentifier::EK_MINIMAL)?;
+    let complete = read_identifier(TypeIdentifier::EK_COMPLETE)?;
+    Ok(Self { minimal, complete })
+  }
+}
+
+impl<C: Context> Writable<C> for TypeInformation {
+  fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
+    for identifier in [self.minimal, self.complete] {
+      writer.write_u8(identifier.kind)?;
+      writer.write_bytes(&identifier.hash)?;
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnumType {
+  pub name: String,
+  /// Enums are either `Final` or `Appendable`. `Mutable` is treated as
+  /// `Appendable`.
+  pub extensibility: Extensibility,
+  pub literals: Vec<EnumLiteral>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnumLiteral {
+  pub name: String,
+  pub value: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructType {
+  pub name: String,
+  pub extensibility: Extensibility,
+  pub members: Vec<StructMember>,
+}
+
+impl StructType {
+  pub fn new(name: &str, extensibility: Extensibility, members: Vec<StructMember>) -> Self {
+    Self {
+      name: name.to_string(),
+      extensibility,
+      members,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructMember {
+  pub id: u32,
+  pub name: String,
+  pub member_type: TypeObject,
+  pub key: bool,
+  pub optional: bool,
+}
+
+impl StructMember {
+  pub fn new(id: u32, name: &str, member_type: TypeObject) -> Self {
+    Self {
+      id,
+      name: name.to_string(),
+      member_type,
+      key: false,
+      optional: false,
+    }
+  }
+
+  #[must_use]
+  pub fn key(mut self) -> Self {
+    self.key = true;
+    self
+  }
+
+  #[must_use]
+  pub fn optional(mut self) -> Self {
+    self.optional = true;
+    self
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnionType {
+  pub name: String,
+  pub extensibility: Extensibility,
+  pub discriminator: Box<TypeObject>,
+  pub cases: Vec<UnionCase>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnionCase {
+  pub id: u32,
+  pub name: String,
+  pub member_type: TypeObject,
+  pub labels: Vec<i32>,
+  pub is_default: bool,
+}
+
+/// A single difference between the target and source types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeChange {
+  /// The types are of a different kind, e.g. `int32` and `int64`.
+  KindChanged {
+    target: String,
+    source: String,
+  },
+  ExtensibilityChanged {
+    target: Extensibility,
+    source: Extensibility,
+  },
+  /// String, sequence or map bound differs. This does not prevent
+  /// assignability, but samples exceeding the target bound are rejected.
+  BoundChanged {
+    target: Option<u32>,
+    source: Option<u32>,
+  },
+  DimensionsChanged {
+    target: Vec<u32>,
+    source: Vec<u32>,
+  },
+  /// The member or union case exists only in the source type.
+  MemberAdded {
+    name: String,
+    id: u32,
+    key: bool,
+  },
+  /// The member or union case exists only in the target type.
+  MemberRemoved {
+    name: String,
+    id: u32,
+    key: bool,
+  },
+  /// Matching members have different names.
+  MemberRenamed {
+    id: u32,
+    target: String,
+    source: String,
+  },
+  /// Members of the same name have different member ids.
+  MemberIdChanged {
+    target: u32,
+    source: u32,
+  },
+  KeyChanged {
+    target: bool,
+    source: bool,
+  },
+  OptionalChanged {
+    target: bool,
+    source: bool,
+  },
+  /// Neither struct has a member of the other.
+  NoCommonMembers,
+  EnumLiteralAdded {
+    name: String,
+  },
+  EnumLiteralRemoved {
+    name: String,
+  },
+  EnumValueChanged {
+    name: String,
+    target: i32,
+    source: i32,
+  },
+  UnionLabelsChanged {
+    target: Vec<i32>,
+    source: Vec<i32>,
+  },
+}
+
+fn fmt_opt_bound(bound: Option<u32>) -> String {
+  bound.map_or_else(|| "unbounded".to_string(), |b| b.to_string())
+}
+
+impl fmt::Display for TypeChange {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::KindChanged { target, source } => {
+        write!(f, "type changed from {source} to {target}")
+      }
+      Self::ExtensibilityChanged { target, source } => {
+        write!(f, "extensibility changed from {source:?} to {target:?}")
+      }
+      Self::BoundChanged { target, source } => write!(
+        f,
+        "bound changed from {} to {}",
+        fmt_opt_bound(*source),
+        fmt_opt_bound(*target)
+      ),
+      Self::DimensionsChanged { target, source } => {
+        write!(f, "array dimensions changed from {source:?} to {target:?}")
+      }
+      Self::MemberAdded { name, id, key } => {
+        let key = if *key { "key " } else { "" };
+        write!(f, "{key}member {name:?} (id {id}) only in source type")
+      }
+      Self::MemberRemoved { name, id, key } => {
+        let key = if *key { "key " } else { "" };
+        write!(f, "{key}member {name:?} (id {id}) only in target type")
+      }
+      Self::MemberRenamed { id, target, source } => {
+        write!(f, "member {id} renamed from {source:?} to {target:?}")
+      }
+      Self::MemberIdChanged { target, source } => {
+        write!(f, "member id changed from {source} to {target}")
+      }
+      Self::KeyChanged { target, source } => {
+        write!(f, "key changed from {source} to {target}")
+      }
+      Self::OptionalChanged { target, source } => {
+        write!(f, "optional changed from {source} to {target}")
+      }
+      Self::NoCommonMembers => write!(f, "no members in common"),
+      Self::EnumLiteralAdded { name } => write!(f, "literal {name:?} only in source type"),
+      Self::EnumLiteralRemoved { name } => write!(f, "literal {name:?} only in target type"),
+      Self::EnumValueChanged {
+        name,
+        target,
+        source,
+      } => write!(f, "value of {name:?} changed from {source} to {target}"),
+      Self::UnionLabelsChanged { target, source } => {
+        write!(f, "case labels changed from {source:?} to {target:?}")
+      }
+    }
+  }
+}
+
+/// A [`TypeChange`] at a location in the type, and whether it still allows
+/// assignment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeDifference {
+  /// Location of the change, e.g. `Pose.position.x`. Elements of sequences
+  /// and arrays are denoted by `[]`.
+  pub path: String,
+  pub change: TypeChange,
+  pub compatible: bool,
+}
+
+impl fmt::Display for TypeDifference {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let verdict = if self.compatible {
+      "compatible"
+    } else {
+      "incompatible"
+    };
+    write!(f, "{}: {} ({verdict})", self.path, self.change)
+  }
+}
+
+/// Outcome of [`is_assignable`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AssignabilityResult {
+  differences: Vec<TypeDifference>,
+}
+
+impl AssignabilityResult {
+  /// True, if the target type is assignable from the source type.
+  pub fn is_assignable(&self) -> bool {
+    self.differences.iter().all(|d| d.compatible)
+  }
+
+  /// All the differences found, including compatible ones.
+  pub fn differences(&self) -> &[TypeDifference] {
+    &self.differences
+  }
+
+  /// The differences that prevent assignment.
+  pub fn incompatibilities(&self) -> impl Iterator<Item = &TypeDifference> {
+    self.differences.iter().filter(|d| !d.compatible)
+  }
+}
+
+impl fmt::Display for AssignabilityResult {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if self.is_assignable() {
+      write!(f, "assignable")?;
+    } else {
+      write!(f, "not assignable")?;
+    }
+    for difference in &self.differences {
+      write!(f, "\n  {difference}")?;
+    }
+    Ok(())
+  }
+}
+
+/// Checks whether samples of the `source` type (the writer's type) can be
+/// read as the `target` type (the reader's type), according to the XTypes
+/// assignability rules.
+pub fn is_assignable(target: &TypeObject, source: &TypeObject) -> AssignabilityResult {
+  let mut result = AssignabilityResult::default();
+  compare(&mut result, &target.to_string(), target, source);
+  result
+}
+
+/// Assignability in both directions between two versions of a type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeEvolution {
+  /// Readers of the old type receiving data written with the new type.
+  pub old_reads_new: AssignabilityResult,
+  /// Readers of the new type receiving data written with the old type.
+  pub new_reads_old: AssignabilityResult,
+}
+
+impl TypeEvolution {
+  /// Upgraded readers understand data from writers that are not upgraded.
+  pub fn is_backward_compatible(&self) -> bool {
+    self.new_reads_old.is_assignable()
+  }
+
+  /// Readers that are not upgraded understand data from upgraded writers.
+  pub fn is_forward_compatible(&self) -> bool {
+    self.old_reads_new.is_assignable()
+  }
+
+  /// Old and new versions can be mixed freely, e.g. during a rolling upgrade.
+  pub fn is_fully_compatible(&self) -> bool {
+    self.is_backward_compatible() && self.is_forward_compatible()
+  }
+}
+
+/// Checks a change from the `old` to the `new` version of a type.
+pub fn check_type_evolution(old: &TypeObject, new: &TypeObject) -> TypeEvolution {
+  TypeEvolution {
+    old_reads_new: is_assignable(old, new),
+    new_reads_old: is_assignable(new, old),
+  }
+}
+
+fn push(result: &mut AssignabilityResult, path: &str, change: TypeChange, compatible: bool) {
+  result.differences.push(TypeDifference {
+    path: path.to_string(),
+    change,
+    compatible,
+  });
+}
+
+fn compare(result: &mut AssignabilityResult, path: &str, target: &TypeObject, source: &TypeObject) {
+  use TypeObject::*;
+
+  match (target.resolved(), source.resolved()) {
+    (String { bound: tb }, String { bound: sb })
+    | (WString { bound: tb }, WString { bound: sb }) => {
+      compare_bounds(result, path, *tb, *sb);
+    }
+    (
+      Sequence {
+        element: te,
+        bound: tb,
+      },
+      Sequence {
+        element: se,
+        bound: sb,
+      },
+    ) => {
+      compare_bounds(result, path, *tb, *sb);
+      compare(result, &format!("{path}[]"), te, se);
+    }
+    (
+      Array {
+        element: te,
+        dimensions: td,
+      },
+      Array {
+        element: se,
+        dimensions: sd,
+      },
+    ) => {
+      if td != sd {
+        let change = TypeChange::DimensionsChanged {
+          target: td.clone(),
+          source: sd.clone(),
+        };
+        push(result, path, change, false);
+      }
+      compare(result, &format!("{path}[]"), te, se);
+    }
+    (
+      Map {
+        key: tk,
+        value: tv,
+        bound: tb,
+      },
+      Map {
+        key: sk,
+        value: sv,
+        bound: sb,
+      },
+    ) => {
+      compare_bounds(result, path, *tb, *sb);
+      compare(result, &format!("{path}.key"), tk, sk);
+      compare(result, &format!("{path}.value"), tv, sv);
+    }
+    (Enum(t), Enum(s)) => compare_enums(result, path, t, s),
+    (Struct(t), Struct(s)) => compare_structs(result, path, t, s),
+    (Union(t), Union(s)) => compare_unions(result, path, t, s),
+    (t, s) if t == s => {}
+    (t, s) => {
+      let change = TypeChange::KindChanged {
+        target: t.to_string(),
+        source: s.to_string(),
+      };
+      push(result, path, change, false);
+    }
+  }
+}
+
+fn compare_bounds(
+  result: &mut AssignabilityResult,
+  path: &str,
+  target: Option<u32>,
+  source: Option<u32>,
+) {
+  if target != source {
+    push(
+      result,
+      path,
+      TypeChange::BoundChanged { target, source },
+      true,
+    );
+  }
+}
+
+fn compare_extensibility(
+  result: &mut AssignabilityResult,
+  path: &str,
+  target: Extensibility,
+  source: Extensibility,
+) -> bool {
+  if target == source {
+    true
+  } else {
+    push(
+      result,
+      path,
+      TypeChange::ExtensibilityChanged { target, source },
+      false,
+    );
+    false
+  }
+}
+
+fn compare_enums(
+  result: &mut AssignabilityResult,
+  path: &str,
+  target: &EnumType,
+  source: &EnumType,
+) {
+  if !compare_extensibility(result, path, target.extensibility, source.extensibility) {
+    return;
+  }
+  let extensible = target.extensibility != Extensibility::Final;
+
+  for t in &target.literals {
+    match source.literals.iter().find(|s| s.name == t.name) {
+      Some(s) if s.value != t.value => {
+        let change = TypeChange::EnumValueChanged {
+          name: t.name.clone(),
+          target: t.value,
+          source: s.value,
+        };
+        push(result, path, change, false);
+      }
+      Some(_) => {}
+      None => {
+        let change = TypeChange::EnumLiteralRemoved {
+          name: t.name.clone(),
+        };
+        push(result, path, change, extensible);
+      }
+    }
+  }
+  for s in &source.literals {
+    if !target.literals.iter().any(|t| t.name == s.name) {
+      let change = TypeChange::EnumLiteralAdded {
+        name: s.name.clone(),
+      };
+      push(result, path, change, extensible);
+    }
+  }
+}
+
+fn compare_structs(
+  result: &mut AssignabilityResult,
+  path: &str,
+  target: &StructType,
+  source: &StructType,
+) {
+  if !compare_extensibility(result, path, target.extensibility, source.extensibility) {
+    return;
+  }
+
+  // Members only in one of the types. Key members are always required.
+  let extensible = target.extensibility != Extensibility::Final;
+  let removed = |result: &mut AssignabilityResult, m: &StructMember| {
+    let change = TypeChange::MemberRemoved {
+      name: m.name.clone(),
+      id: m.id,
+      key: m.key,
+    };
+    push(result, path, change, extensible && !m.key);
+  };
+  let added = |result: &mut AssignabilityResult, m: &StructMember| {
+    let change = TypeChange::MemberAdded {
+      name: m.name.clone(),
+      id: m.id,
+      key: m.key,
+    };
+    push(result, path, change, extensible && !m.key);
+  };
+
+  let mut common_members = 0;
+  match target.extensibility {
+    // Members are matched by position.
+    Extensibility::Final | Extensibility::Appendable => {
+      let common = target.members.len().min(source.members.len());
+      for (t, s) in target.members.iter().zip(&source.members) {
+        compare_members(result, path, t, s);
+      }
+      common_members = common;
+      for t in &target.members[common..] {
+        removed(result, t);
+      }
+      for s in &source.members[common..] {
+        added(result, s);
+      }
+    }
+    // Members are matched by member id.
+    Extensibility::Mutable => {
+      for t in &target.members {
+        match source.members.iter().find(|s| s.id == t.id) {
+          Some(s) => {
+            common_members += 1;
+            compare_members(result, path, t, s);
+          }
+          None => match source.members.iter().find(|s| s.name == t.name) {
+            Some(s) => {
+              let change = TypeChange::MemberIdChanged {
+                target: t.id,
+                source: s.id,
+              };
+              push(result, &format!("{path}.{}", t.name), change, false);
+            }
+            None => removed(result, t),
+          },
+        }
+      }
+      for s in &source.members {
+        let in_target = target
+          .members
+          .iter()
+          .any(|t| t.id == s.id || t.name == s.name);
+        if !in_target {
+          added(result, s);
+        }
+      }
+    }
+  }
+
+  if common_members == 0 && !target.members.is_empty() && !source.members.is_empty() {
+    push(result, path, TypeChange::NoCommonMembers, false);
+  }
+}
+
+fn compare_members(
+  result: &mut AssignabilityResult,
+  path: &str,
+  target: &StructMember,
+  source: &StructMember,
+) {
+  if target.name != source.name {
+    let change = TypeChange::MemberRenamed {
+      id: target.id,
+      target: target.name.clone(),
+      source: source.name.clone(),
+    };
+    push(result, path, change, false);
+    return;
+  }
+  let path = format!("{path}.{}", target.name);
+  if target.key != source.key {
+    let change = TypeChange::KeyChanged {
+      target: target.key,
+      source: source.key,
+    };
+    push(result, &path, change, false);
+  }
+  if target.optional != source.optional {
+    let change = TypeChange::OptionalChanged {
+      target: target.optional,
+      source: source.optional,
+    };
+    push(result, &path, change, false);
+  }
+  compare(result, &path, &target.member_type, &source.member_type);
+}
+
+fn compare_unions(
+  result: &mut AssignabilityResult,
+  path: &str,
+  target: &UnionType,
+  source: &UnionType,
+) {
+  if !compare_extensibility(result, path, target.extensibility, source.extensibility) {
+    return;
+  }
+  compare(
+    result,
+    &format!("{path}.discriminator"),
+    &target.discriminator,
+    &source.discriminator,
+  );
+
+  // A sample of a case unknown to the reader cannot be read, but the other
+  // cases still can.
+  let extensible = target.extensibility != Extensibility::Final;
+  for t in &target.cases {
+    match source.cases.iter().find(|s| s.id == t.id) {
+      Some(s) if s.name != t.name => {
+        let change = TypeChange::MemberRenamed {
+          id: t.id,
+          target: t.name.clone(),
+          source: s.name.clone(),
+        };
+        push(result, path, change, false);
+      }
+      Some(s) => {
+        let case_path = format!("{path}.{}", t.name);
+        if s.labels != t.labels || s.is_default != t.is_default {
+          let change = TypeChange::UnionLabelsChanged {
+            target: t.labels.clone(),
+            source: s.labels.clone(),
+          };
+          push(result, &case_path, change, false);
+        }
+        compare(result, &case_path, &t.member_type, &s.member_type);
+      }
+      None => {
+        let change = TypeChange::MemberRemoved {
+          name: t.name.clone(),
+          id: t.id,
+          key: false,
+        };
+        push(result, path, change, extensible);
+      }
+    }
+  }
+  for s in &source.cases {
+    if !target.cases.iter().any(|t| t.id == s.id) {
+      let change = TypeChange::MemberAdded {
+        name: s.name.clone(),
+        id: s.id,
+        key: false,
+      };
+      push(result, path, change, extensible);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn point(extensibility: Extensibility, members: Vec<StructMember>) -> TypeObject {
+    TypeObject::Struct(StructType::new("Point", extensibility, members))
+  }
+
+  fn xy() -> Vec<StructMember> {
+    vec![
+      StructMember::new(0, "x", TypeObject::Float64),
+      StructMember::new(1, "y", TypeObject::Float64),
+    ]
+  }
+
+  fn xyz() -> Vec<StructMember> {
+    let mut members = xy();
+    members.push(StructMember::new(2, "z", TypeObject::Float64));
+    members
+  }
+
+  #[test]
+  fn identical_types_are_assignable() {
+    let t = point(Extensibility::Final, xy());
+    let result = is_assignable(&t, &t);
+    assert!(result.is_assignable());
+    assert!(result.differences().is_empty());
+  }
+
+  #[test]
+  fn appending_members() {
+    let old = point(Extensibility::Appendable, xy());
+    let new = point(Extensibility::Appendable, xyz());
+    let evolution = check_type_evolution(&old, &new);
+    assert!(evolution.is_fully_compatible());
+    assert_eq!(
+      evolution.old_reads_new.differences(),
+      [TypeDifference {
+        path: "Point".to_string(),
+        change: TypeChange::MemberAdded {
+          name: "z".to_string(),
+          id: 2,
+          key: false
+        },
+        compatible: true,
+      }]
+    );
+
+    // Final types cannot change.
+    let old = point(Extensibility::Final, xy());
+    let new = point(Extensibility::Final, xyz());
+    let evolution = check_type_evolution(&old, &new);
+    assert!(!evolution.is_backward_compatible());
+    assert!(!evolution.is_forward_compatible());
+
+    // Inserting in the middle of an appendable type is not appending.
+    let mut inserted = xy();
+    inserted.insert(1, StructMember::new(2, "z", TypeObject::Float64));
+    let new = point(Extensibility::Appendable, inserted);
+    let old = point(Extensibility::Appendable, xy());
+    assert!(!is_assignable(&old, &new).is_assignable());
+  }
+
+  #[test]
+  fn mutable_members_match_by_id() {
+    let mut reordered = xyz();
+    reordered.reverse();
+    let old = point(Extensibility::Mutable, xy());
+    let new = point(Extensibility::Mutable, reordered);
+    assert!(check_type_evolution(&old, &new).is_fully_compatible());
+
+    let renumbered = vec![
+      StructMember::new(0, "x", TypeObject::Float64),
+      StructMember::new(5, "y", TypeObject::Float64),
+    ];
+    let new = point(Extensibility::Mutable, renumbered);
+    let result = is_assignable(&old, &new);
+    assert!(!result.is_assignable());
+    let incompatible: Vec<_> = result.incompatibilities().collect();
+    assert_eq!(incompatible.len(), 1);
+    assert_eq!(incompatible[0].path, "Point.y");
+    assert_eq!(
+      incompatible[0].change,
+      TypeChange::MemberIdChanged {
+        target: 1,
+        source: 5
+      }
+    );
+  }
+
+  #[test]
+  fn key_members_are_required() {
+    let mut keyed = xy();
+    keyed.push(StructMember::new(2, "id", TypeObject::UInt32).key());
+    let old = point(Extensibility::Mutable, xy());
+    let new = point(Extensibility::Mutable, keyed);
+    let evolution = check_type_evolution(&old, &new);
+    assert!(!evolution.is_backward_compatible());
+    assert!(!evolution.is_forward_compatible());
+  }
+
+  #[test]
+  fn nested_differences_have_paths() {
+    let line = |end_type: TypeObject| {
+      TypeObject::Struct(StructType::new(
+        "Line",
+        Extensibility::Final,
+        vec![StructMember::new(
+          0,
+          "points",
+          TypeObject::Sequence {
+            element: Box::new(point(
+              Extensibility::Final,
+              vec![StructMember::new(0, "x", end_type)],
+            )),
+            bound: None,
+          },
+        )],
+      ))
+    };
+    let result = is_assignable(&line(TypeObject::Float64), &line(TypeObject::Float32));
+    assert!(!result.is_assignable());
+    assert_eq!(
+      result.to_string(),
+      "not assignable\n  Line.points[].x: type changed from float to double (incompatible)"
+    );
+
+    // Aliases are transparent, and bounds do not prevent assignment.
+    let alias = TypeObject::Alias {
+      name: "Name".to_string(),
+      base: Box::new(TypeObject::String { bound: Some(8) }),
+    };
+    let result = is_assignable(&alias, &TypeObject::String { bound: None });
+    assert!(result.is_assignable());
+    assert_eq!(result.differences().len(), 1);
+  }
+
+  #[test]
+  fn enum_literals() {
+    let color = |extensibility, literals: &[(&str, i32)]| {
+      TypeObject::Enum(EnumType {
+        name: "Color".to_string(),
+        extensibility,
+        literals: literals
+          .iter()
+          .map(|(name, value)| EnumLiteral {
+            name: name.to_string(),
+            value: *value,
+          })
+          .collect(),
+      })
+    };
+    let old = color(Extensibility::Appendable, &[("RED", 0), ("GREEN", 1)]);
+    let new = color(
+      Extensibility::Appendable,
+      &[("RED", 0), ("GREEN", 1), ("BLUE", 2)],
+    );
+    assert!(check_type_evolution(&old, &new).is_fully_compatible());
+
+    let renumbered = color(Extensibility::Appendable, &[("RED", 0), ("GREEN", 2)]);
+    assert!(!is_assignable(&old, &renumbered).is_assignable());
+
+    let old = color(Extensibility::Final, &[("RED", 0), ("GREEN", 1)]);
+    assert!(!is_assignable(&old, &new).is_assignable());
+  }
+
+  #[test]
+  fn type_identifiers() {
+    let t = point(Extensibility::Appendable, xy());
+    let renamed = TypeObject::Struct(StructType::new("Position", Extensibility::Appendable, xy()));
+    assert_eq!(
+      TypeIdentifier::minimal(&t),
+      TypeIdentifier::minimal(&renamed)
+    );
+    assert_ne!(
+      TypeIdentifier::complete(&t),
+      TypeIdentifier::complete(&renamed)
+    );
+    assert!(TypeIdentifier::minimal(&t).is_minimal());
+    assert!(!TypeIdentifier::complete(&t).is_minimal());
+
+    // Member names are part of the minimal type, as hashes
+    let mut swapped = xy();
+    swapped.swap(0, 1);
+    let swapped = point(Extensibility::Appendable, swapped);
+    assert_ne!(
+      TypeIdentifier::minimal(&t),
+      TypeIdentifier::minimal(&swapped)
+    );
+
+    // Minimal types are as assignable as the complete ones
+    let new = point(Extensibility::Appendable, xyz());
+    assert!(is_assignable(&t.minimal(), &new.minimal()).is_assignable());
+    let new = point(Extensibility::Final, xyz());
+    assert!(!is_assignable(&t.minimal(), &new.minimal()).is_assignable());
+  }
+
+  #[test]
+  fn type_information_serialization() {
+    let info = TypeInformation::of(&point(Extensibility::Mutable, xyz()));
+    let bytes = info
+      .write_to_vec_with_ctx(Endianness::LittleEndian)
+      .unwrap();
+    assert_eq!(bytes.len(), 30);
+    let read = TypeInformation::read_from_buffer_with_ctx(Endianness::LittleEndian, &bytes);
+    assert_eq!(read.unwrap(), info);
+
+    let mut garbage = bytes;
+    garbage[0] = 0x42;
+    assert!(
+      TypeInformation::read_from_buffer_with_ctx(Endianness::LittleEndian, &garbage).is_err()
+    );
+  }
+}
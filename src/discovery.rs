@@ -1,14 +1,18 @@
 pub(crate) mod builtin_endpoint;
 pub(crate) mod content_filter_property;
+pub(crate) mod damping;
 #[allow(clippy::module_inception)]
 pub(crate) mod discovery;
 pub(crate) mod discovery_db;
+pub(crate) mod health;
+pub(crate) mod lease_assertion;
 
 #[cfg(feature = "security")]
 pub(crate) mod secure_discovery;
 
 pub(crate) mod sedp_messages;
 pub(crate) mod spdp_participant_data;
+pub(crate) mod type_lookup;
 
 pub use sedp_messages::*;
 pub use spdp_participant_data::*;
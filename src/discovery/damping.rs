@@ -0,0 +1,288 @@
+use std::{
+  collections::BTreeMap,
+  time::{Duration, Instant},
+};
+
+use crate::{
+  dds::discovery_damping::DiscoveryDampingSettings,
+  structure::guid::{GuidPrefix, GUID},
+};
+
+// The map of recent changes is pruned when it grows past this, or past twice
+// its size after the previous pruning.
+const MIN_PRUNE_THRESHOLD: usize = 64;
+
+// Delays and coalesces the changes of remote endpoints received in SEDP. See
+// crate::discovery_damping.
+//
+// Each change is admitted with the GUID of its endpoint. It is applied at
+// once, if the endpoint has not changed within the settle time, its
+// participant has budget left, and no earlier change of the endpoint is held.
+// Otherwise it is held, replacing any held change of the same endpoint, and
+// released later.
+//
+// All decisions take the current time as a parameter, so that they can be
+// tested without a clock.
+pub(crate) struct ChangeDamper<T> {
+  settle_time: Duration,
+  max_changes_per_second: Option<u32>,
+  max_burst: u32,
+  // When endpoints changed last. Entries older than the settle time are
+  // pruned as the map grows.
+  last_changes: BTreeMap<GUID, Instant>,
+  prune_threshold: usize,
+  held: BTreeMap<GUID, HeldChange<T>>,
+  // Arrival order of held changes
+  arrivals: u64,
+  // Changes that each remote participant may make now
+  budgets: BTreeMap<GuidPrefix, Budget>,
+}
+
+struct HeldChange<T> {
+  change: T,
+  // When the endpoint has settled, if it does not change again
+  settled_at: Instant,
+  arrival: u64,
+}
+
+struct Budget {
+  changes: f64,
+  updated: Instant,
+}
+
+impl Budget {
+  fn refill(&mut self, rate: u32, capacity: f64, now: Instant) {
+    let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+    self.changes = (self.changes + elapsed * f64::from(rate)).min(capacity);
+    self.updated = now;
+  }
+
+  // When the budget has a change left
+  fn ready_at(&self, rate: u32) -> Instant {
+    if self.changes >= 1.0 {
+      self.updated
+    } else {
+      self.updated + Duration::from_secs_f64((1.0 - self.changes) / f64::from(rate))
+    }
+  }
+}
+
+impl<T> ChangeDamper<T> {
+  pub fn new(settings: DiscoveryDampingSettings) -> Self {
+    Self {
+      settle_time: settings.settle_time,
+      max_changes_per_second: settings.max_changes_per_second.filter(|rate| *rate > 0),
+      max_burst: settings.max_burst.max(1),
+      last_changes: BTreeMap::new(),
+      prune_threshold: MIN_PRUNE_THRESHOLD,
+      held: BTreeMap::new(),
+      arrivals: 0,
+      budgets: BTreeMap::new(),
+    }
+  }
+
+  // Returns the change, if it can be applied now. Otherwise it is held.
+  pub fn admit(&mut self, endpoint: GUID, change: T, now: Instant) -> Option<T> {
+    let settling = self.is_settling(endpoint, now);
+    self.record_change(endpoint, now);
+    let settled_at = now + self.settle_time;
+    if let Some(held) = self.held.get_mut(&endpoint) {
+      held.change = change;
+      held.settled_at = settled_at;
+      return None;
+    }
+    if !settling && self.take_budget(endpoint.prefix, now) {
+      return Some(change);
+    }
+    self.arrivals += 1;
+    self.held.insert(
+      endpoint,
+      HeldChange {
+        change,
+        settled_at: if settling { settled_at } else { now },
+        arrival: self.arrivals,
+      },
+    );
+    None
+  }
+
+  // The endpoint is back in the state that was applied last, so its held
+  // change, if any, is dropped.
+  pub fn revert(&mut self, endpoint: GUID, now: Instant) {
+    if self.held.remove(&endpoint).is_some() {
+      self.record_change(endpoint, now);
+    }
+  }
+
+  pub fn held(&self, endpoint: GUID) -> Option<&T> {
+    self.held.get(&endpoint).map(|held| &held.change)
+  }
+
+  // Held changes that can be applied now, in the order they arrived
+  pub fn release(&mut self, now: Instant) -> Vec<T> {
+    let mut due: Vec<(u64, GUID)> = self
+      .held
+      .iter()
+      .filter(|(_, held)| held.settled_at <= now)
+      .map(|(endpoint, held)| (held.arrival, *endpoint))
+      .collect();
+    due.sort_unstable();
+
+    let mut released = Vec::new();
+    for (_, endpoint) in due {
+      if self.take_budget(endpoint.prefix, now) {
+        released.extend(self.held.remove(&endpoint).map(|held| held.change));
+      }
+    }
+    released
+  }
+
+  // When the next held change can be applied, if any are held
+  pub fn next_release(&self) -> Option<Instant> {
+    self
+      .held
+      .iter()
+      .map(|(endpoint, held)| {
+        match (
+          self.max_changes_per_second,
+          self.budgets.get(&endpoint.prefix),
+        ) {
+          (Some(rate), Some(budget)) => held.settled_at.max(budget.ready_at(rate)),
+          _ => held.settled_at,
+        }
+      })
+      .min()
+  }
+
+  pub fn forget_participant(&mut self, participant: GuidPrefix) {
+    self
+      .held
+      .retain(|endpoint, _| endpoint.prefix != participant);
+    self
+      .last_changes
+      .retain(|endpoint, _| endpoint.prefix != participant);
+    self.budgets.remove(&participant);
+  }
+
+  fn is_settling(&self, endpoint: GUID, now: Instant) -> bool {
+    self
+      .last_changes
+      .get(&endpoint)
+      .is_some_and(|changed| now.saturating_duration_since(*changed) < self.settle_time)
+  }
+
+  fn record_change(&mut self, endpoint: GUID, now: Instant) {
+    if self.settle_time.is_zero() {
+      return;
+    }
+    self.last_changes.insert(endpoint, now);
+    if self.last_changes.len() > self.prune_threshold {
+      let settle_time = self.settle_time;
+      self
+        .last_changes
+        .retain(|_, changed| now.saturating_duration_since(*changed) < settle_time);
+      self.prune_threshold = (2 * self.last_changes.len()).max(MIN_PRUNE_THRESHOLD);
+    }
+  }
+
+  fn take_budget(&mut self, participant: GuidPrefix, now: Instant) -> bool {
+    let Some(rate) = self.max_changes_per_second else {
+      return true;
+    };
+    let capacity = f64::from(self.max_burst);
+    let budget = self.budgets.entry(participant).or_insert(Budget {
+      changes: capacity,
+      updated: now,
+    });
+    budget.refill(rate, capacity, now);
+    if budget.changes >= 1.0 {
+      budget.changes -= 1.0;
+      true
+    } else {
+      false
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::structure::guid::{EntityId, EntityKind};
+
+  fn endpoint(participant: u8, key: u8) -> GUID {
+    GUID::new(
+      GuidPrefix::new(&[participant; 12]),
+      EntityId::new([0, 0, key], EntityKind::READER_WITH_KEY_USER_DEFINED),
+    )
+  }
+
+  #[test]
+  fn flapping_endpoint_is_coalesced_until_settled() {
+    let start = Instant::now();
+    let at = |millis| start + Duration::from_millis(millis);
+    let mut damper = ChangeDamper::new(DiscoveryDampingSettings {
+      settle_time: Duration::from_millis(100),
+      ..DiscoveryDampingSettings::default()
+    });
+
+    // A quiet endpoint changes at once.
+    assert_eq!(
+      damper.admit(endpoint(1, 1), "created", at(0)),
+      Some("created")
+    );
+    // Then its changes are held, and only the latest is applied, once the
+    // endpoint has not changed for the settle time.
+    assert_eq!(damper.admit(endpoint(1, 1), "disposed", at(10)), None);
+    assert_eq!(damper.admit(endpoint(1, 1), "recreated", at(50)), None);
+    assert_eq!(damper.next_release(), Some(at(150)));
+    assert!(damper.release(at(149)).is_empty());
+    assert_eq!(damper.release(at(150)), vec!["recreated"]);
+    assert_eq!(damper.next_release(), None);
+
+    // Other endpoints are not affected.
+    assert_eq!(
+      damper.admit(endpoint(1, 2), "created", at(60)),
+      Some("created")
+    );
+
+    // Going back to the applied state drops the held change.
+    assert_eq!(damper.admit(endpoint(1, 2), "updated", at(70)), None);
+    damper.revert(endpoint(1, 2), at(80));
+    assert_eq!(damper.next_release(), None);
+    assert_eq!(
+      damper.admit(endpoint(1, 2), "updated", at(500)),
+      Some("updated")
+    );
+  }
+
+  #[test]
+  fn participant_rate_is_limited() {
+    let start = Instant::now();
+    let at = |millis| start + Duration::from_millis(millis);
+    let mut damper = ChangeDamper::new(DiscoveryDampingSettings {
+      max_changes_per_second: Some(10),
+      max_burst: 2,
+      ..DiscoveryDampingSettings::default()
+    });
+
+    // A burst of two, then one change per 100 ms, in the order they arrived
+    assert_eq!(damper.admit(endpoint(1, 1), 1, at(0)), Some(1));
+    assert_eq!(damper.admit(endpoint(1, 2), 2, at(0)), Some(2));
+    assert_eq!(damper.admit(endpoint(1, 3), 3, at(0)), None);
+    assert_eq!(damper.admit(endpoint(1, 4), 4, at(0)), None);
+    // Another participant has a budget of its own.
+    assert_eq!(damper.admit(endpoint(2, 1), 5, at(0)), Some(5));
+
+    assert_eq!(damper.next_release(), Some(at(100)));
+    assert_eq!(damper.release(at(100)), vec![3]);
+    // A held change is replaced by a newer one of the same endpoint.
+    assert_eq!(damper.admit(endpoint(1, 4), 6, at(150)), None);
+    assert_eq!(damper.release(at(200)), vec![6]);
+    assert_eq!(damper.next_release(), None);
+
+    assert_eq!(damper.admit(endpoint(1, 5), 7, at(210)), None);
+    damper.forget_participant(GuidPrefix::new(&[1; 12]));
+    assert_eq!(damper.next_release(), None);
+    assert_eq!(damper.admit(endpoint(1, 5), 8, at(210)), Some(8));
+  }
+}
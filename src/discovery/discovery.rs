@@ -1,6 +1,7 @@
 use std::{
+  collections::BTreeMap,
   sync::{Arc, RwLock},
-  time::Duration as StdDuration,
+  time::{Duration as StdDuration, Instant},
 };
 
 #[allow(unused_imports)]
@@ -11,6 +12,7 @@ use paste::paste; // token pasting macro
 
 use crate::{
   dds::{
+    discovery_damping::DiscoveryDampingSettings,
     participant::DomainParticipantWeak,
     qos::{
       policy::{
@@ -20,27 +22,36 @@ use crate::{
       QosPolicies, QosPolicyBuilder,
     },
     readcondition::ReadCondition,
+    resource_profile::ResourceSettings,
     result::{CreateError, CreateResult},
     statusevents::{DomainParticipantStatusEvent, LostReason, StatusChannelSender},
+    xtypes::TypeIdentifier,
   },
   discovery::{
+    damping::ChangeDamper,
     discovery_db::{discovery_db_read, discovery_db_write, DiscoveredVia, DiscoveryDB},
+    health::HealthPublisher,
+    lease_assertion::{LeaseAssertion, SpdpTransmissions},
     sedp_messages::{
       DiscoveredReaderData, DiscoveredTopicData, DiscoveredWriterData, Endpoint_GUID,
       ParticipantMessageData, ParticipantMessageDataKind,
     },
     spdp_participant_data::{Participant_GUID, SpdpDiscoveredParticipantData},
+    type_lookup::{TypeLookupReply, TypeLookupRequest},
   },
+  network::interface_monitor::{AddressChange, InterfaceMonitor},
   polling::{new_simple_timer, TimerPolicy},
+  rpc::{RemoteExceptionCode, ReplyHeader, RequestHeader},
   rtps::constant::*,
   serialization::{pl_cdr_adapters::*, CDRDeserializerAdapter, CDRSerializerAdapter},
   structure::{
     duration::Duration,
     entity::RTPSEntity,
     guid::{EntityId, GuidPrefix, GUID},
+    sequence_number::SequenceNumber,
     time::Timestamp,
   },
-  with_key::{DataReader, DataWriter, Sample},
+  with_key::{DataReader, DataWriter, Sample, WriteOptionsBuilder},
   DomainParticipant,
 };
 // This module implements the control logic of the Discovery process.
@@ -93,10 +104,17 @@ pub enum DiscoveryCommand {
     guid: GUID,
   },
   ManualAssertLiveliness,
+  // Send our SPDP announcement now, e.g. to answer a discovery probe
+  AnnounceParticipant,
   AssertTopicLiveliness {
     writer_guid: GUID,
     manual_assertion: bool,
   },
+  // Ask a remote participant for a type known to us only by its identifier
+  LookupType {
+    participant: GuidPrefix,
+    type_id: TypeIdentifier,
+  },
 
   #[cfg(feature = "security")]
   StartKeyExchangeWithRemoteParticipant {
@@ -108,6 +126,11 @@ pub enum DiscoveryCommand {
     local_endpoint_guid: GUID,
     remote_endpoint_guid: GUID,
   },
+
+  #[cfg(feature = "security")]
+  RekeyLocalEndpoint {
+    local_endpoint_guid: GUID,
+  },
 }
 
 pub struct LivelinessState {
@@ -166,7 +189,6 @@ mod with_key {
   }
 }
 
-#[cfg(feature = "security")] // only used with security feature for now, this is to avoid warning
 mod no_key {
   use serde::{de::DeserializeOwned, Serialize};
   use mio_extras::timer::Timer;
@@ -197,6 +219,21 @@ pub(crate) enum NormalDiscoveryPermission {
   Deny,
 }
 
+// A change of a remote endpoint received in SEDP, with the sequence number of
+// its sample. Discovery damping may hold it before it is applied.
+enum EndpointChange {
+  Reader(SequenceNumber, Sample<DiscoveredReaderData, GUID>),
+  Writer(SequenceNumber, Sample<DiscoveredWriterData, GUID>),
+}
+
+impl EndpointChange {
+  fn sequence_number(&self) -> SequenceNumber {
+    match self {
+      Self::Reader(sn, _) | Self::Writer(sn, _) => *sn,
+    }
+  }
+}
+
 pub(crate) struct Discovery {
   poll: Poll,
   domain_participant: DomainParticipantWeak,
@@ -225,6 +262,13 @@ pub(crate) struct Discovery {
   // timer to periodically announce our presence
   dcps_participant: with_key::DiscoveryTopicPlCdr<SpdpDiscoveredParticipantData>,
   participant_cleanup_timer: Timer<()>, // garbage collection timer for dead remote participants
+  // schedule and watchdog for periodically announcing our presence
+  lease_assertion: LeaseAssertion,
+  // Local IP addresses, checked periodically to survive network transitions
+  interface_monitor: InterfaceMonitor,
+  network_change_timer: Timer<()>,
+  // Publishes our own health periodically, if enabled
+  health_publisher: Option<HealthPublisher>,
 
   // Topic "DCPSSubscription" - announcing and detecting Readers
   dcps_subscription: with_key::DiscoveryTopicPlCdr<DiscoveredReaderData>,
@@ -232,6 +276,12 @@ pub(crate) struct Discovery {
   // Topic "DCPSPublication" - announcing and detecting Writers
   dcps_publication: with_key::DiscoveryTopicPlCdr<DiscoveredWriterData>,
 
+  // Changes of remote Readers and Writers that are held, and the timer that
+  // releases them
+  endpoint_damper: ChangeDamper<EndpointChange>,
+  damping_timer: Timer<()>,
+  damping_timer_deadline: Option<Instant>,
+
   // Topic "DCPSTopic" - announcing and detecting topics
   dcps_topic: with_key::DiscoveryTopicPlCdr<DiscoveredTopicData>,
   topic_cleanup_timer: Timer<()>,
@@ -239,6 +289,14 @@ pub(crate) struct Discovery {
   // DCPSParticipantMessage - used by participants to communicate liveness
   dcps_participant_message: with_key::DiscoveryTopicCDR<ParticipantMessageData>,
 
+  // TypeLookup service of RustDDS, modeled on DDS-XTypes 7.6.3.3 - fetching
+  // the TypeObjects of remote types, so that endpoints can be matched by their
+  // types.
+  type_lookup_request: no_key::DiscoveryTopicCDR<TypeLookupRequest>,
+  type_lookup_reply: no_key::DiscoveryTopicCDR<TypeLookupReply>,
+  // Types we have asked for, and when
+  type_lookups_pending: BTreeMap<TypeIdentifier, Instant>,
+
   // If security is enabled, this field contains a SecureDiscovery struct, an appendix
   // which is used for Secure functionality
   security_opt: Option<SecureDiscovery>,
@@ -282,6 +340,8 @@ impl Discovery {
   const TOPIC_CLEANUP_PERIOD: StdDuration = StdDuration::from_secs(60); // timer for cleaning up inactive topics
   const SPDP_PUBLISH_PERIOD: StdDuration = StdDuration::from_secs(10);
   const CHECK_PARTICIPANT_MESSAGES: StdDuration = StdDuration::from_secs(1);
+  const NETWORK_CHANGE_CHECK_PERIOD: StdDuration = StdDuration::from_secs(2);
+  const TYPE_LOOKUP_RETRY_PERIOD: StdDuration = StdDuration::from_secs(5);
   #[cfg(feature = "security")]
   const CACHED_SECURE_DISCOVERY_MESSAGE_RESEND_PERIOD: StdDuration = StdDuration::from_secs(1);
 
@@ -293,6 +353,20 @@ impl Discovery {
     ownership: None,
     liveliness: None,
     time_based_filter: None,
+    partition: None,
+    writer_restart: None,
+    cache_watermarks: None,
+    delivery_order: None,
+    last_value_cache: None,
+    conflation: None,
+    payload_checksum: None,
+    writer_quarantine: None,
+    forward_error_correction: None,
+    repair_scheduling: None,
+    writer_initialization: None,
+    batching: None,
+    acknowledgment: None,
+    flow_control: None,
     reliability: Some(Reliability::Reliable {
       max_blocking_time: Duration::ZERO,
     }),
@@ -300,6 +374,7 @@ impl Discovery {
     history: Some(History::KeepLast { depth: 1 }),
     resource_limits: None,
     lifespan: None,
+    data_representation: None,
     #[cfg(feature = "security")]
     property: None,
   };
@@ -312,8 +387,12 @@ impl Discovery {
     discovery_updated_sender: mio_channel::SyncSender<DiscoveryNotificationType>,
     discovery_command_receiver: mio_channel::Receiver<DiscoveryCommand>,
     spdp_liveness_receiver: mio_channel::Receiver<GuidPrefix>,
+    spdp_transmissions: SpdpTransmissions,
     participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
     security_plugins_opt: Option<SecurityPluginsHandle>,
+    resource_settings: ResourceSettings,
+    self_health_period: Option<StdDuration>,
+    damping: DiscoveryDampingSettings,
   ) -> CreateResult<Self> {
     // helper macro to handle initialization failures.
     macro_rules! try_construct {
@@ -468,6 +547,18 @@ impl Discovery {
       "Unable to create participant cleanup timer."
     );
 
+    let mut network_change_timer: Timer<()> = new_simple_timer();
+    network_change_timer.set_timeout(Self::NETWORK_CHANGE_CHECK_PERIOD, ());
+    try_construct!(
+      poll.register(
+        &network_change_timer,
+        DISCOVERY_NETWORK_CHANGE_TIMER_TOKEN,
+        Ready::readable(),
+        PollOpt::edge(),
+      ),
+      "Unable to create network change timer."
+    );
+
     // Subscriptions: What are the Readers on the network and what are they
     // subscribing to?
     let dcps_subscription = construct_topic_and_poll!(
@@ -545,6 +636,34 @@ impl Discovery {
       )),
     );
 
+    // TypeLookup service of RustDDS, modeled on DDS-XTypes 7.6.3.3
+    let type_lookup_request = construct_topic_and_poll!(
+      CDR,
+      no_key,
+      builtin_topic_names::TYPE_LOOKUP_REQUEST,
+      builtin_topic_type_names::TYPE_LOOKUP_REQUEST,
+      TypeLookupRequest,
+      Some(Self::create_type_lookup_qos()),
+      false, // Regular stateful RTPS Reader & Writer
+      EntityId::RUSTDDS_TYPE_LOOKUP_REQUEST_READER,
+      DISCOVERY_TYPE_LOOKUP_REQUEST_TOKEN,
+      EntityId::RUSTDDS_TYPE_LOOKUP_REQUEST_WRITER,
+      None, // No timer
+    );
+    let type_lookup_reply = construct_topic_and_poll!(
+      CDR,
+      no_key,
+      builtin_topic_names::TYPE_LOOKUP_REPLY,
+      builtin_topic_type_names::TYPE_LOOKUP_REPLY,
+      TypeLookupReply,
+      Some(Self::create_type_lookup_qos()),
+      false, // Regular stateful RTPS Reader & Writer
+      EntityId::RUSTDDS_TYPE_LOOKUP_REPLY_READER,
+      DISCOVERY_TYPE_LOOKUP_REPLY_TOKEN,
+      EntityId::RUSTDDS_TYPE_LOOKUP_REPLY_WRITER,
+      None, // No timer
+    );
+
     // DDS Security
 
     // Participant
@@ -679,6 +798,45 @@ impl Discovery {
       None // no security configured
     };
 
+    let health_publisher = match self_health_period {
+      Some(period) => {
+        let health_publisher = try_construct!(
+          HealthPublisher::new(&domain_participant, &discovery_publisher, period),
+          "Unable to create self-health DataWriter."
+        );
+        try_construct!(
+          poll.register(
+            &health_publisher.timer,
+            DISCOVERY_SELF_HEALTH_TIMER_TOKEN,
+            Ready::readable(),
+            PollOpt::edge(),
+          ),
+          "Unable to create self-health timer."
+        );
+        Some(health_publisher)
+      }
+      None => None,
+    };
+
+    let damping_timer: Timer<()> = new_simple_timer();
+    try_construct!(
+      poll.register(
+        &damping_timer,
+        DISCOVERY_DAMPING_TIMER_TOKEN,
+        Ready::readable(),
+        PollOpt::edge(),
+      ),
+      "Unable to create discovery damping timer."
+    );
+
+    let lease_assertion = LeaseAssertion::new(
+      Self::SPDP_PUBLISH_PERIOD,
+      Self::spdp_lease_duration(),
+      spdp_transmissions,
+      participant_status_sender.clone(),
+      resource_settings.lease_watchdog_thread,
+    );
+
     Ok(Self {
       poll,
       domain_participant,
@@ -695,11 +853,21 @@ impl Discovery {
       // discovery_publisher,
       dcps_participant,
       participant_cleanup_timer, // SPDP
+      lease_assertion,
+      interface_monitor: InterfaceMonitor::new(),
+      network_change_timer,
+      health_publisher,
       dcps_subscription,
       dcps_publication, // SEDP
+      endpoint_damper: ChangeDamper::new(damping),
+      damping_timer,
+      damping_timer_deadline: None,
       dcps_topic,
       topic_cleanup_timer,      // SEDP
       dcps_participant_message, // liveliness messages
+      type_lookup_request,
+      type_lookup_reply,
+      type_lookups_pending: BTreeMap::new(),
 
       security_opt,
       #[cfg(feature = "security")]
@@ -733,10 +901,11 @@ impl Discovery {
 
     loop {
       let mut events = Events::with_capacity(32); // Should this be outside of the loop?
-      match self
-        .poll
-        .poll(&mut events, Some(std::time::Duration::from_millis(5000)))
-      {
+      let poll_timeout = self
+        .lease_assertion
+        .time_until_due()
+        .min(StdDuration::from_millis(5000));
+      match self.poll.poll(&mut events, Some(poll_timeout)) {
         Ok(_) => (),
         Err(e) => {
           error!("Failed in waiting of poll in discovery. {e:?}");
@@ -746,8 +915,16 @@ impl Discovery {
       if events.is_empty() {
         debug!("Discovery event loop idling.");
       }
+      if !self.assert_participant_lease_if_due() {
+        return;
+      }
 
       for event in events.into_iter() {
+        // Our SPDP announcement goes out on time, even if other events keep
+        // the loop busy.
+        if !self.assert_participant_lease_if_due() {
+          return;
+        }
         match event.token() {
           DISCOVERY_COMMAND_TOKEN => {
             while let Ok(command) = self.discovery_command_receiver.try_recv() {
@@ -771,14 +948,26 @@ impl Discovery {
                   if guid == self.dcps_publication.writer.guid() {
                     continue;
                   }
-                  self.send_endpoint_dispose_message(guid);
+                  #[cfg(feature = "security")]
+                  let security_info = discovery_db_read(&self.discovery_db)
+                    .get_local_topic_writer(guid)
+                    .and_then(|w| w.publication_topic_data.security_info().clone());
+                  #[cfg(not(feature = "security"))]
+                  let security_info = None;
+                  self.send_endpoint_dispose_message(guid, &security_info);
                   discovery_db_write(&self.discovery_db).remove_local_topic_writer(guid);
                 }
                 DiscoveryCommand::RemoveLocalReader { guid } => {
                   if guid == self.dcps_subscription.writer.guid() {
                     continue;
                   }
-                  self.send_endpoint_dispose_message(guid);
+                  #[cfg(feature = "security")]
+                  let security_info = discovery_db_read(&self.discovery_db)
+                    .get_local_topic_reader(guid)
+                    .and_then(|r| r.subscription_topic_data.security_info().clone());
+                  #[cfg(not(feature = "security"))]
+                  let security_info = None;
+                  self.send_endpoint_dispose_message(guid, &security_info);
                   discovery_db_write(&self.discovery_db).remove_local_topic_reader(guid);
                 }
                 DiscoveryCommand::ManualAssertLiveliness => {
@@ -786,6 +975,14 @@ impl Discovery {
                     .liveliness_state
                     .manual_participant_liveness_refresh_requested = true;
                 }
+                DiscoveryCommand::AnnounceParticipant => {
+                  if let Some(dp) = self.domain_participant.clone().upgrade() {
+                    self.spdp_publish(&dp);
+                  } else {
+                    error!("DomainParticipant doesn't exist anymore, exiting Discovery.");
+                    return;
+                  }
+                }
                 DiscoveryCommand::AssertTopicLiveliness {
                   writer_guid,
                   manual_assertion,
@@ -797,6 +994,12 @@ impl Discovery {
                     },
                   );
                 }
+                DiscoveryCommand::LookupType {
+                  participant,
+                  type_id,
+                } => {
+                  self.request_type(participant, type_id);
+                }
                 #[cfg(feature = "security")]
                 DiscoveryCommand::StartKeyExchangeWithRemoteParticipant {
                   participant_guid_prefix,
@@ -823,6 +1026,18 @@ impl Discovery {
                     );
                   }
                 }
+                #[cfg(feature = "security")]
+                DiscoveryCommand::RekeyLocalEndpoint {
+                  local_endpoint_guid,
+                } => {
+                  if let Some(security) = self.security_opt.as_mut() {
+                    security.rekey_local_endpoint(
+                      local_endpoint_guid,
+                      &self.dcps_participant_volatile_message_secure.writer,
+                      &self.discovery_db,
+                    );
+                  }
+                }
               };
             }
           }
@@ -847,19 +1062,29 @@ impl Discovery {
               error!("DomainParticipant doesn't exist anymore, exiting Discovery.");
               return;
             };
-            // reschedule timer
-            while let Some(policy) = self.dcps_participant.timer.poll() {
-              match policy {
-                TimerPolicy::Repeat => {
-                  self
-                    .dcps_participant
-                    .timer
-                    .set_timeout(Self::SPDP_PUBLISH_PERIOD, TimerPolicy::Repeat);
-                }
-                TimerPolicy::OneShot => {
-                  // Do not set again, since it was one-shot.
-                }
-              }
+            // Drain the timer. It is not set again, because periodic announcements
+            // are scheduled by lease_assertion. The timer is used for the initial
+            // and extra announcements only.
+            while self.dcps_participant.timer.poll().is_some() {}
+          }
+          DISCOVERY_NETWORK_CHANGE_TIMER_TOKEN => {
+            if let Some(change) = self.interface_monitor.check() {
+              self.on_network_change(change);
+            }
+            self
+              .network_change_timer
+              .set_timeout(Self::NETWORK_CHANGE_CHECK_PERIOD, ());
+          }
+          DISCOVERY_SELF_HEALTH_TIMER_TOKEN => {
+            if let (Some(health_publisher), Some(dp)) = (
+              self.health_publisher.as_mut(),
+              self.domain_participant.clone().upgrade(),
+            ) {
+              health_publisher.publish(
+                &dp,
+                &self.discovery_db,
+                self.lease_assertion.late_assertions(),
+              );
             }
           }
           DISCOVERY_READER_DATA_TOKEN => {
@@ -868,6 +1093,9 @@ impl Discovery {
           DISCOVERY_WRITER_DATA_TOKEN => {
             self.sedp_receive_publication(None);
           }
+          DISCOVERY_DAMPING_TIMER_TOKEN => {
+            self.release_endpoint_changes();
+          }
           DISCOVERY_TOPIC_DATA_TOKEN => {
             self.sedp_receive_topic_data(None);
           }
@@ -888,6 +1116,12 @@ impl Discovery {
               .timer
               .set_timeout(Self::CHECK_PARTICIPANT_MESSAGES, TimerPolicy::Repeat);
           }
+          DISCOVERY_TYPE_LOOKUP_REQUEST_TOKEN => {
+            self.type_lookup_request_receive();
+          }
+          DISCOVERY_TYPE_LOOKUP_REPLY_TOKEN => {
+            self.type_lookup_reply_receive();
+          }
           SPDP_LIVENESS_TOKEN => {
             while let Ok(guid_prefix) = self.spdp_liveness_receiver.try_recv() {
               discovery_db_write(&self.discovery_db).participant_is_alive(guid_prefix);
@@ -1011,8 +1245,30 @@ impl Discovery {
     &mut self,
     participant_data: &SpdpDiscoveredParticipantData,
   ) {
-    let was_new = discovery_db_write(&self.discovery_db).update_participant(participant_data);
     let guid_prefix = participant_data.participant_guid.prefix;
+    let (was_new, previous_late_lease_assertions) = {
+      let mut db = discovery_db_write(&self.discovery_db);
+      let previous = db
+        .find_participant_proxy(guid_prefix)
+        .and_then(|p| p.late_lease_assertions)
+        .unwrap_or(0);
+      let was_new = db.update_participant(participant_data);
+      if db.find_participant_proxy(guid_prefix).is_none() {
+        return; // not admitted due to resource limits
+      }
+      (was_new, previous)
+    };
+    if let Some(late_assertions) = participant_data.late_lease_assertions {
+      if late_assertions > previous_late_lease_assertions
+        && guid_prefix != self.domain_participant.guid().prefix
+      {
+        info!("Participant {guid_prefix:?} reports {late_assertions} late lease assertions");
+        self.send_participant_status(DomainParticipantStatusEvent::RemoteLeaseAssertionLate {
+          participant: guid_prefix,
+          late_assertions,
+        });
+      }
+    }
     self.send_discovery_notification(DiscoveryNotificationType::ParticipantUpdated { guid_prefix });
     if was_new {
       // Inform DDS Applications
@@ -1049,6 +1305,7 @@ impl Discovery {
 
   fn process_participant_dispose(&mut self, participant_guidp: GuidPrefix) {
     discovery_db_write(&self.discovery_db).remove_participant(participant_guidp, true); // true = actively removed
+    self.endpoint_damper.forget_participant(participant_guidp);
     self.send_discovery_notification(DiscoveryNotificationType::ParticipantLost {
       guid_prefix: participant_guidp,
     });
@@ -1058,33 +1315,52 @@ impl Discovery {
     });
   }
 
-  fn send_endpoint_dispose_message(&self, endpoint_guid: GUID) {
+  // The endpoint is disposed in the topic it was published in, so that the
+  // disposal of an endpoint whose discovery is protected is not sent in the
+  // clear.
+  fn send_endpoint_dispose_message(
+    &self,
+    endpoint_guid: GUID,
+    _security_info: &Option<EndpointSecurityInfo>, // used only with security feature
+  ) {
+    #[cfg(not(feature = "security"))]
+    let discovery_protected = false;
+    #[cfg(feature = "security")]
+    let discovery_protected = self.security_opt.is_some()
+      && crate::discovery::secure_discovery::is_discovery_protected(_security_info);
+
     let is_writer = endpoint_guid.entity_id.entity_kind.is_writer();
     if is_writer {
-      self
-        .dcps_publication
-        .writer
-        .dispose(&Endpoint_GUID(endpoint_guid), None)
-        .unwrap_or_else(|e| error!("Disposing local Writer: {e:?}"));
-      #[cfg(feature = "security")]
-      self
-        .dcps_publications_secure
-        .writer
-        .dispose(&Endpoint_GUID(endpoint_guid), None)
-        .unwrap_or_else(|e| error!("Disposing local Writer: {e:?}"));
+      if discovery_protected {
+        #[cfg(feature = "security")]
+        self
+          .dcps_publications_secure
+          .writer
+          .dispose(&Endpoint_GUID(endpoint_guid), None)
+          .unwrap_or_else(|e| error!("Disposing local Writer: {e:?}"));
+      } else {
+        self
+          .dcps_publication
+          .writer
+          .dispose(&Endpoint_GUID(endpoint_guid), None)
+          .unwrap_or_else(|e| error!("Disposing local Writer: {e:?}"));
+      }
     } else {
       // is reader
-      self
-        .dcps_subscription
-        .writer
-        .dispose(&Endpoint_GUID(endpoint_guid), None)
-        .unwrap_or_else(|e| error!("Disposing local Reader: {e:?}"));
-      #[cfg(feature = "security")]
-      self
-        .dcps_subscriptions_secure
-        .writer
-        .dispose(&Endpoint_GUID(endpoint_guid), None)
-        .unwrap_or_else(|e| error!("Disposing local Reader: {e:?}"));
+      if discovery_protected {
+        #[cfg(feature = "security")]
+        self
+          .dcps_subscriptions_secure
+          .writer
+          .dispose(&Endpoint_GUID(endpoint_guid), None)
+          .unwrap_or_else(|e| error!("Disposing local Reader: {e:?}"));
+      } else {
+        self
+          .dcps_subscription
+          .writer
+          .dispose(&Endpoint_GUID(endpoint_guid), None)
+          .unwrap_or_else(|e| error!("Disposing local Reader: {e:?}"));
+      }
     }
   }
 
@@ -1092,11 +1368,19 @@ impl Discovery {
     let db = discovery_db_read(&self.discovery_db);
 
     for reader in db.get_all_local_topic_readers() {
-      self.send_endpoint_dispose_message(reader.reader_proxy.remote_reader_guid);
+      #[cfg(feature = "security")]
+      let security_info = reader.subscription_topic_data.security_info();
+      #[cfg(not(feature = "security"))]
+      let security_info = &None;
+      self.send_endpoint_dispose_message(reader.reader_proxy.remote_reader_guid, security_info);
     }
 
     for writer in db.get_all_local_topic_writers() {
-      self.send_endpoint_dispose_message(writer.writer_proxy.remote_writer_guid);
+      #[cfg(feature = "security")]
+      let security_info = writer.publication_topic_data.security_info();
+      #[cfg(not(feature = "security"))]
+      let security_info = &None;
+      self.send_endpoint_dispose_message(writer.writer_proxy.remote_writer_guid, security_info);
     }
 
     self
@@ -1114,11 +1398,20 @@ impl Discovery {
 
   // Check if there are messages about new Readers
   pub fn sedp_receive_subscription(&mut self, read_history: Option<GuidPrefix>) {
-    let drds: Vec<Sample<DiscoveredReaderData, GUID>> =
-      match self.dcps_subscription.reader.into_iterator() {
-        Ok(ds) => ds
-          .map(|d| d.map_dispose(|g| g.0)) // map_dispose removes Endpoint_GUID wrapper around GUID
-          .filter(|d|
+    let drds: Vec<(SequenceNumber, Sample<DiscoveredReaderData, GUID>)> = match self
+      .dcps_subscription
+      .reader
+      .take(usize::MAX, ReadCondition::not_read())
+    {
+      Ok(ds) => ds
+        .into_iter()
+        .map(|d| {
+          (
+            d.sample_info().sequence_number(),
+            d.into_value().map_dispose(|g| g.0),
+          )
+        }) // map_dispose removes Endpoint_GUID wrapper around GUID
+        .filter(|(_, d)|
               // If a participant was specified, we must match its GUID prefix.
               match (read_history, d) {
                 (None, _) => true, // Not asked to filter by participant
@@ -1127,14 +1420,14 @@ impl Discovery {
                 (Some(participant_to_update), Sample::Dispose(guid)) =>
                   guid.prefix == participant_to_update,
               })
-          .collect(),
-        Err(e) => {
-          error!("sedp_receive_subscription: {e:?}");
-          return;
-        }
-      };
+        .collect(),
+      Err(e) => {
+        error!("sedp_receive_subscription: {e:?}");
+        return;
+      }
+    };
 
-    for d in drds {
+    for (sn, d) in drds {
       #[cfg(not(feature = "security"))]
       let permission = NormalDiscoveryPermission::Allow;
 
@@ -1148,66 +1441,72 @@ impl Discovery {
       };
 
       if permission == NormalDiscoveryPermission::Allow {
-        match d {
+        let reader_guid = match &d {
           Sample::Value(d) => {
-            let drd = discovery_db_write(&self.discovery_db).update_subscription(&d);
-            debug!(
-              "sedp_receive_subscription - send_discovery_notification ReaderUpdated  {:?}",
-              &drd
-            );
-            self.send_discovery_notification(DiscoveryNotificationType::ReaderUpdated {
-              discovered_reader_data: drd,
-            });
+            let reader_guid = d.reader_proxy.remote_reader_guid;
+            let mut db = discovery_db_write(&self.discovery_db);
+            if db.is_redundant_subscription(d, sn) {
+              drop(db);
+              trace!("sedp_receive_subscription: no change to {:?}", reader_guid);
+              self.revert_endpoint_change(reader_guid, sn);
+              continue;
+            }
+            if !db.admits_endpoint(reader_guid)
+              || !db.admits_topic(d.subscription_topic_data.topic_name())
+            {
+              continue;
+            }
             if read_history.is_some() {
               info!(
                 "Rediscovered reader {:?} topic={:?}",
-                d.reader_proxy.remote_reader_guid,
+                reader_guid,
                 d.subscription_topic_data.topic_name()
               );
             }
+            reader_guid
           }
-          Sample::Dispose(reader_key) => {
-            info!("Dispose Reader {:?}", reader_key);
-            discovery_db_write(&self.discovery_db).remove_topic_reader(reader_key);
-            self.send_discovery_notification(DiscoveryNotificationType::ReaderLost {
-              reader_guid: reader_key,
-            });
-            self.send_participant_status(DomainParticipantStatusEvent::ReaderLost {
-              guid: reader_key,
-              reason: LostReason::Disposed,
-            });
-          }
-        }
+          Sample::Dispose(reader_key) => *reader_key,
+        };
+        self.damp_endpoint_change(reader_guid, EndpointChange::Reader(sn, d));
       }
     } // loop
   }
 
   pub fn sedp_receive_publication(&mut self, read_history: Option<GuidPrefix>) {
-    let dwds: Vec<Sample<DiscoveredWriterData, GUID>> =
-      match self.dcps_publication.reader.into_iterator() {
-        // a lot of cloning here, but we must copy the data out of the
-        // reader before we can use self again, as .read() returns references to within
-        // a reader and thus self
-        Ok(ds) => ds
-          .map(|d| d.map_dispose(|g| g.0)) // map_dispose removes Endpoint_GUID wrapper around GUID
-          // If a participant was specified, we must match its GUID prefix.
-          .filter(|d| match (read_history, d) {
-            (None, _) => true, // Not asked to filter by participant
-            (Some(participant_to_update), Sample::Value(dwd)) => {
-              dwd.writer_proxy.remote_writer_guid.prefix == participant_to_update
-            }
-            (Some(participant_to_update), Sample::Dispose(guid)) => {
-              guid.prefix == participant_to_update
-            }
-          })
-          .collect(),
-        Err(e) => {
-          error!("sedp_receive_publication: {e:?}");
-          return;
-        }
-      };
+    let dwds: Vec<(SequenceNumber, Sample<DiscoveredWriterData, GUID>)> = match self
+      .dcps_publication
+      .reader
+      .take(usize::MAX, ReadCondition::not_read())
+    {
+      // a lot of cloning here, but we must copy the data out of the
+      // reader before we can use self again, as .read() returns references to within
+      // a reader and thus self
+      Ok(ds) => ds
+        .into_iter()
+        .map(|d| {
+          (
+            d.sample_info().sequence_number(),
+            d.into_value().map_dispose(|g| g.0),
+          )
+        }) // map_dispose removes Endpoint_GUID wrapper around GUID
+        // If a participant was specified, we must match its GUID prefix.
+        .filter(|(_, d)| match (read_history, d) {
+          (None, _) => true, // Not asked to filter by participant
+          (Some(participant_to_update), Sample::Value(dwd)) => {
+            dwd.writer_proxy.remote_writer_guid.prefix == participant_to_update
+          }
+          (Some(participant_to_update), Sample::Dispose(guid)) => {
+            guid.prefix == participant_to_update
+          }
+        })
+        .collect(),
+      Err(e) => {
+        error!("sedp_receive_publication: {e:?}");
+        return;
+      }
+    };
 
-    for d in dwds {
+    for (sn, d) in dwds {
       #[cfg(not(feature = "security"))]
       let permission = NormalDiscoveryPermission::Allow;
 
@@ -1221,31 +1520,134 @@ impl Discovery {
       };
 
       if permission == NormalDiscoveryPermission::Allow {
-        match d {
+        let writer_guid = match &d {
           Sample::Value(dwd) => {
-            trace!("sedp_receive_publication discovered {:?}", &dwd);
-            let discovered_writer_data =
-              discovery_db_write(&self.discovery_db).update_publication(&dwd);
-            self.send_discovery_notification(DiscoveryNotificationType::WriterUpdated {
-              discovered_writer_data,
-            });
-            debug!("Discovered Writer {:?}", &dwd);
+            trace!("sedp_receive_publication discovered {:?}", dwd);
+            let writer_guid = dwd.writer_proxy.remote_writer_guid;
+            let mut db = discovery_db_write(&self.discovery_db);
+            if db.is_redundant_publication(dwd, sn) {
+              drop(db);
+              trace!("sedp_receive_publication: no change to {:?}", writer_guid);
+              self.revert_endpoint_change(writer_guid, sn);
+              continue;
+            }
+            if !db.admits_endpoint(writer_guid)
+              || !db.admits_topic(dwd.publication_topic_data.topic_name())
+            {
+              continue;
+            }
+            writer_guid
           }
-          Sample::Dispose(writer_key) => {
-            discovery_db_write(&self.discovery_db).remove_topic_writer(writer_key);
-            self.send_discovery_notification(DiscoveryNotificationType::WriterLost {
-              writer_guid: writer_key,
-            });
-            self.send_participant_status(DomainParticipantStatusEvent::WriterLost {
-              guid: writer_key,
-              reason: LostReason::Disposed,
-            });
+          Sample::Dispose(writer_key) => *writer_key,
+        };
+        self.damp_endpoint_change(writer_guid, EndpointChange::Writer(sn, d));
+      }
+    } // loop
+  }
 
-            debug!("Disposed Writer {:?}", writer_key);
-          }
+  // Applies the change of a remote endpoint, or holds it, if discovery damping
+  // says so.
+  fn damp_endpoint_change(&mut self, endpoint: GUID, change: EndpointChange) {
+    let now = Instant::now();
+    if let Some(change) = self.endpoint_damper.admit(endpoint, change, now) {
+      self.apply_endpoint_change(change);
+    } else {
+      trace!("Discovery damping holds a change of {:?}", endpoint);
+    }
+    self.set_damping_timer(now);
+  }
+
+  // A redundant sample puts the endpoint back to the state that was applied
+  // last, so a held change of it is dropped. A resend of the held sample
+  // itself is redundant too, but must not drop it.
+  fn revert_endpoint_change(&mut self, endpoint: GUID, sn: SequenceNumber) {
+    let resend = self
+      .endpoint_damper
+      .held(endpoint)
+      .is_some_and(|change| change.sequence_number() == sn);
+    if !resend {
+      self.endpoint_damper.revert(endpoint, Instant::now());
+    }
+  }
+
+  fn release_endpoint_changes(&mut self) {
+    while self.damping_timer.poll().is_some() {}
+    self.damping_timer_deadline = None;
+    let now = Instant::now();
+    for change in self.endpoint_damper.release(now) {
+      self.apply_endpoint_change(change);
+    }
+    self.set_damping_timer(now);
+  }
+
+  // Sets the timer for the next held change, unless it already fires earlier.
+  // An early timeout only releases nothing and sets the timer again.
+  fn set_damping_timer(&mut self, now: Instant) {
+    if let Some(next_release) = self.endpoint_damper.next_release() {
+      if self
+        .damping_timer_deadline
+        .map_or(true, |deadline| next_release < deadline)
+      {
+        self
+          .damping_timer
+          .set_timeout(next_release.saturating_duration_since(now), ());
+        self.damping_timer_deadline = Some(next_release);
+      }
+    }
+  }
+
+  fn apply_endpoint_change(&mut self, change: EndpointChange) {
+    match change {
+      EndpointChange::Reader(_, Sample::Value(d)) => {
+        let drd = discovery_db_write(&self.discovery_db).update_subscription(&d);
+        debug!(
+          "sedp_receive_subscription - send_discovery_notification ReaderUpdated  {:?}",
+          &drd
+        );
+        self.send_discovery_notification(DiscoveryNotificationType::ReaderUpdated {
+          discovered_reader_data: drd,
+        });
+      }
+      EndpointChange::Reader(_, Sample::Dispose(reader_key)) => {
+        info!("Dispose Reader {:?}", reader_key);
+        discovery_db_write(&self.discovery_db).remove_topic_reader(reader_key);
+        self.send_discovery_notification(DiscoveryNotificationType::ReaderLost {
+          reader_guid: reader_key,
+        });
+        self.send_participant_status(DomainParticipantStatusEvent::ReaderLost {
+          guid: reader_key,
+          reason: LostReason::Disposed,
+        });
+      }
+      EndpointChange::Writer(_, Sample::Value(dwd)) => {
+        let discovered_writer_data =
+          discovery_db_write(&self.discovery_db).update_publication(&dwd);
+        self.send_discovery_notification(DiscoveryNotificationType::WriterUpdated {
+          discovered_writer_data,
+        });
+        // Look up the type even if no local reader needs it, so that
+        // applications can read the topic as DynamicData.
+        if let Some(type_information) = dwd.publication_topic_data.type_information {
+          self.request_type(
+            dwd.writer_proxy.remote_writer_guid.prefix,
+            type_information.complete,
+          );
         }
+        debug!("Discovered Writer {:?}", &dwd);
       }
-    } // loop
+      EndpointChange::Writer(_, Sample::Dispose(writer_key)) => {
+        discovery_db_write(&self.discovery_db).remove_topic_writer(writer_key);
+        self.send_discovery_notification(DiscoveryNotificationType::WriterLost {
+          writer_guid: writer_key,
+        });
+        self.send_participant_status(DomainParticipantStatusEvent::WriterLost {
+          guid: writer_key,
+          reason: LostReason::Disposed,
+        });
+
+        debug!("Disposed Writer {:?}", writer_key);
+      }
+    }
   }
 
   // TODO: Try to remember why the read_history parameter below was introduced
@@ -1290,12 +1692,13 @@ impl Discovery {
       if permission == NormalDiscoveryPermission::Allow {
         match t {
           Sample::Value((topic_data, writer)) => {
+            let mut db = discovery_db_write(&self.discovery_db);
+            if !db.admits_topic(topic_data.topic_name()) {
+              continue;
+            }
             debug!("sedp_receive_topic_data discovered {:?}", &topic_data);
-            discovery_db_write(&self.discovery_db).update_topic_data(
-              &topic_data,
-              writer,
-              DiscoveredVia::Topic,
-            );
+            db.update_topic_data(&topic_data, writer, DiscoveredVia::Topic);
+            drop(db);
             // Now check if we know any readers of writers to this topic. The topic QoS
             // could cause these to became viable matches against local
             // writers/readers. This is because at least RTI Connext sends QoS
@@ -1331,6 +1734,121 @@ impl Discovery {
     } // loop
   }
 
+  // Ask a remote participant for a type, which we know only by its identifier.
+  // The reply is handled in type_lookup_reply_receive().
+  fn request_type(&mut self, participant: GuidPrefix, type_id: TypeIdentifier) {
+    if discovery_db_read(&self.discovery_db)
+      .get_type_object(&type_id)
+      .is_some()
+    {
+      return; // already received
+    }
+    let now = Instant::now();
+    // Several endpoints of the same type cause several lookups. Ask again only
+    // if the previous request seems to be lost.
+    if let Some(requested) = self.type_lookups_pending.get(&type_id) {
+      if now.duration_since(*requested) < Self::TYPE_LOOKUP_RETRY_PERIOD {
+        return;
+      }
+    }
+    self.type_lookups_pending.insert(type_id, now);
+
+    debug!("Requesting type {type_id} from {participant:?}");
+    let request = TypeLookupRequest {
+      header: RequestHeader::default(),
+      type_ids: vec![type_id],
+    };
+    let write_options = WriteOptionsBuilder::new()
+      .to_single_reader(GUID::new(participant, EntityId::RUSTDDS_TYPE_LOOKUP_REQUEST_READER))
+      .build();
+    if let Err(e) = self
+      .type_lookup_request
+      .writer
+      .write_with_options(request, write_options)
+    {
+      error!("Failed to send TypeLookup request: {e:?}");
+    }
+  }
+
+  // Answer TypeLookup requests with the types that we know
+  fn type_lookup_request_receive(&mut self) {
+    loop {
+      let sample = match self.type_lookup_request.reader.take_next_sample() {
+        Ok(Some(sample)) => sample,
+        Ok(None) => return, // no more data
+        Err(e) => {
+          error!("type_lookup_request_receive: {e:?}");
+          return;
+        }
+      };
+      let requester = sample.sample_info().writer_guid().prefix;
+      let request_id = sample.sample_info().sample_identity();
+      let request = sample.into_value();
+
+      let types = {
+        let db = discovery_db_read(&self.discovery_db);
+        request
+          .type_ids
+          .iter()
+          .filter_map(|id| db.get_type_object(id).map(|t| (*id, t.clone())))
+          .collect()
+      };
+      let reply = TypeLookupReply {
+        header: ReplyHeader {
+          related_request_id: request_id,
+          remote_ex: RemoteExceptionCode::Ok,
+        },
+        types,
+      };
+      let write_options = WriteOptionsBuilder::new()
+        .to_single_reader(GUID::new(requester, EntityId::RUSTDDS_TYPE_LOOKUP_REPLY_READER))
+        .related_sample_identity(request_id)
+        .build();
+      if let Err(e) = self
+        .type_lookup_reply
+        .writer
+        .write_with_options(reply, write_options)
+      {
+        error!("Failed to send TypeLookup reply: {e:?}");
+      }
+    }
+  }
+
+  // Store the received types, and retry matching the remote endpoints of those
+  // types.
+  fn type_lookup_reply_receive(&mut self) {
+    let replies: Vec<TypeLookupReply> = match self.type_lookup_reply.reader.into_iterator() {
+      Ok(iter) => iter.collect(),
+      Err(e) => {
+        error!("type_lookup_reply_receive: {e:?}");
+        return;
+      }
+    };
+
+    for (type_id, type_object) in replies.into_iter().flat_map(|reply| reply.types) {
+      self.type_lookups_pending.remove(&type_id);
+      let mut db = discovery_db_write(&self.discovery_db);
+      if !db.add_remote_type_object(type_id, type_object) {
+        warn!("TypeLookup reply contained a type not matching its identifier {type_id}");
+        continue;
+      }
+      let writers = db.writers_of_type(&type_id);
+      let readers = db.readers_of_type(&type_id);
+      drop(db);
+
+      for discovered_writer_data in writers {
+        self.send_discovery_notification(DiscoveryNotificationType::WriterUpdated {
+          discovered_writer_data,
+        });
+      }
+      for discovered_reader_data in readers {
+        self.send_discovery_notification(DiscoveryNotificationType::ReaderUpdated {
+          discovered_reader_data,
+        });
+      }
+    }
+  }
+
   // These messages are for updating participant liveliness
   // The protocol distinguishes between automatic (by DDS library)
   // and manual (by by application, via DDS API call) liveness
@@ -1376,14 +1894,34 @@ impl Discovery {
     }
   }
 
+  // setting 5 times the duration so lease doesn't break if update fails once or
+  // twice
+  fn spdp_lease_duration() -> Duration {
+    5.0 * Duration::from(Self::SPDP_PUBLISH_PERIOD)
+  }
+
+  // Returns false, if the DomainParticipant is gone, and Discovery should stop.
+  fn assert_participant_lease_if_due(&self) -> bool {
+    self.lease_assertion.repeat_if_not_sent();
+    if !self.lease_assertion.is_due() {
+      return true;
+    }
+    if let Some(dp) = self.domain_participant.clone().upgrade() {
+      self.spdp_publish(&dp);
+      true
+    } else {
+      error!("DomainParticipant doesn't exist anymore, exiting Discovery.");
+      false
+    }
+  }
+
   fn spdp_publish(&self, local_dp: &DomainParticipant) {
-    // setting 5 times the duration so lease doesn't break if update fails once or
-    // twice
-    let data = SpdpDiscoveredParticipantData::from_local_participant(
+    let mut data = SpdpDiscoveredParticipantData::from_local_participant(
       local_dp,
       &self.security_opt,
-      5.0 * Duration::from(Self::SPDP_PUBLISH_PERIOD),
+      Self::spdp_lease_duration(),
     );
+    data.late_lease_assertions = Some(self.lease_assertion.late_assertions());
 
     #[cfg(feature = "security")]
     if let Some(security) = self.security_opt.as_ref() {
@@ -1397,6 +1935,34 @@ impl Discovery {
       .unwrap_or_else(|e| {
         error!("Discovery: Publishing to DCPS participant topic failed: {e:?}");
       });
+    self.lease_assertion.written();
+  }
+
+  // Local IP addresses have changed, e.g. due to WiFi roaming. Update the
+  // locators we advertise, and announce them at once, so that remote
+  // participants can reach us again without waiting for leases to expire.
+  fn on_network_change(&self, change: AddressChange) {
+    let dp = if let Some(dp) = self.domain_participant.clone().upgrade() {
+      dp
+    } else {
+      error!("DomainParticipant doesn't exist anymore, cannot handle network change.");
+      return;
+    };
+    dp.network_changed();
+    let unicast_locators = dp
+      .self_locators()
+      .remove(&USER_TRAFFIC_LISTENER_TOKEN)
+      .unwrap_or_default();
+    discovery_db_write(&self.discovery_db).update_local_unicast_locators(&unicast_locators);
+
+    self.spdp_publish(&dp);
+    self.sedp_publish_writers();
+    self.sedp_publish_readers();
+
+    self.send_participant_status(DomainParticipantStatusEvent::NetworkChanged {
+      added: change.added,
+      removed: change.removed,
+    });
   }
 
   pub fn publish_participant_message(&mut self) {
@@ -1590,11 +2156,12 @@ impl Discovery {
 
   #[cfg(feature = "security")]
   pub fn secure_sedp_receive_subscription(&mut self, read_history: Option<GuidPrefix>) {
-    let sec_subs: Vec<Sample<SubscriptionBuiltinTopicDataSecure, GUID>> =
-      match self.dcps_subscriptions_secure.reader.into_iterator() {
+    let sec_subs: Vec<(SequenceNumber, Sample<SubscriptionBuiltinTopicDataSecure, GUID>)> =
+      match self.dcps_subscriptions_secure.reader.take(usize::MAX, ReadCondition::not_read()) {
         Ok(ds) => ds
-          .map(|d| d.map_dispose(|g| g.0)) // map_dispose removes Endpoint_GUID wrapper around GUID
-          .filter(|d|
+          .into_iter()
+          .map(|d| (d.sample_info().sequence_number(), d.into_value().map_dispose(|g| g.0))) // map_dispose removes Endpoint_GUID wrapper around GUID
+          .filter(|(_, d)|
               // If a participant was specified, we must match its GUID prefix.
               match (read_history, d) {
                 (None, _) => true, // Not asked to filter by participant
@@ -1610,7 +2177,7 @@ impl Discovery {
         }
       };
 
-    for sec_sub_sample in sec_subs {
+    for (sn, sec_sub_sample) in sec_subs {
       let permission = if let Some(security) = self.security_opt.as_mut() {
         security.check_secure_subscription_read(&sec_sub_sample, &self.discovery_db)
       } else {
@@ -1623,7 +2190,15 @@ impl Discovery {
           Sample::Value(sec_sub) => {
             // Currently we use only the DiscoveredReaderData field, no DataTag
             let drd_from_topic = sec_sub.discovered_reader_data;
-            let drd = discovery_db_write(&self.discovery_db).update_subscription(&drd_from_topic);
+            let mut db = discovery_db_write(&self.discovery_db);
+            if db.is_redundant_subscription(&drd_from_topic, sn)
+              || !db.admits_endpoint(drd_from_topic.reader_proxy.remote_reader_guid)
+              || !db.admits_topic(drd_from_topic.subscription_topic_data.topic_name())
+            {
+              continue;
+            }
+            let drd = db.update_subscription(&drd_from_topic);
+            drop(db);
             self.send_discovery_notification(DiscoveryNotificationType::ReaderUpdated {
               discovered_reader_data: drd,
             });
@@ -1644,33 +2219,45 @@ impl Discovery {
 
   #[cfg(feature = "security")]
   pub fn secure_sedp_receive_publication(&mut self, read_history: Option<GuidPrefix>) {
-    let sec_pubs: Vec<Sample<PublicationBuiltinTopicDataSecure, GUID>> =
-      match self.dcps_publications_secure.reader.into_iterator() {
-        Ok(ds) => ds
-          .map(|d| d.map_dispose(|g| g.0)) // map_dispose removes Endpoint_GUID wrapper around GUID
-          // If a participant was specified, we must match its GUID prefix.
-          .filter(|d| match (read_history, d) {
-            (None, _) => true, // Not asked to filter by participant
-            (Some(participant_to_update), Sample::Value(sec_pub)) => {
-              sec_pub
-                .discovered_writer_data
-                .writer_proxy
-                .remote_writer_guid
-                .prefix
-                == participant_to_update
-            }
-            (Some(participant_to_update), Sample::Dispose(guid)) => {
-              guid.prefix == participant_to_update
-            }
-          })
-          .collect(),
-        Err(e) => {
-          error!("secure_sedp_receive_publication: {e:?}");
-          return;
-        }
-      };
+    let sec_pubs: Vec<(
+      SequenceNumber,
+      Sample<PublicationBuiltinTopicDataSecure, GUID>,
+    )> = match self
+      .dcps_publications_secure
+      .reader
+      .take(usize::MAX, ReadCondition::not_read())
+    {
+      Ok(ds) => ds
+        .into_iter()
+        .map(|d| {
+          (
+            d.sample_info().sequence_number(),
+            d.into_value().map_dispose(|g| g.0),
+          )
+        }) // map_dispose removes Endpoint_GUID wrapper around GUID
+        // If a participant was specified, we must match its GUID prefix.
+        .filter(|(_, d)| match (read_history, d) {
+          (None, _) => true, // Not asked to filter by participant
+          (Some(participant_to_update), Sample::Value(sec_pub)) => {
+            sec_pub
+              .discovered_writer_data
+              .writer_proxy
+              .remote_writer_guid
+              .prefix
+              == participant_to_update
+          }
+          (Some(participant_to_update), Sample::Dispose(guid)) => {
+            guid.prefix == participant_to_update
+          }
+        })
+        .collect(),
+      Err(e) => {
+        error!("secure_sedp_receive_publication: {e:?}");
+        return;
+      }
+    };
 
-    for sec_pub_sample in sec_pubs {
+    for (sn, sec_pub_sample) in sec_pubs {
       let permission = if let Some(security) = self.security_opt.as_mut() {
         security.check_secure_publication_read(&sec_pub_sample, &self.discovery_db)
       } else {
@@ -1683,7 +2270,15 @@ impl Discovery {
           Sample::Value(se_pub) => {
             // Currently we use only the DiscoveredWriterData field, no DataTag
             let dwd_from_topic = se_pub.discovered_writer_data;
-            let dwd = discovery_db_write(&self.discovery_db).update_publication(&dwd_from_topic);
+            let mut db = discovery_db_write(&self.discovery_db);
+            if db.is_redundant_publication(&dwd_from_topic, sn)
+              || !db.admits_endpoint(dwd_from_topic.writer_proxy.remote_writer_guid)
+              || !db.admits_topic(dwd_from_topic.publication_topic_data.topic_name())
+            {
+              continue;
+            }
+            let dwd = db.update_publication(&dwd_from_topic);
+            drop(db);
             self.send_discovery_notification(DiscoveryNotificationType::WriterUpdated {
               discovered_writer_data: dwd,
             });
@@ -1718,10 +2313,11 @@ impl Discovery {
     }
   }
 
-  pub fn participant_cleanup(&self) {
+  pub fn participant_cleanup(&mut self) {
     let removed = discovery_db_write(&self.discovery_db).participant_cleanup();
     for (guid_prefix, reason) in removed {
       debug!("participant cleanup - timeout for {:?}", guid_prefix);
+      self.endpoint_damper.forget_participant(guid_prefix);
       self.send_discovery_notification(DiscoveryNotificationType::ParticipantLost { guid_prefix });
       self.send_participant_status(DomainParticipantStatusEvent::ParticipantLost {
         id: guid_prefix,
@@ -1747,6 +2343,10 @@ impl Discovery {
         // Only readers of user-defined topics are published to discovery
         return;
       }
+      if !db.admits_topic(reader_data.subscription_topic_data.topic_name()) {
+        // Neither announced nor matched, see DiscoveryNetworkSettings::topic_filter
+        return;
+      }
 
       #[cfg(not(feature = "security"))]
       let do_nonsecure_write = true;
@@ -1823,6 +2423,9 @@ impl Discovery {
         // Only writers of user-defined topics are published to discovery
         return;
       }
+      if !db.admits_topic(writer_data.publication_topic_data.topic_name()) {
+        return;
+      }
 
       #[cfg(not(feature = "security"))]
       let do_nonsecure_write = true;
@@ -1900,10 +2503,20 @@ impl Discovery {
 
     // Only user-defined topics are published to discovery
     let is_user_defined = !topic_data.topic_name().starts_with("DCPS");
-    if !is_user_defined {
+    if !is_user_defined || !db.admits_topic(topic_name) {
       return;
     }
 
+    #[cfg(feature = "security")]
+    if let Some(security) = self.security_opt.as_ref() {
+      if security.is_topic_discovery_protected(topic_name) {
+        // DCPSTopic has no secure counterpart. The readers and writers of the
+        // topic are announced in the secure topics only.
+        debug!("Not publishing topic {topic_name} to DCPSTopic, its discovery is protected");
+        return;
+      }
+    }
+
     match self.dcps_topic.writer.write(topic_data.clone(), None) {
       Ok(()) => {
         debug!("Published topic {topic_name} to DCPSTopic");
@@ -2002,6 +2615,17 @@ impl Discovery {
       .build()
   }
 
+  // DDS-XTypes 1.3 Section 7.6.3.3.4 Builtin Endpoints
+  pub fn create_type_lookup_qos() -> QosPolicies {
+    QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_std(StdDuration::from_millis(100)),
+      })
+      .history(History::KeepAll)
+      .durability(Durability::Volatile)
+      .build()
+  }
+
   #[cfg(feature = "security")]
   pub fn create_participant_stateless_message_qos() -> QosPolicies {
     // See section 7.4.3 "New DCPSParticipantStatelessMessage builtin Topic" of the
@@ -11,9 +11,10 @@ use log::{debug, error, info, trace, warn};
 use crate::{
   dds::{
     participant::DomainParticipant,
-    qos::HasQoSPolicy,
+    qos::{policy::Partition, HasQoSPolicy},
     statusevents::{DomainParticipantStatusEvent, LostReason, StatusChannelSender},
     topic::{Topic, TopicDescription},
+    topic_namespace::DiscoveryTopicFilter,
   },
   rtps::{
     reader::ReaderIngredients, rtps_reader_proxy::RtpsReaderProxy,
@@ -23,9 +24,13 @@ use crate::{
     duration::Duration,
     entity::RTPSEntity,
     guid::{EntityId, GuidPrefix, GUID},
+    locator::Locator,
+    sequence_number::SequenceNumber,
   },
+  xtypes::{is_assignable, AssignabilityResult, TypeIdentifier, TypeInformation, TypeObject},
 };
 use super::{
+  content_filter_property::ContentFilterProperty,
   sedp_messages::{
     topics_inconsistent, DiscoveredReaderData, DiscoveredTopicData, DiscoveredWriterData,
     ParticipantMessageData, ReaderProxy, SubscriptionBuiltinTopicData, TopicBuiltinTopicData,
@@ -70,6 +75,12 @@ pub(crate) struct DiscoveryDB {
   external_topic_readers_attic: BTreeMap<GUID, DiscoveredReaderData>,
   external_topic_writers_attic: BTreeMap<GUID, DiscoveredWriterData>,
 
+  // Sequence numbers of the SEDP samples, which were last processed for each
+  // remote reader and writer. See is_redundant_subscription(). Entries are
+  // removed together with the endpoint, and are not kept in the attic, so that
+  // a rediscovered endpoint is matched again.
+  sedp_sequence_numbers: BTreeMap<GUID, SequenceNumber>,
+
   // Database of topic updates:
   // Outer level key is topic name
   // Inner key is topic data sender.
@@ -79,6 +90,31 @@ pub(crate) struct DiscoveryDB {
   topic_updated_sender: mio_extras::channel::SyncSender<()>,
 
   participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
+
+  // Limits from ResourceSettings. None means unlimited.
+  max_participants: Option<usize>,
+  max_endpoints: Option<usize>,
+
+  // Only endpoints and topics allowed by this are discovered or announced.
+  // None means all topics.
+  topic_filter: Option<DiscoveryTopicFilter>,
+
+  // Types of local topics, and remote types received from the TypeLookup
+  // service, by their complete TypeIdentifiers
+  type_objects: BTreeMap<TypeIdentifier, TypeObject>,
+}
+
+// Whether a reader and a writer can be matched, as far as their types are
+// concerned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TypeMatch {
+  // The types are assignable, or at least one of the endpoints did not tell
+  // its type. The latter are matched by topic name alone.
+  Assignable,
+  NotAssignable(AssignabilityResult),
+  // The type is not known yet, so the endpoints are not matched before it is
+  // looked up.
+  LookupNeeded(TypeIdentifier),
 }
 
 // How did we discover this topic
@@ -137,9 +173,60 @@ impl DiscoveryDB {
       external_topic_writers: BTreeMap::new(),
       external_topic_readers_attic: BTreeMap::new(),
       external_topic_writers_attic: BTreeMap::new(),
+      sedp_sequence_numbers: BTreeMap::new(),
       topics: BTreeMap::new(),
       topic_updated_sender,
       participant_status_sender,
+      max_participants: None,
+      max_endpoints: None,
+      topic_filter: None,
+      type_objects: BTreeMap::new(),
+    }
+  }
+
+  pub fn set_limits(&mut self, max_participants: Option<usize>, max_endpoints: Option<usize>) {
+    self.max_participants = max_participants;
+    self.max_endpoints = max_endpoints;
+  }
+
+  // Known participants are always admitted. A new one only if there is room.
+  fn admits_participant(&self, guid_prefix: GuidPrefix) -> bool {
+    let count = self.participant_proxies.len();
+    match self.max_participants {
+      Some(max) if count >= max && !self.participant_proxies.contains_key(&guid_prefix) => {
+        debug!("Ignoring participant {guid_prefix:?}: already {count} participants discovered");
+        false
+      }
+      _ => true,
+    }
+  }
+
+  pub fn set_topic_filter(&mut self, topic_filter: Option<DiscoveryTopicFilter>) {
+    self.topic_filter = topic_filter;
+  }
+
+  // Applies both to remote endpoints and topics, and to announcing local ones.
+  pub fn admits_topic(&self, topic_name: &str) -> bool {
+    match &self.topic_filter {
+      Some(filter) if !filter.allows(topic_name) => {
+        trace!("Ignoring topic {topic_name:?}: not allowed by discovery topic filter");
+        false
+      }
+      _ => true,
+    }
+  }
+
+  // Same for remote readers and writers
+  pub fn admits_endpoint(&self, guid: GUID) -> bool {
+    let count = self.external_topic_readers.len() + self.external_topic_writers.len();
+    let known = self.external_topic_readers.contains_key(&guid)
+      || self.external_topic_writers.contains_key(&guid);
+    match self.max_endpoints {
+      Some(max) if count >= max && !known => {
+        debug!("Ignoring endpoint {guid:?}: already {count} remote endpoints discovered");
+        false
+      }
+      _ => true,
     }
   }
 
@@ -165,6 +252,10 @@ impl DiscoveryDB {
       return false;
     }
 
+    if !self.admits_participant(guid.prefix) {
+      return false;
+    }
+
     // We allow discovery to discover self, since our discovery readers
     // will receive our own announcements via broadcast. If we do not recognize
     // our own participant, there is confusion about unknown writers on the
@@ -235,6 +326,9 @@ impl DiscoveryDB {
     self.participant_last_life_signs.remove(&guid_prefix);
     #[cfg(feature = "security")]
     self.authentication_statuses.remove(&guid_prefix);
+    self
+      .sedp_sequence_numbers
+      .retain(|guid, _| guid.prefix != guid_prefix);
 
     if active_disposal {
       self.remove_topic_reader_with_prefix(guid_prefix);
@@ -254,6 +348,15 @@ impl DiscoveryDB {
     }
   }
 
+  // All participants except the local one
+  pub fn remote_participants(&self) -> impl Iterator<Item = &SpdpDiscoveredParticipantData> {
+    self
+      .participant_proxies
+      .iter()
+      .filter(move |(prefix, _)| **prefix != self.my_guid.prefix)
+      .map(|(_, data)| data)
+  }
+
   pub fn find_participant_proxy(
     &self,
     guid_prefix: GuidPrefix,
@@ -277,6 +380,7 @@ impl DiscoveryDB {
   pub fn remove_topic_reader(&mut self, guid: GUID) {
     info!("remove_topic_reader {:?}", guid);
     self.external_topic_readers.remove(&guid);
+    self.sedp_sequence_numbers.remove(&guid);
   }
 
   #[cfg(feature = "security")]
@@ -304,6 +408,7 @@ impl DiscoveryDB {
 
   pub fn remove_topic_writer(&mut self, guid: GUID) {
     self.external_topic_writers.remove(&guid);
+    self.sedp_sequence_numbers.remove(&guid);
   }
 
   // Delete participant proxies, if we have not heard of them within
@@ -414,6 +519,88 @@ impl DiscoveryDB {
     self.local_topic_writers.remove(&guid);
   }
 
+  // Local IP addresses have changed. Our endpoints are now reachable at
+  // `locators`, and must be announced again in SEDP.
+  pub fn update_local_unicast_locators(&mut self, locators: &[Locator]) {
+    for reader in self.local_topic_readers.values_mut() {
+      reader.reader_proxy.unicast_locator_list = locators.to_vec();
+    }
+    for writer in self.local_topic_writers.values_mut() {
+      writer.writer_proxy.unicast_locator_list = locators.to_vec();
+    }
+  }
+
+  // A local endpoint was moved to another Partition, which must be announced
+  // again in SEDP.
+  pub fn update_local_endpoint_partition(&mut self, guid: GUID, partition: &Partition) {
+    if let Some(reader) = self.local_topic_readers.get_mut(&guid) {
+      let mut qos = reader.subscription_topic_data.qos();
+      qos.partition = Some(partition.clone());
+      reader.subscription_topic_data.set_qos(&qos);
+    }
+    if let Some(writer) = self.local_topic_writers.get_mut(&guid) {
+      writer.publication_topic_data.partition = Some(partition.clone());
+    }
+  }
+
+  // A local reader set or cleared its content filter, which must be announced
+  // again in SEDP.
+  pub fn update_local_reader_content_filter(
+    &mut self,
+    guid: GUID,
+    content_filter: Option<ContentFilterProperty>,
+  ) {
+    if let Some(reader) = self.local_topic_readers.get_mut(&guid) {
+      reader.content_filter = content_filter;
+    }
+  }
+
+  // SEDP data for a remote endpoint can arrive repeatedly: resent by a
+  // reliable writer, or as an update that changes nothing. Processing it again
+  // would only repeat the matching with all local endpoints. Returns true, if
+  // the sample with sequence number `sn` carries nothing new, so that
+  // update_subscription() can be skipped. Otherwise the sequence number is
+  // recorded, and the caller must update.
+  pub fn is_redundant_subscription(
+    &mut self,
+    data: &DiscoveredReaderData,
+    sn: SequenceNumber,
+  ) -> bool {
+    let guid = data.reader_proxy.remote_reader_guid;
+    let known = self.external_topic_readers.get(&guid);
+    Self::is_redundant_sedp_sample(&mut self.sedp_sequence_numbers, guid, sn, || {
+      known == Some(data)
+    })
+  }
+
+  pub fn is_redundant_publication(
+    &mut self,
+    data: &DiscoveredWriterData,
+    sn: SequenceNumber,
+  ) -> bool {
+    let guid = data.writer_proxy.remote_writer_guid;
+    let known = self.external_topic_writers.get(&guid);
+    Self::is_redundant_sedp_sample(&mut self.sedp_sequence_numbers, guid, sn, || {
+      known == Some(data)
+    })
+  }
+
+  fn is_redundant_sedp_sample(
+    sequence_numbers: &mut BTreeMap<GUID, SequenceNumber>,
+    guid: GUID,
+    sn: SequenceNumber,
+    unchanged: impl FnOnce() -> bool,
+  ) -> bool {
+    match sequence_numbers.insert(guid, sn) {
+      // Same sample again, no need to even compare.
+      Some(previous_sn) if previous_sn == sn => true,
+      // A new sample for a known endpoint
+      Some(_) => unchanged(),
+      // New or rediscovered endpoint
+      None => false,
+    }
+  }
+
   // TODO: This is silly. Returns one of the parameters cloned, or None
   // TODO: Why are we here checking if discovery db already has this? What about
   // reader proxies in writers?
@@ -533,6 +720,9 @@ impl DiscoveryDB {
 
   // This is for local participant updating the topic table
   pub fn update_topic_data_p(&mut self, topic: &Topic) {
+    if let Some(type_object) = topic.get_type().type_object() {
+      self.add_type_object(type_object);
+    }
     let topic_data = DiscoveredTopicData::new(
       Utc::now(),
       TopicBuiltinTopicData::new(
@@ -635,6 +825,65 @@ impl DiscoveryDB {
     }
   }
 
+  pub fn add_type_object(&mut self, type_object: &TypeObject) {
+    self
+      .type_objects
+      .entry(TypeIdentifier::complete(type_object))
+      .or_insert_with(|| type_object.clone());
+  }
+
+  // A type received from the TypeLookup service. It is rejected, if it does not
+  // have the identifier it was looked up with.
+  pub fn add_remote_type_object(
+    &mut self,
+    type_id: TypeIdentifier,
+    type_object: TypeObject,
+  ) -> bool {
+    if TypeIdentifier::complete(&type_object) == type_id {
+      self.type_objects.insert(type_id, type_object);
+      true
+    } else {
+      false
+    }
+  }
+
+  pub fn get_type_object(&self, type_id: &TypeIdentifier) -> Option<&TypeObject> {
+    self.type_objects.get(type_id)
+  }
+
+  // Can a reader of the type `reader_type` read the data of a writer of the
+  // type `writer_type`?
+  pub fn type_match(
+    &self,
+    reader_type: Option<TypeInformation>,
+    writer_type: Option<TypeInformation>,
+  ) -> TypeMatch {
+    let (reader_type, writer_type) = match (reader_type, writer_type) {
+      (Some(r), Some(w)) => (r, w),
+      _ => return TypeMatch::Assignable,
+    };
+    // Types with the same minimal form differ at most in the type names,
+    // which do not matter.
+    if reader_type.minimal == writer_type.minimal {
+      return TypeMatch::Assignable;
+    }
+    match (
+      self.type_objects.get(&reader_type.complete),
+      self.type_objects.get(&writer_type.complete),
+    ) {
+      (Some(reader_type), Some(writer_type)) => {
+        let result = is_assignable(reader_type, writer_type);
+        if result.is_assignable() {
+          TypeMatch::Assignable
+        } else {
+          TypeMatch::NotAssignable(result)
+        }
+      }
+      (None, _) => TypeMatch::LookupNeeded(reader_type.complete),
+      (_, None) => TypeMatch::LookupNeeded(writer_type.complete),
+    }
+  }
+
   // local topic readers
   pub fn update_local_topic_reader(
     &mut self,
@@ -647,7 +896,7 @@ impl DiscoveryDB {
 
     let reader_proxy = RtpsReaderProxy::from_reader(reader, domain_participant);
 
-    let subscription_data = SubscriptionBuiltinTopicData::new(
+    let mut subscription_data = SubscriptionBuiltinTopicData::new(
       reader_guid,
       Some(domain_participant.guid()),
       topic.name(),
@@ -655,6 +904,7 @@ impl DiscoveryDB {
       &reader.qos_policy,
       sec_info_opt,
     );
+    subscription_data.set_type_information(topic.get_type().type_information());
 
     // TODO: possibly change content filter to dynamic value
     let content_filter = None;
@@ -663,6 +913,7 @@ impl DiscoveryDB {
       reader_proxy: ReaderProxy::from(reader_proxy),
       subscription_topic_data: subscription_data,
       content_filter,
+      unknown_parameters: Vec::new(),
     };
 
     self
@@ -747,6 +998,75 @@ impl DiscoveryDB {
       .collect()
   }
 
+  pub fn writers_on_topic(&self, topic_name: &str) -> Vec<DiscoveredWriterData> {
+    self
+      .external_topic_writers
+      .values()
+      .filter(|dwd| dwd.publication_topic_data.topic_name == topic_name)
+      .cloned()
+      .collect()
+  }
+
+  pub fn readers_on_topic(&self, topic_name: &str) -> Vec<DiscoveredReaderData> {
+    self
+      .external_topic_readers
+      .values()
+      .filter(|drd| drd.subscription_topic_data.topic_name() == topic_name)
+      .cloned()
+      .collect()
+  }
+
+  // Remote writers and readers of the type `type_id`. Their matching waits
+  // until the TypeObject is known.
+  pub fn writers_of_type(&self, type_id: &TypeIdentifier) -> Vec<DiscoveredWriterData> {
+    self
+      .external_topic_writers
+      .values()
+      .filter(|dwd| {
+        dwd
+          .publication_topic_data
+          .type_information
+          .map(|t| t.complete)
+          == Some(*type_id)
+      })
+      .cloned()
+      .collect()
+  }
+
+  pub fn readers_of_type(&self, type_id: &TypeIdentifier) -> Vec<DiscoveredReaderData> {
+    self
+      .external_topic_readers
+      .values()
+      .filter(|drd| {
+        drd
+          .subscription_topic_data
+          .type_information()
+          .map(|t| t.complete)
+          == Some(*type_id)
+      })
+      .cloned()
+      .collect()
+  }
+
+  // The type of a topic, as described by remote writers, or if there are
+  // none, readers. Known only after the TypeObject has been looked up.
+  pub fn type_object_of_topic(&self, topic_name: &str) -> Option<TypeObject> {
+    let writer_types = self
+      .external_topic_writers
+      .values()
+      .filter(|dwd| dwd.publication_topic_data.topic_name == topic_name)
+      .filter_map(|dwd| dwd.publication_topic_data.type_information);
+    let reader_types = self
+      .external_topic_readers
+      .values()
+      .filter(|drd| drd.subscription_topic_data.topic_name == topic_name)
+      .filter_map(|drd| drd.subscription_topic_data.type_information());
+    writer_types
+      .chain(reader_types)
+      .find_map(|t| self.get_type_object(&t.complete))
+      .cloned()
+  }
+
   // // TODO: return iterator somehow?
   #[cfg(test)] // used only for testing
   pub fn get_local_topic_readers<T: TopicDescription>(
@@ -917,6 +1237,7 @@ mod tests {
       reader_proxy: reader1.clone(),
       subscription_topic_data: reader1sub.clone(),
       content_filter: None,
+      unknown_parameters: Vec::new(),
     };
     discovery_db.update_subscription(&dreader1);
 
@@ -928,6 +1249,7 @@ mod tests {
       reader_proxy: reader2,
       subscription_topic_data: reader2sub,
       content_filter: None,
+      unknown_parameters: Vec::new(),
     };
     discovery_db.update_subscription(&dreader2);
 
@@ -937,12 +1259,165 @@ mod tests {
       reader_proxy: reader3,
       subscription_topic_data: reader3sub,
       content_filter: None,
+      unknown_parameters: Vec::new(),
     };
     discovery_db.update_subscription(&dreader3);
 
     // TODO: there might be a need for different scenarios
   }
 
+  #[test]
+  fn discdb_redundant_sedp_samples() {
+    let (discovery_db_event_sender, _discovery_db_event_receiver) =
+      mio_channel::sync_channel::<()>(4);
+    let (status_sender, _status_receiver) = sync_status_channel(16).unwrap();
+    let mut discovery_db = DiscoveryDB::new(
+      GUID::new_participant_guid(),
+      discovery_db_event_sender,
+      status_sender,
+    );
+
+    let mut reader = DiscoveredReaderData {
+      reader_proxy: reader_proxy_data().unwrap(),
+      subscription_topic_data: subscription_builtin_topic_data().unwrap(),
+      content_filter: None,
+      unknown_parameters: Vec::new(),
+    };
+    let guid = reader.reader_proxy.remote_reader_guid;
+
+    // First time must be processed
+    assert!(!discovery_db.is_redundant_subscription(&reader, SequenceNumber::from(1)));
+    discovery_db.update_subscription(&reader);
+    // Repair of the same sample, and an update without changes
+    assert!(discovery_db.is_redundant_subscription(&reader, SequenceNumber::from(1)));
+    assert!(discovery_db.is_redundant_subscription(&reader, SequenceNumber::from(2)));
+
+    // Actual change
+    reader.reader_proxy.expects_inline_qos = !reader.reader_proxy.expects_inline_qos;
+    assert!(!discovery_db.is_redundant_subscription(&reader, SequenceNumber::from(3)));
+    discovery_db.update_subscription(&reader);
+    assert!(discovery_db.is_redundant_subscription(&reader, SequenceNumber::from(3)));
+
+    // After the reader is gone, it is new again.
+    discovery_db.remove_topic_reader(guid);
+    assert!(!discovery_db.is_redundant_subscription(&reader, SequenceNumber::from(3)));
+  }
+
+  #[test]
+  fn discdb_resource_limits() {
+    let (discovery_db_event_sender, _discovery_db_event_receiver) =
+      mio_channel::sync_channel::<()>(4);
+    let (status_sender, _status_receiver) = sync_status_channel(16).unwrap();
+    let mut discovery_db = DiscoveryDB::new(
+      GUID::new_participant_guid(),
+      discovery_db_event_sender,
+      status_sender,
+    );
+    discovery_db.set_limits(Some(1), Some(1));
+
+    let participant = spdp_participant_data().unwrap();
+    let mut other_participant = participant.clone();
+    other_participant.participant_guid = GUID::new_participant_guid();
+    assert!(discovery_db.update_participant(&participant));
+    assert!(!discovery_db.update_participant(&other_participant));
+    assert_eq!(discovery_db.participant_proxies.len(), 1);
+    // Known participant is still updated
+    discovery_db.update_participant(&participant);
+    assert_eq!(discovery_db.participant_proxies.len(), 1);
+
+    let reader = DiscoveredReaderData {
+      reader_proxy: reader_proxy_data().unwrap(),
+      subscription_topic_data: subscription_builtin_topic_data().unwrap(),
+      content_filter: None,
+      unknown_parameters: Vec::new(),
+    };
+    let guid = reader.reader_proxy.remote_reader_guid;
+    let other_guid = GUID::new_participant_guid().from_prefix(guid.entity_id);
+    assert!(discovery_db.admits_endpoint(guid));
+    discovery_db.update_subscription(&reader);
+    assert!(discovery_db.admits_endpoint(guid));
+    assert!(!discovery_db.admits_endpoint(other_guid));
+  }
+
+  #[test]
+  fn discdb_topic_filter() {
+    let (discovery_db_event_sender, _discovery_db_event_receiver) =
+      mio_channel::sync_channel::<()>(4);
+    let (status_sender, _status_receiver) = sync_status_channel(16).unwrap();
+    let mut discovery_db = DiscoveryDB::new(
+      GUID::new_participant_guid(),
+      discovery_db_event_sender,
+      status_sender,
+    );
+    assert!(discovery_db.admits_topic("rt/anything"));
+
+    discovery_db.set_topic_filter(Some(
+      DiscoveryTopicFilter::new()
+        .allow_topic("rt/chatter")
+        .allow_prefix("rt/robot1/"),
+    ));
+    assert!(discovery_db.admits_topic("rt/chatter"));
+    assert!(discovery_db.admits_topic("rt/robot1/odom"));
+    assert!(!discovery_db.admits_topic("rt/chatter2"));
+    assert!(!discovery_db.admits_topic("rt/robot2/odom"));
+  }
+
+  #[test]
+  fn discdb_type_match() {
+    use crate::xtypes::{Extensibility, StructMember, StructType};
+
+    let (discovery_db_event_sender, _discovery_db_event_receiver) =
+      mio_channel::sync_channel::<()>(4);
+    let (status_sender, _status_receiver) = sync_status_channel(16).unwrap();
+    let mut discovery_db = DiscoveryDB::new(
+      GUID::new_participant_guid(),
+      discovery_db_event_sender,
+      status_sender,
+    );
+
+    let point = |extensibility, members: &[&str]| {
+      let members = (0..)
+        .zip(members)
+        .map(|(id, name)| StructMember::new(id, name, TypeObject::Int32))
+        .collect();
+      TypeObject::Struct(StructType::new("Point", extensibility, members))
+    };
+    let xy = point(Extensibility::Appendable, &["x", "y"]);
+    let xyz = point(Extensibility::Appendable, &["x", "y", "z"]);
+    let final_xyz = point(Extensibility::Final, &["x", "y", "z"]);
+    let info = |t: &TypeObject| Some(TypeInformation::of(t));
+
+    // Without type information, endpoints match as before
+    assert_eq!(
+      discovery_db.type_match(None, info(&xy)),
+      TypeMatch::Assignable
+    );
+    assert_eq!(
+      discovery_db.type_match(info(&xy), info(&xy)),
+      TypeMatch::Assignable
+    );
+
+    discovery_db.add_type_object(&xy);
+    assert_eq!(
+      discovery_db.type_match(info(&xy), info(&xyz)),
+      TypeMatch::LookupNeeded(TypeIdentifier::complete(&xyz))
+    );
+
+    // Looked up types must have the identifier that was asked for
+    assert!(!discovery_db.add_remote_type_object(TypeIdentifier::complete(&xyz), xy.clone()));
+    assert!(discovery_db.add_remote_type_object(TypeIdentifier::complete(&xyz), xyz.clone()));
+    assert_eq!(
+      discovery_db.type_match(info(&xy), info(&xyz)),
+      TypeMatch::Assignable
+    );
+
+    discovery_db.add_type_object(&final_xyz);
+    assert!(matches!(
+      discovery_db.type_match(info(&xy), info(&final_xyz)),
+      TypeMatch::NotAssignable(_)
+    ));
+  }
+
   #[test]
   fn discdb_local_topic_reader() {
     let (discovery_db_event_sender, _discovery_db_event_receiver) =
@@ -992,6 +1467,7 @@ mod tests {
       data_reader_waker: data_reader_waker1,
       poll_event_sender: notification_event_sender1,
       security_plugins: None,
+      status_record: Arc::default(),
     };
 
     // Add the reader to the database and verify the info is updated
@@ -1029,6 +1505,7 @@ mod tests {
       data_reader_waker: data_reader_waker2,
       poll_event_sender: notification_event_sender2,
       security_plugins: None,
+      status_record: Arc::default(),
     };
 
     // Add the second reader to the database and verify the info is updated
@@ -0,0 +1,234 @@
+// Self-diagnostics of the local participant, published periodically on a
+// DDS topic, so that fleet monitoring can subscribe to the health of every
+// RustDDS participant in the domain without separate agents.
+//
+// This is not in the DDS specification. The topic is an ordinary
+// user-defined topic, announced by SEDP, so any DDS implementation can
+// subscribe to it, given the type.
+
+use std::{
+  sync::{Arc, RwLock},
+  time::{Duration as StdDuration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use mio_extras::timer::Timer;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  dds::{
+    key::Keyed,
+    participant::{DomainParticipant, DomainParticipantWeak},
+    pubsub::Publisher,
+    qos::{
+      policy::{Durability, History, Reliability},
+      QosPolicies, QosPolicyBuilder,
+    },
+    result::CreateResult,
+    topic::TopicKind,
+    with_key::datawriter::DataWriterCdr,
+  },
+  discovery::discovery_db::{discovery_db_read, DiscoveryDB},
+  polling::new_simple_timer,
+  structure::{duration::Duration, entity::RTPSEntity, guid::GUID, time::Timestamp},
+};
+
+/// Name of the topic where participants publish their [`ParticipantHealth`],
+/// if enabled with
+/// [`DomainParticipantBuilder::self_health`](crate::DomainParticipantBuilder::self_health).
+pub const SELF_HEALTH_TOPIC_NAME: &str = "RustDDSSelfHealth";
+/// Type name of the [`SELF_HEALTH_TOPIC_NAME`] topic.
+pub const SELF_HEALTH_TYPE_NAME: &str = "RustDDSParticipantHealth";
+
+/// Internal health of a participant, as published by itself. One instance
+/// per participant.
+///
+/// Read these with a DataReader of type `ParticipantHealth` on the topic
+/// [`SELF_HEALTH_TOPIC_NAME`], with QoS [`ParticipantHealth::qos`] and CDR
+/// serialization.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParticipantHealth {
+  pub participant: GUID,
+  /// Wall clock time of the participant, when this was measured. Compare to
+  /// the source timestamp of the sample to see clock offset.
+  pub timestamp: Timestamp,
+  /// Time since the participant was constructed, by monotonic clock.
+  pub uptime: Duration,
+  /// Is the thread that runs the Readers and Writers still running. The
+  /// Discovery thread publishes this, so it is running.
+  pub event_loop_running: bool,
+  /// Discovery announcements that were sent late due to local overload
+  pub late_lease_assertions: u32,
+  /// Remote participants currently known
+  pub discovered_participants: u32,
+  /// Topics that have a sample cache
+  pub topic_caches: u32,
+  /// Samples currently in the topic caches
+  pub cached_samples: u64,
+  /// Samples evicted from the topic caches before all DataReaders read them
+  pub evicted_unread_samples: u64,
+  /// Errors returned by the security plugins. Zero if security is not
+  /// configured.
+  pub security_errors: u64,
+}
+
+impl Keyed for ParticipantHealth {
+  type K = GUID;
+
+  fn key(&self) -> Self::K {
+    self.participant
+  }
+}
+
+impl ParticipantHealth {
+  /// QoS of the self-health topic. The latest health of each participant is
+  /// kept for late-joining DataReaders.
+  pub fn qos() -> QosPolicies {
+    QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::ZERO,
+      })
+      .durability(Durability::TransientLocal)
+      .history(History::KeepLast { depth: 1 })
+      .build()
+  }
+
+  fn measure(
+    dp: &DomainParticipant,
+    discovery_db: &Arc<RwLock<DiscoveryDB>>,
+    uptime: StdDuration,
+    late_lease_assertions: u32,
+  ) -> Self {
+    let cache_statistics = dp.dds_cache().read().unwrap().topic_statistics();
+    #[cfg(feature = "security")]
+    let security_errors = dp
+      .security_error_counts()
+      .map_or(0, |counts| counts.total());
+    #[cfg(not(feature = "security"))]
+    let security_errors = 0;
+
+    Self {
+      participant: dp.guid(),
+      timestamp: Timestamp::now(),
+      uptime: uptime.into(),
+      event_loop_running: dp.event_loop_running(),
+      late_lease_assertions,
+      discovered_participants: discovery_db_read(discovery_db)
+        .remote_participants()
+        .count() as u32,
+      topic_caches: cache_statistics.len() as u32,
+      cached_samples: cache_statistics
+        .iter()
+        .map(|s| s.cached_samples as u64)
+        .sum(),
+      evicted_unread_samples: cache_statistics
+        .iter()
+        .map(|s| s.evicted_unread_samples)
+        .sum(),
+      security_errors,
+    }
+  }
+}
+
+// Publishes the health of the local participant periodically. Owned and run
+// by the Discovery thread.
+pub(crate) struct HealthPublisher {
+  writer: DataWriterCdr<ParticipantHealth>,
+  pub timer: Timer<()>,
+  period: StdDuration,
+  started: Instant,
+}
+
+impl HealthPublisher {
+  pub fn new(
+    domain_participant: &DomainParticipantWeak,
+    publisher: &Publisher,
+    period: StdDuration,
+  ) -> CreateResult<Self> {
+    let qos = ParticipantHealth::qos();
+    let topic = domain_participant.create_topic(
+      SELF_HEALTH_TOPIC_NAME.to_string(),
+      SELF_HEALTH_TYPE_NAME.to_string(),
+      &qos,
+      TopicKind::WithKey,
+    )?;
+    let writer = publisher.create_datawriter_cdr(&topic, Some(qos))?;
+    let mut timer = new_simple_timer();
+    timer.set_timeout(period, ());
+    Ok(Self {
+      writer,
+      timer,
+      period,
+      started: Instant::now(),
+    })
+  }
+
+  pub fn publish(
+    &mut self,
+    dp: &DomainParticipant,
+    discovery_db: &Arc<RwLock<DiscoveryDB>>,
+    late_lease_assertions: u32,
+  ) {
+    let health = ParticipantHealth::measure(
+      dp,
+      discovery_db,
+      self.started.elapsed(),
+      late_lease_assertions,
+    );
+    while self.timer.poll().is_some() {}
+    trace!("Publishing self-health {health:?}");
+    if let Err(e) = self.writer.write(health, None) {
+      warn!("Cannot publish self-health: {e:?}");
+    }
+    self.timer.set_timeout(self.period, ());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    dds::participant::DomainParticipantBuilder, serialization::CDRDeserializerAdapter,
+    structure::guid::EntityId,
+  };
+
+  #[test]
+  fn self_health_is_published() {
+    let dp = DomainParticipantBuilder::new(0)
+      .self_health(StdDuration::from_millis(100))
+      .build()
+      .unwrap();
+    // find_topic would not do, because it does not know the topic kind of
+    // local topics.
+    let topic = dp
+      .create_topic(
+        SELF_HEALTH_TOPIC_NAME.to_string(),
+        SELF_HEALTH_TYPE_NAME.to_string(),
+        &ParticipantHealth::qos(),
+        TopicKind::WithKey,
+      )
+      .unwrap();
+    let subscriber = dp.create_subscriber(&QosPolicies::qos_none()).unwrap();
+    let mut reader = subscriber
+      .create_datareader::<ParticipantHealth, CDRDeserializerAdapter<_>>(
+        &topic,
+        Some(ParticipantHealth::qos()),
+      )
+      .unwrap();
+
+    let mut health = None;
+    for _ in 0..50 {
+      if let Some(sample) = reader.take_next_sample().unwrap() {
+        health = sample.into_value().value();
+        break;
+      }
+      std::thread::sleep(StdDuration::from_millis(100));
+    }
+    let health = health.expect("no self-health received");
+    assert_eq!(health.participant, dp.guid());
+    assert_eq!(health.participant.entity_id, EntityId::PARTICIPANT);
+    assert!(health.event_loop_running);
+    assert!(health.topic_caches > 0);
+  }
+}
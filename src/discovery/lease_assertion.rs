@@ -0,0 +1,329 @@
+// Scheduling and self-monitoring of our own SPDP announcements.
+//
+// Remote participants consider us alive only as long as they keep receiving
+// our SPDP announcements (participant lease assertions). The Discovery thread
+// checks the schedule before handling each event, so that a flood of other
+// Discovery traffic cannot postpone the announcement for long.
+//
+// The announcements are written to the SPDP DataWriter, and sent by the event
+// loop thread. The SPDP Writer records each message it sends to the network in
+// SpdpTransmissions. If the event loop has not sent an announcement soon after
+// it was written, the Discovery thread repeats the last sent message itself,
+// so that a stalled event loop does not let our leases expire. The repeated
+// message has the sequence number it had before, but RustDDS renews a lease on
+// any SPDP DATA from the participant, repeated or not.
+//
+// A separate watchdog thread observes the transmissions from outside. If one
+// is late, the local overload is reported to the application as a status
+// event, and counted. The count is sent in our SPDP data, so that remote
+// RustDDS participants can distinguish our local overload from network loss.
+
+use std::{
+  cell::Cell,
+  sync::{
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    Arc, Mutex,
+  },
+  thread::{self, JoinHandle},
+  time::{Duration as StdDuration, Instant},
+};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  dds::statusevents::{DomainParticipantStatusEvent, StatusChannelSender},
+  network::udp_sender::UDPSender,
+  structure::{duration::Duration, locator::Locator},
+};
+
+struct LastTransmission {
+  at: Instant,
+  // The RTPS message and its destinations
+  message: Option<(Vec<u8>, Vec<Locator>)>,
+  // Sender for repeating the message outside of the event loop
+  repeat_sender: Option<UDPSender>,
+}
+
+// Our SPDP messages as they were last sent to the network. Shared by the SPDP
+// Writer in the event loop and the lease assertion in the Discovery thread.
+#[derive(Clone)]
+pub(crate) struct SpdpTransmissions {
+  last: Arc<Mutex<LastTransmission>>,
+}
+
+impl SpdpTransmissions {
+  pub fn new() -> Self {
+    Self {
+      last: Arc::new(Mutex::new(LastTransmission {
+        at: Instant::now(),
+        message: None,
+        repeat_sender: None,
+      })),
+    }
+  }
+
+  // The sender should be set up like the one of the SPDP Writer.
+  pub fn set_repeat_sender(&self, repeat_sender: UDPSender) {
+    self.last.lock().unwrap().repeat_sender = Some(repeat_sender);
+  }
+
+  // To be called by the SPDP Writer each time it has sent our SPDP data.
+  pub fn sent(&self, message: &[u8], destinations: &[Locator]) {
+    let mut last = self.last.lock().unwrap();
+    last.at = Instant::now();
+    last.message = Some((message.to_vec(), destinations.to_vec()));
+  }
+
+  fn last_sent_at(&self) -> Instant {
+    self.last.lock().unwrap().at
+  }
+
+  // Sends the last sent message again. Returns false, if there is nothing to
+  // repeat, or nothing to repeat it with.
+  fn repeat_last(&self) -> bool {
+    let mut last = self.last.lock().unwrap();
+    match (&last.message, &last.repeat_sender) {
+      (Some((message, destinations)), Some(repeat_sender)) => {
+        repeat_sender.send_to_locator_list(message, destinations);
+        last.at = Instant::now();
+        true
+      }
+      _ => false,
+    }
+  }
+}
+
+struct Shared {
+  transmissions: SpdpTransmissions,
+  late_assertions: AtomicU32,
+  stop: AtomicBool,
+}
+
+pub(crate) struct LeaseAssertion {
+  period: StdDuration,
+  // When our SPDP data was last written. The next announcement is due one
+  // period later.
+  last_written: Cell<Instant>,
+  // Whether it is yet to be checked that the last written data was sent
+  send_unchecked: Cell<bool>,
+  shared: Arc<Shared>,
+  watchdog: Option<JoinHandle<()>>,
+}
+
+impl LeaseAssertion {
+  // An announcement is reported late, if it has not been sent within this
+  // fraction of the period past its due time.
+  const WATCHDOG_GRACE_DIVISOR: u32 = 2;
+  // How often the watchdog looks, as a fraction of the period.
+  const WATCHDOG_CHECK_DIVISOR: u32 = 4;
+  // How long the event loop has for sending written data, as a fraction of the
+  // period, before the previous message is repeated. This must be shorter
+  // than the watchdog grace.
+  const SEND_GRACE_DIVISOR: u32 = 4;
+
+  // The first periodic announcement is due one period from now. The initial
+  // announcement is sent separately at startup. Without the watchdog thread,
+  // late announcements are not detected.
+  pub fn new(
+    period: StdDuration,
+    lease_duration: Duration,
+    transmissions: SpdpTransmissions,
+    status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
+    watchdog: bool,
+  ) -> Self {
+    let shared = Arc::new(Shared {
+      transmissions,
+      late_assertions: AtomicU32::new(0),
+      stop: AtomicBool::new(false),
+    });
+    let watchdog = if watchdog {
+      thread::Builder::new()
+        .name("RustDDS lease watchdog".to_string())
+        .spawn({
+          let shared = Arc::clone(&shared);
+          move || watchdog_loop(&shared, period, lease_duration, &status_sender)
+        })
+        .map_err(|e| error!("Cannot start participant lease watchdog: {e}"))
+        .ok()
+    } else {
+      None
+    };
+    Self {
+      period,
+      last_written: Cell::new(Instant::now()),
+      send_unchecked: Cell::new(false),
+      shared,
+      watchdog,
+    }
+  }
+
+  fn send_check_time(&self) -> Instant {
+    self.last_written.get() + self.period / Self::SEND_GRACE_DIVISOR
+  }
+
+  // Time until either the next announcement is due, or it is time to check
+  // that the last one was sent.
+  pub fn time_until_due(&self) -> StdDuration {
+    let mut next = self.last_written.get() + self.period;
+    if self.send_unchecked.get() {
+      next = next.min(self.send_check_time());
+    }
+    next.saturating_duration_since(Instant::now())
+  }
+
+  pub fn is_due(&self) -> bool {
+    self.last_written.get() + self.period <= Instant::now()
+  }
+
+  // To be called each time our SPDP data has been written.
+  pub fn written(&self) {
+    self.last_written.set(Instant::now());
+    self.send_unchecked.set(true);
+  }
+
+  // If the event loop has not sent the last written data in time, the last
+  // message it did send is repeated from here. To be called along with is_due.
+  pub fn repeat_if_not_sent(&self) {
+    if !self.send_unchecked.get() || Instant::now() < self.send_check_time() {
+      return;
+    }
+    self.send_unchecked.set(false);
+    let transmissions = &self.shared.transmissions;
+    if transmissions.last_sent_at() < self.last_written.get() && transmissions.repeat_last() {
+      debug!("SPDP data was not sent by the event loop in time. Repeated the previous message.");
+    }
+  }
+
+  // How many times the watchdog has found the announcement late.
+  pub fn late_assertions(&self) -> u32 {
+    self.shared.late_assertions.load(Ordering::Relaxed)
+  }
+}
+
+impl Drop for LeaseAssertion {
+  fn drop(&mut self) {
+    self.shared.stop.store(true, Ordering::Release);
+    if let Some(watchdog) = self.watchdog.take() {
+      watchdog.thread().unpark();
+      watchdog
+        .join()
+        .unwrap_or_else(|e| error!("Lease watchdog thread panicked: {e:?}"));
+    }
+  }
+}
+
+fn watchdog_loop(
+  shared: &Shared,
+  period: StdDuration,
+  lease_duration: Duration,
+  status_sender: &StatusChannelSender<DomainParticipantStatusEvent>,
+) {
+  let deadline = period + period / LeaseAssertion::WATCHDOG_GRACE_DIVISOR;
+  let mut reported = false; // report each late announcement only once
+  loop {
+    thread::park_timeout(period / LeaseAssertion::WATCHDOG_CHECK_DIVISOR);
+    if shared.stop.load(Ordering::Acquire) {
+      return;
+    }
+    let elapsed = shared.transmissions.last_sent_at().elapsed();
+    if elapsed <= deadline {
+      reported = false;
+    } else if !reported {
+      reported = true;
+      shared.late_assertions.fetch_add(1, Ordering::Relaxed);
+      let late_by = Duration::from_std(elapsed - period);
+      warn!(
+        "Participant lease assertion is late by {late_by:?}. Local overload? Lease duration is \
+         {lease_duration:?}."
+      );
+      status_sender
+        .try_send(DomainParticipantStatusEvent::LeaseAssertionLate {
+          late_by,
+          lease_duration,
+        })
+        .unwrap_or_else(|e| debug!("Cannot report late lease assertion: {e:?}"));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::net::UdpSocket;
+
+  use super::*;
+  use crate::dds::statusevents::sync_status_channel;
+
+  #[test]
+  fn watchdog_reports_late_assertion() {
+    let (status_sender, status_receiver) = sync_status_channel(4).unwrap();
+    let period = StdDuration::from_millis(100);
+    let transmissions = SpdpTransmissions::new();
+    let lease = LeaseAssertion::new(
+      period,
+      Duration::from_secs(1),
+      transmissions.clone(),
+      status_sender,
+      true,
+    );
+    assert!(!lease.is_due());
+
+    // Keep asserting on time
+    for _ in 0..4 {
+      thread::sleep(period / 2);
+      lease.written();
+      transmissions.sent(b"SPDP", &[]);
+    }
+    assert_eq!(lease.late_assertions(), 0);
+
+    // Stall
+    thread::sleep(period * 4);
+    assert!(lease.is_due());
+    assert_eq!(lease.late_assertions(), 1);
+    assert!(matches!(
+      status_receiver.try_recv(),
+      Ok(DomainParticipantStatusEvent::LeaseAssertionLate { .. })
+    ));
+  }
+
+  #[test]
+  fn stalled_event_loop_does_not_stop_lease_assertions() {
+    let (status_sender, status_receiver) = sync_status_channel(4).unwrap();
+    let period = StdDuration::from_millis(100);
+    let remote = UdpSocket::bind("127.0.0.1:0").unwrap();
+    remote.set_read_timeout(Some(period)).unwrap();
+    let remote_locator = Locator::from(remote.local_addr().unwrap());
+
+    let transmissions = SpdpTransmissions::new();
+    transmissions.set_repeat_sender(UDPSender::new_with_random_port().unwrap());
+    let lease = LeaseAssertion::new(
+      period,
+      Duration::from_secs(1),
+      transmissions.clone(),
+      status_sender,
+      true,
+    );
+    // The initial announcement gets sent, and then the event loop stalls.
+    lease.written();
+    transmissions.sent(b"SPDP", &[remote_locator]);
+
+    let mut written = 1;
+    while written < 6 {
+      thread::sleep(lease.time_until_due());
+      lease.repeat_if_not_sent();
+      if lease.is_due() {
+        lease.written(); // but never sent by the event loop
+        written += 1;
+      }
+    }
+    // Each announcement written after the initial one was stood in for by
+    // the message sent before.
+    let mut buf = [0; 16];
+    for _ in 0..4 {
+      let len = remote.recv(&mut buf).unwrap();
+      assert_eq!(&buf[..len], b"SPDP");
+    }
+    assert_eq!(lease.late_assertions(), 0);
+    assert!(status_receiver.try_recv().is_err());
+  }
+}
@@ -31,7 +31,7 @@ use crate::{
     },
     security_error,
     security_plugins::SecurityPluginsHandle,
-    DataHolder, ParticipantBuiltinTopicDataSecure, ParticipantGenericMessage,
+    DataHolder, EndpointSecurityInfo, ParticipantBuiltinTopicDataSecure, ParticipantGenericMessage,
     ParticipantSecurityInfo, ParticipantStatelessMessage, ParticipantVolatileMessageSecure,
     PublicationBuiltinTopicDataSecure, SecurityError, SecurityResult,
     SubscriptionBuiltinTopicDataSecure,
@@ -123,7 +123,7 @@ impl SecureDiscovery {
     // Section "8.8.1 Authentication and AccessControl behavior with local
     // DomainParticipant"
 
-    let mut plugins = security_plugins.lock().unwrap();
+    let mut plugins = security_plugins.write().unwrap();
 
     let participant_guid_prefix = domain_participant.guid().prefix;
 
@@ -183,7 +183,7 @@ impl SecureDiscovery {
 
     // After registering, set local crypto tokens as remote tokens.
     // This is also needed so that we can receive our own secured messages.
-    let mut plugins = security_plugins.get_plugins();
+    let plugins = security_plugins.read_plugins();
 
     // Participant tokens
     plugins
@@ -768,6 +768,18 @@ impl SecureDiscovery {
     }
 
     match sample {
+      Sample::Value((disc_topic, _guid))
+        if self.is_topic_discovery_protected(&disc_topic.topic_data.name) =>
+      {
+        // We do not publish DCPSTopic data of such topics either
+        security_info!(
+          "Received DCPSTopic data of topic {} whose discovery is protected. Ignoring it. \
+           Participant: {:?}",
+          disc_topic.topic_data.name,
+          participant_guidp
+        );
+        NormalDiscoveryPermission::Deny
+      }
       Sample::Value((disc_topic, _guid)) => {
         match self.security_plugins.get_plugins().check_remote_topic(
           participant_guidp,
@@ -868,17 +880,7 @@ impl SecureDiscovery {
   ) {
     // See if this subscription needs to be written to DCPSSubscriptionsSecure or
     // the normal one
-    let do_secure_write =
-      if let Some(sec_info) = local_user_reader.subscription_topic_data.security_info() {
-        let sec_attributes = EndpointSecurityAttributes::from(sec_info.clone());
-        sec_attributes
-          .topic_security_attributes
-          .is_discovery_protected
-      } else {
-        false
-      };
-
-    if do_secure_write {
+    if is_discovery_protected(local_user_reader.subscription_topic_data.security_info()) {
       let sec_sub_data = SubscriptionBuiltinTopicDataSecure::from((*local_user_reader).clone());
       if let Err(e) = secure_sub_writer.write(sec_sub_data, None) {
         error!(
@@ -914,17 +916,7 @@ impl SecureDiscovery {
   ) {
     // See if this publication needs to be written to DCPSPublicationsSecure or the
     // normal one
-    let do_secure_write =
-      if let Some(sec_info) = local_user_writer.publication_topic_data.security_info() {
-        let sec_attributes = EndpointSecurityAttributes::from(sec_info.clone());
-        sec_attributes
-          .topic_security_attributes
-          .is_discovery_protected
-      } else {
-        false
-      };
-
-    if do_secure_write {
+    if is_discovery_protected(local_user_writer.publication_topic_data.security_info()) {
       let sec_pub_data = PublicationBuiltinTopicDataSecure::from((*local_user_writer).clone());
       if let Err(e) = secure_pub_writer.write(sec_pub_data, None) {
         error!(
@@ -952,6 +944,22 @@ impl SecureDiscovery {
     }
   }
 
+  // Whether the discovery of a local topic is protected. There is no secure
+  // counterpart of DCPSTopic, so the DCPSTopic data of such topics is neither
+  // published nor accepted. If the attributes cannot be found out, the topic
+  // is treated as protected.
+  pub fn is_topic_discovery_protected(&self, topic_name: &str) -> bool {
+    self
+      .security_plugins
+      .get_plugins()
+      .get_topic_sec_attributes(self.local_participant_guid.prefix, topic_name)
+      .map(|attributes| attributes.is_discovery_protected)
+      .unwrap_or_else(|e| {
+        security_warn!("Failed to get security attributes of topic {topic_name}: {e}");
+        true
+      })
+  }
+
   pub fn write_liveness_message(
     &self,
     secure_writer: &DataWriterCdr<ParticipantMessageData>,
@@ -1986,7 +1994,7 @@ impl SecureDiscovery {
         let remote_participant_guidp = msg.generic.message_identity.writer_guid.prefix;
         if let Err(e) = self
           .security_plugins
-          .get_plugins()
+          .read_plugins()
           .set_remote_participant_crypto_tokens(remote_participant_guidp, crypto_tokens)
         {
           create_security_error_and_log!(
@@ -2008,7 +2016,7 @@ impl SecureDiscovery {
 
         let set_result = self
           .security_plugins
-          .get_plugins()
+          .read_plugins()
           .set_remote_writer_crypto_tokens(
             msg.generic.source_endpoint_guid,
             msg.generic.destination_endpoint_guid,
@@ -2038,7 +2046,7 @@ impl SecureDiscovery {
 
         let set_result = self
           .security_plugins
-          .get_plugins()
+          .read_plugins()
           .set_remote_reader_crypto_tokens(
             msg.generic.source_endpoint_guid,
             msg.generic.destination_endpoint_guid,
@@ -2214,7 +2222,7 @@ impl SecureDiscovery {
     // TODO: do this only if needed?
     let crypto_tokens_res = self
       .security_plugins
-      .get_plugins()
+      .read_plugins()
       .create_local_participant_crypto_tokens(remote_guid_prefix); // Release lock
     match crypto_tokens_res {
       Err(e) => {
@@ -2254,7 +2262,7 @@ impl SecureDiscovery {
 
         let crypto_tokens_res = self
           .security_plugins
-          .get_plugins()
+          .read_plugins()
           .create_local_writer_crypto_tokens(local_writer_guid, remote_reader_guid); // Release lock
 
         match crypto_tokens_res {
@@ -2300,7 +2308,7 @@ impl SecureDiscovery {
 
         let crypto_tokens_res = self
           .security_plugins
-          .get_plugins()
+          .read_plugins()
           .create_local_reader_crypto_tokens(local_reader_guid, remote_writer_guid); // Release lock
 
         match crypto_tokens_res {
@@ -2392,12 +2400,12 @@ impl SecureDiscovery {
       let set_res = if remote_is_writer {
         self
           .security_plugins
-          .get_plugins()
+          .read_plugins()
           .set_remote_writer_crypto_tokens(remote_endpoint_guid, local_endpoint_guid, crypto_tokens)
       } else {
         self
           .security_plugins
-          .get_plugins()
+          .read_plugins()
           .set_remote_reader_crypto_tokens(remote_endpoint_guid, local_endpoint_guid, crypto_tokens)
       };
 
@@ -2482,12 +2490,12 @@ impl SecureDiscovery {
     let crypto_tokens_res = if remote_is_writer {
       self
         .security_plugins
-        .get_plugins()
+        .read_plugins()
         .create_local_reader_crypto_tokens(local_endpoint_guid, remote_endpoint_guid)
     } else {
       self
         .security_plugins
-        .get_plugins()
+        .read_plugins()
         .create_local_writer_crypto_tokens(local_endpoint_guid, remote_endpoint_guid)
     };
 
@@ -2510,12 +2518,12 @@ impl SecureDiscovery {
       let set_res = if remote_is_writer {
         self
           .security_plugins
-          .get_plugins()
+          .read_plugins()
           .set_remote_writer_crypto_tokens(remote_endpoint_guid, local_endpoint_guid, crypto_tokens)
       } else {
         self
           .security_plugins
-          .get_plugins()
+          .read_plugins()
           .set_remote_reader_crypto_tokens(remote_endpoint_guid, local_endpoint_guid, crypto_tokens)
       };
 
@@ -2579,6 +2587,55 @@ impl SecureDiscovery {
       .insert(remote_endpoint_guid);
   }
 
+  // Generates new keys for a local user-defined endpoint and sends them to the
+  // matched remote endpoints. Their old keys stay valid at the remotes for the
+  // grace period of the crypto plugin.
+  pub fn rekey_local_endpoint(
+    &mut self,
+    local_endpoint_guid: GUID,
+    key_exchange_writer: &no_key::DataWriter<ParticipantVolatileMessageSecure>,
+    discovery_db: &Arc<RwLock<DiscoveryDB>>,
+  ) {
+    // Builtin endpoints exchange keys with the remote participant as a whole
+    if !local_endpoint_guid.entity_id.entity_kind.is_user_defined() {
+      create_security_error_and_log!(
+        "Cannot re-key {local_endpoint_guid:?}: only user-defined endpoints can be re-keyed."
+      );
+      return;
+    }
+
+    let remote_endpoint_guids = match self
+      .security_plugins
+      .read_plugins()
+      .rekey_local_endpoint(local_endpoint_guid)
+    {
+      Ok(guids) => guids,
+      Err(e) => {
+        create_security_error_and_log!("Failed to re-key {local_endpoint_guid:?}: {e}");
+        return;
+      }
+    };
+
+    // Tokens with the old keys that are waiting for resending must not overwrite
+    // the new ones
+    self
+      .cached_key_exchange_messages_for_resend
+      .retain(|msg| msg.generic.source_endpoint_guid != local_endpoint_guid);
+
+    for remote_endpoint_guid in remote_endpoint_guids {
+      self
+        .user_data_endpoints_with_keys_already_sent_to
+        .remove(&remote_endpoint_guid);
+      self.start_key_exchange_with_remote_endpoint(
+        local_endpoint_guid,
+        remote_endpoint_guid,
+        key_exchange_writer,
+        discovery_db,
+      );
+    }
+    security_info!("Re-keyed local endpoint {local_endpoint_guid:?}.");
+  }
+
   fn validate_remote_participant_permissions(
     &mut self,
     remote_guid_prefix: GuidPrefix,
@@ -2749,6 +2806,16 @@ impl SecureDiscovery {
   }
 }
 
+// Whether a local endpoint is announced in the secure discovery topics instead
+// of the normal ones
+pub fn is_discovery_protected(security_info: &Option<EndpointSecurityInfo>) -> bool {
+  security_info.as_ref().is_some_and(|info| {
+    EndpointSecurityAttributes::from(info.clone())
+      .topic_security_attributes
+      .is_discovery_protected
+  })
+}
+
 fn send_discovery_notification(
   discovery_updated_sender: &mio_channel::SyncSender<DiscoveryNotificationType>,
   dntype: DiscoveryNotificationType,
@@ -1,4 +1,4 @@
-use std::time::Instant;
+use std::{collections::BTreeMap, time::Instant};
 
 use serde::{Deserialize, Serialize};
 #[allow(unused_imports)]
@@ -14,8 +14,9 @@ use crate::{
     participant::DomainParticipant,
     qos::{
       policy::{
-        Deadline, DestinationOrder, Durability, History, LatencyBudget, Lifespan, Liveliness,
-        Ownership, Presentation, Reliability, ResourceLimits, TimeBasedFilter,
+        DataRepresentation, Deadline, DestinationOrder, Durability, History, LatencyBudget,
+        Lifespan, Liveliness, Ownership, Partition, Presentation, Reliability, ResourceLimits,
+        TimeBasedFilter,
       },
       HasQoSPolicy, QosPolicies,
     },
@@ -27,8 +28,10 @@ use crate::{
     parameter::Parameter,
     parameter_list::{ParameterList, ParameterListable},
   },
-  network::{constant::user_traffic_unicast_port, util::get_local_unicast_locators},
-  rtps::{rtps_reader_proxy::RtpsReaderProxy, rtps_writer_proxy::RtpsWriterProxy},
+  rtps::{
+    constant::USER_TRAFFIC_LISTENER_TOKEN, rtps_reader_proxy::RtpsReaderProxy,
+    rtps_writer_proxy::RtpsWriterProxy,
+  },
   serialization::{
     pl_cdr_adapters::{
       PlCdrDeserialize, PlCdrDeserializeError, PlCdrSerialize, PlCdrSerializeError,
@@ -43,6 +46,7 @@ use crate::{
     locator::Locator,
     parameter_id::ParameterId,
   },
+  xtypes::TypeInformation,
   Key, Keyed,
 };
 #[cfg(feature = "security")]
@@ -165,17 +169,21 @@ pub struct SubscriptionBuiltinTopicData {
   // pub user_data: Option<UserData>,
   time_based_filter: Option<TimeBasedFilter>,
   presentation: Option<Presentation>,
-  // pub partition: Option<Partition>,
+  partition: Option<Partition>,
   // pub topic_data: Option<TopicData>,
   // pub group_data: Option<GroupData>,
   // pub durability_service: Option<DurabilityService>,
   lifespan: Option<Lifespan>,
+  // From DDS-XTypes:
+  data_representation: Option<DataRepresentation>,
 
   // From spec Remote Procedure Call over DDS:
   service_instance_name: Option<String>,
   related_datawriter_key: Option<GUID>,
   topic_aliases: Option<Vec<String>>, /* Option is a bit redundant, but it indicates if the
                                        * parameter was present or not */
+  // RustDDS-specific, see xtypes
+  type_information: Option<TypeInformation>,
   // DDS Security:
   #[cfg(feature = "security")]
   security_info: Option<EndpointSecurityInfo>,
@@ -208,12 +216,15 @@ impl SubscriptionBuiltinTopicData {
       destination_order: None,
       time_based_filter: None,
       presentation: None,
+      partition: None,
       lifespan: None,
+      data_representation: None,
       // DDS-RPC
       // TODO: these are not implemented
       service_instance_name: None,  // Note: Not implemented
       related_datawriter_key: None, // Note: Not implemented
       topic_aliases: None,          // Note: Not implemented
+      type_information: None,
 
       // DDS Security
       #[cfg(feature = "security")]
@@ -240,6 +251,14 @@ impl SubscriptionBuiltinTopicData {
     &self.type_name
   }
 
+  pub fn type_information(&self) -> Option<TypeInformation> {
+    self.type_information
+  }
+
+  pub fn set_type_information(&mut self, type_information: Option<TypeInformation>) {
+    self.type_information = type_information;
+  }
+
   #[cfg(feature = "security")]
   pub fn security_info(&self) -> &Option<EndpointSecurityInfo> {
     &self.security_info
@@ -255,7 +274,11 @@ impl SubscriptionBuiltinTopicData {
     self.destination_order = qos.destination_order;
     self.time_based_filter = qos.time_based_filter;
     self.presentation = qos.presentation;
+    self.partition.clone_from(&qos.partition);
     self.lifespan = qos.lifespan;
+    self
+      .data_representation
+      .clone_from(&qos.data_representation);
     // history does not exist
     // resource_limits does not exist
   }
@@ -269,11 +292,26 @@ impl SubscriptionBuiltinTopicData {
       ownership: self.ownership,
       liveliness: self.liveliness,
       time_based_filter: self.time_based_filter,
+      partition: self.partition.clone(),
+      writer_restart: None,
+      cache_watermarks: None,
+      delivery_order: None,
+      last_value_cache: None,
+      conflation: None,
+      payload_checksum: None,
+      writer_quarantine: None,
+      forward_error_correction: None,
+      repair_scheduling: None,
+      writer_initialization: None,
+      batching: None,
+      acknowledgment: None,
+      flow_control: None,
       reliability: self.reliability,
       destination_order: self.destination_order,
       history: None, // SubscriptionBuiltinTopicData does not contain History QoS
       resource_limits: None, // nor Resource Limits, see Figure 8.30 in RTPS spec 2.5
       lifespan: self.lifespan,
+      data_representation: self.data_representation.clone(),
 
       #[cfg(feature = "security")]
       property: None, // TODO: no property QoS?
@@ -301,6 +339,9 @@ pub struct DiscoveredReaderData {
   pub reader_proxy: ReaderProxy,
   pub subscription_topic_data: SubscriptionBuiltinTopicData,
   pub content_filter: Option<ContentFilterProperty>,
+  /// Received parameters that RustDDS does not recognize. These are
+  /// serialized back as-is.
+  pub unknown_parameters: Vec<Parameter>,
 }
 
 impl DiscoveredReaderData {
@@ -321,6 +362,7 @@ impl DiscoveredReaderData {
       reader_proxy,
       subscription_topic_data,
       content_filter: None,
+      unknown_parameters: Vec::new(),
     }
   }
 }
@@ -404,6 +446,16 @@ impl PlCdrDeserialize for DiscoveredReaderData {
 
     let qos = QosPolicies::from_parameter_list(ctx, &pl_map)?;
 
+    let mut subscription_topic_data = SubscriptionBuiltinTopicData::new(
+      guid,
+      participant_guid,
+      topic_name,
+      type_name,
+      &qos,
+      security_info,
+    );
+    subscription_topic_data.set_type_information(get_type_information(&pl_map, ctx, guid));
+
     Ok(DiscoveredReaderData {
       reader_proxy: ReaderProxy::new(
         guid,
@@ -411,15 +463,9 @@ impl PlCdrDeserialize for DiscoveredReaderData {
         unicast_locator_list,
         multicast_locator_list,
       ),
-      subscription_topic_data: SubscriptionBuiltinTopicData::new(
-        guid,
-        participant_guid,
-        topic_name,
-        type_name,
-        &qos,
-        security_info,
-      ),
+      subscription_topic_data,
       content_filter,
+      unknown_parameters: pl.unknown_parameters(),
     })
   }
 }
@@ -466,16 +512,20 @@ impl ParameterListable for DiscoveredReaderData {
           destination_order: _,
           time_based_filter: _,
           presentation: _,
+          partition: _,
           lifespan: _,
+          data_representation: _,
 
           service_instance_name,
           related_datawriter_key,
           topic_aliases,
+          type_information,
 
           #[cfg(feature = "security")]
           security_info,
         },
       content_filter,
+      unknown_parameters,
     } = self;
 
     let mut pl = ParameterList::new();
@@ -549,6 +599,11 @@ impl ParameterListable for DiscoveredReaderData {
       content_filter,
       ContentFilterProperty
     );
+    emit_option!(
+      PID_RUSTDDS_TYPE_INFORMATION,
+      type_information,
+      TypeInformation
+    );
 
     #[cfg(feature = "security")]
     emit_option!(
@@ -557,6 +612,8 @@ impl ParameterListable for DiscoveredReaderData {
       EndpointSecurityInfo
     );
 
+    pl.parameters.extend(unknown_parameters.iter().cloned());
+
     Ok(pl)
   }
 }
@@ -621,12 +678,17 @@ pub struct PublicationBuiltinTopicData {
   pub ownership: Option<Ownership>,
   pub destination_order: Option<DestinationOrder>,
   pub presentation: Option<Presentation>,
+  pub partition: Option<Partition>,
+  // From DDS-XTypes:
+  pub data_representation: Option<DataRepresentation>,
 
   // From Remote Procedure Call over DDS:
   pub service_instance_name: Option<String>,
   pub related_datareader_key: Option<GUID>,
   pub topic_aliases: Option<Vec<String>>, /* Option is a bit redundant, but it indicates
                                            * if the parameter was present or not */
+  // RustDDS-specific, see xtypes
+  pub type_information: Option<TypeInformation>,
   // DDS Security:
   #[cfg(feature = "security")]
   pub security_info: Option<EndpointSecurityInfo>,
@@ -659,10 +721,13 @@ impl PublicationBuiltinTopicData {
       ownership: None,
       destination_order: None,
       presentation: None,
+      partition: None,
+      data_representation: None,
 
       service_instance_name: None,  // TODO: These are not supported/used
       related_datareader_key: None, // TODO
       topic_aliases: None,          // TODO
+      type_information: None,
 
       #[cfg(feature = "security")]
       security_info: _security_info,
@@ -693,6 +758,10 @@ impl PublicationBuiltinTopicData {
     self.ownership = qos.ownership;
     self.destination_order = qos.destination_order;
     self.presentation = qos.presentation;
+    self.partition.clone_from(&qos.partition);
+    self
+      .data_representation
+      .clone_from(&qos.data_representation);
   }
 
   pub fn qos(&self) -> QosPolicies {
@@ -704,11 +773,26 @@ impl PublicationBuiltinTopicData {
       ownership: self.ownership,
       liveliness: self.liveliness,
       time_based_filter: self.time_based_filter,
+      partition: self.partition.clone(),
+      writer_restart: None,
+      cache_watermarks: None,
+      delivery_order: None,
+      last_value_cache: None,
+      conflation: None,
+      payload_checksum: None,
+      writer_quarantine: None,
+      forward_error_correction: None,
+      repair_scheduling: None,
+      writer_initialization: None,
+      batching: None,
+      acknowledgment: None,
+      flow_control: None,
       reliability: self.reliability,
       destination_order: self.destination_order,
       history: None,         // PublicationBuiltinTopicData does not contain History QoS
       resource_limits: None, // nor Resource Limits, see Figure 8.30 in RTPS spec 2.5
       lifespan: self.lifespan,
+      data_representation: self.data_representation.clone(),
       #[cfg(feature = "security")]
       property: None, // TODO: no property Qos?
     }
@@ -747,6 +831,9 @@ pub struct DiscoveredWriterData {
 
   pub writer_proxy: WriterProxy,
   pub publication_topic_data: PublicationBuiltinTopicData,
+  /// Received parameters that RustDDS does not recognize. These are
+  /// serialized back as-is.
+  pub unknown_parameters: Vec<Parameter>,
 }
 
 impl Keyed for DiscoveredWriterData {
@@ -764,11 +851,15 @@ impl DiscoveredWriterData {
     dp: &DomainParticipant,
     security_info: Option<EndpointSecurityInfo>,
   ) -> Self {
-    let unicast_port = user_traffic_unicast_port(dp.domain_id(), dp.participant_id());
-    let unicast_addresses = get_local_unicast_locators(unicast_port);
+    // The locators of the actual listeners, which also cover IPv6 and a
+    // non-default port
+    let unicast_addresses = dp
+      .self_locators()
+      .remove(&USER_TRAFFIC_LISTENER_TOKEN)
+      .unwrap_or_default();
     // TODO: Why empty vector below? No multicast?
     let writer_proxy = WriterProxy::new(writer.guid(), vec![], unicast_addresses);
-    let publication_topic_data = PublicationBuiltinTopicData::new_with_qos(
+    let mut publication_topic_data = PublicationBuiltinTopicData::new_with_qos(
       writer.guid(),
       Some(dp.guid()),
       topic.name(),
@@ -776,11 +867,13 @@ impl DiscoveredWriterData {
       &writer.qos(),
       security_info,
     );
+    publication_topic_data.type_information = topic.get_type().type_information();
 
     Self {
       last_updated: Instant::now(),
       writer_proxy,
       publication_topic_data,
+      unknown_parameters: Vec::new(),
     }
   }
 }
@@ -847,6 +940,16 @@ impl PlCdrDeserialize for DiscoveredWriterData {
 
     let qos = QosPolicies::from_parameter_list(ctx, &pl_map)?;
 
+    let mut publication_topic_data = PublicationBuiltinTopicData::new_with_qos(
+      guid,
+      participant_guid,
+      topic_name,
+      type_name,
+      &qos,
+      security_info,
+    );
+    publication_topic_data.type_information = get_type_information(&pl_map, ctx, guid);
+
     Ok(DiscoveredWriterData {
       last_updated: Instant::now(),
       writer_proxy: WriterProxy {
@@ -855,14 +958,8 @@ impl PlCdrDeserialize for DiscoveredWriterData {
         multicast_locator_list,
         data_max_size_serialized,
       },
-      publication_topic_data: PublicationBuiltinTopicData::new_with_qos(
-        guid,
-        participant_guid,
-        topic_name,
-        type_name,
-        &qos,
-        security_info,
-      ),
+      publication_topic_data,
+      unknown_parameters: pl.unknown_parameters(),
     })
   }
 }
@@ -910,14 +1007,18 @@ impl ParameterListable for DiscoveredWriterData {
           destination_order: _,
           time_based_filter: _,
           presentation: _,
+          partition: _,
           lifespan: _,
+          data_representation: _,
 
           service_instance_name,
           related_datareader_key,
           topic_aliases,
+          type_information,
           #[cfg(feature = "security")]
           security_info,
         },
+      unknown_parameters,
     } = self;
 
     let mut pl = ParameterList::new();
@@ -986,6 +1087,11 @@ impl ParameterListable for DiscoveredWriterData {
         StringWithNul
       );
     }
+    emit_option!(
+      PID_RUSTDDS_TYPE_INFORMATION,
+      type_information,
+      TypeInformation
+    );
 
     #[cfg(feature = "security")]
     emit_option!(
@@ -994,10 +1100,32 @@ impl ParameterListable for DiscoveredWriterData {
       EndpointSecurityInfo
     );
 
+    pl.parameters.extend(unknown_parameters.iter().cloned());
+
     Ok(pl)
   }
 }
 
+// The type information is only needed to match by type, so a malformed one
+// does not reject the whole endpoint. Another vendor may use the same
+// vendor-specific parameter id.
+fn get_type_information(
+  pl_map: &BTreeMap<ParameterId, Vec<&Parameter>>,
+  ctx: speedy::Endianness,
+  guid: GUID,
+) -> Option<TypeInformation> {
+  get_option_from_pl_map(
+    pl_map,
+    ctx,
+    ParameterId::PID_RUSTDDS_TYPE_INFORMATION,
+    "type information",
+  )
+  .unwrap_or_else(|e| {
+    debug!("Ignoring type information of {guid:?}: {e:?}");
+    None
+  })
+}
+
 // =======================================================================
 // =======================================================================
 // =======================================================================
@@ -1052,11 +1180,26 @@ impl HasQoSPolicy for TopicBuiltinTopicData {
       ownership: self.ownership,
       liveliness: self.liveliness,
       time_based_filter: None,
+      partition: None,
+      writer_restart: None,
+      cache_watermarks: None,
+      delivery_order: None,
+      last_value_cache: None,
+      conflation: None,
+      payload_checksum: None,
+      writer_quarantine: None,
+      forward_error_correction: None,
+      repair_scheduling: None,
+      writer_initialization: None,
+      batching: None,
+      acknowledgment: None,
+      flow_control: None,
       reliability: self.reliability,
       destination_order: self.destination_order,
       history: self.history,
       resource_limits: self.resource_limits,
       lifespan: self.lifespan,
+      data_representation: None,
       #[cfg(feature = "security")]
       property: None, // TODO: no property Qos?
     }
@@ -1350,6 +1493,7 @@ mod tests {
       reader_proxy,
       subscription_topic_data: sub_topic_data,
       content_filter: Some(content_filter),
+      unknown_parameters: Vec::new(),
     };
 
     // serialize
@@ -1409,6 +1553,7 @@ mod tests {
       last_updated: Instant::now(),
       writer_proxy,
       publication_topic_data: pub_topic_data,
+      unknown_parameters: Vec::new(),
     };
 
     let sdata = dwd
@@ -1430,6 +1575,50 @@ mod tests {
     assert_eq!(sdata, sdata2);
   }
 
+  #[test]
+  fn td_discovered_writer_data_type_information() {
+    let mut writer_proxy = writer_proxy_data().unwrap();
+    let mut pub_topic_data = publication_builtin_topic_data().unwrap();
+    writer_proxy.remote_writer_guid = pub_topic_data.key;
+    let type_information = TypeInformation::of(&crate::xtypes::TypeObject::Int32);
+    pub_topic_data.type_information = Some(type_information);
+
+    let dwd = DiscoveredWriterData {
+      last_updated: Instant::now(),
+      writer_proxy,
+      publication_topic_data: pub_topic_data,
+      unknown_parameters: Vec::new(),
+    };
+    let encoding = RepresentationIdentifier::PL_CDR_LE;
+    let mut pl = dwd.to_parameter_list(encoding).unwrap();
+    let dwd2: DiscoveredWriterData = PlCdrDeserializerAdapter::from_bytes(
+      &pl
+        .serialize_to_bytes(speedy::Endianness::LittleEndian)
+        .unwrap(),
+      encoding,
+    )
+    .unwrap();
+    assert_eq!(
+      dwd2.publication_topic_data.type_information,
+      Some(type_information)
+    );
+
+    // A malformed one is ignored
+    for p in &mut pl.parameters {
+      if p.parameter_id == ParameterId::PID_RUSTDDS_TYPE_INFORMATION {
+        p.value = vec![0x42; 32];
+      }
+    }
+    let dwd3: DiscoveredWriterData = PlCdrDeserializerAdapter::from_bytes(
+      &pl
+        .serialize_to_bytes(speedy::Endianness::LittleEndian)
+        .unwrap(),
+      encoding,
+    )
+    .unwrap();
+    assert_eq!(dwd3.publication_topic_data.type_information, None);
+  }
+
   // Do not test ser/deser. This is never seen on the wire out of
   // DiscoveredTopicData #[test]
   // fn td_topic_data_ser_deser() {
@@ -1,13 +1,13 @@
 use serde::{Deserialize, Serialize};
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
-use speedy::{Readable, Writable};
+use speedy::{Context, Readable, Reader, Writable};
 use chrono::Utc;
 use bytes::Bytes;
 use cdr_encoding_size::CdrEncodingSize;
 
 use crate::{
-  dds::{participant::DomainParticipant, qos::QosPolicies},
+  dds::{participant::DomainParticipant, protocol_identity::ProtocolIdentity, qos::QosPolicies},
   messages::{
     protocol_version::ProtocolVersion,
     submessages::elements::{
@@ -61,6 +61,17 @@ pub struct SpdpDiscoveredParticipantData {
   pub manual_liveliness_count: i32,
   pub builtin_endpoint_qos: Option<BuiltinEndpointQos>,
   pub entity_name: Option<String>,
+  /// RustDDS extension: how many times the participant has detected its own
+  /// announcement to be late because of local overload.
+  pub late_lease_assertions: Option<u32>,
+  /// Received parameters that RustDDS does not recognize. These are
+  /// serialized back as-is.
+  pub unknown_parameters: Vec<Parameter>,
+  /// Name-value pairs of the PID_PROPERTY_LIST of a received announcement.
+  /// Implementations advertise e.g. host and process information here. This
+  /// is not serialized: properties of the local participant are set with the
+  /// Property QoS policy.
+  pub announced_properties: Vec<(String, String)>,
 
   // security
   #[cfg(feature = "security")]
@@ -76,7 +87,103 @@ pub struct SpdpDiscoveredParticipantData {
   pub security_info: Option<ParticipantSecurityInfo>,
 }
 
+/// Version of the DDS implementation of a remote participant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProductVersion {
+  pub major: u8,
+  pub minor: u8,
+  pub release: u8,
+  pub revision: u8,
+}
+
+impl std::fmt::Display for ProductVersion {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let Self {
+      major,
+      minor,
+      release,
+      revision,
+    } = self;
+    write!(f, "{major}.{minor}.{release}.{revision}")
+  }
+}
+
+// Vendor-specific names of announced properties, (RTI Connext, eProsima)
+const HOST_NAME_PROPERTIES: [&str; 2] = ["dds.sys_info.hostname", "fastdds.physical_data.host"];
+const PROCESS_ID_PROPERTIES: [&str; 2] =
+  ["dds.sys_info.process_id", "fastdds.physical_data.process"];
+
+// Name-value pairs of PID_PROPERTY_LIST. The binary properties that may follow
+// are used only by DDS Security, so they are not read.
+struct PropertyList(Vec<(String, String)>);
+
+impl<'a, C: Context> Readable<'a, C> for PropertyList {
+  fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
+    let count = reader.read_u32()?;
+    let mut properties = Vec::new();
+    let mut prev_len = 0;
+    for _ in 0..count {
+      read_pad(reader, prev_len, 4)?;
+      let name: StringWithNul = reader.read_value()?;
+      read_pad(reader, name.len(), 4)?;
+      let value: StringWithNul = reader.read_value()?;
+      prev_len = value.len();
+      properties.push((name.into(), value.into()));
+    }
+    Ok(Self(properties))
+  }
+}
+
 impl SpdpDiscoveredParticipantData {
+  /// Product name of the DDS implementation, if it has a registered vendor
+  /// id.
+  pub fn product_name(&self) -> Option<&'static str> {
+    self.vendor_id.product_name()
+  }
+
+  /// Version of the DDS implementation. RTI Connext and eProsima Fast DDS
+  /// announce this in a vendor-specific parameter.
+  pub fn product_version(&self) -> Option<ProductVersion> {
+    if self.vendor_id != VendorId::RTI_CONNEXT && self.vendor_id != VendorId::EPROSIMA {
+      return None;
+    }
+    self
+      .unknown_parameters
+      .iter()
+      .find(|p| p.parameter_id == ParameterId::PID_PRODUCT_VERSION)
+      .and_then(|p| match p.value[..] {
+        [major, minor, release, revision, ..] => Some(ProductVersion {
+          major,
+          minor,
+          release,
+          revision,
+        }),
+        _ => None,
+      })
+  }
+
+  pub fn announced_property(&self, name: &str) -> Option<&str> {
+    self
+      .announced_properties
+      .iter()
+      .find(|(n, _)| n == name)
+      .map(|(_, value)| value.as_str())
+  }
+
+  /// Host name of the participant, if its implementation announces it
+  pub fn host_name(&self) -> Option<&str> {
+    HOST_NAME_PROPERTIES
+      .iter()
+      .find_map(|name| self.announced_property(name))
+  }
+
+  /// Process id of the participant, if its implementation announces it
+  pub fn process_id(&self) -> Option<&str> {
+    PROCESS_ID_PROPERTIES
+      .iter()
+      .find_map(|name| self.announced_property(name))
+  }
+
   #[cfg(feature = "security")]
   pub(crate) fn supports_security(&self) -> bool {
     // TODO: Is this logic correct? Or maybe we could come up with a more accurate
@@ -235,10 +342,11 @@ impl SpdpDiscoveredParticipantData {
       ));
     }
 
+    let identity = ProtocolIdentity::current();
     Self {
       updated_time: Utc::now(),
-      protocol_version: ProtocolVersion::PROTOCOLVERSION_2_3,
-      vendor_id: VendorId::THIS_IMPLEMENTATION,
+      protocol_version: identity.protocol_version,
+      vendor_id: identity.vendor_id,
       expects_inline_qos: false,
       participant_guid: participant.guid(),
       metatraffic_unicast_locators,
@@ -250,6 +358,9 @@ impl SpdpDiscoveredParticipantData {
       manual_liveliness_count: 0,
       builtin_endpoint_qos: None,
       entity_name: None,
+      late_lease_assertions: None,
+      unknown_parameters: Vec::new(),
+      announced_properties: Vec::new(),
 
       // DDS Security
       #[cfg(feature = "security")]
@@ -341,6 +452,32 @@ impl PlCdrDeserialize for SpdpDiscoveredParticipantData {
       get_option_from_pl_map::< _ , StringWithNul>(&pl_map, ctx, ParameterId::PID_ENTITY_NAME, "entity name")?
       .map( String::from );
 
+    // Vendor-specific parameters are interpreted only if we know the vendor.
+    let late_lease_assertions: Option<u32> = if vendor_id == VendorId::THIS_IMPLEMENTATION {
+      get_option_from_pl_map(
+        &pl_map,
+        ctx,
+        ParameterId::PID_RUSTDDS_LATE_LEASE_ASSERTIONS,
+        "late lease assertions",
+      )?
+    } else {
+      None
+    };
+
+    // Informative only, so a malformed list does not reject the participant
+    let announced_properties = get_option_from_pl_map::<_, PropertyList>(
+      &pl_map,
+      ctx,
+      ParameterId::PID_PROPERTY_LIST,
+      "announced properties",
+    )
+    .unwrap_or_else(|e| {
+      debug!("Ignoring property list of participant {participant_guid:?}: {e:?}");
+      None
+    })
+    .map(|list| list.0)
+    .unwrap_or_default();
+
     // DDS security
     #[cfg(feature = "security")]
     let identity_token: Option<IdentityToken> = get_option_from_pl_map(
@@ -387,6 +524,9 @@ impl PlCdrDeserialize for SpdpDiscoveredParticipantData {
       manual_liveliness_count,
       builtin_endpoint_qos,
       entity_name,
+      late_lease_assertions,
+      unknown_parameters: pl.unknown_parameters(),
+      announced_properties,
       #[cfg(feature = "security")]
       identity_token,
       #[cfg(feature = "security")]
@@ -433,6 +573,9 @@ impl ParameterListable for SpdpDiscoveredParticipantData {
       manual_liveliness_count,
       builtin_endpoint_qos,
       entity_name,
+      late_lease_assertions,
+      unknown_parameters,
+      announced_properties: _, // received only
 
       // DDS security
       #[cfg(feature = "security")]
@@ -517,6 +660,11 @@ impl ParameterListable for SpdpDiscoveredParticipantData {
     // and does not follow CDR encoding.
     let entity_name_n: Option<StringWithNul> = entity_name.clone().map(|e| e.into());
     emit_option!(PID_ENTITY_NAME, &entity_name_n, StringWithNul);
+    emit_option!(
+      PID_RUSTDDS_LATE_LEASE_ASSERTIONS,
+      late_lease_assertions,
+      u32
+    );
 
     #[cfg(feature = "security")] // DDS security
     {
@@ -530,6 +678,8 @@ impl ParameterListable for SpdpDiscoveredParticipantData {
       );
     }
 
+    pl.parameters.extend(unknown_parameters.iter().cloned());
+
     Ok(pl)
   }
 }
@@ -593,6 +743,8 @@ impl PlCdrSerialize for Participant_GUID {
 
 #[cfg(test)]
 mod tests {
+  use speedy::Endianness;
+
   use super::*;
   use crate::{
     dds::adapters::no_key::DeserializerAdapter,
@@ -653,6 +805,38 @@ mod tests {
     }
   }
 
+  #[test]
+  fn vendor_product_version_and_properties() {
+    let data = spdp_participant_data().unwrap();
+    assert_eq!(data.product_name(), Some("FastRTPS, FastDDS"));
+    assert_eq!(data.product_version(), None);
+
+    let mut pl = data
+      .to_parameter_list(RepresentationIdentifier::PL_CDR_LE)
+      .unwrap();
+    pl.push(Parameter::new(
+      ParameterId::PID_PRODUCT_VERSION,
+      vec![2, 14, 1, 0],
+    ));
+    let mut properties = 1_u32.to_le_bytes().to_vec();
+    properties.extend_from_slice(&27_u32.to_le_bytes());
+    properties.extend_from_slice(b"fastdds.physical_data.host\0\0");
+    properties.extend_from_slice(&7_u32.to_le_bytes());
+    properties.extend_from_slice(b"robot1\0\0");
+    pl.push(Parameter::new(ParameterId::PID_PROPERTY_LIST, properties));
+    let bytes = pl.serialize_to_bytes(Endianness::LittleEndian).unwrap();
+
+    let data =
+      SpdpDiscoveredParticipantData::from_pl_cdr_bytes(&bytes, RepresentationIdentifier::PL_CDR_LE)
+        .unwrap();
+    assert_eq!(
+      data.product_version().map(|v| v.to_string()),
+      Some("2.14.1.0".to_string())
+    );
+    assert_eq!(data.host_name(), Some("robot1"));
+    assert_eq!(data.process_id(), None);
+  }
+
   #[test]
   fn deserialize_evil_spdp_fuzz() {
     use hex_literal::hex;
@@ -0,0 +1,54 @@
+// Messages of the TypeLookup service, with which a participant asks a remote
+// participant for the TypeObjects of types that it has seen only as
+// TypeIdentifiers in discovery. See DDS-XTypes 1.3 Section 7.6.3.3
+// "TypeLookup Service".
+//
+// The requests and replies are in the CDR encoding of RustDDS, not in the
+// format of the specification. Therefore the service runs on vendor-specific
+// endpoints, which are not announced in the BuiltinEndpointSet of SPDP, and
+// are matched only with other RustDDS participants.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  dds::xtypes::{TypeIdentifier, TypeObject},
+  rpc::{ReplyHeader, RequestHeader},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct TypeLookupRequest {
+  pub header: RequestHeader,
+  // Complete type identifiers of the requested types
+  pub type_ids: Vec<TypeIdentifier>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct TypeLookupReply {
+  pub header: ReplyHeader,
+  // Those requested types that the replier knows. Unknown types are left out.
+  pub types: Vec<(TypeIdentifier, TypeObject)>,
+}
+
+#[cfg(test)]
+mod tests {
+  use speedy::Endianness;
+
+  use super::*;
+  use crate::serialization::{from_bytes_with_endianness, to_vec_with_endianness};
+
+  #[test]
+  fn type_lookup_reply_serialization() {
+    let type_object = TypeObject::Sequence {
+      element: Box::new(TypeObject::Int32),
+      bound: Some(8),
+    };
+    let reply = TypeLookupReply {
+      header: ReplyHeader::default(),
+      types: vec![(TypeIdentifier::complete(&type_object), type_object)],
+    };
+    let bytes = to_vec_with_endianness(&reply, Endianness::LittleEndian).unwrap();
+    let (decoded, _): (TypeLookupReply, _) =
+      from_bytes_with_endianness(&bytes, Endianness::LittleEndian).unwrap();
+    assert_eq!(decoded, reply);
+  }
+}
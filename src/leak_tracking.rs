@@ -0,0 +1,253 @@
+//! Tracking of internal objects, to find leaks in long runs.
+//!
+//! With the feature `leak_tracking`, RustDDS counts the creation and
+//! destruction of the objects that grow with traffic and with the number of
+//! matched remote entities: cache changes, proxies of remote readers and
+//! writers, and crypto handles. [`leak_report`] tells how many of each are
+//! alive, and how old the oldest of them is.
+//!
+//! In a soak test, take a report after the system has warmed up and another
+//! one after a long run. The kind of object whose live count keeps growing,
+//! see [`LeakReport::live_growth_since`], points to the subsystem that
+//! leaks. Tracking takes a global lock on every creation and destruction, so
+//! the feature is meant for test builds only.
+//!
+//! Without the feature, the trackers are zero-sized and do nothing.
+
+#[cfg(feature = "leak_tracking")]
+use std::{
+  collections::BTreeMap,
+  fmt,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+  },
+  time::{Duration, Instant},
+};
+
+/// Kinds of tracked objects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TrackedObject {
+  /// A sample in a DataWriter history or in a topic cache of DataReaders
+  CacheChange,
+  /// State kept by a DataWriter on a matched remote DataReader
+  ReaderProxy,
+  /// State kept by a DataReader on a matched remote DataWriter
+  WriterProxy,
+  /// A handle of a local or remote entity in the cryptographic plugin, with
+  /// its key material
+  CryptoHandle,
+}
+
+#[cfg(feature = "leak_tracking")]
+impl TrackedObject {
+  const ALL: [TrackedObject; 4] = [
+    TrackedObject::CacheChange,
+    TrackedObject::ReaderProxy,
+    TrackedObject::WriterProxy,
+    TrackedObject::CryptoHandle,
+  ];
+}
+
+// Registers an object on creation, and unregisters it when dropped. A clone
+// is a new object. Trackers compare equal, so that they do not affect the
+// comparison of the objects that contain them.
+#[cfg(feature = "leak_tracking")]
+#[derive(Debug)]
+pub(crate) struct LeakTracker {
+  kind: TrackedObject,
+  id: u64,
+}
+
+#[cfg(not(feature = "leak_tracking"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LeakTracker;
+
+#[cfg(not(feature = "leak_tracking"))]
+impl LeakTracker {
+  pub(crate) fn new(_kind: TrackedObject) -> Self {
+    Self
+  }
+}
+
+#[cfg(feature = "leak_tracking")]
+#[derive(Default)]
+struct KindRegistry {
+  created: u64,
+  dropped: u64,
+  peak_live: usize,
+  // Creation times by id. Ids grow, so the first one is the oldest.
+  live: BTreeMap<u64, Instant>,
+}
+
+#[cfg(feature = "leak_tracking")]
+static REGISTRY: Mutex<Vec<KindRegistry>> = Mutex::new(Vec::new());
+
+#[cfg(feature = "leak_tracking")]
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(feature = "leak_tracking")]
+fn with_registry<R>(kind: TrackedObject, f: impl FnOnce(&mut KindRegistry) -> R) -> R {
+  // A panic elsewhere must not stop the tracking
+  let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+  if registry.is_empty() {
+    registry.resize_with(TrackedObject::ALL.len(), KindRegistry::default);
+  }
+  f(&mut registry[kind as usize])
+}
+
+#[cfg(feature = "leak_tracking")]
+impl LeakTracker {
+  pub(crate) fn new(kind: TrackedObject) -> Self {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    with_registry(kind, |r| {
+      r.created += 1;
+      r.live.insert(id, Instant::now());
+      r.peak_live = r.peak_live.max(r.live.len());
+    });
+    Self { kind, id }
+  }
+}
+
+#[cfg(feature = "leak_tracking")]
+impl Clone for LeakTracker {
+  fn clone(&self) -> Self {
+    Self::new(self.kind)
+  }
+}
+
+#[cfg(feature = "leak_tracking")]
+impl Drop for LeakTracker {
+  fn drop(&mut self) {
+    with_registry(self.kind, |r| {
+      r.dropped += 1;
+      r.live.remove(&self.id);
+    });
+  }
+}
+
+#[cfg(feature = "leak_tracking")]
+impl PartialEq for LeakTracker {
+  fn eq(&self, _other: &Self) -> bool {
+    true
+  }
+}
+
+#[cfg(feature = "leak_tracking")]
+impl Eq for LeakTracker {}
+
+/// Counts of one kind of tracked object
+#[cfg(feature = "leak_tracking")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackedObjectCounts {
+  pub kind: TrackedObject,
+  pub created: u64,
+  pub dropped: u64,
+  /// Objects alive when the report was taken
+  pub live: usize,
+  /// Highest number of objects alive at the same time
+  pub peak_live: usize,
+  /// Age of the oldest object alive, if any
+  pub oldest_live_age: Option<Duration>,
+}
+
+/// Counts of all kinds of tracked objects, from [`leak_report`]
+#[cfg(feature = "leak_tracking")]
+#[derive(Debug, Clone)]
+pub struct LeakReport {
+  pub taken_at: Instant,
+  pub counts: Vec<TrackedObjectCounts>,
+}
+
+#[cfg(feature = "leak_tracking")]
+impl LeakReport {
+  /// Counts of one kind of object
+  pub fn counts_of(&self, kind: TrackedObject) -> Option<&TrackedObjectCounts> {
+    self.counts.iter().find(|c| c.kind == kind)
+  }
+
+  /// Change in the number of live objects of each kind since an `earlier`
+  /// report. In a steady state, this stays near zero.
+  pub fn live_growth_since(&self, earlier: &LeakReport) -> Vec<(TrackedObject, i64)> {
+    self
+      .counts
+      .iter()
+      .map(|c| {
+        let earlier_live = earlier.counts_of(c.kind).map_or(0, |e| e.live);
+        (c.kind, c.live as i64 - earlier_live as i64)
+      })
+      .collect()
+  }
+}
+
+#[cfg(feature = "leak_tracking")]
+impl fmt::Display for LeakReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for c in &self.counts {
+      write!(
+        f,
+        "{:?}: {} live (peak {}), {} created, {} dropped",
+        c.kind, c.live, c.peak_live, c.created, c.dropped
+      )?;
+      if let Some(age) = c.oldest_live_age {
+        write!(f, ", oldest {age:?}")?;
+      }
+      writeln!(f)?;
+    }
+    Ok(())
+  }
+}
+
+/// Takes a report of the tracked objects of all DomainParticipants in this
+/// process.
+#[cfg(feature = "leak_tracking")]
+pub fn leak_report() -> LeakReport {
+  let taken_at = Instant::now();
+  let counts = TrackedObject::ALL
+    .iter()
+    .map(|&kind| {
+      with_registry(kind, |r| TrackedObjectCounts {
+        kind,
+        created: r.created,
+        dropped: r.dropped,
+        live: r.live.len(),
+        peak_live: r.peak_live,
+        oldest_live_age: r
+          .live
+          .values()
+          .next()
+          .map(|created| taken_at.saturating_duration_since(*created)),
+      })
+    })
+    .collect();
+  LeakReport { taken_at, counts }
+}
+
+#[cfg(all(test, feature = "leak_tracking"))]
+mod tests {
+  use super::*;
+
+  fn is_live(tracker: &LeakTracker) -> bool {
+    with_registry(tracker.kind, |r| r.live.contains_key(&tracker.id))
+  }
+
+  #[test]
+  fn trackers_register_and_unregister() {
+    // Other tests create objects concurrently, so check only our own
+    let tracker = LeakTracker::new(TrackedObject::WriterProxy);
+    let copy = tracker.clone();
+    assert_ne!(tracker.id, copy.id);
+    assert!(is_live(&tracker) && is_live(&copy));
+
+    let report = leak_report();
+    let counts = report.counts_of(TrackedObject::WriterProxy).unwrap();
+    assert!(counts.live >= 2 && counts.peak_live >= 2);
+    assert!(counts.oldest_live_age.is_some());
+    assert!(report.to_string().contains("WriterProxy: "));
+
+    let (tracker_id, kind) = (tracker.id, tracker.kind);
+    drop(tracker);
+    assert!(!with_registry(kind, |r| r.live.contains_key(&tracker_id)));
+    assert!(is_live(&copy));
+  }
+}
@@ -171,6 +171,7 @@ mod serialization_test;
 mod checked_impl;
 #[doc(hidden)]
 pub mod discovery; // to access some Discovered data in e.g. ros2-client crate
+pub use discovery::health::{ParticipantHealth, SELF_HEALTH_TOPIC_NAME, SELF_HEALTH_TYPE_NAME};
 mod messages;
 mod network;
 mod rtps;
@@ -178,13 +179,30 @@ mod rtps;
 #[cfg(feature = "security")]
 mod security;
 #[cfg(feature = "security")]
-pub use security::config::DomainParticipantSecurityConfigFiles;
+pub use security::config::{CryptoKeySize, DomainParticipantSecurityConfigFiles, OriginAuthentication};
+#[cfg(feature = "security")]
+pub use security::audit::{
+  AuditedToken, HandshakeAudit, HandshakeAuditRecord, HandshakeAuditStatus, HandshakeDirection,
+  HandshakeTranscript, PermissionsAuditRecord, AUDIT_EXPORT_KEY_LENGTH,
+};
+#[cfg(feature = "security")]
+pub use security::certificate_store::CertificateStore;
+#[cfg(feature = "security")]
+pub use security::cryptographic::SessionKeyCacheStatistics;
+#[cfg(feature = "security")]
+pub use security::decode_limits::{DecodeLimits, DecodeRejection, DecodeRejectionCounts};
+#[cfg(feature = "security")]
+pub use security::error_counts::{SecurityErrorCategory, SecurityErrorCounts};
+#[cfg(feature = "security")]
+pub use security::startup::{SecurityStartupProgress, SecurityStartupStage};
 
 #[cfg(not(feature = "security"))]
 mod no_security;
 
 pub(crate) mod structure;
 
+pub mod leak_tracking;
+
 #[cfg(test)]
 mod test;
 
@@ -193,6 +211,7 @@ mod mio_source;
 // Public modules
 pub mod dds; // this is public, but not advertised
 
+#[cfg(feature = "ros2")]
 #[deprecated(since = "0.8.5", note = "Use crate ros2-client instead.")]
 pub mod ros2;
 /// Helpers for (De)serialization and definitions of (De)serializer adapters
@@ -201,31 +220,66 @@ pub mod serialization;
 // Re-exports from crate root to simplify usage
 #[doc(inline)]
 pub use dds::{
+  communication_status,
+  compliance::ComplianceMode,
+  content_filter,
+  content_filter::ContentFilteredTopic,
+  discovery_damping,
+  discovery_damping::DiscoveryDampingSettings,
+  discovery_network::{DiscoveryNetworkSettings, InterfaceSelector, IpStack},
+  dynamic, flow_control,
+  flow_control::FlowControllerSettings,
+  interceptor, intra_process,
   key::{Key, Keyed},
+  listener,
+  listener::{DataReaderListener, DataWriterListener, DomainParticipantListener},
+  loaned::LoanedSample,
   participant::{DomainParticipant, DomainParticipantBuilder},
-  pubsub::{Publisher, Subscriber},
+  participant_factory::DomainParticipantFactory,
+  peer_resolution, protocol_identity,
+  protocol_identity::ProtocolIdentity,
+  pubsub::{
+    PartitionCutover, PartitionSwitch, PartitionSwitchReport, Publisher, PublisherFlushController,
+    Subscriber,
+  },
   qos,
   qos::{policy, QosPolicies, QosPolicyBuilder},
-  readcondition::ReadCondition,
+  readcondition::{QueryCondition, ReadCondition},
+  resource_profile::{ResourceProfile, ResourceSettings, SocketBuffer, SocketStatistics},
   sampleinfo::{InstanceState, NotAliveGenerationCounts, SampleInfo, SampleState, ViewState},
+  shutdown::ShutdownToken,
+  sliced::{SampleSlices, SlicedSample},
   statusevents::{
     DataReaderStatus, DataWriterStatus, DomainParticipantStatusEvent, EndpointDescription,
-    LostReason, ParticipantDescription, StatusEvented,
+    LostReason, ParticipantDescription, StatusEvented, WriterMisbehavior,
   },
+  tcp_transport,
+  tcp_transport::TcpTransportSettings,
   topic::{Topic, TopicDescription, TopicKind},
+  topic_namespace, traffic_isolation,
+  traffic_isolation::{TrafficClassSettings, TrafficIsolationSettings},
   typedesc::TypeDesc,
-  with_key::{datareader::SelectByKey, WriteOptions, WriteOptionsBuilder},
+  waitset,
+  waitset::{GuardCondition, StatusCondition, WaitSet},
+  with_key::{
+    datareader::SelectByKey, SequenceNumberWatermarks, WriteOptions, WriteOptionsBuilder,
+    WriterHistoryStatus,
+  },
+  xtypes,
 };
 /// Needed to specify serialized data representation in case it is other than
 /// CDR.
 pub use serialization::RepresentationIdentifier;
 #[doc(inline)]
+#[allow(deprecated)]
 pub use serialization::{
   CDRDeserializerAdapter, CDRSerializerAdapter, CdrDeserializer, CdrSerializer,
 };
+/// RTPS protocol version and vendor id, as in [`ProtocolIdentity`]
+pub use messages::{protocol_version::ProtocolVersion, vendor_id::VendorId};
 pub use structure::{
-  duration::Duration, entity::RTPSEntity, guid::GUID, sequence_number::SequenceNumber,
-  time::Timestamp,
+  dds_cache::CacheStatistics, duration::Duration, entity::RTPSEntity, guid::GUID,
+  sequence_number::SequenceNumber, time::Timestamp,
 };
 // re-export from a helper crate
 /// Helper trait to compute the CDR-serialized size of data
@@ -244,3 +298,70 @@ pub mod with_key {
 pub mod rpc {
   pub use crate::structure::rpc::*;
 }
+
+/// Interfaces of the DDS Security plugins, and the types that appear in them.
+///
+/// RustDDS has builtin implementations of the plugins specified in DDS
+/// Security, which are used by
+/// [`DomainParticipantBuilder::builtin_security`]. Custom implementations of
+/// the [`Authentication`], [`AccessControl`] and [`Cryptographic`] traits can
+/// be given with [`DomainParticipantBuilder::with_security_plugins`], also
+/// together with builtin ones.
+///
+/// ```no_run
+/// use rustdds::{
+///   security_plugins::{AccessControlBuiltin, AuthenticationBuiltin, CryptographicBuiltin},
+///   DomainParticipantBuilder, DomainParticipantSecurityConfigFiles,
+/// };
+///
+/// let configs =
+///   DomainParticipantSecurityConfigFiles::with_ros_default_names("security", "".to_string());
+/// // Replace any of these with a custom implementation.
+/// let participant = DomainParticipantBuilder::new(0)
+///   .with_security_plugins(
+///     Box::new(AuthenticationBuiltin::new()),
+///     Box::new(AccessControlBuiltin::new()),
+///     Box::new(CryptographicBuiltin::new()),
+///     configs.into_property_policy(),
+///   )
+///   .build();
+/// ```
+#[cfg(feature = "security")]
+pub mod security_plugins {
+  pub use crate::{
+    discovery::{sedp_messages::TopicBuiltinTopicData, SpdpDiscoveredParticipantData},
+    messages::submessages::{
+      elements::parameter_list::ParameterList, secure_postfix::SecurePostfix,
+      secure_prefix::SecurePrefix, submessage::SecuritySubmessage,
+    },
+    rtps::{
+      message::Message,
+      submessage::{Submessage, SubmessageBody},
+    },
+    security::{
+      access_control::{
+        AccessControl, EndpointSecurityAttributes, LocalEntityAccessControl,
+        ParticipantAccessControl, ParticipantSecurityAttributes, PermissionsCredentialToken,
+        PermissionsHandle, PermissionsToken, RemoteEntityAccessControl, TopicSecurityAttributes,
+      },
+      authentication::{
+        AuthRequestMessageToken, AuthenticatedPeerCredentialToken, Authentication, HandshakeHandle,
+        HandshakeMessageToken, IdentityHandle, IdentityStatusToken, IdentityToken,
+        SharedSecretHandle, ValidationOutcome,
+      },
+      cryptographic::{
+        cryptographic_plugin::{CryptoKeyExchange, CryptoKeyFactory, CryptoTransform},
+        CryptoToken, Cryptographic, DatareaderCryptoHandle, DatareaderCryptoToken,
+        DatawriterCryptoHandle, DatawriterCryptoToken, DecodeOutcome, DecodedSubmessage,
+        EncodedSubmessage, ParticipantCryptoHandle, ParticipantCryptoToken,
+      },
+      decode_limits::DecodeLimiter,
+      types::{
+        BinaryProperty, DataHolder, Property, PublicationBuiltinTopicDataSecure, SecurityError,
+        SecurityErrorContext, SecurityResult, SubscriptionBuiltinTopicDataSecure,
+      },
+      AccessControlBuiltin, AuthenticationBuiltin, CryptographicBuiltin,
+    },
+    structure::guid::GuidPrefix,
+  };
+}
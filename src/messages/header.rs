@@ -1,6 +1,7 @@
 use speedy::{Readable, Writable};
 
 use crate::{
+  dds::protocol_identity::{self, ProtocolIdentity},
   messages::{
     protocol_id::ProtocolId, protocol_version::ProtocolVersion, validity_trait::Validity,
     vendor_id::VendorId,
@@ -17,11 +18,14 @@ pub struct Header {
 }
 
 impl Header {
+  // The version and vendor id are those that the DomainParticipant
+  // advertises, see ProtocolIdentity.
   pub fn new(guid: GuidPrefix) -> Self {
+    let identity = ProtocolIdentity::current();
     Self {
       protocol_id: ProtocolId::PROTOCOL_RTPS,
-      protocol_version: ProtocolVersion::THIS_IMPLEMENTATION,
-      vendor_id: VendorId::THIS_IMPLEMENTATION,
+      protocol_version: identity.protocol_version,
+      vendor_id: identity.vendor_id,
       guid_prefix: guid,
     }
   }
@@ -33,8 +37,7 @@ impl Validity for Header {
     // (1) We cannot reach this point if the message has too few bytes to contain a
     // full header.
     self.protocol_id == ProtocolId::PROTOCOL_RTPS // (2)
-    && self.protocol_version.major <= ProtocolVersion::THIS_IMPLEMENTATION.major
-    // (3)
+    && protocol_identity::accepts_version(self.protocol_version) // (3)
   }
 }
 
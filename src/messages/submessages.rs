@@ -1,6 +1,8 @@
 pub mod ack_nack;
+pub mod app_ack;
 pub mod data;
 pub mod data_frag;
+pub mod fec_parity;
 pub mod gap;
 pub mod heartbeat;
 pub mod heartbeat_frag;
@@ -35,8 +37,9 @@ pub mod submessage_kind;
 #[allow(clippy::module_inception)]
 pub mod submessages {
   pub use super::{
-    ack_nack::*, data::*, data_frag::*, elements::RepresentationIdentifier, gap::*, heartbeat::*,
-    heartbeat_frag::*, info_destination::*, info_reply::*, info_timestamp::*, nack_frag::*,
-    submessage::*, submessage_flag::*, submessage_header::*, submessage_kind::*,
+    ack_nack::*, app_ack::*, data::*, data_frag::*, elements::RepresentationIdentifier,
+    fec_parity::*, gap::*, heartbeat::*, heartbeat_frag::*, info_destination::*, info_reply::*,
+    info_timestamp::*, nack_frag::*, submessage::*, submessage_flag::*, submessage_header::*,
+    submessage_kind::*,
   };
 }
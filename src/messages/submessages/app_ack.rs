@@ -0,0 +1,93 @@
+use std::mem::size_of;
+
+use enumflags2::BitFlags;
+use speedy::{Readable, Writable};
+
+use crate::{
+  messages::submessages::submessages::SubmessageHeader,
+  rtps::{Submessage, SubmessageBody},
+  structure::{guid::EntityId, sequence_number::SequenceNumberSet},
+};
+use super::{
+  submessage::{HasEntityIds, ReaderSubmessage},
+  submessage_flag::APPACK_Flags,
+  submessage_kind::SubmessageKind,
+};
+
+/// RustDDS vendor-specific submessage. A Reader sends it to a Writer to tell
+/// which samples the application has processed and acknowledged. Unlike
+/// ACKNACK, it says nothing about what the Reader has received. See
+/// [`Acknowledgment`](crate::policy::Acknowledgment).
+#[derive(Debug, PartialEq, Eq, Clone, Readable, Writable)]
+pub struct AppAck {
+  pub reader_id: EntityId,
+  pub writer_id: EntityId,
+
+  /// All sequence numbers before the base of the set are acknowledged, as
+  /// well as those in the set. The ones not in the set are not.
+  pub acknowledged: SequenceNumberSet,
+
+  /// Incremented each time a new AppAck is sent
+  pub count: i32,
+}
+
+impl AppAck {
+  pub fn create_submessage(self, flags: BitFlags<APPACK_Flags>) -> Submessage {
+    Submessage {
+      header: SubmessageHeader {
+        kind: SubmessageKind::APP_ACK,
+        flags: flags.bits(),
+        content_length: self.len_serialized() as u16,
+      },
+      body: SubmessageBody::Reader(ReaderSubmessage::AppAck(self, flags)),
+      original_bytes: None,
+    }
+  }
+
+  pub fn len_serialized(&self) -> usize {
+    size_of::<EntityId>() * 2 + self.acknowledged.len_serialized() + size_of::<i32>()
+  }
+}
+
+impl HasEntityIds for AppAck {
+  fn receiver_entity_id(&self) -> EntityId {
+    self.writer_id
+  }
+  fn sender_entity_id(&self) -> EntityId {
+    self.reader_id
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::structure::sequence_number::SequenceNumber;
+
+  serialization_test!( type = AppAck,
+  {
+      app_ack,
+      AppAck {
+          reader_id: EntityId::UNKNOWN,
+          writer_id: EntityId::SEDP_BUILTIN_PUBLICATIONS_WRITER,
+          acknowledged: SequenceNumberSet::from_base_and_set(
+            SequenceNumber::from(3),
+            &[SequenceNumber::from(4)].into_iter().collect(),
+          ),
+          count: 2,
+      },
+      le = [0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x03, 0xC2,
+            0x00, 0x00, 0x00, 0x00,
+            0x03, 0x00, 0x00, 0x00,
+            0x02, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x40,
+            0x02, 0x00, 0x00, 0x00],
+      be = [0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x03, 0xC2,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x03,
+            0x00, 0x00, 0x00, 0x02,
+            0x40, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x02]
+  });
+}
@@ -1,6 +1,4 @@
 use enumflags2::{bitflags, BitFlags};
-#[cfg(test)]
-use byteorder::ByteOrder;
 use speedy::{Context, Endianness, Readable, Writable, Writer};
 use serde::{Deserialize, Serialize};
 #[allow(unused_imports)]
@@ -8,13 +6,15 @@ use log::{debug, error, info, trace, warn};
 
 use crate::{
   dds::key::KeyHash,
-  messages::submessages::elements::{parameter_list::ParameterList, RepresentationIdentifier},
+  messages::submessages::elements::{
+    parameter::Parameter, parameter_list::ParameterList, RepresentationIdentifier,
+  },
   serialization::{pl_cdr_adapters::PlCdrDeserializeError, speedy_pl_cdr_helpers::*},
   structure::{cache_change::ChangeKind, parameter_id::ParameterId, rpc::SampleIdentity},
 };
 #[cfg(test)]
 use crate::{
-  dds::adapters::no_key::*, serialization, serialization::to_vec,
+  dds::adapters::no_key::*, serialization, serialization::to_vec_with_endianness,
   serialization::CDRDeserializerAdapter,
 };
 
@@ -54,6 +54,25 @@ impl InlineQos {
     })
   }
 
+  // See policy::PayloadChecksum. The checksum is over the serialized sample
+  // without the representation identifier and options, i.e. what the
+  // DataReader deserializes.
+  pub fn payload_checksum_parameter(payload: &[u8]) -> Parameter {
+    Parameter {
+      parameter_id: ParameterId::PID_RUSTDDS_PAYLOAD_CHECKSUM,
+      value: md5::compute(payload).to_vec(),
+    }
+  }
+
+  pub fn payload_checksum(params: &ParameterList) -> Option<[u8; 16]> {
+    params
+      .parameters
+      .iter()
+      .find(|p| p.parameter_id == ParameterId::PID_RUSTDDS_PAYLOAD_CHECKSUM)
+      .and_then(|p| p.value.get(..16))
+      .and_then(|digest| digest.try_into().ok())
+  }
+
   pub fn related_sample_identity(
     params: &ParameterList,
     representation_id: RepresentationIdentifier,
@@ -175,8 +194,8 @@ impl StatusInfo {
   }
 
   #[cfg(test)]
-  pub fn into_cdr_bytes<BO: ByteOrder>(self) -> Result<Vec<u8>, serialization::Error> {
-    to_vec::<Self, BO>(&self)
+  pub fn into_cdr_bytes(self, endianness: Endianness) -> Result<Vec<u8>, serialization::Error> {
+    to_vec_with_endianness(&self, endianness)
   }
 
   #[cfg(test)]
@@ -190,8 +209,6 @@ impl StatusInfo {
 
 #[cfg(test)]
 mod tests {
-  use byteorder::{BigEndian, LittleEndian};
-
   use super::*;
 
   #[test]
@@ -201,7 +218,7 @@ mod tests {
       em: [0; 3],
       si: StatusInfoEnum::Disposed | StatusInfoEnum::Unregistered,
     }
-    .into_cdr_bytes::<LittleEndian>()
+    .into_cdr_bytes(Endianness::LittleEndian)
     .unwrap();
 
     let bytes: Vec<u8> = vec![0x00, 0x00, 0x00, 0x03];
@@ -221,7 +238,7 @@ mod tests {
       em: [0; 3],
       si: StatusInfoEnum::Disposed | StatusInfoEnum::Unregistered,
     }
-    .into_cdr_bytes::<BigEndian>()
+    .into_cdr_bytes(Endianness::BigEndian)
     .unwrap();
 
     let bytes: Vec<u8> = vec![0x00, 0x00, 0x00, 0x03];
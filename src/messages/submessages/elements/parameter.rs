@@ -78,9 +78,7 @@ impl<'a, C: Context> Readable<'a, C> for Parameter {
   fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
     let parameter_id: ParameterId = reader.read_value()?;
     let length = reader.read_u16()?;
-
-    let mut value = vec![0; length as usize];
-    reader.read_bytes(&mut value)?;
+    let value = read_parameter_value(reader, length)?;
 
     Ok(Self {
       parameter_id,
@@ -94,6 +92,37 @@ impl<'a, C: Context> Readable<'a, C> for Parameter {
   }
 }
 
+// Reads the value of a Parameter. The length field comes from the sender, so
+// it is not used to preallocate: the buffer grows only as fast as data is
+// actually available.
+pub(crate) fn read_parameter_value<'a, C: Context, R: Reader<'a, C>>(
+  reader: &mut R,
+  length: u16,
+) -> Result<Vec<u8>, C::Error> {
+  let length = usize::from(length);
+  match reader.can_read_at_least(length) {
+    Some(true) => reader.read_vec(length),
+    Some(false) => Err(
+      speedy::Error::custom(format!(
+        "Parameter length {length} is beyond the end of ParameterList"
+      ))
+      .into(),
+    ),
+    None => {
+      // Unknown amount of remaining input, e.g. a stream.
+      const CHUNK: usize = 256;
+      let mut value = Vec::with_capacity(length.min(CHUNK));
+      let mut buf = [0; CHUNK];
+      while value.len() < length {
+        let n = (length - value.len()).min(CHUNK);
+        reader.read_bytes(&mut buf[..n])?;
+        value.extend_from_slice(&buf[..n]);
+      }
+      Ok(value)
+    }
+  }
+}
+
 impl<C: Context> Writable<C> for Parameter {
   #[inline]
   fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
@@ -3,9 +3,14 @@ use std::collections::BTreeMap;
 use bytes::Bytes;
 use speedy::{Context, Readable, Writable, Writer};
 
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
 use crate::{
-  messages::submessages::elements::parameter::Parameter,
-  serialization::pl_cdr_adapters::PlCdrSerializeError, structure::parameter_id::ParameterId,
+  dds::compliance,
+  messages::submessages::elements::parameter::{read_parameter_value, Parameter},
+  serialization::pl_cdr_adapters::PlCdrSerializeError,
+  structure::parameter_id::ParameterId,
   RepresentationIdentifier,
 };
 
@@ -50,6 +55,17 @@ impl ParameterList {
     Ok(Bytes::from(b))
   }
 
+  /// Parameters that RustDDS does not recognize. Discovery data keeps these,
+  /// so that it can be forwarded unchanged, e.g. by a bridge.
+  pub fn unknown_parameters(&self) -> Vec<Parameter> {
+    self
+      .parameters
+      .iter()
+      .filter(|p| !p.parameter_id.is_known())
+      .cloned()
+      .collect()
+  }
+
   pub fn to_map(&self) -> BTreeMap<ParameterId, Vec<&Parameter>> {
     self.parameters.iter().fold(BTreeMap::new(), |mut m, p| {
       m.entry(p.parameter_id).or_default().push(p);
@@ -81,7 +97,8 @@ impl<'a, C: Context> Readable<'a, C> for ParameterList {
   fn read_from<R: speedy::Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
     let mut parameters = Self::default();
 
-    // loop ends in failure to read something or catching sentinel
+    // loop ends in failure to read something or catching sentinel.
+    // Running out of input before the sentinel is an error.
     loop {
       let parameter_id = ParameterId::read_from(reader)?;
       let length = u16::read_from(reader)?;
@@ -90,12 +107,56 @@ impl<'a, C: Context> Readable<'a, C> for ParameterList {
         // This is parameter list end marker.
         // We do not read its Parameter contents ("value"),
         // because it is of size zero by definition.
+        if length != 0
+          && !compliance::tolerate(&format!("PID_SENTINEL has non-zero length {length}."))
+        {
+          return Err(
+            speedy::Error::custom("ParameterList: PID_SENTINEL with non-zero length").into(),
+          );
+        }
         return Ok(parameters);
       }
 
+      // RTPS spec v2.5 Section 9.4.2.11: length is always a multiple of 4.
+      if length % 4 != 0
+        && !compliance::tolerate(&format!(
+          "Parameter {parameter_id:?} length {length} is not a multiple of 4."
+        ))
+      {
+        return Err(
+          speedy::Error::custom(format!(
+            "ParameterList: misaligned parameter {parameter_id:?} length {length}"
+          ))
+          .into(),
+        );
+      }
+
+      // Checks the length against remaining input before allocating anything.
+      let value = read_parameter_value(reader, length)?;
+
+      if parameter_id == ParameterId::PID_PAD {
+        continue; // padding only, nothing to keep
+      }
+
+      // Unknown parameters are ignored by the parameter parsers, but kept in the
+      // list. The exception is an unknown parameter that we are required to
+      // understand. Vendor-specific parameters of other vendors are never
+      // required, since we could not understand them anyway.
+      if !parameter_id.is_known()
+        && parameter_id.is_must_understand()
+        && !parameter_id.is_vendor_specific()
+      {
+        return Err(
+          speedy::Error::custom(format!(
+            "ParameterList: unknown must-understand parameter {parameter_id:?}"
+          ))
+          .into(),
+        );
+      }
+
       parameters.parameters.push(Parameter {
         parameter_id,
-        value: reader.read_vec(length as usize)?,
+        value,
       });
     }
   }
@@ -108,3 +169,73 @@ pub trait ParameterListable {
     encoding: RepresentationIdentifier,
   ) -> Result<ParameterList, PlCdrSerializeError>;
 }
+
+#[cfg(test)]
+mod tests {
+  use speedy::Endianness;
+
+  use super::*;
+
+  fn read_le(bytes: &[u8]) -> Result<ParameterList, speedy::Error> {
+    ParameterList::read_from_buffer_with_ctx(Endianness::LittleEndian, bytes)
+  }
+
+  #[test]
+  fn unknown_parameters_are_kept_and_pad_skipped() {
+    let bytes = [
+      0x15, 0x00, 0x04, 0x00, 0x02, 0x04, 0x00, 0x00, // PID_PROTOCOL_VERSION
+      0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, // PID_PAD
+      0x34, 0x12, 0x04, 0x00, 0xaa, 0xbb, 0xcc, 0xdd, // unknown 0x1234
+      0x01, 0x00, 0x00, 0x00, // PID_SENTINEL
+    ];
+    let pl = read_le(&bytes).unwrap();
+    assert_eq!(pl.parameters.len(), 2);
+    assert_eq!(
+      pl.unknown_parameters(),
+      vec![Parameter::new(
+        ParameterId::read_from_buffer_with_ctx(Endianness::LittleEndian, &[0x34, 0x12]).unwrap(),
+        vec![0xaa, 0xbb, 0xcc, 0xdd]
+      )]
+    );
+
+    // Round trip preserves the unknown parameter
+    let written = pl.write_to_vec_with_ctx(Endianness::LittleEndian).unwrap();
+    assert_eq!(read_le(&written).unwrap(), pl);
+  }
+
+  #[test]
+  fn strict_mode_rejects_deviations() {
+    use crate::dds::compliance::ComplianceMode;
+
+    let misaligned = [
+      0x34, 0x12, 0x03, 0x00, 0xaa, 0xbb, 0xcc, // unknown 0x1234, length 3
+      0x01, 0x00, 0x00, 0x00, // PID_SENTINEL
+    ];
+    let long_sentinel = [0x01, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+    assert_eq!(read_le(&misaligned).unwrap().parameters[0].value.len(), 3);
+    assert!(read_le(&long_sentinel).unwrap().is_empty());
+
+    let _strict = ComplianceMode::Strict.enter();
+    assert!(read_le(&misaligned).is_err());
+    assert!(read_le(&long_sentinel).is_err());
+  }
+
+  #[test]
+  fn reject_malformed_parameter_lists() {
+    // Unknown parameter with the must-understand bit
+    assert!(read_le(&[0x34, 0x52, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00]).is_err());
+    // ... unless it is vendor-specific
+    assert!(read_le(&[0x34, 0xd2, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00]).is_ok());
+    // Length beyond the end of input
+    assert!(read_le(&[0x34, 0x12, 0xff, 0xff, 0x01, 0x00, 0x00, 0x00]).is_err());
+    // Missing sentinel
+    assert!(read_le(&[0x15, 0x00, 0x04, 0x00, 0x02, 0x04, 0x00, 0x00]).is_err());
+    // Huge length in a stream must fail without allocating it up front
+    assert!(ParameterList::read_from_stream_unbuffered_with_ctx(
+      Endianness::LittleEndian,
+      &mut std::io::Cursor::new([0x34, 0x12, 0xff, 0xff, 0x00, 0x00])
+    )
+    .is_err());
+  }
+}
@@ -0,0 +1,174 @@
+use enumflags2::BitFlags;
+use log::error;
+use speedy::{Context, Readable, Reader, Writable, Writer};
+
+use crate::{
+  messages::submessages::submessages::SubmessageHeader,
+  rtps::{Submessage, SubmessageBody},
+  structure::{guid::EntityId, sequence_number::SequenceNumber, time::Timestamp},
+};
+use super::{
+  submessage::{HasEntityIds, WriterSubmessage},
+  submessage_flag::FECPARITY_Flags,
+  submessage_kind::SubmessageKind,
+};
+
+/// RustDDS vendor-specific submessage. It carries the forward error
+/// correction parity of a group of samples a best-effort Writer has sent, so
+/// that a Reader can reconstruct a lost sample of the group without
+/// retransmission. See
+/// [`ForwardErrorCorrection`](crate::policy::ForwardErrorCorrection).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FecParity {
+  pub reader_id: EntityId,
+  pub writer_id: EntityId,
+
+  /// The samples of the group, in the order they were sent.
+  pub samples: Vec<FecSample>,
+
+  /// XOR of the serialized payloads of the samples, each padded with zeros
+  /// to the length of the longest one. The length is a multiple of 4.
+  pub parity: Vec<u8>,
+}
+
+/// A sample protected by a [`FecParity`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Readable, Writable)]
+pub struct FecSample {
+  pub sequence_number: SequenceNumber,
+  /// Length of the serialized payload, including the encapsulation header
+  pub payload_length: u32,
+  /// [`Timestamp::INVALID`], if the sample has no source timestamp
+  pub source_timestamp: Timestamp,
+}
+
+impl FecParity {
+  /// Upper limit to the samples in a group
+  pub const MAX_SAMPLES: usize = 256;
+
+  pub fn create_submessage(self, flags: BitFlags<FECPARITY_Flags>) -> Option<Submessage> {
+    let submessage_len = match self.write_to_vec() {
+      Ok(bytes) => bytes.len() as u16,
+      Err(e) => {
+        error!("Writer couldn't write FEC_PARITY to bytes: {}", e);
+        return None;
+      }
+    };
+
+    Some(Submessage {
+      header: SubmessageHeader {
+        kind: SubmessageKind::FEC_PARITY,
+        flags: flags.bits(),
+        content_length: submessage_len,
+      },
+      body: SubmessageBody::Writer(WriterSubmessage::FecParity(self, flags)),
+      original_bytes: None,
+    })
+  }
+}
+
+// Written manually, so that the lengths are checked before anything is
+// allocated. Other vendors may use the same submessage kind for something
+// else.
+impl<'a, C: Context> Readable<'a, C> for FecParity {
+  fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
+    let reader_id = reader.read_value()?;
+    let writer_id = reader.read_value()?;
+    let sample_count = reader.read_u32()? as usize;
+    if sample_count > Self::MAX_SAMPLES {
+      return Err(speedy::Error::custom(format!("FEC_PARITY has {sample_count} samples")).into());
+    }
+    let samples = (0..sample_count)
+      .map(|_| reader.read_value())
+      .collect::<Result<Vec<FecSample>, _>>()?;
+    let parity_length = reader.read_u32()? as usize;
+    if parity_length % 4 != 0
+      || parity_length > usize::from(u16::MAX)
+      || reader.can_read_at_least(parity_length) == Some(false)
+    {
+      return Err(
+        speedy::Error::custom(format!("FEC_PARITY has parity of {parity_length} bytes")).into(),
+      );
+    }
+    let parity = reader.read_vec(parity_length)?;
+    Ok(Self {
+      reader_id,
+      writer_id,
+      samples,
+      parity,
+    })
+  }
+}
+
+impl<C: Context> Writable<C> for FecParity {
+  fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
+    writer.write_value(&self.reader_id)?;
+    writer.write_value(&self.writer_id)?;
+    writer.write_u32(self.samples.len() as u32)?;
+    for sample in &self.samples {
+      writer.write_value(sample)?;
+    }
+    writer.write_u32(self.parity.len() as u32)?;
+    writer.write_bytes(&self.parity)
+  }
+}
+
+impl HasEntityIds for FecParity {
+  fn receiver_entity_id(&self) -> EntityId {
+    self.reader_id
+  }
+  fn sender_entity_id(&self) -> EntityId {
+    self.writer_id
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  serialization_test!( type = FecParity,
+  {
+      fec_parity,
+      FecParity {
+          reader_id: EntityId::UNKNOWN,
+          writer_id: EntityId::SEDP_BUILTIN_PUBLICATIONS_WRITER,
+          samples: vec![FecSample {
+              sequence_number: SequenceNumber::from(42),
+              payload_length: 5,
+              source_timestamp: Timestamp::INVALID,
+          }],
+          parity: vec![1, 2, 3, 4, 5, 0, 0, 0],
+      },
+      le = [0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x03, 0xC2,
+            0x01, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x2A, 0x00, 0x00, 0x00,
+            0x05, 0x00, 0x00, 0x00,
+            0xFF, 0xFF, 0xFF, 0xFF,
+            0xFF, 0xFF, 0xFF, 0xFF,
+            0x08, 0x00, 0x00, 0x00,
+            0x01, 0x02, 0x03, 0x04,
+            0x05, 0x00, 0x00, 0x00],
+      be = [0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x03, 0xC2,
+            0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x2A,
+            0x00, 0x00, 0x00, 0x05,
+            0xFF, 0xFF, 0xFF, 0xFF,
+            0xFF, 0xFF, 0xFF, 0xFF,
+            0x00, 0x00, 0x00, 0x08,
+            0x01, 0x02, 0x03, 0x04,
+            0x05, 0x00, 0x00, 0x00]
+  });
+
+  #[test]
+  fn fec_parity_with_too_many_samples_is_rejected() {
+    let bytes = [
+      0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xC2, 0xFF, 0xFF, 0xFF, 0xFF,
+    ];
+    assert!(
+      FecParity::read_from_buffer_with_ctx(speedy::Endianness::LittleEndian, &bytes).is_err()
+    );
+  }
+}
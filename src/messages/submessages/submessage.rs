@@ -3,10 +3,10 @@ use enumflags2::BitFlags;
 
 use crate::{
   messages::submessages::{
-    ack_nack::AckNack, data::Data, data_frag::DataFrag, gap::Gap, heartbeat::Heartbeat,
-    heartbeat_frag::HeartbeatFrag, info_destination::InfoDestination, info_reply::InfoReply,
-    info_source::InfoSource, info_timestamp::InfoTimestamp, nack_frag::NackFrag,
-    submessage_flag::*,
+    ack_nack::AckNack, app_ack::AppAck, data::Data, data_frag::DataFrag, fec_parity::FecParity,
+    gap::Gap, heartbeat::Heartbeat, heartbeat_frag::HeartbeatFrag,
+    info_destination::InfoDestination, info_reply::InfoReply, info_source::InfoSource,
+    info_timestamp::InfoTimestamp, nack_frag::NackFrag, submessage_flag::*,
   },
   structure::guid::EntityId,
 };
@@ -27,6 +27,7 @@ pub enum WriterSubmessage {
   Heartbeat(Heartbeat, BitFlags<HEARTBEAT_Flags>),
   #[allow(dead_code)] // Functionality not yet implemented
   HeartbeatFrag(HeartbeatFrag, BitFlags<HEARTBEATFRAG_Flags>),
+  FecParity(FecParity, BitFlags<FECPARITY_Flags>),
 }
 
 // we must write this manually, because
@@ -41,6 +42,7 @@ impl<C: Context> Writable<C> for WriterSubmessage {
       WriterSubmessage::Gap(s, _f) => writer.write_value(s),
       WriterSubmessage::Heartbeat(s, _f) => writer.write_value(s),
       WriterSubmessage::HeartbeatFrag(s, _f) => writer.write_value(s),
+      WriterSubmessage::FecParity(s, _f) => writer.write_value(s),
     }
   }
 }
@@ -49,6 +51,7 @@ impl<C: Context> Writable<C> for WriterSubmessage {
 pub enum ReaderSubmessage {
   AckNack(AckNack, BitFlags<ACKNACK_Flags>),
   NackFrag(NackFrag, BitFlags<NACKFRAG_Flags>),
+  AppAck(AppAck, BitFlags<APPACK_Flags>),
 }
 
 // we must write this manually, because
@@ -60,6 +63,7 @@ impl<C: Context> Writable<C> for ReaderSubmessage {
     match self {
       ReaderSubmessage::AckNack(s, _f) => writer.write_value(s),
       ReaderSubmessage::NackFrag(s, _f) => writer.write_value(s),
+      ReaderSubmessage::AppAck(s, _f) => writer.write_value(s),
     }
   }
 }
@@ -125,6 +129,7 @@ pub enum AckSubmessage {
   AckNack(AckNack),
   #[allow(dead_code)] // Functionality not yet implemented
   NackFrag(NackFrag),
+  AppAck(AppAck),
 }
 
 impl AckSubmessage {
@@ -132,6 +137,7 @@ impl AckSubmessage {
     match self {
       AckSubmessage::AckNack(a) => a.writer_id,
       AckSubmessage::NackFrag(a) => a.writer_id,
+      AckSubmessage::AppAck(a) => a.writer_id,
     }
   }
 }
@@ -149,6 +155,7 @@ impl HasEntityIds for WriterSubmessage {
       WriterSubmessage::Gap(s, _f) => s.receiver_entity_id(),
       WriterSubmessage::Heartbeat(s, _f) => s.receiver_entity_id(),
       WriterSubmessage::HeartbeatFrag(s, _f) => s.receiver_entity_id(),
+      WriterSubmessage::FecParity(s, _f) => s.receiver_entity_id(),
     }
   }
   fn sender_entity_id(&self) -> EntityId {
@@ -158,6 +165,7 @@ impl HasEntityIds for WriterSubmessage {
       WriterSubmessage::Gap(s, _f) => s.sender_entity_id(),
       WriterSubmessage::Heartbeat(s, _f) => s.sender_entity_id(),
       WriterSubmessage::HeartbeatFrag(s, _f) => s.sender_entity_id(),
+      WriterSubmessage::FecParity(s, _f) => s.sender_entity_id(),
     }
   }
 }
@@ -167,12 +175,14 @@ impl HasEntityIds for ReaderSubmessage {
     match self {
       ReaderSubmessage::AckNack(s, _f) => s.receiver_entity_id(),
       ReaderSubmessage::NackFrag(s, _f) => s.receiver_entity_id(),
+      ReaderSubmessage::AppAck(s, _f) => s.receiver_entity_id(),
     }
   }
   fn sender_entity_id(&self) -> EntityId {
     match self {
       ReaderSubmessage::AckNack(s, _f) => s.sender_entity_id(),
       ReaderSubmessage::NackFrag(s, _f) => s.sender_entity_id(),
+      ReaderSubmessage::AppAck(s, _f) => s.sender_entity_id(),
     }
   }
 }
@@ -187,6 +187,24 @@ pub enum INFOREPLYIP4_Flags {
 }
 submessageflag_impls!(INFOREPLYIP4_Flags);
 
+/// RustDDS vendor-specific FEC_PARITY submessage
+#[derive(Debug, PartialOrd, PartialEq, Ord, Eq, Readable, Clone, Copy)]
+#[repr(u8)]
+#[bitflags]
+pub enum FECPARITY_Flags {
+  Endianness = 0b00001,
+}
+submessageflag_impls!(FECPARITY_Flags);
+
+/// RustDDS vendor-specific APP_ACK submessage
+#[derive(Debug, PartialOrd, PartialEq, Ord, Eq, Readable, Clone, Copy)]
+#[repr(u8)]
+#[bitflags]
+pub enum APPACK_Flags {
+  Endianness = 0b00001,
+}
+submessageflag_impls!(APPACK_Flags);
+
 /// Section 7.3.7.5.3 of the Security specification (v. 1.1)
 #[derive(Debug, PartialOrd, PartialEq, Ord, Eq, Readable, Clone, Copy)]
 #[repr(u8)]
@@ -32,6 +32,8 @@ impl SubmessageKind {
   pub const SEC_POSTFIX: Self = Self { value: 0x32 }; // Section 7.3.7.7.2 of the Security specification (v. 1.1)
   pub const SRTPS_PREFIX: Self = Self { value: 0x33 }; // Section 7.3.7.8.2 of the Security specification (v. 1.1)
   pub const SRTPS_POSTFIX: Self = Self { value: 0x34 }; // Section 7.3.7.9.2 of the Security specification (v. 1.1)
+  pub const FEC_PARITY: Self = Self { value: 0x80 }; // RustDDS vendor-specific, see FecParity
+  pub const APP_ACK: Self = Self { value: 0x81 }; // RustDDS vendor-specific, see AppAck
 }
 
 impl Debug for SubmessageKind {
@@ -55,6 +57,8 @@ impl Debug for SubmessageKind {
       Self::SEC_POSTFIX => fmt.write_str("SEC_POSTFIX"),
       Self::SRTPS_PREFIX => fmt.write_str("SRTPS_PREFIX"),
       Self::SRTPS_POSTFIX => fmt.write_str("SRTPS_POSTFIX"),
+      Self::FEC_PARITY => fmt.write_str("FEC_PARITY"),
+      Self::APP_ACK => fmt.write_str("APP_ACK"),
       Self { value: other } => fmt.write_fmt(format_args!("SubmessageKind {} (UNKNOWN!)", other)),
     }
   }
@@ -17,12 +17,27 @@ impl VendorId {
     vendor_id: [0x01, 0x12],
   };
 
+  pub const RTI_CONNEXT: Self = Self {
+    vendor_id: [0x01, 0x01],
+  };
+
+  pub const EPROSIMA: Self = Self {
+    vendor_id: [0x01, 0x0F],
+  };
+
   pub const THIS_IMPLEMENTATION: Self = Self::ATOSTEK;
 
   pub fn as_bytes(&self) -> [u8; 2] {
     self.vendor_id
   }
 
+  /// Product name of a registered vendor id, e.g. "Eclipse Cyclone DDS"
+  pub fn product_name(self) -> Option<&'static str> {
+    self
+      .known_vendor_id_string()
+      .map(|(product, _vendor)| product)
+  }
+
   fn known_vendor_id_string(self) -> Option<(&'static str, &'static str)> {
     match self.vendor_id {
       // from https://www.dds-foundation.org/dds-rtps-vendor-and-product-ids/
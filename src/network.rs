@@ -1,4 +1,7 @@
 pub mod constant;
+pub mod interface_monitor;
+pub mod socket_buffers;
+pub mod tcp;
 pub mod udp_listener;
 pub mod udp_sender;
 pub mod util;
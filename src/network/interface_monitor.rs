@@ -0,0 +1,81 @@
+// Detection of local IP address changes, e.g. WiFi roaming or DHCP renewal.
+//
+// There is no portable notification mechanism for address changes, so the
+// interfaces are polled. Discovery does this periodically, and reconfigures
+// the participant when something has changed.
+
+use std::{collections::BTreeSet, net::IpAddr};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::network::util::get_local_unicast_ip_addrs;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AddressChange {
+  pub added: Vec<IpAddr>,
+  pub removed: Vec<IpAddr>,
+}
+
+pub(crate) struct InterfaceMonitor {
+  addresses: BTreeSet<IpAddr>,
+}
+
+impl InterfaceMonitor {
+  pub fn new() -> Self {
+    Self {
+      addresses: get_local_unicast_ip_addrs().into_iter().collect(),
+    }
+  }
+
+  // Returns the change since the previous call, if any.
+  pub fn check(&mut self) -> Option<AddressChange> {
+    self.update(get_local_unicast_ip_addrs().into_iter().collect())
+  }
+
+  fn update(&mut self, current: BTreeSet<IpAddr>) -> Option<AddressChange> {
+    if current == self.addresses {
+      return None;
+    }
+    let change = AddressChange {
+      added: current.difference(&self.addresses).copied().collect(),
+      removed: self.addresses.difference(&current).copied().collect(),
+    };
+    info!("Local IP addresses changed: {change:?}");
+    self.addresses = current;
+    Some(change)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::net::Ipv4Addr;
+
+  use super::*;
+
+  #[test]
+  fn address_change_is_reported_once() {
+    let a = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10));
+    let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 7));
+    let mut monitor = InterfaceMonitor {
+      addresses: BTreeSet::from([a]),
+    };
+
+    assert_eq!(monitor.update(BTreeSet::from([a])), None);
+    assert_eq!(
+      monitor.update(BTreeSet::from([b])),
+      Some(AddressChange {
+        added: vec![b],
+        removed: vec![a],
+      })
+    );
+    assert_eq!(monitor.update(BTreeSet::from([b])), None);
+    assert_eq!(
+      monitor.update(BTreeSet::new()),
+      Some(AddressChange {
+        added: vec![],
+        removed: vec![b],
+      })
+    );
+  }
+}
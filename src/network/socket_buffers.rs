@@ -0,0 +1,385 @@
+// OS buffer sizes and DSCP marking of the UDP sockets, and receive counters
+// of the listening sockets. See the documentation of ResourceSettings and
+// TrafficIsolationSettings.
+
+use std::{
+  fmt, io,
+  net::{IpAddr, Ipv4Addr, SocketAddr},
+  sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+#[cfg(unix)]
+use std::os::fd::{AsRawFd, BorrowedFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsRawSocket, BorrowedSocket};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use socket2::{SockRef, Socket};
+
+use crate::dds::{
+  resource_profile::{ResourceSettings, SocketBuffer, SocketStatistics},
+  statusevents::{DomainParticipantStatusEvent, StatusChannelSender},
+};
+
+// Buffer sizes requested for the sockets of a participant, and where to
+// report, if the OS gives less. The default requests nothing.
+#[derive(Clone, Default)]
+pub(crate) struct SocketBufferSizes {
+  receive: Option<usize>,
+  send: Option<usize>,
+  status_sender: Option<StatusChannelSender<DomainParticipantStatusEvent>>,
+}
+
+impl SocketBufferSizes {
+  pub fn new(
+    settings: &ResourceSettings,
+    status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
+  ) -> Self {
+    Self {
+      receive: settings.socket_receive_buffer_size,
+      send: settings.socket_send_buffer_size,
+      status_sender: Some(status_sender),
+    }
+  }
+
+  // Sets the size of a buffer of the socket, if it has been requested, and
+  // checks what the OS actually gave.
+  pub fn apply(&self, socket: &Socket, buffer: SocketBuffer) {
+    let requested = match buffer {
+      SocketBuffer::Receive => self.receive,
+      SocketBuffer::Send => self.send,
+    };
+    let Some(requested) = requested else {
+      return;
+    };
+    let result = match buffer {
+      SocketBuffer::Receive => socket.set_recv_buffer_size(requested),
+      SocketBuffer::Send => socket.set_send_buffer_size(requested),
+    }
+    .and_then(|()| effective_size(socket, buffer));
+    let local_address = local_address(socket);
+
+    match result {
+      Ok(effective) if effective >= requested => {
+        debug!("{buffer:?} buffer of {local_address} is {effective} bytes");
+      }
+      Ok(effective) => {
+        warn!(
+          "The OS limits the {buffer:?} buffer of {local_address} to {effective} bytes, \
+           {requested} requested"
+        );
+        if let Some(status_sender) = &self.status_sender {
+          let _ = status_sender.try_send(DomainParticipantStatusEvent::SocketBufferClamped {
+            local_address,
+            buffer,
+            requested,
+            effective,
+          });
+        }
+      }
+      Err(e) => warn!("Cannot set the {buffer:?} buffer size of {local_address}: {e}"),
+    }
+  }
+}
+
+impl fmt::Debug for SocketBufferSizes {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("SocketBufferSizes")
+      .field("receive", &self.receive)
+      .field("send", &self.send)
+      .finish_non_exhaustive()
+  }
+}
+
+// Size of a buffer of the socket. Linux doubles the requested size to leave
+// room for its own bookkeeping, and reports the doubled size, so it is halved
+// to be comparable to the requested one.
+pub(crate) fn effective_size(socket: &Socket, buffer: SocketBuffer) -> io::Result<usize> {
+  let reported = match buffer {
+    SocketBuffer::Receive => socket.recv_buffer_size()?,
+    SocketBuffer::Send => socket.send_buffer_size()?,
+  };
+  Ok(if cfg!(target_os = "linux") {
+    reported / 2
+  } else {
+    reported
+  })
+}
+
+// Marks the datagrams sent from the socket with the DSCP code point, which
+// takes the upper six bits of the IPv4 TOS or IPv6 Traffic Class field.
+pub(crate) fn set_dscp(socket: &Socket, dscp: u8) {
+  let traffic_class = u32::from(dscp) << 2;
+  let local_address = local_address(socket);
+  let result = if local_address.is_ipv6() {
+    set_traffic_class_v6(socket, traffic_class)
+  } else {
+    socket.set_tos(traffic_class)
+  };
+  match result {
+    Ok(()) => debug!("Datagrams from {local_address} have DSCP {dscp}"),
+    Err(e) => warn!("Cannot set DSCP {dscp} on {local_address}: {e}"),
+  }
+}
+
+#[cfg(target_os = "linux")]
+fn set_traffic_class_v6(socket: &Socket, traffic_class: u32) -> io::Result<()> {
+  use std::{mem, ptr};
+
+  let value = traffic_class as libc::c_int;
+  let result = unsafe {
+    libc::setsockopt(
+      socket.as_raw_fd(),
+      libc::IPPROTO_IPV6,
+      libc::IPV6_TCLASS,
+      ptr::addr_of!(value).cast(),
+      mem::size_of_val(&value) as libc::socklen_t,
+    )
+  };
+  if result == 0 {
+    Ok(())
+  } else {
+    Err(io::Error::last_os_error())
+  }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_traffic_class_v6(_socket: &Socket, _traffic_class: u32) -> io::Result<()> {
+  Err(io::Error::new(
+    io::ErrorKind::Unsupported,
+    "IPv6 traffic class is supported on Linux only",
+  ))
+}
+
+fn local_address(socket: &Socket) -> SocketAddr {
+  socket
+    .local_addr()
+    .ok()
+    .and_then(|address| address.as_socket())
+    .unwrap_or_else(|| SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0))
+}
+
+// Gives a socket2 view of a socket, for setting options that its own type
+// does not have methods for. The socket remains owned by `socket`.
+#[cfg(unix)]
+pub(crate) fn with_socket<S: AsRawFd, R>(socket: &S, f: impl FnOnce(&Socket) -> R) -> R {
+  // Safe, because the descriptor stays open as long as `socket` is borrowed.
+  let fd = unsafe { BorrowedFd::borrow_raw(socket.as_raw_fd()) };
+  f(&SockRef::from(&fd))
+}
+
+#[cfg(windows)]
+pub(crate) fn with_socket<S: AsRawSocket, R>(socket: &S, f: impl FnOnce(&Socket) -> R) -> R {
+  // Safe, because the socket stays open as long as `socket` is borrowed.
+  let raw = unsafe { BorrowedSocket::borrow_raw(socket.as_raw_socket()) };
+  f(&SockRef::from(&raw))
+}
+
+// Receive counters of a listening socket. The listener updates them in the
+// event loop thread, and the DomainParticipant reads them.
+#[derive(Debug)]
+pub(crate) struct SocketCounters {
+  local_address: SocketAddr,
+  multicast_group: Option<IpAddr>,
+  // Whether the OS reports dropped datagrams
+  counts_drops: bool,
+  receive_buffer_size: AtomicUsize,
+  received: AtomicU64,
+  dropped: AtomicU64,
+}
+
+impl SocketCounters {
+  // Also asks the OS to report dropped datagrams, where it can.
+  pub fn new(socket: &Socket, multicast_group: Option<IpAddr>) -> Self {
+    #[cfg(target_os = "linux")]
+    let counts_drops = drop_counter::enable(socket)
+      .map_err(|e| warn!("Cannot count dropped datagrams: {e}"))
+      .is_ok();
+    #[cfg(not(target_os = "linux"))]
+    let counts_drops = false;
+
+    let counters = Self {
+      local_address: local_address(socket),
+      multicast_group,
+      counts_drops,
+      receive_buffer_size: AtomicUsize::new(0),
+      received: AtomicU64::new(0),
+      dropped: AtomicU64::new(0),
+    };
+    counters.update_receive_buffer_size(socket);
+    counters
+  }
+
+  pub fn counts_drops(&self) -> bool {
+    self.counts_drops
+  }
+
+  pub fn update_receive_buffer_size(&self, socket: &Socket) {
+    match effective_size(socket, SocketBuffer::Receive) {
+      Ok(size) => self.receive_buffer_size.store(size, Ordering::Relaxed),
+      Err(e) => debug!("Cannot get receive buffer size: {e}"),
+    }
+  }
+
+  pub fn count_received(&self) {
+    self.received.fetch_add(1, Ordering::Relaxed);
+  }
+
+  // The OS reports the total count of drops
+  pub fn set_dropped(&self, dropped: u64) {
+    self.dropped.store(dropped, Ordering::Relaxed);
+  }
+
+  pub fn statistics(&self) -> SocketStatistics {
+    SocketStatistics {
+      local_address: self.local_address,
+      multicast_group: self.multicast_group,
+      receive_buffer_size: self.receive_buffer_size.load(Ordering::Relaxed),
+      received_datagrams: self.received.load(Ordering::Relaxed),
+      dropped_datagrams: self
+        .counts_drops
+        .then(|| self.dropped.load(Ordering::Relaxed)),
+    }
+  }
+}
+
+// Linux reports the count of datagrams dropped on a socket with each
+// received one, if the socket has the SO_RXQ_OVFL option.
+#[cfg(target_os = "linux")]
+pub(crate) mod drop_counter {
+  use std::{io, mem, os::fd::AsRawFd, ptr};
+
+  use socket2::Socket;
+
+  pub fn enable(socket: &Socket) -> io::Result<()> {
+    let on: libc::c_int = 1;
+    let result = unsafe {
+      libc::setsockopt(
+        socket.as_raw_fd(),
+        libc::SOL_SOCKET,
+        libc::SO_RXQ_OVFL,
+        ptr::addr_of!(on).cast(),
+        mem::size_of_val(&on) as libc::socklen_t,
+      )
+    };
+    if result == 0 {
+      Ok(())
+    } else {
+      Err(io::Error::last_os_error())
+    }
+  }
+
+  // Like recv, but returns also the total count of dropped datagrams, if the
+  // OS included it.
+  pub fn recv<S: AsRawFd>(socket: &S, buf: &mut [u8]) -> io::Result<(usize, Option<u32>)> {
+    // Room for the u32 count with its header. The u64 elements align it for
+    // the header.
+    let mut control = [0u64; 8];
+    let mut iov = libc::iovec {
+      iov_base: buf.as_mut_ptr().cast(),
+      iov_len: buf.len(),
+    };
+    // Zeroed, because the fields are different on different C libraries.
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = mem::size_of_val(&control) as _;
+
+    let nbytes = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, 0) };
+    if nbytes < 0 {
+      return Err(io::Error::last_os_error());
+    }
+
+    let mut dropped = None;
+    // Safe, because recvmsg has filled in the control messages, and
+    // msg_controllen tells how far they go.
+    unsafe {
+      let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+      while !cmsg.is_null() {
+        if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SO_RXQ_OVFL {
+          dropped = Some(ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast::<u32>()));
+        }
+        cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+      }
+    }
+    Ok((nbytes as usize, dropped))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::net::UdpSocket;
+
+  use super::*;
+  use crate::dds::statusevents::sync_status_channel;
+
+  // Other systems may refuse a size that they cannot give.
+  #[cfg(target_os = "linux")]
+  #[test]
+  fn clamped_buffer_is_reported() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let (status_sender, status_receiver) = sync_status_channel(4).unwrap();
+    let settings = ResourceSettings {
+      socket_receive_buffer_size: Some(64 * 1024),
+      // More than Linux gives without raising net.core.wmem_max
+      socket_send_buffer_size: Some(1 << 30),
+      ..ResourceSettings::default()
+    };
+    let sizes = SocketBufferSizes::new(&settings, status_sender);
+
+    with_socket(&socket, |socket| {
+      sizes.apply(socket, SocketBuffer::Receive);
+      assert!(effective_size(socket, SocketBuffer::Receive).unwrap() >= 64 * 1024);
+      sizes.apply(socket, SocketBuffer::Send);
+    });
+    match status_receiver.try_recv().ok() {
+      Some(DomainParticipantStatusEvent::SocketBufferClamped {
+        local_address,
+        buffer: SocketBuffer::Send,
+        requested,
+        effective,
+      }) => {
+        assert_eq!(local_address, socket.local_addr().unwrap());
+        assert_eq!(requested, 1 << 30);
+        assert!(effective < requested);
+      }
+      other => panic!("Unexpected status {other:?}"),
+    }
+  }
+
+  #[cfg(target_os = "linux")]
+  #[test]
+  fn dscp_is_set_in_tos() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    with_socket(&socket, |socket| {
+      set_dscp(socket, 46);
+      assert_eq!(socket.tos().unwrap(), 46 << 2);
+    });
+  }
+
+  #[cfg(target_os = "linux")]
+  #[test]
+  fn received_datagrams_are_counted() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let counters = with_socket(&receiver, |socket| SocketCounters::new(socket, None));
+    assert!(counters.counts_drops());
+
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    sender
+      .send_to(&[1, 2, 3], receiver.local_addr().unwrap())
+      .unwrap();
+    let mut buf = [0; 16];
+    let (nbytes, dropped) = drop_counter::recv(&receiver, &mut buf).unwrap();
+    counters.count_received();
+    assert_eq!(&buf[..nbytes], &[1, 2, 3]);
+    // The count is included once the socket has dropped something
+    counters.set_dropped(dropped.unwrap_or(0).into());
+
+    let statistics = counters.statistics();
+    assert_eq!(statistics.local_address, receiver.local_addr().unwrap());
+    assert_eq!(statistics.received_datagrams, 1);
+    assert_eq!(statistics.dropped_datagrams, Some(0));
+    assert!(statistics.receive_buffer_size > 0);
+  }
+}
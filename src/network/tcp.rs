@@ -0,0 +1,513 @@
+// RTPS over TCP, see crate::tcp_transport for the user's view.
+//
+// All TCP I/O happens in a thread of its own, so that connecting to an
+// unreachable server or a slow receiver does not stall the event loop. The
+// event loop hands messages to send over a channel, and gets the received
+// messages from another one.
+
+use std::{
+  collections::HashMap,
+  io::{self, Read, Write},
+  net::{Ipv4Addr, SocketAddr},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc, Arc,
+  },
+  thread,
+  time::Instant,
+};
+
+use bytes::{Buf, Bytes, BytesMut};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use mio_08::{
+  net::{TcpListener, TcpStream},
+  Events, Interest, Poll, Token, Waker,
+};
+use mio_extras::channel as mio_channel;
+
+use crate::{
+  dds::{discovery_network::InterfaceSelector, tcp_transport::TcpTransportSettings},
+  network::util::get_local_unicast_locators,
+  structure::locator::Locator,
+};
+
+const WAKER_TOKEN: Token = Token(0);
+const LISTENER_TOKEN: Token = Token(1);
+const FIRST_CONNECTION_TOKEN: usize = 2;
+
+// Each frame begins with the length of the message, big-endian.
+const FRAME_HEADER_LEN: usize = 4;
+// Messages that the event loop has not handed to the I/O thread yet
+const SEND_QUEUE_LEN: usize = 1024;
+// Received messages that the event loop has not processed yet
+const RECEIVE_QUEUE_LEN: usize = 256;
+// Unsent bytes per connection, beyond which further messages are dropped
+const MAX_UNSENT_BYTES: usize = 4 * 1024 * 1024;
+
+// Sends messages to TCP servers. Cheap to clone.
+#[derive(Clone, Debug)]
+pub(crate) struct TcpSender {
+  queue: mpsc::SyncSender<(SocketAddr, Bytes)>,
+  waker: Arc<Waker>,
+}
+
+impl TcpSender {
+  // Like UDP, sending does not block. If the I/O thread cannot keep up, the
+  // message is dropped.
+  pub fn send(&self, server: SocketAddr, message: &[u8]) {
+    match self
+      .queue
+      .try_send((server, Bytes::copy_from_slice(message)))
+    {
+      Ok(()) => {
+        if let Err(e) = self.waker.wake() {
+          error!("Cannot wake TCP transport: {e}");
+        }
+      }
+      Err(mpsc::TrySendError::Full(_)) => {
+        debug!("TCP send queue is full. Dropping message to {server}.");
+      }
+      Err(mpsc::TrySendError::Disconnected(_)) => {
+        error!("TCP transport has stopped. Cannot send to {server}.");
+      }
+    }
+  }
+}
+
+// The TCP server of a DomainParticipant, and its connections. Stops the I/O
+// thread when dropped. The thread is not joined, because it may be blocked
+// handing a message to the event loop that is dropping this.
+pub(crate) struct TcpTransport {
+  local_port: u16,
+  sender: TcpSender,
+  received: mio_channel::Receiver<Bytes>,
+  stop: Arc<AtomicBool>,
+}
+
+impl TcpTransport {
+  // Listens to `port`, or any free port, if it is 0.
+  pub fn start(port: u16, settings: &TcpTransportSettings) -> io::Result<Self> {
+    let poll = Poll::new()?;
+    let mut listener = TcpListener::bind(SocketAddr::new([0, 0, 0, 0].into(), port))?;
+    let local_port = listener.local_addr()?.port();
+    poll
+      .registry()
+      .register(&mut listener, LISTENER_TOKEN, Interest::READABLE)?;
+    let waker = Arc::new(Waker::new(poll.registry(), WAKER_TOKEN)?);
+
+    let (queue, outgoing) = mpsc::sync_channel(SEND_QUEUE_LEN);
+    let (received_sender, received) = mio_channel::sync_channel(RECEIVE_QUEUE_LEN);
+    let stop = Arc::new(AtomicBool::new(false));
+    let io_thread = IoThread {
+      poll,
+      listener,
+      connections: HashMap::new(),
+      servers: HashMap::new(),
+      failed: HashMap::new(),
+      next_token: FIRST_CONNECTION_TOKEN,
+      outgoing,
+      received: received_sender,
+      settings: settings.clone(),
+      stop: Arc::clone(&stop),
+    };
+    thread::Builder::new()
+      .name(format!("RustDDS TCP transport {local_port}"))
+      .spawn(move || io_thread.run())?;
+    info!("TCP transport listening to port {local_port}");
+
+    Ok(Self {
+      local_port,
+      sender: TcpSender { queue, waker },
+      received,
+      stop,
+    })
+  }
+
+  pub fn local_port(&self) -> u16 {
+    self.local_port
+  }
+
+  // Locators of the server on the allowed local interfaces
+  pub fn locators(&self, allowlist: &[InterfaceSelector]) -> Vec<Locator> {
+    tcp_locators(self.local_port, allowlist)
+  }
+
+  pub fn sender(&self) -> TcpSender {
+    self.sender.clone()
+  }
+
+  // Registered to the event loop poll
+  pub fn received(&self) -> &mio_channel::Receiver<Bytes> {
+    &self.received
+  }
+
+  pub fn take_received(&self) -> Vec<Bytes> {
+    let mut messages = Vec::new();
+    while let Ok(message) = self.received.try_recv() {
+      messages.push(message);
+    }
+    messages
+  }
+}
+
+impl Drop for TcpTransport {
+  fn drop(&mut self) {
+    self.stop.store(true, Ordering::Release);
+    if let Err(e) = self.sender.waker.wake() {
+      error!("Cannot wake TCP transport to stop it: {e}");
+    }
+  }
+}
+
+// TCP locators with `port` on the allowed local IPv4 interfaces. The server
+// listens to IPv4 only.
+pub(crate) fn tcp_locators(port: u16, allowlist: &[InterfaceSelector]) -> Vec<Locator> {
+  get_local_unicast_locators(
+    SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), port),
+    allowlist,
+  )
+  .into_iter()
+  .map(|locator| Locator::tcp(locator.into()))
+  .collect()
+}
+
+fn frame_header(message_len: usize) -> [u8; FRAME_HEADER_LEN] {
+  (message_len as u32).to_be_bytes()
+}
+
+// Splits the complete frames off the front of `buffer`. Fails, if a frame is
+// longer than `max_len`.
+fn take_frames(buffer: &mut BytesMut, max_len: usize) -> io::Result<Vec<Bytes>> {
+  let mut frames = Vec::new();
+  while buffer.len() >= FRAME_HEADER_LEN {
+    let mut header = [0; FRAME_HEADER_LEN];
+    header.copy_from_slice(&buffer[..FRAME_HEADER_LEN]);
+    let len = u32::from_be_bytes(header) as usize;
+    if len > max_len {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Received a message of {len} bytes. The maximum is {max_len}."),
+      ));
+    }
+    if buffer.len() < FRAME_HEADER_LEN + len {
+      break;
+    }
+    buffer.advance(FRAME_HEADER_LEN);
+    frames.push(buffer.split_to(len).freeze());
+  }
+  Ok(frames)
+}
+
+struct Connection {
+  stream: TcpStream,
+  // Address of the server, if this end connected to it
+  server: Option<SocketAddr>,
+  // Connecting has not finished yet
+  connecting: bool,
+  // Received bytes that do not make a complete frame yet
+  received: BytesMut,
+  unsent: BytesMut,
+}
+
+impl Connection {
+  fn new(stream: TcpStream, server: Option<SocketAddr>) -> Self {
+    Self {
+      stream,
+      connecting: server.is_some(),
+      server,
+      received: BytesMut::new(),
+      unsent: BytesMut::new(),
+    }
+  }
+
+  // Called when the stream is writable. Connecting has finished, if the peer
+  // address is known.
+  fn check_connected(&mut self) -> io::Result<()> {
+    if !self.connecting {
+      return Ok(());
+    }
+    if let Some(e) = self.stream.take_error()? {
+      return Err(e);
+    }
+    match self.stream.peer_addr() {
+      Ok(_) => {
+        self.connecting = false;
+        // Frames are small, and latency matters more than throughput.
+        self.stream.set_nodelay(true)?;
+        Ok(())
+      }
+      Err(e) if e.kind() == io::ErrorKind::NotConnected => Ok(()),
+      Err(e) => Err(e),
+    }
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    while !self.connecting && !self.unsent.is_empty() {
+      match self.stream.write(&self.unsent) {
+        Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+        Ok(n) => self.unsent.advance(n),
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+        Err(e) => return Err(e),
+      }
+    }
+    Ok(())
+  }
+
+  // Reads all that is available. The connection is closed by the peer, if
+  // the returned flag is false.
+  fn receive(&mut self, max_message_size: usize) -> io::Result<(Vec<Bytes>, bool)> {
+    let mut chunk = [0; 16 * 1024];
+    let mut open = true;
+    loop {
+      match self.stream.read(&mut chunk) {
+        Ok(0) => {
+          open = false;
+          break;
+        }
+        Ok(n) => self.received.extend_from_slice(&chunk[..n]),
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+        Err(e) => return Err(e),
+      }
+    }
+    Ok((take_frames(&mut self.received, max_message_size)?, open))
+  }
+}
+
+struct IoThread {
+  poll: Poll,
+  listener: TcpListener,
+  connections: HashMap<Token, Connection>,
+  // Connections that this end opened, by server address
+  servers: HashMap<SocketAddr, Token>,
+  // When connecting to a server last failed
+  failed: HashMap<SocketAddr, Instant>,
+  next_token: usize,
+  outgoing: mpsc::Receiver<(SocketAddr, Bytes)>,
+  received: mio_channel::SyncSender<Bytes>,
+  settings: TcpTransportSettings,
+  stop: Arc<AtomicBool>,
+}
+
+impl IoThread {
+  fn run(mut self) {
+    let mut events = Events::with_capacity(64);
+    while !self.stop.load(Ordering::Acquire) {
+      if let Err(e) = self.poll.poll(&mut events, None) {
+        if e.kind() == io::ErrorKind::Interrupted {
+          continue;
+        }
+        error!("TCP transport cannot poll: {e}. Stopping.");
+        return;
+      }
+      for event in events.iter() {
+        match event.token() {
+          WAKER_TOKEN => (), // the send queue is checked below
+          LISTENER_TOKEN => self.accept(),
+          token => {
+            if event.is_writable() {
+              self.on_writable(token);
+            }
+            if event.is_readable() || event.is_read_closed() {
+              self.on_readable(token);
+            }
+          }
+        }
+      }
+      while let Ok((server, message)) = self.outgoing.try_recv() {
+        self.send(server, &message);
+      }
+    }
+  }
+
+  fn register(&mut self, stream: TcpStream, server: Option<SocketAddr>) -> io::Result<Token> {
+    let token = Token(self.next_token);
+    self.next_token += 1;
+    let mut connection = Connection::new(stream, server);
+    self.poll.registry().register(
+      &mut connection.stream,
+      token,
+      Interest::READABLE | Interest::WRITABLE,
+    )?;
+    self.connections.insert(token, connection);
+    Ok(token)
+  }
+
+  fn accept(&mut self) {
+    loop {
+      match self.listener.accept() {
+        Ok((stream, address)) => {
+          debug!("TCP connection from {address}");
+          if let Err(e) = stream.set_nodelay(true) {
+            warn!("Cannot set TCP_NODELAY on connection from {address}: {e}");
+          }
+          if let Err(e) = self.register(stream, None) {
+            error!("Cannot register TCP connection from {address}: {e}");
+          }
+        }
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => return,
+        Err(e) => {
+          warn!("Cannot accept TCP connection: {e}");
+          return;
+        }
+      }
+    }
+  }
+
+  fn on_writable(&mut self, token: Token) {
+    let Some(connection) = self.connections.get_mut(&token) else {
+      return;
+    };
+    let was_connecting = connection.connecting;
+    let result = connection
+      .check_connected()
+      .and_then(|()| connection.flush());
+    match result {
+      Ok(()) => {
+        if was_connecting && !connection.connecting {
+          if let Some(server) = connection.server {
+            info!("TCP connection to {server} established");
+            self.failed.remove(&server);
+          }
+        }
+      }
+      Err(e) => self.close(token, &e),
+    }
+  }
+
+  fn on_readable(&mut self, token: Token) {
+    let Some(connection) = self.connections.get_mut(&token) else {
+      return;
+    };
+    match connection.receive(self.settings.max_message_size) {
+      Ok((messages, open)) => {
+        for message in messages {
+          // Blocks, if the event loop is busy. TCP flow control then slows
+          // down the sender.
+          if self.received.send(message).is_err() {
+            self.stop.store(true, Ordering::Release); // the event loop has stopped
+            return;
+          }
+        }
+        if !open {
+          self.close(token, &io::ErrorKind::UnexpectedEof.into());
+        }
+      }
+      Err(e) => self.close(token, &e),
+    }
+  }
+
+  fn send(&mut self, server: SocketAddr, message: &[u8]) {
+    let token = match self.servers.get(&server) {
+      Some(token) => *token,
+      None => match self.connect(server) {
+        Some(token) => token,
+        None => return,
+      },
+    };
+    let Some(connection) = self.connections.get_mut(&token) else {
+      return;
+    };
+    if connection.unsent.len() + FRAME_HEADER_LEN + message.len() > MAX_UNSENT_BYTES {
+      debug!("TCP connection to {server} is congested. Dropping message.");
+      return;
+    }
+    connection
+      .unsent
+      .extend_from_slice(&frame_header(message.len()));
+    connection.unsent.extend_from_slice(message);
+    if let Err(e) = connection.flush() {
+      self.close(token, &e);
+    }
+  }
+
+  fn connect(&mut self, server: SocketAddr) -> Option<Token> {
+    if let Some(failed_at) = self.failed.get(&server) {
+      if failed_at.elapsed() < self.settings.reconnect_interval {
+        trace!("Not connecting to {server} yet");
+        return None;
+      }
+    }
+    let result = TcpStream::connect(server).and_then(|stream| self.register(stream, Some(server)));
+    match result {
+      Ok(token) => {
+        debug!("Connecting to TCP server {server}");
+        self.servers.insert(server, token);
+        Some(token)
+      }
+      Err(e) => {
+        warn!("Cannot connect to TCP server {server}: {e}");
+        self.failed.insert(server, Instant::now());
+        None
+      }
+    }
+  }
+
+  fn close(&mut self, token: Token, reason: &io::Error) {
+    let Some(mut connection) = self.connections.remove(&token) else {
+      return;
+    };
+    if let Err(e) = self.poll.registry().deregister(&mut connection.stream) {
+      debug!("Cannot deregister TCP connection: {e}");
+    }
+    match connection.server {
+      Some(server) => {
+        info!("TCP connection to {server} closed: {reason}");
+        self.servers.remove(&server);
+        self.failed.insert(server, Instant::now());
+      }
+      None => debug!(
+        "TCP connection from {:?} closed: {reason}",
+        connection.stream.peer_addr()
+      ),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::*;
+
+  #[test]
+  fn frames_are_split_at_their_length() {
+    let mut buffer = BytesMut::new();
+    for message in [&b"first"[..], b"", b"third"] {
+      buffer.extend_from_slice(&frame_header(message.len()));
+      buffer.extend_from_slice(message);
+    }
+    buffer.extend_from_slice(&frame_header(10));
+    buffer.extend_from_slice(b"part");
+
+    let frames = take_frames(&mut buffer, 100).unwrap();
+    assert_eq!(frames, vec![&b"first"[..], b"", b"third"]);
+    // The incomplete frame is left in the buffer.
+    assert_eq!(buffer.len(), FRAME_HEADER_LEN + 4);
+
+    assert!(take_frames(&mut buffer, 9).is_err());
+  }
+
+  #[test]
+  fn messages_pass_between_transports() {
+    let settings = TcpTransportSettings::default();
+    let client = TcpTransport::start(0, &settings).unwrap();
+    let server = TcpTransport::start(0, &settings).unwrap();
+    let server_address = SocketAddr::new([127, 0, 0, 1].into(), server.local_port());
+
+    let messages = [vec![1, 2, 3], vec![], vec![4; 100_000]];
+    for message in &messages {
+      client.sender().send(server_address, message);
+    }
+
+    let mut received = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while received.len() < messages.len() && Instant::now() < deadline {
+      received.extend(server.take_received());
+      thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(received, messages);
+  }
+}
@@ -1,22 +1,30 @@
 use std::{
   io,
   net::{IpAddr, Ipv4Addr, SocketAddr},
+  sync::Arc,
 };
 
 use log::{debug, error, info, trace, warn};
 use socket2::{Domain, Protocol, SockAddr, Socket, Type};
 use bytes::{Bytes, BytesMut};
 
+#[cfg(target_os = "linux")]
+use crate::network::socket_buffers::drop_counter;
 use crate::{
-  network::util::{
-    get_local_multicast_ip_addrs, get_local_multicast_locators, get_local_unicast_locators,
+  dds::{discovery_network::InterfaceSelector, resource_profile::SocketBuffer},
+  network::{
+    socket_buffers::{with_socket, SocketBufferSizes, SocketCounters},
+    util::{
+      get_local_multicast_ip_addrs, get_local_multicast_locators,
+      get_local_multicast_v6_interfaces, get_local_unicast_locators,
+    },
   },
   serialization::padding_needed_for_alignment_4,
   structure::locator::Locator,
 };
 
 const MAX_MESSAGE_SIZE: usize = 64 * 1024; // This is max we can get from UDP.
-const MESSAGE_BUFFER_ALLOCATION_CHUNK: usize = 256 * 1024; // must be >= MAX_MESSAGE_SIZE
+const MESSAGE_BUFFER_ALLOCATION_CHUNK: usize = 256 * 1024; // default, must be >= MAX_MESSAGE_SIZE
 static_assertions::const_assert!(MESSAGE_BUFFER_ALLOCATION_CHUNK > MAX_MESSAGE_SIZE);
 
 /// Listens to messages coming to specified host port combination.
@@ -26,18 +34,67 @@ static_assertions::const_assert!(MESSAGE_BUFFER_ALLOCATION_CHUNK > MAX_MESSAGE_S
 pub struct UDPListener {
   socket: mio_06::net::UdpSocket,
   receive_buffer: BytesMut,
-  multicast_group: Option<Ipv4Addr>,
+  // Size of a new receive_buffer, when the previous one is full
+  buffer_allocation_chunk: usize,
+  multicast_group: Option<IpAddr>,
+  // Local interfaces, where multicast_group has been joined
+  multicast_interfaces: Vec<MulticastInterface>,
+  // Restricts multicast_interfaces, also when they are refreshed
+  interface_allowlist: Vec<InterfaceSelector>,
+  counters: Arc<SocketCounters>,
+}
+
+// IPv4 groups are joined on an interface by its address, IPv6 groups by its
+// index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MulticastInterface {
+  V4(Ipv4Addr),
+  V6(u32),
+}
+
+impl MulticastInterface {
+  // Local interfaces that can join `group`, and that the allowlist selects
+  fn all_for(group: IpAddr, allowlist: &[InterfaceSelector]) -> io::Result<Vec<Self>> {
+    Ok(match group {
+      IpAddr::V4(_) => get_local_multicast_ip_addrs(allowlist)?
+        .into_iter()
+        .filter_map(|a| match a {
+          IpAddr::V4(a) => Some(Self::V4(a)),
+          IpAddr::V6(_) => None,
+        })
+        .collect(),
+      IpAddr::V6(_) => get_local_multicast_v6_interfaces(allowlist)
+        .into_iter()
+        .map(Self::V6)
+        .collect(),
+    })
+  }
+
+  fn join(self, socket: &mio_06::net::UdpSocket, group: IpAddr) -> io::Result<()> {
+    match (group, self) {
+      (IpAddr::V4(group), Self::V4(address)) => socket.join_multicast_v4(&group, &address),
+      (IpAddr::V6(group), Self::V6(index)) => socket.join_multicast_v6(&group, index),
+      _ => Err(io::ErrorKind::InvalidInput.into()),
+    }
+  }
+
+  fn leave(self, socket: &mio_06::net::UdpSocket, group: IpAddr) -> io::Result<()> {
+    match (group, self) {
+      (IpAddr::V4(group), Self::V4(address)) => socket.leave_multicast_v4(&group, &address),
+      (IpAddr::V6(group), Self::V6(index)) => socket.leave_multicast_v6(&group, index),
+      _ => Err(io::ErrorKind::InvalidInput.into()),
+    }
+  }
 }
 
 impl Drop for UDPListener {
   fn drop(&mut self) {
     if let Some(mcg) = self.multicast_group {
-      self
-        .socket
-        .leave_multicast_v4(&mcg, &Ipv4Addr::UNSPECIFIED)
-        .unwrap_or_else(|e| {
+      for interface in &self.multicast_interfaces {
+        interface.leave(&self.socket, mcg).unwrap_or_else(|e| {
           error!("leave_multicast_group: {e:?}");
         });
+      }
     }
   }
 }
@@ -47,8 +104,25 @@ impl UDPListener {
     host: &str,
     port: u16,
     reuse_addr: bool,
-  ) -> io::Result<mio_06::net::UdpSocket> {
-    let raw_socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    multicast_group: Option<IpAddr>,
+  ) -> io::Result<(mio_06::net::UdpSocket, SocketCounters)> {
+    let address = SocketAddr::new(
+      host
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?,
+      port,
+    );
+    let raw_socket = Socket::new(
+      Domain::for_address(address),
+      Type::DGRAM,
+      Some(Protocol::UDP),
+    )?;
+
+    // An IPv6 socket does not receive IPv4, so that an IPv4 socket can have
+    // the same port.
+    if address.is_ipv6() {
+      raw_socket.set_only_v6(true)?;
+    }
 
     // We set ReuseAddr so that other DomainParticipants on this host can
     // bind to the same multicast address and port.
@@ -66,17 +140,11 @@ impl UDPListener {
       }
     }
 
-    let address = SocketAddr::new(
-      host
-        .parse()
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?,
-      port,
-    );
-
     if let Err(e) = raw_socket.bind(&SockAddr::from(address)) {
       info!("new_socket - cannot bind socket: {e:?}");
       return Err(e);
     }
+    let counters = SocketCounters::new(&raw_socket, multicast_group);
 
     let std_socket = std::net::UdpSocket::from(raw_socket);
     std_socket
@@ -90,29 +158,77 @@ impl UDPListener {
       mio_socket.local_addr()
     );
 
-    Ok(mio_socket)
+    Ok((mio_socket, counters))
   }
 
-  pub fn to_locator_address(&self) -> io::Result<Vec<Locator>> {
-    let local_port = self.socket.local_addr()?.port();
+  // The allowlist selects the interfaces, whose addresses are the locators of
+  // a unicast listener.
+  pub fn to_locator_address(&self, allowlist: &[InterfaceSelector]) -> io::Result<Vec<Locator>> {
+    let local_address = self.socket.local_addr()?;
 
     match self.multicast_group {
-      Some(_ipv4_addr) => Ok(get_local_multicast_locators(local_port)),
-      None => Ok(get_local_unicast_locators(local_port)),
+      Some(multicast_group) => Ok(get_local_multicast_locators(
+        multicast_group,
+        local_address.port(),
+      )),
+      None => Ok(get_local_unicast_locators(local_address, allowlist)),
     }
   }
 
+  // Address that the socket is bound to. For a unicast listener, it is
+  // reached by the locators of the allowed local interfaces of its IP
+  // version.
+  pub fn local_address(&self) -> io::Result<SocketAddr> {
+    self.socket.local_addr()
+  }
+
+  pub fn local_port(&self) -> io::Result<u16> {
+    Ok(self.socket.local_addr()?.port())
+  }
+
+  pub fn is_multicast(&self) -> bool {
+    self.multicast_group.is_some()
+  }
+
+  // Sizes below MAX_MESSAGE_SIZE are rounded up to it.
+  pub fn set_receive_buffer_size(&mut self, size: usize) {
+    self.buffer_allocation_chunk = size.max(MAX_MESSAGE_SIZE);
+    self.receive_buffer = BytesMut::with_capacity(self.buffer_allocation_chunk);
+  }
+
+  // Sets the OS receive buffer size, if it is configured.
+  pub fn set_socket_buffer_sizes(&self, sizes: &SocketBufferSizes) {
+    with_socket(&self.socket, |socket| {
+      sizes.apply(socket, SocketBuffer::Receive);
+      self.counters.update_receive_buffer_size(socket);
+    });
+  }
+
+  pub fn counters(&self) -> Arc<SocketCounters> {
+    Arc::clone(&self.counters)
+  }
+
   pub fn new_unicast(host: &str, port: u16) -> io::Result<Self> {
-    let mio_socket = Self::new_listening_socket(host, port, false)?;
+    let (mio_socket, counters) = Self::new_listening_socket(host, port, false, None)?;
 
     Ok(Self {
       socket: mio_socket,
       receive_buffer: BytesMut::with_capacity(MESSAGE_BUFFER_ALLOCATION_CHUNK),
+      buffer_allocation_chunk: MESSAGE_BUFFER_ALLOCATION_CHUNK,
       multicast_group: None,
+      multicast_interfaces: Vec::new(),
+      interface_allowlist: Vec::new(),
+      counters: Arc::new(counters),
     })
   }
 
-  pub fn new_multicast(host: &str, port: u16, multicast_group: Ipv4Addr) -> io::Result<Self> {
+  // The group is joined on the interfaces that the allowlist selects.
+  pub fn new_multicast(
+    host: &str,
+    port: u16,
+    multicast_group: IpAddr,
+    interface_allowlist: &[InterfaceSelector],
+  ) -> io::Result<Self> {
     if !multicast_group.is_multicast() {
       return io::Result::Err(io::Error::new(
         io::ErrorKind::Other,
@@ -120,37 +236,74 @@ impl UDPListener {
       ));
     }
 
-    let mio_socket = Self::new_listening_socket(host, port, true)?;
-
-    for multicast_if_ipaddr in get_local_multicast_ip_addrs()? {
-      match multicast_if_ipaddr {
-        IpAddr::V4(a) => mio_socket
-          .join_multicast_v4(&multicast_group, &a)
-          .unwrap_or_else(|e| {
-            warn!(
-              "join_multicast_v4 failed: {:?}. multicast_group [{:?}] interface [{:?}]",
-              e, multicast_group, a
-            );
-          }),
-
-        IpAddr::V6(addr) => {
-          if let Err(e) = mio_socket.join_multicast_v6(&addr, 0) {
-            warn!(
-              "join_multicast_v6 failed. err: {e}. mcast group: [{multicast_group:?}], \
-               addr:[{addr:?}]"
-            );
-          }
-        }
+    let (mio_socket, counters) =
+      Self::new_listening_socket(host, port, true, Some(multicast_group))?;
+
+    let mut multicast_interfaces = Vec::new();
+    for interface in MulticastInterface::all_for(multicast_group, interface_allowlist)? {
+      match interface.join(&mio_socket, multicast_group) {
+        Ok(()) => multicast_interfaces.push(interface),
+        Err(e) => warn!(
+          "Joining multicast group failed: {e:?}. multicast_group [{multicast_group:?}] \
+           interface [{interface:?}]"
+        ),
       }
     }
 
     Ok(Self {
       socket: mio_socket,
       receive_buffer: BytesMut::with_capacity(MESSAGE_BUFFER_ALLOCATION_CHUNK),
+      buffer_allocation_chunk: MESSAGE_BUFFER_ALLOCATION_CHUNK,
       multicast_group: Some(multicast_group),
+      multicast_interfaces,
+      interface_allowlist: interface_allowlist.to_vec(),
+      counters: Arc::new(counters),
     })
   }
 
+  // Join the multicast group on allowed interfaces that have appeared since
+  // the group was joined, and leave it on those that are gone. The socket
+  // itself is bound to the unspecified address, so it need not be recreated.
+  pub fn refresh_multicast_membership(&mut self) {
+    let multicast_group = match self.multicast_group {
+      Some(g) => g,
+      None => return, // unicast listener
+    };
+    let current = match MulticastInterface::all_for(multicast_group, &self.interface_allowlist) {
+      Ok(interfaces) => interfaces,
+      Err(e) => {
+        error!("Cannot list multicast interfaces: {e:?}");
+        return;
+      }
+    };
+    for gone in self
+      .multicast_interfaces
+      .iter()
+      .filter(|a| !current.contains(a))
+    {
+      // The OS may have already dropped the membership with the address.
+      gone
+        .leave(&self.socket, multicast_group)
+        .unwrap_or_else(|e| debug!("Leaving multicast group on {gone:?}: {e:?}"));
+    }
+    self.multicast_interfaces.retain(|a| current.contains(a));
+    for new in current {
+      if self.multicast_interfaces.contains(&new) {
+        continue;
+      }
+      match new.join(&self.socket, multicast_group) {
+        Ok(()) => {
+          info!("Joined multicast group {multicast_group:?} on interface {new:?}");
+          self.multicast_interfaces.push(new);
+        }
+        Err(e) => warn!(
+          "Joining multicast group failed: {e:?}. multicast_group [{multicast_group:?}] \
+           interface [{new:?}]"
+        ),
+      }
+    }
+  }
+
   pub fn mio_socket(&mut self) -> &mut mio_06::net::UdpSocket {
     &mut self.socket
   }
@@ -204,7 +357,7 @@ impl UDPListener {
 
       // Ensure that receive buffer has enough capacity for a message
       if self.receive_buffer.capacity() < MAX_MESSAGE_SIZE {
-        self.receive_buffer = BytesMut::with_capacity(MESSAGE_BUFFER_ALLOCATION_CHUNK);
+        self.receive_buffer = BytesMut::with_capacity(self.buffer_allocation_chunk);
         debug!("ensure_receive_buffer_capacity - reallocated receive_buffer");
       }
       unsafe {
@@ -218,7 +371,7 @@ impl UDPListener {
         "ensure_receive_buffer_capacity - {} bytes left",
         self.receive_buffer.capacity()
       );
-      let nbytes = match self.socket.recv(&mut self.receive_buffer) {
+      let nbytes = match self.recv() {
         Ok(n) => n,
         Err(e) => {
           self.receive_buffer.clear(); // since nothing was received
@@ -255,6 +408,25 @@ impl UDPListener {
     // Answer: https://github.com/rust-lang/rust/issues/46500
   }
 
+  // Receives one datagram into receive_buffer, and counts it.
+  fn recv(&mut self) -> io::Result<usize> {
+    #[cfg(target_os = "linux")]
+    let nbytes = if self.counters.counts_drops() {
+      let (nbytes, dropped) = drop_counter::recv(&self.socket, &mut self.receive_buffer)?;
+      if let Some(dropped) = dropped {
+        self.counters.set_dropped(dropped.into());
+      }
+      nbytes
+    } else {
+      self.socket.recv(&mut self.receive_buffer)?
+    };
+    #[cfg(not(target_os = "linux"))]
+    let nbytes = self.socket.recv(&mut self.receive_buffer)?;
+
+    self.counters.count_received();
+    Ok(nbytes)
+  }
+
   #[cfg(test)] // normally done in .drop()
   pub fn leave_multicast(&self, address: &Ipv4Addr) -> io::Result<()> {
     if address.is_multicast() {
@@ -277,7 +449,7 @@ mod tests {
   use std::{thread, time};
 
   use super::*;
-  use crate::network::udp_sender::*;
+  use crate::{dds::discovery_network::IpStack, network::udp_sender::*};
 
   #[test]
   fn udpl_single_address() {
@@ -298,7 +470,8 @@ mod tests {
   #[test]
   fn udpl_multicast_address() {
     let listener =
-      UDPListener::new_multicast("0.0.0.0", 10002, Ipv4Addr::new(239, 255, 0, 1)).unwrap();
+      UDPListener::new_multicast("0.0.0.0", 10002, Ipv4Addr::new(239, 255, 0, 1).into(), &[])
+        .unwrap();
     let sender = UDPSender::new_with_random_port().unwrap();
 
     // setsockopt(sender.socket.as_raw_fd(), IpMulticastLoop, &true)
@@ -321,4 +494,35 @@ mod tests {
     assert_eq!(rec_data.len(), 3);
     assert_eq!(rec_data, data);
   }
+
+  #[test]
+  fn udpl_ipv6_beside_ipv4() {
+    // The host may have no IPv6 at all
+    let Ok(listener_v6) = UDPListener::new_unicast("::1", 10003) else {
+      return;
+    };
+    let listener_v4 = UDPListener::new_unicast("127.0.0.1", 10003).unwrap();
+    let sender = UDPSender::with_multicast_options(
+      0,
+      MulticastOptions {
+        enabled: false,
+        ..MulticastOptions::default()
+      },
+      IpStack::DualStack { prefer_v6: true },
+      SenderSocketSettings::default(),
+    )
+    .unwrap();
+
+    let locator_v6 = Locator::from(SocketAddr::new("::1".parse().unwrap(), 10003));
+    sender.send_to_locator(&[6], &locator_v6);
+    let locator_v4 = Locator::from(SocketAddr::new("127.0.0.1".parse().unwrap(), 10003));
+    sender.send_to_locator(&[4], &locator_v4);
+
+    assert_eq!(listener_v6.get_message(), vec![6]);
+    assert_eq!(listener_v4.get_message(), vec![4]);
+    assert_eq!(
+      listener_v6.to_locator_address(&[]).unwrap(),
+      vec![locator_v6]
+    );
+  }
 }
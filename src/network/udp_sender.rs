@@ -1,43 +1,172 @@
 use std::{
+  cell::RefCell,
+  collections::BTreeMap,
   io,
-  net::{IpAddr, SocketAddr, UdpSocket},
+  net::{IpAddr, Ipv6Addr, SocketAddr, UdpSocket},
 };
 #[cfg(test)]
 use std::net::Ipv4Addr;
 
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
-use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use socket2::{Domain, Protocol, SockAddr, SockRef, Socket, Type};
 #[cfg(windows)]
 use local_ip_address::list_afinet_netifas;
 
-use crate::{network::util::get_local_multicast_ip_addrs, structure::locator::Locator};
+use crate::{
+  dds::{
+    discovery_network::{InterfaceSelector, IpStack},
+    resource_profile::SocketBuffer,
+  },
+  network::{
+    socket_buffers::{set_dscp, SocketBufferSizes},
+    tcp::TcpSender,
+    util::{get_local_multicast_ip_addrs, get_local_multicast_v6_interfaces},
+  },
+  structure::locator::Locator,
+};
+
+// How multicast datagrams are sent
+#[derive(Debug, Clone, Copy)]
+pub struct MulticastOptions {
+  // If false, there are no multicast sockets, and nothing is sent to
+  // multicast addresses.
+  pub enabled: bool,
+  pub ttl: u32,
+  pub loopback: bool,
+}
+
+impl Default for MulticastOptions {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      ttl: 1,
+      loopback: true,
+    }
+  }
+}
+
+// How the sockets of a UDPSender are set up. Metatraffic and user traffic
+// may have senders of their own, set up differently.
+#[derive(Debug, Clone, Default)]
+pub struct SenderSocketSettings {
+  // Interfaces that multicast sockets are created for
+  pub interface_allowlist: Vec<InterfaceSelector>,
+  pub buffer_sizes: SocketBufferSizes,
+  // DSCP code point of the sent datagrams
+  pub dscp: Option<u8>,
+}
+
+impl SenderSocketSettings {
+  fn apply(&self, socket: &Socket) {
+    self.buffer_sizes.apply(socket, SocketBuffer::Send);
+    if let Some(dscp) = self.dscp {
+      set_dscp(socket, dscp);
+    }
+  }
+}
+
+// Datagrams held back between begin_batch and end_batch, in sending order.
+type Batch = Vec<(Locator, Vec<u8>)>;
 
 // We need one multicast sender socket per interface
 
 #[derive(Debug)]
 pub struct UDPSender {
   unicast_socket: mio_08::net::UdpSocket,
-  multicast_sockets: Vec<mio_08::net::UdpSocket>,
+  // Present, if the participant uses IPv6
+  unicast_socket_v6: Option<mio_08::net::UdpSocket>,
+  multicast_options: MulticastOptions,
+  ip_stack: IpStack,
+  // Replaced when local network interfaces change. The sender is shared
+  // through Rc within the event loop thread, so a RefCell is enough.
+  multicast_sockets: RefCell<Vec<mio_08::net::UdpSocket>>,
+  multicast_sockets_v6: RefCell<Vec<mio_08::net::UdpSocket>>,
+  // Applied also to the recreated multicast sockets
+  socket_settings: SenderSocketSettings,
+  batch: RefCell<Option<Batch>>,
+  // Messages to TCP locators are handed to this, if TCP is in use.
+  tcp_sender: Option<TcpSender>,
 }
 
 impl UDPSender {
+  #[cfg(test)]
   pub fn new(sender_port: u16) -> io::Result<Self> {
+    Self::with_multicast_options(
+      sender_port,
+      MulticastOptions::default(),
+      IpStack::default(),
+      SenderSocketSettings::default(),
+    )
+  }
+
+  pub fn with_multicast_options(
+    sender_port: u16,
+    multicast_options: MulticastOptions,
+    ip_stack: IpStack,
+    socket_settings: SenderSocketSettings,
+  ) -> io::Result<Self> {
     let unicast_socket = {
       let saddr: SocketAddr = SocketAddr::new("0.0.0.0".parse().unwrap(), sender_port);
-      mio_08::net::UdpSocket::bind(saddr)?
+      let socket = UdpSocket::bind(saddr)?;
+      socket.set_nonblocking(true)?;
+      socket_settings.apply(&SockRef::from(&socket));
+      mio_08::net::UdpSocket::from_std(socket)
     };
 
     // We set multicasting loop on so that we can hear other DomainParticipant
     // instances running on the same host.
     unicast_socket
-      .set_multicast_loop_v4(true)
+      .set_multicast_loop_v4(multicast_options.loopback)
       .unwrap_or_else(|e| {
         error!("Cannot set multicast loop on: {e:?}");
       });
 
+    let unicast_socket_v6 = if ip_stack.uses_v6() {
+      let saddr = SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), sender_port);
+      let socket = UdpSocket::bind(saddr)?;
+      socket.set_nonblocking(true)?;
+      socket_settings.apply(&SockRef::from(&socket));
+      Some(mio_08::net::UdpSocket::from_std(socket))
+    } else {
+      None
+    };
+
+    let multicast_sockets = if ip_stack.uses_v4() {
+      Self::new_multicast_sockets(multicast_options, &socket_settings)?
+    } else {
+      Vec::new()
+    };
+    let multicast_sockets_v6 = if ip_stack.uses_v6() {
+      Self::new_multicast_sockets_v6(multicast_options, &socket_settings)?
+    } else {
+      Vec::new()
+    };
+
+    let sender = Self {
+      unicast_socket,
+      unicast_socket_v6,
+      multicast_options,
+      ip_stack,
+      multicast_sockets: RefCell::new(multicast_sockets),
+      multicast_sockets_v6: RefCell::new(multicast_sockets_v6),
+      socket_settings,
+      batch: RefCell::new(None),
+      tcp_sender: None,
+    };
+    info!("UDPSender::new() --> {:?}", sender);
+    Ok(sender)
+  }
+
+  fn new_multicast_sockets(
+    options: MulticastOptions,
+    socket_settings: &SenderSocketSettings,
+  ) -> io::Result<Vec<mio_08::net::UdpSocket>> {
     let mut multicast_sockets = Vec::with_capacity(1);
-    for multicast_if_ipaddr in get_local_multicast_ip_addrs()? {
+    if !options.enabled {
+      return Ok(multicast_sockets);
+    }
+    for multicast_if_ipaddr in get_local_multicast_ip_addrs(&socket_settings.interface_allowlist)? {
       // beef: specify output interface
       trace!(
         "UDPSender: Multicast sender on interface {:?}",
@@ -59,12 +188,20 @@ impl UDPSender {
 
           // bind to the multicast interface
           raw_socket.bind(&SockAddr::from(SocketAddr::new(multicast_if_ipaddr, 0)))?;
+          socket_settings.apply(&raw_socket);
 
           // make multicast sock
           let mc_socket = UdpSocket::from(raw_socket);
-          mc_socket.set_multicast_loop_v4(true).unwrap_or_else(|e| {
-            error!("Cannot set IPv4 multicast loop. err: {e}");
-          });
+          mc_socket
+            .set_multicast_loop_v4(options.loopback)
+            .unwrap_or_else(|e| {
+              error!("Cannot set IPv4 multicast loop. err: {e}");
+            });
+          mc_socket
+            .set_multicast_ttl_v4(options.ttl)
+            .unwrap_or_else(|e| {
+              error!("Cannot set IPv4 multicast TTL. err: {e}");
+            });
           mc_socket
         }
 
@@ -75,12 +212,20 @@ impl UDPSender {
           // note: you don't need to use set_multicast_if for ipv6 multicast.
           // it comes for free!
           raw_socket.bind(&SocketAddr::new(addr.into(), 0).into())?;
+          socket_settings.apply(&raw_socket);
+          raw_socket
+            .set_multicast_hops_v6(options.ttl)
+            .unwrap_or_else(|e| {
+              error!("Cannot set IPv6 multicast hops. err: {e}");
+            });
 
           // make multicast sock
           let mc_socket = UdpSocket::from(raw_socket);
-          mc_socket.set_multicast_loop_v6(true).unwrap_or_else(|e| {
-            error!("Cannot set IPv6 multicast loop. err: {e}");
-          });
+          mc_socket
+            .set_multicast_loop_v6(options.loopback)
+            .unwrap_or_else(|e| {
+              error!("Cannot set IPv6 multicast loop. err: {e}");
+            });
 
           mc_socket
         }
@@ -89,12 +234,106 @@ impl UDPSender {
       multicast_sockets.push(mio_08::net::UdpSocket::from_std(mc_socket));
     } // end for
 
-    let sender = Self {
-      unicast_socket,
-      multicast_sockets,
-    };
-    info!("UDPSender::new() --> {:?}", sender);
-    Ok(sender)
+    Ok(multicast_sockets)
+  }
+
+  // IPv6 multicast sockets are selected by interface index, as link-local
+  // interface addresses cannot be bound to without one.
+  fn new_multicast_sockets_v6(
+    options: MulticastOptions,
+    socket_settings: &SenderSocketSettings,
+  ) -> io::Result<Vec<mio_08::net::UdpSocket>> {
+    let mut multicast_sockets = Vec::with_capacity(1);
+    if !options.enabled {
+      return Ok(multicast_sockets);
+    }
+    for interface in get_local_multicast_v6_interfaces(&socket_settings.interface_allowlist) {
+      trace!("UDPSender: IPv6 multicast sender on interface {interface}");
+      let raw_socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+      raw_socket.set_only_v6(true)?;
+      raw_socket.set_multicast_if_v6(interface)?;
+      raw_socket.bind(&SockAddr::from(SocketAddr::new(
+        Ipv6Addr::UNSPECIFIED.into(),
+        0,
+      )))?;
+      socket_settings.apply(&raw_socket);
+      raw_socket
+        .set_multicast_hops_v6(options.ttl)
+        .unwrap_or_else(|e| {
+          error!("Cannot set IPv6 multicast hops. err: {e}");
+        });
+      raw_socket
+        .set_multicast_loop_v6(options.loopback)
+        .unwrap_or_else(|e| {
+          error!("Cannot set IPv6 multicast loop. err: {e}");
+        });
+      multicast_sockets.push(mio_08::net::UdpSocket::from_std(UdpSocket::from(
+        raw_socket,
+      )));
+    }
+    Ok(multicast_sockets)
+  }
+
+  // Multicast sockets are bound to interface addresses, so they must be
+  // recreated when the addresses change. On failure, the old sockets are kept.
+  pub fn refresh_multicast_sockets(&self) {
+    if self.ip_stack.uses_v4() {
+      match Self::new_multicast_sockets(self.multicast_options, &self.socket_settings) {
+        Ok(sockets) => {
+          info!("UDPSender: {} multicast sender sockets", sockets.len());
+          *self.multicast_sockets.borrow_mut() = sockets;
+        }
+        Err(e) => error!("Cannot recreate multicast sender sockets: {e:?}"),
+      }
+    }
+    if self.ip_stack.uses_v6() {
+      match Self::new_multicast_sockets_v6(self.multicast_options, &self.socket_settings) {
+        Ok(sockets) => {
+          info!("UDPSender: {} IPv6 multicast sender sockets", sockets.len());
+          *self.multicast_sockets_v6.borrow_mut() = sockets;
+        }
+        Err(e) => error!("Cannot recreate IPv6 multicast sender sockets: {e:?}"),
+      }
+    }
+  }
+
+  pub fn ip_stack(&self) -> IpStack {
+    self.ip_stack
+  }
+
+  pub fn set_tcp_sender(&mut self, tcp_sender: TcpSender) {
+    self.tcp_sender = Some(tcp_sender);
+  }
+
+  pub fn multicast_enabled(&self) -> bool {
+    self.multicast_options.enabled
+  }
+
+  // Until end_batch, datagrams are collected instead of sent.
+  pub fn begin_batch(&self) {
+    *self.batch.borrow_mut() = Some(Vec::new());
+  }
+
+  pub fn is_batching(&self) -> bool {
+    self.batch.borrow().is_some()
+  }
+
+  // Sends the datagrams collected since begin_batch. `combine` gets the
+  // datagrams to each destination in sending order, and may merge them.
+  pub fn end_batch<F>(&self, combine: F)
+  where
+    F: Fn(Vec<Vec<u8>>) -> Vec<Vec<u8>>,
+  {
+    let batch = self.batch.borrow_mut().take().unwrap_or_default();
+    let mut by_locator: BTreeMap<Locator, Vec<Vec<u8>>> = BTreeMap::new();
+    for (locator, datagram) in batch {
+      by_locator.entry(locator).or_default().push(datagram);
+    }
+    for (locator, datagrams) in by_locator {
+      for datagram in combine(datagrams) {
+        self.send_to_locator(&datagram, &locator);
+      }
+    }
   }
 
   #[cfg(test)]
@@ -132,22 +371,39 @@ impl UDPSender {
   }
 
   pub fn send_to_locator(&self, buffer: &[u8], locator: &Locator) {
+    if let Some(batch) = self.batch.borrow_mut().as_mut() {
+      batch.push((*locator, buffer.to_vec()));
+      return;
+    }
     if buffer.len() > 1500 {
       warn!("send_to_locator: Message size = {}", buffer.len());
     }
     let send = |socket_address: SocketAddr| {
+      let multicast_sockets = if socket_address.is_ipv4() {
+        &self.multicast_sockets
+      } else {
+        &self.multicast_sockets_v6
+      };
       if socket_address.ip().is_multicast() {
-        for socket in &self.multicast_sockets {
+        for socket in multicast_sockets.borrow().iter() {
           self.send_to_udp_socket(buffer, socket, &socket_address);
         }
-      } else {
+      } else if socket_address.is_ipv4() {
         self.send_to_udp_socket(buffer, &self.unicast_socket, &socket_address);
+      } else if let Some(socket) = &self.unicast_socket_v6 {
+        self.send_to_udp_socket(buffer, socket, &socket_address);
+      } else {
+        trace!("send_to_locator: IPv6 is not in use, cannot send to {socket_address}");
       }
     };
 
     match locator {
       Locator::UdpV4(socket_address) => send(SocketAddr::from(*socket_address)),
       Locator::UdpV6(socket_address) => send(SocketAddr::from(*socket_address)),
+      Locator::TcpV4(_) | Locator::TcpV6(_) => match &self.tcp_sender {
+        Some(tcp_sender) => tcp_sender.send(SocketAddr::from(*locator), buffer),
+        None => trace!("send_to_locator: TCP is not in use, cannot send to {locator:?}"),
+      },
       Locator::Invalid | Locator::Reserved => {
         error!("send_to_locator: Cannot send to {:?}", locator);
       }
@@ -187,7 +443,7 @@ impl UDPSender {
     if address.is_multicast() {
       let address = SocketAddr::new(IpAddr::V4(address), port);
       let mut size = 0;
-      for s in self.multicast_sockets {
+      for s in self.multicast_sockets.into_inner() {
         size = s.send_to(buffer, address)?;
       }
       Ok(size)
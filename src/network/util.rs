@@ -1,24 +1,62 @@
 use std::{
   io,
-  net::{IpAddr, SocketAddr},
+  net::{IpAddr, Ipv6Addr, SocketAddr},
 };
 
 use log::error;
 use pnet::datalink::NetworkInterface;
 
-use crate::structure::locator::Locator;
+use crate::{
+  dds::discovery_network::{interface_allowed, InterfaceSelector},
+  structure::locator::Locator,
+};
 
-pub fn get_local_multicast_locators(port: u16) -> Vec<Locator> {
-  let saddr = SocketAddr::new("239.255.0.1".parse().unwrap(), port);
+pub fn get_local_multicast_locators(multicast_group: IpAddr, port: u16) -> Vec<Locator> {
+  let saddr = SocketAddr::new(multicast_group, port);
   vec![Locator::from(saddr)]
 }
 
-pub fn get_local_unicast_locators(port: u16) -> Vec<Locator> {
+/// Locators, by which a socket bound to `local_address` can be reached. A
+/// socket bound to the unspecified address is reached by the addresses of
+/// the local interfaces of the same IP version that the allowlist selects.
+/// Link-local IPv6 addresses are left out, because a locator cannot tell
+/// their interface.
+pub fn get_local_unicast_locators(
+  local_address: SocketAddr,
+  allowlist: &[InterfaceSelector],
+) -> Vec<Locator> {
+  if !local_address.ip().is_unspecified() {
+    return vec![Locator::from(local_address)];
+  }
+  get_allowed_unicast_ip_addrs(allowlist)
+    .into_iter()
+    .filter(|ip| match ip {
+      IpAddr::V4(_) => local_address.is_ipv4(),
+      IpAddr::V6(ip) => local_address.is_ipv6() && !is_unicast_link_local_v6(ip),
+    })
+    .map(|ip| Locator::from(SocketAddr::new(ip, local_address.port())))
+    .collect()
+}
+
+// fe80::/10. Ipv6Addr::is_unicast_link_local is not stable yet.
+fn is_unicast_link_local_v6(ip: &Ipv6Addr) -> bool {
+  ip.segments()[0] & 0xffc0 == 0xfe80
+}
+
+/// Addresses of all local non-loopback interfaces.
+pub fn get_local_unicast_ip_addrs() -> Vec<IpAddr> {
+  get_allowed_unicast_ip_addrs(&[])
+}
+
+/// Addresses of the local non-loopback interfaces that the allowlist
+/// selects.
+pub fn get_allowed_unicast_ip_addrs(allowlist: &[InterfaceSelector]) -> Vec<IpAddr> {
   match if_addrs::get_if_addrs() {
     Ok(ifaces) => ifaces
       .iter()
       .filter(|ip| !ip.is_loopback())
-      .map(|ip| Locator::from(SocketAddr::new(ip.ip(), port)))
+      .filter(|ip| interface_allowed(allowlist, &ip.name, ip.ip()))
+      .map(|ip| ip.ip())
       .collect(),
     Err(e) => {
       error!(
@@ -30,10 +68,11 @@ pub fn get_local_unicast_locators(port: u16) -> Vec<Locator> {
   }
 }
 
-/// Enumerates local interfaces that we may use for multicasting.
+/// Enumerates local interfaces that we may use for multicasting, and that the
+/// allowlist selects.
 ///
 /// The result of this function is used to set up senders and listeners.
-pub fn get_local_multicast_ip_addrs() -> io::Result<Vec<IpAddr>> {
+pub fn get_local_multicast_ip_addrs(allowlist: &[InterfaceSelector]) -> io::Result<Vec<IpAddr>> {
   // grab a list of system intefaces.
   //
   // note: `pnet` works on mac, windows, and linux, potentially alongside
@@ -41,27 +80,61 @@ pub fn get_local_multicast_ip_addrs() -> io::Result<Vec<IpAddr>> {
   let interfaces = pnet::datalink::interfaces();
 
   // grab all the ips from each interface
-  Ok(get_local_multicast_ip_addrs_inner(interfaces))
+  Ok(get_local_multicast_ip_addrs_inner(interfaces, allowlist))
 }
 
 /// Inner implementation of [`get_local_multicast_ip_addrs`], for testing
 /// purposes.
-fn get_local_multicast_ip_addrs_inner(interfaces: Vec<NetworkInterface>) -> Vec<IpAddr> {
+fn get_local_multicast_ip_addrs_inner(
+  interfaces: Vec<NetworkInterface>,
+  allowlist: &[InterfaceSelector],
+) -> Vec<IpAddr> {
   interfaces
     .into_iter()
     .filter(|ifaddr| !ifaddr.is_loopback()) // don't use lo interface
     .filter(|ifaddr| ifaddr.is_multicast()) // require support for multicast
-    .flat_map(|ifaddr| ifaddr.ips)
-    .map(|ip_net| ip_net.ip()) // get the ip from each ip network
-    .filter(|ip| ip.is_ipv4()) // use ipv4 only :(
+    .flat_map(|ifaddr| {
+      ifaddr
+        .ips
+        .iter()
+        .map(|ip_net| ip_net.ip()) // get the ip from each ip network
+        .filter(|ip| interface_allowed(allowlist, &ifaddr.name, *ip))
+        .collect::<Vec<_>>()
+    })
+    .filter(|ip| ip.is_ipv4()) // IPv6 multicast is by interface index
     .collect::<Vec<_>>()
 }
 
+/// Indices of local interfaces that we may use for IPv6 multicasting, and
+/// that the allowlist selects by their name or an IPv6 address.
+pub fn get_local_multicast_v6_interfaces(allowlist: &[InterfaceSelector]) -> Vec<u32> {
+  get_local_multicast_v6_interfaces_inner(pnet::datalink::interfaces(), allowlist)
+}
+
+fn get_local_multicast_v6_interfaces_inner(
+  interfaces: Vec<NetworkInterface>,
+  allowlist: &[InterfaceSelector],
+) -> Vec<u32> {
+  interfaces
+    .into_iter()
+    .filter(|ifaddr| !ifaddr.is_loopback())
+    .filter(|ifaddr| ifaddr.is_multicast())
+    .filter(|ifaddr| {
+      ifaddr
+        .ips
+        .iter()
+        .filter(|ip_net| ip_net.is_ipv6())
+        .any(|ip_net| interface_allowed(allowlist, &ifaddr.name, ip_net.ip()))
+    })
+    .map(|ifaddr| ifaddr.index)
+    .collect()
+}
+
 #[cfg(test)]
 mod tests {
   use std::{
     ffi::c_int,
-    net::{Ipv4Addr, Ipv6Addr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
   };
 
   use pnet::{
@@ -69,6 +142,8 @@ mod tests {
     ipnetwork::{IpNetwork, Ipv4Network, Ipv6Network},
   };
 
+  use crate::structure::locator::Locator;
+
   /// Mocks the `get_local_multicast_ip_addrs` function.
   #[test]
   fn test_get_local_multicast_ip_addrs() {
@@ -86,16 +161,97 @@ mod tests {
 
     let interfaces = vec![loopback(), eth0];
 
-    let ips = super::get_local_multicast_ip_addrs_inner(interfaces);
+    let ips = super::get_local_multicast_ip_addrs_inner(interfaces, &[]);
 
-    // TODO: uncomment if IPv6 becomes supported :(
-    // assert_eq!(ips.len(), 2, "should only contain the non-loopback iface");
     assert_eq!(ips.len(), 1, "should only contain the non-loopback iface");
     assert!(ips.contains(&Ipv4Addr::new(192, 168, 0, 137).into()));
+  }
+
+  #[test]
+  fn test_get_local_multicast_v6_interfaces() {
+    let eth0 = interface(
+      "eth0",
+      1,
+      &[IpNetwork::V4(
+        Ipv4Network::new(Ipv4Addr::new(192, 168, 0, 137), 24).unwrap(),
+      )],
+      &[pnet_sys::IFF_MULTICAST],
+    );
+    let eth1 = interface(
+      "eth1",
+      2,
+      &[IpNetwork::V6(
+        Ipv6Network::new(Ipv6Addr::new(0xfd73, 0x40a2, 0x1c3e, 0, 0, 0, 0, 0), 64).unwrap(),
+      )],
+      &[pnet_sys::IFF_MULTICAST],
+    );
+
+    let indices = super::get_local_multicast_v6_interfaces_inner(vec![loopback(), eth0, eth1], &[]);
+    assert_eq!(indices, vec![2]);
+  }
 
-    // TODO: uncomment if ipv6
-    // assert!(ips.contains(&Ipv6Addr::new(0xfd73, 0x40a2, 0x1c3e, 0, 0, 0, 0,
-    // 0).into()))
+  #[test]
+  fn multicast_interfaces_follow_allowlist() {
+    let interfaces = || {
+      let eth0 = interface(
+        "eth0",
+        1,
+        &[IpNetwork::V4(
+          Ipv4Network::new(Ipv4Addr::new(192, 168, 0, 137), 24).unwrap(),
+        )],
+        &[pnet_sys::IFF_MULTICAST],
+      );
+      let eth1 = interface(
+        "eth1",
+        2,
+        &[
+          IpNetwork::V4(Ipv4Network::new(Ipv4Addr::new(10, 0, 0, 5), 8).unwrap()),
+          IpNetwork::V6(
+            Ipv6Network::new(Ipv6Addr::new(0xfd73, 0x40a2, 0x1c3e, 0, 0, 0, 0, 5), 64).unwrap(),
+          ),
+        ],
+        &[pnet_sys::IFF_MULTICAST],
+      );
+      vec![loopback(), eth0, eth1]
+    };
+
+    let by_network = ["10.0.0.0/8".parse().unwrap()];
+    assert_eq!(
+      super::get_local_multicast_ip_addrs_inner(interfaces(), &by_network),
+      vec![IpAddr::from(Ipv4Addr::new(10, 0, 0, 5))]
+    );
+    // An IPv4 network does not select the interface for IPv6
+    assert!(super::get_local_multicast_v6_interfaces_inner(interfaces(), &by_network).is_empty());
+
+    let by_name = ["eth1".parse().unwrap()];
+    assert_eq!(
+      super::get_local_multicast_v6_interfaces_inner(interfaces(), &by_name),
+      vec![2]
+    );
+    assert_eq!(
+      super::get_local_multicast_ip_addrs_inner(interfaces(), &by_name),
+      vec![IpAddr::from(Ipv4Addr::new(10, 0, 0, 5))]
+    );
+  }
+
+  #[test]
+  fn unicast_locators_match_bound_address() {
+    let specific = SocketAddr::new(Ipv4Addr::new(10, 0, 0, 2).into(), 7411);
+    assert_eq!(
+      super::get_local_unicast_locators(specific, &[]),
+      vec![Locator::from(specific)]
+    );
+
+    let any_v6 = SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 7411);
+    for locator in super::get_local_unicast_locators(any_v6, &[]) {
+      match locator {
+        Locator::UdpV6(address) => {
+          assert_eq!(address.port(), 7411);
+          assert!(!super::is_unicast_link_local_v6(address.ip()));
+        }
+        other => panic!("Unexpected locator {other:?}"),
+      }
+    }
   }
 
   /// Tries a number of interfaces, none of which support multicast.
@@ -116,7 +272,7 @@ mod tests {
       ));
     }
 
-    let ips = super::get_local_multicast_ip_addrs_inner(interfaces);
+    let ips = super::get_local_multicast_ip_addrs_inner(interfaces, &[]);
 
     assert!(
       ips.is_empty(),
@@ -126,7 +282,7 @@ mod tests {
 
   #[test]
   fn empty_interfaces() {
-    let ips = super::get_local_multicast_ip_addrs_inner(Vec::new());
+    let ips = super::get_local_multicast_ip_addrs_inner(Vec::new(), &[]);
     assert!(
       ips.is_empty(),
       "blank iface list should result in empty list of ips"
@@ -24,6 +24,20 @@ impl ROSDiscoveryTopic {
       lease_duration: Duration::INFINITE,
     }),
     time_based_filter: None,
+    partition: None,
+    writer_restart: None,
+    cache_watermarks: None,
+    delivery_order: None,
+    last_value_cache: None,
+    conflation: None,
+    payload_checksum: None,
+    writer_quarantine: None,
+    forward_error_correction: None,
+    repair_scheduling: None,
+    writer_initialization: None,
+    batching: None,
+    acknowledgment: None,
+    flow_control: None,
     reliability: Some(Reliability::Reliable {
       max_blocking_time: Duration::ZERO,
     }),
@@ -33,6 +47,7 @@ impl ROSDiscoveryTopic {
     lifespan: Some(Lifespan {
       duration: Duration::INFINITE,
     }),
+    data_representation: None,
     #[cfg(feature = "security")]
     property: None,
   };
@@ -64,6 +79,20 @@ impl ParameterEventsTopic {
     ownership: None,
     liveliness: None,
     time_based_filter: None,
+    partition: None,
+    writer_restart: None,
+    cache_watermarks: None,
+    delivery_order: None,
+    last_value_cache: None,
+    conflation: None,
+    payload_checksum: None,
+    writer_quarantine: None,
+    forward_error_correction: None,
+    repair_scheduling: None,
+    writer_initialization: None,
+    batching: None,
+    acknowledgment: None,
+    flow_control: None,
     reliability: Some(Reliability::Reliable {
       max_blocking_time: Duration::ZERO,
     }),
@@ -71,6 +100,7 @@ impl ParameterEventsTopic {
     history: Some(History::KeepLast { depth: 1 }),
     resource_limits: None,
     lifespan: None,
+    data_representation: None,
     #[cfg(feature = "security")]
     property: None,
   };
@@ -106,6 +136,20 @@ impl RosOutTopic {
       lease_duration: Duration::INFINITE,
     }),
     time_based_filter: None,
+    partition: None,
+    writer_restart: None,
+    cache_watermarks: None,
+    delivery_order: None,
+    last_value_cache: None,
+    conflation: None,
+    payload_checksum: None,
+    writer_quarantine: None,
+    forward_error_correction: None,
+    repair_scheduling: None,
+    writer_initialization: None,
+    batching: None,
+    acknowledgment: None,
+    flow_control: None,
     reliability: Some(Reliability::Reliable {
       max_blocking_time: Duration::ZERO,
     }),
@@ -115,6 +159,7 @@ impl RosOutTopic {
     lifespan: Some(Lifespan {
       duration: Duration::from_secs(10),
     }),
+    data_representation: None,
     #[cfg(feature = "security")]
     property: None,
   };
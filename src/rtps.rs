@@ -2,11 +2,15 @@
 pub(crate) mod constant;
 
 pub(crate) mod dp_event_loop;
+pub(crate) mod fec;
+pub(crate) mod flow_controller;
 pub(crate) mod fragment_assembler;
 pub(crate) mod message_receiver;
 pub(crate) mod reader;
 pub(crate) mod rtps_reader_proxy;
 pub(crate) mod rtps_writer_proxy;
+pub(crate) mod send_trigger;
+pub(crate) mod timer_wheel;
 pub(crate) mod writer;
 
 pub(crate) mod message;
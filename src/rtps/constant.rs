@@ -77,6 +77,36 @@ pub const STANDARD_BUILTIN_WRITERS_INIT_LIST: &[(EntityId, EntityId, u32)] = &[
   ),
 ];
 
+// Helper lists for initializing the builtin endpoints of the TypeLookup service
+// of RustDDS, which are matched only with other RustDDS participants. The
+// endpoints are vendor-specific, so they have no BuiltinEndpointSet bits, and
+// the (always contained) empty set is given instead.
+pub const TYPE_LOOKUP_BUILTIN_READERS_INIT_LIST: &[(EntityId, EntityId, u32)] = &[
+  (
+    EntityId::RUSTDDS_TYPE_LOOKUP_REQUEST_WRITER,
+    EntityId::RUSTDDS_TYPE_LOOKUP_REQUEST_READER,
+    0,
+  ),
+  (
+    EntityId::RUSTDDS_TYPE_LOOKUP_REPLY_WRITER,
+    EntityId::RUSTDDS_TYPE_LOOKUP_REPLY_READER,
+    0,
+  ),
+];
+
+pub const TYPE_LOOKUP_BUILTIN_WRITERS_INIT_LIST: &[(EntityId, EntityId, u32)] = &[
+  (
+    EntityId::RUSTDDS_TYPE_LOOKUP_REQUEST_WRITER,
+    EntityId::RUSTDDS_TYPE_LOOKUP_REQUEST_READER,
+    0,
+  ),
+  (
+    EntityId::RUSTDDS_TYPE_LOOKUP_REPLY_WRITER,
+    EntityId::RUSTDDS_TYPE_LOOKUP_REPLY_READER,
+    0,
+  ),
+];
+
 // Helper list for initializing the authentication topic built-in reader
 #[cfg(feature = "security")]
 pub const AUTHENTICATION_BUILTIN_READERS_INIT_LIST: &[(EntityId, EntityId, u32)] = &[(
@@ -203,6 +233,12 @@ pub const DISCOVERY_LISTENER_TOKEN: Token = Token(6 + PTB);
 pub const DISCOVERY_MUL_LISTENER_TOKEN: Token = Token(7 + PTB);
 pub const USER_TRAFFIC_LISTENER_TOKEN: Token = Token(8 + PTB);
 pub const USER_TRAFFIC_MUL_LISTENER_TOKEN: Token = Token(9 + PTB);
+// IPv6 twins of the above
+pub const DISCOVERY_LISTENER_V6_TOKEN: Token = Token(25 + PTB);
+pub const DISCOVERY_MUL_LISTENER_V6_TOKEN: Token = Token(26 + PTB);
+pub const USER_TRAFFIC_LISTENER_V6_TOKEN: Token = Token(27 + PTB);
+pub const USER_TRAFFIC_MUL_LISTENER_V6_TOKEN: Token = Token(28 + PTB);
+pub const TCP_TRANSPORT_TOKEN: Token = Token(24 + PTB);
 
 pub const ADD_READER_TOKEN: Token = Token(10 + PTB);
 pub const REMOVE_READER_TOKEN: Token = Token(11 + PTB);
@@ -234,9 +270,15 @@ pub const DISCOVERY_TOPIC_DATA_TOKEN: Token = Token(37 + PTB);
 pub const DISCOVERY_TOPIC_CLEANUP_TOKEN: Token = Token(38 + PTB);
 pub const DISCOVERY_PARTICIPANT_MESSAGE_TOKEN: Token = Token(40 + PTB);
 pub const DISCOVERY_PARTICIPANT_MESSAGE_TIMER_TOKEN: Token = Token(41 + PTB);
+pub const DISCOVERY_NETWORK_CHANGE_TIMER_TOKEN: Token = Token(42 + PTB);
+pub const DISCOVERY_SELF_HEALTH_TIMER_TOKEN: Token = Token(43 + PTB);
+pub const DISCOVERY_TYPE_LOOKUP_REQUEST_TOKEN: Token = Token(47 + PTB);
+pub const DISCOVERY_TYPE_LOOKUP_REPLY_TOKEN: Token = Token(48 + PTB);
+pub const DISCOVERY_DAMPING_TIMER_TOKEN: Token = Token(49 + PTB);
 
 pub const DPEV_ACKNACK_TIMER_TOKEN: Token = Token(45 + PTB);
 pub const DPEV_CACHE_CLEAN_TIMER_TOKEN: Token = Token(46 + PTB);
+pub const DPEV_FLOW_CONTROL_TIMER_TOKEN: Token = Token(44 + PTB);
 
 pub const SECURE_DISCOVERY_PARTICIPANT_DATA_TOKEN: Token = Token(50 + PTB);
 // pub const DISCOVERY_PARTICIPANT_CLEANUP_TOKEN: Token = Token(51 + PTB);
@@ -302,6 +344,9 @@ pub mod builtin_topic_names {
   pub const DCPS_PARTICIPANT_MESSAGE_SECURE: &str = "DCPSParticipantMessageSecure";
   pub const DCPS_PARTICIPANT_STATELESS_MESSAGE: &str = "DCPSParticipantStatelessMessage";
   pub const DCPS_PARTICIPANT_VOLATILE_MESSAGE_SECURE: &str = "DCPSParticipantVolatileMessageSecure";
+  // TypeLookup service of RustDDS, named as in DDS-XTypes 1.3: 7.6.3.3.3
+  pub const TYPE_LOOKUP_REQUEST: &str = "DCPSTypeLookupRequest";
+  pub const TYPE_LOOKUP_REPLY: &str = "DCPSTypeLookupReply";
 }
 
 // topic type name over RTPS
@@ -319,4 +364,7 @@ pub mod builtin_topic_type_names {
   pub const DCPS_PARTICIPANT_MESSAGE_SECURE: &str = "ParticipantMessageData";
   pub const DCPS_PARTICIPANT_STATELESS_MESSAGE: &str = "ParticipantStatelessMessage";
   pub const DCPS_PARTICIPANT_VOLATILE_MESSAGE_SECURE: &str = "ParticipantVolatileMessageSecure";
+
+  pub const TYPE_LOOKUP_REQUEST: &str = "TypeLookup_Request";
+  pub const TYPE_LOOKUP_REPLY: &str = "TypeLookup_Reply";
 }
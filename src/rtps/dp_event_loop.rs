@@ -1,5 +1,6 @@
 use std::{
-  collections::HashMap,
+  cell::RefCell,
+  collections::{BTreeSet, HashMap},
   rc::Rc,
   sync::{Arc, RwLock},
   time::{Duration, Instant},
@@ -11,21 +12,31 @@ use mio_extras::channel as mio_channel;
 
 use crate::{
   dds::{
+    discovery_network::IpStack,
+    flow_control::FlowControllerSettings,
+    pubsub::PartitionSwitchReport,
     qos::policy,
     statusevents::{DomainParticipantStatusEvent, StatusChannelSender},
   },
   discovery::{
     discovery::DiscoveryCommand,
-    discovery_db::{discovery_db_read, DiscoveryDB},
+    discovery_db::{discovery_db_read, DiscoveryDB, TypeMatch},
+    lease_assertion::SpdpTransmissions,
     sedp_messages::{DiscoveredReaderData, DiscoveredWriterData},
   },
-  messages::submessages::submessages::AckSubmessage,
-  network::{udp_listener::UDPListener, udp_sender::UDPSender},
+  messages::{submessages::submessages::AckSubmessage, vendor_id::VendorId},
+  network::{
+    tcp::TcpTransport,
+    udp_listener::UDPListener,
+    udp_sender::{MulticastOptions, SenderSocketSettings, UDPSender},
+  },
   polling::new_simple_timer,
   qos::HasQoSPolicy,
   rtps::{
     constant::*,
-    message_receiver::MessageReceiver,
+    flow_controller::FlowController,
+    message::concatenate_messages,
+    message_receiver::{self, MessageReceiver},
     reader::{Reader, ReaderIngredients},
     rtps_reader_proxy::RtpsReaderProxy,
     rtps_writer_proxy::RtpsWriterProxy,
@@ -35,6 +46,7 @@ use crate::{
     dds_cache::DDSCache,
     entity::RTPSEntity,
     guid::{EntityId, GuidPrefix, TokenDecode, GUID},
+    locator::Locator,
   },
 };
 #[cfg(feature = "security")]
@@ -56,14 +68,47 @@ pub struct DomainInfo {
 pub(crate) enum EventLoopCommand {
   Stop,
   PrepareStop,
+  // Local IP addresses have changed
+  NetworkChanged,
+  // Start or stop deferring the data of a Writer until FlushWriters
+  DeferWriterData {
+    writer: EntityId,
+    defer: bool,
+  },
+  // Send the deferred data of Writers together
+  FlushWriters {
+    writers: Vec<EntityId>,
+  },
+  // Move Writers and Readers to a new Partition and match them again
+  SwitchPartition {
+    writers: Vec<EntityId>,
+    readers: Vec<EntityId>,
+    partition: policy::Partition,
+    done: std::sync::mpsc::Sender<PartitionSwitchReport>,
+  },
+  // Probe a remote host and announce this participant to it at once
+  SendDiscoveryProbe {
+    locator: Locator,
+  },
+  // Replace the SPDP peers found by the peer resolver
+  SetResolvedPeers {
+    locators: Vec<Locator>,
+  },
 }
 
+// Upper limit for datagrams combined from the messages of flushed Writers
+const FLUSH_DATAGRAM_MAX_SIZE: usize = 1500;
+
+// RTPS PINGs are answered with an SPDP announcement at most this often.
+const PING_ANSWER_MIN_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct DPEventLoop {
   domain_info: DomainInfo,
   poll: Poll,
   dds_cache: Arc<RwLock<DDSCache>>,
   discovery_db: Arc<RwLock<DiscoveryDB>>,
   udp_listeners: HashMap<Token, UDPListener>,
+  tcp_transport: Option<TcpTransport>,
   message_receiver: MessageReceiver, // This contains our Readers
 
   // If security is enabled, this contains the security plugins
@@ -83,12 +128,28 @@ pub struct DPEventLoop {
   ack_nack_receiver: mio_channel::Receiver<(GuidPrefix, AckSubmessage)>,
 
   writers: HashMap<EntityId, Writer>,
+  // Where SPDP announcements are sent in addition to multicast
+  initial_peer_locators: Vec<Locator>,
+  // Where SPDP announcements are sent, as last found by the peer resolver
+  resolved_peer_locators: Vec<Locator>,
+  // Whether received RTPS PINGs are answered with an SPDP announcement
+  answer_discovery_probes: bool,
+  last_ping_answer: Option<Instant>,
+  // Writers whose data is deferred. Kept also here, because the command may
+  // arrive before the Writer is added.
+  deferred_writers: BTreeSet<EntityId>,
   udp_sender: Rc<UDPSender>,
+  // Sends the metatraffic of the built-in endpoints. The same as udp_sender,
+  // unless metatraffic is isolated.
+  metatraffic_sender: Rc<UDPSender>,
+  // Records the messages sent by the SPDP Writer
+  spdp_transmissions: SpdpTransmissions,
+  // Schedules the messages of the user Writers that are limited
+  flow_controller: Rc<RefCell<FlowController>>,
 
   participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
 
   discovery_update_notification_receiver: mio_channel::Receiver<DiscoveryNotificationType>,
-  #[cfg(feature = "security")]
   discovery_command_sender: mio_channel::SyncSender<DiscoveryCommand>,
 }
 
@@ -107,14 +168,20 @@ impl DPEventLoop {
     remove_writer_receiver: TokenReceiverPair<GUID>,
     stop_poll_receiver: mio_channel::Receiver<EventLoopCommand>,
     discovery_update_notification_receiver: mio_channel::Receiver<DiscoveryNotificationType>,
-    _discovery_command_sender: mio_channel::SyncSender<DiscoveryCommand>,
+    discovery_command_sender: mio_channel::SyncSender<DiscoveryCommand>,
     spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
+    spdp_transmissions: SpdpTransmissions,
     participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
     security_plugins_opt: Option<SecurityPluginsHandle>,
+    multicast_options: MulticastOptions,
+    ip_stack: IpStack,
+    user_traffic_sockets: SenderSocketSettings,
+    metatraffic_sockets: Option<SenderSocketSettings>,
+    initial_peer_locators: Vec<Locator>,
+    answer_discovery_probes: bool,
+    tcp_transport: Option<TcpTransport>,
+    flow_control: FlowControllerSettings,
   ) -> Self {
-    #[cfg(not(feature = "security"))]
-    let _dummy = _discovery_command_sender;
-
     let poll = Poll::new().expect("Unable to create new poll.");
     let (acknack_sender, acknack_receiver) =
       mio_channel::sync_channel::<(GuidPrefix, AckSubmessage)>(100);
@@ -192,8 +259,54 @@ impl DPEventLoop {
       )
       .expect("Failed to register reader update notification.");
 
+    // Discovery repeats our SPDP messages with a sender of its own, set up like
+    // the one that they are sent with.
+    match UDPSender::with_multicast_options(
+      0,
+      multicast_options,
+      ip_stack,
+      metatraffic_sockets
+        .clone()
+        .unwrap_or_else(|| user_traffic_sockets.clone()),
+    ) {
+      Ok(repeat_sender) => spdp_transmissions.set_repeat_sender(repeat_sender),
+      Err(e) => warn!("Cannot create a sender for repeating SPDP messages: {e:?}"),
+    }
+
     // port number 0 means OS chooses an available port number.
-    let udp_sender = UDPSender::new(0).expect("UDPSender construction fail"); // TODO
+    let mut udp_sender =
+      UDPSender::with_multicast_options(0, multicast_options, ip_stack, user_traffic_sockets)
+        .expect("UDPSender construction fail"); // TODO
+    let mut metatraffic_sender = metatraffic_sockets.map(|sockets| {
+      UDPSender::with_multicast_options(0, multicast_options, ip_stack, sockets)
+        .expect("Metatraffic UDPSender construction fail")
+    });
+
+    if let Some(tcp_transport) = &tcp_transport {
+      poll
+        .register(
+          tcp_transport.received(),
+          TCP_TRANSPORT_TOKEN,
+          Ready::readable(),
+          PollOpt::edge(),
+        )
+        .expect("Failed to register TCP transport.");
+      udp_sender.set_tcp_sender(tcp_transport.sender());
+      if let Some(metatraffic_sender) = &mut metatraffic_sender {
+        metatraffic_sender.set_tcp_sender(tcp_transport.sender());
+      }
+    }
+    let udp_sender = Rc::new(udp_sender);
+    let metatraffic_sender = metatraffic_sender.map_or_else(|| udp_sender.clone(), Rc::new);
+    let flow_controller = FlowController::new(flow_control, udp_sender.clone());
+    poll
+      .register(
+        flow_controller.timer(),
+        DPEV_FLOW_CONTROL_TIMER_TOKEN,
+        Ready::readable(),
+        PollOpt::edge(),
+      )
+      .expect("Failed to register flow controller timer.");
 
     #[cfg(not(feature = "security"))]
     let security_plugins_opt = security_plugins_opt.and(None); // make sure it is None an consume value
@@ -204,7 +317,11 @@ impl DPEventLoop {
       dds_cache,
       discovery_db,
       udp_listeners,
-      udp_sender: Rc::new(udp_sender),
+      tcp_transport,
+      udp_sender,
+      metatraffic_sender,
+      spdp_transmissions,
+      flow_controller: Rc::new(RefCell::new(flow_controller)),
       message_receiver: MessageReceiver::new(
         participant_guid_prefix,
         acknack_sender,
@@ -219,11 +336,15 @@ impl DPEventLoop {
       remove_writer_receiver,
       stop_poll_receiver,
       writers: HashMap::new(),
+      initial_peer_locators,
+      resolved_peer_locators: Vec::new(),
+      answer_discovery_probes,
+      last_ping_answer: None,
+      deferred_writers: BTreeSet::new(),
       ack_nack_receiver: acknack_receiver,
       discovery_update_notification_receiver,
       participant_status_sender,
-      #[cfg(feature = "security")]
-      discovery_command_sender: _discovery_command_sender,
+      discovery_command_sender,
     }
   }
 
@@ -296,6 +417,38 @@ impl DPEventLoop {
                       info!("Stopping dp_event_loop");
                       return;
                     }
+                    Ok(EventLoopCommand::NetworkChanged) => {
+                      info!("dp_event_loop: updating multicast sockets after network change.");
+                      for listener in ev_wrapper.udp_listeners.values_mut() {
+                        listener.refresh_multicast_membership();
+                      }
+                      ev_wrapper.udp_sender.refresh_multicast_sockets();
+                      if !Rc::ptr_eq(&ev_wrapper.udp_sender, &ev_wrapper.metatraffic_sender) {
+                        ev_wrapper.metatraffic_sender.refresh_multicast_sockets();
+                      }
+                    }
+                    Ok(EventLoopCommand::DeferWriterData { writer, defer }) => {
+                      ev_wrapper.defer_writer_data(writer, defer);
+                    }
+                    Ok(EventLoopCommand::FlushWriters { writers }) => {
+                      ev_wrapper.flush_writers(&writers);
+                    }
+                    Ok(EventLoopCommand::SwitchPartition {
+                      writers,
+                      readers,
+                      partition,
+                      done,
+                    }) => {
+                      let report = ev_wrapper.switch_partition(&writers, &readers, &partition);
+                      // The application may have stopped waiting.
+                      let _ = done.send(report);
+                    }
+                    Ok(EventLoopCommand::SendDiscoveryProbe { locator }) => {
+                      ev_wrapper.send_discovery_probe(locator);
+                    }
+                    Ok(EventLoopCommand::SetResolvedPeers { locators }) => {
+                      ev_wrapper.set_resolved_peers(locators);
+                    }
                     Err(err) => match err {
                       TryRecvError::Empty => {
                         try_recv_more = false;
@@ -313,7 +466,11 @@ impl DPEventLoop {
               DISCOVERY_LISTENER_TOKEN
               | DISCOVERY_MUL_LISTENER_TOKEN
               | USER_TRAFFIC_LISTENER_TOKEN
-              | USER_TRAFFIC_MUL_LISTENER_TOKEN => {
+              | USER_TRAFFIC_MUL_LISTENER_TOKEN
+              | DISCOVERY_LISTENER_V6_TOKEN
+              | DISCOVERY_MUL_LISTENER_V6_TOKEN
+              | USER_TRAFFIC_LISTENER_V6_TOKEN
+              | USER_TRAFFIC_MUL_LISTENER_V6_TOKEN => {
                 let udp_messages = ev_wrapper
                   .udp_listeners
                   .get_mut(&event.token())
@@ -327,6 +484,22 @@ impl DPEventLoop {
                 for packet in udp_messages {
                   ev_wrapper.message_receiver.handle_received_packet(&packet);
                 }
+                if ev_wrapper.message_receiver.take_ping_received() {
+                  ev_wrapper.answer_ping();
+                }
+              }
+              TCP_TRANSPORT_TOKEN => {
+                let tcp_messages = ev_wrapper
+                  .tcp_transport
+                  .as_ref()
+                  .map(TcpTransport::take_received)
+                  .unwrap_or_default();
+                for message in tcp_messages {
+                  ev_wrapper.message_receiver.handle_received_packet(&message);
+                }
+                if ev_wrapper.message_receiver.take_ping_received() {
+                  ev_wrapper.answer_ping();
+                }
               }
               ADD_READER_TOKEN | REMOVE_READER_TOKEN => {
                 ev_wrapper.handle_reader_action(&event);
@@ -387,6 +560,9 @@ impl DPEventLoop {
                 ev_wrapper.dds_cache.write().unwrap().garbage_collect();
                 cache_gc_timer.set_timeout(CACHE_CLEAN_PERIOD, ());
               }
+              DPEV_FLOW_CONTROL_TIMER_TOKEN => {
+                ev_wrapper.flow_controller.borrow_mut().handle_timer_event();
+              }
 
               fixed_unknown => {
                 error!(
@@ -410,21 +586,24 @@ impl DPEventLoop {
                   Reader::process_command,
                 );
               } else if eid.kind().is_writer() {
-                let local_readers = match ev_wrapper.writers.get_mut(&eid) {
+                let (local_readers, local_deliveries) = match ev_wrapper.writers.get_mut(&eid) {
                   None => {
                     if !preparing_to_stop {
                       error!("Event for unknown writer {eid:?}");
                     };
-                    vec![]
+                    (vec![], vec![])
                   }
                   Some(writer) => {
                     // Writer will record data to DDSCache and send it out.
                     writer.process_writer_command();
-                    writer.local_readers()
+                    (writer.local_readers(), writer.take_local_deliveries())
                   }
                 };
-                // Notify local (same participant) readers that new data is available in the
-                // cache.
+                // Hand intra-process samples to the local readers, and notify local (same
+                // participant) readers that new data is available in the cache.
+                ev_wrapper
+                  .message_receiver
+                  .deliver_local_changes(local_deliveries);
                 ev_wrapper
                   .message_receiver
                   .notify_data_to_readers(local_readers);
@@ -449,8 +628,6 @@ impl DPEventLoop {
     } // loop
   } // fn
 
-  #[cfg(feature = "security")] // Currently used only with security.
-                               // Just remove attribute if used also without.
   fn send_participant_status(&self, event: DomainParticipantStatusEvent) {
     self
       .participant_status_sender
@@ -475,6 +652,178 @@ impl DPEventLoop {
     }
   }
 
+  fn defer_writer_data(&mut self, writer_id: EntityId, defer: bool) {
+    if defer {
+      self.deferred_writers.insert(writer_id);
+    } else {
+      self.deferred_writers.remove(&writer_id);
+    }
+    if let Some(writer) = self.writers.get_mut(&writer_id) {
+      writer.set_defer_data(defer);
+    }
+  }
+
+  // Sends the pending data of the given Writers at once. Messages to the same
+  // destination are combined into as few datagrams as possible.
+  fn flush_writers(&mut self, writer_ids: &[EntityId]) {
+    self.udp_sender.begin_batch();
+    let mut local_readers = Vec::new();
+    let mut local_deliveries = Vec::new();
+    for writer_id in writer_ids {
+      match self.writers.get_mut(writer_id) {
+        Some(writer) => {
+          // Take in the data that the DataWriter has written so far.
+          writer.process_writer_command();
+          writer.send_pending_data();
+          local_readers.extend(writer.local_readers());
+          local_deliveries.extend(writer.take_local_deliveries());
+        }
+        None => debug!("flush_writers: unknown writer {writer_id:?}"),
+      }
+    }
+    self
+      .udp_sender
+      .end_batch(|messages| concatenate_messages(messages, FLUSH_DATAGRAM_MAX_SIZE));
+    self
+      .message_receiver
+      .deliver_local_changes(local_deliveries);
+    self.message_receiver.notify_data_to_readers(local_readers);
+  }
+
+  // Moves the given Writers and Readers to `partition` at once, and matches
+  // them again with the remote endpoints of their topics.
+  fn switch_partition(
+    &mut self,
+    writer_ids: &[EntityId],
+    reader_ids: &[EntityId],
+    partition: &policy::Partition,
+  ) -> PartitionSwitchReport {
+    // Endpoints created just before the switch may not have been added yet.
+    while let Ok(writer_ing) = self.add_writer_receiver.receiver.try_recv() {
+      self.add_local_writer(writer_ing);
+    }
+    while let Ok(reader_ing) = self.add_reader_receiver.receiver.try_recv() {
+      self.add_local_reader(reader_ing);
+    }
+
+    let mut topics = BTreeSet::new();
+    for writer_id in writer_ids {
+      if let Some(writer) = self.writers.get_mut(writer_id) {
+        writer.set_partition(partition.clone());
+        topics.insert(writer.topic_name().clone());
+      }
+    }
+    for reader_id in reader_ids {
+      if let Some(reader) = self.message_receiver.available_readers.get_mut(reader_id) {
+        reader.set_partition(partition.clone());
+        topics.insert(reader.topic_name().clone());
+      }
+    }
+
+    let (remote_readers, remote_writers): (Vec<_>, Vec<_>) = {
+      let db = discovery_db_read(&self.discovery_db);
+      (
+        topics.iter().flat_map(|t| db.readers_on_topic(t)).collect(),
+        topics.iter().flat_map(|t| db.writers_on_topic(t)).collect(),
+      )
+    };
+    for remote_reader in &remote_readers {
+      self.remote_reader_discovered(remote_reader);
+    }
+    for remote_writer in &remote_writers {
+      self.remote_writer_discovered(remote_writer);
+    }
+    info!(
+      "Switched {} writers and {} readers to partition {:?}",
+      writer_ids.len(),
+      reader_ids.len(),
+      partition
+    );
+
+    PartitionSwitchReport {
+      matched_readers: writer_ids
+        .iter()
+        .filter_map(|id| self.writers.get(id))
+        .map(|w| (w.guid(), w.matched_reader_count()))
+        .collect(),
+      matched_writers: reader_ids
+        .iter()
+        .filter_map(|id| self.message_receiver.available_readers.get(id))
+        .map(|r| (r.guid(), r.matched_writer_count()))
+        .collect(),
+    }
+  }
+
+  // Sends an RTPS PING to the locator, adds it to the destinations of SPDP
+  // announcements, and asks Discovery to announce this participant at once.
+  // The PING prompts implementations that answer it to announce themselves.
+  fn send_discovery_probe(&mut self, locator: Locator) {
+    self
+      .metatraffic_sender
+      .send_to_locator(&message_receiver::rtps_ping_message(), &locator);
+    if !self.initial_peer_locators.contains(&locator) {
+      info!("Adding discovery probe destination {locator:?} to SPDP peers");
+      self.initial_peer_locators.push(locator);
+      self.update_participant(self.domain_info.domain_participant_guid.prefix);
+    }
+    self.request_spdp_announcement();
+  }
+
+  // Replaces the resolved SPDP peers. New peers are probed, and this
+  // participant is announced to them at once. Peers that are no longer
+  // resolved get no more announcements.
+  fn set_resolved_peers(&mut self, locators: Vec<Locator>) {
+    let new_peers: Vec<Locator> = locators
+      .iter()
+      .filter(|locator| !self.resolved_peer_locators.contains(locator))
+      .copied()
+      .collect();
+    if new_peers.is_empty() && locators.len() == self.resolved_peer_locators.len() {
+      return;
+    }
+    info!(
+      "Resolved SPDP peers changed: {} added, now {} in total",
+      new_peers.len(),
+      locators.len()
+    );
+    let ping = message_receiver::rtps_ping_message();
+    for locator in &new_peers {
+      self.metatraffic_sender.send_to_locator(&ping, locator);
+    }
+    self.resolved_peer_locators = locators;
+    self.update_participant(self.domain_info.domain_participant_guid.prefix);
+    if !new_peers.is_empty() {
+      self.request_spdp_announcement();
+    }
+  }
+
+  // A remote participant is probing for us. Announcing right away lets it
+  // discover us without waiting for the next periodic announcement.
+  fn answer_ping(&mut self) {
+    if !self.answer_discovery_probes {
+      return;
+    }
+    let now = Instant::now();
+    if self
+      .last_ping_answer
+      .is_some_and(|last| now < last + PING_ANSWER_MIN_INTERVAL)
+    {
+      trace!("Not answering RTPS PING: answered one recently.");
+      return;
+    }
+    self.last_ping_answer = Some(now);
+    self.request_spdp_announcement();
+  }
+
+  fn request_spdp_announcement(&self) {
+    if let Err(e) = self
+      .discovery_command_sender
+      .try_send(DiscoveryCommand::AnnounceParticipant)
+    {
+      warn!("Could not ask Discovery to announce participant: {e:?}");
+    }
+  }
+
   fn handle_writer_action(&mut self, event: &Event) {
     match event.token() {
       ADD_WRITER_TOKEN => {
@@ -553,6 +902,8 @@ impl DPEventLoop {
         error!("Participant was updated, but DB does not have it. Strange.");
         return;
       };
+    // Remote participants may advertise locators of both IP versions
+    let ip_stack = self.udp_sender.ip_stack();
 
     // Select which builtin endpoints of the remote participant are updated to local
     // readers & writers
@@ -604,6 +955,17 @@ impl DPEventLoop {
       }
     };
 
+    // Our TypeLookup service is matched along with the standard endpoints, but
+    // only with other RustDDS participants, because its requests and replies
+    // are not in the XTypes format.
+    let (mut readers_init_list, mut writers_init_list) = (readers_init_list, writers_init_list);
+    if discovered_participant.vendor_id == VendorId::THIS_IMPLEMENTATION
+      && readers_init_list.starts_with(STANDARD_BUILTIN_READERS_INIT_LIST)
+    {
+      readers_init_list.extend_from_slice(TYPE_LOOKUP_BUILTIN_READERS_INIT_LIST);
+      writers_init_list.extend_from_slice(TYPE_LOOKUP_BUILTIN_WRITERS_INIT_LIST);
+    }
+
     // Update local writers
     for (writer_eid, reader_eid, endpoint) in &readers_init_list {
       if let Some(writer) = self.writers.get_mut(writer_eid) {
@@ -613,7 +975,28 @@ impl DPEventLoop {
           .available_builtin_endpoints
           .contains(*endpoint)
         {
-          let reader_proxy = discovered_participant.as_reader_proxy(true, Some(*reader_eid));
+          let mut reader_proxy = discovered_participant.as_reader_proxy(true, Some(*reader_eid));
+          ip_stack.select_locators(
+            &mut reader_proxy.unicast_locator_list,
+            &mut reader_proxy.multicast_locator_list,
+          );
+          // Our own SPDP Reader stands for all the participants that
+          // announcements are sent to, including the initial peers. The
+          // announcements go to the multicast locators, if multicast is used.
+          if *reader_eid == EntityId::SPDP_BUILTIN_PARTICIPANT_READER
+            && participant_guid_prefix == self.domain_info.domain_participant_guid.prefix
+          {
+            let peers = self
+              .initial_peer_locators
+              .iter()
+              .chain(&self.resolved_peer_locators)
+              .copied();
+            if self.udp_sender.multicast_enabled() {
+              reader_proxy.multicast_locator_list.extend(peers);
+            } else {
+              reader_proxy.unicast_locator_list.extend(peers);
+            }
+          }
 
           // Get the QoS for the built-in topic from the local writer
           let mut qos = writer.qos();
@@ -647,7 +1030,8 @@ impl DPEventLoop {
           .available_builtin_endpoints
           .contains(*endpoint)
         {
-          let wp = discovered_participant.as_writer_proxy(true, Some(*writer_eid));
+          let mut wp = discovered_participant.as_writer_proxy(true, Some(*writer_eid));
+          ip_stack.select_locators(&mut wp.unicast_locator_list, &mut wp.multicast_locator_list);
 
           // Get the QoS for the built-in topic from the local reader
           let qos = reader.qos();
@@ -694,15 +1078,60 @@ impl DPEventLoop {
   }
 
   fn remote_reader_discovered(&mut self, remote_reader: &DiscoveredReaderData) {
+    let remote_reader_guid = remote_reader.reader_proxy.remote_reader_guid;
+    let ip_stack = self.udp_sender.ip_stack();
+    let mut type_incompatibilities = Vec::new();
     for writer in self.writers.values_mut() {
       if remote_reader.subscription_topic_data.topic_name() == writer.topic_name() {
+        let type_match = {
+          let db = discovery_db_read(&self.discovery_db);
+          let local_type = db
+            .get_local_topic_writer(writer.guid())
+            .and_then(|dwd| dwd.publication_topic_data.type_information);
+          db.type_match(
+            remote_reader.subscription_topic_data.type_information(),
+            local_type,
+          )
+        };
+        match type_match {
+          TypeMatch::Assignable => {}
+          TypeMatch::NotAssignable(assignability) => {
+            warn!(
+              "Remote reader {:?} cannot read the type of local writer {:?}: {}",
+              remote_reader_guid,
+              writer.guid(),
+              assignability
+            );
+            // The types may have been matched before by name only
+            writer.reader_lost(remote_reader_guid);
+            type_incompatibilities.push(
+              DomainParticipantStatusEvent::RemoteReaderTypeIncompatible {
+                local_writer: writer.guid(),
+                remote_reader: remote_reader_guid,
+                assignability,
+              },
+            );
+            continue;
+          }
+          TypeMatch::LookupNeeded(type_id) => {
+            // Matching is tried again, when the type arrives
+            self
+              .discovery_command_sender
+              .send(DiscoveryCommand::LookupType {
+                participant: remote_reader_guid.prefix,
+                type_id,
+              })
+              .unwrap_or_else(|e| error!("Cannot ask Discovery to look up a type: {e:?}"));
+            continue;
+          }
+        }
+
         #[cfg(not(feature = "security"))]
         let match_to_reader = true;
         #[cfg(feature = "security")]
         let match_to_reader = if let Some(plugins_handle) = self.security_plugins_opt.as_ref() {
           // Security is enabled.
           let local_writer_guid = writer.guid();
-          let remote_reader_guid = remote_reader.reader_proxy.remote_reader_guid;
 
           // Check do we have compatible security with the remote
           let local_writer_sec_info_opt = plugins_handle
@@ -753,13 +1182,19 @@ impl DPEventLoop {
         if match_to_reader {
           // Should we check if the participant has published a QoS for the topic?
           let requested_qos = remote_reader.subscription_topic_data.qos();
-          writer.update_reader_proxy(
-            &RtpsReaderProxy::from_discovered_reader_data(remote_reader, &[], &[]),
-            &requested_qos,
+          let mut reader_proxy =
+            RtpsReaderProxy::from_discovered_reader_data(remote_reader, &[], &[]);
+          ip_stack.select_locators(
+            &mut reader_proxy.unicast_locator_list,
+            &mut reader_proxy.multicast_locator_list,
           );
+          writer.update_reader_proxy(&reader_proxy, &requested_qos);
         }
       }
     }
+    for event in type_incompatibilities {
+      self.send_participant_status(event);
+    }
   }
 
   fn remote_reader_lost(&mut self, reader_guid: GUID) {
@@ -769,16 +1204,61 @@ impl DPEventLoop {
   }
 
   fn remote_writer_discovered(&mut self, remote_writer: &DiscoveredWriterData) {
+    let remote_writer_guid = remote_writer.writer_proxy.remote_writer_guid;
+    let ip_stack = self.udp_sender.ip_stack();
+    let mut type_incompatibilities = Vec::new();
     // update writer proxies in local readers
     for reader in self.message_receiver.available_readers.values_mut() {
       if &remote_writer.publication_topic_data.topic_name == reader.topic_name() {
+        let type_match = {
+          let db = discovery_db_read(&self.discovery_db);
+          let local_type = db
+            .get_local_topic_reader(reader.guid())
+            .and_then(|drd| drd.subscription_topic_data.type_information());
+          db.type_match(
+            local_type,
+            remote_writer.publication_topic_data.type_information,
+          )
+        };
+        match type_match {
+          TypeMatch::Assignable => {}
+          TypeMatch::NotAssignable(assignability) => {
+            warn!(
+              "Local reader {:?} cannot read the type of remote writer {:?}: {}",
+              reader.guid(),
+              remote_writer_guid,
+              assignability
+            );
+            // The types may have been matched before by name only
+            reader.remove_writer_proxy(remote_writer_guid);
+            type_incompatibilities.push(
+              DomainParticipantStatusEvent::RemoteWriterTypeIncompatible {
+                local_reader: reader.guid(),
+                remote_writer: remote_writer_guid,
+                assignability,
+              },
+            );
+            continue;
+          }
+          TypeMatch::LookupNeeded(type_id) => {
+            // Matching is tried again, when the type arrives
+            self
+              .discovery_command_sender
+              .send(DiscoveryCommand::LookupType {
+                participant: remote_writer_guid.prefix,
+                type_id,
+              })
+              .unwrap_or_else(|e| error!("Cannot ask Discovery to look up a type: {e:?}"));
+            continue;
+          }
+        }
+
         #[cfg(not(feature = "security"))]
         let match_to_writer = true;
         #[cfg(feature = "security")]
         let match_to_writer = if let Some(plugins_handle) = self.security_plugins_opt.as_ref() {
           // Security is enabled.
           let local_reader_guid = reader.guid();
-          let remote_writer_guid = remote_writer.writer_proxy.remote_writer_guid;
 
           // Check do we have compatible security with the remote
           let local_reader_sec_info_opt = plugins_handle
@@ -828,13 +1308,19 @@ impl DPEventLoop {
         if match_to_writer {
           let offered_qos = remote_writer.publication_topic_data.qos();
           // Should we check if the participant has published a QoS for the topic?
-          reader.update_writer_proxy(
-            RtpsWriterProxy::from_discovered_writer_data(remote_writer, &[], &[]),
-            &offered_qos,
+          let mut writer_proxy =
+            RtpsWriterProxy::from_discovered_writer_data(remote_writer, &[], &[]);
+          ip_stack.select_locators(
+            &mut writer_proxy.unicast_locator_list,
+            &mut writer_proxy.multicast_locator_list,
           );
+          reader.update_writer_proxy(writer_proxy, &offered_qos);
         }
       }
     }
+    for event in type_incompatibilities {
+      self.send_participant_status(event);
+    }
   }
 
   fn remote_writer_lost(&mut self, writer_guid: GUID) {
@@ -843,6 +1329,15 @@ impl DPEventLoop {
     }
   }
 
+  // Built-in endpoints send metatraffic, and the others user traffic.
+  fn sender_for(&self, endpoint: GUID) -> Rc<UDPSender> {
+    if endpoint.entity_id.kind().is_built_in() {
+      self.metatraffic_sender.clone()
+    } else {
+      self.udp_sender.clone()
+    }
+  }
+
   fn add_local_reader(&mut self, reader_ing: ReaderIngredients) {
     let timer = new_simple_timer();
     self
@@ -855,9 +1350,10 @@ impl DPEventLoop {
       )
       .expect("Reader timer channel registration failed!");
 
-    let mut new_reader = Reader::new(
+    let udp_sender = self.sender_for(reader_ing.guid);
+    let new_reader = Reader::new(
       reader_ing,
-      self.udp_sender.clone(),
+      udp_sender,
       timer,
       self.participant_status_sender.clone(),
     );
@@ -873,7 +1369,8 @@ impl DPEventLoop {
       )
       .expect("Reader command channel registration failed!!!");
 
-    new_reader.set_requested_deadline_check_timer();
+    // QoS timers (DEADLINE, LIVELINESS) are armed by the Reader itself, when
+    // writers are matched.
     trace!("Add reader: {:?}", new_reader);
     self.message_receiver.add_reader(new_reader);
   }
@@ -918,9 +1415,16 @@ impl DPEventLoop {
       )
       .expect("Writer heartbeat timer channel registration failed!!");
 
-    let new_writer = Writer::new(
+    let udp_sender = self.sender_for(writer_ing.guid);
+    // Metatraffic is not limited.
+    let flow_controlled = !writer_ing.guid.entity_id.kind().is_built_in()
+      && self
+        .flow_controller
+        .borrow_mut()
+        .add_writer(writer_ing.guid, writer_ing.qos_policies.flow_control());
+    let mut new_writer = Writer::new(
       writer_ing,
-      self.udp_sender.clone(),
+      udp_sender,
       timer,
       self.participant_status_sender.clone(),
     );
@@ -935,10 +1439,24 @@ impl DPEventLoop {
       )
       .expect("Writer command channel registration failed!!");
 
+    if flow_controlled {
+      new_writer.set_flow_controller(self.flow_controller.clone());
+    }
+    if self.deferred_writers.contains(&new_writer.guid().entity_id) {
+      new_writer.set_defer_data(true);
+    }
+    if new_writer.guid().entity_id == EntityId::SPDP_BUILTIN_PARTICIPANT_WRITER {
+      new_writer.set_spdp_transmissions(self.spdp_transmissions.clone());
+    }
     self.writers.insert(new_writer.guid().entity_id, new_writer);
   }
 
   fn remove_local_writer(&mut self, writer_guid: &GUID) {
+    self.deferred_writers.remove(&writer_guid.entity_id);
+    self
+      .flow_controller
+      .borrow_mut()
+      .remove_writer(*writer_guid);
     if let Some(w) = self.writers.remove(&writer_guid.entity_id) {
       self
         .poll
@@ -1143,8 +1661,17 @@ mod tests {
         discovery_update_notification_receiver,
         discovery_command_sender,
         spdp_liveness_sender,
+        SpdpTransmissions::new(),
         participant_status_sender,
         None,
+        MulticastOptions::default(),
+        IpStack::default(),
+        SenderSocketSettings::default(),
+        None,
+        Vec::new(),
+        false,
+        None,
+        FlowControllerSettings::default(),
       );
       dp_event_loop
         .poll
@@ -1195,6 +1722,7 @@ mod tests {
         data_reader_waker: data_reader_waker.clone(),
         poll_event_sender: notification_event_sender,
         security_plugins: None,
+        status_record: Arc::default(),
       };
 
       reader_guids.push(new_reader_ing.guid);
@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+
+use crate::{
+  messages::submessages::submessages::{FecParity, FecSample},
+  structure::{guid::EntityId, sequence_number::SequenceNumber, time::Timestamp},
+};
+
+// Forward error correction with XOR parity, see
+// policy::ForwardErrorCorrection.
+//
+// A Writer feeds the serialized payloads of the samples it sends to all
+// Readers to a FecEncoder. After every group_size samples the encoder gives a
+// FecParity, which the Writer sends. A Reader that has received a parity
+// keeps the payloads recently received from that Writer in a FecDecoder. If
+// exactly one sample of a group is missing, it is the XOR of the parity and
+// the other samples.
+
+pub(crate) struct FecEncoder {
+  writer_id: EntityId,
+  group_size: usize,
+  samples: Vec<FecSample>,
+  parity: Vec<u8>,
+}
+
+impl FecEncoder {
+  pub fn new(writer_id: EntityId, group_size: u16) -> Self {
+    let group_size = usize::from(group_size).clamp(1, FecParity::MAX_SAMPLES);
+    Self {
+      writer_id,
+      group_size,
+      samples: Vec::with_capacity(group_size),
+      parity: Vec::new(),
+    }
+  }
+
+  // Adds a sent sample to the current group. Returns the parity of the group,
+  // if the sample completed it.
+  pub fn add(
+    &mut self,
+    sequence_number: SequenceNumber,
+    source_timestamp: Option<Timestamp>,
+    payload: &[u8],
+  ) -> Option<FecParity> {
+    self.samples.push(FecSample {
+      sequence_number,
+      payload_length: payload.len() as u32,
+      source_timestamp: source_timestamp.unwrap_or(Timestamp::INVALID),
+    });
+    xor_into(&mut self.parity, payload);
+
+    if self.samples.len() < self.group_size {
+      return None;
+    }
+    let mut parity = std::mem::take(&mut self.parity);
+    parity.resize(parity.len().next_multiple_of(4), 0);
+    Some(FecParity {
+      reader_id: EntityId::UNKNOWN,
+      writer_id: self.writer_id,
+      samples: std::mem::replace(&mut self.samples, Vec::with_capacity(self.group_size)),
+      parity,
+    })
+  }
+}
+
+// A sample reconstructed from a parity
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct RecoveredSample {
+  pub sequence_number: SequenceNumber,
+  pub source_timestamp: Option<Timestamp>,
+  pub payload: Bytes,
+}
+
+pub(crate) struct FecDecoder {
+  // Serialized payloads of recently received samples
+  received: BTreeMap<SequenceNumber, Bytes>,
+  // How many payloads are kept. Twice the latest group, so that samples that
+  // arrive a bit late or out of order are still available.
+  capacity: usize,
+}
+
+impl FecDecoder {
+  pub fn new() -> Self {
+    Self {
+      received: BTreeMap::new(),
+      capacity: 2 * FecParity::MAX_SAMPLES,
+    }
+  }
+
+  pub fn add_received(&mut self, sequence_number: SequenceNumber, payload: Bytes) {
+    self.received.insert(sequence_number, payload);
+    while self.received.len() > self.capacity {
+      self.received.pop_first();
+    }
+  }
+
+  // Reconstructs the missing sample of the group, if exactly one is missing.
+  pub fn recover(&mut self, fec_parity: &FecParity) -> Option<RecoveredSample> {
+    self.capacity = 2 * fec_parity.samples.len();
+
+    let mut missing = fec_parity
+      .samples
+      .iter()
+      .filter(|sample| !self.received.contains_key(&sample.sequence_number));
+    let lost = *missing.next()?;
+    if missing.next().is_some() {
+      return None; // Too much lost. XOR parity recovers one sample.
+    }
+
+    let mut payload = fec_parity.parity.clone();
+    for sample in &fec_parity.samples {
+      if let Some(received) = self.received.get(&sample.sequence_number) {
+        xor_into(&mut payload, received);
+      }
+    }
+    let length = lost.payload_length as usize;
+    if length > payload.len() {
+      return None; // Inconsistent parity
+    }
+    payload.truncate(length);
+    let payload = Bytes::from(payload);
+    self.add_received(lost.sequence_number, payload.clone());
+
+    Some(RecoveredSample {
+      sequence_number: lost.sequence_number,
+      source_timestamp: Some(lost.source_timestamp).filter(|ts| *ts != Timestamp::INVALID),
+      payload,
+    })
+  }
+}
+
+// XORs data into parity, extending parity with zeros as needed
+fn xor_into(parity: &mut Vec<u8>, data: &[u8]) {
+  if parity.len() < data.len() {
+    parity.resize(data.len(), 0);
+  }
+  for (p, d) in parity.iter_mut().zip(data) {
+    *p ^= d;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn sn(n: i64) -> SequenceNumber {
+    SequenceNumber::from(n)
+  }
+
+  #[test]
+  fn encoder_sends_parity_after_each_group() {
+    let mut encoder = FecEncoder::new(EntityId::UNKNOWN, 2);
+    assert_eq!(encoder.add(sn(1), None, &[1, 2, 3]), None);
+    let parity = encoder.add(sn(2), None, &[4, 5, 6, 7, 8]).unwrap();
+    assert_eq!(parity.parity, vec![1 ^ 4, 2 ^ 5, 3 ^ 6, 7, 8, 0, 0, 0]);
+    assert_eq!(
+      parity
+        .samples
+        .iter()
+        .map(|s| (s.sequence_number, s.payload_length))
+        .collect::<Vec<_>>(),
+      vec![(sn(1), 3), (sn(2), 5)]
+    );
+    // The next group starts from scratch
+    assert_eq!(encoder.add(sn(3), None, &[9]), None);
+    assert_eq!(encoder.add(sn(4), None, &[9]).unwrap().parity, vec![0; 4]);
+  }
+
+  #[test]
+  fn decoder_recovers_one_lost_sample_per_group() {
+    let payloads: Vec<Bytes> = (1..=4)
+      .map(|n| Bytes::from(vec![n; usize::from(n) * 3]))
+      .collect();
+    let timestamp = Timestamp::now();
+    let mut encoder = FecEncoder::new(EntityId::UNKNOWN, 4);
+    let mut parity = None;
+    for (i, payload) in payloads.iter().enumerate() {
+      parity = encoder.add(sn(i as i64 + 1), Some(timestamp), payload);
+    }
+    let parity = parity.unwrap();
+
+    let mut decoder = FecDecoder::new();
+    // Nothing is lost
+    for (i, payload) in payloads.iter().enumerate() {
+      decoder.add_received(sn(i as i64 + 1), payload.clone());
+    }
+    assert_eq!(decoder.recover(&parity), None);
+
+    // Sample 3 is lost
+    let mut decoder = FecDecoder::new();
+    for i in [0, 1, 3] {
+      decoder.add_received(sn(i as i64 + 1), payloads[i].clone());
+    }
+    assert_eq!(
+      decoder.recover(&parity),
+      Some(RecoveredSample {
+        sequence_number: sn(3),
+        source_timestamp: Some(timestamp),
+        payload: payloads[2].clone(),
+      })
+    );
+    // It is not recovered twice
+    assert_eq!(decoder.recover(&parity), None);
+
+    // Two samples are lost
+    let mut decoder = FecDecoder::new();
+    for i in [0, 3] {
+      decoder.add_received(sn(i as i64 + 1), payloads[i].clone());
+    }
+    assert_eq!(decoder.recover(&parity), None);
+  }
+}
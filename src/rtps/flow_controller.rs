@@ -0,0 +1,438 @@
+use std::{
+  cmp::Reverse,
+  collections::{BTreeMap, VecDeque},
+  rc::Rc,
+  time::{Duration, Instant},
+};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use mio_extras::timer::{self, Timer};
+
+use crate::{
+  dds::{flow_control::FlowControllerSettings, qos::policy},
+  network::udp_sender::UDPSender,
+  structure::{guid::GUID, locator::Locator},
+};
+
+// Resolution of the timer that releases waiting messages. The default timer
+// ticks every 100 ms, which would make limited DataWriters send in bursts.
+const TIMER_TICK: Duration = Duration::from_millis(1);
+
+// Schedules the messages of the user DataWriters of a participant according
+// to their bandwidth limits and priorities. See crate::flow_control.
+//
+// A limited Writer gives its messages to the controller, which sends each one
+// at once, if the Writer and the participant have bandwidth left and no
+// earlier message of the Writer is waiting. Otherwise the message is queued,
+// and the timer of the controller fires when the next waiting message can be
+// sent.
+pub(crate) struct FlowController {
+  scheduler: FlowScheduler,
+  udp_sender: Rc<UDPSender>,
+  timer: Timer<()>,
+  // When the timer fires next, if it is set
+  timer_deadline: Option<Instant>,
+}
+
+impl FlowController {
+  pub fn new(settings: FlowControllerSettings, udp_sender: Rc<UDPSender>) -> Self {
+    Self {
+      scheduler: FlowScheduler::new(settings, Instant::now()),
+      udp_sender,
+      timer: timer::Builder::default()
+        .tick_duration(TIMER_TICK)
+        .num_slots(64)
+        .capacity(16)
+        .build(),
+      timer_deadline: None,
+    }
+  }
+
+  pub fn timer(&self) -> &Timer<()> {
+    &self.timer
+  }
+
+  // Starts scheduling the messages of a Writer. Returns false, if the Writer
+  // is not limited at all, and can send its messages directly.
+  pub fn add_writer(&mut self, writer: GUID, flow_control: Option<policy::FlowControl>) -> bool {
+    self
+      .scheduler
+      .add_writer(writer, flow_control, Instant::now())
+  }
+
+  // Drops the waiting messages of the Writer, too.
+  pub fn remove_writer(&mut self, writer: GUID) {
+    self.scheduler.remove_writer(writer);
+  }
+
+  // Whether the Writer has messages waiting. Then all its messages must go
+  // through the controller, so that their order does not change.
+  pub fn is_waiting(&self, writer: GUID) -> bool {
+    self.scheduler.is_waiting(writer)
+  }
+
+  pub fn send(&mut self, writer: GUID, datagram: Vec<u8>, locators: Vec<Locator>) {
+    let now = Instant::now();
+    self.scheduler.enqueue(writer, datagram, locators, now);
+    self.send_ready(now);
+  }
+
+  pub fn handle_timer_event(&mut self) {
+    while self.timer.poll().is_some() {}
+    self.timer_deadline = None;
+    self.send_ready(Instant::now());
+  }
+
+  fn send_ready(&mut self, now: Instant) {
+    while let Some(message) = self.scheduler.next_ready(now) {
+      self
+        .udp_sender
+        .send_to_locator_list(&message.datagram, &message.locators);
+    }
+    if let Some(ready_at) = self.scheduler.next_ready_at() {
+      if self
+        .timer_deadline
+        .map_or(true, |deadline| ready_at < deadline)
+      {
+        self
+          .timer
+          .set_timeout(ready_at.saturating_duration_since(now), ());
+        self.timer_deadline = Some(ready_at);
+      }
+    }
+  }
+}
+
+// A message waiting to be sent
+struct QueuedMessage {
+  datagram: Vec<u8>,
+  locators: Vec<Locator>,
+  queued_at: Instant,
+}
+
+impl QueuedMessage {
+  // Bytes that sending the message takes from the bandwidth
+  fn size(&self) -> usize {
+    self.datagram.len() * self.locators.len()
+  }
+}
+
+// Bandwidth left to send with. The bucket fills at the rate of the limit, up
+// to a burst. The level may go below zero, so that a message larger than the
+// burst can be sent, and the next one waits until the debt is paid.
+struct TokenBucket {
+  bytes_per_second: u64,
+  capacity: i64,
+  level: i64,
+  updated: Instant,
+}
+
+impl TokenBucket {
+  fn new(bytes_per_second: u64, burst_period: Duration, now: Instant) -> Self {
+    let bytes_per_second = bytes_per_second.max(1);
+    let capacity = ((bytes_per_second as f64 * burst_period.as_secs_f64()) as i64).max(1);
+    Self {
+      bytes_per_second,
+      capacity,
+      level: capacity,
+      updated: now,
+    }
+  }
+
+  fn refill(&mut self, now: Instant) {
+    let elapsed = now.saturating_duration_since(self.updated);
+    let added = (elapsed.as_secs_f64() * self.bytes_per_second as f64) as i64;
+    // Less than a byte is left to accumulate.
+    if added > 0 {
+      self.level = (self.level + added).min(self.capacity);
+      self.updated = now;
+    }
+  }
+
+  fn has_bandwidth(&self) -> bool {
+    self.level > 0
+  }
+
+  fn consume(&mut self, bytes: usize) {
+    self.level -= bytes as i64;
+  }
+
+  // When the bucket has bandwidth again
+  fn ready_at(&self) -> Instant {
+    if self.has_bandwidth() {
+      self.updated
+    } else {
+      let debt = (1 - self.level) as f64;
+      self.updated + Duration::from_secs_f64(debt / self.bytes_per_second as f64)
+    }
+  }
+}
+
+struct WriterQueue {
+  priority: i32,
+  bucket: Option<TokenBucket>,
+  messages: VecDeque<QueuedMessage>,
+  queued_bytes: usize,
+}
+
+// The scheduling decisions of FlowController. They take the current time as a
+// parameter, so that they can be tested without a clock.
+struct FlowScheduler {
+  max_queued_bytes: usize,
+  burst_period: Duration,
+  participant_bucket: Option<TokenBucket>,
+  writers: BTreeMap<GUID, WriterQueue>,
+}
+
+impl FlowScheduler {
+  fn new(settings: FlowControllerSettings, now: Instant) -> Self {
+    Self {
+      max_queued_bytes: settings.max_queued_bytes,
+      burst_period: settings.burst_period,
+      participant_bucket: settings
+        .max_bytes_per_second
+        .map(|rate| TokenBucket::new(rate, settings.burst_period, now)),
+      writers: BTreeMap::new(),
+    }
+  }
+
+  fn add_writer(
+    &mut self,
+    writer: GUID,
+    flow_control: Option<policy::FlowControl>,
+    now: Instant,
+  ) -> bool {
+    let writer_limit = flow_control.and_then(|flow_control| flow_control.max_bytes_per_second);
+    if self.participant_bucket.is_none() && writer_limit.is_none() {
+      return false;
+    }
+    let queue = WriterQueue {
+      priority: flow_control.map_or(0, |flow_control| flow_control.priority),
+      bucket: writer_limit.map(|rate| TokenBucket::new(rate, self.burst_period, now)),
+      messages: VecDeque::new(),
+      queued_bytes: 0,
+    };
+    self.writers.insert(writer, queue);
+    true
+  }
+
+  fn remove_writer(&mut self, writer: GUID) {
+    self.writers.remove(&writer);
+  }
+
+  fn is_waiting(&self, writer: GUID) -> bool {
+    self
+      .writers
+      .get(&writer)
+      .is_some_and(|queue| !queue.messages.is_empty())
+  }
+
+  fn enqueue(&mut self, writer: GUID, datagram: Vec<u8>, locators: Vec<Locator>, now: Instant) {
+    let Some(queue) = self.writers.get_mut(&writer) else {
+      error!("Flow controller does not know writer {writer:?}. Message dropped.");
+      return;
+    };
+    let message = QueuedMessage {
+      datagram,
+      locators,
+      queued_at: now,
+    };
+    // A message is accepted to an empty queue regardless of its size, so that
+    // the Writer can always make progress.
+    if !queue.messages.is_empty() && queue.queued_bytes + message.size() > self.max_queued_bytes {
+      debug!(
+        "Flow controller queue of writer {writer:?} is full ({} bytes). Message dropped.",
+        queue.queued_bytes
+      );
+      return;
+    }
+    queue.queued_bytes += message.size();
+    queue.messages.push_back(message);
+  }
+
+  // The next message that can be sent now, if any. Its size is taken from the
+  // bandwidth.
+  fn next_ready(&mut self, now: Instant) -> Option<QueuedMessage> {
+    if let Some(bucket) = &mut self.participant_bucket {
+      bucket.refill(now);
+      if !bucket.has_bandwidth() {
+        return None;
+      }
+    }
+    // The highest priority goes first, and within a priority the message that
+    // has waited the longest.
+    let (_, _, writer) = self
+      .writers
+      .iter_mut()
+      .filter_map(|(guid, queue)| {
+        let queued_at = queue.messages.front()?.queued_at;
+        if let Some(bucket) = &mut queue.bucket {
+          bucket.refill(now);
+          if !bucket.has_bandwidth() {
+            return None;
+          }
+        }
+        Some((Reverse(queue.priority), queued_at, *guid))
+      })
+      .min()?;
+
+    let queue = self.writers.get_mut(&writer)?;
+    let message = queue.messages.pop_front()?;
+    queue.queued_bytes -= message.size();
+    if let Some(bucket) = &mut queue.bucket {
+      bucket.consume(message.size());
+    }
+    if let Some(bucket) = &mut self.participant_bucket {
+      bucket.consume(message.size());
+    }
+    Some(message)
+  }
+
+  // When a waiting message can be sent next, if any are waiting
+  fn next_ready_at(&self) -> Option<Instant> {
+    let writers_ready_at = self
+      .writers
+      .values()
+      .filter_map(|queue| {
+        let queued_at = queue.messages.front()?.queued_at;
+        Some(
+          queue
+            .bucket
+            .as_ref()
+            .map_or(queued_at, TokenBucket::ready_at),
+        )
+      })
+      .min()?;
+    Some(
+      self
+        .participant_bucket
+        .as_ref()
+        .map_or(writers_ready_at, |bucket| {
+          bucket.ready_at().max(writers_ready_at)
+        }),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::net::{Ipv4Addr, SocketAddr};
+
+  use super::*;
+  use crate::structure::guid::{EntityId, EntityKind, GuidPrefix};
+
+  fn writer(key: u8) -> GUID {
+    GUID::new(
+      GuidPrefix::default(),
+      EntityId::new([0, 0, key], EntityKind::WRITER_NO_KEY_USER_DEFINED),
+    )
+  }
+
+  fn locators() -> Vec<Locator> {
+    vec![Locator::from(SocketAddr::new(
+      Ipv4Addr::new(127, 0, 0, 1).into(),
+      7411,
+    ))]
+  }
+
+  // Marks the message with the writer and a running number
+  fn message(writer: u8, number: u8) -> Vec<u8> {
+    let mut datagram = vec![0; 100];
+    datagram[0] = writer;
+    datagram[1] = number;
+    datagram
+  }
+
+  fn sent(scheduler: &mut FlowScheduler, now: Instant) -> Vec<(u8, u8)> {
+    std::iter::from_fn(|| scheduler.next_ready(now))
+      .map(|message| (message.datagram[0], message.datagram[1]))
+      .collect()
+  }
+
+  #[test]
+  fn writer_limit_spaces_out_messages() {
+    let start = Instant::now();
+    let at = |millis| start + Duration::from_millis(millis);
+    let mut scheduler = FlowScheduler::new(FlowControllerSettings::default(), start);
+
+    // Without any limits, the writer is not scheduled.
+    assert!(!scheduler.add_writer(writer(1), None, start));
+    // 1000 bytes per second, i.e. one message of 100 bytes per 100 ms
+    let flow_control = policy::FlowControl {
+      priority: 0,
+      max_bytes_per_second: Some(1000),
+    };
+    assert!(scheduler.add_writer(writer(2), Some(flow_control), start));
+    assert_eq!(scheduler.next_ready_at(), None);
+
+    for number in 0..3 {
+      scheduler.enqueue(writer(2), message(2, number), locators(), start);
+    }
+    assert!(scheduler.is_waiting(writer(2)));
+    assert_eq!(sent(&mut scheduler, start), vec![(2, 0)]);
+    let ready_at = scheduler.next_ready_at().unwrap();
+    assert!(ready_at > at(80) && ready_at <= at(100));
+
+    assert!(sent(&mut scheduler, at(50)).is_empty());
+    assert_eq!(sent(&mut scheduler, at(100)), vec![(2, 1)]);
+    assert_eq!(sent(&mut scheduler, at(200)), vec![(2, 2)]);
+    assert!(!scheduler.is_waiting(writer(2)));
+    assert_eq!(scheduler.next_ready_at(), None);
+  }
+
+  #[test]
+  fn higher_priority_goes_first_when_participant_is_saturated() {
+    let start = Instant::now();
+    let at = |millis| start + Duration::from_millis(millis);
+    let settings = FlowControllerSettings {
+      max_bytes_per_second: Some(1000),
+      ..FlowControllerSettings::default()
+    };
+    let mut scheduler = FlowScheduler::new(settings, start);
+    let bulk = policy::FlowControl {
+      priority: -1,
+      max_bytes_per_second: None,
+    };
+    assert!(scheduler.add_writer(writer(1), Some(bulk), start));
+    // Scheduled by the participant limit, at the default priority
+    assert!(scheduler.add_writer(writer(2), None, start));
+    assert!(scheduler.add_writer(writer(3), None, start));
+
+    for number in 0..3 {
+      scheduler.enqueue(writer(1), message(1, number), locators(), start);
+    }
+    assert_eq!(sent(&mut scheduler, start), vec![(1, 0)]);
+
+    // Control messages queued later overtake the bulk messages, and those of
+    // equal priority go in the order they were queued.
+    scheduler.enqueue(writer(3), message(3, 0), locators(), at(10));
+    scheduler.enqueue(writer(2), message(2, 0), locators(), at(20));
+    assert_eq!(sent(&mut scheduler, at(100)), vec![(3, 0)]);
+    assert_eq!(sent(&mut scheduler, at(200)), vec![(2, 0)]);
+    assert_eq!(sent(&mut scheduler, at(300)), vec![(1, 1)]);
+    assert_eq!(sent(&mut scheduler, at(400)), vec![(1, 2)]);
+  }
+
+  #[test]
+  fn full_queue_drops_messages() {
+    let start = Instant::now();
+    let settings = FlowControllerSettings {
+      max_bytes_per_second: Some(1000),
+      max_queued_bytes: 250,
+      ..FlowControllerSettings::default()
+    };
+    let mut scheduler = FlowScheduler::new(settings, start);
+    assert!(scheduler.add_writer(writer(1), None, start));
+    for number in 0..4 {
+      scheduler.enqueue(writer(1), message(1, number), locators(), start);
+    }
+    let later = start + Duration::from_secs(10);
+    assert_eq!(sent(&mut scheduler, start), vec![(1, 0)]);
+    assert_eq!(sent(&mut scheduler, later), vec![(1, 1)]);
+
+    scheduler.remove_writer(writer(1));
+    assert!(!scheduler.is_waiting(writer(1)));
+    assert_eq!(scheduler.next_ready_at(), None);
+  }
+}
@@ -10,14 +10,11 @@ use crate::{
   dds::ddsdata::DDSData,
   messages::{
     header::Header,
-    protocol_id::ProtocolId,
-    protocol_version::ProtocolVersion,
     submessages::{
       elements::{parameter::Parameter, parameter_list::ParameterList},
       submessages::*,
     },
     validity_trait::Validity,
-    vendor_id::VendorId,
   },
   rtps::{Submessage, SubmessageBody},
   structure::{
@@ -47,6 +44,16 @@ impl Message {
     self.submessages.push(submessage);
   }
 
+  // Whether the message carries sample data, i.e. DATA or DATA_FRAG
+  pub fn carries_data(&self) -> bool {
+    self.submessages.iter().any(|submessage| {
+      matches!(
+        submessage.body,
+        SubmessageBody::Writer(WriterSubmessage::Data(..) | WriterSubmessage::DataFrag(..))
+      )
+    })
+  }
+
   #[cfg(test)]
   pub fn submessages(self) -> Vec<Submessage> {
     self.submessages
@@ -173,11 +180,14 @@ impl MessageBuilder {
     self
   }
 
+  // `writer_qos` is included in the inline QoS, if given. It is meant for
+  // Readers that expect inline QoS.
   pub fn data_msg(
     mut self,
     cache_change: &CacheChange,
     reader_entity_id: EntityId, // The entity id to be included in the submessage
     writer_guid: GUID,
+    writer_qos: Option<&ParameterList>,
     endianness: Endianness,
     security_plugins: Option<&SecurityPluginsHandle>,
   ) -> Self {
@@ -187,7 +197,8 @@ impl MessageBuilder {
 
     let writer_entity_id = writer_guid.entity_id;
 
-    let mut param_list = ParameterList::new(); // inline QoS goes here
+    // inline QoS goes here
+    let mut param_list = writer_qos.cloned().unwrap_or_default();
 
     // Check if we are disposing (by key or by key hash).
     // If yes, then Indicate Dispose by PID_STATUS_INFO in Inline QoS
@@ -199,7 +210,7 @@ impl MessageBuilder {
     // support whole register/unregister mechanism at all. TODO: Does this
     // make sense?
     match cache_change.data_value {
-      DDSData::Data { .. } => (), // data sample, not dispose
+      DDSData::Data { .. } | DDSData::Local { .. } => (), // data sample, not dispose
 
       DDSData::DisposeByKey { .. } => {
         param_list.push(Parameter::create_pid_status_info_parameter(
@@ -242,6 +253,7 @@ impl MessageBuilder {
       } => Some(serialized_payload.clone()), // contents is Bytes
       DDSData::DisposeByKey { ref key, .. } => Some(key.clone()),
       DDSData::DisposeByKeyHash { .. } => None,
+      DDSData::Local { ref sample } => sample.serialized_payload(),
     };
 
     #[cfg(not(feature = "security"))]
@@ -256,7 +268,7 @@ impl MessageBuilder {
           .write_to_vec()
           .map_err(|e| create_security_error_and_log!("{e:?}"))
           .and_then(|serialized_payload| {
-            match security_plugins.map(SecurityPluginsHandle::get_plugins) {
+            match security_plugins.map(SecurityPluginsHandle::read_plugins) {
               Some(security_plugins) => {
                 security_plugins
                   .encode_serialized_payload(serialized_payload, &writer_guid)
@@ -296,7 +308,9 @@ impl MessageBuilder {
 
     let flags: BitFlags<DATA_Flags> = BitFlags::<DATA_Flags>::from_endianness(endianness)
       | (match cache_change.data_value {
-        DDSData::Data { .. } => BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data),
+        DDSData::Data { .. } | DDSData::Local { .. } => {
+          BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data)
+        }
         DDSData::DisposeByKey { .. } => BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Key),
         DDSData::DisposeByKeyHash { .. } => {
           BitFlags::<DATA_Flags>::from_flag(DATA_Flags::InlineQos)
@@ -328,7 +342,8 @@ impl MessageBuilder {
     cache_change: &CacheChange,
     reader_entity_id: EntityId,
     writer_guid: GUID,
-    fragment_number: FragmentNumber, // We support only submessages with one fragment
+    writer_qos: Option<&ParameterList>, // as in data_msg()
+    fragment_number: FragmentNumber,    // We support only submessages with one fragment
     fragment_size: u16,
     sample_size: u32, // all fragments together
     endianness: Endianness,
@@ -340,11 +355,12 @@ impl MessageBuilder {
 
     let writer_entity_id = writer_guid.entity_id;
 
-    let mut param_list = ParameterList::new(); // inline QoS goes here
+    // inline QoS goes here
+    let mut param_list = writer_qos.cloned().unwrap_or_default();
 
     // Check if we are disposing by key hash
     match cache_change.data_value {
-      DDSData::Data { .. } | DDSData::DisposeByKey { .. } => (), // no => ok
+      DDSData::Data { .. } | DDSData::DisposeByKey { .. } | DDSData::Local { .. } => (), // no => ok
       DDSData::DisposeByKeyHash { .. } => {
         error!(
           "data_frag_msg: Called with DDSData::DisposeByKeyHash. This is not legit! Discarding."
@@ -393,7 +409,7 @@ impl MessageBuilder {
 
     #[cfg(feature = "security")]
     let encoded_payload = {
-      let encode_result = match security_plugins.map(SecurityPluginsHandle::get_plugins) {
+      let encode_result = match security_plugins.map(SecurityPluginsHandle::read_plugins) {
         Some(security_plugins) => {
           security_plugins
             .encode_serialized_payload(serialized_payload, &writer_guid)
@@ -440,7 +456,7 @@ impl MessageBuilder {
       BitFlags::<DATAFRAG_Flags>::from_endianness(endianness)
       // key flag
       | (match cache_change.data_value {
-        DDSData::Data { .. } => BitFlags::<DATAFRAG_Flags>::empty(),
+        DDSData::Data { .. } | DDSData::Local { .. } => BitFlags::<DATAFRAG_Flags>::empty(),
         DDSData::DisposeByKey { .. } => BitFlags::<DATAFRAG_Flags>::from_flag(DATAFRAG_Flags::Key),
         DDSData::DisposeByKeyHash { .. } => unreachable!(),
       })
@@ -524,6 +540,14 @@ impl MessageBuilder {
     self
   }
 
+  pub fn fec_parity_msg(mut self, fec_parity: FecParity, endianness: Endianness) -> Self {
+    let flags = BitFlags::<FECPARITY_Flags>::from_endianness(endianness);
+    if let Some(submessage) = fec_parity.create_submessage(flags) {
+      self.submessages.push(submessage);
+    }
+    self
+  }
+
   #[allow(clippy::too_many_arguments)] // Heartbeat just is complicated.
   pub fn heartbeat_msg(
     mut self,
@@ -563,17 +587,60 @@ impl MessageBuilder {
 
   pub fn add_header_and_build(self, guid_prefix: GuidPrefix) -> Message {
     Message {
-      header: Header {
-        protocol_id: ProtocolId::default(),
-        protocol_version: ProtocolVersion::THIS_IMPLEMENTATION,
-        vendor_id: VendorId::THIS_IMPLEMENTATION,
-        guid_prefix,
-      },
+      header: Header::new(guid_prefix),
       submessages: self.submessages,
     }
   }
 }
 
+const MESSAGE_HEADER_SIZE: usize = 20;
+
+/// Combines serialized RTPS messages to the same destination into as few
+/// messages as possible, without exceeding `max_size` bytes each. Messages
+/// are combined only if their headers are identical and they are not
+/// protected as a whole by DDS Security, i.e. do not begin with an
+/// SRTPS_PREFIX submessage.
+///
+/// The receiver state (destination and timestamp) set by one message must not
+/// apply to the next, so INFO_DST and INFO_TS submessages resetting it are
+/// inserted between the combined messages.
+pub(crate) fn concatenate_messages(messages: Vec<Vec<u8>>, max_size: usize) -> Vec<Vec<u8>> {
+  let reset = MessageBuilder::new()
+    .dst_submessage(Endianness::LittleEndian, GuidPrefix::UNKNOWN)
+    .ts_msg(Endianness::LittleEndian, None)
+    .add_header_and_build(GuidPrefix::UNKNOWN)
+    .write_to_vec_with_ctx(Endianness::LittleEndian)
+    .unwrap()
+    .split_off(MESSAGE_HEADER_SIZE);
+
+  let can_combine = |message: &[u8]| {
+    message.len() > MESSAGE_HEADER_SIZE
+      && message[MESSAGE_HEADER_SIZE] != u8::from(SubmessageKind::SRTPS_PREFIX)
+  };
+
+  let mut result: Vec<Vec<u8>> = Vec::with_capacity(messages.len());
+  let mut last_combinable = false;
+  for message in messages {
+    let combinable = can_combine(&message);
+    match result.last_mut() {
+      Some(previous)
+        if last_combinable
+          && combinable
+          && previous[..MESSAGE_HEADER_SIZE] == message[..MESSAGE_HEADER_SIZE]
+          && previous.len() + reset.len() + message.len() - MESSAGE_HEADER_SIZE <= max_size =>
+      {
+        previous.extend_from_slice(&reset);
+        previous.extend_from_slice(&message[MESSAGE_HEADER_SIZE..]);
+      }
+      _ => {
+        result.push(message);
+        last_combinable = combinable;
+      }
+    }
+  }
+  result
+}
+
 #[cfg(test)]
 #[allow(non_snake_case)]
 mod tests {
@@ -811,4 +878,28 @@ mod tests {
     info!("read_from_buffer() --> {rtps:?}");
     // if we get here without panic, the test passes
   }
+
+  #[test]
+  fn concatenate_messages_with_same_header() {
+    let message = |prefix: &[u8]| {
+      MessageBuilder::new()
+        .ts_msg(Endianness::LittleEndian, Some(Timestamp::ZERO))
+        .add_header_and_build(GuidPrefix::new(prefix))
+        .write_to_vec_with_ctx(Endianness::LittleEndian)
+        .unwrap()
+    };
+    let a = message(b"participant1");
+    let b = message(b"participant2");
+
+    let combined = concatenate_messages(vec![a.clone(), a.clone(), b.clone()], 1500);
+    assert_eq!(combined.len(), 2);
+    assert_eq!(combined[1], b);
+    let rtps = Message::read_from_buffer(&Bytes::from(combined[0].clone())).unwrap();
+    // INFO_TS, reset INFO_DST + INFO_TS, INFO_TS
+    assert_eq!(rtps.submessages().len(), 4);
+
+    // Does not fit into one message
+    let combined = concatenate_messages(vec![a.clone(), a.clone()], a.len() + 8);
+    assert_eq!(combined, vec![a.clone(), a]);
+  }
 }
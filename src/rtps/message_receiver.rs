@@ -7,8 +7,9 @@ use log::{debug, error, info, trace, warn};
 use bytes::Bytes;
 
 use crate::{
+  dds::protocol_identity::ProtocolIdentity,
   messages::{protocol_version::ProtocolVersion, submessages::submessages::*, vendor_id::VendorId},
-  rtps::{reader::Reader, Message, Submessage, SubmessageBody},
+  rtps::{reader::Reader, writer::LocalDelivery, Message, Submessage, SubmessageBody},
   structure::{
     entity::RTPSEntity,
     guid::{EntityId, GuidPrefix, GUID},
@@ -19,6 +20,7 @@ use crate::{
 #[cfg(feature = "security")]
 use crate::security::{
   cryptographic::{DecodeOutcome, DecodedSubmessage},
+  protection_cache::{EndpointProtection, ProtectionCache},
   security_plugins::SecurityPluginsHandle,
 };
 #[cfg(feature = "security")]
@@ -32,6 +34,31 @@ use crate::structure::sequence_number::SequenceNumber;
 
 const RTPS_MESSAGE_HEADER_SIZE: usize = 20;
 
+// RTPS PING is a vendor-specific probe, which at least RTI Connext sends to
+// hosts it is configured to discover. It is a bare RTPS header, where the
+// GuidPrefix is replaced by "NDDSPING".
+const RTPS_PING_MESSAGE_SIZE: usize = 16;
+
+pub(crate) fn rtps_ping_message() -> Vec<u8> {
+  let ProtocolIdentity {
+    protocol_version: version,
+    vendor_id,
+    ..
+  } = ProtocolIdentity::current();
+  let mut message = Vec::with_capacity(RTPS_PING_MESSAGE_SIZE);
+  message.extend_from_slice(b"RTPS");
+  message.extend_from_slice(&[version.major, version.minor]);
+  message.extend_from_slice(&vendor_id.as_bytes());
+  message.extend_from_slice(b"NDDSPING");
+  message
+}
+
+fn is_rtps_ping_message(msg_bytes: &[u8]) -> bool {
+  msg_bytes.len() >= RTPS_PING_MESSAGE_SIZE
+    && msg_bytes[0..4] == b"RTPS"[..]
+    && msg_bytes[9..16] == b"DDSPING"[..]
+}
+
 // Secure submessage receiving state machine:
 //
 // [None] ---SecurePrefix--> [Prefix] ---some Submessage--> [SecureSubmessage]
@@ -70,6 +97,7 @@ pub struct SecureWrapping {
 #[derive(Debug, Clone)]
 pub struct MessageReceiverState {
   pub source_guid_prefix: GuidPrefix,
+  pub source_vendor_id: VendorId,
   pub unicast_reply_locator_list: Vec<Locator>,
 
   #[allow(dead_code)]
@@ -87,6 +115,7 @@ impl Default for MessageReceiverState {
   fn default() -> Self {
     Self {
       source_guid_prefix: GuidPrefix::default(),
+      source_vendor_id: VendorId::VENDOR_UNKNOWN,
       unicast_reply_locator_list: Vec::default(),
       multicast_reply_locator_list: Vec::default(),
       source_timestamp: Some(Timestamp::INVALID),
@@ -112,6 +141,8 @@ pub(crate) struct MessageReceiver {
   // repeated messages with duplicate SequenceNumbers, but Discovery needs to see them.
   spdp_liveness_sender: mio_channel::SyncSender<GuidPrefix>,
   security_plugins: Option<SecurityPluginsHandle>,
+  // Set when an RTPS PING is received, cleared by take_ping_received()
+  ping_received: bool,
 
   own_guid_prefix: GuidPrefix,
   pub source_version: ProtocolVersion,
@@ -130,6 +161,8 @@ pub(crate) struct MessageReceiver {
   // For certain topics we have to allow unprotected rtps messages even if the domain is
   // rtps-protected
   must_be_rtps_protection_special_case: bool,
+  #[cfg(feature = "security")]
+  protection_cache: ProtectionCache,
 }
 
 impl MessageReceiver {
@@ -144,6 +177,7 @@ impl MessageReceiver {
       acknack_sender,
       spdp_liveness_sender,
       security_plugins,
+      ping_received: false,
       own_guid_prefix: participant_guid_prefix,
 
       source_version: ProtocolVersion::THIS_IMPLEMENTATION,
@@ -161,6 +195,26 @@ impl MessageReceiver {
       #[cfg(feature = "security")]
       // Protection on by default
       must_be_rtps_protection_special_case: true,
+      #[cfg(feature = "security")]
+      protection_cache: ProtectionCache::default(),
+    }
+  }
+
+  // The protection of a local endpoint. The plugins are consulted only when
+  // the protection of local endpoints has changed since the last time.
+  #[cfg(feature = "security")]
+  fn local_endpoint_protection(&mut self, local_endpoint_guid: GUID) -> EndpointProtection {
+    match &self.security_plugins {
+      None => EndpointProtection::NONE,
+      Some(plugins_handle) => self.protection_cache.get(
+        plugins_handle.protection_generation(),
+        local_endpoint_guid,
+        || {
+          plugins_handle
+            .read_plugins()
+            .endpoint_protection(&local_endpoint_guid)
+        },
+      ),
     }
   }
 
@@ -186,6 +240,7 @@ impl MessageReceiver {
   fn clone_partial_message_receiver_state(&self) -> MessageReceiverState {
     MessageReceiverState {
       source_guid_prefix: self.source_guid_prefix,
+      source_vendor_id: self.source_vendor_id,
       unicast_reply_locator_list: self.unicast_reply_locator_list.clone(),
       multicast_reply_locator_list: self.multicast_reply_locator_list.clone(),
       source_timestamp: self.source_timestamp,
@@ -212,17 +267,19 @@ impl MessageReceiver {
     self.available_readers.get_mut(&reader_id)
   }
 
+  // Whether an RTPS PING has been received since the previous call.
+  pub fn take_ping_received(&mut self) -> bool {
+    std::mem::take(&mut self.ping_received)
+  }
+
   pub fn handle_received_packet(&mut self, msg_bytes: &Bytes) {
     // Check for RTPS ping message. At least RTI implementation sends these.
-    // What should we do with them? The spec does not say.
+    // The spec does not say what to do with them. The event loop may answer
+    // with an SPDP announcement, see take_ping_received().
     if msg_bytes.len() < RTPS_MESSAGE_HEADER_SIZE {
-      if msg_bytes.len() >= 16
-        && msg_bytes[0..4] == b"RTPS"[..]
-        && msg_bytes[9..16] == b"DDSPING"[..]
-      {
-        // TODO: Add some sensible ping message handling here.
-        info!("Received RTPS PING. Do not know how to respond.");
-        debug!("Data was {:?}", &msg_bytes);
+      if is_rtps_ping_message(msg_bytes) {
+        debug!("Received RTPS PING.");
+        self.ping_received = true;
       } else {
         warn!("Message is shorter than RTPS header. Cannot deserialize.");
         debug!("Data was {:?}", &msg_bytes);
@@ -284,7 +341,7 @@ impl MessageReceiver {
       }
 
       Some(security_plugins_handle) => {
-        let security_plugins = security_plugins_handle.get_plugins();
+        let security_plugins = security_plugins_handle.read_plugins();
 
         // If the first submessage is SecureRTPSPrefix, the message has to be decoded
         // using the cryptographic plugin
@@ -309,6 +366,12 @@ impl MessageReceiver {
             Ok(DecodeOutcome::ValidatingReceiverSpecificMACFailed) => {
               return trace!("Failed to validate the receiver-specif MAC for the rtps message.");
             }
+            Ok(DecodeOutcome::LimitExceeded(rejection)) => {
+              return debug!(
+                "Dropping rtps message from {:?}: {rejection}",
+                self.source_guid_prefix
+              );
+            }
             Ok(DecodeOutcome::ParticipantCryptoHandleNotFound(guid_prefix)) => {
               return trace!(
                 "No participant crypto handle found for the participant {:?} for rtps message \
@@ -391,16 +454,13 @@ impl MessageReceiver {
                 Some(_) => {}
 
                 #[cfg(feature = "security")]
-                Some(plugins_handle) => {
+                Some(_) => {
                   for target_entity_id in available_target_entity_ids {
                     let destination_guid = GUID {
                       prefix: self.dest_guid_prefix,
                       entity_id: target_entity_id,
                     };
-                    if plugins_handle
-                      .get_plugins()
-                      .submessage_not_protected(&destination_guid)
-                    {
+                    if !self.local_endpoint_protection(destination_guid).submessage {
                       self.handle_writer_submessage(target_entity_id, submessage.clone());
                     }
                   }
@@ -414,15 +474,12 @@ impl MessageReceiver {
                 Some(_) => {}
 
                 #[cfg(feature = "security")]
-                Some(plugins_handle) => {
+                Some(_) => {
                   let destination_guid = GUID {
                     prefix: self.dest_guid_prefix,
                     entity_id: receiver_entity_id,
                   };
-                  if plugins_handle
-                    .get_plugins()
-                    .submessage_not_protected(&destination_guid)
-                  {
+                  if !self.local_endpoint_protection(destination_guid).submessage {
                     self.handle_writer_submessage(receiver_entity_id, submessage);
                   } else {
                     error!(
@@ -441,27 +498,19 @@ impl MessageReceiver {
               self.handle_reader_submessage(submessage);
             }
             #[cfg(feature = "security")]
-            match self
-              .security_plugins
-              .as_ref()
-              .map(SecurityPluginsHandle::get_plugins)
             {
-              None => self.handle_reader_submessage(submessage),
-              Some(plugins) => {
-                let destination_guid = GUID {
-                  prefix: self.dest_guid_prefix,
-                  entity_id: submessage.receiver_entity_id(),
-                };
-                #[cfg(feature = "security")]
-                // This match branch can only be taken with security feature
-                if plugins.submessage_not_protected(&destination_guid) {
-                  self.handle_reader_submessage(submessage);
-                } else {
-                  error!(
-                    "No writer with unprotected submessages found for the GUID {:?}",
-                    destination_guid
-                  );
-                }
+              let destination_guid = GUID {
+                prefix: self.dest_guid_prefix,
+                entity_id: submessage.receiver_entity_id(),
+              };
+              // Without plugins nothing is protected
+              if !self.local_endpoint_protection(destination_guid).submessage {
+                self.handle_reader_submessage(submessage);
+              } else {
+                error!(
+                  "No writer with unprotected submessages found for the GUID {:?}",
+                  destination_guid
+                );
               }
             }
           }
@@ -581,6 +630,18 @@ impl MessageReceiver {
       entity_id: writer_entity_id,
     };
 
+    // Payloads for readers of unprotected topics need no decoding, so the
+    // plugins are not given for them.
+    #[cfg(feature = "security")]
+    let security_plugins = if self
+      .local_endpoint_protection(GUID::new(self.own_guid_prefix, target_reader_entity_id))
+      .payload
+    {
+      self.security_plugins.clone()
+    } else {
+      None
+    };
+    #[cfg(not(feature = "security"))]
     let security_plugins = self.security_plugins.clone();
 
     let target_reader = if let Some(target_reader) = self.reader_mut(target_reader_entity_id) {
@@ -642,6 +703,10 @@ impl MessageReceiver {
       WriterSubmessage::HeartbeatFrag(heartbeatfrag, _flags) => {
         target_reader.handle_heartbeatfrag_msg(&heartbeatfrag, &mr_state);
       }
+
+      WriterSubmessage::FecParity(fec_parity, _flags) => {
+        target_reader.handle_fec_parity_msg(&fec_parity, &mr_state);
+      }
     }
   }
 
@@ -676,7 +741,7 @@ impl MessageReceiver {
     serialized_payload
       // If there is an encoded_payload, decode it
       .map(
-        |encoded_payload| match security_plugins.map(SecurityPluginsHandle::get_plugins) {
+        |encoded_payload| match security_plugins.map(SecurityPluginsHandle::read_plugins) {
           Some(security_plugins) => security_plugins
             .decode_serialized_payload(
               encoded_payload,
@@ -752,7 +817,7 @@ impl MessageReceiver {
       ..
     } = datafrag.clone();
 
-    match security_plugins.map(SecurityPluginsHandle::get_plugins) {
+    match security_plugins.map(SecurityPluginsHandle::read_plugins) {
       Some(security_plugins) => {
         // Decode
         security_plugins
@@ -851,6 +916,24 @@ impl MessageReceiver {
       ReaderSubmessage::NackFrag(_, _) => {
         // TODO: Implement NackFrag handling
       }
+
+      ReaderSubmessage::AppAck(app_ack, _) => {
+        // The submessage kind is vendor-specific
+        if self.source_vendor_id != VendorId::THIS_IMPLEMENTATION {
+          return;
+        }
+        // Goes to the Writer the same way as AckNack
+        match self
+          .acknack_sender
+          .try_send((self.source_guid_prefix, AckSubmessage::AppAck(app_ack)))
+        {
+          Ok(_) => (),
+          Err(TrySendError::Full(_)) => {
+            info!("AckNack pipe full. Looks like I am very busy. Discarding submessage.");
+          }
+          Err(e) => warn!("AckNack pipe fail: {:?}", e),
+        }
+      }
     }
   }
 
@@ -870,7 +953,7 @@ impl MessageReceiver {
         // Call 8.5.1.9.6 Operation: preprocess_secure_submsg to determine what
         // the submessage contains and then proceed to decode and process accordingly.
 
-        let decode_result = security_plugins_handle.get_plugins().decode_submessage(
+        let decode_result = security_plugins_handle.read_plugins().decode_submessage(
           (
             sec_prefix.clone(),
             encoded_submessage.clone(),
@@ -908,7 +991,7 @@ impl MessageReceiver {
                       && target_reader.entity_id() == EntityId::P2P_BUILTIN_PARTICIPANT_STATELESS_READER)
                     )
                     &&
-                    security_plugins_handle.get_plugins()
+                    security_plugins_handle.read_plugins()
                     .confirm_local_endpoint_guid(&approved_receiving_datareader_crypto_handles,
                       &GUID { prefix: self.dest_guid_prefix,entity_id: target_reader.entity_id() })
               }){
@@ -922,7 +1005,7 @@ impl MessageReceiver {
                 entity_id: receiver_entity_id,
               };
               if security_plugins_handle
-                .get_plugins()
+                .read_plugins()
                 .confirm_local_endpoint_guid(
                   &approved_receiving_datareader_crypto_handles,
                   &receiver_guid,
@@ -944,7 +1027,7 @@ impl MessageReceiver {
               entity_id: receiver_entity_id,
             };
             if security_plugins_handle
-              .get_plugins()
+              .read_plugins()
               .confirm_local_endpoint_guid(
                 &approved_receiving_datawriter_crypto_handles,
                 &receiver_guid,
@@ -971,6 +1054,12 @@ impl MessageReceiver {
           Ok(DecodeOutcome::ValidatingReceiverSpecificMACFailed) => {
             trace!("No endpoints passed the receiver-specific MAC validation for the submessage.");
           }
+          Ok(DecodeOutcome::LimitExceeded(rejection)) => {
+            debug!(
+              "Dropping submessage from {:?}: {rejection}",
+              self.source_guid_prefix
+            );
+          }
           Ok(DecodeOutcome::ParticipantCryptoHandleNotFound(guid_prefix)) => {
             trace!(
               "No participant crypto handle found for the participant {:?} for submessage \
@@ -1032,6 +1121,17 @@ impl MessageReceiver {
     }
   }
 
+  // Gives samples written for intra-process delivery to their local Readers.
+  pub fn deliver_local_changes(&mut self, deliveries: Vec<LocalDelivery>) {
+    for delivery in deliveries {
+      for eid in delivery.readers {
+        if let Some(reader) = self.available_readers.get_mut(&eid) {
+          reader.handle_local_change(&delivery.change);
+        }
+      }
+    }
+  }
+
   pub fn notify_data_to_readers(&mut self, readers: Vec<EntityId>) {
     for eid in readers {
       self
@@ -1091,11 +1191,10 @@ mod tests {
     sync::{Arc, Mutex, RwLock},
   };
 
-  use speedy::{Readable, Writable};
+  use speedy::{Endianness, Readable, Writable};
   use log::info;
   use serde::{Deserialize, Serialize};
   use mio_extras::channel as mio_channel;
-  use byteorder::LittleEndian;
 
   use crate::{
     dds::{
@@ -1108,7 +1207,7 @@ mod tests {
     mio_source,
     network::udp_sender::UDPSender,
     rtps::reader::ReaderIngredients,
-    serialization::from_bytes,
+    serialization::from_bytes_with_endianness,
     structure::{dds_cache::DDSCache, guid::EntityKind},
   };
   use super::*;
@@ -1193,6 +1292,7 @@ mod tests {
       data_reader_waker: data_reader_waker.clone(),
       poll_event_sender: notification_event_sender,
       security_plugins: None,
+      status_record: Arc::default(),
     };
 
     let mut new_reader = Reader::new(
@@ -1244,7 +1344,8 @@ mod tests {
       y: i32,
       size: i32,
     }
-    let (deserialized_shape_type, _) = from_bytes::<ShapeType, LittleEndian>(&a.data()).unwrap();
+    let (deserialized_shape_type, _) =
+      from_bytes_with_endianness::<ShapeType>(&a.data(), Endianness::LittleEndian).unwrap();
     info!("deserialized shapeType: {:?}", deserialized_shape_type);
 
     // Verify the color in the deserialized value is correct
@@ -1297,4 +1398,30 @@ mod tests {
     let new_header = Header::read_from_buffer(&bytes).unwrap();
     assert_eq!(header, new_header);
   }
+
+  #[test]
+  fn mr_test_rtps_ping() {
+    let (acknack_sender, _acknack_receiver) =
+      mio_channel::sync_channel::<(GuidPrefix, AckSubmessage)>(10);
+    let (spdp_liveness_sender, _spdp_liveness_receiver) = mio_channel::sync_channel(8);
+    let mut message_receiver = MessageReceiver::new(
+      GUID::default().prefix,
+      acknack_sender,
+      spdp_liveness_sender,
+      None,
+    );
+
+    let ping = rtps_ping_message();
+    assert_eq!(ping.len(), RTPS_PING_MESSAGE_SIZE);
+    assert_eq!(&ping[8..], b"NDDSPING");
+
+    // Shorter than a header, but not a ping
+    let not_ping = Bytes::from_static(b"RTPS\x02\x04\x01\x0fNOTAPING");
+    message_receiver.handle_received_packet(&not_ping);
+    assert!(!message_receiver.take_ping_received());
+
+    message_receiver.handle_received_packet(&Bytes::from(ping));
+    assert!(message_receiver.take_ping_received());
+    assert!(!message_receiver.take_ping_received());
+  }
 }
@@ -1,5 +1,5 @@
 use std::{
-  collections::BTreeMap,
+  collections::{BTreeMap, BTreeSet},
   fmt, iter,
   rc::Rc,
   sync::{Arc, Mutex, MutexGuard},
@@ -8,17 +8,24 @@ use std::{
 };
 
 use mio_06::Token;
-use mio_extras::{channel as mio_channel, timer::Timer};
+use mio_extras::{
+  channel as mio_channel,
+  timer::{Timeout, Timer},
+};
 use log::{debug, error, info, trace, warn};
 use enumflags2::BitFlags;
 use speedy::{Endianness, Writable};
 
 use crate::{
   dds::{
+    communication_status::ReaderStatusRecord,
+    compliance,
     ddsdata::DDSData,
+    key::KeyHash,
     qos::{policy, HasQoSPolicy, QosPolicies},
     statusevents::{
-      CountWithChange, DataReaderStatus, DomainParticipantStatusEvent, StatusChannelSender,
+      CountWithChange, DataReaderStatus, DomainParticipantStatusEvent, SampleRejectedStatusKind,
+      StatusChannelSender, WriterMisbehavior,
     },
     with_key::{
       datawriter::{WriteOptions, WriteOptionsBuilder},
@@ -27,8 +34,6 @@ use crate::{
   },
   messages::{
     header::Header,
-    protocol_id::ProtocolId,
-    protocol_version::ProtocolVersion,
     submessages::{
       elements::{
         inline_qos::InlineQos, parameter_list::ParameterList, serialized_payload::SerializedPayload,
@@ -40,8 +45,8 @@ use crate::{
   mio_source,
   network::udp_sender::UDPSender,
   rtps::{
-    fragment_assembler::FragmentAssembler, message_receiver::MessageReceiverState,
-    rtps_writer_proxy::RtpsWriterProxy, Message,
+    fec::FecDecoder, fragment_assembler::FragmentAssembler, message_receiver::MessageReceiverState,
+    rtps_writer_proxy::RtpsWriterProxy, timer_wheel::TimerWheel, Message,
   },
   structure::{
     cache_change::{CacheChange, ChangeKind},
@@ -63,7 +68,19 @@ use crate::no_security::SecurityPluginsHandle;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum TimedEvent {
-  DeadlineMissedCheck,
+  QosTimerCheck,
+}
+
+// QoS timers of a Reader. These are kept in a TimerWheel, and only the
+// earliest one is registered to the event loop timer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum QosTimer {
+  // DEADLINE of an instance. Used when the writer sends key hashes inline.
+  InstanceDeadline(KeyHash),
+  // DEADLINE of a writer, whose data does not identify the instance.
+  WriterDeadline(GUID),
+  // LIVELINESS lease of a MANUAL_BY_TOPIC writer
+  WriterLiveliness(GUID),
 }
 
 // Some pieces necessary to construct a reader.
@@ -80,6 +97,8 @@ pub(crate) struct ReaderIngredients {
   pub data_reader_command_receiver: mio_channel::Receiver<ReaderCommand>,
   pub(crate) data_reader_waker: Arc<Mutex<Option<Waker>>>,
   pub(crate) poll_event_sender: mio_source::PollEventSender,
+  // Communication statuses polled through the DataReader
+  pub status_record: Arc<ReaderStatusRecord>,
 
   pub(crate) security_plugins: Option<SecurityPluginsHandle>,
 }
@@ -105,6 +124,7 @@ pub(crate) struct Reader {
   // Should the instant be sent?
   notification_sender: mio_channel::SyncSender<()>,
   status_sender: StatusChannelSender<DataReaderStatus>,
+  status_record: Arc<ReaderStatusRecord>,
   udp_sender: Rc<UDPSender>,
 
   // By default, this reader is a StatefulReader (see RTPS spec section 8.4.12)
@@ -136,11 +156,28 @@ pub(crate) struct Reader {
 
   fragment_assemblers: BTreeMap<GUID, FragmentAssembler>,
   last_fragment_garbage_collect: Timestamp,
+  // Recently received samples of the writers that send FEC parity
+  fec_decoders: BTreeMap<GUID, FecDecoder>,
   matched_writers: BTreeMap<GUID, RtpsWriterProxy>,
   writer_match_count_total: i32, // total count, never decreases
 
   requested_deadline_missed_count: i32,
   offered_incompatible_qos_count: i32,
+  writer_restart_count: i32,
+  writer_quarantine_count: i32,
+  samples_rejected_count: i32,
+
+  // DEADLINE and LIVELINESS timers
+  qos_timers: TimerWheel<QosTimer>,
+  // The expiration time and event loop timeout registered for qos_timers
+  qos_timer_armed: Option<(Timestamp, Timeout)>,
+  // The writer who last updated each instance. Instance deadlines are
+  // cancelled when the writer is lost.
+  instance_writers: BTreeMap<KeyHash, GUID>,
+  // Lease durations of matched writers with MANUAL_BY_TOPIC liveliness
+  writer_liveliness_leases: BTreeMap<GUID, Duration>,
+  // Writers in writer_liveliness_leases whose lease has expired
+  not_alive_writers: BTreeSet<GUID>,
 
   pub(crate) timed_event_timer: Timer<TimedEvent>,
   pub(crate) data_reader_command_receiver: mio_channel::Receiver<ReaderCommand>,
@@ -183,6 +220,7 @@ impl Reader {
     Self {
       notification_sender: i.notification_sender,
       status_sender: i.status_sender,
+      status_record: i.status_record,
       udp_sender,
       like_stateless: i.like_stateless,
       reliability: i
@@ -202,10 +240,19 @@ impl Reader {
       received_heartbeat_count: 0,
       fragment_assemblers: BTreeMap::new(),
       last_fragment_garbage_collect: Timestamp::now(),
+      fec_decoders: BTreeMap::new(),
       matched_writers: BTreeMap::new(),
       writer_match_count_total: 0,
       requested_deadline_missed_count: 0,
       offered_incompatible_qos_count: 0,
+      writer_restart_count: 0,
+      writer_quarantine_count: 0,
+      samples_rejected_count: 0,
+      qos_timers: TimerWheel::new(Timestamp::now()),
+      qos_timer_armed: None,
+      instance_writers: BTreeMap::new(),
+      writer_liveliness_leases: BTreeMap::new(),
+      not_alive_writers: BTreeSet::new(),
       timed_event_timer,
       data_reader_command_receiver: i.data_reader_command_receiver,
       data_reader_waker: i.data_reader_waker,
@@ -224,25 +271,8 @@ impl Reader {
     self.guid().entity_id.as_token()
   }
 
-  pub fn set_requested_deadline_check_timer(&mut self) {
-    if let Some(deadline) = self.qos_policy.deadline {
-      debug!(
-        "GUID={:?} set_requested_deadline_check_timer: {:?}",
-        self.my_guid,
-        deadline.0.to_std()
-      );
-      self
-        .timed_event_timer
-        .set_timeout(deadline.0.to_std(), TimedEvent::DeadlineMissedCheck);
-    } else {
-      trace!(
-        "GUID={:?} - no deadline policy - do not set set_requested_deadline_check_timer",
-        self.my_guid
-      );
-    }
-  }
-
   pub fn send_status_change(&self, change: DataReaderStatus) {
+    self.status_record.record(&change);
     match self.status_sender.try_send(change) {
       Ok(()) => (), // expected result
       Err(mio_channel::TrySendError::Full(_)) => {
@@ -270,60 +300,195 @@ impl Reader {
       .unwrap_or_else(|e| error!("Cannot report participant status: {e:?}"));
   }
 
-  // The deadline that the DataReader was expecting through its QosPolicy
-  // DEADLINE was not respected for a specific instance
-  // if statusChange is returned it should be send to DataReader
-  // this calculation should be repeated every self.qos_policy.deadline
-  fn calculate_if_requested_deadline_is_missed(&mut self) -> Vec<DataReaderStatus> {
-    debug!("calculate_if_requested_deadline_is_missed");
+  fn deadline_period(&self) -> Option<Duration> {
+    match self.qos_policy.deadline {
+      Some(policy::Deadline(period)) if period != Duration::INFINITE => Some(period),
+      _ => None,
+    }
+  }
 
-    let deadline_duration = match self.qos_policy.deadline {
-      None => return vec![],
-      Some(policy::Deadline(deadline_duration)) => deadline_duration,
+  // Make sure that the event loop timer wakes us up when the next QoS timer
+  // expires.
+  fn arm_qos_timer(&mut self) {
+    let next = match self.qos_timers.next_expiration() {
+      Some(next) => next,
+      None => return,
     };
-
-    let mut changes: Vec<DataReaderStatus> = vec![];
-    let now = Timestamp::now();
-    for writer_proxy in self.matched_writers.values_mut() {
-      if let Some(last_change) = writer_proxy.last_change_timestamp() {
-        let since_last = now.duration_since(last_change);
-        // if time singe last received message is greater than deadline increase status
-        // and return notification.
-        trace!(
-          "Comparing deadlines: {:?} - {:?}",
-          since_last,
-          deadline_duration
-        );
-        if since_last > deadline_duration {
-          debug!(
-            "Deadline missed: {:?} - {:?}",
-            since_last, deadline_duration
-          );
-          self.requested_deadline_missed_count += 1;
-          changes.push(DataReaderStatus::RequestedDeadlineMissed {
-            count: CountWithChange::start_from(self.requested_deadline_missed_count, 1),
-          });
-        }
-      } else {
-        // no messages received ever so deadline must be missed.
-        // TODO: But what if the Reader or WriterProxy was just created?
-        self.requested_deadline_missed_count += 1;
-        changes.push(DataReaderStatus::RequestedDeadlineMissed {
-          count: CountWithChange::start_from(self.requested_deadline_missed_count, 1),
-        });
+    match self.qos_timer_armed {
+      Some((armed_at, _)) if armed_at <= next => return, // already armed early enough
+      Some((_, ref timeout)) => {
+        self.timed_event_timer.cancel_timeout(timeout);
       }
-    } // for
-    changes
-  } // fn
+      None => (),
+    }
+    let delay = next.duration_since(Timestamp::now()).to_std();
+    trace!(
+      "GUID={:?} arm_qos_timer: next in {:?}, {} timers",
+      self.my_guid,
+      delay,
+      self.qos_timers.len()
+    );
+    let timeout = self
+      .timed_event_timer
+      .set_timeout(delay, TimedEvent::QosTimerCheck);
+    self.qos_timer_armed = Some((next, timeout));
+  }
 
   pub fn handle_timed_event(&mut self) {
     while let Some(e) = self.timed_event_timer.poll() {
       match e {
-        TimedEvent::DeadlineMissedCheck => {
-          self.handle_requested_deadline_event();
-          self.set_requested_deadline_check_timer(); // re-prime timer
+        TimedEvent::QosTimerCheck => {
+          self.qos_timer_armed = None;
+          self.handle_qos_timers();
+        }
+      }
+    }
+  }
+
+  fn handle_qos_timers(&mut self) {
+    let now = Timestamp::now();
+    for timer in self.qos_timers.expire(now) {
+      match timer {
+        QosTimer::InstanceDeadline(_) | QosTimer::WriterDeadline(_) => {
+          // The deadline that the DataReader was expecting through its QosPolicy
+          // DEADLINE was not respected for a specific instance.
+          if let Some(period) = self.deadline_period() {
+            debug!("Deadline missed: {:?} topic={:?}", timer, self.topic_name);
+            self.requested_deadline_missed_count += 1;
+            self.send_status_change(DataReaderStatus::RequestedDeadlineMissed {
+              count: CountWithChange::start_from(self.requested_deadline_missed_count, 1),
+            });
+            // The deadline is missed again after another period without data.
+            self.qos_timers.schedule(timer, now + period);
+          }
+        }
+        QosTimer::WriterLiveliness(writer) => {
+          if self.writer_liveliness_leases.contains_key(&writer)
+            && self.not_alive_writers.insert(writer)
+          {
+            info!(
+              "Writer {:?} lost liveliness. topic={:?}",
+              writer, self.topic_name
+            );
+            self.send_liveliness_changed(-1);
+          }
+        }
+      }
+    }
+    self.arm_qos_timer();
+  }
+
+  // Report a change in the number of alive writers. Writers that are not
+  // alive are counted as not_alive.
+  fn send_liveliness_changed(&self, alive_change: i32) {
+    let not_alive = self.not_alive_writers.len() as i32;
+    let alive = self.writer_liveliness_leases.len() as i32 - not_alive;
+    self.send_status_change(DataReaderStatus::LivelinessChanged {
+      alive_total: CountWithChange::new(alive, alive_change),
+      not_alive_total: CountWithChange::new(not_alive, -alive_change),
+    });
+  }
+
+  // Any message from a MANUAL_BY_TOPIC writer renews its liveliness lease.
+  fn writer_asserted_liveliness(&mut self, writer: GUID, now: Timestamp) {
+    if let Some(lease) = self.writer_liveliness_leases.get(&writer).copied() {
+      self
+        .qos_timers
+        .schedule(QosTimer::WriterLiveliness(writer), now + lease);
+      if self.not_alive_writers.remove(&writer) {
+        info!(
+          "Writer {:?} regained liveliness. topic={:?}",
+          writer, self.topic_name
+        );
+        self.send_liveliness_changed(1);
+      }
+      self.arm_qos_timer();
+    }
+  }
+
+  // Received a sample: restart its deadline.
+  fn restart_deadline(
+    &mut self,
+    writer: GUID,
+    key_hash: Option<KeyHash>,
+    change_kind: ChangeKind,
+    now: Timestamp,
+  ) {
+    let period = match self.deadline_period() {
+      Some(period) => period,
+      None => return,
+    };
+    match key_hash {
+      Some(key_hash) => {
+        // The writer identifies instances, so writer-level deadline is not needed.
+        self.qos_timers.cancel(&QosTimer::WriterDeadline(writer));
+        if change_kind == ChangeKind::Alive {
+          self
+            .qos_timers
+            .schedule(QosTimer::InstanceDeadline(key_hash), now + period);
+          self.instance_writers.insert(key_hash, writer);
+        } else {
+          // Deadline does not apply to disposed or unregistered instances.
+          self
+            .qos_timers
+            .cancel(&QosTimer::InstanceDeadline(key_hash));
+          self.instance_writers.remove(&key_hash);
         }
       }
+      None => {
+        self
+          .qos_timers
+          .schedule(QosTimer::WriterDeadline(writer), now + period);
+      }
+    }
+    self.arm_qos_timer();
+  }
+
+  // Start tracking DEADLINE and LIVELINESS of a newly matched writer.
+  fn start_writer_qos_timers(&mut self, writer: GUID, offered_qos: &QosPolicies) {
+    let now = Timestamp::now();
+    if let Some(period) = self.deadline_period() {
+      self
+        .qos_timers
+        .schedule(QosTimer::WriterDeadline(writer), now + period);
+    }
+    if let Some(policy::Liveliness::ManualByTopic { lease_duration }) = offered_qos.liveliness {
+      if lease_duration != Duration::INFINITE {
+        self.writer_liveliness_leases.insert(writer, lease_duration);
+        self
+          .qos_timers
+          .schedule(QosTimer::WriterLiveliness(writer), now + lease_duration);
+        self.send_liveliness_changed(1);
+      }
+    }
+    self.arm_qos_timer();
+  }
+
+  fn stop_writer_qos_timers(&mut self, writer: GUID) {
+    self.qos_timers.cancel(&QosTimer::WriterDeadline(writer));
+    let mut lost_instances = Vec::new();
+    self.instance_writers.retain(|key_hash, w| {
+      if *w == writer {
+        lost_instances.push(*key_hash);
+      }
+      *w != writer
+    });
+    for key_hash in lost_instances {
+      self
+        .qos_timers
+        .cancel(&QosTimer::InstanceDeadline(key_hash));
+    }
+
+    if self.writer_liveliness_leases.contains_key(&writer) {
+      self.qos_timers.cancel(&QosTimer::WriterLiveliness(writer));
+      let was_alive = !self.not_alive_writers.remove(&writer);
+      self.writer_liveliness_leases.remove(&writer);
+      let not_alive = self.not_alive_writers.len() as i32;
+      let alive = self.writer_liveliness_leases.len() as i32 - not_alive;
+      self.send_status_change(DataReaderStatus::LivelinessChanged {
+        alive_total: CountWithChange::new(alive, if was_alive { -1 } else { 0 }),
+        not_alive_total: CountWithChange::new(not_alive, if was_alive { 0 } else { -1 }),
+      });
     }
   }
 
@@ -336,6 +501,7 @@ impl Reader {
           warn!("RESET_REQUESTED_DEADLINE_STATUS not implemented!");
           // TODO: This should be implemented.
         }
+        Ok(ReaderCommand::AcknowledgeSamples { samples }) => self.acknowledge_samples(samples),
         // Disconnected is normal when terminating
         Err(TryRecvError::Disconnected) => {
           trace!("DataReader disconnected");
@@ -349,10 +515,29 @@ impl Reader {
     }
   }
 
-  fn handle_requested_deadline_event(&mut self) {
-    debug!("handle_requested_deadline_event");
-    for missed_deadline in self.calculate_if_requested_deadline_is_missed() {
-      self.send_status_change(missed_deadline);
+  fn application_acknowledgments(&self) -> bool {
+    self.qos_policy.acknowledgment() == Some(policy::Acknowledgment::ApplicationExplicit)
+  }
+
+  // Marks samples acknowledged by the application, and tells their Writers.
+  fn acknowledge_samples(&mut self, samples: BTreeMap<GUID, Vec<SequenceNumber>>) {
+    if !self.application_acknowledgments() {
+      debug!(
+        "Samples acknowledged without Acknowledgment::ApplicationExplicit. Ignoring. topic={:?}",
+        self.topic_name
+      );
+      return;
+    }
+    for (writer_guid, seq_nums) in samples {
+      if self.matched_writer(writer_guid).is_none() {
+        debug!("Acknowledged samples of unmatched writer {writer_guid:?}");
+        continue;
+      }
+      self.with_mutable_writer_proxy(writer_guid, |this, writer_proxy| {
+        writer_proxy.app_acknowledge(&seq_nums);
+        let locators = writer_proxy.unicast_locator_list.clone();
+        this.send_app_ack(writer_proxy, &locators);
+      });
     }
   }
 
@@ -395,6 +580,17 @@ impl Reader {
     debug!("update_writer_proxy topic={:?}", self.topic_name);
     let writer = proxy.remote_writer_guid;
 
+    // Partition mismatch is not a QoS incompatibility. We just do not match.
+    // If Partitions were changed, a previous match is removed.
+    if !offered_qos.partitions_match(&self.qos_policy) {
+      debug!(
+        "update_writer_proxy - Partitions do not match. topic={:?} writer={:?}",
+        self.topic_name, writer
+      );
+      self.remove_writer_proxy(writer);
+      return;
+    }
+
     match offered_qos.compliance_failure_wrt(&self.qos_policy) {
       None => {
         // success, update or insert
@@ -415,6 +611,16 @@ impl Reader {
             "Matched new remote writer on topic={:?} writer={:?}",
             self.topic_name, writer
           );
+          if offered_qos.data_representation_differs(&self.qos_policy) {
+            warn!(
+              "Data representation differs: topic={:?} writer={:?} offered={:?} requested={:?}",
+              self.topic_name,
+              writer,
+              offered_qos.data_representation(),
+              self.qos_policy.data_representation()
+            );
+          }
+          self.start_writer_qos_timers(writer, offered_qos);
         }
       }
       Some(bad_policy_id) => {
@@ -443,6 +649,68 @@ impl Reader {
     }
   }
 
+  // A matched writer has restarted its sequence numbering, most likely because
+  // it was restarted with the same GUID. Apply the WriterRestart policy.
+  // Returns true, if messages from the writer should still be processed.
+  fn handle_writer_restart(&mut self, writer_proxy: &mut RtpsWriterProxy) -> bool {
+    let writer = writer_proxy.remote_writer_guid;
+    let policy = self.qos_policy.writer_restart().unwrap_or_default();
+    warn!(
+      "Writer {:?} has restarted its sequence numbering. Applying {:?}. topic={:?}",
+      writer, policy, self.topic_name
+    );
+    match policy {
+      policy::WriterRestart::TreatAsNewWriter => {
+        writer_proxy.reset_sequence_numbering();
+        self.fragment_assemblers.remove(&writer);
+        self.fec_decoders.remove(&writer);
+        self
+          .acquire_the_topic_cache_guard()
+          .writer_restarted(writer);
+      }
+      policy::WriterRestart::Reject => writer_proxy.rejected = true,
+    }
+    self.writer_restart_count += 1;
+    self.send_status_change(DataReaderStatus::WriterRestarted {
+      count: CountWithChange::new(self.writer_restart_count, 1),
+      writer,
+      policy,
+    });
+    policy == policy::WriterRestart::TreatAsNewWriter
+  }
+
+  // A matched writer has sent an invalid submessage, which is ignored. Apply
+  // the WriterQuarantine policy, if any.
+  fn writer_misbehaved(
+    &mut self,
+    writer_proxy: &mut RtpsWriterProxy,
+    misbehavior: WriterMisbehavior,
+  ) {
+    let writer = writer_proxy.remote_writer_guid;
+    warn!(
+      "Writer {:?} violated the RTPS protocol: {:?}. topic={:?}",
+      writer, misbehavior, self.topic_name
+    );
+    let quarantine = match self.qos_policy.writer_quarantine() {
+      Some(quarantine) => quarantine,
+      None => return,
+    };
+    if writer_proxy.record_violation(Timestamp::now(), quarantine) {
+      warn!(
+        "Quarantining Writer {:?} for {:?}. topic={:?}",
+        writer, quarantine.duration, self.topic_name
+      );
+      self.fragment_assemblers.remove(&writer);
+      self.writer_quarantine_count += 1;
+      self.send_status_change(DataReaderStatus::WriterQuarantined {
+        count: CountWithChange::new(self.writer_quarantine_count, 1),
+        writer,
+        last_misbehavior: misbehavior,
+        duration: quarantine.duration,
+      });
+    }
+  }
+
   // return value counts how many new proxies were added
   fn matched_writer_update(&mut self, proxy: RtpsWriterProxy) -> i32 {
     if let Some(op) = self.matched_writer_mut(proxy.remote_writer_guid) {
@@ -457,6 +725,13 @@ impl Reader {
   pub fn remove_writer_proxy(&mut self, writer_guid: GUID) {
     if self.matched_writers.contains_key(&writer_guid) {
       self.matched_writers.remove(&writer_guid);
+      self.fec_decoders.remove(&writer_guid);
+      self.stop_writer_qos_timers(writer_guid);
+      self
+        .acquire_the_topic_cache_guard()
+        .writer_lost(writer_guid);
+      // Wake up the DataReader to update the states of the Writer's instances.
+      self.notify_cache_change();
       #[cfg(feature = "security")]
       if let Some(security_plugins_handle) = &self.security_plugins {
         security_plugins_handle
@@ -555,6 +830,8 @@ impl Reader {
 
     let writer_guid = GUID::new_with_prefix_and_id(mr_state.source_guid_prefix, data.writer_id);
     let writer_seq_num = data.writer_sn; // for borrow checker
+    let key_hash = Self::inline_key_hash(&data.inline_qos);
+    let payload_checksum = Self::inline_payload_checksum(&data.inline_qos, mr_state);
 
     match self.data_to_dds_data(data, data_flags) {
       Ok(dds_data) => self.process_received_data(
@@ -563,6 +840,8 @@ impl Reader {
         write_options_b.build(),
         writer_guid,
         writer_seq_num,
+        key_hash,
+        payload_checksum,
       ),
       Err(e) => debug!("Parsing DATA to DDSData failed: {}", e),
     }
@@ -619,8 +898,18 @@ impl Reader {
       write_options_b = write_options_b.related_sample_identity(related_sample_identity);
     }
 
+    if self
+      .matched_writer_mut(writer_guid)
+      .is_some_and(|wp| wp.is_ignored(receive_timestamp))
+    {
+      trace!("handle_datafrag_msg ignoring writer {:?}", writer_guid);
+      return;
+    }
+
     // Feed to fragment assembler ...
     let writer_seq_num = datafrag.writer_sn; // for borrow checker
+    let key_hash = Self::inline_key_hash(&datafrag.inline_qos);
+    let payload_checksum = Self::inline_payload_checksum(&datafrag.inline_qos, mr_state);
     let completed_dds_data = self
       .fragment_assembler_mutable(writer_guid, datafrag.fragment_size)
       .new_datafrag(datafrag, datafrag_flags);
@@ -635,6 +924,8 @@ impl Reader {
         write_options_b.build(),
         writer_guid,
         writer_seq_num,
+        key_hash,
+        payload_checksum,
       );
     } else {
       self.garbage_collect_fragments();
@@ -695,6 +986,7 @@ impl Reader {
 
   // common parts of processing DATA or a completed DATAFRAG (when all frags are
   // received)
+  #[allow(clippy::too_many_arguments)]
   fn process_received_data(
     &mut self,
     dds_data: DDSData,
@@ -702,6 +994,8 @@ impl Reader {
     write_options: WriteOptions,
     writer_guid: GUID,
     writer_sn: SequenceNumber,
+    key_hash: Option<KeyHash>,
+    payload_checksum: Option<[u8; 16]>,
   ) {
     trace!(
       "handle_data_msg from {:?} seq={:?} topic={:?} reliability={:?} stateless={:?}",
@@ -711,14 +1005,58 @@ impl Reader {
       self.reliability,
       self.like_stateless,
     );
+    // The sample is rejected before it is marked as received, so that a
+    // Reliable Reader requests it again.
+    if !Self::payload_checksum_matches(&dds_data, payload_checksum) {
+      warn!(
+        "Payload checksum mismatch in {:?} from {:?}. Rejecting sample. topic={:?}",
+        writer_sn, writer_guid, self.topic_name
+      );
+      self.samples_rejected_count += 1;
+      self.send_status_change(DataReaderStatus::SampleRejected {
+        count: CountWithChange::start_from(self.samples_rejected_count, 1),
+        last_reason: SampleRejectedStatusKind::ByPayloadChecksum,
+      });
+      return;
+    }
     if !self.like_stateless {
+      if self
+        .matched_writer_mut(writer_guid)
+        .is_some_and(|wp| wp.is_ignored(receive_timestamp))
+      {
+        trace!("handle_data_msg ignoring writer {:?}", writer_guid);
+        return;
+      }
+      if writer_sn < SequenceNumber::new(1) && self.matched_writer(writer_guid).is_some() {
+        self.with_mutable_writer_proxy(writer_guid, |this, wp| {
+          this.writer_misbehaved(wp, WriterMisbehavior::InvalidSequenceNumber);
+        });
+        return;
+      }
+      // Reliable Readers detect writer restarts from HEARTBEATs, but BestEffort
+      // Readers have only DATA to go by. Built-in readers are excluded, because
+      // some implementations resend Discovery data with old sequence numbers.
+      if self.reliability == policy::Reliability::BestEffort
+        && self.my_guid.entity_id.entity_kind.is_user_defined()
+        && self
+          .matched_writer_mut(writer_guid)
+          .is_some_and(|wp| wp.data_indicates_restart(writer_sn))
+        && !self
+          .with_mutable_writer_proxy(writer_guid, Self::handle_writer_restart)
+          .unwrap_or(false)
+      {
+        return;
+      }
+
       let my_entity_id = self.my_guid.entity_id; // to please borrow checker
+      let app_acks = self.application_acknowledgments();
       if let Some(writer_proxy) = self.matched_writer_mut(writer_guid) {
         if writer_proxy.should_ignore_change(writer_sn) {
           // change already present
           trace!("handle_data_msg already have this seq={:?}", writer_sn);
-          if my_entity_id == EntityId::SPDP_BUILTIN_PARTICIPANT_READER {
-            debug!("Accepting duplicate message to participant reader.");
+          if my_entity_id == EntityId::SPDP_BUILTIN_PARTICIPANT_READER
+            && compliance::tolerate("Repeated sequence number from SPDP writer.")
+          {
             // This is an attempted workaround to eProsima FastRTPS not
             // incrementing sequence numbers. (eProsima shapes demo 2.1.0 from
             // 2021)
@@ -728,6 +1066,21 @@ impl Reader {
         }
         // Add the change and get the instant
         writer_proxy.received_changes_add(writer_sn, receive_timestamp);
+        if app_acks && dds_data.change_kind() == ChangeKind::Alive {
+          writer_proxy.expect_app_ack(writer_sn);
+        }
+
+        let key_hash = match dds_data {
+          DDSData::DisposeByKeyHash { key_hash, .. } => Some(key_hash),
+          _ => key_hash,
+        };
+        self.restart_deadline(
+          writer_guid,
+          key_hash,
+          dds_data.change_kind(),
+          receive_timestamp,
+        );
+        self.writer_asserted_liveliness(writer_guid, receive_timestamp);
       } else {
         // no writer proxy found
         debug!(
@@ -745,6 +1098,15 @@ impl Reader {
       // stateless reader: nothing to do before making cache change
     }
 
+    if let (Some(decoder), DDSData::Data { serialized_payload }) =
+      (self.fec_decoders.get_mut(&writer_guid), &dds_data)
+    {
+      decoder.add_received(
+        writer_sn,
+        serialized_payload.bytes_slice(0, serialized_payload.len_serialized()),
+      );
+    }
+
     self.make_cache_change(
       dds_data,
       receive_timestamp,
@@ -760,6 +1122,100 @@ impl Reader {
     self.notify_cache_change();
   }
 
+  // A change written for intra-process delivery by a Writer of the same
+  // participant. It is processed like a received DATA.
+  pub fn handle_local_change(&mut self, change: &CacheChange) {
+    let key_hash = match &change.data_value {
+      DDSData::Local { sample } => Some(sample.key_hash()),
+      _ => None,
+    };
+    self.process_received_data(
+      change.data_value.clone(),
+      Timestamp::now(),
+      change.write_options.clone(),
+      change.writer_guid,
+      change.sequence_number,
+      key_hash,
+      None,
+    );
+  }
+
+  // Reconstructs a sample lost from a group, if the rest of the group has
+  // been received. See policy::ForwardErrorCorrection. The submessage kind is
+  // vendor-specific, so it is processed only from RustDDS writers.
+  pub fn handle_fec_parity_msg(&mut self, fec_parity: &FecParity, mr_state: &MessageReceiverState) {
+    if mr_state.source_vendor_id != VendorId::THIS_IMPLEMENTATION {
+      return;
+    }
+    let writer_guid =
+      GUID::new_with_prefix_and_id(mr_state.source_guid_prefix, fec_parity.writer_id);
+    if self.matched_writer(writer_guid).is_none() {
+      return;
+    }
+    // Samples are kept only from writers that have sent parity, so the first
+    // group is seldom recovered.
+    let recovered = self
+      .fec_decoders
+      .entry(writer_guid)
+      .or_insert_with(FecDecoder::new)
+      .recover(fec_parity);
+    let Some(recovered) = recovered else {
+      return;
+    };
+    let serialized_payload = match SerializedPayload::from_bytes(&recovered.payload) {
+      Ok(serialized_payload) => serialized_payload,
+      Err(e) => return debug!("Cannot parse sample recovered from FEC parity: {e}"),
+    };
+    debug!(
+      "Recovered {:?} from {:?} with FEC parity. topic={:?}",
+      recovered.sequence_number, writer_guid, self.topic_name
+    );
+    let mut write_options_b = WriteOptionsBuilder::new();
+    if let Some(source_timestamp) = recovered.source_timestamp {
+      write_options_b = write_options_b.source_timestamp(source_timestamp);
+    }
+    self.process_received_data(
+      DDSData::new(serialized_payload),
+      Timestamp::now(),
+      write_options_b.build(),
+      writer_guid,
+      recovered.sequence_number,
+      None,
+      None,
+    );
+  }
+
+  // Key hash from inline QoS, if the writer sent it.
+  fn inline_key_hash(inline_qos: &Option<ParameterList>) -> Option<KeyHash> {
+    inline_qos.as_ref().and_then(|params| {
+      InlineQos::key_hash(params).unwrap_or_else(|e| {
+        debug!("Deserializing key_hash: {:?}", &e);
+        None
+      })
+    })
+  }
+
+  // Payload checksum from inline QoS, if the writer sent it. The parameter id
+  // is vendor-specific, so it is looked for only from RustDDS writers.
+  fn inline_payload_checksum(
+    inline_qos: &Option<ParameterList>,
+    mr_state: &MessageReceiverState,
+  ) -> Option<[u8; 16]> {
+    if mr_state.source_vendor_id != VendorId::THIS_IMPLEMENTATION {
+      return None;
+    }
+    inline_qos.as_ref().and_then(InlineQos::payload_checksum)
+  }
+
+  fn payload_checksum_matches(dds_data: &DDSData, payload_checksum: Option<[u8; 16]>) -> bool {
+    match (dds_data, payload_checksum) {
+      (DDSData::Data { serialized_payload }, Some(checksum)) => {
+        InlineQos::payload_checksum_parameter(&serialized_payload.value).value == checksum
+      }
+      _ => true,
+    }
+  }
+
   fn data_to_dds_data(
     &self,
     data: Data,
@@ -882,25 +1338,35 @@ impl Reader {
       return false;
     }
 
-    if !self.matched_writers.contains_key(&writer_guid) {
-      debug!(
-        "HEARTBEAT from {:?}, but no writer proxy available. topic={:?} reader={:?}",
-        writer_guid, self.topic_name, self.my_guid
-      );
-      return false;
-    }
-    // sanity check
-    if heartbeat.first_sn < SequenceNumber::default() {
-      warn!(
-        "Writer {:?} advertised SequenceNumbers from {:?} to {:?}!",
-        writer_guid, heartbeat.first_sn, heartbeat.last_sn
-      );
+    let now = Timestamp::now();
+    match self.matched_writer_mut(writer_guid) {
+      None => {
+        debug!(
+          "HEARTBEAT from {:?}, but no writer proxy available. topic={:?} reader={:?}",
+          writer_guid, self.topic_name, self.my_guid
+        );
+        return false;
+      }
+      Some(wp) => {
+        if wp.is_ignored(now) {
+          return false;
+        }
+      }
     }
 
     self
       .with_mutable_writer_proxy(writer_guid, |this, writer_proxy| {
         // Note: This is worker closure. Use `this` instead of `self`.
 
+        if let Some(misbehavior) =
+          writer_proxy.heartbeat_misbehavior(heartbeat.count, heartbeat.first_sn, heartbeat.last_sn)
+        {
+          this.writer_misbehaved(writer_proxy, misbehavior);
+          return false;
+        }
+        // Heartbeats assert liveliness of MANUAL_BY_TOPIC writers
+        this.writer_asserted_liveliness(writer_guid, now);
+
         // Decide where should we send a reply, i.e. ACKNACK
         let reply_locators = match mr_state.unicast_reply_locator_list.as_slice() {
           [] | [Locator::Invalid] => writer_proxy.unicast_locator_list.clone(),
@@ -908,11 +1374,20 @@ impl Reader {
           others => others.to_vec(),
         };
 
+        // A restarted writer starts its heartbeat count and sequence numbers
+        // from the beginning, so this must be checked before the count.
+        if writer_proxy.heartbeat_indicates_restart(heartbeat.count, heartbeat.last_sn)
+          && !this.handle_writer_restart(writer_proxy)
+        {
+          return false;
+        }
+
         if heartbeat.count <= writer_proxy.received_heartbeat_count {
           // This heartbeat was already seen an processed.
           return false;
         }
         writer_proxy.received_heartbeat_count = heartbeat.count;
+        writer_proxy.heartbeat_first_sn = heartbeat.first_sn;
 
         // remove changes until first_sn.
         writer_proxy.irrelevant_changes_up_to(heartbeat.first_sn);
@@ -1040,6 +1515,11 @@ impl Reader {
             );
           }
 
+          // Lost APP_ACKs are repaired along with ACKNACKs
+          if this.application_acknowledgments() {
+            this.send_app_ack(writer_proxy, &reply_locators);
+          }
+
           this.send_acknack_to(
             acknack_flags,
             response_ack_nack,
@@ -1070,38 +1550,45 @@ impl Reader {
       );
       return;
     }
-    let all_ackable_before;
-    {
-      let writer_proxy = if let Some(wp) = self.matched_writer_mut(writer_guid) {
-        wp
-      } else {
+    match self.matched_writer_mut(writer_guid) {
+      None => {
         info!(
           "GAP from {:?}, but no writer proxy available. topic={:?} reader={:?}",
           writer_guid, self.topic_name, self.my_guid
         );
         return;
-      };
-
-      // Check validity of the GAP message (Section 8.3.8.4.3)
-      if gap.gap_start <= SequenceNumber::new(0) {
-        debug!(
-          "Invalid GAP from {:?}: gap_start={:?} is zero or negative. topic={:?} reader={:?}",
-          writer_guid, gap.gap_start, self.topic_name, self.my_guid
-        );
-        return;
       }
-      if gap.gap_list.base() <= SequenceNumber::new(0) {
-        debug!(
-          "Invalid GAP from {:?}: minimum of gap_list (={:?}) is zero or negative. topic={:?} \
-           reader={:?}",
-          writer_guid,
-          gap.gap_list.base(),
-          self.topic_name,
-          self.my_guid
-        );
-        return;
+      Some(wp) => {
+        if wp.is_ignored(Timestamp::now()) {
+          return;
+        }
       }
-      // TODO: check that maximum(gap_list) - minimum(gap_list) < 256 ?
+    }
+
+    // Check validity of the GAP message (Section 8.3.8.4.3)
+    if gap.gap_start <= SequenceNumber::new(0) || gap.gap_list.base() <= SequenceNumber::new(0) {
+      debug!(
+        "Invalid GAP from {:?}: gap_start={:?} or minimum of gap_list (={:?}) is zero or \
+         negative. topic={:?} reader={:?}",
+        writer_guid,
+        gap.gap_start,
+        gap.gap_list.base(),
+        self.topic_name,
+        self.my_guid
+      );
+      self.with_mutable_writer_proxy(writer_guid, |this, wp| {
+        this.writer_misbehaved(wp, WriterMisbehavior::InvalidGap);
+      });
+      return;
+    }
+    // TODO: check that maximum(gap_list) - minimum(gap_list) < 256 ?
+
+    let all_ackable_before;
+    {
+      let writer_proxy = match self.matched_writer_mut(writer_guid) {
+        Some(wp) => wp,
+        None => return, // checked above
+      };
 
       // Irrelevant sequence numbers communicated in the Gap message are
       // composed of two groups:
@@ -1209,6 +1696,9 @@ impl Reader {
   // notifies DataReaders (or any listeners that history cache has changed for
   // this reader) likely use of mio channel
   pub fn notify_cache_change(&mut self) {
+    // WaitSets waiting for DataAvailable
+    self.status_record.data_available();
+
     // async notify mechanism
     self
       .data_reader_waker
@@ -1286,7 +1776,7 @@ impl Reader {
       // Encode submessages
       SecurityResult::<Vec<Vec<Submessage>>>::from_iter(submessages.iter().map(|submessage| {
         security_plugins_handle
-          .get_plugins()
+          .read_plugins()
           .encode_datareader_submessage(submessage.clone(), &source_guid, &[destination_guid])
           // Convert each encoding output to a Vec of 1 or 3 submessages
           .map(Vec::from)
@@ -1302,7 +1792,7 @@ impl Reader {
         let source_guid_prefix = source_guid.prefix;
         let destination_guid_prefix = destination_guid.prefix;
         // Encode message
-        security_plugins_handle.get_plugins().encode_message(
+        security_plugins_handle.read_plugins().encode_message(
           message,
           &source_guid_prefix,
           &[destination_guid_prefix],
@@ -1324,12 +1814,7 @@ impl Reader {
     let infodst_flags =
       BitFlags::<INFODESTINATION_Flags>::from_flag(INFODESTINATION_Flags::Endianness);
 
-    let mut message = Message::new(Header {
-      protocol_id: ProtocolId::default(),
-      protocol_version: ProtocolVersion::THIS_IMPLEMENTATION,
-      vendor_id: VendorId::THIS_IMPLEMENTATION,
-      guid_prefix: self.my_guid.prefix,
-    });
+    let mut message = Message::new(Header::new(self.my_guid.prefix));
 
     message.add_submessage(info_dst.create_submessage(infodst_flags));
 
@@ -1349,12 +1834,7 @@ impl Reader {
     let infodst_flags =
       BitFlags::<INFODESTINATION_Flags>::from_flag(INFODESTINATION_Flags::Endianness);
 
-    let mut message = Message::new(Header {
-      protocol_id: ProtocolId::default(),
-      protocol_version: ProtocolVersion::THIS_IMPLEMENTATION,
-      vendor_id: VendorId::THIS_IMPLEMENTATION,
-      guid_prefix: self.my_guid.prefix,
-    });
+    let mut message = Message::new(Header::new(self.my_guid.prefix));
 
     message.add_submessage(info_dst.create_submessage(infodst_flags));
 
@@ -1365,6 +1845,33 @@ impl Reader {
     self.encode_and_send(message, destination_guid, dst_locator_list);
   }
 
+  // Sends the state of application acknowledgments, see
+  // policy::Acknowledgment. It is sent in full each time, so that a lost
+  // APP_ACK is repaired by the next one.
+  fn send_app_ack(&self, writer_proxy: &mut RtpsWriterProxy, dst_locator_list: &[Locator]) {
+    let writer_guid = writer_proxy.remote_writer_guid;
+    let app_ack = AppAck {
+      reader_id: self.entity_id(),
+      writer_id: writer_guid.entity_id,
+      acknowledged: writer_proxy.app_ack_state(),
+      count: writer_proxy.next_app_ack_count(),
+    };
+    let infodst_flags =
+      BitFlags::<INFODESTINATION_Flags>::from_flag(INFODESTINATION_Flags::Endianness);
+    let flags = BitFlags::<APPACK_Flags>::from_flag(APPACK_Flags::Endianness);
+
+    let mut message = Message::new(Header::new(self.my_guid.prefix));
+    message.add_submessage(
+      InfoDestination {
+        guid_prefix: writer_guid.prefix,
+      }
+      .create_submessage(infodst_flags),
+    );
+    message.add_submessage(app_ack.create_submessage(flags));
+
+    self.encode_and_send(message, writer_guid, dst_locator_list);
+  }
+
   pub fn send_preemptive_acknacks(&mut self) {
     if self.like_stateless {
       info!(
@@ -1415,6 +1922,16 @@ impl Reader {
     &self.topic_name
   }
 
+  // Writer proxies are matched again by the caller, and removed if the
+  // partitions no longer match.
+  pub fn set_partition(&mut self, partition: policy::Partition) {
+    self.qos_policy.partition = Some(partition);
+  }
+
+  pub fn matched_writer_count(&self) -> usize {
+    self.matched_writers.len()
+  }
+
   fn acquire_the_topic_cache_guard(&self) -> MutexGuard<TopicCache> {
     self.topic_cache.lock().unwrap_or_else(|e| {
       panic!(
@@ -1453,66 +1970,88 @@ impl fmt::Debug for Reader {
 mod tests {
   use std::sync::RwLock;
 
+  use bytes::Bytes;
+
   use crate::{
-    dds::{qos::policy::Reliability, statusevents::sync_status_channel, typedesc::TypeDesc},
+    dds::{
+      qos::policy::Reliability,
+      statusevents::{sync_status_channel, StatusChannelReceiver},
+      typedesc::TypeDesc,
+    },
     structure::{dds_cache::DDSCache, guid::EntityKind},
     QosPolicyBuilder,
   };
   use super::*;
 
-  #[test]
-  fn reader_sends_notification_when_receiving_data() {
-    // 1. Create a reader
-    // Create the DDS cache and a topic
+  // The other ends of the channels of a Reader made by `test_reader`.
+  struct TestReaderChannels {
+    notification_receiver: mio_channel::Receiver<()>,
+    _notification_event_source: mio_source::PollEventSource,
+    status_receiver: StatusChannelReceiver<DataReaderStatus>,
+    _participant_status_receiver: StatusChannelReceiver<DomainParticipantStatusEvent>,
+    _reader_command_sender: mio_channel::SyncSender<ReaderCommand>,
+  }
+
+  // A Reader of a new topic, not attached to a DataReader or an event loop.
+  // Tests feed it submessages by calling its handlers directly.
+  fn test_reader(
+    guid: GUID,
+    qos_policy: QosPolicies,
+    like_stateless: bool,
+  ) -> (Reader, TestReaderChannels) {
     let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
     let topic_name = "test_name";
-    let qos_policy = QosPolicies::qos_none();
-
     let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
       topic_name.to_string(),
       TypeDesc::new("test_type".to_string()),
       &qos_policy,
     );
 
-    // Create notification mechanisms
-    // mio-0.6 channel:
     let (notification_sender, notification_receiver) = mio_channel::sync_channel::<()>(100);
-    // mio-0.8 event source and sender:
-    let (_notification_event_source, notification_event_sender) =
+    let (notification_event_source, notification_event_sender) =
       mio_source::make_poll_channel().unwrap();
-    // async notification waker
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    // Create status channel
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
-    let (participant_status_sender, _participant_status_receiver) =
-      sync_status_channel(16).unwrap();
-
-    // Create reader command channel
-    let (_reader_command_sender, reader_command_receiver) =
+    let (status_sender, status_receiver) = sync_status_channel::<DataReaderStatus>(16).unwrap();
+    let (participant_status_sender, participant_status_receiver) = sync_status_channel(16).unwrap();
+    let (reader_command_sender, reader_command_receiver) =
       mio_channel::sync_channel::<ReaderCommand>(10);
 
-    // Then finally create the reader
-    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
     let reader_ing = ReaderIngredients {
-      guid: reader_guid,
+      guid,
       notification_sender,
       status_sender,
       topic_name: topic_name.to_string(),
       topic_cache_handle,
-      like_stateless: false,
+      like_stateless,
       qos_policy,
       data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
+      data_reader_waker: Arc::new(Mutex::new(None)),
       poll_event_sender: notification_event_sender,
       security_plugins: None,
+      status_record: Arc::default(),
     };
-    let mut reader = Reader::new(
+    let reader = Reader::new(
       reader_ing,
       Rc::new(UDPSender::new(0).unwrap()),
       mio_extras::timer::Builder::default().build(),
       participant_status_sender,
     );
+    let channels = TestReaderChannels {
+      notification_receiver,
+      _notification_event_source: notification_event_source,
+      status_receiver,
+      _participant_status_receiver: participant_status_receiver,
+      _reader_command_sender: reader_command_sender,
+    };
+    (reader, channels)
+  }
+
+  #[test]
+  fn reader_sends_notification_when_receiving_data() {
+    // 1. Create a reader
+    let qos_policy = QosPolicies::qos_none();
+
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (mut reader, channels) = test_reader(reader_guid, qos_policy, false);
 
     // 2. Add info of a matched writer to the reader
     let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
@@ -1543,7 +2082,7 @@ mod tests {
 
     // 5. Verify that the reader sends a notification about the new data
     assert!(
-      notification_receiver.try_recv().is_ok(),
+      channels.notification_receiver.try_recv().is_ok(),
       "Reader did not send a notification through the mio-0.6 channel"
     );
     // TODO: Should the other notification mechanisms (mio-0.8 & async) be also
@@ -1551,53 +2090,67 @@ mod tests {
   }
 
   #[test]
-  fn reader_sends_data_to_topic_cache() {
-    // 1. Create a reader
-    // Create the DDS cache and a topic
-    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
-    let topic_name = "test_name";
+  fn reader_rejects_sample_with_wrong_payload_checksum() {
     let qos_policy = QosPolicies::qos_none();
 
-    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
-      topic_name.to_string(),
-      TypeDesc::new("test_type".to_string()),
-      &qos_policy,
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (mut reader, channels) = test_reader(reader_guid, qos_policy, false);
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      source_vendor_id: VendorId::THIS_IMPLEMENTATION,
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      mr_state.unicast_reply_locator_list.clone(),
+      mr_state.multicast_reply_locator_list.clone(),
+      &QosPolicies::qos_none(),
     );
 
-    // Create mechanisms for notifications, statuses & commands
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
+    let data_flags = BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data);
+    let data_with_checksum_of = |sn: i64, checksummed: &'static [u8]| {
+      let mut data = Data {
+        reader_id: reader_guid.entity_id,
+        writer_id: writer_guid.entity_id,
+        writer_sn: SequenceNumber::new(sn),
+        inline_qos: Some(ParameterList {
+          parameters: vec![InlineQos::payload_checksum_parameter(checksummed)],
+        }),
+        ..Data::default()
+      };
+      data.update_serialized_payload_value(Bytes::from_static(b"sample"));
+      data
+    };
 
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
-    let (participant_status_sender, _participant_status_receiver) =
-      sync_status_channel(16).unwrap();
+    // A corrupted sample is rejected.
+    reader.handle_data_msg(data_with_checksum_of(1, b"sampl3"), data_flags, &mr_state);
+    assert!(channels.notification_receiver.try_recv().is_err());
+    assert!(
+      std::iter::from_fn(|| channels.status_receiver.try_recv().ok()).any(|s| matches!(
+        s,
+        DataReaderStatus::SampleRejected {
+          last_reason: SampleRejectedStatusKind::ByPayloadChecksum,
+          ..
+        }
+      ))
+    );
 
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
+    // An intact one is accepted.
+    reader.handle_data_msg(data_with_checksum_of(2, b"sample"), data_flags, &mr_state);
+    assert!(channels.notification_receiver.try_recv().is_ok());
+  }
+
+  #[test]
+  fn reader_sends_data_to_topic_cache() {
+    // 1. Create a reader
+    let qos_policy = QosPolicies::qos_none();
 
-    // Then create the reader
     let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic_name.to_string(),
-      topic_cache_handle: topic_cache_handle.clone(),
-      like_stateless: false,
-      qos_policy,
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
-    let mut reader = Reader::new(
-      reader_ing,
-      Rc::new(UDPSender::new(0).unwrap()),
-      mio_extras::timer::Builder::default().build(),
-      participant_status_sender,
-    );
+    let (mut reader, _channels) = test_reader(reader_guid, qos_policy, false);
+    let topic_cache_handle = Arc::clone(&reader.topic_cache);
 
     // 2. Add info of a matched writer to the reader
     let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
@@ -1655,55 +2208,14 @@ mod tests {
   #[test]
   fn reader_handles_heartbeats() {
     // 1. Create a reader for a topic with Reliable QoS
-    // Create the DDS cache and the topic
-    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
-    let topic_name = "test_name";
     let reliable_qos = QosPolicyBuilder::new()
       .reliability(Reliability::Reliable {
         max_blocking_time: Duration::from_millis(100),
       })
       .build();
 
-    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
-      topic_name.to_string(),
-      TypeDesc::new("test_type".to_string()),
-      &reliable_qos,
-    );
-
-    // Create mechanisms for notifications, statuses & commands
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
-    let (participant_status_sender, _participant_status_receiver) =
-      sync_status_channel(16).unwrap();
-
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
-    // Then create the reader
     let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic_name.to_string(),
-      topic_cache_handle,
-      like_stateless: false,
-      qos_policy: reliable_qos.clone(),
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
-    let mut reader = Reader::new(
-      reader_ing,
-      Rc::new(UDPSender::new(0).unwrap()),
-      mio_extras::timer::Builder::default().build(),
-      participant_status_sender,
-    );
+    let (mut reader, _channels) = test_reader(reader_guid, reliable_qos.clone(), false);
 
     // 2. Add info of a matched writer to the reader
     let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
@@ -1768,51 +2280,10 @@ mod tests {
   #[test]
   fn reader_handles_gaps() {
     // 1. Create a reader
-    // Create the DDS cache and a topic
-    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
-    let topic_name = "test_name";
     let qos_policy = QosPolicies::qos_none();
 
-    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
-      topic_name.to_string(),
-      TypeDesc::new("test_type".to_string()),
-      &qos_policy,
-    );
-
-    // Create mechanisms for notifications, statuses & commands
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
-    let (participant_status_sender, _participant_status_receiver) =
-      sync_status_channel(16).unwrap();
-
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
-    // Then create the reader
     let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic_name.to_string(),
-      topic_cache_handle,
-      like_stateless: false,
-      qos_policy,
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
-    let mut reader = Reader::new(
-      reader_ing,
-      Rc::new(UDPSender::new(0).unwrap()),
-      mio_extras::timer::Builder::default().build(),
-      participant_status_sender,
-    );
+    let (mut reader, _channels) = test_reader(reader_guid, qos_policy, false);
 
     // 2. Add info of a matched writer to the reader
     let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
@@ -1904,53 +2375,12 @@ mod tests {
   #[test]
   fn stateless_reader_does_not_contain_writer_proxies() {
     // 1. Create a stateless-like reader
-    // Create the DDS cache and a topic
-    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
-    let topic_name = "test_name";
     let qos_policy = QosPolicies::builder()
       .reliability(Reliability::BestEffort) // Stateless needs to be BestEffort
       .build();
 
-    let topic_cache_handle = dds_cache.write().unwrap().add_new_topic(
-      topic_name.to_string(),
-      TypeDesc::new("test_type".to_string()),
-      &qos_policy,
-    );
-
-    // Create mechanisms for notifications, statuses & commands
-    let (notification_sender, _notification_receiver) = mio_channel::sync_channel::<()>(100);
-    let (_notification_event_source, notification_event_sender) =
-      mio_source::make_poll_channel().unwrap();
-    let data_reader_waker = Arc::new(Mutex::new(None));
-
-    let (status_sender, _status_receiver) = sync_status_channel::<DataReaderStatus>(4).unwrap();
-    let (participant_status_sender, _participant_status_receiver) =
-      sync_status_channel(16).unwrap();
-
-    let (_reader_command_sender, reader_command_receiver) =
-      mio_channel::sync_channel::<ReaderCommand>(10);
-
-    let like_stateless = true;
     let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
-    let reader_ing = ReaderIngredients {
-      guid: reader_guid,
-      notification_sender,
-      status_sender,
-      topic_name: topic_name.to_string(),
-      topic_cache_handle,
-      like_stateless,
-      qos_policy,
-      data_reader_command_receiver: reader_command_receiver,
-      data_reader_waker,
-      poll_event_sender: notification_event_sender,
-      security_plugins: None,
-    };
-    let mut reader = Reader::new(
-      reader_ing,
-      Rc::new(UDPSender::new(0).unwrap()),
-      mio_extras::timer::Builder::default().build(),
-      participant_status_sender,
-    );
+    let (mut reader, _channels) = test_reader(reader_guid, qos_policy, true);
 
     // 2. Attempt to add info of a matched writer to the reader
     let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
@@ -1972,4 +2402,284 @@ mod tests {
     // we attempted to add
     assert!(reader.matched_writer(writer_guid).is_none());
   }
+
+  #[test]
+  fn reader_tracks_deadline_and_liveliness_of_writer() {
+    // 1. Create a reader with a DEADLINE
+    let period = Duration::from_millis(50);
+    let qos_policy = QosPolicyBuilder::new()
+      .deadline(policy::Deadline(period))
+      .build();
+
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (mut reader, channels) = test_reader(reader_guid, qos_policy, false);
+
+    // 2. Match a writer with MANUAL_BY_TOPIC liveliness
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    let writer_qos = QosPolicyBuilder::new()
+      .deadline(policy::Deadline(period))
+      .liveliness(policy::Liveliness::ManualByTopic {
+        lease_duration: period,
+      })
+      .build();
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      mr_state.unicast_reply_locator_list.clone(),
+      mr_state.multicast_reply_locator_list.clone(),
+      &writer_qos,
+    );
+    // skip SubscriptionMatched
+    let statuses: Vec<_> =
+      std::iter::from_fn(|| channels.status_receiver.try_recv().ok()).collect();
+    assert!(statuses.iter().any(|s| matches!(
+      s,
+      DataReaderStatus::LivelinessChanged { alive_total, .. } if alive_total.count() == 1
+    )));
+
+    // 3. No data from writer: deadline is missed and liveliness is lost
+    std::thread::sleep(StdDuration::from_millis(100));
+    reader.handle_qos_timers();
+    let statuses: Vec<_> =
+      std::iter::from_fn(|| channels.status_receiver.try_recv().ok()).collect();
+    assert!(statuses
+      .iter()
+      .any(|s| matches!(s, DataReaderStatus::RequestedDeadlineMissed { .. })));
+    assert!(statuses.iter().any(|s| matches!(
+      s,
+      DataReaderStatus::LivelinessChanged { alive_total, not_alive_total }
+        if alive_total.count() == 0 && not_alive_total.count() == 1
+    )));
+
+    // 4. Data from writer restores liveliness and restarts the deadline
+    let data = Data {
+      reader_id: reader_guid.entity_id,
+      writer_id: writer_guid.entity_id,
+      ..Data::default()
+    };
+    reader.handle_data_msg(
+      data,
+      BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data),
+      &mr_state,
+    );
+    reader.handle_qos_timers();
+    let statuses: Vec<_> =
+      std::iter::from_fn(|| channels.status_receiver.try_recv().ok()).collect();
+    assert!(!statuses
+      .iter()
+      .any(|s| matches!(s, DataReaderStatus::RequestedDeadlineMissed { .. })));
+    assert!(statuses.iter().any(|s| matches!(
+      s,
+      DataReaderStatus::LivelinessChanged { alive_total, .. } if alive_total.count() == 1
+    )));
+
+    // 5. Removing the writer cancels its timers
+    reader.remove_writer_proxy(writer_guid);
+    assert!(reader.qos_timers.is_empty());
+  }
+
+  #[test]
+  fn reader_resets_writer_state_on_writer_restart() {
+    // 1. Create a Reliable reader with default WriterRestart policy
+    let reliable_qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .build();
+
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (mut reader, channels) = test_reader(reader_guid, reliable_qos.clone(), false);
+    let topic_cache_handle = Arc::clone(&reader.topic_cache);
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      mr_state.unicast_reply_locator_list.clone(),
+      mr_state.multicast_reply_locator_list.clone(),
+      &reliable_qos,
+    );
+
+    let data_flags = BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data);
+    let data_sn = |sn| Data {
+      reader_id: reader_guid.entity_id,
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::new(sn),
+      ..Data::default()
+    };
+    let heartbeat = |count, last_sn| Heartbeat {
+      reader_id: reader_guid.entity_id,
+      writer_id: writer_guid.entity_id,
+      first_sn: SequenceNumber::new(1),
+      last_sn: SequenceNumber::new(last_sn),
+      count,
+    };
+    let restarts = || {
+      std::iter::from_fn(|| channels.status_receiver.try_recv().ok())
+        .filter(|s| matches!(s, DataReaderStatus::WriterRestarted { .. }))
+        .count()
+    };
+
+    // 2. Receive samples 1..=3 from the writer
+    for sn in 1..=3 {
+      reader.handle_data_msg(data_sn(sn), data_flags, &mr_state);
+    }
+    reader.handle_heartbeat_msg(&heartbeat(10, 3), true, &mr_state);
+    assert_eq!(
+      reader
+        .matched_writer(writer_guid)
+        .unwrap()
+        .all_ackable_before(),
+      SequenceNumber::new(4)
+    );
+
+    // 3. The writer restarts and has written only one sample. A single
+    // heartbeat could be just reordered, so it is not enough.
+    reader.handle_heartbeat_msg(&heartbeat(1, 1), true, &mr_state);
+    assert_eq!(restarts(), 0);
+
+    // 4. The next heartbeat confirms the restart. The reader starts over and
+    // asks for the missing sample.
+    assert!(reader.handle_heartbeat_msg(&heartbeat(2, 1), true, &mr_state));
+    assert_eq!(restarts(), 1);
+    assert_eq!(
+      reader
+        .matched_writer(writer_guid)
+        .unwrap()
+        .all_ackable_before(),
+      SequenceNumber::new(1)
+    );
+
+    // 5. Sample 1 of the restarted writer is accepted, not taken as duplicate
+    reader.handle_data_msg(data_sn(1), data_flags, &mr_state);
+    let instant = reader.seqnum_instant_map[&SequenceNumber::new(1)];
+    assert!(topic_cache_handle
+      .lock()
+      .unwrap()
+      .get_change(&instant)
+      .is_some());
+    assert_eq!(
+      reader
+        .matched_writer(writer_guid)
+        .unwrap()
+        .all_ackable_before(),
+      SequenceNumber::new(2)
+    );
+  }
+
+  #[test]
+  fn reader_quarantines_misbehaving_writer() {
+    // 1. Create a Reliable reader that quarantines a writer for its second
+    // protocol violation
+    let quarantine = policy::WriterQuarantine {
+      max_violations: 2,
+      duration: Duration::from_secs(60),
+    };
+    let qos = QosPolicyBuilder::new()
+      .reliability(Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .writer_quarantine(quarantine)
+      .build();
+
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (mut reader, channels) = test_reader(reader_guid, qos.clone(), false);
+
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let mr_state = MessageReceiverState {
+      source_guid_prefix: writer_guid.prefix,
+      ..Default::default()
+    };
+    reader.matched_writer_add(
+      writer_guid,
+      EntityId::UNKNOWN,
+      mr_state.unicast_reply_locator_list.clone(),
+      mr_state.multicast_reply_locator_list.clone(),
+      &qos,
+    );
+
+    let data_flags = BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data);
+    let data_sn = |sn| Data {
+      reader_id: reader_guid.entity_id,
+      writer_id: writer_guid.entity_id,
+      writer_sn: SequenceNumber::new(sn),
+      ..Data::default()
+    };
+    let heartbeat = |count, first_sn, last_sn| Heartbeat {
+      reader_id: reader_guid.entity_id,
+      writer_id: writer_guid.entity_id,
+      first_sn: SequenceNumber::new(first_sn),
+      last_sn: SequenceNumber::new(last_sn),
+      count,
+    };
+    let quarantines = || -> Vec<DataReaderStatus> {
+      std::iter::from_fn(|| channels.status_receiver.try_recv().ok())
+        .filter(|s| matches!(s, DataReaderStatus::WriterQuarantined { .. }))
+        .collect()
+    };
+    let ackable_before = |reader: &Reader| {
+      reader
+        .matched_writer(writer_guid)
+        .unwrap()
+        .all_ackable_before()
+    };
+
+    // 2. Receive sample 1 and a valid heartbeat
+    reader.handle_data_msg(data_sn(1), data_flags, &mr_state);
+    reader.handle_heartbeat_msg(&heartbeat(1, 1, 1), true, &mr_state);
+    assert_eq!(ackable_before(&reader), SequenceNumber::new(2));
+
+    // 3. A heartbeat with a negative range is ignored, but does not yet
+    // quarantine the writer.
+    assert!(!reader.handle_heartbeat_msg(&heartbeat(2, 5, 1), false, &mr_state));
+    assert_eq!(
+      reader
+        .matched_writer(writer_guid)
+        .unwrap()
+        .received_heartbeat_count,
+      1
+    );
+    assert!(quarantines().is_empty());
+
+    // 4. A heartbeat announcing an absurd range is the second violation
+    assert!(!reader.handle_heartbeat_msg(&heartbeat(3, 1, i64::MAX), false, &mr_state));
+    match quarantines().as_slice() {
+      [DataReaderStatus::WriterQuarantined {
+        count,
+        writer,
+        last_misbehavior,
+        duration,
+      }] => {
+        assert_eq!(count.count(), 1);
+        assert_eq!(*writer, writer_guid);
+        assert_eq!(
+          *last_misbehavior,
+          WriterMisbehavior::ExcessiveHeartbeatRange
+        );
+        assert_eq!(*duration, quarantine.duration);
+      }
+      other => panic!("Expected WriterQuarantined, got {other:?}"),
+    }
+
+    // 5. Samples of the quarantined writer are ignored
+    reader.handle_data_msg(data_sn(2), data_flags, &mr_state);
+    assert_eq!(ackable_before(&reader), SequenceNumber::new(2));
+
+    // 6. After the quarantine the writer is served again
+    let after_quarantine = Timestamp::now() + Duration::from_secs(61);
+    assert!(!reader
+      .matched_writer_mut(writer_guid)
+      .unwrap()
+      .is_ignored(after_quarantine));
+    reader.handle_data_msg(data_sn(2), data_flags, &mr_state);
+    assert_eq!(ackable_before(&reader), SequenceNumber::new(3));
+  }
 }
@@ -9,7 +9,10 @@ use log::{debug, error, info, trace, warn};
 
 use crate::{
   dds::{participant::DomainParticipant, qos::QosPolicies},
-  discovery::sedp_messages::DiscoveredReaderData,
+  discovery::{
+    content_filter_property::ContentFilterProperty, sedp_messages::DiscoveredReaderData,
+  },
+  leak_tracking::{LeakTracker, TrackedObject},
   messages::submessages::submessage::AckSubmessage,
   rtps::constant::*,
   structure::{
@@ -56,11 +59,18 @@ pub(crate) struct RtpsReaderProxy {
   // We will send the SNs as GAP until they have been acked.
   // This is to be used in Reliable mode only.
   pending_gap: BTreeSet<SequenceNumber>,
+
+  // Changes sent to the Reader that its application has not acknowledged
+  // with APP_ACK. Tracked only with Acknowledgment::ApplicationExplicit.
+  app_unacknowledged: BTreeSet<SequenceNumber>,
   // true = send repair data messages due to NACKs, buffer messages by DataWriter
   // false = send data messages directly from DataWriter
   pub repair_mode: bool,
   qos: QosPolicies,
   frags_requested: BTreeMap<SequenceNumber, BitVec>,
+  // Content filter that the Reader advertises, if any
+  pub content_filter: Option<ContentFilterProperty>,
+  _leak_tracker: LeakTracker,
 }
 
 impl RtpsReaderProxy {
@@ -75,9 +85,12 @@ impl RtpsReaderProxy {
       all_acked_before: SequenceNumber::zero(),
       unsent_changes: BTreeSet::new(),
       pending_gap: BTreeSet::new(),
+      app_unacknowledged: BTreeSet::new(),
       repair_mode: false,
       qos,
       frags_requested: BTreeMap::new(),
+      content_filter: None,
+      _leak_tracker: LeakTracker::new(TrackedObject::ReaderProxy),
     }
   }
 
@@ -113,12 +126,18 @@ impl RtpsReaderProxy {
       warn!("Upddate changes QoS in ReaderProxy.");
       self.qos = update.qos.clone();
     }
+
+    self.content_filter.clone_from(&update.content_filter);
   }
 
   pub fn qos(&self) -> &QosPolicies {
     &self.qos
   }
 
+  pub fn has_multicast_locator(&self) -> bool {
+    self.multicast_locator_list.iter().any(Locator::is_udp)
+  }
+
   pub fn expects_inline_qos(&self) -> bool {
     self.expects_in_line_qos
   }
@@ -163,14 +182,19 @@ impl RtpsReaderProxy {
       remote_group_entity_id: EntityId::UNKNOWN, // TODO
       unicast_locator_list,
       multicast_locator_list,
+      // Our Readers know the Writer QoS from discovery. This is advertised in
+      // PID_EXPECTS_INLINE_QOS of our SEDP data.
       expects_in_line_qos: false,
       is_active: true,
       all_acked_before: SequenceNumber::zero(),
       unsent_changes: BTreeSet::new(),
       pending_gap: BTreeSet::new(),
+      app_unacknowledged: BTreeSet::new(),
       repair_mode: false,
       qos: reader.qos_policy.clone(),
       frags_requested: BTreeMap::new(),
+      content_filter: None,
+      _leak_tracker: LeakTracker::new(TrackedObject::ReaderProxy),
     }
   }
 
@@ -219,9 +243,12 @@ impl RtpsReaderProxy {
       all_acked_before: SequenceNumber::zero(),
       unsent_changes: BTreeSet::new(),
       pending_gap: BTreeSet::new(),
+      app_unacknowledged: BTreeSet::new(),
       repair_mode: false,
       qos: discovered_reader_data.subscription_topic_data.qos(),
       frags_requested: BTreeMap::new(),
+      content_filter: discovered_reader_data.content_filter.clone(),
+      _leak_tracker: LeakTracker::new(TrackedObject::ReaderProxy),
     }
   }
 
@@ -282,13 +309,37 @@ impl RtpsReaderProxy {
         // TODO
         error!("NACKFRAG not implemented");
       }
+
+      AckSubmessage::AppAck(app_ack) => {
+        let acknowledged = &app_ack.acknowledged;
+        self.app_unacknowledged = self.app_unacknowledged.split_off(&acknowledged.base());
+        for sn in acknowledged.iter() {
+          self.app_unacknowledged.remove(&sn);
+        }
+      }
     }
   }
 
+  pub fn expect_app_ack(&mut self, seq_num: SequenceNumber) {
+    self.app_unacknowledged.insert(seq_num);
+  }
+
+  pub fn remove_app_unacknowledged(&mut self, seq_num: SequenceNumber) {
+    self.app_unacknowledged.remove(&seq_num);
+  }
+
+  pub fn app_unacknowledged(&self) -> impl Iterator<Item = SequenceNumber> + '_ {
+    self.app_unacknowledged.iter().copied()
+  }
+
   pub fn insert_pending_gap(&mut self, seq_num: SequenceNumber) {
     self.pending_gap.insert(seq_num);
   }
 
+  pub fn remove_pending_gap(&mut self, seq_num: SequenceNumber) {
+    self.pending_gap.remove(&seq_num);
+  }
+
   pub fn set_pending_gap_up_to(&mut self, last_gap_sn: SequenceNumber) {
     // form SN range from 1 to last_gap_sn (inclusive)
     let gap_sn_range = SequenceNumberRange::new(SequenceNumber::new(1), last_gap_sn);
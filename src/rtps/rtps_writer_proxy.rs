@@ -1,15 +1,20 @@
 use core::ops::Bound::{Included, Unbounded};
-use std::{cmp::max, collections::BTreeMap};
+use std::{
+  cmp::max,
+  collections::{BTreeMap, BTreeSet},
+};
 
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 
 use crate::{
+  dds::{qos::policy, statusevents::WriterMisbehavior},
   discovery::sedp_messages::DiscoveredWriterData,
+  leak_tracking::{LeakTracker, TrackedObject},
   structure::{
     guid::{EntityId, GUID},
     locator::Locator,
-    sequence_number::SequenceNumber,
+    sequence_number::{SequenceNumber, SequenceNumberSet},
     time::Timestamp,
   },
 };
@@ -66,14 +71,38 @@ pub(crate) struct RtpsWriterProxy {
 
   pub sent_ack_nack_count: i32,
 
-  ack_base: SequenceNumber, // We can ACK everything before this number.
   // ack_base can be increased from N-1 to N, if we receive DATA with SequenceNumber N-1
   // heartbeat(first,last) => ack_base can be increased to first.
   // GAP is treated like receiving a message.
+  ack_base: SequenceNumber, // We can ACK everything before this number.
 
-  // These are used for quick tracking of
-  last_received_sequence_number: SequenceNumber,
-  last_received_timestamp: Timestamp,
+  // Evidence of the remote Writer having restarted its sequence numbering:
+  // the count of a HEARTBEAT (Reliable) or the SequenceNumber of a DATA
+  // (BestEffort) that announced only sequence numbers we have already been
+  // past. A restart is concluded only from two such messages in increasing
+  // order, so that a single reordered or duplicated message does not look like
+  // a restart.
+  restart_suspect: Option<i64>,
+
+  // Writer has restarted and WriterRestart::Reject policy is in effect.
+  // Everything from it is ignored until the proxy is removed.
+  pub rejected: bool,
+
+  // first_sn of the latest HEARTBEAT. A Writer only ever removes changes from
+  // the beginning of its history, so this does not decrease, unless the Writer
+  // restarts.
+  pub heartbeat_first_sn: SequenceNumber,
+
+  // Changes received from the Writer that the application has not
+  // acknowledged. Tracked only with Acknowledgment::ApplicationExplicit.
+  app_unacknowledged: BTreeSet<SequenceNumber>,
+  sent_app_ack_count: i32,
+
+  // Times of recent protocol violations of the Writer, oldest first, and the
+  // end of its quarantine. See the WriterQuarantine policy.
+  recent_violations: Vec<Timestamp>,
+  quarantined_until: Option<Timestamp>,
+  _leak_tracker: LeakTracker,
 }
 
 impl RtpsWriterProxy {
@@ -94,8 +123,14 @@ impl RtpsWriterProxy {
       // Sequence numbering must start at 1.
       // Therefore, we can ACK all sequence numbers below 1 even before receiving anything.
       ack_base: SequenceNumber::new(1),
-      last_received_sequence_number: SequenceNumber::new(0),
-      last_received_timestamp: Timestamp::INVALID,
+      restart_suspect: None,
+      rejected: false,
+      heartbeat_first_sn: SequenceNumber::new(0),
+      app_unacknowledged: BTreeSet::new(),
+      sent_app_ack_count: 0,
+      recent_violations: Vec::new(),
+      quarantined_until: None,
+      _leak_tracker: LeakTracker::new(TrackedObject::WriterProxy),
     }
   }
 
@@ -117,15 +152,111 @@ impl RtpsWriterProxy {
     self.remote_group_entity_id = other.remote_group_entity_id;
   }
 
-  // This is used to check for DEADLINE policy
-  pub fn last_change_timestamp(&self) -> Option<Timestamp> {
-    if self.last_received_sequence_number > SequenceNumber::new(0) {
-      Some(self.last_received_timestamp)
+  // Heartbeat announces nothing beyond last_sn, but we have already received
+  // or been told to skip past that. Returns true, if the Writer has restarted
+  // its sequence numbering.
+  pub fn heartbeat_indicates_restart(&mut self, count: i32, last_sn: SequenceNumber) -> bool {
+    let behind = last_sn + SequenceNumber::new(1) < self.ack_base;
+    // Only newer heartbeats may clear suspicion, old ones may be just late.
+    let fresh = count > self.received_heartbeat_count;
+    self.restart_evidence(behind, fresh, i64::from(count))
+  }
+
+  // DATA with a sequence number that we already have. Returns true, if the
+  // Writer has restarted its sequence numbering.
+  pub fn data_indicates_restart(&mut self, sn: SequenceNumber) -> bool {
+    let duplicate = self.should_ignore_change(sn);
+    self.restart_evidence(duplicate, !duplicate, i64::from(sn))
+  }
+
+  fn restart_evidence(&mut self, suspicious: bool, may_clear: bool, order: i64) -> bool {
+    if !suspicious {
+      if may_clear {
+        self.restart_suspect = None;
+      }
+      return false;
+    }
+    match self.restart_suspect {
+      Some(prev) if prev < order => true,
+      _ => {
+        self.restart_suspect = Some(order);
+        false
+      }
+    }
+  }
+
+  // Forget everything received from the Writer, so that its restarted sequence
+  // numbering is accepted as if it were a new Writer. The ACKNACK count keeps
+  // increasing, as the protocol requires.
+  pub fn reset_sequence_numbering(&mut self) {
+    self.changes.clear();
+    self.ack_base = SequenceNumber::new(1);
+    self.received_heartbeat_count = 0;
+    self.restart_suspect = None;
+    self.heartbeat_first_sn = SequenceNumber::new(0);
+    self.app_unacknowledged.clear();
+  }
+
+  // Checks the sequence number range of a HEARTBEAT, see RTPS spec v2.5
+  // Section "8.3.8.6.3 Validity". Returns the violation, if the HEARTBEAT must
+  // be ignored.
+  pub fn heartbeat_misbehavior(
+    &self,
+    count: i32,
+    first_sn: SequenceNumber,
+    last_sn: SequenceNumber,
+  ) -> Option<WriterMisbehavior> {
+    if first_sn < SequenceNumber::new(1) {
+      Some(WriterMisbehavior::InvalidHeartbeatRange)
+    } else if i64::from(last_sn) - i64::from(first_sn) >= i64::from(i32::MAX) {
+      // Writer history size is limited by the 32-bit RESOURCE_LIMITS. Such a
+      // range could only be used to make us compute a huge set of missing
+      // changes.
+      Some(WriterMisbehavior::ExcessiveHeartbeatRange)
+    } else if last_sn + SequenceNumber::new(1) < first_sn {
+      Some(WriterMisbehavior::InvalidHeartbeatRange)
+    } else if count > self.received_heartbeat_count
+      && first_sn < self.heartbeat_first_sn
+      && last_sn + SequenceNumber::new(1) >= self.ack_base
+    {
+      // A restarted Writer moves back also its last_sn, which is detected
+      // separately.
+      Some(WriterMisbehavior::SequenceNumberRegression)
     } else {
       None
     }
   }
 
+  // Records a protocol violation of the Writer. Returns true, if the Writer
+  // has now committed max_violations within the quarantine duration, and is
+  // quarantined.
+  pub fn record_violation(&mut self, now: Timestamp, quarantine: policy::WriterQuarantine) -> bool {
+    self
+      .recent_violations
+      .retain(|t| now.duration_since(*t) < quarantine.duration);
+    self.recent_violations.push(now);
+    let max_violations = usize::try_from(quarantine.max_violations).unwrap_or(0);
+    if self.recent_violations.len() < max(max_violations, 1) {
+      return false;
+    }
+    self.recent_violations.clear();
+    self.quarantined_until = Some(now + quarantine.duration);
+    true
+  }
+
+  // Messages from a rejected or quarantined Writer are ignored. A quarantine
+  // ends by itself, and then the Writer is served again.
+  pub fn is_ignored(&mut self, now: Timestamp) -> bool {
+    if self.quarantined_until.is_some_and(|until| until <= now) {
+      info!(
+        "Quarantine of Writer {:?} has ended",
+        self.remote_writer_guid
+      );
+      self.quarantined_until = None;
+    }
+    self.rejected || self.quarantined_until.is_some()
+  }
+
   // Check if we no samples in the received state.
   pub fn no_changes_received(&self) -> bool {
     self.ack_base == SequenceNumber::new(0) && self.changes.is_empty()
@@ -207,12 +338,6 @@ impl RtpsWriterProxy {
   pub fn received_changes_add(&mut self, seq_num: SequenceNumber, receive_timestamp: Timestamp) {
     self.changes.insert(seq_num, Some(receive_timestamp));
 
-    // Update deadline tracker
-    if seq_num > self.last_received_sequence_number {
-      self.last_received_sequence_number = seq_num;
-      self.last_received_timestamp = receive_timestamp;
-    }
-
     // We get to advance ack_base if it was equal to seq_num
     // If ack_base < seq_num, we are still missing seq_num-1 or others below
     // If ack_base > seq_num, this is either a duplicate or ack_base was wrong.
@@ -222,6 +347,43 @@ impl RtpsWriterProxy {
     }
   }
 
+  // A received change that the application is to acknowledge
+  pub fn expect_app_ack(&mut self, seq_num: SequenceNumber) {
+    self.app_unacknowledged.insert(seq_num);
+  }
+
+  pub fn app_acknowledge(&mut self, seq_nums: &[SequenceNumber]) {
+    for sn in seq_nums {
+      self.app_unacknowledged.remove(sn);
+    }
+  }
+
+  pub fn next_app_ack_count(&mut self) -> i32 {
+    self.sent_app_ack_count += 1;
+    self.sent_app_ack_count
+  }
+
+  // The acknowledgments of the application, as sent in APP_ACK. Everything
+  // before the first unacknowledged change is acknowledged, or was never
+  // delivered to the application. Changes not received yet are not
+  // acknowledged.
+  pub fn app_ack_state(&self) -> SequenceNumberSet {
+    let base = self
+      .app_unacknowledged
+      .first()
+      .map_or(self.ack_base, |&first| first.min(self.ack_base))
+      .max(SequenceNumber::new(1));
+    let end = self.ack_base.min(base + SequenceNumber::new(256));
+    let acknowledged = if base < end {
+      SequenceNumber::range_inclusive(base, end - SequenceNumber::new(1))
+        .filter(|sn| !self.app_unacknowledged.contains(sn))
+        .collect()
+    } else {
+      BTreeSet::new()
+    };
+    SequenceNumberSet::from_base_and_set(base, &acknowledged)
+  }
+
   // Used to add individual irrelevant changes from GAP message
   pub fn set_irrelevant_change(&mut self, seq_num: SequenceNumber) {
     // If sequence number is still in the relevant range,
@@ -326,8 +488,14 @@ impl RtpsWriterProxy {
       received_heartbeat_count: 0,
       sent_ack_nack_count: 0,
       ack_base: SequenceNumber::default(),
-      last_received_sequence_number: SequenceNumber::new(0),
-      last_received_timestamp: Timestamp::INVALID,
+      restart_suspect: None,
+      rejected: false,
+      heartbeat_first_sn: SequenceNumber::new(0),
+      app_unacknowledged: BTreeSet::new(),
+      sent_app_ack_count: 0,
+      recent_violations: Vec::new(),
+      quarantined_until: None,
+      _leak_tracker: LeakTracker::new(TrackedObject::WriterProxy),
     }
   } // fn
 
@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+
+use crate::{
+  dds::key::KeyHash,
+  structure::{duration::Duration, time::Timestamp},
+};
+
+// Decides when a Writer pushes the changes written to it.
+//
+// Each written change is added to the trigger, and the Writer sends the
+// pending changes when the trigger is due, followed by a single HEARTBEAT.
+// The trigger is due when any of these is reached:
+//
+// * count: the number of pending changes,
+// * size: the total payload size of pending changes,
+// * deadline: the oldest pending change has waited for the maximum delay.
+//
+// Without a maximum delay, the trigger is due as soon as anything is pending,
+// i.e. changes are sent when the Writer has processed the writes that are
+// currently queued. With LATENCY_BUDGET, the budget is the maximum delay, so
+// writes made within the budget are sent together.
+//
+// A held trigger is never due. It is held while a PublisherFlushController
+// defers sending, and then the pending changes are sent only on an explicit
+// flush.
+//
+// If conflation is in effect, the pending changes that have an instance are
+// conflated when taken: only the newest one of each instance is sent.
+//
+// All decisions take the current time as a parameter, so that the send
+// timing is deterministic and can be tested without a clock.
+pub(crate) struct SendTrigger {
+  max_count: usize,
+  max_bytes: usize,
+  max_delay: Option<Duration>,
+  held: bool,
+  // Pending changes in writing order, with their instance, if they are to be
+  // conflated.
+  pending: Vec<(Option<KeyHash>, Timestamp)>,
+  pending_bytes: usize,
+  // When the oldest pending change was added
+  oldest_pending_at: Option<Timestamp>,
+}
+
+// Changes taken from a SendTrigger
+pub(crate) struct PendingChanges {
+  // The changes to send, in writing order
+  pub to_send: Vec<Timestamp>,
+  // How many changes were left out due to conflation
+  pub conflated: usize,
+}
+
+impl SendTrigger {
+  // Sending many changes in a row may delay other traffic, so a burst is
+  // limited to this many changes or about this many payload bytes.
+  pub const DEFAULT_MAX_COUNT: usize = 64;
+  pub const DEFAULT_MAX_BYTES: usize = 64 * 1024;
+
+  pub fn new(max_count: usize, max_bytes: usize, max_delay: Option<Duration>) -> Self {
+    Self {
+      max_count: max_count.max(1),
+      max_bytes,
+      max_delay: max_delay.filter(|delay| *delay > Duration::ZERO && *delay != Duration::INFINITE),
+      held: false,
+      pending: Vec::new(),
+      pending_bytes: 0,
+      oldest_pending_at: None,
+    }
+  }
+
+  pub fn with_max_delay(max_delay: Option<Duration>) -> Self {
+    Self::new(Self::DEFAULT_MAX_COUNT, Self::DEFAULT_MAX_BYTES, max_delay)
+  }
+
+  pub fn set_held(&mut self, held: bool) {
+    self.held = held;
+  }
+
+  pub fn is_held(&self) -> bool {
+    self.held
+  }
+
+  pub fn add(
+    &mut self,
+    instance: Option<KeyHash>,
+    timestamp: Timestamp,
+    bytes: usize,
+    now: Timestamp,
+  ) {
+    if self.pending.is_empty() {
+      self.oldest_pending_at = Some(now);
+    }
+    self.pending.push((instance, timestamp));
+    self.pending_bytes += bytes;
+  }
+
+  // The time when the pending changes must be sent, if not sent earlier due to
+  // count or size.
+  pub fn deadline(&self) -> Option<Timestamp> {
+    if self.held {
+      return None;
+    }
+    self
+      .oldest_pending_at
+      .map(|oldest| oldest + self.max_delay.unwrap_or(Duration::ZERO))
+  }
+
+  pub fn is_due(&self, now: Timestamp) -> bool {
+    !self.held
+      && !self.pending.is_empty()
+      && (self.pending.len() >= self.max_count
+        || self.pending_bytes >= self.max_bytes
+        || self.deadline().is_some_and(|deadline| deadline <= now))
+  }
+
+  // Takes all the pending changes, whether the trigger is due or not.
+  pub fn take_pending(&mut self) -> PendingChanges {
+    let pending = std::mem::take(&mut self.pending);
+    self.pending_bytes = 0;
+    self.oldest_pending_at = None;
+
+    let newest: BTreeMap<KeyHash, Timestamp> = pending
+      .iter()
+      .filter_map(|(instance, timestamp)| instance.map(|i| (i, *timestamp)))
+      .collect();
+    let count = pending.len();
+    let to_send: Vec<Timestamp> = pending
+      .into_iter()
+      .filter(|(instance, timestamp)| match instance {
+        Some(instance) => newest.get(instance) == Some(timestamp),
+        None => true,
+      })
+      .map(|(_, timestamp)| timestamp)
+      .collect();
+    PendingChanges {
+      conflated: count - to_send.len(),
+      to_send,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dds::key::Key;
+
+  fn at(secs: i32) -> Timestamp {
+    Timestamp::ZERO + Duration::from_secs(secs)
+  }
+
+  #[test]
+  fn due_without_delay_unless_held() {
+    let mut trigger = SendTrigger::with_max_delay(None);
+    assert!(!trigger.is_due(at(0)));
+    trigger.add(None, at(1), 10, at(1));
+    assert!(trigger.is_due(at(1)));
+
+    trigger.set_held(true);
+    assert!(!trigger.is_due(at(100)));
+    assert_eq!(trigger.deadline(), None);
+    trigger.set_held(false);
+    assert_eq!(trigger.take_pending().to_send, vec![at(1)]);
+    assert!(!trigger.is_due(at(100)));
+  }
+
+  #[test]
+  fn due_on_deadline_count_or_size() {
+    let mut trigger = SendTrigger::new(3, 1000, Some(Duration::from_secs(10)));
+    trigger.add(None, at(1), 100, at(1));
+    trigger.add(None, at(5), 100, at(5));
+    assert_eq!(trigger.deadline(), Some(at(11)));
+    assert!(!trigger.is_due(at(10)));
+    assert!(trigger.is_due(at(11)));
+
+    trigger.add(None, at(6), 100, at(6));
+    assert!(trigger.is_due(at(6)));
+    assert_eq!(trigger.take_pending().to_send, vec![at(1), at(5), at(6)]);
+    assert_eq!(trigger.deadline(), None);
+
+    trigger.add(None, at(20), 1000, at(20));
+    assert!(trigger.is_due(at(20)));
+  }
+
+  #[test]
+  fn conflates_pending_changes_by_instance() {
+    let mut trigger = SendTrigger::with_max_delay(Some(Duration::from_secs(10)));
+    let (instance_1, instance_2) = (1i32.hash_key(false), 2i32.hash_key(false));
+    for (secs, instance) in [
+      (1, Some(instance_1)),
+      (2, None),
+      (3, Some(instance_2)),
+      (4, Some(instance_1)),
+    ] {
+      trigger.add(instance, at(secs), 0, at(secs));
+    }
+    let pending = trigger.take_pending();
+    assert_eq!(pending.to_send, vec![at(2), at(3), at(4)]);
+    assert_eq!(pending.conflated, 1);
+    assert!(trigger.take_pending().to_send.is_empty());
+  }
+}
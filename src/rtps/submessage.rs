@@ -6,8 +6,11 @@ use log::{debug, error, trace};
 use speedy::{Context, Readable, Writable, Writer};
 
 use crate::{
+  dds::compliance,
   messages::submessages::{
     ack_nack::AckNack,
+    app_ack::AppAck,
+    fec_parity::FecParity,
     heartbeat::Heartbeat,
     info_destination::InfoDestination,
     info_source::InfoSource,
@@ -15,9 +18,9 @@ use crate::{
     nack_frag::NackFrag,
     submessage::{ReaderSubmessage, WriterSubmessage},
     submessage_flag::{
-      endianness_flag, ACKNACK_Flags, DATAFRAG_Flags, DATA_Flags, GAP_Flags, HEARTBEATFRAG_Flags,
-      HEARTBEAT_Flags, INFODESTINATION_Flags, INFOREPLY_Flags, INFOSOURCE_Flags,
-      INFOTIMESTAMP_Flags, NACKFRAG_Flags,
+      endianness_flag, ACKNACK_Flags, APPACK_Flags, DATAFRAG_Flags, DATA_Flags, FECPARITY_Flags,
+      GAP_Flags, HEARTBEATFRAG_Flags, HEARTBEAT_Flags, INFODESTINATION_Flags, INFOREPLY_Flags,
+      INFOSOURCE_Flags, INFOTIMESTAMP_Flags, NACKFRAG_Flags,
     },
     submessage_header::SubmessageHeader,
     submessage_kind::SubmessageKind,
@@ -90,6 +93,20 @@ impl Submessage {
       ));
     };
 
+    // RTPS spec v2.5 Section 9.4.1: Submessages are aligned to 4 bytes.
+    if sub_content_length % 4 != 0
+      && buffer.len() > sub_header_length + sub_content_length
+      && !compliance::tolerate(&format!(
+        "Misaligned {:?} submessage length {sub_content_length}, not the last one.",
+        sub_header.kind
+      ))
+    {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Submessage length {sub_content_length} is not a multiple of 4"),
+      ));
+    }
+
     // split first submessage to new buffer
     let mut sub_buffer = buffer.split_to(sub_header_length + sub_content_length);
     let original_submessage_bytes = sub_buffer.clone();
@@ -193,6 +210,31 @@ impl Submessage {
         ))
       }
 
+      SubmessageKind::FEC_PARITY => {
+        // Vendor-specific. Other vendors may use the same kind for something
+        // else, so a submessage that does not parse is ignored.
+        let f = BitFlags::<FECPARITY_Flags>::from_bits_truncate(sub_header.flags);
+        match FecParity::read_from_buffer_with_ctx(e, &sub_content_buffer) {
+          Ok(fec_parity) => mk_w_subm(WriterSubmessage::FecParity(fec_parity, f)),
+          Err(e) => {
+            trace!("Vendor-specific submessage kind {:?}: {e}", sub_header.kind);
+            Ok(None)
+          }
+        }
+      }
+
+      SubmessageKind::APP_ACK => {
+        // Vendor-specific, like FEC_PARITY
+        let f = BitFlags::<APPACK_Flags>::from_bits_truncate(sub_header.flags);
+        match AppAck::read_from_buffer_with_ctx(e, &sub_content_buffer) {
+          Ok(app_ack) => mk_r_subm(ReaderSubmessage::AppAck(app_ack, f)),
+          Err(e) => {
+            trace!("Vendor-specific submessage kind {:?}: {e}", sub_header.kind);
+            Ok(None)
+          }
+        }
+      }
+
       // interpreter submessages
       SubmessageKind::INFO_DST => {
         let f = BitFlags::<INFODESTINATION_Flags>::from_bits_truncate(sub_header.flags);
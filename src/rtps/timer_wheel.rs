@@ -0,0 +1,355 @@
+use std::{cmp::max, collections::BTreeMap};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::structure::time::Timestamp;
+
+// Hierarchical timer wheel for QoS timers (DEADLINE, LIVELINESS, ...).
+//
+// Readers and Writers may need to track a timer for each instance or each
+// remote endpoint, which may be tens of thousands of timers. Registering each
+// one with the event loop (mio) timer is not scalable. Instead, each Reader or
+// Writer keeps a TimerWheel and registers only the earliest expiration with
+// the event loop.
+//
+// The wheel has LEVELS levels of SLOTS slots each. Level 0 slots are one tick
+// wide, level 1 slots are SLOTS ticks wide, etc. A timer is placed on the level
+// given by the most significant bit where its expiration tick differs from the
+// current tick. When a higher-level slot is reached, its timers are cascaded
+// down to lower levels. This gives O(1) insert and cancel, and expiration cost
+// proportional to the number of expiring timers.
+//
+// Cancel and reschedule are lazy: the authoritative expiration time of each key
+// is in `expirations`, and slot entries that do not agree with it are stale and
+// are dropped when their slot is processed.
+
+// One tick is 2^22 / 2^32 seconds, i.e. slightly less than 1 ms.
+const TICK_SHIFT: u32 = 22;
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS; // 64
+const LEVELS: usize = 6; // 64^6 ticks is about 2 years
+
+// Ticks covered by the whole wheel. Timers beyond the current wheel epoch are
+// kept in an overflow list and placed into the wheel when the epoch changes.
+const WHEEL_RANGE: u64 = 1 << (SLOT_BITS as usize * LEVELS);
+
+struct Level<K> {
+  occupied: u64, // bit i is set, if slots[i] is not empty
+  slots: Vec<Vec<(K, u64)>>,
+}
+
+impl<K> Level<K> {
+  fn new() -> Self {
+    Self {
+      occupied: 0,
+      slots: (0..SLOTS).map(|_| Vec::new()).collect(),
+    }
+  }
+}
+
+fn slot_range(level: usize) -> u64 {
+  1 << (SLOT_BITS as usize * level)
+}
+
+fn level_range(level: usize) -> u64 {
+  1 << (SLOT_BITS as usize * (level + 1))
+}
+
+pub(crate) struct TimerWheel<K> {
+  current_tick: u64,
+  levels: Vec<Level<K>>,
+  overflow: Vec<(K, u64)>,
+  // Authoritative expiration tick of each active timer
+  expirations: BTreeMap<K, u64>,
+}
+
+impl<K: Ord + Clone> TimerWheel<K> {
+  pub fn new(now: Timestamp) -> Self {
+    Self {
+      current_tick: Self::tick_floor(now),
+      levels: (0..LEVELS).map(|_| Level::new()).collect(),
+      overflow: Vec::new(),
+      expirations: BTreeMap::new(),
+    }
+  }
+
+  fn tick_floor(t: Timestamp) -> u64 {
+    t.to_ticks() >> TICK_SHIFT
+  }
+
+  // Round up, so that timers never expire before their time.
+  fn tick_ceil(t: Timestamp) -> u64 {
+    t.to_ticks().saturating_add((1 << TICK_SHIFT) - 1) >> TICK_SHIFT
+  }
+
+  fn tick_to_timestamp(tick: u64) -> Timestamp {
+    Timestamp::from_ticks(tick << TICK_SHIFT)
+  }
+
+  pub fn len(&self) -> usize {
+    self.expirations.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.expirations.is_empty()
+  }
+
+  /// Set a timer to expire at `expires_at`. If the key already has a
+  /// timer, it is rescheduled.
+  pub fn schedule(&mut self, key: K, expires_at: Timestamp) {
+    let tick = Self::tick_ceil(expires_at);
+    if self.expirations.insert(key.clone(), tick) == Some(tick) {
+      // Already scheduled to the same tick. The old slot entry is still valid.
+      return;
+    }
+    self.place(key, tick);
+  }
+
+  /// Remove a timer. Returns true if the timer existed.
+  pub fn cancel(&mut self, key: &K) -> bool {
+    // The slot entry becomes stale and is dropped later.
+    self.expirations.remove(key).is_some()
+  }
+
+  /// The time when the next timer expires, if any.
+  ///
+  /// This may be early, if the earliest timer is cancelled or is on a
+  /// higher level of the wheel. Calling [`Self::expire`] at that time is
+  /// harmless, it just returns nothing.
+  pub fn next_expiration(&self) -> Option<Timestamp> {
+    if self.is_empty() {
+      None
+    } else {
+      self
+        .next_slot()
+        .map(|(_slot, tick)| Self::tick_to_timestamp(tick))
+    }
+  }
+
+  /// Advance wheel time to `now`, remove expired timers and return their keys.
+  ///
+  /// Keys are returned in expiration order, with arbitrary order within one
+  /// tick.
+  pub fn expire(&mut self, now: Timestamp) -> Vec<K> {
+    let now_tick = Self::tick_floor(now);
+    let mut expired = Vec::new();
+
+    while let Some((level, slot_tick)) = self.next_slot() {
+      if slot_tick > now_tick {
+        break;
+      }
+      self.current_tick = max(self.current_tick, slot_tick);
+      let entries = match level {
+        Some((level, slot)) => {
+          self.levels[level].occupied &= !(1 << slot);
+          std::mem::take(&mut self.levels[level].slots[slot])
+        }
+        None => std::mem::take(&mut self.overflow),
+      };
+      for (key, tick) in entries {
+        if self.expirations.get(&key) != Some(&tick) {
+          continue; // stale entry: cancelled or rescheduled
+        }
+        if tick <= self.current_tick {
+          self.expirations.remove(&key);
+          expired.push(key);
+        } else {
+          // cascade down to a lower level
+          self.place(key, tick);
+        }
+      }
+    }
+
+    self.current_tick = max(self.current_tick, now_tick);
+    expired
+  }
+
+  fn place(&mut self, key: K, tick: u64) {
+    // Timers in the past are placed in the current slot, so that they expire
+    // on next call to expire().
+    let placement_tick = tick.max(self.current_tick);
+
+    let significant = (placement_tick ^ self.current_tick) | (SLOTS as u64 - 1);
+    let level = ((63 - significant.leading_zeros()) / SLOT_BITS) as usize;
+    if level >= LEVELS {
+      self.overflow.push((key, tick));
+      return;
+    }
+    let slot = ((placement_tick >> (SLOT_BITS as usize * level)) as usize) & (SLOTS - 1);
+
+    let l = &mut self.levels[level];
+    l.slots[slot].push((key, tick));
+    l.occupied |= 1 << slot;
+  }
+
+  // Find the next slot to process and the tick when it is due.
+  // Level and slot number are None for the overflow list.
+  //
+  // Lower levels always expire before higher levels, because a timer is on
+  // level L only if its tick agrees with the current tick on all digits
+  // above L, and a level L+1 timer does not.
+  fn next_slot(&self) -> Option<(Option<(usize, usize)>, u64)> {
+    let now = self.current_tick;
+    for (level_num, level) in self.levels.iter().enumerate() {
+      if level.occupied == 0 {
+        continue;
+      }
+      let now_slot = ((now / slot_range(level_num)) as usize) & (SLOTS - 1);
+      // Occupied slots are never behind the current slot, so a rotate is not
+      // needed. Clear lower bits anyway, for robustness.
+      let ahead = level.occupied & (u64::MAX << now_slot);
+      let slot = if ahead != 0 {
+        ahead.trailing_zeros() as usize
+      } else {
+        level.occupied.trailing_zeros() as usize
+      };
+      let level_start = now & !(level_range(level_num) - 1);
+      let slot_tick = level_start + slot as u64 * slot_range(level_num);
+      return Some((Some((level_num, slot)), slot_tick.max(now)));
+    }
+    if self.overflow.is_empty() {
+      None
+    } else {
+      // Start of the next wheel epoch
+      Some((None, (now | (WHEEL_RANGE - 1)).saturating_add(1)))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Instant;
+
+  use super::*;
+  use crate::structure::duration::Duration;
+
+  #[test]
+  fn timers_expire_in_order() {
+    let start = Timestamp::now();
+    let mut wheel = TimerWheel::new(start);
+
+    wheel.schedule(3, start + Duration::from_millis(300));
+    wheel.schedule(1, start + Duration::from_millis(10));
+    wheel.schedule(2, start + Duration::from_secs(100));
+    assert_eq!(wheel.len(), 3);
+
+    assert!(wheel.expire(start).is_empty());
+    assert_eq!(wheel.expire(start + Duration::from_millis(20)), vec![1]);
+    assert!(wheel.expire(start + Duration::from_millis(200)).is_empty());
+    assert_eq!(wheel.expire(start + Duration::from_secs(1)), vec![3]);
+    assert!(wheel.expire(start + Duration::from_secs(99)).is_empty());
+    assert_eq!(wheel.expire(start + Duration::from_secs(101)), vec![2]);
+    assert!(wheel.is_empty());
+    assert_eq!(wheel.next_expiration(), None);
+  }
+
+  #[test]
+  fn timers_do_not_expire_early() {
+    let start = Timestamp::now();
+    let mut wheel = TimerWheel::new(start);
+    let deadline = start + Duration::from_millis(5000);
+    wheel.schedule(1, deadline);
+
+    assert!(wheel.expire(deadline - Duration::from_millis(2)).is_empty());
+    assert!(wheel.next_expiration().unwrap() >= deadline - Duration::from_millis(2));
+    assert_eq!(wheel.expire(deadline + Duration::from_millis(1)), vec![1]);
+  }
+
+  #[test]
+  fn cancel_and_reschedule() {
+    let start = Timestamp::now();
+    let mut wheel = TimerWheel::new(start);
+
+    wheel.schedule("a", start + Duration::from_millis(100));
+    wheel.schedule("b", start + Duration::from_millis(100));
+    assert!(wheel.cancel(&"a"));
+    assert!(!wheel.cancel(&"a"));
+    // move b later
+    wheel.schedule("b", start + Duration::from_secs(10));
+
+    assert!(wheel.expire(start + Duration::from_secs(1)).is_empty());
+    assert_eq!(wheel.len(), 1);
+    // move b earlier again
+    wheel.schedule("b", start + Duration::from_secs(2));
+    assert_eq!(wheel.expire(start + Duration::from_secs(3)), vec!["b"]);
+    assert!(wheel.expire(start + Duration::from_secs(20)).is_empty());
+  }
+
+  #[test]
+  fn past_and_far_future_timers() {
+    let start = Timestamp::now();
+    let mut wheel = TimerWheel::new(start);
+
+    wheel.schedule(1, start - Duration::from_secs(1));
+    wheel.schedule(2, start + Duration::from_secs(100_000_000)); // over 3 years
+    assert_eq!(wheel.expire(start), vec![1]);
+    assert!(wheel
+      .expire(start + Duration::from_secs(90_000_000))
+      .is_empty());
+    assert_eq!(
+      wheel.expire(start + Duration::from_secs(100_000_001)),
+      vec![2]
+    );
+  }
+
+  #[test]
+  fn next_expiration_tracks_earliest() {
+    let start = Timestamp::now();
+    let mut wheel = TimerWheel::new(start);
+    assert_eq!(wheel.next_expiration(), None);
+
+    wheel.schedule(1, start + Duration::from_secs(60));
+    let first = wheel.next_expiration().unwrap();
+    assert!(first <= start + Duration::from_secs(61));
+
+    wheel.schedule(2, start + Duration::from_millis(50));
+    let second = wheel.next_expiration().unwrap();
+    assert!(second <= start + Duration::from_millis(51));
+  }
+
+  // Benchmark: 100k instances, each rescheduled on every sample.
+  // Run with `cargo test --release timer_wheel_100k -- --ignored --nocapture`
+  #[test]
+  #[ignore]
+  fn timer_wheel_100k_instances_benchmark() {
+    const INSTANCES: u32 = 100_000;
+    const ROUNDS: u32 = 10;
+
+    let start = Timestamp::now();
+    let mut wheel = TimerWheel::new(start);
+    let deadline = Duration::from_millis(100);
+
+    let bench_start = Instant::now();
+    for i in 0..INSTANCES {
+      wheel.schedule(i, start + deadline);
+    }
+    println!("schedule {INSTANCES} timers: {:?}", bench_start.elapsed());
+
+    // Each round, all instances get a sample and are rescheduled.
+    let bench_start = Instant::now();
+    for round in 1..=ROUNDS {
+      let now = start + Duration::from_millis(i64::from(round) * 50);
+      for i in 0..INSTANCES {
+        wheel.schedule(i, now + deadline);
+      }
+      assert!(wheel.expire(now).is_empty());
+    }
+    println!(
+      "reschedule {} timers: {:?}",
+      INSTANCES * ROUNDS,
+      bench_start.elapsed()
+    );
+
+    // Then samples stop and all deadlines are missed.
+    let bench_start = Instant::now();
+    let expired = wheel.expire(start + Duration::from_secs(10));
+    println!(
+      "expire {} timers: {:?}",
+      expired.len(),
+      bench_start.elapsed()
+    );
+    assert_eq!(expired.len(), INSTANCES as usize);
+    assert!(wheel.is_empty());
+  }
+}
@@ -1,5 +1,7 @@
 use std::{
-  cmp::max,
+  borrow::Cow,
+  cell::RefCell,
+  cmp::{max, min},
   collections::{BTreeMap, BTreeSet},
   ops::Bound::Included,
   rc::Rc,
@@ -12,13 +14,16 @@ use log::{debug, error, info, trace, warn};
 use speedy::{Endianness, Writable};
 use mio_extras::{
   channel::{self as mio_channel, TrySendError},
-  timer::Timer,
+  timer::{Timeout, Timer},
 };
 use mio_06::Token;
 
 use crate::{
   dds::{
+    communication_status::WriterStatusRecord,
+    content_filter::MatchedReaderFilters,
     ddsdata::DDSData,
+    key::KeyHash,
     qos::{
       policy,
       policy::{History, Reliability},
@@ -27,21 +32,34 @@ use crate::{
     statusevents::{
       CountWithChange, DataWriterStatus, DomainParticipantStatusEvent, StatusChannelSender,
     },
-    with_key::datawriter::WriteOptions,
+    with_key::datawriter::{
+      SequenceNumberWatermarks, WriteOptions, WriteOptionsBuilder, WriterHistoryStatus,
+    },
+  },
+  discovery::lease_assertion::SpdpTransmissions,
+  messages::submessages::{
+    elements::{inline_qos::InlineQos, parameter::Parameter, parameter_list::ParameterList},
+    submessages::{AckSubmessage, FecParity},
   },
-  messages::submessages::submessages::AckSubmessage,
   network::udp_sender::UDPSender,
   rtps::{
     constant::{NACK_RESPONSE_DELAY, NACK_SUPPRESSION_DURATION},
+    fec::FecEncoder,
+    flow_controller::FlowController,
+    message::concatenate_messages,
     rtps_reader_proxy::RtpsReaderProxy,
+    send_trigger::SendTrigger,
+    timer_wheel::TimerWheel,
     Message, MessageBuilder,
   },
+  serialization::{pl_cdr_adapters::PlCdrSerializeError, speedy_pl_cdr_helpers::StringWithNul},
   structure::{
-    cache_change::CacheChange,
+    cache_change::{CacheChange, ChangeKind},
     duration::Duration,
     entity::RTPSEntity,
     guid::{EntityId, GuidPrefix, GUID},
     locator::Locator,
+    parameter_id::ParameterId,
     sequence_number::{FragmentNumber, SequenceNumber},
     time::Timestamp,
   },
@@ -49,11 +67,60 @@ use crate::{
 #[cfg(feature = "security")]
 use crate::{
   rtps::Submessage,
-  security::{security_plugins::SecurityPluginsHandle, SecurityResult},
+  security::{
+    config::OriginAuthentication, security_plugins::SecurityPluginsHandle, SecurityResult,
+  },
 };
 #[cfg(not(feature = "security"))]
 use crate::no_security::SecurityPluginsHandle;
 
+// A periodic HEARTBEAT is multicast only if it reaches at least this many
+// Readers that have not acknowledged everything. Otherwise, it is unicast
+// to each of them.
+const MULTICAST_HEARTBEAT_MIN_READERS: usize = 2;
+
+// Upper limit for datagrams combined from the messages of a batch of changes
+const BATCH_DATAGRAM_MAX_SIZE: usize = 1500;
+
+// Upper limit for messages packed by the Batching policy. Leaves room for the
+// IP and UDP headers within the largest UDP datagram.
+const BATCHING_MAX_MESSAGE_SIZE: usize = 65_000;
+
+// Splits the readers of a message into batches of at most max_macs readers,
+// so that the message to each batch carries at most max_macs
+// receiver-specific MACs. The readers of one participant are kept in the same
+// batch if they fit in one, so that the participant receives the message only
+// once.
+#[cfg(feature = "security")]
+fn receiver_specific_mac_batches<T: Copy>(
+  readers: &[T],
+  guid: impl Fn(&T) -> GUID,
+  max_macs: usize,
+) -> Vec<Vec<T>> {
+  let max_macs = max_macs.max(1);
+  let mut readers = readers.to_vec();
+  readers.sort_by_key(&guid);
+
+  let mut participants: Vec<Vec<T>> = Vec::new();
+  for reader in readers {
+    match participants.last_mut() {
+      Some(participant) if guid(&participant[0]).prefix == guid(&reader).prefix => {
+        participant.push(reader);
+      }
+      _ => participants.push(vec![reader]),
+    }
+  }
+
+  let mut batches: Vec<Vec<T>> = Vec::new();
+  for participant in participants {
+    match batches.last_mut() {
+      Some(batch) if batch.len() + participant.len() <= max_macs => batch.extend(participant),
+      _ => batches.extend(participant.chunks(max_macs).map(<[T]>::to_vec)),
+    }
+  }
+  batches
+}
+
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum DeliveryMode {
   Unicast,
@@ -66,6 +133,17 @@ pub(crate) enum TimedEvent {
   CacheCleaning,
   SendRepairData { to_reader: GUID },
   SendRepairFrags { to_reader: GUID },
+  QosTimerCheck,
+  SendTrigger,
+  AckWaitTimeout,
+}
+
+// QoS timers of a Writer. These are kept in a TimerWheel, and only the
+// earliest one is registered to the event loop timer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum QosTimer {
+  // DEADLINE offered by this Writer
+  OfferedDeadline,
 }
 
 // This is used to construct an actual Writer.
@@ -78,6 +156,13 @@ pub(crate) struct WriterIngredients {
   pub(crate) like_stateless: bool, // Usually false (see like_stateless attribute of Writer)
   pub qos_policies: QosPolicies,
   pub status_sender: StatusChannelSender<DataWriterStatus>,
+  // Shared with the DataWriter, which reports it
+  pub conflated_samples: Arc<atomic::AtomicU64>,
+  pub watermarks: Arc<WatermarkTracker>,
+  // Content filters of matched readers, applied by the DataWriter
+  pub matched_reader_filters: Arc<MatchedReaderFilters>,
+  // Communication statuses polled through the DataWriter
+  pub status_record: Arc<WriterStatusRecord>,
 
   pub(crate) security_plugins: Option<SecurityPluginsHandle>,
 }
@@ -90,16 +175,60 @@ impl WriterIngredients {
   }
 }
 
+// Sequence number watermarks of a Writer, updated by the Writer and read by
+// its DataWriter.
+#[derive(Debug)]
+pub(crate) struct WatermarkTracker {
+  watermarks: Mutex<SequenceNumberWatermarks>,
+  // Notification threshold for the number of unacknowledged samples.
+  // Zero means no notifications.
+  threshold: atomic::AtomicU64,
+}
+
+impl WatermarkTracker {
+  pub fn new() -> Self {
+    Self {
+      watermarks: Mutex::new(SequenceNumberWatermarks {
+        lowest_unacknowledged: SequenceNumber::new(1),
+        highest_written: SequenceNumber::zero(),
+      }),
+      threshold: atomic::AtomicU64::new(0),
+    }
+  }
+
+  pub fn watermarks(&self) -> SequenceNumberWatermarks {
+    *self.watermarks.lock().unwrap()
+  }
+
+  pub fn set_threshold(&self, threshold: Option<u64>) {
+    self
+      .threshold
+      .store(threshold.unwrap_or(0), atomic::Ordering::Relaxed);
+  }
+
+  // Stores new watermarks. If the number of unacknowledged samples dropped
+  // below the threshold, returns the new number and the threshold.
+  fn update(&self, new: SequenceNumberWatermarks) -> Option<(u64, u64)> {
+    let old = std::mem::replace(&mut *self.watermarks.lock().unwrap(), new);
+    let threshold = self.threshold.load(atomic::Ordering::Relaxed);
+    let unacknowledged = new.unacknowledged();
+    (threshold > 0 && old.unacknowledged() >= threshold && unacknowledged < threshold)
+      .then_some((unacknowledged, threshold))
+  }
+}
+
 struct AckWaiter {
   wait_until: SequenceNumber,
-  complete_channel: StatusChannelSender<()>,
+  complete_channel: StatusChannelSender<bool>,
   readers_pending: BTreeSet<GUID>,
+  timeout: Option<Timeout>, // Ends the wait unsuccessfully
 }
 
 impl AckWaiter {
-  pub fn notify_wait_complete(&self) {
+  // Tells the application whether all the Readers acknowledged
+  pub fn notify_wait_complete(&self, all_acked: bool) {
     // it is normal for the send to fail, because receiver may have timed out
-    let _ = self.complete_channel.try_send(());
+    let _ = self.complete_channel.try_send(all_acked);
   }
   pub fn reader_acked_or_lost(&mut self, guid: GUID, acked_before: Option<SequenceNumber>) -> bool // true = waiting complete
   {
@@ -225,6 +354,97 @@ impl HistoryBuffer {
       }
     }
   }
+
+  // Removes the changes before the sequence number, which need not be in the
+  // buffer. Returns the number of changes removed.
+  fn purge_before(&mut self, before: SequenceNumber) -> usize {
+    let before = min(before, self.last_seq.plus_1());
+    if before <= self.first_seq {
+      return 0;
+    }
+    let kept = self.sequence_number_to_instant.split_off(&before);
+    let removed = std::mem::replace(&mut self.sequence_number_to_instant, kept);
+    for timestamp in removed.values() {
+      self.history_buffer.remove(timestamp);
+    }
+    self.first_seq = before;
+    debug!(
+      "HistoryBuffer: purged {} changes before {before:?} topic={}",
+      removed.len(),
+      self.topic_name
+    );
+    removed.len()
+  }
+
+  // Takes the sequence number into use without keeping a change for it.
+  // Removes all changes before it.
+  fn skip(&mut self, sequence_number: SequenceNumber) {
+    if sequence_number > self.last_seq {
+      self.last_seq = sequence_number;
+      self.purge_before(sequence_number.plus_1());
+    } else {
+      error!("HistoryBuffer: Tried to skip changes out of SequenceNumber order.");
+    }
+  }
+
+  // Local samples are serialized when the first Reader that may need them over
+  // the network is matched.
+  fn serialize_local_changes(&mut self) {
+    for cc in self.history_buffer.values_mut() {
+      cc.data_value.serialize_local();
+    }
+  }
+
+  fn bytes_held(&self) -> usize {
+    self
+      .history_buffer
+      .values()
+      .map(|cc| cc.data_value.payload_size())
+      .sum()
+  }
+}
+
+// helper struct for Writer
+// The latest sample of each instance, kept for the LastValueCache policy
+// independently of the HistoryBuffer.
+#[derive(Default)]
+struct LastValues {
+  sequence_numbers: BTreeMap<KeyHash, SequenceNumber>,
+  changes: BTreeMap<SequenceNumber, CacheChange>,
+}
+
+impl LastValues {
+  fn update(&mut self, instance: KeyHash, cc: &CacheChange) {
+    if let Some(old_sn) = self.sequence_numbers.remove(&instance) {
+      self.changes.remove(&old_sn);
+    }
+    // A disposed or unregistered instance has no value for late joiners.
+    if cc.data_value.change_kind() == ChangeKind::Alive {
+      self.sequence_numbers.insert(instance, cc.sequence_number);
+      self.changes.insert(cc.sequence_number, cc.clone());
+    }
+  }
+
+  fn get_by_sn(&self, sn: SequenceNumber) -> Option<&CacheChange> {
+    self.changes.get(&sn)
+  }
+
+  fn sequence_numbers(&self) -> impl Iterator<Item = SequenceNumber> + '_ {
+    self.changes.keys().copied()
+  }
+
+  fn serialize_local_changes(&mut self) {
+    for cc in self.changes.values_mut() {
+      cc.data_value.serialize_local();
+    }
+  }
+}
+
+// A sample written for intra-process delivery, to be given directly to the
+// local Readers.
+pub(crate) struct LocalDelivery {
+  pub readers: Vec<EntityId>,
+  pub change: CacheChange,
 }
 
 pub(crate) struct Writer {
@@ -287,6 +507,11 @@ pub(crate) struct Writer {
 
   // Sending mechanism
   udp_sender: Rc<UDPSender>,
+  // Schedules the messages of this Writer by the FlowControl policy and the
+  // bandwidth limit of the participant, if either applies
+  flow_controller: Option<Rc<RefCell<FlowController>>>,
+  // Set for the SPDP Writer only. Records our SPDP messages as they are sent.
+  spdp_transmissions: Option<SpdpTransmissions>,
 
   // By default, this writer is a StatefulWriter (see RTPS spec section 8.4.9)
   // If like_stateless is true, then the writer mimics the behavior of a Best-Effort
@@ -304,6 +529,29 @@ pub(crate) struct Writer {
   my_topic_name: String,
 
   history_buffer: HistoryBuffer,
+  last_values: LastValues,
+  // Samples written for intra-process delivery, waiting for the event loop to
+  // hand them to the local Readers
+  local_deliveries: Vec<LocalDelivery>,
+  // Conflation policy is in effect. See policy::Conflation.
+  conflation: bool,
+  conflated_samples: Arc<atomic::AtomicU64>,
+  // ForwardErrorCorrection policy is in effect. The current group of samples.
+  fec_encoder: Option<FecEncoder>,
+  watermarks: Arc<WatermarkTracker>,
+  matched_reader_filters: Arc<MatchedReaderFilters>,
+  status_record: Arc<WriterStatusRecord>,
+  // Decides when written changes are pushed to Readers. Held while sending is
+  // deferred by a PublisherFlushController.
+  send_trigger: SendTrigger,
+  // The event loop timeout registered for the send_trigger deadline
+  send_trigger_armed: Option<Timeout>,
+  // Largest message that pending changes are packed into, if the Batching
+  // policy is in effect
+  batch_max_size: Option<usize>,
+  // Periodic timers have been started. Deferred until the first Reader
+  // matches by the WriterInitialization policy.
+  initialized: bool,
 
   /// Contains timer that needs to be set to timeout with duration of
   /// self.heartbeat_period timed_event_handler sends notification when timer
@@ -312,14 +560,26 @@ pub(crate) struct Writer {
   pub(crate) timed_event_timer: Timer<TimedEvent>,
 
   qos_policies: QosPolicies,
+  // Topic name and QoS policies sent as inline QoS to Readers that expect it.
+  // Such Readers do not track discovery state, so they learn our QoS from
+  // each DATA.
+  inline_qos: ParameterList,
 
   // Used for sending status info about messages sent
   status_sender: StatusChannelSender<DataWriterStatus>,
-  // offered_deadline_status: OfferedDeadlineMissedStatus,
+  offered_deadline_missed_count: i32,
+  // DEADLINE timers
+  qos_timers: TimerWheel<QosTimer>,
+  // The expiration time and event loop timeout registered for qos_timers
+  qos_timer_armed: Option<(Timestamp, Timeout)>,
   ack_waiter: Option<AckWaiter>,
   participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
 
   security_plugins: Option<SecurityPluginsHandle>,
+  // The most receiver-specific MACs in one message, if the writer is origin
+  // authenticated
+  #[cfg(feature = "security")]
+  max_receiver_specific_macs: usize,
 }
 
 pub enum WriterCommand {
@@ -328,9 +588,42 @@ pub enum WriterCommand {
     ddsdata: DDSData,
     write_options: WriteOptions,
     sequence_number: SequenceNumber,
+    // Instance of the sample, if the LastValueCache policy is enabled.
+    instance: Option<KeyHash>,
+  },
+  // Changes written together, each with its sequence number and instance as
+  // in DDSData. They are sent at once.
+  DDSDataBatch {
+    changes: Vec<(DDSData, SequenceNumber, Option<KeyHash>)>,
+    write_options: WriteOptions,
   },
   WaitForAcknowledgments {
-    all_acked: StatusChannelSender<()>,
+    all_acked: StatusChannelSender<bool>,
+    // If the Readers have not acknowledged by then, the wait ends with false.
+    max_wait: Option<std::time::Duration>,
+  },
+  HistoryStatus {
+    reply: std::sync::mpsc::Sender<WriterHistoryStatus>,
+  },
+  // Removes the acknowledged changes, or all of them, from the history. The
+  // number of changes removed is sent back.
+  PurgeHistory {
+    acknowledged_only: bool,
+    reply: std::sync::mpsc::Sender<usize>,
+  },
+  // Samples that each Reader has not acknowledged with APP_ACK
+  UnacknowledgedSamples {
+    reply: std::sync::mpsc::Sender<BTreeMap<GUID, Vec<SequenceNumber>>>,
+  },
+  // Writes up to count changes that reader has not acknowledged with APP_ACK
+  // again, to to_reader only. The new sequence numbers are taken from
+  // first_sequence_number on. The number of changes written is sent back.
+  RedeliverUnacknowledged {
+    reader: GUID,
+    to_reader: GUID,
+    first_sequence_number: SequenceNumber,
+    count: usize,
+    reply: std::sync::mpsc::Sender<usize>,
   },
   // ResetOfferedDeadlineMissedStatus { writer_guid: GUID },
 }
@@ -339,7 +632,7 @@ impl Writer {
   pub fn new(
     i: WriterIngredients,
     udp_sender: Rc<UDPSender>,
-    mut timed_event_timer: Timer<TimedEvent>,
+    timed_event_timer: Timer<TimedEvent>,
     participant_status_sender: StatusChannelSender<DomainParticipantStatusEvent>,
   ) -> Self {
     // If writer should behave statelessly, only BestEffort QoS is currently
@@ -373,18 +666,32 @@ impl Writer {
     // TODO: Configuration value
     let cache_cleaning_period = Duration::from_secs(6);
 
-    // Start periodic Heartbeat
-    if let Some(period) = heartbeat_period {
-      timed_event_timer.set_timeout(std::time::Duration::from(period), TimedEvent::Heartbeat);
-    }
-    // start periodic cache cleaning
-    timed_event_timer.set_timeout(
-      std::time::Duration::from(cache_cleaning_period),
-      TimedEvent::CacheCleaning,
-    );
+    let endianness = Endianness::LittleEndian;
+    let inline_qos =
+      Self::inline_qos(&i.topic_name, &i.qos_policies, endianness).unwrap_or_else(|e| {
+        error!(
+          "Cannot serialize inline QoS for topic {}: {e}",
+          i.topic_name
+        );
+        ParameterList::new()
+      });
 
-    Self {
-      endianness: Endianness::LittleEndian,
+    // Parity is computed from plaintext samples, so it is not sent with
+    // security, which may encrypt the samples.
+    let fec_encoder = match i.qos_policies.forward_error_correction() {
+      Some(policy::ForwardErrorCorrection::Xor { group_size })
+        if !i.qos_policies.is_reliable() && !i.like_stateless && i.security_plugins.is_none() =>
+      {
+        Some(FecEncoder::new(i.guid.entity_id, group_size))
+      }
+      _ => None,
+    };
+
+    let initialized =
+      i.qos_policies.writer_initialization() != Some(policy::WriterInitialization::OnFirstMatch);
+
+    let mut writer = Self {
+      endianness,
       heartbeat_message_counter: atomic::AtomicI32::new(1),
       push_mode: true,
       heartbeat_period,
@@ -403,16 +710,156 @@ impl Writer {
       matched_readers_count_total: 0,
       requested_incompatible_qos_count: 0,
       udp_sender,
+      flow_controller: None,
+      spdp_transmissions: None,
       my_topic_name: i.topic_name.clone(),
       history_buffer: HistoryBuffer::new(i.topic_name),
+      last_values: LastValues::default(),
+      local_deliveries: Vec::new(),
+      send_trigger: match i.qos_policies.batching() {
+        // Batches are bounded by size and delay, not by the count of samples.
+        Some(batching) => SendTrigger::new(
+          usize::MAX,
+          batching.max_bytes as usize,
+          Some(batching.max_delay),
+        ),
+        None => SendTrigger::with_max_delay(
+          i.qos_policies
+            .latency_budget()
+            .map(|latency_budget| latency_budget.duration),
+        ),
+      },
+      send_trigger_armed: None,
+      batch_max_size: i
+        .qos_policies
+        .batching()
+        .map(|batching| (batching.max_bytes as usize).min(BATCHING_MAX_MESSAGE_SIZE)),
+      initialized: false,
+      conflation: !i.qos_policies.is_reliable()
+        && i.qos_policies.conflation() == Some(policy::Conflation::LatestPerInstance),
+      conflated_samples: i.conflated_samples,
+      fec_encoder,
+      watermarks: i.watermarks,
+      matched_reader_filters: i.matched_reader_filters,
+      status_record: i.status_record,
       timed_event_timer,
       like_stateless: i.like_stateless,
+      #[cfg(feature = "security")]
+      max_receiver_specific_macs: OriginAuthentication::max_receiver_specific_macs_of(
+        &i.qos_policies,
+      ),
       qos_policies: i.qos_policies,
+      inline_qos,
       status_sender: i.status_sender,
+      offered_deadline_missed_count: 0,
+      qos_timers: TimerWheel::new(Timestamp::now()),
+      qos_timer_armed: None,
       participant_status_sender,
       ack_waiter: None,
 
       security_plugins: i.security_plugins,
+    };
+    if initialized {
+      writer.initialize();
+    }
+    writer
+  }
+
+  // Starts the periodic timers
+  fn initialize(&mut self) {
+    if self.initialized {
+      return;
+    }
+    self.initialized = true;
+    // Start periodic Heartbeat
+    if let Some(period) = self.heartbeat_period {
+      self
+        .timed_event_timer
+        .set_timeout(std::time::Duration::from(period), TimedEvent::Heartbeat);
+    }
+    // start periodic cache cleaning
+    self.timed_event_timer.set_timeout(
+      std::time::Duration::from(self.cache_cleaning_period),
+      TimedEvent::CacheCleaning,
+    );
+  }
+
+  // Samples written before the WriterInitialization policy has set up the
+  // Writer are not kept, if no Reader could receive them later.
+  fn discards_unmatched_samples(&self) -> bool {
+    !self.initialized && self.qos_policies.is_volatile() && !self.last_value_cache_enabled()
+  }
+
+  // The QoS policies that may be sent inline, according to RTPS spec v2.5
+  // Section "8.7.1 Adding in-line Parameters", and the topic name. The
+  // Reader-side policies HISTORY, RESOURCE_LIMITS and TIME_BASED_FILTER are
+  // left out.
+  fn inline_qos(
+    topic_name: &str,
+    qos: &QosPolicies,
+    endianness: Endianness,
+  ) -> Result<ParameterList, PlCdrSerializeError> {
+    let mut inline_qos = ParameterList::new();
+    inline_qos.push(Parameter::new(
+      ParameterId::PID_TOPIC_NAME,
+      StringWithNul::from(topic_name.to_string()).write_to_vec_with_ctx(endianness)?,
+    ));
+    for parameter in qos.to_parameter_list(endianness)? {
+      if !matches!(
+        parameter.parameter_id,
+        ParameterId::PID_HISTORY
+          | ParameterId::PID_RESOURCE_LIMITS
+          | ParameterId::PID_TIME_BASED_FILTER
+      ) {
+        inline_qos.push(parameter);
+      }
+    }
+    Ok(inline_qos)
+  }
+
+  // Inline QoS to be included in DATA, if the recipient(s) expect it.
+  // Multicast DATA reaches all matched Readers.
+  fn inline_qos_for(&self, target_reader_opt: Option<&RtpsReaderProxy>) -> Option<&ParameterList> {
+    let expected = match target_reader_opt {
+      Some(reader) => reader.expects_inline_qos(),
+      None => self
+        .readers
+        .values()
+        .any(RtpsReaderProxy::expects_inline_qos),
+    };
+    expected.then_some(&self.inline_qos)
+  }
+
+  // The checksum parameter of the change, if the PayloadChecksum policy is in
+  // effect. Only samples are checksummed, not disposals.
+  fn payload_checksum_for(&self, cc: &CacheChange) -> Option<Parameter> {
+    if self.qos_policies.payload_checksum() != Some(policy::PayloadChecksum::Md5) {
+      return None;
+    }
+    match &cc.data_value {
+      DDSData::Data { serialized_payload } => Some(InlineQos::payload_checksum_parameter(
+        &serialized_payload.value,
+      )),
+      DDSData::Local { sample } => sample
+        .serialized_payload()
+        .map(|p| InlineQos::payload_checksum_parameter(&p.value)),
+      DDSData::DisposeByKey { .. } | DDSData::DisposeByKeyHash { .. } => None,
+    }
+  }
+
+  // Inline QoS of a DATA or DATAFRAG: the given QoS parameters, if any, and
+  // the payload checksum, if any.
+  fn with_payload_checksum<'a>(
+    inline_qos: Option<&'a ParameterList>,
+    checksum: Option<&Parameter>,
+  ) -> Option<Cow<'a, ParameterList>> {
+    match checksum {
+      None => inline_qos.map(Cow::Borrowed),
+      Some(checksum) => {
+        let mut params = inline_qos.cloned().unwrap_or_default();
+        params.push(checksum.clone());
+        Some(Cow::Owned(params))
+      }
     }
   }
 
@@ -426,6 +873,35 @@ impl Writer {
     self.qos_policies.is_reliable()
   }
 
+  fn has_remote_readers(&self) -> bool {
+    self
+      .readers
+      .keys()
+      .any(|guid| guid.prefix != self.my_guid.prefix)
+  }
+
+  // Queues the change for the local Readers that it is meant for.
+  fn add_local_delivery(&mut self, change: CacheChange) {
+    let single_reader = change.write_options.to_single_reader();
+    let readers = self
+      .local_readers()
+      .into_iter()
+      .filter(|entity_id| {
+        let guid = GUID::new_with_prefix_and_id(self.my_guid.prefix, *entity_id);
+        single_reader.map_or(true, |single| single == guid)
+          && !change.write_options.excluded_readers().contains(&guid)
+      })
+      .collect();
+    self
+      .local_deliveries
+      .push(LocalDelivery { readers, change });
+  }
+
+  // Samples written for intra-process delivery since the previous call
+  pub fn take_local_deliveries(&mut self) -> Vec<LocalDelivery> {
+    std::mem::take(&mut self.local_deliveries)
+  }
+
   /// Lists the known local (same DomainParticipant) ReaderProxies
   /// Note that local non-matching Readers are not here.
   pub fn local_readers(&self) -> Vec<EntityId> {
@@ -505,10 +981,79 @@ impl Writer {
             } // if
           } // if let
         } // SendRepairFrags
+        TimedEvent::QosTimerCheck => {
+          self.qos_timer_armed = None;
+          self.handle_qos_timers();
+        }
+        TimedEvent::SendTrigger => {
+          self.send_trigger_armed = None;
+          self.send_if_due();
+        }
+        TimedEvent::AckWaitTimeout => {
+          self.end_ack_wait(false);
+        }
       } // match
     } // while
   } // fn
 
+  fn deadline_period(&self) -> Option<Duration> {
+    match self.qos_policies.deadline {
+      Some(policy::Deadline(period)) if period != Duration::INFINITE => Some(period),
+      _ => None,
+    }
+  }
+
+  // Make sure that the event loop timer wakes us up when the next QoS timer
+  // expires.
+  fn arm_qos_timer(&mut self) {
+    let next = match self.qos_timers.next_expiration() {
+      Some(next) => next,
+      None => return,
+    };
+    match self.qos_timer_armed {
+      Some((armed_at, _)) if armed_at <= next => return, // already armed early enough
+      Some((_, ref timeout)) => {
+        self.timed_event_timer.cancel_timeout(timeout);
+      }
+      None => (),
+    }
+    let delay = next.duration_since(Timestamp::now()).to_std();
+    let timeout = self
+      .timed_event_timer
+      .set_timeout(delay, TimedEvent::QosTimerCheck);
+    self.qos_timer_armed = Some((next, timeout));
+  }
+
+  fn handle_qos_timers(&mut self) {
+    let now = Timestamp::now();
+    for timer in self.qos_timers.expire(now) {
+      match timer {
+        QosTimer::OfferedDeadline => {
+          if let Some(period) = self.deadline_period() {
+            debug!("Offered deadline missed. topic={:?}", self.my_topic_name);
+            self.offered_deadline_missed_count += 1;
+            self.send_status(DataWriterStatus::OfferedDeadlineMissed {
+              count: CountWithChange::start_from(self.offered_deadline_missed_count, 1),
+            });
+            // The deadline is missed again after another period without writes.
+            self.qos_timers.schedule(timer, now + period);
+          }
+        }
+      }
+    }
+    self.arm_qos_timer();
+  }
+
+  // A sample was written: restart the offered deadline.
+  fn restart_deadline(&mut self, now: Timestamp) {
+    if let Some(period) = self.deadline_period() {
+      self
+        .qos_timers
+        .schedule(QosTimer::OfferedDeadline, now + period);
+      self.arm_qos_timer();
+    }
+  }
+
   /// This is called by dp_wrapper every time cacheCleaning message is received.
   fn handle_cache_cleaning(&mut self) {
     let resource_limit = 32;
@@ -546,94 +1091,50 @@ impl Writer {
 
   // Receive new data samples from the DDS DataWriter
   pub fn process_writer_command(&mut self) {
+    self.process_writer_commands();
+    self.send_if_due();
+    self.update_watermarks();
+  }
+
+  fn process_writer_commands(&mut self) {
     while let Ok(cc) = self.writer_command_receiver.try_recv() {
       match cc {
         WriterCommand::DDSData {
-          ddsdata: dds_data,
+          ddsdata,
           write_options,
           sequence_number,
+          instance,
         } => {
           // Signal that there is now space in the DataWriter to Writer queue
-          {
-            self
-              .writer_command_receiver_waker
-              .lock()
-              .unwrap()
-              .as_ref()
-              .map(|w| w.wake_by_ref());
-          }
-
-          // Insert data to local HistoryBuffer
-          let timestamp =
-            self.insert_to_history_buffer(dds_data, write_options.clone(), sequence_number);
-
-          // If not acting stateless-like, notify reader proxies that there is a new
-          // sample
-          if !self.like_stateless {
-            for reader in &mut self.readers.values_mut() {
-              reader.notify_new_cache_change(sequence_number);
-
-              // If the data is meant for a single reader only, set others as pending GAP for
-              // this sequence number.
-              if let Some(single_reader_guid) = write_options.to_single_reader() {
-                if reader.remote_reader_guid != single_reader_guid {
-                  reader.insert_pending_gap(sequence_number);
-                }
-              }
-            }
-          }
-
-          if self.push_mode {
-            // Send data (DATA or DATAFRAGs) and a Heartbeat
-            if let Some(cc) = self.history_buffer.get_change(timestamp) {
-              let target_reader_opt = match write_options.to_single_reader() {
-                Some(guid) => self.readers.get(&guid), // Sending only to this reader
-                None => None,                          // Sending to all matched readers
-              };
-
-              let send_also_heartbeat = true;
-              self.send_cache_change(cc, send_also_heartbeat, target_reader_opt);
-            } else {
-              error!("Lost the cache change that was just added?!");
-            }
-          } else {
-            // Send Heartbeat only.
-            // Readers will ask for the DATA with ACKNACK, if they are interested.
-            let final_flag = false; // false = request that readers acknowledge with ACKNACK.
-            let liveliness_flag = false; // This is not a manual liveliness assertion (DDS API call), but side-effect of
-            let hb_message = MessageBuilder::new()
-              .heartbeat_msg(
-                self.entity_id(), // from Writer
-                self.history_buffer.first_change_sequence_number(),
-                self.history_buffer.last_change_sequence_number(),
-                self.next_heartbeat_count(),
-                self.endianness,
-                EntityId::UNKNOWN, // to Reader
-                final_flag,
-                liveliness_flag,
-              )
-              .add_header_and_build(self.my_guid.prefix);
-            self.send_message_to_readers(
-              DeliveryMode::Multicast,
-              hb_message,
-              &mut self.readers.values(),
-            );
-          }
+          self.wake_writer_command_sender();
+          self.add_written_change(ddsdata, write_options, sequence_number, instance);
+        }
+        WriterCommand::DDSDataBatch {
+          changes,
+          write_options,
+        } => {
+          self.wake_writer_command_sender();
+          self.add_written_batch(changes, &write_options);
         }
 
         // WriterCommand::ResetOfferedDeadlineMissedStatus { writer_guid: _, } => {
         //   self.reset_offered_deadline_missed_status();
         // }
-        WriterCommand::WaitForAcknowledgments { all_acked } => {
+        WriterCommand::WaitForAcknowledgments {
+          all_acked,
+          max_wait,
+        } => {
           if self.like_stateless {
             error!(
               "Attempted to wait for acknowledgements in a stateless Writer, which currently only \
                supports BestEffort QoS. Ignoring. topic={:?}",
               self.my_topic_name
             );
-            let _ = all_acked.try_send(()); // Let the poor waiter continue.
+            let _ = all_acked.try_send(true); // Let the poor waiter continue.
             return;
           }
+          // A new wait replaces the previous one, which ends unsuccessfully.
+          self.end_ack_wait(false);
 
           let wait_until = self.history_buffer.last_change_sequence_number();
           let readers_pending: BTreeSet<_> = self
@@ -649,23 +1150,541 @@ impl Writer {
             .collect();
           self.ack_waiter = if readers_pending.is_empty() {
             // all acked already: try to signal app waiting at DataWriter
-            let _ = all_acked.try_send(());
+            let _ = all_acked.try_send(true);
             // but we ignore any failure to signal, if no-one is listening
             // since that is normal. They may have timed out and stopped waiting.
             None
           } else {
             // Someone still needs to ack. Wait for them.
+            let timeout = max_wait.map(|max_wait| {
+              self
+                .timed_event_timer
+                .set_timeout(max_wait, TimedEvent::AckWaitTimeout)
+            });
             Some(AckWaiter {
               wait_until,
               complete_channel: all_acked,
               readers_pending,
+              timeout,
             })
           };
         }
+
+        WriterCommand::HistoryStatus { reply } => {
+          // The DataWriter may have timed out already
+          let _ = reply.send(self.history_status());
+        }
+
+        WriterCommand::PurgeHistory {
+          acknowledged_only,
+          reply,
+        } => {
+          let before = if acknowledged_only {
+            self.lowest_unacknowledged()
+          } else {
+            self.history_buffer.last_change_sequence_number().plus_1()
+          };
+          let _ = reply.send(self.history_buffer.purge_before(before));
+        }
+
+        WriterCommand::UnacknowledgedSamples { reply } => {
+          let _ = reply.send(self.unacknowledged_samples());
+        }
+
+        WriterCommand::RedeliverUnacknowledged {
+          reader,
+          to_reader,
+          first_sequence_number,
+          count,
+          reply,
+        } => {
+          let redelivered =
+            self.redeliver_unacknowledged(reader, to_reader, first_sequence_number, count);
+          let _ = reply.send(redelivered);
+        }
+      }
+    }
+  }
+
+  fn wake_writer_command_sender(&self) {
+    self
+      .writer_command_receiver_waker
+      .lock()
+      .unwrap()
+      .as_ref()
+      .map(|w| w.wake_by_ref());
+  }
+
+  // Adds a change written by the DataWriter to the history, and notifies the
+  // Readers. In push mode, the change is added to the send trigger instead of
+  // sending it immediately.
+  fn add_written_change(
+    &mut self,
+    mut dds_data: DDSData,
+    write_options: WriteOptions,
+    sequence_number: SequenceNumber,
+    instance: Option<KeyHash>,
+  ) {
+    // Sequence numbers skipped by the DataWriter, because a write was
+    // cancelled or timed out, are announced to the readers as GAPs.
+    let skipped_from = self.history_buffer.last_change_sequence_number().plus_1();
+    if !self.like_stateless && skipped_from < sequence_number {
+      let skipped_until = sequence_number - SequenceNumber::new(1);
+      for reader in self.readers.values_mut() {
+        for skipped in SequenceNumber::range_inclusive(skipped_from, skipped_until) {
+          reader.notify_new_cache_change(skipped);
+          reader.insert_pending_gap(skipped);
+        }
+      }
+    }
+
+    if self.discards_unmatched_samples() {
+      self.history_buffer.skip(sequence_number);
+      self.restart_deadline(Timestamp::now());
+      return;
+    }
+
+    // A sample written for intra-process delivery is given directly to
+    // the local Readers. Remote Readers need it serialized.
+    let local_sample = matches!(dds_data, DDSData::Local { .. }).then(|| dds_data.clone());
+    if local_sample.is_some() && self.has_remote_readers() {
+      dds_data.serialize_local();
+    }
+
+    // Insert data to local HistoryBuffer
+    let payload_size = dds_data.payload_size();
+    let timestamp = self.insert_to_history_buffer(dds_data, write_options.clone(), sequence_number);
+    self.restart_deadline(timestamp);
+
+    // Samples meant for a single Reader are not kept as last values, nor
+    // conflated.
+    let instance = instance.filter(|_| write_options.to_single_reader().is_none());
+    if let Some(instance) = instance.filter(|_| self.last_value_cache_enabled()) {
+      if let Some(cc) = self.history_buffer.get_change(timestamp) {
+        self.last_values.update(instance, cc);
+      }
+    }
+
+    // If not acting stateless-like, notify reader proxies that there is a new
+    // sample
+    if !self.like_stateless {
+      let app_acks = self.application_acknowledgments();
+      for reader in &mut self.readers.values_mut() {
+        // Readers that get the sample are to acknowledge it
+        if app_acks
+          && write_options
+            .to_single_reader()
+            .map_or(true, |guid| guid == reader.remote_reader_guid)
+          && !write_options
+            .excluded_readers()
+            .contains(&reader.remote_reader_guid)
+        {
+          reader.expect_app_ack(sequence_number);
+        }
+        if local_sample.is_some() && reader.remote_reader_guid.prefix == self.my_guid.prefix {
+          continue; // delivered in process
+        }
+        reader.notify_new_cache_change(sequence_number);
+
+        // If the data is meant for a single reader only, set others as pending GAP for
+        // this sequence number.
+        if let Some(single_reader_guid) = write_options.to_single_reader() {
+          if reader.remote_reader_guid != single_reader_guid {
+            reader.insert_pending_gap(sequence_number);
+          }
+        }
+        // Likewise for readers whose content filter the data does not pass
+        if write_options
+          .excluded_readers()
+          .contains(&reader.remote_reader_guid)
+        {
+          reader.insert_pending_gap(sequence_number);
+        }
+      }
+    }
+
+    if let Some(data_value) = local_sample {
+      self.add_local_delivery(CacheChange::new(
+        self.my_guid,
+        sequence_number,
+        write_options,
+        data_value,
+      ));
+    }
+
+    if self.push_mode {
+      // Data (DATA or DATAFRAGs) and a Heartbeat are sent when the trigger
+      // is due.
+      self.send_trigger.add(
+        instance.filter(|_| self.conflation),
+        timestamp,
+        payload_size,
+        Timestamp::now(),
+      );
+    } else {
+      // Send Heartbeat only.
+      // Readers will ask for the DATA with ACKNACK, if they are interested.
+      let final_flag = false; // false = request that readers acknowledge with ACKNACK.
+      let liveliness_flag = false; // This is not a manual liveliness assertion (DDS API call), but side-effect of
+      let hb_message = MessageBuilder::new()
+        .heartbeat_msg(
+          self.entity_id(), // from Writer
+          self.history_buffer.first_change_sequence_number(),
+          self.history_buffer.last_change_sequence_number(),
+          self.next_heartbeat_count(),
+          self.endianness,
+          EntityId::UNKNOWN, // to Reader
+          final_flag,
+          liveliness_flag,
+        )
+        .add_header_and_build(self.my_guid.prefix);
+      self.send_message_to_readers(
+        DeliveryMode::Multicast,
+        hb_message,
+        &mut self.readers.values(),
+      );
+    }
+  }
+
+  // Adds changes written together, e.g. by DataWriter::dispose_all, and sends
+  // them at once, unless sending is deferred. Their messages to the same
+  // destination are combined into as few datagrams as possible.
+  fn add_written_batch(
+    &mut self,
+    changes: Vec<(DDSData, SequenceNumber, Option<KeyHash>)>,
+    write_options: &WriteOptions,
+  ) {
+    let max_size = self.batch_max_size.unwrap_or(BATCH_DATAGRAM_MAX_SIZE);
+    self.send_combined(max_size, |writer| {
+      for (dds_data, sequence_number, instance) in changes {
+        writer.add_written_change(dds_data, write_options.clone(), sequence_number, instance);
+      }
+      if writer.push_mode && !writer.send_trigger.is_held() {
+        writer.send_pending_changes();
+      }
+    });
+  }
+
+  // Runs f so that the messages it sends are combined into messages of at
+  // most max_size bytes, where possible.
+  fn send_combined(&mut self, max_size: usize, f: impl FnOnce(&mut Self)) {
+    // A flush of the event loop may be collecting datagrams already.
+    let batching = !self.udp_sender.is_batching();
+    if batching {
+      self.udp_sender.begin_batch();
+    }
+    f(self);
+    if batching {
+      self
+        .udp_sender
+        .end_batch(|messages| concatenate_messages(messages, max_size));
+    }
+  }
+
+  fn application_acknowledgments(&self) -> bool {
+    self.qos_policies.acknowledgment() == Some(policy::Acknowledgment::ApplicationExplicit)
+  }
+
+  fn unacknowledged_samples(&self) -> BTreeMap<GUID, Vec<SequenceNumber>> {
+    if !self.application_acknowledgments() {
+      return BTreeMap::new();
+    }
+    self
+      .readers
+      .iter()
+      .map(|(guid, rp)| (*guid, rp.app_unacknowledged().collect()))
+      .collect()
+  }
+
+  // Writes the changes that reader has not acknowledged again, as new changes
+  // to to_reader only. They replace the originals in the tracking of
+  // application acknowledgments. Returns the number of changes written.
+  fn redeliver_unacknowledged(
+    &mut self,
+    reader: GUID,
+    to_reader: GUID,
+    first_sequence_number: SequenceNumber,
+    count: usize,
+  ) -> usize {
+    if !self.readers.contains_key(&to_reader) {
+      warn!(
+        "Cannot redeliver to {to_reader:?}, which is not matched. topic={:?}",
+        self.my_topic_name
+      );
+      return 0;
+    }
+    let Some(reader_proxy) = self.readers.get(&reader) else {
+      return 0;
+    };
+    let changes: Vec<(SequenceNumber, DDSData, WriteOptions)> = reader_proxy
+      .app_unacknowledged()
+      .filter_map(|sn| self.history_buffer.get_by_sn(sn))
+      .take(count)
+      .map(|cc| {
+        let mut options = WriteOptionsBuilder::new()
+          .related_sample_identity_opt(cc.write_options.related_sample_identity())
+          .to_single_reader(to_reader);
+        if let Some(source_timestamp) = cc.write_options.source_timestamp() {
+          options = options.source_timestamp(source_timestamp);
+        }
+        (cc.sequence_number, cc.data_value.clone(), options.build())
+      })
+      .collect();
+    if let Some(reader_proxy) = self.readers.get_mut(&reader) {
+      for (sn, ..) in &changes {
+        reader_proxy.remove_app_unacknowledged(*sn);
+      }
+    }
+
+    let redelivered = changes.len();
+    let max_size = self.batch_max_size.unwrap_or(BATCH_DATAGRAM_MAX_SIZE);
+    self.send_combined(max_size, |writer| {
+      for (i, (_, dds_data, write_options)) in changes.into_iter().enumerate() {
+        let sequence_number = first_sequence_number + SequenceNumber::from(i as i64);
+        writer.add_written_change(dds_data, write_options, sequence_number, None);
+      }
+      if writer.push_mode && !writer.send_trigger.is_held() {
+        writer.send_pending_changes();
+      }
+    });
+    redelivered
+  }
+
+  fn history_status(&self) -> WriterHistoryStatus {
+    let last_sequence_number = self.history_buffer.last_change_sequence_number();
+    let unacknowledged = if self.like_stateless {
+      BTreeMap::new()
+    } else {
+      self
+        .readers
+        .iter()
+        .filter(|(_, rp)| rp.qos().is_reliable())
+        .map(|(guid, rp)| {
+          let window = i64::from(last_sequence_number) - i64::from(rp.all_acked_before) + 1;
+          (*guid, window.max(0) as u64)
+        })
+        .collect()
+    };
+    WriterHistoryStatus {
+      first_sequence_number: self.history_buffer.first_change_sequence_number(),
+      last_sequence_number,
+      samples: self.history_buffer.history_buffer.len(),
+      bytes: self.history_buffer.bytes_held(),
+      unacknowledged,
+    }
+  }
+
+  fn last_value_cache_enabled(&self) -> bool {
+    self.qos_policies.last_value_cache() == Some(policy::LastValueCache::Enabled)
+  }
+
+  // Sends the pending changes, if the send trigger is due. Otherwise makes sure
+  // that the event loop timer wakes us up at the trigger deadline.
+  fn send_if_due(&mut self) {
+    self.send_if_due_at(Timestamp::now());
+  }
+
+  fn send_if_due_at(&mut self, now: Timestamp) {
+    if self.send_trigger.is_due(now) {
+      self.send_pending_data();
+    } else if let Some(deadline) = self.send_trigger.deadline() {
+      if self.send_trigger_armed.is_none() {
+        let delay = deadline.duration_since(now).to_std();
+        let timeout = self
+          .timed_event_timer
+          .set_timeout(delay, TimedEvent::SendTrigger);
+        self.send_trigger_armed = Some(timeout);
+      }
+    }
+  }
+
+  // Sends a change from the history buffer to its target Reader(s). Returns
+  // false, if the change is no longer in the buffer.
+  fn push_change(&self, timestamp: Timestamp, send_also_heartbeat: bool) -> bool {
+    match self.history_buffer.get_change(timestamp) {
+      Some(cc) => {
+        let target_reader_opt = match cc.write_options.to_single_reader() {
+          Some(guid) => self.readers.get(&guid), // Sending only to this reader
+          None => None,                          // Sending to all matched readers
+        };
+        let excluded = cc.write_options.excluded_readers();
+        if target_reader_opt.is_none() && !excluded.is_empty() {
+          // Sending to each reader whose content filter the data passes
+          for reader in self
+            .readers
+            .values()
+            .filter(|r| !excluded.contains(&r.remote_reader_guid))
+          {
+            self.send_cache_change(cc, send_also_heartbeat, Some(reader));
+          }
+        } else {
+          self.send_cache_change(cc, send_also_heartbeat, target_reader_opt);
+        }
+        true
       }
+      None => false,
     }
   }
 
+  pub fn set_flow_controller(&mut self, flow_controller: Rc<RefCell<FlowController>>) {
+    self.flow_controller = Some(flow_controller);
+  }
+
+  pub fn set_spdp_transmissions(&mut self, spdp_transmissions: SpdpTransmissions) {
+    self.spdp_transmissions = Some(spdp_transmissions);
+  }
+
+  // While deferred, written changes are not pushed to Readers until
+  // send_pending_data is called. Ending the deferral sends any changes still
+  // pending.
+  pub fn set_defer_data(&mut self, defer: bool) {
+    self.send_trigger.set_held(defer);
+    if !defer {
+      self.send_pending_data();
+    }
+  }
+
+  // Sends the pending changes now, whether the send trigger is due or not,
+  // followed by a single Heartbeat. Of conflated changes, only the newest of
+  // each instance is sent, and the others are counted as conflated. By the
+  // Batching policy, the changes are packed into as few messages as possible.
+  pub fn send_pending_data(&mut self) {
+    match self.batch_max_size {
+      Some(max_size) => self.send_combined(max_size, Self::send_pending_changes),
+      None => self.send_pending_changes(),
+    }
+  }
+
+  fn send_pending_changes(&mut self) {
+    if let Some(timeout) = self.send_trigger_armed.take() {
+      self.timed_event_timer.cancel_timeout(&timeout);
+    }
+    let pending = self.send_trigger.take_pending();
+    if pending.conflated > 0 {
+      self
+        .conflated_samples
+        .fetch_add(pending.conflated as u64, atomic::Ordering::Relaxed);
+      debug!(
+        "Conflated {} samples. topic={:?}",
+        pending.conflated, self.my_topic_name
+      );
+    }
+    let count = pending.to_send.len();
+    for (i, timestamp) in pending.to_send.into_iter().enumerate() {
+      self.send_repairs_ahead_of(timestamp);
+      let send_also_heartbeat = i + 1 == count;
+      if self.push_change(timestamp, send_also_heartbeat) {
+        self.add_to_fec_group(timestamp);
+      } else {
+        debug!(
+          "Pending change already removed from history buffer. topic={:?}",
+          self.my_topic_name
+        );
+      }
+    }
+  }
+
+  // Sends the repairs pending before a new change, as the RepairScheduling
+  // policy says. Only changes older than the new one are repairs.
+  fn send_repairs_ahead_of(&mut self, timestamp: Timestamp) {
+    let max_repairs = match self.qos_policies.repair_scheduling().unwrap_or_default() {
+      policy::RepairScheduling::NewestFirst => return,
+      policy::RepairScheduling::RepairFirst => usize::MAX,
+      policy::RepairScheduling::Weighted { repairs_per_sample } => usize::from(repairs_per_sample),
+    };
+    if !self.is_reliable() || self.like_stateless {
+      return;
+    }
+    let Some(new_sn) = self
+      .history_buffer
+      .get_change(timestamp)
+      .map(|cc| cc.sequence_number)
+    else {
+      return;
+    };
+    let repairing: Vec<GUID> = self
+      .readers
+      .values()
+      .filter(|rp| rp.repair_mode)
+      .map(|rp| rp.remote_reader_guid)
+      .collect();
+    for reader_guid in repairing {
+      // Each call removes one change from the unsent set
+      for _ in 0..max_repairs {
+        let repair_pending = self
+          .readers
+          .get(&reader_guid)
+          .and_then(RtpsReaderProxy::first_unsent_change)
+          .is_some_and(|sn| sn < new_sn);
+        if !repair_pending {
+          break;
+        }
+        self.handle_repair_data_send(reader_guid);
+      }
+    }
+  }
+
+  // Adds a sent change to the forward error correction group, if the
+  // ForwardErrorCorrection policy is in effect, and sends the parity when the
+  // group is complete. Only samples that went to all Readers in a single DATA
+  // are protected.
+  fn add_to_fec_group(&mut self, timestamp: Timestamp) {
+    let (Some(encoder), Some(cc)) = (
+      self.fec_encoder.as_mut(),
+      self.history_buffer.get_change(timestamp),
+    ) else {
+      return;
+    };
+    if cc.write_options.to_single_reader().is_some()
+      || !cc.write_options.excluded_readers().is_empty()
+      || cc.data_value.payload_size() > self.data_max_size_serialized
+    {
+      return;
+    }
+    let DDSData::Data { serialized_payload } = &cc.data_value else {
+      return;
+    };
+    let payload = serialized_payload.bytes_slice(0, serialized_payload.len_serialized());
+    if let Some(fec_parity) = encoder.add(
+      cc.sequence_number,
+      cc.write_options.source_timestamp(),
+      &payload,
+    ) {
+      self.send_fec_parity(fec_parity);
+    }
+  }
+
+  fn send_fec_parity(&self, fec_parity: FecParity) {
+    let message = MessageBuilder::new()
+      .fec_parity_msg(fec_parity, self.endianness)
+      .add_header_and_build(self.my_guid.prefix);
+    self.send_message_to_readers(DeliveryMode::Multicast, message, &mut self.readers.values());
+  }
+
+  // Adds the HEARTBEAT that follows new data to the message of the DATA, or of
+  // the last DATAFRAG, so that both go in a single datagram. The Reader then
+  // acknowledges or asks for repair as soon as it has processed the data,
+  // instead of waiting for a separately sent HEARTBEAT.
+  fn add_piggyback_heartbeat(
+    &self,
+    message_builder: MessageBuilder,
+    reader_entity_id: EntityId,
+  ) -> MessageBuilder {
+    let final_flag = false; // false = request that readers acknowledge with ACKNACK.
+    let liveliness_flag = false; // This is not a manual liveliness assertion (DDS API call), but side-effect of
+                                 // writing new data.
+    message_builder.heartbeat_msg(
+      self.entity_id(), // from Writer
+      self.history_buffer.first_change_sequence_number(),
+      self.history_buffer.last_change_sequence_number(),
+      self.next_heartbeat_count(),
+      self.endianness,
+      reader_entity_id, // to Reader
+      final_flag,
+      liveliness_flag,
+    )
+  }
+
   // Returns a boolean telling if the data had to be fragmented
   fn send_cache_change(
     &self,
@@ -710,6 +1729,7 @@ impl Writer {
 
     let data_size = cc.data_value.payload_size();
     let fragmentation_needed = data_size > self.data_max_size_serialized;
+    let payload_checksum = self.payload_checksum_for(cc);
 
     if !fragmentation_needed {
       // We can send DATA
@@ -742,25 +1762,18 @@ impl Writer {
         cc,
         reader_entity_id,
         self.my_guid, // writer
+        Self::with_payload_checksum(
+          self.inline_qos_for(target_reader_opt),
+          payload_checksum.as_ref(),
+        )
+        .as_deref(),
         self.endianness,
         self.security_plugins.as_ref(),
       );
 
       // Add HEARTBEAT if needed
       if send_also_heartbeat && !self.like_stateless {
-        let final_flag = false; // false = request that readers acknowledge with ACKNACK.
-        let liveliness_flag = false; // This is not a manual liveliness assertion (DDS API call), but side-effect of
-                                     // writing new data.
-        message_builder = message_builder.heartbeat_msg(
-          self.entity_id(), // from Writer
-          self.history_buffer.first_change_sequence_number(),
-          self.history_buffer.last_change_sequence_number(),
-          self.next_heartbeat_count(),
-          self.endianness,
-          reader_entity_id, // to Reader
-          final_flag,
-          liveliness_flag,
-        );
+        message_builder = self.add_piggyback_heartbeat(message_builder, reader_entity_id);
       }
 
       let data_message = message_builder.add_header_and_build(self.my_guid.prefix);
@@ -807,6 +1820,16 @@ impl Writer {
           cc,
           reader_entity_id, // reader
           self.my_guid,     // writer
+          // Inline QoS in the first fragment is enough. The checksum goes
+          // to each fragment, since the Reader may complete the sample with
+          // any of them.
+          Self::with_payload_checksum(
+            self
+              .inline_qos_for(target_reader_opt)
+              .filter(|_| frag_num == FragmentNumber::new(1)),
+            payload_checksum.as_ref(),
+          )
+          .as_deref(),
           frag_num,
           fragment_size,
           data_size.try_into().unwrap(),
@@ -814,29 +1837,16 @@ impl Writer {
           self.security_plugins.as_ref(),
         );
 
+        // Add HEARTBEAT to the last fragment if needed. Fragments leave room
+        // for it in the datagram.
+        if frag_num == FragmentNumber::new(num_frags) && send_also_heartbeat && !self.like_stateless
+        {
+          message_builder = self.add_piggyback_heartbeat(message_builder, reader_entity_id);
+        }
+
         let datafrag_msg = message_builder.add_header_and_build(self.my_guid.prefix);
         messages_to_send.push(datafrag_msg);
       } // end for
-
-      // Add HEARTBEAT message if needed
-      if send_also_heartbeat && !self.like_stateless {
-        let final_flag = false; // false = request that readers acknowledge with ACKNACK.
-        let liveliness_flag = false; // This is not a manual liveliness assertion (DDS API call), but side-effect of
-                                     // writing new data.
-        let hb_msg = MessageBuilder::new()
-          .heartbeat_msg(
-            self.entity_id(), // from Writer
-            self.history_buffer.first_change_sequence_number(),
-            self.history_buffer.last_change_sequence_number(),
-            self.next_heartbeat_count(),
-            self.endianness,
-            reader_entity_id, // to Reader
-            final_flag,
-            liveliness_flag,
-          )
-          .add_header_and_build(self.my_guid.prefix);
-        messages_to_send.push(hb_msg);
-      }
     }
 
     // Send the messages, either to all readers or just one
@@ -910,62 +1920,99 @@ impl Writer {
     let first_change = self.history_buffer.first_change_sequence_number();
     let last_change = self.history_buffer.last_change_sequence_number();
 
-    if self
+    // Only Readers that have not acknowledged everything need a HEARTBEAT,
+    // unless this is a liveliness assertion, which is for everyone.
+    let lagging: Vec<&RtpsReaderProxy> = self
       .readers
       .values()
-      .all(|rp| last_change < rp.all_acked_before)
-    {
+      .filter(|rp| is_manual_assertion || last_change >= rp.all_acked_before)
+      .collect();
+
+    if lagging.is_empty() {
       trace!("heartbeat tick: all readers have all available data.");
+      return;
+    }
+
+    debug!(
+      "Writer {:?} topic={:} HEARTBEAT {:?} to {:?} for {} readers",
+      self.guid().entity_id,
+      self.topic_name(),
+      first_change,
+      last_change,
+      lagging.len(),
+    );
+
+    // Multicast pays off only if it reaches several lagging Readers at once.
+    // Otherwise, address the HEARTBEAT to each lagging Reader, so that Readers
+    // that are up to date are not bothered. In the volatile key exchange topic
+    // we cannot send to multiple readers by any means.
+    let (multicast, unicast): (Vec<&RtpsReaderProxy>, Vec<&RtpsReaderProxy>) =
+      if self.entity_id() == EntityId::P2P_BUILTIN_PARTICIPANT_VOLATILE_SECURE_WRITER {
+        (Vec::new(), lagging)
+      } else {
+        lagging
+          .into_iter()
+          .partition(|rp| rp.has_multicast_locator())
+      };
+    let (multicast, unicast) = if multicast.len() >= MULTICAST_HEARTBEAT_MIN_READERS {
+      (multicast, unicast)
     } else {
-      // the interface to .heartbeat_msg is silly: we give ref to ourself
-      // and that function then queries us.
+      (Vec::new(), [multicast, unicast].concat())
+    };
+
+    if !multicast.is_empty() {
       let hb_message = MessageBuilder::new()
         .ts_msg(self.endianness, Some(Timestamp::now()))
         .heartbeat_msg(
           self.entity_id(), // from Writer
-          self.history_buffer.first_change_sequence_number(),
-          self.history_buffer.last_change_sequence_number(),
+          first_change,
+          last_change,
           self.next_heartbeat_count(),
           self.endianness,
           EntityId::UNKNOWN, // to Reader
           final_flag,
           liveliness_flag,
-        )
-        .add_header_and_build(self.my_guid.prefix);
-
-      debug!(
-        "Writer {:?} topic={:} HEARTBEAT {:?} to {:?}",
-        self.guid().entity_id,
-        self.topic_name(),
-        first_change,
-        last_change,
-      );
-
-      // In the volatile key exchange topic we cannot send to multiple readers by any
-      // means, so we handle that separately.
-      if self.entity_id() == EntityId::P2P_BUILTIN_PARTICIPANT_VOLATILE_SECURE_WRITER {
-        for rp in self.readers.values() {
-          if last_change < rp.all_acked_before {
-            // Everything we have has been acknowledged already. Do nothing.
-          } else {
-            self.send_message_to_readers(
-              DeliveryMode::Unicast,
-              hb_message.clone(),
-              &mut std::iter::once(rp),
-            );
-          }
-        }
-      } else {
-        // Normal case
-        self.send_message_to_readers(
-          DeliveryMode::Multicast,
-          hb_message,
-          &mut self.readers.values(),
-        );
-      }
+        )
+        .add_header_and_build(self.my_guid.prefix);
+      self.send_message_to_readers(
+        DeliveryMode::Multicast,
+        hb_message,
+        &mut multicast.into_iter(),
+      );
+    }
+    for rp in unicast {
+      self.send_heartbeat_to_reader(rp, final_flag, liveliness_flag);
     }
   }
 
+  // HEARTBEAT addressed to a single Reader and sent via unicast.
+  fn send_heartbeat_to_reader(
+    &self,
+    reader_proxy: &RtpsReaderProxy,
+    final_flag: bool,
+    liveliness_flag: bool,
+  ) {
+    let hb_message = MessageBuilder::new()
+      .ts_msg(self.endianness, Some(Timestamp::now()))
+      .dst_submessage(self.endianness, reader_proxy.remote_reader_guid.prefix)
+      .heartbeat_msg(
+        self.entity_id(), // from Writer
+        self.history_buffer.first_change_sequence_number(),
+        self.history_buffer.last_change_sequence_number(),
+        self.next_heartbeat_count(),
+        self.endianness,
+        reader_proxy.remote_reader_guid.entity_id, // to Reader
+        final_flag,
+        liveliness_flag,
+      )
+      .add_header_and_build(self.my_guid.prefix);
+    self.send_message_to_readers(
+      DeliveryMode::Unicast,
+      hb_message,
+      &mut std::iter::once(reader_proxy),
+    );
+  }
+
   /// When receiving an ACKNACK Message indicating a Reader is missing some data
   /// samples, the Writer must respond by either sending the missing data
   /// samples, sending a GAP message when the sample is not relevant, or
@@ -975,6 +2022,16 @@ impl Writer {
     reader_guid_prefix: GuidPrefix,
     ack_submessage: &AckSubmessage,
   ) {
+    // Application acknowledgments do not depend on reliability
+    if let AckSubmessage::AppAck(app_ack) = ack_submessage {
+      let reader_guid = GUID::new(reader_guid_prefix, app_ack.reader_id);
+      let last_seq = self.history_buffer.last_change_sequence_number();
+      if let Some(reader_proxy) = self.lookup_reader_proxy_mut(reader_guid) {
+        reader_proxy.handle_ack_nack(ack_submessage, last_seq);
+      }
+      return;
+    }
+
     // sanity check
     if !self.is_reliable() || self.like_stateless {
       // Stateless-like Writer currently supports only BestEffort QoS, so ignore
@@ -1074,11 +2131,13 @@ impl Writer {
             );
           }
         } // if have reader_proxy
+        self.update_watermarks();
 
         // See if we need to respond by GAP message
         if let Some(reader_proxy) = self.readers.get(&reader_guid) {
           if !reader_proxy.get_pending_gap().is_empty() {
             let gap_message = MessageBuilder::new()
+              .dst_submessage(self.endianness, reader_guid.prefix)
               .gap_msg(
                 reader_proxy.get_pending_gap(),
                 self.my_guid.entity_id,
@@ -1108,6 +2167,7 @@ impl Writer {
           },
         );
       }
+      AckSubmessage::AppAck(_) => (), // handled above
     }
   }
 
@@ -1119,11 +2179,18 @@ impl Writer {
       .as_mut()
       .is_some_and(|aw| aw.reader_acked_or_lost(guid, acked_before));
     if completed {
-      self
-        .ack_waiter
-        .as_ref()
-        .map(AckWaiter::notify_wait_complete);
-      self.ack_waiter = None;
+      self.end_ack_wait(true);
+    }
+  }
+
+  // Ends the wait of the application for acknowledgments, if any, telling
+  // whether all the Readers acknowledged.
+  fn end_ack_wait(&mut self, all_acked: bool) {
+    if let Some(ack_waiter) = self.ack_waiter.take() {
+      if let Some(timeout) = &ack_waiter.timeout {
+        self.timed_event_timer.cancel_timeout(timeout);
+      }
+      ack_waiter.notify_wait_complete(all_acked);
     }
   }
 
@@ -1198,9 +2265,11 @@ impl Writer {
       // just send a GAP message, not DATA.
       let pending_gaps = reader_proxy.get_pending_gap();
 
-      // Check what we actually have in store
+      // Check what we actually have in store. The LastValueCache policy may
+      // keep samples that are older than the history.
       let first_available = self.history_buffer.first_change_sequence_number();
-      if unsent_sn < first_available {
+      let last_value = self.last_values.get_by_sn(unsent_sn);
+      if unsent_sn < first_available && last_value.is_none() {
         // Reader is requesting older than what we actually have. Notify that they are
         // gone.
         all_irrelevant_before = Some(first_available);
@@ -1213,7 +2282,7 @@ impl Writer {
         no_longer_relevant.extend(pending_gaps);
       } else {
         // Reader not pending gap on unsent_sn. Get the cache change from topic cache
-        if let Some(cc) = self.history_buffer.get_by_sn(unsent_sn) {
+        if let Some(cc) = self.history_buffer.get_by_sn(unsent_sn).or(last_value) {
           // // DEBUG
           // if self.my_guid.entity_id == EntityId::SEDP_BUILTIN_PUBLICATIONS_WRITER
           //   && reader_proxy.remote_reader_guid.prefix != self.my_guid.prefix
@@ -1241,6 +2310,14 @@ impl Writer {
           }
           // mark as sent
           reader_proxy.mark_change_sent(unsent_sn);
+
+          // Repair data goes only to the NACKing Reader, so the HEARTBEATs
+          // accompanying multicast DATA may not reach it. Follow up the last
+          // repair with a HEARTBEAT, so that the Reader can ACK or NACK again
+          // without waiting for the next periodic HEARTBEAT.
+          if reader_proxy.first_unsent_change().is_none() {
+            self.send_heartbeat_to_reader(reader_proxy, false, false);
+          }
         } else {
           // Did not find a cache change for the sequence number. Mark for GAP.
           no_longer_relevant.insert(unsent_sn);
@@ -1306,6 +2383,8 @@ impl Writer {
     let max_send_count = 8;
 
     let reader_guid = reader_proxy.remote_reader_guid;
+    // Checksum of the sample that the previous frag was of
+    let mut payload_checksum: Option<(SequenceNumber, Option<Parameter>)> = None;
 
     // Get (an iterator to) frags requested but not yet sent
     // reader_proxy.
@@ -1336,10 +2415,23 @@ impl Writer {
         let fragment_size: u32 = self.data_max_size_serialized as u32; // TODO: overflow check
         let data_size: u32 = cache_change.data_value.payload_size() as u32; // TODO: overflow check
 
+        if !matches!(payload_checksum, Some((checksum_sn, _)) if checksum_sn == seq_num) {
+          payload_checksum = Some((seq_num, self.payload_checksum_for(cache_change)));
+        }
+
         message_builder = message_builder.data_frag_msg(
           cache_change,
           reader_guid.entity_id, // reader
           self.my_guid,          // writer
+          Self::with_payload_checksum(
+            self
+              .inline_qos_for(Some(&*reader_proxy))
+              .filter(|_| frag_num == FragmentNumber::new(1)),
+            payload_checksum
+              .as_ref()
+              .and_then(|(_, checksum)| checksum.as_ref()),
+          )
+          .as_deref(),
           frag_num,
           fragment_size as u16, // TODO: overflow check
           data_size,
@@ -1448,7 +2540,7 @@ impl Writer {
       // Encode submessages
       SecurityResult::<Vec<Vec<Submessage>>>::from_iter(submessages.iter().map(|submessage| {
         security_plugins_handle
-          .get_plugins()
+          .read_plugins()
           .encode_datawriter_submessage(submessage.clone(), &source_guid, &destination_guid_list)
           // Convert each encoding output to a Vec of 1 or 3 submessages
           .map(Vec::from)
@@ -1467,7 +2559,7 @@ impl Writer {
           .map(|guid| guid.prefix)
           .collect();
         // Encode message
-        security_plugins_handle.get_plugins().encode_message(
+        security_plugins_handle.read_plugins().encode_message(
           message,
           &source_guid_prefix,
           &destination_guid_prefix_list,
@@ -1495,7 +2587,46 @@ impl Writer {
     let readers = readers.collect::<Vec<_>>(); // clone iterator
 
     #[cfg(feature = "security")]
-    let encoded = self.security_encode(message, &readers);
+    if readers.len() > self.max_receiver_specific_macs && self.is_origin_authenticated() {
+      // Each batch is sent by unicast, since multicast would deliver it also to
+      // the readers whose MACs are in other batches.
+      for batch in receiver_specific_mac_batches(
+        &readers,
+        |reader| reader.remote_reader_guid,
+        self.max_receiver_specific_macs,
+      ) {
+        self.encode_and_send(DeliveryMode::Unicast, message.clone(), &batch);
+      }
+      return;
+    }
+    self.encode_and_send(preferred_mode, message, &readers);
+  }
+
+  #[cfg(feature = "security")]
+  fn is_origin_authenticated(&self) -> bool {
+    self.security_plugins.as_ref().is_some_and(|handle| {
+      handle
+        .read_plugins()
+        .is_writer_origin_authenticated(&self.guid())
+    })
+  }
+
+  fn encode_and_send(
+    &self,
+    preferred_mode: DeliveryMode,
+    message: Message,
+    readers: &[&RtpsReaderProxy],
+  ) {
+    // Samples go through the flow controller, and so does everything after
+    // them, until they have been sent.
+    let carries_data = message.carries_data();
+    let flow_controller = self
+      .flow_controller
+      .as_ref()
+      .filter(|flow_controller| carries_data || flow_controller.borrow().is_waiting(self.my_guid));
+
+    #[cfg(feature = "security")]
+    let encoded = self.security_encode(message, readers);
     #[cfg(not(feature = "security"))]
     let encoded: Result<Message, ()> = Ok(message);
 
@@ -1503,6 +2634,10 @@ impl Writer {
       Ok(message) => {
         let buffer = message.write_to_vec_with_ctx(self.endianness).unwrap();
         let mut already_sent_to = BTreeSet::new();
+        // Destinations in sending order
+        let mut destinations = Vec::new();
+        // Multicast locators are ignored, if multicast is disabled.
+        let multicast_enabled = self.udp_sender.multicast_enabled();
 
         macro_rules! send_unless_sent_and_mark {
           ($locs:expr) => {
@@ -1510,7 +2645,7 @@ impl Writer {
               if already_sent_to.contains(loc) {
                 trace!("Already sent to {:?}", loc);
               } else {
-                self.udp_sender.send_to_locator(&buffer, loc);
+                destinations.push(*loc);
                 already_sent_to.insert(loc.clone());
               }
             }
@@ -1523,11 +2658,11 @@ impl Writer {
             reader
               .unicast_locator_list
               .iter()
-              .find(|l| Locator::is_udp(l)),
+              .find(|l| l.is_udp() || l.is_tcp()),
             reader
               .multicast_locator_list
               .iter()
-              .find(|l| Locator::is_udp(l)),
+              .find(|l| multicast_enabled && Locator::is_udp(l)),
           ) {
             (DeliveryMode::Multicast, _, Some(_mc_locator)) => {
               send_unless_sent_and_mark!(reader.multicast_locator_list);
@@ -1546,6 +2681,22 @@ impl Writer {
             }
           } // match
         }
+
+        match flow_controller {
+          Some(flow_controller) if !destinations.is_empty() => {
+            flow_controller
+              .borrow_mut()
+              .send(self.my_guid, buffer, destinations);
+          }
+          _ => {
+            self.udp_sender.send_to_locator_list(&buffer, &destinations);
+            if let Some(spdp_transmissions) =
+              self.spdp_transmissions.as_ref().filter(|_| carries_data)
+            {
+              spdp_transmissions.sent(&buffer, &destinations);
+            }
+          }
+        }
       }
       Err(e) => error!("Failed to send message to readers. Encoding failed: {e:?}"),
     }
@@ -1553,6 +2704,7 @@ impl Writer {
 
   // Send status to DataWriter or however is listening
   fn send_status(&self, status: DataWriterStatus) {
+    self.status_record.record(&status);
     self
       .status_sender
       .try_send(status)
@@ -1573,6 +2725,16 @@ impl Writer {
     requested_qos: &QosPolicies,
   ) {
     debug!("update_reader_proxy topic={:?}", self.my_topic_name);
+    // Partition mismatch is not a QoS incompatibility. We just do not match.
+    // If Partitions were changed, a previous match is removed.
+    if !self.qos_policies.partitions_match(requested_qos) {
+      debug!(
+        "update_reader_proxy - Partitions do not match. topic={:?} reader={:?}",
+        self.my_topic_name, reader_proxy.remote_reader_guid
+      );
+      self.reader_lost(reader_proxy.remote_reader_guid);
+      return;
+    }
     match self.qos_policies.compliance_failure_wrt(requested_qos) {
       // matched QoS
       None => {
@@ -1588,6 +2750,10 @@ impl Writer {
             local_writer: self.my_guid,
             remote_reader: reader_proxy.remote_reader_guid,
           });
+          if self.readers.len() == 1 {
+            self.send_status(DataWriterStatus::ReaderPresenceChanged { has_readers: true });
+          }
+          self.initialize();
           // If we're reliable, should we send out a heartbeat so that new reader can
           // catch up?
           info!(
@@ -1596,6 +2762,21 @@ impl Writer {
             &reader_proxy.remote_reader_guid
           );
           debug!("Reader details: {:?}", &reader_proxy);
+          if self.qos_policies.data_representation_differs(requested_qos) {
+            warn!(
+              "Data representation differs: topic={:?} reader={:?} offered={:?} requested={:?}",
+              self.topic_name(),
+              reader_proxy.remote_reader_guid,
+              self.qos_policies.data_representation(),
+              requested_qos.data_representation()
+            );
+          }
+          // The new Reader gets samples over the network, so they cannot stay
+          // unserialized.
+          self.history_buffer.serialize_local_changes();
+          self.last_values.serialize_local_changes();
+          self.send_last_values(reader_proxy.remote_reader_guid);
+          self.update_watermarks();
         }
       }
       Some(bad_policy_id) => {
@@ -1634,6 +2815,10 @@ impl Writer {
   fn matched_reader_update(&mut self, updated_reader_proxy: &RtpsReaderProxy) -> i32 {
     let mut new = 0;
     let is_volatile = self.qos().is_volatile(); // Get this in advance to work with the borrow checker
+    self.matched_reader_filters.update(
+      updated_reader_proxy.remote_reader_guid,
+      updated_reader_proxy.content_filter.as_ref(),
+    );
     self
       .readers
       .entry(updated_reader_proxy.remote_reader_guid)
@@ -1646,14 +2831,39 @@ impl Writer {
           // before matching with this reader. Therefore we set the reader as pending GAP
           // for all existing sequence numbers
           new_proxy.set_pending_gap_up_to(self.history_buffer.last_change_sequence_number());
+          // ... except for the latest samples of the LastValueCache policy.
+          for sn in self.last_values.sequence_numbers() {
+            new_proxy.remove_pending_gap(sn);
+          }
         }
         new_proxy
       });
     new
   }
 
+  // Sends the samples kept for the LastValueCache policy to a newly matched
+  // Reader. The cache is empty, if the policy is not enabled.
+  fn send_last_values(&self, reader_guid: GUID) {
+    let reader = match self.readers.get(&reader_guid) {
+      Some(reader) => reader,
+      None => return,
+    };
+    let count = self.last_values.changes.len();
+    for (i, cc) in self.last_values.changes.values().enumerate() {
+      let send_also_heartbeat = i + 1 == count;
+      self.send_cache_change(cc, send_also_heartbeat, Some(reader));
+    }
+    if count > 0 {
+      debug!(
+        "Sent {count} last values to new reader {reader_guid:?} topic={:?}",
+        self.my_topic_name
+      );
+    }
+  }
+
   fn matched_reader_remove(&mut self, guid: GUID) -> Option<RtpsReaderProxy> {
     let removed = self.readers.remove(&guid);
+    self.matched_reader_filters.remove(guid);
     if let Some(ref removed_reader) = removed {
       info!(
         "Removed reader proxy. topic={:?} reader={:?}",
@@ -1686,9 +2896,46 @@ impl Writer {
         current: CountWithChange::new(self.readers.len() as i32, -1),
         reader: guid,
       });
+      if self.readers.is_empty() {
+        self.send_status(DataWriterStatus::ReaderPresenceChanged { has_readers: false });
+      }
     }
     // also remember to remove reader from ack_waiter
     self.update_ack_waiters(guid, None);
+    self.update_watermarks();
+  }
+
+  // Publishes the current watermarks to the DataWriter, and notifies it if
+  // the unacknowledged window shrank below its threshold.
+  fn update_watermarks(&self) {
+    if self.like_stateless {
+      return;
+    }
+    let shrunk = self.watermarks.update(SequenceNumberWatermarks {
+      lowest_unacknowledged: self.lowest_unacknowledged(),
+      highest_written: self.history_buffer.last_change_sequence_number(),
+    });
+    if let Some((unacknowledged, threshold)) = shrunk {
+      self.send_status(DataWriterStatus::UnacknowledgedBelowThreshold {
+        unacknowledged,
+        threshold,
+      });
+    }
+  }
+
+  // Lowest sequence number that some matched reliable Reader has not
+  // acknowledged. Stateless-like writers consider everything acknowledged.
+  fn lowest_unacknowledged(&self) -> SequenceNumber {
+    let next = self.history_buffer.last_change_sequence_number().plus_1();
+    if self.like_stateless {
+      return next;
+    }
+    self
+      .readers
+      .values()
+      .filter(|rp| rp.qos().is_reliable())
+      .map(|rp| rp.all_acked_before)
+      .fold(next, SequenceNumber::min)
   }
 
   // Entire remote participant was lost.
@@ -1712,6 +2959,16 @@ impl Writer {
     &self.my_topic_name
   }
 
+  // Reader proxies are matched again by the caller, and removed if the
+  // partitions no longer match.
+  pub fn set_partition(&mut self, partition: policy::Partition) {
+    self.qos_policies.partition = Some(partition);
+  }
+
+  pub fn matched_reader_count(&self) -> usize {
+    self.readers.len()
+  }
+
   fn send_participant_status(&self, event: DomainParticipantStatusEvent) {
     self
       .participant_status_sender
@@ -1748,16 +3005,55 @@ mod tests {
   use std::thread;
 
   use byteorder::LittleEndian;
+  use bytes::Bytes;
   use log::info;
 
   use crate::{
     dds::{
-      participant::DomainParticipant, qos::QosPolicies, topic::TopicKind,
-      with_key::datawriter::DataWriter,
+      key::Key,
+      participant::DomainParticipant,
+      qos::QosPolicies,
+      statusevents::{sync_status_channel, StatusChannelReceiver},
+      topic::TopicKind,
+      intra_process::LocalSample,
+      with_key::datawriter::{DataWriter, WriteOptionsBuilder},
+    },
+    messages::submessages::{
+      elements::serialized_payload::SerializedPayload,
+      submessage::WriterSubmessage,
+      submessage_kind::SubmessageKind,
+      submessages::{AckNack, AppAck},
     },
     serialization::CDRSerializerAdapter,
+    RepresentationIdentifier,
+    structure::{guid::EntityKind, sequence_number::SequenceNumberSet},
     test::random_data::*,
   };
+  use super::*;
+
+  #[test]
+  fn inline_qos_has_topic_name_and_writer_policies() {
+    let qos = QosPolicies::builder()
+      .reliability(Reliability::BestEffort)
+      .history(History::KeepLast { depth: 3 })
+      .build();
+    let inline_qos = Writer::inline_qos("Shapes", &qos, Endianness::LittleEndian).unwrap();
+    let pids: Vec<ParameterId> = inline_qos
+      .parameters
+      .iter()
+      .map(|p| p.parameter_id)
+      .collect();
+    assert_eq!(
+      pids,
+      vec![ParameterId::PID_TOPIC_NAME, ParameterId::PID_RELIABILITY]
+    );
+    assert_eq!(
+      inline_qos.parameters[0].value,
+      StringWithNul::from("Shapes".to_string())
+        .write_to_vec_with_ctx(Endianness::LittleEndian)
+        .unwrap()
+    );
+  }
 
   #[test]
   fn test_writer_receives_datawriter_cache_change_notifications() {
@@ -1809,4 +3105,983 @@ mod tests {
     thread::sleep(std::time::Duration::from_millis(100));
     info!("writerResult:  {:?}", write_result);
   }
+
+  // The other ends of the channels of a Writer made by `test_writer`.
+  struct TestWriterChannels {
+    command_sender: mio_channel::SyncSender<WriterCommand>,
+    status_receiver: StatusChannelReceiver<DataWriterStatus>,
+    _participant_status_receiver: StatusChannelReceiver<DomainParticipantStatusEvent>,
+  }
+
+  // A Writer that is not attached to a DataWriter or an event loop. Tests drive
+  // it by sending commands and calling its handlers directly.
+  fn test_writer(guid: GUID, qos_policies: QosPolicies) -> (Writer, TestWriterChannels) {
+    let (command_sender, writer_command_receiver) = mio_channel::sync_channel(10);
+    let (status_sender, status_receiver) = sync_status_channel(16).unwrap();
+    let (participant_status_sender, participant_status_receiver) = sync_status_channel(16).unwrap();
+    let writer_ing = WriterIngredients {
+      guid,
+      writer_command_receiver,
+      writer_command_receiver_waker: Arc::new(Mutex::new(None)),
+      topic_name: "test_name".to_string(),
+      like_stateless: false,
+      qos_policies,
+      status_sender,
+      conflated_samples: Arc::new(atomic::AtomicU64::new(0)),
+      watermarks: Arc::new(WatermarkTracker::new()),
+      matched_reader_filters: Arc::new(MatchedReaderFilters::default()),
+      status_record: Arc::new(WriterStatusRecord::default()),
+      security_plugins: None,
+    };
+    let writer = Writer::new(
+      writer_ing,
+      Rc::new(UDPSender::new(0).unwrap()),
+      mio_extras::timer::Builder::default().build(),
+      participant_status_sender,
+    );
+    let channels = TestWriterChannels {
+      command_sender,
+      status_receiver,
+      _participant_status_receiver: participant_status_receiver,
+    };
+    (writer, channels)
+  }
+
+  #[test]
+  fn heartbeat_tick_is_addressed_to_lagging_readers_only() {
+    let (mut writer, _channels) = test_writer(
+      GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED),
+      QosPolicies::qos_none(),
+    );
+
+    // Two unicast-only Readers, one of which has acknowledged everything
+    let mut add_reader = |prefix_byte: u8, all_acked_before: SequenceNumber| {
+      let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+      socket.set_nonblocking(true).unwrap();
+      let mut guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+      guid.prefix.bytes[0] = prefix_byte;
+      let mut proxy = RtpsReaderProxy::new(guid, QosPolicies::qos_none(), false);
+      proxy.unicast_locator_list = vec![Locator::from(socket.local_addr().unwrap())];
+      proxy.all_acked_before = all_acked_before;
+      writer.readers.insert(guid, proxy);
+      socket
+    };
+    let lagging = add_reader(1, SequenceNumber::zero());
+    let up_to_date = add_reader(2, SequenceNumber::new(1));
+
+    writer.handle_heartbeat_tick(false);
+    thread::sleep(std::time::Duration::from_millis(50));
+
+    let mut buf = [0; 1024];
+    assert!(lagging.recv(&mut buf).is_ok());
+    assert!(up_to_date.recv(&mut buf).is_err());
+  }
+
+  #[test]
+  fn last_values_are_sent_to_new_volatile_reader() {
+    let (mut writer, channels) = test_writer(
+      GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+      QosPolicies::builder()
+        .durability(policy::Durability::Volatile)
+        .last_value_cache(policy::LastValueCache::Enabled)
+        .build(),
+    );
+
+    // Instance 1 is written twice, instance 2 is written and disposed.
+    let instance_1 = 1i32.hash_key(false);
+    let instance_2 = 2i32.hash_key(false);
+    let commands = [
+      (instance_1, DDSData::new(SerializedPayload::default())),
+      (instance_2, DDSData::new(SerializedPayload::default())),
+      (instance_1, DDSData::new(SerializedPayload::default())),
+      (
+        instance_2,
+        DDSData::new_disposed_by_key_hash(ChangeKind::NotAliveDisposed, instance_2),
+      ),
+    ];
+    for (sn, (instance, ddsdata)) in (1..).zip(commands) {
+      channels
+        .command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata,
+          write_options: WriteOptions::default(),
+          sequence_number: SequenceNumber::new(sn),
+          instance: Some(instance),
+        })
+        .unwrap();
+    }
+    writer.process_writer_command();
+    assert_eq!(
+      writer.last_values.sequence_numbers().collect::<Vec<_>>(),
+      vec![SequenceNumber::new(3)]
+    );
+
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_nonblocking(true).unwrap();
+    let guid = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let mut proxy = RtpsReaderProxy::new(guid, QosPolicies::qos_none(), false);
+    proxy.unicast_locator_list = vec![Locator::from(socket.local_addr().unwrap())];
+    writer.update_reader_proxy(&proxy, &QosPolicies::qos_none());
+
+    // The last value is not GAPped, but everything else is.
+    let pending_gap = writer.readers[&guid].get_pending_gap();
+    assert!(!pending_gap.contains(&SequenceNumber::new(3)));
+    assert!(pending_gap.contains(&SequenceNumber::new(1)));
+
+    thread::sleep(std::time::Duration::from_millis(50));
+    let mut buf = [0; 1024];
+    assert!(socket.recv(&mut buf).is_ok());
+  }
+
+  #[test]
+  fn local_samples_are_delivered_in_process() {
+    let (mut writer, channels) = test_writer(
+      GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+      QosPolicies::builder()
+        .durability(policy::Durability::Volatile)
+        .build(),
+    );
+    let write = |sn: i64| {
+      let data = Arc::new(RandomData {
+        a: sn,
+        b: "shared".to_string(),
+      });
+      channels
+        .command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata: DDSData::Local {
+            sample: LocalSample::new::<RandomData, CDRSerializerAdapter<RandomData>>(data),
+          },
+          write_options: WriteOptions::default(),
+          sequence_number: SequenceNumber::new(sn),
+          instance: None,
+        })
+        .unwrap();
+    };
+    let is_local = |writer: &Writer, sn: i64| {
+      matches!(
+        writer
+          .history_buffer
+          .get_by_sn(SequenceNumber::new(sn))
+          .map(|cc| &cc.data_value),
+        Some(DDSData::Local { .. })
+      )
+    };
+
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let local_reader = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let mut proxy = RtpsReaderProxy::new(local_reader, QosPolicies::qos_none(), false);
+    proxy.unicast_locator_list = vec![Locator::from(socket.local_addr().unwrap())];
+    writer.update_reader_proxy(&proxy, &QosPolicies::qos_none());
+
+    // Only a local Reader: the sample stays unserialized.
+    write(1);
+    writer.process_writer_command();
+    let deliveries = writer.take_local_deliveries();
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].readers, vec![local_reader.entity_id]);
+    assert_eq!(deliveries[0].change.sequence_number, SequenceNumber::new(1));
+    assert!(is_local(&writer, 1));
+    assert!(writer.readers[&local_reader]
+      .unsent_changes_debug()
+      .is_empty());
+
+    // A remote Reader needs the kept sample and new ones serialized.
+    let remote_reader = GUID::new(GuidPrefix::new(b"RemoteReader"), local_reader.entity_id);
+    proxy.remote_reader_guid = remote_reader;
+    writer.update_reader_proxy(&proxy, &QosPolicies::qos_none());
+    assert!(!is_local(&writer, 1));
+
+    write(2);
+    writer.process_writer_command();
+    let deliveries = writer.take_local_deliveries();
+    assert_eq!(deliveries[0].readers, vec![local_reader.entity_id]);
+    assert!(matches!(
+      deliveries[0].change.data_value,
+      DDSData::Local { .. }
+    ));
+    assert!(!is_local(&writer, 2));
+    assert!(writer.take_local_deliveries().is_empty());
+  }
+
+  #[test]
+  fn conflation_sends_latest_per_instance() {
+    let (mut writer, channels) = test_writer(
+      GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED),
+      QosPolicies::builder()
+        .reliability(policy::Reliability::BestEffort)
+        .conflation(policy::Conflation::LatestPerInstance)
+        .build(),
+    );
+    let conflated_samples = Arc::clone(&writer.conflated_samples);
+
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_nonblocking(true).unwrap();
+    let guid = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let mut proxy = RtpsReaderProxy::new(guid, QosPolicies::qos_none(), false);
+    proxy.unicast_locator_list = vec![Locator::from(socket.local_addr().unwrap())];
+    writer.update_reader_proxy(&proxy, &QosPolicies::qos_none());
+
+    // Instance 1 is written three times and instance 2 once, before the Writer
+    // gets to send anything.
+    let instance_1 = 1i32.hash_key(false);
+    let instance_2 = 2i32.hash_key(false);
+    for (sn, instance) in (1..).zip([instance_1, instance_1, instance_2, instance_1]) {
+      channels
+        .command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata: DDSData::new(SerializedPayload::default()),
+          write_options: WriteOptions::default(),
+          sequence_number: SequenceNumber::new(sn),
+          instance: Some(instance),
+        })
+        .unwrap();
+    }
+    writer.process_writer_command();
+    assert_eq!(conflated_samples.load(atomic::Ordering::Relaxed), 2);
+
+    thread::sleep(std::time::Duration::from_millis(50));
+    let mut buf = [0; 1024];
+    assert!(socket.recv(&mut buf).is_ok());
+    assert!(socket.recv(&mut buf).is_ok());
+    assert!(socket.recv(&mut buf).is_err());
+  }
+
+  #[test]
+  fn latency_budget_sends_together_at_deadline() {
+    let (mut writer, channels) = test_writer(
+      GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED),
+      QosPolicies::builder()
+        .reliability(policy::Reliability::BestEffort)
+        .latency_budget(policy::LatencyBudget {
+          duration: Duration::from_millis(100),
+        })
+        .build(),
+    );
+
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_nonblocking(true).unwrap();
+    let guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let mut proxy = RtpsReaderProxy::new(guid, QosPolicies::qos_none(), false);
+    proxy.unicast_locator_list = vec![Locator::from(socket.local_addr().unwrap())];
+    writer.update_reader_proxy(&proxy, &QosPolicies::qos_none());
+
+    for sn in 1..=2 {
+      channels
+        .command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata: DDSData::new(SerializedPayload::default()),
+          write_options: WriteOptions::default(),
+          sequence_number: SequenceNumber::new(sn),
+          instance: None,
+        })
+        .unwrap();
+      writer.process_writer_command();
+    }
+    // Nothing is sent within the budget, but the send trigger timer is set to
+    // its end.
+    let mut buf = [0; 1024];
+    assert!(socket.recv(&mut buf).is_err());
+    assert!(writer.send_trigger_armed.is_some());
+    let deadline = writer.send_trigger.deadline().unwrap();
+    writer.send_if_due_at(deadline - Duration::from_millis(1));
+    assert!(socket.recv(&mut buf).is_err());
+
+    // Both samples are sent together at the deadline.
+    writer.send_if_due_at(deadline);
+    assert_eq!(writer.send_trigger.deadline(), None);
+    socket.set_nonblocking(false).unwrap();
+    socket
+      .set_read_timeout(Some(std::time::Duration::from_secs(1)))
+      .unwrap();
+    assert!(socket.recv(&mut buf).is_ok());
+    assert!(socket.recv(&mut buf).is_ok());
+    socket.set_nonblocking(true).unwrap();
+    assert!(socket.recv(&mut buf).is_err());
+  }
+
+  #[test]
+  fn data_and_heartbeat_are_sent_in_one_datagram() {
+    let (mut writer, channels) = test_writer(
+      GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED),
+      QosPolicies::builder()
+        .reliability(policy::Reliability::Reliable {
+          max_blocking_time: Duration::ZERO,
+        })
+        .build(),
+    );
+
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let mut proxy = RtpsReaderProxy::new(guid, QosPolicies::qos_none(), false);
+    proxy.unicast_locator_list = vec![Locator::from(socket.local_addr().unwrap())];
+    writer.update_reader_proxy(&proxy, &QosPolicies::qos_none());
+
+    let mut write = |sn: i64, payload_size: usize| {
+      channels
+        .command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata: DDSData::new(SerializedPayload::new(
+            RepresentationIdentifier::CDR_LE,
+            vec![0; payload_size],
+          )),
+          write_options: WriteOptionsBuilder::new()
+            .source_timestamp(Timestamp::now())
+            .build(),
+          sequence_number: SequenceNumber::new(sn),
+          instance: None,
+        })
+        .unwrap();
+      writer.process_writer_command();
+    };
+    // The datagrams must have been sent by the time the Writer has processed
+    // the write, and not later by some timer. The timeout only guards against
+    // the test hanging.
+    socket
+      .set_read_timeout(Some(std::time::Duration::from_millis(100)))
+      .unwrap();
+    let receive_submessage_kinds = || {
+      let mut buf = [0; 2048];
+      let len = socket.recv(&mut buf).unwrap();
+      Message::read_from_buffer(&Bytes::copy_from_slice(&buf[..len]))
+        .unwrap()
+        .submessages
+        .iter()
+        .map(|s| s.header.kind)
+        .collect::<Vec<_>>()
+    };
+
+    // A small sample goes in a single DATA, followed by the HEARTBEAT.
+    write(1, 100);
+    assert_eq!(
+      receive_submessage_kinds(),
+      vec![
+        SubmessageKind::INFO_TS,
+        SubmessageKind::DATA,
+        SubmessageKind::HEARTBEAT
+      ]
+    );
+
+    // A large sample is fragmented, and the HEARTBEAT follows the last fragment.
+    write(2, 2500);
+    for _ in 0..2 {
+      assert_eq!(
+        receive_submessage_kinds(),
+        vec![SubmessageKind::INFO_TS, SubmessageKind::DATA_FRAG]
+      );
+    }
+    assert_eq!(
+      receive_submessage_kinds(),
+      vec![
+        SubmessageKind::INFO_TS,
+        SubmessageKind::DATA_FRAG,
+        SubmessageKind::HEARTBEAT
+      ]
+    );
+
+    // Nothing else was sent.
+    socket.set_nonblocking(true).unwrap();
+    let mut buf = [0; 2048];
+    assert!(socket.recv(&mut buf).is_err());
+  }
+
+  #[test]
+  fn batching_packs_pending_samples_into_few_datagrams() {
+    let (mut writer, channels) = test_writer(
+      GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED),
+      QosPolicies::builder()
+        .batching(policy::Batching {
+          max_bytes: 1000,
+          max_delay: Duration::from_secs(3600),
+        })
+        .build(),
+    );
+
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let mut proxy = RtpsReaderProxy::new(guid, QosPolicies::qos_none(), false);
+    proxy.unicast_locator_list = vec![Locator::from(socket.local_addr().unwrap())];
+    writer.update_reader_proxy(&proxy, &QosPolicies::qos_none());
+
+    let mut write = |sn: i64| {
+      channels
+        .command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata: DDSData::new(SerializedPayload::new(
+            RepresentationIdentifier::CDR_LE,
+            vec![0; 250],
+          )),
+          write_options: WriteOptionsBuilder::new()
+            .source_timestamp(Timestamp::now())
+            .build(),
+          sequence_number: SequenceNumber::new(sn),
+          instance: None,
+        })
+        .unwrap();
+      writer.process_writer_command();
+    };
+
+    // Nothing is sent before the pending samples fill a batch.
+    socket.set_nonblocking(true).unwrap();
+    let mut buf = [0; 2048];
+    for sn in 1..=3 {
+      write(sn);
+    }
+    assert!(socket.recv(&mut buf).is_err());
+
+    // The fourth sample fills it, and the four are sent in fewer datagrams.
+    write(4);
+    socket.set_nonblocking(false).unwrap();
+    socket
+      .set_read_timeout(Some(std::time::Duration::from_millis(100)))
+      .unwrap();
+    let mut datagrams = 0;
+    let mut data_submessages = 0;
+    while data_submessages < 4 {
+      let len = socket.recv(&mut buf).unwrap();
+      assert!(len <= 1000);
+      datagrams += 1;
+      data_submessages += Message::read_from_buffer(&Bytes::copy_from_slice(&buf[..len]))
+        .unwrap()
+        .submessages
+        .iter()
+        .filter(|s| s.header.kind == SubmessageKind::DATA)
+        .count();
+    }
+    assert_eq!(data_submessages, 4);
+    assert!(datagrams < 4);
+  }
+
+  #[test]
+  fn app_acks_are_tracked_and_unacknowledged_samples_redelivered() {
+    let writer_guid = GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    let (mut writer, channels) = test_writer(
+      writer_guid,
+      QosPolicies::builder()
+        .acknowledgment(policy::Acknowledgment::ApplicationExplicit)
+        .build(),
+    );
+
+    let stopped = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let other = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    for guid in [stopped, other] {
+      let proxy = RtpsReaderProxy::new(guid, QosPolicies::qos_none(), false);
+      writer.update_reader_proxy(&proxy, &QosPolicies::qos_none());
+    }
+    for sn in 1..=3 {
+      channels
+        .command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata: DDSData::new(SerializedPayload::new(
+            RepresentationIdentifier::CDR_LE,
+            vec![sn as u8; 4],
+          )),
+          write_options: WriteOptionsBuilder::new()
+            .source_timestamp(Timestamp::now())
+            .build(),
+          sequence_number: SequenceNumber::new(sn),
+          instance: None,
+        })
+        .unwrap();
+      writer.process_writer_command();
+    }
+    let sns = |range: std::ops::RangeInclusive<i64>| -> Vec<SequenceNumber> {
+      range.map(SequenceNumber::new).collect()
+    };
+    assert_eq!(writer.unacknowledged_samples()[&stopped], sns(1..=3));
+
+    // Samples 1 and 3 are processed
+    writer.handle_ack_nack(
+      stopped.prefix,
+      &AckSubmessage::AppAck(AppAck {
+        reader_id: stopped.entity_id,
+        writer_id: writer_guid.entity_id,
+        acknowledged: SequenceNumberSet::from_base_and_set(
+          SequenceNumber::new(2),
+          &[SequenceNumber::new(3)].into_iter().collect(),
+        ),
+        count: 1,
+      }),
+    );
+    assert_eq!(writer.unacknowledged_samples()[&stopped], sns(2..=2));
+
+    // Sample 2 is handed to the other reader as sample 4.
+    let (reply, redelivered) = std::sync::mpsc::channel();
+    channels
+      .command_sender
+      .send(WriterCommand::RedeliverUnacknowledged {
+        reader: stopped,
+        to_reader: other,
+        first_sequence_number: SequenceNumber::new(4),
+        count: 1,
+        reply,
+      })
+      .unwrap();
+    writer.process_writer_command();
+    assert_eq!(redelivered.try_recv(), Ok(1));
+    let unacknowledged = writer.unacknowledged_samples();
+    assert!(unacknowledged[&stopped].is_empty());
+    assert_eq!(unacknowledged[&other], sns(1..=4));
+    let redelivered = writer
+      .history_buffer
+      .get_by_sn(SequenceNumber::new(4))
+      .unwrap();
+    assert_eq!(redelivered.write_options.to_single_reader(), Some(other));
+    assert_eq!(
+      redelivered.data_value,
+      writer
+        .history_buffer
+        .get_by_sn(SequenceNumber::new(2))
+        .unwrap()
+        .data_value
+    );
+  }
+
+  #[test]
+  fn fec_parity_follows_each_group() {
+    let (mut writer, channels) = test_writer(
+      GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED),
+      QosPolicies::builder()
+        .reliability(policy::Reliability::BestEffort)
+        .forward_error_correction(policy::ForwardErrorCorrection::Xor { group_size: 2 })
+        .build(),
+    );
+
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+      .set_read_timeout(Some(std::time::Duration::from_millis(100)))
+      .unwrap();
+    let guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let mut proxy = RtpsReaderProxy::new(guid, QosPolicies::qos_none(), false);
+    proxy.unicast_locator_list = vec![Locator::from(socket.local_addr().unwrap())];
+    writer.update_reader_proxy(&proxy, &QosPolicies::qos_none());
+
+    let payloads = [vec![1, 2, 3, 4], vec![5, 6, 7, 8, 9, 10, 11, 12]];
+    for (sn, payload) in (1..).zip(&payloads) {
+      channels
+        .command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata: DDSData::new(SerializedPayload::new(
+            RepresentationIdentifier::CDR_LE,
+            payload.clone(),
+          )),
+          write_options: WriteOptions::default(),
+          sequence_number: SequenceNumber::new(sn),
+          instance: None,
+        })
+        .unwrap();
+      writer.process_writer_command();
+    }
+
+    let receive_message = || {
+      let mut buf = [0; 2048];
+      let len = socket.recv(&mut buf).unwrap();
+      Message::read_from_buffer(&Bytes::copy_from_slice(&buf[..len])).unwrap()
+    };
+    // Two DATAs, and then the parity of both
+    for _ in 0..2 {
+      assert_eq!(
+        receive_message().submessages[0].header.kind,
+        SubmessageKind::DATA
+      );
+    }
+    let message = receive_message();
+    let fec_parity = match &message.submessages[0].body {
+      crate::rtps::SubmessageBody::Writer(WriterSubmessage::FecParity(fec_parity, _)) => fec_parity,
+      other => panic!("Expected FEC_PARITY, got {other:?}"),
+    };
+    assert_eq!(
+      fec_parity
+        .samples
+        .iter()
+        .map(|s| (s.sequence_number, s.payload_length))
+        .collect::<Vec<_>>(),
+      vec![(SequenceNumber::new(1), 8), (SequenceNumber::new(2), 12)]
+    );
+    // The encapsulation headers are equal, so they cancel out.
+    assert_eq!(
+      fec_parity.parity,
+      vec![0, 0, 0, 0, 1 ^ 5, 2 ^ 6, 3 ^ 7, 4 ^ 8, 9, 10, 11, 12]
+    );
+  }
+
+  // Writes samples 1 and 2, with the Reader requesting sample 1 again in
+  // between. Returns the sequence numbers of the DATAs the Reader receives.
+  fn sent_data_with_repair_pending(repair_scheduling: policy::RepairScheduling) -> Vec<i64> {
+    let (mut writer, channels) = test_writer(
+      GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED),
+      QosPolicies::builder()
+        .reliability(policy::Reliability::Reliable {
+          max_blocking_time: Duration::ZERO,
+        })
+        .repair_scheduling(repair_scheduling)
+        .build(),
+    );
+
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+      .set_read_timeout(Some(std::time::Duration::from_millis(100)))
+      .unwrap();
+    let guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let mut proxy = RtpsReaderProxy::new(guid, QosPolicies::qos_none(), false);
+    proxy.unicast_locator_list = vec![Locator::from(socket.local_addr().unwrap())];
+    writer.update_reader_proxy(&proxy, &QosPolicies::qos_none());
+
+    let write = |writer: &mut Writer, sn: i64| {
+      channels
+        .command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata: DDSData::new(SerializedPayload::new(
+            RepresentationIdentifier::CDR_LE,
+            vec![0; 4],
+          )),
+          write_options: WriteOptions::default(),
+          sequence_number: SequenceNumber::new(sn),
+          instance: None,
+        })
+        .unwrap();
+      writer.process_writer_command();
+    };
+
+    write(&mut writer, 1);
+    // The repair timer is not run in this test. Only the scheduling policy
+    // can send the repair.
+    let ack_nack = AckNack {
+      reader_id: guid.entity_id,
+      writer_id: writer.my_guid.entity_id,
+      reader_sn_state: SequenceNumberSet::from_base_and_set(
+        SequenceNumber::new(1),
+        &[SequenceNumber::new(1)].into(),
+      ),
+      count: 1,
+    };
+    writer.handle_ack_nack(guid.prefix, &AckSubmessage::AckNack(ack_nack));
+    write(&mut writer, 2);
+
+    let mut sent = Vec::new();
+    let mut buf = [0; 2048];
+    while let Ok(len) = socket.recv(&mut buf) {
+      let message = Message::read_from_buffer(&Bytes::copy_from_slice(&buf[..len])).unwrap();
+      for submessage in message.submessages {
+        if let crate::rtps::SubmessageBody::Writer(WriterSubmessage::Data(data, _)) =
+          submessage.body
+        {
+          sent.push(i64::from(data.writer_sn));
+        }
+      }
+    }
+    sent
+  }
+
+  #[test]
+  fn repair_scheduling_orders_repairs_and_new_data() {
+    assert_eq!(
+      sent_data_with_repair_pending(policy::RepairScheduling::NewestFirst),
+      vec![1, 2]
+    );
+    assert_eq!(
+      sent_data_with_repair_pending(policy::RepairScheduling::RepairFirst),
+      vec![1, 1, 2]
+    );
+    assert_eq!(
+      sent_data_with_repair_pending(policy::RepairScheduling::Weighted {
+        repairs_per_sample: 1
+      }),
+      vec![1, 1, 2]
+    );
+  }
+
+  #[test]
+  fn unacknowledged_window_notifies_below_threshold() {
+    let reliable = QosPolicies::builder()
+      .reliability(policy::Reliability::Reliable {
+        max_blocking_time: Duration::ZERO,
+      })
+      .build();
+    let (mut writer, channels) = test_writer(
+      GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED),
+      reliable.clone(),
+    );
+    let watermarks = Arc::clone(&writer.watermarks);
+
+    let mut reader_guids = Vec::new();
+    for prefix_byte in [1, 2] {
+      let mut guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+      guid.prefix.bytes[0] = prefix_byte;
+      let mut proxy = RtpsReaderProxy::new(guid, reliable.clone(), false);
+      proxy.all_acked_before = SequenceNumber::new(1);
+      writer.readers.insert(guid, proxy);
+      reader_guids.push(guid);
+    }
+
+    for sn in 1..=4 {
+      channels
+        .command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata: DDSData::new(SerializedPayload::default()),
+          write_options: WriteOptions::default(),
+          sequence_number: SequenceNumber::new(sn),
+          instance: None,
+        })
+        .unwrap();
+    }
+    writer.process_writer_command();
+    assert_eq!(
+      watermarks.watermarks(),
+      SequenceNumberWatermarks {
+        lowest_unacknowledged: SequenceNumber::new(1),
+        highest_written: SequenceNumber::new(4),
+      }
+    );
+    assert_eq!(watermarks.watermarks().unacknowledged(), 4);
+
+    // The slower reader determines the lowest unacknowledged sequence number.
+    watermarks.set_threshold(Some(3));
+    for (guid, acked_before) in reader_guids.iter().zip([5, 3]) {
+      writer.readers.get_mut(guid).unwrap().all_acked_before = SequenceNumber::new(acked_before);
+    }
+    writer.update_watermarks();
+    assert_eq!(watermarks.watermarks().unacknowledged(), 2);
+    assert!(matches!(
+      channels.status_receiver.try_recv(),
+      Ok(DataWriterStatus::UnacknowledgedBelowThreshold {
+        unacknowledged: 2,
+        threshold: 3
+      })
+    ));
+
+    // Staying below the threshold is not notified again.
+    writer.reader_lost(reader_guids[1]);
+    assert_eq!(watermarks.watermarks().unacknowledged(), 0);
+    assert!(!matches!(
+      channels.status_receiver.try_recv(),
+      Ok(DataWriterStatus::UnacknowledgedBelowThreshold { .. })
+    ));
+  }
+
+  #[test]
+  fn lazy_writer_initializes_on_first_match() {
+    let volatile = QosPolicies::builder()
+      .durability(policy::Durability::Volatile)
+      .writer_initialization(policy::WriterInitialization::OnFirstMatch)
+      .build();
+    let (mut writer, channels) = test_writer(
+      GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED),
+      volatile.clone(),
+    );
+    assert!(!writer.initialized);
+
+    // Samples written with no Readers only consume sequence numbers.
+    for sn in 1..=2 {
+      channels
+        .command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata: DDSData::new(SerializedPayload::default()),
+          write_options: WriteOptions::default(),
+          sequence_number: SequenceNumber::new(sn),
+          instance: None,
+        })
+        .unwrap();
+    }
+    writer.process_writer_command();
+    assert!(writer.history_buffer.history_buffer.is_empty());
+    assert_eq!(
+      writer.history_buffer.last_change_sequence_number(),
+      SequenceNumber::new(2)
+    );
+    assert_eq!(
+      writer.history_buffer.first_change_sequence_number(),
+      SequenceNumber::new(3)
+    );
+
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let proxy = RtpsReaderProxy::new(reader_guid, volatile.clone(), false);
+    writer.update_reader_proxy(&proxy, &volatile);
+    assert!(writer.initialized);
+    assert!(matches!(
+      channels.status_receiver.try_recv(),
+      Ok(DataWriterStatus::PublicationMatched { .. })
+    ));
+    assert!(matches!(
+      channels.status_receiver.try_recv(),
+      Ok(DataWriterStatus::ReaderPresenceChanged { has_readers: true })
+    ));
+
+    writer.reader_lost(reader_guid);
+    assert!(matches!(
+      channels.status_receiver.try_recv(),
+      Ok(DataWriterStatus::PublicationMatched { .. })
+    ));
+    assert!(matches!(
+      channels.status_receiver.try_recv(),
+      Ok(DataWriterStatus::ReaderPresenceChanged { has_readers: false })
+    ));
+    // Set up once is enough.
+    assert!(writer.initialized);
+  }
+
+  #[test]
+  fn history_is_reported_and_purged() {
+    let reliable = QosPolicies::builder()
+      .reliability(policy::Reliability::Reliable {
+        max_blocking_time: Duration::ZERO,
+      })
+      .build();
+    let (mut writer, channels) = test_writer(
+      GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED),
+      reliable.clone(),
+    );
+
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let mut proxy = RtpsReaderProxy::new(reader_guid, reliable, false);
+    proxy.all_acked_before = SequenceNumber::new(3);
+    writer.readers.insert(reader_guid, proxy);
+
+    for sn in 1..=4 {
+      channels
+        .command_sender
+        .send(WriterCommand::DDSData {
+          ddsdata: DDSData::new(SerializedPayload::new(
+            RepresentationIdentifier::CDR_LE,
+            vec![0; 8],
+          )),
+          write_options: WriteOptions::default(),
+          sequence_number: SequenceNumber::new(sn),
+          instance: None,
+        })
+        .unwrap();
+    }
+    writer.process_writer_command();
+
+    let (reply, status) = std::sync::mpsc::channel();
+    channels
+      .command_sender
+      .send(WriterCommand::HistoryStatus { reply })
+      .unwrap();
+    writer.process_writer_command();
+    let status = status.try_recv().unwrap();
+    assert_eq!(status.first_sequence_number, SequenceNumber::new(1));
+    assert_eq!(status.last_sequence_number, SequenceNumber::new(4));
+    assert_eq!(status.samples, 4);
+    assert_eq!(status.bytes, 4 * (4 + 8)); // with encapsulation headers
+    assert_eq!(status.unacknowledged.get(&reader_guid), Some(&2));
+
+    // Samples 1 and 2 are acknowledged
+    let (reply, removed) = std::sync::mpsc::channel();
+    channels
+      .command_sender
+      .send(WriterCommand::PurgeHistory {
+        acknowledged_only: true,
+        reply,
+      })
+      .unwrap();
+    writer.process_writer_command();
+    assert_eq!(removed.try_recv(), Ok(2));
+    assert_eq!(writer.history_status().samples, 2);
+    assert_eq!(
+      writer.history_status().first_sequence_number,
+      SequenceNumber::new(3)
+    );
+
+    let (reply, removed) = std::sync::mpsc::channel();
+    channels
+      .command_sender
+      .send(WriterCommand::PurgeHistory {
+        acknowledged_only: false,
+        reply,
+      })
+      .unwrap();
+    writer.process_writer_command();
+    assert_eq!(removed.try_recv(), Ok(2));
+    let status = writer.history_status();
+    assert_eq!(status.samples, 0);
+    assert_eq!(status.bytes, 0);
+    assert_eq!(status.first_sequence_number, SequenceNumber::new(5));
+  }
+
+  #[test]
+  fn ack_wait_ends_unsuccessfully_at_timeout() {
+    let reliable = QosPolicies::builder()
+      .reliability(policy::Reliability::Reliable {
+        max_blocking_time: Duration::ZERO,
+      })
+      .build();
+    let (mut writer, channels) = test_writer(
+      GUID::dummy_test_guid(EntityKind::WRITER_NO_KEY_USER_DEFINED),
+      reliable.clone(),
+    );
+
+    // A reliable Reader that never acknowledges anything
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let proxy = RtpsReaderProxy::new(reader_guid, reliable, false);
+    writer.readers.insert(reader_guid, proxy);
+
+    channels
+      .command_sender
+      .send(WriterCommand::DDSData {
+        ddsdata: DDSData::new(SerializedPayload::default()),
+        write_options: WriteOptions::default(),
+        sequence_number: SequenceNumber::new(1),
+        instance: None,
+      })
+      .unwrap();
+    let (all_acked, all_acked_receiver) = sync_status_channel(1).unwrap();
+    channels
+      .command_sender
+      .send(WriterCommand::WaitForAcknowledgments {
+        all_acked,
+        max_wait: Some(std::time::Duration::from_millis(10)),
+      })
+      .unwrap();
+    writer.process_writer_command();
+    assert!(all_acked_receiver.try_recv().is_err());
+
+    thread::sleep(std::time::Duration::from_millis(300));
+    writer.handle_timed_event();
+    assert!(matches!(all_acked_receiver.try_recv(), Ok(false)));
+  }
+
+  #[cfg(feature = "security")]
+  #[test]
+  fn receiver_specific_mac_batches_keep_participants_together() {
+    let reader = |participant: u8, reader: u8| {
+      GUID::new(
+        GuidPrefix::new(&[participant; 12]),
+        EntityId::create_custom_entity_id([0, 0, reader], EntityKind::READER_WITH_KEY_USER_DEFINED),
+      )
+    };
+    let readers = [
+      reader(1, 1),
+      reader(2, 1),
+      reader(1, 2),
+      reader(3, 1),
+      reader(3, 2),
+      reader(4, 1),
+      reader(4, 2),
+      reader(4, 3),
+      reader(4, 4),
+    ];
+
+    // Everything fits in one batch
+    assert_eq!(
+      receiver_specific_mac_batches(&readers, |r| *r, 100),
+      vec![{
+        let mut sorted = readers.to_vec();
+        sorted.sort();
+        sorted
+      }]
+    );
+
+    let batches = receiver_specific_mac_batches(&readers, |r| *r, 3);
+    assert_eq!(
+      batches,
+      vec![
+        vec![reader(1, 1), reader(1, 2), reader(2, 1)],
+        vec![reader(3, 1), reader(3, 2)],
+        // A participant with more readers than fit in a batch is split
+        vec![reader(4, 1), reader(4, 2), reader(4, 3)],
+        vec![reader(4, 4)],
+      ]
+    );
+  }
 }
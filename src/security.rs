@@ -1,11 +1,17 @@
 pub mod access_control;
+pub mod audit;
 pub mod authentication;
 mod certificate;
+pub mod certificate_store;
 pub mod config;
 pub mod cryptographic;
+pub mod decode_limits;
+pub mod error_counts;
 pub mod logging;
 mod private_key;
+pub(crate) mod protection_cache;
 pub mod security_plugins;
+pub mod startup;
 pub mod types;
 
 pub use types::*;
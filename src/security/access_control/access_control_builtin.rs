@@ -42,6 +42,12 @@ pub struct AccessControlBuiltin {
   permissions_handle_counter: u32,
 }
 
+impl Default for AccessControlBuiltin {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl AccessControl for AccessControlBuiltin {}
 
 impl AccessControlBuiltin {
@@ -167,7 +173,9 @@ impl AccessControlBuiltin {
       | builtin_topic_names::DCPS_PUBLICATIONS_SECURE
       | builtin_topic_names::DCPS_SUBSCRIPTION
       | builtin_topic_names::DCPS_SUBSCRIPTIONS_SECURE
-      | builtin_topic_names::DCPS_TOPIC => Ok(true),
+      | builtin_topic_names::DCPS_TOPIC
+      | builtin_topic_names::TYPE_LOOKUP_REQUEST
+      | builtin_topic_names::TYPE_LOOKUP_REPLY => Ok(true),
 
       // General case
       topic_name => {
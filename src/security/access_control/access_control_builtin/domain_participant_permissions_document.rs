@@ -345,7 +345,13 @@ impl Criterion {
     debug_assert!(!self.topics.is_empty());
 
     self.topics.iter().any(|glob| glob.matches(topic_name))
-      && partitions.all(|p| self.partitions.iter().any(|glob| glob.matches(p)))
+      && partitions.all(|p| {
+        if self.partitions.is_empty() {
+          p.is_empty()
+        } else {
+          self.partitions.iter().any(|glob| glob.matches(p))
+        }
+      })
       && data_tags.all(|(name, value)| self.data_tags.iter().any(|dt| dt.check(name, value)))
   }
 
@@ -792,4 +798,50 @@ mod tests {
 
     println!("{:?}", grant);
   }
+
+  #[test]
+  pub fn partition_criteria() {
+    let domain_participant_permissions_xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<dds>
+  <permissions>
+    <grant name="PartitionPermission">
+      <subject_name>CN=some_subject</subject_name>
+      <validity>
+        <not_before>2013-10-26T00:00:00Z</not_before>
+        <not_after>2038-10-26T22:45:30Z</not_after>
+      </validity>
+      <allow_rule>
+        <domains><id>0</id></domains>
+        <publish>
+          <topics><topic>Square</topic></topics>
+        </publish>
+        <subscribe>
+          <topics><topic>Square</topic></topics>
+          <partitions><partition>P*</partition></partitions>
+        </subscribe>
+      </allow_rule>
+      <default>DENY</default>
+    </grant>
+  </permissions>
+</dds>
+"#;
+    let dpd = DomainParticipantPermissions::from_xml(domain_participant_permissions_xml).unwrap();
+    let grant = dpd
+      .find_grant(
+        &DistinguishedName::parse("CN=some_subject").unwrap(),
+        &chrono::Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+      )
+      .unwrap();
+    let allowed = |action, partitions: &[&str]| {
+      bool::from(grant.check_action(action, 0, "Square", partitions, &[]))
+    };
+
+    // Without partitions in the criteria, only the default partition matches
+    assert!(allowed(Action::Publish, &[""]));
+    assert!(!allowed(Action::Publish, &["P1"]));
+    // All partitions of the entity must match
+    assert!(allowed(Action::Subscribe, &["P1", "P2"]));
+    assert!(!allowed(Action::Subscribe, &["P1", "Q"]));
+    assert!(!allowed(Action::Subscribe, &[""]));
+  }
 }
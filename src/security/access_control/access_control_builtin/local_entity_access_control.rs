@@ -9,66 +9,35 @@ use super::{
   types::{BuiltinPluginEndpointSecurityAttributes, Entity},
 };
 
-impl AccessControlBuiltin {
-  fn get_endpoint_security_attributes(
-    &self,
-    permissions_handle: PermissionsHandle,
-    topic_name: &str,
-  ) -> SecurityResult<EndpointSecurityAttributes> {
-    // Special handling for builtin topics
+// How the endpoints of a builtin topic are protected. Governance sets the
+// discovery and liveliness protection separately from each other, and from
+// the RTPS protection, which applies to whole messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum BuiltinTopicProtection {
+  // Secure SPDP and SEDP
+  Discovery,
+  // Secure ParticipantMessage, i.e. writer liveliness
+  Liveliness,
+  // ParticipantVolatileMessageSecure, which exchanges the keys
+  KeyExchange,
+  Unprotected,
+}
+
+impl BuiltinTopicProtection {
+  // None for topics that are not builtin
+  pub(super) fn of(topic_name: &str) -> Option<Self> {
     match topic_name {
       // 7.4.8: is_submessage_protected shall match is_discovery_protected of the participant
       // security attributes
       builtin_topic_names::DCPS_PARTICIPANT_SECURE
       | builtin_topic_names::DCPS_PUBLICATIONS_SECURE
-      | builtin_topic_names::DCPS_SUBSCRIPTIONS_SECURE => {
-        self.get_domain_rule(&permissions_handle).map(
-          |DomainRule {
-             discovery_protection_kind,
-             ..
-           }| {
-            let (
-              is_submessage_protected,
-              is_submessage_encrypted,
-              is_submessage_origin_authenticated,
-            ) = discovery_protection_kind.to_security_attributes_format();
+      | builtin_topic_names::DCPS_SUBSCRIPTIONS_SECURE => Some(Self::Discovery),
 
-            EndpointSecurityAttributes::for_builtin_topic(
-              is_submessage_protected,
-              is_submessage_encrypted,
-              is_submessage_origin_authenticated,
-            )
-          },
-        )
-      }
       // 7.4.8: is_submessage_protected shall match is_liveliness_protected of the participant
       // security attributes
-      builtin_topic_names::DCPS_PARTICIPANT_MESSAGE_SECURE => {
-        self.get_domain_rule(&permissions_handle).map(
-          |DomainRule {
-             liveliness_protection_kind,
-             ..
-           }| {
-            let (
-              is_submessage_protected,
-              is_submessage_encrypted,
-              is_submessage_origin_authenticated,
-            ) = liveliness_protection_kind.to_security_attributes_format();
-
-            EndpointSecurityAttributes::for_builtin_topic(
-              is_submessage_protected,
-              is_submessage_encrypted,
-              is_submessage_origin_authenticated,
-            )
-          },
-        )
-      }
+      builtin_topic_names::DCPS_PARTICIPANT_MESSAGE_SECURE => Some(Self::Liveliness),
 
-      // This topic is for sharing keys. A unique encryption key is used for each receiver, so no
-      // additional origin authentication is needed.
-      builtin_topic_names::DCPS_PARTICIPANT_VOLATILE_MESSAGE_SECURE => Ok(
-        EndpointSecurityAttributes::for_builtin_topic(true, true, false),
-      ),
+      builtin_topic_names::DCPS_PARTICIPANT_VOLATILE_MESSAGE_SECURE => Some(Self::KeyExchange),
 
       // 7.4.8 for stateless, the others are used for normal unprotected discovery
       builtin_topic_names::DCPS_PARTICIPANT_STATELESS_MESSAGE
@@ -76,10 +45,48 @@ impl AccessControlBuiltin {
       | builtin_topic_names::DCPS_PARTICIPANT_MESSAGE
       | builtin_topic_names::DCPS_PUBLICATION
       | builtin_topic_names::DCPS_SUBSCRIPTION
-      | builtin_topic_names::DCPS_TOPIC => Ok(EndpointSecurityAttributes::empty()),
+      | builtin_topic_names::DCPS_TOPIC
+      | builtin_topic_names::TYPE_LOOKUP_REQUEST
+      | builtin_topic_names::TYPE_LOOKUP_REPLY => Some(Self::Unprotected),
+
+      _ => None,
+    }
+  }
+
+  pub(super) fn security_attributes(self, domain_rule: &DomainRule) -> EndpointSecurityAttributes {
+    let protection_kind = match self {
+      Self::Discovery => domain_rule.discovery_protection_kind,
+      Self::Liveliness => domain_rule.liveliness_protection_kind,
+      // This topic is for sharing keys. A unique encryption key is used for each receiver, so no
+      // additional origin authentication is needed.
+      Self::KeyExchange => return EndpointSecurityAttributes::for_builtin_topic(true, true, false),
+      Self::Unprotected => return EndpointSecurityAttributes::empty(),
+    };
+    let (is_submessage_protected, is_submessage_encrypted, is_submessage_origin_authenticated) =
+      protection_kind.to_security_attributes_format();
+    EndpointSecurityAttributes::for_builtin_topic(
+      is_submessage_protected,
+      is_submessage_encrypted,
+      is_submessage_origin_authenticated,
+    )
+  }
+}
+
+impl AccessControlBuiltin {
+  fn get_endpoint_security_attributes(
+    &self,
+    permissions_handle: PermissionsHandle,
+    topic_name: &str,
+  ) -> SecurityResult<EndpointSecurityAttributes> {
+    match BuiltinTopicProtection::of(topic_name) {
+      // Unprotected builtin topics do not depend on the governance
+      Some(BuiltinTopicProtection::Unprotected) => Ok(EndpointSecurityAttributes::empty()),
+      Some(protection) => self
+        .get_domain_rule(&permissions_handle)
+        .map(|domain_rule| protection.security_attributes(domain_rule)),
 
       // General case
-      topic_name => self
+      None => self
         .get_domain_rule(&permissions_handle)
         .and_then(|domain_rule| {
           domain_rule.find_topic_rule(topic_name).ok_or_else(|| {
@@ -135,15 +142,15 @@ impl LocalEntityAccessControl for AccessControlBuiltin {
     permissions_handle: PermissionsHandle,
     domain_id: u16,
     topic_name: String,
-    _qos: &QosPolicies,
+    qos: &QosPolicies,
   ) -> SecurityResult<bool> {
-    let partitions = &[]; // Partitions currently unsupported. TODO: get from PartitionQosPolicy
+    let partition = qos.partition().unwrap_or_default();
     let data_tags = &[]; // Data tagging currently unsupported. TODO: get from DataTagQosPolicy
     self.check_entity(
       permissions_handle,
       domain_id,
       &topic_name,
-      partitions,
+      &partition.names(),
       data_tags,
       &Entity::Datawriter,
     )
@@ -154,15 +161,15 @@ impl LocalEntityAccessControl for AccessControlBuiltin {
     permissions_handle: PermissionsHandle,
     domain_id: u16,
     topic_name: String,
-    _qos: &QosPolicies,
+    qos: &QosPolicies,
   ) -> SecurityResult<bool> {
-    let partitions = &[]; // Partitions currently unsupported. TODO: get from PartitionQosPolicy
+    let partition = qos.partition().unwrap_or_default();
     let data_tags = &[]; // Data tagging currently unsupported. TODO: get from DataTagQosPolicy
     self.check_entity(
       permissions_handle,
       domain_id,
       &topic_name,
-      partitions,
+      &partition.names(),
       data_tags,
       &Entity::Datareader,
     )
@@ -175,7 +182,7 @@ impl LocalEntityAccessControl for AccessControlBuiltin {
     topic_name: String,
     _qos: &QosPolicies,
   ) -> SecurityResult<bool> {
-    let partitions = &[]; // Partitions currently unsupported. TODO: get from PartitionQosPolicy
+    let partitions = &[]; // Topics are not in partitions
     let data_tags = &[]; // Data tagging currently unsupported. TODO: get from DataTagQosPolicy
     self.check_entity(
       permissions_handle,
@@ -250,3 +257,209 @@ impl LocalEntityAccessControl for AccessControlBuiltin {
     self.get_endpoint_security_attributes(permissions_handle, &topic_name)
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    super::{
+      domain_governance_document::ProtectionKind,
+      participant_access_control::participant_security_attributes,
+    },
+    *,
+  };
+
+  const PROTECTION_KINDS: [ProtectionKind; 5] = [
+    ProtectionKind::None,
+    ProtectionKind::Sign,
+    ProtectionKind::Encrypt,
+    ProtectionKind::SignWithOriginAuthentication,
+    ProtectionKind::EncryptWithOriginAuthentication,
+  ];
+
+  fn domain_rule(
+    discovery_protection_kind: ProtectionKind,
+    liveliness_protection_kind: ProtectionKind,
+    rtps_protection_kind: ProtectionKind,
+  ) -> DomainRule {
+    DomainRule {
+      domains: Vec::new(),
+      allow_unauthenticated_participants: false,
+      enable_join_access_control: true,
+      discovery_protection_kind,
+      liveliness_protection_kind,
+      rtps_protection_kind,
+      topic_access_rules: Vec::new(),
+    }
+  }
+
+  // The masks that are advertised in discovery for the endpoints of a builtin
+  // topic, as raw bits
+  fn builtin_endpoint_masks(rule: &DomainRule, topic_name: &str) -> (u32, u32) {
+    let attributes = BuiltinTopicProtection::of(topic_name)
+      .unwrap()
+      .security_attributes(rule);
+    let plugin_mask = attributes.plugin_endpoint_attributes.0;
+    (
+      EndpointSecurityAttributesMask::from(attributes).0.bits(),
+      plugin_mask,
+    )
+  }
+
+  fn participant_masks(rule: &DomainRule) -> (u32, u32) {
+    let attributes = participant_security_attributes(rule);
+    let plugin_mask = attributes.plugin_participant_attributes.0;
+    (
+      ParticipantSecurityAttributesMask::from(attributes).0.bits(),
+      plugin_mask,
+    )
+  }
+
+  #[test]
+  fn builtin_topics_follow_their_own_protection_kind() {
+    for discovery in PROTECTION_KINDS {
+      for liveliness in PROTECTION_KINDS {
+        for rtps in PROTECTION_KINDS {
+          let rule = domain_rule(discovery, liveliness, rtps);
+          // Each builtin topic is protected as if the other kinds were NONE
+          for topic_name in [
+            builtin_topic_names::DCPS_PARTICIPANT_SECURE,
+            builtin_topic_names::DCPS_PUBLICATIONS_SECURE,
+            builtin_topic_names::DCPS_SUBSCRIPTIONS_SECURE,
+          ] {
+            let alone = domain_rule(discovery, ProtectionKind::None, ProtectionKind::None);
+            assert_eq!(
+              builtin_endpoint_masks(&rule, topic_name),
+              builtin_endpoint_masks(&alone, topic_name),
+            );
+          }
+          let alone = domain_rule(ProtectionKind::None, liveliness, ProtectionKind::None);
+          assert_eq!(
+            builtin_endpoint_masks(&rule, builtin_topic_names::DCPS_PARTICIPANT_MESSAGE_SECURE),
+            builtin_endpoint_masks(&alone, builtin_topic_names::DCPS_PARTICIPANT_MESSAGE_SECURE),
+          );
+
+          // The volatile and unprotected topics do not depend on the governance
+          let unprotected = domain_rule(
+            ProtectionKind::None,
+            ProtectionKind::None,
+            ProtectionKind::None,
+          );
+          for topic_name in [
+            builtin_topic_names::DCPS_PARTICIPANT_VOLATILE_MESSAGE_SECURE,
+            builtin_topic_names::DCPS_PARTICIPANT_STATELESS_MESSAGE,
+            builtin_topic_names::DCPS_PARTICIPANT,
+            builtin_topic_names::DCPS_PUBLICATION,
+            builtin_topic_names::DCPS_SUBSCRIPTION,
+            builtin_topic_names::DCPS_PARTICIPANT_MESSAGE,
+          ] {
+            assert_eq!(
+              builtin_endpoint_masks(&rule, topic_name),
+              builtin_endpoint_masks(&unprotected, topic_name),
+            );
+          }
+
+          let participant = participant_security_attributes(&rule);
+          assert_eq!(
+            participant.is_discovery_protected,
+            discovery != ProtectionKind::None
+          );
+          assert_eq!(
+            participant.is_liveliness_protected,
+            liveliness != ProtectionKind::None
+          );
+          assert_eq!(participant.is_rtps_protected, rtps != ProtectionKind::None);
+        }
+      }
+    }
+    assert_eq!(BuiltinTopicProtection::of("Square"), None);
+  }
+
+  // The bits of the masks are defined in sections 8.4.2.5, 8.4.2.8, 9.4.2.4
+  // and 9.4.2.6 of the Security specification (v. 1.1). Other implementations
+  // decide from them how to protect the traffic to us, so they must not
+  // change.
+  #[test]
+  fn advertised_masks_match_specification() {
+    let none = ProtectionKind::None;
+    let sedp = builtin_topic_names::DCPS_PUBLICATIONS_SECURE;
+    // (governance kind, endpoint mask, plugin endpoint mask)
+    let builtin_endpoint_vectors = [
+      (ProtectionKind::None, 0x8000_0000, 0x8000_0000),
+      (ProtectionKind::Sign, 0x8000_0008, 0x8000_0000),
+      (ProtectionKind::Encrypt, 0x8000_0008, 0x8000_0001),
+      (
+        ProtectionKind::SignWithOriginAuthentication,
+        0x8000_0008,
+        0x8000_0004,
+      ),
+      (
+        ProtectionKind::EncryptWithOriginAuthentication,
+        0x8000_0008,
+        0x8000_0005,
+      ),
+    ];
+    for (kind, endpoint_mask, plugin_mask) in builtin_endpoint_vectors {
+      assert_eq!(
+        builtin_endpoint_masks(&domain_rule(kind, none, none), sedp),
+        (endpoint_mask, plugin_mask),
+        "discovery protection {kind:?}"
+      );
+      assert_eq!(
+        builtin_endpoint_masks(
+          &domain_rule(none, kind, none),
+          builtin_topic_names::DCPS_PARTICIPANT_MESSAGE_SECURE
+        ),
+        (endpoint_mask, plugin_mask),
+        "liveliness protection {kind:?}"
+      );
+    }
+    assert_eq!(
+      builtin_endpoint_masks(
+        &domain_rule(none, none, none),
+        builtin_topic_names::DCPS_PARTICIPANT_VOLATILE_MESSAGE_SECURE
+      ),
+      (0x8000_0008, 0x8000_0001)
+    );
+
+    // (discovery, liveliness, rtps, participant mask, plugin participant mask)
+    let participant_vectors = [
+      (none, none, none, 0x8000_0000, 0x8000_0000),
+      (
+        ProtectionKind::Encrypt,
+        none,
+        none,
+        0x8000_0002,
+        0x8000_0002,
+      ),
+      (none, ProtectionKind::Sign, none, 0x8000_0004, 0x8000_0000),
+      (
+        none,
+        none,
+        ProtectionKind::SignWithOriginAuthentication,
+        0x8000_0001,
+        0x8000_0008,
+      ),
+      (
+        ProtectionKind::EncryptWithOriginAuthentication,
+        ProtectionKind::EncryptWithOriginAuthentication,
+        ProtectionKind::EncryptWithOriginAuthentication,
+        0x8000_0007,
+        0x8000_003F,
+      ),
+      (
+        ProtectionKind::Sign,
+        ProtectionKind::Encrypt,
+        ProtectionKind::EncryptWithOriginAuthentication,
+        0x8000_0007,
+        0x8000_000D,
+      ),
+    ];
+    for (discovery, liveliness, rtps, participant_mask, plugin_mask) in participant_vectors {
+      assert_eq!(
+        participant_masks(&domain_rule(discovery, liveliness, rtps)),
+        (participant_mask, plugin_mask),
+        "protection {discovery:?}, {liveliness:?}, {rtps:?}"
+      );
+    }
+  }
+}
@@ -349,47 +349,55 @@ impl ParticipantAccessControl for AccessControlBuiltin {
   }
 
   fn set_listener(&self) -> SecurityResult<()> {
-    todo!();
+    Err(create_security_error_and_log!(
+      "set_listener not supported. Use status events in DataReader/DataWriter instead."
+    ))
   }
 
   fn get_participant_sec_attributes(
     &self,
     permissions_handle: PermissionsHandle,
   ) -> SecurityResult<ParticipantSecurityAttributes> {
-    self.get_domain_rule(&permissions_handle).map(
-      |DomainRule {
-         allow_unauthenticated_participants,
-         enable_join_access_control,
-         discovery_protection_kind,
-         liveliness_protection_kind,
-         rtps_protection_kind,
-         ..
-       }| {
-        let (is_rtps_protected, is_rtps_encrypted, is_rtps_origin_authenticated) =
-          rtps_protection_kind.to_security_attributes_format();
-        let (is_discovery_protected, is_discovery_encrypted, is_discovery_origin_authenticated) =
-          discovery_protection_kind.to_security_attributes_format();
-        let (is_liveliness_protected, is_liveliness_encrypted, is_liveliness_origin_authenticated) =
-          liveliness_protection_kind.to_security_attributes_format();
-
-        ParticipantSecurityAttributes {
-          allow_unauthenticated_participants: *allow_unauthenticated_participants,
-          is_access_protected: *enable_join_access_control,
-          is_discovery_protected,
-          is_liveliness_protected,
-          is_rtps_protected,
-          plugin_participant_attributes: BuiltinPluginParticipantSecurityAttributes {
-            is_discovery_encrypted,
-            is_discovery_origin_authenticated,
-            is_liveliness_encrypted,
-            is_liveliness_origin_authenticated,
-            is_rtps_encrypted,
-            is_rtps_origin_authenticated,
-          }
-          .into(),
-          ac_participant_properties: Vec::new(),
-        }
-      },
-    )
+    self
+      .get_domain_rule(&permissions_handle)
+      .map(participant_security_attributes)
+  }
+}
+
+// The discovery, liveliness and RTPS protection kinds of the governance are
+// applied separately of each other.
+pub(super) fn participant_security_attributes(
+  DomainRule {
+    allow_unauthenticated_participants,
+    enable_join_access_control,
+    discovery_protection_kind,
+    liveliness_protection_kind,
+    rtps_protection_kind,
+    ..
+  }: &DomainRule,
+) -> ParticipantSecurityAttributes {
+  let (is_rtps_protected, is_rtps_encrypted, is_rtps_origin_authenticated) =
+    rtps_protection_kind.to_security_attributes_format();
+  let (is_discovery_protected, is_discovery_encrypted, is_discovery_origin_authenticated) =
+    discovery_protection_kind.to_security_attributes_format();
+  let (is_liveliness_protected, is_liveliness_encrypted, is_liveliness_origin_authenticated) =
+    liveliness_protection_kind.to_security_attributes_format();
+
+  ParticipantSecurityAttributes {
+    allow_unauthenticated_participants: *allow_unauthenticated_participants,
+    is_access_protected: *enable_join_access_control,
+    is_discovery_protected,
+    is_liveliness_protected,
+    is_rtps_protected,
+    plugin_participant_attributes: BuiltinPluginParticipantSecurityAttributes {
+      is_discovery_encrypted,
+      is_discovery_origin_authenticated,
+      is_liveliness_encrypted,
+      is_liveliness_origin_authenticated,
+      is_rtps_encrypted,
+      is_rtps_origin_authenticated,
+    }
+    .into(),
+    ac_participant_properties: Vec::new(),
   }
 }
@@ -19,17 +19,17 @@ impl RemoteEntityAccessControl for AccessControlBuiltin {
     domain_id: u16,
     publication_data: &PublicationBuiltinTopicDataSecure,
   ) -> SecurityResult<bool> {
-    let partitions = &[]; // Partitions currently unsupported. TODO: get from publication_data
     let data_tags = &[]; // Data tagging currently unsupported. TODO: get from publication_data
 
     let PublicationBuiltinTopicDataSecure {
       discovered_writer_data:
         DiscoveredWriterData {
-          publication_topic_data: PublicationBuiltinTopicData { topic_name, .. },
+          publication_topic_data: topic_data @ PublicationBuiltinTopicData { topic_name, .. },
           ..
         },
       ..
     } = publication_data;
+    let partition = topic_data.qos().partition().unwrap_or_default();
 
     // Move the following check to validate_remote_permissions from check_remote_
     // methods, as there we have access to the tokens: "If the PluginClassName
@@ -40,7 +40,7 @@ impl RemoteEntityAccessControl for AccessControlBuiltin {
       permissions_handle,
       domain_id,
       topic_name,
-      partitions,
+      &partition.names(),
       data_tags,
       &Entity::Datawriter,
     )
@@ -53,17 +53,18 @@ impl RemoteEntityAccessControl for AccessControlBuiltin {
     domain_id: u16,
     subscription_data: &SubscriptionBuiltinTopicDataSecure,
   ) -> SecurityResult<(bool, bool)> {
-    let partitions = &[]; // Partitions currently unsupported. TODO: get from publication_data
-    let data_tags = &[]; // Data tagging currently unsupported. TODO: get from publication_data
+    let data_tags = &[]; // Data tagging currently unsupported. TODO: get from subscription_data
 
     let SubscriptionBuiltinTopicDataSecure {
       discovered_reader_data:
         DiscoveredReaderData {
-          subscription_topic_data: SubscriptionBuiltinTopicData { topic_name, .. },
+          subscription_topic_data: topic_data @ SubscriptionBuiltinTopicData { topic_name, .. },
           ..
         },
       ..
     } = subscription_data;
+    let partition = topic_data.qos().partition().unwrap_or_default();
+    let partitions = &partition.names();
 
     // This method differs from the other similar ones because of the possibility of
     // a relay only datareader
@@ -119,8 +120,8 @@ impl RemoteEntityAccessControl for AccessControlBuiltin {
     domain_id: u16,
     topic_data: &TopicBuiltinTopicData,
   ) -> SecurityResult<bool> {
-    let partitions = &[]; // Partitions currently unsupported. TODO: get from publication_data
-    let data_tags = &[]; // Data tagging currently unsupported. TODO: get from publication_data
+    let partitions = &[]; // Topics are not in partitions
+    let data_tags = &[]; // Data tagging currently unsupported. TODO: get from topic_data
 
     let TopicBuiltinTopicData { name, .. } = topic_data;
 
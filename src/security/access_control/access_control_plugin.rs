@@ -21,7 +21,7 @@ use super::*;
 /// We split the plugin interface to 3 parts according to the 5 groups described
 /// in 8.8.3
 pub trait AccessControl:
-  ParticipantAccessControl + LocalEntityAccessControl + RemoteEntityAccessControl
+  ParticipantAccessControl + LocalEntityAccessControl + RemoteEntityAccessControl + Sync
 {
 }
 
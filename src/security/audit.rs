@@ -0,0 +1,465 @@
+//! Audit trail of authentication handshakes.
+//!
+//! When a [`HandshakeAudit`] is given to
+//! [`DomainParticipantBuilder::handshake_audit`](crate::DomainParticipantBuilder::handshake_audit),
+//! the security plugins record, for each remote participant, the handshake
+//! tokens exchanged, the negotiated algorithms, and the outcome of remote
+//! permissions validation. The application keeps a clone of the audit handle
+//! to query the transcripts, and may export them to a file, optionally
+//! encrypted with AES-256-GCM.
+//!
+//! Note that the handshake tokens contain the identity certificates and the
+//! signed permissions documents of both participants, but no private keys or
+//! shared secrets.
+
+use std::{
+  collections::BTreeMap,
+  fs, io,
+  path::Path,
+  sync::{Arc, Mutex},
+};
+
+use ring::{
+  aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+  rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use speedy::Endianness;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  serialization::{from_bytes_with_endianness, to_vec_with_endianness},
+  structure::guid::GuidPrefix,
+  Timestamp,
+};
+use super::{
+  access_control::PermissionsToken,
+  authentication::{AuthenticatedPeerCredentialToken, HandshakeMessageToken, ValidationOutcome},
+  DataHolder, SecurityError,
+};
+
+// Export file layout: MAGIC, format version, encryption flag, and then either
+// the plain CDR payload, or nonce + AES-256-GCM sealed payload (tag last).
+const EXPORT_MAGIC: &[u8; 4] = b"RHSA";
+const EXPORT_VERSION: u8 = 1;
+const EXPORT_PLAIN: u8 = 0;
+const EXPORT_AES_256_GCM: u8 = 1;
+const EXPORT_HEADER_LEN: usize = 6;
+
+/// Length of the key for encrypted export, in bytes.
+pub const AUDIT_EXPORT_KEY_LENGTH: usize = 32;
+
+/// Was the handshake message sent or received by the local participant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HandshakeDirection {
+  Sent,
+  Received,
+}
+
+/// Contents of a security token, e.g. a HandshakeMessageToken.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditedToken {
+  pub class_id: String,
+  pub properties: Vec<(String, String)>,
+  pub binary_properties: Vec<(String, Vec<u8>)>,
+}
+
+impl AuditedToken {
+  pub fn binary_property(&self, name: &str) -> Option<&[u8]> {
+    self
+      .binary_properties
+      .iter()
+      .find(|(n, _)| n == name)
+      .map(|(_, value)| value.as_slice())
+  }
+
+  fn binary_property_string(&self, name: &str) -> Option<String> {
+    self
+      .binary_property(name)
+      .map(|value| String::from_utf8_lossy(value).into_owned())
+  }
+}
+
+impl From<&DataHolder> for AuditedToken {
+  fn from(dh: &DataHolder) -> Self {
+    Self {
+      class_id: dh.class_id.clone(),
+      properties: dh
+        .properties
+        .iter()
+        .map(|p| (p.name.clone(), p.value.clone()))
+        .collect(),
+      binary_properties: dh
+        .binary_properties
+        .iter()
+        .map(|bp| (bp.name.clone(), bp.value.to_vec()))
+        .collect(),
+    }
+  }
+}
+
+/// One handshake message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandshakeAuditRecord {
+  pub timestamp: Timestamp,
+  pub direction: HandshakeDirection,
+  pub token: AuditedToken,
+}
+
+/// Result of validating the permissions of a remote participant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermissionsAuditRecord {
+  pub timestamp: Timestamp,
+  pub permissions_token: AuditedToken,
+  pub peer_credential_token: AuditedToken,
+  /// `None` if permissions were granted, otherwise the reason for rejection.
+  pub rejection: Option<String>,
+}
+
+impl PermissionsAuditRecord {
+  pub fn is_granted(&self) -> bool {
+    self.rejection.is_none()
+  }
+
+  /// The signed permissions document of the remote participant, as carried in
+  /// the peer credential token.
+  pub fn permissions_document(&self) -> Option<String> {
+    self.peer_credential_token.binary_property_string("c.perm")
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HandshakeAuditStatus {
+  InProgress,
+  Authenticated,
+  Failed,
+}
+
+/// Everything recorded about authenticating one remote participant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandshakeTranscript {
+  pub remote_guid_prefix: GuidPrefix,
+  pub status: HandshakeAuditStatus,
+  pub messages: Vec<HandshakeAuditRecord>,
+  /// Signature algorithm announced by the local participant
+  pub local_dsign_algo: Option<String>,
+  /// Signature algorithm announced by the remote participant
+  pub remote_dsign_algo: Option<String>,
+  /// Key agreement algorithm chosen by the handshake initiator
+  pub kagree_algo: Option<String>,
+  /// Failures reported by the authentication plugin, in order.
+  pub errors: Vec<String>,
+  pub permissions: Vec<PermissionsAuditRecord>,
+}
+
+impl HandshakeTranscript {
+  fn new(remote_guid_prefix: GuidPrefix) -> Self {
+    Self {
+      remote_guid_prefix,
+      status: HandshakeAuditStatus::InProgress,
+      messages: Vec::new(),
+      local_dsign_algo: None,
+      remote_dsign_algo: None,
+      kagree_algo: None,
+      errors: Vec::new(),
+      permissions: Vec::new(),
+    }
+  }
+
+  /// Outcome of the latest permissions validation, if any.
+  pub fn permissions_granted(&self) -> Option<bool> {
+    self
+      .permissions
+      .last()
+      .map(PermissionsAuditRecord::is_granted)
+  }
+}
+
+/// In-memory store of handshake transcripts, keyed by remote participant.
+///
+/// This is a cheaply cloneable handle. All clones refer to the same store.
+#[derive(Clone, Default)]
+pub struct HandshakeAudit {
+  transcripts: Arc<Mutex<BTreeMap<GuidPrefix, HandshakeTranscript>>>,
+}
+
+impl HandshakeAudit {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn remote_participants(&self) -> Vec<GuidPrefix> {
+    self.transcripts.lock().unwrap().keys().copied().collect()
+  }
+
+  pub fn transcript(&self, remote_guid_prefix: &GuidPrefix) -> Option<HandshakeTranscript> {
+    self
+      .transcripts
+      .lock()
+      .unwrap()
+      .get(remote_guid_prefix)
+      .cloned()
+  }
+
+  pub fn transcripts(&self) -> Vec<HandshakeTranscript> {
+    self.transcripts.lock().unwrap().values().cloned().collect()
+  }
+
+  /// Forget all recorded transcripts.
+  pub fn clear(&self) {
+    self.transcripts.lock().unwrap().clear();
+  }
+
+  /// Write all transcripts to a file. If `key` is given, the file contents are
+  /// encrypted with AES-256-GCM using that key.
+  pub fn export_to_file(
+    &self,
+    path: impl AsRef<Path>,
+    key: Option<&[u8; AUDIT_EXPORT_KEY_LENGTH]>,
+  ) -> io::Result<()> {
+    let bytes = export_bytes(&self.transcripts(), key)?;
+    fs::write(path, bytes)
+  }
+
+  /// Read transcripts from a file written by
+  /// [`export_to_file`](Self::export_to_file). The same `key` must be given.
+  pub fn read_export(
+    path: impl AsRef<Path>,
+    key: Option<&[u8; AUDIT_EXPORT_KEY_LENGTH]>,
+  ) -> io::Result<Vec<HandshakeTranscript>> {
+    import_bytes(&fs::read(path)?, key)
+  }
+
+  fn update(&self, remote_guid_prefix: GuidPrefix, f: impl FnOnce(&mut HandshakeTranscript)) {
+    let mut transcripts = self.transcripts.lock().unwrap();
+    f(transcripts
+      .entry(remote_guid_prefix)
+      .or_insert_with(|| HandshakeTranscript::new(remote_guid_prefix)));
+  }
+
+  pub(crate) fn record_message(
+    &self,
+    remote_guid_prefix: GuidPrefix,
+    direction: HandshakeDirection,
+    token: &HandshakeMessageToken,
+  ) {
+    let token = AuditedToken::from(&token.data_holder);
+    self.update(remote_guid_prefix, |transcript| {
+      if let Some(algo) = token.binary_property_string("c.dsign_algo") {
+        match direction {
+          HandshakeDirection::Sent => transcript.local_dsign_algo = Some(algo),
+          HandshakeDirection::Received => transcript.remote_dsign_algo = Some(algo),
+        }
+      }
+      if let Some(algo) = token.binary_property_string("c.kagree_algo") {
+        transcript.kagree_algo = Some(algo);
+      }
+      transcript.messages.push(HandshakeAuditRecord {
+        timestamp: Timestamp::now(),
+        direction,
+        token,
+      });
+    });
+  }
+
+  pub(crate) fn record_outcome(
+    &self,
+    remote_guid_prefix: GuidPrefix,
+    outcome: Result<&ValidationOutcome, &SecurityError>,
+  ) {
+    self.update(remote_guid_prefix, |transcript| match outcome {
+      Ok(ValidationOutcome::Ok) | Ok(ValidationOutcome::OkFinalMessage) => {
+        transcript.status = HandshakeAuditStatus::Authenticated;
+      }
+      Ok(_) => {} // still pending
+      Err(e) => {
+        transcript.status = HandshakeAuditStatus::Failed;
+        transcript.errors.push(e.msg.clone());
+      }
+    });
+  }
+
+  pub(crate) fn record_permissions(
+    &self,
+    remote_guid_prefix: GuidPrefix,
+    permissions_token: &PermissionsToken,
+    peer_credential_token: &AuthenticatedPeerCredentialToken,
+    result: Result<(), &SecurityError>,
+  ) {
+    let record = PermissionsAuditRecord {
+      timestamp: Timestamp::now(),
+      permissions_token: AuditedToken::from(&permissions_token.data_holder),
+      peer_credential_token: AuditedToken::from(&peer_credential_token.data_holder),
+      rejection: result.err().map(|e| e.msg.clone()),
+    };
+    self.update(remote_guid_prefix, |transcript| {
+      transcript.permissions.push(record);
+    });
+  }
+}
+
+impl std::fmt::Debug for HandshakeAudit {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("HandshakeAudit")
+      .field("remote_participants", &self.remote_participants())
+      .finish()
+  }
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn aead_key(key: &[u8; AUDIT_EXPORT_KEY_LENGTH]) -> LessSafeKey {
+  // Cannot fail, because key length matches the algorithm
+  LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).unwrap())
+}
+
+fn export_bytes(
+  transcripts: &[HandshakeTranscript],
+  key: Option<&[u8; AUDIT_EXPORT_KEY_LENGTH]>,
+) -> io::Result<Vec<u8>> {
+  let mut payload = to_vec_with_endianness(&transcripts, Endianness::LittleEndian)
+    .map_err(|e| invalid_data(format!("Cannot serialize handshake audit: {e}")))?;
+
+  let mut bytes = Vec::with_capacity(EXPORT_HEADER_LEN + NONCE_LEN + payload.len() + 16);
+  bytes.extend_from_slice(EXPORT_MAGIC);
+  bytes.push(EXPORT_VERSION);
+  match key {
+    None => {
+      bytes.push(EXPORT_PLAIN);
+    }
+    Some(key) => {
+      bytes.push(EXPORT_AES_256_GCM);
+      let mut nonce = [0; NONCE_LEN];
+      SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "Cannot generate nonce"))?;
+      // The header is authenticated, but not encrypted.
+      aead_key(key)
+        .seal_in_place_append_tag(
+          Nonce::assume_unique_for_key(nonce),
+          Aad::from(&bytes[..]),
+          &mut payload,
+        )
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "Cannot encrypt handshake audit"))?;
+      bytes.extend_from_slice(&nonce);
+    }
+  }
+  bytes.extend_from_slice(&payload);
+  Ok(bytes)
+}
+
+fn import_bytes(
+  bytes: &[u8],
+  key: Option<&[u8; AUDIT_EXPORT_KEY_LENGTH]>,
+) -> io::Result<Vec<HandshakeTranscript>> {
+  if bytes.len() < EXPORT_HEADER_LEN || &bytes[..4] != EXPORT_MAGIC || bytes[4] != EXPORT_VERSION {
+    return Err(invalid_data("Not a handshake audit export"));
+  }
+  let (header, body) = bytes.split_at(EXPORT_HEADER_LEN);
+  let mut decrypted;
+  let payload = match (header[5], key) {
+    (EXPORT_PLAIN, None) => body,
+    (EXPORT_AES_256_GCM, Some(key)) => {
+      if body.len() < NONCE_LEN {
+        return Err(invalid_data("Truncated handshake audit export"));
+      }
+      let (nonce, sealed) = body.split_at(NONCE_LEN);
+      decrypted = sealed.to_vec();
+      aead_key(key)
+        .open_in_place(
+          Nonce::try_assume_unique_for_key(nonce).map_err(|_| invalid_data("Bad nonce"))?,
+          Aad::from(header),
+          &mut decrypted,
+        )
+        .map_err(|_| invalid_data("Cannot decrypt handshake audit: wrong key or corrupt file"))?
+    }
+    (EXPORT_PLAIN, Some(_)) => return Err(invalid_data("Handshake audit export is not encrypted")),
+    (EXPORT_AES_256_GCM, None) => return Err(invalid_data("Handshake audit export is encrypted")),
+    (other, _) => return Err(invalid_data(format!("Unknown encryption flag {other}"))),
+  };
+  from_bytes_with_endianness::<Vec<HandshakeTranscript>>(payload, Endianness::LittleEndian)
+    .map(|(transcripts, _len)| transcripts)
+    .map_err(|e| invalid_data(format!("Cannot deserialize handshake audit: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+  use bytes::Bytes;
+
+  use super::*;
+  use crate::security::{BinaryProperty, DataHolderBuilder};
+
+  fn handshake_token(class_id: &str, dsign_algo: &str) -> HandshakeMessageToken {
+    HandshakeMessageToken::from(
+      DataHolderBuilder::with_class_id(class_id.to_string())
+        .add_binary_property("c.dsign_algo", Bytes::from(dsign_algo.to_string()), true)
+        .add_binary_property(
+          "c.kagree_algo",
+          Bytes::from_static(b"ECDH+prime256v1-CEUM"),
+          true,
+        )
+        .build(),
+    )
+  }
+
+  #[test]
+  fn records_transcript_and_exports_encrypted() {
+    let audit = HandshakeAudit::new();
+    let remote = GuidPrefix::new(&[1; 12]);
+
+    audit.record_message(
+      remote,
+      HandshakeDirection::Sent,
+      &handshake_token("DDS:Auth:PKI-DH:1.0+Req", "ECDSA-SHA256"),
+    );
+    audit.record_outcome(remote, Ok(&ValidationOutcome::PendingHandshakeMessage));
+    audit.record_message(
+      remote,
+      HandshakeDirection::Received,
+      &handshake_token("DDS:Auth:PKI-DH:1.0+Reply", "RSASSA-PSS-SHA256"),
+    );
+    audit.record_outcome(remote, Ok(&ValidationOutcome::OkFinalMessage));
+    audit.record_permissions(
+      remote,
+      &PermissionsToken::from(DataHolder::dummy()),
+      &AuthenticatedPeerCredentialToken::from(DataHolder {
+        class_id: "DDS:Auth:PKI-DH:1.0".to_string(),
+        properties: vec![],
+        binary_properties: vec![BinaryProperty::with_propagate(
+          "c.perm",
+          Bytes::from_static(b"<permissions/>"),
+        )],
+      }),
+      Ok(()),
+    );
+
+    let transcript = audit.transcript(&remote).unwrap();
+    assert_eq!(transcript.status, HandshakeAuditStatus::Authenticated);
+    assert_eq!(transcript.messages.len(), 2);
+    assert_eq!(transcript.local_dsign_algo.as_deref(), Some("ECDSA-SHA256"));
+    assert_eq!(
+      transcript.remote_dsign_algo.as_deref(),
+      Some("RSASSA-PSS-SHA256")
+    );
+    assert_eq!(transcript.permissions_granted(), Some(true));
+    assert_eq!(
+      transcript.permissions[0].permissions_document().as_deref(),
+      Some("<permissions/>")
+    );
+
+    let key = [7; AUDIT_EXPORT_KEY_LENGTH];
+    let encrypted = export_bytes(&audit.transcripts(), Some(&key)).unwrap();
+    assert_eq!(
+      import_bytes(&encrypted, Some(&key)).unwrap(),
+      vec![transcript.clone()]
+    );
+    assert!(import_bytes(&encrypted, Some(&[8; AUDIT_EXPORT_KEY_LENGTH])).is_err());
+    assert!(import_bytes(&encrypted, None).is_err());
+
+    let plain = export_bytes(&audit.transcripts(), None).unwrap();
+    assert_eq!(import_bytes(&plain, None).unwrap(), vec![transcript]);
+  }
+}
@@ -10,8 +10,8 @@ use ring::agreement;
 use crate::{
   create_security_error_and_log,
   security::{
-    access_control::PermissionsToken, certificate, private_key, security_error, SecurityError,
-    SecurityResult,
+    access_control::PermissionsToken, certificate, certificate_store::CertificateStore,
+    private_key, security_error, SecurityError, SecurityResult,
   },
   GUID,
 };
@@ -91,9 +91,8 @@ struct LocalParticipantInfo {
   guid: GUID,
   id_cert_private_key: private_key::PrivateKey, // PrivateKey is actually (private,public) key pair
   identity_certificate: certificate::Certificate, // Certificate contains the public key also
-  identity_ca: certificate::Certificate,        /* Certification Authority who has signed
-                                                 * identity_certificate */
-  signed_permissions_document_xml: Bytes, // We do not care about UTF-8:ness anymore
+  certificate_store: CertificateStore,          // Validates identity certificates
+  signed_permissions_document_xml: Bytes,       // We do not care about UTF-8:ness anymore
   local_permissions_token: Option<PermissionsToken>,
 }
 
@@ -200,6 +199,12 @@ pub struct AuthenticationBuiltin {
   secure_random_generator: ring::rand::SystemRandom,
 }
 
+impl Default for AuthenticationBuiltin {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl AuthenticationBuiltin {
   pub fn new() -> Self {
     Self {
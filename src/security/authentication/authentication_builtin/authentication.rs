@@ -1,9 +1,9 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, time::Duration};
 
-use byteorder::BigEndian;
 use bytes::Bytes;
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
+use speedy::Endianness;
 
 use crate::{
   create_security_error_and_log, discovery,
@@ -20,18 +20,20 @@ use crate::{
       *,
     },
     certificate::*,
+    certificate_store::CertificateStore,
     config::*,
     *,
   },
-  serialization::{pl_cdr_adapters::PlCdrDeserialize, to_vec},
+  serialization::{pl_cdr_adapters::PlCdrDeserialize, to_vec_with_endianness},
   structure::guid::GuidPrefix,
   QosPolicies, RepresentationIdentifier, GUID,
 };
 use super::{
   types::{
     parse_signature_algo_name_to_ring, BuiltinAuthenticatedPeerCredentialToken,
-    BuiltinIdentityToken, DH_MODP_KAGREE_ALGO_NAME, ECDH_KAGREE_ALGO_NAME,
-    QOS_IDENTITY_CA_PROPERTY_NAME, QOS_IDENTITY_CERTIFICATE_PROPERTY_NAME,
+    BuiltinIdentityStatusToken, BuiltinIdentityToken, DH_MODP_KAGREE_ALGO_NAME,
+    ECDH_KAGREE_ALGO_NAME, QOS_CLOCK_SKEW_TOLERANCE_PROPERTY_NAME, QOS_IDENTITY_CA_PROPERTY_NAME,
+    QOS_IDENTITY_CERTIFICATE_PROPERTY_NAME, QOS_IDENTITY_CRL_PROPERTY_NAME,
     QOS_PASSWORD_PROPERTY_NAME, QOS_PRIVATE_KEY_PROPERTY_NAME,
   },
   BuiltinHandshakeState, DHKeys, LocalParticipantInfo, RemoteParticipantInfo,
@@ -79,6 +81,60 @@ fn validate_remote_guid(
   }
 }
 
+// The trust anchors, revocation lists and clock skew tolerance configured for
+// validating identity certificates
+fn certificate_store_from_qos(participant_qos: &QosPolicies) -> SecurityResult<CertificateStore> {
+  let mut store = CertificateStore::new();
+
+  let identity_ca_pem = participant_qos
+    .get_property(QOS_IDENTITY_CA_PROPERTY_NAME)
+    .and_then(|certificate_uri| {
+      read_uri(&certificate_uri).map_err(|conf_err| {
+        create_security_error_and_log!(
+          "Failed to read the identity CA certificate from {}: {:?}",
+          certificate_uri,
+          conf_err
+        )
+      })
+    })?;
+  store
+    .add_trust_anchors_pem(identity_ca_pem)
+    .map_err(|e| create_security_error_and_log!("{e:?}"))?;
+
+  if let Some(crl_uri) = participant_qos.get_optional_property(QOS_IDENTITY_CRL_PROPERTY_NAME) {
+    let crl_pem = read_uri(&crl_uri).map_err(|conf_err| {
+      create_security_error_and_log!(
+        "Failed to read the certificate revocation list from {}: {:?}",
+        crl_uri,
+        conf_err
+      )
+    })?;
+    store
+      .add_revocation_lists_pem(crl_pem)
+      .map_err(|e| create_security_error_and_log!("{e:?}"))?;
+  }
+
+  if let Some(tolerance) =
+    participant_qos.get_optional_property(QOS_CLOCK_SKEW_TOLERANCE_PROPERTY_NAME)
+  {
+    let seconds = tolerance.parse().map_err(|e| {
+      create_security_error_and_log!("Invalid clock skew tolerance {tolerance:?}: {e}")
+    })?;
+    store.set_clock_skew_tolerance(Duration::from_secs(seconds));
+  }
+
+  Ok(store)
+}
+
+// The identity certificate in a handshake message, and the CA certificates that
+// may follow it to complete its chain
+fn certificate_and_chain(c_id: &[u8]) -> SecurityResult<(Certificate, Vec<Certificate>)> {
+  let mut certificates = Certificate::from_pem_bundle(c_id)?;
+  // from_pem_bundle does not return an empty Vec
+  let certificate = certificates.remove(0);
+  Ok((certificate, certificates))
+}
+
 impl Authentication for AuthenticationBuiltin {
   fn validate_local_identity(
     &mut self,
@@ -107,32 +163,20 @@ impl Authentication for AuthenticationBuiltin {
     // If it does not pass, our identity certificate is useless,
     // because others would not accept it either.
     //
-    // (Should also check if the certificate has been revoked.
-    // The CA certificate may have a revocation list and/or check an on-line OCSP
-    // server.)
+    // The certificate is validated with a CertificateStore, which also checks
+    // its validity period and the configured revocation lists.
     //
     // The returned IdentityHandle must be capable of
     // * reading this participant's public key (from identity_certificate)
     // * performing verify and sign operations with this participant's private key
     // * accessing the participant GUID (candidate or adjusted??)
 
+    let certificate_store = certificate_store_from_qos(participant_qos)?;
+    // Our IdentityToken names the first identity CA
+    let identity_ca = certificate_store.trust_anchors()[0].clone();
+
     //TODO: These loading code snippets are too cut-and-paste. Copied from access
     // control.
-    let identity_ca = participant_qos
-      .get_property(QOS_IDENTITY_CA_PROPERTY_NAME)
-      .and_then(|certificate_uri| {
-        read_uri(&certificate_uri).map_err(|conf_err| {
-          create_security_error_and_log!(
-            "Failed to read the identity CA certificate from {}: {:?}",
-            certificate_uri,
-            conf_err
-          )
-        })
-      })
-      .and_then(|certificate_contents_pem| {
-        Certificate::from_pem(certificate_contents_pem)
-          .map_err(|e| create_security_error_and_log!("{e:?}"))
-      })?;
 
     let identity_certificate = participant_qos
       .get_property(QOS_IDENTITY_CERTIFICATE_PROPERTY_NAME)
@@ -169,17 +213,16 @@ impl Authentication for AuthenticationBuiltin {
         })
       })?;
 
-    // Verify that CA has signed our identity
-    identity_certificate
-      .verify_signed_by_certificate(&identity_ca)
-      .map_err(|_e| {
+    // Verify that an identity CA has signed our identity, and that it is
+    // neither expired nor revoked
+    certificate_store
+      .validate(&identity_certificate, &[])
+      .map_err(|e| {
         create_security_error_and_log!(
-          "My own identity certificate does not verify against identity CA."
+          "My own identity certificate does not validate against identity CAs: {e}"
         )
       })?;
 
-    // TODO: Check (somehow) that my identity has not been revoked.
-
     // Compute the new adjusted GUID. The start is computed from the hash of the
     // Subject Name in the identity certificate.
     let guid_start = guid_start_from_certificate(&identity_certificate)?;
@@ -224,7 +267,7 @@ impl Authentication for AuthenticationBuiltin {
       guid: adjusted_guid,
       identity_certificate,
       id_cert_private_key,
-      identity_ca,
+      certificate_store,
       signed_permissions_document_xml: Bytes::new(), /* This is to filled in later by
                                                       * initialization calling
                                                       * .set_permissions_credential_and_token() */
@@ -268,18 +311,23 @@ impl Authentication for AuthenticationBuiltin {
       ));
     }
 
-    // TODO: return an identity token with actual content
     Ok(local_info.identity_token.clone().into())
   }
 
-  // Currently only mocked
+  // Section "9.3.2.2 DDS:Auth:PKI-DH IdentityStatusToken"
   fn get_identity_status_token(
     &self,
-    _handle: IdentityHandle,
+    handle: IdentityHandle,
   ) -> SecurityResult<IdentityStatusToken> {
-    // TODO: actual implementation
+    let local_info = self.get_local_participant_info()?;
+    if handle != local_info.identity_handle {
+      return Err(create_security_error_and_log!(
+        "The given handle does not correspond to the local identity handle"
+      ));
+    }
 
-    Ok(IdentityStatusToken::dummy())
+    // OCSP is not supported, so the optional ocsp_status property is left out.
+    Ok(BuiltinIdentityStatusToken::new(None).into())
   }
 
   fn set_permissions_credential_and_token(
@@ -445,9 +493,8 @@ impl Authentication for AuthenticationBuiltin {
       BinaryProperty::with_propagate("c.kagree_algo", kagree_algo.clone()),
     ];
     let hash_c1 = Sha256::hash(
-      &to_vec::<Vec<BinaryProperty>, BigEndian>(&c_properties).map_err(|e| SecurityError {
-        msg: format!("Error serializing C1: {}", e),
-      })?,
+      &to_vec_with_endianness(&c_properties, Endianness::BigEndian)
+        .map_err(|e| SecurityError::new(format!("Error serializing C1: {}", e)))?,
     );
 
     // This is an initiator-generated 256-bit nonce
@@ -530,10 +577,12 @@ impl Authentication for AuthenticationBuiltin {
 
     // "Verifies Cert1 with the configured Identity CA"
     // So Cert1 is now `request.c_id`
-    let cert1 = Certificate::from_pem(request.c_id.as_ref())?;
+    let (cert1, cert1_chain) = certificate_and_chain(request.c_id.as_ref())?;
 
     // Verify that 1's identity cert checks out against CA.
-    cert1.verify_signed_by_certificate(&local_info.identity_ca)?;
+    local_info
+      .certificate_store
+      .validate(&cert1, &cert1_chain)?;
 
     // Verify that the remote GUID is as specified by the spec
     let remote_pdata =
@@ -574,9 +623,8 @@ impl Authentication for AuthenticationBuiltin {
       BinaryProperty::with_propagate("c.kagree_algo", request.c_kagree_algo.clone()),
     ];
     let computed_c1_hash = Sha256::hash(
-      &to_vec::<Vec<BinaryProperty>, BigEndian>(&c_properties).map_err(|e| SecurityError {
-        msg: format!("Error serializing C1: {}", e),
-      })?,
+      &to_vec_with_endianness(&c_properties, Endianness::BigEndian)
+        .map_err(|e| SecurityError::new(format!("Error serializing C1: {}", e)))?,
     );
 
     // Sanity check, received hash(c1) should match what we computed
@@ -614,9 +662,8 @@ impl Authentication for AuthenticationBuiltin {
       BinaryProperty::with_propagate("c.kagree_algo", kagree_algo.clone()),
     ];
     let c2_hash = Sha256::hash(
-      &to_vec::<Vec<BinaryProperty>, BigEndian>(&c2_properties).map_err(|e| SecurityError {
-        msg: format!("Error serializing C2: {}", e),
-      })?,
+      &to_vec_with_endianness(&c2_properties, Endianness::BigEndian)
+        .map_err(|e| SecurityError::new(format!("Error serializing C2: {}", e)))?,
     );
 
     // Spec: "Sign(Hash(C2) | Challenge2 | DH2 | Challenge1 | DH1 | Hash(C1)) )",
@@ -634,9 +681,8 @@ impl Authentication for AuthenticationBuiltin {
     ];
 
     let contents_signature = local_info.id_cert_private_key.sign(
-      &to_vec::<Vec<BinaryProperty>, BigEndian>(&cc2_properties).map_err(|e| SecurityError {
-        msg: format!("Error serializing CC2: {}", e),
-      })?,
+      &to_vec_with_endianness(&cc2_properties, Endianness::BigEndian)
+        .map_err(|e| SecurityError::new(format!("Error serializing CC2: {}", e)))?,
     )?;
 
     let reply_token = BuiltinHandshakeMessageToken {
@@ -720,10 +766,12 @@ impl Authentication for AuthenticationBuiltin {
 
         // "Verifies Cert2 with the configured Identity CA"
         // So Cert2 is now `request.c_id`
-        let cert2 = Certificate::from_pem(reply.c_id.as_ref())?;
+        let (cert2, cert2_chain) = certificate_and_chain(reply.c_id.as_ref())?;
 
         // Verify that 2's identity cert checks out against CA.
-        cert2.verify_signed_by_certificate(&local_info.identity_ca)?;
+        local_info
+          .certificate_store
+          .validate(&cert2, &cert2_chain)?;
 
         // Verify that the remote GUID is as specified by the spec.
         // Note that spec does say that this check needs to be done here. But it seems
@@ -772,9 +820,8 @@ impl Authentication for AuthenticationBuiltin {
           BinaryProperty::with_propagate("c.kagree_algo", reply.c_kagree_algo.clone()),
         ];
         let c2_hash_recomputed = Sha256::hash(
-          &to_vec::<Vec<BinaryProperty>, BigEndian>(&c2_properties).map_err(|e| SecurityError {
-            msg: format!("Error serializing C2: {}", e),
-          })?,
+          &to_vec_with_endianness(&c2_properties, Endianness::BigEndian)
+            .map_err(|e| SecurityError::new(format!("Error serializing C2: {}", e)))?,
         );
 
         if let Some(received_hash_c2) = reply.hash_c2 {
@@ -818,9 +865,8 @@ impl Authentication for AuthenticationBuiltin {
 
         // Verify "C2" contents against reply.signature and 2's public key
         cert2.verify_signed_data_with_algorithm(
-          to_vec::<Vec<BinaryProperty>, BigEndian>(&cc2_properties).map_err(|e| SecurityError {
-            msg: format!("Error serializing CC2: {}", e),
-          })?,
+          to_vec_with_endianness(&cc2_properties, Endianness::BigEndian)
+            .map_err(|e| SecurityError::new(format!("Error serializing CC2: {}", e)))?,
           reply.signature,
           c2_signature_algorithm,
         )?; // verify ok or exit here
@@ -859,11 +905,8 @@ impl Authentication for AuthenticationBuiltin {
         ];
 
         let final_contents_signature = local_info.id_cert_private_key.sign(
-          &to_vec::<Vec<BinaryProperty>, BigEndian>(&cc_final_properties).map_err(|e| {
-            SecurityError {
-              msg: format!("Error serializing CC_final: {}", e),
-            }
-          })?,
+          &to_vec_with_endianness(&cc_final_properties, Endianness::BigEndian)
+            .map_err(|e| SecurityError::new(format!("Error serializing CC_final: {}", e)))?,
         )?;
 
         // Create HandshakeFinalMessageToken to complete handshake
@@ -993,11 +1036,8 @@ impl Authentication for AuthenticationBuiltin {
 
         remote_id_certificate
           .verify_signed_data_with_algorithm(
-            to_vec::<Vec<BinaryProperty>, BigEndian>(&cc_final_properties).map_err(|e| {
-              SecurityError {
-                msg: format!("Error serializing CC_final: {}", e),
-              }
-            })?,
+            to_vec_with_endianness(&cc_final_properties, Endianness::BigEndian)
+              .map_err(|e| SecurityError::new(format!("Error serializing CC_final: {}", e)))?,
             final_token.signature,
             remote_signature_algorithm,
           )
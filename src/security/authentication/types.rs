@@ -37,6 +37,7 @@ pub type HandshakeHandle = u32;
 pub struct Sha256([u8; 32]);
 
 impl Sha256 {
+  #[cfg(any(test, not(feature = "security_hardened")))]
   pub fn dummy() -> Self {
     Sha256(<[u8; 32]>::default())
   }
@@ -89,10 +90,20 @@ impl TryFrom<&[u8]> for Sha256 {
 
 // Shared secret resulting from successful handshake
 // This is a SHA256 hash of D-H key agreement result
-#[derive(Debug, Clone, Eq, PartialEq)]
+// Hardened builds do not print it.
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(not(feature = "security_hardened"), derive(Debug))]
 pub struct SharedSecret([u8; 32]);
 
+#[cfg(feature = "security_hardened")]
+impl std::fmt::Debug for SharedSecret {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "SharedSecret(<redacted>)")
+  }
+}
+
 impl SharedSecret {
+  #[cfg(any(test, not(feature = "security_hardened")))]
   pub fn dummy() -> Self {
     SharedSecret(<[u8; 32]>::default())
   }
@@ -137,6 +148,7 @@ impl TryFrom<&[u8]> for SharedSecret {
 pub struct Challenge([u8; 32]);
 
 impl Challenge {
+  #[cfg(any(test, not(feature = "security_hardened")))]
   pub fn dummy() -> Self {
     Challenge(<[u8; 32]>::default())
   }
@@ -197,7 +209,8 @@ impl IdentityToken {
     self.data_holder.class_id.clone()
   }
 
-  // Mock value used for development
+  // Mock value used for development. Not in hardened builds.
+  #[cfg(any(test, not(feature = "security_hardened")))]
   pub fn dummy() -> Self {
     Self {
       data_holder: DataHolder::dummy(),
@@ -221,7 +234,8 @@ impl From<DataHolder> for IdentityStatusToken {
 }
 
 impl IdentityStatusToken {
-  // Mock value used for development
+  // Mock value used for development. Not in hardened builds.
+  #[cfg(any(test, not(feature = "security_hardened")))]
   pub fn dummy() -> Self {
     Self {
       data_holder: DataHolder::dummy(),
@@ -246,7 +260,8 @@ impl From<DataHolder> for AuthRequestMessageToken {
 }
 
 impl AuthRequestMessageToken {
-  // Mock value used for development
+  // Mock value used for development. Not in hardened builds.
+  #[cfg(any(test, not(feature = "security_hardened")))]
   pub fn dummy() -> Self {
     Self {
       data_holder: DataHolder::dummy(),
@@ -268,7 +283,8 @@ impl From<DataHolder> for HandshakeMessageToken {
 }
 
 impl HandshakeMessageToken {
-  // Mock value used for development
+  // Mock value used for development. Not in hardened builds.
+  #[cfg(any(test, not(feature = "security_hardened")))]
   pub fn dummy() -> Self {
     Self {
       data_holder: DataHolder::dummy(),
@@ -290,7 +306,8 @@ impl From<DataHolder> for AuthenticatedPeerCredentialToken {
 }
 
 impl AuthenticatedPeerCredentialToken {
-  // Mock value used for development
+  // Mock value used for development. Not in hardened builds.
+  #[cfg(any(test, not(feature = "security_hardened")))]
   pub fn dummy() -> Self {
     Self {
       data_holder: DataHolder::dummy(),
@@ -17,6 +17,8 @@
 // Permissions documents. The verification of the two can use the same or
 // different Certificate instances.
 
+use std::time::Duration;
+
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 use bytes::Bytes;
@@ -25,6 +27,7 @@ use x509_certificate::{
 };
 use der::Decode;
 use bcder::{encode::Values, Mode};
+use x509_cert::ext::pkix::BasicConstraints;
 
 use crate::security::{
   authentication::authentication_builtin::types::{
@@ -41,19 +44,55 @@ use crate::security::{
 pub struct Certificate {
   cert: CapturedX509Certificate,
   subject_name: DistinguishedName,
+  issuer_name: DistinguishedName,
+  serial_number: Vec<u8>,
+  // Validity period as time since the Unix epoch
+  not_before: Duration,
+  not_after: Duration,
+  // Basic Constraints extension says that this is a CA certificate
+  is_ca: bool,
 }
 
 impl Certificate {
   pub fn from_pem(pem_data: impl AsRef<[u8]>) -> Result<Self, ConfigError> {
     let cert = CapturedX509Certificate::from_pem(pem_data)
       .map_err(to_config_error_parse("Cannot read X.509 Certificate"))?;
+    Self::from_captured(cert)
+  }
 
+  // All the certificates in PEM data, in the order they appear. A file may
+  // contain e.g. several trust anchors, or a certificate followed by the CA
+  // certificates that complete its chain.
+  pub fn from_pem_bundle(pem_data: impl AsRef<[u8]>) -> Result<Vec<Self>, ConfigError> {
+    let certs = CapturedX509Certificate::from_pem_multiple(pem_data)
+      .map_err(to_config_error_parse("Cannot read X.509 Certificates"))?;
+    if certs.is_empty() {
+      return Err(ConfigError::Parse(
+        "No X.509 Certificates in PEM data".to_string(),
+      ));
+    }
+    certs.into_iter().map(Self::from_captured).collect()
+  }
+
+  fn from_captured(cert: CapturedX509Certificate) -> Result<Self, ConfigError> {
     let other_cert = x509_cert::certificate::Certificate::from_der(cert.constructed_data())
       .map_err(to_config_error_parse("Cannot read X.509 Certificate(2)"))?;
+    let tbs = other_cert.tbs_certificate;
 
-    let subject_name = other_cert.tbs_certificate.subject.into();
+    let is_ca = tbs
+      .get::<BasicConstraints>()
+      .map_err(to_config_error_parse("Cannot read X.509 Basic Constraints"))?
+      .is_some_and(|(_critical, basic_constraints)| basic_constraints.ca);
 
-    Ok(Certificate { cert, subject_name })
+    Ok(Certificate {
+      cert,
+      subject_name: tbs.subject.into(),
+      issuer_name: tbs.issuer.into(),
+      serial_number: tbs.serial_number.as_bytes().to_vec(),
+      not_before: tbs.validity.not_before.to_unix_duration(),
+      not_after: tbs.validity.not_after.to_unix_duration(),
+      is_ca,
+    })
   }
 
   pub fn to_pem(&self) -> String {
@@ -86,6 +125,25 @@ impl Certificate {
     &self.subject_name
   }
 
+  pub fn issuer_name(&self) -> &DistinguishedName {
+    &self.issuer_name
+  }
+
+  // Big-endian bytes, as in DER encoding
+  pub fn serial_number(&self) -> &[u8] {
+    &self.serial_number
+  }
+
+  // Is the certificate valid at the given time since the Unix epoch
+  pub fn is_valid_at(&self, now: Duration, clock_skew_tolerance: Duration) -> bool {
+    self.not_before <= now + clock_skew_tolerance
+      && now.saturating_sub(clock_skew_tolerance) <= self.not_after
+  }
+
+  pub fn is_ca(&self) -> bool {
+    self.is_ca
+  }
+
   pub fn subject_name_der(&self) -> Result<Vec<u8>, ConfigError> {
     let er = &self.cert.subject_name().encode_ref();
     let mut buf = Vec::with_capacity(er.encoded_len(Mode::Der));
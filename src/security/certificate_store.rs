@@ -0,0 +1,468 @@
+//! Validation of X.509 certificate chains against configured trust anchors.
+//!
+//! The builtin authentication plugin validates the identity certificates of
+//! DomainParticipants with a [`CertificateStore`]. The store is configured with
+//! the [`DomainParticipantSecurityConfigFiles`](crate::DomainParticipantSecurityConfigFiles):
+//!
+//! * The identity CA file may contain several certificates. Each of them is a
+//!   trust anchor, i.e. a chain that reaches any of them is trusted.
+//! * An optional file of certificate revocation lists (CRLs). A CRL is used
+//!   only if it is signed by the CA that issued the certificate.
+//! * A clock skew tolerance, which is allowed when checking the validity
+//!   periods of certificates.
+//!
+//! A remote participant may send the CA certificates that complete its chain
+//! after its identity certificate. Online revocation checking (OCSP) is not
+//! supported.
+
+use std::time::{Duration, SystemTime};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use der::{Decode, Encode};
+use x509_cert::crl::CertificateList;
+
+use crate::security::{
+  certificate::{Certificate, DistinguishedName},
+  config::{to_config_error_parse, ConfigError},
+  types::{security_error, SecurityResult},
+};
+
+// A CRL together with what is needed from it
+struct RevocationList {
+  issuer_name: DistinguishedName,
+  // To-be-signed part and signature, for verifying the CRL with its issuer
+  signed_data: Vec<u8>,
+  signature: Vec<u8>,
+  signature_algorithm: &'static dyn ring::signature::VerificationAlgorithm,
+  // Time since the Unix epoch
+  next_update: Option<Duration>,
+  revoked_serial_numbers: Vec<Vec<u8>>,
+}
+
+/// Trust anchors, revocation lists and clock skew tolerance for validating
+/// X.509 certificate chains. See the [module documentation](self).
+pub struct CertificateStore {
+  trust_anchors: Vec<Certificate>,
+  revocation_lists: Vec<RevocationList>,
+  clock_skew_tolerance: Duration,
+}
+
+impl CertificateStore {
+  /// Chains longer than this are not built.
+  pub const MAX_CHAIN_LENGTH: usize = 8;
+
+  /// An empty store, which trusts nothing.
+  pub fn new() -> Self {
+    Self {
+      trust_anchors: Vec::new(),
+      revocation_lists: Vec::new(),
+      clock_skew_tolerance: Duration::ZERO,
+    }
+  }
+
+  /// Adds all the certificates in the PEM data as trust anchors. Returns the
+  /// number of certificates added.
+  pub fn add_trust_anchors_pem(
+    &mut self,
+    pem_data: impl AsRef<[u8]>,
+  ) -> Result<usize, ConfigError> {
+    let anchors = Certificate::from_pem_bundle(pem_data)?;
+    let count = anchors.len();
+    self.trust_anchors.extend(anchors);
+    Ok(count)
+  }
+
+  /// Adds all the CRLs in the PEM data. Returns the number of CRLs added.
+  ///
+  /// The signatures of the CRLs are verified when they are used, so the
+  /// trust anchors need not be added first.
+  pub fn add_revocation_lists_pem(
+    &mut self,
+    pem_data: impl AsRef<[u8]>,
+  ) -> Result<usize, ConfigError> {
+    let text = String::from_utf8_lossy(pem_data.as_ref());
+    let mut count = 0;
+    for block in pem_blocks(&text, "X509 CRL") {
+      // x509-cert does not give CertificateList a PEM label, so decode the
+      // PEM block first.
+      let (_label, der_bytes) = der::pem::decode_vec(block.as_bytes())
+        .map_err(to_config_error_parse("Cannot read X.509 CRL PEM"))?;
+      let crl = CertificateList::from_der(&der_bytes)
+        .map_err(to_config_error_parse("Cannot read X.509 CRL"))?;
+      self.revocation_lists.push(RevocationList::try_from(crl)?);
+      count += 1;
+    }
+    if count == 0 {
+      return Err(ConfigError::Parse("No X.509 CRLs in PEM data".to_string()));
+    }
+    Ok(count)
+  }
+
+  /// How much the validity periods of certificates may be exceeded, to allow
+  /// for clocks that are not in sync.
+  pub fn set_clock_skew_tolerance(&mut self, tolerance: Duration) {
+    self.clock_skew_tolerance = tolerance;
+  }
+
+  pub fn clock_skew_tolerance(&self) -> Duration {
+    self.clock_skew_tolerance
+  }
+
+  /// Validates the identity certificate in PEM data. The certificate may be
+  /// followed by the CA certificates that complete its chain.
+  pub fn validate_pem(&self, pem_data: impl AsRef<[u8]>) -> SecurityResult<()> {
+    let certificates = Certificate::from_pem_bundle(pem_data)?;
+    // from_pem_bundle does not return an empty Vec
+    self.validate(&certificates[0], &certificates[1..])
+  }
+
+  pub(in crate::security) fn trust_anchors(&self) -> &[Certificate] {
+    &self.trust_anchors
+  }
+
+  // Validates `certificate` with the given untrusted CA certificates, which may
+  // complete its chain to a trust anchor.
+  pub(in crate::security) fn validate(
+    &self,
+    certificate: &Certificate,
+    intermediates: &[Certificate],
+  ) -> SecurityResult<()> {
+    let now = SystemTime::now()
+      .duration_since(SystemTime::UNIX_EPOCH)
+      .unwrap_or(Duration::ZERO);
+    self.validate_at(certificate, intermediates, now)
+  }
+
+  // `now` is time since the Unix epoch
+  fn validate_at(
+    &self,
+    certificate: &Certificate,
+    intermediates: &[Certificate],
+    now: Duration,
+  ) -> SecurityResult<()> {
+    let mut current = certificate;
+    for _ in 0..Self::MAX_CHAIN_LENGTH {
+      self.check_validity_period(current, now)?;
+
+      // Is it signed by a trust anchor? The anchor itself needs no issuer.
+      if let Some(anchor) = self
+        .trust_anchors
+        .iter()
+        .find(|anchor| is_issued_by(current, anchor))
+      {
+        self.check_validity_period(anchor, now)?;
+        return self.check_not_revoked(current, anchor, now);
+      }
+
+      let issuer = intermediates
+        .iter()
+        .find(|ca| ca.is_ca() && is_issued_by(current, ca))
+        .ok_or_else(|| {
+          security_error(&format!(
+            "Certificate of {} does not chain to a trust anchor",
+            current.subject_name()
+          ))
+        })?;
+      self.check_not_revoked(current, issuer, now)?;
+      current = issuer;
+    }
+    Err(security_error(&format!(
+      "Certificate chain of {} is longer than {}",
+      certificate.subject_name(),
+      Self::MAX_CHAIN_LENGTH
+    )))
+  }
+
+  fn check_validity_period(&self, certificate: &Certificate, now: Duration) -> SecurityResult<()> {
+    if certificate.is_valid_at(now, self.clock_skew_tolerance) {
+      Ok(())
+    } else {
+      Err(security_error(&format!(
+        "Certificate of {} is expired or not yet valid",
+        certificate.subject_name()
+      )))
+    }
+  }
+
+  fn check_not_revoked(
+    &self,
+    certificate: &Certificate,
+    issuer: &Certificate,
+    now: Duration,
+  ) -> SecurityResult<()> {
+    for crl in self
+      .revocation_lists
+      .iter()
+      .filter(|crl| crl.issuer_name.matches(issuer.subject_name()))
+    {
+      if let Err(e) = issuer.verify_signed_data_with_algorithm(
+        &crl.signed_data,
+        &crl.signature,
+        crl.signature_algorithm,
+      ) {
+        warn!(
+          "Ignoring a CRL of {} that does not verify: {e}",
+          issuer.subject_name()
+        );
+        continue;
+      }
+      if crl
+        .next_update
+        .is_some_and(|next_update| next_update + self.clock_skew_tolerance < now)
+      {
+        // A stale list still tells which certificates were revoked
+        warn!("CRL of {} is out of date", issuer.subject_name());
+      }
+      if crl
+        .revoked_serial_numbers
+        .iter()
+        .any(|serial_number| serial_number.as_slice() == certificate.serial_number())
+      {
+        return Err(security_error(&format!(
+          "Certificate of {} has been revoked",
+          certificate.subject_name()
+        )));
+      }
+    }
+    Ok(())
+  }
+}
+
+impl Default for CertificateStore {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl TryFrom<CertificateList> for RevocationList {
+  type Error = ConfigError;
+
+  fn try_from(crl: CertificateList) -> Result<Self, ConfigError> {
+    // Section "9.3.2.5.1 HandshakeRequestMessageToken objects" allows only these
+    // signature algorithms for certificates. CRLs are signed by the same CAs.
+    let algorithm_oid = crl.signature_algorithm.oid;
+    let signature_algorithm: &'static dyn ring::signature::VerificationAlgorithm =
+      if algorithm_oid == const_oid::ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2") {
+        &ring::signature::ECDSA_P256_SHA256_ASN1
+      } else if algorithm_oid == const_oid::ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.11") {
+        &ring::signature::RSA_PKCS1_2048_8192_SHA256
+      } else {
+        return Err(ConfigError::Parse(format!(
+          "Unsupported CRL signature algorithm {algorithm_oid}"
+        )));
+      };
+
+    let signed_data = crl
+      .tbs_cert_list
+      .to_der()
+      .map_err(to_config_error_parse("Cannot encode X.509 CRL"))?;
+    let signature = crl
+      .signature
+      .as_bytes()
+      .ok_or_else(|| ConfigError::Parse("X.509 CRL signature is not octet-aligned".to_string()))?
+      .to_vec();
+
+    let tbs = crl.tbs_cert_list;
+    Ok(RevocationList {
+      issuer_name: tbs.issuer.into(),
+      signed_data,
+      signature,
+      signature_algorithm,
+      next_update: tbs.next_update.map(|t| t.to_unix_duration()),
+      revoked_serial_numbers: tbs
+        .revoked_certificates
+        .unwrap_or_default()
+        .into_iter()
+        .map(|revoked| revoked.serial_number.as_bytes().to_vec())
+        .collect(),
+    })
+  }
+}
+
+fn is_issued_by(certificate: &Certificate, issuer: &Certificate) -> bool {
+  certificate.issuer_name().matches(issuer.subject_name())
+    && certificate.verify_signed_by_certificate(issuer).is_ok()
+}
+
+// The PEM blocks with the given label, including their BEGIN and END lines
+fn pem_blocks<'a>(text: &'a str, label: &str) -> Vec<&'a str> {
+  let begin = format!("-----BEGIN {label}-----");
+  let end = format!("-----END {label}-----");
+  let mut blocks = Vec::new();
+  let mut rest = text;
+  while let Some(start) = rest.find(&begin) {
+    match rest[start..].find(&end) {
+      Some(len) => {
+        let stop = start + len + end.len();
+        blocks.push(&rest[start..stop]);
+        rest = &rest[stop..];
+      }
+      None => break,
+    }
+  }
+  blocks
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // A root CA, an intermediate CA signed by it, and certificates of alice and
+  // bob signed by the intermediate. eve is signed by alice, who is not a CA.
+  // The CRL of the intermediate revokes bob. Generated with openssl. The
+  // certificates of alice, bob and eve are valid from 2025 to 2035.
+  const ROOT_CA: &str = r#"-----BEGIN CERTIFICATE-----
+MIIBlTCCATugAwIBAgIUQuz6GpCnZzzO+5SLa0wM+IuQrcQwCgYIKoZIzj0EAwIw
+FzEVMBMGA1UEAwwMVGVzdCBSb290IENBMCAXDTI0MDEwMTAwMDAwMFoYDzIwNTQw
+MTAxMDAwMDAwWjAXMRUwEwYDVQQDDAxUZXN0IFJvb3QgQ0EwWTATBgcqhkjOPQIB
+BggqhkjOPQMBBwNCAASZ4TSpxU7WaZ7ZP1yLwzBbGCVO60raeZEDcMEG0cHx3EIf
+xOri5XTPPxlhj9oPviZwb4dS4w4oWjIGz8wOWqlto2MwYTAdBgNVHQ4EFgQUBC4m
+f5uGnk23Fr1XkYjRowY9kdQwHwYDVR0jBBgwFoAUBC4mf5uGnk23Fr1XkYjRowY9
+kdQwDwYDVR0TAQH/BAUwAwEB/zAOBgNVHQ8BAf8EBAMCAQYwCgYIKoZIzj0EAwID
+SAAwRQIhAO3OTSScLm6mAg22J9RSZ9VBCP96KyO4c4D3XxPRgANAAiAJL6NO4Mp4
+6+WecheUsAtTJaFZSNU9MYCut/NDpNPAlQ==
+-----END CERTIFICATE-----
+"#;
+
+  const INTERMEDIATE_CA: &str = r#"-----BEGIN CERTIFICATE-----
+MIIBizCCATCgAwIBAgIBEDAKBggqhkjOPQQDAjAXMRUwEwYDVQQDDAxUZXN0IFJv
+b3QgQ0EwIBcNMjQwMTAxMDAwMDAwWhgPMjA1NDAxMDEwMDAwMDBaMB8xHTAbBgNV
+BAMMFFRlc3QgSW50ZXJtZWRpYXRlIENBMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcD
+QgAEGoAP8wm2y1XhZ6Ievb50GBKEZ27B47onaz+D54PiCflerv8ns8a1KCs2Kkrk
+sGYetNztSuTX4NdggRXvYMkdIaNjMGEwDwYDVR0TAQH/BAUwAwEB/zAOBgNVHQ8B
+Af8EBAMCAQYwHQYDVR0OBBYEFMSAf3omm60/EKcJ4w/5IUjfo/GuMB8GA1UdIwQY
+MBaAFAQuJn+bhp5Ntxa9V5GI0aMGPZHUMAoGCCqGSM49BAMCA0kAMEYCIQDlwxvI
+420gLPng0WEYWul8KOwl+acChyGvcwWotGdUogIhAIL0LRq3VDQOjDRa9LPASS7y
+VgK89QA9I+8l2kdL/47v
+-----END CERTIFICATE-----
+"#;
+
+  const ALICE: &str = r#"-----BEGIN CERTIFICATE-----
+MIIBbzCCARSgAwIBAgIBIDAKBggqhkjOPQQDAjAfMR0wGwYDVQQDDBRUZXN0IElu
+dGVybWVkaWF0ZSBDQTAeFw0yNTAxMDEwMDAwMDBaFw0zNTAxMDEwMDAwMDBaMBAx
+DjAMBgNVBAMMBWFsaWNlMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEk8UXMOpD
+GKqgxqyXywIN9dZGThs2IPbr/hpi2ziXwq39O2H0aT/rGSVjkKZnr70KEEjtq8Fr
+63Tekwx7fEmn96NQME4wDAYDVR0TAQH/BAIwADAdBgNVHQ4EFgQUr4N5sHpLKOJK
+/EqhMjsH/cYSSvEwHwYDVR0jBBgwFoAUxIB/eiabrT8QpwnjD/khSN+j8a4wCgYI
+KoZIzj0EAwIDSQAwRgIhAOT1NGIuWlGwSGxHzPtC4xVU5y2lXwbvpAEKr8R7WJ/b
+AiEA6lyWAkSC7Hsqau2vQBAatq8eH/MT4QRiFLFtZrX9/D8=
+-----END CERTIFICATE-----
+"#;
+
+  const BOB: &str = r#"-----BEGIN CERTIFICATE-----
+MIIBazCCARKgAwIBAgIBITAKBggqhkjOPQQDAjAfMR0wGwYDVQQDDBRUZXN0IElu
+dGVybWVkaWF0ZSBDQTAeFw0yNTAxMDEwMDAwMDBaFw0zNTAxMDEwMDAwMDBaMA4x
+DDAKBgNVBAMMA2JvYjBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABMdRKfNPM69U
+8VuS1wMFyHtwhwcK6bQZ53fzE3PLA9n4Il1dqcPN8Fg1SrJnRHVeeau1X/5vDHaG
+JtRdHSVkHIijUDBOMAwGA1UdEwEB/wQCMAAwHQYDVR0OBBYEFOvTZ/kVEUscuO8S
+wYWz+s38MVS9MB8GA1UdIwQYMBaAFMSAf3omm60/EKcJ4w/5IUjfo/GuMAoGCCqG
+SM49BAMCA0cAMEQCIBE6QL/+fYW+QG5cFPp29Jfmu7jS7gQfrjLEOV0NcuZgAiA6
+44rd9jugJvkX57NhC3CKzeQjXuixvISSutMHkSsvUA==
+-----END CERTIFICATE-----
+"#;
+
+  const EVE: &str = r#"-----BEGIN CERTIFICATE-----
+MIIBXjCCAQOgAwIBAgIBIjAKBggqhkjOPQQDAjAQMQ4wDAYDVQQDDAVhbGljZTAe
+Fw0yNTAxMDEwMDAwMDBaFw0zNTAxMDEwMDAwMDBaMA4xDDAKBgNVBAMMA2V2ZTBZ
+MBMGByqGSM49AgEGCCqGSM49AwEHA0IABP2k0yaPM6Fx8/AzptWNpUX4cCKoZyZF
+d+N4Nv6c9/Uv7QHy9FejCGztnzCT6QNzqQ6dYEJabnZABggneOiRHtSjUDBOMAwG
+A1UdEwEB/wQCMAAwHQYDVR0OBBYEFFdGVuA7UfZyKo8S5ZXaecZFQM1HMB8GA1Ud
+IwQYMBaAFK+DebB6SyjiSvxKoTI7B/3GEkrxMAoGCCqGSM49BAMCA0kAMEYCIQDy
+F1DAA2BKnHoYSUMba4c8ddHOnG7AaeL2BNmegSNvnwIhAO3YJXjT3TBqVIDyJBGG
+hGjyyWhFi34m7mZw1iCme7V+
+-----END CERTIFICATE-----
+"#;
+
+  const INTERMEDIATE_CRL: &str = r#"-----BEGIN X509 CRL-----
+MIHOMHYCAQEwCgYIKoZIzj0EAwIwHzEdMBsGA1UEAwwUVGVzdCBJbnRlcm1lZGlh
+dGUgQ0EXDTI2MTAxNjE2NDAzNVoYDzIwNTQwMzAzMTY0MDM1WjAUMBICASEXDTI2
+MTAxNjE2NDAzNVqgDjAMMAoGA1UdFAQDAgEBMAoGCCqGSM49BAMCA0gAMEUCIFSw
+nnNCKA9DRCR6k8FuO3yqJnsYdM1mYGPPrb6LF7NIAiEAnmk2imYL+eHScTGPJFxu
+NA7YEfTR2pCq+ZR1pTk8ctA=
+-----END X509 CRL-----
+"#;
+
+  // 2030-01-01
+  const NOW: Duration = Duration::from_secs(1_893_456_000);
+
+  fn cert(pem: &str) -> Certificate {
+    Certificate::from_pem(pem).unwrap()
+  }
+
+  fn root_store() -> CertificateStore {
+    let mut store = CertificateStore::new();
+    assert_eq!(store.add_trust_anchors_pem(ROOT_CA).unwrap(), 1);
+    store
+  }
+
+  #[test]
+  fn validates_chains_to_trust_anchors() {
+    let store = root_store();
+    let intermediate = cert(INTERMEDIATE_CA);
+
+    // The intermediate is needed to reach the root
+    assert!(store.validate_at(&cert(ALICE), &[], NOW).is_err());
+    store
+      .validate_at(&cert(ALICE), std::slice::from_ref(&intermediate), NOW)
+      .unwrap();
+    // A certificate that is not a CA does not complete a chain
+    assert!(store
+      .validate_at(&cert(EVE), &[cert(ALICE), intermediate], NOW)
+      .is_err());
+
+    // The intermediate can also be trusted directly
+    let mut store = CertificateStore::new();
+    let anchors = [INTERMEDIATE_CA, ROOT_CA].concat();
+    assert_eq!(store.add_trust_anchors_pem(anchors).unwrap(), 2);
+    store.validate_at(&cert(ALICE), &[], NOW).unwrap();
+
+    // A bundle of a certificate and its chain
+    root_store()
+      .validate_pem([ALICE, INTERMEDIATE_CA].concat())
+      .unwrap();
+    assert!(CertificateStore::new().validate_pem(ALICE).is_err());
+  }
+
+  #[test]
+  fn checks_validity_periods_with_clock_skew_tolerance() {
+    let mut store = root_store();
+    let intermediates = [cert(INTERMEDIATE_CA)];
+    let day = Duration::from_secs(24 * 60 * 60);
+    // 2025-01-01, when alice becomes valid
+    let not_before = Duration::from_secs(1_735_689_600);
+    // 2035-01-01, when alice expires
+    let not_after = Duration::from_secs(2_051_222_400);
+
+    let alice = cert(ALICE);
+    assert!(store
+      .validate_at(&alice, &intermediates, not_before - day)
+      .is_err());
+    assert!(store
+      .validate_at(&alice, &intermediates, not_after + day)
+      .is_err());
+
+    store.set_clock_skew_tolerance(2 * day);
+    store
+      .validate_at(&alice, &intermediates, not_before - day)
+      .unwrap();
+    store
+      .validate_at(&alice, &intermediates, not_after + day)
+      .unwrap();
+  }
+
+  #[test]
+  fn rejects_revoked_certificates() {
+    let mut store = root_store();
+    let intermediates = [cert(INTERMEDIATE_CA)];
+    store.validate_at(&cert(BOB), &intermediates, NOW).unwrap();
+
+    assert_eq!(store.add_revocation_lists_pem(INTERMEDIATE_CRL).unwrap(), 1);
+    assert!(store.validate_at(&cert(BOB), &intermediates, NOW).is_err());
+    store
+      .validate_at(&cert(ALICE), &intermediates, NOW)
+      .unwrap();
+
+    assert!(store.add_revocation_lists_pem(ALICE).is_err());
+  }
+}
@@ -1,12 +1,18 @@
 use std::{
   borrow::Borrow,
   path::{Path, PathBuf},
+  time::Duration,
 };
 
 use crate::{
   qos, security,
   security::{
-    authentication::authentication_builtin::types::CertificateAlgorithm, private_key::PrivateKey,
+    authentication::authentication_builtin::types::CertificateAlgorithm,
+    private_key::PrivateKey,
+    types::{
+      CRYPTO_KEY_SIZE_PROPERTY_NAME, CRYPTO_MAX_RECEIVER_SPECIFIC_MACS_PROPERTY_NAME,
+      CRYPTO_ORIGIN_AUTHENTICATION_PROPERTY_NAME, CRYPTO_REKEY_GRACE_PERIOD_PROPERTY_NAME,
+    },
   },
 };
 use super::{
@@ -15,7 +21,8 @@ use super::{
     QOS_PERMISSIONS_DOCUMENT_PROPERTY_NAME,
   },
   authentication::authentication_builtin::types::{
-    QOS_IDENTITY_CA_PROPERTY_NAME, QOS_IDENTITY_CERTIFICATE_PROPERTY_NAME,
+    QOS_CLOCK_SKEW_TOLERANCE_PROPERTY_NAME, QOS_IDENTITY_CA_PROPERTY_NAME,
+    QOS_IDENTITY_CERTIFICATE_PROPERTY_NAME, QOS_IDENTITY_CRL_PROPERTY_NAME,
     QOS_PASSWORD_PROPERTY_NAME, QOS_PRIVATE_KEY_PROPERTY_NAME,
   },
 };
@@ -41,6 +48,11 @@ pub enum PrivateSigningKey {
   ///
   /// Obviously, this does not work for a use case where a single token contains
   /// several private key objects, and one of those would have to be chosen.
+  ///
+  /// This is given to the authentication plugin as a PKCS#11 URI (RFC 7512)
+  /// in the private key property. When setting that property directly, the
+  /// PIN can also be read from a file with the query attribute `pin-source`
+  /// instead of `pin-value`.
   Pkcs11 {
     /// Dynamic library file for accessing the HSM, e.g.
     /// "/usr/lib/softhsm/libsofthsm2.so". Use absolute path.
@@ -48,8 +60,13 @@ pub enum PrivateSigningKey {
 
     /// Label of the token to use. Label is defined in PKCS#11 spec Section "3.2
     /// Slot and token types" definition of CK_TOKEN_INFO.
+    /// May be empty, if `slot_id` is given.
     token_label: String,
 
+    /// Id of the slot holding the token, if it must be in a specific slot.
+    /// If both this and `token_label` are given, both must match.
+    slot_id: Option<u64>,
+
     /// Login PIN code to operate the token, if any.
     /// Despite the name, the PIN is alphanumeric.
     /// It is used to attempt a read-only login as "user" (not Security
@@ -58,9 +75,122 @@ pub enum PrivateSigningKey {
   },
 }
 
+/// Length of the AES keys that the builtin cryptographic plugin generates for
+/// a participant and its endpoints.
+///
+/// Each side encodes with the keys it generated itself, and the key length of
+/// received keys is taken from their transformation kind, so participants
+/// using different key sizes interoperate. The volatile message channel of
+/// the key exchange always uses 256-bit keys, as the specification requires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CryptoKeySize {
+  /// AES-128, i.e. the AES128_GMAC and AES128_GCM transformations
+  Aes128,
+  /// AES-256, i.e. the AES256_GMAC and AES256_GCM transformations
+  #[default]
+  Aes256,
+}
+
+impl CryptoKeySize {
+  pub(crate) fn to_property(self) -> security::types::Property {
+    let value = match self {
+      Self::Aes128 => "128",
+      Self::Aes256 => "256",
+    };
+    mk_string_prop(CRYPTO_KEY_SIZE_PROPERTY_NAME, value.to_string())
+  }
+}
+
+pub(crate) fn rekey_grace_period_property(
+  grace_period: std::time::Duration,
+) -> security::types::Property {
+  mk_string_prop(
+    CRYPTO_REKEY_GRACE_PERIOD_PROPERTY_NAME,
+    grace_period.as_millis().to_string(),
+  )
+}
+
+/// Origin authentication of the submessages of a DataWriter.
+///
+/// The keys of a DataWriter are shared by all its matched readers, so any of
+/// them could forge submessages of the writer. With origin authentication the
+/// writer appends a receiver-specific MAC for each matched reader, computed
+/// with a key that only that reader has. The governance document can require
+/// this for a topic with the metadata protection kinds
+/// `SIGN_WITH_ORIGIN_AUTHENTICATION` and `ENCRYPT_WITH_ORIGIN_AUTHENTICATION`.
+/// This enables it for a single DataWriter, whose submessages the governance
+/// document must protect.
+///
+/// Each MAC makes the message 20 bytes longer. So that messages to many
+/// readers do not grow without bound, a message carries at most
+/// `max_receiver_specific_macs` MACs. To more readers, the writer sends the
+/// message in batches by unicast, each with the MACs of its own readers.
+/// Readers of one participant are kept in the same batch.
+///
+/// ```
+/// use rustdds::{OriginAuthentication, QosPolicyBuilder};
+///
+/// let writer_qos = QosPolicyBuilder::new()
+///   .property(OriginAuthentication::default().to_qos_property())
+///   .build();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OriginAuthentication {
+  pub max_receiver_specific_macs: usize,
+}
+
+impl OriginAuthentication {
+  /// Remote participants reject messages with more MACs than their
+  /// [`DecodeLimits`](crate::DecodeLimits) allow, 256 by default.
+  pub const DEFAULT_MAX_RECEIVER_SPECIFIC_MACS: usize = 32;
+
+  /// The DataWriter property policy that enables origin authentication.
+  pub fn to_qos_property(self) -> qos::policy::Property {
+    qos::policy::Property {
+      value: vec![
+        mk_string_prop(
+          CRYPTO_ORIGIN_AUTHENTICATION_PROPERTY_NAME,
+          "true".to_string(),
+        ),
+        mk_string_prop(
+          CRYPTO_MAX_RECEIVER_SPECIFIC_MACS_PROPERTY_NAME,
+          self.max_receiver_specific_macs.to_string(),
+        ),
+      ],
+      binary_value: Vec::new(),
+    }
+  }
+
+  // The MAC limit of a DataWriter with these QoS policies. It applies also when
+  // origin authentication is required by governance rather than enabled by the
+  // property.
+  pub(crate) fn max_receiver_specific_macs_of(qos: &qos::QosPolicies) -> usize {
+    qos
+      .property()
+      .and_then(|property| {
+        property
+          .value
+          .into_iter()
+          .find(|p| p.name == CRYPTO_MAX_RECEIVER_SPECIFIC_MACS_PROPERTY_NAME)
+      })
+      .and_then(|p| p.value.parse().ok())
+      .filter(|max| *max > 0)
+      .unwrap_or(Self::DEFAULT_MAX_RECEIVER_SPECIFIC_MACS)
+  }
+}
+
+impl Default for OriginAuthentication {
+  fn default() -> Self {
+    Self {
+      max_receiver_specific_macs: Self::DEFAULT_MAX_RECEIVER_SPECIFIC_MACS,
+    }
+  }
+}
+
 /// This holds the paths to files that configure DDS Security.
 pub struct DomainParticipantSecurityConfigFiles {
-  /// CA that is used to validate identities of DomainParticipants
+  /// CA that is used to validate identities of DomainParticipants. The file
+  /// may contain several CA certificates, each of which is trusted.
   pub identity_ca_certificate: PathBuf,
   /// Identity docuemnt for this Participant
   pub participant_identity_certificate: PathBuf,
@@ -73,8 +203,12 @@ pub struct DomainParticipantSecurityConfigFiles {
   pub domain_governance_document: PathBuf,
   /// Access control rules for topics and participants.
   pub participant_permissions_document: PathBuf,
-  /// CRLs are not yet implemented.
+  /// Revocation lists of the identity CAs, in PEM format. Identity
+  /// certificates listed there are rejected.
   pub certificate_revocation_list: Option<PathBuf>,
+  /// How much the validity periods of identity certificates may be exceeded,
+  /// to allow for clocks that are not in sync. None means no tolerance.
+  pub clock_skew_tolerance: Option<Duration>,
 }
 
 impl DomainParticipantSecurityConfigFiles {
@@ -98,6 +232,7 @@ impl DomainParticipantSecurityConfigFiles {
       domain_governance_document: own_and_append(&d, "governance.p7s"),
       participant_permissions_document: own_and_append(&d, "permissions.p7s"),
       certificate_revocation_list: None, // "crl.pem"
+      clock_skew_tolerance: None,
     }
   }
 
@@ -117,12 +252,14 @@ impl DomainParticipantSecurityConfigFiles {
       participant_identity_private_key: PrivateSigningKey::Pkcs11 {
         hsm_access_library: own_and_append("", hsm_access_library),
         token_label,
+        slot_id: None,
         token_pin,
       },
       permissions_ca_certificate: own_and_append(&d, "permissions_ca.cert.pem"),
       domain_governance_document: own_and_append(&d, "governance.p7s"),
       participant_permissions_document: own_and_append(&d, "permissions.p7s"),
       certificate_revocation_list: None, // "crl.pem"
+      clock_skew_tolerance: None,
     }
   }
 
@@ -139,20 +276,26 @@ impl DomainParticipantSecurityConfigFiles {
         }
         PrivateSigningKey::Pkcs11 {
           ref token_label,
+          slot_id,
           ref token_pin,
           ref hsm_access_library,
         } => {
           // for example
-          // pkcs11:object=my_private_key_name?pin-value=OpenSesame&module-path=/usr/lib/
+          // pkcs11:token=my_token;slot-id=0?pin-value=OpenSesame&module-path=/usr/lib/
           // libhsm.so
-          let mut pkcs11_uri = format!("pkcs11:object={}", token_label);
+          let mut path_attrs = Vec::new();
+          if !token_label.is_empty() {
+            path_attrs.push(format!("token={token_label}"));
+          }
+          if let Some(slot_id) = slot_id {
+            path_attrs.push(format!("slot-id={slot_id}"));
+          }
+          let mut query_attrs = Vec::new();
           if let Some(pin) = token_pin {
-            pkcs11_uri.push_str(&format!(
-              "?pin-value={}&module-path={}",
-              pin,
-              hsm_access_library.display()
-            ));
+            query_attrs.push(format!("pin-value={pin}"));
           }
+          query_attrs.push(format!("module-path={}", hsm_access_library.display()));
+          let pkcs11_uri = format!("pkcs11:{}?{}", path_attrs.join(";"), query_attrs.join("&"));
           mk_string_prop(QOS_PRIVATE_KEY_PROPERTY_NAME, pkcs11_uri)
         }
       },
@@ -172,6 +315,15 @@ impl DomainParticipantSecurityConfigFiles {
     if let PrivateSigningKey::Files { file_password, .. } = self.participant_identity_private_key {
       value.push(mk_string_prop(QOS_PASSWORD_PROPERTY_NAME, file_password));
     }
+    if let Some(crl) = self.certificate_revocation_list {
+      value.push(mk_file_prop(QOS_IDENTITY_CRL_PROPERTY_NAME, crl));
+    }
+    if let Some(tolerance) = self.clock_skew_tolerance {
+      value.push(mk_string_prop(
+        QOS_CLOCK_SKEW_TOLERANCE_PROPERTY_NAME,
+        tolerance.as_secs().to_string(),
+      ));
+    }
 
     qos::policy::Property {
       value,
@@ -9,9 +9,13 @@ pub use types::*;
 // single object. Having three separate interfaces and using them as such
 // would force us to have three references to the potentially same object
 // under different dyn types, which is too hard to do.
+//
+// The plugin must be Sync, because encoding and decoding of different
+// endpoints run concurrently through shared references.
 pub trait Cryptographic:
   cryptographic_plugin::CryptoKeyFactory
   + cryptographic_plugin::CryptoKeyExchange
   + cryptographic_plugin::CryptoTransform
+  + Sync
 {
 }
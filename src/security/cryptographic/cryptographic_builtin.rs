@@ -5,21 +5,32 @@ mod crypto_key_factory;
 mod crypto_transform;
 mod encode;
 mod key_material;
+mod key_material_map;
+mod session_key_cache;
 pub(crate) mod types;
 mod validate_receiver_specific_macs;
 
-use std::collections::{HashMap, HashSet};
+use std::{
+  collections::{HashMap, HashSet},
+  sync::{Arc, RwLock},
+  time::{Duration, Instant},
+};
 
 use crate::{
   create_security_error_and_log,
+  leak_tracking::{LeakTracker, TrackedObject},
   security::{
     access_control::types::*,
     authentication::types::*,
     cryptographic::{cryptographic_builtin::types::*, cryptographic_plugin::*, types::*},
+    decode_limits::DecodeLimiter,
     types::*,
   },
 };
-use self::{builtin_key::*, key_material::*};
+use self::{
+  builtin_key::*, key_material::*, key_material_map::KeyMaterialMap,
+  session_key_cache::SessionKeyCache,
+};
 
 // A struct implementing the builtin Cryptographic plugin
 // See sections 8.5 and 9.5 of the Security specification (v. 1.1)
@@ -28,7 +39,11 @@ pub struct CryptographicBuiltin {
   // These are the materials the local entity uses for encoding and the matched remote entity for
   // decoding, or in case of volatile endpoints a flag signalling that the entity only has
   // receiver-specific key materials. They are generated locally.
-  common_encode_key_materials: HashMap<CryptoHandle, CommonEncodeKeyMaterials>,
+  //
+  // Each entry has a lock of its own: encoding reads the common and the receiver-specific
+  // materials of the entity under the read lock, and re-keying replaces them under the write lock,
+  // so that an encoder does not mix materials from before and after re-keying.
+  common_encode_key_materials: KeyMaterialMap<RwLock<CommonEncodeKeyMaterials>>,
 
   // Receiver-specific encode key materials indexed by remote (receiver) handles.
   // For non-volatile entities they contain the common encode key material of the local entity, and
@@ -39,7 +54,7 @@ pub struct CryptographicBuiltin {
   // In case of volatile entities these contain a receiver-specific key derived from a shared
   // secret as a result of key exchange, which is used for payload encoding in order to provide
   // the volatile channel.
-  receiver_specific_encode_key_materials: HashMap<CryptoHandle, KeyMaterial_AES_GCM_GMAC_seq>,
+  receiver_specific_encode_key_materials: KeyMaterialMap<KeyMaterial_AES_GCM_GMAC_seq>,
 
   // Decode key materials indexed by remote (sender) handles.
   // These are the materials the matched remote entity uses for encoding and the local entity for
@@ -48,9 +63,16 @@ pub struct CryptographicBuiltin {
   // as a result of key exchange. If origin authentication is enabled, they include the
   // receiver-specific key material, which the remote entity uses to compute a receiver-specific
   // MAC and the local entity to verify it.
-  decode_key_materials: HashMap<CryptoHandle, KeyMaterial_AES_GCM_GMAC_seq>,
+  //
+  // The materials that the remote entity used before it re-keyed are kept
+  // alongside for the grace period.
+  decode_key_materials: KeyMaterialMap<DecodeKeyMaterials>,
+  rekey_grace_period: Duration,
 
   participant_encrypt_options: HashMap<ParticipantCryptoHandle, ParticipantSecurityAttributes>,
+  // Key size of each local participant, which its endpoints use unless their own properties
+  // specify one
+  participant_use_256_bit_key: HashMap<ParticipantCryptoHandle, bool>,
   endpoint_encrypt_options: HashMap<EndpointCryptoHandle, EndpointSecurityAttributes>,
   participant_to_endpoint_info: HashMap<ParticipantCryptoHandle, HashSet<EndpointInfo>>,
   // For reverse lookups
@@ -81,18 +103,38 @@ pub struct CryptographicBuiltin {
   matched_local_endpoint: HashMap<EndpointCryptoHandle, EndpointCryptoHandle>,
 
   crypto_handle_counter: u32,
+  // Tracks the handles in use, see module leak_tracking
+  handle_trackers: HashMap<CryptoHandle, LeakTracker>,
+
+  // Shared with SecurityPlugins, which counts the rejections
+  decode_limiter: Arc<DecodeLimiter>,
+
+  // Session keys derived from the master keys above. Encoding and decoding
+  // run through shared references, so the cache locks internally.
+  session_keys: SessionKeyCache,
+}
+
+impl Default for CryptographicBuiltin {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 // Combine the trait implementations from the submodules
 impl super::Cryptographic for CryptographicBuiltin {}
 
 impl CryptographicBuiltin {
+  // How long the old keys of a re-keyed remote entity are kept by default
+  const DEFAULT_REKEY_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
   pub fn new() -> Self {
     CryptographicBuiltin {
-      common_encode_key_materials: HashMap::new(),
-      receiver_specific_encode_key_materials: HashMap::new(),
-      decode_key_materials: HashMap::new(),
+      common_encode_key_materials: KeyMaterialMap::default(),
+      receiver_specific_encode_key_materials: KeyMaterialMap::default(),
+      decode_key_materials: KeyMaterialMap::default(),
+      rekey_grace_period: Self::DEFAULT_REKEY_GRACE_PERIOD,
       participant_encrypt_options: HashMap::new(),
+      participant_use_256_bit_key: HashMap::new(),
       endpoint_encrypt_options: HashMap::new(),
       participant_to_endpoint_info: HashMap::new(),
       endpoint_to_participant: HashMap::new(),
@@ -100,34 +142,33 @@ impl CryptographicBuiltin {
       matched_remote_endpoint: HashMap::new(),
       matched_local_endpoint: HashMap::new(),
       crypto_handle_counter: 0,
+      handle_trackers: HashMap::new(),
+      decode_limiter: Arc::new(DecodeLimiter::default()),
+      session_keys: SessionKeyCache::default(),
     }
   }
 
   fn insert_common_encode_key_materials(
-    &mut self,
+    &self,
     local_entity_crypto_handle: CryptoHandle,
     key_materials: CommonEncodeKeyMaterials,
   ) -> SecurityResult<()> {
-    match self
+    if self
       .common_encode_key_materials
-      .insert(local_entity_crypto_handle, key_materials)
+      .insert_new(local_entity_crypto_handle, RwLock::new(key_materials))
     {
-      None => SecurityResult::Ok(()),
-      Some(old_key_materials) => {
-        self
-          .common_encode_key_materials
-          .insert(local_entity_crypto_handle, old_key_materials);
-        SecurityResult::Err(create_security_error_and_log!(
-          "The CryptoHandle {} was already associated with common encode key materials",
-          local_entity_crypto_handle
-        ))
-      }
+      SecurityResult::Ok(())
+    } else {
+      SecurityResult::Err(create_security_error_and_log!(
+        "The CryptoHandle {} was already associated with common encode key materials",
+        local_entity_crypto_handle
+      ))
     }
   }
   fn get_common_encode_key_materials(
     &self,
     local_entity_crypto_handle: &CryptoHandle,
-  ) -> SecurityResult<&CommonEncodeKeyMaterials> {
+  ) -> SecurityResult<Arc<RwLock<CommonEncodeKeyMaterials>>> {
     self
       .common_encode_key_materials
       .get(local_entity_crypto_handle)
@@ -140,30 +181,26 @@ impl CryptographicBuiltin {
   }
 
   fn insert_receiver_specific_encode_key_materials(
-    &mut self,
+    &self,
     remote_entity_crypto_handle: CryptoHandle,
     key_materials: KeyMaterial_AES_GCM_GMAC_seq,
   ) -> SecurityResult<()> {
-    match self
+    if self
       .receiver_specific_encode_key_materials
-      .insert(remote_entity_crypto_handle, key_materials)
+      .insert_new(remote_entity_crypto_handle, key_materials)
     {
-      None => SecurityResult::Ok(()),
-      Some(old_key_materials) => {
-        self
-          .receiver_specific_encode_key_materials
-          .insert(remote_entity_crypto_handle, old_key_materials);
-        SecurityResult::Err(create_security_error_and_log!(
-          "The CryptoHandle {} was already associated with receiver-specific encode key materials",
-          remote_entity_crypto_handle
-        ))
-      }
+      SecurityResult::Ok(())
+    } else {
+      SecurityResult::Err(create_security_error_and_log!(
+        "The CryptoHandle {} was already associated with receiver-specific encode key materials",
+        remote_entity_crypto_handle
+      ))
     }
   }
   fn get_receiver_specific_encode_key_materials(
     &self,
     remote_entity_crypto_handle: &CryptoHandle,
-  ) -> SecurityResult<&KeyMaterial_AES_GCM_GMAC_seq> {
+  ) -> SecurityResult<Arc<KeyMaterial_AES_GCM_GMAC_seq>> {
     self
       .receiver_specific_encode_key_materials
       .get(remote_entity_crypto_handle)
@@ -176,45 +213,83 @@ impl CryptographicBuiltin {
   }
 
   fn insert_decode_key_materials(
-    &mut self,
+    &self,
     remote_entity_crypto_handle: CryptoHandle,
     key_materials: KeyMaterial_AES_GCM_GMAC_seq,
   ) -> SecurityResult<()> {
-    match self
-      .decode_key_materials
-      .insert(remote_entity_crypto_handle, key_materials)
+    if self.decode_key_materials.insert_new(
+      remote_entity_crypto_handle,
+      DecodeKeyMaterials::new(key_materials),
+    ) {
+      SecurityResult::Ok(())
+    } else {
+      SecurityResult::Err(create_security_error_and_log!(
+        "The CryptoHandle {} was already associated with decode key material",
+        remote_entity_crypto_handle
+      ))
+    }
+  }
+
+  // Removes all key materials of the entity, and the session keys derived from
+  // them.
+  fn remove_key_materials(&self, crypto_handle: CryptoHandle) {
+    if let Some(common_encode_key_materials) =
+      self.common_encode_key_materials.remove(&crypto_handle)
     {
-      None => SecurityResult::Ok(()),
-      Some(old_key_materials) => {
-        self
-          .decode_key_materials
-          .insert(remote_entity_crypto_handle, old_key_materials);
-        SecurityResult::Err(create_security_error_and_log!(
-          "The CryptoHandle {} was already associated with decode key material",
-          remote_entity_crypto_handle
-        ))
+      if let CommonEncodeKeyMaterials::Some(key_materials) =
+        &*common_encode_key_materials.read().unwrap()
+      {
+        self.forget_session_keys(key_materials);
       }
     }
+    if let Some(key_materials) = self
+      .receiver_specific_encode_key_materials
+      .remove(&crypto_handle)
+    {
+      self.forget_session_keys(&key_materials);
+    }
+    if let Some(decode_key_materials) = self.decode_key_materials.remove(&crypto_handle) {
+      for key_materials in decode_key_materials.all() {
+        self.forget_session_keys(key_materials);
+      }
+    }
+  }
+
+  // Sets the decode key materials that a remote entity sent in crypto tokens.
+  // If the remote has re-keyed, the materials it used before are kept for the
+  // grace period.
+  fn replace_decode_key_materials(
+    &self,
+    remote_entity_crypto_handle: CryptoHandle,
+    key_materials: KeyMaterial_AES_GCM_GMAC_seq,
+  ) {
+    let now = Instant::now();
+    self
+      .decode_key_materials
+      .update(remote_entity_crypto_handle, |old| match old {
+        None => DecodeKeyMaterials::new(key_materials),
+        Some(old) => old.replaced(key_materials, now, now + self.rekey_grace_period),
+      });
   }
 
+  // The key material that the remote entity used for encoding with the key
+  // id: either its current one, or one it used before re-keying, if the grace
+  // period has not passed. See "9.5.3.3.5 Computation of plaintext from
+  // ciphertext"
   fn get_decode_key_material(
     &self,
     remote_entity_crypto_handle: CryptoHandle,
     key_id: CryptoTransformKeyId,
     key_material_scope: KeyMaterialScope,
-  ) -> Option<&KeyMaterial_AES_GCM_GMAC> {
-    // TODO:
-    // Received packet is specifying key_id used to encrypt, but
-    // we just ignore that and assume the key_id is uniquely determined by
-    // crypto handle.
-    // So implement storing multiple keys per handle, distinguished by key_id.
-    // See "9.5.3.3.5 Computation of plaintext from ciphertext"
-
+  ) -> Option<KeyMaterial_AES_GCM_GMAC> {
+    let now = Instant::now();
     self
       .decode_key_materials
-      .get(&remote_entity_crypto_handle)
+      .get(&remote_entity_crypto_handle)?
+      .usable_at(now)
       .map(|key_materials| key_materials.select(key_material_scope))
-      .filter(|KeyMaterial_AES_GCM_GMAC { sender_key_id, .. }| sender_key_id.eq(&key_id))
+      .find(|KeyMaterial_AES_GCM_GMAC { sender_key_id, .. }| sender_key_id.eq(&key_id))
+      .cloned()
   }
 
   fn insert_endpoint_info(
@@ -293,7 +368,7 @@ impl CryptographicBuiltin {
   fn compute_session_key(
     rec_spec: ReceiverSpecific,
     master_key: &BuiltinKey,
-    master_salt: &BuiltinKey,
+    master_salt: &MasterSalt,
     iv: BuiltinInitializationVector,
   ) -> BuiltinKey {
     if let BuiltinKey::None = master_key {
@@ -326,6 +401,48 @@ impl CryptographicBuiltin {
     BuiltinKey::from_bytes(master_key.key_length(), digest.as_ref()).unwrap()
   }
 
+  // Like compute_session_key, but takes the key from the session key cache, if
+  // it has been computed already. `key_id` identifies the master key.
+  fn session_key(
+    &self,
+    key_id: CryptoTransformKeyId,
+    rec_spec: ReceiverSpecific,
+    master_key: &BuiltinKey,
+    master_salt: &MasterSalt,
+    iv: BuiltinInitializationVector,
+  ) -> BuiltinKey {
+    if let BuiltinKey::None = master_key {
+      return BuiltinKey::None;
+    }
+    self.session_keys.get_or_derive(
+      key_id,
+      iv.session_id(),
+      rec_spec,
+      master_key,
+      master_salt,
+      || Self::compute_session_key(rec_spec, master_key, master_salt, iv),
+    )
+  }
+
+  // Removes the cached session keys derived from key materials that are no
+  // longer used, so that they are not kept in memory.
+  fn forget_session_keys(&self, key_materials: &KeyMaterial_AES_GCM_GMAC_seq) {
+    for scope in [
+      KeyMaterialScope::MessageOrSubmessage,
+      KeyMaterialScope::PayloadOnly,
+    ] {
+      let key_material = key_materials.select(scope);
+      for key_id in [
+        key_material.sender_key_id,
+        key_material.receiver_specific_key_id,
+      ] {
+        if !key_id.is_zero() {
+          self.session_keys.remove_key_id(key_id);
+        }
+      }
+    }
+  }
+
   // Get materials needed for encoding
   fn session_encoding_materials(
     &self,
@@ -335,11 +452,15 @@ impl CryptographicBuiltin {
   ) -> SecurityResult<EncodeSessionMaterials> {
     let common_encode_key_materials =
       self.get_common_encode_key_materials(&sending_local_entity_crypto_handle)?;
+    // Held until the receiver-specific materials have been read, see
+    // common_encode_key_materials
+    let common_encode_key_materials = common_encode_key_materials.read().unwrap();
+    let volatile_key_materials;
 
-    let common_encode_key_material = match common_encode_key_materials {
+    let common_encode_key_material = match &*common_encode_key_materials {
       CommonEncodeKeyMaterials::Some(common_encode_key_materials) => common_encode_key_materials,
-      CommonEncodeKeyMaterials::Volatile(_) => {
-        if let [receiving_remote_volatile_endpoint_crypto_handle] =
+      CommonEncodeKeyMaterials::Volatile => {
+        volatile_key_materials = if let [receiving_remote_volatile_endpoint_crypto_handle] =
           receiving_remote_entity_crypto_handles
         {
           self.get_receiver_specific_encode_key_materials(
@@ -349,7 +470,8 @@ impl CryptographicBuiltin {
           Err(create_security_error_and_log!(
             "For volatile local endpoint, expected exactly one remote endpoint handle."
           ))
-        }?
+        }?;
+        volatile_key_materials.as_ref()
       }
     }
     .select(key_material_scope);
@@ -366,7 +488,8 @@ impl CryptographicBuiltin {
 
     let initialization_vector = self.random_initialization_vector();
 
-    let session_key = Self::compute_session_key(
+    let session_key = self.session_key(
+      *sender_key_id,
       ReceiverSpecific::No,
       master_sender_key,
       master_salt,
@@ -381,10 +504,11 @@ impl CryptographicBuiltin {
         .filter_map(|receiver_crypto_handle| {
           self
             .get_receiver_specific_encode_key_materials(receiver_crypto_handle)
-            .map(|m| m.select(key_material_scope))
             // Compare to the common key material and get the receiver specific key material
-            .and_then(|receiver_key_material| {
-              receiver_key_material.receiver_key_material_for(common_encode_key_material)
+            .and_then(|receiver_key_materials| {
+              receiver_key_materials
+                .select(key_material_scope)
+                .receiver_key_material_for(common_encode_key_material)
             })
             // Map to session keys
             .map(|ReceiverSpecificKeyMaterial { key_id, key }| {
@@ -392,7 +516,8 @@ impl CryptographicBuiltin {
               if key_id.is_zero() {
                 None
               } else {
-                let session_key = Self::compute_session_key(
+                let session_key = self.session_key(
+                  key_id,
                   ReceiverSpecific::Yes,
                   &key,
                   master_salt,
@@ -457,31 +582,32 @@ impl CryptographicBuiltin {
       master_receiver_specific_key,
     } = self.get_decode_key_material(remote_sender_handle, header_key_id, key_material_scope)?;
 
-    let transformation_kind = *transformation_kind;
-    let session_key = Self::compute_session_key(
+    let session_key = self.session_key(
+      sender_key_id,
       ReceiverSpecific::No,
-      master_sender_key,
-      master_salt,
+      &master_sender_key,
+      &master_salt,
       initialization_vector,
     );
 
     let receiver_specific_key = if receiver_specific_key_id.is_zero() {
       None // does not exist
     } else {
-      let session_key = Self::compute_session_key(
+      let session_key = self.session_key(
+        receiver_specific_key_id,
         ReceiverSpecific::Yes,
-        master_receiver_specific_key,
-        master_salt,
+        &master_receiver_specific_key,
+        &master_salt,
         initialization_vector,
       );
       Some(ReceiverSpecificKeyMaterial {
-        key_id: *receiver_specific_key_id,
+        key_id: receiver_specific_key_id,
         key: session_key,
       })
     };
 
     Some(DecodeSessionMaterials {
-      key_id: *sender_key_id,
+      key_id: sender_key_id,
       transformation_kind,
       session_key,
       receiver_specific_key,
@@ -508,3 +634,548 @@ struct DecodeSessionMaterials {
   receiver_specific_key: Option<ReceiverSpecificKeyMaterial>,
   // Either we have receiver specific key material specific to us or not.
 }
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    sync::{Mutex, RwLock},
+    thread,
+    time::Instant,
+  };
+
+  use bytes::Bytes;
+  use enumflags2::BitFlags;
+  use speedy::Writable;
+
+  use super::*;
+  use crate::{
+    messages::submessages::{
+      elements::parameter_list::ParameterList,
+      submessages::{
+        FromEndianness, Heartbeat, SecuritySubmessage, WriterSubmessage, HEARTBEAT_Flags,
+      },
+    },
+    rtps::{Submessage, SubmessageBody},
+    security::{
+      access_control::access_control_builtin::types::{
+        BuiltinPluginEndpointSecurityAttributes, BuiltinPluginParticipantSecurityAttributes,
+      },
+      config::CryptoKeySize,
+    },
+    structure::{guid::EntityId, sequence_number::SequenceNumber},
+  };
+
+  fn participant_attributes() -> ParticipantSecurityAttributes {
+    ParticipantSecurityAttributes {
+      plugin_participant_attributes: BuiltinPluginParticipantSecurityAttributes {
+        is_rtps_encrypted: false,
+        is_discovery_encrypted: false,
+        is_liveliness_encrypted: false,
+        is_rtps_origin_authenticated: false,
+        is_discovery_origin_authenticated: false,
+        is_liveliness_origin_authenticated: false,
+      }
+      .into(),
+      ..ParticipantSecurityAttributes::empty()
+    }
+  }
+
+  fn payload_encrypting_attributes() -> EndpointSecurityAttributes {
+    EndpointSecurityAttributes {
+      is_payload_protected: true,
+      plugin_endpoint_attributes: BuiltinPluginEndpointSecurityAttributes {
+        is_submessage_encrypted: false,
+        is_submessage_origin_authenticated: false,
+        is_payload_encrypted: true,
+      }
+      .into(),
+      ..EndpointSecurityAttributes::empty()
+    }
+  }
+
+  fn register_encrypting_writers(
+    crypto: &mut CryptographicBuiltin,
+    count: usize,
+  ) -> Vec<CryptoHandle> {
+    let participant = crypto
+      .register_local_participant(1, 1, &[], participant_attributes())
+      .unwrap();
+    (0..count)
+      .map(|_| {
+        crypto
+          .register_local_datawriter(participant, &[], payload_encrypting_attributes())
+          .unwrap()
+      })
+      .collect()
+  }
+
+  fn shared_secret() -> SharedSecretHandle {
+    SharedSecretHandle {
+      shared_secret: SharedSecret::dummy(),
+      challenge1: Challenge::dummy(),
+      challenge2: Challenge::dummy(),
+    }
+  }
+
+  fn payload_transformation_kind(
+    crypto: &CryptographicBuiltin,
+    handle: CryptoHandle,
+  ) -> BuiltinCryptoTransformationKind {
+    let common_encode_key_materials = crypto.common_encode_key_materials.get(&handle).unwrap();
+    let common_encode_key_materials = common_encode_key_materials.read().unwrap();
+    match &*common_encode_key_materials {
+      CommonEncodeKeyMaterials::Some(key_materials) => {
+        key_materials
+          .select(KeyMaterialScope::PayloadOnly)
+          .transformation_kind
+      }
+      CommonEncodeKeyMaterials::Volatile => panic!("No key materials for handle {handle}"),
+    }
+  }
+
+  // A payload-encrypting writer and a reader in plugins of their own, whose
+  // participants are configured with the given key sizes
+  struct MatchedWriterAndReader {
+    sender: CryptographicBuiltin,
+    sender_participant: ParticipantCryptoHandle,
+    writer: DatawriterCryptoHandle,
+    remote_reader: DatareaderCryptoHandle,
+    receiver: CryptographicBuiltin,
+    receiver_participant: ParticipantCryptoHandle,
+    reader: DatareaderCryptoHandle,
+    remote_sender: ParticipantCryptoHandle,
+    remote_writer: DatawriterCryptoHandle,
+  }
+
+  impl MatchedWriterAndReader {
+    fn new(writer_key_size: CryptoKeySize, reader_key_size: CryptoKeySize) -> Self {
+      Self::with_attributes(
+        writer_key_size,
+        reader_key_size,
+        payload_encrypting_attributes(),
+      )
+    }
+
+    fn with_attributes(
+      writer_key_size: CryptoKeySize,
+      reader_key_size: CryptoKeySize,
+      attributes: EndpointSecurityAttributes,
+    ) -> Self {
+      let mut sender = CryptographicBuiltin::new();
+      let sender_participant = sender
+        .register_local_participant(
+          1,
+          1,
+          &[writer_key_size.to_property()],
+          participant_attributes(),
+        )
+        .unwrap();
+      let writer = sender
+        .register_local_datawriter(sender_participant, &[], attributes.clone())
+        .unwrap();
+
+      let mut receiver = CryptographicBuiltin::new();
+      let receiver_participant = receiver
+        .register_local_participant(
+          2,
+          2,
+          &[reader_key_size.to_property()],
+          participant_attributes(),
+        )
+        .unwrap();
+      let reader = receiver
+        .register_local_datareader(receiver_participant, &[], attributes)
+        .unwrap();
+
+      let remote_receiver = sender
+        .register_matched_remote_participant(sender_participant, 2, 2, shared_secret())
+        .unwrap();
+      let remote_reader = sender
+        .register_matched_remote_datareader(writer, remote_receiver, shared_secret(), false)
+        .unwrap();
+      let remote_sender = receiver
+        .register_matched_remote_participant(receiver_participant, 1, 1, shared_secret())
+        .unwrap();
+      let remote_writer = receiver
+        .register_matched_remote_datawriter(reader, remote_sender, shared_secret())
+        .unwrap();
+
+      let endpoints = Self {
+        sender,
+        sender_participant,
+        writer,
+        remote_reader,
+        receiver,
+        receiver_participant,
+        reader,
+        remote_sender,
+        remote_writer,
+      };
+      endpoints.send_writer_tokens();
+      endpoints
+    }
+
+    fn send_writer_tokens(&self) {
+      let tokens = self
+        .sender
+        .create_local_datawriter_crypto_tokens(self.writer, self.remote_reader)
+        .unwrap();
+      self
+        .receiver
+        .set_remote_datawriter_crypto_tokens(self.reader, self.remote_writer, tokens)
+        .unwrap();
+    }
+
+    fn encode(&self, payload: &[u8]) -> (Vec<u8>, ParameterList) {
+      let (encoded, inline_qos) = self
+        .sender
+        .encode_serialized_payload(payload.to_vec(), self.writer)
+        .unwrap();
+      assert_ne!(encoded, payload);
+      (encoded, inline_qos)
+    }
+
+    fn decode(&self, (encoded, inline_qos): (Vec<u8>, ParameterList)) -> SecurityResult<Vec<u8>> {
+      self
+        .receiver
+        .decode_serialized_payload(encoded, inline_qos, self.reader, self.remote_writer)
+    }
+  }
+
+  fn send_encrypted_payload(writer_key_size: CryptoKeySize, reader_key_size: CryptoKeySize) {
+    let endpoints = MatchedWriterAndReader::new(writer_key_size, reader_key_size);
+    let expected_kind = match writer_key_size {
+      CryptoKeySize::Aes128 => {
+        BuiltinCryptoTransformationKind::CRYPTO_TRANSFORMATION_KIND_AES128_GCM
+      }
+      CryptoKeySize::Aes256 => {
+        BuiltinCryptoTransformationKind::CRYPTO_TRANSFORMATION_KIND_AES256_GCM
+      }
+    };
+    assert_eq!(
+      payload_transformation_kind(&endpoints.sender, endpoints.writer),
+      expected_kind
+    );
+
+    let payload = b"key size interoperability";
+    let decoded = endpoints.decode(endpoints.encode(payload)).unwrap();
+    assert_eq!(decoded, payload);
+  }
+
+  #[test]
+  fn aes128_participant_interoperates_with_aes256_peer() {
+    send_encrypted_payload(CryptoKeySize::Aes128, CryptoKeySize::Aes256);
+    send_encrypted_payload(CryptoKeySize::Aes256, CryptoKeySize::Aes128);
+    send_encrypted_payload(CryptoKeySize::Aes128, CryptoKeySize::Aes128);
+  }
+
+  #[test]
+  fn endpoint_key_size_overrides_participant() {
+    let mut crypto = CryptographicBuiltin::new();
+    let participant = crypto
+      .register_local_participant(
+        1,
+        1,
+        &[CryptoKeySize::Aes128.to_property()],
+        participant_attributes(),
+      )
+      .unwrap();
+    let writer = crypto
+      .register_local_datawriter(
+        participant,
+        &[CryptoKeySize::Aes256.to_property()],
+        payload_encrypting_attributes(),
+      )
+      .unwrap();
+    assert_eq!(
+      payload_transformation_kind(&crypto, writer),
+      BuiltinCryptoTransformationKind::CRYPTO_TRANSFORMATION_KIND_AES256_GCM
+    );
+
+    // The volatile channel uses 256-bit keys regardless of the key size
+    let volatile_writer = crypto
+      .register_local_datawriter(
+        participant,
+        &[volatile_writer_recognition_property()],
+        payload_encrypting_attributes(),
+      )
+      .unwrap();
+    let remote_participant = crypto
+      .register_matched_remote_participant(participant, 2, 2, shared_secret())
+      .unwrap();
+    let remote_reader = crypto
+      .register_matched_remote_datareader(
+        volatile_writer,
+        remote_participant,
+        shared_secret(),
+        false,
+      )
+      .unwrap();
+    let volatile_key_material = crypto
+      .receiver_specific_encode_key_materials
+      .get(&remote_reader)
+      .unwrap()
+      .key_material()
+      .clone();
+    assert_eq!(
+      volatile_key_material.transformation_kind,
+      BuiltinCryptoTransformationKind::CRYPTO_TRANSFORMATION_KIND_AES256_GCM
+    );
+    assert_eq!(
+      volatile_key_material.master_sender_key.key_length(),
+      KeyLength::AES256
+    );
+  }
+
+  #[test]
+  fn rekeyed_writer_is_decoded_with_old_keys_during_grace_period() {
+    let mut endpoints = MatchedWriterAndReader::new(CryptoKeySize::Aes256, CryptoKeySize::Aes256);
+    let before = endpoints.encode(b"before");
+
+    endpoints
+      .sender
+      .rekey_local_datawriter(endpoints.writer)
+      .unwrap();
+    let after = endpoints.encode(b"after");
+    // The reader cannot decode with the new keys before receiving them
+    assert!(endpoints.decode(after.clone()).is_err());
+
+    endpoints.send_writer_tokens();
+    assert_eq!(endpoints.decode(before).unwrap(), b"before");
+    assert_eq!(endpoints.decode(after.clone()).unwrap(), b"after");
+
+    // Without a grace period, the keys are dropped at once on re-keying
+    endpoints.receiver.rekey_grace_period = Duration::ZERO;
+    endpoints
+      .sender
+      .rekey_local_datawriter(endpoints.writer)
+      .unwrap();
+    endpoints.send_writer_tokens();
+    assert!(endpoints.decode(after).is_err());
+    assert_eq!(
+      endpoints.decode(endpoints.encode(b"latest")).unwrap(),
+      b"latest"
+    );
+  }
+
+  #[test]
+  fn rekeying_a_writer_does_not_block_other_writers() {
+    let mut endpoints = MatchedWriterAndReader::new(CryptoKeySize::Aes256, CryptoKeySize::Aes256);
+    let other_writer = endpoints
+      .sender
+      .register_local_datawriter(
+        endpoints.sender_participant,
+        &[],
+        payload_encrypting_attributes(),
+      )
+      .unwrap();
+
+    // Hold the materials of the first writer like re-keying does
+    let rekeyed = endpoints
+      .sender
+      .common_encode_key_materials
+      .get(&endpoints.writer)
+      .unwrap();
+    let rekeying = rekeyed.write().unwrap();
+    let encoded = endpoints
+      .sender
+      .encode_serialized_payload(b"other".to_vec(), other_writer);
+    assert!(encoded.is_ok());
+    drop(rekeying);
+
+    // Re-keying and key exchange take shared references, so they run while
+    // another thread encodes
+    thread::scope(|s| {
+      s.spawn(|| {
+        endpoints
+          .sender
+          .rekey_local_datawriter(endpoints.writer)
+          .unwrap();
+        endpoints.send_writer_tokens();
+      });
+      for _ in 0..100 {
+        endpoints
+          .sender
+          .encode_serialized_payload(b"other".to_vec(), other_writer)
+          .unwrap();
+      }
+    });
+    assert_eq!(
+      endpoints.decode(endpoints.encode(b"rekeyed")).unwrap(),
+      b"rekeyed"
+    );
+  }
+
+  // The builtin endpoint protection kinds of the governance, as
+  // (is_submessage_protected, is_submessage_encrypted,
+  // is_submessage_origin_authenticated), with the CryptoTransformKind of
+  // section 9.5.2.1.1 of the Security specification (v. 1.1) that protected
+  // submessages carry in their SecurePrefix
+  #[allow(clippy::type_complexity)]
+  const BUILTIN_PROTECTION_VECTORS: [(&str, bool, bool, bool, Option<CryptoTransformKind>); 5] = [
+    ("NONE", false, false, false, None),
+    ("SIGN", true, false, false, Some([0, 0, 0, 3])),
+    ("ENCRYPT", true, true, false, Some([0, 0, 0, 4])),
+    (
+      "SIGN_WITH_ORIGIN_AUTHENTICATION",
+      true,
+      false,
+      true,
+      Some([0, 0, 0, 3]),
+    ),
+    (
+      "ENCRYPT_WITH_ORIGIN_AUTHENTICATION",
+      true,
+      true,
+      true,
+      Some([0, 0, 0, 4]),
+    ),
+  ];
+
+  #[test]
+  fn builtin_endpoint_submessages_follow_protection_kind() {
+    for (kind, protected, encrypted, origin_authenticated, wire_kind) in BUILTIN_PROTECTION_VECTORS
+    {
+      let attributes = EndpointSecurityAttributes {
+        is_submessage_protected: protected,
+        plugin_endpoint_attributes: BuiltinPluginEndpointSecurityAttributes {
+          is_submessage_encrypted: encrypted,
+          is_submessage_origin_authenticated: origin_authenticated,
+          is_payload_encrypted: false,
+        }
+        .into(),
+        ..EndpointSecurityAttributes::empty()
+      };
+      let endpoints = MatchedWriterAndReader::with_attributes(
+        CryptoKeySize::Aes256,
+        CryptoKeySize::Aes256,
+        attributes,
+      );
+
+      // A secure SEDP heartbeat
+      let heartbeat = Heartbeat {
+        reader_id: EntityId::SEDP_BUILTIN_PUBLICATIONS_SECURE_READER,
+        writer_id: EntityId::SEDP_BUILTIN_PUBLICATIONS_SECURE_WRITER,
+        first_sn: SequenceNumber::new(1),
+        last_sn: SequenceNumber::new(3),
+        count: 1,
+      };
+      let submessage = heartbeat
+        .clone()
+        .create_submessage(BitFlags::<HEARTBEAT_Flags>::from_endianness(
+          speedy::Endianness::LittleEndian,
+        ))
+        .unwrap();
+      let encoded = endpoints
+        .sender
+        .encode_datawriter_submessage(submessage, endpoints.writer, vec![endpoints.remote_reader])
+        .unwrap();
+
+      let (prefix, body, postfix) = match (encoded, wire_kind) {
+        (EncodedSubmessage::Unencoded(_), None) => continue,
+        (EncodedSubmessage::Encoded(prefix, body, postfix), Some(_)) => (prefix, body, postfix),
+        _ => panic!("{kind}: protected differently than the governance says"),
+      };
+      let (prefix, postfix) = match (prefix.body, postfix.body) {
+        (
+          SubmessageBody::Security(SecuritySubmessage::SecurePrefix(prefix, _)),
+          SubmessageBody::Security(SecuritySubmessage::SecurePostfix(postfix, _)),
+        ) => (prefix, postfix),
+        _ => panic!("{kind}: expected SecurePrefix and SecurePostfix"),
+      };
+      assert_eq!(
+        Some(prefix.crypto_header.transformation_id.transformation_kind),
+        wire_kind,
+        "{kind}"
+      );
+      // Encrypted submessages are hidden in a SecureBody, signed ones are not
+      assert_eq!(
+        matches!(
+          body.body,
+          SubmessageBody::Security(SecuritySubmessage::SecureBody(..))
+        ),
+        encrypted,
+        "{kind}"
+      );
+
+      // Received submessages keep their bytes, which the MAC is checked against
+      let mut body_bytes = Bytes::from(body.write_to_vec().unwrap());
+      let body = Submessage::read_from_buffer(&mut body_bytes)
+        .unwrap()
+        .unwrap();
+
+      let decoded = endpoints
+        .receiver
+        .decode_submessage(
+          (prefix, body, postfix),
+          endpoints.receiver_participant,
+          endpoints.remote_sender,
+        )
+        .unwrap();
+      match decoded {
+        DecodeOutcome::Success(DecodedSubmessage::Writer(
+          WriterSubmessage::Heartbeat(decoded, _),
+          _,
+        )) => assert_eq!(decoded, heartbeat, "{kind}"),
+        _ => panic!("{kind}: the heartbeat was not decoded"),
+      }
+    }
+  }
+
+  // Benchmark: several writers encrypting payloads in parallel, each in its
+  // own thread, through a global mutex vs. a shared read lock.
+  // Run with `cargo test --release --features security multi_writer_encode -- --ignored --nocapture`
+  #[test]
+  #[ignore]
+  fn multi_writer_encode_throughput_benchmark() {
+    const WRITERS: usize = 8;
+    const SAMPLES_PER_WRITER: usize = 20_000;
+    const PAYLOAD_SIZE: usize = 1024;
+
+    let mut crypto = CryptographicBuiltin::new();
+    let writers = register_encrypting_writers(&mut crypto, WRITERS);
+    let payload = vec![0xA5_u8; PAYLOAD_SIZE];
+
+    let mutex = Mutex::new(crypto);
+    let bench_start = Instant::now();
+    thread::scope(|s| {
+      for &writer in &writers {
+        let (mutex, payload) = (&mutex, &payload);
+        s.spawn(move || {
+          for _ in 0..SAMPLES_PER_WRITER {
+            let crypto = mutex.lock().unwrap();
+            crypto
+              .encode_serialized_payload(payload.clone(), writer)
+              .unwrap();
+          }
+        });
+      }
+    });
+    println!(
+      "mutex: {WRITERS} writers encoded {} samples: {:?}",
+      WRITERS * SAMPLES_PER_WRITER,
+      bench_start.elapsed()
+    );
+
+    let rw_lock = RwLock::new(mutex.into_inner().unwrap());
+    let bench_start = Instant::now();
+    thread::scope(|s| {
+      for &writer in &writers {
+        let (rw_lock, payload) = (&rw_lock, &payload);
+        s.spawn(move || {
+          for _ in 0..SAMPLES_PER_WRITER {
+            let crypto = rw_lock.read().unwrap();
+            crypto
+              .encode_serialized_payload(payload.clone(), writer)
+              .unwrap();
+          }
+        });
+      }
+    });
+    println!(
+      "read lock: {WRITERS} writers encoded {} samples: {:?}",
+      WRITERS * SAMPLES_PER_WRITER,
+      bench_start.elapsed()
+    );
+  }
+}
@@ -1,13 +1,31 @@
-use crate::security::{security_error, SecurityResult};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  create_security_error_and_log,
+  security::{security_error, SecurityError, SecurityResult},
+};
 use super::types::BuiltinCryptoTransformationKind;
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+// Hardened builds do not print the key bytes.
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(not(feature = "security_hardened"), derive(Debug))]
 pub(super) enum BuiltinKey {
   None,
   AES128([u8; AES128_KEY_LENGTH]),
   AES256([u8; AES256_KEY_LENGTH]),
 }
 
+#[cfg(feature = "security_hardened")]
+impl std::fmt::Debug for BuiltinKey {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      BuiltinKey::None => write!(f, "None"),
+      BuiltinKey::AES128(_) => write!(f, "AES128(<redacted>)"),
+      BuiltinKey::AES256(_) => write!(f, "AES256(<redacted>)"),
+    }
+  }
+}
+
 impl BuiltinKey {
   pub(super) fn as_bytes(&self) -> &[u8] {
     match self {
@@ -17,7 +35,17 @@ impl BuiltinKey {
     }
   }
 
+  // Takes the first `length` bytes, so that e.g. a 256-bit digest can be used
+  // as a 128-bit key. Key material longer than `sequence<octet, 32>` allows is
+  // rejected.
   pub(super) fn from_bytes(length: KeyLength, bytes: &[u8]) -> SecurityResult<Self> {
+    if bytes.len() > MAX_KEY_MATERIAL_LENGTH {
+      return Err(create_security_error_and_log!(
+        "BuiltinKey: source material is {} bytes, at most {} allowed.",
+        bytes.len(),
+        MAX_KEY_MATERIAL_LENGTH
+      ));
+    }
     let l = length as usize;
     match length {
       KeyLength::None => Ok(BuiltinKey::None),
@@ -64,11 +92,107 @@ impl BuiltinKey {
   }
 }
 
+/// Master salt of key material. The salt is not a key, but it has the same
+/// length as the keys of the key material (9.5.3.3.2), so the same
+/// representation is used. The newtype keeps salts and keys from being mixed
+/// up. Hardened builds do not print the salt.
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(not(feature = "security_hardened"), derive(Debug))]
+pub(super) struct MasterSalt(BuiltinKey);
+
+#[cfg(feature = "security_hardened")]
+impl std::fmt::Debug for MasterSalt {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "MasterSalt(<redacted>)")
+  }
+}
+
+impl MasterSalt {
+  pub(super) const NONE: Self = Self(BuiltinKey::None);
+
+  pub(super) fn as_bytes(&self) -> &[u8] {
+    self.0.as_bytes()
+  }
+
+  pub(super) fn from_bytes(length: KeyLength, bytes: &[u8]) -> SecurityResult<Self> {
+    BuiltinKey::from_bytes(length, bytes).map(Self)
+  }
+
+  pub(super) fn generate_random(key_len: KeyLength) -> Self {
+    Self(BuiltinKey::generate_random(key_len))
+  }
+}
+
+impl From<BuiltinKey> for MasterSalt {
+  fn from(key: BuiltinKey) -> Self {
+    Self(key)
+  }
+}
+
+/// Contents of a `sequence<octet, 32>` in serialized key material: at most
+/// [`MAX_KEY_MATERIAL_LENGTH`] bytes. The limit is checked on construction and
+/// when deserializing. Hardened builds do not print the octets.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(not(feature = "security_hardened"), derive(Debug))]
+#[serde(try_from = "Vec<u8>", into = "Vec<u8>")]
+pub(super) struct KeyMaterialOctets(Vec<u8>);
+
+#[cfg(feature = "security_hardened")]
+impl std::fmt::Debug for KeyMaterialOctets {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "KeyMaterialOctets(<{} octets redacted>)", self.0.len())
+  }
+}
+
+impl KeyMaterialOctets {
+  pub(super) fn as_bytes(&self) -> &[u8] {
+    &self.0
+  }
+}
+
+impl TryFrom<Vec<u8>> for KeyMaterialOctets {
+  type Error = SecurityError;
+  fn try_from(bytes: Vec<u8>) -> SecurityResult<Self> {
+    if bytes.len() > MAX_KEY_MATERIAL_LENGTH {
+      Err(create_security_error_and_log!(
+        "Key material sequence has {} octets, at most {} allowed.",
+        bytes.len(),
+        MAX_KEY_MATERIAL_LENGTH
+      ))
+    } else {
+      Ok(Self(bytes))
+    }
+  }
+}
+
+impl From<KeyMaterialOctets> for Vec<u8> {
+  fn from(octets: KeyMaterialOctets) -> Self {
+    octets.0
+  }
+}
+
+// Keys and salts are never longer than the limit, so these cannot fail.
+impl From<&BuiltinKey> for KeyMaterialOctets {
+  fn from(key: &BuiltinKey) -> Self {
+    Self(key.as_bytes().to_vec())
+  }
+}
+
+impl From<&MasterSalt> for KeyMaterialOctets {
+  fn from(salt: &MasterSalt) -> Self {
+    Self(salt.as_bytes().to_vec())
+  }
+}
+
+/// Maximum length of the salt and keys in key material, from the
+/// `sequence<octet, 32>` type in 9.5.2.1.1
+pub(super) const MAX_KEY_MATERIAL_LENGTH: usize = 32;
+
 pub(super) const AES128_KEY_LENGTH: usize = 16;
 
 pub(super) const AES256_KEY_LENGTH: usize = 32;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(super) enum KeyLength {
   None = 0, // for cases where encryption or signing is not requested
   AES128 = AES128_KEY_LENGTH as isize,
@@ -86,3 +210,50 @@ impl From<BuiltinCryptoTransformationKind> for KeyLength {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use speedy::Endianness;
+
+  use super::*;
+  use crate::serialization::{from_bytes_with_endianness, to_vec_with_endianness};
+
+  #[test]
+  fn key_material_length_is_bounded() {
+    assert!(KeyMaterialOctets::try_from(vec![1; MAX_KEY_MATERIAL_LENGTH]).is_ok());
+    assert!(KeyMaterialOctets::try_from(vec![1; MAX_KEY_MATERIAL_LENGTH + 1]).is_err());
+    assert!(BuiltinKey::from_bytes(KeyLength::AES128, &[1; MAX_KEY_MATERIAL_LENGTH + 1]).is_err());
+
+    // Deserializing checks the bound, too
+    let deserialize = |len: usize| {
+      let bytes = to_vec_with_endianness(&vec![1_u8; len], Endianness::BigEndian).unwrap();
+      from_bytes_with_endianness::<KeyMaterialOctets>(&bytes, Endianness::BigEndian)
+        .map(|(octets, _)| octets)
+    };
+    assert_eq!(
+      deserialize(AES256_KEY_LENGTH).unwrap().as_bytes(),
+      &[1; AES256_KEY_LENGTH]
+    );
+    assert!(deserialize(MAX_KEY_MATERIAL_LENGTH + 1).is_err());
+
+    let salt = MasterSalt::generate_random(KeyLength::AES128);
+    assert_eq!(KeyMaterialOctets::from(&salt).as_bytes(), salt.as_bytes());
+  }
+
+  #[cfg(feature = "security_hardened")]
+  #[test]
+  fn hardened_debug_hides_key_bytes() {
+    let key = BuiltinKey::AES128([0xAB; AES128_KEY_LENGTH]);
+    let salt = MasterSalt::from(key.clone());
+    let octets = KeyMaterialOctets::from(&key);
+    for printed in [
+      format!("{key:?}"),
+      format!("{salt:?}"),
+      format!("{octets:?}"),
+    ] {
+      assert!(printed.contains("redacted"), "{printed}");
+      assert!(!printed.contains("171"), "{printed}");
+      assert!(!printed.to_lowercase().contains("ab"), "{printed}");
+    }
+  }
+}
@@ -1,3 +1,4 @@
+use log::warn;
 use ring::{digest, hmac};
 
 use crate::{
@@ -17,6 +18,10 @@ use super::{aes_gcm_gmac::keygen, builtin_key::*, key_material::*};
 impl CryptographicBuiltin {
   fn generate_crypto_handle(&mut self) -> CryptoHandle {
     self.crypto_handle_counter += 1;
+    self.handle_trackers.insert(
+      self.crypto_handle_counter,
+      LeakTracker::new(TrackedObject::CryptoHandle),
+    );
     self.crypto_handle_counter
   }
 
@@ -95,21 +100,19 @@ impl CryptographicBuiltin {
       challenge1,
       challenge2,
     }: &SharedSecretHandle,
-    use_256_bit_key: bool,
   ) -> SecurityResult<KeyMaterial_AES_GCM_GMAC_seq> {
-    let transformation_kind = if use_256_bit_key {
-      BuiltinCryptoTransformationKind::CRYPTO_TRANSFORMATION_KIND_AES256_GCM
-    } else {
-      BuiltinCryptoTransformationKind::CRYPTO_TRANSFORMATION_KIND_AES128_GCM
-    };
+    // The derived key is always 256 bits, and both sides must agree on the kind
+    // without exchanging tokens, so the configured key size does not apply here.
+    let transformation_kind =
+      BuiltinCryptoTransformationKind::CRYPTO_TRANSFORMATION_KIND_AES256_GCM;
 
     let salt_cookie: &[u8] = b"keyexchange salt".as_ref(); // Not a typo
     let key_cookie: &[u8] = b"key exchange key".as_ref();
 
-    let master_salt = Self::hash_shared_secret(
+    let master_salt = MasterSalt::from(Self::hash_shared_secret(
       [challenge1.as_ref(), salt_cookie, challenge2.as_ref()],
       shared_secret,
-    );
+    ));
 
     let master_sender_key = Self::hash_shared_secret(
       [challenge2.as_ref(), key_cookie, challenge1.as_ref()],
@@ -129,7 +132,7 @@ impl CryptographicBuiltin {
       // The volatile topic has no payload protection
       KeyMaterial_AES_GCM_GMAC {
         transformation_kind: BuiltinCryptoTransformationKind::CRYPTO_TRANSFORMATION_KIND_NONE,
-        master_salt: BuiltinKey::None,
+        master_salt: MasterSalt::NONE,
         sender_key_id: CryptoTransformKeyId::ZERO,
         master_sender_key: BuiltinKey::None,
         receiver_specific_key_id: CryptoTransformKeyId::ZERO,
@@ -151,11 +154,54 @@ impl CryptographicBuiltin {
     BuiltinKey::from_bytes(KeyLength::AES256, hashed_secret.as_ref()).unwrap()
   }
 
-  fn use_256_bit_key(properties: &[Property]) -> bool {
+  // The key size given in the properties, if any. Only "128" selects 128-bit
+  // keys.
+  fn key_size_property(properties: &[Property]) -> Option<bool> {
     properties
       .iter()
-      .find(|property| property.name.eq("dds.sec.crypto.keysize"))
-      .map_or(true, |property| !property.value.eq("128"))
+      .find(|property| property.name.eq(CRYPTO_KEY_SIZE_PROPERTY_NAME))
+      .map(|property| !property.value.eq("128"))
+  }
+
+  fn rekey_grace_period_property(properties: &[Property]) -> Option<std::time::Duration> {
+    let property = properties
+      .iter()
+      .find(|property| property.name.eq(CRYPTO_REKEY_GRACE_PERIOD_PROPERTY_NAME))?;
+    match property.value.parse() {
+      Ok(millis) => Some(std::time::Duration::from_millis(millis)),
+      Err(e) => {
+        warn!(
+          "Ignoring {CRYPTO_REKEY_GRACE_PERIOD_PROPERTY_NAME} value {:?}: {e}",
+          property.value
+        );
+        None
+      }
+    }
+  }
+
+  // Whether origin authentication is enabled by the DataWriter properties,
+  // in addition to the governance document.
+  fn origin_authentication_property(properties: &[Property]) -> bool {
+    properties.iter().any(|property| {
+      property.name.eq(CRYPTO_ORIGIN_AUTHENTICATION_PROPERTY_NAME) && property.value.eq("true")
+    })
+  }
+
+  // Endpoints without a key size of their own use that of their participant,
+  // so that the whole participant can be configured at once.
+  fn endpoint_use_256_bit_key(
+    &self,
+    participant_crypto_handle: ParticipantCryptoHandle,
+    endpoint_properties: &[Property],
+  ) -> bool {
+    Self::key_size_property(endpoint_properties)
+      .or_else(|| {
+        self
+          .participant_use_256_bit_key
+          .get(&participant_crypto_handle)
+          .copied()
+      })
+      .unwrap_or(true)
   }
 
   fn transformation_kind(
@@ -176,7 +222,7 @@ impl CryptographicBuiltin {
     }
   }
 
-  fn generate_key_id(&mut self) -> CryptoTransformKeyId {
+  fn generate_key_id(&self) -> CryptoTransformKeyId {
     loop {
       let candidate = CryptoTransformKeyId::random();
       if !self.used_local_key_ids.contains(&candidate) {
@@ -187,14 +233,14 @@ impl CryptographicBuiltin {
   }
 
   fn generate_key_material(
-    &mut self,
+    &self,
     transformation_kind: BuiltinCryptoTransformationKind,
   ) -> KeyMaterial_AES_GCM_GMAC {
     let key_length = KeyLength::from(transformation_kind);
     KeyMaterial_AES_GCM_GMAC {
       transformation_kind,
 
-      master_salt: BuiltinKey::generate_random(key_length),
+      master_salt: MasterSalt::generate_random(key_length),
 
       sender_key_id: self.generate_key_id(),
       master_sender_key: keygen(key_length),
@@ -205,7 +251,7 @@ impl CryptographicBuiltin {
   }
 
   fn generate_receiver_specific_key(
-    &mut self,
+    &self,
     key_materials: KeyMaterial_AES_GCM_GMAC_seq,
     origin_authentication: bool,
   ) -> KeyMaterial_AES_GCM_GMAC_seq {
@@ -219,15 +265,86 @@ impl CryptographicBuiltin {
     }
   }
 
+  fn is_submessage_origin_authenticated(
+    &self,
+    local_endpoint_crypto_handle: EndpointCryptoHandle,
+  ) -> SecurityResult<bool> {
+    self
+      .endpoint_encrypt_options
+      .get(&local_endpoint_crypto_handle)
+      .ok_or_else(|| {
+        create_security_error_and_log!(
+          "Endpoint encrypt options not found for the EndpointCryptoHandle {}",
+          local_endpoint_crypto_handle
+        )
+      })
+      .and_then(|endpoint_security_attributes| {
+        BuiltinPluginEndpointSecurityAttributes::try_from(
+          endpoint_security_attributes.plugin_endpoint_attributes,
+        )
+      })
+      .map(|plugin_endpoint_attributes| {
+        plugin_endpoint_attributes.is_submessage_origin_authenticated
+      })
+  }
+
+  // Generates new common encode key materials for a local endpoint, of the
+  // same transformation kinds as before, and new receiver-specific materials
+  // for its matched remote endpoints. The remote endpoints need the new
+  // materials in crypto tokens to decode anything encoded after this.
+  fn rekey_local_endpoint(
+    &self,
+    local_endpoint_crypto_handle: EndpointCryptoHandle,
+  ) -> SecurityResult<()> {
+    let common_encode_key_materials =
+      self.get_common_encode_key_materials(&local_endpoint_crypto_handle)?;
+    // Encoders of this endpoint wait until all of its materials are replaced
+    let mut common_encode_key_materials = common_encode_key_materials.write().unwrap();
+    let key_materials = match &*common_encode_key_materials {
+      CommonEncodeKeyMaterials::Some(key_materials) => key_materials,
+      CommonEncodeKeyMaterials::Volatile => {
+        return Err(create_security_error_and_log!(
+          "The CryptoHandle {} is volatile. Its keys are derived from shared secrets and cannot \
+           be re-keyed.",
+          local_endpoint_crypto_handle
+        ))
+      }
+    };
+    let key_materials = match key_materials {
+      KeyMaterial_AES_GCM_GMAC_seq::One(key_material) => KeyMaterial_AES_GCM_GMAC_seq::One(
+        self.generate_key_material(key_material.transformation_kind),
+      ),
+      KeyMaterial_AES_GCM_GMAC_seq::Two(submessage_key_material, payload_key_material) => {
+        KeyMaterial_AES_GCM_GMAC_seq::Two(
+          self.generate_key_material(submessage_key_material.transformation_kind),
+          self.generate_key_material(payload_key_material.transformation_kind),
+        )
+      }
+    };
+    let is_submessage_origin_authenticated =
+      self.is_submessage_origin_authenticated(local_endpoint_crypto_handle)?;
+
+    let remote_endpoint_crypto_handles: Vec<EndpointCryptoHandle> = self
+      .matched_remote_endpoint
+      .get(&local_endpoint_crypto_handle)
+      .map(|remote_endpoints| remote_endpoints.values().copied().collect())
+      .unwrap_or_default();
+    for remote_endpoint_crypto_handle in remote_endpoint_crypto_handles {
+      let receiver_specific_key_materials = self
+        .generate_receiver_specific_key(key_materials.clone(), is_submessage_origin_authenticated);
+      self.receiver_specific_encode_key_materials.insert(
+        remote_endpoint_crypto_handle,
+        receiver_specific_key_materials,
+      );
+    }
+    *common_encode_key_materials = CommonEncodeKeyMaterials::Some(key_materials);
+    Ok(())
+  }
+
   fn unregister_endpoint(&mut self, endpoint_info: EndpointInfo) {
     let endpoint_crypto_handle = endpoint_info.crypto_handle;
-    self
-      .common_encode_key_materials
-      .remove(&endpoint_crypto_handle);
-    self
-      .receiver_specific_encode_key_materials
-      .remove(&endpoint_crypto_handle);
-    self.decode_key_materials.remove(&endpoint_crypto_handle);
+    self.handle_trackers.remove(&endpoint_crypto_handle);
+    self.remove_key_materials(endpoint_crypto_handle);
     self
       .endpoint_encrypt_options
       .remove(&endpoint_crypto_handle);
@@ -286,10 +403,18 @@ impl CryptoKeyFactory for CryptographicBuiltin {
       )?;
     let crypto_handle = self.generate_crypto_handle();
 
+    let use_256_bit_key = Self::key_size_property(participant_properties).unwrap_or(true);
+    self
+      .participant_use_256_bit_key
+      .insert(crypto_handle, use_256_bit_key);
+    if let Some(grace_period) = Self::rekey_grace_period_property(participant_properties) {
+      self.rekey_grace_period = grace_period;
+    }
+
     let key_material = self.generate_key_material(Self::transformation_kind(
       participant_security_attributes.is_rtps_protected,
       plugin_participant_security_attributes.is_rtps_encrypted,
-      Self::use_256_bit_key(participant_properties),
+      use_256_bit_key,
     ));
     self
       .insert_common_encode_key_materials(
@@ -311,11 +436,11 @@ impl CryptoKeyFactory for CryptographicBuiltin {
 
     let local_participant_key_materials = self
       .get_common_encode_key_materials(&local_participant_crypto_handle)
-      .cloned()
+      .map(|key_materials| key_materials.read().unwrap().clone())
       .and_then(
         |common_encode_key_materials| match common_encode_key_materials {
           CommonEncodeKeyMaterials::Some(value) => Ok(value),
-          CommonEncodeKeyMaterials::Volatile(_) => Err(create_security_error_and_log!(
+          CommonEncodeKeyMaterials::Volatile => Err(create_security_error_and_log!(
             "The local_participant_crypto_handle {} points to volatile, but a participant cannot \
              be volatile",
             local_participant_crypto_handle
@@ -360,23 +485,35 @@ impl CryptoKeyFactory for CryptographicBuiltin {
     &mut self,
     participant_crypto: ParticipantCryptoHandle,
     datawriter_properties: &[Property],
-    datawriter_security_attributes: EndpointSecurityAttributes,
+    mut datawriter_security_attributes: EndpointSecurityAttributes,
   ) -> SecurityResult<DatawriterCryptoHandle> {
     //TODO: this is only a mock implementation
     let plugin_endpoint_security_attributes = BuiltinPluginEndpointSecurityAttributes::try_from(
       datawriter_security_attributes.plugin_endpoint_attributes,
     )?;
 
+    // Receiver-specific MACs are appended to protected submessages only
+    if datawriter_security_attributes.is_submessage_protected
+      && Self::origin_authentication_property(datawriter_properties)
+    {
+      datawriter_security_attributes.plugin_endpoint_attributes =
+        BuiltinPluginEndpointSecurityAttributes {
+          is_submessage_origin_authenticated: true,
+          ..plugin_endpoint_security_attributes
+        }
+        .into();
+    }
+
     let local_datawriter_crypto_handle = self.generate_crypto_handle();
 
-    let use_256_bit_key = Self::use_256_bit_key(datawriter_properties);
+    let use_256_bit_key = self.endpoint_use_256_bit_key(participant_crypto, datawriter_properties);
 
     // The key material for volatile datawriter is derived from the shared secret in
     // register_matched_remote_datareader
     if Self::is_volatile(datawriter_properties) {
       self.insert_common_encode_key_materials(
         local_datawriter_crypto_handle,
-        CommonEncodeKeyMaterials::Volatile(use_256_bit_key),
+        CommonEncodeKeyMaterials::Volatile,
       )?;
     } else {
       let submessage_transformation_kind = Self::transformation_kind(
@@ -437,7 +574,7 @@ impl CryptoKeyFactory for CryptographicBuiltin {
     //TODO: this is only a mock implementation
     let common_encode_key_materials = self
       .get_common_encode_key_materials(&local_datawriter_crypto_handle)
-      .cloned()?;
+      .map(|key_materials| key_materials.read().unwrap().clone())?;
 
     // Find a handle for the remote datareader corresponding to the (remote
     // participant, local datawriter) pair, or generate a new one
@@ -448,9 +585,8 @@ impl CryptoKeyFactory for CryptographicBuiltin {
       );
 
     let receiver_specific_encode_key_materials = match common_encode_key_materials {
-      CommonEncodeKeyMaterials::Volatile(use_256_bit_key) => {
-        let volatile_key_materials =
-          Self::derive_volatile_key_materials(&shared_secret, use_256_bit_key)?;
+      CommonEncodeKeyMaterials::Volatile => {
+        let volatile_key_materials = Self::derive_volatile_key_materials(&shared_secret)?;
 
         // Instead of sending keys over the network like in other topics, the same key
         // material is used for decoding
@@ -461,23 +597,8 @@ impl CryptoKeyFactory for CryptographicBuiltin {
         volatile_key_materials
       }
       CommonEncodeKeyMaterials::Some(common_encode_key_materials) => {
-        let is_submessage_origin_authenticated = self
-          .endpoint_encrypt_options
-          .get(&local_datawriter_crypto_handle)
-          .ok_or_else(|| {
-            create_security_error_and_log!(
-              "Datawriter encrypt options not found for the DatawriterCryptoHandle {}",
-              local_datawriter_crypto_handle
-            )
-          })
-          .and_then(|datawriter_security_attributes| {
-            BuiltinPluginEndpointSecurityAttributes::try_from(
-              datawriter_security_attributes.plugin_endpoint_attributes,
-            )
-          })
-          .map(|plugin_datawriter_attributes| {
-            plugin_datawriter_attributes.is_submessage_origin_authenticated
-          })?;
+        let is_submessage_origin_authenticated =
+          self.is_submessage_origin_authenticated(local_datawriter_crypto_handle)?;
 
         self.generate_receiver_specific_key(
           common_encode_key_materials,
@@ -527,13 +648,14 @@ impl CryptoKeyFactory for CryptographicBuiltin {
 
     let local_datareader_crypto_handle = self.generate_crypto_handle();
 
-    let use_256_bit_key = Self::use_256_bit_key(datareader_properties);
+    let use_256_bit_key =
+      self.endpoint_use_256_bit_key(participant_crypto_handle, datareader_properties);
     // The key material for volatile datareader is derived from the shared secret in
     // register_matched_remote_datawriter
     if Self::is_volatile(datareader_properties) {
       self.insert_common_encode_key_materials(
         local_datareader_crypto_handle,
-        CommonEncodeKeyMaterials::Volatile(use_256_bit_key),
+        CommonEncodeKeyMaterials::Volatile,
       )?;
     } else {
       let key_material = self.generate_key_material(Self::transformation_kind(
@@ -573,7 +695,7 @@ impl CryptoKeyFactory for CryptographicBuiltin {
     //TODO: this is only a mock implementation
     let common_encode_key_materials = self
       .get_common_encode_key_materials(&local_datareader_crypto_handle)
-      .cloned()?;
+      .map(|key_materials| key_materials.read().unwrap().clone())?;
 
     // Find a handle for the remote datawriter corresponding to the (remote
     // participant, local datareader) pair, or generate a new one
@@ -584,9 +706,8 @@ impl CryptoKeyFactory for CryptographicBuiltin {
       );
 
     let receiver_specific_encode_key_materials = match common_encode_key_materials {
-      CommonEncodeKeyMaterials::Volatile(use_256_bit_key) => {
-        let volatile_key_materials =
-          Self::derive_volatile_key_materials(&shared_secret, use_256_bit_key)?;
+      CommonEncodeKeyMaterials::Volatile => {
+        let volatile_key_materials = Self::derive_volatile_key_materials(&shared_secret)?;
 
         // Instead of sending keys over the network like in other topics, the same key
         // material is used for decoding
@@ -597,23 +718,8 @@ impl CryptoKeyFactory for CryptographicBuiltin {
         volatile_key_materials
       }
       CommonEncodeKeyMaterials::Some(common_encode_key_materials) => {
-        let is_submessage_origin_authenticated = self
-          .endpoint_encrypt_options
-          .get(&local_datareader_crypto_handle)
-          .ok_or_else(|| {
-            create_security_error_and_log!(
-              "Datareader encrypt options not found for the handle {}",
-              local_datareader_crypto_handle
-            )
-          })
-          .and_then(|datareader_security_attributes| {
-            BuiltinPluginEndpointSecurityAttributes::try_from(
-              datareader_security_attributes.plugin_endpoint_attributes,
-            )
-          })
-          .map(|plugin_datareader_attributes| {
-            plugin_datareader_attributes.is_submessage_origin_authenticated
-          })?;
+        let is_submessage_origin_authenticated =
+          self.is_submessage_origin_authenticated(local_datareader_crypto_handle)?;
         self.generate_receiver_specific_key(
           common_encode_key_materials,
           is_submessage_origin_authenticated,
@@ -656,6 +762,9 @@ impl CryptoKeyFactory for CryptographicBuiltin {
     self
       .participant_encrypt_options
       .remove(&participant_crypto_handle);
+    self
+      .participant_use_256_bit_key
+      .remove(&participant_crypto_handle);
     if let Some(endpoint_info_set) = self
       .participant_to_endpoint_info
       .remove(&participant_crypto_handle)
@@ -664,13 +773,8 @@ impl CryptoKeyFactory for CryptographicBuiltin {
         self.unregister_endpoint(endpoint_info);
       }
     }
-    self
-      .common_encode_key_materials
-      .remove(&participant_crypto_handle);
-    self
-      .receiver_specific_encode_key_materials
-      .remove(&participant_crypto_handle);
-    self.decode_key_materials.remove(&participant_crypto_handle);
+    self.remove_key_materials(participant_crypto_handle);
+    self.handle_trackers.remove(&participant_crypto_handle);
     Ok(())
   }
 
@@ -697,4 +801,27 @@ impl CryptoKeyFactory for CryptographicBuiltin {
     });
     Ok(())
   }
+
+  fn rekey_local_datawriter(
+    &self,
+    local_datawriter_crypto_handle: DatawriterCryptoHandle,
+  ) -> SecurityResult<()> {
+    self.rekey_local_endpoint(local_datawriter_crypto_handle)
+  }
+
+  fn rekey_local_datareader(
+    &self,
+    local_datareader_crypto_handle: DatareaderCryptoHandle,
+  ) -> SecurityResult<()> {
+    self.rekey_local_endpoint(local_datareader_crypto_handle)
+  }
+
+  fn is_datawriter_origin_authenticated(
+    &self,
+    local_datawriter_crypto_handle: DatawriterCryptoHandle,
+  ) -> bool {
+    self
+      .is_submessage_origin_authenticated(local_datawriter_crypto_handle)
+      .unwrap_or(false)
+  }
 }
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use bytes::Bytes;
 use enumflags2::BitFlags;
 use speedy::{Readable, Writable};
@@ -21,7 +23,10 @@ use crate::{
     submessages::InterpreterSubmessage,
   },
   rtps::{Message, Submessage, SubmessageBody},
-  security::cryptographic::cryptographic_builtin::*,
+  security::{
+    cryptographic::cryptographic_builtin::*,
+    decode_limits::{DecodeLimiter, DecodeRejection},
+  },
 };
 use super::{
   aes_gcm_gmac::{compute_mac, decrypt, encrypt, validate_mac},
@@ -31,6 +36,15 @@ use super::{
 };
 
 impl CryptographicBuiltin {
+  // The number of receiver-specific MACs is checked from the serialized
+  // footer, before deserialization allocates space for them.
+  fn check_crypto_footer(&self, footer: &[u8]) -> Result<(), DecodeRejection> {
+    match BuiltinCryptoFooter::declared_receiver_specific_mac_count(footer) {
+      Some(count) => self.decode_limiter.check_receiver_specific_macs(count),
+      None => Ok(()), // Too short to be valid. Deserialization will fail.
+    }
+  }
+
   fn encode_submessage(
     &self,
     plain_rtps_submessage: Submessage,
@@ -338,6 +352,9 @@ impl CryptoTransform for CryptographicBuiltin {
         builtin_crypto_header_extra: BuiltinCryptoHeaderExtra(initialization_vector),
       } = BuiltinCryptoHeader::try_from(crypto_header.clone())?;
 
+      if let Err(rejection) = self.check_crypto_footer(&crypto_footer.data) {
+        return Ok(DecodeOutcome::LimitExceeded(rejection));
+      }
       let BuiltinCryptoFooter { common_mac, receiver_specific_macs }
         = BuiltinCryptoFooter::try_from(crypto_footer.clone())?;
 
@@ -492,6 +509,9 @@ impl CryptoTransform for CryptographicBuiltin {
       builtin_crypto_header_extra: BuiltinCryptoHeaderExtra(initialization_vector),
     } = BuiltinCryptoHeader::try_from(crypto_header)?;
 
+    if let Err(rejection) = self.check_crypto_footer(&crypto_footer.data) {
+      return Ok(DecodeOutcome::LimitExceeded(rejection));
+    }
     let BuiltinCryptoFooter {
       common_mac,
       receiver_specific_macs,
@@ -846,4 +866,12 @@ impl CryptoTransform for CryptographicBuiltin {
       }
     }
   }
+
+  fn set_decode_limiter(&mut self, limiter: Arc<DecodeLimiter>) {
+    self.decode_limiter = limiter;
+  }
+
+  fn session_key_cache_statistics(&self) -> Option<SessionKeyCacheStatistics> {
+    Some(self.session_keys.statistics())
+  }
 }
@@ -1,12 +1,13 @@
-use byteorder::BigEndian;
+use std::time::Instant;
+
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
+use speedy::Endianness;
 
 use crate::{
   create_security_error_and_log,
   security::{SecurityError, SecurityResult},
-  serialization::to_vec,
-  CdrDeserializer,
+  serialization::{from_bytes_with_endianness, to_vec_with_endianness},
 };
 use super::{
   builtin_key::*, BuiltinCryptoToken, BuiltinCryptoTransformationKind, CryptoToken,
@@ -19,7 +20,7 @@ use super::{
 #[derive(Clone, Debug)]
 pub(super) struct KeyMaterial_AES_GCM_GMAC {
   pub transformation_kind: BuiltinCryptoTransformationKind,
-  pub master_salt: BuiltinKey, // Salt length should match the keys by 9.5.3.3.2
+  pub master_salt: MasterSalt, // Salt length should match the keys by 9.5.3.3.2
   pub sender_key_id: CryptoTransformKeyId,
   pub master_sender_key: BuiltinKey,
   pub receiver_specific_key_id: CryptoTransformKeyId,
@@ -31,13 +32,18 @@ impl TryFrom<Bytes> for KeyMaterial_AES_GCM_GMAC {
   type Error = SecurityError;
   fn try_from(value: Bytes) -> Result<Self, Self::Error> {
     // Deserialize CDR-formatted key material
-    Serializable_KeyMaterial_AES_GCM_GMAC::deserialize(&mut CdrDeserializer::<BigEndian>::new(
+    from_bytes_with_endianness::<Serializable_KeyMaterial_AES_GCM_GMAC>(
       value.as_ref(),
-    ))
+      Endianness::BigEndian,
+    )
+    .map(|(key_material, _)| key_material)
     .map_err(
       // Map deserialization error to SecurityError
-      |e| Self::Error {
-        msg: format!("Error deserializing KeyMaterial_AES_GCM_GMAC: {}", e),
+      |e| {
+        SecurityError::new(format!(
+          "Error deserializing KeyMaterial_AES_GCM_GMAC: {}",
+          e
+        ))
       },
     )
     .and_then(KeyMaterial_AES_GCM_GMAC::try_from)
@@ -49,11 +55,9 @@ impl TryFrom<KeyMaterial_AES_GCM_GMAC> for Bytes {
     // Convert the key material to the serializable structure
     let serializable_key_material = Serializable_KeyMaterial_AES_GCM_GMAC::from(key_material);
     // Serialize
-    to_vec::<Serializable_KeyMaterial_AES_GCM_GMAC, BigEndian>(&serializable_key_material)
+    to_vec_with_endianness(&serializable_key_material, Endianness::BigEndian)
       .map(Bytes::from)
-      .map_err(|e| Self::Error {
-        msg: format!("Error serializing KeyMaterial_AES_GCM_GMAC: {}", e),
-      })
+      .map_err(|e| SecurityError::new(format!("Error serializing KeyMaterial_AES_GCM_GMAC: {}", e)))
   }
 }
 
@@ -174,13 +178,17 @@ impl TryFrom<Bytes> for KeyMaterial_AES_GCM_GMAC_seq {
   type Error = SecurityError;
   fn try_from(value: Bytes) -> Result<Self, Self::Error> {
     // Deserialize CDR-formatted key material
-    let serializable_key_materials = Vec::<Serializable_KeyMaterial_AES_GCM_GMAC>::deserialize(
-      &mut CdrDeserializer::<BigEndian>::new(value.as_ref()),
-    )
+    let serializable_key_materials = from_bytes_with_endianness::<
+      Vec<Serializable_KeyMaterial_AES_GCM_GMAC>,
+    >(value.as_ref(), Endianness::BigEndian)
+    .map(|(key_materials, _)| key_materials)
     .map_err(
       // Map deserialization error to SecurityError
-      |e| Self::Error {
-        msg: format!("Error deserializing Vec<KeyMaterial_AES_GCM_GMAC>: {}", e),
+      |e| {
+        SecurityError::new(format!(
+          "Error deserializing Vec<KeyMaterial_AES_GCM_GMAC>: {}",
+          e
+        ))
       },
     )?;
 
@@ -201,16 +209,20 @@ impl TryFrom<KeyMaterial_AES_GCM_GMAC_seq> for Bytes {
   type Error = SecurityError;
   fn try_from(key_materials: KeyMaterial_AES_GCM_GMAC_seq) -> Result<Self, Self::Error> {
     // Convert the key material to the serializable structure
-    let serializable_key_materials = Vec::from(key_materials)
-      .iter()
-      .map(|key_material| Serializable_KeyMaterial_AES_GCM_GMAC::from(key_material.clone()))
-      .collect();
+    let serializable_key_materials: Vec<Serializable_KeyMaterial_AES_GCM_GMAC> =
+      Vec::from(key_materials)
+        .iter()
+        .map(|key_material| Serializable_KeyMaterial_AES_GCM_GMAC::from(key_material.clone()))
+        .collect();
 
     // Serialize
-    to_vec::<Vec<Serializable_KeyMaterial_AES_GCM_GMAC>, BigEndian>(&serializable_key_materials)
+    to_vec_with_endianness(&serializable_key_materials, Endianness::BigEndian)
       .map(Bytes::from)
-      .map_err(|e| Self::Error {
-        msg: format!("Error serializing KeyMaterial_AES_GCM_GMAC_seq: {}", e),
+      .map_err(|e| {
+        SecurityError::new(format!(
+          "Error serializing KeyMaterial_AES_GCM_GMAC_seq: {}",
+          e
+        ))
       })
   }
 }
@@ -269,7 +281,7 @@ pub(super) struct ReceiverSpecificKeyMaterial {
   pub key: BuiltinKey,
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub(super) enum ReceiverSpecific {
   No,
   Yes,
@@ -303,16 +315,16 @@ impl TryFrom<KeyMaterial_AES_GCM_GMAC_seq> for Vec<CryptoToken> {
 #[derive(Deserialize, Serialize, PartialEq, Clone)]
 struct Serializable_KeyMaterial_AES_GCM_GMAC {
   transformation_kind: CryptoTransformKind,
-  master_salt: Vec<u8>, // sequence<octet, 32>
+  master_salt: KeyMaterialOctets, // sequence<octet, 32>
   sender_key_id: CryptoTransformKeyId,
-  master_sender_key: Vec<u8>, // sequence<octet, 32>
+  master_sender_key: KeyMaterialOctets, // sequence<octet, 32>
   receiver_specific_key_id: CryptoTransformKeyId,
-  master_receiver_specific_key: Vec<u8>, // sequence<octet, 32>
+  master_receiver_specific_key: KeyMaterialOctets, // sequence<octet, 32>
 }
 
 // The `sequence<octet, 32>` IDL type in the spec means variable-length
-// sequence. Vec<u8> is encoding-compatible, as long as we limit the length to
-// 32.
+// sequence. KeyMaterialOctets is encoding-compatible with Vec<u8>, and limits
+// the length to 32.
 
 impl TryFrom<Serializable_KeyMaterial_AES_GCM_GMAC> for KeyMaterial_AES_GCM_GMAC {
   type Error = SecurityError;
@@ -334,14 +346,14 @@ impl TryFrom<Serializable_KeyMaterial_AES_GCM_GMAC> for KeyMaterial_AES_GCM_GMAC
     let master_receiver_specific_key = if receiver_specific_key_id.eq(&CryptoTransformKeyId::ZERO) {
       BuiltinKey::None
     } else {
-      BuiltinKey::from_bytes(key_length, &master_receiver_specific_key)?
+      BuiltinKey::from_bytes(key_length, master_receiver_specific_key.as_bytes())?
     };
 
     Ok(Self {
       transformation_kind,
-      master_salt: BuiltinKey::from_bytes(key_length, &master_salt)?,
+      master_salt: MasterSalt::from_bytes(key_length, master_salt.as_bytes())?,
       sender_key_id,
-      master_sender_key: BuiltinKey::from_bytes(key_length, &master_sender_key)?,
+      master_sender_key: BuiltinKey::from_bytes(key_length, master_sender_key.as_bytes())?,
       receiver_specific_key_id,
       master_receiver_specific_key,
     })
@@ -361,11 +373,11 @@ impl From<KeyMaterial_AES_GCM_GMAC> for Serializable_KeyMaterial_AES_GCM_GMAC {
   ) -> Self {
     Serializable_KeyMaterial_AES_GCM_GMAC {
       transformation_kind: transformation_kind.into(),
-      master_salt: master_salt.as_bytes().into(),
+      master_salt: KeyMaterialOctets::from(&master_salt),
       sender_key_id,
-      master_sender_key: master_sender_key.as_bytes().into(),
+      master_sender_key: KeyMaterialOctets::from(&master_sender_key),
       receiver_specific_key_id,
-      master_receiver_specific_key: master_receiver_specific_key.as_bytes().into(),
+      master_receiver_specific_key: KeyMaterialOctets::from(&master_receiver_specific_key),
     }
   }
 }
@@ -380,5 +392,62 @@ impl From<KeyMaterial_AES_GCM_GMAC> for Serializable_KeyMaterial_AES_GCM_GMAC {
 #[allow(clippy::large_enum_variant)]
 pub(super) enum CommonEncodeKeyMaterials {
   Some(KeyMaterial_AES_GCM_GMAC_seq),
-  Volatile(bool), // bool is for use_256_bit_key
+  // Volatile endpoints always use AES256_GCM: 9.5.2.1.2
+  Volatile,
+}
+
+// The key materials a remote entity encodes with, and the ones it used before
+// it re-keyed, each with the instant until which they are kept. Messages
+// encoded before the re-keying may still be in flight, and can be decoded with
+// the previous materials.
+#[derive(Clone)]
+pub(super) struct DecodeKeyMaterials {
+  current: KeyMaterial_AES_GCM_GMAC_seq,
+  previous: Vec<(Instant, KeyMaterial_AES_GCM_GMAC_seq)>,
+}
+
+impl DecodeKeyMaterials {
+  pub fn new(current: KeyMaterial_AES_GCM_GMAC_seq) -> Self {
+    Self {
+      current,
+      previous: Vec::new(),
+    }
+  }
+
+  // The materials after replacing the current ones. The current ones are kept
+  // until `keep_until`, unless the same tokens were received again, e.g.
+  // because they were resent.
+  pub fn replaced(
+    &self,
+    current: KeyMaterial_AES_GCM_GMAC_seq,
+    now: Instant,
+    keep_until: Instant,
+  ) -> Self {
+    let mut previous: Vec<_> = self
+      .previous
+      .iter()
+      .filter(|(previous_keep_until, _)| *previous_keep_until > now)
+      .cloned()
+      .collect();
+    if self.current.key_material().sender_key_id != current.key_material().sender_key_id {
+      previous.push((keep_until, self.current.clone()));
+    }
+    Self { current, previous }
+  }
+
+  // The current materials, followed by the previous ones that are still kept
+  // at `now`
+  pub fn usable_at(&self, now: Instant) -> impl Iterator<Item = &KeyMaterial_AES_GCM_GMAC_seq> {
+    let previous = self
+      .previous
+      .iter()
+      .filter(move |(keep_until, _)| *keep_until > now)
+      .map(|(_, key_materials)| key_materials);
+    std::iter::once(&self.current).chain(previous)
+  }
+
+  pub fn all(&self) -> impl Iterator<Item = &KeyMaterial_AES_GCM_GMAC_seq> {
+    std::iter::once(&self.current)
+      .chain(self.previous.iter().map(|(_, key_materials)| key_materials))
+  }
 }
@@ -0,0 +1,65 @@
+use std::{
+  collections::{hash_map::Entry, HashMap},
+  sync::{Arc, RwLock},
+};
+
+use crate::security::cryptographic::types::CryptoHandle;
+
+// Key materials of entities, indexed by their crypto handles.
+//
+// Each entry is behind an Arc. Encoding and decoding clone the entry of the
+// entity they need and release the map lock right away. Key exchange and
+// re-keying build the new materials first, and then swap the entry of that one
+// entity. So updating the keys of one endpoint does not need exclusive access
+// to the plugin, and holds up the other endpoints only for the time it takes to
+// swap a pointer.
+pub(super) struct KeyMaterialMap<T> {
+  entries: RwLock<HashMap<CryptoHandle, Arc<T>>>,
+}
+
+impl<T> Default for KeyMaterialMap<T> {
+  fn default() -> Self {
+    Self {
+      entries: RwLock::new(HashMap::new()),
+    }
+  }
+}
+
+impl<T> KeyMaterialMap<T> {
+  pub fn get(&self, crypto_handle: &CryptoHandle) -> Option<Arc<T>> {
+    self.entries.read().unwrap().get(crypto_handle).cloned()
+  }
+
+  // Inserts the materials, unless the handle already has some. Returns whether
+  // they were inserted.
+  pub fn insert_new(&self, crypto_handle: CryptoHandle, key_materials: T) -> bool {
+    match self.entries.write().unwrap().entry(crypto_handle) {
+      Entry::Vacant(entry) => {
+        entry.insert(Arc::new(key_materials));
+        true
+      }
+      Entry::Occupied(_) => false,
+    }
+  }
+
+  pub fn insert(&self, crypto_handle: CryptoHandle, key_materials: T) -> Option<Arc<T>> {
+    self
+      .entries
+      .write()
+      .unwrap()
+      .insert(crypto_handle, Arc::new(key_materials))
+  }
+
+  // Replaces the materials of the handle with ones computed from the current
+  // ones. Concurrent updates of the same handle are applied one after the
+  // other, so `update` should be cheap.
+  pub fn update(&self, crypto_handle: CryptoHandle, update: impl FnOnce(Option<&T>) -> T) {
+    let mut entries = self.entries.write().unwrap();
+    let updated = update(entries.get(&crypto_handle).map(Arc::as_ref));
+    entries.insert(crypto_handle, Arc::new(updated));
+  }
+
+  pub fn remove(&self, crypto_handle: &CryptoHandle) -> Option<Arc<T>> {
+    self.entries.write().unwrap().remove(crypto_handle)
+  }
+}
@@ -0,0 +1,226 @@
+use std::{
+  collections::HashMap,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    RwLock,
+  },
+};
+
+use crate::security::cryptographic::types::{CryptoTransformKeyId, SessionKeyCacheStatistics};
+use super::{
+  builtin_key::{BuiltinKey, MasterSalt},
+  key_material::ReceiverSpecific,
+  types::SessionId,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct SessionKeyId {
+  key_id: CryptoTransformKeyId,
+  session_id: SessionId,
+  receiver_specific: ReceiverSpecific,
+}
+
+struct CachedSessionKey {
+  // What the session key was derived from. Key ids are chosen by the remote
+  // participants, so different master keys may share one. A cached key is used
+  // only if it was derived from the same master key and salt.
+  master_key: BuiltinKey,
+  master_salt: MasterSalt,
+  session_key: BuiltinKey,
+  // Updated under the read lock
+  last_used: AtomicU64,
+}
+
+// Session keys derived from master keys, so that the HMAC of section 9.5.3.3.3
+// "Computation of SessionKey and SessionReceiverSpecificKey" of the DDS
+// Security spec v1.1 is not computed for every encoded or decoded
+// (sub)message. A session key depends on the master key, the master salt and
+// the session id only, and a session typically lasts for many messages.
+//
+// The cache is bounded, because received session ids are arbitrary. When it is
+// full, the least recently used key is evicted.
+//
+// Lookups of cached keys take only the read lock, so that endpoints encode and
+// decode in parallel. The write lock is taken to insert a new session key,
+// after it has been derived.
+pub(super) struct SessionKeyCache {
+  keys: RwLock<HashMap<SessionKeyId, CachedSessionKey>>,
+  capacity: usize,
+  // Incremented on every lookup, to order the keys by use
+  clock: AtomicU64,
+  hits: AtomicU64,
+  misses: AtomicU64,
+  evictions: AtomicU64,
+}
+
+impl SessionKeyCache {
+  pub const DEFAULT_CAPACITY: usize = 1024;
+
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      keys: RwLock::new(HashMap::new()),
+      capacity: capacity.max(1),
+      clock: AtomicU64::new(0),
+      hits: AtomicU64::new(0),
+      misses: AtomicU64::new(0),
+      evictions: AtomicU64::new(0),
+    }
+  }
+
+  // The session key for the given master key material and session, either
+  // cached or computed with `derive`. Concurrent lookups of a missing key may
+  // both derive it, which gives the same key.
+  pub fn get_or_derive(
+    &self,
+    key_id: CryptoTransformKeyId,
+    session_id: SessionId,
+    receiver_specific: ReceiverSpecific,
+    master_key: &BuiltinKey,
+    master_salt: &MasterSalt,
+    derive: impl FnOnce() -> BuiltinKey,
+  ) -> BuiltinKey {
+    let now = self.clock.fetch_add(1, Ordering::Relaxed) + 1;
+    let id = SessionKeyId {
+      key_id,
+      session_id,
+      receiver_specific,
+    };
+    if let Some(cached) = self.keys.read().unwrap().get(&id) {
+      if cached.master_key == *master_key && cached.master_salt == *master_salt {
+        cached.last_used.store(now, Ordering::Relaxed);
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        return cached.session_key.clone();
+      }
+    }
+
+    self.misses.fetch_add(1, Ordering::Relaxed);
+    let session_key = derive();
+    let mut keys = self.keys.write().unwrap();
+    if !keys.contains_key(&id) && keys.len() >= self.capacity {
+      self.evict_least_recently_used(&mut keys);
+    }
+    keys.insert(
+      id,
+      CachedSessionKey {
+        master_key: master_key.clone(),
+        master_salt: master_salt.clone(),
+        session_key: session_key.clone(),
+        last_used: AtomicU64::new(now),
+      },
+    );
+    session_key
+  }
+
+  // Forgets the session keys derived from the master keys with this id, e.g.
+  // when the key material is removed.
+  pub fn remove_key_id(&self, key_id: CryptoTransformKeyId) {
+    let mut keys = self.keys.write().unwrap();
+    let count = keys.len();
+    keys.retain(|id, _| id.key_id != key_id);
+    self
+      .evictions
+      .fetch_add((count - keys.len()) as u64, Ordering::Relaxed);
+  }
+
+  pub fn statistics(&self) -> SessionKeyCacheStatistics {
+    SessionKeyCacheStatistics {
+      hits: self.hits.load(Ordering::Relaxed),
+      misses: self.misses.load(Ordering::Relaxed),
+      evictions: self.evictions.load(Ordering::Relaxed),
+      cached_keys: self.keys.read().unwrap().len(),
+    }
+  }
+
+  fn evict_least_recently_used(&self, keys: &mut HashMap<SessionKeyId, CachedSessionKey>) {
+    let oldest = keys
+      .iter()
+      .min_by_key(|(_, cached)| cached.last_used.load(Ordering::Relaxed))
+      .map(|(id, _)| *id);
+    if let Some(oldest) = oldest {
+      keys.remove(&oldest);
+      self.evictions.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+}
+
+impl Default for SessionKeyCache {
+  fn default() -> Self {
+    Self::new(Self::DEFAULT_CAPACITY)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::security::cryptographic::cryptographic_builtin::builtin_key::KeyLength;
+
+  fn key(byte: u8) -> BuiltinKey {
+    BuiltinKey::from_bytes(KeyLength::AES128, &[byte; 16]).unwrap()
+  }
+
+  #[test]
+  fn session_keys_are_derived_once_per_session() {
+    let cache = SessionKeyCache::new(2);
+    let salt = MasterSalt::from(key(0));
+    let key_id = CryptoTransformKeyId::from([0, 0, 0, 1]);
+    let mut derivations = 0;
+    let mut get = |cache: &SessionKeyCache, session: u8, master_key: &BuiltinKey| {
+      cache.get_or_derive(
+        key_id,
+        SessionId::new([0, 0, 0, session]),
+        ReceiverSpecific::No,
+        master_key,
+        &salt,
+        || {
+          derivations += 1;
+          key(session)
+        },
+      )
+    };
+
+    for _ in 0..100 {
+      assert_eq!(get(&cache, 1, &key(10)), key(1));
+    }
+    // Another master key with the same key id is not given the cached key
+    get(&cache, 1, &key(11));
+    get(&cache, 2, &key(11));
+    // Session 1 is the least recently used
+    get(&cache, 3, &key(11));
+    get(&cache, 2, &key(11));
+    assert_eq!(derivations, 4);
+
+    let statistics = cache.statistics();
+    assert_eq!(statistics.hits, 100);
+    assert_eq!(statistics.misses, 4);
+    assert_eq!(statistics.evictions, 1);
+    assert_eq!(statistics.cached_keys, 2);
+
+    cache.remove_key_id(key_id);
+    assert_eq!(cache.statistics().cached_keys, 0);
+  }
+
+  #[test]
+  fn session_keys_are_derived_outside_the_lock() {
+    let cache = SessionKeyCache::new(2);
+    let salt = MasterSalt::from(key(0));
+    let key_id = CryptoTransformKeyId::from([0, 0, 0, 1]);
+    let get = |session: u8, derive: &mut dyn FnMut() -> BuiltinKey| {
+      cache.get_or_derive(
+        key_id,
+        SessionId::new([0, 0, 0, session]),
+        ReceiverSpecific::No,
+        &key(10),
+        &salt,
+        derive,
+      )
+    };
+    get(1, &mut || key(1));
+    // Other lookups are not blocked while a session key is being derived
+    let derived = get(2, &mut || {
+      assert_eq!(get(1, &mut || unreachable!("session 1 is cached")), key(1));
+      key(2)
+    });
+    assert_eq!(derived, key(2));
+    assert_eq!(cache.statistics().hits, 1);
+  }
+}
@@ -1,6 +1,5 @@
-use byteorder::BigEndian;
 use serde::{Deserialize, Serialize};
-use speedy::Readable;
+use speedy::{Endianness, Readable};
 
 use crate::{
   create_security_error_and_log,
@@ -9,8 +8,7 @@ use crate::{
     crypto_header::{CryptoHeader, PluginCryptoHeaderExtra},
   },
   security::{cryptographic::EndpointCryptoHandle, BinaryProperty, DataHolder, SecurityError},
-  serialization::to_vec,
-  CdrDeserializer,
+  serialization::{from_bytes_with_endianness, to_vec_with_endianness},
 };
 use super::{
   key_material::*, CryptoToken, CryptoTransformIdentifier, CryptoTransformKeyId,
@@ -40,30 +38,24 @@ impl TryFrom<CryptoToken> for BuiltinCryptoToken {
             key_material: KeyMaterial_AES_GCM_GMAC::try_from(bp0.value.clone())?,
           })
         } else {
-          Err(Self::Error {
-            msg: format!(
-              "The binary property of CryptoToken has the wrong name. Expected {}, got {}.",
-              CRYPTO_TOKEN_KEY_MATERIAL_NAME, bp0.name
-            ),
-          })
+          Err(SecurityError::new(format!(
+            "The binary property of CryptoToken has the wrong name. Expected {}, got {}.",
+            CRYPTO_TOKEN_KEY_MATERIAL_NAME, bp0.name
+          )))
         }
       }
 
-      (CRYPTO_TOKEN_CLASS_ID, [], _) => Err(Self::Error {
-        msg: String::from(
-          "CryptoToken has wrong binary_properties. Expected exactly 1 binary property.",
-        ),
-      }),
-      (CRYPTO_TOKEN_CLASS_ID, _, _) => Err(Self::Error {
-        msg: String::from("CryptoToken has wrong properties. Expected properties to be empty."),
-      }),
-
-      (cid, _, _) => Err(Self::Error {
-        msg: format!(
-          "CryptoToken has wrong class_id. Expected {}, got {}",
-          CRYPTO_TOKEN_CLASS_ID, cid
-        ),
-      }),
+      (CRYPTO_TOKEN_CLASS_ID, [], _) => Err(SecurityError::new(String::from(
+        "CryptoToken has wrong binary_properties. Expected exactly 1 binary property.",
+      ))),
+      (CRYPTO_TOKEN_CLASS_ID, _, _) => Err(SecurityError::new(String::from(
+        "CryptoToken has wrong properties. Expected properties to be empty.",
+      ))),
+
+      (cid, _, _) => Err(SecurityError::new(format!(
+        "CryptoToken has wrong class_id. Expected {}, got {}",
+        CRYPTO_TOKEN_CLASS_ID, cid
+      ))),
     }
   }
 }
@@ -116,9 +108,9 @@ impl TryFrom<CryptoTransformKind> for BuiltinCryptoTransformationKind {
       [0, 0, 0, 2] => Ok(Self::CRYPTO_TRANSFORMATION_KIND_AES128_GCM),
       [0, 0, 0, 3] => Ok(Self::CRYPTO_TRANSFORMATION_KIND_AES256_GMAC),
       [0, 0, 0, 4] => Ok(Self::CRYPTO_TRANSFORMATION_KIND_AES256_GCM),
-      _ => Err(Self::Error {
-        msg: String::from("Invalid CryptoTransformKind"),
-      }),
+      _ => Err(SecurityError::new(String::from(
+        "Invalid CryptoTransformKind",
+      ))),
     }
   }
 }
@@ -208,7 +200,7 @@ impl From<BuiltinInitializationVector> for [u8; INITIALIZATION_VECTOR_LENGTH] {
   }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) struct SessionId([u8; 4]);
 
 impl SessionId {
@@ -342,7 +334,7 @@ pub(super) type BuiltinMAC = [u8; MAC_LENGTH];
 
 /// CryptoFooter type from section 9.5.2.5 of the Security specification (v.
 /// 1.1)
-#[derive(Deserialize, Serialize, PartialEq)]
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub(super) struct BuiltinCryptoFooter {
   pub common_mac: BuiltinMAC,
   pub receiver_specific_macs: Vec<ReceiverSpecificMAC>,
@@ -354,6 +346,13 @@ impl BuiltinCryptoFooter {
     + 4 // receiver_specific_macs = Vec::new()
   }
 
+  // Number of receiver-specific MACs that serialized footer data claims to
+  // have, or None if the data is too short.
+  pub fn declared_receiver_specific_mac_count(data: &[u8]) -> Option<usize> {
+    let count = data.get(MAC_LENGTH..MAC_LENGTH + 4)?;
+    Some(u32::from_be_bytes(count.try_into().ok()?) as usize)
+  }
+
   pub fn only_common_mac(common_mac: BuiltinMAC) -> Self {
     BuiltinCryptoFooter {
       common_mac,
@@ -366,12 +365,12 @@ impl TryFrom<&[u8]> for BuiltinCryptoFooter {
   type Error = SecurityError;
   fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
     // Deserialize the data
-    BuiltinCryptoFooter::deserialize(&mut CdrDeserializer::<BigEndian>::new(data)).map_err(
-      // Map deserialization error to SecurityError
-      |e| Self::Error {
-        msg: format!("Error deserializing BuiltinCryptoFooter: {}", e),
-      },
-    )
+    from_bytes_with_endianness::<BuiltinCryptoFooter>(data, Endianness::BigEndian)
+      .map(|(footer, _)| footer)
+      .map_err(
+        // Map deserialization error to SecurityError
+        |e| SecurityError::new(format!("Error deserializing BuiltinCryptoFooter: {}", e)),
+      )
   }
 }
 impl TryFrom<CryptoFooter> for BuiltinCryptoFooter {
@@ -384,9 +383,8 @@ impl TryFrom<BuiltinCryptoFooter> for Vec<u8> {
   type Error = SecurityError;
   fn try_from(value: BuiltinCryptoFooter) -> Result<Self, Self::Error> {
     // Serialize
-    to_vec::<BuiltinCryptoFooter, BigEndian>(&value).map_err(|e| Self::Error {
-      msg: format!("Error serializing BuiltinCryptoFooter: {}", e),
-    })
+    to_vec_with_endianness(&value, Endianness::BigEndian)
+      .map_err(|e| SecurityError::new(format!("Error serializing BuiltinCryptoFooter: {}", e)))
   }
 }
 impl TryFrom<BuiltinCryptoFooter> for CryptoFooter {
@@ -423,3 +421,55 @@ pub(super) struct EndpointInfo {
   pub crypto_handle: EndpointCryptoHandle,
   pub kind: EndpointKind,
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Section 9.5.2.5: common_mac, then the sequence of (key id, MAC) pairs,
+  // all big-endian.
+  #[test]
+  fn crypto_footer_golden_bytes() {
+    let footer = BuiltinCryptoFooter {
+      common_mac: [0xc0; MAC_LENGTH],
+      receiver_specific_macs: vec![
+        ReceiverSpecificMAC {
+          receiver_mac_key_id: CryptoTransformKeyId::from([0, 0, 0, 1]),
+          receiver_mac: [0xa1; MAC_LENGTH],
+        },
+        ReceiverSpecificMAC {
+          receiver_mac_key_id: CryptoTransformKeyId::from([0, 0, 0, 2]),
+          receiver_mac: [0xa2; MAC_LENGTH],
+        },
+      ],
+    };
+    let mut expected = vec![0xc0; MAC_LENGTH];
+    expected.extend_from_slice(&[0, 0, 0, 2]);
+    expected.extend_from_slice(&[0, 0, 0, 1]);
+    expected.extend_from_slice(&[0xa1; MAC_LENGTH]);
+    expected.extend_from_slice(&[0, 0, 0, 2]);
+    expected.extend_from_slice(&[0xa2; MAC_LENGTH]);
+
+    let crypto_footer = CryptoFooter::try_from(footer).unwrap();
+    assert_eq!(crypto_footer.data, expected);
+    assert_eq!(
+      BuiltinCryptoFooter::declared_receiver_specific_mac_count(&expected),
+      Some(2)
+    );
+
+    let decoded = BuiltinCryptoFooter::try_from(crypto_footer).unwrap();
+    assert_eq!(decoded.receiver_specific_macs.len(), 2);
+    assert_eq!(
+      decoded.receiver_specific_macs[1].receiver_mac,
+      [0xa2; MAC_LENGTH]
+    );
+
+    let only_common =
+      CryptoFooter::try_from(BuiltinCryptoFooter::only_common_mac([7; MAC_LENGTH])).unwrap();
+    assert_eq!(
+      only_common.data.len(),
+      BuiltinCryptoFooter::minimal_serialized_len()
+    );
+    assert!(BuiltinCryptoFooter::try_from(&expected[..MAC_LENGTH + 10]).is_err());
+  }
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use crate::{
   messages::submessages::{
     elements::parameter_list::ParameterList, secure_postfix::SecurePostfix,
@@ -5,7 +7,8 @@ use crate::{
   },
   rtps::{Message, Submessage},
   security::{
-    access_control::types::*, authentication::types::*, cryptographic::types::*, types::*,
+    access_control::types::*, authentication::types::*, cryptographic::types::*,
+    decode_limits::DecodeLimiter, types::*,
   },
 };
 // Imports for doc references
@@ -89,9 +92,53 @@ pub trait CryptoKeyFactory: Send {
     &mut self,
     datareader_crypto_handle: DatareaderCryptoHandle,
   ) -> SecurityResult<()>;
+
+  /// Not in the specification. Replaces the key material of a local
+  /// DataWriter with newly generated material, so that long-lived writers
+  /// can rotate their keys. The new keys are used for encoding from then on,
+  /// so the crypto tokens of the matched remote DataReaders must be created
+  /// and sent again. Like key exchange, re-keying takes a shared reference.
+  /// The default implementation does not support re-keying.
+  fn rekey_local_datawriter(
+    &self,
+    _local_datawriter_crypto_handle: DatawriterCryptoHandle,
+  ) -> SecurityResult<()> {
+    Err(security_error(
+      "This Cryptographic plugin does not support re-keying",
+    ))
+  }
+
+  /// Not in the specification. Like
+  /// [`rekey_local_datawriter`](Self::rekey_local_datawriter), but for a
+  /// local DataReader, whose new crypto tokens must be sent to the matched
+  /// remote DataWriters.
+  fn rekey_local_datareader(
+    &self,
+    _local_datareader_crypto_handle: DatareaderCryptoHandle,
+  ) -> SecurityResult<()> {
+    Err(security_error(
+      "This Cryptographic plugin does not support re-keying",
+    ))
+  }
+
+  /// Not in the specification. Whether the encoded submessages of a local
+  /// DataWriter carry a receiver-specific MAC for each receiving DataReader,
+  /// so that their size grows with the number of receivers. The default
+  /// implementation returns false.
+  fn is_datawriter_origin_authenticated(
+    &self,
+    _local_datawriter_crypto_handle: DatawriterCryptoHandle,
+  ) -> bool {
+    false
+  }
 }
 
 /// CryptoKeyExchange: section 8.5.1.8 of the Security specification (v. 1.1)
+///
+/// Differs from the specification by taking shared references, so that
+/// exchanging keys with one remote entity does not stop encoding and decoding
+/// for the others. Implementations keep the key materials behind locks of
+/// their own.
 pub trait CryptoKeyExchange {
   /// create_local_participant_crypto_tokens: section 8.5.1.8.1 of the Security
   /// specification (v. 1.1)
@@ -99,7 +146,7 @@ pub trait CryptoKeyExchange {
   /// In a vector, return the tokens that would be written in
   /// `local_participant_crypto_tokens`.
   fn create_local_participant_crypto_tokens(
-    &mut self,
+    &self,
     local_participant_crypto_handle: ParticipantCryptoHandle,
     remote_participant_crypto_handle: ParticipantCryptoHandle,
   ) -> SecurityResult<Vec<ParticipantCryptoToken>>;
@@ -107,7 +154,7 @@ pub trait CryptoKeyExchange {
   /// set_remote_participant_crypto_tokens: section 8.5.1.8.2 of the Security
   /// specification (v. 1.1)
   fn set_remote_participant_crypto_tokens(
-    &mut self,
+    &self,
     local_participant_crypto_handle: ParticipantCryptoHandle,
     remote_participant_crypto_handle: ParticipantCryptoHandle,
     remote_participant_tokens: Vec<ParticipantCryptoToken>,
@@ -119,7 +166,7 @@ pub trait CryptoKeyExchange {
   /// In a vector, return the tokens that would be written in
   /// `local_datawriter_crypto_tokens`.
   fn create_local_datawriter_crypto_tokens(
-    &mut self,
+    &self,
     local_datawriter_crypto_handle: DatawriterCryptoHandle,
     remote_datareader_crypto_handle: DatareaderCryptoHandle,
   ) -> SecurityResult<Vec<DatawriterCryptoToken>>;
@@ -127,7 +174,7 @@ pub trait CryptoKeyExchange {
   /// set_remote_datawriter_crypto_tokens: section 8.5.1.8.4 of the Security
   /// specification (v. 1.1)
   fn set_remote_datawriter_crypto_tokens(
-    &mut self,
+    &self,
     local_datareader_crypto_handle: DatareaderCryptoHandle,
     remote_datawriter_crypto_handle: DatawriterCryptoHandle,
     remote_datawriter_tokens: Vec<DatawriterCryptoToken>,
@@ -139,7 +186,7 @@ pub trait CryptoKeyExchange {
   /// In a vector, return the tokens that would be written in
   /// `local_datareader_crypto_tokens`.
   fn create_local_datareader_crypto_tokens(
-    &mut self,
+    &self,
     local_datareader_crypto_handle: DatareaderCryptoHandle,
     remote_datawriter_crypto_handle: DatawriterCryptoHandle,
   ) -> SecurityResult<Vec<DatareaderCryptoToken>>;
@@ -147,7 +194,7 @@ pub trait CryptoKeyExchange {
   /// set_remote_datareader_crypto_tokens: section 8.5.1.8.6 of the Security
   /// specification (v. 1.1)
   fn set_remote_datareader_crypto_tokens(
-    &mut self,
+    &self,
     local_datawriter_crypto_handle: DatawriterCryptoHandle,
     remote_datareader_crypto_handle: DatareaderCryptoHandle,
     remote_datareader_tokens: Vec<DatareaderCryptoToken>,
@@ -155,7 +202,7 @@ pub trait CryptoKeyExchange {
 
   /// return_crypto_tokens: section 8.5.1.8.7 of the Security specification (v.
   /// 1.1)
-  fn return_crypto_tokens(&mut self, crypto_tokens: Vec<CryptoToken>) -> SecurityResult<()>;
+  fn return_crypto_tokens(&self, crypto_tokens: Vec<CryptoToken>) -> SecurityResult<()>;
 }
 
 /// CryptoTransform: section 8.5.1.9 of the Security specification (v. 1.1)
@@ -276,4 +323,17 @@ pub trait CryptoTransform: Send {
     receiving_datareader_crypto_handle: DatareaderCryptoHandle,
     sending_datawriter_crypto_handle: DatawriterCryptoHandle,
   ) -> SecurityResult<Vec<u8>>;
+
+  /// Not in the specification. Gives the plugin the limiter of the
+  /// participant, against which it should check sizes and counts declared in
+  /// received data that only the plugin can parse, such as the number of
+  /// receiver-specific MACs in its CryptoFooter. Called before any decoding.
+  /// The default implementation ignores the limiter.
+  fn set_decode_limiter(&mut self, _limiter: Arc<DecodeLimiter>) {}
+
+  /// Not in the specification. Statistics of the cache of derived session
+  /// keys, if the plugin has one. The default implementation returns `None`.
+  fn session_key_cache_statistics(&self) -> Option<SessionKeyCacheStatistics> {
+    None
+  }
 }
@@ -6,7 +6,7 @@ use speedy::{Readable, Writable};
 use crate::{
   messages::submessages::submessage::{InterpreterSubmessage, ReaderSubmessage, WriterSubmessage},
   rtps::Submessage,
-  security::types::DataHolder,
+  security::{decode_limits::DecodeRejection, types::DataHolder},
   structure::guid::GuidPrefix,
 };
 
@@ -89,6 +89,24 @@ impl fmt::Display for CryptoTransformKeyId {
   }
 }
 
+/// Statistics of the session key cache of a cryptographic plugin. Not in the
+/// specification.
+///
+/// Session keys are derived from master keys for each session, and a session
+/// lasts for many messages, so the derived keys are cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SessionKeyCacheStatistics {
+  /// Lookups that found the session key in the cache
+  pub hits: u64,
+  /// Lookups that had to derive the session key
+  pub misses: u64,
+  /// Keys removed from the cache, because it was full or their key material
+  /// was removed
+  pub evictions: u64,
+  /// Number of keys currently in the cache
+  pub cached_keys: usize,
+}
+
 /// [super::cryptographic_plugin::CryptoTransform::encode_datawriter_submessage]
 /// and [super::cryptographic_plugin::CryptoTransform::encode_datareader_submessage]
 /// may return the unencoded input or an encoded message between a
@@ -134,4 +152,7 @@ pub enum DecodeOutcome<T> {
   /// It is normal to receive encoded messages from participants that have
   /// not been matched with.
   ParticipantCryptoHandleNotFound(GuidPrefix),
+  /// The input exceeds the configured decode limits, and was not processed
+  /// further.
+  LimitExceeded(DecodeRejection),
 }
@@ -0,0 +1,195 @@
+//! Limits on secured input from remote participants.
+//!
+//! The decode path of the security plugins handles data whose size is
+//! declared by the sender. To keep a malicious peer from causing huge
+//! allocations or long computations, input exceeding [`DecodeLimits`] is
+//! rejected before it is decrypted or its MACs are verified. Rejections are
+//! logged and counted, and the counts can be read with
+//! [`DomainParticipant::security_decode_rejections`](crate::DomainParticipant::security_decode_rejections).
+//!
+//! The limits are set with
+//! [`DomainParticipantBuilder::security_decode_limits`](crate::DomainParticipantBuilder::security_decode_limits).
+
+use std::{
+  fmt,
+  sync::atomic::{AtomicU64, Ordering},
+};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+/// Upper bounds for secured input. The defaults accommodate any legitimate
+/// traffic from RustDDS or other implementations of the builtin plugins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+  /// Maximum size in bytes of the CryptoContent in a SecureBody submessage,
+  /// or of an encoded serialized payload.
+  pub max_crypto_content_size: usize,
+  /// Maximum number of receiver-specific MACs in a CryptoFooter.
+  pub max_receiver_specific_macs: usize,
+  /// Maximum number of crypto tokens in one key exchange message.
+  pub max_crypto_tokens: usize,
+}
+
+impl DecodeLimits {
+  pub const DEFAULT_MAX_CRYPTO_CONTENT_SIZE: usize = 16 * 1024 * 1024;
+  pub const DEFAULT_MAX_RECEIVER_SPECIFIC_MACS: usize = 256;
+  pub const DEFAULT_MAX_CRYPTO_TOKENS: usize = 16;
+}
+
+impl Default for DecodeLimits {
+  fn default() -> Self {
+    Self {
+      max_crypto_content_size: Self::DEFAULT_MAX_CRYPTO_CONTENT_SIZE,
+      max_receiver_specific_macs: Self::DEFAULT_MAX_RECEIVER_SPECIFIC_MACS,
+      max_crypto_tokens: Self::DEFAULT_MAX_CRYPTO_TOKENS,
+    }
+  }
+}
+
+/// Why secured input was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeRejection {
+  CryptoContentTooLarge { size: usize, limit: usize },
+  TooManyReceiverSpecificMacs { count: usize, limit: usize },
+  TooManyCryptoTokens { count: usize, limit: usize },
+}
+
+impl fmt::Display for DecodeRejection {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::CryptoContentTooLarge { size, limit } => {
+        write!(f, "CryptoContent of {size} bytes exceeds the limit {limit}")
+      }
+      Self::TooManyReceiverSpecificMacs { count, limit } => {
+        write!(f, "{count} receiver-specific MACs exceed the limit {limit}")
+      }
+      Self::TooManyCryptoTokens { count, limit } => {
+        write!(f, "{count} crypto tokens exceed the limit {limit}")
+      }
+    }
+  }
+}
+
+/// Number of rejections so far, by reason.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DecodeRejectionCounts {
+  pub crypto_content_too_large: u64,
+  pub too_many_receiver_specific_macs: u64,
+  pub too_many_crypto_tokens: u64,
+}
+
+/// Checks input against [`DecodeLimits`] and counts rejections.
+///
+/// One limiter is shared by the security plugins of a participant. The
+/// Cryptographic plugin receives it through `set_decode_limiter`, and uses it
+/// for the parts of its wire format that only it can parse, such as the
+/// CryptoFooter.
+#[derive(Debug, Default)]
+pub struct DecodeLimiter {
+  limits: DecodeLimits,
+  crypto_content_too_large: AtomicU64,
+  too_many_receiver_specific_macs: AtomicU64,
+  too_many_crypto_tokens: AtomicU64,
+}
+
+impl DecodeLimiter {
+  pub fn new(limits: DecodeLimits) -> Self {
+    Self {
+      limits,
+      ..Self::default()
+    }
+  }
+
+  pub fn limits(&self) -> DecodeLimits {
+    self.limits
+  }
+
+  pub fn check_crypto_content_size(&self, size: usize) -> Result<(), DecodeRejection> {
+    let limit = self.limits.max_crypto_content_size;
+    self.check(
+      size <= limit,
+      &self.crypto_content_too_large,
+      DecodeRejection::CryptoContentTooLarge { size, limit },
+    )
+  }
+
+  pub fn check_receiver_specific_macs(&self, count: usize) -> Result<(), DecodeRejection> {
+    let limit = self.limits.max_receiver_specific_macs;
+    self.check(
+      count <= limit,
+      &self.too_many_receiver_specific_macs,
+      DecodeRejection::TooManyReceiverSpecificMacs { count, limit },
+    )
+  }
+
+  pub fn check_crypto_tokens(&self, count: usize) -> Result<(), DecodeRejection> {
+    let limit = self.limits.max_crypto_tokens;
+    self.check(
+      count <= limit,
+      &self.too_many_crypto_tokens,
+      DecodeRejection::TooManyCryptoTokens { count, limit },
+    )
+  }
+
+  fn check(
+    &self,
+    within_limit: bool,
+    counter: &AtomicU64,
+    rejection: DecodeRejection,
+  ) -> Result<(), DecodeRejection> {
+    if within_limit {
+      Ok(())
+    } else {
+      counter.fetch_add(1, Ordering::Relaxed);
+      warn!("Rejecting secured input: {rejection}");
+      Err(rejection)
+    }
+  }
+
+  pub fn rejection_counts(&self) -> DecodeRejectionCounts {
+    DecodeRejectionCounts {
+      crypto_content_too_large: self.crypto_content_too_large.load(Ordering::Relaxed),
+      too_many_receiver_specific_macs: self.too_many_receiver_specific_macs.load(Ordering::Relaxed),
+      too_many_crypto_tokens: self.too_many_crypto_tokens.load(Ordering::Relaxed),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rejections_are_counted_by_reason() {
+    let limiter = DecodeLimiter::new(DecodeLimits {
+      max_crypto_content_size: 100,
+      max_receiver_specific_macs: 2,
+      max_crypto_tokens: 1,
+    });
+
+    assert!(limiter.check_crypto_content_size(100).is_ok());
+    assert_eq!(
+      limiter.check_crypto_content_size(101),
+      Err(DecodeRejection::CryptoContentTooLarge {
+        size: 101,
+        limit: 100
+      })
+    );
+    assert!(limiter.check_receiver_specific_macs(2).is_ok());
+    assert!(limiter.check_receiver_specific_macs(3).is_err());
+    assert!(limiter
+      .check_receiver_specific_macs(u32::MAX as usize)
+      .is_err());
+    assert!(limiter.check_crypto_tokens(1).is_ok());
+
+    assert_eq!(
+      limiter.rejection_counts(),
+      DecodeRejectionCounts {
+        crypto_content_too_large: 1,
+        too_many_receiver_specific_macs: 2,
+        too_many_crypto_tokens: 0,
+      }
+    );
+  }
+}
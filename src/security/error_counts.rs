@@ -0,0 +1,141 @@
+//! Counts of security errors by category.
+//!
+//! Errors returned by the security plugins are counted when they pass through
+//! the participant, so that e.g. a spike in crypto decode failures can be
+//! noticed without reading logs. The counts can be read with
+//! [`DomainParticipant::security_error_counts`](crate::DomainParticipant::security_error_counts).
+
+use std::{
+  fmt,
+  sync::atomic::{AtomicU64, Ordering},
+};
+
+/// Which part of DDS Security an error came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SecurityErrorCategory {
+  /// Identity validation and authentication handshakes
+  Authentication,
+  /// Permissions validation and access checks of remote entities
+  AccessControl,
+  /// Receiving crypto tokens from remote participants
+  KeyExchange,
+  /// Protecting outgoing messages, submessages and payloads
+  CryptoEncode,
+  /// Verifying and decrypting incoming messages, submessages and payloads
+  CryptoDecode,
+  #[default]
+  Other,
+}
+
+impl fmt::Display for SecurityErrorCategory {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = match self {
+      Self::Authentication => "authentication",
+      Self::AccessControl => "access control",
+      Self::KeyExchange => "key exchange",
+      Self::CryptoEncode => "crypto encode",
+      Self::CryptoDecode => "crypto decode",
+      Self::Other => "other",
+    };
+    write!(f, "{name}")
+  }
+}
+
+/// Number of security errors so far, by category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SecurityErrorCounts {
+  pub authentication: u64,
+  pub access_control: u64,
+  pub key_exchange: u64,
+  pub crypto_encode: u64,
+  pub crypto_decode: u64,
+  pub other: u64,
+}
+
+impl SecurityErrorCounts {
+  pub fn get(&self, category: SecurityErrorCategory) -> u64 {
+    match category {
+      SecurityErrorCategory::Authentication => self.authentication,
+      SecurityErrorCategory::AccessControl => self.access_control,
+      SecurityErrorCategory::KeyExchange => self.key_exchange,
+      SecurityErrorCategory::CryptoEncode => self.crypto_encode,
+      SecurityErrorCategory::CryptoDecode => self.crypto_decode,
+      SecurityErrorCategory::Other => self.other,
+    }
+  }
+
+  pub fn total(&self) -> u64 {
+    self.authentication
+      + self.access_control
+      + self.key_exchange
+      + self.crypto_encode
+      + self.crypto_decode
+      + self.other
+  }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct SecurityErrorCounters {
+  authentication: AtomicU64,
+  access_control: AtomicU64,
+  key_exchange: AtomicU64,
+  crypto_encode: AtomicU64,
+  crypto_decode: AtomicU64,
+  other: AtomicU64,
+}
+
+impl SecurityErrorCounters {
+  pub fn record(&self, category: SecurityErrorCategory) {
+    let counter = match category {
+      SecurityErrorCategory::Authentication => &self.authentication,
+      SecurityErrorCategory::AccessControl => &self.access_control,
+      SecurityErrorCategory::KeyExchange => &self.key_exchange,
+      SecurityErrorCategory::CryptoEncode => &self.crypto_encode,
+      SecurityErrorCategory::CryptoDecode => &self.crypto_decode,
+      SecurityErrorCategory::Other => &self.other,
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn counts(&self) -> SecurityErrorCounts {
+    SecurityErrorCounts {
+      authentication: self.authentication.load(Ordering::Relaxed),
+      access_control: self.access_control.load(Ordering::Relaxed),
+      key_exchange: self.key_exchange.load(Ordering::Relaxed),
+      crypto_encode: self.crypto_encode.load(Ordering::Relaxed),
+      crypto_decode: self.crypto_decode.load(Ordering::Relaxed),
+      other: self.other.load(Ordering::Relaxed),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    security::{SecurityError, SecurityErrorContext},
+    structure::guid::{EntityKind, GUID},
+  };
+
+  #[test]
+  fn context_chain_and_counters() {
+    let remote = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    let error = SecurityError::new("bad MAC".to_string())
+      .with_context(SecurityErrorContext::new("cryptographic", "decode_submessage").remote(remote))
+      .with_category(SecurityErrorCategory::CryptoDecode)
+      .with_context(SecurityErrorContext::new("receiver", "handle_message"));
+
+    assert_eq!(error.category(), SecurityErrorCategory::CryptoDecode);
+    assert_eq!(error.root_cause().msg, "bad MAC");
+    assert_eq!(error.remote_guid(), Some(remote));
+    assert_eq!(error.context().map(|c| c.operation), Some("handle_message"));
+
+    let counters = SecurityErrorCounters::default();
+    counters.record(error.category());
+    counters.record(SecurityErrorCategory::Authentication);
+    let counts = counters.counts();
+    assert_eq!(counts.get(SecurityErrorCategory::CryptoDecode), 1);
+    assert_eq!(counts.authentication, 1);
+    assert_eq!(counts.total(), 2);
+  }
+}
@@ -1,4 +1,4 @@
-use std::{collections::BTreeMap, str::FromStr};
+use std::{collections::BTreeMap, fmt, str::FromStr, sync::Mutex};
 
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
@@ -17,78 +17,100 @@ use der::{asn1, Encode};
 
 use crate::security::{
   authentication::authentication_builtin::types::CertificateAlgorithm,
-  config::{parse_config_error, to_config_error_parse, ConfigError},
+  config::{parse_config_error, to_config_error_other, to_config_error_parse, ConfigError},
   types::{security_error, SecurityResult},
 };
 
+/// Signing with the private key of the participant identity certificate (see
+/// neighbouring module). The PKI-DH handshake signs its messages through
+/// this, so it does not need to know where the key is kept.
+pub(crate) trait SigningKey: fmt::Debug + Send + Sync {
+  /// Signs `msg` as the DDS Security handshake requires, i.e. an ASN.1 DER
+  /// encoded ECDSA-SHA256 or RSASSA-PSS-SHA256 signature.
+  fn sign(&self, msg: &[u8]) -> SecurityResult<Bytes>;
+}
+
 /// PrivateKey is the private key associaed with a Certificate (see neghbouring
 /// module)
 ///
 /// It can live within a .pem file (and normal memory), or in a Hardware
 /// Security Module.
-
 #[derive(Debug)]
-pub(crate) enum PrivateKey {
-  InMemory {
-    priv_key: InMemorySigningKeyPair,
-  },
-  InHSM {
-    key_algorithm: CertificateAlgorithm,
-    // We store context and slot just in case dropping them would close them.
-    #[allow(dead_code)]
-    context: Pkcs11,
-    #[allow(dead_code)]
-    slot: Slot,
-    session: Session,
-    key_object_handle: ObjectHandle,
-  },
+pub(crate) struct PrivateKey {
+  key: Box<dyn SigningKey>,
 }
 
 // TODO: decrypt a password protected key
 impl PrivateKey {
+  pub fn new(key: impl SigningKey + 'static) -> Self {
+    Self { key: Box::new(key) }
+  }
+
   pub fn from_pem(pem_data: impl AsRef<[u8]>) -> Result<Self, ConfigError> {
     let priv_key = InMemorySigningKeyPair::from_pkcs8_pem(pem_data.as_ref())
       .map_err(to_config_error_parse("Private key parse error"))?;
 
-    Ok(PrivateKey::InMemory { priv_key })
+    Ok(Self::new(InMemoryKey { priv_key }))
   }
 
-  // Process:
-  //
-  // decode object label in path
-  // load HSM library
-  // initialize library
-  // get slots and enumerate
-  // find slot where token is present and matches object label
-  // open RO session to token and login with PIN
-  // find private key objects
-  // check that private key object supports "sign"
-  // store library handle, slot handle, session handle to PrivateKey object
-  // store object handle to PrivateKey
-  // return PrivateKey
-
   pub fn from_pkcs11_uri_path_and_query(
     path_and_query: &str,
     key_algorithm: CertificateAlgorithm,
   ) -> Result<Self, ConfigError> {
-    let interesting_attributes = vec![
-      AttributeType::AllowedMechanisms,
-      AttributeType::Class,
-      AttributeType::Label,
-      AttributeType::KeyType,
-      AttributeType::Sign,
-      AttributeType::Sensitive,
-    ];
-    // decode URI: path and possible query (PIN)
-    //
-    // example path_and_query, which is part of URI after scheme "pkcs11:"
-    //
-    // token=my_token_label?pin-value=1234&module-path=/usr/lib/softhsm/libsofthsm2.
-    // so
-    //
-    // The "module-path" is a query-attribute like the PIN, and not a path-attribute
-    // like "token", which may seem a bit strange choice, but that is what RFC
-    // 7512 says.
+    let uri = Pkcs11Uri::parse(path_and_query)?;
+    Pkcs11Key::open(&uri, key_algorithm).map(Self::new)
+  }
+
+  pub fn sign(&self, msg: &[u8]) -> SecurityResult<Bytes> {
+    self.key.sign(msg)
+  }
+}
+
+// Key loaded from a .pem file
+#[derive(Debug)]
+struct InMemoryKey {
+  priv_key: InMemorySigningKeyPair,
+}
+
+impl SigningKey for InMemoryKey {
+  fn sign(&self, msg: &[u8]) -> SecurityResult<Bytes> {
+    self
+      .priv_key
+      .try_sign(msg)
+      .map(|s| Bytes::copy_from_slice(s.as_ref()))
+      .map_err(|e| security_error(&format!("Signing failure: {e:?}")))
+  }
+}
+
+// Where the private key is found in a Hardware Security Module. This is
+// decoded from the part of a PKCS#11 URI (RFC 7512) after the scheme
+// "pkcs11:", for example
+//
+// token=my_token_label;slot-id=0?pin-value=1234&module-path=/usr/lib/softhsm/libsofthsm2.so
+//
+// The "module-path" is a query-attribute like the PIN, and not a
+// path-attribute like "token", which may seem a bit strange choice, but that
+// is what RFC 7512 says.
+//
+// Earlier versions gave the token label as "object", so that is accepted in
+// place of "token".
+//
+// No Debug, so that the PIN is not logged by accident.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct Pkcs11Uri {
+  token_label: Option<String>,
+  slot_id: Option<u64>,
+  pin_value: Option<String>,
+  // File containing the PIN
+  pin_source: Option<String>,
+  module_path: String,
+}
+
+impl Pkcs11Uri {
+  // Some semi-reasnoable default value.
+  const DEFAULT_MODULE_PATH: &'static str = "/usr/lib/softhsm/libsofthsm2.so";
+
+  pub fn parse(path_and_query: &str) -> Result<Self, ConfigError> {
     let (path, query) = path_and_query
       .split_once('?')
       .unwrap_or((path_and_query, ""));
@@ -97,19 +119,91 @@ impl PrivateKey {
     let query_attrs: BTreeMap<&str, &str> =
       query.split('&').filter_map(|a| a.split_once('=')).collect();
 
-    let token_label = path_attrs.get("object").ok_or(parse_config_error(
-      "pkcs11 URI must specify attribute \"object\" i.e. token label".to_string(),
-    ))?;
+    let token_label = path_attrs
+      .get("token")
+      .or_else(|| path_attrs.get("object"))
+      .map(|label| label.to_string());
+    let slot_id = match path_attrs.get("slot-id") {
+      Some(id) => Some(
+        id.parse::<u64>()
+          .map_err(|e| parse_config_error(format!("pkcs11 URI has a bad slot-id {id:?}: {e}")))?,
+      ),
+      None => None,
+    };
+    if token_label.is_none() && slot_id.is_none() {
+      return Err(parse_config_error(
+        "pkcs11 URI must specify attribute \"token\" i.e. token label, or \"slot-id\"".to_string(),
+      ));
+    }
 
-    let pin_value_opt = query_attrs.get("pin-value");
+    Ok(Self {
+      token_label,
+      slot_id,
+      pin_value: query_attrs.get("pin-value").map(|p| p.to_string()),
+      pin_source: query_attrs.get("pin-source").map(|p| p.to_string()),
+      module_path: query_attrs
+        .get("module-path")
+        .copied()
+        .unwrap_or(Self::DEFAULT_MODULE_PATH)
+        .to_string(),
+    })
+  }
 
-    let module_path: &str = query_attrs
-      .get("module-path")
-      .cloned()
-      .unwrap_or("/usr/lib/softhsm/libsofthsm2.so"); // Some semi-reasnoable default value.
+  // Does the URI select the token in the slot
+  fn selects(&self, slot_id: u64, token_label: &str) -> bool {
+    self.slot_id.unwrap_or(slot_id) == slot_id
+      && self.token_label.as_deref().unwrap_or(token_label) == token_label
+  }
 
-    info!("Opening PKCS#11 HSM client library {}", module_path);
-    let context = Pkcs11::new(module_path)?;
+  // The login PIN. A PIN source is a file, whose first line is the PIN.
+  fn pin(&self) -> Result<Option<AuthPin>, ConfigError> {
+    let pin = match (&self.pin_value, &self.pin_source) {
+      (Some(value), _) => Some(value.clone()),
+      (None, Some(source)) => {
+        let path = source.strip_prefix("file:").unwrap_or(source);
+        let contents = std::fs::read_to_string(path)
+          .map_err(to_config_error_other(&format!("I/O error reading {path}")))?;
+        Some(contents.lines().next().unwrap_or_default().to_string())
+      }
+      (None, None) => None,
+    };
+    // unwrap is safe, because error type is Infallible
+    Ok(pin.map(|p| AuthPin::from_str(&p).unwrap()))
+  }
+}
+
+// Key in a Hardware Security Module. Signing is done by the HSM, so the key
+// never leaves it.
+#[derive(Debug)]
+struct Pkcs11Key {
+  key_algorithm: CertificateAlgorithm,
+  // We store context and slot just in case dropping them would close them.
+  #[allow(dead_code)]
+  context: Pkcs11,
+  #[allow(dead_code)]
+  slot: Slot,
+  // Session is not Sync, but PrivateKey is used through shared references
+  // from several threads.
+  session: Mutex<Session>,
+  key_object_handle: ObjectHandle,
+}
+
+impl Pkcs11Key {
+  // Process:
+  //
+  // load HSM library
+  // initialize library
+  // get slots and enumerate
+  // find slot where token is present and matches the URI
+  // open RO session to token and login with PIN
+  // find private key objects
+  // check that private key object supports "sign"
+  // store library handle, slot handle, session handle and object handle
+  fn open(uri: &Pkcs11Uri, key_algorithm: CertificateAlgorithm) -> Result<Self, ConfigError> {
+    let secret_pin_opt = uri.pin()?;
+
+    info!("Opening PKCS#11 HSM client library {}", uri.module_path);
+    let context = Pkcs11::new(&uri.module_path)?;
     context.initialize(CInitializeArgs::OsThreads)?;
     debug!("PKCS#11 HSM library info: {:?}", context.get_library_info());
 
@@ -118,75 +212,39 @@ impl PrivateKey {
     for (num, slot) in slots.iter().enumerate() {
       let slot_info = context.get_slot_info(*slot);
       match slot_info {
-        Ok(si) if si.token_present() => {
-          match context.get_token_info(*slot) {
-            Ok(token_info) => {
-              if token_info.label() == *token_label {
-                info!("Found matching token \"{}\" in slot {}.", token_label, num);
-                let session = context.open_ro_session(*slot)?;
-                let secret_pin_opt: Option<AuthPin> =
-                  pin_value_opt.map(|p| AuthPin::from_str(p).unwrap());
-                // unwrap is safe, because error type is Infallible
-                session.login(UserType::User, secret_pin_opt.as_ref())?; // bail on failure
-                info!(
-                  "Logged into token \"{}\" , using PIN = {:?}",
-                  token_label,
-                  secret_pin_opt.is_some()
-                );
-
-                // Now ,iterate though objects in the token.
-                for (obj_num, obj) in session.find_objects(&[])?.iter().enumerate() {
-                  let attr = session.get_attributes(*obj, &interesting_attributes)?;
-                  // Check the attributes. Does this look like a private key?
-                  if attr
-                    .iter()
-                    .any(|a| a == &Attribute::Class(ObjectClass::PRIVATE_KEY))
-                    && attr.iter().any(|a| a == &Attribute::Sign(true))
-                  {
-                    // Is a private key and declares to support "sign" operation.
-                    let object_label = attr.iter().find_map(|a| match a {
-                      Attribute::Label(bytes) => Some(String::from_utf8_lossy(bytes)),
-                      _ => None,
-                    });
-                    info!(
-                      "Object {}: Found a Private Key. label={:?}",
-                      obj_num, object_label
-                    );
-
-                    // Test that the signing operation works.
-                    let test_signature_result =
-                      session.sign(&key_algorithm.into(), *obj, b"This is just dummy data");
-                    if test_signature_result.is_ok() {
-                      debug!("Object {obj_num}: Test signing success.");
-                      // Object looks like a legit private key, so we'll use that.
-                      return Ok(PrivateKey::InHSM {
-                        key_algorithm,
-                        context,
-                        slot: *slot,
-                        session,
-                        key_object_handle: *obj,
-                      });
-                    } else {
-                      warn!(
-                        "Object {}: Test signing fails: {:?}. Cannot use this as private key.",
-                        obj_num, test_signature_result
-                      );
-                    }
-                  } else {
-                    debug!("Object {}: Attributes do not match {:?}", obj_num, attr);
-                  }
-                } // for objects
-              } else {
-                debug!(
-                  "Slot {}, token label={}: This is not the token we are looking for.",
-                  num,
-                  token_info.label()
-                );
-              }
+        Ok(si) if si.token_present() => match context.get_token_info(*slot) {
+          Ok(token_info) if uri.selects(slot.id(), token_info.label()) => {
+            info!(
+              "Found matching token \"{}\" in slot {} (id {}).",
+              token_info.label(),
+              num,
+              slot.id()
+            );
+            let session = context.open_ro_session(*slot)?;
+            session.login(UserType::User, secret_pin_opt.as_ref())?; // bail on failure
+            info!(
+              "Logged into token \"{}\" , using PIN = {:?}",
+              token_info.label(),
+              secret_pin_opt.is_some()
+            );
+
+            if let Some(key_object_handle) = Self::find_signing_key(&session, key_algorithm)? {
+              return Ok(Self {
+                key_algorithm,
+                context,
+                slot: *slot,
+                session: Mutex::new(session),
+                key_object_handle,
+              });
             }
-            Err(e) => warn!("Slot {}: get_token_info() fails: {:?}", num, e),
           }
-        } // Ok with token_present
+          Ok(token_info) => debug!(
+            "Slot {}, token label={}: This is not the token we are looking for.",
+            num,
+            token_info.label()
+          ),
+          Err(e) => warn!("Slot {}: get_token_info() fails: {:?}", num, e),
+        }, // Ok with token_present
         Ok(_) => info!("Slot {} has no token", num),
         Err(e) => warn!("Slot {} get_slot_info() error: {:?}", num, e),
       }
@@ -197,70 +255,113 @@ impl PrivateKey {
     ))
   }
 
-  pub fn sign(&self, msg: &[u8]) -> SecurityResult<Bytes> {
-    match self {
-      PrivateKey::InMemory { priv_key } => priv_key
-        .try_sign(msg)
-        .map(|s| Bytes::copy_from_slice(s.as_ref()))
-        .map_err(|e| security_error(&format!("Signing failure: {e:?}"))),
-
-      PrivateKey::InHSM {
-        key_algorithm,
-        session,
-        key_object_handle,
-        ..
-      } => {
-        // DDS Security uses ASN.1-encoded ECDSA-SHA256 signatures.
-        //
-        // PKCS#11 (HSM) provides fixed-length ECDSA-signatures without SHA256.
-        //
-        // In order to
-        // use HSM signing in DDS, we must first compute the SHA256 digest, then sign
-        // using ECDSA. The result is two 32-byte integers (r,s) concatenated
-        // together. These need to be ASN.1 DER-encoded according to RFC 3279
-        // Section 2.2.3 as
-        // ```
-        // Ecdsa-Sig-Value  ::=  SEQUENCE  {
-        //   r     INTEGER,
-        //   s     INTEGER  }
-        // ```
-        //
-        // Thanks to the
-        // [ring crate documentation](https://docs.rs/ring/0.17.8/ring/signature/index.html)
-        // for explaining this.
-
-        // First, hash the message to be signed. Then sign the hash, not the message.
-        let msg_digest = digest::digest(&digest::SHA256, msg);
-
-        // Second, ask HSM to compute the signature
-        let sign_mechanism = Mechanism::from(*key_algorithm);
-        let hsm_signature_raw =
-          session.sign(&sign_mechanism, *key_object_handle, msg_digest.as_ref())?;
-
-        // Sanity check.
-        if hsm_signature_raw.len() != 64 {
-          return Err(security_error(&format!(
-            "Expected signature len=64, got len={}",
-            hsm_signature_raw.len()
-          )));
+  // The first object in the token that is a private key and can sign
+  fn find_signing_key(
+    session: &Session,
+    key_algorithm: CertificateAlgorithm,
+  ) -> Result<Option<ObjectHandle>, ConfigError> {
+    let interesting_attributes = vec![
+      AttributeType::AllowedMechanisms,
+      AttributeType::Class,
+      AttributeType::Label,
+      AttributeType::KeyType,
+      AttributeType::Sign,
+      AttributeType::Sensitive,
+    ];
+    // Now ,iterate though objects in the token.
+    for (obj_num, obj) in session.find_objects(&[])?.iter().enumerate() {
+      let attr = session.get_attributes(*obj, &interesting_attributes)?;
+      // Check the attributes. Does this look like a private key?
+      if attr
+        .iter()
+        .any(|a| a == &Attribute::Class(ObjectClass::PRIVATE_KEY))
+        && attr.iter().any(|a| a == &Attribute::Sign(true))
+      {
+        // Is a private key and declares to support "sign" operation.
+        let object_label = attr.iter().find_map(|a| match a {
+          Attribute::Label(bytes) => Some(String::from_utf8_lossy(bytes)),
+          _ => None,
+        });
+        info!(
+          "Object {}: Found a Private Key. label={:?}",
+          obj_num, object_label
+        );
+
+        // Test that the signing operation works.
+        let test_signature_result =
+          session.sign(&key_algorithm.into(), *obj, b"This is just dummy data");
+        if test_signature_result.is_ok() {
+          debug!("Object {obj_num}: Test signing success.");
+          // Object looks like a legit private key, so we'll use that.
+          return Ok(Some(*obj));
+        } else {
+          warn!(
+            "Object {}: Test signing fails: {:?}. Cannot use this as private key.",
+            obj_num, test_signature_result
+          );
         }
+      } else {
+        debug!("Object {}: Attributes do not match {:?}", obj_num, attr);
+      }
+    } // for objects
+    Ok(None)
+  }
+}
 
-        // Third, convert raw signature to ASN.1 with DER
-        let (r, s) = hsm_signature_raw.split_at(32); // safe, because of the length check above
-        let mut hsm_signature_der = Vec::with_capacity(80); // typically needs about 70..72 bytes
+impl SigningKey for Pkcs11Key {
+  fn sign(&self, msg: &[u8]) -> SecurityResult<Bytes> {
+    // DDS Security uses ASN.1-encoded ECDSA-SHA256 signatures.
+    //
+    // PKCS#11 (HSM) provides fixed-length ECDSA-signatures without SHA256.
+    //
+    // In order to
+    // use HSM signing in DDS, we must first compute the SHA256 digest, then sign
+    // using ECDSA. The result is two 32-byte integers (r,s) concatenated
+    // together. These need to be ASN.1 DER-encoded according to RFC 3279
+    // Section 2.2.3 as
+    // ```
+    // Ecdsa-Sig-Value  ::=  SEQUENCE  {
+    //   r     INTEGER,
+    //   s     INTEGER  }
+    // ```
+    //
+    // Thanks to the
+    // [ring crate documentation](https://docs.rs/ring/0.17.8/ring/signature/index.html)
+    // for explaining this.
 
-        // Safety: We expect all the .unwrap() calls below to succeed, becaues the
-        // possible errors are size overflows, and here the input sizes are fixed.
-        let sequence_of_r_s = vec![
-          asn1::UintRef::new(r).unwrap(),
-          asn1::UintRef::new(s).unwrap(),
-        ];
-        sequence_of_r_s.encode(&mut hsm_signature_der).unwrap();
+    // First, hash the message to be signed. Then sign the hash, not the message.
+    let msg_digest = digest::digest(&digest::SHA256, msg);
 
-        Ok(Bytes::from(hsm_signature_der))
-      }
+    // Second, ask HSM to compute the signature
+    let sign_mechanism = Mechanism::from(self.key_algorithm);
+    let hsm_signature_raw = self.session.lock().unwrap().sign(
+      &sign_mechanism,
+      self.key_object_handle,
+      msg_digest.as_ref(),
+    )?;
+
+    // Sanity check.
+    if hsm_signature_raw.len() != 64 {
+      return Err(security_error(&format!(
+        "Expected signature len=64, got len={}",
+        hsm_signature_raw.len()
+      )));
     }
-  } // fn
+
+    // Third, convert raw signature to ASN.1 with DER
+    let (r, s) = hsm_signature_raw.split_at(32); // safe, because of the length check above
+    let mut hsm_signature_der = Vec::with_capacity(80); // typically needs about 70..72 bytes
+
+    // Safety: We expect all the .unwrap() calls below to succeed, becaues the
+    // possible errors are size overflows, and here the input sizes are fixed.
+    let sequence_of_r_s = vec![
+      asn1::UintRef::new(r).unwrap(),
+      asn1::UintRef::new(s).unwrap(),
+    ];
+    sequence_of_r_s.encode(&mut hsm_signature_der).unwrap();
+
+    Ok(Bytes::from(hsm_signature_der))
+  }
 }
 
 // Map our internal choice of certificate key algoritm to cryptoki Mechanism
@@ -307,4 +408,30 @@ EEJPTyIYWQldbZS8GH/4SOMViyWhL/BcSU48V0RidVPvyKqqiLyVaVmk
 
     println!("{:?}", key);
   }
+
+  #[test]
+  fn parse_pkcs11_uri() {
+    let uri =
+      Pkcs11Uri::parse("token=my_token;slot-id=3?pin-value=1234&module-path=/usr/lib/libhsm.so")
+        .unwrap();
+    assert_eq!(uri.token_label.as_deref(), Some("my_token"));
+    assert_eq!(uri.slot_id, Some(3));
+    assert_eq!(uri.pin_value.as_deref(), Some("1234"));
+    assert_eq!(uri.module_path, "/usr/lib/libhsm.so");
+    assert!(uri.selects(3, "my_token"));
+    assert!(!uri.selects(4, "my_token"));
+    assert!(!uri.selects(3, "other_token"));
+
+    // The token label alone, in the earlier form
+    let uri = Pkcs11Uri::parse("object=my_token").unwrap();
+    assert!(uri.selects(7, "my_token"));
+    assert_eq!(uri.module_path, Pkcs11Uri::DEFAULT_MODULE_PATH);
+    assert!(uri.pin().unwrap().is_none());
+
+    // The slot alone
+    assert!(Pkcs11Uri::parse("slot-id=0").unwrap().selects(0, "any"));
+
+    assert!(Pkcs11Uri::parse("?pin-value=1234").is_err());
+    assert!(Pkcs11Uri::parse("slot-id=first").is_err());
+  }
 }
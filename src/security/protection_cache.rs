@@ -0,0 +1,110 @@
+//! Classification of received submessages by the protection of their local
+//! endpoint, without calling the security plugins.
+//!
+//! In a participant with security, every received submessage and payload
+//! must be checked against the security attributes of its local endpoint, to
+//! know whether it may arrive unprotected. The attributes change only when
+//! local endpoints are registered or unregistered, so the message receiver
+//! caches them. Unprotected topics are then processed without locking or
+//! calling the plugins at all, even when a handshake or key exchange holds
+//! them exclusively.
+
+use std::{
+  collections::HashMap,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+  },
+};
+
+use crate::structure::guid::GUID;
+
+// Which parts of the messages of a local endpoint are protected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct EndpointProtection {
+  pub submessage: bool,
+  pub payload: bool,
+}
+
+impl EndpointProtection {
+  pub const NONE: Self = Self {
+    submessage: false,
+    payload: false,
+  };
+}
+
+// Counts changes to the protection of local endpoints. Shared by the security
+// plugins, which bump it, and the caches, which compare it without locking.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ProtectionGeneration(Arc<AtomicU64>);
+
+impl ProtectionGeneration {
+  pub fn current(&self) -> u64 {
+    self.0.load(Ordering::Acquire)
+  }
+
+  pub fn bump(&self) {
+    self.0.fetch_add(1, Ordering::AcqRel);
+  }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ProtectionCache {
+  generation: u64,
+  endpoints: HashMap<GUID, EndpointProtection>,
+}
+
+impl ProtectionCache {
+  // Received submessages name arbitrary entities, so the cache is bounded.
+  // Local endpoints are far fewer than this.
+  const MAX_ENTRIES: usize = 4096;
+
+  // The protection of the endpoint, if cached at the given generation.
+  // Otherwise it is found out with `lookup` and cached.
+  pub fn get(
+    &mut self,
+    generation: u64,
+    local_endpoint_guid: GUID,
+    lookup: impl FnOnce() -> EndpointProtection,
+  ) -> EndpointProtection {
+    if generation != self.generation || self.endpoints.len() >= Self::MAX_ENTRIES {
+      self.endpoints.clear();
+      self.generation = generation;
+    }
+    *self
+      .endpoints
+      .entry(local_endpoint_guid)
+      .or_insert_with(lookup)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::structure::guid::EntityKind;
+
+  #[test]
+  fn steady_state_needs_no_lookups() {
+    let generation = ProtectionGeneration::default();
+    let mut cache = ProtectionCache::default();
+    let endpoint = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let mut lookups = 0;
+
+    for _ in 0..10_000 {
+      let protection = cache.get(generation.current(), endpoint, || {
+        lookups += 1;
+        EndpointProtection::NONE
+      });
+      assert_eq!(protection, EndpointProtection::NONE);
+    }
+    assert_eq!(lookups, 1);
+
+    // A registration change invalidates the cached protection
+    generation.bump();
+    let protection = cache.get(generation.current(), endpoint, || EndpointProtection {
+      submessage: true,
+      payload: false,
+    });
+    assert!(protection.submessage);
+  }
+}
@@ -1,7 +1,7 @@
 use core::fmt;
 use std::{
   collections::{HashMap, HashSet},
-  sync::{Arc, Mutex, MutexGuard},
+  sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 
 use bytes::Bytes;
@@ -24,12 +24,16 @@ use crate::{
 };
 use super::{
   access_control::*,
+  audit::{HandshakeAudit, HandshakeDirection},
   authentication::*,
   cryptographic::{
     DatareaderCryptoHandle, DatareaderCryptoToken, DatawriterCryptoHandle, DatawriterCryptoToken,
     DecodeOutcome, DecodedSubmessage, EncodedSubmessage, EndpointCryptoHandle,
-    ParticipantCryptoHandle, ParticipantCryptoToken,
+    ParticipantCryptoHandle, ParticipantCryptoToken, SessionKeyCacheStatistics,
   },
+  decode_limits::{DecodeLimiter, DecodeLimits, DecodeRejectionCounts},
+  error_counts::{SecurityErrorCategory, SecurityErrorCounters, SecurityErrorCounts},
+  protection_cache::{EndpointProtection, ProtectionGeneration},
   types::*,
   Cryptographic,
 };
@@ -53,14 +57,27 @@ pub(crate) struct SecurityPlugins {
   rtps_not_protected: HashSet<GuidPrefix>,
   submessage_not_protected: HashSet<GUID>,
   payload_not_protected: HashSet<GUID>,
+  // Bumped whenever the sets above change, so that receivers can cache them
+  protection_generation: ProtectionGeneration,
+
+  // If set, handshakes and remote permissions validations are recorded here
+  handshake_audit: Option<HandshakeAudit>,
+
+  // Limits for received secured data. Shared with the crypto plugin.
+  decode_limiter: Arc<DecodeLimiter>,
+
+  // Errors of plugin calls, by category
+  error_counters: SecurityErrorCounters,
 }
 
 impl SecurityPlugins {
   pub fn new(
     auth: Box<impl Authentication + 'static>,
     access: Box<impl AccessControl + 'static>,
-    crypto: Box<impl Cryptographic + 'static>,
+    mut crypto: Box<impl Cryptographic + 'static>,
   ) -> Self {
+    let decode_limiter = Arc::new(DecodeLimiter::default());
+    crypto.set_decode_limiter(Arc::clone(&decode_limiter));
     Self {
       auth,
       access,
@@ -76,6 +93,92 @@ impl SecurityPlugins {
       rtps_not_protected: HashSet::new(),
       submessage_not_protected: HashSet::new(),
       payload_not_protected: HashSet::new(),
+      protection_generation: ProtectionGeneration::default(),
+
+      handshake_audit: None,
+      decode_limiter,
+      error_counters: SecurityErrorCounters::default(),
+    }
+  }
+
+  pub fn set_handshake_audit(&mut self, audit: HandshakeAudit) {
+    self.handshake_audit = Some(audit);
+  }
+
+  pub fn set_decode_limits(&mut self, limits: DecodeLimits) {
+    self.decode_limiter = Arc::new(DecodeLimiter::new(limits));
+    self
+      .crypto
+      .set_decode_limiter(Arc::clone(&self.decode_limiter));
+  }
+
+  pub fn decode_rejection_counts(&self) -> DecodeRejectionCounts {
+    self.decode_limiter.rejection_counts()
+  }
+
+  pub fn error_counts(&self) -> SecurityErrorCounts {
+    self.error_counters.counts()
+  }
+
+  pub fn session_key_cache_statistics(&self) -> Option<SessionKeyCacheStatistics> {
+    self.crypto.session_key_cache_statistics()
+  }
+
+  // Adds context to the error of a failed plugin call, and counts it.
+  fn track<T>(
+    &self,
+    result: SecurityResult<T>,
+    category: SecurityErrorCategory,
+    context: SecurityErrorContext,
+  ) -> SecurityResult<T> {
+    result.map_err(|e| {
+      self.error_counters.record(category);
+      e.with_context(context).with_category(category)
+    })
+  }
+
+  fn participant_guid(guid_prefix: GuidPrefix) -> GUID {
+    GUID::new(guid_prefix, EntityId::PARTICIPANT)
+  }
+
+  fn track_remote_check<T>(
+    &self,
+    result: SecurityResult<T>,
+    operation: &'static str,
+    remote_participant_guidp: GuidPrefix,
+  ) -> SecurityResult<T> {
+    self.track(
+      result,
+      SecurityErrorCategory::AccessControl,
+      SecurityErrorContext::new("access_control", operation)
+        .remote(Self::participant_guid(remote_participant_guidp)),
+    )
+  }
+
+  // Size of the CryptoContent in a SecureBody submessage, if it is one
+  fn crypto_content_size(submessage: &Submessage) -> Option<usize> {
+    match &submessage.body {
+      SubmessageBody::Security(SecuritySubmessage::SecureBody(secure_body, _)) => {
+        Some(secure_body.crypto_content.data.len())
+      }
+      _ => None,
+    }
+  }
+
+  fn audit_handshake_step(
+    &self,
+    remote_guidp: GuidPrefix,
+    message_in: Option<&HandshakeMessageToken>,
+    result: Result<(&ValidationOutcome, Option<&HandshakeMessageToken>), &SecurityError>,
+  ) {
+    if let Some(audit) = &self.handshake_audit {
+      if let Some(token) = message_in {
+        audit.record_message(remote_guidp, HandshakeDirection::Received, token);
+      }
+      if let Ok((_, Some(token))) = result {
+        audit.record_message(remote_guidp, HandshakeDirection::Sent, token);
+      }
+      audit.record_outcome(remote_guidp, result.map(|(outcome, _)| outcome));
     }
   }
 
@@ -382,11 +485,17 @@ impl SecurityPlugins {
   ) -> SecurityResult<(ValidationOutcome, Option<AuthRequestMessageToken>)> {
     let local_identity_handle = self.get_identity_handle(&local_participant_guidp)?;
 
-    let (outcome, remote_id_handle, auth_req_token_opt) = self.auth.validate_remote_identity(
+    let result = self.auth.validate_remote_identity(
       remote_auth_request_token,
       local_identity_handle,
       remote_identity_token,
       remote_participant_guidp,
+    );
+    let (outcome, remote_id_handle, auth_req_token_opt) = self.track(
+      result,
+      SecurityErrorCategory::Authentication,
+      SecurityErrorContext::new("authentication", "validate_remote_identity")
+        .remote(Self::participant_guid(remote_participant_guidp)),
     )?;
 
     // Add remote identity handle to cache
@@ -404,10 +513,23 @@ impl SecurityPlugins {
     let initiator_identity_handle = self.get_identity_handle(&local_guidp)?;
     let replier_identity_handle = self.get_identity_handle(&remote_guidp)?;
 
-    let (outcome, handshake_handle, handshake_token) = self.auth.begin_handshake_request(
+    let result = self.auth.begin_handshake_request(
       initiator_identity_handle,
       replier_identity_handle,
       serialized_local_participant_data,
+    );
+    self.audit_handshake_step(
+      remote_guidp,
+      None,
+      result
+        .as_ref()
+        .map(|(outcome, _, token)| (outcome, Some(token))),
+    );
+    let (outcome, handshake_handle, handshake_token) = self.track(
+      result,
+      SecurityErrorCategory::Authentication,
+      SecurityErrorContext::new("authentication", "begin_handshake_request")
+        .remote(Self::participant_guid(remote_guidp)),
     )?;
 
     // Store handshake handle
@@ -428,11 +550,28 @@ impl SecurityPlugins {
     let initiator_identity_handle = self.get_identity_handle(&remote_participant_guidp)?;
     let replier_identity_handle = self.get_identity_handle(&local_participant_guidp)?;
 
-    let (outcome, handshake_handle, handshake_token) = self.auth.begin_handshake_reply(
+    let message_in_copy = self
+      .handshake_audit
+      .as_ref()
+      .map(|_| handshake_message_in.clone());
+    let result = self.auth.begin_handshake_reply(
       handshake_message_in,
       initiator_identity_handle,
       replier_identity_handle,
       serialized_local_participant_data,
+    );
+    self.audit_handshake_step(
+      remote_participant_guidp,
+      message_in_copy.as_ref(),
+      result
+        .as_ref()
+        .map(|(outcome, _, token)| (outcome, Some(token))),
+    );
+    let (outcome, handshake_handle, handshake_token) = self.track(
+      result,
+      SecurityErrorCategory::Authentication,
+      SecurityErrorContext::new("authentication", "begin_handshake_reply")
+        .remote(Self::participant_guid(remote_participant_guidp)),
     )?;
 
     // Store handshake handle
@@ -450,9 +589,26 @@ impl SecurityPlugins {
   ) -> SecurityResult<(ValidationOutcome, Option<HandshakeMessageToken>)> {
     let handshake_handle = self.get_handshake_handle(&remote_participant_guidp)?;
 
-    self
+    let message_in_copy = self
+      .handshake_audit
+      .as_ref()
+      .map(|_| handshake_message_in.clone());
+    let result = self
       .auth
-      .process_handshake(handshake_message_in, handshake_handle)
+      .process_handshake(handshake_message_in, handshake_handle);
+    self.audit_handshake_step(
+      remote_participant_guidp,
+      message_in_copy.as_ref(),
+      result
+        .as_ref()
+        .map(|(outcome, token_opt)| (outcome, token_opt.as_ref())),
+    );
+    self.track(
+      result,
+      SecurityErrorCategory::Authentication,
+      SecurityErrorContext::new("authentication", "process_handshake")
+        .remote(Self::participant_guid(remote_participant_guidp)),
+    )
   }
 
   pub fn get_authenticated_peer_credential_token(
@@ -504,12 +660,26 @@ impl SecurityPlugins {
     let local_id_handle = self.get_identity_handle(&local_participant_guidp)?;
     let remote_id_handle = self.get_identity_handle(&remote_participant_guidp)?;
 
-    let permissions_handle = self.access.validate_remote_permissions(
+    let result = self.access.validate_remote_permissions(
       &*self.auth,
       local_id_handle,
       remote_id_handle,
       remote_permissions_token,
       remote_credential_token,
+    );
+    if let Some(audit) = &self.handshake_audit {
+      audit.record_permissions(
+        remote_participant_guidp,
+        remote_permissions_token,
+        remote_credential_token,
+        result.as_ref().map(|_| ()),
+      );
+    }
+    let permissions_handle = self.track(
+      result,
+      SecurityErrorCategory::AccessControl,
+      SecurityErrorContext::new("access_control", "validate_remote_permissions")
+        .remote(Self::participant_guid(remote_participant_guidp)),
     )?;
 
     self.insert_to_permissions_handle_cache(remote_participant_guidp, permissions_handle);
@@ -571,9 +741,10 @@ impl SecurityPlugins {
     participant_guidp: GuidPrefix,
   ) -> SecurityResult<bool> {
     let handle = self.get_permissions_handle(&participant_guidp)?;
-    self
+    let result = self
       .access
-      .check_remote_participant(handle, domain_id, None)
+      .check_remote_participant(handle, domain_id, None);
+    self.track_remote_check(result, "check_remote_participant", participant_guidp)
   }
 
   // This function is called when DataReaders from non-secure discovery
@@ -588,9 +759,10 @@ impl SecurityPlugins {
     // Convert normal DiscoveredReaderData to SubscriptionBuiltinTopicDataSecure,
     // which is what Access control plugin expects
     let secure_sub_data = SubscriptionBuiltinTopicDataSecure::from(reader_data.clone());
-    self
+    let result = self
       .access
-      .check_remote_datareader(handle, domain_id, &secure_sub_data)
+      .check_remote_datareader(handle, domain_id, &secure_sub_data);
+    self.track_remote_check(result, "check_remote_datareader", participant_guidp)
   }
 
   // This function is called when DataReaders from secure discovery
@@ -602,9 +774,10 @@ impl SecurityPlugins {
     sub_data: &SubscriptionBuiltinTopicDataSecure,
   ) -> SecurityResult<(bool, bool)> {
     let handle = self.get_permissions_handle(&participant_guidp)?;
-    self
+    let result = self
       .access
-      .check_remote_datareader(handle, domain_id, sub_data)
+      .check_remote_datareader(handle, domain_id, sub_data);
+    self.track_remote_check(result, "check_remote_datareader", participant_guidp)
   }
 
   // This function is called when DataWriters from non-secure discovery
@@ -619,9 +792,10 @@ impl SecurityPlugins {
     // Convert normal DiscoveredWriterData to PublicationBuiltinTopicDataSecure,
     // which is what Access control plugin expects
     let secure_pub_data = PublicationBuiltinTopicDataSecure::from(writer_data.clone());
-    self
+    let result = self
       .access
-      .check_remote_datawriter(handle, domain_id, &secure_pub_data)
+      .check_remote_datawriter(handle, domain_id, &secure_pub_data);
+    self.track_remote_check(result, "check_remote_datawriter", participant_guidp)
   }
 
   // This function is called when DataWriters from secure discovery
@@ -633,9 +807,10 @@ impl SecurityPlugins {
     pub_data: &PublicationBuiltinTopicDataSecure,
   ) -> SecurityResult<bool> {
     let handle = self.get_permissions_handle(&participant_guidp)?;
-    self
+    let result = self
       .access
-      .check_remote_datawriter(handle, domain_id, pub_data)
+      .check_remote_datawriter(handle, domain_id, pub_data);
+    self.track_remote_check(result, "check_remote_datawriter", participant_guidp)
   }
 
   pub fn check_remote_topic(
@@ -645,9 +820,10 @@ impl SecurityPlugins {
     topic_data: &TopicBuiltinTopicData,
   ) -> SecurityResult<bool> {
     let handle = self.get_permissions_handle(&participant_guidp)?;
-    self
+    let result = self
       .access
-      .check_remote_topic(handle, domain_id, topic_data)
+      .check_remote_topic(handle, domain_id, topic_data);
+    self.track_remote_check(result, "check_remote_topic", participant_guidp)
   }
 
   pub fn get_permissions_token(
@@ -754,12 +930,13 @@ impl SecurityPlugins {
       properties.push(volatile_reader_recognition_property());
     }
 
-    if !reader_security_attributes.is_submessage_protected {
-      self.submessage_not_protected.insert(reader_guid);
-    }
-    if !reader_security_attributes.is_payload_protected {
-      self.payload_not_protected.insert(reader_guid);
-    }
+    self.set_endpoint_protection(
+      reader_guid,
+      EndpointProtection {
+        submessage: reader_security_attributes.is_submessage_protected,
+        payload: reader_security_attributes.is_payload_protected,
+      },
+    );
 
     let crypto_handle = self.crypto.register_local_datareader(
       local_participant_crypto_handle,
@@ -789,12 +966,13 @@ impl SecurityPlugins {
       properties.push(volatile_writer_recognition_property());
     }
 
-    if !writer_security_attributes.is_submessage_protected {
-      self.submessage_not_protected.insert(writer_guid);
-    }
-    if !writer_security_attributes.is_payload_protected {
-      self.payload_not_protected.insert(writer_guid);
-    }
+    self.set_endpoint_protection(
+      writer_guid,
+      EndpointProtection {
+        submessage: writer_security_attributes.is_submessage_protected,
+        payload: writer_security_attributes.is_payload_protected,
+      },
+    );
 
     let crypto_handle = self.crypto.register_local_datawriter(
       local_participant_crypto_handle,
@@ -910,12 +1088,14 @@ impl SecurityPlugins {
   } */
 
   pub fn unregister_local_reader(&mut self, reader_guid: &GUID) -> SecurityResult<()> {
+    self.forget_endpoint_protection(reader_guid);
     self
       .remove_local_endpoint_crypto_handle(reader_guid)
       .map_or(Ok(()), |handle| self.crypto.unregister_datareader(handle))
   }
 
   pub fn unregister_local_writer(&mut self, writer_guid: &GUID) -> SecurityResult<()> {
+    self.forget_endpoint_protection(writer_guid);
     self
       .remove_local_endpoint_crypto_handle(writer_guid)
       .map_or(Ok(()), |handle| self.crypto.unregister_datawriter(handle))
@@ -958,12 +1138,51 @@ impl SecurityPlugins {
       .remove_remote_endpoint_crypto_handle((matched_local_reader_guid, writer_guid))
       .map_or(Ok(()), |handle| self.crypto.unregister_datawriter(handle))
   }
+
+  /// Whether the submessages of a local writer carry a receiver-specific MAC
+  /// for each reader they are sent to.
+  pub fn is_writer_origin_authenticated(&self, writer_guid: &GUID) -> bool {
+    self
+      .local_endpoint_crypto_handle_cache
+      .get(writer_guid)
+      .is_some_and(|handle| self.crypto.is_datawriter_origin_authenticated(*handle))
+  }
+
+  /// Generates new keys for a local writer or reader. Returns the GUIDs of the
+  /// matched remote endpoints, to which new crypto tokens must be sent.
+  pub fn rekey_local_endpoint(&self, local_endpoint_guid: GUID) -> SecurityResult<Vec<GUID>> {
+    let local_endpoint_crypto_handle =
+      self.get_local_endpoint_crypto_handle(&local_endpoint_guid)?;
+    let result = if local_endpoint_guid.entity_id.entity_kind.is_writer() {
+      self
+        .crypto
+        .rekey_local_datawriter(local_endpoint_crypto_handle)
+    } else {
+      self
+        .crypto
+        .rekey_local_datareader(local_endpoint_crypto_handle)
+    };
+    self.track(
+      result,
+      SecurityErrorCategory::KeyExchange,
+      SecurityErrorContext::new("cryptographic", "rekey_local_endpoint"),
+    )?;
+
+    Ok(
+      self
+        .remote_endpoint_crypto_handle_cache
+        .keys()
+        .filter(|(local_guid, _)| *local_guid == local_endpoint_guid)
+        .map(|(_, remote_guid)| *remote_guid)
+        .collect(),
+    )
+  }
 }
 
 /// Interface for using the CryptoKeyExchange of the Cryptographic plugin
 impl SecurityPlugins {
   pub fn create_local_participant_crypto_tokens(
-    &mut self,
+    &self,
     remote_participant_guidp: GuidPrefix,
   ) -> SecurityResult<Vec<ParticipantCryptoToken>> {
     let local_crypto_handle = self.get_local_participant_crypto_handle()?;
@@ -976,7 +1195,7 @@ impl SecurityPlugins {
   }
 
   pub fn create_local_writer_crypto_tokens(
-    &mut self,
+    &self,
     local_writer_guid: GUID,
     remote_reader_guid: GUID,
   ) -> SecurityResult<Vec<ParticipantCryptoToken>> {
@@ -991,7 +1210,7 @@ impl SecurityPlugins {
   }
 
   pub fn create_local_reader_crypto_tokens(
-    &mut self,
+    &self,
     local_reader_guid: GUID,
     remote_writer_guid: GUID,
   ) -> SecurityResult<Vec<ParticipantCryptoToken>> {
@@ -1005,53 +1224,81 @@ impl SecurityPlugins {
     )
   }
 
+  fn check_crypto_token_count(&self, count: usize) -> SecurityResult<()> {
+    self
+      .decode_limiter
+      .check_crypto_tokens(count)
+      .map_err(|rejection| create_security_error_and_log!("{rejection}"))
+  }
+
   pub fn set_remote_participant_crypto_tokens(
-    &mut self,
+    &self,
     remote_participant_guidp: GuidPrefix,
     remote_participant_tokens: Vec<ParticipantCryptoToken>,
   ) -> SecurityResult<()> {
+    self.check_crypto_token_count(remote_participant_tokens.len())?;
     let local_crypto_handle = self.get_local_participant_crypto_handle()?;
     let remote_crypto_handle =
       self.get_remote_participant_crypto_handle(&remote_participant_guidp)?;
 
-    self.crypto.set_remote_participant_crypto_tokens(
+    let result = self.crypto.set_remote_participant_crypto_tokens(
       local_crypto_handle,
       remote_crypto_handle,
       remote_participant_tokens,
+    );
+    self.track(
+      result,
+      SecurityErrorCategory::KeyExchange,
+      SecurityErrorContext::new("cryptographic", "set_remote_participant_crypto_tokens")
+        .remote(Self::participant_guid(remote_participant_guidp)),
     )
   }
 
   pub fn set_remote_writer_crypto_tokens(
-    &mut self,
+    &self,
     remote_writer_guid: GUID,
     local_reader_guid: GUID,
     remote_crypto_tokens: Vec<DatawriterCryptoToken>,
   ) -> SecurityResult<()> {
+    self.check_crypto_token_count(remote_crypto_tokens.len())?;
     let local_reader_crypto_handle = self.get_local_endpoint_crypto_handle(&local_reader_guid)?;
     let remote_writer_crypto_handle =
       self.get_remote_endpoint_crypto_handle((&local_reader_guid, &remote_writer_guid))?;
 
-    self.crypto.set_remote_datawriter_crypto_tokens(
+    let result = self.crypto.set_remote_datawriter_crypto_tokens(
       local_reader_crypto_handle,
       remote_writer_crypto_handle,
       remote_crypto_tokens,
+    );
+    self.track(
+      result,
+      SecurityErrorCategory::KeyExchange,
+      SecurityErrorContext::new("cryptographic", "set_remote_datawriter_crypto_tokens")
+        .remote(remote_writer_guid),
     )
   }
 
   pub fn set_remote_reader_crypto_tokens(
-    &mut self,
+    &self,
     remote_reader_guid: GUID,
     local_writer_guid: GUID,
     remote_crypto_tokens: Vec<DatareaderCryptoToken>,
   ) -> SecurityResult<()> {
+    self.check_crypto_token_count(remote_crypto_tokens.len())?;
     let local_writer_crypto_handle = self.get_local_endpoint_crypto_handle(&local_writer_guid)?;
     let remote_reader_crypto_handle =
       self.get_remote_endpoint_crypto_handle((&local_writer_guid, &remote_reader_guid))?;
 
-    self.crypto.set_remote_datareader_crypto_tokens(
+    let result = self.crypto.set_remote_datareader_crypto_tokens(
       local_writer_crypto_handle,
       remote_reader_crypto_handle,
       remote_crypto_tokens,
+    );
+    self.track(
+      result,
+      SecurityErrorCategory::KeyExchange,
+      SecurityErrorContext::new("cryptographic", "set_remote_datareader_crypto_tokens")
+        .remote(remote_reader_guid),
     )
   }
 }
@@ -1068,9 +1315,14 @@ impl SecurityPlugins {
       return Ok((serialized_payload, ParameterList::new()));
     }
 
-    self.crypto.encode_serialized_payload(
+    let result = self.crypto.encode_serialized_payload(
       serialized_payload,
       self.get_local_endpoint_crypto_handle(sending_datawriter_guid)?,
+    );
+    self.track(
+      result,
+      SecurityErrorCategory::CryptoEncode,
+      SecurityErrorContext::new("cryptographic", "encode_serialized_payload"),
     )
   }
 
@@ -1097,10 +1349,15 @@ impl SecurityPlugins {
         receiving_datareader_crypto_list.sort();
         receiving_datareader_crypto_list.dedup();
 
-        self.crypto.encode_datawriter_submessage(
+        let result = self.crypto.encode_datawriter_submessage(
           plain_submessage,
           self.get_local_endpoint_crypto_handle(source_guid)?,
           receiving_datareader_crypto_list,
+        );
+        self.track(
+          result,
+          SecurityErrorCategory::CryptoEncode,
+          SecurityErrorContext::new("cryptographic", "encode_datawriter_submessage"),
         )
       }
       SubmessageBody::Interpreter(_) => Ok(EncodedSubmessage::Unencoded(plain_submessage)),
@@ -1136,10 +1393,15 @@ impl SecurityPlugins {
         receiving_datawriter_crypto_list.sort();
         receiving_datawriter_crypto_list.dedup();
 
-        self.crypto.encode_datareader_submessage(
+        let result = self.crypto.encode_datareader_submessage(
           plain_submessage,
           self.get_local_endpoint_crypto_handle(source_guid)?,
           receiving_datawriter_crypto_list,
+        );
+        self.track(
+          result,
+          SecurityErrorCategory::CryptoEncode,
+          SecurityErrorContext::new("cryptographic", "encode_datareader_submessage"),
         )
       }
       SubmessageBody::Interpreter(_) => Ok(EncodedSubmessage::Unencoded(plain_submessage)),
@@ -1241,10 +1503,15 @@ impl SecurityPlugins {
     receiving_participant_crypto_list.sort();
     receiving_participant_crypto_list.dedup();
 
-    self.crypto.encode_rtps_message(
+    let result = self.crypto.encode_rtps_message(
       plain_message,
       self.get_local_participant_crypto_handle()?,
       receiving_participant_crypto_list,
+    );
+    self.track(
+      result,
+      SecurityErrorCategory::CryptoEncode,
+      SecurityErrorContext::new("cryptographic", "encode_rtps_message"),
     )
   }
 
@@ -1256,7 +1523,15 @@ impl SecurityPlugins {
     encoded_message: Message,
     source_guid_prefix: &GuidPrefix,
   ) -> SecurityResult<DecodeOutcome<Message>> {
-    self
+    let content_size = encoded_message
+      .submessages
+      .iter()
+      .filter_map(Self::crypto_content_size)
+      .sum();
+    if let Err(rejection) = self.decode_limiter.check_crypto_content_size(content_size) {
+      return Ok(DecodeOutcome::LimitExceeded(rejection));
+    }
+    let result = self
       .remote_participant_crypto_handle_cache
       .get(source_guid_prefix)
       .map_or(
@@ -1270,7 +1545,13 @@ impl SecurityPlugins {
             *source_crypto_handle,
           )
         },
-      )
+      );
+    self.track(
+      result,
+      SecurityErrorCategory::CryptoDecode,
+      SecurityErrorContext::new("cryptographic", "decode_rtps_message")
+        .remote(Self::participant_guid(*source_guid_prefix)),
+    )
   }
 
   // Currently only those submessages whose destination is the local participant
@@ -1281,7 +1562,12 @@ impl SecurityPlugins {
     encoded_rtps_submessage: (SecurePrefix, Submessage, SecurePostfix),
     source_guid_prefix: &GuidPrefix,
   ) -> SecurityResult<DecodeOutcome<DecodedSubmessage>> {
-    self
+    if let Some(content_size) = Self::crypto_content_size(&encoded_rtps_submessage.1) {
+      if let Err(rejection) = self.decode_limiter.check_crypto_content_size(content_size) {
+        return Ok(DecodeOutcome::LimitExceeded(rejection));
+      }
+    }
+    let result = self
       .remote_participant_crypto_handle_cache
       .get(source_guid_prefix)
       .map_or(
@@ -1295,7 +1581,13 @@ impl SecurityPlugins {
             *source_crypto_handle,
           )
         },
-      )
+      );
+    self.track(
+      result,
+      SecurityErrorCategory::CryptoDecode,
+      SecurityErrorContext::new("cryptographic", "decode_submessage")
+        .remote(Self::participant_guid(*source_guid_prefix)),
+    )
   }
 
   pub fn decode_serialized_payload(
@@ -1309,6 +1601,10 @@ impl SecurityPlugins {
       Ok(encoded_payload)
     } else {
       self
+        .decode_limiter
+        .check_crypto_content_size(encoded_payload.len())
+        .map_err(|rejection| create_security_error_and_log!("{rejection}"))?;
+      let result = self
         .crypto
         .decode_serialized_payload(
           Vec::from(encoded_payload),
@@ -1316,7 +1612,13 @@ impl SecurityPlugins {
           self.get_local_endpoint_crypto_handle(destination_guid)?,
           self.get_remote_endpoint_crypto_handle((destination_guid, source_guid))?,
         )
-        .map(Bytes::from)
+        .map(Bytes::from);
+      self.track(
+        result,
+        SecurityErrorCategory::CryptoDecode,
+        SecurityErrorContext::new("cryptographic", "decode_serialized_payload")
+          .remote(*source_guid),
+      )
     }
   }
 
@@ -1333,26 +1635,79 @@ impl SecurityPlugins {
   pub fn payload_not_protected(&self, local_endpoint_guid: &GUID) -> bool {
     self.payload_not_protected.contains(local_endpoint_guid)
   }
+
+  pub fn endpoint_protection(&self, local_endpoint_guid: &GUID) -> EndpointProtection {
+    EndpointProtection {
+      submessage: !self.submessage_not_protected(local_endpoint_guid),
+      payload: !self.payload_not_protected(local_endpoint_guid),
+    }
+  }
+
+  fn set_endpoint_protection(&mut self, local_endpoint_guid: GUID, protection: EndpointProtection) {
+    if protection.submessage {
+      self.submessage_not_protected.remove(&local_endpoint_guid);
+    } else {
+      self.submessage_not_protected.insert(local_endpoint_guid);
+    }
+    if protection.payload {
+      self.payload_not_protected.remove(&local_endpoint_guid);
+    } else {
+      self.payload_not_protected.insert(local_endpoint_guid);
+    }
+    self.protection_generation.bump();
+  }
+
+  // Endpoints that are not registered are treated as protected, so that
+  // nothing unprotected is accepted for them.
+  fn forget_endpoint_protection(&mut self, local_endpoint_guid: &GUID) {
+    self.submessage_not_protected.remove(local_endpoint_guid);
+    self.payload_not_protected.remove(local_endpoint_guid);
+    self.protection_generation.bump();
+  }
 }
 
+// The plugins are shared by the event loop, Discovery, and application threads
+// writing data. Encoding and decoding need only shared access, so that
+// independent endpoints can be processed concurrently. So do key exchange and
+// re-keying: the crypto plugin locks the key materials of each entity
+// separately. Exclusive access is needed only for registering and
+// unregistering entities, and for authentication, which change the handle
+// caches and are rare in comparison.
 #[derive(Clone)]
 pub(crate) struct SecurityPluginsHandle {
-  inner: Arc<Mutex<SecurityPlugins>>,
+  inner: Arc<RwLock<SecurityPlugins>>,
   who_has_it: Arc<Mutex<Option<String>>>,
+  protection_generation: ProtectionGeneration,
 }
 
 impl SecurityPluginsHandle {
   pub(crate) fn new(s: SecurityPlugins) -> Self {
     Self {
-      inner: Arc::new(Mutex::new(s)),
+      protection_generation: s.protection_generation.clone(),
+      inner: Arc::new(RwLock::new(s)),
       who_has_it: Arc::new(Mutex::new(None)),
     }
   }
 
-  pub(crate) fn get_plugins(&self) -> MutexGuard<SecurityPlugins> {
+  // Changes whenever the protection of a local endpoint changes. Reading this
+  // does not lock the plugins.
+  pub(crate) fn protection_generation(&self) -> u64 {
+    self.protection_generation.current()
+  }
+
+  // Shared access for encode and decode operations, and other queries.
+  pub(crate) fn read_plugins(&self) -> RwLockReadGuard<'_, SecurityPlugins> {
+    self.inner.read().unwrap_or_else(|poisoned| {
+      create_security_error_and_log!("Security plugins are poisoned! {poisoned:?}");
+      panic!("Security plugins are poisoned!");
+    })
+  }
+
+  // Exclusive access
+  pub(crate) fn get_plugins(&self) -> RwLockWriteGuard<'_, SecurityPlugins> {
     let mut count = 0;
     loop {
-      match self.try_lock() {
+      match self.inner.try_write() {
         Ok(guard) => {
           *self.who_has_it.lock().unwrap() = std::thread::current().name().map(|s| s.to_owned());
           return guard;
@@ -1384,7 +1739,7 @@ impl fmt::Debug for SecurityPluginsHandle {
 }
 
 impl std::ops::Deref for SecurityPluginsHandle {
-  type Target = Mutex<SecurityPlugins>;
+  type Target = RwLock<SecurityPlugins>;
   fn deref(&self) -> &Self::Target {
     &self.inner
   }
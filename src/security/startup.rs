@@ -0,0 +1,106 @@
+//! Progress of secure DomainParticipant construction.
+//!
+//! Validating the identity certificate chain, verifying the S/MIME signatures
+//! of the governance and permissions documents, and setting up the
+//! cryptographic state can take a noticeable time on small CPUs. A callback
+//! given to
+//! [`DomainParticipantBuilder::security_startup_progress`](crate::DomainParticipantBuilder::security_startup_progress)
+//! is called as each step begins, so that the application can show progress.
+//!
+//! To keep the calling thread free altogether, build the participant with
+//! [`DomainParticipantBuilder::build_async`](crate::DomainParticipantBuilder::build_async).
+
+use std::time::Instant;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+/// Steps of secure DomainParticipant construction, in the order they are
+/// taken. See DDS Security spec v1.1 Section "8.8.1 Authentication and
+/// AccessControl behavior with local DomainParticipant".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SecurityStartupStage {
+  /// Loading the identity certificate and private key, and validating the
+  /// certificate chain.
+  ValidatingIdentity,
+  /// Verifying the signatures of the governance and permissions documents.
+  ValidatingPermissions,
+  /// Checking that the permissions allow joining the domain.
+  CheckingParticipantAccess,
+  /// Generating the cryptographic keys of the participant.
+  RegisteringCryptography,
+  /// Creating the security tokens of secure Discovery.
+  StartingSecureDiscovery,
+  /// Construction is complete. The participant starts authenticating remote
+  /// participants.
+  Ready,
+}
+
+/// A step of secure DomainParticipant construction, and the time elapsed
+/// since construction started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SecurityStartupProgress {
+  pub stage: SecurityStartupStage,
+  pub elapsed: std::time::Duration,
+}
+
+pub(crate) type SecurityStartupCallback = Box<dyn FnMut(SecurityStartupProgress) + Send>;
+
+// Reports the progress of construction to the application, if it asked for
+// it.
+pub(crate) struct SecurityStartupReporter {
+  callback: Option<SecurityStartupCallback>,
+  started: Instant,
+}
+
+impl SecurityStartupReporter {
+  pub fn new(callback: Option<SecurityStartupCallback>) -> Self {
+    Self {
+      callback,
+      started: Instant::now(),
+    }
+  }
+
+  pub fn elapsed(&self) -> std::time::Duration {
+    self.started.elapsed()
+  }
+
+  pub fn report(&mut self, stage: SecurityStartupStage) {
+    let progress = SecurityStartupProgress {
+      stage,
+      elapsed: self.elapsed(),
+    };
+    debug!("Secure participant construction: {progress:?}");
+    if let Some(callback) = self.callback.as_mut() {
+      callback(progress);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{Arc, Mutex};
+
+  use super::*;
+
+  #[test]
+  fn stages_are_reported_in_order() {
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let reported_clone = Arc::clone(&reported);
+    let mut reporter = SecurityStartupReporter::new(Some(Box::new(move |progress| {
+      reported_clone.lock().unwrap().push(progress);
+    })));
+    reporter.report(SecurityStartupStage::ValidatingIdentity);
+    reporter.report(SecurityStartupStage::Ready);
+
+    let reported = reported.lock().unwrap();
+    assert_eq!(
+      reported.iter().map(|p| p.stage).collect::<Vec<_>>(),
+      vec![
+        SecurityStartupStage::ValidatingIdentity,
+        SecurityStartupStage::Ready
+      ]
+    );
+    assert!(reported[0].elapsed <= reported[1].elapsed);
+  }
+}
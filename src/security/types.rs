@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 use bytes::Bytes;
 use enumflags2::{bitflags, BitFlags};
@@ -14,7 +14,7 @@ use crate::{
     parameter_list::{ParameterList, ParameterListable},
   },
   security,
-  security::config::ConfigError,
+  security::{config::ConfigError, error_counts::SecurityErrorCategory},
   serialization::{
     padding_needed_for_alignment_4,
     pl_cdr_adapters::{
@@ -29,56 +29,157 @@ use crate::{
 // Result type with generic OK type. Error type is SecurityError.
 pub type SecurityResult<T> = std::result::Result<T, SecurityError>;
 
-// Something like the SecurityException of the specification
+// Something like the SecurityException of the specification. An error may
+// wrap the error that caused it, adding context of where it happened. The
+// message of the wrapping error includes the messages of its causes.
 #[derive(Debug, thiserror::Error)]
 #[error("Security exception: {msg}")]
 pub struct SecurityError {
   pub(crate) msg: String,
+  category: SecurityErrorCategory,
+  context: Option<SecurityErrorContext>,
+  cause: Option<Box<SecurityError>>,
 }
 
-pub fn security_error(msg: &str) -> SecurityError {
-  SecurityError {
-    msg: msg.to_string(),
+impl SecurityError {
+  /// A new error in the category `Other`. Custom plugins may set the category
+  /// with [`with_category`](Self::with_category).
+  pub fn new(msg: String) -> Self {
+    Self {
+      msg,
+      category: SecurityErrorCategory::Other,
+      context: None,
+      cause: None,
+    }
+  }
+
+  /// Wraps this error in an error that tells where it happened. The category
+  /// is kept.
+  #[must_use]
+  pub fn with_context(self, context: SecurityErrorContext) -> Self {
+    Self {
+      msg: format!("{context}: {}", self.msg),
+      category: self.category,
+      context: Some(context),
+      cause: Some(Box::new(self)),
+    }
+  }
+
+  #[must_use]
+  pub fn with_category(mut self, category: SecurityErrorCategory) -> Self {
+    self.category = category;
+    self
+  }
+
+  pub fn category(&self) -> SecurityErrorCategory {
+    self.category
+  }
+
+  pub fn context(&self) -> Option<&SecurityErrorContext> {
+    self.context.as_ref()
+  }
+
+  /// The error this one wraps, if any.
+  pub fn cause(&self) -> Option<&SecurityError> {
+    self.cause.as_deref()
   }
+
+  /// The innermost error in the chain of causes.
+  pub fn root_cause(&self) -> &SecurityError {
+    let mut error = self;
+    while let Some(cause) = error.cause() {
+      error = cause;
+    }
+    error
+  }
+
+  /// The remote participant or endpoint that the error concerns, from the
+  /// innermost context that names one.
+  pub fn remote_guid(&self) -> Option<GUID> {
+    let mut remote = None;
+    let mut error = Some(self);
+    while let Some(e) = error {
+      if let Some(guid) = e.context.as_ref().and_then(|c| c.remote_guid) {
+        remote = Some(guid);
+      }
+      error = e.cause();
+    }
+    remote
+  }
+}
+
+/// Where a [`SecurityError`] happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityErrorContext {
+  /// Security plugin or other part of the implementation, e.g.
+  /// "cryptographic"
+  pub module: &'static str,
+  /// Operation that failed, e.g. "decode_submessage"
+  pub operation: &'static str,
+  pub remote_guid: Option<GUID>,
+}
+
+impl SecurityErrorContext {
+  pub fn new(module: &'static str, operation: &'static str) -> Self {
+    Self {
+      module,
+      operation,
+      remote_guid: None,
+    }
+  }
+
+  #[must_use]
+  pub fn remote(mut self, remote_guid: GUID) -> Self {
+    self.remote_guid = Some(remote_guid);
+    self
+  }
+}
+
+impl fmt::Display for SecurityErrorContext {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}::{}", self.module, self.operation)?;
+    if let Some(guid) = self.remote_guid {
+      write!(f, " (remote {guid:?})")?;
+    }
+    Ok(())
+  }
+}
+
+pub fn security_error(msg: &str) -> SecurityError {
+  SecurityError::new(msg.to_string())
 }
 
 impl From<ring::error::Unspecified> for SecurityError {
   fn from(_e: ring::error::Unspecified) -> Self {
-    SecurityError {
-      msg: "The ring crypto library gives 'Unspecified' error. That's all we are authorized to \
-            know. Sorry."
+    SecurityError::new(
+      "The ring crypto library gives 'Unspecified' error. That's all we are authorized to know. \
+       Sorry."
         .to_string(),
-    }
+    )
   }
 }
 
 impl From<speedy::Error> for SecurityError {
   fn from(e: speedy::Error) -> Self {
-    SecurityError {
-      msg: format!("Serialization/deserialization error: {e:?}"),
-    }
+    SecurityError::new(format!("Serialization/deserialization error: {e:?}"))
   }
 }
 
 impl From<&str> for SecurityError {
   fn from(e: &str) -> Self {
-    SecurityError {
-      msg: format!("SecurityError {e}"),
-    }
+    SecurityError::new(format!("SecurityError {e}"))
   }
 }
 
 impl From<String> for SecurityError {
   fn from(msg: String) -> Self {
-    SecurityError { msg }
+    SecurityError::new(msg)
   }
 }
 
 impl From<ConfigError> for SecurityError {
   fn from(e: ConfigError) -> Self {
-    SecurityError {
-      msg: format!("ConfigError {e:?}"),
-    }
+    SecurityError::new(format!("ConfigError {e:?}"))
   }
 }
 
@@ -86,25 +187,19 @@ use x509_certificate::X509CertificateError;
 
 impl From<X509CertificateError> for SecurityError {
   fn from(e: X509CertificateError) -> Self {
-    SecurityError {
-      msg: format!("X509CertificateError {e:?}"),
-    }
+    SecurityError::new(format!("X509CertificateError {e:?}"))
   }
 }
 
 impl From<openssl::error::ErrorStack> for SecurityError {
   fn from(e: openssl::error::ErrorStack) -> Self {
-    SecurityError {
-      msg: format!("openssl Error: {e:?}"),
-    }
+    SecurityError::new(format!("openssl Error: {e:?}"))
   }
 }
 
 impl From<cryptoki::error::Error> for SecurityError {
   fn from(e: cryptoki::error::Error) -> Self {
-    SecurityError {
-      msg: format!("cryptoki (PKCS#11) Error: {e:?}"),
-    }
+    SecurityError::new(format!("cryptoki (PKCS#11) Error: {e:?}"))
   }
 }
 
@@ -113,7 +208,7 @@ impl From<cryptoki::error::Error> for SecurityError {
 macro_rules! create_security_error_and_log {
   ($($arg:tt)*) => (
       { log::error!($($arg)*);  // Note: this needs to be security-specific logging
-        SecurityError{ msg: format!($($arg)*) }
+        SecurityError::new(format!($($arg)*))
       }
     )
 }
@@ -441,6 +536,7 @@ pub struct DataHolder {
 }
 
 impl DataHolder {
+  #[cfg(any(test, not(feature = "security_hardened")))]
   pub fn dummy() -> Self {
     Self {
       class_id: "dummy".to_string(),
@@ -1017,6 +1113,21 @@ impl From<ParticipantGenericMessage> for ParticipantStatelessMessage {
   }
 }
 
+// Selects the AES key length of the builtin crypto plugin: "128" or "256"
+// (default)
+pub const CRYPTO_KEY_SIZE_PROPERTY_NAME: &str = "dds.sec.crypto.keysize";
+// How many milliseconds the builtin crypto plugin keeps the old keys of a
+// re-keyed remote entity
+pub const CRYPTO_REKEY_GRACE_PERIOD_PROPERTY_NAME: &str = "dds.sec.crypto.rekey_grace_period_ms";
+// DataWriter property: "true" makes the builtin crypto plugin append a
+// receiver-specific MAC for each matched reader to protected submessages, even
+// if the governance document does not require origin authentication
+pub const CRYPTO_ORIGIN_AUTHENTICATION_PROPERTY_NAME: &str = "dds.sec.crypto.origin_authentication";
+// DataWriter property: the most receiver-specific MACs sent in one message.
+// To more matched readers the message is sent in batches.
+pub const CRYPTO_MAX_RECEIVER_SPECIFIC_MACS_PROPERTY_NAME: &str =
+  "dds.sec.crypto.max_receiver_specific_macs";
+
 pub const VOLATILE_ENDPOINT_RECOGNITION_PROPERTY_NAME: &str = "dds.sec.builtin_endpoint_name";
 pub const VOLATILE_WRITER_RECOGNITION_PROPERTY_VALUE: &str =
   "BuiltinParticipantVolatileMessageSecureWriter";
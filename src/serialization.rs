@@ -3,18 +3,28 @@ mod cdr_adapters;
 pub(crate) mod pl_cdr_adapters;
 pub(crate) mod speedy_pl_cdr_helpers;
 
+pub mod extensible;
+pub mod idl;
 mod representation_identifier;
+pub mod xcdr2;
 
-// Most of the CDR encoding/decoding comes from this external crate
-pub use cdr_encoding::{
-  from_bytes, to_vec, to_writer, CdrDeserializer, CdrSerializer, Error, Result,
-};
+// Most of the CDR encoding/decoding comes from this external crate. Its byte
+// order is a type parameter. Where the byte order comes from the data, use the
+// functions taking `Endianness` or `RepresentationIdentifier` below instead.
+pub use cdr_encoding::{to_writer, CdrSerializer, Error, Result};
 // Export some parts of inner modules
+#[allow(deprecated)]
 pub use cdr_adapters::{
-  deserialize_from_cdr_with_decoder_and_rep_id, deserialize_from_cdr_with_rep_id,
-  to_writer_with_rep_id, CDRDeserializerAdapter, CDRSerializerAdapter, CdrDeserializeSeedDecoder,
+  deserialize_from_cdr_with_decoder_and_endianness, deserialize_from_cdr_with_decoder_and_rep_id,
+  deserialize_from_cdr_with_rep_id, from_bytes, from_bytes_with_endianness,
+  from_encapsulated_bytes, to_vec, to_vec_with_endianness, to_writer_with_rep_id,
+  CDRDeserializerAdapter, CDRSerializerAdapter, CdrDeserializeSeedDecoder, CdrDeserializer,
 };
 pub use representation_identifier::RepresentationIdentifier;
+pub use extensible::{
+  Extensible, ExtensibleDeserializerAdapter, ExtensibleSerializerAdapter, StructLayout,
+};
+pub use xcdr2::{Xcdr2Encoding, Xcdr2SerializerAdapter};
 
 // Compute how much padding bytes are needed to
 // get the next multiple of 4
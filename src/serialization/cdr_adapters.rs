@@ -6,10 +6,13 @@ use serde::{
 };
 use bytes::Bytes;
 use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use speedy::Endianness;
 
+use super::xcdr2::{deserialize_xcdr2_with_decoder, to_writer_xcdr2};
 use crate::{
   dds::{
     adapters::{no_key, with_key},
+    content_filter::DdsSqlFilter,
     key::Keyed,
   },
   RepresentationIdentifier,
@@ -39,7 +42,7 @@ where
   type Error = Error;
 
   fn output_encoding() -> RepresentationIdentifier {
-    RepresentationIdentifier::CDR_LE
+    RepresentationIdentifier::cdr(endianness_of::<BO>())
   }
 
   fn to_bytes(value: &D) -> Result<Bytes> {
@@ -48,6 +51,10 @@ where
     to_writer::<D, BO, &mut Vec<u8>>(&mut buffer, value)?;
     Ok(Bytes::from(buffer))
   }
+
+  fn matches_sql_filter(value: &D, filter: &DdsSqlFilter) -> Option<bool> {
+    Some(filter.matches(value))
+  }
 }
 
 impl<D, BO> with_key::SerializerAdapter<D> for CDRSerializerAdapter<D, BO>
@@ -64,7 +71,18 @@ where
   }
 }
 
-/// Serialize
+// The byte order of a `ByteOrder` type, as a value
+pub(crate) fn endianness_of<BO: ByteOrder>() -> Endianness {
+  let mut one = [0; 2];
+  BO::write_u16(&mut one, 1);
+  if one[0] == 1 {
+    Endianness::LittleEndian
+  } else {
+    Endianness::BigEndian
+  }
+}
+
+/// Serialize, as classic CDR or XCDR2 depending on `encoding`
 pub fn to_writer_with_rep_id<T, W>(
   writer: W,
   value: &T,
@@ -74,14 +92,60 @@ where
   T: Serialize,
   W: io::Write,
 {
-  match encoding {
-    RepresentationIdentifier::CDR_LE | RepresentationIdentifier::PL_CDR_LE => {
-      to_writer::<T, LittleEndian, W>(writer, value)
-    }
+  if let Some((xcdr2_encoding, endianness)) = encoding.xcdr2_encoding() {
+    return to_writer_xcdr2(writer, value, xcdr2_encoding, endianness);
+  }
+  match encoding.cdr_endianness() {
+    Some(Endianness::LittleEndian) => to_writer::<T, LittleEndian, W>(writer, value),
     _ => to_writer::<T, BigEndian, W>(writer, value),
   }
 }
 
+/// Serializes to CDR in a byte order chosen at run time.
+///
+/// This replaces [`to_vec`], whose byte order is a type parameter.
+pub fn to_vec_with_endianness<T>(value: &T, endianness: Endianness) -> Result<Vec<u8>>
+where
+  T: Serialize,
+{
+  match endianness {
+    Endianness::LittleEndian => cdr_encoding::to_vec::<T, LittleEndian>(value),
+    Endianness::BigEndian => cdr_encoding::to_vec::<T, BigEndian>(value),
+  }
+}
+
+/// Serializes to CDR in the byte order `BO`.
+#[deprecated(note = "Use to_vec_with_endianness, which takes the byte order as a value")]
+pub fn to_vec<T, BO>(value: &T) -> Result<Vec<u8>>
+where
+  T: Serialize,
+  BO: ByteOrder,
+{
+  cdr_encoding::to_vec::<T, BO>(value)
+}
+
+/// Deserializes CDR in the byte order `BO`.
+///
+/// Returns deserialized object and byte count of stream consumed.
+#[deprecated(
+  note = "Use from_bytes_with_endianness, or from_encapsulated_bytes to take the byte order from \
+          the encapsulation header"
+)]
+pub fn from_bytes<'de, T, BO>(input_bytes: &[u8]) -> Result<(T, usize)>
+where
+  T: Deserialize<'de>,
+  BO: ByteOrder,
+{
+  cdr_encoding::from_bytes::<T, BO>(input_bytes)
+}
+
+/// CDR deserializer for the byte order `BO`.
+#[deprecated(
+  note = "Use from_bytes_with_endianness or deserialize_from_cdr_with_decoder_and_endianness, \
+          which choose the byte order at run time"
+)]
+pub type CdrDeserializer<'i, BO> = cdr_encoding::CdrDeserializer<'i, BO>;
+
 /// This type adapts CdrDeserializer (which implements serde::Deserializer) to
 /// work as a [`with_key::DeserializerAdapter`] and
 /// [`no_key::DeserializerAdapter`].
@@ -89,14 +153,30 @@ where
 /// CdrDeserializer cannot directly implement
 /// the trait itself, because CdrDeserializer has the type parameter BO open,
 /// and the adapter needs to be bi-endian.
+///
+/// XCDR2 data is decoded too, see the [`xcdr2`](crate::serialization::xcdr2)
+/// module.
 pub struct CDRDeserializerAdapter<D> {
   phantom: PhantomData<D>,
 }
 
-const REPR_IDS: [RepresentationIdentifier; 3] = [
+pub(crate) const REPR_IDS: [RepresentationIdentifier; 16] = [
   RepresentationIdentifier::CDR_BE,
   RepresentationIdentifier::CDR_LE,
+  RepresentationIdentifier::PL_CDR_BE,
   RepresentationIdentifier::PL_CDR_LE,
+  RepresentationIdentifier::XCDR2_BE,
+  RepresentationIdentifier::XCDR2_LE,
+  RepresentationIdentifier::D_CDR2_BE,
+  RepresentationIdentifier::D_CDR2_LE,
+  RepresentationIdentifier::PL_XCDR2_BE,
+  RepresentationIdentifier::PL_XCDR2_LE,
+  RepresentationIdentifier::CDR2_BE,
+  RepresentationIdentifier::CDR2_LE,
+  RepresentationIdentifier::D_CDR_BE,
+  RepresentationIdentifier::D_CDR_LE,
+  RepresentationIdentifier::PL_CDR2_BE,
+  RepresentationIdentifier::PL_CDR2_LE,
 ];
 
 impl<D> no_key::DeserializerAdapter<D> for CDRDeserializerAdapter<D> {
@@ -240,7 +320,8 @@ where
   deserialize_from_cdr_with_decoder_and_rep_id::<PhantomData<T>>(input_bytes, encoding, PhantomData)
 }
 
-/// Decode type using the given [`DeserializeSeed`]-based decoder.
+/// Decode type using the given [`DeserializeSeed`]-based decoder. Both
+/// classic CDR and XCDR2 are decoded.
 ///
 /// Returns deserialized object and byte count of stream consumed.
 pub fn deserialize_from_cdr_with_decoder_and_rep_id<'de, S>(
@@ -251,26 +332,159 @@ pub fn deserialize_from_cdr_with_decoder_and_rep_id<'de, S>(
 where
   S: DeserializeSeed<'de>,
 {
-  match encoding {
-    RepresentationIdentifier::CDR_LE | RepresentationIdentifier::PL_CDR_LE => {
-      let mut deserializer = CdrDeserializer::<LittleEndian>::new(input_bytes);
+  if let Some((xcdr2_encoding, endianness)) = encoding.xcdr2_encoding() {
+    return deserialize_xcdr2_with_decoder(input_bytes, xcdr2_encoding, endianness, decoder);
+  }
+  match encoding.cdr_endianness() {
+    Some(endianness) => {
+      deserialize_from_cdr_with_decoder_and_endianness(input_bytes, endianness, decoder)
+    }
+    None => Err(Error::Message(format!(
+      "Unknown serialization format. requested={:?}.",
+      encoding
+    ))),
+  }
+}
+
+/// Deserializes CDR in a byte order chosen at run time.
+///
+/// Returns deserialized object and byte count of stream consumed. This
+/// replaces [`from_bytes`] and [`CdrDeserializer`], whose byte order is a type
+/// parameter.
+pub fn from_bytes_with_endianness<'de, T>(
+  input_bytes: &[u8],
+  endianness: Endianness,
+) -> Result<(T, usize)>
+where
+  T: Deserialize<'de>,
+{
+  deserialize_from_cdr_with_decoder_and_endianness::<PhantomData<T>>(
+    input_bytes,
+    endianness,
+    PhantomData,
+  )
+}
+
+/// Decode type using the given [`DeserializeSeed`]-based decoder, in a byte
+/// order chosen at run time.
+///
+/// Returns deserialized object and byte count of stream consumed.
+pub fn deserialize_from_cdr_with_decoder_and_endianness<'de, S>(
+  input_bytes: &[u8],
+  endianness: Endianness,
+  decoder: S,
+) -> Result<(S::Value, usize)>
+where
+  S: DeserializeSeed<'de>,
+{
+  match endianness {
+    Endianness::LittleEndian => {
+      let mut deserializer = cdr_encoding::CdrDeserializer::<LittleEndian>::new(input_bytes);
       Ok((
         decoder.deserialize(&mut deserializer)?,
         deserializer.bytes_consumed(),
       ))
     }
-
-    RepresentationIdentifier::CDR_BE | RepresentationIdentifier::PL_CDR_BE => {
-      let mut deserializer = CdrDeserializer::<BigEndian>::new(input_bytes);
+    Endianness::BigEndian => {
+      let mut deserializer = cdr_encoding::CdrDeserializer::<BigEndian>::new(input_bytes);
       Ok((
         decoder.deserialize(&mut deserializer)?,
         deserializer.bytes_consumed(),
       ))
     }
+  }
+}
 
-    repr_id => Err(Error::Message(format!(
-      "Unknown serialization format. requested={:?}.",
-      repr_id
-    ))),
+/// Deserializes a serialized payload that begins with the RTPS encapsulation
+/// header, i.e. the representation identifier and options. The byte order is
+/// taken from the header.
+///
+/// Returns deserialized object and byte count consumed, including the header.
+pub fn from_encapsulated_bytes<'de, T>(input_bytes: &[u8]) -> Result<(T, usize)>
+where
+  T: Deserialize<'de>,
+{
+  const HEADER_SIZE: usize = 4;
+  if input_bytes.len() < HEADER_SIZE {
+    return Err(Error::Message(
+      "Serialized payload is shorter than the encapsulation header.".to_string(),
+    ));
+  }
+  let encoding = RepresentationIdentifier {
+    bytes: [input_bytes[0], input_bytes[1]],
+  };
+  let (value, consumed) = deserialize_from_cdr_with_rep_id(&input_bytes[HEADER_SIZE..], encoding)?;
+  Ok((value, HEADER_SIZE + consumed))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn byte_order_is_dispatched_at_run_time() {
+    let value: (u16, String) = (0x1234, "abc".to_string());
+    for endianness in [Endianness::LittleEndian, Endianness::BigEndian] {
+      let encoding = RepresentationIdentifier::cdr(endianness);
+      assert_eq!(encoding.cdr_endianness(), Some(endianness));
+
+      let mut payload = encoding.to_bytes().to_vec();
+      payload.extend_from_slice(&[0, 0]);
+      payload.extend(to_vec_with_endianness(&value, endianness).unwrap());
+      let (decoded, consumed) = from_encapsulated_bytes::<(u16, String)>(&payload).unwrap();
+      assert_eq!(decoded, value);
+      assert_eq!(consumed, payload.len());
+    }
+    assert_eq!(
+      to_vec_with_endianness(&0x1234_u16, Endianness::BigEndian).unwrap(),
+      vec![0x12, 0x34]
+    );
+    assert!(from_encapsulated_bytes::<u16>(&[0, 4, 0, 0, 1, 2]).is_err());
+    assert!(from_encapsulated_bytes::<u16>(&[0, 1]).is_err());
+  }
+
+  #[test]
+  fn xcdr2_is_selected_by_representation_identifier() {
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Sample {
+      a: u8,
+      b: u64,
+    }
+
+    let value = Sample { a: 1, b: 2 };
+    for encoding in [
+      RepresentationIdentifier::XCDR2_BE,
+      RepresentationIdentifier::D_CDR2_LE,
+      RepresentationIdentifier::PL_XCDR2_LE,
+    ] {
+      assert!(REPR_IDS.contains(&encoding));
+      let mut bytes = Vec::new();
+      to_writer_with_rep_id(&mut bytes, &value, encoding).unwrap();
+      let (decoded, consumed) =
+        deserialize_from_cdr_with_rep_id::<Sample>(&bytes, encoding).unwrap();
+      assert_eq!(decoded, value);
+      assert_eq!(consumed, bytes.len());
+    }
+    // XCDR2 aligns the u64 to 4 bytes, classic CDR to 8.
+    let mut bytes = Vec::new();
+    to_writer_with_rep_id(&mut bytes, &value, RepresentationIdentifier::XCDR2_LE).unwrap();
+    assert_eq!(bytes.len(), 12);
+    let mut bytes = Vec::new();
+    to_writer_with_rep_id(&mut bytes, &value, RepresentationIdentifier::CDR_LE).unwrap();
+    assert_eq!(bytes.len(), 16);
+  }
+
+  #[test]
+  fn serializer_adapter_declares_its_byte_order() {
+    use no_key::SerializerAdapter;
+
+    assert_eq!(
+      <CDRSerializerAdapter<u32> as SerializerAdapter<u32>>::output_encoding(),
+      RepresentationIdentifier::CDR_LE
+    );
+    assert_eq!(
+      <CDRSerializerAdapter<u32, BigEndian> as SerializerAdapter<u32>>::output_encoding(),
+      RepresentationIdentifier::CDR_BE
+    );
   }
 }
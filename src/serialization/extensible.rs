@@ -0,0 +1,705 @@
+//! Extensibility and member ids of data types, for the XCDR2 encoding.
+//!
+//! Serde tells the names of structs and their fields, but not the XTypes
+//! properties of the types. The [`dds_type!`](crate::dds_type) macro declares
+//! a struct with `#[dds(...)]` attributes, and implements [`Extensible`] for
+//! it:
+//!
+//! * `#[dds(extensibility = "final" | "appendable" | "mutable")]` before the
+//!   other attributes of the struct. The default is `"appendable"`.
+//! * `#[dds(id = 5)]` before the other attributes of a field sets its member
+//!   id. A field without one has the id after that of the previous field, and
+//!   the first field has id 0.
+//!
+//! ```
+//! use rustdds::{dds_type, serialization::*};
+//! use serde::{Deserialize, Serialize};
+//!
+//! dds_type! {
+//!   #[dds(extensibility = "mutable")]
+//!   #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//!   pub struct Pose {
+//!     #[dds(id = 10)]
+//!     pub x: f64,
+//!     pub y: f64,
+//!     pub label: Option<String>,
+//!   }
+//! }
+//!
+//! let layouts = Pose::layouts();
+//! assert_eq!(layouts[0].member_ids, &[10, 11, 12]);
+//! ```
+//!
+//! Write such types with [`ExtensibleSerializerAdapter`], and read them with
+//! [`ExtensibleDeserializerAdapter`]. Then a type may evolve as its
+//! extensibility allows, see [`xtypes`](crate::xtypes): readers skip members
+//! they do not know, and give default values to members missing from the
+//! data.
+//!
+//! The macro does not support generic or tuple structs, and the struct must
+//! not be renamed with `#[serde(rename)]`, because layouts are found by the
+//! struct name. Types used in the fields must implement [`Extensible`] too.
+//! For types with no structs inside, such as enums, an empty
+//! `impl Extensible for MyEnum {}` will do.
+//!
+//! The macro also describes the struct as a [`TypeObject`], for matching by
+//! type in Discovery. Give it to [`create_topic_with_type_object`]. There is
+//! no TypeObject, if the type contains a recursive struct or a type with no
+//! [`Extensible::type_object`], such as a tuple. Key members are not marked as
+//! such.
+//!
+//! [`create_topic_with_type_object`]: crate::DomainParticipant::create_topic_with_type_object
+
+use std::{
+  cell::RefCell,
+  collections::{BTreeMap, HashMap},
+  marker::PhantomData,
+};
+
+use serde::{
+  de::{DeserializeOwned, DeserializeSeed},
+  Deserialize, Serialize,
+};
+use bytes::Bytes;
+use byteorder::{ByteOrder, LittleEndian};
+
+use super::{
+  cdr_adapters::{deserialize_from_cdr_with_rep_id, endianness_of, REPR_IDS},
+  xcdr2::{Xcdr2Deserializer, Xcdr2Encoding, Xcdr2Serializer},
+  Error, Result,
+};
+use crate::{
+  dds::{
+    adapters::{no_key, with_key},
+    content_filter::DdsSqlFilter,
+  },
+  xtypes::{Extensibility, StructMember, StructType, TypeObject},
+  Keyed, RepresentationIdentifier,
+};
+
+/// The XTypes properties of a struct
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StructLayout {
+  /// Name of the struct, as given to serde
+  pub name: &'static str,
+  pub extensibility: Extensibility,
+  /// Member ids of the fields, in field order. Empty means that the ids are
+  /// the field indices.
+  pub member_ids: &'static [u32],
+}
+
+/// A type whose structs have known [`StructLayout`]s.
+///
+/// Implemented by [`dds_type!`](crate::dds_type), and for the standard types
+/// that serde supports.
+pub trait Extensible {
+  /// Adds the layouts of the structs in this type, this one first if it is
+  /// a struct. Does nothing by default.
+  fn collect_layouts(_layouts: &mut Vec<StructLayout>) {}
+
+  /// The layouts of the structs in this type
+  fn layouts() -> Vec<StructLayout> {
+    let mut layouts = Vec::new();
+    Self::collect_layouts(&mut layouts);
+    layouts
+  }
+
+  /// Description of this type, if known. `None` by default.
+  fn type_object() -> Option<TypeObject> {
+    None
+  }
+
+  // True for Option, whose struct members are optional
+  #[doc(hidden)]
+  const OPTIONAL: bool = false;
+}
+
+macro_rules! extensible_without_structs {
+  ($($type:ty => $type_object:expr),* $(,)?) => {
+    $(impl Extensible for $type {
+      fn type_object() -> Option<TypeObject> {
+        Some($type_object)
+      }
+    })*
+  };
+}
+
+extensible_without_structs!(
+  bool => TypeObject::Boolean,
+  i8 => TypeObject::Int8,
+  i16 => TypeObject::Int16,
+  i32 => TypeObject::Int32,
+  i64 => TypeObject::Int64,
+  u8 => TypeObject::UInt8,
+  u16 => TypeObject::UInt16,
+  u32 => TypeObject::UInt32,
+  u64 => TypeObject::UInt64,
+  f32 => TypeObject::Float32,
+  f64 => TypeObject::Float64,
+  char => TypeObject::Char8,
+  String => TypeObject::String { bound: None },
+  str => TypeObject::String { bound: None },
+);
+
+impl Extensible for () {}
+
+fn sequence_of(element: Option<TypeObject>) -> Option<TypeObject> {
+  Some(TypeObject::Sequence {
+    element: Box::new(element?),
+    bound: None,
+  })
+}
+
+fn map_of(key: Option<TypeObject>, value: Option<TypeObject>) -> Option<TypeObject> {
+  Some(TypeObject::Map {
+    key: Box::new(key?),
+    value: Box::new(value?),
+    bound: None,
+  })
+}
+
+macro_rules! extensible_containers {
+  ($([$($param:ident),*] $type:ty $(=> $type_object:expr)?),* $(,)?) => {
+    $(impl<$($param: Extensible),*> Extensible for $type {
+      fn collect_layouts(layouts: &mut Vec<StructLayout>) {
+        $($param::collect_layouts(layouts);)*
+      }
+      $(fn type_object() -> Option<TypeObject> {
+        $type_object
+      })?
+    })*
+  };
+}
+
+extensible_containers!(
+  [T] Vec<T> => sequence_of(T::type_object()),
+  [T] Box<T> => T::type_object(),
+  [T] [T] => sequence_of(T::type_object()),
+  [K, V] BTreeMap<K, V> => map_of(K::type_object(), V::type_object()),
+  [K, V] HashMap<K, V> => map_of(K::type_object(), V::type_object()),
+  [A] (A,),
+  [A, B] (A, B),
+  [A, B, C] (A, B, C),
+  [A, B, C, D] (A, B, C, D),
+);
+
+impl<T: Extensible + ?Sized> Extensible for &T {
+  fn collect_layouts(layouts: &mut Vec<StructLayout>) {
+    T::collect_layouts(layouts);
+  }
+
+  fn type_object() -> Option<TypeObject> {
+    T::type_object()
+  }
+}
+
+impl<T: Extensible> Extensible for Option<T> {
+  fn collect_layouts(layouts: &mut Vec<StructLayout>) {
+    T::collect_layouts(layouts);
+  }
+
+  fn type_object() -> Option<TypeObject> {
+    T::type_object()
+  }
+
+  const OPTIONAL: bool = true;
+}
+
+impl<T: Extensible, const N: usize> Extensible for [T; N] {
+  fn collect_layouts(layouts: &mut Vec<StructLayout>) {
+    T::collect_layouts(layouts);
+  }
+
+  fn type_object() -> Option<TypeObject> {
+    Some(TypeObject::Array {
+      element: Box::new(T::type_object()?),
+      dimensions: vec![u32::try_from(N).ok()?],
+    })
+  }
+}
+
+// The encoding of a type as a whole, given by its outermost struct
+fn encoding_of(layouts: &[StructLayout]) -> Xcdr2Encoding {
+  layouts
+    .first()
+    .map_or(Xcdr2Encoding::Plain, |layout| layout.extensibility.into())
+}
+
+/// Declares a struct with XTypes extensibility and member ids. See the
+/// [`extensible`](crate::serialization::extensible) module.
+#[macro_export]
+macro_rules! dds_type {
+  // A field with a member id
+  (@fields $ext:tt $attrs:tt $vis:tt $name:ident [$($done:tt)*]
+    #[dds(id = $id:literal)]
+    $(#[$field_attr:meta])* $field_vis:vis $field:ident : $type:ty $(, $($rest:tt)*)?
+  ) => {
+    $crate::dds_type!(@fields $ext $attrs $vis $name
+      [$($done)* [[$(#[$field_attr])*] [$field_vis] $field [$type]
+        [::core::option::Option::Some($id)]]]
+      $($($rest)*)?);
+  };
+  // A field without
+  (@fields $ext:tt $attrs:tt $vis:tt $name:ident [$($done:tt)*]
+    $(#[$field_attr:meta])* $field_vis:vis $field:ident : $type:ty $(, $($rest:tt)*)?
+  ) => {
+    $crate::dds_type!(@fields $ext $attrs $vis $name
+      [$($done)* [[$(#[$field_attr])*] [$field_vis] $field [$type]
+        [::core::option::Option::None]]]
+      $($($rest)*)?);
+  };
+  // All fields done
+  (@fields [$ext:literal] [$($attr:tt)*] [$vis:vis] $name:ident
+    [$([[$($field_attr:tt)*] [$field_vis:vis] $field:ident [$type:ty] [$id:expr]])*]
+  ) => {
+    $($attr)*
+    $vis struct $name {
+      $($($field_attr)* $field_vis $field: $type,)*
+    }
+
+    impl $crate::serialization::Extensible for $name {
+      fn collect_layouts(layouts: &mut ::std::vec::Vec<$crate::serialization::StructLayout>) {
+        const EXTENSIBILITY: $crate::xtypes::Extensibility =
+          $crate::serialization::extensible::extensibility_from_str($ext);
+        const MEMBER_IDS: &[u32] =
+          &$crate::serialization::extensible::member_ids([$($id),*]);
+        let name = ::core::stringify!($name);
+        // Also stops the recursion of recursive types
+        if layouts.iter().any(|layout| layout.name == name) {
+          return;
+        }
+        layouts.push($crate::serialization::StructLayout {
+          name,
+          extensibility: EXTENSIBILITY,
+          member_ids: MEMBER_IDS,
+        });
+        $(<$type as $crate::serialization::Extensible>::collect_layouts(layouts);)*
+      }
+
+      fn type_object() -> ::core::option::Option<$crate::xtypes::TypeObject> {
+        $crate::serialization::extensible::struct_type_object::<Self>(&[$((
+          ::core::stringify!($field),
+          <$type as $crate::serialization::Extensible>::OPTIONAL,
+          <$type as $crate::serialization::Extensible>::type_object
+            as fn() -> ::core::option::Option<$crate::xtypes::TypeObject>,
+        ),)*])
+      }
+    }
+  };
+  (
+    #[dds(extensibility = $ext:literal)]
+    $(#[$attr:meta])*
+    $vis:vis struct $name:ident { $($fields:tt)* }
+  ) => {
+    $crate::dds_type!(@fields [$ext] [$(#[$attr])*] [$vis] $name [] $($fields)*);
+  };
+  (
+    $(#[$attr:meta])*
+    $vis:vis struct $name:ident { $($fields:tt)* }
+  ) => {
+    $crate::dds_type!(@fields ["appendable"] [$(#[$attr])*] [$vis] $name [] $($fields)*);
+  };
+}
+
+// Used by dds_type!. A const fn, so that an unknown extensibility is a
+// compile error.
+#[doc(hidden)]
+pub const fn extensibility_from_str(extensibility: &str) -> Extensibility {
+  const fn equals(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+      return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+      if a[i] != b[i] {
+        return false;
+      }
+      i += 1;
+    }
+    true
+  }
+
+  if equals(extensibility, "final") {
+    Extensibility::Final
+  } else if equals(extensibility, "appendable") {
+    Extensibility::Appendable
+  } else if equals(extensibility, "mutable") {
+    Extensibility::Mutable
+  } else {
+    panic!("extensibility must be \"final\", \"appendable\" or \"mutable\"")
+  }
+}
+
+// Name, optionality and TypeObject of a struct field
+type FieldDescription<'a> = (&'a str, bool, fn() -> Option<TypeObject>);
+
+// Used by dds_type!. Describes the struct T, given the names, optionality and
+// types of its fields. The layout of T has the rest.
+#[doc(hidden)]
+pub fn struct_type_object<T: Extensible>(fields: &[FieldDescription<'_>]) -> Option<TypeObject> {
+  thread_local! {
+    // Structs whose TypeObject is being built. A recursive type has no
+    // TypeObject, as the type model cannot refer to another type.
+    static IN_PROGRESS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+  }
+
+  let layout = *T::layouts().first()?;
+  if IN_PROGRESS.with(|p| p.borrow().contains(&layout.name)) {
+    return None;
+  }
+  IN_PROGRESS.with(|p| p.borrow_mut().push(layout.name));
+  let members = fields
+    .iter()
+    .enumerate()
+    .map(|(i, (name, optional, type_object))| {
+      let id = layout.member_ids.get(i).copied().unwrap_or(i as u32);
+      let member = StructMember::new(id, name, type_object()?);
+      Some(if *optional { member.optional() } else { member })
+    })
+    .collect::<Option<Vec<_>>>();
+  IN_PROGRESS.with(|p| p.borrow_mut().pop());
+
+  Some(TypeObject::Struct(StructType::new(
+    layout.name,
+    layout.extensibility,
+    members?,
+  )))
+}
+
+// Used by dds_type!. Fields without an explicit id get the next id after the
+// previous field.
+#[doc(hidden)]
+pub const fn member_ids<const N: usize>(explicit_ids: [Option<u32>; N]) -> [u32; N] {
+  let mut ids = [0; N];
+  let mut next = 0;
+  let mut i = 0;
+  while i < N {
+    if let Some(id) = explicit_ids[i] {
+      next = id;
+    }
+    ids[i] = next;
+    next += 1;
+    i += 1;
+  }
+  ids
+}
+
+/// Serializes [`Extensible`] types to XCDR2, encoding each struct as its
+/// extensibility requires.
+pub struct ExtensibleSerializerAdapter<D, BO = LittleEndian>
+where
+  BO: ByteOrder,
+{
+  phantom: PhantomData<D>,
+  ghost: PhantomData<BO>,
+}
+
+fn to_bytes_extensible<T, BO>(value: &T) -> Result<Bytes>
+where
+  T: Serialize + Extensible,
+  BO: ByteOrder,
+{
+  let layouts = T::layouts();
+  let mut serializer =
+    Xcdr2Serializer::new(encoding_of(&layouts), endianness_of::<BO>()).with_layouts(layouts);
+  value.serialize(&mut serializer)?;
+  Ok(Bytes::from(serializer.into_inner()))
+}
+
+impl<D, BO> no_key::SerializerAdapter<D> for ExtensibleSerializerAdapter<D, BO>
+where
+  D: Serialize + Extensible,
+  BO: ByteOrder,
+{
+  type Error = Error;
+
+  fn output_encoding() -> RepresentationIdentifier {
+    RepresentationIdentifier::xcdr2(encoding_of(&D::layouts()), endianness_of::<BO>())
+  }
+
+  fn to_bytes(value: &D) -> Result<Bytes> {
+    to_bytes_extensible::<D, BO>(value)
+  }
+
+  fn matches_sql_filter(value: &D, filter: &DdsSqlFilter) -> Option<bool> {
+    Some(filter.matches(value))
+  }
+}
+
+impl<D, BO> with_key::SerializerAdapter<D> for ExtensibleSerializerAdapter<D, BO>
+where
+  D: Keyed + Serialize + Extensible,
+  <D as Keyed>::K: Serialize + Extensible,
+  BO: ByteOrder,
+{
+  fn key_to_bytes(value: &D::K) -> Result<Bytes> {
+    to_bytes_extensible::<D::K, BO>(value)
+  }
+}
+
+/// Deserializes [`Extensible`] types from XCDR2, decoding each struct as its
+/// extensibility requires, and also from classic CDR.
+pub struct ExtensibleDeserializerAdapter<D> {
+  phantom: PhantomData<D>,
+}
+
+impl<D> no_key::DeserializerAdapter<D> for ExtensibleDeserializerAdapter<D> {
+  type Error = Error;
+  type Decoded = D;
+
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    &REPR_IDS
+  }
+
+  fn transform_decoded(decoded: Self::Decoded) -> D {
+    decoded
+  }
+}
+
+impl<D> with_key::DeserializerAdapter<D> for ExtensibleDeserializerAdapter<D>
+where
+  D: Keyed + DeserializeOwned + Extensible,
+  <D as Keyed>::K: DeserializeOwned + Extensible,
+{
+  type DecodedKey = D::K;
+
+  fn transform_decoded_key(decoded_key: Self::DecodedKey) -> D::K {
+    decoded_key
+  }
+}
+
+impl<'de, D> no_key::DefaultDecoder<D> for ExtensibleDeserializerAdapter<D>
+where
+  D: Deserialize<'de> + Extensible,
+{
+  type Decoder = ExtensibleDecoder<D>;
+  const DECODER: Self::Decoder = ExtensibleDecoder(PhantomData);
+}
+
+impl<D> with_key::DefaultDecoder<D> for ExtensibleDeserializerAdapter<D>
+where
+  D: Keyed + DeserializeOwned + Extensible,
+  D::K: DeserializeOwned + Extensible,
+{
+  type Decoder = ExtensibleDecoder<D>;
+  const DECODER: Self::Decoder = ExtensibleDecoder(PhantomData);
+}
+
+/// Decode [`Extensible`] types based on their `serde::Deserialize`
+/// implementation.
+pub struct ExtensibleDecoder<D>(PhantomData<D>);
+
+fn from_bytes_extensible<'de, T>(
+  input_bytes: &[u8],
+  encoding: RepresentationIdentifier,
+) -> Result<T>
+where
+  T: Deserialize<'de> + Extensible,
+{
+  match encoding.xcdr2_encoding() {
+    Some((xcdr2_encoding, endianness)) => {
+      let mut deserializer =
+        Xcdr2Deserializer::new(input_bytes, xcdr2_encoding, endianness).with_layouts(T::layouts());
+      PhantomData::<T>.deserialize(&mut deserializer)
+    }
+    None => deserialize_from_cdr_with_rep_id(input_bytes, encoding).map(|r| r.0),
+  }
+}
+
+impl<'de, D> no_key::Decode<D> for ExtensibleDecoder<D>
+where
+  D: Deserialize<'de> + Extensible,
+{
+  type Error = Error;
+
+  fn decode_bytes(self, input_bytes: &[u8], encoding: RepresentationIdentifier) -> Result<D> {
+    from_bytes_extensible(input_bytes, encoding)
+  }
+}
+
+impl<Dec, DecKey> with_key::Decode<Dec, DecKey> for ExtensibleDecoder<Dec>
+where
+  Dec: DeserializeOwned + Extensible,
+  DecKey: DeserializeOwned + Extensible,
+{
+  fn decode_key_bytes(
+    self,
+    input_key_bytes: &[u8],
+    encoding: RepresentationIdentifier,
+  ) -> Result<DecKey> {
+    from_bytes_extensible(input_key_bytes, encoding)
+  }
+}
+
+impl<D> Clone for ExtensibleDecoder<D> {
+  fn clone(&self) -> Self {
+    Self(self.0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde::{Deserialize, Serialize};
+
+  use super::*;
+  use crate::dds::adapters::no_key::{DeserializerAdapter, SerializerAdapter};
+
+  mod v1 {
+    use super::*;
+
+    crate::dds_type! {
+      #[derive(Serialize, Deserialize, Debug, PartialEq)]
+      pub struct Position {
+        pub x: i32,
+        pub y: i32,
+      }
+    }
+
+    crate::dds_type! {
+      #[dds(extensibility = "mutable")]
+      #[derive(Serialize, Deserialize, Debug, PartialEq)]
+      pub struct Robot {
+        #[dds(id = 10)]
+        pub name: String,
+        pub position: Position,
+      }
+    }
+  }
+
+  mod v2 {
+    use super::*;
+
+    crate::dds_type! {
+      #[derive(Serialize, Deserialize, Debug, PartialEq)]
+      pub struct Position {
+        pub x: i32,
+        pub y: i32,
+        pub z: i32,
+      }
+    }
+
+    // Members reordered and added, ids kept
+    crate::dds_type! {
+      #[dds(extensibility = "mutable")]
+      #[derive(Serialize, Deserialize, Debug, PartialEq)]
+      pub struct Robot {
+        #[dds(id = 5)]
+        pub battery: Option<f32>,
+        #[dds(id = 11)]
+        pub position: Position,
+        #[dds(id = 10)]
+        pub name: String,
+      }
+    }
+  }
+
+  fn round<S, D>(value: &S) -> D
+  where
+    S: Serialize + Extensible,
+    D: DeserializeOwned + Extensible,
+  {
+    let bytes = ExtensibleSerializerAdapter::<S>::to_bytes(value).unwrap();
+    let encoding = ExtensibleSerializerAdapter::<S>::output_encoding();
+    ExtensibleDeserializerAdapter::<D>::from_bytes(&bytes, encoding).unwrap()
+  }
+
+  #[test]
+  fn layouts_follow_attributes() {
+    assert_eq!(
+      v2::Robot::layouts(),
+      vec![
+        StructLayout {
+          name: "Robot",
+          extensibility: Extensibility::Mutable,
+          member_ids: &[5, 11, 10],
+        },
+        StructLayout {
+          name: "Position",
+          extensibility: Extensibility::Appendable,
+          member_ids: &[0, 1, 2],
+        },
+      ]
+    );
+    assert_eq!(
+      <ExtensibleSerializerAdapter<v1::Robot> as SerializerAdapter<v1::Robot>>::output_encoding(),
+      RepresentationIdentifier::PL_XCDR2_LE
+    );
+  }
+
+  #[test]
+  fn types_evolve() {
+    let old = v1::Robot {
+      name: "r1".to_string(),
+      position: v1::Position { x: 1, y: 2 },
+    };
+    let new: v2::Robot = round(&old);
+    assert_eq!(
+      new,
+      v2::Robot {
+        battery: None,
+        position: v2::Position { x: 1, y: 2, z: 0 },
+        name: "r1".to_string(),
+      }
+    );
+
+    let new = v2::Robot {
+      battery: Some(0.5),
+      position: v2::Position { x: 3, y: 4, z: 5 },
+      name: "r2".to_string(),
+    };
+    let old: v1::Robot = round(&new);
+    assert_eq!(
+      old,
+      v1::Robot {
+        name: "r2".to_string(),
+        position: v1::Position { x: 3, y: 4 },
+      }
+    );
+  }
+
+  #[test]
+  fn member_ids_continue_from_explicit_ones() {
+    assert_eq!(member_ids([None, Some(7), None, Some(2)]), [0, 7, 8, 2]);
+    assert_eq!(member_ids::<0>([]), []);
+  }
+
+  crate::dds_type! {
+    #[allow(dead_code)]
+    #[derive(Serialize, Deserialize)]
+    struct Tree {
+      label: String,
+      children: Vec<Tree>,
+    }
+  }
+
+  #[test]
+  fn type_objects_follow_attributes() {
+    let position = TypeObject::Struct(StructType::new(
+      "Position",
+      Extensibility::Appendable,
+      vec![
+        StructMember::new(0, "x", TypeObject::Int32),
+        StructMember::new(1, "y", TypeObject::Int32),
+        StructMember::new(2, "z", TypeObject::Int32),
+      ],
+    ));
+    let robot = TypeObject::Struct(StructType::new(
+      "Robot",
+      Extensibility::Mutable,
+      vec![
+        StructMember::new(5, "battery", TypeObject::Float32).optional(),
+        StructMember::new(11, "position", position),
+        StructMember::new(10, "name", TypeObject::String { bound: None }),
+      ],
+    ));
+    assert_eq!(v2::Robot::type_object(), Some(robot));
+
+    let old = v1::Robot::type_object().unwrap();
+    let new = v2::Robot::type_object().unwrap();
+    assert!(crate::xtypes::check_type_evolution(&old, &new).is_fully_compatible());
+
+    assert_eq!(Tree::type_object(), None);
+    assert_eq!(<(i32, i32)>::type_object(), None);
+  }
+}
@@ -0,0 +1,1604 @@
+//! Generating Rust types from OMG IDL.
+//!
+//! [`generate_rust`] translates the type declarations of an IDL file to Rust
+//! source code, so that types shared with other DDS implementations need not
+//! be written again by hand. Call it from a build script, or print the code
+//! with the `rustdds_gen` example: `cargo run --example rustdds_gen --
+//! shapes.idl`.
+//!
+//! ```
+//! use rustdds::serialization::idl::generate_rust;
+//!
+//! let rust = generate_rust(
+//!   r#"
+//!   module shapes {
+//!     @mutable
+//!     struct ShapeType {
+//!       @key string<128> color;
+//!       long x;
+//!       long y;
+//!       @optional long shapesize;
+//!     };
+//!   };
+//!   "#,
+//! )
+//! .unwrap();
+//! assert!(rust.contains("pub struct ShapeType {"));
+//! assert!(rust.contains("pub shapesize: Option<i32>,"));
+//! assert!(rust.contains("impl ::rustdds::Keyed for ShapeType {"));
+//! ```
+//!
+//! The declarations are mapped as follows:
+//!
+//! * A `module` is a `pub mod`. Types in other modules are referred to with
+//!   relative `super::` paths, so the output can be included anywhere.
+//! * A `struct` is declared with [`dds_type!`](crate::dds_type), with members
+//!   in IDL order, and the extensibility and member ids given by the
+//!   `@final`, `@appendable`, `@mutable`, `@extensibility` and `@id`
+//!   annotations. The members of a base struct come first. `@optional`
+//!   members are `Option`s. `@key` members make the struct
+//!   [`Keyed`](crate::Keyed): the key is the member itself, or a generated
+//!   `<Name>Key` struct if there are several.
+//! * An `enum` is a Rust enum, with the IDL values (see `@value`) as
+//!   discriminants. It is serialized as a 32-bit integer with `serde_repr`.
+//! * A `union` is a Rust enum with a variant for each case. The variant of a
+//!   `default` case carries the discriminator, too. Unions are encoded as
+//!   final types.
+//! * A `typedef` is a type alias, and a `const` is a Rust `const`.
+//! * `string` is `String`, `sequence<T>` is `Vec<T>`, `map<K, V>` is
+//!   `BTreeMap<K, V>`, and arrays are Rust arrays. Bounds are not checked.
+//!
+//! The generated code depends on the `serde` and `serde_repr` crates.
+//!
+//! Preprocessor directives are ignored. Names that are not declared in the
+//! input are emitted as written, so the types of an `#include`d file must be
+//! generated separately, and be in scope where the output is included. Wide
+//! characters and strings, `fixed`, `any`, bitmasks, bitsets and interfaces
+//! are not supported, and neither are key members other than integers,
+//! booleans, characters and strings.
+
+use std::{collections::HashMap, fmt, fmt::Write, iter::Peekable, vec};
+
+/// An error in the IDL input
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("IDL line {line}: {message}")]
+pub struct IdlError {
+  /// Line number of the error, starting from 1
+  pub line: usize,
+  pub message: String,
+}
+
+/// Translates the type declarations of `idl` to Rust source code. See the
+/// [module documentation](self).
+pub fn generate_rust(idl: &str) -> Result<String, IdlError> {
+  let tokens = tokenize(idl)?;
+  let mut parser = Parser {
+    tokens: tokens.into_iter().peekable(),
+    line: 1,
+  };
+  let definitions = parser.definitions()?;
+  if let Some(token) = parser.peek() {
+    let token = token.to_string();
+    return Err(parser.error(format!("unexpected `{token}`")));
+  }
+
+  let mut generator = Generator {
+    symbols: HashMap::new(),
+    out: String::from("// Generated from IDL by RustDDS. Do not edit.\n"),
+  };
+  generator.collect_symbols(&[], &definitions);
+  generator.definitions(&[], &definitions)?;
+  Ok(generator.out)
+}
+
+// Lexical analysis
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Ident(String),
+  // Numbers and string and character literals, as written
+  Literal(String),
+  Scope, // ::
+  Punct(char),
+}
+
+impl fmt::Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Token::Ident(s) | Token::Literal(s) => write!(f, "{s}"),
+      Token::Scope => write!(f, "::"),
+      Token::Punct(c) => write!(f, "{c}"),
+    }
+  }
+}
+
+// Tokens with their line numbers. Comments and preprocessor directives are
+// skipped.
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, IdlError> {
+  let error = |line, message: String| IdlError { line, message };
+  let mut tokens = Vec::new();
+  let mut chars = input.chars().peekable();
+  let mut line = 1;
+  let mut line_start = true;
+
+  while let Some(&c) = chars.peek() {
+    if c == '\n' {
+      chars.next();
+      line += 1;
+      line_start = true;
+    } else if c.is_whitespace() {
+      chars.next();
+    } else if c == '#' && line_start {
+      while chars.next_if(|&c| c != '\n').is_some() {}
+    } else if c == '/' {
+      chars.next();
+      match chars.peek() {
+        Some('/') => while chars.next_if(|&c| c != '\n').is_some() {},
+        Some('*') => {
+          chars.next();
+          let mut previous = ' ';
+          loop {
+            match chars.next() {
+              None => return Err(error(line, "unterminated comment".to_string())),
+              Some('/') if previous == '*' => break,
+              Some(c) => {
+                if c == '\n' {
+                  line += 1;
+                }
+                previous = c;
+              }
+            }
+          }
+        }
+        _ => {
+          line_start = false;
+          tokens.push((Token::Punct('/'), line));
+        }
+      }
+    } else {
+      line_start = false;
+      if c.is_alphabetic() || c == '_' {
+        let mut ident = String::new();
+        while let Some(c) = chars.next_if(|&c| c.is_alphanumeric() || c == '_') {
+          ident.push(c);
+        }
+        tokens.push((Token::Ident(ident), line));
+      } else if c.is_ascii_digit() || c == '.' {
+        let mut number = String::new();
+        while let Some(c) = chars.next_if(|&c| c.is_ascii_alphanumeric() || c == '.') {
+          number.push(c);
+        }
+        tokens.push((Token::Literal(number), line));
+      } else if c == '"' || c == '\'' {
+        let mut literal = String::new();
+        literal.push(c);
+        chars.next();
+        loop {
+          match chars.next() {
+            None | Some('\n') => return Err(error(line, "unterminated literal".to_string())),
+            Some('\\') => {
+              literal.push('\\');
+              literal.extend(chars.next());
+            }
+            Some(d) => {
+              literal.push(d);
+              if d == c {
+                break;
+              }
+            }
+          }
+        }
+        tokens.push((Token::Literal(literal), line));
+      } else if c == ':' {
+        chars.next();
+        if chars.next_if_eq(&':').is_some() {
+          tokens.push((Token::Scope, line));
+        } else {
+          tokens.push((Token::Punct(':'), line));
+        }
+      } else if "{}[]()<>;,=@-+*%|&^~".contains(c) {
+        chars.next();
+        tokens.push((Token::Punct(c), line));
+      } else {
+        return Err(error(line, format!("unexpected character `{c}`")));
+      }
+    }
+  }
+  Ok(tokens)
+}
+
+// Syntax tree
+
+#[derive(Debug)]
+enum Definition {
+  Module {
+    name: String,
+    definitions: Vec<Definition>,
+  },
+  Struct(StructDef),
+  Enum(EnumDef),
+  Union(UnionDef),
+  Typedef {
+    name: String,
+    type_spec: TypeSpec,
+  },
+  Const {
+    name: String,
+    type_spec: TypeSpec,
+    value: Expr,
+  },
+}
+
+#[derive(Debug)]
+struct StructDef {
+  name: String,
+  line: usize,
+  base: Option<Vec<String>>,
+  extensibility: Option<&'static str>,
+  members: Vec<Member>,
+}
+
+#[derive(Debug)]
+struct Member {
+  name: String,
+  line: usize,
+  type_spec: TypeSpec,
+  key: bool,
+  optional: bool,
+  id: Option<u32>,
+}
+
+#[derive(Debug)]
+struct EnumDef {
+  name: String,
+  extensibility: Option<&'static str>,
+  literals: Vec<(String, i32)>,
+}
+
+#[derive(Debug)]
+struct UnionDef {
+  name: String,
+  line: usize,
+  discriminator: TypeSpec,
+  cases: Vec<UnionCase>,
+}
+
+#[derive(Debug)]
+struct UnionCase {
+  name: String,
+  type_spec: TypeSpec,
+  labels: Vec<Expr>,
+  is_default: bool,
+  id: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+enum TypeSpec {
+  // Rust name of a primitive type
+  Primitive(&'static str),
+  String,
+  Sequence(Box<TypeSpec>),
+  Map(Box<TypeSpec>, Box<TypeSpec>),
+  Array(Box<TypeSpec>, Expr),
+  Named(Vec<String>),
+}
+
+// A constant expression, as tokens that are literals, operators or names
+type Expr = Vec<ExprPart>;
+
+#[derive(Debug, Clone)]
+enum ExprPart {
+  Text(String),
+  Name(Vec<String>),
+}
+
+#[derive(Debug)]
+struct Annotation {
+  name: String,
+  // The parameters, as written
+  value: Option<String>,
+}
+
+fn find<'a>(annotations: &'a [Annotation], name: &str) -> Option<&'a Annotation> {
+  annotations.iter().find(|a| a.name == name)
+}
+
+// A flag annotation, such as `@key` or `@key(TRUE)`
+fn flag(annotations: &[Annotation], name: &str) -> bool {
+  find(annotations, name).is_some_and(|a| {
+    a.value
+      .as_ref()
+      .map_or(true, |v| v.eq_ignore_ascii_case("TRUE"))
+  })
+}
+
+// Parsing
+
+struct Parser {
+  tokens: Peekable<vec::IntoIter<(Token, usize)>>,
+  line: usize,
+}
+
+impl Parser {
+  fn error(&self, message: String) -> IdlError {
+    IdlError {
+      line: self.line,
+      message,
+    }
+  }
+
+  fn peek(&mut self) -> Option<&Token> {
+    self.tokens.peek().map(|(token, _)| token)
+  }
+
+  fn next(&mut self) -> Result<Token, IdlError> {
+    match self.tokens.next() {
+      Some((token, line)) => {
+        self.line = line;
+        Ok(token)
+      }
+      None => Err(self.error("unexpected end of input".to_string())),
+    }
+  }
+
+  fn eat(&mut self, expected: &Token) -> bool {
+    if self.peek() == Some(expected) {
+      self.tokens.next();
+      true
+    } else {
+      false
+    }
+  }
+
+  fn eat_punct(&mut self, c: char) -> bool {
+    self.eat(&Token::Punct(c))
+  }
+
+  fn eat_keyword(&mut self, keyword: &str) -> bool {
+    self.eat(&Token::Ident(keyword.to_string()))
+  }
+
+  fn expect_punct(&mut self, c: char) -> Result<(), IdlError> {
+    match self.next()? {
+      Token::Punct(p) if p == c => Ok(()),
+      token => Err(self.error(format!("expected `{c}`, found `{token}`"))),
+    }
+  }
+
+  fn ident(&mut self) -> Result<String, IdlError> {
+    match self.next()? {
+      Token::Ident(name) => Ok(name),
+      token => Err(self.error(format!("expected a name, found `{token}`"))),
+    }
+  }
+
+  fn scoped_name(&mut self) -> Result<Vec<String>, IdlError> {
+    // An absolute name is looked up like a relative one
+    self.eat(&Token::Scope);
+    let mut name = vec![self.ident()?];
+    while self.eat(&Token::Scope) {
+      name.push(self.ident()?);
+    }
+    Ok(name)
+  }
+
+  // Definitions until the end of input or a closing brace
+  fn definitions(&mut self) -> Result<Vec<Definition>, IdlError> {
+    let mut definitions: Vec<Definition> = Vec::new();
+    while self.peek().is_some() && self.peek() != Some(&Token::Punct('}')) {
+      for definition in self.definition()? {
+        // A reopened module continues the earlier one
+        if let Definition::Module {
+          name,
+          definitions: more,
+        } = definition
+        {
+          let earlier = definitions.iter_mut().find_map(|d| match d {
+            Definition::Module {
+              name: n,
+              definitions,
+            } if *n == name => Some(definitions),
+            _ => None,
+          });
+          match earlier {
+            Some(earlier) => earlier.extend(more),
+            None => definitions.push(Definition::Module {
+              name,
+              definitions: more,
+            }),
+          }
+        } else {
+          definitions.push(definition);
+        }
+      }
+    }
+    Ok(definitions)
+  }
+
+  // Forward declarations give no definitions, and typedefs may give several
+  fn definition(&mut self) -> Result<Vec<Definition>, IdlError> {
+    let annotations = self.annotations()?;
+    let keyword = self.ident()?;
+    let definitions = match keyword.as_str() {
+      "module" => {
+        let name = self.ident()?;
+        self.expect_punct('{')?;
+        let definitions = self.definitions()?;
+        self.expect_punct('}')?;
+        vec![Definition::Module { name, definitions }]
+      }
+      "struct" => self
+        .struct_def(&annotations)?
+        .map(Definition::Struct)
+        .into_iter()
+        .collect(),
+      "enum" => vec![Definition::Enum(self.enum_def(&annotations)?)],
+      "union" => self
+        .union_def()?
+        .map(Definition::Union)
+        .into_iter()
+        .collect(),
+      "typedef" => {
+        let type_spec = self.type_spec()?;
+        let mut definitions = Vec::new();
+        loop {
+          let name = self.ident()?;
+          let type_spec = self.array_dimensions(type_spec.clone())?;
+          definitions.push(Definition::Typedef { name, type_spec });
+          if !self.eat_punct(',') {
+            break;
+          }
+        }
+        definitions
+      }
+      "const" => {
+        let type_spec = self.type_spec()?;
+        let name = self.ident()?;
+        self.expect_punct('=')?;
+        let value = self.expr()?;
+        vec![Definition::Const {
+          name,
+          type_spec,
+          value,
+        }]
+      }
+      other => return Err(self.error(format!("`{other}` declarations are not supported"))),
+    };
+    self.expect_punct(';')?;
+    Ok(definitions)
+  }
+
+  fn annotations(&mut self) -> Result<Vec<Annotation>, IdlError> {
+    let mut annotations = Vec::new();
+    while self.eat_punct('@') {
+      let name = self.scoped_name()?.pop().unwrap_or_default();
+      let value = if self.eat_punct('(') {
+        let mut value = String::new();
+        let mut depth = 0;
+        loop {
+          match self.next()? {
+            Token::Punct(')') if depth == 0 => break,
+            token => {
+              match token {
+                Token::Punct('(') => depth += 1,
+                Token::Punct(')') => depth -= 1,
+                _ => (),
+              }
+              value.push_str(&token.to_string());
+            }
+          }
+        }
+        Some(value)
+      } else {
+        None
+      };
+      annotations.push(Annotation { name, value });
+    }
+    Ok(annotations)
+  }
+
+  fn extensibility(&self, annotations: &[Annotation]) -> Result<Option<&'static str>, IdlError> {
+    let mut extensibility = None;
+    for annotation in annotations {
+      let value = match annotation.name.as_str() {
+        "final" => "final",
+        "appendable" => "appendable",
+        "mutable" => "mutable",
+        "extensibility" => match annotation.value.as_deref().unwrap_or_default() {
+          "FINAL" => "final",
+          "APPENDABLE" => "appendable",
+          "MUTABLE" => "mutable",
+          other => return Err(self.error(format!("unknown extensibility `{other}`"))),
+        },
+        _ => continue,
+      };
+      extensibility = Some(value);
+    }
+    Ok(extensibility)
+  }
+
+  fn number(&self, annotations: &[Annotation], name: &str) -> Result<Option<i64>, IdlError> {
+    find(annotations, name)
+      .map(|annotation| {
+        let value = annotation.value.as_deref().unwrap_or_default();
+        parse_integer(value).ok_or_else(|| self.error(format!("bad @{name} value `{value}`")))
+      })
+      .transpose()
+  }
+
+  fn member_id(&self, annotations: &[Annotation]) -> Result<Option<u32>, IdlError> {
+    self
+      .number(annotations, "id")?
+      .map(|id| u32::try_from(id).map_err(|_| self.error(format!("bad member id {id}"))))
+      .transpose()
+  }
+
+  fn struct_def(&mut self, annotations: &[Annotation]) -> Result<Option<StructDef>, IdlError> {
+    let name = self.ident()?;
+    let line = self.line;
+    if self.peek() == Some(&Token::Punct(';')) {
+      return Ok(None);
+    }
+    let base = if self.eat_punct(':') {
+      Some(self.scoped_name()?)
+    } else {
+      None
+    };
+    self.expect_punct('{')?;
+    let mut members = Vec::new();
+    while !self.eat_punct('}') {
+      let annotations = self.annotations()?;
+      let type_spec = self.type_spec()?;
+      let key = flag(&annotations, "key");
+      let optional = flag(&annotations, "optional");
+      let mut id = self.member_id(&annotations)?;
+      loop {
+        let name = self.ident()?;
+        let type_spec = self.array_dimensions(type_spec.clone())?;
+        members.push(Member {
+          name,
+          line: self.line,
+          type_spec,
+          key,
+          optional,
+          // Further declarators get the following ids
+          id: id.take(),
+        });
+        if !self.eat_punct(',') {
+          break;
+        }
+      }
+      self.expect_punct(';')?;
+    }
+    Ok(Some(StructDef {
+      name,
+      line,
+      base,
+      extensibility: self.extensibility(annotations)?,
+      members,
+    }))
+  }
+
+  fn enum_def(&mut self, annotations: &[Annotation]) -> Result<EnumDef, IdlError> {
+    let name = self.ident()?;
+    self.expect_punct('{')?;
+    let mut literals = Vec::new();
+    let mut next_value = 0;
+    loop {
+      let annotations = self.annotations()?;
+      let literal = self.ident()?;
+      let value = match self.number(&annotations, "value")? {
+        Some(value) => {
+          i32::try_from(value).map_err(|_| self.error(format!("bad enum value {value}")))?
+        }
+        None => next_value,
+      };
+      literals.push((literal, value));
+      next_value = value.wrapping_add(1);
+      if !self.eat_punct(',') || self.peek() == Some(&Token::Punct('}')) {
+        break;
+      }
+    }
+    self.expect_punct('}')?;
+    Ok(EnumDef {
+      name,
+      extensibility: self.extensibility(annotations)?,
+      literals,
+    })
+  }
+
+  fn union_def(&mut self) -> Result<Option<UnionDef>, IdlError> {
+    let name = self.ident()?;
+    let line = self.line;
+    if self.peek() == Some(&Token::Punct(';')) {
+      return Ok(None);
+    }
+    if !self.eat_keyword("switch") {
+      return Err(self.error("expected `switch`".to_string()));
+    }
+    self.expect_punct('(')?;
+    self.annotations()?;
+    let discriminator = self.type_spec()?;
+    self.expect_punct(')')?;
+    self.expect_punct('{')?;
+    let mut cases = Vec::new();
+    while !self.eat_punct('}') {
+      let mut labels = Vec::new();
+      let mut is_default = false;
+      loop {
+        if self.eat_keyword("case") {
+          labels.push(self.expr()?);
+        } else if self.eat_keyword("default") {
+          is_default = true;
+        } else {
+          break;
+        }
+        self.expect_punct(':')?;
+      }
+      if labels.is_empty() && !is_default {
+        return Err(self.error("expected `case` or `default`".to_string()));
+      }
+      let annotations = self.annotations()?;
+      let type_spec = self.type_spec()?;
+      let name = self.ident()?;
+      let type_spec = self.array_dimensions(type_spec)?;
+      self.expect_punct(';')?;
+      cases.push(UnionCase {
+        name,
+        type_spec,
+        labels,
+        is_default,
+        id: self.member_id(&annotations)?,
+      });
+    }
+    Ok(Some(UnionDef {
+      name,
+      line,
+      discriminator,
+      cases,
+    }))
+  }
+
+  fn type_spec(&mut self) -> Result<TypeSpec, IdlError> {
+    let name = match self.peek() {
+      Some(Token::Ident(name)) => name.clone(),
+      _ => return Ok(TypeSpec::Named(self.scoped_name()?)),
+    };
+    let primitive = match name.as_str() {
+      "short" | "int16" => "i16",
+      "long" | "int32" => "i32",
+      "int8" => "i8",
+      "int64" => "i64",
+      "uint8" | "octet" => "u8",
+      "uint16" => "u16",
+      "uint32" => "u32",
+      "uint64" => "u64",
+      "float" => "f32",
+      "double" => "f64",
+      "boolean" => "bool",
+      "char" => "char",
+      "unsigned" => "unsigned",
+      "string" => {
+        self.next()?;
+        if self.eat_punct('<') {
+          self.expr()?;
+          self.expect_punct('>')?;
+        }
+        return Ok(TypeSpec::String);
+      }
+      "sequence" => {
+        self.next()?;
+        self.expect_punct('<')?;
+        let element = self.type_spec()?;
+        if self.eat_punct(',') {
+          self.expr()?;
+        }
+        self.expect_punct('>')?;
+        return Ok(TypeSpec::Sequence(Box::new(element)));
+      }
+      "map" => {
+        self.next()?;
+        self.expect_punct('<')?;
+        let key = self.type_spec()?;
+        self.expect_punct(',')?;
+        let value = self.type_spec()?;
+        if self.eat_punct(',') {
+          self.expr()?;
+        }
+        self.expect_punct('>')?;
+        return Ok(TypeSpec::Map(Box::new(key), Box::new(value)));
+      }
+      "wchar" | "wstring" | "fixed" | "any" | "Object" | "ValueBase" => {
+        return Err(self.error(format!("type `{name}` is not supported")));
+      }
+      _ => return Ok(TypeSpec::Named(self.scoped_name()?)),
+    };
+    self.next()?;
+    let primitive = match primitive {
+      "unsigned" if self.eat_keyword("short") => "u16",
+      "unsigned" if self.eat_keyword("long") => {
+        if self.eat_keyword("long") {
+          "u64"
+        } else {
+          "u32"
+        }
+      }
+      "unsigned" => return Err(self.error("expected `short` or `long`".to_string())),
+      "i32" if self.eat_keyword("long") => "i64",
+      "i32" if self.eat_keyword("double") => {
+        return Err(self.error("type `long double` is not supported".to_string()));
+      }
+      primitive => primitive,
+    };
+    Ok(TypeSpec::Primitive(primitive))
+  }
+
+  // Array dimensions after a declarator, e.g. `matrix[3][4]`
+  fn array_dimensions(&mut self, element: TypeSpec) -> Result<TypeSpec, IdlError> {
+    let mut dimensions = Vec::new();
+    while self.eat_punct('[') {
+      dimensions.push(self.expr()?);
+      self.expect_punct(']')?;
+    }
+    Ok(
+      dimensions
+        .into_iter()
+        .rev()
+        .fold(element, |element, length| {
+          TypeSpec::Array(Box::new(element), length)
+        }),
+    )
+  }
+
+  // A constant expression, which ends at a token that cannot continue it
+  fn expr(&mut self) -> Result<Expr, IdlError> {
+    let mut expr = Vec::new();
+    let mut depth = 0;
+    loop {
+      match self.peek() {
+        Some(Token::Punct('(')) => depth += 1,
+        Some(Token::Punct(')')) if depth > 0 => depth -= 1,
+        Some(Token::Punct(';' | ',' | ':' | '>' | ']' | ')')) | None => break,
+        Some(Token::Ident(_) | Token::Scope) => {
+          let name = self.scoped_name()?;
+          expr.push(match name.as_slice() {
+            [b] if b == "TRUE" => ExprPart::Text("true".to_string()),
+            [b] if b == "FALSE" => ExprPart::Text("false".to_string()),
+            _ => ExprPart::Name(name),
+          });
+          continue;
+        }
+        _ => (),
+      }
+      let token = self.next()?;
+      expr.push(ExprPart::Text(token.to_string()));
+    }
+    if expr.is_empty() {
+      let found = self
+        .peek()
+        .map_or("end of input".to_string(), |t| format!("`{t}`"));
+      return Err(self.error(format!("expected a constant, found {found}")));
+    }
+    Ok(expr)
+  }
+}
+
+fn parse_integer(text: &str) -> Option<i64> {
+  match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+    Some(hex) => i64::from_str_radix(hex, 16).ok(),
+    None => text.parse().ok(),
+  }
+}
+
+// Code generation
+
+#[derive(Clone, Copy)]
+enum Symbol<'a> {
+  Struct(&'a StructDef),
+  Enum(&'a EnumDef),
+  // A literal of the enum, in the scope of the enum
+  EnumLiteral(&'a EnumDef),
+  Union,
+  Typedef(&'a TypeSpec),
+  Const,
+}
+
+struct Generator<'a> {
+  // By scoped names
+  symbols: HashMap<Vec<String>, Symbol<'a>>,
+  out: String,
+}
+
+// Names declared in IDL that are Rust keywords are escaped
+fn rust_name(name: &str) -> String {
+  const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "priv",
+    "pub", "ref", "return", "static", "struct", "trait", "true", "try", "type", "unsafe", "use",
+    "where", "while", "yield",
+  ];
+  match name {
+    "crate" | "self" | "Self" | "super" => format!("{name}_"),
+    _ if KEYWORDS.contains(&name) => format!("r#{name}"),
+    _ => name.to_string(),
+  }
+}
+
+// Variant names of unions, e.g. "long_value" and "longValue" are "LongValue"
+fn variant_name(name: &str) -> String {
+  let mut variant = String::new();
+  let mut upper = true;
+  for c in name.chars() {
+    if c == '_' {
+      upper = true;
+    } else if upper {
+      variant.extend(c.to_uppercase());
+      upper = false;
+    } else {
+      variant.push(c);
+    }
+  }
+  variant
+}
+
+fn is_snake_case(name: &str) -> bool {
+  !name.chars().any(char::is_uppercase)
+}
+
+fn is_camel_case(name: &str) -> bool {
+  !name.starts_with(|c: char| c.is_lowercase()) && !name.trim_matches('_').contains('_')
+}
+
+fn extensibility_attribute(extensibility: Option<&str>) -> String {
+  extensibility.map_or(String::new(), |e| {
+    format!("    #[dds(extensibility = \"{e}\")]\n")
+  })
+}
+
+impl<'a> Generator<'a> {
+  fn collect_symbols(&mut self, scope: &[String], definitions: &'a [Definition]) {
+    let scoped = |name: &str| {
+      let mut scoped = scope.to_vec();
+      scoped.push(name.to_string());
+      scoped
+    };
+    for definition in definitions {
+      match definition {
+        Definition::Module { name, definitions } => {
+          self.collect_symbols(&scoped(name), definitions);
+        }
+        Definition::Struct(s) => {
+          self.symbols.insert(scoped(&s.name), Symbol::Struct(s));
+        }
+        Definition::Enum(e) => {
+          self.symbols.insert(scoped(&e.name), Symbol::Enum(e));
+          // Enum literals are in the scope of the enum
+          for (literal, _) in &e.literals {
+            self.symbols.insert(scoped(literal), Symbol::EnumLiteral(e));
+          }
+        }
+        Definition::Union(u) => {
+          self.symbols.insert(scoped(&u.name), Symbol::Union);
+        }
+        Definition::Typedef { name, type_spec } => {
+          self
+            .symbols
+            .insert(scoped(name), Symbol::Typedef(type_spec));
+        }
+        Definition::Const { name, .. } => {
+          self.symbols.insert(scoped(name), Symbol::Const);
+        }
+      }
+    }
+  }
+
+  // Finds a name as IDL does, from the innermost enclosing scope outwards
+  fn lookup(&self, scope: &[String], name: &[String]) -> Option<(Vec<String>, Symbol<'a>)> {
+    (0..=scope.len()).rev().find_map(|i| {
+      let mut scoped = scope[..i].to_vec();
+      scoped.extend_from_slice(name);
+      let symbol = *self.symbols.get(&scoped)?;
+      Some((scoped, symbol))
+    })
+  }
+
+  // Rust path from the module `from` to a scoped name
+  fn path(from: &[String], to: &[String]) -> String {
+    let common = from
+      .iter()
+      .zip(&to[..to.len() - 1])
+      .take_while(|(a, b)| a == b)
+      .count();
+    let mut path = vec!["super".to_string(); from.len() - common];
+    path.extend(to[common..].iter().map(|n| rust_name(n)));
+    path.join("::")
+  }
+
+  // Rust path to a name, looked up from `scope`, used in the module `from`.
+  // Unknown names are emitted as written.
+  fn name_path(&self, scope: &[String], from: &[String], name: &[String]) -> String {
+    match self.lookup(scope, name) {
+      Some((mut scoped, Symbol::EnumLiteral(e))) => {
+        // The literal is a variant of its enum
+        let literal = scoped.pop().unwrap_or_default();
+        scoped.push(e.name.clone());
+        format!("{}::{}", Self::path(from, &scoped), rust_name(&literal))
+      }
+      Some((scoped, _)) => Self::path(from, &scoped),
+      None => name
+        .iter()
+        .map(|n| rust_name(n))
+        .collect::<Vec<_>>()
+        .join("::"),
+    }
+  }
+
+  fn expr(&self, scope: &[String], from: &[String], expr: &Expr) -> String {
+    expr
+      .iter()
+      .map(|part| match part {
+        ExprPart::Text(text) => text.clone(),
+        ExprPart::Name(name) => self.name_path(scope, from, name),
+      })
+      .collect()
+  }
+
+  // Rust type of `type_spec`, looked up from `scope`, used in the module `from`
+  fn rust_type(&self, scope: &[String], from: &[String], type_spec: &TypeSpec) -> String {
+    match type_spec {
+      TypeSpec::Primitive(p) => p.to_string(),
+      TypeSpec::String => "String".to_string(),
+      TypeSpec::Sequence(element) => format!("Vec<{}>", self.rust_type(scope, from, element)),
+      TypeSpec::Map(key, value) => format!(
+        "::std::collections::BTreeMap<{}, {}>",
+        self.rust_type(scope, from, key),
+        self.rust_type(scope, from, value)
+      ),
+      TypeSpec::Array(element, length) => {
+        let length = self.expr(scope, from, length);
+        let length = match parse_integer(&length) {
+          Some(_) => length,
+          None => format!("({length}) as usize"),
+        };
+        format!("[{}; {length}]", self.rust_type(scope, from, element))
+      }
+      TypeSpec::Named(name) => self.name_path(scope, from, name),
+    }
+  }
+
+  // The type behind any typedefs, and the scope to look its names up from
+  fn resolve(&self, scope: &[String], type_spec: &'a TypeSpec) -> (Vec<String>, &'a TypeSpec) {
+    let mut resolved = (scope.to_vec(), type_spec);
+    // Bounded, in case of a cyclic typedef
+    for _ in 0..100 {
+      match resolved.1 {
+        TypeSpec::Named(name) => match self.lookup(&resolved.0, name) {
+          Some((scoped, Symbol::Typedef(t))) => {
+            resolved = (scoped[..scoped.len() - 1].to_vec(), t);
+          }
+          _ => break,
+        },
+        _ => break,
+      }
+    }
+    resolved
+  }
+
+  fn definitions(
+    &mut self,
+    scope: &[String],
+    definitions: &'a [Definition],
+  ) -> Result<(), IdlError> {
+    for (i, definition) in definitions.iter().enumerate() {
+      if i > 0 || scope.is_empty() {
+        self.out.push('\n');
+      }
+      match definition {
+        Definition::Module { name, definitions } => {
+          let mut inner = scope.to_vec();
+          inner.push(name.clone());
+          if !is_snake_case(name) {
+            self.out.push_str("#[allow(non_snake_case)]\n");
+          }
+          writeln!(self.out, "pub mod {} {{", rust_name(name)).unwrap();
+          let start = self.out.len();
+          self.definitions(&inner, definitions)?;
+          // Indent the module contents
+          let contents = self.out.split_off(start);
+          for line in contents.lines() {
+            if !line.is_empty() {
+              self.out.push_str("    ");
+            }
+            self.out.push_str(line);
+            self.out.push('\n');
+          }
+          self.out.push_str("}\n");
+        }
+        Definition::Struct(s) => self.struct_def(scope, s)?,
+        Definition::Enum(e) => self.enum_def(e),
+        Definition::Union(u) => self.union_def(scope, u)?,
+        Definition::Typedef { name, type_spec } => {
+          if !is_camel_case(name) {
+            self.out.push_str("#[allow(non_camel_case_types)]\n");
+          }
+          let rust_type = self.rust_type(scope, scope, type_spec);
+          writeln!(self.out, "pub type {} = {rust_type};", rust_name(name)).unwrap();
+        }
+        Definition::Const {
+          name,
+          type_spec,
+          value,
+        } => {
+          let rust_type = match self.resolve(scope, type_spec).1 {
+            TypeSpec::String => "&str".to_string(),
+            _ => self.rust_type(scope, scope, type_spec),
+          };
+          if name.chars().any(char::is_lowercase) {
+            self.out.push_str("#[allow(non_upper_case_globals)]\n");
+          }
+          let value = self.expr(scope, scope, value);
+          writeln!(
+            self.out,
+            "pub const {}: {rust_type} = {value};",
+            rust_name(name)
+          )
+          .unwrap();
+        }
+      }
+    }
+    Ok(())
+  }
+
+  // The members of a struct and its bases, with the scopes of their
+  // declarations
+  fn all_members(
+    &self,
+    scope: &[String],
+    s: &'a StructDef,
+  ) -> Result<Vec<(Vec<String>, &'a Member)>, IdlError> {
+    let mut members = Vec::new();
+    if let Some(base) = &s.base {
+      match self.lookup(scope, base) {
+        Some((scoped, Symbol::Struct(base))) => {
+          members = self.all_members(&scoped[..scoped.len() - 1], base)?;
+        }
+        _ => {
+          return Err(IdlError {
+            line: s.line,
+            message: format!("unknown base struct `{}`", base.join("::")),
+          })
+        }
+      }
+    }
+    members.extend(s.members.iter().map(|m| (scope.to_vec(), m)));
+    Ok(members)
+  }
+
+  fn struct_def(&mut self, scope: &[String], s: &'a StructDef) -> Result<(), IdlError> {
+    let name = rust_name(&s.name);
+    let members = self.all_members(scope, s)?;
+    let mut fields = String::new();
+    for (member_scope, member) in &members {
+      if let Some(id) = member.id {
+        writeln!(fields, "        #[dds(id = {id})]").unwrap();
+      }
+      let mut rust_type = self.rust_type(member_scope, scope, &member.type_spec);
+      if member.optional {
+        rust_type = format!("Option<{rust_type}>");
+      }
+      writeln!(
+        fields,
+        "        pub {}: {rust_type},",
+        rust_name(&member.name)
+      )
+      .unwrap();
+    }
+
+    self.out.push_str("::rustdds::dds_type! {\n");
+    self.out.push_str(&extensibility_attribute(s.extensibility));
+    if !is_camel_case(&s.name) {
+      self.out.push_str("    #[allow(non_camel_case_types)]\n");
+    }
+    if !members.iter().all(|(_, m)| is_snake_case(&m.name)) {
+      self.out.push_str("    #[allow(non_snake_case)]\n");
+    }
+    self.out.push_str(
+      "    #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]\n",
+    );
+    write!(self.out, "    pub struct {name} {{\n{fields}    }}\n}}\n").unwrap();
+
+    let keys = members
+      .iter()
+      .filter(|(_, m)| m.key)
+      .map(|(member_scope, member)| {
+        let error = |message| IdlError {
+          line: member.line,
+          message,
+        };
+        if member.optional {
+          return Err(error(format!("key member `{}` is optional", member.name)));
+        }
+        let copy = match self.resolve(member_scope, &member.type_spec).1 {
+          TypeSpec::Primitive("f32" | "f64") => None,
+          TypeSpec::Primitive(_) => Some(true),
+          TypeSpec::String => Some(false),
+          _ => None,
+        };
+        let copy = copy.ok_or_else(|| {
+          error(format!(
+            "key member `{}` must be an integer, boolean, character or string",
+            member.name
+          ))
+        })?;
+        let field = rust_name(&member.name);
+        let value = if copy {
+          format!("self.{field}")
+        } else {
+          format!("self.{field}.clone()")
+        };
+        let rust_type = self.rust_type(member_scope, scope, &member.type_spec);
+        Ok((field, rust_type, value))
+      })
+      .collect::<Result<Vec<_>, IdlError>>()?;
+
+    let (key_type, key_value) = match keys.as_slice() {
+      [] => return Ok(()),
+      [(_, rust_type, value)] => (rust_type.clone(), value.clone()),
+      keys => {
+        // A struct of the key members
+        let key_name = format!("{}Key", s.name);
+        self.out.push_str("\n::rustdds::dds_type! {\n");
+        self.out.push_str(&extensibility_attribute(s.extensibility));
+        if !keys.iter().all(|(field, ..)| is_snake_case(field)) {
+          self.out.push_str("    #[allow(non_snake_case)]\n");
+        }
+        self.out.push_str(concat!(
+          "    #[derive(\n",
+          "        Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash,\n",
+          "        ::serde::Serialize, ::serde::Deserialize, ::rustdds::CdrEncodingSize,\n",
+          "    )]\n",
+        ));
+        writeln!(self.out, "    pub struct {key_name} {{").unwrap();
+        for (field, rust_type, _) in keys {
+          writeln!(self.out, "        pub {field}: {rust_type},").unwrap();
+        }
+        self.out.push_str("    }\n}\n\n");
+        writeln!(self.out, "impl ::rustdds::Key for {key_name} {{}}").unwrap();
+        let values = keys
+          .iter()
+          .map(|(field, _, value)| format!("            {field}: {value},\n"))
+          .collect::<String>();
+        (
+          key_name.clone(),
+          format!("{key_name} {{\n{values}        }}"),
+        )
+      }
+    };
+    self.out.push_str(&template(
+      KEYED_TEMPLATE,
+      &[
+        ("$NAME", &name),
+        ("$KEY_TYPE", &key_type),
+        ("$KEY", &key_value),
+      ],
+    ));
+    Ok(())
+  }
+
+  fn enum_def(&mut self, e: &'a EnumDef) {
+    let name = rust_name(&e.name);
+    if !is_camel_case(&e.name) || !e.literals.iter().all(|(l, _)| is_camel_case(l)) {
+      self.out.push_str("#[allow(non_camel_case_types)]\n");
+    }
+    let mut variants = String::new();
+    let mut literals = String::new();
+    for (i, (literal, value)) in e.literals.iter().enumerate() {
+      if i == 0 {
+        variants.push_str("    #[default]\n");
+      }
+      writeln!(variants, "    {} = {value},", rust_name(literal)).unwrap();
+      writeln!(
+        literals,
+        "                EnumLiteral {{ name: \"{literal}\".to_string(), value: {value} }},"
+      )
+      .unwrap();
+    }
+    let extensibility = match e.extensibility {
+      Some("final") => "Final",
+      _ => "Appendable",
+    };
+    self.out.push_str(&template(
+      ENUM_TEMPLATE,
+      &[
+        ("$NAME", &name),
+        ("$IDL_NAME", &e.name),
+        ("$VARIANTS", &variants),
+        ("$EXTENSIBILITY", extensibility),
+        ("$LITERALS", &literals),
+      ],
+    ));
+  }
+
+  fn union_def(&mut self, scope: &[String], u: &'a UnionDef) -> Result<(), IdlError> {
+    let name = rust_name(&u.name);
+    let (discriminator_scope, discriminator) = self.resolve(scope, &u.discriminator);
+    // Enum discriminators are matched exhaustively
+    let discriminator_enum = match discriminator {
+      TypeSpec::Primitive("f32" | "f64") => None,
+      TypeSpec::Primitive(_) => Some(None),
+      TypeSpec::Named(n) => match self.lookup(&discriminator_scope, n) {
+        Some((_, Symbol::Enum(e))) => Some(Some(e)),
+        _ => None,
+      },
+      _ => None,
+    };
+    let discriminator_enum = discriminator_enum.ok_or_else(|| IdlError {
+      line: u.line,
+      message: format!(
+        "discriminator of union `{}` must be an integer, boolean, character or enum",
+        u.name
+      ),
+    })?;
+    let discriminator_type = self.rust_type(scope, scope, &u.discriminator);
+
+    let mut variants = String::new();
+    let mut serialize_arms = String::new();
+    let mut deserialize_arms = String::new();
+    let mut cases = String::new();
+    let mut collect_layouts = String::new();
+    let mut label_count = 0;
+    for (i, case) in u.cases.iter().enumerate() {
+      let variant = variant_name(&case.name);
+      let rust_type = self.rust_type(scope, scope, &case.type_spec);
+      let labels = case
+        .labels
+        .iter()
+        .map(|label| match (discriminator_enum, label.as_slice()) {
+          // Literals of the discriminator enum need not be qualified
+          (Some(e), [ExprPart::Name(n)])
+            if n.len() == 1 && e.literals.iter().any(|(l, _)| *l == n[0]) =>
+          {
+            format!("{discriminator_type}::{}", n[0])
+          }
+          _ => self.expr(scope, scope, label),
+        })
+        .collect::<Vec<_>>();
+      label_count += labels.len();
+      if case.is_default {
+        writeln!(
+          variants,
+          "    {variant}({discriminator_type}, {rust_type}),"
+        )
+        .unwrap();
+        writeln!(
+          serialize_arms,
+          "            Self::{variant}(discriminator, value) => write(tuple, discriminator, value),"
+        )
+        .unwrap();
+        writeln!(
+          deserialize_arms,
+          "                    discriminator => {name}::{variant}(discriminator, value(seq)?),"
+        )
+        .unwrap();
+      } else {
+        writeln!(variants, "    {variant}({rust_type}),").unwrap();
+        writeln!(
+          serialize_arms,
+          "            Self::{variant}(value) => write(tuple, &{}, value),",
+          labels[0]
+        )
+        .unwrap();
+        // The default case comes last in the match
+        let arm = format!(
+          "                    {} => {name}::{variant}(value(seq)?),\n",
+          labels.join(" | ")
+        );
+        deserialize_arms.insert_str(
+          deserialize_arms
+            .find("                    discriminator =>")
+            .unwrap_or(deserialize_arms.len()),
+          &arm,
+        );
+      }
+
+      let label_values = labels
+        .iter()
+        .map(|label| match parse_integer(label) {
+          Some(_) => label.clone(),
+          None => format!("({label}) as i32"),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+      write!(
+        cases,
+        r#"                UnionCase {{
+                    id: {},
+                    name: "{}".to_string(),
+                    member_type: <{rust_type} as Extensible>::type_object()?,
+                    labels: vec![{label_values}],
+                    is_default: {},
+                }},
+"#,
+        case.id.unwrap_or(i as u32),
+        case.name,
+        case.is_default
+      )
+      .unwrap();
+      writeln!(
+        collect_layouts,
+        "        <{rust_type} as Extensible>::collect_layouts(layouts);"
+      )
+      .unwrap();
+    }
+    let exhaustive = u.cases.iter().any(|c| c.is_default)
+      || discriminator_enum.is_some_and(|e| label_count >= e.literals.len());
+    if !exhaustive {
+      write!(
+        deserialize_arms,
+        r#"                    discriminator => {{
+                        let message = format!("unknown discriminator {{discriminator:?}}");
+                        return Err(Error::custom(message));
+                    }}
+"#
+      )
+      .unwrap();
+    }
+
+    if !is_camel_case(&u.name) {
+      self.out.push_str("#[allow(non_camel_case_types)]\n");
+    }
+    self.out.push_str(&template(
+      UNION_TEMPLATE,
+      &[
+        ("$NAME", &name),
+        ("$IDL_NAME", &u.name),
+        ("$DISCRIMINATOR", &discriminator_type),
+        ("$VARIANTS", &variants),
+        ("$SERIALIZE_ARMS", &serialize_arms),
+        ("$DESERIALIZE_ARMS", &deserialize_arms),
+        ("$COLLECT_LAYOUTS", &collect_layouts),
+        ("$CASES", &cases),
+      ],
+    ));
+    Ok(())
+  }
+}
+
+// Fills in the `$NAME`s of a code template
+fn template(template: &str, values: &[(&str, &str)]) -> String {
+  values
+    .iter()
+    .fold(template.to_string(), |code, (name, value)| {
+      code.replace(name, value)
+    })
+}
+
+const KEYED_TEMPLATE: &str = r#"
+impl ::rustdds::Keyed for $NAME {
+    type K = $KEY_TYPE;
+
+    fn key(&self) -> Self::K {
+        $KEY
+    }
+}
+"#;
+
+const ENUM_TEMPLATE: &str = r#"#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+    ::serde_repr::Serialize_repr, ::serde_repr::Deserialize_repr,
+)]
+#[repr(i32)]
+pub enum $NAME {
+$VARIANTS}
+
+impl ::rustdds::serialization::Extensible for $NAME {
+    fn type_object() -> Option<::rustdds::xtypes::TypeObject> {
+        use ::rustdds::xtypes::*;
+        Some(TypeObject::Enum(EnumType {
+            name: "$IDL_NAME".to_string(),
+            extensibility: Extensibility::$EXTENSIBILITY,
+            literals: vec![
+$LITERALS            ],
+        }))
+    }
+}
+"#;
+
+// A union is serialized as its discriminator followed by the value of the
+// case, like a final struct
+const UNION_TEMPLATE: &str = r#"#[derive(Debug, Clone, PartialEq)]
+pub enum $NAME {
+$VARIANTS}
+
+impl ::serde::Serialize for $NAME {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use ::serde::ser::SerializeTuple;
+        fn write<S: SerializeTuple>(
+            mut tuple: S,
+            discriminator: &$DISCRIMINATOR,
+            value: &impl ::serde::Serialize,
+        ) -> Result<S::Ok, S::Error> {
+            tuple.serialize_element(discriminator)?;
+            tuple.serialize_element(value)?;
+            tuple.end()
+        }
+        let tuple = serializer.serialize_tuple(2)?;
+        match self {
+$SERIALIZE_ARMS        }
+    }
+}
+
+impl<'de> ::serde::Deserialize<'de> for $NAME {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct UnionVisitor;
+
+        impl<'de> ::serde::de::Visitor<'de> for UnionVisitor {
+            type Value = $NAME;
+
+            fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                formatter.write_str("union $IDL_NAME")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<$NAME, A::Error>
+            where
+                A: ::serde::de::SeqAccess<'de>,
+            {
+                use ::serde::de::Error;
+                fn value<'de, A: ::serde::de::SeqAccess<'de>, T: ::serde::Deserialize<'de>>(
+                    mut seq: A,
+                ) -> Result<T, A::Error> {
+                    seq.next_element()?.ok_or_else(|| Error::invalid_length(1, &"union value"))
+                }
+                let discriminator: $DISCRIMINATOR = seq
+                    .next_element()?
+                    .ok_or_else(|| Error::invalid_length(0, &self))?;
+                Ok(match discriminator {
+$DESERIALIZE_ARMS                })
+            }
+        }
+
+        deserializer.deserialize_tuple(2, UnionVisitor)
+    }
+}
+
+impl ::rustdds::serialization::Extensible for $NAME {
+    fn collect_layouts(layouts: &mut Vec<::rustdds::serialization::StructLayout>) {
+        use ::rustdds::serialization::Extensible;
+$COLLECT_LAYOUTS    }
+
+    fn type_object() -> Option<::rustdds::xtypes::TypeObject> {
+        use ::rustdds::{serialization::Extensible, xtypes::*};
+        Some(TypeObject::Union(UnionType {
+            name: "$IDL_NAME".to_string(),
+            extensibility: Extensibility::Final,
+            discriminator: Box::new(<$DISCRIMINATOR as Extensible>::type_object()?),
+            cases: vec![
+$CASES            ],
+        }))
+    }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn structs_with_keys_and_extensibility() {
+    let rust = generate_rust(
+      r#"
+      #include "other.idl"
+      // Shapes
+      module shapes {
+        const long MAX_POINTS = 8;
+        typedef sequence<long, MAX_POINTS> Points;
+
+        /* A base struct */
+        @final
+        struct Base {
+          @key unsigned long long id;
+        };
+
+        @mutable
+        struct Shape : Base {
+          @key string<128> color;
+          @id(10) long x, y;
+          @optional double size;
+          Points points;
+          octet bytes[MAX_POINTS][2];
+          other::Thing thing;
+        };
+      };
+      "#,
+    )
+    .unwrap();
+
+    // Base members first, and the ids of further declarators follow
+    assert!(rust.contains(
+      "    ::rustdds::dds_type! {
+        #[dds(extensibility = \"mutable\")]
+        #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+        pub struct Shape {
+            pub id: u64,
+            pub color: String,
+            #[dds(id = 10)]
+            pub x: i32,
+            pub y: i32,
+            pub size: Option<f64>,
+            pub points: Points,
+            pub bytes: [[u8; 2]; (MAX_POINTS) as usize],
+            pub thing: other::Thing,
+        }
+    }"
+    ));
+    assert!(rust.contains("pub mod shapes {"));
+    assert!(rust.contains("    pub const MAX_POINTS: i32 = 8;"));
+    assert!(rust.contains("    pub type Points = Vec<i32>;"));
+    assert!(rust.contains("    impl ::rustdds::Keyed for Base {\n        type K = u64;"));
+    assert!(rust.contains("        #[dds(extensibility = \"final\")]"));
+
+    // Several key members make a key struct
+    assert!(rust.contains("        pub struct ShapeKey {\n            pub id: u64,"));
+    assert!(rust.contains("    impl ::rustdds::Key for ShapeKey {}"));
+    assert!(rust.contains("            ShapeKey {\n                id: self.id,"));
+    assert!(rust.contains("                color: self.color.clone(),"));
+  }
+
+  #[test]
+  fn enums_unions_and_scopes() {
+    let rust = generate_rust(
+      r#"
+      module a {
+        enum Color { RED, @value(5) GREEN, BLUE };
+      };
+      module b {
+        union Paint switch (a::Color) {
+          case a::RED: long red_value;
+          case GREEN: case BLUE: string name;
+        };
+        union Value switch (short) {
+          case 1: float f;
+          default: sequence<a::Color> colors;
+        };
+      };
+      module a {
+        struct type { b::Paint paint; };
+      };
+      "#,
+    )
+    .unwrap();
+
+    // Reopened modules are merged
+    assert_eq!(rust.matches("pub mod a {").count(), 1);
+    assert!(rust.contains("        RED = 0,\n        GREEN = 5,\n        BLUE = 6,"));
+    assert!(rust.contains("    pub struct r#type {\n            pub paint: super::b::Paint,"));
+    assert!(rust.contains("        RedValue(i32),\n        Name(String),"));
+    assert!(rust.contains("Self::RedValue(value) => write(tuple, &super::a::Color::RED, value),"));
+    assert!(
+      rust.contains("super::a::Color::GREEN | super::a::Color::BLUE => Paint::Name(value(seq)?),")
+    );
+    assert!(rust.contains("        Colors(i16, Vec<super::a::Color>),"));
+    assert!(rust.contains("1 => Value::F(value(seq)?),\n"));
+    assert!(rust.contains("discriminator => Value::Colors(discriminator, value(seq)?),"));
+    // All literals have a case, so there is no error for other ones
+    assert_eq!(rust.matches("unknown discriminator").count(), 0);
+    assert!(rust.contains("labels: vec![(super::a::Color::GREEN) as i32,"));
+  }
+
+  #[test]
+  fn errors() {
+    let error = generate_rust("struct A {\n  long x\n};").unwrap_err();
+    assert_eq!(error.line, 3);
+    assert_eq!(error.message, "expected `;`, found `}`");
+
+    let error = generate_rust("struct A {\n  @key double x;\n};").unwrap_err();
+    assert_eq!(error.line, 2);
+
+    let error = generate_rust("interface I {};").unwrap_err();
+    assert_eq!(error.message, "`interface` declarations are not supported");
+
+    assert!(generate_rust("struct A { wstring w; };").is_err());
+    assert!(generate_rust("struct A { long x; }").is_err());
+    assert!(generate_rust("/* unterminated").is_err());
+  }
+}
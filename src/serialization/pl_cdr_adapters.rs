@@ -5,6 +5,7 @@ use byteorder::{ByteOrder, LittleEndian};
 
 use crate::{
   dds::adapters::{no_key, with_key},
+  serialization::cdr_adapters::endianness_of,
   structure::parameter_id::ParameterId,
   Keyed, RepresentationIdentifier,
 };
@@ -44,13 +45,11 @@ where
   type Error = PlCdrSerializeError;
 
   fn output_encoding() -> RepresentationIdentifier {
-    // TODO: This works only for BO=LittleEndian
-    RepresentationIdentifier::PL_CDR_LE
+    RepresentationIdentifier::pl_cdr(endianness_of::<BO>())
   }
 
   fn to_bytes(value: &D) -> Result<Bytes, Self::Error> {
-    // TODO: This works only for BO=LittleEndian
-    value.to_pl_cdr_bytes(RepresentationIdentifier::PL_CDR_LE)
+    value.to_pl_cdr_bytes(Self::output_encoding())
   }
 }
 
@@ -61,8 +60,7 @@ where
   BO: ByteOrder,
 {
   fn key_to_bytes(value: &D::K) -> Result<Bytes, Self::Error> {
-    // TODO: This works only for BO=LittleEndian
-    value.to_pl_cdr_bytes(RepresentationIdentifier::PL_CDR_LE)
+    value.to_pl_cdr_bytes(RepresentationIdentifier::pl_cdr(endianness_of::<BO>()))
   }
 }
 
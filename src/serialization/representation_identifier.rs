@@ -1,8 +1,10 @@
 use std::io;
 
-use speedy::{Readable, Writable};
+use speedy::{Endianness, Readable, Writable};
 use byteorder::ReadBytesExt;
 
+use super::xcdr2::Xcdr2Encoding;
+
 /// Used to identify serialization format of payload data over RTPS.
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Readable, Writable)]
 pub struct RepresentationIdentifier {
@@ -95,4 +97,62 @@ impl RepresentationIdentifier {
   pub fn to_bytes(self) -> [u8; 2] {
     self.bytes
   }
+
+  /// Byte order of the CDR and PL_CDR (XCDR version 1) encodings. `None` for
+  /// other representations, which RustDDS does not (de)serialize as CDR.
+  pub fn cdr_endianness(self) -> Option<Endianness> {
+    match self {
+      Self::CDR_LE | Self::PL_CDR_LE => Some(Endianness::LittleEndian),
+      Self::CDR_BE | Self::PL_CDR_BE => Some(Endianness::BigEndian),
+      _ => None,
+    }
+  }
+
+  /// The CDR representation in the given byte order
+  pub fn cdr(endianness: Endianness) -> Self {
+    match endianness {
+      Endianness::LittleEndian => Self::CDR_LE,
+      Endianness::BigEndian => Self::CDR_BE,
+    }
+  }
+
+  /// The PL_CDR representation in the given byte order
+  pub fn pl_cdr(endianness: Endianness) -> Self {
+    match endianness {
+      Endianness::LittleEndian => Self::PL_CDR_LE,
+      Endianness::BigEndian => Self::PL_CDR_BE,
+    }
+  }
+
+  /// Encoding and byte order of the XCDR2 representations. `None` for other
+  /// representations.
+  ///
+  /// The identifiers of RTPS v2.3 Table 10.3 (`CDR2_*`, `PL_CDR2_*` and
+  /// `D_CDR_*`) are accepted as aliases of those of DDS-XTypes v1.3.
+  pub fn xcdr2_encoding(self) -> Option<(Xcdr2Encoding, Endianness)> {
+    use Endianness::{BigEndian, LittleEndian};
+
+    match self {
+      Self::XCDR2_BE | Self::CDR2_BE => Some((Xcdr2Encoding::Plain, BigEndian)),
+      Self::XCDR2_LE | Self::CDR2_LE => Some((Xcdr2Encoding::Plain, LittleEndian)),
+      Self::D_CDR2_BE | Self::D_CDR_BE => Some((Xcdr2Encoding::Delimited, BigEndian)),
+      Self::D_CDR2_LE | Self::D_CDR_LE => Some((Xcdr2Encoding::Delimited, LittleEndian)),
+      Self::PL_XCDR2_BE | Self::PL_CDR2_BE => Some((Xcdr2Encoding::ParameterList, BigEndian)),
+      Self::PL_XCDR2_LE | Self::PL_CDR2_LE => Some((Xcdr2Encoding::ParameterList, LittleEndian)),
+      _ => None,
+    }
+  }
+
+  /// The XCDR2 representation of DDS-XTypes v1.3 with the given encoding and
+  /// byte order
+  pub fn xcdr2(encoding: Xcdr2Encoding, endianness: Endianness) -> Self {
+    match (encoding, endianness) {
+      (Xcdr2Encoding::Plain, Endianness::BigEndian) => Self::XCDR2_BE,
+      (Xcdr2Encoding::Plain, Endianness::LittleEndian) => Self::XCDR2_LE,
+      (Xcdr2Encoding::Delimited, Endianness::BigEndian) => Self::D_CDR2_BE,
+      (Xcdr2Encoding::Delimited, Endianness::LittleEndian) => Self::D_CDR2_LE,
+      (Xcdr2Encoding::ParameterList, Endianness::BigEndian) => Self::PL_XCDR2_BE,
+      (Xcdr2Encoding::ParameterList, Endianness::LittleEndian) => Self::PL_XCDR2_LE,
+    }
+  }
 }
@@ -5,6 +5,7 @@ use log::{debug, error, info, trace, warn};
 use speedy::{Context, Readable, Reader, Writable, Writer};
 
 use crate::{
+  dds::compliance,
   messages::submessages::elements::parameter::Parameter,
   serialization::pl_cdr_adapters::{PlCdrDeserializeError, PlCdrSerializeError},
   structure::parameter_id::ParameterId,
@@ -14,29 +15,17 @@ use crate::{
 pub fn pl_cdr_rep_id_to_speedy(
   encoding: RepresentationIdentifier,
 ) -> Result<speedy::Endianness, PlCdrSerializeError> {
-  match encoding {
-    RepresentationIdentifier::PL_CDR_LE => Ok(speedy::Endianness::LittleEndian),
-    RepresentationIdentifier::PL_CDR_BE => Ok(speedy::Endianness::BigEndian),
-    RepresentationIdentifier::CDR_LE => Ok(speedy::Endianness::LittleEndian),
-    RepresentationIdentifier::CDR_BE => Ok(speedy::Endianness::BigEndian),
-    rep_id => Err(PlCdrSerializeError::NotSupported(format!(
-      "Unknown {rep_id:?}"
-    ))),
-  }
+  encoding
+    .cdr_endianness()
+    .ok_or_else(|| PlCdrSerializeError::NotSupported(format!("Unknown {encoding:?}")))
 }
 
 pub fn pl_cdr_rep_id_to_speedy_d(
   encoding: RepresentationIdentifier,
 ) -> Result<speedy::Endianness, PlCdrDeserializeError> {
-  match encoding {
-    RepresentationIdentifier::PL_CDR_LE => Ok(speedy::Endianness::LittleEndian),
-    RepresentationIdentifier::PL_CDR_BE => Ok(speedy::Endianness::BigEndian),
-    RepresentationIdentifier::CDR_LE => Ok(speedy::Endianness::LittleEndian),
-    RepresentationIdentifier::CDR_BE => Ok(speedy::Endianness::BigEndian),
-    rep_id => Err(PlCdrDeserializeError::NotSupported(format!(
-      "Unknown {rep_id:?}"
-    ))),
-  }
+  encoding
+    .cdr_endianness()
+    .ok_or_else(|| PlCdrDeserializeError::NotSupported(format!("Unknown {encoding:?}")))
 }
 
 // This is a helper type for serialization.
@@ -94,12 +83,19 @@ impl<'a, C: Context> Readable<'a, C> for StringWithNul {
   fn read_from<R: speedy::Reader<'a, C>>(reader: &mut R) -> std::result::Result<Self, C::Error> {
     // TODO: Should align to 4 before reading string length
     let mut raw_str: String = reader.read_value()?;
-    let assumed_nul = raw_str.pop(); //
-    match assumed_nul {
+    match raw_str.pop() {
       Some('\0') => { /* fine */ }
-      Some(other) => error!("StringWithNul deserialize: Expected NUL character, decoded {other:?}"),
+      Some(other) => {
+        if !compliance::tolerate("CDR string does not end with NUL character.") {
+          return Err(speedy::Error::custom("CDR string is not NUL-terminated").into());
+        }
+        raw_str.push(other); // was part of the string after all
+      }
       None => {
-        error!("StringWithNul deserialize: Expected NUL character, but end of input reached.");
+        // Some implementations encode an empty string as length 0.
+        if !compliance::tolerate("CDR string has length zero, expected at least NUL character.") {
+          return Err(speedy::Error::custom("CDR string has length zero").into());
+        }
       }
     }
     Ok(StringWithNul { string: raw_str })
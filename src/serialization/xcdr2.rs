@@ -0,0 +1,1782 @@
+//! Extended CDR version 2 (XCDR2) encoding, as in Section 7.4.3 "Extended CDR
+//! Representation" of the DDS-XTypes specification v1.3.
+//!
+//! XCDR2 differs from classic CDR in that 8-byte values are aligned to 4
+//! bytes only, and in how structs are encoded. That depends on the
+//! extensibility of the type, and is chosen by an [`Xcdr2Encoding`]:
+//!
+//! * [`Plain`](Xcdr2Encoding::Plain) for final types: the members follow each
+//!   other, as in classic CDR.
+//! * [`Delimited`](Xcdr2Encoding::Delimited) for appendable types: each struct
+//!   begins with a DHEADER, i.e. its length in bytes. A reader skips members
+//!   it does not know at the end of a struct.
+//! * [`ParameterList`](Xcdr2Encoding::ParameterList) for mutable types: each
+//!   struct begins with a DHEADER, and each member with an EMHEADER, which
+//!   gives its member id and length. Members may come in any order, and a
+//!   reader skips members it does not know. Absent `Option` members are left
+//!   out.
+//!
+//! Members that a reader knows, but which are not in the data, get default
+//! values: zeros, empty strings and sequences, and absent `Option`s. This
+//! happens when the writer has an older version of an appendable or mutable
+//! type.
+//!
+//! Serde does not tell the extensibility of types, so by default the encoding
+//! applies to all structs in a sample, and member ids are the indices of the
+//! struct fields. Types declared with [`dds_type!`](crate::dds_type) tell
+//! their extensibility and member ids, see
+//! [`Extensible`](crate::serialization::Extensible).
+//!
+//! Serde does not tell the element types of sequences before their elements
+//! either, so sequences, arrays and maps are encoded without the DHEADER that
+//! XCDR2 puts before collections of non-primitive elements. Enums are encoded
+//! as unions with a 32-bit discriminator, and `char` as an 8-bit character.
+//!
+//! The representation identifier of a serialized payload selects the
+//! encoding and byte order, see
+//! [`RepresentationIdentifier::xcdr2_encoding`](crate::RepresentationIdentifier::xcdr2_encoding).
+//! [`CDRDeserializerAdapter`](crate::CDRDeserializerAdapter) decodes both
+//! classic CDR and XCDR2, and [`Xcdr2SerializerAdapter`] encodes XCDR2.
+
+use std::{io, marker::PhantomData};
+
+use serde::{
+  de::{
+    self, value::U32Deserializer, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess,
+    SeqAccess, VariantAccess, Visitor,
+  },
+  ser::{self, Serialize},
+  Deserialize,
+};
+use bytes::Bytes;
+use byteorder::{ByteOrder, LittleEndian};
+use speedy::Endianness;
+
+use super::{cdr_adapters::endianness_of, extensible::StructLayout, Error, Result};
+use crate::{
+  dds::{
+    adapters::{no_key, with_key},
+    content_filter::DdsSqlFilter,
+  },
+  xtypes::Extensibility,
+  Keyed, RepresentationIdentifier,
+};
+
+/// Encoding of structs in XCDR2. See the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Xcdr2Encoding {
+  /// PLAIN_CDR2, for final types
+  Plain,
+  /// DELIMITED_CDR2, for appendable types
+  Delimited,
+  /// PL_CDR2, for mutable types
+  ParameterList,
+}
+
+impl From<Extensibility> for Xcdr2Encoding {
+  fn from(extensibility: Extensibility) -> Self {
+    match extensibility {
+      Extensibility::Final => Self::Plain,
+      Extensibility::Appendable => Self::Delimited,
+      Extensibility::Mutable => Self::ParameterList,
+    }
+  }
+}
+
+// Flag of an EMHEADER telling that the reader must understand the member
+const MUST_UNDERSTAND: u32 = 0x8000_0000;
+const MEMBER_ID_MASK: u32 = 0x0fff_ffff;
+// EMHEADER length code meaning that the length is in the next u32
+const LENGTH_CODE_NEXTINT: u32 = 4;
+
+/// Serializes to XCDR2.
+///
+/// The result does not include the encapsulation header.
+pub fn to_vec_xcdr2<T>(
+  value: &T,
+  encoding: Xcdr2Encoding,
+  endianness: Endianness,
+) -> Result<Vec<u8>>
+where
+  T: Serialize + ?Sized,
+{
+  let mut serializer = Xcdr2Serializer::new(encoding, endianness);
+  value.serialize(&mut serializer)?;
+  Ok(serializer.into_inner())
+}
+
+/// Serializes to XCDR2 into a writer.
+pub fn to_writer_xcdr2<T, W>(
+  mut writer: W,
+  value: &T,
+  encoding: Xcdr2Encoding,
+  endianness: Endianness,
+) -> Result<()>
+where
+  T: Serialize + ?Sized,
+  W: io::Write,
+{
+  // Lengths are written before the data they describe, so the data is
+  // encoded into memory first.
+  let bytes = to_vec_xcdr2(value, encoding, endianness)?;
+  writer
+    .write_all(&bytes)
+    .map_err(|e| Error::Message(format!("Cannot write XCDR2 data: {e}")))
+}
+
+/// Deserializes XCDR2.
+///
+/// Returns deserialized object and byte count consumed.
+pub fn from_bytes_xcdr2<'de, T>(
+  input_bytes: &[u8],
+  encoding: Xcdr2Encoding,
+  endianness: Endianness,
+) -> Result<(T, usize)>
+where
+  T: Deserialize<'de>,
+{
+  deserialize_xcdr2_with_decoder(input_bytes, encoding, endianness, PhantomData::<T>)
+}
+
+/// Deserializes XCDR2 into a type that may borrow from the input, such as
+/// one with `&str` or `&[u8]` fields.
+///
+/// Returns deserialized object and byte count consumed.
+pub fn from_bytes_xcdr2_borrowed<'de, T>(
+  input_bytes: &'de [u8],
+  encoding: Xcdr2Encoding,
+  endianness: Endianness,
+) -> Result<(T, usize)>
+where
+  T: Deserialize<'de>,
+{
+  let mut deserializer = Xcdr2Deserializer::new(input_bytes, encoding, endianness).borrowing();
+  let value = T::deserialize(&mut deserializer)?;
+  Ok((value, deserializer.bytes_consumed()))
+}
+
+/// Decode XCDR2 using the given [`DeserializeSeed`]-based decoder.
+///
+/// Returns deserialized object and byte count consumed.
+pub fn deserialize_xcdr2_with_decoder<'de, S>(
+  input_bytes: &[u8],
+  encoding: Xcdr2Encoding,
+  endianness: Endianness,
+  decoder: S,
+) -> Result<(S::Value, usize)>
+where
+  S: DeserializeSeed<'de>,
+{
+  let mut deserializer = Xcdr2Deserializer::new(input_bytes, encoding, endianness);
+  let value = decoder.deserialize(&mut deserializer)?;
+  Ok((value, deserializer.bytes_consumed()))
+}
+
+fn message(msg: impl Into<String>) -> Error {
+  Error::Message(msg.into())
+}
+
+/// Selects the encoding of an [`Xcdr2SerializerAdapter`].
+pub trait Xcdr2EncodingKind {
+  const ENCODING: Xcdr2Encoding;
+}
+
+/// Marker for [`Xcdr2Encoding::Plain`]
+pub struct PlainCdr2;
+/// Marker for [`Xcdr2Encoding::Delimited`]
+pub struct DelimitedCdr2;
+/// Marker for [`Xcdr2Encoding::ParameterList`]
+pub struct PlCdr2;
+
+impl Xcdr2EncodingKind for PlainCdr2 {
+  const ENCODING: Xcdr2Encoding = Xcdr2Encoding::Plain;
+}
+impl Xcdr2EncodingKind for DelimitedCdr2 {
+  const ENCODING: Xcdr2Encoding = Xcdr2Encoding::Delimited;
+}
+impl Xcdr2EncodingKind for PlCdr2 {
+  const ENCODING: Xcdr2Encoding = Xcdr2Encoding::ParameterList;
+}
+
+/// Serializes data to XCDR2, as a [`no_key::SerializerAdapter`] and
+/// [`with_key::SerializerAdapter`]. The encoding is chosen by `E`, which is
+/// [`PlainCdr2`], [`DelimitedCdr2`] or [`PlCdr2`].
+///
+/// Decode with [`CDRDeserializerAdapter`](crate::CDRDeserializerAdapter).
+pub struct Xcdr2SerializerAdapter<D, E = DelimitedCdr2, BO = LittleEndian>
+where
+  E: Xcdr2EncodingKind,
+  BO: ByteOrder,
+{
+  phantom: PhantomData<D>,
+  encoding: PhantomData<E>,
+  ghost: PhantomData<BO>,
+}
+
+impl<D, E, BO> no_key::SerializerAdapter<D> for Xcdr2SerializerAdapter<D, E, BO>
+where
+  D: Serialize,
+  E: Xcdr2EncodingKind,
+  BO: ByteOrder,
+{
+  type Error = Error;
+
+  fn output_encoding() -> RepresentationIdentifier {
+    RepresentationIdentifier::xcdr2(E::ENCODING, endianness_of::<BO>())
+  }
+
+  fn to_bytes(value: &D) -> Result<Bytes> {
+    to_vec_xcdr2(value, E::ENCODING, endianness_of::<BO>()).map(Bytes::from)
+  }
+
+  fn matches_sql_filter(value: &D, filter: &DdsSqlFilter) -> Option<bool> {
+    Some(filter.matches(value))
+  }
+}
+
+impl<D, E, BO> with_key::SerializerAdapter<D> for Xcdr2SerializerAdapter<D, E, BO>
+where
+  D: Keyed + Serialize,
+  <D as Keyed>::K: Serialize,
+  E: Xcdr2EncodingKind,
+  BO: ByteOrder,
+{
+  fn key_to_bytes(value: &D::K) -> Result<Bytes> {
+    to_vec_xcdr2(value, E::ENCODING, endianness_of::<BO>()).map(Bytes::from)
+  }
+}
+
+/// A [`serde::Serializer`] producing XCDR2.
+pub struct Xcdr2Serializer {
+  output: Vec<u8>,
+  encoding: Xcdr2Encoding,
+  endianness: Endianness,
+  layouts: Vec<StructLayout>,
+  // Where the data of the current parameter list member begins. An absent
+  // Option there leaves out the member.
+  member_start: Option<usize>,
+  member_omitted: bool,
+}
+
+impl Xcdr2Serializer {
+  /// A serializer encoding all structs with `encoding`
+  pub fn new(encoding: Xcdr2Encoding, endianness: Endianness) -> Self {
+    Self {
+      output: Vec::new(),
+      encoding,
+      endianness,
+      layouts: Vec::new(),
+      member_start: None,
+      member_omitted: false,
+    }
+  }
+
+  /// Encodes the structs that have a layout as it says, and others with the
+  /// encoding given to [`new`](Self::new).
+  pub fn with_layouts(mut self, layouts: Vec<StructLayout>) -> Self {
+    self.layouts = layouts;
+    self
+  }
+
+  pub fn into_inner(self) -> Vec<u8> {
+    self.output
+  }
+
+  fn align(&mut self, alignment: usize) {
+    let padding = (alignment - self.output.len() % alignment) % alignment;
+    self.output.resize(self.output.len() + padding, 0);
+  }
+
+  // Writes a primitive given in little-endian byte order. XCDR2 aligns
+  // primitives to their size, but to 4 bytes at most.
+  fn put<const N: usize>(&mut self, mut le_bytes: [u8; N]) {
+    self.align(N.min(4));
+    if self.endianness == Endianness::BigEndian {
+      le_bytes.reverse();
+    }
+    self.output.extend_from_slice(&le_bytes);
+  }
+
+  fn put_u32(&mut self, value: u32) {
+    self.put(value.to_le_bytes());
+  }
+
+  fn patch_u32(&mut self, position: usize, value: u32) {
+    let bytes = match self.endianness {
+      Endianness::LittleEndian => value.to_le_bytes(),
+      Endianness::BigEndian => value.to_be_bytes(),
+    };
+    self.output[position..position + 4].copy_from_slice(&bytes);
+  }
+
+  fn put_length(&mut self, length: usize) -> Result<()> {
+    let length = u32::try_from(length).map_err(|_| message("Too long for XCDR2"))?;
+    self.put_u32(length);
+    Ok(())
+  }
+
+  fn at_member_start(&self) -> bool {
+    self.member_start == Some(self.output.len())
+  }
+
+  fn struct_encoding(&self, name: &str) -> (Xcdr2Encoding, &'static [u32]) {
+    struct_encoding(&self.layouts, self.encoding, name)
+  }
+}
+
+// Encoding and member ids of a struct. Empty member ids mean that they are
+// the field indices.
+fn struct_encoding(
+  layouts: &[StructLayout],
+  default: Xcdr2Encoding,
+  name: &str,
+) -> (Xcdr2Encoding, &'static [u32]) {
+  match layouts.iter().find(|layout| layout.name == name) {
+    Some(layout) => (layout.extensibility.into(), layout.member_ids),
+    None => (default, &[]),
+  }
+}
+
+/// Serializes sequences, tuples and maps, whose elements need no headers.
+pub struct Compound<'a> {
+  ser: &'a mut Xcdr2Serializer,
+}
+
+/// Serializes structs, with the headers of the encoding.
+pub struct StructSerializer<'a> {
+  ser: &'a mut Xcdr2Serializer,
+  encoding: Xcdr2Encoding,
+  member_ids: &'static [u32],
+  // Position of the DHEADER
+  dheader: Option<usize>,
+  next_field: usize,
+}
+
+impl<'a> ser::Serializer for &'a mut Xcdr2Serializer {
+  type Ok = ();
+  type Error = Error;
+  type SerializeSeq = Compound<'a>;
+  type SerializeTuple = Compound<'a>;
+  type SerializeTupleStruct = Compound<'a>;
+  type SerializeTupleVariant = Compound<'a>;
+  type SerializeMap = Compound<'a>;
+  type SerializeStruct = StructSerializer<'a>;
+  type SerializeStructVariant = StructSerializer<'a>;
+
+  fn serialize_bool(self, v: bool) -> Result<()> {
+    self.put([u8::from(v)]);
+    Ok(())
+  }
+
+  fn serialize_i8(self, v: i8) -> Result<()> {
+    self.put(v.to_le_bytes());
+    Ok(())
+  }
+
+  fn serialize_i16(self, v: i16) -> Result<()> {
+    self.put(v.to_le_bytes());
+    Ok(())
+  }
+
+  fn serialize_i32(self, v: i32) -> Result<()> {
+    self.put(v.to_le_bytes());
+    Ok(())
+  }
+
+  fn serialize_i64(self, v: i64) -> Result<()> {
+    self.put(v.to_le_bytes());
+    Ok(())
+  }
+
+  fn serialize_u8(self, v: u8) -> Result<()> {
+    self.put([v]);
+    Ok(())
+  }
+
+  fn serialize_u16(self, v: u16) -> Result<()> {
+    self.put(v.to_le_bytes());
+    Ok(())
+  }
+
+  fn serialize_u32(self, v: u32) -> Result<()> {
+    self.put(v.to_le_bytes());
+    Ok(())
+  }
+
+  fn serialize_u64(self, v: u64) -> Result<()> {
+    self.put(v.to_le_bytes());
+    Ok(())
+  }
+
+  fn serialize_f32(self, v: f32) -> Result<()> {
+    self.put(v.to_le_bytes());
+    Ok(())
+  }
+
+  fn serialize_f64(self, v: f64) -> Result<()> {
+    self.put(v.to_le_bytes());
+    Ok(())
+  }
+
+  fn serialize_char(self, v: char) -> Result<()> {
+    let c = u8::try_from(v).map_err(|_| message(format!("Character {v:?} is not a char8")))?;
+    self.put([c]);
+    Ok(())
+  }
+
+  fn serialize_str(self, v: &str) -> Result<()> {
+    // The length includes the terminating NUL.
+    self.put_length(v.len() + 1)?;
+    self.output.extend_from_slice(v.as_bytes());
+    self.output.push(0);
+    Ok(())
+  }
+
+  fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+    self.put_length(v.len())?;
+    self.output.extend_from_slice(v);
+    Ok(())
+  }
+
+  fn serialize_none(self) -> Result<()> {
+    if self.at_member_start() {
+      self.member_omitted = true;
+    } else {
+      self.put([0]);
+    }
+    Ok(())
+  }
+
+  fn serialize_some<T>(self, value: &T) -> Result<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    if self.at_member_start() {
+      // The presence of the member tells that the value is present.
+      self.member_start = None;
+    } else {
+      self.put([1]);
+    }
+    value.serialize(self)
+  }
+
+  fn serialize_unit(self) -> Result<()> {
+    Ok(())
+  }
+
+  fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+    Ok(())
+  }
+
+  fn serialize_unit_variant(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+  ) -> Result<()> {
+    self.put_u32(variant_index);
+    Ok(())
+  }
+
+  fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    value.serialize(self)
+  }
+
+  fn serialize_newtype_variant<T>(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+    value: &T,
+  ) -> Result<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    self.put_u32(variant_index);
+    value.serialize(self)
+  }
+
+  fn serialize_seq(self, len: Option<usize>) -> Result<Compound<'a>> {
+    let len = len.ok_or_else(|| message("XCDR2 sequences must have a known length"))?;
+    self.put_length(len)?;
+    Ok(Compound { ser: self })
+  }
+
+  fn serialize_tuple(self, _len: usize) -> Result<Compound<'a>> {
+    Ok(Compound { ser: self })
+  }
+
+  fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>> {
+    Ok(Compound { ser: self })
+  }
+
+  fn serialize_tuple_variant(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+    _len: usize,
+  ) -> Result<Compound<'a>> {
+    self.put_u32(variant_index);
+    Ok(Compound { ser: self })
+  }
+
+  fn serialize_map(self, len: Option<usize>) -> Result<Compound<'a>> {
+    let len = len.ok_or_else(|| message("XCDR2 maps must have a known length"))?;
+    self.put_length(len)?;
+    Ok(Compound { ser: self })
+  }
+
+  fn serialize_struct(self, name: &'static str, _len: usize) -> Result<StructSerializer<'a>> {
+    let (encoding, member_ids) = self.struct_encoding(name);
+    let dheader = match encoding {
+      Xcdr2Encoding::Plain => None,
+      Xcdr2Encoding::Delimited | Xcdr2Encoding::ParameterList => {
+        self.align(4);
+        let position = self.output.len();
+        self.put_u32(0); // patched at the end
+        Some(position)
+      }
+    };
+    Ok(StructSerializer {
+      ser: self,
+      encoding,
+      member_ids,
+      dheader,
+      next_field: 0,
+    })
+  }
+
+  fn serialize_struct_variant(
+    self,
+    name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+    len: usize,
+  ) -> Result<StructSerializer<'a>> {
+    self.put_u32(variant_index);
+    self.serialize_struct(name, len)
+  }
+}
+
+impl<'a> ser::SerializeSeq for Compound<'a> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    value.serialize(&mut *self.ser)
+  }
+
+  fn end(self) -> Result<()> {
+    Ok(())
+  }
+}
+
+impl<'a> ser::SerializeTuple for Compound<'a> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    value.serialize(&mut *self.ser)
+  }
+
+  fn end(self) -> Result<()> {
+    Ok(())
+  }
+}
+
+impl<'a> ser::SerializeTupleStruct for Compound<'a> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    value.serialize(&mut *self.ser)
+  }
+
+  fn end(self) -> Result<()> {
+    Ok(())
+  }
+}
+
+impl<'a> ser::SerializeTupleVariant for Compound<'a> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    value.serialize(&mut *self.ser)
+  }
+
+  fn end(self) -> Result<()> {
+    Ok(())
+  }
+}
+
+impl<'a> ser::SerializeMap for Compound<'a> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    key.serialize(&mut *self.ser)
+  }
+
+  fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    value.serialize(&mut *self.ser)
+  }
+
+  fn end(self) -> Result<()> {
+    Ok(())
+  }
+}
+
+impl<'a> StructSerializer<'a> {
+  fn member<T>(&mut self, value: &T) -> Result<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    let field = self.next_field;
+    self.next_field += 1;
+    if self.encoding != Xcdr2Encoding::ParameterList {
+      return value.serialize(&mut *self.ser);
+    }
+    let member_id = match self.member_ids.get(field) {
+      Some(member_id) => *member_id,
+      None => u32::try_from(field).unwrap_or(u32::MAX),
+    };
+
+    // EMHEADER and NEXTINT, patched when the length is known
+    self.ser.align(4);
+    let emheader = self.ser.output.len();
+    self.ser.put_u32(0);
+    self.ser.put_u32(0);
+    let data_start = self.ser.output.len();
+    self.ser.member_start = Some(data_start);
+    self.ser.member_omitted = false;
+    let result = value.serialize(&mut *self.ser);
+    self.ser.member_start = None;
+    result?;
+
+    if self.ser.member_omitted {
+      self.ser.member_omitted = false;
+      self.ser.output.truncate(emheader);
+      return Ok(());
+    }
+    let length = self.ser.output.len() - data_start;
+    if member_id > MEMBER_ID_MASK {
+      return Err(message("Too many members for an XCDR2 parameter list"));
+    }
+    // Members of 1, 2, 4 or 8 bytes have length codes of their own, and no
+    // NEXTINT. Dropping the 4 bytes of NEXTINT keeps the data aligned.
+    let length_code = match length {
+      1 => Some(0),
+      2 => Some(1),
+      4 => Some(2),
+      8 => Some(3),
+      _ => None,
+    };
+    match length_code {
+      Some(length_code) => {
+        self.ser.output.drain(emheader + 4..data_start);
+        self
+          .ser
+          .patch_u32(emheader, (length_code << 28) | member_id);
+      }
+      None => {
+        let length = u32::try_from(length).map_err(|_| message("Too long for XCDR2"))?;
+        self
+          .ser
+          .patch_u32(emheader, (LENGTH_CODE_NEXTINT << 28) | member_id);
+        self.ser.patch_u32(emheader + 4, length);
+      }
+    }
+    Ok(())
+  }
+
+  fn finish(self) -> Result<()> {
+    if let Some(dheader) = self.dheader {
+      let length = self.ser.output.len() - dheader - 4;
+      let length = u32::try_from(length).map_err(|_| message("Too long for XCDR2"))?;
+      self.ser.patch_u32(dheader, length);
+    }
+    Ok(())
+  }
+}
+
+impl<'a> ser::SerializeStruct for StructSerializer<'a> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    self.member(value)
+  }
+
+  fn skip_field(&mut self, _key: &'static str) -> Result<()> {
+    // Member ids are given by field index, also when fields are skipped.
+    self.next_field += 1;
+    Ok(())
+  }
+
+  fn end(self) -> Result<()> {
+    self.finish()
+  }
+}
+
+impl<'a> ser::SerializeStructVariant for StructSerializer<'a> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+  where
+    T: Serialize + ?Sized,
+  {
+    self.member(value)
+  }
+
+  fn skip_field(&mut self, _key: &'static str) -> Result<()> {
+    self.next_field += 1;
+    Ok(())
+  }
+
+  fn end(self) -> Result<()> {
+    self.finish()
+  }
+}
+
+/// How an [`Xcdr2Deserializer`] gives strings and byte sequences to the
+/// deserialized type: [`Copying`] or [`Borrowing`] them.
+pub trait Lending<'i, 'de> {
+  #[doc(hidden)]
+  fn visit_str<V: Visitor<'de>>(string: &'i str, visitor: V) -> Result<V::Value>;
+  #[doc(hidden)]
+  fn visit_bytes<V: Visitor<'de>>(bytes: &'i [u8], visitor: V) -> Result<V::Value>;
+}
+
+/// Strings and byte sequences are copied, so the deserialized value does not
+/// depend on the input.
+pub struct Copying;
+
+/// Strings and byte sequences are borrowed from the input, so `&str` and
+/// `&[u8]` can be deserialized without copying.
+pub struct Borrowing;
+
+impl<'i, 'de> Lending<'i, 'de> for Copying {
+  fn visit_str<V: Visitor<'de>>(string: &'i str, visitor: V) -> Result<V::Value> {
+    visitor.visit_str(string)
+  }
+
+  fn visit_bytes<V: Visitor<'de>>(bytes: &'i [u8], visitor: V) -> Result<V::Value> {
+    visitor.visit_bytes(bytes)
+  }
+}
+
+impl<'de> Lending<'de, 'de> for Borrowing {
+  fn visit_str<V: Visitor<'de>>(string: &'de str, visitor: V) -> Result<V::Value> {
+    visitor.visit_borrowed_str(string)
+  }
+
+  fn visit_bytes<V: Visitor<'de>>(bytes: &'de [u8], visitor: V) -> Result<V::Value> {
+    visitor.visit_borrowed_bytes(bytes)
+  }
+}
+
+/// A [`serde::Deserializer`] for XCDR2.
+///
+/// It copies strings and byte sequences, unless made
+/// [`borrowing`](Self::borrowing).
+pub struct Xcdr2Deserializer<'i, L = Copying> {
+  input: &'i [u8],
+  position: usize,
+  encoding: Xcdr2Encoding,
+  endianness: Endianness,
+  layouts: Vec<StructLayout>,
+  // Where the data of the current parameter list member begins. An Option
+  // there is present without a flag.
+  member_start: Option<usize>,
+  lending: PhantomData<L>,
+}
+
+impl<'i> Xcdr2Deserializer<'i> {
+  /// A deserializer decoding all structs with `encoding`
+  pub fn new(input: &'i [u8], encoding: Xcdr2Encoding, endianness: Endianness) -> Self {
+    Self {
+      input,
+      position: 0,
+      encoding,
+      endianness,
+      layouts: Vec::new(),
+      member_start: None,
+      lending: PhantomData,
+    }
+  }
+
+  /// Makes the deserializer lend strings and byte sequences from the input,
+  /// instead of copying them. Then the deserialized value cannot outlive the
+  /// input.
+  pub fn borrowing(self) -> Xcdr2Deserializer<'i, Borrowing> {
+    Xcdr2Deserializer {
+      input: self.input,
+      position: self.position,
+      encoding: self.encoding,
+      endianness: self.endianness,
+      layouts: self.layouts,
+      member_start: self.member_start,
+      lending: PhantomData,
+    }
+  }
+}
+
+impl<'i, L> Xcdr2Deserializer<'i, L> {
+  /// Decodes the structs that have a layout as it says, and others with the
+  /// encoding given to [`new`](Self::new).
+  pub fn with_layouts(mut self, layouts: Vec<StructLayout>) -> Self {
+    self.layouts = layouts;
+    self
+  }
+
+  pub fn bytes_consumed(&self) -> usize {
+    self.position
+  }
+
+  fn align(&mut self, alignment: usize) -> Result<()> {
+    let padding = (alignment - self.position % alignment) % alignment;
+    self.take(padding).map(|_| ())
+  }
+
+  fn take(&mut self, count: usize) -> Result<&'i [u8]> {
+    let end = self
+      .position
+      .checked_add(count)
+      .filter(|end| *end <= self.input.len())
+      .ok_or_else(|| message("XCDR2 data ended unexpectedly"))?;
+    let bytes = &self.input[self.position..end];
+    self.position = end;
+    Ok(bytes)
+  }
+
+  // Reads a primitive, and returns it in little-endian byte order.
+  fn get<const N: usize>(&mut self) -> Result<[u8; N]> {
+    self.align(N.min(4))?;
+    let mut bytes = [0; N];
+    bytes.copy_from_slice(self.take(N)?);
+    if self.endianness == Endianness::BigEndian {
+      bytes.reverse();
+    }
+    Ok(bytes)
+  }
+
+  fn get_u32(&mut self) -> Result<u32> {
+    self.get().map(u32::from_le_bytes)
+  }
+
+  fn get_length(&mut self) -> Result<usize> {
+    let length = self.get_u32()? as usize;
+    // Each element takes at least a byte, so this rejects corrupt lengths
+    // before anything is allocated for them.
+    if length > self.input.len() - self.position {
+      return Err(message(format!("XCDR2 length {length} exceeds the data")));
+    }
+    Ok(length)
+  }
+
+  // The end position of a struct, read from its DHEADER
+  fn get_dheader(&mut self) -> Result<usize> {
+    let length = self.get_length()?;
+    Ok(self.position + length)
+  }
+
+  fn at_member_start(&self) -> bool {
+    self.member_start == Some(self.position)
+  }
+}
+
+macro_rules! deserialize_primitive {
+  ($method:ident, $visit:ident, $type:ty) => {
+    fn $method<V>(self, visitor: V) -> Result<V::Value>
+    where
+      V: Visitor<'de>,
+    {
+      visitor.$visit(<$type>::from_le_bytes(self.get()?))
+    }
+  };
+}
+
+impl<'de, 'i, L: Lending<'i, 'de>> de::Deserializer<'de> for &mut Xcdr2Deserializer<'i, L> {
+  type Error = Error;
+
+  fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    Err(message("XCDR2 is not self-describing"))
+  }
+
+  fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    match self.get::<1>()? {
+      [0] => visitor.visit_bool(false),
+      [1] => visitor.visit_bool(true),
+      [b] => Err(message(format!("Invalid XCDR2 boolean {b}"))),
+    }
+  }
+
+  deserialize_primitive!(deserialize_i8, visit_i8, i8);
+  deserialize_primitive!(deserialize_i16, visit_i16, i16);
+  deserialize_primitive!(deserialize_i32, visit_i32, i32);
+  deserialize_primitive!(deserialize_i64, visit_i64, i64);
+  deserialize_primitive!(deserialize_u8, visit_u8, u8);
+  deserialize_primitive!(deserialize_u16, visit_u16, u16);
+  deserialize_primitive!(deserialize_u32, visit_u32, u32);
+  deserialize_primitive!(deserialize_u64, visit_u64, u64);
+  deserialize_primitive!(deserialize_f32, visit_f32, f32);
+  deserialize_primitive!(deserialize_f64, visit_f64, f64);
+
+  fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    let [c] = self.get::<1>()?;
+    visitor.visit_char(char::from(c))
+  }
+
+  fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    let length = self.get_length()?;
+    let bytes = match self.take(length)? {
+      [string @ .., 0] => string,
+      _ => return Err(message("XCDR2 string is not NUL-terminated")),
+    };
+    let string =
+      std::str::from_utf8(bytes).map_err(|e| message(format!("Invalid XCDR2 string: {e}")))?;
+    L::visit_str(string, visitor)
+  }
+
+  fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.deserialize_str(visitor)
+  }
+
+  fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    let length = self.get_length()?;
+    L::visit_bytes(self.take(length)?, visitor)
+  }
+
+  fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.deserialize_bytes(visitor)
+  }
+
+  fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    if self.at_member_start() {
+      self.member_start = None;
+      return visitor.visit_some(self);
+    }
+    match self.get::<1>()? {
+      [0] => visitor.visit_none(),
+      [1] => visitor.visit_some(self),
+      [b] => Err(message(format!("Invalid XCDR2 optional flag {b}"))),
+    }
+  }
+
+  fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_newtype_struct(self)
+  }
+
+  fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    let count = self.get_length()?;
+    visitor.visit_seq(Counted { de: self, count })
+  }
+
+  fn deserialize_tuple<V>(self, count: usize, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_seq(Counted { de: self, count })
+  }
+
+  fn deserialize_tuple_struct<V>(
+    self,
+    _name: &'static str,
+    count: usize,
+    visitor: V,
+  ) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_seq(Counted { de: self, count })
+  }
+
+  fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    let count = self.get_length()?;
+    visitor.visit_map(Counted { de: self, count })
+  }
+
+  fn deserialize_struct<V>(
+    self,
+    name: &'static str,
+    fields: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    let (encoding, member_ids) = struct_encoding(&self.layouts, self.encoding, name);
+    match encoding {
+      Xcdr2Encoding::Plain => visitor.visit_seq(Counted {
+        de: self,
+        count: fields.len(),
+      }),
+      Xcdr2Encoding::Delimited => {
+        let end = self.get_dheader()?;
+        let value = visitor.visit_seq(Delimited {
+          de: &mut *self,
+          end,
+          count: fields.len(),
+        })?;
+        // Skip members appended in a newer version of the type.
+        self.position = end;
+        Ok(value)
+      }
+      Xcdr2Encoding::ParameterList => {
+        let end = self.get_dheader()?;
+        let value = visitor.visit_map(ParameterList {
+          de: &mut *self,
+          end,
+          fields,
+          member_ids,
+          seen: vec![false; fields.len()],
+          member: None,
+        })?;
+        self.position = end;
+        Ok(value)
+      }
+    }
+  }
+
+  fn deserialize_enum<V>(
+    self,
+    _name: &'static str,
+    _variants: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_enum(self)
+  }
+
+  fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.deserialize_u32(visitor)
+  }
+
+  fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    Err(message("XCDR2 is not self-describing"))
+  }
+
+  fn is_human_readable(&self) -> bool {
+    false
+  }
+}
+
+// A known number of elements
+struct Counted<'a, 'i, L> {
+  de: &'a mut Xcdr2Deserializer<'i, L>,
+  count: usize,
+}
+
+impl<'de, 'a, 'i, L: Lending<'i, 'de>> SeqAccess<'de> for Counted<'a, 'i, L> {
+  type Error = Error;
+
+  fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+  where
+    T: DeserializeSeed<'de>,
+  {
+    if self.count == 0 {
+      return Ok(None);
+    }
+    self.count -= 1;
+    seed.deserialize(&mut *self.de).map(Some)
+  }
+
+  fn size_hint(&self) -> Option<usize> {
+    Some(self.count)
+  }
+}
+
+impl<'de, 'a, 'i, L: Lending<'i, 'de>> MapAccess<'de> for Counted<'a, 'i, L> {
+  type Error = Error;
+
+  fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+  where
+    K: DeserializeSeed<'de>,
+  {
+    if self.count == 0 {
+      return Ok(None);
+    }
+    self.count -= 1;
+    seed.deserialize(&mut *self.de).map(Some)
+  }
+
+  fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    seed.deserialize(&mut *self.de)
+  }
+
+  fn size_hint(&self) -> Option<usize> {
+    Some(self.count)
+  }
+}
+
+// The members of a delimited struct. If the struct ends before all members
+// are read, the rest get default values.
+struct Delimited<'a, 'i, L> {
+  de: &'a mut Xcdr2Deserializer<'i, L>,
+  end: usize,
+  count: usize,
+}
+
+impl<'de, 'a, 'i, L: Lending<'i, 'de>> SeqAccess<'de> for Delimited<'a, 'i, L> {
+  type Error = Error;
+
+  fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+  where
+    T: DeserializeSeed<'de>,
+  {
+    if self.count == 0 {
+      return Ok(None);
+    }
+    self.count -= 1;
+    if self.de.position >= self.end {
+      // Written with an older version of the type
+      seed.deserialize(Defaults).map(Some)
+    } else {
+      seed.deserialize(&mut *self.de).map(Some)
+    }
+  }
+}
+
+// The members of a parameter list struct, which are identified by their
+// member ids. Members that are not present get default values.
+struct ParameterList<'a, 'i, L> {
+  de: &'a mut Xcdr2Deserializer<'i, L>,
+  end: usize,
+  fields: &'static [&'static str],
+  member_ids: &'static [u32],
+  seen: Vec<bool>,
+  // The member whose key was read last
+  member: Option<Member>,
+}
+
+enum Member {
+  Present { data_start: usize, data_end: usize },
+  Missing,
+}
+
+impl<'a, 'i, L> ParameterList<'a, 'i, L> {
+  fn field_index(&self, member_id: u32) -> Option<usize> {
+    if self.member_ids.is_empty() {
+      Some(member_id as usize).filter(|i| *i < self.fields.len())
+    } else {
+      self.member_ids.iter().position(|id| *id == member_id)
+    }
+  }
+}
+
+impl<'de, 'a, 'i, L: Lending<'i, 'de>> MapAccess<'de> for ParameterList<'a, 'i, L> {
+  type Error = Error;
+
+  fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+  where
+    K: DeserializeSeed<'de>,
+  {
+    loop {
+      // Members begin at 4-byte boundaries, but the struct may end before one.
+      let aligned = self.de.position + (4 - self.de.position % 4) % 4;
+      if aligned >= self.end {
+        let Some(missing) = self.seen.iter().position(|seen| !seen) else {
+          return Ok(None);
+        };
+        self.seen[missing] = true;
+        self.member = Some(Member::Missing);
+        return seed
+          .deserialize(self.fields[missing].into_deserializer())
+          .map(Some);
+      }
+      self.de.position = aligned;
+      let emheader = self.de.get_u32()?;
+      let member_id = emheader & MEMBER_ID_MASK;
+      let (data_start, length) = match (emheader >> 28) & 0x7 {
+        length_code @ 0..=3 => (self.de.position, 1 << length_code),
+        LENGTH_CODE_NEXTINT => {
+          let length = self.de.get_length()?;
+          (self.de.position, length)
+        }
+        // NEXTINT is also the first four bytes of the data: a DHEADER or a
+        // sequence length, which gives the length.
+        length_code => {
+          let data_start = self.de.position;
+          let next_int = self.de.get_u32()? as usize;
+          let length = match length_code {
+            5 => Some(next_int),
+            6 => next_int.checked_mul(4),
+            _ => next_int.checked_mul(8),
+          };
+          let length = length
+            .and_then(|l| l.checked_add(4))
+            .ok_or_else(|| message("XCDR2 member length overflows"))?;
+          (data_start, length)
+        }
+      };
+      let data_end = data_start
+        .checked_add(length)
+        .filter(|e| *e <= self.end)
+        .ok_or_else(|| message("XCDR2 member exceeds its struct"))?;
+
+      match self.field_index(member_id) {
+        Some(field) => {
+          self.seen[field] = true;
+          self.member = Some(Member::Present {
+            data_start,
+            data_end,
+          });
+          return seed
+            .deserialize(self.fields[field].into_deserializer())
+            .map(Some);
+        }
+        None if emheader & MUST_UNDERSTAND != 0 => {
+          return Err(message(format!(
+            "Unknown XCDR2 member {member_id} must be understood"
+          )));
+        }
+        None => self.de.position = data_end,
+      }
+    }
+  }
+
+  fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    let (data_start, data_end) = match self.member.take() {
+      Some(Member::Present {
+        data_start,
+        data_end,
+      }) => (data_start, data_end),
+      Some(Member::Missing) => return seed.deserialize(Defaults),
+      None => return Err(message("XCDR2 member value requested before its key")),
+    };
+    self.de.position = data_start;
+    self.de.member_start = Some(data_start);
+    let value = seed.deserialize(&mut *self.de);
+    self.de.member_start = None;
+    self.de.position = data_end;
+    value
+  }
+}
+
+impl<'de, 'i, L: Lending<'i, 'de>> EnumAccess<'de> for &mut Xcdr2Deserializer<'i, L> {
+  type Error = Error;
+  type Variant = Self;
+
+  fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    let discriminator = self.get_u32()?;
+    let value = seed.deserialize(U32Deserializer::<Error>::new(discriminator))?;
+    Ok((value, self))
+  }
+}
+
+impl<'de, 'i, L: Lending<'i, 'de>> VariantAccess<'de> for &mut Xcdr2Deserializer<'i, L> {
+  type Error = Error;
+
+  fn unit_variant(self) -> Result<()> {
+    Ok(())
+  }
+
+  fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+  where
+    T: DeserializeSeed<'de>,
+  {
+    seed.deserialize(self)
+  }
+
+  fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    de::Deserializer::deserialize_tuple(self, len, visitor)
+  }
+
+  fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    de::Deserializer::deserialize_struct(self, "", fields, visitor)
+  }
+}
+
+// Deserializes the default values of XTypes: zeros, empty strings and
+// sequences, absent optionals, and the first enumerators.
+struct Defaults;
+
+macro_rules! deserialize_default {
+  ($method:ident, $visit:ident, $value:expr) => {
+    fn $method<V>(self, visitor: V) -> Result<V::Value>
+    where
+      V: Visitor<'de>,
+    {
+      visitor.$visit($value)
+    }
+  };
+}
+
+impl<'de> de::Deserializer<'de> for Defaults {
+  type Error = Error;
+
+  deserialize_default!(deserialize_bool, visit_bool, false);
+  deserialize_default!(deserialize_i8, visit_i8, 0);
+  deserialize_default!(deserialize_i16, visit_i16, 0);
+  deserialize_default!(deserialize_i32, visit_i32, 0);
+  deserialize_default!(deserialize_i64, visit_i64, 0);
+  deserialize_default!(deserialize_u8, visit_u8, 0);
+  deserialize_default!(deserialize_u16, visit_u16, 0);
+  deserialize_default!(deserialize_u32, visit_u32, 0);
+  deserialize_default!(deserialize_u64, visit_u64, 0);
+  deserialize_default!(deserialize_f32, visit_f32, 0.0);
+  deserialize_default!(deserialize_f64, visit_f64, 0.0);
+  deserialize_default!(deserialize_char, visit_char, '\0');
+  // Static, so that borrowing types can have them too
+  deserialize_default!(deserialize_str, visit_borrowed_str, "");
+  deserialize_default!(deserialize_string, visit_borrowed_str, "");
+  deserialize_default!(deserialize_bytes, visit_borrowed_bytes, &[]);
+  deserialize_default!(deserialize_byte_buf, visit_borrowed_bytes, &[]);
+  deserialize_default!(deserialize_identifier, visit_u32, 0);
+
+  fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_none()
+  }
+
+  fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_newtype_struct(self)
+  }
+
+  fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_seq(DefaultElements(0))
+  }
+
+  fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_seq(DefaultElements(len))
+  }
+
+  fn deserialize_tuple_struct<V>(
+    self,
+    _name: &'static str,
+    len: usize,
+    visitor: V,
+  ) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_seq(DefaultElements(len))
+  }
+
+  fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_map(DefaultElements(0))
+  }
+
+  fn deserialize_struct<V>(
+    self,
+    _name: &'static str,
+    fields: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_seq(DefaultElements(fields.len()))
+  }
+
+  fn deserialize_enum<V>(
+    self,
+    _name: &'static str,
+    _variants: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_enum(self)
+  }
+
+  fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_unit()
+  }
+}
+
+// A number of default values
+struct DefaultElements(usize);
+
+impl<'de> SeqAccess<'de> for DefaultElements {
+  type Error = Error;
+
+  fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+  where
+    T: DeserializeSeed<'de>,
+  {
+    if self.0 == 0 {
+      return Ok(None);
+    }
+    self.0 -= 1;
+    seed.deserialize(Defaults).map(Some)
+  }
+}
+
+impl<'de> MapAccess<'de> for DefaultElements {
+  type Error = Error;
+
+  fn next_key_seed<K>(&mut self, _seed: K) -> Result<Option<K::Value>>
+  where
+    K: DeserializeSeed<'de>,
+  {
+    Ok(None)
+  }
+
+  fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    seed.deserialize(Defaults)
+  }
+}
+
+impl<'de> EnumAccess<'de> for Defaults {
+  type Error = Error;
+  type Variant = Self;
+
+  fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    let value = seed.deserialize(U32Deserializer::<Error>::new(0))?;
+    Ok((value, self))
+  }
+}
+
+impl<'de> VariantAccess<'de> for Defaults {
+  type Error = Error;
+
+  fn unit_variant(self) -> Result<()> {
+    Ok(())
+  }
+
+  fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+  where
+    T: DeserializeSeed<'de>,
+  {
+    seed.deserialize(self)
+  }
+
+  fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_seq(DefaultElements(len))
+  }
+
+  fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_seq(DefaultElements(fields.len()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde::{Deserialize, Serialize};
+
+  use super::*;
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+  struct Inner {
+    a: u8,
+    b: f64,
+  }
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+  struct Outer {
+    inner: Inner,
+    name: String,
+    values: Vec<u16>,
+    maybe: Option<i32>,
+  }
+
+  fn sample() -> Outer {
+    Outer {
+      inner: Inner { a: 7, b: 1.5 },
+      name: "abc".to_string(),
+      values: vec![1, 2],
+      maybe: None,
+    }
+  }
+
+  #[test]
+  fn plain_aligns_to_four_bytes() {
+    let bytes = to_vec_xcdr2(&sample().inner, Xcdr2Encoding::Plain, Endianness::BigEndian).unwrap();
+    let mut expected = vec![7, 0, 0, 0];
+    expected.extend_from_slice(&1.5_f64.to_be_bytes());
+    assert_eq!(bytes, expected);
+  }
+
+  #[test]
+  fn delimited_and_parameter_list_headers() {
+    let inner = sample().inner;
+    let bytes = to_vec_xcdr2(&inner, Xcdr2Encoding::Delimited, Endianness::LittleEndian).unwrap();
+    assert_eq!(bytes[..4], 12_u32.to_le_bytes());
+    assert_eq!(bytes.len(), 16);
+
+    let bytes = to_vec_xcdr2(
+      &inner,
+      Xcdr2Encoding::ParameterList,
+      Endianness::LittleEndian,
+    )
+    .unwrap();
+    let mut expected = Vec::new();
+    expected.extend_from_slice(&20_u32.to_le_bytes()); // DHEADER
+    expected.extend_from_slice(&0x0000_0000_u32.to_le_bytes()); // id 0, 1 byte
+    expected.extend_from_slice(&[7, 0, 0, 0]);
+    expected.extend_from_slice(&0x3000_0001_u32.to_le_bytes()); // id 1, 8 bytes
+    expected.extend_from_slice(&1.5_f64.to_le_bytes());
+    assert_eq!(bytes, expected);
+  }
+
+  #[test]
+  fn round_trip() {
+    let mut value = sample();
+    for maybe in [None, Some(-3)] {
+      value.maybe = maybe;
+      for encoding in [
+        Xcdr2Encoding::Plain,
+        Xcdr2Encoding::Delimited,
+        Xcdr2Encoding::ParameterList,
+      ] {
+        for endianness in [Endianness::LittleEndian, Endianness::BigEndian] {
+          let bytes = to_vec_xcdr2(&value, encoding, endianness).unwrap();
+          let (decoded, consumed) =
+            from_bytes_xcdr2::<Outer>(&bytes, encoding, endianness).unwrap();
+          assert_eq!(decoded, value);
+          assert_eq!(consumed, bytes.len());
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn readers_skip_unknown_members() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Old {
+      a: u8,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct New {
+      a: u8,
+      b: String,
+    }
+
+    let new = New {
+      a: 5,
+      b: "appended".to_string(),
+    };
+    for encoding in [Xcdr2Encoding::Delimited, Xcdr2Encoding::ParameterList] {
+      let bytes = to_vec_xcdr2(&new, encoding, Endianness::LittleEndian).unwrap();
+      let (old, consumed) =
+        from_bytes_xcdr2::<Old>(&bytes, encoding, Endianness::LittleEndian).unwrap();
+      assert_eq!(old, Old { a: 5 });
+      assert_eq!(consumed, bytes.len());
+    }
+  }
+
+  #[test]
+  fn parameter_list_members_in_any_order() {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&0_u32.to_be_bytes()); // DHEADER, set below
+    bytes.extend_from_slice(&0x3000_0001_u32.to_be_bytes());
+    bytes.extend_from_slice(&2.5_f64.to_be_bytes());
+    // An unknown member with NEXTINT
+    bytes.extend_from_slice(&0x4000_0009_u32.to_be_bytes());
+    bytes.extend_from_slice(&1_u32.to_be_bytes());
+    bytes.extend_from_slice(&[0xff, 0, 0, 0]);
+    bytes.extend_from_slice(&0x0000_0000_u32.to_be_bytes());
+    bytes.push(3);
+    bytes[..4].copy_from_slice(&29_u32.to_be_bytes());
+
+    let (inner, _) =
+      from_bytes_xcdr2::<Inner>(&bytes, Xcdr2Encoding::ParameterList, Endianness::BigEndian)
+        .unwrap();
+    assert_eq!(inner, Inner { a: 3, b: 2.5 });
+
+    // The same with the must understand flag on the unknown member
+    bytes[16] |= 0x80;
+    assert!(
+      from_bytes_xcdr2::<Inner>(&bytes, Xcdr2Encoding::ParameterList, Endianness::BigEndian)
+        .is_err()
+    );
+  }
+
+  #[test]
+  fn borrowed_strings_and_bytes() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Message<'a> {
+      topic: &'a str,
+      payload: &'a [u8],
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Header<'a> {
+      topic: &'a str,
+    }
+
+    let message = Message {
+      topic: "sensors",
+      payload: &[1, 2, 3],
+    };
+    for encoding in [
+      Xcdr2Encoding::Plain,
+      Xcdr2Encoding::Delimited,
+      Xcdr2Encoding::ParameterList,
+    ] {
+      let bytes = to_vec_xcdr2(&message, encoding, Endianness::BigEndian).unwrap();
+      let (decoded, consumed) =
+        from_bytes_xcdr2_borrowed::<Message>(&bytes, encoding, Endianness::BigEndian).unwrap();
+      assert_eq!(decoded, message);
+      assert_eq!(consumed, bytes.len());
+      // Not copied
+      assert!(bytes.as_ptr_range().contains(&decoded.topic.as_ptr()));
+      assert!(bytes.as_ptr_range().contains(&decoded.payload.as_ptr()));
+    }
+
+    // Borrowed members missing from the data get default values too
+    let bytes = to_vec_xcdr2(
+      &Header { topic: "old" },
+      Xcdr2Encoding::Delimited,
+      Endianness::LittleEndian,
+    )
+    .unwrap();
+    let (decoded, _) = from_bytes_xcdr2_borrowed::<Message>(
+      &bytes,
+      Xcdr2Encoding::Delimited,
+      Endianness::LittleEndian,
+    )
+    .unwrap();
+    assert_eq!(decoded.payload, &[] as &[u8]);
+  }
+}
@@ -3,6 +3,7 @@ use log::{debug, error, info, trace, warn};
 
 use crate::{
   dds::{ddsdata::DDSData, with_key::datawriter::WriteOptions},
+  leak_tracking::{LeakTracker, TrackedObject},
   structure::{guid::GUID, sequence_number::SequenceNumber},
 };
 
@@ -19,6 +20,7 @@ pub struct CacheChange {
   pub sequence_number: SequenceNumber,
   pub write_options: WriteOptions,
   pub data_value: DDSData,
+  _leak_tracker: LeakTracker,
 }
 
 #[cfg(test)]
@@ -43,6 +45,7 @@ impl CacheChange {
       sequence_number,
       write_options,
       data_value,
+      _leak_tracker: LeakTracker::new(TrackedObject::CacheChange),
     }
   }
 
@@ -1,7 +1,7 @@
 use std::{
   cmp::max,
-  collections::{BTreeMap, HashMap},
-  ops::Bound::{Excluded, Included},
+  collections::{BTreeMap, BTreeSet, HashMap},
+  ops::Bound::{Excluded, Included, Unbounded},
   sync::{Arc, Mutex},
 };
 
@@ -12,9 +12,10 @@ use crate::{
   create_error_internal,
   dds::{
     qos::{
-      policy::{History, ResourceLimits},
+      policy::{CacheWatermarks, History, ResourceLimits},
       QosPolicies,
     },
+    resource_profile::ResourceSettings,
     typedesc::TypeDesc,
     CreateError, CreateResult,
   },
@@ -44,15 +45,32 @@ use super::cache_change::CacheChange;
 /// the actual TopicCaches. For a given topic, the Reader/Writer and
 /// DataReader/DataWriter get a clone of the handle and
 /// interact with the TopicCache through this handle.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct DDSCache {
   topic_caches: HashMap<String, Arc<Mutex<TopicCache>>>,
+  // Cache size limit for topics, whose QoS has no ResourceLimits
+  default_max_samples: i32,
+}
+
+impl Default for DDSCache {
+  fn default() -> Self {
+    Self::with_default_max_samples(ResourceSettings::default().default_cache_max_samples)
+  }
 }
 
 impl DDSCache {
+  #[cfg(test)]
   pub fn new() -> Self {
     Self::default()
   }
+
+  pub fn with_default_max_samples(default_max_samples: i32) -> Self {
+    Self {
+      topic_caches: HashMap::new(),
+      default_max_samples,
+    }
+  }
+
   // Insert new topic if it does not exist.
   // If it exists already, update cache size limits.
   // Return a handle to the cache topic.
@@ -72,6 +90,7 @@ impl DDSCache {
         topic_name,
         topic_data_type,
         qos,
+        self.default_max_samples,
       ))));
 
     topic_cache_handle.clone()
@@ -97,6 +116,15 @@ impl DDSCache {
     }
   }
 
+  // Statistics of all the topic caches
+  pub(crate) fn topic_statistics(&self) -> Vec<CacheStatistics> {
+    self
+      .topic_caches
+      .values()
+      .map(|tc| tc.lock().unwrap().statistics())
+      .collect()
+  }
+
   pub fn garbage_collect(&mut self) {
     for tc in self.topic_caches.values_mut() {
       let mut tc = tc.lock().unwrap();
@@ -105,10 +133,28 @@ impl DDSCache {
           tc.remove_changes_before(Timestamp::ZERO);
         }
       }
+      tc.evict_to_watermarks();
     }
   }
 }
 
+/// Statistics of a DataReader's cache eviction, as configured by
+/// [`CacheWatermarks`](crate::policy::CacheWatermarks).
+///
+/// The cache is shared by all local DataReaders of the same Topic, and so are
+/// these statistics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStatistics {
+  /// Samples currently in the cache
+  pub cached_samples: usize,
+  /// How many times the high watermark has been reached
+  pub evictions: u64,
+  /// Evicted samples that all DataReaders had already read
+  pub evicted_read_samples: u64,
+  /// Evicted samples that some DataReader had not yet read. These are lost.
+  pub evicted_unread_samples: u64,
+}
+
 #[derive(Debug)]
 pub(crate) struct TopicCache {
   topic_name: String,
@@ -120,6 +166,7 @@ pub(crate) struct TopicCache {
   min_keep_samples: History,
   max_keep_samples: i32, // from QoS, for quick, repeated access
   // TODO: Change this to Option<u32>, where None means "no limit".
+  default_max_samples: i32, // used if QoS has no ResourceLimits
 
   // The main content of the cache is in this map.
   // Timestamp is assumed to be unique id over all the CacheChanges.
@@ -140,10 +187,32 @@ pub(crate) struct TopicCache {
   // Therefore, data before the marker SN can be handed off to a Reliable DataReader.
   // Initially, we consider the marker for each Writer (GUID) to be SequenceNumber::new(1)
   received_reliably_before: BTreeMap<GUID, SequenceNumber>,
+
+  // Eviction limits, if requested by some DataReader, and the results so far
+  watermarks: Option<CacheWatermarks>,
+  statistics: CacheStatistics,
+
+  // How far each local DataReader has read from each Writer.
+  // Samples read by all of them are the first to be evicted.
+  read_marks: BTreeMap<GUID, BTreeMap<GUID, SequenceNumber>>,
+
+  // How many times each Writer has restarted its sequence numbering.
+  // DataReaders compare this to what they have seen, in order to reset their
+  // read pointers.
+  writer_restarts: BTreeMap<GUID, u32>,
+  // How many times each Writer has been lost, i.e. unmatched from the local
+  // DataReaders. DataReaders follow this like writer_restarts, in order to
+  // update the instance states.
+  writer_losses: BTreeMap<GUID, u32>,
 }
 
 impl TopicCache {
-  pub fn new(topic_name: String, topic_data_type: TypeDesc, topic_qos: &QosPolicies) -> Self {
+  pub fn new(
+    topic_name: String,
+    topic_data_type: TypeDesc,
+    topic_qos: &QosPolicies,
+    default_max_samples: i32,
+  ) -> Self {
     let mut new_self = Self {
       topic_name,
       topic_data_type,
@@ -151,10 +220,16 @@ impl TopicCache {
       min_keep_samples: History::KeepLast { depth: 1 }, /* dummy value, next call will overwrite
                                                          * this */
       max_keep_samples: 1, // dummy value, next call will overwrite this
+      default_max_samples,
       changes: BTreeMap::new(),
       changes_reallocated_up_to: Timestamp::ZERO,
       sequence_numbers: BTreeMap::new(),
       received_reliably_before: BTreeMap::new(),
+      watermarks: None,
+      statistics: CacheStatistics::default(),
+      read_marks: BTreeMap::new(),
+      writer_restarts: BTreeMap::new(),
+      writer_losses: BTreeMap::new(),
     };
 
     new_self.update_keep_limits(topic_qos);
@@ -175,11 +250,11 @@ impl TopicCache {
     let max_keep_samples = qos
       .resource_limits()
       .unwrap_or(ResourceLimits {
-        // This is some default limits out of the hat, if there was no QoS specification.
-        // The purpose of these limits is to prevent excessive memory usage.
-        max_samples: 64,
-        max_instances: 64,
-        max_samples_per_instance: 64,
+        // Default limits from the participant's ResourceSettings, if there was no QoS
+        // specification. The purpose of these limits is to prevent excessive memory usage.
+        max_samples: self.default_max_samples,
+        max_instances: self.default_max_samples,
+        max_samples_per_instance: self.default_max_samples,
       })
       .max_samples;
     // TODO: We cannot currently keep track of instance counts, because TopicCache
@@ -194,6 +269,103 @@ impl TopicCache {
     // actual update. This is will only ever increase cache size.
     self.min_keep_samples = max(min_keep_samples, self.min_keep_samples);
     self.max_keep_samples = max(max_keep_samples, self.max_keep_samples);
+
+    // Watermarks, too, can only increase.
+    if let Some(CacheWatermarks { high, low }) = qos.cache_watermarks() {
+      let low = low.clamp(0, max(high, 0));
+      self.watermarks = Some(match self.watermarks {
+        Some(old) => CacheWatermarks {
+          high: max(old.high, high),
+          low: max(old.low, low),
+        },
+        None => CacheWatermarks { high, low },
+      });
+    }
+  }
+
+  pub fn register_reader(&mut self, reader: GUID) {
+    self.read_marks.entry(reader).or_default();
+  }
+
+  pub fn unregister_reader(&mut self, reader: GUID) {
+    self.read_marks.remove(&reader);
+  }
+
+  // A local DataReader has read (or skipped) this sample.
+  pub fn mark_read(&mut self, reader: GUID, writer: GUID, sn: SequenceNumber) {
+    if let Some(marks) = self.read_marks.get_mut(&reader) {
+      let mark = marks.entry(writer).or_insert(sn);
+      *mark = max(*mark, sn);
+    }
+  }
+
+  fn is_read_by_all(&self, cc: &CacheChange) -> bool {
+    self.read_marks.values().all(|marks| {
+      marks
+        .get(&cc.writer_guid)
+        .is_some_and(|sn| cc.sequence_number <= *sn)
+    })
+  }
+
+  // Background eviction: When the high watermark is reached, evict samples
+  // down to the low watermark. Samples that all DataReaders have read go
+  // first, and then the oldest remaining ones.
+  pub fn evict_to_watermarks(&mut self) {
+    let (high, low) = match self.watermarks {
+      Some(CacheWatermarks { high, low }) => (max(high, 0) as usize, max(low, 0) as usize),
+      None => return,
+    };
+    let sample_count = self.changes.len();
+    if sample_count < high {
+      return;
+    }
+    let excess = sample_count - low;
+
+    let mut to_evict: Vec<Timestamp> = self
+      .changes
+      .iter()
+      .filter(|(_, cc)| self.is_read_by_all(cc))
+      .map(|(ts, _)| *ts)
+      .take(excess)
+      .collect();
+    let read_count = to_evict.len();
+    if read_count < excess {
+      let read: BTreeSet<Timestamp> = to_evict.iter().copied().collect();
+      to_evict.extend(
+        self
+          .changes
+          .keys()
+          .filter(|ts| !read.contains(ts))
+          .take(excess - read_count)
+          .copied(),
+      );
+    }
+    let unread_count = to_evict.len() - read_count;
+
+    for ts in to_evict {
+      if let Some(cc) = self.changes.remove(&ts) {
+        self.remove_sn(&cc);
+      }
+    }
+
+    self.statistics.evictions += 1;
+    self.statistics.evicted_read_samples += read_count as u64;
+    self.statistics.evicted_unread_samples += unread_count as u64;
+    info!(
+      "Topic {} reached high watermark {}: evicted {} read and {} unread samples. {:?}",
+      self.topic_name,
+      high,
+      read_count,
+      unread_count,
+      self.statistics()
+    );
+  }
+
+  pub fn statistics(&self) -> CacheStatistics {
+    CacheStatistics {
+      cached_samples: self.changes.len(),
+      ..self.statistics
+    }
   }
 
   // Returns true if the "reliably_received_before"-marker was actually moved
@@ -203,6 +375,35 @@ impl TopicCache {
     prev_sn.unwrap_or(SequenceNumber::new(1)) < sn
   }
 
+  // The Writer has restarted its sequence numbering. Its old samples are
+  // dropped, because the new ones will reuse the same sequence numbers.
+  pub fn writer_restarted(&mut self, writer: GUID) {
+    if let Some(sn_map) = self.sequence_numbers.remove(&writer) {
+      for instant in sn_map.values() {
+        self.changes.remove(instant);
+      }
+    }
+    self.received_reliably_before.remove(&writer);
+    for marks in self.read_marks.values_mut() {
+      marks.remove(&writer);
+    }
+    *self.writer_restarts.entry(writer).or_default() += 1;
+  }
+
+  pub fn writer_restarts(&self) -> &BTreeMap<GUID, u32> {
+    &self.writer_restarts
+  }
+
+  // The Writer is no longer matched. Its samples are kept, so that they can
+  // still be read.
+  pub fn writer_lost(&mut self, writer: GUID) {
+    *self.writer_losses.entry(writer).or_default() += 1;
+  }
+
+  pub fn writer_losses(&self) -> &BTreeMap<GUID, u32> {
+    &self.writer_losses
+  }
+
   pub fn get_change(&self, instant: &Timestamp) -> Option<&CacheChange> {
     self.changes.get(instant)
   }
@@ -332,7 +533,43 @@ impl TopicCache {
     )
   }
 
-  fn reliable_before(&self, writer: GUID) -> SequenceNumber {
+  // All unread changes, also those after missing sequence numbers. Sequence
+  // numbers in read_ahead_sn are above last_read_sn, but have been read
+  // already, out of order.
+  pub fn get_changes_as_received<'a>(
+    &'a self,
+    last_read_sn: &'a BTreeMap<GUID, SequenceNumber>,
+    read_ahead_sn: &'a BTreeMap<GUID, BTreeSet<SequenceNumber>>,
+  ) -> Box<dyn Iterator<Item = (Timestamp, &'a CacheChange)> + 'a> {
+    Box::new(
+      self
+        .sequence_numbers
+        .iter()
+        .flat_map(move |(guid, sn_map)| {
+          let lower_bound_exc = last_read_sn
+            .get(guid)
+            .cloned()
+            .unwrap_or(SequenceNumber::zero());
+          let read_ahead = read_ahead_sn.get(guid);
+          sn_map
+            .range((Excluded(lower_bound_exc), Unbounded))
+            .filter(move |(sn, _)| !read_ahead.is_some_and(|r| r.contains(*sn)))
+        })
+        .filter_map(|(_sn, t)| self.get_change(t).map(|cc| (*t, cc))),
+    )
+  }
+
+  // Smallest sequence number from the writer in the cache, which is at least
+  // `sn`.
+  pub fn first_cached_sn_from(&self, writer: GUID, sn: SequenceNumber) -> Option<SequenceNumber> {
+    self
+      .sequence_numbers
+      .get(&writer)
+      .and_then(|sn_map| sn_map.range(sn..).next())
+      .map(|(sn, _)| *sn)
+  }
+
+  pub fn reliable_before(&self, writer: GUID) -> SequenceNumber {
     self
       .received_reliably_before
       .get(&writer)
@@ -444,17 +681,25 @@ impl TopicCache {
 #[cfg(test)]
 mod tests {
   use std::{
+    collections::{BTreeMap, BTreeSet},
     sync::{Arc, RwLock},
     thread,
   };
 
-  use super::DDSCache;
+  use super::{CacheStatistics, DDSCache, TopicCache};
   use crate::{
     dds::{
-      ddsdata::DDSData, qos::QosPolicies, typedesc::TypeDesc, with_key::datawriter::WriteOptions,
+      ddsdata::DDSData,
+      qos::{policy, QosPolicies, QosPolicyBuilder},
+      typedesc::TypeDesc,
+      with_key::datawriter::WriteOptions,
     },
     messages::submessages::elements::serialized_payload::SerializedPayload,
-    structure::{cache_change::CacheChange, guid::GUID, sequence_number::SequenceNumber},
+    structure::{
+      cache_change::CacheChange,
+      guid::{EntityKind, GUID},
+      sequence_number::SequenceNumber,
+    },
   };
 
   #[test]
@@ -525,4 +770,117 @@ mod tests {
       3
     );
   }
+
+  #[test]
+  fn evict_read_samples_first_between_watermarks() {
+    let qos = QosPolicyBuilder::new()
+      .history(policy::History::KeepAll)
+      .resource_limits(policy::ResourceLimits {
+        max_samples: 100,
+        max_instances: 100,
+        max_samples_per_instance: 100,
+      })
+      .cache_watermarks(policy::CacheWatermarks { high: 10, low: 4 })
+      .build();
+    let mut topic_cache = TopicCache::new(
+      "EvictionTopic".to_string(),
+      TypeDesc::new("Whatever".to_string()),
+      &qos,
+      64,
+    );
+    let reader = GUID::dummy_test_guid(EntityKind::READER_WITH_KEY_USER_DEFINED);
+    let writer = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    topic_cache.register_reader(reader);
+
+    let add = |tc: &mut TopicCache, from: i64, to: i64| {
+      for sn in from..=to {
+        let cc = CacheChange::new(
+          writer,
+          SequenceNumber::new(sn),
+          WriteOptions::default(),
+          DDSData::new(SerializedPayload::default()),
+        );
+        let instant = crate::Timestamp::ZERO + crate::Duration::from_millis(sn);
+        tc.add_change(&instant, cc);
+      }
+    };
+
+    // Below high watermark, nothing is evicted
+    add(&mut topic_cache, 1, 9);
+    topic_cache.mark_read(reader, writer, SequenceNumber::new(3));
+    topic_cache.evict_to_watermarks();
+    assert_eq!(topic_cache.statistics().cached_samples, 9);
+    assert_eq!(topic_cache.statistics().evictions, 0);
+
+    // At high watermark, evict down to low watermark: 3 read + 3 oldest unread
+    add(&mut topic_cache, 10, 10);
+    topic_cache.evict_to_watermarks();
+    assert_eq!(
+      topic_cache.statistics(),
+      CacheStatistics {
+        cached_samples: 4,
+        evictions: 1,
+        evicted_read_samples: 3,
+        evicted_unread_samples: 3,
+      }
+    );
+    let remaining: Vec<SequenceNumber> = topic_cache
+      .get_changes_in_range_best_effort(crate::Timestamp::ZERO, crate::Timestamp::now())
+      .map(|(_, cc)| cc.sequence_number)
+      .collect();
+    assert_eq!(
+      remaining,
+      (7..=10).map(SequenceNumber::new).collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn changes_as_received_skip_missing_and_read_ahead() {
+    let mut topic_cache = TopicCache::new(
+      "AsReceivedTopic".to_string(),
+      TypeDesc::new("Whatever".to_string()),
+      &QosPolicies::qos_none(),
+      64,
+    );
+    let writer = GUID::dummy_test_guid(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    for sn in [1, 2, 4, 5] {
+      let cc = CacheChange::new(
+        writer,
+        SequenceNumber::new(sn),
+        WriteOptions::default(),
+        DDSData::new(SerializedPayload::default()),
+      );
+      let instant = crate::Timestamp::ZERO + crate::Duration::from_millis(sn);
+      topic_cache.add_change(&instant, cc);
+    }
+    // #3 is missing, so nothing is reliably received beyond #2.
+    topic_cache.mark_reliably_received_before(writer, SequenceNumber::new(3));
+
+    let last_read_sn = BTreeMap::from([(writer, SequenceNumber::new(1))]);
+    let read_ahead_sn = BTreeMap::from([(writer, BTreeSet::from([SequenceNumber::new(4)]))]);
+
+    let reliable: Vec<SequenceNumber> = topic_cache
+      .get_changes_in_range_reliable(&last_read_sn)
+      .map(|(_, cc)| cc.sequence_number)
+      .collect();
+    assert_eq!(reliable, vec![SequenceNumber::new(2)]);
+
+    let as_received: Vec<SequenceNumber> = topic_cache
+      .get_changes_as_received(&last_read_sn, &read_ahead_sn)
+      .map(|(_, cc)| cc.sequence_number)
+      .collect();
+    assert_eq!(
+      as_received,
+      vec![SequenceNumber::new(2), SequenceNumber::new(5)]
+    );
+
+    assert_eq!(
+      topic_cache.first_cached_sn_from(writer, SequenceNumber::new(3)),
+      Some(SequenceNumber::new(4))
+    );
+    assert_eq!(
+      topic_cache.first_cached_sn_from(writer, SequenceNumber::new(6)),
+      None
+    );
+  }
 }
@@ -127,6 +127,11 @@ impl EntityKind {
   pub const WRITER_GROUP_BUILT_IN: Self = Self(0xC8);
   pub const READER_GROUP_BUILT_IN: Self = Self(0xC9);
 
+  // RTPS spec Section 9.3.1.2: the two most significant bits 0b01 mark
+  // vendor-specific entities.
+  pub const WRITER_NO_KEY_VENDOR_SPECIFIC: Self = Self(0x43);
+  pub const READER_NO_KEY_VENDOR_SPECIFIC: Self = Self(0x44);
+
   pub const MIN: Self = Self(0x00);
   pub const MAX: Self = Self(0xFF);
 
@@ -137,10 +142,11 @@ impl EntityKind {
   // 1
   // 2 = user-defined alt token (timers etc)
   // 3
-  // 4 = fixed poll tokens (not entity-specific)
+  // 4 = fixed poll tokens (not entity-specific), or vendor-specific entity
+  //     if the entity key is not zero
   pub const POLL_TOKEN_BASE: usize = 0x40;
   // 5 = fixed poll tokens continued
-  // 6 = fixed poll tokens continued
+  // 6 = fixed poll tokens continued, or vendor-specific alt token
   // 7 = fixed poll tokens continued
   // 8 = fixed poll tokens continued
   // 9
@@ -200,6 +206,13 @@ impl fmt::Debug for EntityKind {
       Self::READER_WITH_KEY_BUILT_IN => f.write_str("EntityKind::READER_WITH_KEY_BUILT_IN"),
       Self::WRITER_GROUP_BUILT_IN => f.write_str("EntityKind::WRITER_GROUP_BUILT_IN"),
       Self::READER_GROUP_BUILT_IN => f.write_str("EntityKind::READER_GROUP_BUILT_IN"),
+
+      Self::WRITER_NO_KEY_VENDOR_SPECIFIC => {
+        f.write_str("EntityKind::WRITER_NO_KEY_VENDOR_SPECIFIC")
+      }
+      Self::READER_NO_KEY_VENDOR_SPECIFIC => {
+        f.write_str("EntityKind::READER_NO_KEY_VENDOR_SPECIFIC")
+      }
       _ => f.write_fmt(format_args!("EntityKind({:x?})", self.0)),
     }
   }
@@ -276,6 +289,27 @@ impl EntityId {
     entity_kind: EntityKind::READER_WITH_KEY_BUILT_IN,
   };
 
+  // Endpoints of the TypeLookup service of RustDDS. The service is not the
+  // one of DDS-XTypes 1.3 Section 7.6.3.3, because the requests and replies
+  // are not in the format of the specification, so the endpoints are
+  // vendor-specific.
+  pub const RUSTDDS_TYPE_LOOKUP_REQUEST_WRITER: Self = Self {
+    entity_key: [0x00, 0x03, 0x00],
+    entity_kind: EntityKind::WRITER_NO_KEY_VENDOR_SPECIFIC,
+  };
+  pub const RUSTDDS_TYPE_LOOKUP_REQUEST_READER: Self = Self {
+    entity_key: [0x00, 0x03, 0x00],
+    entity_kind: EntityKind::READER_NO_KEY_VENDOR_SPECIFIC,
+  };
+  pub const RUSTDDS_TYPE_LOOKUP_REPLY_WRITER: Self = Self {
+    entity_key: [0x00, 0x03, 0x01],
+    entity_kind: EntityKind::WRITER_NO_KEY_VENDOR_SPECIFIC,
+  };
+  pub const RUSTDDS_TYPE_LOOKUP_REPLY_READER: Self = Self {
+    entity_key: [0x00, 0x03, 0x01],
+    entity_kind: EntityKind::READER_NO_KEY_VENDOR_SPECIFIC,
+  };
+
   // DDS SEcurity spec v1.1
   // Section "7.3.7 Mapping to UDP/IP PSM"
   // Table 9 – EntityId values for secure builtin data writers and data readers
@@ -378,7 +412,7 @@ impl EntityId {
 
     // check sanity, as the result should be
     let kind_kind = u4 & (0xC0 | 0x10);
-    if kind_kind == 0xC0 || kind_kind == 0x00 {
+    if kind_kind == 0xC0 || kind_kind == 0x00 || kind_kind == 0x40 {
       // this is ok, all normal
     } else {
       warn!("EntityId::from_usize tried to decode 0x{:x?}", number);
@@ -401,6 +435,9 @@ impl EntityId {
     match (t.0 & 0xF0) as u8 {
       0x00 | 0xC0 => TokenDecode::Entity(Self::from_usize(t.0)),
       0x20 | 0xE0 => TokenDecode::AltEntity(Self::from_usize(t.0 & !0x20)),
+      // Fixed tokens have no entity key
+      0x40 if t.0 > 0xFF => TokenDecode::Entity(Self::from_usize(t.0)),
+      0x60 if t.0 > 0xFF => TokenDecode::AltEntity(Self::from_usize(t.0 & !0x20)),
       0x40 | 0x50 | 0x60 | 0x70 | 0x80 => TokenDecode::FixedToken(t),
       _other => {
         warn!("EntityId::from_token tried to decode 0x{:x?}", t.0);
@@ -472,6 +509,18 @@ impl fmt::Debug for EntityId {
       Self::P2P_BUILTIN_PARTICIPANT_MESSAGE_READER => {
         f.write_str("EntityId::P2P_BUILTIN_PARTICIPANT_MESSAGE_READER")
       }
+      Self::RUSTDDS_TYPE_LOOKUP_REQUEST_WRITER => {
+        f.write_str("EntityId::RUSTDDS_TYPE_LOOKUP_REQUEST_WRITER")
+      }
+      Self::RUSTDDS_TYPE_LOOKUP_REQUEST_READER => {
+        f.write_str("EntityId::RUSTDDS_TYPE_LOOKUP_REQUEST_READER")
+      }
+      Self::RUSTDDS_TYPE_LOOKUP_REPLY_WRITER => {
+        f.write_str("EntityId::RUSTDDS_TYPE_LOOKUP_REPLY_WRITER")
+      }
+      Self::RUSTDDS_TYPE_LOOKUP_REPLY_READER => {
+        f.write_str("EntityId::RUSTDDS_TYPE_LOOKUP_REPLY_READER")
+      }
       // TODO: This list is missing multiple entries.
       // Can can we somehow autogenerate this?
       _ => {
@@ -628,20 +677,20 @@ impl fmt::Debug for GUID {
 mod tests {
   use speedy::Endianness;
   use log::info;
-  use byteorder::BigEndian;
 
   use super::*;
 
   #[test]
   fn serde_test() {
-    use crate::serialization::{from_bytes, to_vec};
+    use crate::serialization::{from_bytes_with_endianness, to_vec_with_endianness};
 
     let test_bytes = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
     let test_guid = GUID::from_bytes(test_bytes);
-    let ser = to_vec::<GUID, BigEndian>(&test_guid).unwrap();
+    let ser = to_vec_with_endianness(&test_guid, Endianness::BigEndian).unwrap();
     assert_eq!(test_bytes.to_vec(), ser);
 
-    let (and_back, _byte_count) = from_bytes::<GUID, BigEndian>(&ser).unwrap();
+    let (and_back, _byte_count) =
+      from_bytes_with_endianness::<GUID>(&ser, Endianness::BigEndian).unwrap();
     assert_eq!(test_guid, and_back);
   }
 
@@ -698,6 +747,22 @@ mod tests {
     assert_eq!(e6, entity6);
   }
 
+  #[test]
+  fn vendor_specific_entity_id_tokens() {
+    for e in [
+      EntityId::RUSTDDS_TYPE_LOOKUP_REQUEST_WRITER,
+      EntityId::RUSTDDS_TYPE_LOOKUP_REPLY_READER,
+    ] {
+      assert_eq!(EntityId::from_token(e.as_token()), TokenDecode::Entity(e));
+      assert_eq!(
+        EntityId::from_token(e.as_alt_token()),
+        TokenDecode::AltEntity(e)
+      );
+    }
+    let fixed = crate::rtps::constant::STOP_POLL_TOKEN;
+    assert_eq!(EntityId::from_token(fixed), TokenDecode::FixedToken(fixed));
+  }
+
   #[test]
   fn minimum_bytes_needed() {
     assert_eq!(
@@ -8,6 +8,10 @@ mod kind {
   pub const RESERVED: i32 = 0;
   pub const UDP_V4: i32 = 1;
   pub const UDP_V6: i32 = 2;
+  // Not in the RTPS specification. These are the values that e.g. Fast DDS
+  // uses.
+  pub const TCP_V4: i32 = 4;
+  pub const TCP_V6: i32 = 8;
 }
 
 const INVALID_PORT: u16 = 0;
@@ -19,6 +23,8 @@ pub enum Locator {
   Reserved,
   UdpV4(SocketAddrV4),
   UdpV6(SocketAddrV6),
+  TcpV4(SocketAddrV4),
+  TcpV6(SocketAddrV6),
   Other {
     kind: i32,
     port: u32,
@@ -27,14 +33,31 @@ pub enum Locator {
 }
 
 impl Locator {
+  /// Locator of a TCP server. See [`tcp_transport`](crate::tcp_transport).
+  pub fn tcp(socket_address: SocketAddr) -> Self {
+    match Self::from(socket_address) {
+      Self::UdpV4(socket_address) => Self::TcpV4(socket_address),
+      Self::UdpV6(socket_address) => Self::TcpV6(socket_address),
+      other => other,
+    }
+  }
+
   pub fn is_udp(&self) -> bool {
     matches!(self, Self::UdpV4(_) | Self::UdpV6(_))
   }
 
+  pub fn is_tcp(&self) -> bool {
+    matches!(self, Self::TcpV4(_) | Self::TcpV6(_))
+  }
+
   pub fn is_loopback(&self) -> bool {
     match self {
-      Locator::UdpV4(socket_address) => socket_address.ip().is_loopback(),
-      Locator::UdpV6(socket_address) => socket_address.ip().is_loopback(),
+      Locator::UdpV4(socket_address) | Locator::TcpV4(socket_address) => {
+        socket_address.ip().is_loopback()
+      }
+      Locator::UdpV6(socket_address) | Locator::TcpV6(socket_address) => {
+        socket_address.ip().is_loopback()
+      }
       _ => false,
     }
   }
@@ -43,8 +66,8 @@ impl Locator {
 impl From<Locator> for SocketAddr {
   fn from(locator: Locator) -> Self {
     match locator {
-      Locator::UdpV4(socket_address) => socket_address.into(),
-      Locator::UdpV6(socket_address) => socket_address.into(),
+      Locator::UdpV4(socket_address) | Locator::TcpV4(socket_address) => socket_address.into(),
+      Locator::UdpV6(socket_address) | Locator::TcpV6(socket_address) => socket_address.into(),
       Locator::Invalid | Locator::Reserved | Locator::Other { .. } => {
         let ip = Ipv6Addr::from(INVALID_ADDRESS).into();
         Self::new(ip, INVALID_PORT)
@@ -86,7 +109,7 @@ impl From<repr::Locator> for Locator {
     match repr.kind {
       kind::INVALID => Self::Invalid,
       kind::RESERVED => Self::Reserved,
-      kind::UDP_V4 => {
+      kind::UDP_V4 | kind::TCP_V4 => {
         let ip = Ipv4Addr::new(
           repr.address[12],
           repr.address[13],
@@ -96,13 +119,21 @@ impl From<repr::Locator> for Locator {
         // repr.port is 32 bits, but we just truncate it to u16
         let socket_address = SocketAddrV4::new(ip, repr.port as u16);
 
-        Self::UdpV4(socket_address)
+        if repr.kind == kind::UDP_V4 {
+          Self::UdpV4(socket_address)
+        } else {
+          Self::TcpV4(socket_address)
+        }
       }
-      kind::UDP_V6 => {
+      kind::UDP_V6 | kind::TCP_V6 => {
         let ip = Ipv6Addr::from(repr.address);
         let socket_address = SocketAddrV6::new(ip, repr.port as u16, 0, 0);
 
-        Self::UdpV6(socket_address)
+        if repr.kind == kind::UDP_V6 {
+          Self::UdpV6(socket_address)
+        } else {
+          Self::TcpV6(socket_address)
+        }
       }
       kind => Self::Other {
         kind,
@@ -118,14 +149,22 @@ impl From<Locator> for repr::Locator {
     let (kind, port, address) = match locator {
       Locator::Invalid => (kind::INVALID, INVALID_PORT.into(), INVALID_ADDRESS),
       Locator::Reserved => (kind::RESERVED, INVALID_PORT.into(), INVALID_ADDRESS),
-      Locator::UdpV4(socket_address) => {
-        let kind = kind::UDP_V4;
+      Locator::UdpV4(socket_address) | Locator::TcpV4(socket_address) => {
+        let kind = if locator.is_udp() {
+          kind::UDP_V4
+        } else {
+          kind::TCP_V4
+        };
         let port = socket_address.port();
         let address = socket_address.ip().to_ipv6_compatible().octets();
         (kind, port.into(), address)
       }
-      Locator::UdpV6(socket_address) => {
-        let kind = kind::UDP_V6;
+      Locator::UdpV6(socket_address) | Locator::TcpV6(socket_address) => {
+        let kind = if locator.is_udp() {
+          kind::UDP_V6
+        } else {
+          kind::TCP_V6
+        };
         let port = socket_address.port();
         let address = socket_address.ip().octets();
         (kind, port.into(), address)
@@ -190,6 +229,18 @@ mod tests {
     =>  Locator::from(SocketAddr::new(Ipv4Addr::new(0x0A, 0, 0, 0x0F).into(), 0x1cf2))
     ; "IPv4 fuzz"
   )]
+  #[test_case(
+    &[
+      0x04, 0x00, 0x00, 0x00,  // LOCATOR_KIND_TCPv4, as in Fast DDS
+      0xF3, 0x1C, 0x00, 0x00,  // Locator_t::port(7411),
+      0x00, 0x00, 0x00, 0x00,  // Locator_t::address[0:3]
+      0x00, 0x00, 0x00, 0x00,  // Locator_t::address[4:7]
+      0x00, 0x00, 0x00, 0x00,  // Locator_t::address[8:11]
+      0x0A, 0x00, 0x00, 0x02   // Locator_t::address[12:15]
+    ]
+    =>  Locator::tcp(SocketAddr::new(Ipv4Addr::new(10, 0, 0, 2).into(), 7411))
+    ; "TCPv4 deserialize"
+  )]
   // test body
   fn deserialize_le(little_endian: &[u8]) -> Locator {
     repr::Locator::read_from_buffer_with_ctx(Endianness::LittleEndian, little_endian)
@@ -282,6 +333,26 @@ mod tests {
     ]
     ; "IPv6"
   )]
+  #[test_case(
+    Locator::tcp(SocketAddr::new(Ipv4Addr::new(10, 0, 0, 2).into(), 7411)),
+    [
+      0x00, 0x00, 0x00, 0x04,  // LOCATOR_KIND_TCPv4, as in Fast DDS
+      0x00, 0x00, 0x1C, 0xF3,  // Locator_t::port(7411),
+      0x00, 0x00, 0x00, 0x00,  // Locator_t::address[0:3]
+      0x00, 0x00, 0x00, 0x00,  // Locator_t::address[4:7]
+      0x00, 0x00, 0x00, 0x00,  // Locator_t::address[8:11]
+      0x0A, 0x00, 0x00, 0x02   // Locator_t::address[12:15]
+    ],
+    [
+      0x04, 0x00, 0x00, 0x00,  // LOCATOR_KIND_TCPv4, as in Fast DDS
+      0xF3, 0x1C, 0x00, 0x00,  // Locator_t::port(7411),
+      0x00, 0x00, 0x00, 0x00,  // Locator_t::address[0:3]
+      0x00, 0x00, 0x00, 0x00,  // Locator_t::address[4:7]
+      0x00, 0x00, 0x00, 0x00,  // Locator_t::address[8:11]
+      0x0A, 0x00, 0x00, 0x02   // Locator_t::address[12:15]
+    ]
+    ; "TCPv4"
+  )]
   fn serialization(locator: Locator, big_endian: [u8; 24], little_endian: [u8; 24]) {
     assert_eq!(
       locator
@@ -64,6 +64,9 @@ impl ParameterId {
   pub const PID_KEY_HASH: Self = Self { value: 0x0070 };
   pub const PID_STATUS_INFO: Self = Self { value: 0x0071 };
 
+  // DDS-XTypes v1.3, DataRepresentationQosPolicy
+  pub const PID_DATA_REPRESENTATION: Self = Self { value: 0x0073 };
+
   // From Specification "Remote Procedure Calls over DDS v1.0"
   // Section 7.6.2.1.1 Extended PublicationBuiltin TopicData and
   // 7.6.2.1.2 Extended SubscriptionBuiltinTopicData
@@ -97,6 +100,24 @@ impl ParameterId {
   pub const PID_RELATED_SAMPLE_IDENTITY: Self = Self { value: 0x0083 };
   pub const PID_RELATED_SAMPLE_IDENTITY_CUSTOM: Self = Self { value: 0x800f };
 
+  // RustDDS vendor-specific: How many times the participant has found its own
+  // SPDP announcement to be late. Meaningful only with our own VendorId.
+  pub const PID_RUSTDDS_LATE_LEASE_ASSERTIONS: Self = Self { value: 0x8101 };
+
+  // RustDDS vendor-specific inline QoS: MD5 checksum of the serialized payload
+  // of a DATA or DATAFRAG. See policy::PayloadChecksum.
+  pub const PID_RUSTDDS_PAYLOAD_CHECKSUM: Self = Self { value: 0x8102 };
+
+  // RustDDS vendor-specific: identifiers of the type of a reader or writer, see
+  // xtypes::TypeInformation. Not the XTypes PID_TYPE_INFORMATION, because the
+  // identifiers are not computed as XTypes specifies.
+  pub const PID_RUSTDDS_TYPE_INFORMATION: Self = Self { value: 0x8103 };
+
+  // Vendor-specific of RTI Connext and eProsima Fast DDS: version of the
+  // implementation as four octets. Not listed in is_known(), because other
+  // vendors may use the id for something else.
+  pub const PID_PRODUCT_VERSION: Self = Self { value: 0x8000 };
+
   // DDS Security spec v1.1:
 
   // Section 7.4.1.4 Extension to RTPS Standard DCPSParticipants Builtin Topic
@@ -118,6 +139,96 @@ impl ParameterId {
   // Section 7.4.1.6 "New DCPSParticipantSecure Builtin Topic"
   // Table 13
   pub const PID_IDENTITY_STATUS_TOKEN: Self = Self { value: 0x1006 };
+
+  // RTPS spec v2.5 Section 9.6.2.2.1 "ParameterId space":
+  // Bit 0x8000 marks a vendor-specific parameter, and bit 0x4000 a parameter
+  // that must be understood, or else the whole ParameterList is rejected.
+  const VENDOR_SPECIFIC_FLAG: u16 = 0x8000;
+  const MUST_UNDERSTAND_FLAG: u16 = 0x4000;
+
+  pub fn is_vendor_specific(&self) -> bool {
+    self.value & Self::VENDOR_SPECIFIC_FLAG != 0
+  }
+
+  pub fn is_must_understand(&self) -> bool {
+    self.value & Self::MUST_UNDERSTAND_FLAG != 0
+  }
+
+  /// Is this one of the parameters defined above?
+  pub fn is_known(&self) -> bool {
+    matches!(
+      *self,
+      Self::PID_PAD
+        | Self::PID_SENTINEL
+        | Self::PID_USER_DATA
+        | Self::PID_TOPIC_NAME
+        | Self::PID_TYPE_NAME
+        | Self::PID_GROUP_DATA
+        | Self::PID_TOPIC_DATA
+        | Self::PID_DURABILITY
+        | Self::PID_DURABILITY_SERVICE
+        | Self::PID_DEADLINE
+        | Self::PID_LATENCY_BUDGET
+        | Self::PID_LIVELINESS
+        | Self::PID_RELIABILITY
+        | Self::PID_LIFESPAN
+        | Self::PID_DESTINATION_ORDER
+        | Self::PID_HISTORY
+        | Self::PID_RESOURCE_LIMITS
+        | Self::PID_OWNERSHIP
+        | Self::PID_OWNERSHIP_STRENGTH
+        | Self::PID_PRESENTATION
+        | Self::PID_PARTITION
+        | Self::PID_TIME_BASED_FILTER
+        | Self::PID_TRANSPORT_PRIO
+        | Self::PID_PROTOCOL_VERSION
+        | Self::PID_VENDOR_ID
+        | Self::PID_UNICAST_LOCATOR
+        | Self::PID_MULTICAST_LOCATOR
+        | Self::PID_MULTICAST_IPADDRESS
+        | Self::PID_DEFAULT_UNICAST_LOCATOR
+        | Self::PID_DEFAULT_MULTICAST_LOCATOR
+        | Self::PID_METATRAFFIC_UNICAST_LOCATOR
+        | Self::PID_METATRAFFIC_MULTICAST_LOCATOR
+        | Self::PID_DEFAULT_UNICAST_IPADDRESS
+        | Self::PID_DEFAULT_UNICAST_PORT
+        | Self::PID_METATRAFFIC_UNICAST_IPADDRESS
+        | Self::PID_METATRAFFIC_UNICAST_PORT
+        | Self::PID_METATRAFFIC_MULTICAST_IPADDRESS
+        | Self::PID_METATRAFFIC_MULTICAST_PORT
+        | Self::PID_EXPECTS_INLINE_QOS
+        | Self::PID_PARTICIPANT_MANUAL_LIVELINESS_COUNT
+        | Self::PID_PARTICIPANT_BUILTIN_ENDPOINTS
+        | Self::PID_PARTICIPANT_LEASE_DURATION
+        | Self::PID_CONTENT_FILTER_PROPERTY
+        | Self::PID_PARTICIPANT_GUID
+        | Self::PID_GROUP_GUID
+        | Self::PID_GROUP_ENTITYID
+        | Self::PID_BUILTIN_ENDPOINT_SET
+        | Self::PID_ENDPOINT_GUID
+        | Self::PID_BUILTIN_ENDPOINT_QOS
+        | Self::PID_PROPERTY_LIST
+        | Self::PID_TYPE_MAX_SIZE_SERIALIZED
+        | Self::PID_ENTITY_NAME
+        | Self::PID_KEY_HASH
+        | Self::PID_STATUS_INFO
+        | Self::PID_DATA_REPRESENTATION
+        | Self::PID_SERVICE_INSTANCE_NAME
+        | Self::PID_RELATED_ENTITY_GUID
+        | Self::PID_TOPIC_ALIASES
+        | Self::PID_RELATED_SAMPLE_IDENTITY
+        | Self::PID_RELATED_SAMPLE_IDENTITY_CUSTOM
+        | Self::PID_RUSTDDS_LATE_LEASE_ASSERTIONS
+        | Self::PID_RUSTDDS_PAYLOAD_CHECKSUM
+        | Self::PID_RUSTDDS_TYPE_INFORMATION
+        | Self::PID_IDENTITY_TOKEN
+        | Self::PID_PERMISSIONS_TOKEN
+        | Self::PID_DATA_TAGS
+        | Self::PID_ENDPOINT_SECURITY_INFO
+        | Self::PID_PARTICIPANT_SECURITY_INFO
+        | Self::PID_IDENTITY_STATUS_TOKEN
+    )
+  }
 }
 
 #[cfg(test)]
@@ -436,5 +547,11 @@ mod tests {
       ParameterId::PID_STATUS_INFO,
       le = [0x71, 0x00],
       be = [0x00, 0x71]
+  },
+  {
+      pid_data_representation,
+      ParameterId::PID_DATA_REPRESENTATION,
+      le = [0x73, 0x00],
+      be = [0x00, 0x73]
   });
 }
@@ -61,7 +61,18 @@ impl Default for RemoteExceptionCode {
 }
 
 #[derive(
-  Clone, Default, PartialOrd, PartialEq, Ord, Eq, Readable, Writable, Hash, Serialize, Deserialize,
+  Clone,
+  Default,
+  Debug,
+  PartialOrd,
+  PartialEq,
+  Ord,
+  Eq,
+  Readable,
+  Writable,
+  Hash,
+  Serialize,
+  Deserialize,
 )]
 pub struct RequestHeader {
   pub request_id: SampleIdentity,
@@ -69,7 +80,18 @@ pub struct RequestHeader {
 }
 
 #[derive(
-  Clone, Default, PartialOrd, PartialEq, Ord, Eq, Readable, Writable, Hash, Serialize, Deserialize,
+  Clone,
+  Default,
+  Debug,
+  PartialOrd,
+  PartialEq,
+  Ord,
+  Eq,
+  Readable,
+  Writable,
+  Hash,
+  Serialize,
+  Deserialize,
 )]
 pub struct ReplyHeader {
   pub related_request_id: SampleIdentity,
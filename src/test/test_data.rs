@@ -86,7 +86,7 @@ use crate::{
     qos::{
       policy::{
         Deadline, DestinationOrder, Durability, History, LatencyBudget, Lifespan, Liveliness,
-        Ownership, Presentation, PresentationAccessScope, Reliability, ResourceLimits,
+        Ownership, Partition, Presentation, PresentationAccessScope, Reliability, ResourceLimits,
         TimeBasedFilter,
       },
       QosPolicyBuilder,
@@ -253,6 +253,7 @@ pub(crate) fn subscription_builtin_topic_data() -> Option<SubscriptionBuiltinTop
       coherent_access: false,
       ordered_access: true,
     })
+    .partition(Partition::new(&["some partition", "other*"]))
     .lifespan(Lifespan {
       duration: Duration::from(StdDuration::from_secs(6 * 60)),
     })
@@ -298,9 +299,12 @@ pub(crate) fn publication_builtin_topic_data() -> Option<PublicationBuiltinTopic
       coherent_access: true,
       ordered_access: false,
     }),
+    partition: Some(Partition::new(&["a", "bc", "def"])),
+    data_representation: None,
     related_datareader_key: None,
     service_instance_name: None,
     topic_aliases: None,
+    type_information: None,
     #[cfg(feature = "security")]
     security_info: None,
   };